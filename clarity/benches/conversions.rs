@@ -0,0 +1,116 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Benchmarks for the Clarity conversion natives, measured across input sizes so that the
+//! parameters of their cost functions can be fit to real timings.
+//!
+//! Each native is looked up by its Clarity name and called directly, without parsing or cost
+//! tracking, so the timings cover only the conversion itself.
+//!
+//! Run with `cargo bench -p clarity --bench conversions`.
+
+use clarity::vm::callables::{CallableType, NativeHandle};
+use clarity::vm::errors::InterpreterResult;
+use clarity::vm::functions::lookup_reserved_functions;
+use clarity::vm::{ClarityVersion, Value};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// Buffer lengths to convert. `buff-to-(u)int-*` accept buffers of up to 16 bytes.
+const BUFFER_LENGTHS: [usize; 6] = [0, 1, 2, 4, 8, 16];
+/// Number of decimal digits in the integers converted to and from strings.  39 digits is the
+/// longest `u128`.
+const DIGIT_COUNTS: [u32; 5] = [1, 5, 10, 20, 39];
+
+/// Look up a single-argument conversion native by its Clarity name
+fn conversion_native(name: &str) -> &'static dyn Fn(Value) -> InterpreterResult<Value> {
+    match lookup_reserved_functions(name, &ClarityVersion::latest()) {
+        Some(CallableType::NativeFunction(_, NativeHandle::SingleArg(native), _)) => native,
+        _ => panic!("{} is not a single-argument native function", name),
+    }
+}
+
+/// An integer with `digits` decimal digits
+fn uint_with_digits(digits: u32) -> u128 {
+    if digits >= 39 {
+        u128::MAX
+    } else {
+        10u128.pow(digits) - 1
+    }
+}
+
+fn bench_buff_to_int(c: &mut Criterion) {
+    for name in [
+        "buff-to-int-le",
+        "buff-to-int-be",
+        "buff-to-uint-le",
+        "buff-to-uint-be",
+    ] {
+        let native = conversion_native(name);
+        let mut group = c.benchmark_group(name);
+        for len in BUFFER_LENGTHS {
+            let buff = Value::buff_from(vec![0xa5; len]).unwrap();
+            group.bench_with_input(BenchmarkId::from_parameter(len), &buff, |b, buff| {
+                b.iter(|| native(black_box(buff.clone())).unwrap())
+            });
+        }
+        group.finish();
+    }
+}
+
+fn bench_string_to_int(c: &mut Criterion) {
+    for name in ["string-to-int?", "string-to-uint?"] {
+        let native = conversion_native(name);
+        let mut group = c.benchmark_group(name);
+        for digits in DIGIT_COUNTS {
+            // i128 only holds 38 digits, so string-to-int? returns none for the longest input
+            let string = uint_with_digits(digits).to_string();
+            let ascii = Value::string_ascii_from_bytes(string.clone().into_bytes()).unwrap();
+            let utf8 = Value::string_utf8_from_bytes(string.into_bytes()).unwrap();
+            group.bench_with_input(BenchmarkId::new("ascii", digits), &ascii, |b, ascii| {
+                b.iter(|| native(black_box(ascii.clone())).unwrap())
+            });
+            group.bench_with_input(BenchmarkId::new("utf8", digits), &utf8, |b, utf8| {
+                b.iter(|| native(black_box(utf8.clone())).unwrap())
+            });
+        }
+        group.finish();
+    }
+}
+
+fn bench_int_to_string(c: &mut Criterion) {
+    for name in ["int-to-ascii", "int-to-utf8"] {
+        let native = conversion_native(name);
+        let mut group = c.benchmark_group(name);
+        for digits in DIGIT_COUNTS {
+            let uint = Value::UInt(uint_with_digits(digits));
+            let int = Value::Int(uint_with_digits(digits.min(38)) as i128);
+            group.bench_with_input(BenchmarkId::new("uint", digits), &uint, |b, uint| {
+                b.iter(|| native(black_box(uint.clone())).unwrap())
+            });
+            group.bench_with_input(BenchmarkId::new("int", digits), &int, |b, int| {
+                b.iter(|| native(black_box(int.clone())).unwrap())
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(
+    conversions,
+    bench_buff_to_int,
+    bench_string_to_int,
+    bench_int_to_string
+);
+criterion_main!(conversions);
@@ -184,6 +184,11 @@ impl<'a> ArithmeticOnlyChecker<'a> {
             Append | Concat | AsMaxLen | ContractOf | PrincipalOf | ListCons | Print
             | AsContract | ElementAt | ElementAtAlias | IndexOf | IndexOfAlias | Map | Filter
             | Fold | Slice | ReplaceAt => Err(Error::FunctionNotPermitted(function)),
+            BuffAnd | BuffOr | BuffXor | BuffNot => Err(Error::FunctionNotPermitted(function)),
+            ConcatMany | PrintEvent | Replicate | Repeat | Find | Position => {
+                Err(Error::FunctionNotPermitted(function))
+            }
+            Secp256k1RecoverPrincipal => Err(Error::FunctionNotPermitted(function)),
             BuffToIntLe | BuffToUIntLe | BuffToIntBe | BuffToUIntBe => {
                 Err(Error::FunctionNotPermitted(function))
             }
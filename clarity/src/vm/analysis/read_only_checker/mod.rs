@@ -293,10 +293,12 @@ impl<'a, 'b> ReadOnlyChecker<'a, 'b> {
             | TupleGet | TupleMerge | Len | Print | AsContract | Begin | FetchVar
             | GetStxBalance | StxGetAccount | GetTokenBalance | GetAssetOwner | GetTokenSupply
             | ElementAt | IndexOf | Slice | ReplaceAt | BitwiseAnd | BitwiseOr | BitwiseNot
-            | BitwiseLShift | BitwiseRShift | BitwiseXor2 | ElementAtAlias | IndexOfAlias => {
+            | BitwiseLShift | BitwiseRShift | BitwiseXor2 | ElementAtAlias | IndexOfAlias
+            | BuffAnd | BuffOr | BuffXor | BuffNot | ConcatMany | Replicate | Repeat => {
                 // Check all arguments.
                 self.check_each_expression_is_read_only(args)
             }
+            Secp256k1RecoverPrincipal | PrintEvent => self.check_each_expression_is_read_only(args),
             FromConsensusBuff => {
                 // Check only the second+ arguments: the first argument is a type parameter
                 check_argument_count(2, args)?;
@@ -351,7 +353,7 @@ impl<'a, 'b> ReadOnlyChecker<'a, 'b> {
                 //     read-only or not.
                 self.check_expression_application_is_read_only(args)
             }
-            Filter => {
+            Filter | Find | Position => {
                 check_argument_count(2, args)?;
                 self.check_expression_application_is_read_only(args)
             }
@@ -0,0 +1,190 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Property tests for trait conformance.
+//!
+//! Each case generates a trait definition plus a handful of contracts that claim to
+//! implement it, using the strategies in `proptest_utils`.  Some implementations are built to
+//! be compatible with the trait (same or wider argument types, same return types, optional
+//! extra methods), and the rest are derived from a compatible one by a single
+//! `TraitMutation` that must make the analysis reject it.
+
+use proptest::prelude::*;
+use stacks_common::types::StacksEpochId;
+
+use crate::vm::analysis::errors::CheckErrors;
+use crate::vm::analysis::{type_check, CheckError};
+use crate::vm::ast::parse;
+use crate::vm::database::MemoryBackingStore;
+use crate::vm::tests::proptest_utils::{
+    prop_compatible_impl, prop_incompatible_impl, prop_trait_spec, ImplSpec, TraitSpec,
+};
+use crate::vm::types::QualifiedContractIdentifier;
+use crate::vm::ClarityVersion;
+
+/// Maximum number of implementing contracts per case
+const MAX_IMPLEMENTATIONS: usize = 4;
+const MAX_METHODS: usize = 4;
+const MAX_ARGS: usize = 3;
+/// Maximum nesting of optional/list types
+const MAX_TYPE_NESTING: u32 = 2;
+
+const TRAIT_CONTRACT_NAME: &str = "trait-defs";
+const TRAIT_NAME: &str = "conformance-trait";
+
+fn trait_path() -> String {
+    format!(".{}.{}", TRAIT_CONTRACT_NAME, TRAIT_NAME)
+}
+
+/// Analyze the trait contract followed by each implementation, in order.
+/// Returns the result of analyzing each implementation.
+fn check_implementations(
+    trait_spec: &TraitSpec,
+    implementations: &[ImplSpec],
+) -> Vec<Result<(), CheckError>> {
+    let version = ClarityVersion::Clarity2;
+    let epoch = StacksEpochId::Epoch21;
+    let mut marf = MemoryBackingStore::new();
+    let mut db = marf.as_analysis_db();
+
+    let trait_contract_id = QualifiedContractIdentifier::local(TRAIT_CONTRACT_NAME).unwrap();
+    let trait_src = trait_spec.source(TRAIT_NAME);
+    let mut trait_contract = parse(&trait_contract_id, &trait_src, version, epoch).unwrap();
+    // The backing store only accepts analysis metadata for deployed contracts, so keep
+    // everything in an outer nesting level that is never committed to the store.
+    db.begin();
+    db.execute(|db| {
+        type_check(
+            &trait_contract_id,
+            &mut trait_contract,
+            db,
+            true,
+            &epoch,
+            &version,
+        )
+    })
+    .unwrap_or_else(|e| panic!("Generated trait failed to check: {:?}\n{}", e, trait_src));
+
+    let results = implementations
+        .iter()
+        .enumerate()
+        .map(|(i, implementation)| {
+            let impl_contract_id =
+                QualifiedContractIdentifier::local(&format!("implementation-{}", i)).unwrap();
+            let impl_src = implementation.source(&trait_path());
+            let mut impl_contract = parse(&impl_contract_id, &impl_src, version, epoch)
+                .unwrap_or_else(|e| panic!("Failed to parse: {:?}\n{}", e, impl_src));
+            db.execute(|db| {
+                type_check(
+                    &impl_contract_id,
+                    &mut impl_contract,
+                    db,
+                    true,
+                    &epoch,
+                    &version,
+                )
+            })
+            .map(|_| ())
+        })
+        .collect();
+    db.roll_back().unwrap();
+    results
+}
+
+/// A trait, and implementations of it drawn from `prop_impl(trait)`
+fn prop_case<F>(
+    num_implementations: std::ops::RangeInclusive<usize>,
+    prop_impl: F,
+) -> impl Strategy<Value = (TraitSpec, Vec<ImplSpec>)>
+where
+    F: Fn(&TraitSpec) -> BoxedStrategy<ImplSpec>,
+{
+    prop_trait_spec(MAX_METHODS, MAX_ARGS, MAX_TYPE_NESTING).prop_flat_map(move |trait_spec| {
+        let implementations =
+            prop::collection::vec(prop_impl(&trait_spec), num_implementations.clone());
+        (Just(trait_spec), implementations)
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn prop_compatible_implementations_are_accepted(
+        (trait_spec, implementations) in prop_case(
+            MAX_IMPLEMENTATIONS..=MAX_IMPLEMENTATIONS,
+            prop_compatible_impl,
+        )
+    ) {
+        let results = check_implementations(&trait_spec, &implementations);
+        for (implementation, result) in implementations.iter().zip(results) {
+            prop_assert!(
+                result.is_ok(),
+                "compatible implementation rejected: {:?}\n{}\n{}",
+                result,
+                trait_spec.source(TRAIT_NAME),
+                implementation.source(&trait_path())
+            );
+        }
+    }
+
+    #[test]
+    fn prop_incompatible_implementations_are_rejected(
+        (trait_spec, implementations) in prop_case(
+            MAX_IMPLEMENTATIONS..=MAX_IMPLEMENTATIONS,
+            prop_incompatible_impl,
+        )
+    ) {
+        let results = check_implementations(&trait_spec, &implementations);
+        for (implementation, result) in implementations.iter().zip(results) {
+            prop_assert!(
+                matches!(
+                    result,
+                    Err(CheckError {
+                        err: CheckErrors::BadTraitImplementation(..),
+                        ..
+                    })
+                ),
+                "implementation with {:?} was not rejected as a bad trait implementation: {:?}\n{}\n{}",
+                implementation.mutation,
+                result,
+                trait_spec.source(TRAIT_NAME),
+                implementation.source(&trait_path())
+            );
+        }
+    }
+
+    #[test]
+    fn prop_mixed_implementations_are_classified(
+        (trait_spec, implementations) in prop_case(1..=MAX_IMPLEMENTATIONS, |trait_spec| {
+            prop_oneof![prop_compatible_impl(trait_spec), prop_incompatible_impl(trait_spec)]
+                .boxed()
+        })
+    ) {
+        let results = check_implementations(&trait_spec, &implementations);
+        for (implementation, result) in implementations.iter().zip(results) {
+            prop_assert_eq!(
+                implementation.mutation.is_none(),
+                result.is_ok(),
+                "{:?} was misclassified: {:?}\n{}\n{}",
+                implementation.mutation,
+                result,
+                trait_spec.source(TRAIT_NAME),
+                implementation.source(&trait_path())
+            );
+        }
+    }
+}
@@ -76,5 +76,7 @@ impl TraitChecker {
     }
 }
 
+#[cfg(test)]
+mod conformance;
 #[cfg(test)]
 mod tests;
@@ -779,6 +779,21 @@ impl TypedNativeFunction {
                 )
                 .into())
             }
+            BuffAnd
+            | BuffOr
+            | BuffXor
+            | BuffNot
+            | ConcatMany
+            | Secp256k1RecoverPrincipal
+            | PrintEvent
+            | Replicate
+            | Repeat
+            | Find
+            | Position => {
+                return Err(CheckErrors::Expects(
+                    "Clarity 3 keywords should not show up in 2.05".into(),
+                ))
+            }
         };
 
         Ok(out)
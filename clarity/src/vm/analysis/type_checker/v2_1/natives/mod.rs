@@ -29,8 +29,8 @@ use crate::vm::costs::{
 use crate::vm::errors::{Error as InterpError, RuntimeErrorType};
 use crate::vm::functions::{handle_binding_list, NativeFunctions};
 use crate::vm::types::signatures::{
-    CallableSubtype, FunctionArgSignature, FunctionReturnsSignature, SequenceSubtype, ASCII_40,
-    UTF8_40,
+    CallableSubtype, FunctionArgSignature, FunctionReturnsSignature, SequenceSubtype, ASCII_128,
+    ASCII_40, UTF8_40,
 };
 use crate::vm::types::TypeSignature::SequenceType;
 use crate::vm::types::{
@@ -112,6 +112,20 @@ fn check_special_print(
     checker.type_check(&args[0], context)
 }
 
+fn check_special_print_event(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    check_argument_count(2, args)?;
+    checker.type_check_expects(&args[0], context, &ASCII_128)?;
+    let payload_type = checker.type_check(&args[1], context)?;
+    match payload_type {
+        TypeSignature::TupleType(_) => Ok(payload_type),
+        _ => Err(CheckErrors::ExpectedTuple(payload_type).into()),
+    }
+}
+
 fn check_special_as_contract(
     checker: &mut TypeChecker,
     args: &[SymbolicExpression],
@@ -697,6 +711,20 @@ fn check_secp256k1_recover(
     )
 }
 
+fn check_secp256k1_recover_principal(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    check_argument_count(2, args)?;
+    checker.type_check_expects(&args[0], context, &BUFF_32)?;
+    checker.type_check_expects(&args[1], context, &BUFF_65)?;
+    Ok(
+        TypeSignature::new_response(TypeSignature::PrincipalType, TypeSignature::UIntType)
+            .map_err(|_| CheckErrors::Expects("Bad constructor".into()))?,
+    )
+}
+
 fn check_secp256k1_verify(
     checker: &mut TypeChecker,
     args: &[SymbolicExpression],
@@ -947,6 +975,9 @@ impl TypedNativeFunction {
                 BUFF_32.clone(),
             ))),
             Secp256k1Recover => Special(SpecialNativeFunction(&check_secp256k1_recover)),
+            Secp256k1RecoverPrincipal => {
+                Special(SpecialNativeFunction(&check_secp256k1_recover_principal))
+            }
             Secp256k1Verify => Special(SpecialNativeFunction(&check_secp256k1_verify)),
             GetStxBalance => Simple(SimpleNativeFunction(FunctionType::Fixed(FixedFunction {
                 args: vec![FunctionArg::new(
@@ -1065,9 +1096,14 @@ impl TypedNativeFunction {
             SetVar => Special(SpecialNativeFunction(&check_special_set_var)),
             Map => Special(SpecialNativeFunction(&sequences::check_special_map)),
             Filter => Special(SpecialNativeFunction(&sequences::check_special_filter)),
+            Find => Special(SpecialNativeFunction(&sequences::check_special_find)),
+            Position => Special(SpecialNativeFunction(&sequences::check_special_position)),
             Fold => Special(SpecialNativeFunction(&sequences::check_special_fold)),
             Append => Special(SpecialNativeFunction(&sequences::check_special_append)),
             Concat => Special(SpecialNativeFunction(&sequences::check_special_concat)),
+            ConcatMany => Special(SpecialNativeFunction(&sequences::check_special_concat_many)),
+            Replicate => Special(SpecialNativeFunction(&sequences::check_special_replicate)),
+            Repeat => Special(SpecialNativeFunction(&sequences::check_special_repeat)),
             AsMaxLen => Special(SpecialNativeFunction(&sequences::check_special_as_max_len)),
             Len => Special(SpecialNativeFunction(&sequences::check_special_len)),
             ElementAt | ElementAtAlias => {
@@ -1078,6 +1114,10 @@ impl TypedNativeFunction {
             }
             Slice => Special(SpecialNativeFunction(&sequences::check_special_slice)),
             ReplaceAt => Special(SpecialNativeFunction(&sequences::check_special_replace_at)),
+            BuffAnd | BuffOr | BuffXor => Special(SpecialNativeFunction(
+                &sequences::check_special_buff_bitwise,
+            )),
+            BuffNot => Special(SpecialNativeFunction(&sequences::check_special_buff_not)),
             ListCons => Special(SpecialNativeFunction(&check_special_list_cons)),
             FetchEntry => Special(SpecialNativeFunction(&maps::check_special_fetch_entry)),
             SetEntry => Special(SpecialNativeFunction(&maps::check_special_set_entry)),
@@ -1088,6 +1128,7 @@ impl TypedNativeFunction {
             TupleMerge => Special(SpecialNativeFunction(&check_special_merge)),
             Begin => Special(SpecialNativeFunction(&check_special_begin)),
             Print => Special(SpecialNativeFunction(&check_special_print)),
+            PrintEvent => Special(SpecialNativeFunction(&check_special_print_event)),
             AsContract => Special(SpecialNativeFunction(&check_special_as_contract)),
             ContractCall => Special(SpecialNativeFunction(&check_contract_call)),
             ContractOf => Special(SpecialNativeFunction(&check_contract_of)),
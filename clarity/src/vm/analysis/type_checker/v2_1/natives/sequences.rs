@@ -196,6 +196,65 @@ pub fn check_special_filter(
     Ok(argument_type)
 }
 
+/// Type-check the predicate and sequence passed to `find?` or `position?`.  The predicate must
+/// take an element of the sequence and return a bool.  Returns the element type.
+fn check_predicate_over_sequence(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    check_argument_count(2, args)?;
+
+    let function_name = args[0]
+        .match_atom()
+        .ok_or(CheckErrors::NonFunctionApplication)?;
+    // we will only lookup native or defined functions here.
+    //   you _cannot_ search with a special function.
+    let function_type = get_simple_native_or_user_define(function_name, checker)?;
+
+    runtime_cost(ClarityCostFunction::AnalysisIterableFunc, checker, 0)?;
+    let argument_type = checker.type_check(&args[1], context)?;
+
+    let input_type = match argument_type {
+        TypeSignature::SequenceType(ref sequence_type) => Ok(sequence_type.unit_type()?),
+        _ => Err(CheckErrors::ExpectedSequence(argument_type.clone())),
+    }?;
+
+    let predicate_type = function_type.check_args(
+        checker,
+        &[input_type.clone()],
+        context.epoch,
+        context.clarity_version,
+    )?;
+
+    if TypeSignature::BoolType != predicate_type {
+        return Err(CheckErrors::TypeError(TypeSignature::BoolType, predicate_type).into());
+    }
+
+    Ok(input_type)
+}
+
+/// Type-check `find?`, which returns the first element for which a predicate holds
+pub fn check_special_find(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    let element_type = check_predicate_over_sequence(checker, args, context)?;
+    Ok(TypeSignature::new_option(element_type)?)
+}
+
+/// Type-check `position?`, which returns the index of the first element for which a predicate
+/// holds
+pub fn check_special_position(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    check_predicate_over_sequence(checker, args, context)?;
+    Ok(TypeSignature::new_option(TypeSignature::UIntType)?)
+}
+
 pub fn check_special_fold(
     checker: &mut TypeChecker,
     args: &[SymbolicExpression],
@@ -304,6 +363,57 @@ pub fn check_special_concat(
     Ok(res)
 }
 
+/// Type-check `concat-many`, which joins one or more strings of the same kind.
+/// The result's maximum length is the sum of the arguments' maximum lengths.
+pub fn check_special_concat_many(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    check_arguments_at_least(1, args)?;
+
+    runtime_cost(ClarityCostFunction::AnalysisIterableFunc, checker, 0)?;
+
+    let first_type = checker.type_check(&args[0], context)?;
+    let mut total_len: u32 = match &first_type {
+        TypeSignature::SequenceType(StringType(ASCII(len))) => u32::from(len),
+        TypeSignature::SequenceType(StringType(UTF8(len))) => u32::from(len),
+        _ => {
+            return Err(
+                CheckErrors::TypeError(TypeSignature::max_string_ascii()?, first_type).into(),
+            )
+        }
+    };
+
+    for arg in args[1..].iter() {
+        let arg_type = checker.type_check(arg, context)?;
+        analysis_typecheck_cost(checker, &first_type, &arg_type)?;
+        let arg_len = match (&first_type, &arg_type) {
+            (
+                TypeSignature::SequenceType(StringType(ASCII(_))),
+                TypeSignature::SequenceType(StringType(ASCII(len))),
+            ) => u32::from(len),
+            (
+                TypeSignature::SequenceType(StringType(UTF8(_))),
+                TypeSignature::SequenceType(StringType(UTF8(len))),
+            ) => u32::from(len),
+            _ => return Err(CheckErrors::TypeError(first_type, arg_type).into()),
+        };
+        total_len = total_len
+            .checked_add(arg_len)
+            .ok_or(CheckErrors::MaxLengthOverflow)?;
+    }
+
+    match first_type {
+        TypeSignature::SequenceType(StringType(ASCII(_))) => Ok(TypeSignature::SequenceType(
+            StringType(ASCII(total_len.try_into()?)),
+        )),
+        _ => Ok(TypeSignature::SequenceType(StringType(UTF8(
+            total_len.try_into()?,
+        )))),
+    }
+}
+
 pub fn check_special_append(
     checker: &mut TypeChecker,
     args: &[SymbolicExpression],
@@ -389,6 +499,92 @@ pub fn check_special_as_max_len(
     }
 }
 
+/// Type-check the count passed to `replicate` or `repeat`, which must be a uint literal so that
+/// the result's maximum length is known statically
+fn check_literal_count(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> CheckResult<u32> {
+    let count = match args[1].expr {
+        SymbolicExpressionType::LiteralValue(Value::UInt(count)) => count,
+        _ => {
+            let count_type = checker.type_check(&args[1], context)?;
+            return Err(CheckErrors::TypeError(TypeSignature::UIntType, count_type).into());
+        }
+    };
+    runtime_cost(
+        ClarityCostFunction::AnalysisTypeAnnotate,
+        checker,
+        TypeSignature::UIntType.type_size()?,
+    )?;
+    checker
+        .type_map
+        .set_type(&args[1], TypeSignature::UIntType)?;
+
+    Ok(u32::try_from(count).map_err(|_e| CheckErrors::MaxLengthOverflow)?)
+}
+
+/// Type-check `replicate`, which makes a list of `count` copies of an element
+pub fn check_special_replicate(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    check_argument_count(2, args)?;
+
+    let count = check_literal_count(checker, args, context)?;
+    let element_type = checker.type_check(&args[0], context)?;
+    runtime_cost(
+        ClarityCostFunction::AnalysisListItemsCheck,
+        checker,
+        element_type.type_size()?,
+    )?;
+
+    Ok(TypeSignature::SequenceType(ListType(
+        ListTypeData::new_list(element_type, count)?,
+    )))
+}
+
+/// Type-check `repeat`, which repeats a sequence `count` times.
+/// The result's maximum length is `count` times the sequence's maximum length.
+pub fn check_special_repeat(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    check_argument_count(2, args)?;
+
+    let count = check_literal_count(checker, args, context)?;
+    let sequence = checker.type_check(&args[0], context)?;
+    runtime_cost(ClarityCostFunction::AnalysisIterableFunc, checker, 0)?;
+
+    let repeated_len = |len: u32| len.checked_mul(count).ok_or(CheckErrors::ValueTooLarge);
+    match sequence {
+        TypeSignature::SequenceType(ListType(list)) => {
+            let max_len = repeated_len(list.get_max_len())?;
+            let (entry_type, _) = list.destruct();
+            Ok(TypeSignature::SequenceType(ListType(
+                ListTypeData::new_list(entry_type, max_len)?,
+            )))
+        }
+        TypeSignature::SequenceType(BufferType(len)) => Ok(TypeSignature::SequenceType(
+            BufferType(BufferLength::try_from(repeated_len(u32::from(len))?)?),
+        )),
+        TypeSignature::SequenceType(StringType(ASCII(len))) => {
+            Ok(TypeSignature::SequenceType(StringType(ASCII(
+                BufferLength::try_from(repeated_len(u32::from(len))?)?,
+            ))))
+        }
+        TypeSignature::SequenceType(StringType(UTF8(len))) => {
+            Ok(TypeSignature::SequenceType(StringType(UTF8(
+                StringUTF8Length::try_from(repeated_len(u32::from(len))?)?,
+            ))))
+        }
+        _ => Err(CheckErrors::ExpectedSequence(sequence).into()),
+    }
+}
+
 pub fn check_special_len(
     checker: &mut TypeChecker,
     args: &[SymbolicExpression],
@@ -513,3 +709,46 @@ pub fn check_special_replace_at(
     let final_type = TypeSignature::new_option(input_type)?;
     Ok(final_type)
 }
+
+fn expect_buffer_type(input_type: TypeSignature) -> TypeResult {
+    match input_type {
+        TypeSignature::SequenceType(BufferType(_)) => Ok(input_type),
+        _ => Err(CheckErrors::TypeError(TypeSignature::max_buffer()?, input_type).into()),
+    }
+}
+
+/// Type-check `buff-and`, `buff-or` and `buff-xor`.
+/// Both operands must be buffers of the same declared length.
+pub fn check_special_buff_bitwise(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    check_argument_count(2, args)?;
+
+    let lhs_type = expect_buffer_type(checker.type_check(&args[0], context)?)?;
+    let rhs_type = expect_buffer_type(checker.type_check(&args[1], context)?)?;
+
+    runtime_cost(ClarityCostFunction::AnalysisIterableFunc, checker, 0)?;
+
+    if lhs_type != rhs_type {
+        return Err(CheckErrors::TypeError(lhs_type, rhs_type).into());
+    }
+
+    Ok(lhs_type)
+}
+
+/// Type-check `buff-not`, which returns a buffer of the same type as its operand
+pub fn check_special_buff_not(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    check_argument_count(1, args)?;
+
+    let input_type = expect_buffer_type(checker.type_check(&args[0], context)?)?;
+
+    runtime_cost(ClarityCostFunction::AnalysisIterableFunc, checker, 0)?;
+
+    Ok(input_type)
+}
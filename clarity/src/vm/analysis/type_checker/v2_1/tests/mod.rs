@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use proptest::prelude::*;
 #[cfg(test)]
 use rstest::rstest;
 #[cfg(test)]
@@ -30,6 +31,8 @@ use crate::vm::ast::errors::ParseErrors;
 use crate::vm::ast::{build_ast, parse};
 use crate::vm::contexts::OwnedEnvironment;
 use crate::vm::representations::SymbolicExpression;
+use crate::vm::test_util::contract_source::value_source;
+use crate::vm::tests::proptest_utils::prop_mistyped_value;
 use crate::vm::tests::test_clarity_versions;
 use crate::vm::types::signatures::TypeSignature::OptionalType;
 use crate::vm::types::signatures::{ListTypeData, StringUTF8Length};
@@ -39,7 +42,7 @@ use crate::vm::types::TypeSignature::{BoolType, IntType, PrincipalType, Sequence
 use crate::vm::types::Value::Sequence;
 use crate::vm::types::{
     BufferLength, FixedFunction, FunctionType, PrincipalData, QualifiedContractIdentifier,
-    TraitIdentifier, TypeSignature, Value, BUFF_1, BUFF_20, BUFF_21, BUFF_32, BUFF_64,
+    TraitIdentifier, TypeSignature, Value, BUFF_1, BUFF_20, BUFF_21, BUFF_32, BUFF_64, BUFF_65,
 };
 use crate::vm::{execute_v2, ClarityName, ClarityVersion};
 
@@ -1696,6 +1699,326 @@ fn test_replace_at_buff() {
     }
 }
 
+#[test]
+fn test_buff_bitwise() {
+    let good = [
+        "(buff-and 0x0f0f 0xff00)",
+        "(buff-or 0x00 0x01)",
+        "(buff-xor (sha256 u1) (sha256 u2))",
+        "(buff-not 0x00112233)",
+        "(buff-not (buff-xor 0x0011 0x2233))",
+    ];
+    let expected = ["(buff 2)", "(buff 1)", "(buff 32)", "(buff 4)", "(buff 2)"];
+
+    for (good_test, expected) in good.iter().zip(expected.iter()) {
+        assert_eq!(
+            expected,
+            &format!("{}", type_check_helper(good_test).unwrap())
+        );
+    }
+
+    let bad = [
+        "(buff-and 0x00 0x0011)",
+        "(buff-or 0x00 \"a\")",
+        "(buff-xor u1 0x00)",
+        "(buff-and 0x00 0x00 0x00)",
+        "(buff-not 1)",
+        "(buff-not 0x00 0x00)",
+    ];
+    let bad_expected = [
+        CheckErrors::TypeError(buff_type(1), buff_type(2)),
+        CheckErrors::TypeError(TypeSignature::max_buffer().unwrap(), ascii_type(1)),
+        CheckErrors::TypeError(TypeSignature::max_buffer().unwrap(), UIntType),
+        CheckErrors::IncorrectArgumentCount(2, 3),
+        CheckErrors::TypeError(TypeSignature::max_buffer().unwrap(), IntType),
+        CheckErrors::IncorrectArgumentCount(1, 2),
+    ];
+    for (bad_test, expected) in bad.iter().zip(bad_expected.iter()) {
+        assert_eq!(expected, &type_check_helper(bad_test).unwrap_err().err);
+    }
+
+    // the buffer bitwise natives are only available in Clarity 3
+    assert!(matches!(
+        mem_run_analysis(
+            "(buff-not 0x00)",
+            ClarityVersion::Clarity2,
+            StacksEpochId::Epoch21
+        )
+        .unwrap_err()
+        .err,
+        CheckErrors::UnknownFunction(_)
+    ));
+}
+
+#[test]
+fn test_concat_many() {
+    let good = [
+        r#"(concat-many "ab" "c")"#,
+        r#"(concat-many "abc")"#,
+        r#"(concat-many "a" "bc" "def" (int-to-ascii 1))"#,
+        r#"(concat-many u"a" u"bc")"#,
+    ];
+    let expected = [
+        "(string-ascii 3)",
+        "(string-ascii 3)",
+        "(string-ascii 46)",
+        "(string-utf8 3)",
+    ];
+
+    for (good_test, expected) in good.iter().zip(expected.iter()) {
+        assert_eq!(
+            expected,
+            &format!("{}", type_check_helper(good_test).unwrap())
+        );
+    }
+
+    let bad = [
+        "(concat-many)",
+        r#"(concat-many "a" u"b")"#,
+        r#"(concat-many u"a" "b")"#,
+        "(concat-many 0x01 0x02)",
+        r#"(concat-many "a" 1)"#,
+    ];
+    let utf8_1 = SequenceType(StringType(UTF8(StringUTF8Length::try_from(1u32).unwrap())));
+    let bad_expected = [
+        CheckErrors::RequiresAtLeastArguments(1, 0),
+        CheckErrors::TypeError(ascii_type(1), utf8_1.clone()),
+        CheckErrors::TypeError(utf8_1, ascii_type(1)),
+        CheckErrors::TypeError(TypeSignature::max_string_ascii().unwrap(), buff_type(1)),
+        CheckErrors::TypeError(ascii_type(1), IntType),
+    ];
+    for (bad_test, expected) in bad.iter().zip(bad_expected.iter()) {
+        assert_eq!(expected, &type_check_helper(bad_test).unwrap_err().err);
+    }
+
+    // `concat-many` is only available in Clarity 3
+    assert!(matches!(
+        mem_run_analysis(
+            r#"(concat-many "a" "b")"#,
+            ClarityVersion::Clarity2,
+            StacksEpochId::Epoch21
+        )
+        .unwrap_err()
+        .err,
+        CheckErrors::UnknownFunction(_)
+    ));
+}
+
+#[test]
+fn test_print_event() {
+    let good = [
+        r#"(print-event "transfer" { amount: u1 })"#,
+        r#"(print-event "" { a: 1, b: "x" })"#,
+    ];
+    let expected = [
+        "(tuple (amount uint))",
+        "(tuple (a int) (b (string-ascii 1)))",
+    ];
+
+    for (good_test, expected) in good.iter().zip(expected.iter()) {
+        assert_eq!(
+            expected,
+            &format!("{}", type_check_helper(good_test).unwrap())
+        );
+    }
+
+    let long_topic = format!(r#"(print-event "{}" {{ a: 1 }})"#, "a".repeat(129));
+    let bad = [
+        r#"(print-event "topic")"#,
+        r#"(print-event u"topic" { a: 1 })"#,
+        long_topic.as_str(),
+        r#"(print-event "topic" 1)"#,
+        r#"(print-event "topic" (list 1 2))"#,
+    ];
+    let utf8_5 = SequenceType(StringType(UTF8(StringUTF8Length::try_from(5u32).unwrap())));
+    let bad_expected = [
+        CheckErrors::IncorrectArgumentCount(2, 1),
+        CheckErrors::TypeError(ascii_type(128), utf8_5),
+        CheckErrors::TypeError(ascii_type(128), ascii_type(129)),
+        CheckErrors::ExpectedTuple(IntType),
+        CheckErrors::ExpectedTuple(TypeSignature::list_of(IntType, 2).unwrap()),
+    ];
+    for (bad_test, expected) in bad.iter().zip(bad_expected.iter()) {
+        assert_eq!(expected, &type_check_helper(bad_test).unwrap_err().err);
+    }
+
+    // `print-event` is only available in Clarity 3
+    assert!(matches!(
+        mem_run_analysis(
+            r#"(print-event "topic" { a: 1 })"#,
+            ClarityVersion::Clarity2,
+            StacksEpochId::Epoch21
+        )
+        .unwrap_err()
+        .err,
+        CheckErrors::UnknownFunction(_)
+    ));
+}
+
+#[test]
+fn test_secp256k1_recover_principal() {
+    let good = [
+        "(secp256k1-recover-principal? (sha256 u1) 0x8738487ebe69b93d8e51583be8eee50bb4213fc49c767d329632730cc193b873554428fc936ca3569afc15f1c9365f6591d6251a89fee9c9ac661116824d3a1301)",
+        "(secp256k1-recover-principal? (sha256 u1) 0x00)",
+    ];
+    for good_test in good.iter() {
+        assert_eq!(
+            "(response principal uint)",
+            &format!("{}", type_check_helper(good_test).unwrap())
+        );
+    }
+
+    let bad = [
+        "(secp256k1-recover-principal? (sha256 u1))",
+        "(secp256k1-recover-principal? (sha512 u1) 0x00)",
+        "(secp256k1-recover-principal? (sha256 u1) (concat (sha512 u1) 0x0000))",
+        "(secp256k1-recover-principal? (sha256 u1) 1)",
+    ];
+    let bad_expected = [
+        CheckErrors::IncorrectArgumentCount(2, 1),
+        CheckErrors::TypeError(BUFF_32.clone(), BUFF_64.clone()),
+        CheckErrors::TypeError(BUFF_65.clone(), buff_type(66)),
+        CheckErrors::TypeError(BUFF_65.clone(), IntType),
+    ];
+    for (bad_test, expected) in bad.iter().zip(bad_expected.iter()) {
+        assert_eq!(expected, &type_check_helper(bad_test).unwrap_err().err);
+    }
+
+    // `secp256k1-recover-principal?` is only available in Clarity 3
+    assert!(matches!(
+        mem_run_analysis(
+            "(secp256k1-recover-principal? (sha256 u1) 0x00)",
+            ClarityVersion::Clarity2,
+            StacksEpochId::Epoch21
+        )
+        .unwrap_err()
+        .err,
+        CheckErrors::UnknownFunction(_)
+    ));
+}
+
+#[test]
+fn test_replicate_and_repeat() {
+    let good = [
+        "(replicate 0 u3)",
+        "(replicate 0x0102 u10)",
+        "(replicate (list 1 2) u2)",
+        "(replicate 0 u0)",
+        "(repeat 0x00 u32)",
+        r#"(repeat "ab" u3)"#,
+        r#"(repeat u"ab" u3)"#,
+        "(repeat (list 1 2) u2)",
+    ];
+    let expected = [
+        "(list 3 int)",
+        "(list 10 (buff 2))",
+        "(list 2 (list 2 int))",
+        "(list 0 int)",
+        "(buff 32)",
+        "(string-ascii 6)",
+        "(string-utf8 6)",
+        "(list 4 int)",
+    ];
+
+    for (good_test, expected) in good.iter().zip(expected.iter()) {
+        assert_eq!(
+            expected,
+            &format!("{}", type_check_helper(good_test).unwrap())
+        );
+    }
+
+    let bad = [
+        "(replicate 0)",
+        "(replicate 0 3)",
+        "(replicate 0x00 u2000000)",
+        "(repeat 1 u2)",
+        "(repeat 0x0000 u1048576)",
+        "(repeat 0x00 u4294967296)",
+    ];
+    let bad_expected = [
+        CheckErrors::IncorrectArgumentCount(2, 1),
+        CheckErrors::TypeError(UIntType, IntType),
+        CheckErrors::ValueTooLarge,
+        CheckErrors::ExpectedSequence(IntType),
+        CheckErrors::ValueTooLarge,
+        CheckErrors::MaxLengthOverflow,
+    ];
+    for (bad_test, expected) in bad.iter().zip(bad_expected.iter()) {
+        assert_eq!(expected, &type_check_helper(bad_test).unwrap_err().err);
+    }
+
+    // `replicate` and `repeat` are only available in Clarity 3
+    for program in ["(replicate 0 u3)", "(repeat 0x00 u3)"] {
+        assert!(matches!(
+            mem_run_analysis(program, ClarityVersion::Clarity2, StacksEpochId::Epoch21)
+                .unwrap_err()
+                .err,
+            CheckErrors::UnknownFunction(_)
+        ));
+    }
+}
+
+#[test]
+fn test_find_and_position() {
+    let good = [
+        "(find? not (list true false))",
+        "(position? not (list true false))",
+        "(define-private (is-big (x int)) (> x 10))
+         (find? is-big (list 1 20 3))",
+        "(define-private (is-b (char (string-ascii 1))) (is-eq char \"b\"))
+         (find? is-b \"abc\")",
+        "(define-private (is-zero (byte (buff 1))) (is-eq byte 0x00))
+         (position? is-zero 0x0102)",
+        "(define-private (is-a (char (string-utf8 1))) (is-eq char u\"a\"))
+         (find? is-a u\"bca\")",
+    ];
+    let expected = [
+        "(optional bool)",
+        "(optional uint)",
+        "(optional int)",
+        "(optional (string-ascii 1))",
+        "(optional uint)",
+        "(optional (string-utf8 1))",
+    ];
+
+    for (good_test, expected) in good.iter().zip(expected.iter()) {
+        assert_eq!(
+            expected,
+            &format!("{}", type_check_helper(good_test).unwrap())
+        );
+    }
+
+    let bad = [
+        "(find? hash160 (list 1 2 3 4))",
+        "(position? not (list 1 2 3 4))",
+        "(find? not (list true) 1)",
+        "(position? ynot (list 1 2 3 4))",
+        "(find? not true)",
+        "(position? if (list true))",
+    ];
+    let bad_expected = [
+        CheckErrors::TypeError(BoolType, buff_type(20)),
+        CheckErrors::TypeError(BoolType, IntType),
+        CheckErrors::IncorrectArgumentCount(2, 3),
+        CheckErrors::UnknownFunction("ynot".to_string()),
+        CheckErrors::ExpectedSequence(BoolType),
+        CheckErrors::IllegalOrUnknownFunctionApplication("if".to_string()),
+    ];
+    for (bad_test, expected) in bad.iter().zip(bad_expected.iter()) {
+        assert_eq!(expected, &type_check_helper(bad_test).unwrap_err().err);
+    }
+
+    // `find?` and `position?` are only available in Clarity 3
+    for program in ["(find? not (list true))", "(position? not (list true))"] {
+        assert!(matches!(
+            mem_run_analysis(program, ClarityVersion::Clarity2, StacksEpochId::Epoch21)
+                .unwrap_err()
+                .err,
+            CheckErrors::UnknownFunction(_)
+        ));
+    }
+}
+
 #[test]
 fn test_replace_at_ascii() {
     let good = [
@@ -3657,3 +3980,14 @@ fn test_principal_admits() {
         assert!(res.is_err());
     }
 }
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn prop_mistyped_initial_values_are_rejected((ty, value) in prop_mistyped_value(3)) {
+        let contract = format!("(define-data-var v {} {})", ty, value_source(&value));
+        let result = mem_type_check(&contract);
+        prop_assert!(result.is_err(), "{} passed the type checker", contract);
+    }
+}
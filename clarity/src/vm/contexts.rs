@@ -1404,6 +1404,17 @@ impl<'a, 'b, 'hooks> Environment<'a, 'b, 'hooks> {
         Ok(())
     }
 
+    pub fn register_structured_event(&mut self, topic: String, payload: Value) -> Result<()> {
+        let event = StacksTransactionEvent::StructuredEvent(StructuredEventData {
+            contract_identifier: self.contract_context.contract_identifier.clone(),
+            topic,
+            payload,
+        });
+
+        self.push_to_event_batch(event);
+        Ok(())
+    }
+
     pub fn register_stx_transfer_event(
         &mut self,
         sender: PrincipalData,
@@ -999,6 +999,51 @@ The `func` argument must be a literal function name.
 "#,
 };
 
+const FIND_API: SpecialAPI = SpecialAPI {
+    input_type: "Function(A) -> bool, sequence_A",
+    snippet: "find? ${1:func} ${2:sequence}",
+    output_type: "(optional A)",
+    signature: "(find? func sequence)",
+    description: "The `find?` function applies the input function `func` to the elements of the
+input sequence in order, and returns the first element for which `func` returned `true`,
+wrapped in `some`. If there is no such element, it returns `none`. Unlike `filter` or `fold`,
+`func` is not applied to the elements after the first match.
+Applicable sequence types are `(list A)`, `buff`, `string-ascii` and `string-utf8`,
+for which the corresponding element types are, respectively, `A`, `(buff 1)`, `(string-ascii 1)` and `(string-utf8 1)`.
+The `func` argument must be a literal function name.
+",
+    example: r#"
+(define-private (is-big (x int)) (> x 10))
+(find? is-big (list 1 20 3 40)) ;; Returns (some 20)
+(find? is-big (list 1 2 3)) ;; Returns none
+(define-private (is-b (char (string-ascii 1))) (is-eq char "b"))
+(find? is-b "abc") ;; Returns (some "b")
+"#,
+};
+
+const POSITION_API: SpecialAPI = SpecialAPI {
+    input_type: "Function(A) -> bool, sequence_A",
+    snippet: "position? ${1:func} ${2:sequence}",
+    output_type: "(optional uint)",
+    signature: "(position? func sequence)",
+    description: "The `position?` function applies the input function `func` to the elements of
+the input sequence in order, and returns the index of the first element for which `func`
+returned `true`, wrapped in `some`. If there is no such element, it returns `none`. It
+generalizes `index-of?`, which looks for an element equal to a given item. Unlike `filter` or
+`fold`, `func` is not applied to the elements after the first match.
+Applicable sequence types are `(list A)`, `buff`, `string-ascii` and `string-utf8`,
+for which the corresponding element types are, respectively, `A`, `(buff 1)`, `(string-ascii 1)` and `(string-utf8 1)`.
+The `func` argument must be a literal function name.
+",
+    example: r#"
+(define-private (is-big (x int)) (> x 10))
+(position? is-big (list 1 20 3 40)) ;; Returns (some u1)
+(position? is-big (list 1 2 3)) ;; Returns none
+(define-private (is-zero (byte (buff 1))) (is-eq byte 0x00))
+(position? is-zero 0x010200) ;; Returns (some u2)
+"#,
+};
+
 const FOLD_API: SpecialAPI = SpecialAPI {
     input_type: "Function(A, B) -> B, sequence_A, B",
     snippet: "fold ${1:func} ${2:sequence} ${3:initial-value}",
@@ -1187,6 +1232,17 @@ nodes configured for development (as opposed to production mining nodes), this f
     example: "(print (+ 1 2 3)) ;; Returns 6",
 };
 
+const PRINT_EVENT_API: SpecialAPI = SpecialAPI {
+    input_type: "(string-ascii 128), (tuple)",
+    snippet: "print-event ${1:topic} ${2:payload}",
+    output_type: "(tuple)",
+    signature: "(print-event topic payload)",
+    description: "The `print-event` function emits an event with the given `topic` and tuple `payload`, and
+returns `payload`. Unlike `print` events, these events are reported in transaction receipts as `structured_event`s
+carrying their topic, so that event observers can subscribe to every event of a topic, from any contract.",
+    example: "(print-event \"transfer\" { amount: u100, memo: 0x00 }) ;; Returns (tuple (amount u100) (memo 0x00))",
+};
+
 const FETCH_ENTRY_API: SpecialAPI = SpecialAPI {
     input_type: "MapName, tuple",
     snippet: "map-get? ${1:map-name} ${2:key-tuple}",
@@ -1372,6 +1428,25 @@ On success, it returns the public key as a 33-byte buffer. This function may fai
  ;; Returns (ok 0x03adb8de4bfb65db2cfd6120d55c6526ae9c52e675db7e47308636534ba7786110)"
 };
 
+const SECP256K1RECOVER_PRINCIPAL_API: SpecialAPI = SpecialAPI {
+    input_type: "(buff 32), (buff 65)",
+    snippet: "secp256k1-recover-principal? ${1:message-hash} ${2:signature}",
+    output_type: "(response principal uint)",
+    signature: "(secp256k1-recover-principal? message-hash signature)",
+    description: "The `secp256k1-recover-principal?` function recovers the public key used to sign the message
+whose sha256 is `message-hash` with the provided `signature`, and returns the single-signature principal
+derived from it, like `(principal-of? (unwrap! (secp256k1-recover? message-hash signature) ...))` would.
+The returned principal is suited to the network the function is called on.
+This function may fail with one of the following error codes:
+
+* `(err u1)` - the signature does not match the message hash
+* `(err u2)` - the signature is invalid
+",
+    example: "(secp256k1-recover-principal? 0xde5b9eb9e7c5592930eb2e30a01369c36586d872082ed8181ee83d2a0ec20f04
+ 0x8738487ebe69b93d8e51583be8eee50bb4213fc49c767d329632730cc193b873554428fc936ca3569afc15f1c9365f6591d6251a89fee9c9ac661116824d3a1301)
+ ;; Returns (ok ST1AW6EKPGT61SQ9FNVDS17RKNWT8ZP582VF9HSCP)"
+};
+
 const SECP256K1VERIFY_API: SpecialAPI = SpecialAPI {
     input_type: "(buff 32), (buff 64) | (buff 65), (buff 33)",
     snippet: "secp256k1-verify ${1:message-hash} ${2:signature} ${3:public-key})",
@@ -2450,6 +2525,108 @@ If the provided index is out of bounds, this functions returns `none`.
 "#,
 };
 
+const BUFF_AND: SpecialAPI = SpecialAPI {
+    input_type: "(buff N), (buff N)",
+    output_type: "(buff N)",
+    snippet: "buff-and ${1:buff-1} ${2:buff-2}",
+    signature: "(buff-and b1 b2)",
+    description: "Returns the result of bitwise and'ing the buffers `b1` and `b2`, byte by byte.
+Both buffers must have the same type. If the two buffers turn out to have different lengths at
+runtime, a runtime error is thrown.",
+    example: "(buff-and 0x0f0f 0xff00) ;; Returns 0x0f00
+(buff-and 0xdeadbeef 0xffff0000) ;; Returns 0xdead0000
+",
+};
+
+const BUFF_OR: SpecialAPI = SpecialAPI {
+    input_type: "(buff N), (buff N)",
+    output_type: "(buff N)",
+    snippet: "buff-or ${1:buff-1} ${2:buff-2}",
+    signature: "(buff-or b1 b2)",
+    description:
+        "Returns the result of bitwise inclusive or'ing the buffers `b1` and `b2`, byte by byte.
+Both buffers must have the same type. If the two buffers turn out to have different lengths at
+runtime, a runtime error is thrown.",
+    example: "(buff-or 0x0f0f 0xf000) ;; Returns 0xff0f
+(buff-or 0x0001 0x0100) ;; Returns 0x0101
+",
+};
+
+const BUFF_XOR: SpecialAPI = SpecialAPI {
+    input_type: "(buff N), (buff N)",
+    output_type: "(buff N)",
+    snippet: "buff-xor ${1:buff-1} ${2:buff-2}",
+    signature: "(buff-xor b1 b2)",
+    description:
+        "Returns the result of bitwise exclusive or'ing the buffers `b1` and `b2`, byte by byte.
+Both buffers must have the same type. If the two buffers turn out to have different lengths at
+runtime, a runtime error is thrown.",
+    example: "(buff-xor 0x0f0f 0xffff) ;; Returns 0xf0f0
+(buff-xor 0x1234 0x1234) ;; Returns 0x0000
+",
+};
+
+const BUFF_NOT: SpecialAPI = SpecialAPI {
+    input_type: "(buff N)",
+    output_type: "(buff N)",
+    snippet: "buff-not ${1:buff}",
+    signature: "(buff-not b)",
+    description:
+        "Returns the one's complement of the buffer `b`, inverting every bit of every byte.",
+    example: "(buff-not 0x0f00) ;; Returns 0xf0ff
+(buff-not 0xff) ;; Returns 0x00
+",
+};
+
+const CONCAT_MANY_API: SpecialAPI = SpecialAPI {
+    input_type: "string_A, string_A, ...",
+    snippet: "concat-many ${1:string-1} ${2:string-2}",
+    output_type: "string_A",
+    signature: "(concat-many string1 string2 ...)",
+    description: "The `concat-many` function takes one or more strings of the same type,
+either all `string-ascii` or all `string-utf8`, and returns them joined in order.
+The maximum length of the result is the sum of the maximum lengths of the arguments.
+This is useful for building human-readable messages without nesting `concat` calls.
+",
+    example: r#"
+(concat-many "block " "height: " "42") ;; Returns "block height: 42"
+(concat-many u"a" u"b" u"c") ;; Returns u"abc"
+(concat-many "alone") ;; Returns "alone"
+"#,
+};
+
+const REPLICATE_API: SpecialAPI = SpecialAPI {
+    input_type: "A, uint",
+    snippet: "replicate ${1:element} ${2:count}",
+    output_type: "(list A)",
+    signature: "(replicate element count)",
+    description: "The `replicate` function takes an element and a uint-valued, literal count,
+and returns a list of `count` copies of the element. The list's maximum length is `count`.
+This is useful for initializing fixed-size lists, e.g. the accumulator of a `fold`, without
+spelling out every element.",
+    example: r#"
+(replicate 0 u3) ;; Returns (0 0 0)
+(replicate none u2) ;; Returns (none none)
+(len (replicate 0x00 u32)) ;; Returns u32
+"#,
+};
+
+const REPEAT_API: SpecialAPI = SpecialAPI {
+    input_type: "sequence_A, uint",
+    snippet: "repeat ${1:sequence} ${2:count}",
+    output_type: "sequence_A",
+    signature: "(repeat sequence count)",
+    description: "The `repeat` function takes a sequence and a uint-valued, literal count,
+and returns the sequence repeated `count` times. The maximum length of the result is `count`
+times the maximum length of the sequence. Applicable sequence types are `(list A)`, `buff`,
+`string-ascii` and `string-utf8`.",
+    example: r#"
+(repeat 0x00 u4) ;; Returns 0x00000000
+(repeat "ab" u3) ;; Returns "ababab"
+(repeat (list 1 2) u2) ;; Returns (1 2 1 2)
+"#,
+};
+
 pub fn make_api_reference(function: &NativeFunctions) -> FunctionAPI {
     use crate::vm::functions::NativeFunctions::*;
     let name = function.get_name();
@@ -2490,9 +2667,14 @@ pub fn make_api_reference(function: &NativeFunctions) -> FunctionAPI {
         SetVar => make_for_special(&SET_VAR_API, function),
         Map => make_for_special(&MAP_API, function),
         Filter => make_for_special(&FILTER_API, function),
+        Find => make_for_special(&FIND_API, function),
+        Position => make_for_special(&POSITION_API, function),
         Fold => make_for_special(&FOLD_API, function),
         Append => make_for_special(&APPEND_API, function),
         Concat => make_for_special(&CONCAT_API, function),
+        ConcatMany => make_for_special(&CONCAT_MANY_API, function),
+        Replicate => make_for_special(&REPLICATE_API, function),
+        Repeat => make_for_special(&REPEAT_API, function),
         AsMaxLen => make_for_special(&ASSERTS_MAX_LEN_API, function),
         Len => make_for_special(&LEN_API, function),
         ElementAt | ElementAtAlias => make_for_special(&ELEMENT_AT_API, function),
@@ -2513,8 +2695,10 @@ pub fn make_api_reference(function: &NativeFunctions) -> FunctionAPI {
         Sha512Trunc256 => make_for_special(&SHA512T256_API, function),
         Keccak256 => make_for_special(&KECCAK256_API, function),
         Secp256k1Recover => make_for_special(&SECP256K1RECOVER_API, function),
+        Secp256k1RecoverPrincipal => make_for_special(&SECP256K1RECOVER_PRINCIPAL_API, function),
         Secp256k1Verify => make_for_special(&SECP256K1VERIFY_API, function),
         Print => make_for_special(&PRINT_API, function),
+        PrintEvent => make_for_special(&PRINT_EVENT_API, function),
         ContractCall => make_for_special(&CONTRACT_CALL_API, function),
         ContractOf => make_for_special(&CONTRACT_OF_API, function),
         PrincipalOf => make_for_special(&PRINCIPAL_OF_API, function),
@@ -2560,6 +2744,10 @@ pub fn make_api_reference(function: &NativeFunctions) -> FunctionAPI {
         BitwiseNot => make_for_simple_native(&BITWISE_NOT_API, &function, name),
         BitwiseLShift => make_for_simple_native(&BITWISE_LEFT_SHIFT_API, &function, name),
         BitwiseRShift => make_for_simple_native(&BITWISE_RIGHT_SHIFT_API, &function, name),
+        BuffAnd => make_for_special(&BUFF_AND, function),
+        BuffOr => make_for_special(&BUFF_OR, function),
+        BuffXor => make_for_special(&BUFF_XOR, function),
+        BuffNot => make_for_special(&BUFF_NOT, function),
     }
 }
 
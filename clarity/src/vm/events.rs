@@ -17,6 +17,7 @@
 use serde_json::json;
 use stacks_common::codec::StacksMessageCodec;
 use stacks_common::types::chainstate::StacksAddress;
+use stacks_common::util::hash::to_hex;
 
 use super::types::serialization::SerializationError;
 use crate::vm::analysis::ContractAnalysis;
@@ -29,6 +30,8 @@ use crate::vm::types::{
 #[derive(Debug, Clone, PartialEq)]
 pub enum StacksTransactionEvent {
     SmartContractEvent(SmartContractEventData),
+    /// An event emitted by `print-event`
+    StructuredEvent(StructuredEventData),
     STXEvent(STXEventType),
     NFTEvent(NFTEventType),
     FTEvent(FTEventType),
@@ -49,6 +52,13 @@ impl StacksTransactionEvent {
                 "type": "contract_event",
                 "contract_event": event_data.json_serialize()?
             }),
+            StacksTransactionEvent::StructuredEvent(event_data) => json!({
+                "txid": format!("0x{:?}", txid),
+                "event_index": event_index,
+                "committed": committed,
+                "type": "structured_event",
+                "structured_event": event_data.json_serialize()?
+            }),
             StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(event_data)) => json!({
                 "txid": format!("0x{:?}", txid),
                 "event_index": event_index,
@@ -341,6 +351,28 @@ impl FTBurnEventData {
     }
 }
 
+/// An event emitted by `(print-event topic payload)`.  Unlike `print` events, whose topic is
+/// always "print", these carry a topic chosen by the contract, and a tuple payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructuredEventData {
+    pub contract_identifier: QualifiedContractIdentifier,
+    pub topic: String,
+    pub payload: Value,
+}
+
+impl StructuredEventData {
+    pub fn json_serialize(&self) -> Result<serde_json::Value, SerializationError> {
+        let mut bytes = vec![];
+        self.payload.serialize_write(&mut bytes)?;
+        Ok(json!({
+            "contract_identifier": self.contract_identifier.to_string(),
+            "topic": self.topic,
+            "payload": self.payload,
+            "raw_payload": format!("0x{}", to_hex(&bytes)),
+        }))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct SmartContractEventData {
     pub key: (QualifiedContractIdentifier, String),
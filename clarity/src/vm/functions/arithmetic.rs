@@ -383,6 +383,66 @@ pub fn native_bitwise_not(a: Value) -> InterpreterResult<Value> {
     type_force_unary_arithmetic!(bitwise_not, a)
 }
 
+fn expect_buffer(value: Value) -> InterpreterResult<Vec<u8>> {
+    match value {
+        Value::Sequence(SequenceData::Buffer(BuffData { data })) => Ok(data),
+        _ => Err(CheckErrors::TypeValueError(TypeSignature::max_buffer()?, value).into()),
+    }
+}
+
+// The buffer bitwise operations combine their operands byte by byte, so the
+// operands must be exactly the same length at runtime. The type checker only
+// guarantees that their declared maximum lengths agree.
+fn buff_bitwise_binary(
+    name: &str,
+    a: Value,
+    b: Value,
+    op: fn(u8, u8) -> u8,
+) -> InterpreterResult<Value> {
+    let a = expect_buffer(a)?;
+    let b = expect_buffer(b)?;
+    if a.len() != b.len() {
+        return Err(RuntimeErrorType::Arithmetic(format!(
+            "{} operands must have equal length (got {} and {})",
+            name,
+            a.len(),
+            b.len()
+        ))
+        .into());
+    }
+    let result = a.into_iter().zip(b).map(|(x, y)| op(x, y)).collect();
+    Value::buff_from(result)
+}
+
+pub fn native_buff_and(a: Value, b: Value) -> InterpreterResult<Value> {
+    buff_bitwise_binary("buff-and", a, b, |x, y| x & y)
+}
+
+pub fn native_buff_or(a: Value, b: Value) -> InterpreterResult<Value> {
+    buff_bitwise_binary("buff-or", a, b, |x, y| x | y)
+}
+
+pub fn native_buff_xor(a: Value, b: Value) -> InterpreterResult<Value> {
+    buff_bitwise_binary("buff-xor", a, b, |x, y| x ^ y)
+}
+
+pub fn native_buff_not(a: Value) -> InterpreterResult<Value> {
+    let a = expect_buffer(a)?;
+    Value::buff_from(a.into_iter().map(|x| !x).collect())
+}
+
+/// Cost input for the buffer bitwise operations: the length of the longest operand
+pub fn cost_input_buff_len(args: &[Value]) -> InterpreterResult<u64> {
+    Ok(args
+        .iter()
+        .map(|value| match value {
+            Value::Sequence(SequenceData::Buffer(BuffData { data })) => data.len() as u64,
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0))
+}
+
 // This function is 'special', because it must access the context to determine
 // the clarity version.
 fn special_geq_v1(
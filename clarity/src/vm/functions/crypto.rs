@@ -182,6 +182,59 @@ pub fn special_secp256k1_recover(
     };
 }
 
+/// `secp256k1-recover?` followed by `principal-of?`: returns the standard principal of the
+/// key that signed `message-hash`, with the same error codes as `secp256k1-recover?`.
+pub fn special_secp256k1_recover_principal(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    // (secp256k1-recover-principal? (..))
+    // arg0 => (buff 32), arg1 => (buff 65)
+    check_argument_count(2, args)?;
+
+    // charged as both of the functions it stands in for
+    runtime_cost(ClarityCostFunction::Secp256k1recover, env, 0)?;
+    runtime_cost(ClarityCostFunction::PrincipalOf, env, 0)?;
+
+    let param0 = eval(&args[0], env, context)?;
+    let message = match param0 {
+        Value::Sequence(SequenceData::Buffer(BuffData { ref data })) => {
+            if data.len() != 32 {
+                return Err(CheckErrors::TypeValueError(BUFF_32.clone(), param0).into());
+            }
+            data
+        }
+        _ => return Err(CheckErrors::TypeValueError(BUFF_32.clone(), param0).into()),
+    };
+
+    let param1 = eval(&args[1], env, context)?;
+    let signature = match param1 {
+        Value::Sequence(SequenceData::Buffer(BuffData { ref data })) => {
+            if data.len() > 65 {
+                return Err(CheckErrors::TypeValueError(BUFF_65.clone(), param1).into());
+            }
+            if data.len() < 65 || data[64] > 3 {
+                return Ok(Value::err_uint(2));
+            }
+            data
+        }
+        _ => return Err(CheckErrors::TypeValueError(BUFF_65.clone(), param1).into()),
+    };
+
+    let pub_key = match secp256k1_recover(&message, &signature)
+        .ok()
+        .and_then(|pub_key| Secp256k1PublicKey::from_slice(&pub_key).ok())
+    {
+        Some(pub_key) => pub_key,
+        None => return Ok(Value::err_uint(1)),
+    };
+    let principal =
+        pubkey_to_address_v2(pub_key, env.global_context.mainnet)?.to_account_principal();
+    Ok(Value::okay(Value::Principal(principal))
+        .map_err(|_| InterpreterError::Expect("Failed to construct ok".into()))?)
+}
+
 pub fn special_secp256k1_verify(
     args: &[SymbolicExpression],
     env: &mut Environment,
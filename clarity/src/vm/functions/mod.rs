@@ -31,9 +31,10 @@ use crate::vm::errors::{
 pub use crate::vm::functions::assets::stx_transfer_consolidated;
 use crate::vm::representations::SymbolicExpressionType::{Atom, List};
 use crate::vm::representations::{ClarityName, SymbolicExpression, SymbolicExpressionType};
+use crate::vm::types::signatures::ASCII_128;
 use crate::vm::types::{
-    BuffData, CharType, PrincipalData, ResponseData, SequenceData, TypeSignature, Value, BUFF_32,
-    BUFF_33, BUFF_65,
+    ASCIIData, BuffData, CharType, PrincipalData, ResponseData, SequenceData, TypeSignature, Value,
+    BUFF_32, BUFF_33, BUFF_65,
 };
 use crate::vm::Value::CallableContract;
 use crate::vm::{eval, is_reserved, Environment, LocalContext};
@@ -193,6 +194,17 @@ define_versioned_named_enum!(NativeFunctions(ClarityVersion) {
     ToConsensusBuff("to-consensus-buff?", ClarityVersion::Clarity2),
     FromConsensusBuff("from-consensus-buff?", ClarityVersion::Clarity2),
     ReplaceAt("replace-at?", ClarityVersion::Clarity2),
+    BuffAnd("buff-and", ClarityVersion::Clarity3),
+    BuffOr("buff-or", ClarityVersion::Clarity3),
+    BuffXor("buff-xor", ClarityVersion::Clarity3),
+    BuffNot("buff-not", ClarityVersion::Clarity3),
+    ConcatMany("concat-many", ClarityVersion::Clarity3),
+    Secp256k1RecoverPrincipal("secp256k1-recover-principal?", ClarityVersion::Clarity3),
+    PrintEvent("print-event", ClarityVersion::Clarity3),
+    Replicate("replicate", ClarityVersion::Clarity3),
+    Repeat("repeat", ClarityVersion::Clarity3),
+    Find("find?", ClarityVersion::Clarity3),
+    Position("position?", ClarityVersion::Clarity3),
 });
 
 ///
@@ -282,6 +294,8 @@ pub fn lookup_reserved_functions(name: &str, version: &ClarityVersion) -> Option
             SetVar => SpecialFunction("special_set-var", &database::special_set_variable),
             Map => SpecialFunction("special_map", &sequences::special_map),
             Filter => SpecialFunction("special_filter", &sequences::special_filter),
+            Find => SpecialFunction("special_find", &sequences::special_find),
+            Position => SpecialFunction("special_position", &sequences::special_position),
             BuffToIntLe => NativeFunction(
                 "native_buff_to_int_le",
                 NativeHandle::SingleArg(&conversions::native_buff_to_int_le),
@@ -333,6 +347,9 @@ pub fn lookup_reserved_functions(name: &str, version: &ClarityVersion) -> Option
             ),
             Fold => SpecialFunction("special_fold", &sequences::special_fold),
             Concat => SpecialFunction("special_concat", &sequences::special_concat),
+            ConcatMany => SpecialFunction("special_concat_many", &sequences::special_concat_many),
+            Replicate => SpecialFunction("special_replicate", &sequences::special_replicate),
+            Repeat => SpecialFunction("special_repeat", &sequences::special_repeat),
             AsMaxLen => SpecialFunction("special_as_max_len", &sequences::special_as_max_len),
             Append => SpecialFunction("special_append", &sequences::special_append),
             Len => NativeFunction(
@@ -407,7 +424,12 @@ pub fn lookup_reserved_functions(name: &str, version: &ClarityVersion) -> Option
             Secp256k1Verify => {
                 SpecialFunction("native_secp256k1-verify", &crypto::special_secp256k1_verify)
             }
+            Secp256k1RecoverPrincipal => SpecialFunction(
+                "native_secp256k1-recover-principal",
+                &crypto::special_secp256k1_recover_principal,
+            ),
             Print => SpecialFunction("special_print", &special_print),
+            PrintEvent => SpecialFunction("special_print-event", &special_print_event),
             ContractCall => {
                 SpecialFunction("special_contract-call", &database::special_contract_call)
             }
@@ -553,6 +575,32 @@ pub fn lookup_reserved_functions(name: &str, version: &ClarityVersion) -> Option
                 NativeHandle::MoreArg(&arithmetic::native_bitwise_xor),
                 ClarityCostFunction::Xor,
             ),
+            BuffAnd => NativeFunction205(
+                "native_buff_and",
+                NativeHandle::DoubleArg(&arithmetic::native_buff_and),
+                ClarityCostFunction::BitwiseAnd,
+                &arithmetic::cost_input_buff_len,
+            ),
+            BuffOr => NativeFunction205(
+                "native_buff_or",
+                NativeHandle::DoubleArg(&arithmetic::native_buff_or),
+                ClarityCostFunction::BitwiseOr,
+                &arithmetic::cost_input_buff_len,
+            ),
+            BuffXor => NativeFunction205(
+                "native_buff_xor",
+                NativeHandle::DoubleArg(&arithmetic::native_buff_xor),
+                ClarityCostFunction::Xor,
+                &arithmetic::cost_input_buff_len,
+            ),
+            // `cost_bitwise_not` is constant, which would under-charge for long buffers,
+            // so this uses the linear `cost_xor` (xor against all ones) instead.
+            BuffNot => NativeFunction205(
+                "native_buff_not",
+                NativeHandle::SingleArg(&arithmetic::native_buff_not),
+                ClarityCostFunction::Xor,
+                &arithmetic::cost_input_buff_len,
+            ),
         };
         Some(callable)
     } else {
@@ -612,6 +660,46 @@ fn special_print(
     Ok(input)
 }
 
+fn special_print_event(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    // (print-event topic payload)
+    // arg0 => (string-ascii 128), arg1 => tuple
+    check_argument_count(2, args)?;
+
+    let topic = eval(&args[0], env, context)?;
+    let payload = eval(&args[1], env, context)?;
+
+    runtime_cost(
+        ClarityCostFunction::Print,
+        env,
+        topic.size()?.saturating_add(payload.size()?),
+    )?;
+
+    let topic = match topic {
+        Value::Sequence(SequenceData::String(CharType::ASCII(ASCIIData { data })))
+            if data.len() <= 128 =>
+        {
+            String::from_utf8(data).map_err(|_| {
+                InterpreterError::Expect("Clarity ASCII string is not valid UTF-8".into())
+            })?
+        }
+        _ => return Err(CheckErrors::TypeValueError(ASCII_128, topic).into()),
+    };
+    if !matches!(payload, Value::Tuple(_)) {
+        return Err(CheckErrors::ExpectedTuple(TypeSignature::type_of(&payload)?).into());
+    }
+
+    if cfg!(feature = "developer-mode") {
+        debug!("{}: {}", &topic, &payload);
+    }
+
+    env.register_structured_event(topic, payload.clone())?;
+    Ok(payload)
+}
+
 fn special_if(
     args: &[SymbolicExpression],
     env: &mut Environment,
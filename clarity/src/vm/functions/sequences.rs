@@ -21,13 +21,16 @@ use stacks_common::types::StacksEpochId;
 use crate::vm::costs::cost_functions::ClarityCostFunction;
 use crate::vm::costs::{cost_functions, runtime_cost, CostOverflowingMath};
 use crate::vm::errors::{
-    check_argument_count, check_arguments_at_least, CheckErrors, InterpreterResult as Result,
-    RuntimeErrorType,
+    check_argument_count, check_arguments_at_least, CheckErrors, InterpreterError,
+    InterpreterResult as Result, RuntimeErrorType,
 };
 use crate::vm::representations::{SymbolicExpression, SymbolicExpressionType};
-use crate::vm::types::signatures::ListTypeData;
+use crate::vm::types::signatures::{BufferLength, ListTypeData, StringUTF8Length};
 use crate::vm::types::TypeSignature::BoolType;
-use crate::vm::types::{CharType, ListData, SequenceData, TypeSignature, Value};
+use crate::vm::types::{
+    ASCIIData, BuffData, CharType, ListData, SequenceData, SequencedValue, TypeSignature, UTF8Data,
+    Value,
+};
 use crate::vm::{apply, eval, lookup_function, CallableType, Environment, LocalContext};
 
 pub fn list_cons(
@@ -79,6 +82,81 @@ pub fn special_filter(
     Ok(sequence)
 }
 
+/// The element at `index` of `sequence`, which must be in bounds
+fn sequence_element(sequence: &SequenceData, index: usize) -> Result<Value> {
+    match sequence {
+        SequenceData::Buffer(data) => BuffData::to_value(&data.items()[index]),
+        SequenceData::List(data) => ListData::to_value(&data.items()[index]),
+        SequenceData::String(CharType::ASCII(data)) => ASCIIData::to_value(&data.items()[index]),
+        SequenceData::String(CharType::UTF8(data)) => UTF8Data::to_value(&data.items()[index]),
+    }
+}
+
+/// Apply the predicate named by `args[0]` to the elements of the sequence `args[1]` in order,
+/// stopping at the first one for which it returns `true`.  Returns that element and its index.
+/// This is charged the `filter` cost, and each element is only copied out of the sequence as
+/// the predicate is applied to it; the predicate applications are charged separately.
+fn find_first_match(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Option<(u128, Value)>> {
+    check_argument_count(2, args)?;
+
+    runtime_cost(ClarityCostFunction::Filter, env, 0)?;
+
+    let function_name = args[0].match_atom().ok_or(CheckErrors::ExpectedName)?;
+
+    let sequence = eval(&args[1], env, context)?;
+    let function = lookup_function(function_name, env)?;
+
+    let sequence_data = match sequence {
+        Value::Sequence(sequence_data) => sequence_data,
+        _ => return Err(CheckErrors::ExpectedSequence(TypeSignature::type_of(&sequence)?).into()),
+    };
+    for index in 0..sequence_data.len() {
+        let element = sequence_element(&sequence_data, index)?;
+        let argument = [SymbolicExpression::atom_value(element)];
+        let predicate_eval = apply(&function, &argument, env, context)?;
+        match predicate_eval {
+            Value::Bool(true) => {
+                let [argument] = argument;
+                let element = argument.match_atom_value().cloned().ok_or_else(|| {
+                    InterpreterError::Expect("BUG: sequence element is not a value".into())
+                })?;
+                return Ok(Some((index as u128, element)));
+            }
+            Value::Bool(false) => {}
+            _ => return Err(CheckErrors::TypeValueError(BoolType, predicate_eval).into()),
+        }
+    }
+    Ok(None)
+}
+
+/// Find the first element of a sequence for which a predicate holds
+pub fn special_find(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    match find_first_match(args, env, context)? {
+        Some((_, element)) => Value::some(element),
+        None => Ok(Value::none()),
+    }
+}
+
+/// Find the index of the first element of a sequence for which a predicate holds
+pub fn special_position(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    match find_first_match(args, env, context)? {
+        Some((index, _)) => Value::some(Value::UInt(index)),
+        None => Ok(Value::none()),
+    }
+}
+
 pub fn special_fold(
     args: &[SymbolicExpression],
     env: &mut Environment,
@@ -273,6 +351,128 @@ pub fn special_concat_v205(
     Ok(wrapped_seq)
 }
 
+/// Join one or more strings of the same kind, charged as a single `concat` over the total
+/// length of the inputs.
+pub fn special_concat_many(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    check_arguments_at_least(1, args)?;
+
+    let mut seqs = Vec::with_capacity(args.len());
+    let mut total_len: u64 = 0;
+    for arg in args.iter() {
+        match eval(arg, env, context)? {
+            Value::Sequence(seq @ SequenceData::String(_)) => {
+                total_len = total_len.cost_overflow_add(seq.len() as u64)?;
+                seqs.push(seq);
+            }
+            _ => {
+                runtime_cost(ClarityCostFunction::Concat, env, 1)?;
+                return Err(RuntimeErrorType::BadTypeConstruction.into());
+            }
+        }
+    }
+
+    runtime_cost(ClarityCostFunction::Concat, env, total_len)?;
+
+    let mut seqs = seqs.into_iter();
+    let mut result = seqs
+        .next()
+        .ok_or(CheckErrors::RequiresAtLeastArguments(1, 0))?;
+    for seq in seqs {
+        result.concat(env.epoch(), seq)?;
+    }
+    Ok(Value::Sequence(result))
+}
+
+/// Get the count passed to `replicate` or `repeat`, which must be a uint literal
+fn eval_literal_count(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<u32> {
+    if let Some(Value::UInt(count)) = args[1].match_literal_value() {
+        Ok(u32::try_from(*count).map_err(|_e| CheckErrors::MaxLengthOverflow)?)
+    } else {
+        let actual_count = eval(&args[1], env, context)?;
+        Err(CheckErrors::TypeError(
+            TypeSignature::UIntType,
+            TypeSignature::type_of(&actual_count)?,
+        )
+        .into())
+    }
+}
+
+/// Make a list of `count` copies of an element, charged as a `list` of that many elements.
+pub fn special_replicate(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    check_argument_count(2, args)?;
+
+    let element = eval(&args[0], env, context)?;
+    let count = eval_literal_count(args, env, context)?;
+
+    let list_size = u64::from(element.size()?).cost_overflow_mul(u64::from(count))?;
+    runtime_cost(ClarityCostFunction::ListCons, env, list_size)?;
+
+    // fails if the list would be too large, before it is built
+    let list_type = ListTypeData::new_list(TypeSignature::type_of(&element)?, count)?;
+    Value::list_with_type(env.epoch(), vec![element; count as usize], list_type)
+}
+
+/// Repeat a sequence `count` times, charged as a single `concat` over the result's length.
+pub fn special_repeat(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    check_argument_count(2, args)?;
+
+    let sequence = eval(&args[0], env, context)?;
+    let count = eval_literal_count(args, env, context)?;
+
+    let sequence = match sequence {
+        Value::Sequence(sequence) => sequence,
+        _ => {
+            runtime_cost(ClarityCostFunction::Concat, env, 1)?;
+            return Err(CheckErrors::ExpectedSequence(TypeSignature::type_of(&sequence)?).into());
+        }
+    };
+    let total_len = (sequence.len() as u64).cost_overflow_mul(u64::from(count))?;
+    runtime_cost(ClarityCostFunction::Concat, env, total_len)?;
+
+    // the result's length must make a valid type, which bounds its size, before it is built
+    let total_len = u32::try_from(total_len).map_err(|_e| CheckErrors::ValueTooLarge)?;
+    let count = count as usize;
+    match sequence {
+        SequenceData::Buffer(buff) => {
+            BufferLength::try_from(total_len)?;
+            Value::buff_from(buff.data.repeat(count))
+        }
+        SequenceData::String(CharType::ASCII(ascii)) => {
+            BufferLength::try_from(total_len)?;
+            Value::string_ascii_from_bytes(ascii.data.repeat(count))
+        }
+        SequenceData::String(CharType::UTF8(utf8)) => {
+            StringUTF8Length::try_from(total_len)?;
+            let data = (0..count).flat_map(|_| utf8.data.iter().cloned()).collect();
+            Ok(Value::Sequence(SequenceData::String(CharType::UTF8(
+                UTF8Data { data },
+            ))))
+        }
+        SequenceData::List(list) => {
+            let item_type = list.type_signature.get_list_item_type().clone();
+            let list_type = ListTypeData::new_list(item_type, total_len)?;
+            let data = (0..count).flat_map(|_| list.data.iter().cloned()).collect();
+            Value::list_with_type(env.epoch(), data, list_type)
+        }
+    }
+}
+
 pub fn special_as_max_len(
     args: &[SymbolicExpression],
     env: &mut Environment,
@@ -0,0 +1,416 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Render a `ContractContext` back into Clarity source.
+//!
+//! The rendered contract declares the context's constants, data variables, maps, tokens,
+//! traits and functions, so a context built by a test (rather than by evaluating a contract)
+//! can be deployed through the usual parse/analyze/evaluate pipeline.  A context does not
+//! record the initial values of its data variables, so each one is initialized to the
+//! simplest value of its type.  Output is deterministic: definitions are sorted by name.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::vm::callables::{DefineType, DefinedFunction};
+use crate::vm::contexts::ContractContext;
+use crate::vm::representations::{ClarityName, SymbolicExpression, SymbolicExpressionType};
+use crate::vm::types::signatures::{CallableSubtype, FunctionSignature};
+use crate::vm::types::{
+    CharType, PrincipalData, QualifiedContractIdentifier, SequenceData, SequenceSubtype,
+    StringSubtype, TraitIdentifier, TypeSignature, Value,
+};
+
+/// Standard principal used to initialize principal-typed data variables
+const DEFAULT_PRINCIPAL: &str = "'ST000000000000000000002AMW42H";
+
+/// Names under which the rendered contract refers to each trait
+struct TraitAliases {
+    contract_identifier: QualifiedContractIdentifier,
+    aliases: BTreeMap<TraitIdentifier, ClarityName>,
+}
+
+impl TraitAliases {
+    /// Traits defined in `context` keep their names; every other trait mentioned in a
+    /// signature is imported with `use-trait` under a fresh name.
+    fn new(context: &ContractContext) -> TraitAliases {
+        let mut referenced = BTreeSet::new();
+        for function in context.functions.values() {
+            for arg_type in function.get_arg_types() {
+                collect_traits(arg_type, &mut referenced);
+            }
+        }
+        for signature in context.defined_traits.values().flat_map(|t| t.values()) {
+            for arg_type in signature.args.iter() {
+                collect_traits(arg_type, &mut referenced);
+            }
+            collect_traits(&signature.returns, &mut referenced);
+        }
+
+        let mut aliases = BTreeMap::new();
+        let mut next_alias = 0;
+        for trait_id in referenced.into_iter() {
+            if trait_id.contract_identifier == context.contract_identifier
+                && context.defined_traits.contains_key(&trait_id.name)
+            {
+                let name = trait_id.name.clone();
+                aliases.insert(trait_id, name);
+                continue;
+            }
+            let alias = loop {
+                let candidate = format!("imported-trait-{}", next_alias);
+                next_alias += 1;
+                if !context.is_name_used(&candidate) {
+                    break candidate;
+                }
+            };
+            aliases.insert(
+                trait_id,
+                ClarityName::try_from(alias).expect("BUG: generated an invalid trait alias"),
+            );
+        }
+        TraitAliases {
+            contract_identifier: context.contract_identifier.clone(),
+            aliases,
+        }
+    }
+
+    fn alias(&self, trait_id: &TraitIdentifier) -> &ClarityName {
+        self.aliases
+            .get(trait_id)
+            .unwrap_or_else(|| panic!("BUG: no alias for trait {}", trait_id))
+    }
+
+    /// `use-trait` definitions for all traits defined outside of this contract
+    fn imports(&self) -> Vec<String> {
+        self.aliases
+            .iter()
+            .filter(|(trait_id, alias)| {
+                trait_id.contract_identifier != self.contract_identifier || trait_id.name != **alias
+            })
+            .map(|(trait_id, alias)| format!("(use-trait {} {})", alias, self.trait_path(trait_id)))
+            .collect()
+    }
+
+    /// How this contract names a trait defined in another contract
+    fn trait_path(&self, trait_id: &TraitIdentifier) -> String {
+        if trait_id.contract_identifier.issuer == self.contract_identifier.issuer {
+            format!(".{}.{}", trait_id.contract_identifier.name, trait_id.name)
+        } else {
+            format!("'{}.{}", trait_id.contract_identifier, trait_id.name)
+        }
+    }
+
+    fn type_source(&self, type_sig: &TypeSignature) -> String {
+        match type_sig {
+            TypeSignature::CallableType(CallableSubtype::Trait(trait_id))
+            | TypeSignature::TraitReferenceType(trait_id) => format!("<{}>", self.alias(trait_id)),
+            TypeSignature::OptionalType(inner) => format!("(optional {})", self.type_source(inner)),
+            TypeSignature::ResponseType(inner) => format!(
+                "(response {} {})",
+                self.type_source(&inner.0),
+                self.type_source(&inner.1)
+            ),
+            TypeSignature::SequenceType(SequenceSubtype::ListType(list_type)) => format!(
+                "(list {} {})",
+                list_type.get_max_len(),
+                self.type_source(list_type.get_list_item_type())
+            ),
+            TypeSignature::TupleType(tuple_type) => {
+                let fields: Vec<_> = tuple_type
+                    .get_type_map()
+                    .iter()
+                    .map(|(name, field_type)| {
+                        format!("({} {})", name, self.type_source(field_type))
+                    })
+                    .collect();
+                format!("(tuple {})", fields.join(" "))
+            }
+            TypeSignature::CallableType(CallableSubtype::Principal(_))
+            | TypeSignature::ListUnionType(_) => "principal".into(),
+            TypeSignature::NoType => panic!("BUG: cannot declare an undetermined type"),
+            other => format!("{}", other),
+        }
+    }
+
+    fn signature_source(&self, name: &ClarityName, signature: &FunctionSignature) -> String {
+        let args: Vec<_> = signature
+            .args
+            .iter()
+            .map(|arg| self.type_source(arg))
+            .collect();
+        format!(
+            "({} ({}) {})",
+            name,
+            args.join(" "),
+            self.type_source(&signature.returns)
+        )
+    }
+
+    fn function_source(&self, name: &ClarityName, function: &DefinedFunction) -> String {
+        let define = match function.define_type {
+            DefineType::Public => "define-public",
+            DefineType::ReadOnly => "define-read-only",
+            DefineType::Private => "define-private",
+        };
+        let mut header = vec![name.to_string()];
+        header.extend(
+            function
+                .get_arguments()
+                .iter()
+                .zip(function.get_arg_types().iter())
+                .map(|(arg, arg_type)| format!("({} {})", arg, self.type_source(arg_type))),
+        );
+        format!(
+            "({} ({})\n  {})",
+            define,
+            header.join(" "),
+            expression_source(function.get_body())
+        )
+    }
+}
+
+fn collect_traits(type_sig: &TypeSignature, traits: &mut BTreeSet<TraitIdentifier>) {
+    match type_sig {
+        TypeSignature::CallableType(CallableSubtype::Trait(trait_id))
+        | TypeSignature::TraitReferenceType(trait_id) => {
+            traits.insert(trait_id.clone());
+        }
+        TypeSignature::OptionalType(inner) => collect_traits(inner, traits),
+        TypeSignature::ResponseType(inner) => {
+            collect_traits(&inner.0, traits);
+            collect_traits(&inner.1, traits);
+        }
+        TypeSignature::SequenceType(SequenceSubtype::ListType(list_type)) => {
+            collect_traits(list_type.get_list_item_type(), traits)
+        }
+        TypeSignature::TupleType(tuple_type) => {
+            for field_type in tuple_type.get_type_map().values() {
+                collect_traits(field_type, traits);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn sorted<'a, V>(
+    items: impl Iterator<Item = (&'a ClarityName, V)>,
+) -> impl Iterator<Item = (&'a ClarityName, V)> {
+    let mut items: Vec<_> = items.collect();
+    items.sort_by(|a, b| a.0.cmp(b.0));
+    items.into_iter()
+}
+
+/// Clarity source that defines everything in `context`
+pub fn contract_source(context: &ContractContext) -> String {
+    let aliases = TraitAliases::new(context);
+    let mut lines = aliases.imports();
+
+    for (name, methods) in sorted(context.defined_traits.iter()) {
+        let signatures: Vec<_> = methods
+            .iter()
+            .map(|(method, signature)| aliases.signature_source(method, signature))
+            .collect();
+        lines.push(format!(
+            "(define-trait {} (\n  {}))",
+            name,
+            signatures.join("\n  ")
+        ));
+    }
+
+    let mut implemented: Vec<_> = context.implemented_traits.iter().collect();
+    implemented.sort();
+    for trait_id in implemented.into_iter() {
+        lines.push(format!("(impl-trait {})", aliases.trait_path(trait_id)));
+    }
+
+    for (name, value) in sorted(context.variables.iter()) {
+        lines.push(format!(
+            "(define-constant {} {})",
+            name,
+            value_source(value)
+        ));
+    }
+    for (name, metadata) in sorted(context.meta_data_var.iter()) {
+        lines.push(format!(
+            "(define-data-var {} {} {})",
+            name,
+            aliases.type_source(&metadata.value_type),
+            default_value_source(&metadata.value_type)
+        ));
+    }
+    for (name, metadata) in sorted(context.meta_data_map.iter()) {
+        lines.push(format!(
+            "(define-map {} {} {})",
+            name,
+            aliases.type_source(&metadata.key_type),
+            aliases.type_source(&metadata.value_type)
+        ));
+    }
+    for (name, metadata) in sorted(context.meta_ft.iter()) {
+        match metadata.total_supply {
+            Some(total_supply) => lines.push(format!(
+                "(define-fungible-token {} u{})",
+                name, total_supply
+            )),
+            None => lines.push(format!("(define-fungible-token {})", name)),
+        }
+    }
+    for (name, metadata) in sorted(context.meta_nft.iter()) {
+        lines.push(format!(
+            "(define-non-fungible-token {} {})",
+            name,
+            aliases.type_source(&metadata.key_type)
+        ));
+    }
+
+    for (name, function) in sorted(context.functions.iter()) {
+        lines.push(aliases.function_source(name, function));
+    }
+
+    lines.join("\n")
+}
+
+/// A Clarity expression that evaluates to `value`
+pub fn value_source(value: &Value) -> String {
+    match value {
+        Value::Int(i) => format!("{}", i),
+        Value::UInt(u) => format!("u{}", u),
+        Value::Bool(b) => format!("{}", b),
+        Value::Sequence(SequenceData::Buffer(buff)) => format!("0x{}", buff),
+        Value::Sequence(SequenceData::List(list)) => {
+            let items: Vec<_> = list.data.iter().map(value_source).collect();
+            if items.is_empty() {
+                "(list)".into()
+            } else {
+                format!("(list {})", items.join(" "))
+            }
+        }
+        Value::Sequence(SequenceData::String(CharType::ASCII(ascii))) => {
+            let mut source = String::from("\"");
+            for byte in ascii.data.iter() {
+                match *byte {
+                    b'"' => source.push_str("\\\""),
+                    b'\\' => source.push_str("\\\\"),
+                    b'\n' => source.push_str("\\n"),
+                    b'\t' => source.push_str("\\t"),
+                    b'\r' => source.push_str("\\r"),
+                    byte => source.push(byte as char),
+                }
+            }
+            source.push('"');
+            source
+        }
+        Value::Sequence(SequenceData::String(CharType::UTF8(utf8))) => {
+            let mut source = String::from("u\"");
+            for encoded in utf8.data.iter() {
+                let c = std::str::from_utf8(encoded)
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                    .expect("BUG: string-utf8 value holds an invalid character");
+                match c {
+                    '"' => source.push_str("\\\""),
+                    '\\' => source.push_str("\\\\"),
+                    ' '..='~' => source.push(c),
+                    c => source.push_str(&format!("\\u{{{:x}}}", c as u32)),
+                }
+            }
+            source.push('"');
+            source
+        }
+        Value::Principal(principal) => format!("'{}", principal),
+        Value::CallableContract(callable) => format!(
+            "'{}",
+            PrincipalData::Contract(callable.contract_identifier.clone())
+        ),
+        Value::Optional(optional) => match optional.data {
+            Some(ref inner) => format!("(some {})", value_source(inner)),
+            None => "none".into(),
+        },
+        Value::Response(response) => format!(
+            "({} {})",
+            if response.committed { "ok" } else { "err" },
+            value_source(&response.data)
+        ),
+        Value::Tuple(tuple) => {
+            let fields: Vec<_> = tuple
+                .data_map
+                .iter()
+                .map(|(name, field)| format!("({} {})", name, value_source(field)))
+                .collect();
+            format!("(tuple {})", fields.join(" "))
+        }
+    }
+}
+
+/// The simplest Clarity expression whose value is admitted by `type_sig`
+pub fn default_value_source(type_sig: &TypeSignature) -> String {
+    match type_sig {
+        TypeSignature::IntType => "0".into(),
+        TypeSignature::UIntType => "u0".into(),
+        TypeSignature::BoolType => "false".into(),
+        TypeSignature::PrincipalType | TypeSignature::ListUnionType(_) => DEFAULT_PRINCIPAL.into(),
+        TypeSignature::CallableType(CallableSubtype::Principal(contract_id)) => {
+            format!("'{}", contract_id)
+        }
+        TypeSignature::SequenceType(SequenceSubtype::BufferType(len)) => {
+            if u32::from(len) == 0 {
+                "0x".into()
+            } else {
+                "0x00".into()
+            }
+        }
+        TypeSignature::SequenceType(SequenceSubtype::StringType(StringSubtype::ASCII(_))) => {
+            "\"\"".into()
+        }
+        TypeSignature::SequenceType(SequenceSubtype::StringType(StringSubtype::UTF8(_))) => {
+            "u\"\"".into()
+        }
+        TypeSignature::SequenceType(SequenceSubtype::ListType(_)) => "(list)".into(),
+        TypeSignature::OptionalType(_) => "none".into(),
+        TypeSignature::ResponseType(inner) => format!("(ok {})", default_value_source(&inner.0)),
+        TypeSignature::TupleType(tuple_type) => {
+            let fields: Vec<_> = tuple_type
+                .get_type_map()
+                .iter()
+                .map(|(name, field_type)| {
+                    format!("({} {})", name, default_value_source(field_type))
+                })
+                .collect();
+            format!("(tuple {})", fields.join(" "))
+        }
+        TypeSignature::CallableType(CallableSubtype::Trait(_))
+        | TypeSignature::TraitReferenceType(_)
+        | TypeSignature::NoType => panic!("BUG: no literal value of type {}", type_sig),
+    }
+}
+
+/// Clarity source for an expression, as stored in a defined function's body
+pub fn expression_source(expr: &SymbolicExpression) -> String {
+    match &expr.expr {
+        SymbolicExpressionType::List(items) => {
+            let items: Vec<_> = items.iter().map(expression_source).collect();
+            format!("({})", items.join(" "))
+        }
+        SymbolicExpressionType::Atom(name) => name.to_string(),
+        SymbolicExpressionType::AtomValue(value) | SymbolicExpressionType::LiteralValue(value) => {
+            value_source(value)
+        }
+        SymbolicExpressionType::TraitReference(name, _) => format!("<{}>", name),
+        SymbolicExpressionType::Field(trait_id) => {
+            format!("'{}.{}", trait_id.contract_identifier, trait_id.name)
+        }
+    }
+}
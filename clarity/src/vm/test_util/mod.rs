@@ -16,6 +16,8 @@ use crate::vm::representations::SymbolicExpression;
 use crate::vm::types::{PrincipalData, ResponseData, StandardPrincipalData, TupleData, Value};
 use crate::vm::{execute as vm_execute, execute_on_network as vm_execute_on_network, StacksEpoch};
 
+pub mod contract_source;
+
 pub struct UnitTestBurnStateDB {
     pub epoch_id: StacksEpochId,
     pub ast_rules: ASTRules,
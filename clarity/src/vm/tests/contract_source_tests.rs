@@ -0,0 +1,525 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Round-trip tests for `test_util::contract_source`.
+//!
+//! Each case generates a `ContractContext` directly (constants, storage, traits, trait
+//! implementations and functions), renders it to Clarity source, runs the source through the
+//! parser, the static analysis and the evaluator, and checks that the evaluated context
+//! matches the generated one.  Types and values are drawn from the `proptest_utils`
+//! strategies by a deterministic runner, so failures are reproducible.
+
+use std::collections::BTreeMap;
+
+use hashbrown::HashMap;
+use proptest::strategy::{Strategy, ValueTree};
+use proptest::test_runner::TestRunner;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use stacks_common::consts::CHAIN_ID_TESTNET;
+use stacks_common::types::StacksEpochId;
+
+use crate::vm::analysis::type_check;
+use crate::vm::ast::parse;
+use crate::vm::callables::{DefineType, DefinedFunction};
+use crate::vm::contexts::{ContractContext, GlobalContext};
+use crate::vm::costs::LimitedCostTracker;
+use crate::vm::database::{
+    DataMapMetadata, DataVariableMetadata, FungibleTokenMetadata, MemoryBackingStore,
+    NonFungibleTokenMetadata,
+};
+use crate::vm::representations::{ClarityName, SymbolicExpression};
+use crate::vm::test_util::contract_source::{contract_source, value_source};
+use crate::vm::tests::proptest_utils::{prop_signature, prop_value};
+use crate::vm::types::signatures::{CallableSubtype, FunctionSignature};
+use crate::vm::types::{
+    OptionalData, PrincipalData, QualifiedContractIdentifier, SequenceData, StandardPrincipalData,
+    TraitIdentifier, TupleData, TypeSignature, Value,
+};
+use crate::vm::{eval_all, ClarityVersion};
+
+/// Number of generated contracts
+const NUM_CASES: u64 = 64;
+const MAX_DEFINITIONS: usize = 4;
+const MAX_ARGS: usize = 3;
+/// Maximum nesting of compound types
+const MAX_TYPE_NESTING: u32 = 2;
+
+const LIBRARY_CONTRACT_NAME: &str = "trait-library";
+const GENERATED_CONTRACT_NAME: &str = "generated";
+
+fn name(name: &str) -> ClarityName {
+    ClarityName::try_from(name.to_string()).unwrap()
+}
+
+fn sample<S: Strategy>(runner: &mut TestRunner, strategy: S) -> S::Value {
+    strategy.new_tree(runner).unwrap().current()
+}
+
+fn arbitrary_type(runner: &mut TestRunner, max_nesting: u32) -> TypeSignature {
+    sample(runner, prop_signature(max_nesting))
+}
+
+/// Whether `value` contains no `none` or empty list, so that its own type is fully
+/// determined
+fn determines_type(value: &Value) -> bool {
+    match value {
+        Value::Optional(OptionalData { data: None }) => false,
+        Value::Optional(OptionalData { data: Some(inner) }) => determines_type(inner),
+        Value::Response(response) => determines_type(&response.data),
+        Value::Sequence(SequenceData::List(list)) => {
+            !list.data.is_empty() && list.data.iter().all(determines_type)
+        }
+        Value::Tuple(tuple) => tuple.data_map.values().all(determines_type),
+        _ => true,
+    }
+}
+
+/// A value admitted by `type_sig`.
+/// If `allow_empty` is false, the value is never `none` or an empty list, so that its own
+/// type is fully determined.
+fn arbitrary_value(runner: &mut TestRunner, type_sig: &TypeSignature, allow_empty: bool) -> Value {
+    let values = prop_value(type_sig.clone());
+    if allow_empty {
+        sample(runner, values)
+    } else {
+        sample(
+            runner,
+            values.prop_filter("value must determine its type", determines_type),
+        )
+    }
+}
+
+fn arbitrary_response_type(runner: &mut TestRunner) -> TypeSignature {
+    TypeSignature::new_response(
+        arbitrary_type(runner, MAX_TYPE_NESTING - 1),
+        arbitrary_type(runner, MAX_TYPE_NESTING - 1),
+    )
+    .unwrap()
+}
+
+/// `(<function> <args>...)`
+fn call(function: &str, args: Vec<SymbolicExpression>) -> SymbolicExpression {
+    let mut items = vec![SymbolicExpression::atom(name(function))];
+    items.extend(args);
+    SymbolicExpression::list(items)
+}
+
+/// A literal expression that evaluates to `value`
+fn literal(value: Value) -> SymbolicExpression {
+    match value {
+        Value::Principal(_) => SymbolicExpression::literal_value(value),
+        value => SymbolicExpression::atom_value(value),
+    }
+}
+
+/// A contract that only defines traits, for the generated contract to use and implement
+fn arbitrary_library(runner: &mut TestRunner) -> ContractContext {
+    let contract_id = QualifiedContractIdentifier::local(LIBRARY_CONTRACT_NAME).unwrap();
+    let mut context = ContractContext::new(contract_id, ClarityVersion::Clarity2);
+    for i in 0..runner.rng().gen_range(1..=MAX_DEFINITIONS) {
+        let methods = (0..runner.rng().gen_range(1..=MAX_DEFINITIONS))
+            .map(|j| {
+                let signature = FunctionSignature {
+                    args: (0..runner.rng().gen_range(0..=MAX_ARGS))
+                        .map(|_| arbitrary_type(runner, MAX_TYPE_NESTING))
+                        .collect(),
+                    returns: arbitrary_response_type(runner),
+                };
+                // method names are unique across traits, so that one function can't be
+                // asked to implement two different signatures
+                (
+                    name(&format!("library-trait-{}-method-{}", i, j)),
+                    signature,
+                )
+            })
+            .collect();
+        context
+            .defined_traits
+            .insert(name(&format!("library-trait-{}", i)), methods);
+    }
+    context
+}
+
+/// Generates the definitions of a contract, keeping track of what its function bodies can
+/// refer to
+struct ContextBuilder {
+    context: ContractContext,
+    library_traits: Vec<TraitIdentifier>,
+}
+
+impl ContextBuilder {
+    fn new(library: &ContractContext) -> ContextBuilder {
+        let contract_id = QualifiedContractIdentifier::local(GENERATED_CONTRACT_NAME).unwrap();
+        let mut library_traits: Vec<_> = library
+            .defined_traits
+            .keys()
+            .map(|trait_name| TraitIdentifier {
+                name: trait_name.clone(),
+                contract_identifier: library.contract_identifier.clone(),
+            })
+            .collect();
+        library_traits.sort();
+        ContextBuilder {
+            context: ContractContext::new(contract_id, ClarityVersion::Clarity2),
+            library_traits,
+        }
+    }
+
+    fn arbitrary_arg_type(&self, runner: &mut TestRunner) -> TypeSignature {
+        if runner.rng().gen_bool(0.2) {
+            let trait_id = self.library_traits.choose(runner.rng()).unwrap().clone();
+            TypeSignature::CallableType(CallableSubtype::Trait(trait_id))
+        } else {
+            arbitrary_type(runner, MAX_TYPE_NESTING)
+        }
+    }
+
+    fn define_storage(&mut self, runner: &mut TestRunner) {
+        for i in 0..runner.rng().gen_range(0..=MAX_DEFINITIONS) {
+            let value_type = arbitrary_type(runner, MAX_TYPE_NESTING);
+            let value = arbitrary_value(runner, &value_type, false);
+            self.context
+                .variables
+                .insert(name(&format!("const-{}", i)), value);
+        }
+        for i in 0..runner.rng().gen_range(0..=MAX_DEFINITIONS) {
+            let var_name = name(&format!("var-{}", i));
+            let value_type = arbitrary_type(runner, MAX_TYPE_NESTING);
+            self.context.persisted_names.insert(var_name.clone());
+            self.context
+                .meta_data_var
+                .insert(var_name, DataVariableMetadata { value_type });
+        }
+        for i in 0..runner.rng().gen_range(0..=MAX_DEFINITIONS) {
+            let map_name = name(&format!("map-{}", i));
+            let key_type = arbitrary_type(runner, MAX_TYPE_NESTING);
+            let value_type = arbitrary_type(runner, MAX_TYPE_NESTING);
+            self.context.persisted_names.insert(map_name.clone());
+            self.context.meta_data_map.insert(
+                map_name,
+                DataMapMetadata {
+                    key_type,
+                    value_type,
+                },
+            );
+        }
+        for i in 0..runner.rng().gen_range(0..=MAX_DEFINITIONS) {
+            let ft_name = name(&format!("ft-{}", i));
+            let total_supply = if runner.rng().gen_bool(0.5) {
+                Some(runner.rng().gen_range(1..=1_000_000))
+            } else {
+                None
+            };
+            self.context.persisted_names.insert(ft_name.clone());
+            self.context
+                .meta_ft
+                .insert(ft_name, FungibleTokenMetadata { total_supply });
+        }
+        for i in 0..runner.rng().gen_range(0..=MAX_DEFINITIONS) {
+            let nft_name = name(&format!("nft-{}", i));
+            let key_type = arbitrary_type(runner, MAX_TYPE_NESTING);
+            self.context.persisted_names.insert(nft_name.clone());
+            self.context
+                .meta_nft
+                .insert(nft_name, NonFungibleTokenMetadata { key_type });
+        }
+    }
+
+    fn define_traits(&mut self, runner: &mut TestRunner) {
+        for i in 0..runner.rng().gen_range(0..=MAX_DEFINITIONS) {
+            let methods = (0..runner.rng().gen_range(1..=MAX_DEFINITIONS))
+                .map(|j| {
+                    let signature = FunctionSignature {
+                        args: (0..runner.rng().gen_range(0..=MAX_ARGS))
+                            .map(|_| self.arbitrary_arg_type(runner))
+                            .collect(),
+                        returns: arbitrary_response_type(runner),
+                    };
+                    (name(&format!("method-{}", j)), signature)
+                })
+                .collect();
+            self.context
+                .defined_traits
+                .insert(name(&format!("trait-{}", i)), methods);
+        }
+    }
+
+    fn define_function(
+        &mut self,
+        function_name: ClarityName,
+        args: Vec<(ClarityName, TypeSignature)>,
+        body: SymbolicExpression,
+        define_type: DefineType,
+    ) {
+        let function = DefinedFunction::new(
+            args,
+            body,
+            define_type,
+            &function_name,
+            &self.context.contract_identifier.to_string(),
+        );
+        self.context.functions.insert(function_name, function);
+    }
+
+    /// Public functions with the signatures of a random subset of the library's traits
+    fn implement_traits(&mut self, runner: &mut TestRunner, library: &ContractContext) {
+        for trait_id in self.library_traits.clone().into_iter() {
+            if runner.rng().gen_bool(0.5) {
+                continue;
+            }
+            let methods = library.defined_traits.get(&trait_id.name).unwrap();
+            for (method_name, signature) in methods.iter() {
+                let args = signature
+                    .args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg_type)| (name(&format!("a{}", i)), arg_type.clone()))
+                    .collect();
+                let ok_type = match &signature.returns {
+                    TypeSignature::ResponseType(inner) => &inner.0,
+                    other => panic!("Trait method returns {}", other),
+                };
+                let body = call("ok", vec![literal(arbitrary_value(runner, ok_type, true))]);
+                self.define_function(method_name.clone(), args, body, DefineType::Public);
+            }
+            self.context.implemented_traits.insert(trait_id);
+        }
+    }
+
+    /// An expression that the analysis accepts in the body of a function with `args`
+    fn arbitrary_body(
+        &self,
+        runner: &mut TestRunner,
+        args: &[(ClarityName, TypeSignature)],
+    ) -> SymbolicExpression {
+        let mut choices: Vec<SymbolicExpression> = vec![];
+        let value_type = arbitrary_type(runner, MAX_TYPE_NESTING);
+        choices.push(literal(arbitrary_value(runner, &value_type, true)));
+        for (arg_name, arg_type) in args.iter() {
+            if !matches!(arg_type, TypeSignature::CallableType(_)) {
+                choices.push(SymbolicExpression::atom(arg_name.clone()));
+            }
+        }
+        for const_name in self.context.variables.keys() {
+            choices.push(SymbolicExpression::atom(const_name.clone()));
+        }
+        for var_name in self.context.meta_data_var.keys() {
+            choices.push(call(
+                "var-get",
+                vec![SymbolicExpression::atom(var_name.clone())],
+            ));
+        }
+        for (map_name, metadata) in self.context.meta_data_map.iter() {
+            let key = arbitrary_value(runner, &metadata.key_type, true);
+            choices.push(call(
+                "map-get?",
+                vec![SymbolicExpression::atom(map_name.clone()), literal(key)],
+            ));
+        }
+        for ft_name in self.context.meta_ft.keys() {
+            choices.push(call(
+                "ft-get-supply",
+                vec![SymbolicExpression::atom(ft_name.clone())],
+            ));
+        }
+        for (nft_name, metadata) in self.context.meta_nft.iter() {
+            let key = arbitrary_value(runner, &metadata.key_type, true);
+            choices.push(call(
+                "nft-get-owner?",
+                vec![SymbolicExpression::atom(nft_name.clone()), literal(key)],
+            ));
+        }
+        let index = runner.rng().gen_range(0..choices.len());
+        choices.swap_remove(index)
+    }
+
+    fn define_functions(&mut self, runner: &mut TestRunner) {
+        for i in 0..runner.rng().gen_range(0..=MAX_DEFINITIONS) {
+            let args: Vec<_> = (0..runner.rng().gen_range(0..=MAX_ARGS))
+                .map(|j| (name(&format!("a{}", j)), self.arbitrary_arg_type(runner)))
+                .collect();
+            let define_type = [
+                DefineType::Public,
+                DefineType::ReadOnly,
+                DefineType::Private,
+            ]
+            .choose(runner.rng())
+            .unwrap()
+            .clone();
+            let mut body = self.arbitrary_body(runner, &args);
+            if define_type == DefineType::Public {
+                body = call("ok", vec![body]);
+            }
+            self.define_function(name(&format!("fn-{}", i)), args, body, define_type);
+        }
+    }
+}
+
+fn arbitrary_case(runner: &mut TestRunner) -> (ContractContext, ContractContext) {
+    let library = arbitrary_library(runner);
+    let mut builder = ContextBuilder::new(&library);
+    builder.define_storage(runner);
+    builder.define_traits(runner);
+    builder.implement_traits(runner, &library);
+    builder.define_functions(runner);
+    (library, builder.context)
+}
+
+/// Parse, analyze and evaluate the rendered source of `library` and then `context`, and
+/// return the context that the evaluator builds for `context`
+fn deploy(library: &ContractContext, context: &ContractContext) -> ContractContext {
+    let version = ClarityVersion::Clarity2;
+    let epoch = StacksEpochId::Epoch21;
+    let mut analysis_marf = MemoryBackingStore::new();
+    let mut analysis_db = analysis_marf.as_analysis_db();
+
+    // The backing store only accepts analysis metadata for deployed contracts, so keep the
+    // library's analysis pending in an outer nesting level while the contract is checked.
+    analysis_db.begin();
+    for (contract_id, source) in [
+        (&library.contract_identifier, contract_source(library)),
+        (&context.contract_identifier, contract_source(context)),
+    ] {
+        let mut contract = parse(contract_id, &source, version, epoch)
+            .unwrap_or_else(|e| panic!("Failed to parse: {:?}\n{}", e, source));
+        analysis_db
+            .execute(|db| type_check(contract_id, &mut contract, db, true, &epoch, &version))
+            .unwrap_or_else(|e| panic!("Failed to check: {:?}\n{}", e, source));
+    }
+    analysis_db.roll_back().unwrap();
+
+    let source = contract_source(context);
+    let contract = parse(&context.contract_identifier, &source, version, epoch).unwrap();
+    let mut deployed = ContractContext::new(context.contract_identifier.clone(), version);
+    let mut marf = MemoryBackingStore::new();
+    let mut global_context = GlobalContext::new(
+        false,
+        CHAIN_ID_TESTNET,
+        marf.as_clarity_db(),
+        LimitedCostTracker::new_free(),
+        epoch,
+    );
+    global_context
+        .execute(|g| eval_all(&contract, &mut deployed, g, None))
+        .unwrap_or_else(|e| panic!("Failed to evaluate: {:?}\n{}", e, source));
+    deployed
+}
+
+fn sorted<V: Clone>(map: &HashMap<ClarityName, V>) -> BTreeMap<ClarityName, V> {
+    map.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+}
+
+#[test]
+fn prop_generated_contracts_round_trip() {
+    let mut runner = TestRunner::deterministic();
+    for case in 0..NUM_CASES {
+        let (library, context) = arbitrary_case(&mut runner);
+        let source = contract_source(&context);
+        let deployed = deploy(&library, &context);
+
+        assert_eq!(
+            contract_source(&deployed),
+            source,
+            "Case {}: evaluated contract renders differently",
+            case
+        );
+        assert_eq!(
+            sorted(&deployed.meta_data_var),
+            sorted(&context.meta_data_var)
+        );
+        assert_eq!(
+            sorted(&deployed.meta_data_map),
+            sorted(&context.meta_data_map)
+        );
+        assert_eq!(sorted(&deployed.meta_ft), sorted(&context.meta_ft));
+        assert_eq!(sorted(&deployed.meta_nft), sorted(&context.meta_nft));
+        assert_eq!(
+            sorted(&deployed.defined_traits),
+            sorted(&context.defined_traits)
+        );
+        assert_eq!(deployed.implemented_traits, context.implemented_traits);
+        assert_eq!(deployed.persisted_names, context.persisted_names);
+
+        assert_eq!(deployed.functions.len(), context.functions.len());
+        for (function_name, function) in context.functions.iter() {
+            let deployed_function = deployed
+                .functions
+                .get(function_name)
+                .unwrap_or_else(|| panic!("Case {}: {} was not defined", case, function_name));
+            assert_eq!(deployed_function.define_type, function.define_type);
+            assert_eq!(deployed_function.get_arguments(), function.get_arguments());
+            assert_eq!(deployed_function.get_arg_types(), function.get_arg_types());
+        }
+    }
+}
+
+#[test]
+fn test_value_source() {
+    let principal = StandardPrincipalData(26, [0x11; 20]);
+    let contract = QualifiedContractIdentifier::new(principal.clone(), "foo".into());
+    let tuple = TupleData::from_data(vec![
+        (name("a"), Value::Int(-1)),
+        (name("b"), Value::none()),
+    ])
+    .unwrap();
+    let cases = [
+        (Value::Int(-12), "-12".to_string()),
+        (Value::UInt(12), "u12".to_string()),
+        (
+            Value::buff_from(vec![0xde, 0xad]).unwrap(),
+            "0xdead".to_string(),
+        ),
+        (
+            Value::string_ascii_from_bytes(b"a \"b\"\\\n".to_vec()).unwrap(),
+            r#""a \"b\"\\\n""#.to_string(),
+        ),
+        (
+            Value::string_utf8_from_bytes("é\"€".as_bytes().to_vec()).unwrap(),
+            r#"u"\u{e9}\"\u{20ac}""#.to_string(),
+        ),
+        (
+            Value::Principal(PrincipalData::Standard(principal.clone())),
+            format!("'{}", principal),
+        ),
+        (
+            Value::Principal(PrincipalData::Contract(contract.clone())),
+            format!("'{}", contract),
+        ),
+        (
+            Value::okay(Value::some(Value::Bool(true)).unwrap()).unwrap(),
+            "(ok (some true))".to_string(),
+        ),
+        (
+            Value::cons_list_unsanitized(vec![]).unwrap(),
+            "(list)".to_string(),
+        ),
+        (Value::from(tuple), "(tuple (a -1) (b none))".to_string()),
+    ];
+    for (value, expected) in cases.into_iter() {
+        assert_eq!(value_source(&value), expected);
+    }
+
+    // every rendered value evaluates back to itself
+    let mut runner = TestRunner::deterministic();
+    for _ in 0..NUM_CASES {
+        let type_sig = arbitrary_type(&mut runner, MAX_TYPE_NESTING);
+        let value = arbitrary_value(&mut runner, &type_sig, true);
+        let source = value_source(&value);
+        let evaluated = crate::vm::execute_v2(&source).unwrap().unwrap();
+        assert_eq!(value_source(&evaluated), source);
+    }
+}
@@ -25,10 +25,14 @@ use crate::vm::errors::Error;
 use crate::vm::types::Value;
 
 mod assets;
+#[cfg(test)]
+mod contract_source_tests;
 mod contracts;
 mod datamaps;
 mod defines;
 mod principals;
+#[cfg(test)]
+pub mod proptest_utils;
 mod sequences;
 #[cfg(test)]
 mod simple_apply_eval;
@@ -0,0 +1,1392 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Proptest strategies for Clarity type signatures and values.
+//!
+//! Types are kept small (short sequences, few tuple fields) so that many of them can be
+//! generated per test, and values are only ever generated for a type, so that every value
+//! has a type signature that admits it.
+//!
+//! Typed values shrink through `PropValue`'s own shrinker rather than proptest's combinators,
+//! which shrink the type and the value separately and make little progress on nested values.
+//!
+//! For negative tests, the mutators at the end of this file take generated values and break
+//! them in a targeted way: `prop_mistyped_value()` pairs a value with a type that is one
+//! change away from admitting it, and `prop_malformed_serialization()` corrupts a single spot
+//! of a value's serialization.  Either way, every input they produce must be rejected.
+//!
+//! For trait conformance, `prop_trait_spec()` generates trait definitions, and
+//! `prop_compatible_impl()` and `prop_incompatible_impl()` generate contracts that claim to
+//! implement one: the former must pass analysis, and the latter carry a single
+//! `TraitMutation` that must make analysis reject them.
+
+use std::collections::BTreeSet;
+use std::iter;
+use std::mem::discriminant;
+
+use proptest::prelude::*;
+use proptest::strategy::{NewTree, ValueTree};
+use proptest::test_runner::TestRunner;
+
+use crate::vm::representations::{ClarityName, ContractName};
+use crate::vm::types::serialization::TypePrefix;
+use crate::vm::types::TypeSignature::{
+    BoolType, IntType, OptionalType, PrincipalType, ResponseType, SequenceType, TupleType, UIntType,
+};
+use crate::vm::types::{
+    ASCIIData, BuffData, BufferLength, CharType, ListTypeData, OptionalData, PrincipalData,
+    QualifiedContractIdentifier, ResponseData, SequenceData, SequenceSubtype,
+    StandardPrincipalData, StringSubtype, StringUTF8Length, TupleData, TupleTypeSignature,
+    TypeSignature, UTF8Data, Value,
+};
+
+/// Names that can also be written in contract source: a `u` followed by a digit would be
+/// read as a `uint` literal
+fn prop_name() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9-]{0,15}".prop_filter("name must not read as a uint literal", |name| {
+        !name.starts_with('u') || !name[1..].starts_with(|c: char| c.is_ascii_digit())
+    })
+}
+
+pub fn prop_clarity_name() -> impl Strategy<Value = ClarityName> {
+    prop_name().prop_map(|name| ClarityName::try_from(name).unwrap())
+}
+
+pub fn prop_contract_name() -> impl Strategy<Value = ContractName> {
+    prop_name().prop_map(|name| ContractName::try_from(name).unwrap())
+}
+
+pub fn prop_standard_principal() -> impl Strategy<Value = StandardPrincipalData> {
+    (0u8..32, any::<[u8; 20]>()).prop_map(|(version, bytes)| StandardPrincipalData(version, bytes))
+}
+
+pub fn prop_principal() -> impl Strategy<Value = PrincipalData> {
+    prop_oneof![
+        prop_standard_principal().prop_map(PrincipalData::Standard),
+        (prop_standard_principal(), prop_contract_name()).prop_map(|(issuer, name)| {
+            PrincipalData::Contract(QualifiedContractIdentifier { issuer, name })
+        }),
+    ]
+}
+
+/// Type signatures that contain no other types
+fn prop_leaf_signature() -> impl Strategy<Value = TypeSignature> {
+    prop_oneof![
+        Just(IntType),
+        Just(UIntType),
+        Just(BoolType),
+        Just(PrincipalType),
+        (1u32..=32).prop_map(|len| SequenceType(SequenceSubtype::BufferType(
+            BufferLength::try_from(len).unwrap()
+        ))),
+        (1u32..=32).prop_map(|len| SequenceType(SequenceSubtype::StringType(
+            StringSubtype::ASCII(BufferLength::try_from(len).unwrap())
+        ))),
+        (1u32..=8).prop_map(
+            |len| SequenceType(SequenceSubtype::StringType(StringSubtype::UTF8(
+                StringUTF8Length::try_from(len).unwrap()
+            )))
+        ),
+    ]
+}
+
+/// Type signatures with at most `max_nesting` levels of optionals, responses, lists and
+/// tuples, i.e. of depth at most `max_nesting + 1`
+pub fn prop_signature(max_nesting: u32) -> impl Strategy<Value = TypeSignature> {
+    prop_leaf_signature().prop_recursive(max_nesting, 32, 4, |inner| {
+        prop_oneof![
+            inner
+                .clone()
+                .prop_map(|ty| TypeSignature::new_option(ty).unwrap()),
+            (inner.clone(), inner.clone())
+                .prop_map(|(ok, err)| TypeSignature::new_response(ok, err).unwrap()),
+            (inner.clone(), 1u32..=4).prop_map(|(ty, max_len)| SequenceType(
+                SequenceSubtype::ListType(ListTypeData::new_list(ty, max_len).unwrap())
+            )),
+            prop::collection::btree_map(prop_clarity_name(), inner, 1..=4)
+                .prop_map(|fields| TupleType(TupleTypeSignature::try_from(fields).unwrap())),
+        ]
+    })
+}
+
+/// Values admitted by `ty`
+pub fn prop_value(ty: TypeSignature) -> BoxedStrategy<Value> {
+    match ty {
+        IntType => any::<i128>().prop_map(Value::Int).boxed(),
+        UIntType => any::<u128>().prop_map(Value::UInt).boxed(),
+        BoolType => any::<bool>().prop_map(Value::Bool).boxed(),
+        PrincipalType => prop_principal().prop_map(Value::Principal).boxed(),
+        SequenceType(SequenceSubtype::BufferType(len)) => {
+            prop::collection::vec(any::<u8>(), 0..=u32::from(&len) as usize)
+                .prop_map(|data| Value::buff_from(data).unwrap())
+                .boxed()
+        }
+        SequenceType(SequenceSubtype::StringType(StringSubtype::ASCII(len))) => {
+            prop::collection::vec(0x20u8..0x7f, 0..=u32::from(&len) as usize)
+                .prop_map(|data| Value::string_ascii_from_bytes(data).unwrap())
+                .boxed()
+        }
+        SequenceType(SequenceSubtype::StringType(StringSubtype::UTF8(len))) => {
+            prop::collection::vec(any::<char>(), 0..=u32::from(&len) as usize)
+                .prop_map(|chars| {
+                    let string: String = chars.into_iter().collect();
+                    Value::string_utf8_from_bytes(string.into_bytes()).unwrap()
+                })
+                .boxed()
+        }
+        OptionalType(inner) => prop_oneof![
+            Just(Value::none()),
+            prop_value(*inner).prop_map(|value| Value::some(value).unwrap()),
+        ]
+        .boxed(),
+        ResponseType(types) => {
+            let (ok_type, err_type) = *types;
+            prop_oneof![
+                prop_value(ok_type).prop_map(|value| Value::okay(value).unwrap()),
+                prop_value(err_type).prop_map(|value| Value::error(value).unwrap()),
+            ]
+            .boxed()
+        }
+        SequenceType(SequenceSubtype::ListType(list_type)) => prop::collection::vec(
+            prop_value(list_type.get_list_item_type().clone()),
+            0..=list_type.get_max_len() as usize,
+        )
+        .prop_map(|items| Value::cons_list_unsanitized(items).unwrap())
+        .boxed(),
+        TupleType(tuple_type) => {
+            let fields: Vec<_> = tuple_type
+                .get_type_map()
+                .iter()
+                .map(|(name, ty)| (Just(name.clone()), prop_value(ty.clone())))
+                .collect();
+            fields
+                .prop_map(|fields| Value::Tuple(TupleData::from_data(fields).unwrap()))
+                .boxed()
+        }
+        ty => panic!("No values are generated for type {}", ty),
+    }
+}
+
+/// A type signature with at most `max_nesting` levels of nesting, and a value it admits
+pub fn prop_typed_value(max_nesting: u32) -> PropValue {
+    PropValue { max_nesting }
+}
+
+/// Strategy for a type signature and a value it admits, see `prop_typed_value()`.
+///
+/// Failing cases shrink greedily, trying the most aggressive simplification first: tuple
+/// fields are dropped (from the type and from every value of it), then optionals become
+/// `none`, sequences are emptied or shortened, and numbers jump to zero or halve their
+/// distance to it, before the simplifications of any nested values are tried.  Every
+/// shrunk value is still admitted by the type it is paired with.
+#[derive(Debug, Clone)]
+pub struct PropValue {
+    max_nesting: u32,
+}
+
+impl Strategy for PropValue {
+    type Tree = PropValueTree;
+    type Value = (TypeSignature, Value);
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let (ty, value) = prop_signature(self.max_nesting)
+            .prop_flat_map(|ty| (Just(ty.clone()), prop_value(ty)))
+            .new_tree(runner)?
+            .current();
+        Ok(PropValueTree::new(ty, value))
+    }
+}
+
+/// Simpler alternatives to a value, most aggressive first, computed as they are needed
+type Candidates<T> = Box<dyn Iterator<Item = T>>;
+
+/// Value tree of `PropValue`
+pub struct PropValueTree {
+    current: (TypeSignature, Value),
+    /// Alternatives to `current` not tried yet
+    candidates: Candidates<(TypeSignature, Value)>,
+    /// The last pair that failed the test, and the alternatives it had left, to go back to
+    /// if `current` passes
+    last_failing: Option<((TypeSignature, Value), Candidates<(TypeSignature, Value)>)>,
+}
+
+impl PropValueTree {
+    fn new(ty: TypeSignature, value: Value) -> Self {
+        let candidates = shrink_typed_value(&ty, &value);
+        Self {
+            current: (ty, value),
+            candidates,
+            last_failing: None,
+        }
+    }
+}
+
+impl ValueTree for PropValueTree {
+    type Value = (TypeSignature, Value);
+
+    fn current(&self) -> Self::Value {
+        self.current.clone()
+    }
+
+    fn simplify(&mut self) -> bool {
+        let candidate = match self.candidates.next() {
+            Some(candidate) => candidate,
+            None => return false,
+        };
+        let candidates = shrink_typed_value(&candidate.0, &candidate.1);
+        let failing = std::mem::replace(&mut self.current, candidate);
+        let remaining = std::mem::replace(&mut self.candidates, candidates);
+        self.last_failing = Some((failing, remaining));
+        true
+    }
+
+    fn complicate(&mut self) -> bool {
+        match self.last_failing.take() {
+            Some((failing, remaining)) => {
+                self.current = failing;
+                self.candidates = remaining;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Simpler alternatives to `value`: first those with fewer tuple fields in `ty`, then those
+/// of the same type
+fn shrink_typed_value(ty: &TypeSignature, value: &Value) -> Candidates<(TypeSignature, Value)> {
+    let same_type = {
+        let ty = ty.clone();
+        shrink_value(value).map(move |value| (ty.clone(), value))
+    };
+    Box::new(shrink_tuple_arity(ty, value).chain(same_type))
+}
+
+/// A step from a type into one of the types it contains
+#[derive(Debug, Clone)]
+enum TypePathStep {
+    Some,
+    Ok,
+    Err,
+    Item,
+    Field(ClarityName),
+}
+
+/// The paths to the tuple types within `ty`, outermost first, with their field names
+fn tuple_type_paths(
+    ty: &TypeSignature,
+    path: &mut Vec<TypePathStep>,
+    paths: &mut Vec<(Vec<TypePathStep>, Vec<ClarityName>)>,
+) {
+    let children: Vec<(TypePathStep, &TypeSignature)> = match ty {
+        OptionalType(inner) => vec![(TypePathStep::Some, inner.as_ref())],
+        ResponseType(types) => vec![(TypePathStep::Ok, &types.0), (TypePathStep::Err, &types.1)],
+        SequenceType(SequenceSubtype::ListType(list_type)) => {
+            vec![(TypePathStep::Item, list_type.get_list_item_type())]
+        }
+        TupleType(tuple_type) => {
+            let type_map = tuple_type.get_type_map();
+            paths.push((path.clone(), type_map.keys().cloned().collect()));
+            type_map
+                .iter()
+                .map(|(name, inner)| (TypePathStep::Field(name.clone()), inner))
+                .collect()
+        }
+        _ => vec![],
+    };
+    for (step, inner) in children.into_iter() {
+        path.push(step);
+        tuple_type_paths(inner, path, paths);
+        path.pop();
+    }
+}
+
+/// Alternatives to `ty` and `value` where one of the tuple types in `ty` keeps a single one of
+/// its fields, or drops one of them
+fn shrink_tuple_arity(ty: &TypeSignature, value: &Value) -> Candidates<(TypeSignature, Value)> {
+    let mut paths = vec![];
+    tuple_type_paths(ty, &mut vec![], &mut paths);
+    let mut edits = vec![];
+    for (path, fields) in paths.iter() {
+        if fields.len() < 2 {
+            continue;
+        }
+        for field in fields.iter() {
+            edits.push((path.clone(), iter::once(field.clone()).collect()));
+        }
+    }
+    for (path, fields) in paths.into_iter() {
+        if fields.len() < 3 {
+            continue;
+        }
+        for field in fields.iter() {
+            let keep: BTreeSet<_> = fields.iter().filter(|f| *f != field).cloned().collect();
+            edits.push((path.clone(), keep));
+        }
+    }
+    let (ty, value) = (ty.clone(), value.clone());
+    Box::new(edits.into_iter().map(
+        move |(path, keep): (Vec<TypePathStep>, BTreeSet<ClarityName>)| {
+            (
+                retain_type_fields(&ty, &path, &keep),
+                retain_value_fields(&value, &path, &keep),
+            )
+        },
+    ))
+}
+
+/// `ty` with only the `keep` fields of the tuple type at `path`
+fn retain_type_fields(
+    ty: &TypeSignature,
+    path: &[TypePathStep],
+    keep: &BTreeSet<ClarityName>,
+) -> TypeSignature {
+    map_type_at(ty, path, &|ty| {
+        let tuple_type = match ty {
+            TupleType(tuple_type) => tuple_type,
+            _ => panic!("Expected a tuple type, got {}", ty),
+        };
+        let fields = tuple_type
+            .get_type_map()
+            .iter()
+            .filter(|(name, _)| keep.contains(*name))
+            .map(|(name, ty)| (name.clone(), ty.clone()))
+            .collect::<Vec<_>>();
+        TupleType(TupleTypeSignature::try_from(fields).unwrap())
+    })
+}
+
+/// `ty` with the type at `path` replaced by `f` of it
+fn map_type_at(
+    ty: &TypeSignature,
+    path: &[TypePathStep],
+    f: &dyn Fn(&TypeSignature) -> TypeSignature,
+) -> TypeSignature {
+    let (step, rest) = match path.split_first() {
+        Some(split) => split,
+        None => return f(ty),
+    };
+    match (step, ty) {
+        (TypePathStep::Some, OptionalType(inner)) => {
+            TypeSignature::new_option(map_type_at(inner, rest, f)).unwrap()
+        }
+        (TypePathStep::Ok, ResponseType(types)) => {
+            TypeSignature::new_response(map_type_at(&types.0, rest, f), types.1.clone()).unwrap()
+        }
+        (TypePathStep::Err, ResponseType(types)) => {
+            TypeSignature::new_response(types.0.clone(), map_type_at(&types.1, rest, f)).unwrap()
+        }
+        (TypePathStep::Item, SequenceType(SequenceSubtype::ListType(list_type))) => {
+            SequenceType(SequenceSubtype::ListType(
+                ListTypeData::new_list(
+                    map_type_at(list_type.get_list_item_type(), rest, f),
+                    list_type.get_max_len(),
+                )
+                .unwrap(),
+            ))
+        }
+        (TypePathStep::Field(name), TupleType(tuple_type)) => {
+            let mut fields = tuple_type.get_type_map().clone();
+            let inner = fields
+                .get_mut(name)
+                .expect("Tuple type path names a missing field");
+            *inner = map_type_at(inner, rest, f);
+            TupleType(TupleTypeSignature::try_from(fields).unwrap())
+        }
+        (step, ty) => panic!("Type path step {:?} does not apply to {}", step, ty),
+    }
+}
+
+/// `value` with only the `keep` fields of the tuples at `path`
+fn retain_value_fields(
+    value: &Value,
+    path: &[TypePathStep],
+    keep: &BTreeSet<ClarityName>,
+) -> Value {
+    let (step, rest) = match path.split_first() {
+        Some(split) => split,
+        None => {
+            let tuple = match value {
+                Value::Tuple(tuple) => tuple,
+                _ => panic!("Expected a tuple, got {}", value),
+            };
+            let fields = tuple
+                .data_map
+                .iter()
+                .filter(|(name, _)| keep.contains(*name))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            return Value::Tuple(TupleData::from_data(fields).unwrap());
+        }
+    };
+    match (step, value) {
+        (TypePathStep::Some, Value::Optional(OptionalData { data: Some(inner) })) => {
+            Value::some(retain_value_fields(inner, rest, keep)).unwrap()
+        }
+        (
+            TypePathStep::Ok,
+            Value::Response(ResponseData {
+                committed: true,
+                data,
+            }),
+        ) => Value::okay(retain_value_fields(data, rest, keep)).unwrap(),
+        (
+            TypePathStep::Err,
+            Value::Response(ResponseData {
+                committed: false,
+                data,
+            }),
+        ) => Value::error(retain_value_fields(data, rest, keep)).unwrap(),
+        (TypePathStep::Item, Value::Sequence(SequenceData::List(list))) => {
+            Value::cons_list_unsanitized(
+                list.data
+                    .iter()
+                    .map(|item| retain_value_fields(item, rest, keep))
+                    .collect(),
+            )
+            .unwrap()
+        }
+        (TypePathStep::Field(name), Value::Tuple(tuple)) => {
+            let fields = tuple
+                .data_map
+                .iter()
+                .map(|(field, inner)| {
+                    if field == name {
+                        (field.clone(), retain_value_fields(inner, rest, keep))
+                    } else {
+                        (field.clone(), inner.clone())
+                    }
+                })
+                .collect();
+            Value::Tuple(TupleData::from_data(fields).unwrap())
+        }
+        // `none`, or the other branch of a response: there are no tuples to edit
+        _ => value.clone(),
+    }
+}
+
+/// Simpler values of the same type as `value`
+fn shrink_value(value: &Value) -> Candidates<Value> {
+    match value {
+        Value::Int(i) => Box::new(towards_zero_i128(*i).map(Value::Int)),
+        Value::UInt(u) => Box::new(towards_zero_u128(*u).map(Value::UInt)),
+        Value::Bool(true) => Box::new(iter::once(Value::Bool(false))),
+        Value::Principal(principal) => {
+            let zero = StandardPrincipalData(0, [0; 20]);
+            let mut candidates = vec![];
+            if let PrincipalData::Contract(contract) = principal {
+                candidates.push(PrincipalData::Standard(contract.issuer.clone()));
+            }
+            if !matches!(principal, PrincipalData::Standard(p) if *p == zero) {
+                candidates.push(PrincipalData::Standard(zero));
+            }
+            candidates.dedup();
+            Box::new(candidates.into_iter().map(Value::Principal))
+        }
+        Value::Sequence(SequenceData::Buffer(buff)) => {
+            let zeroed = Some(vec![0; buff.data.len()]).filter(|zeroed| *zeroed != buff.data);
+            Box::new(
+                shorter(buff.data.clone())
+                    .chain(zeroed)
+                    .map(|data| Value::Sequence(SequenceData::Buffer(BuffData { data }))),
+            )
+        }
+        Value::Sequence(SequenceData::String(CharType::ASCII(string))) => {
+            let simplest =
+                Some(vec![b'a'; string.data.len()]).filter(|simplest| *simplest != string.data);
+            Box::new(shorter(string.data.clone()).chain(simplest).map(|data| {
+                Value::Sequence(SequenceData::String(CharType::ASCII(ASCIIData { data })))
+            }))
+        }
+        Value::Sequence(SequenceData::String(CharType::UTF8(string))) => {
+            let simplest = Some(vec![vec![b'a']; string.data.len()])
+                .filter(|simplest| *simplest != string.data);
+            Box::new(shorter(string.data.clone()).chain(simplest).map(|data| {
+                Value::Sequence(SequenceData::String(CharType::UTF8(UTF8Data { data })))
+            }))
+        }
+        Value::Optional(OptionalData { data: Some(inner) }) => Box::new(
+            iter::once(Value::none())
+                .chain(shrink_value(inner).map(|inner| Value::some(inner).unwrap())),
+        ),
+        Value::Response(ResponseData { committed, data }) => {
+            let committed = *committed;
+            Box::new(shrink_value(data).map(move |inner| {
+                if committed {
+                    Value::okay(inner).unwrap()
+                } else {
+                    Value::error(inner).unwrap()
+                }
+            }))
+        }
+        Value::Sequence(SequenceData::List(list)) => {
+            let items = list.data.clone();
+            let shrunk_items = (0..items.len()).flat_map(move |i| {
+                let items = items.clone();
+                shrink_value(&items[i]).map(move |item| {
+                    let mut items = items.clone();
+                    items[i] = item;
+                    items
+                })
+            });
+            Box::new(
+                shorter(list.data.clone())
+                    .chain(shrunk_items)
+                    .map(|items| Value::cons_list_unsanitized(items).unwrap()),
+            )
+        }
+        Value::Tuple(tuple) => {
+            let fields: Vec<_> = tuple.data_map.clone().into_iter().collect();
+            Box::new((0..fields.len()).flat_map(move |i| {
+                let fields = fields.clone();
+                shrink_value(&fields[i].1).map(move |field| {
+                    let mut fields = fields.clone();
+                    fields[i].1 = field;
+                    Value::Tuple(TupleData::from_data(fields).unwrap())
+                })
+            }))
+        }
+        _ => Box::new(iter::empty()),
+    }
+}
+
+/// Prefixes of `items` from the empty one up, each halving the number of items dropped, then
+/// `items` without each one of its items but the last
+fn shorter<T: Clone + 'static>(items: Vec<T>) -> impl Iterator<Item = Vec<T>> {
+    let len = items.len();
+    let prefixes = {
+        let items = items.clone();
+        iter::successors(Some(len), |dropped| Some(dropped / 2))
+            .take_while(|dropped| *dropped > 0)
+            .map(move |dropped| items[..len - dropped].to_vec())
+    };
+    let removals = (0..len.saturating_sub(1)).map(move |i| {
+        let mut items = items.clone();
+        items.remove(i);
+        items
+    });
+    prefixes.chain(removals)
+}
+
+/// 0, then numbers halving the distance to `target` each time
+fn towards_zero_i128(target: i128) -> impl Iterator<Item = i128> {
+    iter::successors(Some(target), |distance| Some(distance / 2))
+        .take_while(|distance| *distance != 0)
+        .map(move |distance| target - distance)
+}
+
+/// 0, then numbers halving the distance to `target` each time
+fn towards_zero_u128(target: u128) -> impl Iterator<Item = u128> {
+    iter::successors(Some(target), |distance| Some(distance / 2))
+        .take_while(|distance| *distance != 0)
+        .map(move |distance| target - distance)
+}
+
+/// The values directly contained in `value`
+fn inner_values(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Optional(OptionalData { data: Some(inner) }) => vec![inner],
+        Value::Response(ResponseData { data, .. }) => vec![data],
+        Value::Sequence(SequenceData::List(list)) => list.data.iter().collect(),
+        Value::Tuple(tuple) => tuple.data_map.values().collect(),
+        _ => vec![],
+    }
+}
+
+/// The largest magnitude of the integers in `value`
+fn max_int_magnitude(value: &Value) -> u128 {
+    let own = match value {
+        Value::Int(i) => i.unsigned_abs(),
+        Value::UInt(u) => *u,
+        _ => 0,
+    };
+    inner_values(value)
+        .into_iter()
+        .map(max_int_magnitude)
+        .fold(own, u128::max)
+}
+
+/// Whether every list and tuple in `value` has at most one item or field
+fn is_narrow(value: &Value) -> bool {
+    let width = match value {
+        Value::Sequence(SequenceData::List(list)) => list.data.len(),
+        Value::Tuple(tuple) => tuple.data_map.len(),
+        _ => 0,
+    };
+    width <= 1 && inner_values(value).into_iter().all(is_narrow)
+}
+
+/// Leaf types of every kind
+fn leaf_signatures() -> Vec<TypeSignature> {
+    vec![
+        IntType,
+        UIntType,
+        BoolType,
+        PrincipalType,
+        SequenceType(SequenceSubtype::BufferType(
+            BufferLength::try_from(32u32).unwrap(),
+        )),
+        SequenceType(SequenceSubtype::StringType(StringSubtype::ASCII(
+            BufferLength::try_from(32u32).unwrap(),
+        ))),
+        SequenceType(SequenceSubtype::StringType(StringSubtype::UTF8(
+            StringUTF8Length::try_from(8u32).unwrap(),
+        ))),
+    ]
+}
+
+/// Whether `a` and `b` are the same kind of type, whatever their lengths and inner types.
+/// No value is admitted by two types of different kinds.
+fn same_kind(a: &TypeSignature, b: &TypeSignature) -> bool {
+    match (a, b) {
+        (
+            SequenceType(SequenceSubtype::StringType(a)),
+            SequenceType(SequenceSubtype::StringType(b)),
+        ) => discriminant(a) == discriminant(b),
+        (SequenceType(a), SequenceType(b)) => discriminant(a) == discriminant(b),
+        (a, b) => discriminant(a) == discriminant(b),
+    }
+}
+
+/// The number of items in `value`, if it is a sequence
+fn sequence_len(value: &Value) -> Option<u32> {
+    let len = match value {
+        Value::Sequence(SequenceData::Buffer(buff)) => buff.data.len(),
+        Value::Sequence(SequenceData::String(CharType::ASCII(string))) => string.data.len(),
+        Value::Sequence(SequenceData::String(CharType::UTF8(string))) => string.data.len(),
+        Value::Sequence(SequenceData::List(list)) => list.data.len(),
+        _ => return None,
+    };
+    Some(len as u32)
+}
+
+/// The sequence type `ty` with a maximum length of `max_len`
+fn with_max_len(ty: &TypeSignature, max_len: u32) -> TypeSignature {
+    match ty {
+        SequenceType(SequenceSubtype::BufferType(_)) => SequenceType(SequenceSubtype::BufferType(
+            BufferLength::try_from(max_len).unwrap(),
+        )),
+        SequenceType(SequenceSubtype::StringType(StringSubtype::ASCII(_))) => {
+            SequenceType(SequenceSubtype::StringType(StringSubtype::ASCII(
+                BufferLength::try_from(max_len).unwrap(),
+            )))
+        }
+        SequenceType(SequenceSubtype::StringType(StringSubtype::UTF8(_))) => {
+            SequenceType(SequenceSubtype::StringType(StringSubtype::UTF8(
+                StringUTF8Length::try_from(max_len).unwrap(),
+            )))
+        }
+        SequenceType(SequenceSubtype::ListType(list_type)) => {
+            SequenceType(SequenceSubtype::ListType(
+                ListTypeData::new_list(list_type.get_list_item_type().clone(), max_len).unwrap(),
+            ))
+        }
+        ty => panic!("Expected a sequence type, got {}", ty),
+    }
+}
+
+/// The paths to the types within `ty` that some part of `values` has, outermost first, with
+/// the types there and the length of the longest sequence there
+fn occupied_type_paths(
+    ty: &TypeSignature,
+    values: &[&Value],
+    path: &mut Vec<TypePathStep>,
+    paths: &mut Vec<(Vec<TypePathStep>, TypeSignature, Option<u32>)>,
+) {
+    if values.is_empty() {
+        return;
+    }
+    let longest = values.iter().filter_map(|value| sequence_len(value)).max();
+    paths.push((path.clone(), ty.clone(), longest));
+
+    let children: Vec<(TypePathStep, &TypeSignature, Vec<&Value>)> = match ty {
+        OptionalType(inner) => {
+            let inner_values = values
+                .iter()
+                .filter_map(|value| match value {
+                    Value::Optional(OptionalData { data: Some(inner) }) => Some(inner.as_ref()),
+                    _ => None,
+                })
+                .collect();
+            vec![(TypePathStep::Some, inner.as_ref(), inner_values)]
+        }
+        ResponseType(types) => {
+            let branch = |ok: bool| {
+                values
+                    .iter()
+                    .filter_map(move |value| match value {
+                        Value::Response(ResponseData { committed, data }) if *committed == ok => {
+                            Some(data.as_ref())
+                        }
+                        _ => None,
+                    })
+                    .collect()
+            };
+            vec![
+                (TypePathStep::Ok, &types.0, branch(true)),
+                (TypePathStep::Err, &types.1, branch(false)),
+            ]
+        }
+        SequenceType(SequenceSubtype::ListType(list_type)) => {
+            let items = values
+                .iter()
+                .flat_map(|value| match value {
+                    Value::Sequence(SequenceData::List(list)) => list.data.iter().collect(),
+                    _ => vec![],
+                })
+                .collect();
+            vec![(TypePathStep::Item, list_type.get_list_item_type(), items)]
+        }
+        TupleType(tuple_type) => tuple_type
+            .get_type_map()
+            .iter()
+            .map(|(name, inner)| {
+                let fields = values
+                    .iter()
+                    .filter_map(|value| match value {
+                        Value::Tuple(tuple) => tuple.data_map.get(name),
+                        _ => None,
+                    })
+                    .collect();
+                (TypePathStep::Field(name.clone()), inner, fields)
+            })
+            .collect(),
+        _ => vec![],
+    };
+    for (step, inner, inner_values) in children.into_iter() {
+        path.push(step);
+        occupied_type_paths(inner, &inner_values, path, paths);
+        path.pop();
+    }
+}
+
+/// A type and a value it does not admit.
+///
+/// The value is admitted by a generated type, which is then changed in one place that holds
+/// part of the value: either the type there is replaced by a leaf type of another kind, or, if
+/// the value has a non-empty sequence there, the sequence type's maximum length is set to one
+/// less than the length of the longest one.
+pub fn prop_mistyped_value(max_nesting: u32) -> impl Strategy<Value = (TypeSignature, Value)> {
+    (prop_typed_value(max_nesting), any::<prop::sample::Index>()).prop_map(
+        |((ty, value), index)| {
+            let mut paths = vec![];
+            occupied_type_paths(&ty, &[&value], &mut vec![], &mut paths);
+
+            // (path, replacement type) if Some, else (path, shorter maximum length)
+            let mut corruptions = vec![];
+            for (i, (_, inner, longest)) in paths.iter().enumerate() {
+                for leaf in leaf_signatures().into_iter() {
+                    if !same_kind(&leaf, inner) {
+                        corruptions.push((i, Some(leaf), 0));
+                    }
+                }
+                if let Some(longest) = longest.filter(|longest| *longest > 0) {
+                    corruptions.push((i, None, longest - 1));
+                }
+            }
+
+            let (i, replacement, max_len) = index.get(&corruptions).clone();
+            let path = &paths[i].0;
+            let mistyped = match replacement {
+                Some(replacement) => map_type_at(&ty, path, &|_| replacement.clone()),
+                None => map_type_at(&ty, path, &|inner| with_max_len(inner, max_len)),
+            };
+            (mistyped, value)
+        },
+    )
+}
+
+/// A way of corrupting a serialized value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SerializationCorruption {
+    /// Drop some of the bytes at the end
+    Truncate,
+    /// Replace one of the type prefixes by a byte that is not one
+    BadTypePrefix,
+    /// Replace one of the length prefixes of a sequence or tuple by a length longer than
+    /// the rest of the input
+    OverlongLength,
+}
+
+/// The offsets of the type prefixes and of the length prefixes in the serialization of
+/// `value`, which starts at `offset`
+fn serialization_landmarks(
+    value: &Value,
+    offset: usize,
+    type_prefixes: &mut Vec<usize>,
+    length_prefixes: &mut Vec<usize>,
+) {
+    type_prefixes.push(offset);
+    match value {
+        Value::Optional(OptionalData { data: Some(inner) })
+        | Value::Response(ResponseData { data: inner, .. }) => {
+            serialization_landmarks(inner, offset + 1, type_prefixes, length_prefixes)
+        }
+        Value::Sequence(SequenceData::List(list)) => {
+            length_prefixes.push(offset + 1);
+            let mut item_offset = offset + 5;
+            for item in list.data.iter() {
+                serialization_landmarks(item, item_offset, type_prefixes, length_prefixes);
+                item_offset += item.serialized_size().unwrap() as usize;
+            }
+        }
+        Value::Sequence(_) => length_prefixes.push(offset + 1),
+        Value::Tuple(tuple) => {
+            length_prefixes.push(offset + 1);
+            let mut field_offset = offset + 5;
+            for (name, field) in tuple.data_map.iter() {
+                field_offset += 1 + name.as_str().len();
+                serialization_landmarks(field, field_offset, type_prefixes, length_prefixes);
+                field_offset += field.serialized_size().unwrap() as usize;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A type, and the serialization of a value it admits with one corruption applied.  No
+/// prefix of a serialization is the serialization of another value, so none of them
+/// deserialize, whether typed or untyped.
+pub fn prop_malformed_serialization(
+    max_nesting: u32,
+) -> impl Strategy<Value = (TypeSignature, SerializationCorruption, Vec<u8>)> {
+    let corruption = prop_oneof![
+        Just(SerializationCorruption::Truncate),
+        Just(SerializationCorruption::BadTypePrefix),
+        Just(SerializationCorruption::OverlongLength),
+    ];
+    let excess = prop_oneof![0u32..64, any::<u32>()];
+    (
+        prop_typed_value(max_nesting),
+        corruption,
+        any::<prop::sample::Index>(),
+        any::<u8>().prop_filter("Type prefix", |byte| TypePrefix::from_u8(*byte).is_none()),
+        excess,
+    )
+        .prop_map(|((ty, value), corruption, index, bad_prefix, excess)| {
+            let mut bytes = value.serialize_to_vec().unwrap();
+            let mut type_prefixes = vec![];
+            let mut length_prefixes = vec![];
+            serialization_landmarks(&value, 0, &mut type_prefixes, &mut length_prefixes);
+
+            // values without sequences or tuples have no length prefixes to corrupt
+            let corruption = match corruption {
+                SerializationCorruption::OverlongLength if length_prefixes.is_empty() => {
+                    SerializationCorruption::Truncate
+                }
+                corruption => corruption,
+            };
+            match corruption {
+                SerializationCorruption::Truncate => {
+                    bytes.truncate(index.index(bytes.len()));
+                }
+                SerializationCorruption::BadTypePrefix => {
+                    bytes[*index.get(&type_prefixes)] = bad_prefix;
+                }
+                SerializationCorruption::OverlongLength => {
+                    let offset = *index.get(&length_prefixes);
+                    let remaining = (bytes.len() - offset - 4) as u32;
+                    let len = remaining.saturating_add(1).saturating_add(excess);
+                    bytes[offset..offset + 4].copy_from_slice(&len.to_be_bytes());
+                }
+            }
+            (ty, corruption, bytes)
+        })
+}
+
+/// Shortest sequence type in a trait conformance case, so that every sized type can be narrowed
+const MIN_CONFORMANCE_LEN: u32 = 2;
+const MAX_CONFORMANCE_LEN: u32 = 8;
+
+/// The maximum length of `ty`, if it is a conformance signature's sequence type
+fn sequence_max_len(ty: &TypeSignature) -> Option<u32> {
+    match ty {
+        SequenceType(SequenceSubtype::BufferType(len))
+        | SequenceType(SequenceSubtype::StringType(StringSubtype::ASCII(len))) => {
+            Some(u32::from(len))
+        }
+        SequenceType(SequenceSubtype::ListType(list_type)) => Some(list_type.get_max_len()),
+        _ => None,
+    }
+}
+
+/// Type signatures that the trait conformance strategies know how to widen, narrow and
+/// inhabit: leaf types other than UTF-8 strings, optionals and lists, with at most
+/// `max_nesting` levels of nesting.  Sequence types are at least `MIN_CONFORMANCE_LEN` long.
+pub fn prop_conformance_signature(max_nesting: u32) -> impl Strategy<Value = TypeSignature> {
+    let leaf = prop_oneof![
+        Just(IntType),
+        Just(UIntType),
+        Just(BoolType),
+        Just(PrincipalType),
+        (MIN_CONFORMANCE_LEN..=MAX_CONFORMANCE_LEN).prop_map(|len| SequenceType(
+            SequenceSubtype::BufferType(BufferLength::try_from(len).unwrap())
+        )),
+        (MIN_CONFORMANCE_LEN..=MAX_CONFORMANCE_LEN).prop_map(|len| SequenceType(
+            SequenceSubtype::StringType(StringSubtype::ASCII(BufferLength::try_from(len).unwrap()))
+        )),
+    ];
+    leaf.prop_recursive(max_nesting, 16, 1, |inner| {
+        prop_oneof![
+            inner
+                .clone()
+                .prop_map(|ty| TypeSignature::new_option(ty).unwrap()),
+            (inner, MIN_CONFORMANCE_LEN..=MAX_CONFORMANCE_LEN).prop_map(|(ty, max_len)| {
+                SequenceType(SequenceSubtype::ListType(
+                    ListTypeData::new_list(ty, max_len).unwrap(),
+                ))
+            }),
+        ]
+    })
+}
+
+/// Types that admit `ty`: each sequence type in it may be up to two items longer
+fn prop_widened_signature(ty: &TypeSignature) -> BoxedStrategy<TypeSignature> {
+    match ty {
+        OptionalType(inner) => prop_widened_signature(inner)
+            .prop_map(|inner| TypeSignature::new_option(inner).unwrap())
+            .boxed(),
+        SequenceType(SequenceSubtype::ListType(list_type)) => {
+            let max_len = list_type.get_max_len();
+            (
+                prop_widened_signature(list_type.get_list_item_type()),
+                0u32..=2,
+            )
+                .prop_map(move |(inner, extra)| {
+                    SequenceType(SequenceSubtype::ListType(
+                        ListTypeData::new_list(inner, max_len + extra).unwrap(),
+                    ))
+                })
+                .boxed()
+        }
+        SequenceType(_) => {
+            let ty = ty.clone();
+            let max_len = sequence_max_len(&ty).unwrap();
+            (0u32..=2)
+                .prop_map(move |extra| with_max_len(&ty, max_len + extra))
+                .boxed()
+        }
+        ty => Just(ty.clone()).boxed(),
+    }
+}
+
+/// A type of the same kind as `ty` that does not admit it, if there is one
+fn narrowed_signature(ty: &TypeSignature) -> Option<TypeSignature> {
+    match ty {
+        OptionalType(inner) => {
+            narrowed_signature(inner).map(|inner| TypeSignature::new_option(inner).unwrap())
+        }
+        SequenceType(_) => Some(with_max_len(ty, sequence_max_len(ty).unwrap() - 1)),
+        _ => None,
+    }
+}
+
+/// A type of another kind than `ty`, which neither admits it nor is admitted by it
+fn other_kind_signature(ty: &TypeSignature) -> TypeSignature {
+    match ty {
+        IntType => UIntType,
+        UIntType | BoolType => IntType,
+        PrincipalType => BoolType,
+        SequenceType(SequenceSubtype::BufferType(len)) => SequenceType(
+            SequenceSubtype::StringType(StringSubtype::ASCII(len.clone())),
+        ),
+        SequenceType(SequenceSubtype::StringType(StringSubtype::ASCII(len))) => {
+            SequenceType(SequenceSubtype::BufferType(len.clone()))
+        }
+        OptionalType(inner) => (**inner).clone(),
+        SequenceType(SequenceSubtype::ListType(list_type)) => {
+            TypeSignature::new_option(list_type.get_list_item_type().clone()).unwrap()
+        }
+        ty => panic!("No other kind of type for {}", ty),
+    }
+}
+
+/// An expression whose inferred type is admitted by `ty`, a conformance signature
+fn conformance_literal(ty: &TypeSignature) -> String {
+    match ty {
+        IntType => "1".into(),
+        UIntType => "u1".into(),
+        BoolType => "true".into(),
+        PrincipalType => "tx-sender".into(),
+        SequenceType(SequenceSubtype::BufferType(_)) => "0x00".into(),
+        SequenceType(SequenceSubtype::StringType(StringSubtype::ASCII(_))) => "\"a\"".into(),
+        OptionalType(inner) => format!("(some {})", conformance_literal(inner)),
+        SequenceType(SequenceSubtype::ListType(list_type)) => {
+            format!(
+                "(list {})",
+                conformance_literal(list_type.get_list_item_type())
+            )
+        }
+        ty => panic!("No literal for type {}", ty),
+    }
+}
+
+/// A method of a generated trait
+#[derive(Debug, Clone)]
+pub struct TraitMethodSpec {
+    pub name: String,
+    pub args: Vec<TypeSignature>,
+    pub ok_type: TypeSignature,
+    pub err_type: TypeSignature,
+}
+
+/// A generated trait definition
+#[derive(Debug, Clone)]
+pub struct TraitSpec {
+    pub methods: Vec<TraitMethodSpec>,
+}
+
+impl TraitSpec {
+    /// The `define-trait` of this trait, named `name`
+    pub fn source(&self, name: &str) -> String {
+        let signatures: Vec<_> = self
+            .methods
+            .iter()
+            .map(|method| {
+                let args: Vec<_> = method.args.iter().map(|arg| arg.to_string()).collect();
+                format!(
+                    "({} ({}) (response {} {}))",
+                    method.name,
+                    args.join(" "),
+                    method.ok_type,
+                    method.err_type
+                )
+            })
+            .collect();
+        format!("(define-trait {} (\n  {}))", name, signatures.join("\n  "))
+    }
+}
+
+/// Traits with one to `max_methods` methods, named `method-0`, `method-1`, ..., each taking up
+/// to `max_args` arguments.  Every type is a `prop_conformance_signature(max_nesting)`.
+pub fn prop_trait_spec(
+    max_methods: usize,
+    max_args: usize,
+    max_nesting: u32,
+) -> impl Strategy<Value = TraitSpec> {
+    let method = (
+        prop::collection::vec(prop_conformance_signature(max_nesting), 0..=max_args),
+        prop_conformance_signature(max_nesting),
+        prop_conformance_signature(max_nesting),
+    );
+    prop::collection::vec(method, 1..=max_methods).prop_map(|methods| TraitSpec {
+        methods: methods
+            .into_iter()
+            .enumerate()
+            .map(|(i, (args, ok_type, err_type))| TraitMethodSpec {
+                name: format!("method-{}", i),
+                args,
+                ok_type,
+                err_type,
+            })
+            .collect(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Public,
+    ReadOnly,
+    Private,
+}
+
+/// A function of a generated trait implementation
+#[derive(Debug, Clone)]
+pub struct ImplMethodSpec {
+    pub name: String,
+    pub visibility: Visibility,
+    pub args: Vec<TypeSignature>,
+    /// The body returns `(ok <literal of this type>)`
+    pub returns: TypeSignature,
+}
+
+impl ImplMethodSpec {
+    fn source(&self) -> String {
+        let define = match self.visibility {
+            Visibility::Public => "define-public",
+            Visibility::ReadOnly => "define-read-only",
+            Visibility::Private => "define-private",
+        };
+        let args: Vec<_> = self
+            .args
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| format!("(a{} {})", i, arg))
+            .collect();
+        format!(
+            "({} ({} {}) (ok {}))",
+            define,
+            self.name,
+            args.join(" "),
+            conformance_literal(&self.returns)
+        )
+    }
+}
+
+/// A single change to a compatible trait implementation that makes it non-compliant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraitMutation {
+    /// Leave out one of the trait's methods
+    DropMethod,
+    /// Make a method private
+    MakePrivate,
+    /// Add an argument to a method
+    AddArg,
+    /// Remove an argument from a method
+    RemoveArg,
+    /// Replace an argument with one of a different kind
+    ChangeArgKind,
+    /// Replace a sized argument with one that is too short for the trait's argument
+    NarrowArg,
+    /// Return a value of a different kind than the trait's ok type
+    ChangeReturnKind,
+}
+
+impl TraitMutation {
+    const ALL: [TraitMutation; 7] = [
+        TraitMutation::DropMethod,
+        TraitMutation::MakePrivate,
+        TraitMutation::AddArg,
+        TraitMutation::RemoveArg,
+        TraitMutation::ChangeArgKind,
+        TraitMutation::NarrowArg,
+        TraitMutation::ChangeReturnKind,
+    ];
+
+    /// Whether this mutation can be applied to `method`
+    fn applies_to(&self, method: &TraitMethodSpec) -> bool {
+        match self {
+            TraitMutation::RemoveArg | TraitMutation::ChangeArgKind => !method.args.is_empty(),
+            TraitMutation::NarrowArg => method
+                .args
+                .iter()
+                .any(|arg| narrowed_signature(arg).is_some()),
+            _ => true,
+        }
+    }
+}
+
+/// A generated contract that claims to implement a trait
+#[derive(Debug, Clone)]
+pub struct ImplSpec {
+    pub methods: Vec<ImplMethodSpec>,
+    /// `Some` if this implementation was mutated to be non-compliant
+    pub mutation: Option<TraitMutation>,
+}
+
+impl ImplSpec {
+    /// The contract's source, which implements the trait `trait_path`, e.g. `.contract.trait`
+    pub fn source(&self, trait_path: &str) -> String {
+        let mut lines = vec![format!("(impl-trait {})", trait_path)];
+        lines.extend(self.methods.iter().map(|method| method.source()));
+        lines.join("\n")
+    }
+}
+
+/// Implementations that comply with `trait_spec`: each of its methods is public or read-only,
+/// takes arguments of the same or wider types and returns a value of its ok type, and there
+/// may be an extra method that is not part of the trait
+pub fn prop_compatible_impl(trait_spec: &TraitSpec) -> BoxedStrategy<ImplSpec> {
+    let methods: Vec<_> = trait_spec
+        .methods
+        .iter()
+        .map(|method| {
+            let name = method.name.clone();
+            let returns = method.ok_type.clone();
+            let args: Vec<_> = method.args.iter().map(prop_widened_signature).collect();
+            let visibility = prop_oneof![Just(Visibility::Public), Just(Visibility::ReadOnly)];
+            (visibility, args).prop_map(move |(visibility, args)| ImplMethodSpec {
+                name: name.clone(),
+                visibility,
+                args,
+                returns: returns.clone(),
+            })
+        })
+        .collect();
+    let extra_method = (prop_conformance_signature(0), prop_conformance_signature(0)).prop_map(
+        |(arg, returns)| ImplMethodSpec {
+            name: "extra-method".into(),
+            visibility: Visibility::Public,
+            args: vec![arg],
+            returns,
+        },
+    );
+    (methods, proptest::option::of(extra_method))
+        .prop_map(|(mut methods, extra_method)| {
+            methods.extend(extra_method);
+            methods
+        })
+        .prop_shuffle()
+        .prop_map(|methods| ImplSpec {
+            methods,
+            mutation: None,
+        })
+        .boxed()
+}
+
+/// Implementations of `trait_spec` with exactly one defect: a compatible implementation with
+/// one `TraitMutation` applied to one of the trait's methods
+pub fn prop_incompatible_impl(trait_spec: &TraitSpec) -> BoxedStrategy<ImplSpec> {
+    let targets: Vec<_> = TraitMutation::ALL
+        .iter()
+        .flat_map(|mutation| {
+            trait_spec
+                .methods
+                .iter()
+                .enumerate()
+                .filter(move |(_, method)| mutation.applies_to(method))
+                .map(move |(i, _)| (*mutation, i))
+        })
+        .collect();
+    let trait_spec = trait_spec.clone();
+    (
+        prop_compatible_impl(&trait_spec),
+        prop::sample::select(targets),
+        any::<prop::sample::Index>(),
+        prop_conformance_signature(0),
+    )
+        .prop_map(move |(mut spec, (mutation, i), index, new_arg)| {
+            let trait_method = &trait_spec.methods[i];
+            let position = spec
+                .methods
+                .iter()
+                .position(|method| method.name == trait_method.name)
+                .expect("BUG: compatible implementation is missing a trait method");
+            let method = &mut spec.methods[position];
+            match mutation {
+                TraitMutation::DropMethod => {
+                    spec.methods.remove(position);
+                }
+                TraitMutation::MakePrivate => method.visibility = Visibility::Private,
+                TraitMutation::AddArg => {
+                    method
+                        .args
+                        .insert(index.index(method.args.len() + 1), new_arg);
+                }
+                TraitMutation::RemoveArg => {
+                    method.args.remove(index.index(method.args.len()));
+                }
+                TraitMutation::ChangeArgKind => {
+                    let arg = index.get_mut(&mut method.args);
+                    *arg = other_kind_signature(arg);
+                }
+                TraitMutation::NarrowArg => {
+                    let narrowable: Vec<_> = trait_method
+                        .args
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, arg)| {
+                            narrowed_signature(arg).map(|narrower| (i, narrower))
+                        })
+                        .collect();
+                    // too short to admit the trait's argument, whatever it was widened to
+                    let (arg, narrower) = index.get(&narrowable).clone();
+                    method.args[arg] = narrower;
+                }
+                TraitMutation::ChangeReturnKind => {
+                    method.returns = other_kind_signature(&method.returns)
+                }
+            }
+            spec.mutation = Some(mutation);
+            spec
+        })
+        .boxed()
+}
+
+#[test]
+fn prop_value_shrinks_to_minimal_counterexample() {
+    let result = TestRunner::deterministic().run(&prop_typed_value(4), |(_, value)| {
+        prop_assert!(max_int_magnitude(&value) < 1000);
+        Ok(())
+    });
+    let (ty, value) = match result {
+        Err(proptest::test_runner::TestError::Fail(_, minimal)) => minimal,
+        result => panic!("Expected the property to fail, got {:?}", result),
+    };
+    assert_eq!(max_int_magnitude(&value), 1000);
+    assert!(is_narrow(&value), "{} is not minimal", value);
+    assert!(ty
+        .admits(&stacks_common::types::StacksEpochId::latest(), &value)
+        .unwrap());
+}
+
+#[test]
+fn prop_value_shrinks_within_type() {
+    let epoch = stacks_common::types::StacksEpochId::latest();
+    let mut runner = TestRunner::deterministic();
+    for _ in 0..64 {
+        let mut tree = prop_typed_value(4).new_tree(&mut runner).unwrap();
+        // accept every simplification, as if the test always failed
+        while tree.simplify() {
+            let (ty, value) = tree.current();
+            assert!(
+                ty.admits(&epoch, &value).unwrap(),
+                "{} does not admit {}",
+                ty,
+                value
+            );
+        }
+        let (_, value) = tree.current();
+        assert_eq!(max_int_magnitude(&value), 0);
+        assert!(is_narrow(&value), "{} is not minimal", value);
+    }
+}
+
+#[test]
+fn prop_mistyped_value_is_not_admitted() {
+    let epoch = stacks_common::types::StacksEpochId::latest();
+    let mut runner = TestRunner::deterministic();
+    for _ in 0..256 {
+        let (ty, value) = prop_mistyped_value(4)
+            .new_tree(&mut runner)
+            .unwrap()
+            .current();
+        assert!(
+            !matches!(ty.admits(&epoch, &value), Ok(true)),
+            "{} admits {}",
+            ty,
+            value
+        );
+    }
+}
+
+#[test]
+fn serialization_landmarks_are_prefixes() {
+    let mut runner = TestRunner::deterministic();
+    for _ in 0..64 {
+        let (_, value) = prop_typed_value(4).new_tree(&mut runner).unwrap().current();
+        let bytes = value.serialize_to_vec().unwrap();
+        let mut type_prefixes = vec![];
+        let mut length_prefixes = vec![];
+        serialization_landmarks(&value, 0, &mut type_prefixes, &mut length_prefixes);
+
+        for offset in type_prefixes.iter() {
+            assert!(TypePrefix::from_u8(bytes[*offset]).is_some());
+        }
+        for offset in length_prefixes.iter() {
+            assert!(matches!(
+                TypePrefix::from_u8(bytes[*offset - 1]),
+                Some(
+                    TypePrefix::Buffer
+                        | TypePrefix::List
+                        | TypePrefix::Tuple
+                        | TypePrefix::StringASCII
+                        | TypePrefix::StringUTF8
+                )
+            ));
+            assert!(type_prefixes.contains(&(*offset - 1)));
+        }
+    }
+}
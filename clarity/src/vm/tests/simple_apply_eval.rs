@@ -30,6 +30,7 @@ use crate::vm::contexts::OwnedEnvironment;
 use crate::vm::costs::LimitedCostTracker;
 use crate::vm::database::MemoryBackingStore;
 use crate::vm::errors::{CheckErrors, Error, RuntimeErrorType, ShortReturnType};
+use crate::vm::events::StacksTransactionEvent;
 use crate::vm::tests::{execute, test_clarity_versions};
 use crate::vm::types::signatures::*;
 use crate::vm::types::{
@@ -1347,6 +1348,298 @@ fn test_bitwise() {
     }
 }
 
+#[test]
+fn test_buff_bitwise() {
+    let tests = [
+        "(buff-and 0x0f0f 0xff00)",
+        "(buff-and 0xdeadbeef 0xffff0000)",
+        "(buff-or 0x0f0f 0xf000)",
+        "(buff-xor 0x0f0f 0xffff)",
+        "(buff-xor 0x1234 0x1234)",
+        "(buff-not 0x0f00)",
+        "(buff-not (buff-not 0xcafe))",
+    ];
+
+    let expectations = ["0f00", "dead0000", "ff0f", "f0f0", "0000", "f0ff", "cafe"];
+
+    for (program, expectation) in tests.iter().zip(expectations.iter()) {
+        assert_eq!(
+            Value::buff_from(hex_bytes(expectation).unwrap()).unwrap(),
+            execute_with_parameters(
+                program,
+                ClarityVersion::Clarity3,
+                StacksEpochId::Epoch30,
+                ASTRules::PrecheckSize,
+                false
+            )
+            .unwrap()
+            .unwrap()
+        );
+    }
+
+    // buffers whose lengths differ at runtime are rejected
+    for program in ["(buff-and 0x01 0x0102)", "(buff-xor 0x0102 0x03)"] {
+        let err = execute_with_parameters(
+            program,
+            ClarityVersion::Clarity3,
+            StacksEpochId::Epoch30,
+            ASTRules::PrecheckSize,
+            false,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Runtime(RuntimeErrorType::Arithmetic(_), _)
+        ));
+    }
+}
+
+#[test]
+fn test_concat_many() {
+    let run = |program: &str| {
+        execute_with_parameters(
+            program,
+            ClarityVersion::Clarity3,
+            StacksEpochId::Epoch30,
+            ASTRules::PrecheckSize,
+            false,
+        )
+    };
+
+    let tests = [
+        (
+            r#"(concat-many "block " "height: " "42")"#,
+            "block height: 42",
+        ),
+        (r#"(concat-many "alone")"#, "alone"),
+        (r#"(concat-many "" "a" "" "b")"#, "ab"),
+    ];
+    for (program, expectation) in tests.iter() {
+        assert_eq!(
+            Value::string_ascii_from_bytes(expectation.as_bytes().to_vec()).unwrap(),
+            run(program).unwrap().unwrap()
+        );
+    }
+
+    assert_eq!(
+        Value::string_utf8_from_bytes("abc".as_bytes().to_vec()).unwrap(),
+        run(r#"(concat-many u"a" u"b" u"c")"#).unwrap().unwrap()
+    );
+
+    // mixing string kinds, or passing anything other than strings, is rejected
+    for program in [r#"(concat-many "a" u"b")"#, r#"(concat-many 0x01 0x02)"#] {
+        assert!(matches!(
+            run(program).unwrap_err(),
+            Error::Runtime(RuntimeErrorType::BadTypeConstruction, _)
+        ));
+    }
+}
+
+#[test]
+fn test_print_event() {
+    let run = |program: &str| {
+        let mut marf = MemoryBackingStore::new();
+        let mut env = OwnedEnvironment::new(marf.as_clarity_db(), StacksEpochId::Epoch30);
+        let contract_context = ContractContext::new(
+            QualifiedContractIdentifier::transient(),
+            ClarityVersion::Clarity3,
+        );
+        env.execute_in_env(
+            QualifiedContractIdentifier::transient().issuer.into(),
+            None,
+            Some(contract_context),
+            |exec_env| exec_env.eval_raw(program),
+        )
+        .map(|(value, _, events)| (value, events))
+    };
+
+    let (value, events) = run(r#"(print-event "transfer" { amount: u10 })"#).unwrap();
+    let payload = execute("{ amount: u10 }");
+    assert_eq!(value, payload);
+    assert_eq!(events.len(), 1);
+    match &events[0] {
+        StacksTransactionEvent::StructuredEvent(event_data) => {
+            assert_eq!(event_data.topic, "transfer");
+            assert_eq!(event_data.payload, payload);
+            assert_eq!(
+                event_data.contract_identifier,
+                QualifiedContractIdentifier::transient()
+            );
+        }
+        other => panic!("Expected a structured event, got {:?}", other),
+    }
+
+    // the topic must be an ASCII string of at most 128 bytes, and the payload a tuple
+    let long_topic = format!(r#"(print-event "{}" {{ a: 1 }})"#, "a".repeat(129));
+    for program in [r#"(print-event u"transfer" { a: 1 })"#, long_topic.as_str()] {
+        assert!(matches!(
+            run(program).unwrap_err(),
+            Error::Unchecked(CheckErrors::TypeValueError(..))
+        ));
+    }
+    assert!(matches!(
+        run(r#"(print-event "transfer" 1)"#).unwrap_err(),
+        Error::Unchecked(CheckErrors::ExpectedTuple(_))
+    ));
+}
+
+#[test]
+fn test_secp256k1_recover_principal() {
+    let run = |program: &str, mainnet: bool| {
+        execute_with_parameters(
+            program,
+            ClarityVersion::Clarity3,
+            StacksEpochId::Epoch30,
+            ASTRules::PrecheckSize,
+            mainnet,
+        )
+    };
+    let pub_key = StacksPublicKey::from_hex(
+        "03adb8de4bfb65db2cfd6120d55c6526ae9c52e675db7e47308636534ba7786110",
+    )
+    .unwrap();
+    let principal_of = |version| {
+        StacksAddress::from_public_keys(
+            version,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![pub_key],
+        )
+        .unwrap()
+        .to_account_principal()
+    };
+
+    // same result as `principal-of?` over `secp256k1-recover?`, on either network
+    let program = "(unwrap! (secp256k1-recover-principal? 0xde5b9eb9e7c5592930eb2e30a01369c36586d872082ed8181ee83d2a0ec20f04 0x8738487ebe69b93d8e51583be8eee50bb4213fc49c767d329632730cc193b873554428fc936ca3569afc15f1c9365f6591d6251a89fee9c9ac661116824d3a1301) 4)";
+    let composed = "(unwrap! (principal-of? (unwrap! (secp256k1-recover? 0xde5b9eb9e7c5592930eb2e30a01369c36586d872082ed8181ee83d2a0ec20f04 0x8738487ebe69b93d8e51583be8eee50bb4213fc49c767d329632730cc193b873554428fc936ca3569afc15f1c9365f6591d6251a89fee9c9ac661116824d3a1301) 4)) 4)";
+    for (mainnet, version) in [
+        (true, C32_ADDRESS_VERSION_MAINNET_SINGLESIG),
+        (false, C32_ADDRESS_VERSION_TESTNET_SINGLESIG),
+    ] {
+        let expected = Value::Principal(principal_of(version));
+        assert_eq!(expected, run(program, mainnet).unwrap().unwrap());
+        assert_eq!(expected, run(composed, mainnet).unwrap().unwrap());
+    }
+
+    // error codes match `secp256k1-recover?`
+    let errors = [
+        // the signature does not match the message hash
+        ("(unwrap-err! (secp256k1-recover-principal? 0x0000000000000000000000000000000000000000000000000000000000000000 0x0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000) 3)", 1),
+        // bad recovery id
+        ("(unwrap-err! (secp256k1-recover-principal? 0xde5b9eb9e7c5592930eb2e30a01369c36586d872082ed8181ee83d2a0ec20f04 0x8738487ebe69b93d8e51583be8eee50bb4213fc49c767d329632730cc193b873554428fc936ca3569afc15f1c9365f6591d6251a89fee9c9ac661116824d3a1306) 3)", 2),
+        // too short
+        ("(unwrap-err! (secp256k1-recover-principal? 0xde5b9eb9e7c5592930eb2e30a01369c36586d872082ed8181ee83d2a0ec20f04 0x8738487ebe69b93d8e51583be8eee50bb4213fc49c767d329632730cc193b873554428fc936ca3569afc15f1c9365f6591d6251a89fee9c9ac661116824d3a13) 3)", 2),
+    ];
+    for (program, code) in errors.iter() {
+        assert_eq!(Value::UInt(*code), run(program, false).unwrap().unwrap());
+    }
+}
+
+#[test]
+fn test_replicate_and_repeat() {
+    let run = |program: &str| {
+        execute_with_parameters(
+            program,
+            ClarityVersion::Clarity3,
+            StacksEpochId::Epoch30,
+            ASTRules::PrecheckSize,
+            false,
+        )
+    };
+
+    let tests = [
+        ("(replicate 0 u3)", "(list 0 0 0)"),
+        ("(replicate none u2)", "(list none none)"),
+        ("(replicate (list 1 2) u2)", "(list (list 1 2) (list 1 2))"),
+        ("(replicate 0 u0)", "(list)"),
+        ("(repeat 0x0102 u3)", "0x010201020102"),
+        (r#"(repeat "ab" u3)"#, r#""ababab""#),
+        (r#"(repeat u"ab" u2)"#, r#"u"abab""#),
+        ("(repeat (list 1 2) u2)", "(list 1 2 1 2)"),
+        // a fixed-size accumulator for `fold`
+        (
+            "(define-private (bump (x int) (acc (list 4 int))) (map + acc (replicate x u4)))
+             (fold bump (list 1 2 3) (replicate 0 u4))",
+            "(list 6 6 6 6)",
+        ),
+    ];
+    for (program, expectation) in tests.iter() {
+        assert_eq!(
+            execute(expectation),
+            run(program).unwrap().unwrap(),
+            "{}",
+            program
+        );
+    }
+
+    assert_eq!(
+        Value::buff_from(vec![]).unwrap(),
+        run("(repeat 0x01 u0)").unwrap().unwrap()
+    );
+
+    // too large to build
+    assert!(matches!(
+        run("(repeat 0x0000 u1048576)").unwrap_err(),
+        Error::Unchecked(CheckErrors::ValueTooLarge)
+    ));
+    // the count must be a literal
+    assert!(matches!(
+        run("(replicate 0 (+ u1 u2))").unwrap_err(),
+        Error::Unchecked(CheckErrors::TypeError(TypeSignature::UIntType, _))
+    ));
+}
+
+#[test]
+fn test_find_and_position() {
+    let run = |program: &str| {
+        execute_with_parameters(
+            program,
+            ClarityVersion::Clarity3,
+            StacksEpochId::Epoch30,
+            ASTRules::PrecheckSize,
+            false,
+        )
+    };
+
+    let defines = "(define-private (is-big (x int)) (> x 10))
+         (define-private (is-b (char (string-ascii 1))) (is-eq char \"b\"))
+         (define-private (is-zero (byte (buff 1))) (is-eq byte 0x00))
+         (define-private (is-a (char (string-utf8 1))) (is-eq char u\"a\"))";
+    let tests = [
+        ("(find? is-big (list 1 20 3 40))", "(some 20)"),
+        ("(position? is-big (list 1 20 3 40))", "(some u1)"),
+        ("(find? is-big (list 1 2 3))", "none"),
+        ("(position? is-big (list))", "none"),
+        ("(find? is-b \"abcb\")", "(some \"b\")"),
+        ("(position? is-b \"abcb\")", "(some u1)"),
+        ("(find? is-zero 0x010002)", "(some 0x00)"),
+        ("(position? is-zero 0x010002)", "(some u1)"),
+        ("(find? is-a u\"bca\")", "(some u\"a\")"),
+        ("(position? is-a u\"bcd\")", "none"),
+        ("(find? not (list true false true))", "(some false)"),
+    ];
+    for (program, expectation) in tests.iter() {
+        let program = format!("{} {}", defines, program);
+        assert_eq!(
+            execute(expectation),
+            run(&program).unwrap().unwrap(),
+            "{}",
+            program
+        );
+    }
+
+    // the predicate is not applied past the first match
+    let program = "(define-private (small-or-fail (x int)) (begin (unwrap-panic (if (< x 100) (some true) none)) (> x 1)))
+         (position? small-or-fail (list 1 2 1000))";
+    assert_eq!(
+        Value::some(Value::UInt(1)).unwrap(),
+        run(program).unwrap().unwrap()
+    );
+    let program = "(define-private (small-or-fail (x int)) (begin (unwrap-panic (if (< x 100) (some true) none)) (> x 1)))
+         (position? small-or-fail (list 1 1000 2))";
+    assert!(run(program).is_err());
+}
+
 #[test]
 fn test_some() {
     let tests = [
@@ -77,6 +77,56 @@ const SANITIZATION_READ_BOUND: u64 = 15_000_000;
 ///  clarity depth limit is supported.
 const UNSANITIZED_DEPTH_CHECK: usize = 16;
 
+/// Limits on the input that `Value::deserialize_read_with_limits()` will decode.
+///
+/// The limits that the consensus deserializer enforces are given by `consensus()`, and must
+///  not be changed for any deserialization whose outcome is part of consensus. Other callers
+///  (e.g., RPC handlers decoding untrusted input) may tighten them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeserializationLimits {
+    /// The deepest nesting of values the deserializer will descend into, as measured by
+    ///  `Value::serialization_depth()`. Input that nests any deeper fails with
+    ///  `CheckErrors::TypeSignatureTooDeep` before it is read.
+    pub max_depth: usize,
+    /// The most bytes the deserializer will read. A value whose serialization is any longer
+    ///  fails with an `IOError`.
+    pub max_read_bytes: u64,
+}
+
+impl DeserializationLimits {
+    /// The limits enforced by `Value::deserialize_read()` for the given arguments.
+    pub fn consensus(typed: bool, sanitize: bool) -> DeserializationLimits {
+        DeserializationLimits {
+            max_depth: if sanitize {
+                MAX_TYPE_DEPTH as usize
+            } else {
+                UNSANITIZED_DEPTH_CHECK
+            },
+            max_read_bytes: if sanitize && typed {
+                SANITIZATION_READ_BOUND
+            } else {
+                BOUND_VALUE_SERIALIZATION_BYTES as u64
+            },
+        }
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> DeserializationLimits {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_max_read_bytes(mut self, max_read_bytes: u64) -> DeserializationLimits {
+        self.max_read_bytes = max_read_bytes;
+        self
+    }
+
+    /// Would the serialization of `value` be decoded within these limits?
+    pub fn admits(&self, value: &Value) -> Result<bool, SerializationError> {
+        Ok(value.serialization_depth() <= self.max_depth
+            && u64::from(value.serialized_size()?) <= self.max_read_bytes)
+    }
+}
+
 impl std::fmt::Display for SerializationError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -539,13 +589,37 @@ impl Value {
         expected_type: Option<&TypeSignature>,
         sanitize: bool,
     ) -> Result<(Value, u64), SerializationError> {
-        let bound_value_serialization_bytes = if sanitize && expected_type.is_some() {
-            SANITIZATION_READ_BOUND
-        } else {
-            BOUND_VALUE_SERIALIZATION_BYTES as u64
-        };
-        let mut bound_reader = BoundReader::from_reader(r, bound_value_serialization_bytes);
-        let value = Value::inner_deserialize_read(&mut bound_reader, expected_type, sanitize)?;
+        let limits = DeserializationLimits::consensus(expected_type.is_some(), sanitize);
+        Self::deserialize_read_count_with_limits(r, expected_type, sanitize, &limits)
+    }
+
+    /// Deserialize just like `deserialize_read` but enforce `limits` instead of the
+    ///  consensus limits.
+    pub fn deserialize_read_with_limits<R: Read>(
+        r: &mut R,
+        expected_type: Option<&TypeSignature>,
+        sanitize: bool,
+        limits: &DeserializationLimits,
+    ) -> Result<Value, SerializationError> {
+        Self::deserialize_read_count_with_limits(r, expected_type, sanitize, limits)
+            .map(|(value, _)| value)
+    }
+
+    /// Deserialize just like `deserialize_read_count` but enforce `limits` instead of the
+    ///  consensus limits.
+    pub fn deserialize_read_count_with_limits<R: Read>(
+        r: &mut R,
+        expected_type: Option<&TypeSignature>,
+        sanitize: bool,
+        limits: &DeserializationLimits,
+    ) -> Result<(Value, u64), SerializationError> {
+        let mut bound_reader = BoundReader::from_reader(r, limits.max_read_bytes);
+        let value = Value::inner_deserialize_read(
+            &mut bound_reader,
+            expected_type,
+            sanitize,
+            limits.max_depth,
+        )?;
         let bytes_read = bound_reader.num_read();
         if let Some(expected_type) = expected_type {
             let expect_size = match expected_type.max_serialized_size() {
@@ -577,6 +651,7 @@ impl Value {
         r: &mut R,
         top_expected_type: Option<&TypeSignature>,
         sanitize: bool,
+        max_depth: usize,
     ) -> Result<Value, SerializationError> {
         use super::PrincipalData::*;
         use super::Value::*;
@@ -586,12 +661,7 @@ impl Value {
         }];
 
         while !stack.is_empty() {
-            let depth_check = if sanitize {
-                MAX_TYPE_DEPTH as usize
-            } else {
-                UNSANITIZED_DEPTH_CHECK
-            };
-            if stack.len() > depth_check {
+            if stack.len() > max_depth {
                 return Err(CheckErrors::TypeSignatureTooDeep.into());
             }
 
@@ -1170,6 +1240,30 @@ impl Value {
         Value::try_deserialize_bytes_untyped(&data)
     }
 
+    /// How deeply the deserializer must nest to decode this value: 1 for a value that
+    ///  contains no other values (including an empty list and `none`), plus 1 for each
+    ///  level of non-empty list, tuple, `some`, `ok` or `err` around it.
+    pub fn serialization_depth(&self) -> usize {
+        let children_depth = match self {
+            Value::Optional(OptionalData { data: Some(value) }) => value.serialization_depth(),
+            Value::Response(response) => response.data.serialization_depth(),
+            Value::Sequence(SequenceData::List(list)) => list
+                .data
+                .iter()
+                .map(|item| item.serialization_depth())
+                .max()
+                .unwrap_or(0),
+            Value::Tuple(tuple) => tuple
+                .data_map
+                .values()
+                .map(|value| value.serialization_depth())
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        };
+        1 + children_depth
+    }
+
     pub fn serialized_size(&self) -> Result<u32, SerializationError> {
         let mut counter = WriteCounter { count: 0 };
         self.serialize_write(&mut counter).map_err(|_| {
@@ -1376,14 +1470,18 @@ impl StacksMessageCodec for Value {
 pub mod tests {
     use std::io::Write;
 
+    use proptest::prelude::*;
     use rstest::rstest;
     use rstest_reuse::{self, *};
     use stacks_common::types::StacksEpochId;
 
     use super::super::*;
-    use super::SerializationError;
+    use super::{DeserializationLimits, SerializationError, TypePrefix};
     use crate::vm::database::{ClarityDeserializable, ClaritySerializable, RollbackWrapper};
     use crate::vm::errors::Error;
+    use crate::vm::tests::proptest_utils::{
+        prop_malformed_serialization, prop_mistyped_value, prop_typed_value,
+    };
     use crate::vm::tests::test_clarity_versions;
     use crate::vm::types::TypeSignature::{BoolType, IntType};
     use crate::vm::ClarityVersion;
@@ -2223,4 +2321,142 @@ pub mod tests {
         test_bad_expectation(contract_p2, TypeSignature::BoolType);
         test_bad_expectation(standard_p, TypeSignature::BoolType);
     }
+
+    #[test]
+    fn test_deserialization_limits() {
+        let value = Value::some(Value::okay(Value::Int(1)).unwrap()).unwrap();
+        assert_eq!(value.serialization_depth(), 3);
+        let bytes = value.serialize_to_vec().unwrap();
+
+        let limits = DeserializationLimits::consensus(false, true);
+        assert_eq!(limits.max_depth, MAX_TYPE_DEPTH as usize);
+        assert_eq!(
+            Value::deserialize_read_with_limits(&mut bytes.as_slice(), None, true, &limits)
+                .unwrap(),
+            value
+        );
+
+        let shallow = limits.with_max_depth(2);
+        assert!(!shallow.admits(&value).unwrap());
+        assert_eq!(
+            Value::deserialize_read_with_limits(&mut bytes.as_slice(), None, true, &shallow),
+            Err(CheckErrors::TypeSignatureTooDeep.into())
+        );
+
+        let short = limits.with_max_read_bytes(bytes.len() as u64 - 1);
+        assert!(!short.admits(&value).unwrap());
+        assert!(matches!(
+            Value::deserialize_read_with_limits(&mut bytes.as_slice(), None, true, &short),
+            Err(SerializationError::IOError(_))
+        ));
+
+        // empty lists and `none` need no nesting to decode
+        assert_eq!(Value::none().serialization_depth(), 1);
+        assert_eq!(
+            Value::cons_list_unsanitized(vec![])
+                .unwrap()
+                .serialization_depth(),
+            1
+        );
+    }
+
+    /// Serialization of `true` wrapped in a `some`, `ok`, `err` or single-item list for each
+    ///  entry of `wrappers`
+    fn nested_serialization(wrappers: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        for wrapper in wrappers.iter() {
+            match wrapper % 4 {
+                0 => bytes.push(TypePrefix::OptionalSome as u8),
+                1 => bytes.push(TypePrefix::ResponseOk as u8),
+                2 => bytes.push(TypePrefix::ResponseErr as u8),
+                _ => {
+                    bytes.push(TypePrefix::List as u8);
+                    bytes.extend_from_slice(&1u32.to_be_bytes());
+                }
+            }
+        }
+        bytes.push(TypePrefix::BoolTrue as u8);
+        bytes
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(256))]
+
+        #[test]
+        fn prop_values_round_trip((ty, value) in prop_typed_value(4)) {
+            let bytes = value.serialize_to_vec().unwrap();
+            prop_assert_eq!(value.serialized_size().unwrap() as usize, bytes.len());
+            prop_assert!(DeserializationLimits::consensus(true, true).admits(&value).unwrap());
+            prop_assert!(DeserializationLimits::consensus(false, false).admits(&value).unwrap());
+
+            for sanitize in [true, false] {
+                let typed = Value::try_deserialize_bytes_exact(&bytes, &ty, sanitize).unwrap();
+                prop_assert_eq!(&typed, &value);
+            }
+            let untyped = Value::try_deserialize_bytes_untyped(&bytes).unwrap();
+            prop_assert_eq!(&untyped, &value);
+        }
+
+        #[test]
+        fn prop_deserialization_enforces_limits(
+            (ty, value) in prop_typed_value(4),
+            max_depth in 1usize..8,
+            max_read_bytes in 0u64..512,
+        ) {
+            let bytes = value.serialize_to_vec().unwrap();
+            let limits = DeserializationLimits::consensus(true, true)
+                .with_max_depth(max_depth)
+                .with_max_read_bytes(max_read_bytes);
+            let result =
+                Value::deserialize_read_with_limits(&mut bytes.as_slice(), Some(&ty), true, &limits);
+            if limits.admits(&value).unwrap() {
+                prop_assert_eq!(result.unwrap(), value);
+            } else {
+                prop_assert!(result.is_err());
+            }
+        }
+
+        #[test]
+        fn prop_deep_nesting_fails_gracefully(
+            wrappers in prop::collection::vec(any::<u8>(), 0..10_000),
+        ) {
+            let bytes = nested_serialization(&wrappers);
+            for sanitize in [true, false] {
+                let limits = DeserializationLimits::consensus(false, sanitize);
+                let result = Value::deserialize_read(&mut bytes.as_slice(), None, sanitize);
+                if wrappers.len() < limits.max_depth {
+                    prop_assert_eq!(result.unwrap().serialization_depth(), wrappers.len() + 1);
+                } else {
+                    prop_assert_eq!(result, Err(CheckErrors::TypeSignatureTooDeep.into()));
+                }
+            }
+        }
+
+        #[test]
+        fn prop_mistyped_values_do_not_deserialize((ty, value) in prop_mistyped_value(4)) {
+            let bytes = value.serialize_to_vec().unwrap();
+            for sanitize in [true, false] {
+                let result = Value::try_deserialize_bytes_exact(&bytes, &ty, sanitize);
+                prop_assert!(result.is_err(), "{} deserialized as {}: {:?}", value, ty, result);
+            }
+        }
+
+        #[test]
+        fn prop_malformed_serializations_do_not_deserialize(
+            (ty, corruption, bytes) in prop_malformed_serialization(4),
+        ) {
+            for sanitize in [true, false] {
+                let typed = Value::try_deserialize_bytes_exact(&bytes, &ty, sanitize);
+                prop_assert!(typed.is_err(), "{:?}: {:?}", corruption, typed);
+                let untyped = Value::deserialize_read(&mut bytes.as_slice(), None, sanitize);
+                prop_assert!(untyped.is_err(), "{:?}: {:?}", corruption, untyped);
+            }
+        }
+
+        #[test]
+        fn prop_arbitrary_bytes_do_not_panic(bytes in prop::collection::vec(any::<u8>(), 0..256)) {
+            let _ = Value::try_deserialize_bytes_untyped(&bytes);
+            let _ = Value::deserialize_read(&mut bytes.as_slice(), None, true);
+        }
+    }
 }
@@ -0,0 +1,603 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Adapter layer for driving a `SignerRunLoop` from an async executor (e.g. tokio or
+//! async-std) instead of a dedicated OS thread.
+//!
+//! The runloop is driven by a future on the embedder's executor.  The event receiver blocks on
+//! its HTTP server socket, and each `run_one_pass()` may block on requests to the node, so both
+//! are handed to the executor's blocking pool; the future only waits on channels and timers.
+//! Events, commands and results flow over bounded channels, so a slow runloop applies
+//! backpressure to the event receiver instead of queueing events without limit.
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::mpsc::{channel, RecvTimeoutError, SendError, TryRecvError, TrySendError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crate::error::EventError;
+use crate::events::{EventReceiver, EventStopSignaler, SignerEvent, SignerEventTrait};
+use crate::runloop::SignerRunLoop;
+
+/// A boxed, sendable future, as handed to an `AsyncExecutor`
+pub type BoxedFuture<O> = Pin<Box<dyn Future<Output = O> + Send + 'static>>;
+
+/// The executor facilities the async runloop needs.  Implement this for whatever runtime the
+/// signer is embedded in; libsigner does not depend on any particular one.
+pub trait AsyncExecutor: Clone + Send + Sync + 'static {
+    /// Spawn a future onto the executor
+    fn spawn(&self, fut: BoxedFuture<()>);
+    /// Run a blocking closure somewhere it will not stall the executor's worker threads
+    /// (e.g. `tokio::task::spawn_blocking`)
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send + 'static>);
+    /// Get a future that resolves once `duration` has passed
+    fn sleep(&self, duration: Duration) -> BoxedFuture<()>;
+}
+
+struct ChannelState<T> {
+    queue: VecDeque<T>,
+    capacity: usize,
+    senders: usize,
+    receiver_alive: bool,
+    recv_waker: Option<Waker>,
+    /// The waker of each pending `BoundedSend`, keyed by its id
+    send_wakers: HashMap<u64, Waker>,
+    /// The id to give the next `BoundedSend` that has to wait
+    next_send_id: u64,
+}
+
+struct ChannelShared<T> {
+    state: Mutex<ChannelState<T>>,
+    /// signaled whenever there is room in the queue, or the receiver hangs up
+    not_full: Condvar,
+    /// signaled whenever a value is queued, or the last sender hangs up
+    not_empty: Condvar,
+}
+
+/// Create a bounded channel that can be used from both async and blocking code.
+/// A `capacity` of 0 is treated as 1.
+pub fn bounded_channel<T>(capacity: usize) -> (BoundedSender<T>, BoundedReceiver<T>) {
+    let shared = Arc::new(ChannelShared {
+        state: Mutex::new(ChannelState {
+            queue: VecDeque::new(),
+            capacity: capacity.max(1),
+            senders: 1,
+            receiver_alive: true,
+            recv_waker: None,
+            send_wakers: HashMap::new(),
+            next_send_id: 0,
+        }),
+        not_full: Condvar::new(),
+        not_empty: Condvar::new(),
+    });
+    (
+        BoundedSender {
+            shared: shared.clone(),
+        },
+        BoundedReceiver { shared },
+    )
+}
+
+/// Sending half of a bounded channel
+pub struct BoundedSender<T> {
+    shared: Arc<ChannelShared<T>>,
+}
+
+/// Receiving half of a bounded channel
+pub struct BoundedReceiver<T> {
+    shared: Arc<ChannelShared<T>>,
+}
+
+impl<T> BoundedSender<T> {
+    fn push(shared: &ChannelShared<T>, state: &mut ChannelState<T>, value: T) {
+        state.queue.push_back(value);
+        if let Some(waker) = state.recv_waker.take() {
+            waker.wake();
+        }
+        shared.not_empty.notify_one();
+    }
+
+    /// Send `value` if there is room for it, without waiting
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.shared.state.lock().unwrap();
+        if !state.receiver_alive {
+            return Err(TrySendError::Disconnected(value));
+        }
+        if state.queue.len() >= state.capacity {
+            return Err(TrySendError::Full(value));
+        }
+        Self::push(&self.shared, &mut state, value);
+        Ok(())
+    }
+
+    /// Send `value` if there is room for it, or else in place of the oldest queued value that
+    /// `evictable` accepts, without waiting.  Returns the evicted value, if one had to be.
+    /// Fails with `TrySendError::Full` if the queue is full and no queued value is evictable.
+    pub fn try_send_evicting<F>(&self, value: T, evictable: F) -> Result<Option<T>, TrySendError<T>>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let mut state = self.shared.state.lock().unwrap();
+        if !state.receiver_alive {
+            return Err(TrySendError::Disconnected(value));
+        }
+        let mut evicted = None;
+        if state.queue.len() >= state.capacity {
+            let Some(index) = state.queue.iter().position(evictable) else {
+                return Err(TrySendError::Full(value));
+            };
+            evicted = state.queue.remove(index);
+        }
+        Self::push(&self.shared, &mut state, value);
+        Ok(evicted)
+    }
+
+    /// Send `value`, blocking the calling thread until there is room for it.
+    /// Do not call this from within an async task.
+    pub fn blocking_send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            if !state.receiver_alive {
+                return Err(SendError(value));
+            }
+            if state.queue.len() < state.capacity {
+                Self::push(&self.shared, &mut state, value);
+                return Ok(());
+            }
+            state = self.shared.not_full.wait(state).unwrap();
+        }
+    }
+
+    /// Send `value`, waiting asynchronously until there is room for it
+    pub fn send(&self, value: T) -> BoundedSend<'_, T> {
+        BoundedSend {
+            sender: self,
+            value: Some(value),
+            id: None,
+        }
+    }
+
+    /// Number of wakers registered by pending `send()` futures
+    #[cfg(test)]
+    pub(crate) fn num_send_wakers(&self) -> usize {
+        self.shared.state.lock().unwrap().send_wakers.len()
+    }
+}
+
+impl<T> Clone for BoundedSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        BoundedSender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for BoundedSender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.senders -= 1;
+        if state.senders == 0 {
+            // wake the receiver so it sees the hang-up
+            if let Some(waker) = state.recv_waker.take() {
+                waker.wake();
+            }
+            self.shared.not_empty.notify_all();
+        }
+    }
+}
+
+/// Future returned by `BoundedSender::send()`
+pub struct BoundedSend<'a, T> {
+    sender: &'a BoundedSender<T>,
+    value: Option<T>,
+    /// Identifies this future's waker slot, once it has had to wait
+    id: Option<u64>,
+}
+
+impl<T> Unpin for BoundedSend<'_, T> {}
+
+impl<T> Future for BoundedSend<'_, T> {
+    type Output = Result<(), SendError<T>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let value = self
+            .value
+            .take()
+            .expect("FATAL: polled a completed BoundedSend");
+        let mut state = self.sender.shared.state.lock().unwrap();
+        if !state.receiver_alive {
+            return Poll::Ready(Err(SendError(value)));
+        }
+        if state.queue.len() < state.capacity {
+            BoundedSender::push(&self.sender.shared, &mut state, value);
+            if let Some(id) = self.id.take() {
+                state.send_wakers.remove(&id);
+            }
+            return Poll::Ready(Ok(()));
+        }
+        // keep one waker per future, no matter how often it is polled
+        let id = match self.id {
+            Some(id) => id,
+            None => {
+                let id = state.next_send_id;
+                state.next_send_id = state.next_send_id.wrapping_add(1);
+                id
+            }
+        };
+        match state.send_wakers.get_mut(&id) {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            Some(waker) => *waker = cx.waker().clone(),
+            None => {
+                state.send_wakers.insert(id, cx.waker().clone());
+            }
+        }
+        drop(state);
+        self.id = Some(id);
+        self.value = Some(value);
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for BoundedSend<'_, T> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            if let Ok(mut state) = self.sender.shared.state.lock() {
+                state.send_wakers.remove(&id);
+            }
+        }
+    }
+}
+
+impl<T> BoundedReceiver<T> {
+    /// Dequeue the next value, if any, and let waiting senders know there is room
+    fn pop(shared: &ChannelShared<T>, state: &mut ChannelState<T>) -> Option<T> {
+        let value = state.queue.pop_front()?;
+        for (_, waker) in state.send_wakers.drain() {
+            waker.wake();
+        }
+        shared.not_full.notify_one();
+        Some(value)
+    }
+
+    pub(crate) fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut state = self.shared.state.lock().unwrap();
+        if let Some(value) = Self::pop(&self.shared, &mut state) {
+            return Poll::Ready(Some(value));
+        }
+        if state.senders == 0 {
+            return Poll::Ready(None);
+        }
+        state.recv_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// Receive a value if one is queued, without waiting
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.shared.state.lock().unwrap();
+        if let Some(value) = Self::pop(&self.shared, &mut state) {
+            return Ok(value);
+        }
+        if state.senders == 0 {
+            Err(TryRecvError::Disconnected)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+
+    /// Receive the next value, blocking the calling thread until one is queued.  Returns `None`
+    /// once all senders have hung up and the queue is drained.
+    /// Do not call this from within an async task.
+    pub fn blocking_recv(&self) -> Option<T> {
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            if let Some(value) = Self::pop(&self.shared, &mut state) {
+                return Some(value);
+            }
+            if state.senders == 0 {
+                return None;
+            }
+            state = self.shared.not_empty.wait(state).unwrap();
+        }
+    }
+
+    /// Like `blocking_recv()`, but waits at most `timeout` for a value to be queued
+    pub fn blocking_recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            if let Some(value) = Self::pop(&self.shared, &mut state) {
+                return Ok(value);
+            }
+            if state.senders == 0 {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            state = self
+                .shared
+                .not_empty
+                .wait_timeout(state, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    /// Wait for the next value.  Resolves to `None` once all senders have hung up and the
+    /// queue is drained.
+    pub fn recv(&self) -> BoundedRecv<'_, T> {
+        BoundedRecv { receiver: self }
+    }
+
+    /// Wait up to `timeout` for the next value, using `executor` for the timer
+    pub fn recv_timeout<E: AsyncExecutor>(
+        &self,
+        timeout: Duration,
+        executor: &E,
+    ) -> BoundedRecvTimeout<'_, T> {
+        BoundedRecvTimeout {
+            receiver: self,
+            sleep: executor.sleep(timeout),
+        }
+    }
+}
+
+impl<T> Drop for BoundedReceiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.receiver_alive = false;
+        for (_, waker) in state.send_wakers.drain() {
+            waker.wake();
+        }
+        self.shared.not_full.notify_all();
+    }
+}
+
+/// Future returned by `BoundedReceiver::recv()`
+pub struct BoundedRecv<'a, T> {
+    receiver: &'a BoundedReceiver<T>,
+}
+
+impl<T> Future for BoundedRecv<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Future returned by `BoundedReceiver::recv_timeout()`
+pub struct BoundedRecvTimeout<'a, T> {
+    receiver: &'a BoundedReceiver<T>,
+    sleep: BoxedFuture<()>,
+}
+
+impl<T> Future for BoundedRecvTimeout<'_, T> {
+    type Output = Result<T, RecvTimeoutError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.receiver.poll_recv(cx) {
+            Poll::Ready(Some(value)) => return Poll::Ready(Ok(value)),
+            Poll::Ready(None) => return Poll::Ready(Err(RecvTimeoutError::Disconnected)),
+            Poll::Pending => {}
+        }
+        match self.sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(RecvTimeoutError::Timeout)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Run the blocking closure `f` on `executor`'s blocking pool, and wait for its output.
+/// Resolves to `None` if `f` panicked.
+pub async fn run_blocking<E, F, O>(executor: &E, f: F) -> Option<O>
+where
+    E: AsyncExecutor,
+    F: FnOnce() -> O + Send + 'static,
+    O: Send + 'static,
+{
+    let (output_send, output_recv) = bounded_channel(1);
+    executor.spawn_blocking(Box::new(move || {
+        let _ = output_send.try_send(f());
+    }));
+    output_recv.recv().await
+}
+
+/// The async counterpart to `SignerRunLoop::main_loop()`.  It receives events from
+/// `event_recv`, waiting for up to `signer_loop.get_event_timeout()` units of time, and feeds
+/// them into `run_one_pass()` until it either returns a final state or the event receiver hangs
+/// up.  At this point, it calls `event_stop_signaler.send()` to terminate the receiver.
+///
+/// Each pass runs on `executor`'s blocking pool, since it may block on the node.  The results
+/// it sends are forwarded to `result_send` once the pass is over; results sent from a clone of
+/// the pass's sender after that are dropped.
+pub async fn async_main_loop<R, CMD, T, SL, EVST, E>(
+    mut signer_loop: SL,
+    event_recv: &BoundedReceiver<SignerEvent<T>>,
+    command_recv: &BoundedReceiver<CMD>,
+    result_send: &BoundedSender<R>,
+    mut event_stop_signaler: EVST,
+    executor: &E,
+) -> Option<R>
+where
+    R: Send + 'static,
+    CMD: Send + 'static,
+    T: SignerEventTrait + 'static,
+    SL: SignerRunLoop<R, CMD, T> + Send + 'static,
+    EVST: EventStopSignaler,
+    E: AsyncExecutor,
+{
+    loop {
+        let poll_timeout = signer_loop.get_event_timeout();
+        let next_event_opt = match event_recv.recv_timeout(poll_timeout, executor).await {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => {
+                info!("Event receiver disconnected");
+                return None;
+            }
+        };
+        // Do not block for commands
+        let next_command_opt = command_recv.try_recv().ok();
+        let pass = run_blocking(executor, move || {
+            let (pass_result_send, pass_result_recv) = channel();
+            let final_state =
+                signer_loop.run_one_pass(next_event_opt, next_command_opt, pass_result_send);
+            let results: Vec<R> = pass_result_recv.try_iter().collect();
+            (signer_loop, final_state, results)
+        })
+        .await;
+        let Some((returned_loop, final_state, results)) = pass else {
+            error!("Runloop pass panicked; signaling event-receiver to stop");
+            event_stop_signaler.send();
+            return None;
+        };
+        signer_loop = returned_loop;
+        for result in results {
+            if result_send.send(result).await.is_err() {
+                debug!("Runloop result receiver hung up");
+            }
+        }
+        if let Some(final_state) = final_state {
+            info!("Runloop exit; signaling event-receiver to stop");
+            event_stop_signaler.send();
+            return Some(final_state);
+        }
+    }
+}
+
+/// A signer whose runloop is running on an async executor
+pub struct AsyncRunningSigner<EV: EventReceiver<T>, R, T: SignerEventTrait> {
+    /// receives the runloop's final state once it exits
+    result_recv: BoundedReceiver<Option<R>>,
+    /// kill signal for the event receiver
+    stop_signal: EV::ST,
+}
+
+impl<EV: EventReceiver<T>, R, T: SignerEventTrait> AsyncRunningSigner<EV, R, T> {
+    /// Stop the signer, and get the final state
+    pub async fn stop(mut self) -> Option<R> {
+        self.stop_signal.send();
+        self.join().await
+    }
+
+    /// Wait for the signer's runloop to terminate, and get the final state.
+    /// WARNING: This will wait forever if the event receiver stop signal was never sent/no error
+    /// occurs.
+    pub async fn join(self) -> Option<R> {
+        self.result_recv.recv().await.flatten()
+    }
+}
+
+/// The async counterpart to `Signer`: a runloop and event receiver to be run on an async
+/// executor, taking commands and delivering results over bounded channels.
+pub struct AsyncSigner<CMD, R, SL, EV, T> {
+    /// the runloop itself
+    signer_loop: SL,
+    /// the event receiver to use
+    event_receiver: EV,
+    /// the command receiver to use
+    command_receiver: BoundedReceiver<CMD>,
+    /// the result sender to use
+    result_sender: BoundedSender<R>,
+    /// phantom data for the codec
+    phantom_data: PhantomData<T>,
+}
+
+impl<CMD, R, SL, EV, T> AsyncSigner<CMD, R, SL, EV, T> {
+    /// Create a new async signer with the given runloop and event receiver.
+    pub fn new(
+        runloop: SL,
+        event_receiver: EV,
+        command_receiver: BoundedReceiver<CMD>,
+        result_sender: BoundedSender<R>,
+    ) -> AsyncSigner<CMD, R, SL, EV, T> {
+        AsyncSigner {
+            signer_loop: runloop,
+            event_receiver,
+            command_receiver,
+            result_sender,
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<
+        CMD: Send + 'static,
+        R: Send + 'static,
+        T: SignerEventTrait + 'static,
+        SL: SignerRunLoop<R, CMD, T> + Send + 'static,
+        EV: EventReceiver<T> + Send + 'static,
+    > AsyncSigner<CMD, R, SL, EV, T>
+{
+    /// Like `Signer::spawn()`, but runs the signer on `executor` instead of in threads of its
+    /// own.  The event receiver is run on the executor's blocking pool, and delivers events to
+    /// the runloop over a channel that holds at most `event_capacity` events.
+    ///
+    /// On success, this method consumes the AsyncSigner and returns an AsyncRunningSigner with
+    /// which the caller can shut down the system.
+    pub fn spawn<E: AsyncExecutor>(
+        self,
+        bind_addr: SocketAddr,
+        executor: E,
+        event_capacity: usize,
+    ) -> Result<AsyncRunningSigner<EV, R, T>, EventError> {
+        let AsyncSigner {
+            signer_loop,
+            mut event_receiver,
+            command_receiver,
+            result_sender,
+            ..
+        } = self;
+
+        event_receiver.bind(bind_addr)?;
+        let stop_signaler = event_receiver.get_stop_signaler()?;
+        let ret_stop_signaler = event_receiver.get_stop_signaler()?;
+
+        let (event_send, event_recv) = bounded_channel(event_capacity);
+        executor.spawn_blocking(Box::new(move || {
+            event_receiver
+                .main_loop_with(|_receiver, event| event_send.blocking_send(event).is_ok())
+        }));
+
+        let (final_send, final_recv) = bounded_channel(1);
+        let loop_executor = executor.clone();
+        executor.spawn(Box::pin(async move {
+            let final_state = async_main_loop(
+                signer_loop,
+                &event_recv,
+                &command_receiver,
+                &result_sender,
+                stop_signaler,
+                &loop_executor,
+            )
+            .await;
+            let _ = final_send.try_send(final_state);
+        }));
+
+        Ok(AsyncRunningSigner {
+            result_recv: final_recv,
+            stop_signal: ret_stop_signaler,
+        })
+    }
+}
@@ -74,4 +74,10 @@ pub enum EventError {
     /// Empty chunks event
     #[error("Empty chunks event")]
     EmptyChunksEvent,
+    /// The event receiver already has as many consumers as it accepts
+    #[error("Too many event consumers (at most {0})")]
+    TooManyConsumers(usize),
+    /// The event receiver did not switch listeners in time
+    #[error("Timed out waiting for the event receiver to switch listeners")]
+    RebindTimeout,
 }
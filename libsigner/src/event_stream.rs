@@ -0,0 +1,167 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Consume signer events as an `Iterator` or a futures `Stream`.
+//!
+//! `SignerEventStream` owns the event receiver and the thread it runs on.  Embedders that just
+//! want the events (custom signer frontends, test harnesses) do not need to wire up consumer
+//! channels or stop signalers themselves: the receiver is stopped and its thread joined when the
+//! stream is stopped or dropped.
+
+use std::marker::PhantomData;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::mpsc::RecvTimeoutError;
+use std::task::{Context, Poll};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use futures_core::Stream;
+
+use crate::async_runloop::{bounded_channel, BoundedReceiver};
+use crate::error::EventError;
+use crate::events::{
+    EventReceiver, EventStopSignaler, SignerEvent, SignerEventReceiver, SignerEventTrait,
+};
+use crate::runloop::THREAD_STACK_SIZE;
+
+/// Number of received events a `SignerEventStream` holds before the event receiver stops
+/// accepting new ones, unless a different capacity is given
+pub const DEFAULT_EVENT_STREAM_CAPACITY: usize = 1024;
+
+/// The events received by an event receiver, as an `Iterator` (blocking) or a `Stream` (async).
+/// Both end once the event receiver stops.
+pub struct SignerEventStream<T: SignerEventTrait, EV: EventReceiver<T> = SignerEventReceiver<T>> {
+    /// events from the receiver thread.  Taken when the stream is stopped.
+    events: Option<BoundedReceiver<SignerEvent<T>>>,
+    /// kill signal for the event receiver
+    stop_signal: EV::ST,
+    /// the thread running the event receiver
+    event_join: Option<JoinHandle<()>>,
+    /// the address the event receiver is bound to
+    local_addr: SocketAddr,
+    phantom_data: PhantomData<EV>,
+}
+
+impl<T: SignerEventTrait + 'static> SignerEventStream<T, SignerEventReceiver<T>> {
+    /// Start receiving signer events from the node on `bind_addr`
+    pub fn bind(bind_addr: SocketAddr, is_mainnet: bool) -> Result<Self, EventError> {
+        Self::start(
+            SignerEventReceiver::new(is_mainnet),
+            bind_addr,
+            DEFAULT_EVENT_STREAM_CAPACITY,
+        )
+    }
+}
+
+impl<T: SignerEventTrait + 'static, EV: EventReceiver<T> + Send + 'static>
+    SignerEventStream<T, EV>
+{
+    /// Bind `event_receiver` to `bind_addr` and run it in its own thread.  At most `capacity`
+    /// events are buffered; if the stream's consumer falls that far behind, the receiver stops
+    /// accepting events from the node until it catches up.
+    pub fn start(
+        mut event_receiver: EV,
+        bind_addr: SocketAddr,
+        capacity: usize,
+    ) -> Result<Self, EventError> {
+        let local_addr = event_receiver.bind(bind_addr)?;
+        let mut stop_signal = event_receiver.get_stop_signaler()?;
+
+        let (event_send, event_recv) = bounded_channel(capacity);
+        let event_join = thread::Builder::new()
+            .name("event_stream".to_string())
+            .stack_size(THREAD_STACK_SIZE)
+            .spawn(move || {
+                event_receiver
+                    .main_loop_with(|_receiver, event| event_send.blocking_send(event).is_ok())
+            })
+            .map_err(|e| {
+                error!("EventReceiver failed to start: {:?}", &e);
+                stop_signal.send();
+                EventError::FailedToStart
+            })?;
+
+        Ok(SignerEventStream {
+            events: Some(event_recv),
+            stop_signal,
+            event_join: Some(event_join),
+            local_addr,
+            phantom_data: PhantomData,
+        })
+    }
+}
+
+impl<T: SignerEventTrait, EV: EventReceiver<T>> SignerEventStream<T, EV> {
+    /// The address the event receiver is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Wait at most `timeout` for the next event.
+    /// Fails with `RecvTimeoutError::Disconnected` once the event receiver has stopped.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Result<SignerEvent<T>, RecvTimeoutError> {
+        match self.events.as_ref() {
+            Some(events) => events.blocking_recv_timeout(timeout),
+            None => Err(RecvTimeoutError::Disconnected),
+        }
+    }
+
+    /// Stop the event receiver and wait for its thread to exit.
+    /// Events that were received but not yet consumed are dropped.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        // hang up first, so a receiver blocked on a full channel can see the stop signal
+        if self.events.take().is_none() {
+            return;
+        }
+        self.stop_signal.send();
+        if let Some(event_join) = self.event_join.take() {
+            if event_join.join().is_err() {
+                warn!("Event stream receiver thread panicked");
+            }
+        }
+    }
+}
+
+impl<T: SignerEventTrait, EV: EventReceiver<T>> Iterator for SignerEventStream<T, EV> {
+    type Item = SignerEvent<T>;
+
+    /// Block until the next event arrives, or the event receiver stops
+    fn next(&mut self) -> Option<SignerEvent<T>> {
+        self.events.as_ref()?.blocking_recv()
+    }
+}
+
+impl<T: SignerEventTrait, EV: EventReceiver<T>> Stream for SignerEventStream<T, EV> {
+    type Item = SignerEvent<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<SignerEvent<T>>> {
+        match self.events.as_ref() {
+            Some(events) => events.poll_recv(cx),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+impl<T: SignerEventTrait, EV: EventReceiver<T>> Drop for SignerEventStream<T, EV> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,443 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Worker pool for the signer event receiver.
+//!
+//! An acceptor thread takes the node's requests off the HTTP server in the order they arrive and
+//! numbers them, and a pool of worker threads reads, decodes and answers them concurrently, so a
+//! request with a slow body does not hold up the ones behind it.  An `EventSequencer` then
+//! releases the decoded events so that events which must stay in order still do: those of the
+//! same StackerDB contract, and those posted to the same path otherwise.
+//!
+//! The acceptor can be handed a new HTTP server to move to.  It keeps taking requests off the
+//! old server too, until the old server goes idle or is closed explicitly, so that a node still
+//! posting to the old address does not lose events.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use clarity::vm::types::QualifiedContractIdentifier;
+use tiny_http::{Request as HttpRequest, Server as HttpServer};
+
+use crate::events::{
+    handle_request, recv_request, respond_to_dispatcher, RejectedEventResponses, RetiredListener,
+    SharedListenAddr, SignerEvent, SignerEventTrait,
+};
+use crate::EventError;
+
+/// The path StackerDB events are posted to
+const STACKERDB_CHUNKS_PATH: &str = "/stackerdb_chunks";
+
+/// What the event of a request has to stay in order with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum EventOrderKey {
+    /// A StackerDB event whose contract is not known, because its body has not been decoded
+    StackerDB,
+    /// A StackerDB event from the given contract
+    StackerDBContract(QualifiedContractIdentifier),
+    /// Any other request, by its path
+    Path(String),
+}
+
+impl EventOrderKey {
+    /// The key of a request that has not been decoded yet
+    pub(crate) fn for_path(path: &str) -> Self {
+        if path == STACKERDB_CHUNKS_PATH {
+            Self::StackerDB
+        } else {
+            Self::Path(path.to_string())
+        }
+    }
+
+    /// The key of a request that was posted to `path` and decoded into `result`
+    pub(crate) fn for_result<T: SignerEventTrait>(
+        path: &str,
+        result: &Result<SignerEvent<T>, EventError>,
+    ) -> Self {
+        let first_chunk = match result {
+            Ok(SignerEvent::MinerMessages(messages, _))
+            | Ok(SignerEvent::SignerMessages(_, messages)) => messages.first(),
+            _ => None,
+        };
+        match first_chunk {
+            Some((chunk_id, _)) => Self::StackerDBContract(chunk_id.contract_id.clone()),
+            None => Self::for_path(path),
+        }
+    }
+
+    /// Must an event with this key that arrived earlier be released before one with `later`?
+    fn precedes(&self, later: &Self) -> bool {
+        match (self, later) {
+            (Self::StackerDB, Self::StackerDB | Self::StackerDBContract(_))
+            | (Self::StackerDBContract(_), Self::StackerDB) => true,
+            (Self::StackerDBContract(earlier), Self::StackerDBContract(later)) => earlier == later,
+            (Self::Path(earlier), Self::Path(later)) => earlier == later,
+            _ => false,
+        }
+    }
+}
+
+/// Releases events decoded out of order, once every event that has to come before them has
+/// been released.
+#[derive(Debug)]
+pub(crate) struct EventSequencer<E> {
+    /// Requests accepted but not decoded yet, by sequence number
+    pending: BTreeMap<u64, EventOrderKey>,
+    /// Decoded events not released yet, by sequence number
+    decoded: BTreeMap<u64, (EventOrderKey, E)>,
+}
+
+impl<E> EventSequencer<E> {
+    pub(crate) fn new() -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            decoded: BTreeMap::new(),
+        }
+    }
+
+    /// Note that request `seq` was accepted and will be decoded
+    pub(crate) fn accept(&mut self, seq: u64, key: EventOrderKey) {
+        self.pending.insert(seq, key);
+    }
+
+    /// Note that request `seq` was decoded into `event`
+    pub(crate) fn decode(&mut self, seq: u64, key: EventOrderKey, event: E) {
+        self.pending.remove(&seq);
+        self.decoded.insert(seq, (key, event));
+    }
+
+    /// Take the earliest event that nothing has to be released before
+    pub(crate) fn pop_ready(&mut self) -> Option<E> {
+        let ready_seq = self
+            .decoded
+            .iter()
+            .enumerate()
+            .find(|(i, (seq, (key, _)))| {
+                let blocked_by_pending = self
+                    .pending
+                    .range(..**seq)
+                    .any(|(_, pending_key)| pending_key.precedes(key));
+                let blocked_by_decoded = self
+                    .decoded
+                    .values()
+                    .take(*i)
+                    .any(|(decoded_key, _)| decoded_key.precedes(key));
+                !blocked_by_pending && !blocked_by_decoded
+            })
+            .map(|(_, (seq, _))| *seq)?;
+        self.decoded.remove(&ready_seq).map(|(_, event)| event)
+    }
+}
+
+/// What the pool's threads tell the event receiver
+pub(crate) enum PoolMessage<T: SignerEventTrait> {
+    /// A request was accepted
+    Accepted(u64, EventOrderKey),
+    /// A request was decoded
+    Decoded(u64, EventOrderKey, Result<SignerEvent<T>, EventError>),
+}
+
+/// The HTTP servers the acceptor takes requests off
+pub(crate) struct AcceptorListeners {
+    /// The server the event receiver listens on
+    pub(crate) current: HttpServer,
+    /// The server the event receiver moved away from, while it is still served
+    pub(crate) retired: Option<RetiredListener>,
+    /// The retired server's address, shared with listener rebinders
+    pub(crate) retired_addr: SharedListenAddr,
+    /// How long the retired server is served after the last request reached it
+    pub(crate) retired_idle: Duration,
+}
+
+/// What the event receiver asks of the acceptor
+enum AcceptorCommand {
+    /// Move to `server`, retiring the current server, which listens on `old_addr`
+    Replace {
+        server: HttpServer,
+        old_addr: SocketAddr,
+    },
+    /// Close the retired server
+    CloseRetired,
+}
+
+/// The acceptor and worker threads of an event receiver
+pub(crate) struct EventWorkerPool<T: SignerEventTrait> {
+    /// What the acceptor and workers report, in the order they report it
+    pub(crate) messages: Receiver<PoolMessage<T>>,
+    pub(crate) sequencer: EventSequencer<Result<SignerEvent<T>, EventError>>,
+    /// Requests for the acceptor
+    commands: Sender<AcceptorCommand>,
+}
+
+impl<T: SignerEventTrait> EventWorkerPool<T> {
+    /// Start taking requests off `listeners` with `num_workers` workers.  The threads exit once
+    /// `stop_signal` is set and the current server is sent a request, as `SignerStopSignaler`
+    /// does.
+    pub(crate) fn start(
+        listeners: AcceptorListeners,
+        num_workers: usize,
+        local_addr: Option<SocketAddr>,
+        rejected_event_responses: RejectedEventResponses,
+        stop_signal: Arc<AtomicBool>,
+    ) -> Result<Self, EventError> {
+        let (messages_send, messages) = channel();
+        let (jobs_send, jobs_recv) = channel::<(u64, String, HttpRequest)>();
+        let (commands, commands_recv) = channel();
+        let jobs_recv = Arc::new(Mutex::new(jobs_recv));
+
+        for i in 0..num_workers {
+            let jobs_recv = jobs_recv.clone();
+            let messages_send = messages_send.clone();
+            thread::Builder::new()
+                .name(format!("signer_event_worker_{i}"))
+                .spawn(move || {
+                    Self::run_worker(
+                        jobs_recv,
+                        messages_send,
+                        local_addr,
+                        rejected_event_responses,
+                    )
+                })
+                .map_err(|e| {
+                    error!("Failed to start event worker: {e:?}");
+                    EventError::FailedToStart
+                })?;
+        }
+        thread::Builder::new()
+            .name("signer_event_acceptor".to_string())
+            .spawn(move || {
+                Self::run_acceptor(
+                    listeners,
+                    jobs_send,
+                    messages_send,
+                    commands_recv,
+                    stop_signal,
+                )
+            })
+            .map_err(|e| {
+                error!("Failed to start event acceptor: {e:?}");
+                EventError::FailedToStart
+            })?;
+
+        Ok(Self {
+            messages,
+            sequencer: EventSequencer::new(),
+            commands,
+        })
+    }
+
+    /// Have the acceptor move to `server` once it takes its next request off the current one,
+    /// which listens on `old_addr`.  Fails if the acceptor has exited.
+    pub(crate) fn replace_server(
+        &self,
+        server: HttpServer,
+        old_addr: SocketAddr,
+    ) -> Result<(), EventError> {
+        self.commands
+            .send(AcceptorCommand::Replace { server, old_addr })
+            .map_err(|_| EventError::Terminated)
+    }
+
+    /// Have the acceptor close the retired server once it takes its next request.  Fails if
+    /// the acceptor has exited.
+    pub(crate) fn close_retired(&self) -> Result<(), EventError> {
+        self.commands
+            .send(AcceptorCommand::CloseRetired)
+            .map_err(|_| EventError::Terminated)
+    }
+
+    /// Number the requests in the order they arrive, and hand them to the workers.  Carries
+    /// out the requests sent on `commands` as they come.
+    fn run_acceptor(
+        mut listeners: AcceptorListeners,
+        jobs: Sender<(u64, String, HttpRequest)>,
+        messages: Sender<PoolMessage<T>>,
+        commands: Receiver<AcceptorCommand>,
+        stop_signal: Arc<AtomicBool>,
+    ) {
+        let mut next_seq = 0;
+        loop {
+            let request = match recv_request(
+                &listeners.current,
+                &mut listeners.retired,
+                listeners.retired_idle,
+            ) {
+                Ok(request) => request,
+                Err(e) => {
+                    error!("Event acceptor failed to receive request: {e:?}");
+                    break;
+                }
+            };
+            if stop_signal.load(Ordering::SeqCst) {
+                // the stop signaler's wake-up request
+                respond_to_dispatcher(request, 200);
+                break;
+            }
+            if !Self::dispatch(request, &mut next_seq, &jobs, &messages) {
+                break;
+            }
+            while let Ok(command) = commands.try_recv() {
+                match command {
+                    AcceptorCommand::Replace { server, old_addr } => {
+                        let old_server = std::mem::replace(&mut listeners.current, server);
+                        let retired = RetiredListener::new(
+                            old_server,
+                            old_addr,
+                            listeners.retired_addr.clone(),
+                        );
+                        if let Some(previous) = listeners.retired.replace(retired) {
+                            previous.close("replaced");
+                        }
+                        debug!("Event acceptor moved to a new listener");
+                    }
+                    AcceptorCommand::CloseRetired => {
+                        if let Some(retired) = listeners.retired.take() {
+                            retired.close("requested");
+                        }
+                    }
+                }
+            }
+            // the stop signaler may have woken up a listener the acceptor no longer waits on
+            if stop_signal.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+        debug!("Event acceptor exit");
+    }
+
+    /// Number `request` and hand it to the workers.
+    /// Returns false if the workers or the event receiver have exited.
+    fn dispatch(
+        request: HttpRequest,
+        next_seq: &mut u64,
+        jobs: &Sender<(u64, String, HttpRequest)>,
+        messages: &Sender<PoolMessage<T>>,
+    ) -> bool {
+        let seq = *next_seq;
+        *next_seq += 1;
+        let path = request.url().to_string();
+        messages
+            .send(PoolMessage::Accepted(seq, EventOrderKey::for_path(&path)))
+            .is_ok()
+            && jobs.send((seq, path, request)).is_ok()
+    }
+
+    /// Read, decode and answer requests until the acceptor exits
+    fn run_worker(
+        jobs: Arc<Mutex<Receiver<(u64, String, HttpRequest)>>>,
+        messages: Sender<PoolMessage<T>>,
+        local_addr: Option<SocketAddr>,
+        rejected_event_responses: RejectedEventResponses,
+    ) {
+        loop {
+            let job = match jobs.lock() {
+                Ok(jobs) => jobs.recv(),
+                Err(_) => break,
+            };
+            let Ok((seq, path, request)) = job else {
+                break;
+            };
+            let result = handle_request(local_addr, &rejected_event_responses, request);
+            let key = EventOrderKey::for_result(&path, &result);
+            if messages
+                .send(PoolMessage::Decoded(seq, key, result))
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
+
+    /// Wait for the next event that can be released.
+    /// Fails with `EventError::Terminated` once the acceptor and workers have exited.
+    pub(crate) fn next_event(&mut self) -> Result<SignerEvent<T>, EventError> {
+        loop {
+            if let Some(result) = self.sequencer.pop_ready() {
+                return result;
+            }
+            match self.messages.recv() {
+                Ok(PoolMessage::Accepted(seq, key)) => self.sequencer.accept(seq, key),
+                Ok(PoolMessage::Decoded(seq, key, result)) => {
+                    self.sequencer.decode(seq, key, result)
+                }
+                Err(_) => return Err(EventError::Terminated),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockstack_lib::util_lib::boot::boot_code_id;
+
+    use super::*;
+
+    fn contract(name: &str) -> EventOrderKey {
+        EventOrderKey::StackerDBContract(boot_code_id(name, false))
+    }
+
+    #[test]
+    fn sequencer_keeps_streams_in_order() {
+        let mut sequencer = EventSequencer::new();
+        sequencer.accept(0, EventOrderKey::StackerDB);
+        sequencer.accept(1, EventOrderKey::for_path("/new_burn_block"));
+        sequencer.accept(2, EventOrderKey::StackerDB);
+        sequencer.accept(3, EventOrderKey::StackerDB);
+
+        // a burn block is not held up by a StackerDB event that is still being read
+        sequencer.decode(1, EventOrderKey::for_path("/new_burn_block"), 1);
+        assert_eq!(sequencer.pop_ready(), Some(1));
+
+        // but a StackerDB event is, since it may be from the same contract
+        sequencer.decode(2, contract("signers-0-1"), 2);
+        assert_eq!(sequencer.pop_ready(), None);
+
+        // once the earlier event is decoded, events of other contracts are released
+        sequencer.decode(0, contract("signers-0-0"), 0);
+        sequencer.decode(3, contract("signers-0-0"), 3);
+        assert_eq!(sequencer.pop_ready(), Some(0));
+        assert_eq!(sequencer.pop_ready(), Some(2));
+        assert_eq!(sequencer.pop_ready(), Some(3));
+        assert_eq!(sequencer.pop_ready(), None);
+    }
+
+    #[test]
+    fn sequencer_keeps_paths_in_order() {
+        let mut sequencer = EventSequencer::new();
+        sequencer.accept(0, EventOrderKey::for_path("/new_burn_block"));
+        sequencer.accept(1, EventOrderKey::StackerDB);
+        sequencer.accept(2, EventOrderKey::for_path("/new_burn_block"));
+        sequencer.accept(3, EventOrderKey::for_path("/proposal_response"));
+
+        // events posted to other paths overtake a burn block that is still being read
+        sequencer.decode(2, EventOrderKey::for_path("/new_burn_block"), 2);
+        sequencer.decode(1, contract("signers-0-0"), 1);
+        sequencer.decode(3, EventOrderKey::for_path("/proposal_response"), 3);
+        assert_eq!(sequencer.pop_ready(), Some(1));
+        assert_eq!(sequencer.pop_ready(), Some(3));
+        assert_eq!(sequencer.pop_ready(), None);
+
+        // while the later burn block waits for it
+        sequencer.decode(0, EventOrderKey::for_path("/new_burn_block"), 0);
+        assert_eq!(sequencer.pop_ready(), Some(0));
+        assert_eq!(sequencer.pop_ready(), Some(2));
+        assert_eq!(sequencer.pop_ready(), None);
+    }
+}
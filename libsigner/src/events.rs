@@ -15,11 +15,12 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::fmt::Debug;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::Sender;
-use std::sync::Arc;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use blockstack_lib::chainstate::nakamoto::NakamotoBlock;
 use blockstack_lib::chainstate::stacks::boot::{MINERS_NAME, SIGNERS_NAME};
@@ -32,6 +33,7 @@ use blockstack_lib::net::stackerdb::MINER_SLOT_COUNT;
 use blockstack_lib::util_lib::boot::boot_code_id;
 use clarity::vm::types::serialization::SerializationError;
 use clarity::vm::types::QualifiedContractIdentifier;
+use libstackerdb::StackerDBChunkData;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use stacks_common::codec::{
@@ -51,16 +53,18 @@ use wsts::net::{
 };
 use wsts::state_machine::signer;
 
+use crate::async_runloop::BoundedSender;
+use crate::event_workers::{AcceptorListeners, EventWorkerPool};
 use crate::http::{decode_http_body, decode_http_request};
 use crate::EventError;
 
 /// Define the trait for the event processor
-pub trait SignerEventTrait<T: StacksMessageCodec + Clone + Debug + Send = Self>:
-    StacksMessageCodec + Clone + Debug + Send
+pub trait SignerEventTrait<T: StacksMessageCodec + Clone + Debug + Send + 'static = Self>:
+    StacksMessageCodec + Clone + Debug + Send + 'static
 {
 }
 
-impl<T: StacksMessageCodec + Clone + Debug + Send> SignerEventTrait for T {}
+impl<T: StacksMessageCodec + Clone + Debug + Send + 'static> SignerEventTrait for T {}
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 /// BlockProposal sent to signers
@@ -93,24 +97,210 @@ impl StacksMessageCodec for BlockProposal {
     }
 }
 
+/// Identifies the StackerDB chunk that a message was read from.
+/// A chunk that the node delivers more than once has the same identifier each time.
+/// The slot and version alone are not enough: a slot's version is reset whenever the slot is
+/// given to a new signer (e.g. the `.miners` slots, at every tenure), so different chunks can
+/// be written to the same slot at the same version.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StackerDBChunkId {
+    /// The StackerDB contract the chunk was written to
+    pub contract_id: QualifiedContractIdentifier,
+    /// The slot the chunk was written to
+    pub slot_id: u32,
+    /// The version of the slot
+    pub slot_version: u32,
+    /// The hash of the chunk's data
+    pub data_hash: Sha512Trunc256Sum,
+}
+
+impl StackerDBChunkId {
+    /// Identify a chunk of the given StackerDB contract
+    pub fn new(contract_id: &QualifiedContractIdentifier, chunk: &StackerDBChunkData) -> Self {
+        Self {
+            contract_id: contract_id.clone(),
+            slot_id: chunk.slot_id,
+            slot_version: chunk.slot_version,
+            data_hash: chunk.data_hash(),
+        }
+    }
+}
+
+impl std::fmt::Display for StackerDBChunkId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}/{}",
+            &self.contract_id, self.slot_id, self.slot_version, &self.data_hash
+        )
+    }
+}
+
 /// Event enum for newly-arrived signer subscribed events
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum SignerEvent<T: SignerEventTrait> {
     /// A miner sent a message over .miners
-    /// The `Vec<T>` will contain any signer messages made by the miner.
+    /// The `Vec` will contain any signer messages made by the miner, along with
+    /// the chunk each was read from.
     /// The `StacksPublicKey` is the message sender's public key.
-    MinerMessages(Vec<T>, StacksPublicKey),
-    /// The signer messages for other signers and miners to observe
+    MinerMessages(Vec<(StackerDBChunkId, T)>, StacksPublicKey),
+    /// The signer messages for other signers and miners to observe, along with
+    /// the chunk each was read from.
     /// The u32 is the signer set to which the message belongs (either 0 or 1)
-    SignerMessages(u32, Vec<T>),
+    SignerMessages(u32, Vec<(StackerDBChunkId, T)>),
     /// A new block proposal validation response from the node
     BlockValidationResponse(BlockValidateResponse),
     /// Status endpoint request
     StatusCheck,
-    /// A new burn block event was received with the given burnchain block height
-    NewBurnBlock(u64),
+    /// A new burn block event was received
+    NewBurnBlock {
+        /// The burnchain block height
+        burn_height: u64,
+        /// The timestamp in the burnchain block's header, in seconds since the epoch.
+        /// None if the node did not report it.
+        burn_header_timestamp: Option<u64>,
+    },
+}
+
+/// The kinds of `SignerEvent`, without their contents
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SignerEventType {
+    /// `SignerEvent::MinerMessages`
+    MinerMessages,
+    /// `SignerEvent::SignerMessages`
+    SignerMessages,
+    /// `SignerEvent::BlockValidationResponse`
+    BlockValidationResponse,
+    /// `SignerEvent::StatusCheck`
+    StatusCheck,
+    /// `SignerEvent::NewBurnBlock`
+    NewBurnBlock,
+}
+
+impl<T: SignerEventTrait> SignerEvent<T> {
+    /// The kind of this event
+    pub fn event_type(&self) -> SignerEventType {
+        match self {
+            SignerEvent::MinerMessages(..) => SignerEventType::MinerMessages,
+            SignerEvent::SignerMessages(..) => SignerEventType::SignerMessages,
+            SignerEvent::BlockValidationResponse(..) => SignerEventType::BlockValidationResponse,
+            SignerEvent::StatusCheck => SignerEventType::StatusCheck,
+            SignerEvent::NewBurnBlock { .. } => SignerEventType::NewBurnBlock,
+        }
+    }
 }
 
+/// What the event receiver does with an event for a bounded consumer whose queue is full
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Wait until the consumer makes room.  No consumer gets any further events meanwhile.
+    Block,
+    /// Discard the oldest event in the consumer's queue
+    DropOldest,
+    /// Discard the new event if it is of one of these types, or else the oldest queued event
+    /// of one of these types.  If there is none, wait as with `Block`.
+    DropEventTypes(Vec<SignerEventType>),
+}
+
+/// A downstream consumer of the event receiver's events
+enum EventConsumer<T: SignerEventTrait> {
+    /// Added with `add_consumer()`.  Events queue without limit.
+    Unbounded(Sender<SignerEvent<T>>),
+    /// Added with `add_bounded_consumer()`
+    Bounded {
+        sender: BoundedSender<SignerEvent<T>>,
+        policy: BackpressurePolicy,
+        /// Number of events discarded so far because the consumer's queue was full
+        dropped: u64,
+    },
+}
+
+impl<T: SignerEventTrait> EventConsumer<T> {
+    /// Hand `ev` to the consumer, applying its backpressure policy if its queue is full.
+    /// `index` identifies the consumer in logs.
+    /// Returns false if the consumer hung up.
+    fn deliver(&mut self, index: usize, ev: SignerEvent<T>) -> bool {
+        let (sender, policy, dropped) = match self {
+            EventConsumer::Unbounded(sender) => return sender.send(ev).is_ok(),
+            EventConsumer::Bounded {
+                sender,
+                policy,
+                dropped,
+            } => (sender, policy, dropped),
+        };
+        let ev = match sender.try_send(ev) {
+            Ok(()) => return true,
+            Err(TrySendError::Disconnected(_)) => return false,
+            Err(TrySendError::Full(ev)) => ev,
+        };
+        let event_type = ev.event_type();
+        let ev = match policy {
+            BackpressurePolicy::Block => ev,
+            BackpressurePolicy::DropOldest => match sender.try_send_evicting(ev, |_| true) {
+                Ok(evicted) => {
+                    Self::record_dropped(index, dropped, evicted.map(|ev| ev.event_type()));
+                    return true;
+                }
+                Err(TrySendError::Disconnected(_)) => return false,
+                Err(TrySendError::Full(ev)) => ev,
+            },
+            BackpressurePolicy::DropEventTypes(droppable) => {
+                if droppable.contains(&event_type) {
+                    Self::record_dropped(index, dropped, Some(event_type));
+                    return true;
+                }
+                match sender
+                    .try_send_evicting(ev, |queued| droppable.contains(&queued.event_type()))
+                {
+                    Ok(evicted) => {
+                        Self::record_dropped(index, dropped, evicted.map(|ev| ev.event_type()));
+                        return true;
+                    }
+                    Err(TrySendError::Disconnected(_)) => return false,
+                    Err(TrySendError::Full(ev)) => ev,
+                }
+            }
+        };
+        debug!("Event consumer #{index} is full; waiting for it to make room";
+            "event_type" => ?event_type,
+        );
+        sender.blocking_send(ev).is_ok()
+    }
+
+    /// Count an event of type `event_type` that was discarded for want of room, warning about
+    /// the first discard and then at every power of two, so a stuck consumer does not flood the
+    /// logs
+    fn record_dropped(index: usize, dropped: &mut u64, event_type: Option<SignerEventType>) {
+        let Some(event_type) = event_type else {
+            return;
+        };
+        *dropped = dropped.saturating_add(1);
+        if dropped.is_power_of_two() {
+            warn!("Event consumer #{index} is not keeping up; discarding events";
+                "event_type" => ?event_type,
+                "total_dropped" => *dropped,
+            );
+        } else {
+            debug!("Event consumer #{index} is full; discarded event";
+                "event_type" => ?event_type,
+            );
+        }
+    }
+}
+
+/// How long a listener the event receiver moved away from is still served after the last
+/// request reached it, unless it is closed sooner with `ListenerRebinder::close_retired`
+pub const DEFAULT_RETIRED_LISTENER_IDLE: Duration = Duration::from_secs(30);
+
+/// How long to wait for a request on the current listener before checking the retired one
+const RETIRED_LISTENER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a `ListenerRebinder` waits for the event receiver to switch listeners
+const LISTENER_REBIND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The path POSTed to in order to wake the event receiver up for a rebind
+const REBIND_PATH: &str = "/rebind";
+
 /// Trait to implement a stop-signaler for the event receiver thread.
 /// The caller calls `send()` and the event receiver loop (which lives in a separate thread) will
 /// terminate.
@@ -141,7 +331,20 @@ pub trait EventReceiver<T: SignerEventTrait> {
 
     /// Main loop for the receiver.
     /// Typically, this is started in a separate thread.
-    fn main_loop(&mut self) {
+    fn main_loop(&mut self)
+    where
+        Self: Sized,
+    {
+        self.main_loop_with(|receiver, event| receiver.forward_event(event))
+    }
+
+    /// Main loop for the receiver, which hands each event to `forward` instead of to the
+    /// registered consumers.  The loop exits once `forward` returns false.
+    fn main_loop_with<F>(&mut self, mut forward: F)
+    where
+        Self: Sized,
+        F: FnMut(&mut Self, SignerEvent<T>) -> bool,
+    {
         loop {
             if self.is_stopped() {
                 info!("Event receiver stopped");
@@ -163,7 +366,7 @@ pub trait EventReceiver<T: SignerEventTrait> {
                     continue;
                 }
             };
-            if !self.forward_event(next_event) {
+            if !forward(self, next_event) {
                 info!("Failed to forward event");
                 break;
             }
@@ -172,33 +375,438 @@ pub trait EventReceiver<T: SignerEventTrait> {
     }
 }
 
+/// The HTTP status codes with which the event receiver answers events it rejects, so that the
+/// node can tell that delivery failed.  Events that are accepted, or that the receiver has no
+/// use for, are always answered with 200.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RejectedEventResponses {
+    /// Status for events whose body cannot be read or decoded
+    pub malformed: u16,
+    /// Status for StackerDB events from a contract that signers do not follow
+    pub unrecognized_contract: u16,
+}
+
+impl Default for RejectedEventResponses {
+    fn default() -> Self {
+        Self {
+            malformed: 400,
+            unrecognized_contract: 422,
+        }
+    }
+}
+
+impl RejectedEventResponses {
+    /// Answer every event with 200, as if it had been accepted.  Older nodes retry an event
+    /// until it is acknowledged, so this keeps them from retrying rejected events forever.
+    pub const ACKNOWLEDGE_ALL: Self = Self {
+        malformed: 200,
+        unrecognized_contract: 200,
+    };
+
+    /// The status with which to answer an event that was rejected with `error`
+    pub fn status_for(&self, error: &EventError) -> u16 {
+        match error {
+            EventError::Deserialize(_) | EventError::MalformedRequest(_) => self.malformed,
+            EventError::UnrecognizedStackerDBContract(_) => self.unrecognized_contract,
+            _ => 200,
+        }
+    }
+}
+
+/// The address the event receiver listens on, shared with the handles that have to reach it
+/// from other threads
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SharedListenAddr(Arc<Mutex<Option<SocketAddr>>>);
+
+impl SharedListenAddr {
+    fn new(addr: SocketAddr) -> Self {
+        Self(Arc::new(Mutex::new(Some(addr))))
+    }
+
+    fn get(&self) -> Option<SocketAddr> {
+        *self.0.lock().expect("listen address poisoned")
+    }
+
+    fn set(&self, addr: SocketAddr) {
+        *self.0.lock().expect("listen address poisoned") = Some(addr);
+    }
+
+    fn clear(&self) {
+        *self.0.lock().expect("listen address poisoned") = None;
+    }
+}
+
+/// A request from a `ListenerRebinder` to the event receiver
+pub(crate) enum ListenerCommand {
+    /// Move to another listener
+    Rebind {
+        /// The address to listen on instead
+        addr: SocketAddr,
+        /// Where to send the outcome
+        reply: Sender<Result<SocketAddr, EventError>>,
+    },
+    /// Close the retired listener
+    CloseRetired {
+        /// Where to send the address of the listener that was closed, if any
+        reply: Sender<Option<SocketAddr>>,
+    },
+}
+
+/// A listener the event receiver has moved away from.  It is still served alongside the
+/// current one, so that a node that still posts events to the old address does not lose them
+/// while its configuration catches up, until no request has reached it for a while or it is
+/// closed explicitly.
+pub(crate) struct RetiredListener {
+    server: HttpServer,
+    addr: SocketAddr,
+    /// When a request last reached the listener, or when it was retired
+    last_request: Instant,
+    /// The listener's address, shared with listener rebinders
+    shared_addr: SharedListenAddr,
+}
+
+impl RetiredListener {
+    pub(crate) fn new(server: HttpServer, addr: SocketAddr, shared_addr: SharedListenAddr) -> Self {
+        shared_addr.set(addr);
+        Self {
+            server,
+            addr,
+            last_request: Instant::now(),
+            shared_addr,
+        }
+    }
+
+    /// Stop serving the listener.  Returns its address.
+    pub(crate) fn close(self, reason: &str) -> SocketAddr {
+        self.shared_addr.clear();
+        info!("Closed the old event listener"; "addr" => %self.addr, "reason" => reason);
+        self.addr
+    }
+}
+
+/// Wait for the next request on `current`, or on `retired` while it is open.  The retired
+/// listener is closed once no request has reached it for `retired_idle`.
+pub(crate) fn recv_request(
+    current: &HttpServer,
+    retired: &mut Option<RetiredListener>,
+    retired_idle: Duration,
+) -> io::Result<HttpRequest> {
+    loop {
+        let Some(listener) = retired.as_mut() else {
+            return current.recv();
+        };
+        if listener.last_request.elapsed() >= retired_idle {
+            if let Some(listener) = retired.take() {
+                listener.close("idle");
+            }
+            continue;
+        }
+        match listener.server.try_recv() {
+            Ok(Some(request)) => {
+                listener.last_request = Instant::now();
+                return Ok(request);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!("Failed to receive a request on the old event listener: {e:?}");
+                if let Some(listener) = retired.take() {
+                    listener.close("failed");
+                }
+                continue;
+            }
+        }
+        if let Some(request) = current.recv_timeout(RETIRED_LISTENER_POLL_INTERVAL)? {
+            return Ok(request);
+        }
+    }
+}
+
+/// A handle with which to move a running event receiver to another address, e.g. when the
+/// signer host's network configuration changes.  The new listener is bound before the old one
+/// is retired, and the retired listener is still served until it has been idle for a while
+/// (see `SignerEventReceiver::with_retired_listener_idle`) or until `close_retired` is called.
+#[derive(Clone)]
+pub struct ListenerRebinder {
+    requests: Sender<ListenerCommand>,
+    local_addr: SharedListenAddr,
+    retired_addr: SharedListenAddr,
+}
+
+impl ListenerRebinder {
+    /// The address the event receiver listens on, if it is bound
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr.get()
+    }
+
+    /// The address of the listener the event receiver moved away from, while it is still
+    /// served
+    pub fn retired_addr(&self) -> Option<SocketAddr> {
+        self.retired_addr.get()
+    }
+
+    /// Make the event receiver listen on `addr` instead, and wait until it does.  Returns the
+    /// address it now listens on.  If `addr` cannot be bound, the event receiver keeps
+    /// listening where it was.
+    pub fn rebind(&self, addr: SocketAddr) -> Result<SocketAddr, EventError> {
+        let old_addr = self.local_addr.get().ok_or(EventError::NotBound)?;
+        let (reply_send, reply_recv) = channel();
+        self.send(
+            old_addr,
+            ListenerCommand::Rebind {
+                addr,
+                reply: reply_send,
+            },
+        )?;
+        match reply_recv.recv_timeout(LISTENER_REBIND_TIMEOUT) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => Err(EventError::RebindTimeout),
+            Err(RecvTimeoutError::Disconnected) => Err(EventError::Terminated),
+        }
+    }
+
+    /// Stop serving the listener the event receiver moved away from, without waiting for it
+    /// to go idle.  Returns its address, or None if there was none.
+    pub fn close_retired(&self) -> Result<Option<SocketAddr>, EventError> {
+        let local_addr = self.local_addr.get().ok_or(EventError::NotBound)?;
+        let (reply_send, reply_recv) = channel();
+        self.send(
+            local_addr,
+            ListenerCommand::CloseRetired { reply: reply_send },
+        )?;
+        match reply_recv.recv_timeout(LISTENER_REBIND_TIMEOUT) {
+            Ok(closed) => Ok(closed),
+            Err(RecvTimeoutError::Timeout) => Err(EventError::RebindTimeout),
+            Err(RecvTimeoutError::Disconnected) => Err(EventError::Terminated),
+        }
+    }
+
+    /// Hand `command` to the event receiver, and wake it up on its listener at `local_addr`
+    fn send(&self, local_addr: SocketAddr, command: ListenerCommand) -> Result<(), EventError> {
+        self.requests
+            .send(command)
+            .map_err(|_| EventError::Terminated)?;
+        // the event receiver may be waiting for a request on its listener
+        if let Err(e) = wake_listener(local_addr, REBIND_PATH, "rebind") {
+            warn!("Failed to wake up the event receiver for a listener command: {e:?}");
+        }
+        Ok(())
+    }
+}
+
+/// POST `body` to `path` on the event receiver listening on `addr`, so that it wakes up and
+/// checks for signals from other threads
+fn wake_listener(addr: SocketAddr, path: &str, body: &str) -> io::Result<()> {
+    let mut stream = TcpStream::connect(addr)?;
+    let req = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\nContent-Type: text/plain\r\n\r\n{}",
+        path,
+        addr,
+        body.len(),
+        body
+    );
+    stream.write_all(req.as_bytes())
+}
+
 /// Event receiver for Signer events
 pub struct SignerEventReceiver<T: SignerEventTrait> {
     /// Address we bind to
     local_addr: Option<SocketAddr>,
+    /// The same address, shared with stop signalers and listener rebinders
+    shared_local_addr: SharedListenAddr,
+    /// Requests from listener rebinders
+    commands_send: Sender<ListenerCommand>,
+    commands_recv: Receiver<ListenerCommand>,
     /// server socket that listens for HTTP POSTs from the node
     http_server: Option<HttpServer>,
-    /// channel into which to write newly-discovered data
-    out_channels: Vec<Sender<SignerEvent<T>>>,
+    /// The listener the receiver moved away from, while it is still served.  Held by the
+    /// worker pool instead once it is started.
+    retired_listener: Option<RetiredListener>,
+    /// The retired listener's address, shared with listener rebinders
+    retired_addr: SharedListenAddr,
+    /// How long the retired listener is served after the last request reached it
+    retired_listener_idle: Duration,
+    /// consumers to hand newly-discovered data to
+    consumers: Vec<EventConsumer<T>>,
+    /// Most consumers that can be added.  None if there is no limit.
+    max_consumers: Option<usize>,
     /// inter-thread stop variable -- if set to true, then the `main_loop` will exit
     stop_signal: Arc<AtomicBool>,
     /// Whether the receiver is running on mainnet
     is_mainnet: bool,
+    /// How to answer events that are rejected
+    rejected_event_responses: RejectedEventResponses,
+    /// Number of threads that read and decode requests.  With just one, requests are handled
+    /// one at a time on the thread calling `next_event()`.
+    worker_threads: usize,
+    /// The threads handling requests, once started
+    worker_pool: Option<EventWorkerPool<T>>,
 }
 
 impl<T: SignerEventTrait> SignerEventReceiver<T> {
     /// Make a new Signer event receiver, and return both the receiver and the read end of a
     /// channel into which node-received data can be obtained.
     pub fn new(is_mainnet: bool) -> SignerEventReceiver<T> {
+        let (commands_send, commands_recv) = channel();
         SignerEventReceiver {
             http_server: None,
             local_addr: None,
-            out_channels: vec![],
+            shared_local_addr: SharedListenAddr::default(),
+            commands_send,
+            commands_recv,
+            retired_listener: None,
+            retired_addr: SharedListenAddr::default(),
+            retired_listener_idle: DEFAULT_RETIRED_LISTENER_IDLE,
+            consumers: vec![],
+            max_consumers: None,
             stop_signal: Arc::new(AtomicBool::new(false)),
             is_mainnet,
+            rejected_event_responses: RejectedEventResponses::default(),
+            worker_threads: 1,
+            worker_pool: None,
+        }
+    }
+
+    /// Read and decode up to `worker_threads` requests at a time, so that a slow request does
+    /// not hold up the others.  Events are still passed on in the order they arrived, except
+    /// that events of different StackerDB contracts, or posted to different paths, may overtake
+    /// each other.  Takes effect when the first event is awaited.
+    pub fn with_worker_threads(mut self, worker_threads: usize) -> Self {
+        self.worker_threads = worker_threads.max(1);
+        self
+    }
+
+    /// Accept at most `max_consumers` consumers.  Consumers that hang up no longer count
+    /// towards the limit.
+    pub fn with_max_consumers(mut self, max_consumers: usize) -> Self {
+        self.max_consumers = Some(max_consumers);
+        self
+    }
+
+    /// Add an event consumer whose events queue in `event_out`, which is bounded.  If the
+    /// consumer falls behind and its queue fills up, new events for it are handled according to
+    /// `policy`, so that only a `Block` policy lets a slow consumer hold up the others.
+    /// Fails if the receiver already has as many consumers as it accepts.
+    pub fn add_bounded_consumer(
+        &mut self,
+        event_out: BoundedSender<SignerEvent<T>>,
+        policy: BackpressurePolicy,
+    ) -> Result<(), EventError> {
+        self.check_consumer_limit()?;
+        self.consumers.push(EventConsumer::Bounded {
+            sender: event_out,
+            policy,
+            dropped: 0,
+        });
+        Ok(())
+    }
+
+    /// Fail if another consumer would take the receiver over its limit
+    fn check_consumer_limit(&self) -> Result<(), EventError> {
+        match self.max_consumers {
+            Some(max_consumers) if self.consumers.len() >= max_consumers => {
+                Err(EventError::TooManyConsumers(max_consumers))
+            }
+            _ => Ok(()),
         }
     }
 
+    /// Answer rejected events with the status codes in `responses`
+    pub fn with_rejected_event_responses(mut self, responses: RejectedEventResponses) -> Self {
+        self.rejected_event_responses = responses;
+        self
+    }
+
+    /// Keep serving a listener the receiver moved away from until no request has reached it
+    /// for `idle`.  Defaults to `DEFAULT_RETIRED_LISTENER_IDLE`.
+    pub fn with_retired_listener_idle(mut self, idle: Duration) -> Self {
+        self.retired_listener_idle = idle;
+        self
+    }
+
+    /// Get a handle with which another thread can move this receiver to another address while
+    /// it runs.  Rebinding fails until the receiver is bound.
+    pub fn listener_rebinder(&self) -> ListenerRebinder {
+        ListenerRebinder {
+            requests: self.commands_send.clone(),
+            local_addr: self.shared_local_addr.clone(),
+            retired_addr: self.retired_addr.clone(),
+        }
+    }
+
+    /// Carry out the pending requests from listener rebinders
+    fn handle_listener_commands(&mut self) {
+        while let Ok(command) = self.commands_recv.try_recv() {
+            match command {
+                ListenerCommand::Rebind { addr, reply } => {
+                    let result = self.rebind_listener(addr);
+                    if let Err(e) = &result {
+                        warn!("Failed to move the event receiver to {addr}: {e:?}");
+                    }
+                    // the rebinder may have given up waiting
+                    let _ = reply.send(result);
+                }
+                ListenerCommand::CloseRetired { reply } => {
+                    let _ = reply.send(self.close_retired_listener());
+                }
+            }
+        }
+    }
+
+    /// Stop serving the retired listener.  Returns its address, or None if there was none.
+    fn close_retired_listener(&mut self) -> Option<SocketAddr> {
+        if let Some(worker_pool) = self.worker_pool.as_ref() {
+            let addr = self.retired_addr.get()?;
+            if let Err(e) = worker_pool.close_retired() {
+                warn!("Failed to close the old event listener: {e:?}");
+                return None;
+            }
+            // the acceptor thread closes it once it is woken up
+            if let Some(local_addr) = self.local_addr {
+                if let Err(e) = wake_listener(local_addr, REBIND_PATH, "rebind") {
+                    warn!("Failed to wake up the event acceptor to close the old listener: {e:?}");
+                }
+            }
+            return Some(addr);
+        }
+        self.retired_listener
+            .take()
+            .map(|listener| listener.close("requested"))
+    }
+
+    /// Listen on `addr` instead of the current address.  The old listener is retired: it is
+    /// still served until it goes idle or is closed.  A listener retired earlier is closed.
+    fn rebind_listener(&mut self, addr: SocketAddr) -> Result<SocketAddr, EventError> {
+        let old_addr = self.local_addr.ok_or(EventError::NotBound)?;
+        if addr == old_addr {
+            return Ok(addr);
+        }
+        if self.worker_pool.is_none() && self.http_server.is_none() {
+            return Err(EventError::NotBound);
+        }
+        let server = HttpServer::http(addr).map_err(io::Error::other)?;
+
+        if let Some(worker_pool) = self.worker_pool.as_mut() {
+            // the acceptor thread switches over once it is woken up
+            worker_pool.replace_server(server, old_addr)?;
+            if let Err(e) = wake_listener(old_addr, REBIND_PATH, "rebind") {
+                warn!("Failed to wake up the event acceptor for a rebind: {e:?}");
+            }
+        } else if let Some(old_server) = self.http_server.replace(server) {
+            let retired = RetiredListener::new(old_server, old_addr, self.retired_addr.clone());
+            if let Some(previous) = self.retired_listener.replace(retired) {
+                previous.close("replaced");
+            }
+        }
+
+        self.local_addr = Some(addr);
+        self.shared_local_addr.set(addr);
+        info!("Event receiver moved to a new listener";
+            "old_addr" => %old_addr,
+            "new_addr" => %addr,
+        );
+        Ok(addr)
+    }
+
     /// Do something with the socket
     pub fn with_server<F, R>(&mut self, todo: F) -> Result<R, EventError>
     where
@@ -215,12 +823,40 @@ impl<T: SignerEventTrait> SignerEventReceiver<T> {
         self.http_server = Some(server);
         Ok(res)
     }
+
+    /// Wait for the next event decoded by the worker pool, starting the pool on first use
+    fn next_pooled_event(&mut self) -> Result<SignerEvent<T>, EventError> {
+        if self.is_stopped() {
+            return Err(EventError::Terminated);
+        }
+        if self.worker_pool.is_none() {
+            let server = self.http_server.take().ok_or(EventError::NotBound)?;
+            let listeners = AcceptorListeners {
+                current: server,
+                retired: self.retired_listener.take(),
+                retired_addr: self.retired_addr.clone(),
+                retired_idle: self.retired_listener_idle,
+            };
+            self.worker_pool = Some(EventWorkerPool::start(
+                listeners,
+                self.worker_threads,
+                self.local_addr,
+                self.rejected_event_responses,
+                self.stop_signal.clone(),
+            )?);
+        }
+        self.worker_pool
+            .as_mut()
+            .ok_or(EventError::NotBound)?
+            .next_event()
+    }
 }
 
 /// Stop signaler implementation
 pub struct SignerStopSignaler {
     stop_signal: Arc<AtomicBool>,
-    local_addr: SocketAddr,
+    /// Follows the event receiver if it is moved to another address
+    local_addr: SharedListenAddr,
 }
 
 impl SignerStopSignaler {
@@ -228,7 +864,7 @@ impl SignerStopSignaler {
     pub fn new(sig: Arc<AtomicBool>, local_addr: SocketAddr) -> SignerStopSignaler {
         SignerStopSignaler {
             stop_signal: sig,
-            local_addr,
+            local_addr: SharedListenAddr::new(local_addr),
         }
     }
 }
@@ -239,16 +875,12 @@ impl EventStopSignaler for SignerStopSignaler {
         self.stop_signal.store(true, Ordering::SeqCst);
         // wake up the thread so the atomicbool can be checked
         // This makes me sad...but for now...it works.
-        if let Ok(mut stream) = TcpStream::connect(self.local_addr) {
-            // We need to send actual data to trigger the event receiver
-            let body = "Yo. Shut this shit down!".to_string();
-            let req = format!(
-                "POST /shutdown HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\nContent-Type: text/plain\r\n\r\n{}",
-                self.local_addr,
-                body.len(),
-                body
-            );
-            if let Err(e) = stream.write_all(req.as_bytes()) {
+        let Some(local_addr) = self.local_addr.get() else {
+            return;
+        };
+        // We need to send actual data to trigger the event receiver
+        if let Err(e) = wake_listener(local_addr, "/shutdown", "Yo. Shut this shit down!") {
+            if e.kind() != io::ErrorKind::ConnectionRefused {
                 error!("Failed to send shutdown request: {}", e);
             }
         }
@@ -264,6 +896,7 @@ impl<T: SignerEventTrait> EventReceiver<T> for SignerEventReceiver<T> {
     fn bind(&mut self, listener: SocketAddr) -> Result<SocketAddr, EventError> {
         self.http_server = Some(HttpServer::http(listener).expect("failed to start HttpServer"));
         self.local_addr = Some(listener);
+        self.shared_local_addr.set(listener);
         Ok(listener)
     }
 
@@ -271,52 +904,27 @@ impl<T: SignerEventTrait> EventReceiver<T> for SignerEventReceiver<T> {
     /// Errors are recoverable -- the caller should call this method again even if it returns an
     /// error.
     fn next_event(&mut self) -> Result<SignerEvent<T>, EventError> {
-        self.with_server(|event_receiver, http_server, _is_mainnet| {
+        self.handle_listener_commands();
+        if self.worker_threads > 1 {
+            return self.next_pooled_event();
+        }
+        let mut retired = self.retired_listener.take();
+        let retired_idle = self.retired_listener_idle;
+        let result = self.with_server(|event_receiver, http_server, _is_mainnet| {
             // were we asked to terminate?
             if event_receiver.is_stopped() {
                 return Err(EventError::Terminated);
             }
             debug!("Request handling");
-            let request = http_server.recv()?;
-            debug!("Got request"; "method" => %request.method(), "path" => request.url());
-
-            if request.url() == "/status" {
-                request
-                .respond(HttpResponse::from_string("OK"))
-                .expect("response failed");
-                return Ok(SignerEvent::StatusCheck);
-            }
-
-            if request.method() != &HttpMethod::Post {
-                return Err(EventError::MalformedRequest(format!(
-                    "Unrecognized method '{}'",
-                    &request.method(),
-                )));
-            }
-            if request.url() == "/stackerdb_chunks" {
-                process_stackerdb_event(event_receiver.local_addr, request)
-                    .map_err(|e| {
-                        error!("Error processing stackerdb_chunks message"; "err" => ?e);
-                        e
-                    })
-            } else if request.url() == "/proposal_response" {
-                process_proposal_response(request)
-            } else if request.url() == "/new_burn_block" {
-                process_new_burn_block_event(request)
-            } else {
-                let url = request.url().to_string();
-                // `/new_block` is expected, but not specifically handled. do not log.
-                if &url != "/new_block" {
-                    debug!(
-                        "[{:?}] next_event got request with unexpected url {}, return OK so other side doesn't keep sending this",
-                        event_receiver.local_addr,
-                        url
-                    );
-                }
-                ack_dispatcher(request);
-                Err(EventError::UnrecognizedEvent(url))
-            }
-        })?
+            let request = recv_request(http_server, &mut retired, retired_idle)?;
+            handle_request(
+                event_receiver.local_addr,
+                &event_receiver.rejected_event_responses,
+                request,
+            )
+        });
+        self.retired_listener = retired;
+        result?
     }
 
     /// Determine if the receiver is hung up
@@ -324,94 +932,157 @@ impl<T: SignerEventTrait> EventReceiver<T> for SignerEventReceiver<T> {
         self.stop_signal.load(Ordering::SeqCst)
     }
 
-    /// Forward an event
-    /// Return true on success; false on error.
+    /// Forward an event to every consumer.  Consumers that hung up are removed, without
+    /// affecting delivery to the others.
+    /// Return true on success; false once there are no consumers left.
     /// Returning false terminates the event receiver.
     fn forward_event(&mut self, ev: SignerEvent<T>) -> bool {
-        if self.out_channels.is_empty() {
+        if self.consumers.is_empty() {
             // nothing to do
             error!("No channels connected to event receiver");
-            false
-        } else if self.out_channels.len() == 1 {
-            // avoid a clone
-            if let Err(e) = self.out_channels[0].send(ev) {
-                error!("Failed to send to signer runloop: {:?}", &e);
-                return false;
-            }
-            true
-        } else {
-            for (i, out_channel) in self.out_channels.iter().enumerate() {
-                if let Err(e) = out_channel.send(ev.clone()) {
-                    error!("Failed to send to signer runloop #{}: {:?}", i, &e);
-                    return false;
-                }
+            return false;
+        }
+        let mut hung_up = vec![];
+        let mut ev = Some(ev);
+        let num_consumers = self.consumers.len();
+        for (i, consumer) in self.consumers.iter_mut().enumerate() {
+            // avoid a clone for the last consumer
+            let ev = if i + 1 == num_consumers {
+                ev.take()
+            } else {
+                ev.clone()
+            };
+            let Some(ev) = ev else {
+                break;
+            };
+            if !consumer.deliver(i, ev) {
+                hung_up.push(i);
             }
-            true
         }
+        for i in hung_up.into_iter().rev() {
+            error!("Event consumer #{i} hung up; no longer forwarding events to it");
+            self.consumers.remove(i);
+        }
+        !self.consumers.is_empty()
     }
 
-    /// Add an event consumer.  A received event will be forwarded to this Sender.
+    /// Add an event consumer.  A received event will be forwarded to this Sender, which queues
+    /// events without limit.  If the receiver already has as many consumers as it accepts, the
+    /// Sender is dropped instead, so the consumer sees a hang-up.
     fn add_consumer(&mut self, out_channel: Sender<SignerEvent<T>>) {
-        self.out_channels.push(out_channel);
+        if let Err(e) = self.check_consumer_limit() {
+            error!("Failed to add event consumer: {e}");
+            return;
+        }
+        self.consumers.push(EventConsumer::Unbounded(out_channel));
     }
 
     /// Get a stopped signaler.  The caller can then use it to terminate the event receiver loop,
     /// even if it's in a different thread.
     fn get_stop_signaler(&mut self) -> Result<SignerStopSignaler, EventError> {
-        if let Some(local_addr) = self.local_addr {
-            Ok(SignerStopSignaler::new(
-                self.stop_signal.clone(),
-                local_addr,
-            ))
+        if self.local_addr.is_some() {
+            Ok(SignerStopSignaler {
+                stop_signal: self.stop_signal.clone(),
+                local_addr: self.shared_local_addr.clone(),
+            })
         } else {
             Err(EventError::NotBound)
         }
     }
 }
 
-fn ack_dispatcher(request: HttpRequest) {
-    if let Err(e) = request.respond(HttpResponse::empty(200u16)) {
+/// Read and decode a request from the node, and answer it
+pub(crate) fn handle_request<T: SignerEventTrait>(
+    local_addr: Option<SocketAddr>,
+    rejected_event_responses: &RejectedEventResponses,
+    mut request: HttpRequest,
+) -> Result<SignerEvent<T>, EventError> {
+    debug!("Got request"; "method" => %request.method(), "path" => request.url());
+
+    if request.url() == "/status" {
+        request
+            .respond(HttpResponse::from_string("OK"))
+            .expect("response failed");
+        return Ok(SignerEvent::StatusCheck);
+    }
+
+    let result = if request.method() != &HttpMethod::Post {
+        Err(EventError::MalformedRequest(format!(
+            "Unrecognized method '{}'",
+            &request.method(),
+        )))
+    } else if request.url() == "/stackerdb_chunks" {
+        process_stackerdb_event(local_addr, &mut request).map_err(|e| {
+            error!("Error processing stackerdb_chunks message"; "err" => ?e);
+            e
+        })
+    } else if request.url() == "/proposal_response" {
+        process_proposal_response(&mut request)
+    } else if request.url() == "/new_burn_block" {
+        process_new_burn_block_event(&mut request)
+    } else {
+        let url = request.url().to_string();
+        // `/new_block` is expected, but not specifically handled. do not log.
+        if &url != "/new_block" {
+            debug!(
+                "[{:?}] next_event got request with unexpected url {}, return OK so other side doesn't keep sending this",
+                local_addr,
+                url
+            );
+        }
+        Err(EventError::UnrecognizedEvent(url))
+    };
+
+    let status = match &result {
+        Ok(_) => 200,
+        Err(e) => rejected_event_responses.status_for(e),
+    };
+    if status != 200 {
+        warn!("Rejecting event"; "path" => request.url(), "status" => status);
+    }
+    respond_to_dispatcher(request, status);
+    result
+}
+
+pub(crate) fn respond_to_dispatcher(request: HttpRequest, status: u16) {
+    if let Err(e) = request.respond(HttpResponse::empty(status)) {
         error!("Failed to respond to request: {:?}", &e);
     };
 }
 
-/// Process a stackerdb event from the node
-fn process_stackerdb_event<T: SignerEventTrait>(
-    local_addr: Option<SocketAddr>,
-    mut request: HttpRequest,
-) -> Result<SignerEvent<T>, EventError> {
-    debug!("Got stackerdb_chunks event");
+/// Read the body of an event
+fn read_event_body(request: &mut HttpRequest) -> Result<String, EventError> {
     let mut body = String::new();
     if let Err(e) = request.as_reader().read_to_string(&mut body) {
         error!("Failed to read body: {:?}", &e);
-        ack_dispatcher(request);
         return Err(EventError::MalformedRequest(format!(
             "Failed to read body: {:?}",
             &e
         )));
     }
+    Ok(body)
+}
+
+/// Process a stackerdb event from the node
+fn process_stackerdb_event<T: SignerEventTrait>(
+    local_addr: Option<SocketAddr>,
+    request: &mut HttpRequest,
+) -> Result<SignerEvent<T>, EventError> {
+    debug!("Got stackerdb_chunks event");
+    let body = read_event_body(request)?;
 
     let event: StackerDBChunksEvent = serde_json::from_slice(body.as_bytes())
         .map_err(|e| EventError::Deserialize(format!("Could not decode body to JSON: {:?}", &e)))?;
 
     let event_contract_id = event.contract_id.clone();
 
-    let signer_event = match SignerEvent::try_from(event) {
-        Err(e) => {
-            info!(
-                "[{:?}] next_event got event from an unexpected contract id {}, return OK so other side doesn't keep sending this",
-                local_addr,
-                event_contract_id
-            );
-            ack_dispatcher(request);
-            return Err(e);
-        }
-        Ok(x) => x,
-    };
-
-    ack_dispatcher(request);
-
-    Ok(signer_event)
+    SignerEvent::try_from(event).map_err(|e| {
+        info!(
+            "[{:?}] next_event got event from an unexpected contract id {}",
+            local_addr, event_contract_id
+        );
+        e
+    })
 }
 
 impl<T: SignerEventTrait> TryFrom<StackerDBChunksEvent> for SignerEvent<T> {
@@ -433,7 +1104,7 @@ impl<T: SignerEventTrait> TryFrom<StackerDBChunksEvent> for SignerEvent<T> {
                         "Failed to recover PK from StackerDB chunk: {e}"
                     ))
                 })?);
-                messages.push(msg);
+                messages.push((StackerDBChunkId::new(&event.contract_id, &chunk), msg));
             }
             SignerEvent::MinerMessages(messages, miner_pk.ok_or(EventError::EmptyChunksEvent)?)
         } else if event.contract_id.name.starts_with(SIGNERS_NAME) && event.contract_id.is_boot() {
@@ -443,10 +1114,13 @@ impl<T: SignerEventTrait> TryFrom<StackerDBChunksEvent> for SignerEvent<T> {
                 return Err(EventError::UnrecognizedStackerDBContract(event.contract_id));
             };
             // signer-XXX-YYY boot contract
-            let signer_messages: Vec<T> = event
+            let signer_messages: Vec<(StackerDBChunkId, T)> = event
                 .modified_slots
                 .iter()
-                .filter_map(|chunk| read_next::<T, _>(&mut &chunk.data[..]).ok())
+                .filter_map(|chunk| {
+                    let msg = read_next::<T, _>(&mut &chunk.data[..]).ok()?;
+                    Some((StackerDBChunkId::new(&event.contract_id, chunk), msg))
+                })
                 .collect();
             SignerEvent::SignerMessages(signer_set, signer_messages)
         } else {
@@ -458,64 +1132,38 @@ impl<T: SignerEventTrait> TryFrom<StackerDBChunksEvent> for SignerEvent<T> {
 
 /// Process a proposal response from the node
 fn process_proposal_response<T: SignerEventTrait>(
-    mut request: HttpRequest,
+    request: &mut HttpRequest,
 ) -> Result<SignerEvent<T>, EventError> {
     debug!("Got proposal_response event");
-    let mut body = String::new();
-    if let Err(e) = request.as_reader().read_to_string(&mut body) {
-        error!("Failed to read body: {:?}", &e);
-
-        if let Err(e) = request.respond(HttpResponse::empty(200u16)) {
-            error!("Failed to respond to request: {:?}", &e);
-        }
-        return Err(EventError::MalformedRequest(format!(
-            "Failed to read body: {:?}",
-            &e
-        )));
-    }
+    let body = read_event_body(request)?;
 
     let event: BlockValidateResponse = serde_json::from_slice(body.as_bytes())
         .map_err(|e| EventError::Deserialize(format!("Could not decode body to JSON: {:?}", &e)))?;
 
-    if let Err(e) = request.respond(HttpResponse::empty(200u16)) {
-        error!("Failed to respond to request: {:?}", &e);
-    }
-
     Ok(SignerEvent::BlockValidationResponse(event))
 }
 
 /// Process a new burn block event from the node
 fn process_new_burn_block_event<T: SignerEventTrait>(
-    mut request: HttpRequest,
+    request: &mut HttpRequest,
 ) -> Result<SignerEvent<T>, EventError> {
     debug!("Got burn_block event");
-    let mut body = String::new();
-    if let Err(e) = request.as_reader().read_to_string(&mut body) {
-        error!("Failed to read body: {:?}", &e);
-
-        if let Err(e) = request.respond(HttpResponse::empty(200u16)) {
-            error!("Failed to respond to request: {:?}", &e);
-        }
-        return Err(EventError::MalformedRequest(format!(
-            "Failed to read body: {:?}",
-            &e
-        )));
-    }
+    let body = read_event_body(request)?;
     #[derive(Debug, Deserialize)]
     struct TempBurnBlockEvent {
         burn_block_hash: String,
         burn_block_height: u64,
+        burn_block_timestamp: Option<u64>,
         reward_recipients: Vec<serde_json::Value>,
         reward_slot_holders: Vec<String>,
         burn_amount: u64,
     }
     let temp: TempBurnBlockEvent = serde_json::from_slice(body.as_bytes())
         .map_err(|e| EventError::Deserialize(format!("Could not decode body to JSON: {:?}", &e)))?;
-    let event = SignerEvent::NewBurnBlock(temp.burn_block_height);
-    if let Err(e) = request.respond(HttpResponse::empty(200u16)) {
-        error!("Failed to respond to request: {:?}", &e);
-    }
-    Ok(event)
+    Ok(SignerEvent::NewBurnBlock {
+        burn_height: temp.burn_block_height,
+        burn_header_timestamp: temp.burn_block_timestamp,
+    })
 }
 
 pub fn get_signers_db_signer_set_message_id(name: &str) -> Option<(u32, u32)> {
@@ -36,7 +36,10 @@ extern crate libc;
 #[cfg(test)]
 mod tests;
 
+mod async_runloop;
 mod error;
+mod event_stream;
+mod event_workers;
 mod events;
 mod http;
 mod runloop;
@@ -47,10 +50,16 @@ pub mod v0;
 /// v1 signer related code
 pub mod v1;
 
+pub use crate::async_runloop::{
+    async_main_loop, bounded_channel, run_blocking, AsyncExecutor, AsyncRunningSigner, AsyncSigner,
+    BoundedReceiver, BoundedSender, BoxedFuture,
+};
 pub use crate::error::{EventError, RPCError};
+pub use crate::event_stream::{SignerEventStream, DEFAULT_EVENT_STREAM_CAPACITY};
 pub use crate::events::{
-    BlockProposal, EventReceiver, EventStopSignaler, SignerEvent, SignerEventReceiver,
-    SignerEventTrait, SignerStopSignaler,
+    BackpressurePolicy, BlockProposal, EventReceiver, EventStopSignaler, ListenerRebinder,
+    RejectedEventResponses, SignerEvent, SignerEventReceiver, SignerEventTrait, SignerEventType,
+    SignerStopSignaler, StackerDBChunkId, DEFAULT_RETIRED_LISTENER_IDLE,
 };
 pub use crate::runloop::{RunningSigner, Signer, SignerRunLoop};
 pub use crate::session::{SignerSession, StackerDBSession};
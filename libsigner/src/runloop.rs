@@ -33,7 +33,7 @@ use crate::events::{EventReceiver, EventStopSignaler, SignerEvent, SignerEventTr
 
 /// Some libcs, like musl, have a very small stack size.
 /// Make sure it's big enough.
-const THREAD_STACK_SIZE: usize = 128 * 1024 * 1024; // 128 MB
+pub(crate) const THREAD_STACK_SIZE: usize = 128 * 1024 * 1024; // 128 MB
 
 /// stderr fileno
 const STDERR: i32 = 2;
@@ -193,6 +193,25 @@ pub fn set_runloop_signal_handler<ST: EventStopSignaler + Send + 'static>(mut st
 }
 
 impl<CMD, R, SL, EV, T> Signer<CMD, R, SL, EV, T> {
+    /// Take the event receiver, command receiver, result sender, and runloop out of this signer
+    /// so they can be started.  Fails if they were already taken.
+    pub(crate) fn take_parts(&mut self) -> Result<(EV, Receiver<CMD>, Sender<R>, SL), EventError> {
+        let event_receiver = self
+            .event_receiver
+            .take()
+            .ok_or(EventError::AlreadyRunning)?;
+        let command_receiver = self
+            .command_receiver
+            .take()
+            .ok_or(EventError::AlreadyRunning)?;
+        let result_sender = self
+            .result_sender
+            .take()
+            .ok_or(EventError::AlreadyRunning)?;
+        let signer_loop = self.signer_loop.take().ok_or(EventError::AlreadyRunning)?;
+        Ok((event_receiver, command_receiver, result_sender, signer_loop))
+    }
+
     /// Create a new signer with the given runloop and event receiver.
     pub fn new(
         runloop: SL,
@@ -229,19 +248,8 @@ impl<
     /// On success, this method consumes the Signer and returns a RunningSigner with the relevant
     /// inter-thread communication primitives for the caller to shut down the system.
     pub fn spawn(&mut self, bind_addr: SocketAddr) -> Result<RunningSigner<EV, R, T>, EventError> {
-        let mut event_receiver = self
-            .event_receiver
-            .take()
-            .ok_or(EventError::AlreadyRunning)?;
-        let command_receiver = self
-            .command_receiver
-            .take()
-            .ok_or(EventError::AlreadyRunning)?;
-        let result_sender = self
-            .result_sender
-            .take()
-            .ok_or(EventError::AlreadyRunning)?;
-        let mut signer_loop = self.signer_loop.take().ok_or(EventError::AlreadyRunning)?;
+        let (mut event_receiver, command_receiver, result_sender, mut signer_loop) =
+            self.take_parts()?;
 
         let (event_send, event_recv) = channel();
         event_receiver.add_consumer(event_send);
@@ -20,6 +20,13 @@ use wsts::curve::ecdsa;
 use wsts::curve::point::{Compressed, Point};
 use wsts::state_machine::PublicKeys;
 
+/// The percentage of the total signing weight required to sign a message.
+/// This matches `threshold-consensus` in the signers voting contract.
+const SIGNING_THRESHOLD_PERCENT: u64 = 70;
+
+/// The percentage of the total signing weight required to complete DKG
+const DKG_THRESHOLD_PERCENT: u64 = 90;
+
 /// A reward set parsed into the structures required by WSTS party members and coordinators.
 #[derive(Debug, Clone)]
 pub struct SignerEntries {
@@ -123,15 +130,43 @@ impl SignerEntries {
             .map_err(|_| Error::SignerCountOverflow)
     }
 
+    /// Return the signing weight of each signer id, i.e. the number of Key IDs it holds
+    pub fn signer_weights(&self) -> HashMap<u32, u32> {
+        self.signer_key_ids
+            .iter()
+            .map(|(signer_id, key_ids)| {
+                (*signer_id, u32::try_from(key_ids.len()).unwrap_or(u32::MAX))
+            })
+            .collect()
+    }
+
+    /// Return the total signing weight of the reward set
+    pub fn total_weight(&self) -> Result<u32, Error> {
+        self.signer_key_ids
+            .values()
+            .map(|key_ids| key_ids.len() as u64)
+            .sum::<u64>()
+            .try_into()
+            .map_err(|_| Error::SignerCountOverflow)
+    }
+
+    /// Return the weight that must be met to reach `percent` of the total signing weight,
+    ///  rounding up the same way as the signers voting contract's `get-threshold-weight`
+    fn threshold_weight(&self, percent: u64) -> Result<u32, Error> {
+        let total_weight = u64::from(self.total_weight()?);
+        (total_weight * percent)
+            .div_ceil(100)
+            .try_into()
+            .map_err(|_| Error::SignerCountOverflow)
+    }
+
     /// Return the number of Key IDs required to sign a message with the WSTS group signature
     pub fn get_signing_threshold(&self) -> Result<u32, Error> {
-        let num_keys = self.count_keys()?;
-        Ok((num_keys as f64 * 7_f64 / 10_f64).ceil() as u32)
+        self.threshold_weight(SIGNING_THRESHOLD_PERCENT)
     }
 
     /// Return the number of Key IDs required to sign a message with the WSTS group signature
     pub fn get_dkg_threshold(&self) -> Result<u32, Error> {
-        let num_keys = self.count_keys()?;
-        Ok((num_keys as f64 * 9_f64 / 10_f64).ceil() as u32)
+        self.threshold_weight(DKG_THRESHOLD_PERCENT)
     }
 }
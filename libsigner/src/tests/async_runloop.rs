@@ -0,0 +1,334 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Sender, TrySendError};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread, ThreadId};
+use std::time::{Duration, Instant};
+
+use super::SimpleRunLoop;
+use crate::events::{EventStopSignaler, SignerEvent};
+use crate::v1::messages::SignerMessage;
+use crate::{async_main_loop, bounded_channel, AsyncExecutor, BoxedFuture, SignerRunLoop};
+
+/// Wakes a thread parked in `block_on()`
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Minimal executor for testing: drive a future to completion on the current thread
+pub(super) fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = Box::pin(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+/// Future that resolves at `deadline`, using a helper thread as the timer
+struct Sleep {
+    deadline: Instant,
+    timer_started: bool,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let now = Instant::now();
+        if now >= self.deadline {
+            return Poll::Ready(());
+        }
+        if !self.timer_started {
+            self.timer_started = true;
+            let remaining = self.deadline - now;
+            let waker = cx.waker().clone();
+            thread::spawn(move || {
+                thread::sleep(remaining);
+                waker.wake();
+            });
+        }
+        Poll::Pending
+    }
+}
+
+/// An `AsyncExecutor` that runs each spawned future on its own thread
+#[derive(Clone)]
+struct ThreadExecutor;
+
+impl AsyncExecutor for ThreadExecutor {
+    fn spawn(&self, fut: BoxedFuture<()>) {
+        thread::spawn(move || block_on(fut));
+    }
+
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send + 'static>) {
+        thread::spawn(f);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxedFuture<()> {
+        Box::pin(Sleep {
+            deadline: Instant::now() + duration,
+            timer_started: false,
+        })
+    }
+}
+
+struct FlagStopSignaler(Arc<AtomicBool>);
+
+impl EventStopSignaler for FlagStopSignaler {
+    fn send(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn test_bounded_channel_backpressure() {
+    let (send, recv) = bounded_channel(2);
+    send.try_send(1).unwrap();
+    send.try_send(2).unwrap();
+    assert!(matches!(send.try_send(3), Err(TrySendError::Full(3))));
+
+    // a blocking sender waits until the receiver makes room
+    let blocking_send = send.clone();
+    let sender_thread = thread::spawn(move || blocking_send.blocking_send(3).unwrap());
+    assert_eq!(block_on(recv.recv()), Some(1));
+    sender_thread.join().unwrap();
+    assert_eq!(block_on(recv.recv()), Some(2));
+    assert_eq!(block_on(recv.recv()), Some(3));
+
+    // an async sender completes once there is room
+    block_on(send.send(4)).unwrap();
+    assert_eq!(recv.try_recv(), Ok(4));
+
+    // times out when empty, and reports the hang-up once all senders are gone
+    let res = block_on(recv.recv_timeout(Duration::from_millis(10), &ThreadExecutor));
+    assert!(res.is_err());
+    drop(send);
+    assert_eq!(block_on(recv.recv()), None);
+}
+
+#[test]
+fn test_bounded_send_registers_one_waker() {
+    let (send, recv) = bounded_channel(1);
+    send.try_send(1).unwrap();
+
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut first = send.send(2);
+    let mut second = send.send(3);
+    // polling a pending send again does not pile up wakers
+    for _ in 0..3 {
+        assert!(Pin::new(&mut first).poll(&mut cx).is_pending());
+        assert!(Pin::new(&mut second).poll(&mut cx).is_pending());
+    }
+    assert_eq!(send.num_send_wakers(), 2);
+
+    // a dropped send gives up its slot
+    drop(second);
+    assert_eq!(send.num_send_wakers(), 1);
+
+    assert_eq!(recv.try_recv(), Ok(1));
+    assert!(matches!(
+        Pin::new(&mut first).poll(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert_eq!(send.num_send_wakers(), 0);
+    assert_eq!(recv.try_recv(), Ok(2));
+}
+
+#[test]
+fn test_bounded_channel_eviction() {
+    let (send, recv) = bounded_channel(2);
+    // nothing is evicted while there is room
+    assert_eq!(send.try_send_evicting(1, |_| true), Ok(None));
+    assert_eq!(send.try_send_evicting(2, |_| true), Ok(None));
+
+    // the oldest evictable value makes way
+    assert_eq!(send.try_send_evicting(3, |v| v % 2 == 0), Ok(Some(2)));
+    assert!(matches!(
+        send.try_send_evicting(5, |v| v % 2 == 0),
+        Err(TrySendError::Full(5))
+    ));
+    assert_eq!(send.try_send_evicting(4, |_| true), Ok(Some(1)));
+    assert_eq!(recv.try_recv(), Ok(3));
+    assert_eq!(recv.try_recv(), Ok(4));
+
+    drop(recv);
+    assert!(matches!(
+        send.try_send_evicting(6, |_| true),
+        Err(TrySendError::Disconnected(6))
+    ));
+}
+
+#[test]
+fn test_async_main_loop() {
+    let max_events = 3;
+    let signer_loop = SimpleRunLoop::<SignerMessage>::new(max_events);
+    let (event_send, event_recv) = bounded_channel(1);
+    let (_cmd_send, cmd_recv) = bounded_channel::<super::Command>(1);
+    let (res_send, _res_recv) = bounded_channel(1);
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    let producer = thread::spawn(move || {
+        for height in 0..max_events {
+            event_send
+                .blocking_send(SignerEvent::NewBurnBlock {
+                    burn_height: height as u64,
+                    burn_header_timestamp: None,
+                })
+                .unwrap();
+        }
+    });
+
+    let final_state = block_on(async_main_loop(
+        signer_loop,
+        &event_recv,
+        &cmd_recv,
+        &res_send,
+        FlagStopSignaler(stopped.clone()),
+        &ThreadExecutor,
+    ))
+    .unwrap();
+    producer.join().unwrap();
+
+    assert_eq!(
+        final_state,
+        (0..max_events)
+            .map(|height| SignerEvent::NewBurnBlock {
+                burn_height: height as u64,
+                burn_header_timestamp: None,
+            })
+            .collect::<Vec<_>>()
+    );
+    assert!(stopped.load(Ordering::SeqCst));
+}
+
+/// Runloop that reports, for each pass, the thread it ran on and the command it was given, and
+/// exits after `max_passes` passes
+struct ThreadReportingRunLoop {
+    passes: usize,
+    max_passes: usize,
+}
+
+impl SignerRunLoop<(ThreadId, Option<u64>), u64, SignerMessage> for ThreadReportingRunLoop {
+    fn set_event_timeout(&mut self, _timeout: Duration) {}
+
+    fn get_event_timeout(&self) -> Duration {
+        Duration::from_millis(10)
+    }
+
+    fn run_one_pass(
+        &mut self,
+        _event: Option<SignerEvent<SignerMessage>>,
+        cmd: Option<u64>,
+        res: Sender<(ThreadId, Option<u64>)>,
+    ) -> Option<(ThreadId, Option<u64>)> {
+        self.passes += 1;
+        let report = (thread::current().id(), cmd);
+        if self.passes >= self.max_passes {
+            return Some(report);
+        }
+        res.send(report).unwrap();
+        None
+    }
+}
+
+#[test]
+fn test_async_main_loop_runs_passes_off_the_executor() {
+    let signer_loop = ThreadReportingRunLoop {
+        passes: 0,
+        max_passes: 3,
+    };
+    let (_event_send, event_recv) = bounded_channel::<SignerEvent<SignerMessage>>(1);
+    let (cmd_send, cmd_recv) = bounded_channel(1);
+    let (res_send, res_recv) = bounded_channel(4);
+    let stopped = Arc::new(AtomicBool::new(false));
+    cmd_send.try_send(7).unwrap();
+
+    let executor_thread = thread::current().id();
+    let (final_thread, _) = block_on(async_main_loop(
+        signer_loop,
+        &event_recv,
+        &cmd_recv,
+        &res_send,
+        FlagStopSignaler(stopped.clone()),
+        &ThreadExecutor,
+    ))
+    .unwrap();
+    assert!(stopped.load(Ordering::SeqCst));
+    assert_ne!(final_thread, executor_thread);
+
+    // the results sent during each pass reach the result channel, and the command was
+    // handed to the first pass
+    let (first_thread, first_cmd) = res_recv.try_recv().unwrap();
+    assert_ne!(first_thread, executor_thread);
+    assert_eq!(first_cmd, Some(7));
+    let (second_thread, second_cmd) = res_recv.try_recv().unwrap();
+    assert_ne!(second_thread, executor_thread);
+    assert_eq!(second_cmd, None);
+    assert!(res_recv.try_recv().is_err());
+}
+
+/// Runloop whose passes panic
+struct PanickingRunLoop;
+
+impl SignerRunLoop<(), (), SignerMessage> for PanickingRunLoop {
+    fn set_event_timeout(&mut self, _timeout: Duration) {}
+
+    fn get_event_timeout(&self) -> Duration {
+        Duration::from_millis(10)
+    }
+
+    fn run_one_pass(
+        &mut self,
+        _event: Option<SignerEvent<SignerMessage>>,
+        _cmd: Option<()>,
+        _res: Sender<()>,
+    ) -> Option<()> {
+        panic!("runloop pass failed");
+    }
+}
+
+#[test]
+fn test_async_main_loop_stops_if_a_pass_panics() {
+    let (_event_send, event_recv) = bounded_channel::<SignerEvent<SignerMessage>>(1);
+    let (_cmd_send, cmd_recv) = bounded_channel(1);
+    let (res_send, _res_recv) = bounded_channel(1);
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    let final_state = block_on(async_main_loop(
+        PanickingRunLoop,
+        &event_recv,
+        &cmd_recv,
+        &res_send,
+        FlagStopSignaler(stopped.clone()),
+        &ThreadExecutor,
+    ));
+    assert_eq!(final_state, None);
+    assert!(stopped.load(Ordering::SeqCst));
+}
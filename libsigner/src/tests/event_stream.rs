@@ -0,0 +1,70 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::future::poll_fn;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::pin::Pin;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+
+use futures_core::Stream;
+use stacks_common::util::sleep_ms;
+
+use super::async_runloop::block_on;
+use crate::events::SignerEvent;
+use crate::v1::messages::SignerMessage;
+use crate::SignerEventStream;
+
+/// Ask the event receiver at `endpoint` for its status, retrying until it is listening
+fn send_status_check(endpoint: SocketAddr) {
+    let mut sock = loop {
+        match TcpStream::connect(endpoint) {
+            Ok(sock) => break sock,
+            Err(..) => sleep_ms(100),
+        }
+    };
+    let req = format!(
+        "GET /status HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        endpoint
+    );
+    sock.write_all(req.as_bytes()).unwrap();
+    let mut buf = [0; 128];
+    let _ = sock.read(&mut buf).unwrap();
+}
+
+#[test]
+fn test_event_stream() {
+    let endpoint: SocketAddr = "127.0.0.1:32000".parse().unwrap();
+    let mut stream = SignerEventStream::<SignerMessage>::bind(endpoint, false).unwrap();
+    assert_eq!(stream.local_addr(), endpoint);
+
+    // as an iterator
+    send_status_check(endpoint);
+    assert_eq!(stream.next(), Some(SignerEvent::StatusCheck));
+    assert_eq!(
+        stream.next_timeout(Duration::from_millis(100)),
+        Err(RecvTimeoutError::Timeout)
+    );
+
+    // as a stream
+    send_status_check(endpoint);
+    let event = block_on(poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)));
+    assert_eq!(event, Some(SignerEvent::StatusCheck));
+
+    // stopping the stream stops the receiver thread
+    stream.stop();
+}
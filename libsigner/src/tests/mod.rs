@@ -14,6 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+mod async_runloop;
+mod event_stream;
 mod http;
 
 use std::fmt::Debug;
@@ -24,7 +26,7 @@ use std::time::Duration;
 use std::{mem, thread};
 
 use blockstack_lib::chainstate::nakamoto::signer_set::NakamotoSigners;
-use blockstack_lib::chainstate::stacks::boot::SIGNERS_NAME;
+use blockstack_lib::chainstate::stacks::boot::{NakamotoSignerEntry, SIGNERS_NAME};
 use blockstack_lib::chainstate::stacks::events::StackerDBChunksEvent;
 use blockstack_lib::util_lib::boot::boot_code_id;
 use clarity::vm::types::QualifiedContractIdentifier;
@@ -33,13 +35,17 @@ use stacks_common::codec::{
     read_next, read_next_at_most, read_next_exact, write_next, Error as CodecError,
     StacksMessageCodec,
 };
+use stacks_common::types::chainstate::StacksPublicKey;
 use stacks_common::util::secp256k1::Secp256k1PrivateKey;
 use stacks_common::util::sleep_ms;
 use wsts::net::{DkgBegin, Packet};
 
-use crate::events::{SignerEvent, SignerEventTrait};
+use crate::events::{SignerEvent, SignerEventTrait, StackerDBChunkId};
 use crate::v1::messages::SignerMessage;
-use crate::{Signer, SignerEventReceiver, SignerRunLoop};
+use crate::{
+    bounded_channel, BackpressurePolicy, EventError, EventReceiver, RejectedEventResponses, Signer,
+    SignerEntries, SignerEventReceiver, SignerEventType, SignerRunLoop,
+};
 
 /// Simple runloop implementation.  It receives `max_events` events and returns `events` from the
 /// last call to `run_one_pass` as its final state.
@@ -167,7 +173,8 @@ fn test_simple_signer() {
         .map(|chunk| {
             let msg = chunk.modified_slots[0].data.clone();
             let signer_message = read_next::<SignerMessage, _>(&mut &msg[..]).unwrap();
-            SignerEvent::SignerMessages(0, vec![signer_message])
+            let chunk_id = StackerDBChunkId::new(&chunk.contract_id, &chunk.modified_slots[0]);
+            SignerEvent::SignerMessages(0, vec![(chunk_id, signer_message)])
         })
         .collect();
 
@@ -217,3 +224,371 @@ fn test_status_endpoint() {
     assert_eq!(sent_events, accepted_events);
     mock_stacks_node.join().unwrap();
 }
+
+/// POST `body` to `path` on the event receiver at `endpoint`, and return the response's status
+/// line
+fn post_event(endpoint: SocketAddr, path: &str, body: &str) -> String {
+    let mut sock = loop {
+        match TcpStream::connect(endpoint) {
+            Ok(sock) => break sock,
+            Err(..) => sleep_ms(100),
+        }
+    };
+    let req = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        path,
+        endpoint,
+        body.len(),
+        body
+    );
+    sock.write_all(req.as_bytes()).unwrap();
+    let mut res = String::new();
+    sock.read_to_string(&mut res).unwrap();
+    res.lines().next().unwrap_or_default().to_string()
+}
+
+#[test]
+fn test_rejected_event_responses() {
+    let endpoint: SocketAddr = "127.0.0.1:31500".parse().unwrap();
+    let mut ev = SignerEventReceiver::<SignerMessage>::new(false);
+    ev.bind(endpoint).unwrap();
+
+    let unrecognized_contract = serde_json::to_string(&StackerDBChunksEvent {
+        contract_id: boot_code_id("not-a-signers-contract", false),
+        modified_slots: vec![],
+    })
+    .unwrap();
+
+    let send_event = |ev: &mut SignerEventReceiver<SignerMessage>, path: &str, body: &str| {
+        let path = path.to_string();
+        let body = body.to_string();
+        let mock_stacks_node = thread::spawn(move || post_event(endpoint, &path, &body));
+        assert!(ev.next_event().is_err());
+        mock_stacks_node.join().unwrap()
+    };
+
+    assert_eq!(
+        send_event(&mut ev, "/new_burn_block", "{\"not\": \"an event\"}"),
+        "HTTP/1.1 400 Bad Request"
+    );
+    assert_eq!(
+        send_event(&mut ev, "/stackerdb_chunks", &unrecognized_contract),
+        "HTTP/1.1 422 Unprocessable Entity"
+    );
+    // events the signer has no use for are still acknowledged
+    assert_eq!(send_event(&mut ev, "/new_block", "{}"), "HTTP/1.1 200 OK");
+
+    let mut ev = ev.with_rejected_event_responses(RejectedEventResponses::ACKNOWLEDGE_ALL);
+    assert_eq!(
+        send_event(&mut ev, "/new_burn_block", "{\"not\": \"an event\"}"),
+        "HTTP/1.1 200 OK"
+    );
+    assert_eq!(
+        send_event(&mut ev, "/stackerdb_chunks", &unrecognized_contract),
+        "HTTP/1.1 200 OK"
+    );
+}
+
+/// A full bounded consumer sheds events according to its policy without holding up the others,
+/// and a consumer that hangs up is dropped without stopping delivery to the rest
+#[test]
+fn test_bounded_consumer_backpressure() {
+    let new_burn_block = |height| SignerEvent::<SignerMessage>::NewBurnBlock {
+        burn_height: height,
+        burn_header_timestamp: None,
+    };
+
+    let mut ev = SignerEventReceiver::<SignerMessage>::new(false).with_max_consumers(4);
+    let (unbounded_send, unbounded_recv) = channel();
+    ev.add_consumer(unbounded_send);
+    let (drop_oldest_send, drop_oldest_recv) = bounded_channel(1);
+    ev.add_bounded_consumer(drop_oldest_send, BackpressurePolicy::DropOldest)
+        .unwrap();
+    let (drop_types_send, drop_types_recv) = bounded_channel(2);
+    ev.add_bounded_consumer(
+        drop_types_send,
+        BackpressurePolicy::DropEventTypes(vec![SignerEventType::StatusCheck]),
+    )
+    .unwrap();
+    let (hung_up_send, hung_up_recv) = bounded_channel(1);
+    ev.add_bounded_consumer(hung_up_send, BackpressurePolicy::Block)
+        .unwrap();
+
+    // no more consumers are accepted
+    let (extra_send, _extra_recv) = bounded_channel(1);
+    assert!(matches!(
+        ev.add_bounded_consumer(extra_send, BackpressurePolicy::Block),
+        Err(EventError::TooManyConsumers(4))
+    ));
+
+    drop(hung_up_recv);
+    assert!(ev.forward_event(SignerEvent::StatusCheck));
+    assert!(ev.forward_event(new_burn_block(1)));
+    assert!(ev.forward_event(new_burn_block(2)));
+    assert!(ev.forward_event(SignerEvent::StatusCheck));
+
+    // the unbounded consumer got everything
+    assert_eq!(unbounded_recv.try_iter().count(), 4);
+
+    // the drop-oldest consumer only kept the latest event
+    assert_eq!(drop_oldest_recv.try_recv(), Ok(SignerEvent::StatusCheck));
+    assert!(drop_oldest_recv.try_recv().is_err());
+
+    // the drop-types consumer shed status checks to make room for burn blocks
+    assert_eq!(drop_types_recv.try_recv(), Ok(new_burn_block(1)));
+    assert_eq!(drop_types_recv.try_recv(), Ok(new_burn_block(2)));
+    assert!(drop_types_recv.try_recv().is_err());
+
+    // the hung-up consumer no longer counts towards the limit
+    let (replacement_send, replacement_recv) = bounded_channel(1);
+    ev.add_bounded_consumer(replacement_send, BackpressurePolicy::DropOldest)
+        .unwrap();
+
+    // the receiver stops once every consumer has hung up
+    drop(unbounded_recv);
+    drop(drop_oldest_recv);
+    drop(drop_types_recv);
+    drop(replacement_recv);
+    assert!(!ev.forward_event(SignerEvent::StatusCheck));
+}
+
+/// A StackerDB event whose body arrives slowly does not hold up other events when the receiver
+/// has a worker pool, but does hold up later events of the same contract
+#[test]
+fn test_worker_pool_slow_request() {
+    let endpoint: SocketAddr = "127.0.0.1:31600".parse().unwrap();
+    let mut ev = SignerEventReceiver::<SignerMessage>::new(false).with_worker_threads(3);
+    ev.bind(endpoint).unwrap();
+
+    let contract_id = NakamotoSigners::make_signers_db_contract_id(0, 0, false);
+    let chunk_events: Vec<_> = (0..2)
+        .map(|slot_id| {
+            let msg = wsts::net::Message::DkgBegin(DkgBegin { dkg_id: slot_id });
+            let message = SignerMessage::Packet(Packet { msg, sig: vec![] });
+            let mut chunk = StackerDBChunkData::new(slot_id as u32, 1, message.serialize_to_vec());
+            chunk.sign(&Secp256k1PrivateKey::new()).unwrap();
+            serde_json::to_string(&StackerDBChunksEvent {
+                contract_id: contract_id.clone(),
+                modified_slots: vec![chunk],
+            })
+            .unwrap()
+        })
+        .collect();
+
+    // a node that stalls halfway through the first StackerDB event. tiny_http reads bodies of
+    // up to 1024 bytes before handing the request over, so pad this one past that.
+    let slow_body = format!("{:<2048}", chunk_events[0]);
+    let slow_node = thread::spawn(move || {
+        let mut sock = loop {
+            match TcpStream::connect(endpoint) {
+                Ok(sock) => break sock,
+                Err(..) => sleep_ms(100),
+            }
+        };
+        let req = format!(
+            "POST /stackerdb_chunks HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            endpoint,
+            slow_body.len(),
+        );
+        let (first_half, second_half) = slow_body.split_at(slow_body.len() / 2);
+        sock.write_all(req.as_bytes()).unwrap();
+        sock.write_all(first_half.as_bytes()).unwrap();
+        sock.flush().unwrap();
+        sleep_ms(2000);
+        sock.write_all(second_half.as_bytes()).unwrap();
+        let mut res = String::new();
+        sock.read_to_string(&mut res).unwrap();
+    });
+    sleep_ms(500);
+
+    let burn_block = "{\"burn_block_hash\": \"0x00\", \"burn_block_height\": 5, \"burn_block_timestamp\": 1713195662, \"reward_recipients\": [], \"reward_slot_holders\": [], \"burn_amount\": 0}";
+    let fast_body = chunk_events[1].clone();
+    let fast_node = thread::spawn(move || {
+        assert_eq!(
+            post_event(endpoint, "/new_burn_block", burn_block),
+            "HTTP/1.1 200 OK"
+        );
+        assert_eq!(
+            post_event(endpoint, "/stackerdb_chunks", &fast_body),
+            "HTTP/1.1 200 OK"
+        );
+    });
+
+    assert_eq!(
+        ev.next_event().unwrap(),
+        SignerEvent::NewBurnBlock {
+            burn_height: 5,
+            burn_header_timestamp: Some(1713195662),
+        }
+    );
+    let slot_ids: Vec<_> = (0..2)
+        .map(|_| match ev.next_event().unwrap() {
+            SignerEvent::SignerMessages(0, messages) => messages[0].0.slot_id,
+            event => panic!("Unexpected event {event:?}"),
+        })
+        .collect();
+    assert_eq!(slot_ids, vec![0, 1]);
+
+    slow_node.join().unwrap();
+    fast_node.join().unwrap();
+}
+
+/// Take events off `ev` until it returns a new burn block, and return the block's height
+fn next_burn_height(ev: &mut SignerEventReceiver<SignerMessage>) -> u64 {
+    loop {
+        match ev.next_event() {
+            Ok(SignerEvent::NewBurnBlock { burn_height, .. }) => return burn_height,
+            Ok(event) => panic!("Unexpected event {event:?}"),
+            // e.g. the rebinder's wake-up request
+            Err(_) => continue,
+        }
+    }
+}
+
+/// Wait until nothing listens on `endpoint` anymore
+fn wait_for_closed(endpoint: SocketAddr) {
+    for _ in 0..50 {
+        if TcpStream::connect(endpoint).is_err() {
+            return;
+        }
+        sleep_ms(100);
+    }
+    panic!("{endpoint} is still open");
+}
+
+/// The event receiver moves to a new address without losing the events that reached the old
+/// one, and keeps serving the old one until it is closed or goes idle, with and without a
+/// worker pool
+#[test]
+fn test_rebind_listener() {
+    let burn_block = |height: u64| {
+        format!("{{\"burn_block_hash\": \"0x00\", \"burn_block_height\": {height}, \"burn_block_timestamp\": 1713195662, \"reward_recipients\": [], \"reward_slot_holders\": [], \"burn_amount\": 0}}")
+    };
+
+    for (worker_threads, port) in [(1, 31700), (3, 31710)] {
+        let old_endpoint: SocketAddr = format!("127.0.0.1:{port}").parse().unwrap();
+        let new_endpoint: SocketAddr = format!("127.0.0.1:{}", port + 1).parse().unwrap();
+        let taken_endpoint: SocketAddr = format!("127.0.0.1:{}", port + 2).parse().unwrap();
+        let last_endpoint: SocketAddr = format!("127.0.0.1:{}", port + 3).parse().unwrap();
+        let mut ev = SignerEventReceiver::<SignerMessage>::new(false)
+            .with_worker_threads(worker_threads)
+            .with_retired_listener_idle(Duration::from_secs(3));
+        let rebinder = ev.listener_rebinder();
+        assert!(matches!(
+            rebinder.rebind(new_endpoint),
+            Err(EventError::NotBound)
+        ));
+        ev.bind(old_endpoint).unwrap();
+
+        // an event is in flight on the old listener when the rebind is asked for
+        let old_node =
+            thread::spawn(move || post_event(old_endpoint, "/new_burn_block", &burn_block(5)));
+        sleep_ms(500);
+        let rebind = {
+            let rebinder = rebinder.clone();
+            thread::spawn(move || rebinder.rebind(new_endpoint))
+        };
+        assert_eq!(next_burn_height(&mut ev), 5);
+        assert_eq!(old_node.join().unwrap(), "HTTP/1.1 200 OK");
+
+        // later events arrive on the new listener
+        let new_node =
+            thread::spawn(move || post_event(new_endpoint, "/new_burn_block", &burn_block(6)));
+        assert_eq!(next_burn_height(&mut ev), 6);
+        assert_eq!(new_node.join().unwrap(), "HTTP/1.1 200 OK");
+        assert_eq!(rebind.join().unwrap().unwrap(), new_endpoint);
+        assert_eq!(rebinder.local_addr(), Some(new_endpoint));
+        assert_eq!(rebinder.retired_addr(), Some(old_endpoint));
+
+        // a node that has not caught up yet still reaches the old listener
+        let old_node =
+            thread::spawn(move || post_event(old_endpoint, "/new_burn_block", &burn_block(7)));
+        assert_eq!(next_burn_height(&mut ev), 7);
+        assert_eq!(old_node.join().unwrap(), "HTTP/1.1 200 OK");
+
+        // until the old listener is closed
+        let close = {
+            let rebinder = rebinder.clone();
+            thread::spawn(move || rebinder.close_retired())
+        };
+        let new_node = thread::spawn(move || {
+            sleep_ms(500);
+            post_event(new_endpoint, "/new_burn_block", &burn_block(8))
+        });
+        assert_eq!(next_burn_height(&mut ev), 8);
+        new_node.join().unwrap();
+        assert_eq!(close.join().unwrap().unwrap(), Some(old_endpoint));
+        wait_for_closed(old_endpoint);
+        assert_eq!(rebinder.retired_addr(), None);
+
+        // the receiver stays where it is if it cannot bind to the new address
+        let _taken = std::net::TcpListener::bind(taken_endpoint).unwrap();
+        let rebind = {
+            let rebinder = rebinder.clone();
+            thread::spawn(move || rebinder.rebind(taken_endpoint))
+        };
+        let new_node = thread::spawn(move || {
+            sleep_ms(500);
+            post_event(new_endpoint, "/new_burn_block", &burn_block(9))
+        });
+        assert_eq!(next_burn_height(&mut ev), 9);
+        assert!(rebind.join().unwrap().is_err());
+        assert_eq!(rebinder.local_addr(), Some(new_endpoint));
+        new_node.join().unwrap();
+
+        // an old listener that nothing reaches any more is closed once it has been idle
+        let rebind = {
+            let rebinder = rebinder.clone();
+            thread::spawn(move || rebinder.rebind(last_endpoint))
+        };
+        let last_node = thread::spawn(move || {
+            sleep_ms(500);
+            post_event(last_endpoint, "/new_burn_block", &burn_block(10))
+        });
+        assert_eq!(next_burn_height(&mut ev), 10);
+        assert_eq!(rebind.join().unwrap().unwrap(), last_endpoint);
+        last_node.join().unwrap();
+        assert_eq!(rebinder.retired_addr(), Some(new_endpoint));
+        let last_node = thread::spawn(move || {
+            sleep_ms(4000);
+            post_event(last_endpoint, "/new_burn_block", &burn_block(11))
+        });
+        assert_eq!(next_burn_height(&mut ev), 11);
+        last_node.join().unwrap();
+        wait_for_closed(new_endpoint);
+        assert_eq!(rebinder.retired_addr(), None);
+    }
+}
+
+#[test]
+fn test_signer_entries_weighted_thresholds() {
+    let reward_set: Vec<_> = [1, 5, 4]
+        .iter()
+        .map(|weight| NakamotoSignerEntry {
+            signing_key: StacksPublicKey::from_private(&Secp256k1PrivateKey::new())
+                .to_bytes_compressed()
+                .try_into()
+                .unwrap(),
+            stacked_amt: 0,
+            weight: *weight,
+        })
+        .collect();
+    let entries = SignerEntries::parse(false, &reward_set).unwrap();
+
+    assert_eq!(entries.signer_weights().get(&1), Some(&5));
+    assert_eq!(entries.total_weight().unwrap(), 10);
+    assert_eq!(entries.count_keys().unwrap(), 10);
+    // ceil(10 * 70 / 100) and ceil(10 * 90 / 100)
+    assert_eq!(entries.get_signing_threshold().unwrap(), 7);
+    assert_eq!(entries.get_dkg_threshold().unwrap(), 9);
+
+    // thresholds round up, like the signers voting contract
+    let reward_set: Vec<_> = reward_set
+        .into_iter()
+        .map(|entry| NakamotoSignerEntry { weight: 1, ..entry })
+        .collect();
+    let entries = SignerEntries::parse(false, &reward_set).unwrap();
+    assert_eq!(entries.total_weight().unwrap(), 3);
+    assert_eq!(entries.get_signing_threshold().unwrap(), 3);
+    assert_eq!(entries.get_dkg_threshold().unwrap(), 3);
+}
@@ -227,7 +227,13 @@ RejectCodeTypePrefix {
     /// Nonce timeout
     NonceTimeout = 5,
     /// Aggregator error
-    AggregatorError = 6
+    AggregatorError = 6,
+    /// Miner refused by the signer's policy
+    MinerRefused = 7,
+    /// Wrong reward cycle
+    RewardCycleMismatch = 8,
+    /// Signing round expired
+    RoundExpired = 9
 });
 
 impl TryFrom<u8> for RejectCodeTypePrefix {
@@ -249,6 +255,9 @@ impl From<&RejectCode> for RejectCodeTypePrefix {
             RejectCode::ConnectivityIssues => RejectCodeTypePrefix::ConnectivityIssues,
             RejectCode::NonceTimeout(_) => RejectCodeTypePrefix::NonceTimeout,
             RejectCode::AggregatorError(_) => RejectCodeTypePrefix::AggregatorError,
+            RejectCode::MinerRefused => RejectCodeTypePrefix::MinerRefused,
+            RejectCode::RewardCycleMismatch(_) => RejectCodeTypePrefix::RewardCycleMismatch,
+            RejectCode::RoundExpired => RejectCodeTypePrefix::RoundExpired,
         }
     }
 }
@@ -1249,6 +1258,20 @@ impl BlockRejection {
             signer_signature_hash,
         }
     }
+
+    /// Create a new BlockRejection for the provided block and reason code, with a detail
+    /// message appended to the reason
+    pub fn new_with_detail(
+        signer_signature_hash: Sha512Trunc256Sum,
+        reason_code: RejectCode,
+        detail: &str,
+    ) -> Self {
+        Self {
+            reason: format!("{reason_code} {detail}"),
+            reason_code,
+            signer_signature_hash,
+        }
+    }
 }
 
 impl StacksMessageCodec for BlockRejection {
@@ -1301,6 +1324,41 @@ pub enum RejectCode {
     MissingTransactions(Vec<StacksTransaction>),
     /// The block was rejected due to connectivity issues with the signer
     ConnectivityIssues,
+    /// The signer's policy refuses to sign blocks proposed by this miner
+    MinerRefused,
+    /// The block was proposed for a reward cycle other than the one the signer signs for
+    RewardCycleMismatch(u64),
+    /// The signing round for the block expired before it completed
+    RoundExpired,
+}
+
+/// How a miner should treat a block rejection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectCategory {
+    /// The block itself is at fault, so the miner must propose a different one
+    InvalidBlock,
+    /// The rejection is not the block's fault, and proposing it again may succeed
+    Transient,
+    /// The signer refuses on policy grounds, so no block from this miner will be signed
+    Policy,
+}
+
+impl RejectCode {
+    /// How a miner should treat a rejection with this code
+    pub fn category(&self) -> RejectCategory {
+        match self {
+            RejectCode::ValidationFailed(_)
+            | RejectCode::SignedRejection(_)
+            | RejectCode::MissingTransactions(_)
+            | RejectCode::RewardCycleMismatch(_) => RejectCategory::InvalidBlock,
+            RejectCode::NonceTimeout(_)
+            | RejectCode::InsufficientSigners(_)
+            | RejectCode::AggregatorError(_)
+            | RejectCode::ConnectivityIssues
+            | RejectCode::RoundExpired => RejectCategory::Transient,
+            RejectCode::MinerRefused => RejectCategory::Policy,
+        }
+    }
 }
 
 impl From<&SignError> for RejectCode {
@@ -1330,6 +1388,10 @@ impl StacksMessageCodec for RejectCode {
             }
             RejectCode::AggregatorError(reason) => write_next(fd, &reason.as_bytes().to_vec())?,
             RejectCode::ConnectivityIssues => write_next(fd, &4u8)?,
+            RejectCode::RewardCycleMismatch(reward_cycle) => write_next(fd, reward_cycle)?,
+            RejectCode::MinerRefused | RejectCode::RoundExpired => {
+                // No additional data to serialize / deserialize
+            }
         };
         Ok(())
     }
@@ -1369,6 +1431,11 @@ impl StacksMessageCodec for RejectCode {
                 })?;
                 RejectCode::AggregatorError(reason)
             }
+            RejectCodeTypePrefix::MinerRefused => RejectCode::MinerRefused,
+            RejectCodeTypePrefix::RewardCycleMismatch => {
+                RejectCode::RewardCycleMismatch(read_next::<u64, _>(fd)?)
+            }
+            RejectCodeTypePrefix::RoundExpired => RejectCode::RoundExpired,
         };
         Ok(code)
     }
@@ -1405,6 +1472,19 @@ impl std::fmt::Display for RejectCode {
                 "An internal error occurred in the signer when aggregating the signaure: {:?}",
                 reason
             ),
+            RejectCode::MinerRefused => write!(
+                f,
+                "The signer's policy refuses to sign blocks proposed by this miner."
+            ),
+            RejectCode::RewardCycleMismatch(reward_cycle) => write!(
+                f,
+                "The block was not proposed for the signer's reward cycle ({}).",
+                reward_cycle
+            ),
+            RejectCode::RoundExpired => write!(
+                f,
+                "The signing round for the block expired before it completed."
+            ),
         }
     }
 }
@@ -1520,6 +1600,56 @@ mod test {
         let deserialized_code = read_next::<RejectCode, _>(&mut &serialized_code[..])
             .expect("Failed to deserialize RejectCode");
         assert_eq!(code, deserialized_code);
+
+        for code in [
+            RejectCode::MinerRefused,
+            RejectCode::RewardCycleMismatch(3),
+            RejectCode::RoundExpired,
+        ] {
+            let serialized_code = code.serialize_to_vec();
+            let deserialized_code = read_next::<RejectCode, _>(&mut &serialized_code[..])
+                .expect("Failed to deserialize RejectCode");
+            assert_eq!(code, deserialized_code);
+        }
+    }
+
+    #[test]
+    fn reject_code_category() {
+        assert_eq!(
+            RejectCode::ValidationFailed(ValidateRejectCode::InvalidBlock).category(),
+            RejectCategory::InvalidBlock
+        );
+        assert_eq!(
+            RejectCode::RewardCycleMismatch(3).category(),
+            RejectCategory::InvalidBlock
+        );
+        assert_eq!(
+            RejectCode::NonceTimeout(vec![1]).category(),
+            RejectCategory::Transient
+        );
+        assert_eq!(
+            RejectCode::RoundExpired.category(),
+            RejectCategory::Transient
+        );
+        assert_eq!(RejectCode::MinerRefused.category(), RejectCategory::Policy);
+    }
+
+    #[test]
+    fn serde_block_rejection_with_detail() {
+        let rejection = BlockRejection::new_with_detail(
+            Sha512Trunc256Sum([3u8; 32]),
+            RejectCode::MinerRefused,
+            "(miner key is denylisted)",
+        );
+        assert!(rejection.reason.ends_with("(miner key is denylisted)"));
+        let serialized_rejection = rejection.serialize_to_vec();
+        let deserialized_rejection = read_next::<BlockRejection, _>(&mut &serialized_rejection[..])
+            .expect("Failed to deserialize BlockRejection");
+        assert_eq!(rejection, deserialized_rejection);
+        assert_eq!(
+            deserialized_rejection.reason_code.category(),
+            RejectCategory::Policy
+        );
     }
 
     #[test]
@@ -55,6 +55,16 @@ pub enum Command {
     GenerateStackingSignature(GenerateStackingSignatureArgs),
     /// Check a configuration file and output config information
     CheckConfig(RunSignerArgs),
+    /// Encrypt a Stacks private key for use as `encrypted_stacks_private_key` in the config file
+    EncryptPrivateKey(EncryptPrivateKeyArgs),
+    /// Export a running signer's aggregate public key and DKG public shares for a reward cycle
+    DumpDkgKeys(DkgKeyRpcArgs),
+    /// Make a running signer use a known-good aggregate public key for a reward cycle
+    ImportAggregateKey(ImportAggregateKeyArgs),
+    /// Move a running signer's event receiver to another address without restarting it
+    RebindListener(RebindListenerArgs),
+    /// Stop serving the address a running signer's event receiver moved away from
+    CloseOldListener(OperatorRpcArgs),
 }
 
 /// Basic arguments for all cyrptographic and stacker-db functionality
@@ -196,6 +206,67 @@ pub struct GenerateStackingSignatureArgs {
     pub json: bool,
 }
 
+#[derive(Parser, Debug, Clone)]
+/// Arguments for the encrypt-private-key command
+pub struct EncryptPrivateKeyArgs {
+    /// The Stacks private key to encrypt, in hexadecimal format
+    #[arg(short, long, value_parser = parse_private_key)]
+    pub private_key: StacksPrivateKey,
+    /// File holding the passphrase (or raw key) to encrypt with.
+    /// If not given, it is read from the `STACKS_SIGNER_KEY_PASSPHRASE` environment variable.
+    #[arg(long, value_name = "FILE")]
+    pub passphrase_file: Option<PathBuf>,
+    /// Treat the passphrase as a hex-encoded 32-byte key (e.g. a data key issued by a KMS)
+    #[arg(long, action=ArgAction::SetTrue, required=false)]
+    pub raw_key: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+/// Arguments for requests to a running signer's operator RPC
+pub struct OperatorRpcArgs {
+    /// The signer's `operator_rpc_socket`
+    #[arg(long, value_name = "SOCKET")]
+    pub socket: PathBuf,
+    /// File holding the signer's `operator_rpc_auth_token`.
+    /// If not given, it is read from the `STACKS_SIGNER_OPERATOR_RPC_AUTH_TOKEN` environment
+    /// variable.
+    #[arg(long, value_name = "FILE")]
+    pub auth_token_file: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug, Clone)]
+/// Arguments for requests about a reward cycle's DKG keys to a running signer's operator RPC
+pub struct DkgKeyRpcArgs {
+    /// The base arguments
+    #[clap(flatten)]
+    pub rpc_args: OperatorRpcArgs,
+    /// The reward cycle whose keys to operate on
+    #[arg(short, long)]
+    pub reward_cycle: u64,
+}
+
+#[derive(Parser, Debug, Clone)]
+/// Arguments for the import-aggregate-key command
+pub struct ImportAggregateKeyArgs {
+    /// The base arguments
+    #[clap(flatten)]
+    pub rpc_args: DkgKeyRpcArgs,
+    /// The aggregate public key, as a hex-encoded compressed point
+    #[arg(long)]
+    pub aggregate_key: String,
+}
+
+#[derive(Parser, Debug, Clone)]
+/// Arguments for the rebind-listener command
+pub struct RebindListenerArgs {
+    /// The base arguments
+    #[clap(flatten)]
+    pub rpc_args: OperatorRpcArgs,
+    /// The address to receive events from the node on instead
+    #[arg(long)]
+    pub endpoint: String,
+}
+
 /// Parse the contract ID
 fn parse_contract(contract: &str) -> Result<QualifiedContractIdentifier, String> {
     QualifiedContractIdentifier::parse(contract).map_err(|e| format!("Invalid contract: {}", e))
@@ -14,21 +14,27 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+/// The node version module for tracking which optional features the stacks node serves
+mod node_version;
 /// The stacker db module for communicating with the stackerdb contract
 mod stackerdb;
 /// The stacks node client module for communicating with the stacks node
 pub(crate) mod stacks_client;
+/// The write manager module for tracking and retrying writes to this signer's stackerdb slots
+mod write_manager;
 
 use std::time::Duration;
 
 use clarity::vm::errors::Error as ClarityError;
 use clarity::vm::types::serialization::SerializationError;
 use libstackerdb::Error as StackerDBError;
+pub use node_version::*;
 use slog::slog_debug;
 pub use stackerdb::*;
 pub use stacks_client::*;
 use stacks_common::codec::Error as CodecError;
 use stacks_common::debug;
+pub use write_manager::*;
 
 /// Backoff timer initial interval in milliseconds
 const BACKOFF_INITIAL_INTERVAL: u64 = 128;
@@ -50,6 +56,9 @@ pub enum ClientError {
     /// Stacker-db instance rejected the chunk
     #[error("Stacker-db rejected the chunk. Reason: {0}")]
     PutChunkRejected(String),
+    /// Stacker-db kept rejecting the chunk's slot version
+    #[error("Gave up writing to stacker-db slot after {0} consecutive version conflicts")]
+    PersistentSlotConflict(u32),
     /// Failed to call a read only function
     #[error("Failed to call read only function. {0}")]
     ReadOnlyFailure(String),
@@ -115,7 +124,7 @@ pub(crate) mod tests {
 
     use blockstack_lib::chainstate::stacks::boot::POX_4_NAME;
     use blockstack_lib::net::api::getaccount::AccountEntryResponse;
-    use blockstack_lib::net::api::getinfo::RPCPeerInfoData;
+    use blockstack_lib::net::api::getinfo::{RPCPeerInfoData, RPC_FEATURES};
     use blockstack_lib::net::api::getpoxinfo::{
         RPCPoxCurrentCycleInfo, RPCPoxEpoch, RPCPoxInfoData, RPCPoxNextCycleInfo,
     };
@@ -141,6 +150,8 @@ pub(crate) mod tests {
 
     use super::*;
     use crate::config::{GlobalConfig, SignerConfig};
+    use crate::dkg_keys::DkgKeyRegistry;
+    use crate::message_signing::MessageSigningRegistry;
 
     pub struct MockServerClient {
         pub server: TcpListener,
@@ -387,6 +398,9 @@ pub(crate) mod tests {
                     .map(|cid| format!("{}", cid))
                     .collect(),
             ),
+            burnchain_backfill: None,
+            burnchain_sync: None,
+            features: Some(RPC_FEATURES),
         };
         let peer_info_json =
             serde_json::to_string(&peer_info).expect("Failed to serialize peer info");
@@ -469,9 +483,8 @@ pub(crate) mod tests {
         let mut end_key_id = start_key_id;
         let mut signer_public_keys = HashMap::new();
         let mut signer_slot_ids = vec![];
-        let ecdsa_private_key = config.ecdsa_private_key;
-        let ecdsa_public_key =
-            ecdsa::PublicKey::new(&ecdsa_private_key).expect("Failed to create ecdsa public key");
+        let ecdsa_public_key = ecdsa::PublicKey::new(&config.ecdsa_private_key.scalar())
+            .expect("Failed to create ecdsa public key");
         // Key ids start from 1 hence the wrapping adds everywhere
         for signer_id in 0..num_signers {
             end_key_id = if signer_id.wrapping_add(1) == num_signers {
@@ -546,8 +559,8 @@ pub(crate) mod tests {
                 signer_public_keys,
             },
             signer_slot_ids,
-            ecdsa_private_key: config.ecdsa_private_key,
-            stacks_private_key: config.stacks_private_key,
+            ecdsa_private_key: config.ecdsa_private_key.clone(),
+            stacks_private_key: config.stacks_private_key.clone(),
             node_host: config.node_host.to_string(),
             mainnet: config.network.is_mainnet(),
             dkg_end_timeout: config.dkg_end_timeout,
@@ -557,7 +570,16 @@ pub(crate) mod tests {
             sign_timeout: config.sign_timeout,
             tx_fee_ustx: config.tx_fee_ustx,
             max_tx_fee_ustx: config.max_tx_fee_ustx,
+            dkg_vote_retry: config.dkg_vote_retry,
             db_path: config.db_path.clone(),
+            miner_key_policy: config.miner_key_policy.clone(),
+            signature_receipt_webhook: config.signature_receipt_webhook.clone(),
+            message_signing: MessageSigningRegistry::default(),
+            dkg_keys: DkgKeyRegistry::default(),
+            stale_round_max_age: config.stale_round_max_age,
+            coordinator_selection: config.coordinator_selection,
+            escalation: config.escalation.clone(),
+            participation: config.participation.for_reward_cycle(reward_cycle),
         }
     }
 
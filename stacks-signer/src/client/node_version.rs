@@ -0,0 +1,162 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use blockstack_lib::net::api::getinfo::{
+    RPCPeerInfoData, RPC_FEATURE_BLOCK_PROPOSAL, RPC_FEATURE_STACKERDB, RPC_FEATURE_STACKER_SET,
+};
+
+/// An optional RPC feature of the stacks node that the signer relies on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeFeature {
+    /// The StackerDB API, over which signers exchange messages
+    StackerDB,
+    /// Reward set queries, used to find the registered signers
+    RewardSet,
+    /// Block proposal validation
+    BlockProposal,
+}
+
+impl NodeFeature {
+    /// Every feature a signer needs in order to sign
+    pub const REQUIRED: [NodeFeature; 3] = [
+        NodeFeature::StackerDB,
+        NodeFeature::RewardSet,
+        NodeFeature::BlockProposal,
+    ];
+
+    /// This feature's bit in the feature bitfield the node reports in `/v2/info`
+    pub const fn bit(&self) -> u64 {
+        match self {
+            NodeFeature::StackerDB => RPC_FEATURE_STACKERDB,
+            NodeFeature::RewardSet => RPC_FEATURE_STACKER_SET,
+            NodeFeature::BlockProposal => RPC_FEATURE_BLOCK_PROPOSAL,
+        }
+    }
+
+    /// The endpoint that serves this feature
+    pub const fn endpoint(&self) -> &'static str {
+        match self {
+            NodeFeature::StackerDB => "/v2/stackerdb",
+            NodeFeature::RewardSet => "/v2/stacker_set",
+            NodeFeature::BlockProposal => "/v2/block_proposal",
+        }
+    }
+}
+
+impl fmt::Display for NodeFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} ({})", self, self.endpoint())
+    }
+}
+
+/// The version of the stacks node the signer talks to, and the features it serves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeVersion {
+    /// The node's version string
+    pub server_version: String,
+    /// The node's peer network protocol version
+    pub peer_version: u32,
+    /// The feature bitfield the node advertises, or `None` if the node predates feature
+    /// advertisement
+    pub features: Option<u64>,
+    /// Bits of the features whose endpoints turned out not to exist on this node
+    missing: u64,
+}
+
+impl From<&RPCPeerInfoData> for NodeVersion {
+    fn from(peer_info: &RPCPeerInfoData) -> Self {
+        Self {
+            server_version: peer_info.server_version.clone(),
+            peer_version: peer_info.peer_version,
+            features: peer_info.features,
+            missing: 0,
+        }
+    }
+}
+
+impl NodeVersion {
+    /// Whether the node may serve `feature`. A node that does not advertise its features is
+    /// assumed to serve it until a request to its endpoint fails with a 404.
+    pub fn supports(&self, feature: NodeFeature) -> bool {
+        if self.missing & feature.bit() != 0 {
+            return false;
+        }
+        self.features
+            .map_or(true, |features| features & feature.bit() != 0)
+    }
+
+    /// Record that the node does not serve `feature`
+    pub fn mark_missing(&mut self, feature: NodeFeature) {
+        self.missing |= feature.bit();
+    }
+
+    /// Whether `other` describes the same node release, in which case everything learned
+    /// about this one still applies
+    pub fn same_release(&self, other: &NodeVersion) -> bool {
+        self.server_version == other.server_version
+            && self.peer_version == other.peer_version
+            && self.features == other.features
+    }
+
+    /// The features required for signing that the node does not serve
+    pub fn missing_required(&self) -> Vec<NodeFeature> {
+        NodeFeature::REQUIRED
+            .into_iter()
+            .filter(|feature| !self.supports(*feature))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockstack_lib::net::api::getinfo::RPC_FEATURES;
+
+    use super::*;
+    use crate::client::tests::build_get_peer_info_response;
+
+    #[test]
+    fn advertised_features_are_supported() {
+        let (_, mut peer_info) = build_get_peer_info_response(None, None);
+        peer_info.features = Some(RPC_FEATURES);
+        let version = NodeVersion::from(&peer_info);
+        assert!(version.missing_required().is_empty());
+
+        peer_info.features = Some(RPC_FEATURE_STACKERDB | RPC_FEATURE_BLOCK_PROPOSAL);
+        let version = NodeVersion::from(&peer_info);
+        assert!(version.supports(NodeFeature::StackerDB));
+        assert!(!version.supports(NodeFeature::RewardSet));
+        assert_eq!(version.missing_required(), vec![NodeFeature::RewardSet]);
+    }
+
+    #[test]
+    fn legacy_node_features_are_learned_from_404s() {
+        let (_, mut peer_info) = build_get_peer_info_response(None, None);
+        peer_info.features = None;
+        let mut version = NodeVersion::from(&peer_info);
+        assert!(version.missing_required().is_empty());
+
+        version.mark_missing(NodeFeature::BlockProposal);
+        assert!(!version.supports(NodeFeature::BlockProposal));
+        assert!(version.supports(NodeFeature::RewardSet));
+        // what was learned still applies to the same release...
+        assert!(version.same_release(&NodeVersion::from(&peer_info)));
+        // ...but not once the node is upgraded
+        peer_info.server_version = "stacks-node 3.0.0.0.0".into();
+        assert!(!version.same_release(&NodeVersion::from(&peer_info)));
+    }
+}
@@ -27,8 +27,9 @@ use stacks_common::{debug, error, warn};
 use wsts::net::Packet;
 
 use super::ClientError;
-use crate::client::retry_with_exponential_backoff;
+use crate::client::{retry_with_exponential_backoff, SlotWriteManager};
 use crate::config::SignerConfig;
+use crate::secrets::Secret;
 
 /// The signer StackerDB slot ID, purposefully wrapped to prevent conflation with SignerID
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, PartialOrd, Ord)]
@@ -47,9 +48,9 @@ pub struct StackerDB {
     /// Maps message ID to the DB session.
     signers_message_stackerdb_sessions: HashMap<MessageSlotID, StackerDBSession>,
     /// The private key used in all stacks node communications
-    stacks_private_key: StacksPrivateKey,
-    /// A map of a message ID to last chunk version for each session
-    slot_versions: HashMap<MessageSlotID, HashMap<SignerSlotID, u32>>,
+    stacks_private_key: Secret,
+    /// Tracks the versions of this signer's slots and paces retries on version conflicts
+    write_manager: SlotWriteManager,
     /// The signer slot ID -- the index into the signer list for this signer daemon's signing key.
     signer_slot_id: SignerSlotID,
     /// The reward cycle of the connecting signer
@@ -62,7 +63,7 @@ impl From<&SignerConfig> for StackerDB {
     fn from(config: &SignerConfig) -> Self {
         Self::new(
             &config.node_host,
-            config.stacks_private_key,
+            config.stacks_private_key.stacks_private_key(),
             config.mainnet,
             config.reward_cycle,
             config.signer_slot_id,
@@ -93,8 +94,8 @@ impl StackerDB {
 
         Self {
             signers_message_stackerdb_sessions,
-            stacks_private_key,
-            slot_versions: HashMap::new(),
+            stacks_private_key: Secret::new(&stacks_private_key),
+            write_manager: SlotWriteManager::default(),
             signer_slot_id,
             reward_cycle,
             next_transaction_session,
@@ -120,22 +121,9 @@ impl StackerDB {
     ) -> Result<StackerDBChunkAckData, ClientError> {
         let slot_id = self.signer_slot_id;
         loop {
-            let mut slot_version = if let Some(versions) = self.slot_versions.get_mut(msg_id) {
-                if let Some(version) = versions.get(&slot_id) {
-                    *version
-                } else {
-                    versions.insert(slot_id, 0);
-                    1
-                }
-            } else {
-                let mut versions = HashMap::new();
-                versions.insert(slot_id, 0);
-                self.slot_versions.insert(*msg_id, versions);
-                1
-            };
-
+            let slot_version = self.write_manager.next_version(msg_id);
             let mut chunk = StackerDBChunkData::new(slot_id.0, slot_version, message_bytes.clone());
-            chunk.sign(&self.stacks_private_key)?;
+            chunk.sign(&self.stacks_private_key.stacks_private_key())?;
 
             let Some(session) = self.signers_message_stackerdb_sessions.get_mut(msg_id) else {
                 panic!("FATAL: would loop forever trying to send a message with ID {}, for which we don't have a session", msg_id);
@@ -147,46 +135,48 @@ impl StackerDB {
             );
 
             let send_request = || session.put_chunk(&chunk).map_err(backoff::Error::transient);
-            let chunk_ack: StackerDBChunkAckData = retry_with_exponential_backoff(send_request)?;
-
-            if let Some(versions) = self.slot_versions.get_mut(msg_id) {
-                // NOTE: per the above, this is always executed
-                versions.insert(slot_id, slot_version.saturating_add(1));
-            } else {
-                return Err(ClientError::NotConnected);
-            }
+            let chunk_ack: StackerDBChunkAckData =
+                match retry_with_exponential_backoff(send_request) {
+                    Ok(chunk_ack) => chunk_ack,
+                    Err(e) => {
+                        self.write_manager.record_error(msg_id);
+                        return Err(e);
+                    }
+                };
 
             if chunk_ack.accepted {
                 debug!("Chunk accepted by stackerdb: {chunk_ack:?}");
+                self.write_manager.record_accepted(msg_id, slot_version);
                 return Ok(chunk_ack);
             } else {
                 warn!("Chunk rejected by stackerdb: {chunk_ack:?}");
             }
-            if let Some(code) = chunk_ack.code {
-                match StackerDBErrorCodes::from_code(code) {
-                    Some(StackerDBErrorCodes::DataAlreadyExists) => {
-                        if let Some(slot_metadata) = chunk_ack.metadata {
-                            warn!("Failed to send message to stackerdb due to wrong version number. Attempted {}. Expected {}. Retrying...", slot_version, slot_metadata.slot_version);
-                            slot_version = slot_metadata.slot_version;
-                        } else {
-                            warn!("Failed to send message to stackerdb due to wrong version number. Attempted {}. Expected unknown version number. Incrementing and retrying...", slot_version);
-                        }
-                        if let Some(versions) = self.slot_versions.get_mut(msg_id) {
-                            // NOTE: per the above, this is always executed
-                            versions.insert(slot_id, slot_version.saturating_add(1));
-                        } else {
-                            return Err(ClientError::NotConnected);
-                        }
-                    }
-                    _ => {
-                        warn!("Failed to send message to stackerdb: {:?}", chunk_ack);
-                        return Err(ClientError::PutChunkRejected(
-                            chunk_ack
-                                .reason
-                                .unwrap_or_else(|| "No reason given".to_string()),
-                        ));
+            // Nodes which predate error codes only reject chunks for their version
+            let code = chunk_ack
+                .code
+                .map(StackerDBErrorCodes::from_code)
+                .unwrap_or(Some(StackerDBErrorCodes::DataAlreadyExists));
+            match code {
+                Some(StackerDBErrorCodes::DataAlreadyExists) => {
+                    let stored_version = chunk_ack.metadata.map(|md| md.slot_version);
+                    warn!("Failed to send message to stackerdb due to wrong version number. Attempted {slot_version}. Expected {stored_version:?}. Retrying...");
+                    let wait =
+                        self.write_manager
+                            .record_conflict(msg_id, slot_version, stored_version)?;
+                    if !wait.is_zero() {
+                        debug!("Backing off for {wait:?} before retrying stackerdb slot {msg_id}");
+                        std::thread::sleep(wait);
                     }
                 }
+                _ => {
+                    warn!("Failed to send message to stackerdb: {:?}", chunk_ack);
+                    self.write_manager.record_rejected(msg_id);
+                    return Err(ClientError::PutChunkRejected(
+                        chunk_ack
+                            .reason
+                            .unwrap_or_else(|| "No reason given".to_string()),
+                    ));
+                }
             }
         }
     }
@@ -219,6 +209,26 @@ impl StackerDB {
         Ok(messages)
     }
 
+    /// Claim this signer's slots ahead of its reward cycle by learning the versions they
+    /// already hold, so that its first writes of the reward cycle are not rejected as stale.
+    /// Returns the number of slots that already hold a chunk.
+    pub fn sync_slot_versions(&mut self) -> Result<usize, ClientError> {
+        let slot_id = self.signer_slot_id.0;
+        let mut synced = 0;
+        for (msg_id, session) in self.signers_message_stackerdb_sessions.iter_mut() {
+            let send_request = || session.list_chunks().map_err(backoff::Error::transient);
+            let slots = retry_with_exponential_backoff(send_request)?;
+            let Some(slot) = slots.iter().find(|slot| slot.slot_id == slot_id) else {
+                continue;
+            };
+            if slot.slot_version > 0 {
+                self.write_manager.record_stored(msg_id, slot.slot_version);
+                synced += 1;
+            }
+        }
+        Ok(synced)
+    }
+
     /// Get the ordered DKG packets from stackerdb for the signer slot IDs.
     pub fn get_dkg_packets(
         &mut self,
@@ -355,6 +365,8 @@ mod tests {
         TransactionSmartContract, TransactionVersion,
     };
     use blockstack_lib::util_lib::strings::StacksString;
+    use libstackerdb::SlotMetadata;
+    use stacks_common::util::hash::Sha512Trunc256Sum;
 
     use super::*;
     use crate::client::tests::{generate_signer_config, mock_server_from_config, write_response};
@@ -442,4 +454,56 @@ mod tests {
         write_response(mock_server, response_bytes.as_slice());
         assert_eq!(ack, h.join().unwrap().unwrap());
     }
+
+    #[test]
+    fn send_signer_message_should_retry_with_refreshed_version() {
+        let config = GlobalConfig::load_from_file("./src/tests/conf/signer-1.toml").unwrap();
+        let signer_config = generate_signer_config(&config, 5, 20);
+        let mut stackerdb = StackerDB::from(&signer_config);
+
+        let signer_message = SignerMessage::Transactions(vec![]);
+        let conflict = StackerDBChunkAckData {
+            accepted: false,
+            reason: Some("Data for this slot and version already exist".into()),
+            metadata: Some(SlotMetadata::new_unsigned(
+                signer_config.signer_slot_id.0,
+                7,
+                Sha512Trunc256Sum([0u8; 32]),
+            )),
+            code: Some(StackerDBErrorCodes::DataAlreadyExists.code()),
+        };
+        let ack = StackerDBChunkAckData {
+            accepted: true,
+            reason: None,
+            metadata: None,
+            code: None,
+        };
+
+        let mock_server = mock_server_from_config(&config);
+        let h = spawn(move || {
+            let ack = stackerdb.send_message_with_retry(signer_message);
+            (ack, stackerdb)
+        });
+        let mut response_bytes = b"HTTP/1.1 200 OK\n\n".to_vec();
+        let payload = serde_json::to_string(&conflict).expect("Failed to serialize ack");
+        response_bytes.extend(payload.as_bytes());
+        std::thread::sleep(Duration::from_millis(500));
+        write_response(mock_server, response_bytes.as_slice());
+
+        let mock_server = mock_server_from_config(&config);
+        let mut response_bytes = b"HTTP/1.1 200 OK\n\n".to_vec();
+        let payload = serde_json::to_string(&ack).expect("Failed to serialize ack");
+        response_bytes.extend(payload.as_bytes());
+        write_response(mock_server, response_bytes.as_slice());
+
+        // the retry went out with the version after the one the node reported
+        let (result, stackerdb) = h.join().unwrap();
+        assert_eq!(ack, result.unwrap());
+        assert_eq!(
+            stackerdb
+                .write_manager
+                .next_version(&MessageSlotID::Transactions),
+            9
+        );
+    }
 }
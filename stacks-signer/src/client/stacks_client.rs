@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 
 use blockstack_lib::burnchains::Txid;
 use blockstack_lib::chainstate::nakamoto::NakamotoBlock;
@@ -37,18 +38,20 @@ use clarity::util::hash::to_hex;
 use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier};
 use clarity::vm::{ClarityName, ContractName, Value as ClarityValue};
 use reqwest::header::AUTHORIZATION;
+use reqwest::StatusCode;
 use serde_json::json;
-use slog::slog_debug;
+use slog::{slog_debug, slog_info, slog_warn};
 use stacks_common::codec::StacksMessageCodec;
 use stacks_common::consts::{CHAIN_ID_MAINNET, CHAIN_ID_TESTNET};
-use stacks_common::debug;
 use stacks_common::types::chainstate::{StacksAddress, StacksPrivateKey, StacksPublicKey};
 use stacks_common::types::StacksEpochId;
+use stacks_common::{debug, info, warn};
 use wsts::curve::point::{Compressed, Point};
 
-use crate::client::{retry_with_exponential_backoff, ClientError};
+use crate::client::{retry_with_exponential_backoff, ClientError, NodeFeature, NodeVersion};
 use crate::config::GlobalConfig;
 use crate::runloop::RewardCycleInfo;
+use crate::secrets::Secret;
 
 /// The Stacks signer client used to communicate with the stacks node
 #[derive(Clone, Debug)]
@@ -56,7 +59,7 @@ pub struct StacksClient {
     /// The stacks address of the signer
     stacks_address: StacksAddress,
     /// The private key used in all stacks node communications
-    stacks_private_key: StacksPrivateKey,
+    stacks_private_key: Secret,
     /// The stacks node HTTP base endpoint
     http_origin: String,
     /// The types of transactions
@@ -69,12 +72,15 @@ pub struct StacksClient {
     stacks_node_client: reqwest::blocking::Client,
     /// the auth password for the stacks node
     auth_password: String,
+    /// The stacks node's version and features, as of the last `/v2/info` response.
+    /// Shared between clones, since they all talk to the same node.
+    node_version: Arc<Mutex<Option<NodeVersion>>>,
 }
 
 impl From<&GlobalConfig> for StacksClient {
     fn from(config: &GlobalConfig) -> Self {
         Self {
-            stacks_private_key: config.stacks_private_key,
+            stacks_private_key: config.stacks_private_key.clone(),
             stacks_address: config.stacks_address,
             http_origin: format!("http://{}", config.node_host),
             tx_version: config.network.to_transaction_version(),
@@ -82,6 +88,7 @@ impl From<&GlobalConfig> for StacksClient {
             stacks_node_client: reqwest::blocking::Client::new(),
             mainnet: config.network.is_mainnet(),
             auth_password: config.auth_password.clone(),
+            node_version: Arc::new(Mutex::new(None)),
         }
     }
 }
@@ -107,7 +114,7 @@ impl StacksClient {
         };
         let stacks_address = StacksAddress::p2pkh(mainnet, &pubkey);
         Self {
-            stacks_private_key,
+            stacks_private_key: Secret::new(&stacks_private_key),
             stacks_address,
             http_origin: format!("http://{}", node_host),
             tx_version,
@@ -115,6 +122,7 @@ impl StacksClient {
             stacks_node_client: reqwest::blocking::Client::new(),
             mainnet,
             auth_password,
+            node_version: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -123,6 +131,106 @@ impl StacksClient {
         &self.stacks_address
     }
 
+    /// Ask the stacks node for its version and the optional features it serves, and cache
+    /// the answer. Returns an error if the node lacks a feature the signer requires.
+    pub fn negotiate_node_version(&self) -> Result<NodeVersion, ClientError> {
+        self.get_peer_info()?;
+        let node_version = self
+            .node_version()
+            .expect("FATAL: node version not recorded after /v2/info");
+        if let Some(feature) = node_version.missing_required().first() {
+            return Err(Self::unsupported_feature(*feature, &node_version));
+        }
+        Ok(node_version)
+    }
+
+    /// The stacks node's version and features, as of the last `/v2/info` response
+    pub fn node_version(&self) -> Option<NodeVersion> {
+        self.node_version
+            .lock()
+            .expect("FATAL: node version lock poisoned")
+            .clone()
+    }
+
+    /// Cache the node version reported by `/v2/info`. Anything learned about the previous
+    /// version is dropped if the node was upgraded or downgraded.
+    fn record_node_version(&self, peer_info: &RPCPeerInfoData) {
+        let latest = NodeVersion::from(peer_info);
+        let mut cached = self
+            .node_version
+            .lock()
+            .expect("FATAL: node version lock poisoned");
+        match cached.as_ref() {
+            Some(current) if current.same_release(&latest) => return,
+            Some(current) => {
+                info!("Stacks node version changed";
+                    "from" => &current.server_version,
+                    "to" => &latest.server_version,
+                    "peer_version" => latest.peer_version,
+                    "features" => ?latest.features,
+                );
+            }
+            None => {
+                info!("Connected to stacks node";
+                    "server_version" => &latest.server_version,
+                    "peer_version" => latest.peer_version,
+                    "features" => ?latest.features,
+                );
+            }
+        }
+        let missing = latest.missing_required();
+        if !missing.is_empty() {
+            warn!("Stacks node does not serve features the signer requires. Upgrade the node.";
+                "server_version" => &latest.server_version,
+                "missing" => ?missing,
+            );
+        }
+        *cached = Some(latest);
+    }
+
+    fn unsupported_feature(feature: NodeFeature, node_version: &NodeVersion) -> ClientError {
+        ClientError::UnsupportedStacksFeature(format!(
+            "{feature} is not served by stacks node {}",
+            node_version.server_version
+        ))
+    }
+
+    /// Fail without contacting the node if it is known not to serve `feature`
+    fn require_feature(&self, feature: NodeFeature) -> Result<(), ClientError> {
+        match self.node_version() {
+            Some(node_version) if !node_version.supports(feature) => {
+                Err(Self::unsupported_feature(feature, &node_version))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the status of a response from `feature`'s endpoint. A 404 means the node does not
+    /// serve the feature, which is remembered so that later requests fail fast.
+    fn check_feature_response(
+        &self,
+        feature: NodeFeature,
+        status: StatusCode,
+    ) -> Result<(), ClientError> {
+        if status == StatusCode::NOT_FOUND {
+            let mut cached = self
+                .node_version
+                .lock()
+                .expect("FATAL: node version lock poisoned");
+            if let Some(node_version) = cached.as_mut() {
+                warn!("Stacks node does not serve {feature}. Upgrade the node.";
+                    "server_version" => &node_version.server_version,
+                );
+                node_version.mark_missing(feature);
+                return Err(Self::unsupported_feature(feature, node_version));
+            }
+        }
+        if !status.is_success() {
+            return Err(ClientError::RequestFailure(status));
+        }
+        Ok(())
+    }
+
     /// Retrieve the signer slots stored within the stackerdb contract
     pub fn get_stackerdb_signer_slots(
         &self,
@@ -267,6 +375,7 @@ impl StacksClient {
 
     /// Submit the block proposal to the stacks node. The block will be validated and returned via the HTTP endpoint for Block events.
     pub fn submit_block_for_validation(&self, block: NakamotoBlock) -> Result<(), ClientError> {
+        self.require_feature(NodeFeature::BlockProposal)?;
         let block_proposal = NakamotoBlockProposal {
             block,
             chain_id: self.chain_id,
@@ -285,10 +394,7 @@ impl StacksClient {
 
         let response = retry_with_exponential_backoff(send_request)?;
         timer.stop_and_record();
-        if !response.status().is_success() {
-            return Err(ClientError::RequestFailure(response.status()));
-        }
-        Ok(())
+        self.check_feature_response(NodeFeature::BlockProposal, response.status())
     }
 
     /// Retrieve the approved DKG aggregate public key for the given reward cycle
@@ -375,6 +481,7 @@ impl StacksClient {
             return Err(ClientError::RequestFailure(response.status()));
         }
         let peer_info_data = response.json::<RPCPeerInfoData>()?;
+        self.record_node_version(&peer_info_data);
         Ok(peer_info_data)
     }
 
@@ -411,6 +518,7 @@ impl StacksClient {
         reward_cycle: u64,
     ) -> Result<Option<Vec<NakamotoSignerEntry>>, ClientError> {
         debug!("Getting reward set for reward cycle {reward_cycle}...");
+        self.require_feature(NodeFeature::RewardSet)?;
         let timer = crate::monitoring::new_rpc_call_timer(
             &self.reward_set_path(reward_cycle),
             &self.http_origin,
@@ -423,9 +531,7 @@ impl StacksClient {
         };
         let response = retry_with_exponential_backoff(send_request)?;
         timer.stop_and_record();
-        if !response.status().is_success() {
-            return Err(ClientError::RequestFailure(response.status()));
-        }
+        self.check_feature_response(NodeFeature::RewardSet, response.status())?;
         let stackers_response = response.json::<GetStackersResponse>()?;
         Ok(stackers_response.stacker_set.signers)
     }
@@ -545,7 +651,7 @@ impl StacksClient {
             contract_name,
             function_name,
             &function_args,
-            &self.stacks_private_key,
+            &self.stacks_private_key.stacks_private_key(),
             self.tx_version,
             self.chain_id,
             nonce,
@@ -709,7 +815,7 @@ impl StacksClient {
     ) -> Result<StacksTransaction, ClientError> {
         let mut tx_signer = StacksTransactionSigner::new(&unsigned_tx);
         tx_signer
-            .sign_origin(&self.stacks_private_key)
+            .sign_origin(&self.stacks_private_key.stacks_private_key())
             .map_err(|e| ClientError::TransactionGenerationFailure(e.to_string()))?;
 
         tx_signer
@@ -732,6 +838,7 @@ mod tests {
     use blockstack_lib::chainstate::stacks::boot::{
         NakamotoSignerEntry, PoxStartCycleInfo, RewardSet,
     };
+    use blockstack_lib::net::api::getinfo::{RPC_FEATURE_BLOCK_PROPOSAL, RPC_FEATURE_STACKERDB};
     use clarity::vm::types::{
         ListData, ListTypeData, ResponseData, SequenceData, TupleData, TupleTypeSignature,
         TypeSignature,
@@ -1256,6 +1363,61 @@ mod tests {
         assert_eq!(h.join().unwrap().unwrap(), peer_info);
     }
 
+    #[test]
+    fn negotiate_node_version_should_fail_on_missing_feature() {
+        let mock = MockServerClient::new();
+        let (_, mut peer_info) = build_get_peer_info_response(None, None);
+        peer_info.features = Some(RPC_FEATURE_STACKERDB | RPC_FEATURE_BLOCK_PROPOSAL);
+        let peer_info_json = serde_json::to_string(&peer_info).unwrap();
+        let response = format!("HTTP/1.1 200 OK\n\n{peer_info_json}");
+        let h = spawn(move || {
+            let negotiated = mock.client.negotiate_node_version();
+            // the node is known not to serve reward sets, so this never reaches the node
+            let reward_set = mock.client.get_reward_set_signers(0);
+            (negotiated, reward_set, mock.client.node_version())
+        });
+        write_response(mock.server, response.as_bytes());
+        let (negotiated, reward_set, node_version) = h.join().unwrap();
+        assert!(matches!(
+            negotiated,
+            Err(ClientError::UnsupportedStacksFeature(_))
+        ));
+        assert!(matches!(
+            reward_set,
+            Err(ClientError::UnsupportedStacksFeature(_))
+        ));
+        assert_eq!(node_version, Some(NodeVersion::from(&peer_info)));
+    }
+
+    #[test]
+    fn legacy_node_reward_set_404_should_fail_fast() {
+        let mock = MockServerClient::new();
+        let (_, mut peer_info) = build_get_peer_info_response(None, None);
+        peer_info.features = None;
+        let peer_info_json = serde_json::to_string(&peer_info).unwrap();
+        let response = format!("HTTP/1.1 200 OK\n\n{peer_info_json}");
+        let h = spawn(move || {
+            let negotiated = mock.client.negotiate_node_version();
+            let first = mock.client.get_reward_set_signers(0);
+            // the 404 was remembered, so this never reaches the node
+            let second = mock.client.get_reward_set_signers(0);
+            (negotiated, first, second)
+        });
+        write_response(mock.server, response.as_bytes());
+        let mock = MockServerClient::from_config(mock.config);
+        write_response(mock.server, b"HTTP/1.1 404 Not Found\n\n");
+        let (negotiated, first, second) = h.join().unwrap();
+        assert!(negotiated.is_ok());
+        assert!(matches!(
+            first,
+            Err(ClientError::UnsupportedStacksFeature(_))
+        ));
+        assert!(matches!(
+            second,
+            Err(ClientError::UnsupportedStacksFeature(_))
+        ));
+    }
+
     #[test]
     fn get_last_round_should_succeed() {
         let mock = MockServerClient::new();
@@ -0,0 +1,215 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+use std::time::Duration;
+
+use hashbrown::HashMap;
+use libsigner::v1::messages::MessageSlotID;
+use slog::slog_warn;
+use stacks_common::warn;
+
+use super::{ClientError, BACKOFF_INITIAL_INTERVAL, BACKOFF_MAX_INTERVAL};
+use crate::monitoring;
+
+/// The number of consecutive version conflicts on a slot before a write is abandoned
+pub const MAX_CONSECUTIVE_SLOT_CONFLICTS: u32 = 8;
+
+/// Tracks the versions of this signer's StackerDB slots, so that each write goes out with
+/// the next version the node will accept, and paces retries when writes keep conflicting
+/// (e.g. because another process is writing with the same signing key, or the node's view
+/// of the slot is ahead of ours after a restart).
+#[derive(Debug)]
+pub struct SlotWriteManager {
+    /// The last version known to be stored in each slot
+    versions: HashMap<MessageSlotID, u32>,
+    /// The number of consecutive conflicts seen for each slot
+    conflicts: HashMap<MessageSlotID, u32>,
+    /// The number of consecutive conflicts after which a write is abandoned
+    max_conflicts: u32,
+}
+
+impl Default for SlotWriteManager {
+    fn default() -> Self {
+        Self::new(MAX_CONSECUTIVE_SLOT_CONFLICTS)
+    }
+}
+
+impl SlotWriteManager {
+    /// Create a new write manager which gives up after `max_conflicts` consecutive conflicts
+    pub fn new(max_conflicts: u32) -> Self {
+        Self {
+            versions: HashMap::new(),
+            conflicts: HashMap::new(),
+            max_conflicts,
+        }
+    }
+
+    /// The version to use for the next write to the given slot
+    pub fn next_version(&self, msg_id: &MessageSlotID) -> u32 {
+        self.versions
+            .get(msg_id)
+            .map(|version| version.saturating_add(1))
+            .unwrap_or(1)
+    }
+
+    /// The number of consecutive conflicts seen for the given slot
+    pub fn consecutive_conflicts(&self, msg_id: &MessageSlotID) -> u32 {
+        self.conflicts.get(msg_id).copied().unwrap_or(0)
+    }
+
+    /// Record that the node accepted a write of `version` to the given slot
+    pub fn record_accepted(&mut self, msg_id: &MessageSlotID, version: u32) {
+        self.versions.insert(*msg_id, version);
+        self.conflicts.remove(msg_id);
+        monitoring::increment_stackerdb_publish_results("accepted");
+    }
+
+    /// Record the version the node already stores in the given slot, e.g. one written before a
+    /// restart, so that the next write does not conflict with it
+    pub fn record_stored(&mut self, msg_id: &MessageSlotID, version: u32) {
+        let known = self.versions.entry(*msg_id).or_insert(version);
+        *known = (*known).max(version);
+    }
+
+    /// Record that the node rejected a write of `attempted` to the given slot because the slot
+    /// already holds a version at least as new.  `stored` is the node's version, if it told us.
+    /// Returns how long to wait before retrying with the refreshed version, or an error if the
+    /// slot has conflicted too many times in a row.
+    pub fn record_conflict(
+        &mut self,
+        msg_id: &MessageSlotID,
+        attempted: u32,
+        stored: Option<u32>,
+    ) -> Result<Duration, ClientError> {
+        monitoring::increment_stackerdb_publish_results("conflict");
+        let refreshed = stored.unwrap_or(attempted).max(attempted);
+        self.versions.insert(*msg_id, refreshed);
+
+        let conflicts = self.consecutive_conflicts(msg_id).saturating_add(1);
+        if conflicts >= self.max_conflicts {
+            warn!(
+                "Giving up on stackerdb slot {msg_id} after {conflicts} consecutive version conflicts";
+                "attempted_version" => attempted,
+                "stored_version" => ?stored,
+            );
+            self.conflicts.remove(msg_id);
+            monitoring::increment_stackerdb_publish_results("abandoned");
+            return Err(ClientError::PersistentSlotConflict(conflicts));
+        }
+        self.conflicts.insert(*msg_id, conflicts);
+        Ok(Self::conflict_backoff(conflicts))
+    }
+
+    /// Record that the node rejected a write for a reason other than a version conflict
+    pub fn record_rejected(&mut self, msg_id: &MessageSlotID) {
+        self.conflicts.remove(msg_id);
+        monitoring::increment_stackerdb_publish_results("rejected");
+    }
+
+    /// Record that a write could not be delivered to the node at all
+    pub fn record_error(&mut self, msg_id: &MessageSlotID) {
+        self.conflicts.remove(msg_id);
+        monitoring::increment_stackerdb_publish_results("error");
+    }
+
+    /// How long to wait after the `conflicts`-th consecutive conflict.  The first retry goes
+    /// out immediately, since it already carries the refreshed version; later ones back off
+    /// exponentially.
+    fn conflict_backoff(conflicts: u32) -> Duration {
+        if conflicts <= 1 {
+            return Duration::ZERO;
+        }
+        let millis = BACKOFF_INITIAL_INTERVAL
+            .saturating_mul(1u64 << (conflicts - 2).min(32))
+            .min(BACKOFF_MAX_INTERVAL);
+        Duration::from_millis(millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_follow_accepted_writes() {
+        let mut manager = SlotWriteManager::default();
+        let msg_id = MessageSlotID::DkgBegin;
+        assert_eq!(manager.next_version(&msg_id), 1);
+        manager.record_accepted(&msg_id, 1);
+        assert_eq!(manager.next_version(&msg_id), 2);
+        // other slots are tracked independently
+        assert_eq!(manager.next_version(&MessageSlotID::DkgEnd), 1);
+
+        // a rejection for any other reason doesn't consume a version
+        manager.record_rejected(&msg_id);
+        assert_eq!(manager.next_version(&msg_id), 2);
+    }
+
+    #[test]
+    fn stored_versions_are_never_rolled_back() {
+        let mut manager = SlotWriteManager::default();
+        let msg_id = MessageSlotID::DkgBegin;
+        manager.record_stored(&msg_id, 5);
+        assert_eq!(manager.next_version(&msg_id), 6);
+
+        // an older view of the slot doesn't undo a newer one
+        manager.record_accepted(&msg_id, 9);
+        manager.record_stored(&msg_id, 5);
+        assert_eq!(manager.next_version(&msg_id), 10);
+    }
+
+    #[test]
+    fn conflicts_refresh_version_and_back_off() {
+        let mut manager = SlotWriteManager::new(4);
+        let msg_id = MessageSlotID::DkgPublicShares;
+
+        // the node tells us what it has
+        let wait = manager.record_conflict(&msg_id, 1, Some(7)).unwrap();
+        assert_eq!(wait, Duration::ZERO);
+        assert_eq!(manager.next_version(&msg_id), 8);
+
+        // the node doesn't tell us what it has, so just step past what we tried
+        let wait = manager.record_conflict(&msg_id, 8, None).unwrap();
+        assert_eq!(wait, Duration::from_millis(BACKOFF_INITIAL_INTERVAL));
+        assert_eq!(manager.next_version(&msg_id), 9);
+
+        // never go backwards
+        let wait = manager.record_conflict(&msg_id, 9, Some(3)).unwrap();
+        assert_eq!(wait, Duration::from_millis(BACKOFF_INITIAL_INTERVAL * 2));
+        assert_eq!(manager.next_version(&msg_id), 10);
+        assert_eq!(manager.consecutive_conflicts(&msg_id), 3);
+
+        // persistent conflicts are abandoned, and the next write starts afresh
+        assert!(matches!(
+            manager.record_conflict(&msg_id, 10, Some(12)),
+            Err(ClientError::PersistentSlotConflict(4))
+        ));
+        assert_eq!(manager.consecutive_conflicts(&msg_id), 0);
+        assert_eq!(manager.next_version(&msg_id), 13);
+
+        // an accepted write clears the conflict count
+        manager.record_conflict(&msg_id, 13, Some(13)).unwrap();
+        manager.record_accepted(&msg_id, 14);
+        assert_eq!(manager.consecutive_conflicts(&msg_id), 0);
+    }
+
+    #[test]
+    fn conflict_backoff_is_capped() {
+        assert_eq!(
+            SlotWriteManager::conflict_backoff(u32::MAX),
+            Duration::from_millis(BACKOFF_MAX_INTERVAL)
+        );
+    }
+}
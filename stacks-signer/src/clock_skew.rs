@@ -0,0 +1,211 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use slog::{slog_debug, slog_error, slog_info, slog_warn};
+use stacks_common::{debug, error, info, warn};
+
+/// How many of the most recent burnchain tips the clock skew is estimated from
+pub const CLOCK_SKEW_SAMPLES: usize = 5;
+
+/// How many burnchain tips must have been sampled before the local clock can be considered
+/// skewed, so that one odd header timestamp can't suspend signing
+pub const MIN_CLOCK_SKEW_SAMPLES: usize = 3;
+
+/// Compares the local clock against the header timestamps of the burn blocks the node reports,
+/// so that a signer whose clock is off stops taking part in DKG and signing rounds, whose
+/// timeouts it would get wrong, instead of failing them silently.
+///
+/// Each sample is taken when the node reports a new burnchain tip, and is the local time minus
+/// the tip's header timestamp: positive if the local clock is ahead. Miners set header
+/// timestamps loosely, so the skew is estimated as the median of the last few samples, and the
+/// clock is not considered skewed until there are at least `MIN_CLOCK_SKEW_SAMPLES` of them.
+#[derive(Debug)]
+pub struct ClockSkewMonitor {
+    /// How far the local clock may be from the burnchain's. None if the check is disabled.
+    max_skew: Option<Duration>,
+    /// The skew measured at each of the most recent burnchain tips, in seconds, oldest first
+    samples: VecDeque<i64>,
+    /// The highest burnchain block seen so far
+    highest_burn_height: Option<u64>,
+    /// Whether the estimated skew exceeded `max_skew` at the last sample
+    skewed: bool,
+}
+
+impl ClockSkewMonitor {
+    /// Create a monitor with no samples. The local clock is never considered skewed if
+    /// `max_skew` is None.
+    pub fn new(max_skew: Option<Duration>) -> Self {
+        Self {
+            max_skew,
+            samples: VecDeque::with_capacity(CLOCK_SKEW_SAMPLES),
+            highest_burn_height: None,
+            skewed: false,
+        }
+    }
+
+    /// Whether the local clock was too far from the burnchain's at the last sample
+    pub fn is_skewed(&self) -> bool {
+        self.skewed
+    }
+
+    /// The estimated skew of the local clock, in seconds: positive if it is ahead of the
+    /// burnchain's. None if no sample has been taken yet.
+    pub fn skew(&self) -> Option<i64> {
+        let mut samples: Vec<i64> = self.samples.iter().copied().collect();
+        samples.sort_unstable();
+        samples.get(samples.len() / 2).copied()
+    }
+
+    /// Compare the local time `now` against the header timestamp of a burn block the node
+    /// reported, both in seconds since the epoch, alerting if the local clock has become
+    /// skewed or is no longer skewed.
+    /// Burn blocks below the highest one seen so far are ignored, since they are not new.
+    /// Returns the estimated skew, if the burn block was sampled.
+    pub fn observe(
+        &mut self,
+        burn_height: u64,
+        burn_header_timestamp: u64,
+        now: u64,
+    ) -> Option<i64> {
+        let max_skew = self.max_skew?;
+        if matches!(self.highest_burn_height, Some(highest) if burn_height <= highest) {
+            return None;
+        }
+        self.highest_burn_height = Some(burn_height);
+
+        let sample = i64::try_from(now)
+            .unwrap_or(i64::MAX)
+            .saturating_sub(i64::try_from(burn_header_timestamp).unwrap_or(i64::MAX));
+        if self.samples.len() >= CLOCK_SKEW_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        let skew = self.skew()?;
+        crate::monitoring::update_clock_skew(skew);
+
+        let skewed = self.samples.len() >= MIN_CLOCK_SKEW_SAMPLES
+            && skew.unsigned_abs() > max_skew.as_secs();
+        debug!("Compared the local clock against the burnchain's";
+            "burn_height" => burn_height,
+            "burn_header_timestamp" => burn_header_timestamp,
+            "sample_secs" => sample,
+            "skew_secs" => skew,
+        );
+        match (skewed, self.skewed) {
+            (true, false) => {
+                let skew_secs = skew.unsigned_abs();
+                let direction = if skew > 0 { "ahead of" } else { "behind" };
+                error!("The local clock is {skew_secs} seconds {direction} the burnchain's. Signing is suspended until it is corrected: check that the clock is synchronized (e.g. with NTP), and that the node has finished syncing the burnchain.";
+                    "skew_secs" => skew,
+                    "max_skew_secs" => max_skew.as_secs(),
+                    "burn_height" => burn_height,
+                );
+                crate::monitoring::update_clock_skewed(true);
+            }
+            (false, true) => {
+                info!("The local clock is back in line with the burnchain's. Resuming signing.";
+                    "skew_secs" => skew,
+                );
+                crate::monitoring::update_clock_skewed(false);
+            }
+            (false, false) if sample.unsigned_abs() > max_skew.as_secs() => {
+                warn!("Burn block header timestamp is {sample} seconds off the local clock. Signing will be suspended if this persists.";
+                    "burn_height" => burn_height,
+                    "burn_header_timestamp" => burn_header_timestamp,
+                    "skew_secs" => skew,
+                );
+            }
+            _ => {}
+        }
+        self.skewed = skewed;
+        Some(skew)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_713_195_662;
+
+    #[test]
+    fn clock_in_line_is_not_skewed() {
+        let mut monitor = ClockSkewMonitor::new(Some(Duration::from_secs(600)));
+        assert_eq!(monitor.skew(), None);
+        assert_eq!(monitor.observe(100, NOW - 30, NOW), Some(30));
+        // local clocks that are slightly behind are fine too
+        assert_eq!(monitor.observe(101, NOW + 20, NOW), Some(30));
+        assert_eq!(monitor.observe(102, NOW + 20, NOW), Some(-20));
+        assert!(!monitor.is_skewed());
+    }
+
+    #[test]
+    fn skewed_clock_degrades_and_recovers() {
+        let mut monitor = ClockSkewMonitor::new(Some(Duration::from_secs(600)));
+        // the local clock is an hour behind, but one or two samples are not enough to tell
+        assert_eq!(monitor.observe(100, NOW + 3600, NOW), Some(-3600));
+        assert!(!monitor.is_skewed());
+        assert_eq!(monitor.observe(101, NOW + 3600, NOW), Some(-3600));
+        assert!(!monitor.is_skewed());
+        assert_eq!(monitor.observe(102, NOW + 3600, NOW), Some(-3600));
+        assert!(monitor.is_skewed());
+
+        // once corrected, the old samples are outvoted
+        assert_eq!(monitor.observe(103, NOW + 3600, NOW + 3600), Some(-3600));
+        assert_eq!(monitor.observe(104, NOW + 3600, NOW + 3610), Some(-3600));
+        assert!(monitor.is_skewed());
+        assert_eq!(monitor.observe(105, NOW + 3600, NOW + 3605), Some(0));
+        assert!(!monitor.is_skewed());
+    }
+
+    #[test]
+    fn one_odd_timestamp_is_outvoted() {
+        let mut monitor = ClockSkewMonitor::new(Some(Duration::from_secs(600)));
+        monitor.observe(100, NOW, NOW + 5);
+        monitor.observe(101, NOW, NOW + 10);
+        // a miner set its timestamp well into the future
+        assert_eq!(monitor.observe(102, NOW + 7200, NOW + 15), Some(5));
+        assert!(!monitor.is_skewed());
+    }
+
+    #[test]
+    fn only_new_tips_are_sampled() {
+        let mut monitor = ClockSkewMonitor::new(Some(Duration::from_secs(600)));
+        assert_eq!(monitor.observe(100, NOW, NOW), Some(0));
+        // replayed or reorged burn blocks are old news
+        assert_eq!(monitor.observe(100, NOW - 3600, NOW), None);
+        assert_eq!(monitor.observe(99, NOW - 3600, NOW), None);
+        assert!(!monitor.is_skewed());
+
+        for height in 101..=(101 + CLOCK_SKEW_SAMPLES as u64) {
+            monitor.observe(height, NOW - 3600, NOW);
+        }
+        assert_eq!(monitor.samples.len(), CLOCK_SKEW_SAMPLES);
+        assert_eq!(monitor.skew(), Some(3600));
+        assert!(monitor.is_skewed());
+    }
+
+    #[test]
+    fn disabled_check_never_skews() {
+        let mut monitor = ClockSkewMonitor::new(None);
+        assert_eq!(monitor.observe(100, NOW + 3600, NOW), None);
+        assert!(!monitor.is_skewed());
+        assert_eq!(monitor.skew(), None);
+    }
+}
@@ -14,26 +14,62 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
 use std::fmt::Display;
-use std::fs;
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::path::PathBuf;
 use std::time::Duration;
+use std::{env, fs};
 
 use blockstack_lib::chainstate::stacks::TransactionVersion;
-use libsigner::SignerEntries;
+use libsigner::{SignerEntries, DEFAULT_RETIRED_LISTENER_IDLE};
 use serde::Deserialize;
 use stacks_common::address::{
     AddressHashMode, C32_ADDRESS_VERSION_MAINNET_SINGLESIG, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
 };
 use stacks_common::consts::{CHAIN_ID_MAINNET, CHAIN_ID_TESTNET};
 use stacks_common::types::chainstate::{StacksAddress, StacksPrivateKey, StacksPublicKey};
-use stacks_common::types::PrivateKey;
-use wsts::curve::scalar::Scalar;
+use zeroize::Zeroizing;
 
 use crate::client::SignerSlotID;
+use crate::divergence::DivergenceConfig;
+use crate::dkg_keys::DkgKeyRegistry;
+use crate::escalation::EscalationConfig;
+use crate::message_signing::MessageSigningRegistry;
+use crate::participation::{Participation, ParticipationConfig};
+use crate::secrets::{decrypt_private_key, Secret, KEY_PASSPHRASE_ENV};
 
 const EVENT_TIMEOUT_MS: u64 = 5000;
+/// Reported in place of private key material in config errors
+const REDACTED: &str = "<redacted>";
+/// Default lower bound (in millisecs) for adaptive coordinator timeouts
+const ADAPTIVE_TIMEOUT_MIN_MS: u64 = 1_000;
+/// Default upper bound (in millisecs) for adaptive coordinator timeouts
+const ADAPTIVE_TIMEOUT_MAX_MS: u64 = 300_000;
+/// Default interval (in millisecs) between checks of the node's chain tip
+const CHAIN_TIP_CHECK_INTERVAL_MS: u64 = 30_000;
+/// Default number of burnchain blocks the node may lag behind block proposals
+const CHAIN_TIP_MAX_BURN_LAG: u64 = 2;
+/// Default number of Stacks blocks the node may lag behind block proposals
+const CHAIN_TIP_MAX_STACKS_LAG: u64 = 50;
+/// Default time (in millisecs) a block proposal counts towards the network's chain tip
+const CHAIN_TIP_OBSERVATION_WINDOW_MS: u64 = 600_000;
+/// Default number of seconds the local clock may be ahead of or behind new burn blocks' timestamps
+const CLOCK_SKEW_MAX_SECS: u64 = 600;
+/// Default number of burn blocks a DKG or signing round may go without packets before it expires
+const STALE_ROUND_MAX_AGE: u64 = 12;
+/// Default number of threads that read and parse requests from the node
+const EVENT_WORKER_THREADS: usize = 4;
+/// Default number of consecutive DKG or signing rounds that must fail before escalating
+const ESCALATION_THRESHOLD: u32 = 3;
+/// Default interval (in millisecs) between checks of the next reward set during the prepare phase
+const NEXT_CYCLE_POLL_INTERVAL_MS: u64 = 10_000;
+/// Default time (in millisecs) the signer may spend processing a single event
+const EVENT_PROCESSING_TIMEOUT_MS: u64 = 30_000;
+/// Default time (in secs) an aggregate key vote may go unconfirmed before it is rebroadcast
+const DKG_VOTE_CONFIRM_TIMEOUT_SECS: u64 = 1_200;
+/// Default percentage by which the fee of a rebroadcast aggregate key vote is raised
+const DKG_VOTE_FEE_BUMP_PERCENT: u64 = 25;
 // Default transaction fee to use in microstacks (if unspecificed in the config file)
 const TX_FEE_USTX: u64 = 10_000;
 
@@ -110,6 +146,117 @@ impl Network {
     }
 }
 
+/// How the signers order the potential coordinators for a reward cycle.
+/// Every signer in a deployment must use the same mode, or they will disagree on the coordinator.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CoordinatorSelectionMode {
+    /// Every signer is equally likely to coordinate, regardless of its signing weight
+    #[default]
+    Uniform,
+    /// Signers are likely to coordinate in proportion to their signing weight
+    Weighted,
+}
+
+/// The outcome of evaluating a miner's public key against the configured miner key lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinerKeyDecision {
+    /// The miner may have its proposals signed
+    Allowed,
+    /// The miner's key is on the deny list
+    Denylisted,
+    /// An allow list is configured and the miner's key is not on it
+    NotAllowlisted,
+}
+
+impl MinerKeyDecision {
+    /// Whether the miner's proposals should be considered
+    pub const fn is_allowed(&self) -> bool {
+        matches!(self, Self::Allowed)
+    }
+
+    /// A short reason string, suitable for logs and metric labels
+    pub const fn reason(&self) -> &'static str {
+        match self {
+            Self::Allowed => "allowed",
+            Self::Denylisted => "denylisted",
+            Self::NotAllowlisted => "not_allowlisted",
+        }
+    }
+}
+
+/// When, and how, to rebroadcast aggregate key votes that do not confirm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DkgVoteRetryConfig {
+    /// How long a vote may go unconfirmed before it is rebroadcast
+    pub confirm_timeout: Duration,
+    /// The percentage by which the fee of a rebroadcast vote is raised over the previous
+    /// broadcast's, so that it replaces it in the mempool (pre Epoch 3.0)
+    pub fee_bump_percent: u64,
+}
+
+impl DkgVoteRetryConfig {
+    /// The fee to pay when rebroadcasting a vote that was broadcast with `tx_fee`
+    pub fn bumped_fee(&self, tx_fee: u64) -> u64 {
+        let bump = tx_fee.saturating_mul(self.fee_bump_percent) / 100;
+        tx_fee.saturating_add(bump.max(1))
+    }
+}
+
+impl Default for DkgVoteRetryConfig {
+    fn default() -> Self {
+        Self {
+            confirm_timeout: Duration::from_secs(DKG_VOTE_CONFIRM_TIMEOUT_SECS),
+            fee_bump_percent: DKG_VOTE_FEE_BUMP_PERCENT,
+        }
+    }
+}
+
+/// Operator-configured miner public key allow/deny lists.
+/// The deny list always takes precedence. If an allow list is configured, only the miners on
+/// it are allowed; otherwise every miner not on the deny list is allowed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MinerKeyPolicy {
+    /// If set, only these miner keys are allowed
+    pub allowlist: Option<Vec<StacksPublicKey>>,
+    /// Miner keys whose proposals are never signed
+    pub denylist: Vec<StacksPublicKey>,
+}
+
+impl MinerKeyPolicy {
+    /// Evaluate the given miner public key against this policy
+    pub fn evaluate(&self, miner_key: &StacksPublicKey) -> MinerKeyDecision {
+        let miner_key_bytes = miner_key.to_bytes_compressed();
+        let matches = |key: &StacksPublicKey| key.to_bytes_compressed() == miner_key_bytes;
+        if self.denylist.iter().any(matches) {
+            return MinerKeyDecision::Denylisted;
+        }
+        match &self.allowlist {
+            Some(allowlist) if !allowlist.iter().any(matches) => MinerKeyDecision::NotAllowlisted,
+            _ => MinerKeyDecision::Allowed,
+        }
+    }
+
+    /// Parse a list of hex-encoded public keys for the given config field
+    fn parse_keys(field: &str, keys: &[String]) -> Result<Vec<StacksPublicKey>, ConfigError> {
+        keys.iter()
+            .map(|key| {
+                StacksPublicKey::from_hex(key)
+                    .map_err(|_| ConfigError::BadField(field.to_string(), key.clone()))
+            })
+            .collect()
+    }
+}
+
+/// The bounds within which coordinator timeouts adapt to observed latency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptiveTimeoutConfig {
+    /// The smallest timeout that may be used
+    pub min_timeout: Duration,
+    /// The largest timeout that may be used
+    pub max_timeout: Duration,
+}
+
 /// The Configuration info needed for an individual signer per reward cycle
 #[derive(Debug, Clone)]
 pub struct SignerConfig {
@@ -125,10 +272,10 @@ pub struct SignerConfig {
     pub signer_entries: SignerEntries,
     /// The signer slot ids of all signers registered for this reward cycle
     pub signer_slot_ids: Vec<SignerSlotID>,
-    /// The Scalar representation of the private key for signer communication
-    pub ecdsa_private_key: Scalar,
+    /// The private key for signer communication, used as a WSTS `Scalar`
+    pub ecdsa_private_key: Secret,
     /// The private key for this signer
-    pub stacks_private_key: StacksPrivateKey,
+    pub stacks_private_key: Secret,
     /// The node host for this signer
     pub node_host: String,
     /// Whether this signer is running on mainnet or not
@@ -147,8 +294,26 @@ pub struct SignerConfig {
     pub tx_fee_ustx: u64,
     /// If set, will use the estimated fee up to this amount.
     pub max_tx_fee_ustx: Option<u64>,
+    /// When and how to rebroadcast aggregate key votes that do not confirm
+    pub dkg_vote_retry: DkgVoteRetryConfig,
     /// The path to the signer's database file
     pub db_path: PathBuf,
+    /// The miner public key allow/deny lists
+    pub miner_key_policy: MinerKeyPolicy,
+    /// URL to POST a receipt to whenever this signer contributes to a completed signature
+    pub signature_receipt_webhook: Option<String>,
+    /// The arbitrary messages the operator has asked this signer to sign
+    pub message_signing: MessageSigningRegistry,
+    /// Where the signer publishes its DKG key material for the operator to export
+    pub dkg_keys: DkgKeyRegistry,
+    /// How many burn blocks a DKG or signing round may go without packets before it expires
+    pub stale_round_max_age: u64,
+    /// How the potential coordinators are ordered
+    pub coordinator_selection: CoordinatorSelectionMode,
+    /// When and how to alert the operator that rounds keep failing
+    pub escalation: EscalationConfig,
+    /// Which kinds of operation this signer takes part in during its reward cycle
+    pub participation: Participation,
 }
 
 /// The parsed configuration for the signer
@@ -158,10 +323,10 @@ pub struct GlobalConfig {
     pub node_host: String,
     /// endpoint to the event receiver
     pub endpoint: SocketAddr,
-    /// The Scalar representation of the private key for signer communication
-    pub ecdsa_private_key: Scalar,
+    /// The private key for signer communication, used as a WSTS `Scalar`
+    pub ecdsa_private_key: Secret,
     /// The signer's Stacks private key
-    pub stacks_private_key: StacksPrivateKey,
+    pub stacks_private_key: Secret,
     /// The signer's Stacks address
     pub stacks_address: StacksAddress,
     /// The network to use. One of "mainnet" or "testnet".
@@ -178,16 +343,74 @@ pub struct GlobalConfig {
     pub nonce_timeout: Option<Duration>,
     /// timeout to gather signature shares
     pub sign_timeout: Option<Duration>,
+    /// If set, the above timeouts are derived from the latencies observed in prior
+    /// rounds, within these bounds
+    pub adaptive_timeouts: Option<AdaptiveTimeoutConfig>,
     /// the STX tx fee to use in uSTX.
     pub tx_fee_ustx: u64,
     /// the max STX tx fee to use in uSTX when estimating fees
     pub max_tx_fee_ustx: Option<u64>,
+    /// When and how to rebroadcast aggregate key votes that do not confirm
+    pub dkg_vote_retry: DkgVoteRetryConfig,
     /// the authorization password for the block proposal endpoint
     pub auth_password: String,
     /// The path to the signer's database file
     pub db_path: PathBuf,
     /// Metrics endpoint
     pub metrics_endpoint: Option<SocketAddr>,
+    /// The miner public key allow/deny lists
+    pub miner_key_policy: MinerKeyPolicy,
+    /// URL to POST a receipt to whenever this signer contributes to a completed signature
+    pub signature_receipt_webhook: Option<String>,
+    /// Unix socket on which to serve the operator RPC
+    pub operator_rpc_socket: Option<PathBuf>,
+    /// Token that operator RPC requests must present
+    pub operator_rpc_auth_token: Option<String>,
+    /// How long the event receiver keeps serving its old address after moving, once no event
+    /// has reached it
+    pub retired_listener_idle: Duration,
+    /// When to stop signing because the node's chain tip has diverged from the network's
+    pub chain_tip_divergence: DivergenceConfig,
+    /// How far the local clock may be from the timestamps of new burn blocks before signing is
+    /// suspended. None if the local clock is not checked.
+    pub max_clock_skew: Option<Duration>,
+    /// How many burn blocks a DKG or signing round may go without packets before it expires
+    pub stale_round_max_age: u64,
+    /// How the potential coordinators are ordered
+    pub coordinator_selection: CoordinatorSelectionMode,
+    /// Whether to acknowledge events that the event receiver rejects, instead of answering
+    /// them with an error status
+    pub ack_rejected_events: bool,
+    /// How many threads read and parse requests from the node concurrently
+    pub event_worker_threads: usize,
+    /// When and how to alert the operator that rounds keep failing
+    pub escalation: EscalationConfig,
+    /// How often to check whether the signer is in the next reward set, once it can be
+    /// calculated, until the signer is registered for the next reward cycle
+    pub next_cycle_poll_interval: Duration,
+    /// How long the signer may spend processing a single event before the rest of it is
+    /// abandoned. None if event processing is not bounded.
+    pub event_processing_timeout: Option<Duration>,
+    /// How long a single pass of the runloop may take before its remaining events and commands
+    /// are left for the next pass. None if passes are not bounded.
+    pub pass_budget: Option<Duration>,
+    /// Which kinds of operation the signer takes part in, by reward cycle
+    pub participation: ParticipationConfig,
+}
+
+/// What the signer takes part in during one reward cycle, as given in the config file
+#[derive(Deserialize, Debug)]
+struct RawParticipationOverride {
+    /// The reward cycle to override
+    pub reward_cycle: u64,
+    /// Whether to take part in DKG rounds. If not set, will default to `participate_in_dkg`
+    pub dkg: Option<bool>,
+    /// Whether to take part in signing rounds. If not set, will default to
+    /// `participate_in_signing`
+    pub signing: Option<bool>,
+    /// Whether to vote for the aggregate key. If not set, will default to
+    /// `participate_in_key_votes`
+    pub key_votes: Option<bool>,
 }
 
 /// Internal struct for loading up the config file
@@ -199,7 +422,14 @@ struct RawConfigFile {
     pub endpoint: String,
     /// The hex representation of the signer's Stacks private key used for communicating
     /// with the Stacks Node, including writing to the Stacker DB instance.
-    pub stacks_private_key: String,
+    /// Exactly one of this or `encrypted_stacks_private_key` must be set.
+    pub stacks_private_key: Option<String>,
+    /// The signer's Stacks private key, encrypted with `stacks-signer encrypt-private-key`
+    pub encrypted_stacks_private_key: Option<String>,
+    /// File holding the passphrase (or raw key) that `encrypted_stacks_private_key` is
+    /// encrypted under. If not set, it is read from the `STACKS_SIGNER_KEY_PASSPHRASE`
+    /// environment variable.
+    pub key_passphrase_file: Option<String>,
     /// The network to use. One of "mainnet" or "testnet".
     pub network: Network,
     /// The time to wait (in millisecs) for a response from the stacker-db instance
@@ -214,17 +444,110 @@ struct RawConfigFile {
     pub nonce_timeout_ms: Option<u64>,
     /// timeout in (millisecs) to gather signature shares
     pub sign_timeout_ms: Option<u64>,
+    /// Whether to derive the DKG/nonce/sign timeouts from the latencies observed in prior rounds
+    pub adaptive_timeouts: Option<bool>,
+    /// lower bound (in millisecs) for adaptive timeouts. If not set, will default to ADAPTIVE_TIMEOUT_MIN_MS
+    pub adaptive_timeout_min_ms: Option<u64>,
+    /// upper bound (in millisecs) for adaptive timeouts. If not set, will default to ADAPTIVE_TIMEOUT_MAX_MS
+    pub adaptive_timeout_max_ms: Option<u64>,
     /// the STX tx fee to use in uSTX. If not set, will default to TX_FEE_USTX
     pub tx_fee_ustx: Option<u64>,
     /// the max STX tx fee to use in uSTX when estimating fees.
     /// If not set, will use tx_fee_ustx.
     pub max_tx_fee_ustx: Option<u64>,
+    /// How long (in secs) an aggregate key vote may go unconfirmed before it is rebroadcast.
+    /// If not set, will default to DKG_VOTE_CONFIRM_TIMEOUT_SECS
+    pub dkg_vote_confirm_timeout_secs: Option<u64>,
+    /// The percentage by which the fee of a rebroadcast aggregate key vote is raised, capped by
+    /// max_tx_fee_ustx. If not set, will default to DKG_VOTE_FEE_BUMP_PERCENT
+    pub dkg_vote_fee_bump_percent: Option<u64>,
     /// The authorization password for the block proposal endpoint
     pub auth_password: String,
     /// The path to the signer's database file or :memory: for an in-memory database
     pub db_path: String,
     /// Metrics endpoint
     pub metrics_endpoint: Option<String>,
+    /// Hex-encoded miner public keys whose block proposals may be signed.
+    /// If not set, all miners not in `miner_denylist` are allowed.
+    pub miner_allowlist: Option<Vec<String>>,
+    /// Hex-encoded miner public keys whose block proposals will never be signed
+    pub miner_denylist: Option<Vec<String>>,
+    /// URL to POST a JSON receipt to whenever this signer contributes to a completed signature
+    pub signature_receipt_webhook: Option<String>,
+    /// Path of a Unix socket on which to serve the operator RPC: signing arbitrary digests,
+    /// exporting the signer's DKG keys and importing a known-good aggregate key, and moving the
+    /// event receiver to another address while the signer runs. If not set, the operator RPC is
+    /// disabled.
+    pub operator_rpc_socket: Option<String>,
+    /// Token that operator RPC requests must present. Required if `operator_rpc_socket` is set.
+    pub operator_rpc_auth_token: Option<String>,
+    /// How long (in millisecs) the event receiver keeps serving its old address after moving to
+    /// another one, once no event has reached the old address. If not set, will default to
+    /// libsigner's DEFAULT_RETIRED_LISTENER_IDLE.
+    pub retired_listener_idle_ms: Option<u64>,
+    /// interval (in millisecs) between checks of the node's chain tip. If not set, will default to CHAIN_TIP_CHECK_INTERVAL_MS
+    pub chain_tip_check_interval_ms: Option<u64>,
+    /// How many burnchain blocks the node may lag behind block proposals before signing is
+    /// suspended. If not set, will default to CHAIN_TIP_MAX_BURN_LAG
+    pub chain_tip_max_burn_lag: Option<u64>,
+    /// How many Stacks blocks the node may lag behind block proposals before signing is
+    /// suspended. If not set, will default to CHAIN_TIP_MAX_STACKS_LAG
+    pub chain_tip_max_stacks_lag: Option<u64>,
+    /// How long (in millisecs) a block proposal counts towards the network's chain tip.
+    /// If not set, will default to CHAIN_TIP_OBSERVATION_WINDOW_MS
+    pub chain_tip_observation_window_ms: Option<u64>,
+    /// URL to POST a JSON alert to whenever the node's chain tip diverges from, or catches up
+    /// with, the network's
+    pub chain_tip_divergence_webhook: Option<String>,
+    /// How far (in secs) the local clock may be ahead of or behind the header timestamps of new
+    /// burn blocks before signing is suspended. 0 disables the check. If not set, will default
+    /// to CLOCK_SKEW_MAX_SECS
+    pub max_clock_skew_secs: Option<u64>,
+    /// How many burn blocks a DKG or signing round may go without packets before it is
+    /// abandoned and its late packets are dropped. If not set, will default to STALE_ROUND_MAX_AGE
+    pub stale_round_max_age: Option<u64>,
+    /// How the potential coordinators are ordered: "uniform" or "weighted" (by signing weight).
+    /// All signers must use the same mode. If not set, defaults to "uniform".
+    pub coordinator_selection: Option<CoordinatorSelectionMode>,
+    /// Answer malformed events, and events from StackerDB contracts signers do not follow, with
+    /// 200 instead of 400 and 422 respectively. Needed for nodes that retry every event until it
+    /// is acknowledged. If not set, defaults to false.
+    pub ack_rejected_events: Option<bool>,
+    /// How many threads read and parse requests from the node, so that a slow request does not
+    /// hold up the ones behind it. Events are still delivered in order for each StackerDB
+    /// contract, but events of different contracts, or posted to different paths, may overtake
+    /// each other. Set to 1 to read every request in turn, as earlier versions did. Must be at
+    /// least 1. If not set, will default to EVENT_WORKER_THREADS
+    pub event_worker_threads: Option<usize>,
+    /// How many consecutive DKG or signing rounds of a reward cycle must fail before the
+    /// operator is alerted. If not set, will default to ESCALATION_THRESHOLD
+    pub escalation_threshold: Option<u32>,
+    /// URL to POST a JSON alert to when too many consecutive rounds fail
+    pub escalation_webhook: Option<String>,
+    /// Program to run, followed by its arguments, with a JSON alert on its standard input when
+    /// too many consecutive rounds fail
+    pub escalation_command: Option<Vec<String>>,
+    /// interval (in millisecs) between checks of the next reward set during the prepare phase,
+    /// until the signer is registered for the next reward cycle. If not set, will default to
+    /// NEXT_CYCLE_POLL_INTERVAL_MS
+    pub next_cycle_poll_interval_ms: Option<u64>,
+    /// How long (in millisecs) the signer may spend processing a single event before it is
+    /// reported and the rest of it is abandoned. 0 disables the watchdog. If not set, will
+    /// default to EVENT_PROCESSING_TIMEOUT_MS
+    pub event_processing_timeout_ms: Option<u64>,
+    /// How long (in millisecs) a single pass of the runloop may take before the events and
+    /// commands it has not got to yet are left for the next pass. 0 disables the budget. If not
+    /// set, will default to the event timeout
+    pub pass_budget_ms: Option<u64>,
+    /// Whether to take part in DKG rounds. If not set, defaults to true.
+    pub participate_in_dkg: Option<bool>,
+    /// Whether to take part in signing rounds. If not set, defaults to true.
+    pub participate_in_signing: Option<bool>,
+    /// Whether to vote for the aggregate key that DKG produces. If not set, defaults to true.
+    pub participate_in_key_votes: Option<bool>,
+    /// What to take part in during specific reward cycles, e.g. nothing at all to only
+    /// observe a cycle. Each reward cycle may be overridden once.
+    pub participation_overrides: Option<Vec<RawParticipationOverride>>,
 }
 
 impl RawConfigFile {
@@ -239,15 +562,52 @@ impl RawConfigFile {
     pub fn load_from_file(path: &str) -> Result<Self, ConfigError> {
         Self::try_from(&PathBuf::from(path))
     }
+
+    /// Get the signer's Stacks private key, decrypting it if need be.
+    /// Wipes the plaintext key from this config.
+    fn load_stacks_private_key(&mut self) -> Result<StacksPrivateKey, ConfigError> {
+        match (
+            self.stacks_private_key.take().map(Zeroizing::new),
+            &self.encrypted_stacks_private_key,
+        ) {
+            (Some(key_hex), None) => StacksPrivateKey::from_hex(&key_hex).map_err(|_| {
+                ConfigError::BadField("stacks_private_key".to_string(), REDACTED.to_string())
+            }),
+            (None, Some(encrypted)) => {
+                let secret = match &self.key_passphrase_file {
+                    Some(path) => Zeroizing::new(fs::read_to_string(path).map_err(|e| {
+                        ConfigError::InvalidConfig(format!(
+                            "failed to read key passphrase file: {e:?}"
+                        ))
+                    })?),
+                    None => Zeroizing::new(env::var(KEY_PASSPHRASE_ENV).map_err(|_| {
+                        ConfigError::InvalidConfig(format!(
+                            "`encrypted_stacks_private_key` is set, but neither `key_passphrase_file` nor {KEY_PASSPHRASE_ENV} is"
+                        ))
+                    })?),
+                };
+                // files usually end with a newline, which is not part of the passphrase
+                decrypt_private_key(encrypted, secret.trim_end_matches(['\r', '\n'])).map_err(|e| {
+                    ConfigError::BadField("encrypted_stacks_private_key".to_string(), e.to_string())
+                })
+            }
+            _ => Err(ConfigError::InvalidConfig(
+                "exactly one of `stacks_private_key` or `encrypted_stacks_private_key` must be set"
+                    .to_string(),
+            )),
+        }
+    }
 }
 
 impl TryFrom<&PathBuf> for RawConfigFile {
     type Error = ConfigError;
 
     fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
-        Self::load_from_str(&fs::read_to_string(path).map_err(|e| {
+        // the file may hold a plaintext private key
+        let data = Zeroizing::new(fs::read_to_string(path).map_err(|e| {
             ConfigError::InvalidConfig(format!("failed to read config file: {e:?}"))
-        })?)
+        })?);
+        Self::load_from_str(&data)
     }
 }
 
@@ -256,7 +616,7 @@ impl TryFrom<RawConfigFile> for GlobalConfig {
 
     /// Attempt to decode the raw config file's primitive types into our types.
     /// NOTE: network access is required for this to work
-    fn try_from(raw_data: RawConfigFile) -> Result<Self, Self::Error> {
+    fn try_from(mut raw_data: RawConfigFile) -> Result<Self, Self::Error> {
         url::Url::parse(&format!("http://{}", raw_data.node_host)).map_err(|_| {
             ConfigError::BadField("node_host".to_string(), raw_data.node_host.clone())
         })?;
@@ -270,22 +630,10 @@ impl TryFrom<RawConfigFile> for GlobalConfig {
                 ConfigError::BadField("endpoint".to_string(), raw_data.endpoint.clone())
             })?;
 
-        let stacks_private_key =
-            StacksPrivateKey::from_hex(&raw_data.stacks_private_key).map_err(|_| {
-                ConfigError::BadField(
-                    "stacks_private_key".to_string(),
-                    raw_data.stacks_private_key.clone(),
-                )
-            })?;
-
-        let ecdsa_private_key =
-            Scalar::try_from(&stacks_private_key.to_bytes()[..32]).map_err(|_| {
-                ConfigError::BadField(
-                    "stacks_private_key".to_string(),
-                    raw_data.stacks_private_key.clone(),
-                )
-            })?;
-        let stacks_public_key = StacksPublicKey::from_private(&stacks_private_key);
+        let stacks_private_key = Secret::new(&raw_data.load_stacks_private_key()?);
+        let ecdsa_private_key = stacks_private_key.clone();
+        let stacks_public_key =
+            StacksPublicKey::from_private(&stacks_private_key.stacks_private_key());
         let stacks_address = StacksAddress::from_public_keys(
             raw_data.network.to_address_version(),
             &AddressHashMode::SerializeP2PKH,
@@ -300,6 +648,26 @@ impl TryFrom<RawConfigFile> for GlobalConfig {
         let dkg_private_timeout = raw_data.dkg_private_timeout_ms.map(Duration::from_millis);
         let nonce_timeout = raw_data.nonce_timeout_ms.map(Duration::from_millis);
         let sign_timeout = raw_data.sign_timeout_ms.map(Duration::from_millis);
+        let adaptive_timeouts = if raw_data.adaptive_timeouts.unwrap_or(false) {
+            let min_timeout_ms = raw_data
+                .adaptive_timeout_min_ms
+                .unwrap_or(ADAPTIVE_TIMEOUT_MIN_MS);
+            let max_timeout_ms = raw_data
+                .adaptive_timeout_max_ms
+                .unwrap_or(ADAPTIVE_TIMEOUT_MAX_MS);
+            if min_timeout_ms > max_timeout_ms {
+                return Err(ConfigError::BadField(
+                    "adaptive_timeout_min_ms".to_string(),
+                    min_timeout_ms.to_string(),
+                ));
+            }
+            Some(AdaptiveTimeoutConfig {
+                min_timeout: Duration::from_millis(min_timeout_ms),
+                max_timeout: Duration::from_millis(max_timeout_ms),
+            })
+        } else {
+            None
+        };
         let db_path = raw_data.db_path.into();
 
         let metrics_endpoint = match raw_data.metrics_endpoint {
@@ -315,6 +683,163 @@ impl TryFrom<RawConfigFile> for GlobalConfig {
             None => None,
         };
 
+        if let Some(url) = &raw_data.signature_receipt_webhook {
+            reqwest::Url::parse(url).map_err(|_| {
+                ConfigError::BadField("signature_receipt_webhook".to_string(), url.clone())
+            })?;
+        }
+        let signature_receipt_webhook = raw_data.signature_receipt_webhook;
+
+        if let Some(url) = &raw_data.chain_tip_divergence_webhook {
+            reqwest::Url::parse(url).map_err(|_| {
+                ConfigError::BadField("chain_tip_divergence_webhook".to_string(), url.clone())
+            })?;
+        }
+        let chain_tip_divergence = DivergenceConfig {
+            check_interval: Duration::from_millis(
+                raw_data
+                    .chain_tip_check_interval_ms
+                    .unwrap_or(CHAIN_TIP_CHECK_INTERVAL_MS),
+            ),
+            max_burn_lag: raw_data
+                .chain_tip_max_burn_lag
+                .unwrap_or(CHAIN_TIP_MAX_BURN_LAG),
+            max_stacks_lag: raw_data
+                .chain_tip_max_stacks_lag
+                .unwrap_or(CHAIN_TIP_MAX_STACKS_LAG),
+            observation_window: Duration::from_millis(
+                raw_data
+                    .chain_tip_observation_window_ms
+                    .unwrap_or(CHAIN_TIP_OBSERVATION_WINDOW_MS),
+            ),
+            webhook: raw_data.chain_tip_divergence_webhook,
+        };
+        let max_clock_skew = match raw_data.max_clock_skew_secs.unwrap_or(CLOCK_SKEW_MAX_SECS) {
+            0 => None,
+            max_skew_secs => Some(Duration::from_secs(max_skew_secs)),
+        };
+        let event_processing_timeout = match raw_data
+            .event_processing_timeout_ms
+            .unwrap_or(EVENT_PROCESSING_TIMEOUT_MS)
+        {
+            0 => None,
+            timeout_ms => Some(Duration::from_millis(timeout_ms)),
+        };
+        let pass_budget = match raw_data.pass_budget_ms {
+            Some(0) => None,
+            Some(budget_ms) => Some(Duration::from_millis(budget_ms)),
+            None => Some(event_timeout),
+        };
+
+        let operator_rpc_socket = raw_data.operator_rpc_socket.map(PathBuf::from);
+        let operator_rpc_auth_token = raw_data.operator_rpc_auth_token;
+        if operator_rpc_socket.is_some()
+            && operator_rpc_auth_token
+                .as_deref()
+                .unwrap_or_default()
+                .is_empty()
+        {
+            return Err(ConfigError::BadField(
+                "operator_rpc_auth_token".to_string(),
+                String::new(),
+            ));
+        }
+
+        if let Some(url) = &raw_data.escalation_webhook {
+            reqwest::Url::parse(url).map_err(|_| {
+                ConfigError::BadField("escalation_webhook".to_string(), url.clone())
+            })?;
+        }
+        if matches!(&raw_data.escalation_command, Some(command) if command.is_empty()) {
+            return Err(ConfigError::BadField(
+                "escalation_command".to_string(),
+                "[]".to_string(),
+            ));
+        }
+        let escalation_threshold = raw_data
+            .escalation_threshold
+            .unwrap_or(ESCALATION_THRESHOLD);
+        if escalation_threshold == 0 {
+            return Err(ConfigError::BadField(
+                "escalation_threshold".to_string(),
+                escalation_threshold.to_string(),
+            ));
+        }
+        let escalation = EscalationConfig {
+            threshold: escalation_threshold,
+            webhook: raw_data.escalation_webhook,
+            command: raw_data.escalation_command,
+        };
+
+        let event_worker_threads = raw_data
+            .event_worker_threads
+            .unwrap_or(EVENT_WORKER_THREADS);
+        if event_worker_threads == 0 {
+            return Err(ConfigError::BadField(
+                "event_worker_threads".to_string(),
+                event_worker_threads.to_string(),
+            ));
+        }
+
+        let dkg_vote_confirm_timeout_secs = raw_data
+            .dkg_vote_confirm_timeout_secs
+            .unwrap_or(DKG_VOTE_CONFIRM_TIMEOUT_SECS);
+        if dkg_vote_confirm_timeout_secs == 0 {
+            return Err(ConfigError::BadField(
+                "dkg_vote_confirm_timeout_secs".to_string(),
+                dkg_vote_confirm_timeout_secs.to_string(),
+            ));
+        }
+        let dkg_vote_retry = DkgVoteRetryConfig {
+            confirm_timeout: Duration::from_secs(dkg_vote_confirm_timeout_secs),
+            fee_bump_percent: raw_data
+                .dkg_vote_fee_bump_percent
+                .unwrap_or(DKG_VOTE_FEE_BUMP_PERCENT),
+        };
+
+        let default_participation = Participation {
+            dkg: raw_data.participate_in_dkg.unwrap_or(true),
+            signing: raw_data.participate_in_signing.unwrap_or(true),
+            key_votes: raw_data.participate_in_key_votes.unwrap_or(true),
+        };
+        let mut participation_overrides = BTreeMap::new();
+        for raw_override in raw_data.participation_overrides.unwrap_or_default() {
+            let participation = Participation {
+                dkg: raw_override.dkg.unwrap_or(default_participation.dkg),
+                signing: raw_override
+                    .signing
+                    .unwrap_or(default_participation.signing),
+                key_votes: raw_override
+                    .key_votes
+                    .unwrap_or(default_participation.key_votes),
+            };
+            if participation_overrides
+                .insert(raw_override.reward_cycle, participation)
+                .is_some()
+            {
+                return Err(ConfigError::BadField(
+                    "participation_overrides".to_string(),
+                    raw_override.reward_cycle.to_string(),
+                ));
+            }
+        }
+        let participation = ParticipationConfig {
+            default: default_participation,
+            overrides: participation_overrides,
+        };
+
+        let miner_key_policy = MinerKeyPolicy {
+            allowlist: raw_data
+                .miner_allowlist
+                .as_ref()
+                .map(|keys| MinerKeyPolicy::parse_keys("miner_allowlist", keys))
+                .transpose()?,
+            denylist: MinerKeyPolicy::parse_keys(
+                "miner_denylist",
+                raw_data.miner_denylist.as_deref().unwrap_or_default(),
+            )?,
+        };
+
         Ok(Self {
             node_host: raw_data.node_host,
             endpoint,
@@ -328,11 +853,36 @@ impl TryFrom<RawConfigFile> for GlobalConfig {
             dkg_private_timeout,
             nonce_timeout,
             sign_timeout,
+            adaptive_timeouts,
             tx_fee_ustx: raw_data.tx_fee_ustx.unwrap_or(TX_FEE_USTX),
             max_tx_fee_ustx: raw_data.max_tx_fee_ustx,
+            dkg_vote_retry,
             auth_password: raw_data.auth_password,
             db_path,
             metrics_endpoint,
+            miner_key_policy,
+            signature_receipt_webhook,
+            operator_rpc_socket,
+            operator_rpc_auth_token,
+            retired_listener_idle: raw_data
+                .retired_listener_idle_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_RETIRED_LISTENER_IDLE),
+            chain_tip_divergence,
+            max_clock_skew,
+            stale_round_max_age: raw_data.stale_round_max_age.unwrap_or(STALE_ROUND_MAX_AGE),
+            coordinator_selection: raw_data.coordinator_selection.unwrap_or_default(),
+            ack_rejected_events: raw_data.ack_rejected_events.unwrap_or(false),
+            event_worker_threads,
+            escalation,
+            next_cycle_poll_interval: Duration::from_millis(
+                raw_data
+                    .next_cycle_poll_interval_ms
+                    .unwrap_or(NEXT_CYCLE_POLL_INTERVAL_MS),
+            ),
+            event_processing_timeout,
+            pass_budget,
+            participation,
         })
     }
 }
@@ -381,7 +931,9 @@ Metrics endpoint: {metrics_endpoint}
             node_host = self.node_host,
             endpoint = self.endpoint,
             stacks_address = self.stacks_address,
-            public_key = StacksPublicKey::from_private(&self.stacks_private_key).to_hex(),
+            public_key =
+                StacksPublicKey::from_private(&self.stacks_private_key.stacks_private_key())
+                    .to_hex(),
             network = self.network,
             db_path = self.db_path.to_str().unwrap_or_default(),
             tx_fee = tx_fee,
@@ -482,7 +1034,21 @@ metrics_endpoint = "{metrics_endpoint}"
 
 #[cfg(test)]
 mod tests {
+    use rand::rngs::OsRng;
+    use stacks_common::util::hash::to_hex;
+
     use super::*;
+    use crate::secrets::{encrypt_private_key, KeyEncryptionKind};
+
+    /// The smallest valid signer config.  Tests append the keys they check to it.
+    const BASE_CONFIG_TOML: &str = r#"
+stacks_private_key = "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01"
+node_host = "localhost"
+endpoint = "localhost:30000"
+network = "testnet"
+auth_password = "melon"
+db_path = ":memory:"
+"#;
 
     #[test]
     fn build_signer_config_tomls_should_produce_deserializable_strings() {
@@ -625,6 +1191,458 @@ mod tests {
         assert_eq!(Some(config.tx_fee_ustx), tx_fee_ustx);
     }
 
+    #[test]
+    fn miner_key_lists_should_deserialize_correctly() {
+        let allowed_key = StacksPublicKey::from_private(&StacksPrivateKey::new());
+        let denied_key = StacksPublicKey::from_private(&StacksPrivateKey::new());
+        let other_key = StacksPublicKey::from_private(&StacksPrivateKey::new());
+
+        let config_toml = format!(
+            "{BASE_CONFIG_TOML}miner_allowlist = [\"{allowed}\"]\nminer_denylist = [\"{denied}\"]\n",
+            allowed = allowed_key.to_hex(),
+            denied = denied_key.to_hex(),
+        );
+        let config = GlobalConfig::load_from_str(&config_toml).expect("Failed to parse config");
+        let policy = &config.miner_key_policy;
+        assert_eq!(policy.evaluate(&allowed_key), MinerKeyDecision::Allowed);
+        assert_eq!(policy.evaluate(&denied_key), MinerKeyDecision::Denylisted);
+        assert_eq!(
+            policy.evaluate(&other_key),
+            MinerKeyDecision::NotAllowlisted
+        );
+
+        // Without an allow list, only the deny list applies
+        let policy = MinerKeyPolicy {
+            allowlist: None,
+            denylist: vec![denied_key],
+        };
+        assert_eq!(policy.evaluate(&other_key), MinerKeyDecision::Allowed);
+        assert_eq!(policy.evaluate(&denied_key), MinerKeyDecision::Denylisted);
+
+        // Malformed keys are rejected
+        let bad_toml = config_toml.replace(&denied_key.to_hex(), "not-a-key");
+        assert!(GlobalConfig::load_from_str(&bad_toml).is_err());
+    }
+
+    #[test]
+    fn adaptive_timeouts_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert!(config.adaptive_timeouts.is_none());
+
+        let adaptive_toml = format!("{BASE_CONFIG_TOML}adaptive_timeouts = true\n");
+        let config = GlobalConfig::load_from_str(&adaptive_toml).expect("Failed to parse config");
+        assert_eq!(
+            config.adaptive_timeouts,
+            Some(AdaptiveTimeoutConfig {
+                min_timeout: Duration::from_millis(ADAPTIVE_TIMEOUT_MIN_MS),
+                max_timeout: Duration::from_millis(ADAPTIVE_TIMEOUT_MAX_MS),
+            })
+        );
+
+        let bounded_toml = format!(
+            "{adaptive_toml}adaptive_timeout_min_ms = 500\nadaptive_timeout_max_ms = 2000\n"
+        );
+        let config = GlobalConfig::load_from_str(&bounded_toml).expect("Failed to parse config");
+        assert_eq!(
+            config.adaptive_timeouts,
+            Some(AdaptiveTimeoutConfig {
+                min_timeout: Duration::from_millis(500),
+                max_timeout: Duration::from_millis(2000),
+            })
+        );
+
+        // Inverted bounds are rejected
+        let bad_toml = format!(
+            "{adaptive_toml}adaptive_timeout_min_ms = 2000\nadaptive_timeout_max_ms = 500\n"
+        );
+        assert!(GlobalConfig::load_from_str(&bad_toml).is_err());
+    }
+
+    #[test]
+    fn signature_receipt_webhook_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert!(config.signature_receipt_webhook.is_none());
+
+        let webhook_toml = format!(
+            "{BASE_CONFIG_TOML}signature_receipt_webhook = \"https://audit.example.com/receipts\"\n"
+        );
+        let config = GlobalConfig::load_from_str(&webhook_toml).expect("Failed to parse config");
+        assert_eq!(
+            config.signature_receipt_webhook.as_deref(),
+            Some("https://audit.example.com/receipts")
+        );
+
+        let bad_toml = format!("{BASE_CONFIG_TOML}signature_receipt_webhook = \"not a url\"\n");
+        assert!(GlobalConfig::load_from_str(&bad_toml).is_err());
+    }
+
+    #[test]
+    fn chain_tip_divergence_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert_eq!(
+            config.chain_tip_divergence,
+            DivergenceConfig {
+                check_interval: Duration::from_millis(CHAIN_TIP_CHECK_INTERVAL_MS),
+                max_burn_lag: CHAIN_TIP_MAX_BURN_LAG,
+                max_stacks_lag: CHAIN_TIP_MAX_STACKS_LAG,
+                observation_window: Duration::from_millis(CHAIN_TIP_OBSERVATION_WINDOW_MS),
+                webhook: None,
+            }
+        );
+
+        let custom_toml = format!(
+            "{BASE_CONFIG_TOML}chain_tip_max_burn_lag = 5\nchain_tip_check_interval_ms = 1000\nchain_tip_divergence_webhook = \"https://alerts.example.com/signer\"\n"
+        );
+        let config = GlobalConfig::load_from_str(&custom_toml).expect("Failed to parse config");
+        assert_eq!(config.chain_tip_divergence.max_burn_lag, 5);
+        assert_eq!(
+            config.chain_tip_divergence.check_interval,
+            Duration::from_millis(1000)
+        );
+        assert_eq!(
+            config.chain_tip_divergence.webhook.as_deref(),
+            Some("https://alerts.example.com/signer")
+        );
+
+        let bad_toml = format!("{BASE_CONFIG_TOML}chain_tip_divergence_webhook = \"not a url\"\n");
+        assert!(GlobalConfig::load_from_str(&bad_toml).is_err());
+    }
+
+    #[test]
+    fn escalation_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert_eq!(
+            config.escalation,
+            EscalationConfig {
+                threshold: ESCALATION_THRESHOLD,
+                webhook: None,
+                command: None,
+            }
+        );
+
+        let custom_toml = format!(
+            "{BASE_CONFIG_TOML}escalation_threshold = 5\nescalation_webhook = \"https://alerts.example.com/rounds\"\nescalation_command = [\"/usr/local/bin/page\", \"--team\", \"signers\"]\n"
+        );
+        let config = GlobalConfig::load_from_str(&custom_toml).expect("Failed to parse config");
+        assert_eq!(
+            config.escalation,
+            EscalationConfig {
+                threshold: 5,
+                webhook: Some("https://alerts.example.com/rounds".to_string()),
+                command: Some(vec![
+                    "/usr/local/bin/page".to_string(),
+                    "--team".to_string(),
+                    "signers".to_string(),
+                ]),
+            }
+        );
+
+        for bad in [
+            "escalation_threshold = 0\n",
+            "escalation_webhook = \"not a url\"\n",
+            "escalation_command = []\n",
+        ] {
+            let bad_toml = format!("{BASE_CONFIG_TOML}{bad}");
+            assert!(GlobalConfig::load_from_str(&bad_toml).is_err());
+        }
+    }
+
+    #[test]
+    fn max_clock_skew_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert_eq!(
+            config.max_clock_skew,
+            Some(Duration::from_secs(CLOCK_SKEW_MAX_SECS))
+        );
+
+        let custom_toml = format!("{BASE_CONFIG_TOML}max_clock_skew_secs = 120\n");
+        let config = GlobalConfig::load_from_str(&custom_toml).expect("Failed to parse config");
+        assert_eq!(config.max_clock_skew, Some(Duration::from_secs(120)));
+
+        let disabled_toml = format!("{BASE_CONFIG_TOML}max_clock_skew_secs = 0\n");
+        let config = GlobalConfig::load_from_str(&disabled_toml).expect("Failed to parse config");
+        assert_eq!(config.max_clock_skew, None);
+    }
+
+    #[test]
+    fn stale_round_max_age_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert_eq!(config.stale_round_max_age, STALE_ROUND_MAX_AGE);
+
+        let custom_toml = format!("{BASE_CONFIG_TOML}stale_round_max_age = 3\n");
+        let config = GlobalConfig::load_from_str(&custom_toml).expect("Failed to parse config");
+        assert_eq!(config.stale_round_max_age, 3);
+    }
+
+    #[test]
+    fn dkg_vote_retry_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert_eq!(config.dkg_vote_retry, DkgVoteRetryConfig::default());
+
+        let custom_toml = format!(
+            "{BASE_CONFIG_TOML}dkg_vote_confirm_timeout_secs = 60\ndkg_vote_fee_bump_percent = 50\n"
+        );
+        let config = GlobalConfig::load_from_str(&custom_toml).expect("Failed to parse config");
+        assert_eq!(
+            config.dkg_vote_retry,
+            DkgVoteRetryConfig {
+                confirm_timeout: Duration::from_secs(60),
+                fee_bump_percent: 50,
+            }
+        );
+        assert_eq!(config.dkg_vote_retry.bumped_fee(10_000), 15_000);
+        // the fee always goes up, so the rebroadcast can replace the original in the mempool
+        assert_eq!(config.dkg_vote_retry.bumped_fee(1), 2);
+
+        let bad_toml = format!("{BASE_CONFIG_TOML}dkg_vote_confirm_timeout_secs = 0\n");
+        assert!(GlobalConfig::load_from_str(&bad_toml).is_err());
+    }
+
+    #[test]
+    fn next_cycle_poll_interval_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert_eq!(
+            config.next_cycle_poll_interval,
+            Duration::from_millis(NEXT_CYCLE_POLL_INTERVAL_MS)
+        );
+
+        let custom_toml = format!("{BASE_CONFIG_TOML}next_cycle_poll_interval_ms = 2500\n");
+        let config = GlobalConfig::load_from_str(&custom_toml).expect("Failed to parse config");
+        assert_eq!(config.next_cycle_poll_interval, Duration::from_millis(2500));
+    }
+
+    #[test]
+    fn event_processing_timeout_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert_eq!(
+            config.event_processing_timeout,
+            Some(Duration::from_millis(EVENT_PROCESSING_TIMEOUT_MS))
+        );
+
+        let custom_toml = format!(
+            "{BASE_CONFIG_TOML}event_processing_timeout_ms = 2500
+"
+        );
+        let config = GlobalConfig::load_from_str(&custom_toml).expect("Failed to parse config");
+        assert_eq!(
+            config.event_processing_timeout,
+            Some(Duration::from_millis(2500))
+        );
+
+        let disabled_toml = format!(
+            "{BASE_CONFIG_TOML}event_processing_timeout_ms = 0
+"
+        );
+        let config = GlobalConfig::load_from_str(&disabled_toml).expect("Failed to parse config");
+        assert_eq!(config.event_processing_timeout, None);
+    }
+
+    #[test]
+    fn pass_budget_should_deserialize_correctly() {
+        let config_toml = format!("{BASE_CONFIG_TOML}event_timeout_ms = 2000\n");
+        let config = GlobalConfig::load_from_str(&config_toml).expect("Failed to parse config");
+        assert_eq!(config.pass_budget, Some(Duration::from_millis(2000)));
+
+        let custom_toml = format!("{config_toml}pass_budget_ms = 500\n");
+        let config = GlobalConfig::load_from_str(&custom_toml).expect("Failed to parse config");
+        assert_eq!(config.pass_budget, Some(Duration::from_millis(500)));
+
+        let disabled_toml = format!("{config_toml}pass_budget_ms = 0\n");
+        let config = GlobalConfig::load_from_str(&disabled_toml).expect("Failed to parse config");
+        assert_eq!(config.pass_budget, None);
+    }
+
+    #[test]
+    fn coordinator_selection_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert_eq!(
+            config.coordinator_selection,
+            CoordinatorSelectionMode::Uniform
+        );
+
+        let custom_toml = format!("{BASE_CONFIG_TOML}coordinator_selection = \"weighted\"\n");
+        let config = GlobalConfig::load_from_str(&custom_toml).expect("Failed to parse config");
+        assert_eq!(
+            config.coordinator_selection,
+            CoordinatorSelectionMode::Weighted
+        );
+
+        let bad_toml = format!("{BASE_CONFIG_TOML}coordinator_selection = \"random\"\n");
+        assert!(GlobalConfig::load_from_str(&bad_toml).is_err());
+    }
+
+    #[test]
+    fn ack_rejected_events_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert!(!config.ack_rejected_events);
+
+        let custom_toml = format!("{BASE_CONFIG_TOML}ack_rejected_events = true\n");
+        let config = GlobalConfig::load_from_str(&custom_toml).expect("Failed to parse config");
+        assert!(config.ack_rejected_events);
+    }
+
+    #[test]
+    fn participation_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert_eq!(config.participation, ParticipationConfig::default());
+
+        let custom_toml = format!(
+            r#"{BASE_CONFIG_TOML}participate_in_key_votes = false
+
+[[participation_overrides]]
+reward_cycle = 10
+dkg = false
+signing = false
+
+[[participation_overrides]]
+reward_cycle = 11
+key_votes = true
+"#
+        );
+        let config = GlobalConfig::load_from_str(&custom_toml).expect("Failed to parse config");
+        let default = Participation {
+            key_votes: false,
+            ..Participation::FULL
+        };
+        assert_eq!(config.participation.for_reward_cycle(9), default);
+        assert_eq!(
+            config.participation.for_reward_cycle(10),
+            Participation::OBSERVE_ONLY
+        );
+        assert_eq!(
+            config.participation.for_reward_cycle(11),
+            Participation::FULL
+        );
+
+        let duplicate_toml = format!(
+            "{BASE_CONFIG_TOML}\n[[participation_overrides]]\nreward_cycle = 10\n\n[[participation_overrides]]\nreward_cycle = 10\ndkg = false\n"
+        );
+        assert!(matches!(
+            GlobalConfig::load_from_str(&duplicate_toml),
+            Err(ConfigError::BadField(field, _)) if field == "participation_overrides"
+        ));
+    }
+
+    #[test]
+    fn event_worker_threads_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert_eq!(config.event_worker_threads, EVENT_WORKER_THREADS);
+
+        let custom_toml = format!("{BASE_CONFIG_TOML}event_worker_threads = 2\n");
+        let config = GlobalConfig::load_from_str(&custom_toml).expect("Failed to parse config");
+        assert_eq!(config.event_worker_threads, 2);
+
+        let zero_toml = format!("{BASE_CONFIG_TOML}event_worker_threads = 0\n");
+        assert!(matches!(
+            GlobalConfig::load_from_str(&zero_toml),
+            Err(ConfigError::BadField(field, _)) if field == "event_worker_threads"
+        ));
+    }
+
+    #[test]
+    fn operator_rpc_socket_should_deserialize_correctly() {
+        let config = GlobalConfig::load_from_str(BASE_CONFIG_TOML).expect("Failed to parse config");
+        assert!(config.operator_rpc_socket.is_none());
+        assert_eq!(config.retired_listener_idle, DEFAULT_RETIRED_LISTENER_IDLE);
+
+        let socket_toml = format!(
+            "{BASE_CONFIG_TOML}operator_rpc_socket = \"/run/stacks-signer/operator.sock\"\noperator_rpc_auth_token = \"netops\"\n"
+        );
+        let config = GlobalConfig::load_from_str(&socket_toml).expect("Failed to parse config");
+        assert_eq!(
+            config.operator_rpc_socket,
+            Some(PathBuf::from("/run/stacks-signer/operator.sock"))
+        );
+        assert_eq!(config.operator_rpc_auth_token.as_deref(), Some("netops"));
+
+        let idle_toml = format!("{BASE_CONFIG_TOML}retired_listener_idle_ms = 5000\n");
+        let config = GlobalConfig::load_from_str(&idle_toml).expect("Failed to parse config");
+        assert_eq!(config.retired_listener_idle, Duration::from_millis(5000));
+
+        // The socket must not be served without an auth token
+        let bad_toml = format!(
+            "{BASE_CONFIG_TOML}operator_rpc_socket = \"/run/stacks-signer/operator.sock\"\n"
+        );
+        assert!(GlobalConfig::load_from_str(&bad_toml).is_err());
+        let bad_toml = format!(
+            "{BASE_CONFIG_TOML}operator_rpc_socket = \"/run/stacks-signer/operator.sock\"\noperator_rpc_auth_token = \"\"\n"
+        );
+        assert!(GlobalConfig::load_from_str(&bad_toml).is_err());
+    }
+
+    #[test]
+    fn encrypted_private_key_should_deserialize_correctly() {
+        let private_key = StacksPrivateKey::from_hex(
+            "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+        )
+        .unwrap();
+        let raw_key = to_hex(&[9u8; 32]);
+        let encrypted = encrypt_private_key(
+            &private_key,
+            KeyEncryptionKind::RawKey,
+            &raw_key,
+            &mut OsRng,
+        )
+        .unwrap();
+
+        let key_file = std::env::temp_dir().join(format!(
+            "stacks-signer-key-{}",
+            StacksPublicKey::from_private(&StacksPrivateKey::new()).to_hex()
+        ));
+        fs::write(&key_file, format!("{raw_key}\n")).unwrap();
+
+        let config_toml = format!(
+            r#"
+encrypted_stacks_private_key = "{encrypted}"
+key_passphrase_file = "{key_file}"
+node_host = "localhost"
+endpoint = "localhost:30000"
+network = "testnet"
+auth_password = "melon"
+db_path = ":memory:"
+"#,
+            key_file = key_file.display(),
+        );
+        let config = GlobalConfig::load_from_str(&config_toml).expect("Failed to parse config");
+        assert_eq!(config.stacks_private_key.stacks_private_key(), private_key);
+        assert_eq!(
+            format!("{:?}", config.stacks_private_key),
+            "Secret(<redacted>)"
+        );
+
+        // The wrong key does not decrypt
+        fs::write(&key_file, to_hex(&[1u8; 32])).unwrap();
+        assert!(matches!(
+            GlobalConfig::load_from_str(&config_toml),
+            Err(ConfigError::BadField(field, _)) if field == "encrypted_stacks_private_key"
+        ));
+        fs::remove_file(&key_file).unwrap();
+
+        // Both a plaintext and an encrypted key is ambiguous
+        let both_toml = format!(
+            "{config_toml}stacks_private_key = \"{}\"\n",
+            private_key.to_hex()
+        );
+        assert!(matches!(
+            GlobalConfig::load_from_str(&both_toml),
+            Err(ConfigError::InvalidConfig(_))
+        ));
+
+        // A malformed plaintext key is not echoed back
+        let bad_toml = r#"
+stacks_private_key = "not-a-key"
+node_host = "localhost"
+endpoint = "localhost:30000"
+network = "testnet"
+auth_password = "melon"
+db_path = ":memory:"
+"#;
+        assert!(matches!(
+            GlobalConfig::load_from_str(bad_toml),
+            Err(ConfigError::BadField(_, value)) if value == REDACTED
+        ));
+    }
+
     #[test]
     fn test_config_to_string() {
         let config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
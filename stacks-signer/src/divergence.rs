@@ -0,0 +1,343 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use blockstack_lib::net::api::getinfo::RPCPeerInfoData;
+use libsigner::BlockProposal;
+use serde_derive::{Deserialize, Serialize};
+use slog::{slog_debug, slog_info, slog_warn};
+use stacks_common::util::get_epoch_time_secs;
+use stacks_common::{debug, info, warn};
+
+/// How long to wait for the webhook to answer before giving up on an alert
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A chain tip, as reported by the node or implied by network activity
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainTip {
+    /// The burnchain block height
+    pub burn_height: u64,
+    /// The Stacks block height
+    pub stacks_height: u64,
+}
+
+impl From<&BlockProposal> for ChainTip {
+    /// A proposed block implies that its parent is the Stacks tip, and that the
+    /// burnchain has reached the height the block was mined at.  Only proposals signed by the
+    /// miner that wrote them to the miners' StackerDB should be observed, since anything else
+    /// could be forged.
+    fn from(proposal: &BlockProposal) -> Self {
+        Self {
+            burn_height: proposal.burn_height,
+            stacks_height: proposal.block.header.chain_length.saturating_sub(1),
+        }
+    }
+}
+
+impl From<&RPCPeerInfoData> for ChainTip {
+    fn from(peer_info: &RPCPeerInfoData) -> Self {
+        Self {
+            burn_height: peer_info.burn_block_height,
+            stacks_height: peer_info.stacks_tip_height,
+        }
+    }
+}
+
+/// When to consider the node's view of the chain to have diverged from the network's
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DivergenceConfig {
+    /// How often to ask the node for its chain tip
+    pub check_interval: Duration,
+    /// How many burnchain blocks the node may lag behind the network
+    pub max_burn_lag: u64,
+    /// How many Stacks blocks the node may lag behind the network
+    pub max_stacks_lag: u64,
+    /// How long network activity counts towards the network's chain tip
+    pub observation_window: Duration,
+    /// URL to POST a JSON alert to whenever divergence is detected or resolved
+    pub webhook: Option<String>,
+}
+
+/// How far the node's chain tip lags behind the network's
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    /// The chain tip reported by the node
+    pub node_tip: ChainTip,
+    /// The chain tip implied by recent block proposals
+    pub network_tip: ChainTip,
+    /// Burnchain blocks the node is behind
+    pub burn_lag: u64,
+    /// Stacks blocks the node is behind
+    pub stacks_lag: u64,
+}
+
+/// An alert delivered to the divergence webhook
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DivergenceAlert {
+    /// Whether the node is diverged (true) or has caught up again (false)
+    pub diverged: bool,
+    /// The divergence at the time of the alert
+    pub divergence: Divergence,
+    /// When the alert was raised, in seconds since the epoch
+    pub timestamp: u64,
+}
+
+/// Compares the node's chain tip against the tips implied by the miners' block proposals this
+/// signer receives, so that a signer attached to an isolated or stalled node stops signing instead
+/// of following it.
+#[derive(Debug)]
+pub struct ChainTipMonitor {
+    config: DivergenceConfig,
+    /// Recently observed network chain tips, oldest first
+    observed: VecDeque<(Instant, ChainTip)>,
+    /// When the node was last checked
+    last_check: Option<Instant>,
+    /// The current divergence, if the node is diverged
+    divergence: Option<Divergence>,
+    notifier: Option<DivergenceNotifier>,
+}
+
+impl ChainTipMonitor {
+    /// Create a monitor with no observations
+    pub fn new(config: DivergenceConfig) -> Self {
+        let notifier = config.webhook.clone().map(DivergenceNotifier::new);
+        Self {
+            config,
+            observed: VecDeque::new(),
+            last_check: None,
+            divergence: None,
+            notifier,
+        }
+    }
+
+    /// Record a chain tip implied by network activity
+    pub fn observe(&mut self, tip: ChainTip, now: Instant) {
+        self.observed.push_back((now, tip));
+    }
+
+    /// Whether it is time to check the node's chain tip again
+    pub fn check_due(&self, now: Instant) -> bool {
+        self.last_check.map_or(true, |last_check| {
+            now.saturating_duration_since(last_check) >= self.config.check_interval
+        })
+    }
+
+    /// Whether the node was diverged from the network at the last check
+    pub fn is_diverged(&self) -> bool {
+        self.divergence.is_some()
+    }
+
+    /// The highest chain tip implied by network activity within the observation window
+    pub fn network_tip(&mut self, now: Instant) -> Option<ChainTip> {
+        while let Some((observed_at, _)) = self.observed.front() {
+            if now.saturating_duration_since(*observed_at) <= self.config.observation_window {
+                break;
+            }
+            self.observed.pop_front();
+        }
+        self.observed
+            .iter()
+            .map(|(_, tip)| *tip)
+            .reduce(|highest, tip| ChainTip {
+                burn_height: highest.burn_height.max(tip.burn_height),
+                stacks_height: highest.stacks_height.max(tip.stacks_height),
+            })
+    }
+
+    /// Compare the node's chain tip against the network's, alerting if the node has diverged
+    /// or caught up since the last check.
+    /// Returns the divergence, if the node is diverged.
+    pub fn check(&mut self, node_tip: ChainTip, now: Instant) -> Option<Divergence> {
+        self.last_check = Some(now);
+        let network_tip = self.network_tip(now).unwrap_or(node_tip);
+        let divergence = Divergence {
+            node_tip,
+            network_tip,
+            burn_lag: network_tip.burn_height.saturating_sub(node_tip.burn_height),
+            stacks_lag: network_tip
+                .stacks_height
+                .saturating_sub(node_tip.stacks_height),
+        };
+        crate::monitoring::update_chain_tip_lag(divergence.burn_lag, divergence.stacks_lag);
+        let diverged = divergence.burn_lag > self.config.max_burn_lag
+            || divergence.stacks_lag > self.config.max_stacks_lag;
+        debug!("Checked the node's chain tip against the network's";
+            "node_burn_height" => node_tip.burn_height,
+            "node_stacks_height" => node_tip.stacks_height,
+            "network_burn_height" => network_tip.burn_height,
+            "network_stacks_height" => network_tip.stacks_height,
+        );
+        match (diverged, self.divergence.is_some()) {
+            (true, false) => {
+                warn!("The node's chain tip has diverged from the network's. Signing is suspended until it catches up.";
+                    "burn_lag" => divergence.burn_lag,
+                    "stacks_lag" => divergence.stacks_lag,
+                    "node_burn_height" => node_tip.burn_height,
+                    "network_burn_height" => network_tip.burn_height,
+                );
+                crate::monitoring::update_chain_tip_diverged(true);
+                self.alert(true, divergence);
+            }
+            (false, true) => {
+                info!("The node's chain tip has caught up with the network's. Resuming signing.";
+                    "node_burn_height" => node_tip.burn_height,
+                    "node_stacks_height" => node_tip.stacks_height,
+                );
+                crate::monitoring::update_chain_tip_diverged(false);
+                self.alert(false, divergence);
+            }
+            _ => {}
+        }
+        self.divergence = diverged.then_some(divergence);
+        self.divergence
+    }
+
+    fn alert(&self, diverged: bool, divergence: Divergence) {
+        if let Some(notifier) = self.notifier.as_ref() {
+            notifier.notify(DivergenceAlert {
+                diverged,
+                divergence,
+                timestamp: get_epoch_time_secs(),
+            });
+        }
+    }
+}
+
+/// POSTs divergence alerts to an external webhook as JSON, from a background thread
+#[derive(Debug)]
+struct DivergenceNotifier {
+    sender: Sender<DivergenceAlert>,
+}
+
+impl DivergenceNotifier {
+    fn new(url: String) -> Self {
+        let (sender, receiver) = channel::<DivergenceAlert>();
+        thread::Builder::new()
+            .name("divergence-alerts".into())
+            .spawn(move || {
+                let client = reqwest::blocking::Client::new();
+                // Ends once the notifier (and so the sender) is dropped
+                for alert in receiver.iter() {
+                    let result = client
+                        .post(&url)
+                        .timeout(WEBHOOK_TIMEOUT)
+                        .json(&alert)
+                        .send()
+                        .and_then(|response| response.error_for_status());
+                    if let Err(e) = result {
+                        warn!("Failed to deliver chain tip divergence alert: {e}";
+                            "diverged" => alert.diverged,
+                        );
+                    }
+                }
+            })
+            .expect("FATAL: failed to spawn divergence alert thread");
+        Self { sender }
+    }
+
+    fn notify(&self, alert: DivergenceAlert) {
+        if self.sender.send(alert).is_err() {
+            warn!("Divergence alert thread has exited. Dropping alert.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> DivergenceConfig {
+        DivergenceConfig {
+            check_interval: Duration::from_secs(30),
+            max_burn_lag: 2,
+            max_stacks_lag: 10,
+            observation_window: Duration::from_secs(600),
+            webhook: None,
+        }
+    }
+
+    fn tip(burn_height: u64, stacks_height: u64) -> ChainTip {
+        ChainTip {
+            burn_height,
+            stacks_height,
+        }
+    }
+
+    #[test]
+    fn no_observations_is_not_divergence() {
+        let mut monitor = ChainTipMonitor::new(config());
+        let now = Instant::now();
+        assert!(monitor.check_due(now));
+        assert_eq!(monitor.check(tip(100, 50), now), None);
+        assert!(!monitor.is_diverged());
+        assert!(!monitor.check_due(now + Duration::from_secs(29)));
+        assert!(monitor.check_due(now + Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn lagging_node_diverges_and_recovers() {
+        let mut monitor = ChainTipMonitor::new(config());
+        let now = Instant::now();
+        monitor.observe(tip(102, 50), now);
+        // within the allowed lag
+        assert_eq!(monitor.check(tip(100, 50), now), None);
+
+        monitor.observe(tip(103, 51), now);
+        let divergence = monitor.check(tip(100, 50), now).unwrap();
+        assert_eq!(divergence.burn_lag, 3);
+        assert_eq!(divergence.stacks_lag, 1);
+        assert_eq!(divergence.network_tip, tip(103, 51));
+        assert!(monitor.is_diverged());
+
+        // a node ahead of the network is not diverged
+        assert_eq!(monitor.check(tip(104, 52), now), None);
+        assert!(!monitor.is_diverged());
+    }
+
+    #[test]
+    fn stacks_lag_diverges() {
+        let mut monitor = ChainTipMonitor::new(config());
+        let now = Instant::now();
+        monitor.observe(tip(100, 61), now);
+        let divergence = monitor.check(tip(100, 50), now).unwrap();
+        assert_eq!(divergence.burn_lag, 0);
+        assert_eq!(divergence.stacks_lag, 11);
+    }
+
+    #[test]
+    fn old_observations_expire() {
+        let mut monitor = ChainTipMonitor::new(config());
+        let now = Instant::now();
+        monitor.observe(tip(110, 50), now);
+        monitor.observe(tip(101, 55), now + Duration::from_secs(300));
+        assert_eq!(
+            monitor.network_tip(now + Duration::from_secs(600)),
+            Some(tip(110, 55))
+        );
+        assert_eq!(
+            monitor.network_tip(now + Duration::from_secs(601)),
+            Some(tip(101, 55))
+        );
+        assert!(monitor
+            .check(tip(100, 50), now + Duration::from_secs(601))
+            .is_none());
+        assert_eq!(monitor.network_tip(now + Duration::from_secs(901)), None);
+    }
+}
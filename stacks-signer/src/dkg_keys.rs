@@ -0,0 +1,296 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use hashbrown::HashMap;
+use serde::{de, Deserializer};
+use serde_derive::{Deserialize, Serialize};
+use stacks_common::util::hash::{hex_bytes, to_hex};
+use wsts::common::PolyCommitment;
+use wsts::curve::point::{Compressed, Point};
+
+/// Hex encoding of a compressed point
+pub fn point_to_hex(point: &Point) -> String {
+    to_hex(point.compress().as_bytes())
+}
+
+/// Decode a hex-encoded compressed point
+pub fn point_from_hex(hex: &str) -> Option<Point> {
+    let bytes = hex_bytes(hex.trim_start_matches("0x")).ok()?;
+    let compressed = Compressed::try_from(bytes.as_slice()).ok()?;
+    Point::try_from(&compressed).ok()
+}
+
+/// A signer's view of the DKG key material for one reward cycle.
+/// Only public values are included.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DkgKeys {
+    /// The reward cycle the keys belong to
+    pub reward_cycle: u64,
+    /// This signer's id in the reward cycle's signer set
+    pub signer_id: u32,
+    /// The aggregate key computed from the last DKG round this signer took part in
+    pub dkg_aggregate_public_key: Option<String>,
+    /// The aggregate key the signer is using: the one approved by the voting contract, or the
+    /// imported one if the contract has not approved any
+    pub approved_aggregate_public_key: Option<String>,
+    /// The aggregate key the operator imported, if any
+    pub imported_aggregate_public_key: Option<String>,
+    /// Each party's public polynomial commitments from the last DKG round (the DKG public
+    /// shares), as hex-encoded compressed points. The constant terms sum to the DKG
+    /// aggregate key.
+    #[serde(deserialize_with = "deserialize_public_shares")]
+    pub public_shares: BTreeMap<u32, Vec<String>>,
+}
+
+/// JSON object keys are strings, and the tagged `OperatorResponse` buffers its content before
+/// deserializing it, which loses the conversion back to party ids. Parse them here instead.
+fn deserialize_public_shares<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<u32, Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    <BTreeMap<String, Vec<String>> as de::Deserialize>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(party_id, shares)| {
+            let party_id = party_id.parse().map_err(de::Error::custom)?;
+            Ok((party_id, shares))
+        })
+        .collect()
+}
+
+impl DkgKeys {
+    /// Snapshot the key material of a signer
+    pub fn new<'a>(
+        reward_cycle: u64,
+        signer_id: u32,
+        dkg_aggregate_public_key: Option<&Point>,
+        approved_aggregate_public_key: Option<&Point>,
+        imported_aggregate_public_key: Option<&Point>,
+        party_polynomials: impl Iterator<Item = (&'a u32, &'a PolyCommitment)>,
+    ) -> Self {
+        Self {
+            reward_cycle,
+            signer_id,
+            dkg_aggregate_public_key: dkg_aggregate_public_key.map(point_to_hex),
+            approved_aggregate_public_key: approved_aggregate_public_key.map(point_to_hex),
+            imported_aggregate_public_key: imported_aggregate_public_key.map(point_to_hex),
+            public_shares: party_polynomials
+                .map(|(party_id, commitment)| {
+                    (
+                        *party_id,
+                        commitment.poly.iter().map(point_to_hex).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The latest DKG key material of each reward cycle's signer, shared between the signers
+/// (which publish it) and the RPC server (which exports it to the operator)
+#[derive(Debug, Clone, Default)]
+pub struct DkgKeyRegistry {
+    inner: Arc<Mutex<HashMap<u64, DkgKeys>>>,
+}
+
+impl DkgKeyRegistry {
+    /// Replace the key material for `keys.reward_cycle`
+    pub fn publish(&self, keys: DkgKeys) {
+        let mut inner = self.inner.lock().expect("DKG key registry poisoned");
+        inner.insert(keys.reward_cycle, keys);
+    }
+
+    /// The latest key material published for `reward_cycle`
+    pub fn get(&self, reward_cycle: u64) -> Option<DkgKeys> {
+        let inner = self.inner.lock().expect("DKG key registry poisoned");
+        inner.get(&reward_cycle).cloned()
+    }
+}
+
+#[cfg(unix)]
+pub(crate) use self::rpc::{dump_keys, import_aggregate_key};
+
+#[cfg(unix)]
+mod rpc {
+    use std::sync::mpsc::Sender;
+
+    use slog::slog_info;
+    use stacks_common::info;
+
+    use super::{point_from_hex, DkgKeyRegistry};
+    use crate::local_rpc::OperatorResponse;
+    use crate::runloop::{RunLoopCommand, SignerCommand};
+
+    /// Serve the operator RPC's `dump_keys` method: export the signer's DKG key material for
+    /// `reward_cycle`
+    pub(crate) fn dump_keys(registry: &DkgKeyRegistry, reward_cycle: u64) -> OperatorResponse {
+        match registry.get(reward_cycle) {
+            Some(keys) => OperatorResponse::Keys { keys },
+            None => OperatorResponse::Error {
+                reason: format!("no signer for reward cycle {reward_cycle}"),
+            },
+        }
+    }
+
+    /// Serve the operator RPC's `import_aggregate_key` method: hand the signer a known-good
+    /// aggregate key for `reward_cycle`
+    pub(crate) fn import_aggregate_key(
+        cmd_send: &Sender<RunLoopCommand>,
+        reward_cycle: u64,
+        aggregate_key: &str,
+    ) -> OperatorResponse {
+        let Some(aggregate_key) = point_from_hex(aggregate_key) else {
+            return OperatorResponse::Error {
+                reason: "aggregate_key must be a hex-encoded compressed point".to_string(),
+            };
+        };
+        info!("DKG keys: importing an aggregate key";
+            "reward_cycle" => reward_cycle,
+            "aggregate_key" => %aggregate_key,
+        );
+        let command = RunLoopCommand::new(
+            SignerCommand::ImportAggregateKey { aggregate_key },
+            reward_cycle,
+        );
+        if cmd_send.send(command).is_err() {
+            return OperatorResponse::Error {
+                reason: "signer is shutting down".to_string(),
+            };
+        }
+        OperatorResponse::Queued
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::sync::mpsc::channel;
+
+        use wsts::curve::point::Point;
+        use wsts::curve::scalar::Scalar;
+
+        use super::super::{point_to_hex, DkgKeys};
+        use super::*;
+
+        #[test]
+        fn dumps_published_keys() {
+            let registry = DkgKeyRegistry::default();
+            assert!(matches!(
+                dump_keys(&registry, 4),
+                OperatorResponse::Error { .. }
+            ));
+
+            let key = Point::from(Scalar::from(7));
+            let keys = DkgKeys::new(4, 1, Some(&key), Some(&key), None, std::iter::empty());
+            registry.publish(keys.clone());
+            assert_eq!(dump_keys(&registry, 4), OperatorResponse::Keys { keys });
+        }
+
+        #[test]
+        fn imports_valid_aggregate_keys() {
+            let (cmd_send, cmd_recv) = channel();
+
+            assert!(matches!(
+                import_aggregate_key(&cmd_send, 4, "0badc0de"),
+                OperatorResponse::Error { .. }
+            ));
+            assert!(cmd_recv.try_recv().is_err());
+
+            let aggregate_key = Point::from(Scalar::from(7));
+            assert_eq!(
+                import_aggregate_key(&cmd_send, 4, &point_to_hex(&aggregate_key)),
+                OperatorResponse::Queued
+            );
+            assert_eq!(
+                cmd_recv.try_recv().unwrap(),
+                RunLoopCommand::new(SignerCommand::ImportAggregateKey { aggregate_key }, 4)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wsts::common::PolyCommitment;
+    use wsts::curve::scalar::Scalar;
+    use wsts::schnorr::ID;
+
+    use super::*;
+    use crate::local_rpc::OperatorResponse;
+
+    #[test]
+    fn points_round_trip_through_hex() {
+        let point = Point::from(Scalar::from(42));
+        let hex = point_to_hex(&point);
+        assert_eq!(point_from_hex(&hex), Some(point));
+        assert_eq!(point_from_hex(&format!("0x{hex}")), Some(point));
+        assert_eq!(point_from_hex(&hex[2..]), None);
+        assert_eq!(point_from_hex("not hex"), None);
+    }
+
+    #[test]
+    fn keys_serialize_public_shares_by_party() {
+        let polynomials: HashMap<u32, PolyCommitment> = [
+            (0, Scalar::from(1), Scalar::from(10)),
+            (1, Scalar::from(2), Scalar::from(11)),
+            (2, Scalar::from(3), Scalar::from(12)),
+        ]
+        .into_iter()
+        .map(|(party_id, constant, linear)| {
+            let id = ID {
+                id: Scalar::from(5),
+                kG: Point::from(Scalar::from(6)),
+                kca: Scalar::from(7),
+            };
+            let poly = vec![Point::from(constant), Point::from(linear)];
+            (party_id, PolyCommitment { id, poly })
+        })
+        .collect();
+        let aggregate_key = polynomials
+            .values()
+            .fold(Point::default(), |sum, commitment| sum + commitment.poly[0]);
+
+        let keys = DkgKeys::new(
+            9,
+            2,
+            Some(&aggregate_key),
+            None,
+            Some(&aggregate_key),
+            polynomials.iter(),
+        );
+        assert_eq!(keys.public_shares.len(), 3);
+        assert_eq!(
+            keys.public_shares[&1],
+            vec![
+                point_to_hex(&Point::from(Scalar::from(2))),
+                point_to_hex(&Point::from(Scalar::from(11)))
+            ]
+        );
+        assert!(keys.approved_aggregate_public_key.is_none());
+        assert_eq!(
+            keys.imported_aggregate_public_key,
+            keys.dkg_aggregate_public_key
+        );
+
+        let json = serde_json::to_string(&OperatorResponse::Keys { keys: keys.clone() }).unwrap();
+        assert_eq!(
+            serde_json::from_str::<OperatorResponse>(&json).unwrap(),
+            OperatorResponse::Keys { keys }
+        );
+    }
+}
@@ -0,0 +1,425 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+use slog::{slog_info, slog_warn};
+use stacks_common::util::get_epoch_time_secs;
+use stacks_common::{info, warn};
+use wsts::state_machine::{DkgError, SignError};
+
+/// How long to wait for the webhook to answer before giving up on an alert
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many of a streak's most recent failures an alert describes
+const MAX_REPORTED_FAILURES: usize = 16;
+
+/// When, and how, to tell the operator that rounds keep failing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscalationConfig {
+    /// How many consecutive rounds of one kind (DKG or signing) must fail before escalating
+    pub threshold: u32,
+    /// URL to POST a JSON alert to
+    pub webhook: Option<String>,
+    /// Program (and its arguments) to run with a JSON alert on its standard input
+    pub command: Option<Vec<String>>,
+}
+
+/// A failed DKG or signing round
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RoundFailure {
+    /// The signer coordinating the round
+    pub coordinator_id: u32,
+    /// Why the round failed, e.g. "nonce_timeout", "dkg_public_timeout" or "stale"
+    pub reason: String,
+    /// The signers the failure is attributed to, if any
+    pub signer_ids: Vec<u32>,
+    /// When the round failed, in seconds since the epoch
+    pub timestamp: u64,
+}
+
+impl RoundFailure {
+    fn new(coordinator_id: u32, reason: &str, signer_ids: Vec<u32>) -> Self {
+        Self {
+            coordinator_id,
+            reason: reason.to_string(),
+            signer_ids,
+            timestamp: get_epoch_time_secs(),
+        }
+    }
+
+    /// A signing round coordinated by `coordinator_id` that ended in `error`
+    pub fn from_sign_error(coordinator_id: u32, error: &SignError) -> Self {
+        match error {
+            SignError::NonceTimeout(_valid_signers, malicious_signers) => {
+                Self::new(coordinator_id, "nonce_timeout", malicious_signers.clone())
+            }
+            SignError::InsufficientSigners(malicious_signers) => Self::new(
+                coordinator_id,
+                "insufficient_signers",
+                malicious_signers.clone(),
+            ),
+            SignError::Aggregator(_) => Self::new(coordinator_id, "aggregator", vec![]),
+        }
+    }
+
+    /// A DKG round coordinated by `coordinator_id` that ended in `error`.
+    /// Timeouts are attributed to the signers that were still being waited on.
+    pub fn from_dkg_error(coordinator_id: u32, error: &DkgError) -> Self {
+        match error {
+            DkgError::DkgPublicTimeout(waiting) => {
+                Self::new(coordinator_id, "dkg_public_timeout", waiting.clone())
+            }
+            DkgError::DkgPrivateTimeout(waiting) => {
+                Self::new(coordinator_id, "dkg_private_timeout", waiting.clone())
+            }
+            DkgError::DkgEndTimeout(waiting) => {
+                Self::new(coordinator_id, "dkg_end_timeout", waiting.clone())
+            }
+            DkgError::DkgEndFailure(failures) => {
+                let mut signer_ids: Vec<u32> = failures.keys().copied().collect();
+                signer_ids.sort_unstable();
+                Self::new(coordinator_id, "dkg_end_failure", signer_ids)
+            }
+        }
+    }
+
+    /// A round coordinated by `coordinator_id` that was abandoned after going without packets
+    /// for too long
+    pub fn stale(coordinator_id: u32) -> Self {
+        Self::new(coordinator_id, "stale", vec![])
+    }
+}
+
+/// An alert raised when too many consecutive rounds of one kind have failed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EscalationAlert {
+    /// The signer raising the alert
+    pub signer_id: u32,
+    /// The reward cycle whose rounds are failing
+    pub reward_cycle: u64,
+    /// The kind of round failing: "dkg" or "sign"
+    pub round_kind: String,
+    /// How many rounds of this kind have failed since the last one succeeded
+    pub consecutive_failures: u32,
+    /// The coordinators of the failed rounds, in the order they were first tried
+    pub coordinator_ids: Vec<u32>,
+    /// The most recent failed rounds, oldest first
+    pub failures: Vec<RoundFailure>,
+    /// How many packets for rounds of this kind failed verification since the last one
+    /// succeeded
+    pub malformed_packets: u64,
+}
+
+/// The rounds of one kind that failed since the last one succeeded
+#[derive(Debug, Default)]
+struct FailureStreak {
+    failures: u32,
+    coordinator_ids: Vec<u32>,
+    recent: VecDeque<RoundFailure>,
+    malformed_packets: u64,
+    /// Whether this streak has already been escalated
+    escalated: bool,
+}
+
+/// Counts the consecutive DKG and signing rounds that fail in a reward cycle, and escalates
+/// once as many fail in a row as the configured threshold. A streak is escalated only once;
+/// the next round of its kind to succeed ends it.
+#[derive(Debug)]
+pub struct RoundFailureTracker {
+    signer_id: u32,
+    reward_cycle: u64,
+    threshold: u32,
+    /// Streaks by round kind ("dkg" or "sign")
+    streaks: HashMap<&'static str, FailureStreak>,
+    notifier: Option<EscalationNotifier>,
+}
+
+impl RoundFailureTracker {
+    /// Track the rounds of `signer_id` in `reward_cycle`
+    pub fn new(config: &EscalationConfig, signer_id: u32, reward_cycle: u64) -> Self {
+        let notifier = if config.webhook.is_some() || config.command.is_some() {
+            Some(EscalationNotifier::new(
+                config.webhook.clone(),
+                config.command.clone(),
+            ))
+        } else {
+            None
+        };
+        Self {
+            signer_id,
+            reward_cycle,
+            threshold: config.threshold.max(1),
+            streaks: HashMap::new(),
+            notifier,
+        }
+    }
+
+    /// Record that a round of `round_kind` failed.
+    /// Returns the alert raised, if this failure makes its streak reach the threshold.
+    pub fn record_failure(
+        &mut self,
+        round_kind: &'static str,
+        failure: RoundFailure,
+    ) -> Option<EscalationAlert> {
+        let streak = self.streaks.entry(round_kind).or_default();
+        streak.failures = streak.failures.saturating_add(1);
+        if !streak.coordinator_ids.contains(&failure.coordinator_id) {
+            streak.coordinator_ids.push(failure.coordinator_id);
+        }
+        if streak.recent.len() >= MAX_REPORTED_FAILURES {
+            streak.recent.pop_front();
+        }
+        streak.recent.push_back(failure);
+        if streak.escalated || streak.failures < self.threshold {
+            return None;
+        }
+        streak.escalated = true;
+
+        let alert = EscalationAlert {
+            signer_id: self.signer_id,
+            reward_cycle: self.reward_cycle,
+            round_kind: round_kind.to_string(),
+            consecutive_failures: streak.failures,
+            coordinator_ids: streak.coordinator_ids.clone(),
+            failures: streak.recent.iter().cloned().collect(),
+            malformed_packets: streak.malformed_packets,
+        };
+        info!("Escalating repeated round failures";
+            "signer_id" => alert.signer_id,
+            "reward_cycle" => alert.reward_cycle,
+            "round_kind" => &alert.round_kind,
+            "consecutive_failures" => alert.consecutive_failures,
+            "coordinator_ids" => ?alert.coordinator_ids,
+            "malformed_packets" => alert.malformed_packets,
+        );
+        crate::monitoring::increment_round_failure_escalations(round_kind);
+        if let Some(notifier) = &self.notifier {
+            notifier.notify(alert.clone());
+        }
+        Some(alert)
+    }
+
+    /// Record that a round of `round_kind` succeeded, ending its streak
+    pub fn record_success(&mut self, round_kind: &'static str) {
+        self.streaks.remove(round_kind);
+    }
+
+    /// Record a packet for a round of `round_kind` that failed verification
+    pub fn record_malformed_packet(&mut self, round_kind: &'static str) {
+        let streak = self.streaks.entry(round_kind).or_default();
+        streak.malformed_packets = streak.malformed_packets.saturating_add(1);
+    }
+
+    /// How many rounds of `round_kind` have failed in a row
+    pub fn consecutive_failures(&self, round_kind: &str) -> u32 {
+        self.streaks
+            .get(round_kind)
+            .map(|streak| streak.failures)
+            .unwrap_or(0)
+    }
+}
+
+/// Delivers escalation alerts to the configured webhook and command, from a background thread
+#[derive(Debug)]
+struct EscalationNotifier {
+    sender: Sender<EscalationAlert>,
+}
+
+impl EscalationNotifier {
+    fn new(webhook: Option<String>, command: Option<Vec<String>>) -> Self {
+        let (sender, receiver) = channel::<EscalationAlert>();
+        thread::Builder::new()
+            .name("round-escalations".into())
+            .spawn(move || {
+                let client = reqwest::blocking::Client::new();
+                // Ends once the notifier (and so the sender) is dropped
+                for alert in receiver.iter() {
+                    if let Some(url) = &webhook {
+                        let result = client
+                            .post(url)
+                            .timeout(WEBHOOK_TIMEOUT)
+                            .json(&alert)
+                            .send()
+                            .and_then(|response| response.error_for_status());
+                        if let Err(e) = result {
+                            warn!("Failed to deliver round failure escalation: {e}";
+                                "reward_cycle" => alert.reward_cycle,
+                                "round_kind" => &alert.round_kind,
+                            );
+                        }
+                    }
+                    if let Some(command) = &command {
+                        if let Err(e) = run_escalation_command(command, &alert) {
+                            warn!("Failed to run round failure escalation command: {e}";
+                                "command" => ?command,
+                                "reward_cycle" => alert.reward_cycle,
+                                "round_kind" => &alert.round_kind,
+                            );
+                        }
+                    }
+                }
+            })
+            .expect("FATAL: failed to spawn round escalation thread");
+        Self { sender }
+    }
+
+    fn notify(&self, alert: EscalationAlert) {
+        if self.sender.send(alert).is_err() {
+            warn!("Round escalation thread has exited. Dropping alert.");
+        }
+    }
+}
+
+/// Run `command` with `alert` as JSON on its standard input, and wait for it to exit
+fn run_escalation_command(command: &[String], alert: &EscalationAlert) -> Result<(), String> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| "empty command".to_string())?;
+    let payload = serde_json::to_vec(alert).map_err(|e| e.to_string())?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Dropping stdin closes it, so the command sees the end of the alert
+        stdin.write_all(&payload).map_err(|e| e.to_string())?;
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("command exited with {status}"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn config(threshold: u32) -> EscalationConfig {
+        EscalationConfig {
+            threshold,
+            webhook: None,
+            command: None,
+        }
+    }
+
+    fn failure(coordinator_id: u32, reason: &str) -> RoundFailure {
+        RoundFailure::new(coordinator_id, reason, vec![])
+    }
+
+    #[test]
+    fn escalates_once_per_streak() {
+        let mut tracker = RoundFailureTracker::new(&config(3), 1, 7);
+        tracker.record_malformed_packet("sign");
+        assert!(tracker
+            .record_failure("sign", failure(0, "nonce_timeout"))
+            .is_none());
+        // failures of another kind do not count towards the streak
+        assert!(tracker
+            .record_failure("dkg", failure(0, "dkg_public_timeout"))
+            .is_none());
+        assert!(tracker
+            .record_failure("sign", failure(2, "nonce_timeout"))
+            .is_none());
+        let alert = tracker
+            .record_failure("sign", failure(0, "insufficient_signers"))
+            .expect("third consecutive failure should escalate");
+        assert_eq!(alert.signer_id, 1);
+        assert_eq!(alert.reward_cycle, 7);
+        assert_eq!(alert.round_kind, "sign");
+        assert_eq!(alert.consecutive_failures, 3);
+        assert_eq!(alert.coordinator_ids, vec![0, 2]);
+        assert_eq!(alert.failures.len(), 3);
+        assert_eq!(alert.failures[2].reason, "insufficient_signers");
+        assert_eq!(alert.malformed_packets, 1);
+
+        // the streak has been escalated already
+        assert!(tracker
+            .record_failure("sign", failure(1, "nonce_timeout"))
+            .is_none());
+        assert_eq!(tracker.consecutive_failures("sign"), 4);
+
+        // a success ends the streak, and the next one escalates again
+        tracker.record_success("sign");
+        assert_eq!(tracker.consecutive_failures("sign"), 0);
+        assert_eq!(tracker.consecutive_failures("dkg"), 1);
+        for _ in 0..2 {
+            assert!(tracker
+                .record_failure("sign", failure(1, "nonce_timeout"))
+                .is_none());
+        }
+        let alert = tracker
+            .record_failure("sign", failure(1, "nonce_timeout"))
+            .expect("new streak should escalate");
+        assert_eq!(alert.coordinator_ids, vec![1]);
+        assert_eq!(alert.malformed_packets, 0);
+    }
+
+    #[test]
+    fn alerts_describe_recent_failures_only() {
+        let threshold = MAX_REPORTED_FAILURES as u32 + 4;
+        let mut tracker = RoundFailureTracker::new(&config(threshold), 0, 1);
+        let mut alert = None;
+        for i in 0..threshold {
+            alert = tracker.record_failure("dkg", failure(i % 3, &format!("failure-{i}")));
+        }
+        let alert = alert.expect("streak should escalate");
+        assert_eq!(alert.failures.len(), MAX_REPORTED_FAILURES);
+        assert_eq!(alert.failures[0].reason, "failure-4");
+        assert_eq!(alert.coordinator_ids, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn escalation_command_receives_alert() {
+        let path = std::env::temp_dir().join(format!(
+            "escalation-command-{}-{}.json",
+            std::process::id(),
+            get_epoch_time_secs()
+        ));
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            format!("cat > {}", path.display()),
+        ];
+        let alert = EscalationAlert {
+            signer_id: 3,
+            reward_cycle: 9,
+            round_kind: "dkg".into(),
+            consecutive_failures: 2,
+            coordinator_ids: vec![1],
+            failures: vec![failure(1, "dkg_end_timeout")],
+            malformed_packets: 5,
+        };
+        run_escalation_command(&command, &alert).unwrap();
+        let written: EscalationAlert =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written, alert);
+        fs::remove_file(&path).unwrap();
+
+        assert!(run_escalation_command(&["false".to_string()], &alert).is_err());
+        assert!(run_escalation_command(&[], &alert).is_err());
+    }
+}
@@ -24,25 +24,61 @@ Usage documentation can be found in the [README](https://github.com/Trust-Machin
 pub mod cli;
 /// The signer client for communicating with stackerdb/stacks nodes
 pub mod client;
+/// Detects when the local clock is skewed from the burnchain's
+pub mod clock_skew;
 /// The configuration module for the signer
 pub mod config;
+/// Detects when the node's chain tip diverges from the network's
+pub mod divergence;
+/// Exporting and importing DKG keys over the operator RPC
+pub mod dkg_keys;
+/// Alerting the operator when DKG or signing rounds keep failing
+pub mod escalation;
+/// Moving the event receiver to another address over the operator RPC
+#[cfg(unix)]
+pub mod listener_control;
+/// The operator RPC, served on an owner-only Unix socket
+pub mod local_rpc;
+/// Signing arbitrary digests requested over the operator RPC
+pub mod message_signing;
 /// The monitoring server for the signer
 pub mod monitoring;
+/// Choosing which kinds of operation the signer takes part in, by reward cycle
+pub mod participation;
+/// Bounding the time the runloop spends in a single pass
+pub mod pass_budget;
+/// Registering for the next reward cycle as soon as its reward set is calculable
+pub mod preregistration;
+/// Receipts for completed signatures, delivered to an external webhook
+pub mod receipts;
 /// The primary runloop for the signer
 pub mod runloop;
+/// Private key handling: zeroization and encryption at rest
+pub mod secrets;
+/// Running a signer in-process against a mock stacks node, for end-to-end tests
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+/// Coordinator timeouts that adapt to the latency observed in prior rounds
+pub mod timeouts;
 /// The v0 implementation of the signer. This does not include WSTS support
 pub mod v0;
 /// The v1 implementation of the singer. This includes WSTS support
 pub mod v1;
+/// The watchdog bounding the time spent processing each event
+pub mod watchdog;
 use std::fmt::{Debug, Display};
 use std::sync::mpsc::Sender;
+use std::time::Duration;
 
 use libsigner::{SignerEvent, SignerEventTrait};
 use wsts::state_machine::OperationResult;
 
 use crate::client::StacksClient;
 use crate::config::SignerConfig;
+use crate::divergence::ChainTip;
 use crate::runloop::RunLoopCommand;
+use crate::timeouts::TimeoutPhase;
+use crate::watchdog::EventWatch;
 
 /// A trait which provides a common `Signer` interface for `v1` and `v2`
 pub trait Signer<T: SignerEventTrait>: Debug + Display {
@@ -67,4 +103,23 @@ pub trait Signer<T: SignerEventTrait>: Debug + Display {
         current_reward_cycle: u64,
         command: Option<RunLoopCommand>,
     );
+    /// Take the coordinator phase latencies observed since the last call
+    fn take_round_latencies(&mut self) -> Vec<(TimeoutPhase, Duration)> {
+        vec![]
+    }
+    /// Whether the signer has the aggregate key it needs to sign blocks for its reward cycle
+    fn has_aggregate_key(&self) -> bool {
+        true
+    }
+    /// Take the chain tips implied by the block proposals received since the last call
+    fn take_observed_tips(&mut self) -> Vec<ChainTip> {
+        vec![]
+    }
+    /// Publish the signer's key material and claim its StackerDB slot ahead of its reward
+    /// cycle, once it is known to be registered for it
+    fn publish_registration(&mut self) {}
+    /// Watch the processing of the next event, deferring the rest of it to the next event once
+    /// `watch` reports that it has run over the event watchdog's timeout. Called with None once the event has
+    /// been processed.
+    fn watch_event(&mut self, _watch: Option<EventWatch>) {}
 }
@@ -0,0 +1,93 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::ToSocketAddrs;
+
+use libsigner::ListenerRebinder;
+use slog::slog_info;
+use stacks_common::info;
+
+use crate::local_rpc::OperatorResponse;
+
+/// Serve the operator RPC's `rebind` method: move the event receiver to `endpoint`.  If the
+/// new address cannot be bound, the event receiver keeps listening where it was.
+pub(crate) fn rebind(rebinder: &ListenerRebinder, endpoint: &str) -> OperatorResponse {
+    let Some(endpoint) = endpoint
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+    else {
+        return OperatorResponse::Error {
+            reason: format!("cannot resolve endpoint '{endpoint}'"),
+        };
+    };
+    info!("Listener control: moving the event receiver";
+        "old_endpoint" => ?rebinder.local_addr(),
+        "new_endpoint" => %endpoint,
+    );
+    match rebinder.rebind(endpoint) {
+        Ok(endpoint) => OperatorResponse::Rebound {
+            endpoint: endpoint.to_string(),
+        },
+        Err(e) => OperatorResponse::Error {
+            reason: format!("failed to move the event receiver: {e}"),
+        },
+    }
+}
+
+/// Serve the operator RPC's `close_old_listener` method: stop serving the address the event
+/// receiver moved away from
+pub(crate) fn close_old_listener(rebinder: &ListenerRebinder) -> OperatorResponse {
+    match rebinder.close_retired() {
+        Ok(endpoint) => OperatorResponse::Closed {
+            endpoint: endpoint.map(|endpoint| endpoint.to_string()),
+        },
+        Err(e) => OperatorResponse::Error {
+            reason: format!("failed to close the old listener: {e}"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libsigner::v1::messages::SignerMessage;
+    use libsigner::SignerEventReceiver;
+
+    use super::*;
+
+    #[test]
+    fn requests_are_checked() {
+        let ev = SignerEventReceiver::<SignerMessage>::new(false);
+        let rebinder = ev.listener_rebinder();
+
+        assert!(matches!(
+            rebind(&rebinder, "not an endpoint"),
+            OperatorResponse::Error { .. }
+        ));
+        // the event receiver has not been started
+        assert_eq!(
+            rebind(&rebinder, "127.0.0.1:30001"),
+            OperatorResponse::Error {
+                reason: "failed to move the event receiver: Not bound to a port yet".to_string()
+            }
+        );
+        assert_eq!(
+            close_old_listener(&rebinder),
+            OperatorResponse::Error {
+                reason: "failed to close the old listener: Not bound to a port yet".to_string()
+            }
+        );
+    }
+}
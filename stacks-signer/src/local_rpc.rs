@@ -0,0 +1,575 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The signer's operator RPC, served on a Unix socket that only the signer's own user may
+//! connect to.  Each connection carries one JSON request line and gets one JSON reply line.
+//! A request names the method to run, and must carry the signer's `operator_rpc_auth_token`.
+
+use std::io;
+use std::path::Path;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::dkg_keys::DkgKeys;
+
+/// Environment variable the CLI reads the operator RPC auth token from, if no token file is
+/// given
+pub const OPERATOR_RPC_AUTH_TOKEN_ENV: &str = "STACKS_SIGNER_OPERATOR_RPC_AUTH_TOKEN";
+
+/// A request to the operator RPC: one JSON object per line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperatorRequest {
+    /// The signer's `operator_rpc_auth_token`
+    pub auth_token: String,
+    /// What the signer is asked to do
+    #[serde(flatten)]
+    pub method: OperatorMethod,
+}
+
+/// What an `OperatorRequest` asks for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum OperatorMethod {
+    /// Sign a digest with the reward cycle's aggregate key, and wait for the signature
+    SignMessage {
+        /// The reward cycle whose signer set should sign the digest
+        reward_cycle: u64,
+        /// Hex-encoded 32-byte digest
+        digest: String,
+        /// How long to wait for the signature, in seconds
+        timeout_secs: Option<u64>,
+    },
+    /// Export the signer's DKG key material for the reward cycle
+    DumpKeys {
+        /// The reward cycle whose keys to export
+        reward_cycle: u64,
+    },
+    /// Use a known-good aggregate key for the reward cycle until the voting contract
+    /// approves one
+    ImportAggregateKey {
+        /// The reward cycle the key belongs to
+        reward_cycle: u64,
+        /// Hex-encoded compressed point
+        aggregate_key: String,
+    },
+    /// Receive events from the node on another address.  The old address is still served
+    /// until it has been idle for the signer's `retired_listener_idle_ms`, or until it is
+    /// closed with `CloseOldListener`.
+    Rebind {
+        /// The address to receive events on instead, e.g. `0.0.0.0:30001`
+        endpoint: String,
+    },
+    /// Stop serving the address the event receiver moved away from
+    CloseOldListener,
+}
+
+/// The reply to an `OperatorRequest`: one JSON object per line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum OperatorResponse {
+    /// The signer set produced an aggregate signature over the digest
+    Signed {
+        /// The compressed nonce point `R` followed by `z`, hex-encoded
+        signature: String,
+    },
+    /// No signature yet. The request may be repeated to keep waiting.
+    Pending,
+    /// The signer's key material for the requested reward cycle
+    Keys {
+        /// The aggregate key and public shares
+        keys: DkgKeys,
+    },
+    /// The import was handed to the signer. Dump the keys to see whether it was applied.
+    Queued,
+    /// The event receiver now listens on a new address
+    Rebound {
+        /// The address the event receiver listens on
+        endpoint: String,
+    },
+    /// The old listener is closed
+    Closed {
+        /// The address that is no longer served, or None if there was no old listener
+        endpoint: Option<String>,
+    },
+    /// The request was rejected, or the signer could not carry it out
+    Error {
+        /// Why the request failed
+        reason: String,
+    },
+}
+
+/// Send `request` to the operator RPC served on `socket`, and wait for the reply
+#[cfg(unix)]
+pub fn send_request(socket: &Path, request: &OperatorRequest) -> io::Result<OperatorResponse> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(socket)?;
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(serde_json::from_str(reply.trim())?)
+}
+
+/// Send `request` to the operator RPC served on `socket`, and wait for the reply
+#[cfg(not(unix))]
+pub fn send_request(_socket: &Path, _request: &OperatorRequest) -> io::Result<OperatorResponse> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Unix sockets are not supported on this platform",
+    ))
+}
+
+#[cfg(unix)]
+pub use self::server::{
+    bind_owner_only, serve, OperatorRpcServer, OperatorServices, CONNECTION_IO_TIMEOUT,
+    MAX_CONNECTIONS, MAX_REQUEST_LEN,
+};
+
+#[cfg(unix)]
+mod server {
+    use std::fs::{self, DirBuilder};
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc::Sender;
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use libsigner::ListenerRebinder;
+    use serde::Serialize;
+    use slog::{slog_debug, slog_info, slog_warn};
+    use stacks_common::{debug, info, warn};
+
+    use super::{OperatorMethod, OperatorRequest, OperatorResponse};
+    use crate::dkg_keys::{self, DkgKeyRegistry};
+    use crate::listener_control;
+    use crate::message_signing::{self, MessageSigningRegistry};
+    use crate::runloop::RunLoopCommand;
+
+    /// Longest request line accepted, in bytes
+    pub const MAX_REQUEST_LEN: u64 = 4096;
+
+    /// How long a client may take to send its request, or to take the reply
+    pub const CONNECTION_IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Most connections a server handles at once.  Further connections wait to be accepted.
+    pub const MAX_CONNECTIONS: usize = 8;
+
+    /// The parts of the running signer that the operator RPC acts on
+    #[derive(Clone)]
+    pub struct OperatorServices {
+        /// The digests the operator asked to sign, and their signatures
+        pub message_signing: MessageSigningRegistry,
+        /// The DKG key material the signers publish
+        pub dkg_keys: DkgKeyRegistry,
+        /// Moves the event receiver to another address
+        pub rebinder: ListenerRebinder,
+        /// Hands commands to the signer's runloop
+        pub cmd_send: Sender<RunLoopCommand>,
+    }
+
+    /// Serves the operator RPC on a Unix socket
+    pub struct OperatorRpcServer {
+        listener: UnixListener,
+        auth_token: String,
+        services: OperatorServices,
+    }
+
+    impl OperatorRpcServer {
+        /// Bind to `path`, replacing any stale socket file. Only the signer's own user may
+        /// connect to the socket.
+        pub fn bind(
+            path: &Path,
+            auth_token: String,
+            services: OperatorServices,
+        ) -> io::Result<Self> {
+            let listener = bind_owner_only(path)?;
+            Ok(Self {
+                listener,
+                auth_token,
+                services,
+            })
+        }
+
+        /// Bind to `path` and serve requests in a background thread
+        pub fn spawn(
+            path: PathBuf,
+            auth_token: String,
+            services: OperatorServices,
+        ) -> io::Result<()> {
+            let server = Self::bind(&path, auth_token, services)?;
+            info!("Serving operator RPC requests"; "socket" => %path.display());
+            thread::Builder::new()
+                .name("operator_rpc".to_string())
+                .spawn(move || server.run())?;
+            Ok(())
+        }
+
+        /// Accept connections until the listener fails.  A request may wait for a signature,
+        /// so several connections are handled at once.
+        pub fn run(self) {
+            let Self {
+                listener,
+                auth_token,
+                services,
+            } = self;
+            serve(listener, "Operator RPC", move |line: &str| {
+                handle_request(line, &auth_token, &services)
+            });
+        }
+    }
+
+    /// Compare the expected and provided auth tokens without leaking where they differ
+    fn tokens_match(expected: &str, provided: &str) -> bool {
+        let (expected, provided) = (expected.as_bytes(), provided.as_bytes());
+        expected.len() == provided.len()
+            && expected
+                .iter()
+                .zip(provided)
+                .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+
+    fn handle_request(
+        line: &str,
+        auth_token: &str,
+        services: &OperatorServices,
+    ) -> OperatorResponse {
+        let request: OperatorRequest = match serde_json::from_str(line.trim()) {
+            Ok(request) => request,
+            Err(e) => {
+                return OperatorResponse::Error {
+                    reason: format!("malformed request: {e}"),
+                }
+            }
+        };
+        if !tokens_match(auth_token, &request.auth_token) {
+            warn!("Operator RPC: rejected request with a bad auth token");
+            return OperatorResponse::Error {
+                reason: "unauthorized".to_string(),
+            };
+        }
+        match request.method {
+            OperatorMethod::SignMessage {
+                reward_cycle,
+                digest,
+                timeout_secs,
+            } => message_signing::sign_message(
+                &services.message_signing,
+                &services.cmd_send,
+                reward_cycle,
+                &digest,
+                timeout_secs,
+            ),
+            OperatorMethod::DumpKeys { reward_cycle } => {
+                dkg_keys::dump_keys(&services.dkg_keys, reward_cycle)
+            }
+            OperatorMethod::ImportAggregateKey {
+                reward_cycle,
+                aggregate_key,
+            } => dkg_keys::import_aggregate_key(&services.cmd_send, reward_cycle, &aggregate_key),
+            OperatorMethod::Rebind { endpoint } => {
+                listener_control::rebind(&services.rebinder, &endpoint)
+            }
+            OperatorMethod::CloseOldListener => {
+                listener_control::close_old_listener(&services.rebinder)
+            }
+        }
+    }
+
+    /// Bind a socket at `path` that only the signer's own user may connect to, replacing any
+    /// stale socket file.
+    ///
+    /// The socket is bound inside a new owner-only directory next to `path`, and moved to
+    /// `path` once its permissions are set, so nobody else can connect to it in between.
+    pub fn bind_owner_only(path: &Path) -> io::Result<UnixListener> {
+        let file_name = path.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "socket path has no name")
+        })?;
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let staging_dir = parent.join(format!(
+            ".{}.{:016x}",
+            file_name.to_string_lossy(),
+            rand::random::<u64>()
+        ));
+        DirBuilder::new().mode(0o700).create(&staging_dir)?;
+
+        let staged_path = staging_dir.join(file_name);
+        let bound = UnixListener::bind(&staged_path).and_then(|listener| {
+            fs::set_permissions(&staged_path, fs::Permissions::from_mode(0o600))?;
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            fs::rename(&staged_path, path)?;
+            Ok(listener)
+        });
+        if let Err(e) = fs::remove_dir_all(&staging_dir) {
+            warn!("Failed to remove {}: {e:?}", staging_dir.display());
+        }
+        bound
+    }
+
+    /// Accept connections on `listener` until it fails, answering each connection's request
+    /// line with `handler`'s reply.  Up to `MAX_CONNECTIONS` connections are handled at once,
+    /// each in its own thread.  `name` prefixes the server's log messages.
+    pub fn serve<H, R>(listener: UnixListener, name: &'static str, handler: H)
+    where
+        H: Fn(&str) -> R + Clone + Send + 'static,
+        R: Serialize,
+    {
+        let active = Arc::new((Mutex::new(0usize), Condvar::new()));
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("{name}: failed to accept connection: {e:?}");
+                    continue;
+                }
+            };
+            let slot = ConnectionSlot::acquire(&active);
+            let handler = handler.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("{}_conn", name.to_lowercase().replace(' ', "_")))
+                .spawn(move || {
+                    handle_connection(stream, name, &handler);
+                    drop(slot);
+                });
+            if let Err(e) = spawned {
+                warn!("{name}: failed to spawn connection handler: {e:?}");
+            }
+        }
+    }
+
+    /// One of the `MAX_CONNECTIONS` connections a server may handle at once, released on drop
+    struct ConnectionSlot {
+        active: Arc<(Mutex<usize>, Condvar)>,
+    }
+
+    impl ConnectionSlot {
+        /// Wait for a connection to finish if there are too many
+        fn acquire(active: &Arc<(Mutex<usize>, Condvar)>) -> Self {
+            let (count, freed) = &**active;
+            let mut count = count.lock().expect("FATAL: connection count poisoned");
+            while *count >= MAX_CONNECTIONS {
+                count = freed.wait(count).expect("FATAL: connection count poisoned");
+            }
+            *count += 1;
+            Self {
+                active: active.clone(),
+            }
+        }
+    }
+
+    impl Drop for ConnectionSlot {
+        fn drop(&mut self) {
+            let (count, freed) = &*self.active;
+            if let Ok(mut count) = count.lock() {
+                *count = count.saturating_sub(1);
+            }
+            freed.notify_one();
+        }
+    }
+
+    fn handle_connection<H, R>(stream: UnixStream, name: &str, handler: &H)
+    where
+        H: Fn(&str) -> R,
+        R: Serialize,
+    {
+        let timeouts = stream
+            .set_read_timeout(Some(CONNECTION_IO_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(CONNECTION_IO_TIMEOUT)));
+        let mut writer = match timeouts.and_then(|_| stream.try_clone()) {
+            Ok(writer) => writer,
+            Err(e) => {
+                warn!("{name}: failed to set up connection: {e:?}");
+                return;
+            }
+        };
+        let mut reader = BufReader::new(stream).take(MAX_REQUEST_LEN);
+        let mut line = String::new();
+        if let Err(e) = reader.read_line(&mut line) {
+            debug!("{name}: failed to read request: {e:?}");
+            return;
+        }
+        let mut reply =
+            serde_json::to_string(&handler(&line)).expect("FATAL: failed to encode reply");
+        reply.push('\n');
+        if let Err(e) = writer.write_all(reply.as_bytes()) {
+            debug!("{name}: failed to send reply: {e:?}");
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::sync::mpsc::channel;
+
+        use libsigner::v1::messages::SignerMessage;
+        use libsigner::SignerEventReceiver;
+
+        use super::*;
+
+        fn request(path: &Path, line: &str) -> String {
+            let mut stream = UnixStream::connect(path).unwrap();
+            stream.write_all(line.as_bytes()).unwrap();
+            let mut reply = String::new();
+            BufReader::new(stream).read_line(&mut reply).unwrap();
+            reply
+        }
+
+        fn operator_request(auth_token: &str, method: OperatorMethod) -> String {
+            serde_json::to_string(&OperatorRequest {
+                auth_token: auth_token.to_string(),
+                method,
+            })
+            .unwrap()
+        }
+
+        #[test]
+        fn sockets_are_owner_only_and_serve_requests() {
+            let dir = std::env::temp_dir().join(format!("local_rpc-{}", rand::random::<u64>()));
+            fs::create_dir(&dir).unwrap();
+            let path = dir.join("rpc.sock");
+            // a stale socket file is replaced
+            fs::write(&path, b"stale").unwrap();
+
+            let listener = bind_owner_only(&path).unwrap();
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+            // nothing but the socket is left behind
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+            thread::spawn(move || serve(listener, "Test RPC", |line: &str| line.trim().len()));
+
+            // a client that never sends its request does not hold up the others
+            let _idle = UnixStream::connect(&path).unwrap();
+            assert_eq!(request(&path, "hello\n"), "5\n");
+            assert_eq!(request(&path, "{}\n"), "2\n");
+
+            fs::remove_dir_all(&dir).unwrap();
+        }
+
+        #[test]
+        fn idle_clients_are_dropped() {
+            let dir = std::env::temp_dir().join(format!("local_rpc-{}", rand::random::<u64>()));
+            fs::create_dir(&dir).unwrap();
+            let path = dir.join("rpc.sock");
+            let listener = bind_owner_only(&path).unwrap();
+            thread::spawn(move || serve(listener, "Test RPC", |_: &str| 0));
+
+            let stream = UnixStream::connect(&path).unwrap();
+            stream
+                .set_read_timeout(Some(CONNECTION_IO_TIMEOUT * 2))
+                .unwrap();
+            // the server gives up on the request and closes the connection without a reply
+            let mut reply = String::new();
+            assert_eq!(BufReader::new(stream).read_line(&mut reply).unwrap(), 0);
+
+            fs::remove_dir_all(&dir).unwrap();
+        }
+
+        #[test]
+        fn tokens_must_match_exactly() {
+            assert!(tokens_match("secret", "secret"));
+            assert!(!tokens_match("secret", "secreT"));
+            assert!(!tokens_match("secret", "secret2"));
+            assert!(!tokens_match("secret", ""));
+        }
+
+        #[test]
+        fn every_method_is_authenticated() {
+            let ev = SignerEventReceiver::<SignerMessage>::new(false);
+            let (cmd_send, cmd_recv) = channel();
+            let services = OperatorServices {
+                message_signing: MessageSigningRegistry::default(),
+                dkg_keys: DkgKeyRegistry::default(),
+                rebinder: ev.listener_rebinder(),
+                cmd_send,
+            };
+
+            for method in [
+                OperatorMethod::SignMessage {
+                    reward_cycle: 7,
+                    digest: "00".repeat(32),
+                    timeout_secs: Some(0),
+                },
+                OperatorMethod::DumpKeys { reward_cycle: 7 },
+                OperatorMethod::ImportAggregateKey {
+                    reward_cycle: 7,
+                    aggregate_key: "00".repeat(33),
+                },
+                OperatorMethod::Rebind {
+                    endpoint: "127.0.0.1:30001".to_string(),
+                },
+                OperatorMethod::CloseOldListener,
+            ] {
+                assert_eq!(
+                    handle_request(&operator_request("wrong", method), "secret", &services),
+                    OperatorResponse::Error {
+                        reason: "unauthorized".to_string()
+                    }
+                );
+            }
+            assert!(cmd_recv.try_recv().is_err());
+
+            assert!(matches!(
+                handle_request("not json", "secret", &services),
+                OperatorResponse::Error { .. }
+            ));
+            // an authenticated request reaches its method
+            assert!(matches!(
+                handle_request(
+                    &operator_request("secret", OperatorMethod::DumpKeys { reward_cycle: 7 }),
+                    "secret",
+                    &services
+                ),
+                OperatorResponse::Error { reason } if reason == "no signer for reward cycle 7"
+            ));
+        }
+
+        #[test]
+        fn requests_are_encoded_with_a_method() {
+            assert_eq!(
+                operator_request("secret", OperatorMethod::CloseOldListener),
+                r#"{"auth_token":"secret","method":"close_old_listener"}"#
+            );
+            assert_eq!(
+                operator_request("secret", OperatorMethod::DumpKeys { reward_cycle: 4 }),
+                r#"{"auth_token":"secret","method":"dump_keys","reward_cycle":4}"#
+            );
+            // the wait for a signature is optional
+            let request: OperatorRequest = serde_json::from_str(
+                r#"{"auth_token":"secret","method":"sign_message","reward_cycle":82,"digest":"00"}"#,
+            )
+            .unwrap();
+            assert_eq!(
+                request.method,
+                OperatorMethod::SignMessage {
+                    reward_cycle: 82,
+                    digest: "00".to_string(),
+                    timeout_secs: None,
+                }
+            );
+        }
+    }
+}
@@ -33,18 +33,25 @@ use clap::Parser;
 use clarity::vm::types::QualifiedContractIdentifier;
 use libsigner::{SignerSession, StackerDBSession};
 use libstackerdb::StackerDBChunkData;
+use rand_core::OsRng;
 use slog::slog_debug;
 use stacks_common::debug;
 use stacks_common::util::hash::to_hex;
 use stacks_common::util::secp256k1::{MessageSignature, Secp256k1PublicKey};
 use stacks_signer::cli::{
-    Cli, Command, GenerateStackingSignatureArgs, GetChunkArgs, GetLatestChunkArgs, PutChunkArgs,
-    RunSignerArgs, StackerDBArgs,
+    Cli, Command, DkgKeyRpcArgs, EncryptPrivateKeyArgs, GenerateStackingSignatureArgs,
+    GetChunkArgs, GetLatestChunkArgs, ImportAggregateKeyArgs, OperatorRpcArgs, PutChunkArgs,
+    RebindListenerArgs, RunSignerArgs, StackerDBArgs,
 };
 use stacks_signer::config::GlobalConfig;
+use stacks_signer::local_rpc::{
+    send_request, OperatorMethod, OperatorRequest, OperatorResponse, OPERATOR_RPC_AUTH_TOKEN_ENV,
+};
+use stacks_signer::secrets::{encrypt_private_key, KeyEncryptionKind, KEY_PASSPHRASE_ENV};
 use stacks_signer::v1;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::{fmt, EnvFilter};
+use zeroize::Zeroizing;
 
 /// Create a new stacker db session
 fn stackerdb_session(host: &str, contract: QualifiedContractIdentifier) -> StackerDBSession {
@@ -115,7 +122,7 @@ fn handle_generate_stacking_signature(
 ) -> MessageSignature {
     let config = GlobalConfig::try_from(&args.config).unwrap();
 
-    let private_key = config.stacks_private_key;
+    let private_key = config.stacks_private_key.stacks_private_key();
     let public_key = Secp256k1PublicKey::from_private(&private_key);
 
     let signature = make_pox_4_signer_key_signature(
@@ -162,6 +169,51 @@ fn handle_check_config(args: RunSignerArgs) {
     println!("Config: {}", config);
 }
 
+fn handle_encrypt_private_key(args: EncryptPrivateKeyArgs) {
+    let secret = Zeroizing::new(match &args.passphrase_file {
+        Some(path) => std::fs::read_to_string(path).expect("Failed to read passphrase file"),
+        None => std::env::var(KEY_PASSPHRASE_ENV).unwrap_or_else(|_| {
+            panic!("Neither --passphrase-file nor {KEY_PASSPHRASE_ENV} is set")
+        }),
+    });
+    let kind = if args.raw_key {
+        KeyEncryptionKind::RawKey
+    } else {
+        KeyEncryptionKind::Passphrase
+    };
+    let encrypted = encrypt_private_key(
+        &args.private_key,
+        kind,
+        secret.trim_end_matches(['\r', '\n']),
+        &mut OsRng,
+    )
+    .expect("Failed to encrypt private key");
+    println!("{encrypted}");
+}
+
+/// Send `method` to a running signer's operator RPC, and print the reply
+fn handle_operator_request(args: &OperatorRpcArgs, method: OperatorMethod) {
+    let auth_token = match &args.auth_token_file {
+        Some(path) => std::fs::read_to_string(path).expect("Failed to read auth token file"),
+        None => std::env::var(OPERATOR_RPC_AUTH_TOKEN_ENV).unwrap_or_else(|_| {
+            panic!("Neither --auth-token-file nor {OPERATOR_RPC_AUTH_TOKEN_ENV} is set")
+        }),
+    };
+    let request = OperatorRequest {
+        auth_token: auth_token.trim_end_matches(['\r', '\n']).to_string(),
+        method,
+    };
+    let response =
+        send_request(&args.socket, &request).expect("Failed to reach the signer's operator RPC");
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&response).expect("Failed to serialize JSON")
+    );
+    if let OperatorResponse::Error { .. } = response {
+        std::process::exit(1);
+    }
+}
+
 fn main() {
     let cli = Cli::parse();
 
@@ -192,6 +244,37 @@ fn main() {
         Command::CheckConfig(args) => {
             handle_check_config(args);
         }
+        Command::EncryptPrivateKey(args) => {
+            handle_encrypt_private_key(args);
+        }
+        Command::DumpDkgKeys(DkgKeyRpcArgs {
+            rpc_args,
+            reward_cycle,
+        }) => {
+            handle_operator_request(&rpc_args, OperatorMethod::DumpKeys { reward_cycle });
+        }
+        Command::ImportAggregateKey(ImportAggregateKeyArgs {
+            rpc_args:
+                DkgKeyRpcArgs {
+                    rpc_args,
+                    reward_cycle,
+                },
+            aggregate_key,
+        }) => {
+            handle_operator_request(
+                &rpc_args,
+                OperatorMethod::ImportAggregateKey {
+                    reward_cycle,
+                    aggregate_key,
+                },
+            );
+        }
+        Command::RebindListener(RebindListenerArgs { rpc_args, endpoint }) => {
+            handle_operator_request(&rpc_args, OperatorMethod::Rebind { endpoint });
+        }
+        Command::CloseOldListener(args) => {
+            handle_operator_request(&args, OperatorMethod::CloseOldListener);
+        }
     }
 }
 
@@ -264,7 +347,8 @@ pub mod tests {
         };
 
         let signature = handle_generate_stacking_signature(args.clone(), false);
-        let public_key = Secp256k1PublicKey::from_private(&config.stacks_private_key);
+        let public_key =
+            Secp256k1PublicKey::from_private(&config.stacks_private_key.stacks_private_key());
 
         let valid = call_verify_signer_sig(
             &args.pox_address,
@@ -287,7 +371,8 @@ pub mod tests {
         args.max_amount = 100;
 
         let signature = handle_generate_stacking_signature(args.clone(), false);
-        let public_key = Secp256k1PublicKey::from_private(&config.stacks_private_key);
+        let public_key =
+            Secp256k1PublicKey::from_private(&config.stacks_private_key.stacks_private_key());
 
         let valid = call_verify_signer_sig(
             &args.pox_address,
@@ -320,7 +405,8 @@ pub mod tests {
 
         let signature = handle_generate_stacking_signature(args.clone(), false);
 
-        let public_key = Secp256k1PublicKey::from_private(&config.stacks_private_key);
+        let public_key =
+            Secp256k1PublicKey::from_private(&config.stacks_private_key.stacks_private_key());
 
         let message_hash = make_pox_4_signer_key_message_hash(
             &args.pox_address,
@@ -0,0 +1,337 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use hashbrown::HashMap;
+use stacks_common::util::hash::{to_hex, Sha256Sum};
+use wsts::common::Signature;
+
+/// Prefix of an encoded `ArbitraryMessage`, so that it can never be mistaken for a block
+const ARBITRARY_MESSAGE_MAGIC: &[u8; 8] = b"SGNRMSG\x01";
+
+/// Length of an encoded `ArbitraryMessage`: magic, reward cycle, digest
+const ARBITRARY_MESSAGE_LEN: usize = 8 + 8 + 32;
+
+/// How long a digest stays authorized for signing (and its signature stays available)
+pub const MESSAGE_AUTHORIZATION_TTL: Duration = Duration::from_secs(600);
+
+/// A digest that the signer set is asked to sign outside of block signing, e.g. a
+/// cross-chain attestation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArbitraryMessage {
+    /// The reward cycle whose signer set (and aggregate key) should sign the digest
+    pub reward_cycle: u64,
+    /// The digest to sign
+    pub digest: Sha256Sum,
+}
+
+impl ArbitraryMessage {
+    /// The bytes the signing round is run over
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ARBITRARY_MESSAGE_LEN);
+        bytes.extend_from_slice(ARBITRARY_MESSAGE_MAGIC);
+        bytes.extend_from_slice(&self.reward_cycle.to_be_bytes());
+        bytes.extend_from_slice(self.digest.as_bytes());
+        bytes
+    }
+
+    /// Decode the message a signing round is run over.
+    /// Returns None if it is not an arbitrary message.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != ARBITRARY_MESSAGE_LEN || !bytes.starts_with(ARBITRARY_MESSAGE_MAGIC) {
+            return None;
+        }
+        let mut reward_cycle = [0u8; 8];
+        reward_cycle.copy_from_slice(&bytes[8..16]);
+        Some(Self {
+            reward_cycle: u64::from_be_bytes(reward_cycle),
+            digest: Sha256Sum::from_bytes(&bytes[16..])?,
+        })
+    }
+}
+
+/// Hex encoding of a signature: the compressed nonce point `R` followed by `z`
+pub fn signature_to_hex(signature: &Signature) -> String {
+    let mut bytes = signature.R.compress().data.to_vec();
+    bytes.extend_from_slice(&signature.z.to_bytes());
+    to_hex(&bytes)
+}
+
+#[derive(Debug, Default)]
+struct RegistryState {
+    /// Messages the operator asked to sign, and when that authorization expires
+    authorized: HashMap<ArbitraryMessage, Instant>,
+    /// Signatures over authorized messages, and when they are forgotten
+    signatures: HashMap<ArbitraryMessage, (Signature, Instant)>,
+}
+
+impl RegistryState {
+    fn prune(&mut self, now: Instant) {
+        self.authorized.retain(|_, expiry| *expiry > now);
+        self.signatures.retain(|_, (_, expiry)| *expiry > now);
+    }
+}
+
+/// The arbitrary messages this signer has been asked to sign, shared between the RPC server
+/// (which authorizes messages and waits for their signatures) and the signer (which only
+/// contributes to signing rounds over authorized messages).
+#[derive(Debug, Clone, Default)]
+pub struct MessageSigningRegistry {
+    inner: Arc<(Mutex<RegistryState>, Condvar)>,
+}
+
+impl MessageSigningRegistry {
+    /// Allow this signer to take part in a signing round over `message`, for the next
+    /// `MESSAGE_AUTHORIZATION_TTL`
+    pub fn authorize(&self, message: &ArbitraryMessage) {
+        let (state, _) = &*self.inner;
+        let mut state = state.lock().expect("message signing registry poisoned");
+        let now = Instant::now();
+        state.prune(now);
+        state
+            .authorized
+            .insert(*message, now + MESSAGE_AUTHORIZATION_TTL);
+    }
+
+    /// Has the operator asked this signer to sign `message`?
+    pub fn is_authorized(&self, message: &ArbitraryMessage) -> bool {
+        let (state, _) = &*self.inner;
+        let state = state.lock().expect("message signing registry poisoned");
+        state
+            .authorized
+            .get(message)
+            .map(|expiry| *expiry > Instant::now())
+            .unwrap_or(false)
+    }
+
+    /// Record the aggregate signature over `message`, waking anyone waiting for it.
+    /// Signatures over messages that were never authorized are ignored.
+    pub fn complete(&self, message: &ArbitraryMessage, signature: Signature) {
+        let (state, signed) = &*self.inner;
+        let mut state = state.lock().expect("message signing registry poisoned");
+        let Some(expiry) = state.authorized.get(message).copied() else {
+            return;
+        };
+        state.signatures.insert(*message, (signature, expiry));
+        signed.notify_all();
+    }
+
+    /// Wait at most `timeout` for the aggregate signature over `message`
+    pub fn wait_for_signature(
+        &self,
+        message: &ArbitraryMessage,
+        timeout: Duration,
+    ) -> Option<Signature> {
+        let (state, signed) = &*self.inner;
+        let state = state.lock().expect("message signing registry poisoned");
+        let (state, _) = signed
+            .wait_timeout_while(state, timeout, |state| {
+                !state.signatures.contains_key(message)
+            })
+            .expect("message signing registry poisoned");
+        state
+            .signatures
+            .get(message)
+            .map(|(signature, _)| signature.clone())
+    }
+}
+
+#[cfg(unix)]
+pub(crate) use self::rpc::sign_message;
+
+#[cfg(unix)]
+mod rpc {
+    use std::sync::mpsc::Sender;
+    use std::time::Duration;
+
+    use slog::slog_info;
+    use stacks_common::info;
+    use stacks_common::util::hash::Sha256Sum;
+
+    use super::{
+        signature_to_hex, ArbitraryMessage, MessageSigningRegistry, MESSAGE_AUTHORIZATION_TTL,
+    };
+    use crate::local_rpc::OperatorResponse;
+    use crate::runloop::{RunLoopCommand, SignerCommand};
+
+    /// How long a request waits for the signature, unless it asks otherwise
+    const DEFAULT_WAIT_SECS: u64 = 30;
+
+    /// Longest a request may wait for the signature
+    const MAX_WAIT_SECS: u64 = 300;
+
+    /// Serve the operator RPC's `sign_message` method: authorize the signing of `digest` with
+    /// the reward cycle's aggregate key, starting a signing round if this is the first request
+    /// for it, and wait up to `timeout_secs` for the signature
+    pub(crate) fn sign_message(
+        registry: &MessageSigningRegistry,
+        cmd_send: &Sender<RunLoopCommand>,
+        reward_cycle: u64,
+        digest: &str,
+        timeout_secs: Option<u64>,
+    ) -> OperatorResponse {
+        let Ok(digest) = Sha256Sum::from_hex(digest) else {
+            return OperatorResponse::Error {
+                reason: "digest must be 32 hex-encoded bytes".to_string(),
+            };
+        };
+        let message = ArbitraryMessage {
+            reward_cycle,
+            digest,
+        };
+        let wait =
+            Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_WAIT_SECS).min(MAX_WAIT_SECS));
+
+        // a repeated request for a message that is already signed is answered straight away
+        if let Some(signature) = registry.wait_for_signature(&message, Duration::ZERO) {
+            return OperatorResponse::Signed {
+                signature: signature_to_hex(&signature),
+            };
+        }
+        if !registry.is_authorized(&message) {
+            info!("Message signing: authorized a signing request";
+                "reward_cycle" => message.reward_cycle,
+                "digest" => %message.digest,
+            );
+            registry.authorize(&message);
+            // there is no point in signing once the authorization has lapsed
+            let command =
+                RunLoopCommand::new(SignerCommand::SignMessage { message }, message.reward_cycle)
+                    .with_ttl(MESSAGE_AUTHORIZATION_TTL);
+            if cmd_send.send(command).is_err() {
+                return OperatorResponse::Error {
+                    reason: "signer is shutting down".to_string(),
+                };
+            }
+        }
+        match registry.wait_for_signature(&message, wait) {
+            Some(signature) => OperatorResponse::Signed {
+                signature: signature_to_hex(&signature),
+            },
+            None => OperatorResponse::Pending,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::sync::mpsc::channel;
+
+        use wsts::common::Signature;
+        use wsts::curve::point::Point;
+        use wsts::curve::scalar::Scalar;
+
+        use super::*;
+
+        #[test]
+        fn requests_are_checked_and_queued_once() {
+            let registry = MessageSigningRegistry::default();
+            let (cmd_send, cmd_recv) = channel();
+            let digest = Sha256Sum::from_data(b"attestation");
+            let message = ArbitraryMessage {
+                reward_cycle: 7,
+                digest,
+            };
+
+            let response = sign_message(&registry, &cmd_send, 7, "0badc0de", Some(0));
+            assert!(matches!(response, OperatorResponse::Error { .. }));
+            assert!(!registry.is_authorized(&message));
+
+            let digest_hex = digest.to_hex();
+            assert_eq!(
+                sign_message(&registry, &cmd_send, 7, &digest_hex, Some(0)),
+                OperatorResponse::Pending
+            );
+            assert!(registry.is_authorized(&message));
+            let command = cmd_recv.try_recv().unwrap();
+            assert_eq!(command.command, SignerCommand::SignMessage { message });
+            assert_eq!(command.reward_cycle, 7);
+            assert!(command.expires_at.is_some());
+
+            // Asking again does not start another round
+            assert_eq!(
+                sign_message(&registry, &cmd_send, 7, &digest_hex, Some(0)),
+                OperatorResponse::Pending
+            );
+            assert!(cmd_recv.try_recv().is_err());
+
+            let signature = Signature {
+                R: Point::from(Scalar::from(3)),
+                z: Scalar::from(5),
+            };
+            registry.complete(&message, signature.clone());
+            assert_eq!(
+                sign_message(&registry, &cmd_send, 7, &digest_hex, Some(0)),
+                OperatorResponse::Signed {
+                    signature: signature_to_hex(&signature)
+                }
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use wsts::curve::point::Point;
+    use wsts::curve::scalar::Scalar;
+
+    use super::*;
+
+    #[test]
+    fn arbitrary_messages_round_trip() {
+        let message = ArbitraryMessage {
+            reward_cycle: 12,
+            digest: Sha256Sum::from_data(b"hello"),
+        };
+        let bytes = message.encode();
+        assert_eq!(ArbitraryMessage::decode(&bytes), Some(message));
+        assert_eq!(ArbitraryMessage::decode(&bytes[1..]), None);
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] ^= 0xff;
+        assert_eq!(ArbitraryMessage::decode(&bad_magic), None);
+    }
+
+    #[test]
+    fn registry_only_completes_authorized_messages() {
+        let registry = MessageSigningRegistry::default();
+        let message = ArbitraryMessage {
+            reward_cycle: 3,
+            digest: Sha256Sum::from_data(b"bridge attestation"),
+        };
+        let signature = Signature {
+            R: Point::from(Scalar::from(2)),
+            z: Scalar::from(9),
+        };
+
+        assert!(!registry.is_authorized(&message));
+        registry.complete(&message, signature.clone());
+        assert!(registry
+            .wait_for_signature(&message, Duration::ZERO)
+            .is_none());
+
+        registry.authorize(&message);
+        assert!(registry.is_authorized(&message));
+        let waiter = {
+            let registry = registry.clone();
+            thread::spawn(move || registry.wait_for_signature(&message, Duration::from_secs(30)))
+        };
+        registry.complete(&message, signature.clone());
+        assert_eq!(waiter.join().unwrap(), Some(signature));
+    }
+}
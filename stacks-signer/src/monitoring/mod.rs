@@ -108,6 +108,13 @@ pub fn increment_dkg_votes_submitted() {
     prometheus::DGK_VOTES_SUBMITTED.inc();
 }
 
+/// Increment the number of DKG votes rebroadcast because they did not confirm in time
+#[allow(unused_variables)]
+pub fn increment_dkg_votes_rebroadcast() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::DKG_VOTES_REBROADCAST.inc();
+}
+
 /// Increment the number of commands processed
 #[allow(unused_variables)]
 pub fn increment_operation_results(operation_type: &str) {
@@ -124,6 +131,24 @@ pub fn increment_block_proposals_received() {
     prometheus::BLOCK_PROPOSALS_RECEIVED.inc();
 }
 
+/// Increment the number of miner key policy decisions, labeled by decision reason
+#[allow(unused_variables)]
+pub fn increment_miner_key_decisions(reason: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::MINER_KEY_DECISIONS
+        .with_label_values(&[reason])
+        .inc();
+}
+
+/// Increment the number of stackerdb chunk writes, labeled by outcome
+#[allow(unused_variables)]
+pub fn increment_stackerdb_publish_results(result: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::STACKERDB_PUBLISH_RESULTS
+        .with_label_values(&[result])
+        .inc();
+}
+
 /// Update the stx balance of the signer
 #[allow(unused_variables)]
 pub fn update_signer_stx_balance(balance: i64) {
@@ -131,6 +156,85 @@ pub fn update_signer_stx_balance(balance: i64) {
     prometheus::SIGNER_STX_BALANCE.set(balance);
 }
 
+/// Update how far the node's chain tip lags behind the tip implied by block proposals
+#[allow(unused_variables)]
+pub fn update_chain_tip_lag(burn_lag: u64, stacks_lag: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::CHAIN_TIP_BURN_LAG.set(burn_lag as i64);
+        prometheus::CHAIN_TIP_STACKS_LAG.set(stacks_lag as i64);
+    }
+}
+
+/// Update whether the node's chain tip has diverged from the network's
+#[allow(unused_variables)]
+pub fn update_chain_tip_diverged(diverged: bool) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::CHAIN_TIP_DIVERGED.set(i64::from(diverged));
+}
+
+/// Update the estimated skew of the local clock from the burnchain's, in seconds
+#[allow(unused_variables)]
+pub fn update_clock_skew(skew_secs: i64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::CLOCK_SKEW_SECS.set(skew_secs);
+}
+
+/// Update whether the local clock is skewed from the burnchain's
+#[allow(unused_variables)]
+pub fn update_clock_skewed(skewed: bool) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::CLOCK_SKEWED.set(i64::from(skewed));
+}
+
+/// Increment the number of queued commands dropped because they expired, by reason
+/// ('reward_cycle_passed' or 'deadline_passed')
+#[allow(unused_variables)]
+pub fn increment_commands_expired(reason: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::COMMANDS_EXPIRED
+        .with_label_values(&[reason])
+        .inc();
+}
+
+/// Increment the number of stale rounds expired, by round type ('dkg' or 'sign')
+#[allow(unused_variables)]
+pub fn increment_stale_rounds_collected(round_type: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::STALE_ROUNDS_COLLECTED
+        .with_label_values(&[round_type])
+        .inc();
+}
+
+/// Increment the number of escalations of repeated round failures, by round type ('dkg' or
+/// 'sign')
+#[allow(unused_variables)]
+pub fn increment_round_failure_escalations(round_type: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::ROUND_FAILURE_ESCALATIONS
+        .with_label_values(&[round_type])
+        .inc();
+}
+
+/// Increment the number of packets dropped because their round had expired
+#[allow(unused_variables)]
+pub fn increment_stale_round_packets_dropped() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::STALE_ROUND_PACKETS_DROPPED.inc();
+}
+
+/// Increment the number of events that took longer to process than the event watchdog allows
+pub fn increment_event_processing_timeouts() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::EVENT_PROCESSING_TIMEOUTS.inc();
+}
+
+/// Increment the number of runloop passes that ran out of time and left work for the next pass
+pub fn increment_pass_budget_exhaustions() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::PASS_BUDGET_EXHAUSTIONS.inc();
+}
+
 /// Update the signer nonce metric
 #[allow(unused_variables)]
 pub fn update_signer_nonce(nonce: u64) {
@@ -65,6 +65,11 @@ lazy_static! {
         "The number of DGK votes submitted by the signer"
     ))
     .unwrap();
+    pub static ref DKG_VOTES_REBROADCAST: IntCounter = register_int_counter!(opts!(
+        "stacks_signer_dkg_votes_rebroadcast",
+        "The number of DKG votes the signer rebroadcast because they did not confirm in time"
+    ))
+    .unwrap();
     pub static ref OPERATION_RESULTS: IntCounterVec = register_int_counter_vec!(
         "stacks_signer_operation_results_dkg",
         "The number of DKG operation results",
@@ -76,6 +81,51 @@ lazy_static! {
         "The number of block proposals received by the signer"
     ))
     .unwrap();
+    pub static ref MINER_KEY_DECISIONS: IntCounterVec = register_int_counter_vec!(
+        "stacks_signer_miner_key_decisions",
+        "The number of miner message batches evaluated against the miner allow/deny lists. `reason` is one of 'allowed', 'denylisted' or 'not_allowlisted'",
+        &["reason"]
+    )
+    .unwrap();
+    pub static ref STACKERDB_PUBLISH_RESULTS: IntCounterVec = register_int_counter_vec!(
+        "stacks_signer_stackerdb_publish_results",
+        "The number of chunk writes to the signer's stackerdb slots. `result` is one of 'accepted', 'conflict', 'rejected', 'error' or 'abandoned'",
+        &["result"]
+    )
+    .unwrap();
+    pub static ref COMMANDS_EXPIRED: IntCounterVec = register_int_counter_vec!(
+        "stacks_signer_commands_expired",
+        "The number of queued commands dropped because they expired before they could be executed. `reason` is one of 'reward_cycle_passed' or 'deadline_passed'",
+        &["reason"]
+    )
+    .unwrap();
+    pub static ref STALE_ROUNDS_COLLECTED: IntCounterVec = register_int_counter_vec!(
+        "stacks_signer_stale_rounds_collected",
+        "The number of DKG and signing rounds expired after going without packets for too many burn blocks. `round_type` is one of 'dkg' or 'sign'",
+        &["round_type"]
+    )
+    .unwrap();
+    pub static ref ROUND_FAILURE_ESCALATIONS: IntCounterVec = register_int_counter_vec!(
+        "stacks_signer_round_failure_escalations",
+        "The number of times the operator was alerted that too many consecutive DKG or signing rounds failed. `round_type` is one of 'dkg' or 'sign'",
+        &["round_type"]
+    )
+    .unwrap();
+    pub static ref STALE_ROUND_PACKETS_DROPPED: IntCounter = register_int_counter!(opts!(
+        "stacks_signer_stale_round_packets_dropped",
+        "The number of packets dropped because their round had expired"
+    ))
+    .unwrap();
+    pub static ref EVENT_PROCESSING_TIMEOUTS: IntCounter = register_int_counter!(opts!(
+        "stacks_signer_event_processing_timeouts",
+        "The number of events that took longer to process than the event watchdog allows"
+    ))
+    .unwrap();
+    pub static ref PASS_BUDGET_EXHAUSTIONS: IntCounter = register_int_counter!(opts!(
+        "stacks_signer_pass_budget_exhaustions",
+        "The number of runloop passes that ran out of time and left work for the next pass"
+    ))
+    .unwrap();
     pub static ref CURRENT_REWARD_CYCLE: IntGauge = register_int_gauge!(opts!(
         "stacks_signer_current_reward_cycle",
         "The current reward cycle"
@@ -88,6 +138,26 @@ lazy_static! {
         "stacks_signer_nonce",
         "The current nonce of the signer"
     )).unwrap();
+    pub static ref CHAIN_TIP_BURN_LAG: IntGauge = register_int_gauge!(opts!(
+        "stacks_signer_chain_tip_burn_lag",
+        "The number of burnchain blocks the Stacks node is behind the tip implied by block proposals"
+    )).unwrap();
+    pub static ref CHAIN_TIP_STACKS_LAG: IntGauge = register_int_gauge!(opts!(
+        "stacks_signer_chain_tip_stacks_lag",
+        "The number of Stacks blocks the Stacks node is behind the tip implied by block proposals"
+    )).unwrap();
+    pub static ref CHAIN_TIP_DIVERGED: IntGauge = register_int_gauge!(opts!(
+        "stacks_signer_chain_tip_diverged",
+        "Whether signing is suspended because the Stacks node has diverged from the network (1) or not (0)"
+    )).unwrap();
+    pub static ref CLOCK_SKEW_SECS: IntGauge = register_int_gauge!(opts!(
+        "stacks_signer_clock_skew_secs",
+        "The estimated number of seconds the local clock is ahead of (positive) or behind (negative) the burnchain's"
+    )).unwrap();
+    pub static ref CLOCK_SKEWED: IntGauge = register_int_gauge!(opts!(
+        "stacks_signer_clock_skewed",
+        "Whether signing is suspended because the local clock is skewed from the burnchain's (1) or not (0)"
+    )).unwrap();
 
     pub static ref SIGNER_RPC_CALL_LATENCIES_HISTOGRAM: HistogramVec = register_histogram_vec!(histogram_opts!(
         "stacks_signer_node_rpc_call_latencies_histogram",
@@ -0,0 +1,167 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+/// The kinds of operation a signer takes part in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationType {
+    /// DKG rounds, which generate the reward cycle's aggregate key
+    Dkg,
+    /// Signing rounds, over blocks or over messages requested by the operator
+    Signing,
+    /// Votes for the aggregate key in the voting contract
+    KeyVote,
+}
+
+impl OperationType {
+    /// The label used for this operation type in logs and config errors
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dkg => "dkg",
+            Self::Signing => "signing",
+            Self::KeyVote => "key_vote",
+        }
+    }
+}
+
+impl Display for OperationType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Which kinds of operation a signer takes part in during a reward cycle.
+///
+/// A signer that does not take part in an operation still follows the chain and the other
+/// signers' messages, but drops the packets of that operation's rounds, does not start them as
+/// coordinator, and does not broadcast anything for them. Signing needs the key shares that
+/// only taking part in DKG gives the signer, so a signer that sits out DKG for a reward cycle
+/// has nothing to sign with in it either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Participation {
+    /// Whether to take part in DKG rounds
+    pub dkg: bool,
+    /// Whether to take part in signing rounds
+    pub signing: bool,
+    /// Whether to vote for the aggregate key that DKG produces
+    pub key_votes: bool,
+}
+
+impl Participation {
+    /// Take part in everything
+    pub const FULL: Self = Self {
+        dkg: true,
+        signing: true,
+        key_votes: true,
+    };
+
+    /// Take part in nothing, only observe
+    pub const OBSERVE_ONLY: Self = Self {
+        dkg: false,
+        signing: false,
+        key_votes: false,
+    };
+
+    /// Whether to take part in operations of the given type
+    pub const fn allows(&self, operation: OperationType) -> bool {
+        match operation {
+            OperationType::Dkg => self.dkg,
+            OperationType::Signing => self.signing,
+            OperationType::KeyVote => self.key_votes,
+        }
+    }
+
+    /// The operation types not taken part in
+    pub fn excluded(&self) -> Vec<OperationType> {
+        [
+            OperationType::Dkg,
+            OperationType::Signing,
+            OperationType::KeyVote,
+        ]
+        .into_iter()
+        .filter(|operation| !self.allows(*operation))
+        .collect()
+    }
+}
+
+impl Default for Participation {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+/// Which kinds of operation the signer takes part in, by default and in specific reward
+/// cycles, so that an operator can roll out a signer's capabilities one cycle at a time
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParticipationConfig {
+    /// What to take part in during reward cycles without an override
+    pub default: Participation,
+    /// What to take part in during specific reward cycles
+    pub overrides: BTreeMap<u64, Participation>,
+}
+
+impl ParticipationConfig {
+    /// What to take part in during the given reward cycle
+    pub fn for_reward_cycle(&self, reward_cycle: u64) -> Participation {
+        self.overrides
+            .get(&reward_cycle)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{OperationType, Participation, ParticipationConfig};
+
+    #[test]
+    fn participation_allows_operations() {
+        let participation = Participation {
+            dkg: true,
+            signing: false,
+            key_votes: true,
+        };
+        assert!(participation.allows(OperationType::Dkg));
+        assert!(!participation.allows(OperationType::Signing));
+        assert!(participation.allows(OperationType::KeyVote));
+        assert_eq!(participation.excluded(), vec![OperationType::Signing]);
+
+        assert!(Participation::FULL.excluded().is_empty());
+        assert_eq!(Participation::OBSERVE_ONLY.excluded().len(), 3);
+    }
+
+    #[test]
+    fn participation_config_overrides_reward_cycles() {
+        let config = ParticipationConfig {
+            default: Participation {
+                key_votes: false,
+                ..Participation::FULL
+            },
+            overrides: BTreeMap::from([(12, Participation::OBSERVE_ONLY)]),
+        };
+        assert_eq!(config.for_reward_cycle(11), config.default);
+        assert_eq!(config.for_reward_cycle(12), Participation::OBSERVE_ONLY);
+        assert_eq!(config.for_reward_cycle(13), config.default);
+        assert_eq!(
+            ParticipationConfig::default().for_reward_cycle(12),
+            Participation::FULL
+        );
+    }
+}
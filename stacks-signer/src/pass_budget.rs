@@ -0,0 +1,156 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The time the runloop may spend in a single pass.
+///
+/// The event loop only polls for the next event once a pass returns, so a pass that works
+/// through a long backlog of events and commands holds back the events the node delivers in the
+/// meantime. Once the budget is spent, the rest of the pass's work is left for the next pass.
+#[derive(Debug, Clone, Copy)]
+pub struct PassBudget {
+    /// How long the pass may take. None if passes are not bounded.
+    budget: Option<Duration>,
+    /// When the pass began
+    started_at: Instant,
+}
+
+impl PassBudget {
+    /// Start timing a pass that may take up to `budget`
+    pub const fn start(budget: Option<Duration>, now: Instant) -> Self {
+        Self {
+            budget,
+            started_at: now,
+        }
+    }
+
+    /// Has the pass spent its budget by `now`?
+    pub fn is_exhausted(&self, now: Instant) -> bool {
+        matches!(self.budget, Some(budget) if now.saturating_duration_since(self.started_at) >= budget)
+    }
+}
+
+/// Have `process` work through the events a signer has left over from earlier passes, oldest
+/// first, and then `event`, until the pass's budget is spent. At least one event is always
+/// processed, so that every signer makes progress on every pass. If there is nothing to process,
+/// `process` is called with no event, so that the signer can do its periodic work.
+/// Returns the events left for the next pass.
+pub fn process_within_budget<E: Clone>(
+    budget: &PassBudget,
+    mut backlog: VecDeque<E>,
+    event: Option<&E>,
+    mut process: impl FnMut(Option<&E>),
+) -> VecDeque<E> {
+    let mut processed_any = false;
+    while let Some(next) = backlog.pop_front() {
+        if processed_any && budget.is_exhausted(Instant::now()) {
+            backlog.push_front(next);
+            backlog.extend(event.cloned());
+            return backlog;
+        }
+        process(Some(&next));
+        processed_any = true;
+    }
+    if processed_any {
+        if event.is_none() {
+            return backlog;
+        }
+        if budget.is_exhausted(Instant::now()) {
+            backlog.extend(event.cloned());
+            return backlog;
+        }
+    }
+    process(event);
+    backlog
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::time::{Duration, Instant};
+
+    use super::{process_within_budget, PassBudget};
+
+    #[test]
+    fn pass_budget_is_exhausted() {
+        let now = Instant::now();
+        let budget = PassBudget::start(Some(Duration::from_millis(100)), now);
+        assert!(!budget.is_exhausted(now));
+        assert!(!budget.is_exhausted(now + Duration::from_millis(99)));
+        assert!(budget.is_exhausted(now + Duration::from_millis(100)));
+
+        let unbounded = PassBudget::start(None, now);
+        assert!(!unbounded.is_exhausted(now + Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn process_within_budget_processes_everything_in_order() {
+        let budget = PassBudget::start(None, Instant::now());
+        let mut processed = vec![];
+        let left = process_within_budget(&budget, VecDeque::from([1, 2]), Some(&3), |event| {
+            processed.push(event.copied())
+        });
+        assert!(left.is_empty());
+        assert_eq!(processed, vec![Some(1), Some(2), Some(3)]);
+
+        // with nothing to do, the signer still gets to do its periodic work
+        let mut processed = vec![];
+        let left = process_within_budget(&budget, VecDeque::<u32>::new(), None, |event| {
+            processed.push(event.copied())
+        });
+        assert!(left.is_empty());
+        assert_eq!(processed, vec![None]);
+
+        // but not if it worked through its backlog
+        let mut processed = vec![];
+        let left = process_within_budget(&budget, VecDeque::from([1]), None, |event| {
+            processed.push(event.copied())
+        });
+        assert!(left.is_empty());
+        assert_eq!(processed, vec![Some(1)]);
+    }
+
+    #[test]
+    fn process_within_budget_defers_the_rest() {
+        let spent = PassBudget::start(Some(Duration::ZERO), Instant::now());
+
+        // the oldest event is processed, and the rest are carried over
+        let mut processed = vec![];
+        let left = process_within_budget(&spent, VecDeque::from([1, 2]), Some(&3), |event| {
+            processed.push(event.copied())
+        });
+        assert_eq!(processed, vec![Some(1)]);
+        assert_eq!(left, VecDeque::from([2, 3]));
+
+        // with no backlog, the new event is always processed
+        let mut processed = vec![];
+        let left = process_within_budget(&spent, VecDeque::new(), Some(&4), |event| {
+            processed.push(event.copied())
+        });
+        assert_eq!(processed, vec![Some(4)]);
+        assert!(left.is_empty());
+
+        // carried over events are processed on the next pass before new ones
+        let mut processed = vec![];
+        let left = process_within_budget(&spent, VecDeque::from([2, 3]), Some(&5), |event| {
+            processed.push(event.copied())
+        });
+        assert_eq!(processed, vec![Some(2)]);
+        assert_eq!(left, VecDeque::from([3, 5]));
+    }
+}
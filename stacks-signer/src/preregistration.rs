@@ -0,0 +1,179 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+/// Where the signer is in registering for the next reward cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreregistrationStatus {
+    /// The next reward set cannot be calculated yet, or the signer is not in it
+    Pending {
+        /// How many times the signer has looked for itself in the next reward set
+        attempts: u64,
+    },
+    /// The signer is configured for the next reward cycle, and has published its key material
+    /// to its StackerDB slot
+    Registered {
+        /// How long after the first attempt the signer registered
+        elapsed: Duration,
+    },
+}
+
+/// Tracks the signer's registration for the next reward cycle while its reward set is being
+/// calculated.
+///
+/// The node only calculates the next reward set once it has processed the first burn block of
+/// the prepare phase, which may be after the signer hears about that block. Rather than wait
+/// for the next burn block to look again, the runloop polls on this interval, so that the signer
+/// is ready well before the first block of its reward cycle.
+#[derive(Debug, Clone)]
+pub struct NextCyclePreregistration {
+    /// How long to wait between attempts
+    poll_interval: Duration,
+    /// The reward cycle being registered for
+    reward_cycle: Option<u64>,
+    /// When the first attempt for `reward_cycle` was made
+    started_at: Option<Instant>,
+    /// When the latest attempt for `reward_cycle` was made
+    last_attempt: Option<Instant>,
+    /// The registration's status for `reward_cycle`
+    status: PreregistrationStatus,
+}
+
+impl NextCyclePreregistration {
+    /// Create a new tracker which polls on the given interval
+    pub const fn new(poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            reward_cycle: None,
+            started_at: None,
+            last_attempt: None,
+            status: PreregistrationStatus::Pending { attempts: 0 },
+        }
+    }
+
+    /// Start tracking `reward_cycle`, if it is not already tracked
+    fn track(&mut self, reward_cycle: u64) {
+        if self.reward_cycle == Some(reward_cycle) {
+            return;
+        }
+        self.reward_cycle = Some(reward_cycle);
+        self.started_at = None;
+        self.last_attempt = None;
+        self.status = PreregistrationStatus::Pending { attempts: 0 };
+    }
+
+    /// Should the signer look for itself in the reward set of `reward_cycle` now?
+    pub fn attempt_due(&mut self, reward_cycle: u64, now: Instant) -> bool {
+        self.track(reward_cycle);
+        if matches!(self.status, PreregistrationStatus::Registered { .. }) {
+            return false;
+        }
+        self.last_attempt.map_or(true, |last| {
+            now.saturating_duration_since(last) >= self.poll_interval
+        })
+    }
+
+    /// Record an attempt to register for `reward_cycle`, and whether it succeeded
+    pub fn record_attempt(&mut self, reward_cycle: u64, registered: bool, now: Instant) {
+        self.track(reward_cycle);
+        let started_at = *self.started_at.get_or_insert(now);
+        self.last_attempt = Some(now);
+        self.status = match self.status {
+            status @ PreregistrationStatus::Registered { .. } => status,
+            PreregistrationStatus::Pending { .. } if registered => {
+                PreregistrationStatus::Registered {
+                    elapsed: now.saturating_duration_since(started_at),
+                }
+            }
+            PreregistrationStatus::Pending { attempts } => PreregistrationStatus::Pending {
+                attempts: attempts.saturating_add(1),
+            },
+        };
+    }
+
+    /// The registration's status for `reward_cycle`, if it has been tracked
+    pub fn status(&self, reward_cycle: u64) -> Option<PreregistrationStatus> {
+        (self.reward_cycle == Some(reward_cycle)).then_some(self.status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attempts_are_spaced_by_the_poll_interval() {
+        let interval = Duration::from_secs(10);
+        let mut preregistration = NextCyclePreregistration::new(interval);
+        let start = Instant::now();
+        assert_eq!(preregistration.status(5), None);
+
+        assert!(preregistration.attempt_due(5, start));
+        preregistration.record_attempt(5, false, start);
+        assert_eq!(
+            preregistration.status(5),
+            Some(PreregistrationStatus::Pending { attempts: 1 })
+        );
+
+        assert!(!preregistration.attempt_due(5, start + Duration::from_secs(9)));
+        assert!(preregistration.attempt_due(5, start + interval));
+        preregistration.record_attempt(5, false, start + interval);
+        assert_eq!(
+            preregistration.status(5),
+            Some(PreregistrationStatus::Pending { attempts: 2 })
+        );
+    }
+
+    #[test]
+    fn registration_stops_attempts() {
+        let interval = Duration::from_secs(10);
+        let mut preregistration = NextCyclePreregistration::new(interval);
+        let start = Instant::now();
+
+        preregistration.record_attempt(5, false, start);
+        preregistration.record_attempt(5, true, start + Duration::from_secs(25));
+        assert_eq!(
+            preregistration.status(5),
+            Some(PreregistrationStatus::Registered {
+                elapsed: Duration::from_secs(25)
+            })
+        );
+        assert!(!preregistration.attempt_due(5, start + Duration::from_secs(100)));
+
+        // a later failed attempt does not undo the registration
+        preregistration.record_attempt(5, false, start + Duration::from_secs(200));
+        assert!(matches!(
+            preregistration.status(5),
+            Some(PreregistrationStatus::Registered { .. })
+        ));
+    }
+
+    #[test]
+    fn new_reward_cycle_resets_tracking() {
+        let interval = Duration::from_secs(10);
+        let mut preregistration = NextCyclePreregistration::new(interval);
+        let start = Instant::now();
+
+        preregistration.record_attempt(5, true, start);
+        assert!(preregistration.attempt_due(6, start));
+        assert_eq!(preregistration.status(5), None);
+        assert_eq!(
+            preregistration.status(6),
+            Some(PreregistrationStatus::Pending { attempts: 0 })
+        );
+    }
+}
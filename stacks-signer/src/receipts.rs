@@ -0,0 +1,157 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+use slog::{slog_debug, slog_warn};
+use stacks_common::util::hash::Sha256Sum;
+use stacks_common::{debug, warn};
+use wsts::curve::point::Point;
+
+/// How long to wait for the webhook to answer before giving up on a receipt
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A record that this signer contributed a signature share to a completed signature
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SignatureReceipt {
+    /// The signer that contributed to the signature
+    pub signer_id: u32,
+    /// The reward cycle the signature was produced in
+    pub reward_cycle: u64,
+    /// The SHA-256 digest of the signed payload
+    pub payload_digest: Sha256Sum,
+    /// The aggregate public key the signature verifies against, if one was approved
+    pub aggregate_key: Option<String>,
+    /// The signers whose signature shares went into the signature, in ascending order
+    pub signer_ids: Vec<u32>,
+}
+
+impl SignatureReceipt {
+    /// Build a receipt for a completed signature over `payload`
+    pub fn new(
+        signer_id: u32,
+        reward_cycle: u64,
+        payload: &[u8],
+        aggregate_key: Option<&Point>,
+        signer_ids: impl IntoIterator<Item = u32>,
+    ) -> Self {
+        let mut signer_ids: Vec<u32> = signer_ids.into_iter().collect();
+        signer_ids.sort_unstable();
+        signer_ids.dedup();
+        Self {
+            signer_id,
+            reward_cycle,
+            payload_digest: Sha256Sum::from_data(payload),
+            aggregate_key: aggregate_key.map(|key| key.to_string()),
+            signer_ids,
+        }
+    }
+}
+
+/// POSTs signature receipts to an external webhook as JSON.
+/// Receipts are delivered from a background thread, so a slow or unreachable webhook never
+/// holds up signing; receipts that cannot be delivered are logged and dropped.
+#[derive(Debug)]
+pub struct ReceiptNotifier {
+    sender: Sender<SignatureReceipt>,
+}
+
+impl ReceiptNotifier {
+    /// Start delivering receipts to `url`
+    pub fn new(url: String) -> Self {
+        let (sender, receiver) = channel::<SignatureReceipt>();
+        thread::Builder::new()
+            .name("signature-receipts".into())
+            .spawn(move || {
+                let client = reqwest::blocking::Client::new();
+                // Ends once the notifier (and so the sender) is dropped
+                for receipt in receiver.iter() {
+                    let result = client
+                        .post(&url)
+                        .timeout(WEBHOOK_TIMEOUT)
+                        .json(&receipt)
+                        .send()
+                        .and_then(|response| response.error_for_status());
+                    match result {
+                        Ok(_) => debug!("Delivered signature receipt";
+                            "payload_digest" => %receipt.payload_digest,
+                            "reward_cycle" => receipt.reward_cycle,
+                        ),
+                        Err(e) => warn!("Failed to deliver signature receipt: {e}";
+                            "payload_digest" => %receipt.payload_digest,
+                            "reward_cycle" => receipt.reward_cycle,
+                        ),
+                    }
+                }
+            })
+            .expect("FATAL: failed to spawn signature receipt thread");
+        Self { sender }
+    }
+
+    /// Queue a receipt for delivery
+    pub fn notify(&self, receipt: SignatureReceipt) {
+        if self.sender.send(receipt).is_err() {
+            warn!("Signature receipt thread has exited. Dropping receipt.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn receipt_sorts_and_dedups_signer_ids() {
+        let receipt = SignatureReceipt::new(2, 7, b"payload", None, vec![3, 0, 2, 3]);
+        assert_eq!(receipt.signer_ids, vec![0, 2, 3]);
+        assert_eq!(receipt.payload_digest, Sha256Sum::from_data(b"payload"));
+        assert!(receipt.aggregate_key.is_none());
+    }
+
+    #[test]
+    fn receipts_are_posted_to_webhook() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/receipts", listener.local_addr().unwrap());
+        let notifier = ReceiptNotifier::new(url);
+        let receipt = SignatureReceipt::new(1, 10, b"block", None, vec![0, 1]);
+        notifier.notify(receipt.clone());
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        // read until the whole JSON body has arrived
+        while !request.ends_with(b"}") {
+            let n = stream.read(&mut buf).unwrap();
+            assert!(n > 0, "webhook connection closed early");
+            request.extend_from_slice(&buf[..n]);
+        }
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+
+        let request = String::from_utf8(request).unwrap();
+        assert!(request.starts_with("POST /receipts HTTP/1.1"));
+        let body = request.split("\r\n\r\n").nth(1).unwrap();
+        let posted: SignatureReceipt = serde_json::from_str(body).unwrap();
+        assert_eq!(posted, receipt);
+    }
+}
@@ -1,5 +1,4 @@
 use std::collections::VecDeque;
-use std::fmt::Debug;
 // Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
 // Copyright (C) 2020-2024 Stacks Open Internet Foundation
 //
@@ -16,22 +15,32 @@ use std::fmt::Debug;
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 use std::sync::mpsc::Sender;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use blockstack_lib::burnchains::PoxConstants;
 use blockstack_lib::chainstate::stacks::boot::SIGNERS_NAME;
 use blockstack_lib::util_lib::boot::boot_code_id;
-use clarity::codec::StacksMessageCodec;
 use hashbrown::HashMap;
-use libsigner::{BlockProposal, SignerEntries, SignerEvent, SignerRunLoop};
+use libsigner::{BlockProposal, SignerEntries, SignerEvent, SignerEventTrait, SignerRunLoop};
 use slog::{slog_debug, slog_error, slog_info, slog_warn};
 use stacks_common::types::chainstate::StacksAddress;
+use stacks_common::util::get_epoch_time_secs;
 use stacks_common::{debug, error, info, warn};
 use wsts::common::MerkleRoot;
+use wsts::curve::point::Point;
 use wsts::state_machine::OperationResult;
 
 use crate::client::{retry_with_exponential_backoff, ClientError, SignerSlotID, StacksClient};
+use crate::clock_skew::ClockSkewMonitor;
 use crate::config::{GlobalConfig, SignerConfig};
+use crate::divergence::{ChainTip, ChainTipMonitor};
+use crate::dkg_keys::DkgKeyRegistry;
+use crate::message_signing::{ArbitraryMessage, MessageSigningRegistry};
+use crate::participation::OperationType;
+use crate::pass_budget::{process_within_budget, PassBudget};
+use crate::preregistration::{NextCyclePreregistration, PreregistrationStatus};
+use crate::timeouts::{AdaptiveTimeouts, TimeoutPhase};
+use crate::watchdog::{event_source, EventWatchdog};
 use crate::Signer as SignerTrait;
 
 /// Which signer operation to perform
@@ -48,6 +57,28 @@ pub enum SignerCommand {
         /// Taproot merkle root
         merkle_root: Option<MerkleRoot>,
     },
+    /// Sign an arbitrary digest that the operator requested over the message signing RPC
+    SignMessage {
+        /// The digest to sign, and the reward cycle whose signer set should sign it
+        message: ArbitraryMessage,
+    },
+    /// Use a known-good aggregate key until the voting contract approves one, e.g. to recover
+    /// a signer that lost its DKG state
+    ImportAggregateKey {
+        /// The aggregate key to use
+        aggregate_key: Point,
+    },
+}
+
+impl SignerCommand {
+    /// The kind of operation the command takes part in, if it takes part in one
+    pub const fn operation_type(&self) -> Option<OperationType> {
+        match self {
+            Self::Dkg => Some(OperationType::Dkg),
+            Self::Sign { .. } | Self::SignMessage { .. } => Some(OperationType::Signing),
+            Self::ImportAggregateKey { .. } => None,
+        }
+    }
 }
 
 /// Which operation to perform
@@ -55,19 +86,111 @@ pub enum SignerCommand {
 pub struct RunLoopCommand {
     /// Which signer operation to perform
     pub command: SignerCommand,
-    /// The reward cycle we are performing the operation for
+    /// The reward cycle we are performing the operation for. The command is dropped if it
+    /// is still queued once this reward cycle has passed.
     pub reward_cycle: u64,
+    /// If set, the command is also dropped if it is still queued at this time, in seconds
+    /// since the epoch
+    pub expires_at: Option<u64>,
+}
+
+/// Why a queued command was dropped instead of being executed
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CommandExpiry {
+    /// The reward cycle the command was issued for has passed
+    RewardCyclePassed,
+    /// The deadline set by the command's issuer has passed
+    DeadlinePassed,
 }
 
+impl CommandExpiry {
+    /// The label used for this reason in logs and metrics
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::RewardCyclePassed => "reward_cycle_passed",
+            Self::DeadlinePassed => "deadline_passed",
+        }
+    }
+}
+
+impl RunLoopCommand {
+    /// A command for `reward_cycle`, which stays valid until that reward cycle has passed
+    pub const fn new(command: SignerCommand, reward_cycle: u64) -> Self {
+        Self {
+            command,
+            reward_cycle,
+            expires_at: None,
+        }
+    }
+
+    /// Drop the command if it is still queued `ttl` from now, even if its reward cycle has not
+    /// passed yet
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.expires_at = Some(get_epoch_time_secs().saturating_add(ttl.as_secs()));
+        self
+    }
+
+    /// Whether the command has expired at time `now` (in seconds since the epoch), and why
+    pub fn expiry(&self, current_reward_cycle: u64, now: u64) -> Option<CommandExpiry> {
+        if self.reward_cycle < current_reward_cycle {
+            Some(CommandExpiry::RewardCyclePassed)
+        } else if matches!(self.expires_at, Some(expires_at) if now >= expires_at) {
+            Some(CommandExpiry::DeadlinePassed)
+        } else {
+            None
+        }
+    }
+}
+
+/// Remove the commands that have expired from `commands`, logging why each was dropped
+pub fn drop_expired_commands(commands: &mut VecDeque<RunLoopCommand>, current_reward_cycle: u64) {
+    let now = get_epoch_time_secs();
+    commands.retain(|command| {
+        let Some(expiry) = command.expiry(current_reward_cycle, now) else {
+            return true;
+        };
+        warn!("Dropping an expired command";
+            "reason" => expiry.as_str(),
+            "command" => ?command.command,
+            "reward_cycle" => command.reward_cycle,
+            "current_reward_cycle" => current_reward_cycle,
+            "expires_at" => ?command.expires_at,
+        );
+        crate::monitoring::increment_commands_expired(expiry.as_str());
+        false
+    });
+}
+
+/// The number of state transitions the runloop remembers
+pub const STATE_HISTORY_LENGTH: usize = 32;
+
 /// The runloop state
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum State {
-    /// The runloop is uninitialized
+    /// The runloop has not yet loaded the reward cycle info from the stacks node
     Uninitialized,
-    /// The runloop has no registered signers
-    NoRegisteredSigners,
-    /// The runloop has registered signers
-    RegisteredSigners,
+    /// The reward set is not yet determined, or this signer is not registered in it
+    WaitingForRewardSet,
+    /// The signer is registered, but DKG has not yet produced an approved aggregate key
+    RegisteredPendingDkg,
+    /// The signer is registered and has an approved aggregate key
+    Ready,
+    /// The runloop failed to refresh its view of the network, and may be acting on stale
+    /// reward cycle info until the next successful refresh, or the node's chain tip has
+    /// diverged from the network's and signing is suspended until it catches up, or the local
+    /// clock is skewed from the burnchain's and signing is suspended until it is corrected
+    Degraded,
+}
+
+/// A change in the runloop state
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct StateTransition {
+    /// The state before the transition
+    pub from: State,
+    /// The state after the transition
+    pub to: State,
+    /// When the transition happened, in seconds since the epoch
+    pub timestamp: u64,
 }
 
 /// The current reward cycle info
@@ -81,7 +204,8 @@ pub struct RewardCycleInfo {
     pub prepare_phase_block_length: u64,
     /// The first burn block height
     pub first_burnchain_block_height: u64,
-    /// The burnchain block height of the last query
+    /// The burnchain block height of the last query, or of the latest burn block the signer
+    /// has heard about since
     pub last_burnchain_block_height: u64,
 }
 
@@ -123,7 +247,7 @@ impl RewardCycleInfo {
 pub struct RunLoop<Signer, T>
 where
     Signer: SignerTrait<T>,
-    T: StacksMessageCodec + Clone + Send + Debug,
+    T: SignerEventTrait,
 {
     /// Configuration info
     pub config: GlobalConfig,
@@ -132,27 +256,61 @@ where
     /// The internal signer for an odd or even reward cycle
     /// Keyed by reward cycle % 2
     pub stacks_signers: HashMap<u64, Signer>,
-    /// The state of the runloop
+    /// The state of the runloop. Only updated via `transition`, so that changes are logged
     pub state: State,
+    /// The most recent state transitions, oldest first
+    pub state_history: VecDeque<StateTransition>,
     /// The commands received thus far
     pub commands: VecDeque<RunLoopCommand>,
     /// The current reward cycle info. Only None if the runloop is uninitialized
     pub current_reward_cycle_info: Option<RewardCycleInfo>,
+    /// Coordinator timeouts learned from prior rounds. Only set if adaptive timeouts are enabled
+    pub adaptive_timeouts: Option<AdaptiveTimeouts>,
+    /// The arbitrary messages the operator has asked this signer to sign
+    pub message_signing: MessageSigningRegistry,
+    /// The DKG key material of each signer, for the operator to export
+    pub dkg_keys: DkgKeyRegistry,
+    /// Compares the node's chain tip against the tips implied by block proposals
+    pub chain_tip_monitor: ChainTipMonitor,
+    /// Compares the local clock against the timestamps of new burn blocks
+    pub clock_skew_monitor: ClockSkewMonitor,
+    /// Tracks the registration for the next reward cycle during the prepare phase
+    pub next_cycle_preregistration: NextCyclePreregistration,
+    /// Bounds the time spent processing each event
+    pub event_watchdog: EventWatchdog,
+    /// The events each signer has yet to process because earlier passes ran out of time, keyed
+    /// by reward cycle % 2
+    pub deferred_events: HashMap<u64, VecDeque<SignerEvent<T>>>,
     /// Phantom data for the message codec
     _phantom_data: std::marker::PhantomData<T>,
 }
 
-impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug> RunLoop<Signer, T> {
+impl<Signer: SignerTrait<T>, T: SignerEventTrait> RunLoop<Signer, T> {
     /// Create a new signer runloop from the provided configuration
     pub fn new(config: GlobalConfig) -> Self {
         let stacks_client = StacksClient::from(&config);
+        let adaptive_timeouts = config.adaptive_timeouts.map(AdaptiveTimeouts::new);
+        let chain_tip_monitor = ChainTipMonitor::new(config.chain_tip_divergence.clone());
+        let clock_skew_monitor = ClockSkewMonitor::new(config.max_clock_skew);
+        let next_cycle_preregistration =
+            NextCyclePreregistration::new(config.next_cycle_poll_interval);
+        let event_watchdog = EventWatchdog::new(config.event_processing_timeout);
         Self {
             config,
             stacks_client,
             stacks_signers: HashMap::with_capacity(2),
             state: State::Uninitialized,
+            state_history: VecDeque::with_capacity(STATE_HISTORY_LENGTH),
             commands: VecDeque::new(),
             current_reward_cycle_info: None,
+            adaptive_timeouts,
+            message_signing: MessageSigningRegistry::default(),
+            dkg_keys: DkgKeyRegistry::default(),
+            chain_tip_monitor,
+            clock_skew_monitor,
+            next_cycle_preregistration,
+            event_watchdog,
+            deferred_events: HashMap::new(),
             _phantom_data: std::marker::PhantomData,
         }
     }
@@ -235,23 +393,52 @@ impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug> RunLo
             key_ids,
             signer_entries,
             signer_slot_ids: signer_slot_ids.into_values().collect(),
-            ecdsa_private_key: self.config.ecdsa_private_key,
-            stacks_private_key: self.config.stacks_private_key,
+            ecdsa_private_key: self.config.ecdsa_private_key.clone(),
+            stacks_private_key: self.config.stacks_private_key.clone(),
             node_host: self.config.node_host.to_string(),
             mainnet: self.config.network.is_mainnet(),
-            dkg_end_timeout: self.config.dkg_end_timeout,
-            dkg_private_timeout: self.config.dkg_private_timeout,
-            dkg_public_timeout: self.config.dkg_public_timeout,
-            nonce_timeout: self.config.nonce_timeout,
-            sign_timeout: self.config.sign_timeout,
+            dkg_end_timeout: self.get_timeout(TimeoutPhase::DkgEnd),
+            dkg_private_timeout: self.get_timeout(TimeoutPhase::DkgPrivate),
+            dkg_public_timeout: self.get_timeout(TimeoutPhase::DkgPublic),
+            nonce_timeout: self.get_timeout(TimeoutPhase::Nonce),
+            sign_timeout: self.get_timeout(TimeoutPhase::Sign),
             tx_fee_ustx: self.config.tx_fee_ustx,
             max_tx_fee_ustx: self.config.max_tx_fee_ustx,
+            dkg_vote_retry: self.config.dkg_vote_retry,
             db_path: self.config.db_path.clone(),
+            miner_key_policy: self.config.miner_key_policy.clone(),
+            signature_receipt_webhook: self.config.signature_receipt_webhook.clone(),
+            message_signing: self.message_signing.clone(),
+            dkg_keys: self.dkg_keys.clone(),
+            stale_round_max_age: self.config.stale_round_max_age,
+            coordinator_selection: self.config.coordinator_selection,
+            escalation: self.config.escalation.clone(),
+            participation: self.config.participation.for_reward_cycle(reward_cycle),
         })
     }
 
-    /// Refresh signer configuration for a specific reward cycle
-    fn refresh_signer_config(&mut self, reward_cycle: u64) {
+    /// Get the coordinator timeout to use for the given phase.
+    /// If adaptive timeouts are enabled, this is derived from the latencies observed so far,
+    /// falling back to the configured timeout.
+    fn get_timeout(&self, phase: TimeoutPhase) -> Option<Duration> {
+        let configured = match phase {
+            TimeoutPhase::DkgPublic => self.config.dkg_public_timeout,
+            TimeoutPhase::DkgPrivate => self.config.dkg_private_timeout,
+            TimeoutPhase::DkgEnd => self.config.dkg_end_timeout,
+            TimeoutPhase::Nonce => self.config.nonce_timeout,
+            TimeoutPhase::Sign => self.config.sign_timeout,
+        };
+        let Some(adaptive_timeouts) = self.adaptive_timeouts.as_ref() else {
+            return configured;
+        };
+        let timeout = adaptive_timeouts.timeout(phase, configured);
+        debug!("Using adaptive {phase:?} timeout: {timeout:?} (configured: {configured:?})");
+        timeout
+    }
+
+    /// Refresh signer configuration for a specific reward cycle.
+    /// Returns whether the signer is registered for it.
+    fn refresh_signer_config(&mut self, reward_cycle: u64) -> bool {
         let reward_index = reward_cycle % 2;
         if let Some(new_signer_config) = self.get_signer_config(reward_cycle) {
             let signer_id = new_signer_config.signer_id;
@@ -270,13 +457,71 @@ impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug> RunLo
             let new_signer = Signer::new(new_signer_config);
             info!("{new_signer} initialized.");
             self.stacks_signers.insert(reward_index, new_signer);
+            // Events left over by the signer this one replaces are not for it
+            self.deferred_events.remove(&reward_index);
+            true
         } else {
             warn!("Signer is not registered for reward cycle {reward_cycle}. Waiting for confirmed registration...");
+            false
+        }
+    }
+
+    /// Register for the next reward cycle if its reward set can be calculated, i.e. if
+    /// `burn_block_height` is in the prepare phase of the next reward cycle, and the signer is
+    /// not registered for it yet. Unless `force` is set, the reward set is checked at most once
+    /// per `next_cycle_poll_interval`, so this can be called on every pass.
+    fn preregister_next_cycle(&mut self, burn_block_height: u64, force: bool) {
+        let Some(reward_cycle_info) = self.current_reward_cycle_info else {
+            return;
+        };
+        if !reward_cycle_info.is_in_next_prepare_phase(burn_block_height) {
+            return;
+        }
+        let next_reward_cycle = reward_cycle_info.reward_cycle.saturating_add(1);
+        let reward_index = next_reward_cycle % 2;
+        let now = Instant::now();
+        if matches!(
+            self.stacks_signers.get(&reward_index),
+            Some(signer) if signer.reward_cycle() == next_reward_cycle
+        ) {
+            // e.g. registered when the runloop was initialized
+            self.next_cycle_preregistration
+                .record_attempt(next_reward_cycle, true, now);
+            return;
+        }
+        if !self
+            .next_cycle_preregistration
+            .attempt_due(next_reward_cycle, now)
+            && !force
+        {
+            return;
+        }
+        debug!("Burnchain block height ({burn_block_height}) is in the prepare phase of the next reward cycle ({next_reward_cycle}). Checking for signer registration...");
+        let registered = self.refresh_signer_config(next_reward_cycle);
+        self.next_cycle_preregistration
+            .record_attempt(next_reward_cycle, registered, now);
+        if !registered {
+            return;
+        }
+        let Some(signer) = self.stacks_signers.get_mut(&reward_index) else {
+            return;
+        };
+        signer.publish_registration();
+        if let Some(PreregistrationStatus::Registered { elapsed }) =
+            self.next_cycle_preregistration.status(next_reward_cycle)
+        {
+            info!("{signer}: Registered for the next reward cycle ahead of time";
+                "burn_block_height" => burn_block_height,
+                "elapsed_ms" => elapsed.as_millis(),
+            );
         }
     }
 
     fn initialize_runloop(&mut self) -> Result<(), ClientError> {
         debug!("Initializing signer runloop...");
+        // Fail with a clear error, rather than on 404s, if the node is too old to sign with
+        let node_version = self.stacks_client.negotiate_node_version()?;
+        debug!("Stacks node supports signing"; "server_version" => &node_version.server_version);
         let reward_cycle_info = retry_with_exponential_backoff(|| {
             self.stacks_client
                 .get_current_reward_cycle_info()
@@ -284,17 +529,10 @@ impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug> RunLo
         })?;
         let current_reward_cycle = reward_cycle_info.reward_cycle;
         self.refresh_signer_config(current_reward_cycle);
-        // We should only attempt to initialize the next reward cycle signer if we are in the prepare phase of the next reward cycle
-        if reward_cycle_info.is_in_next_prepare_phase(reward_cycle_info.last_burnchain_block_height)
-        {
-            self.refresh_signer_config(current_reward_cycle.saturating_add(1));
-        }
         self.current_reward_cycle_info = Some(reward_cycle_info);
-        if self.stacks_signers.is_empty() {
-            self.state = State::NoRegisteredSigners;
-        } else {
-            self.state = State::RegisteredSigners;
-        }
+        // We should only attempt to initialize the next reward cycle signer if we are in the prepare phase of the next reward cycle
+        self.preregister_next_cycle(reward_cycle_info.last_burnchain_block_height, true);
+        self.transition(self.registration_state());
         Ok(())
     }
 
@@ -328,27 +566,101 @@ impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug> RunLo
             })?;
             *reward_cycle_info = new_reward_cycle_info;
         }
+        reward_cycle_info.last_burnchain_block_height = reward_cycle_info
+            .last_burnchain_block_height
+            .max(current_burn_block_height);
         let current_reward_cycle = reward_cycle_info.reward_cycle;
         // We should only attempt to refresh the signer if we are not configured for the next reward cycle yet and we received a new burn block for its prepare phase
-        if reward_cycle_info.is_in_next_prepare_phase(current_burn_block_height) {
-            let next_reward_cycle = current_reward_cycle.saturating_add(1);
-            if self
-                .stacks_signers
-                .get(&(next_reward_cycle % 2))
-                .map(|signer| signer.reward_cycle() != next_reward_cycle)
-                .unwrap_or(true)
-            {
-                info!("Received a new burnchain block height ({current_burn_block_height}) in the prepare phase of the next reward cycle ({next_reward_cycle}). Checking for signer registration...");
-                self.refresh_signer_config(next_reward_cycle);
+        self.preregister_next_cycle(current_burn_block_height, true);
+        self.cleanup_stale_signers(current_reward_cycle);
+        self.transition(self.registration_state());
+        Ok(())
+    }
+
+    /// The state the runloop is in, given the signers it currently runs.
+    /// The active signer is the one for the earliest reward cycle, since stale signers have
+    /// already been cleaned up; a signer for the next reward cycle running DKG in the prepare
+    /// phase does not hold back the current one.
+    fn registration_state(&self) -> State {
+        match self
+            .stacks_signers
+            .values()
+            .min_by_key(|signer| signer.reward_cycle())
+        {
+            None => State::WaitingForRewardSet,
+            Some(signer) if signer.has_aggregate_key() => State::Ready,
+            Some(_) => State::RegisteredPendingDkg,
+        }
+    }
+
+    /// Move the runloop to the given state, recording the change if it is one
+    fn transition(&mut self, new_state: State) {
+        if self.state == new_state {
+            return;
+        }
+        info!(
+            "Signer runloop state transition: {:?} -> {:?}",
+            self.state, new_state
+        );
+        if self.state_history.len() >= STATE_HISTORY_LENGTH {
+            self.state_history.pop_front();
+        }
+        self.state_history.push_back(StateTransition {
+            from: self.state,
+            to: new_state,
+            timestamp: get_epoch_time_secs(),
+        });
+        self.state = new_state;
+    }
+
+    /// Check the node's chain tip against the network's, if a check is due.
+    /// Returns whether the node is diverged, in which case the runloop is degraded.
+    fn check_chain_tip_divergence(&mut self) -> bool {
+        let now = Instant::now();
+        if self.chain_tip_monitor.check_due(now) {
+            match self.stacks_client.get_peer_info() {
+                Ok(peer_info) => {
+                    let was_diverged = self.chain_tip_monitor.is_diverged();
+                    self.chain_tip_monitor
+                        .check(ChainTip::from(&peer_info), now);
+                    if was_diverged && !self.chain_tip_monitor.is_diverged() {
+                        self.transition(self.registration_state());
+                    }
+                }
+                Err(e) => warn!("Failed to get the node's chain tip: {e}"),
             }
         }
-        self.cleanup_stale_signers(current_reward_cycle);
-        if self.stacks_signers.is_empty() {
-            self.state = State::NoRegisteredSigners;
-        } else {
-            self.state = State::RegisteredSigners;
+        if self.chain_tip_monitor.is_diverged() {
+            self.transition(State::Degraded);
+            return true;
         }
-        Ok(())
+        false
+    }
+
+    /// Compare the local clock against the header timestamp of the burn block in `event`, if it
+    /// is a new burn block.
+    /// Returns whether the local clock is skewed, in which case the runloop is degraded.
+    fn check_clock_skew(&mut self, event: Option<&SignerEvent<T>>) -> bool {
+        if let Some(SignerEvent::NewBurnBlock {
+            burn_height,
+            burn_header_timestamp: Some(burn_header_timestamp),
+        }) = event
+        {
+            let was_skewed = self.clock_skew_monitor.is_skewed();
+            self.clock_skew_monitor.observe(
+                *burn_height,
+                *burn_header_timestamp,
+                get_epoch_time_secs(),
+            );
+            if was_skewed && !self.clock_skew_monitor.is_skewed() {
+                self.transition(self.registration_state());
+            }
+        }
+        if self.clock_skew_monitor.is_skewed() {
+            self.transition(State::Degraded);
+            return true;
+        }
+        false
     }
 
     fn cleanup_stale_signers(&mut self, current_reward_cycle: u64) {
@@ -362,11 +674,12 @@ impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug> RunLo
         }
         for idx in to_delete {
             self.stacks_signers.remove(&idx);
+            self.deferred_events.remove(&idx);
         }
     }
 }
 
-impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug>
+impl<Signer: SignerTrait<T>, T: SignerEventTrait>
     SignerRunLoop<Vec<OperationResult>, RunLoopCommand, T> for RunLoop<Signer, T>
 {
     fn set_event_timeout(&mut self, timeout: Duration) {
@@ -383,6 +696,7 @@ impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug>
         cmd: Option<RunLoopCommand>,
         res: Sender<Vec<OperationResult>>,
     ) -> Option<Vec<OperationResult>> {
+        let pass_started_at = Instant::now();
         debug!(
             "Running one pass for the signer. state={:?}, cmd={cmd:?}, event={event:?}",
             self.state
@@ -398,10 +712,17 @@ impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug>
                 }
                 return None;
             }
-        } else if let Some(SignerEvent::NewBurnBlock(current_burn_block_height)) = event {
-            if let Err(e) = self.refresh_runloop(current_burn_block_height) {
+        } else if let Some(SignerEvent::NewBurnBlock { burn_height, .. }) = event {
+            if let Err(e) = self.refresh_runloop(burn_height) {
                 error!("Failed to refresh signer runloop: {e}.");
                 warn!("Signer may have an outdated view of the network.");
+                self.transition(State::Degraded);
+            }
+        } else if let Some(reward_cycle_info) = self.current_reward_cycle_info {
+            // The next reward set may have become calculable since the last burn block
+            self.preregister_next_cycle(reward_cycle_info.last_burnchain_block_height, false);
+            if self.state == State::WaitingForRewardSet {
+                self.transition(self.registration_state());
             }
         }
         let current_reward_cycle = self
@@ -409,7 +730,7 @@ impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug>
             .as_ref()
             .expect("FATAL: cannot be an initialized signer with no reward cycle info.")
             .reward_cycle;
-        if self.state == State::NoRegisteredSigners {
+        if self.state == State::WaitingForRewardSet {
             let next_reward_cycle = current_reward_cycle.saturating_add(1);
             if let Some(event) = event {
                 info!("Signer is not registered for the current reward cycle ({current_reward_cycle}). Reward set is not yet determined or signer is not registered for the upcoming reward cycle ({next_reward_cycle}).");
@@ -417,19 +738,74 @@ impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug>
             }
             return None;
         }
-        for signer in self.stacks_signers.values_mut() {
-            signer.process_event(
-                &self.stacks_client,
-                event.as_ref(),
-                res.clone(),
-                current_reward_cycle,
-            );
+        if self.check_clock_skew(event.as_ref()) {
+            if let Some(event) = event {
+                warn!("Signer's clock is skewed from the burnchain's. Ignoring event: {event:?}");
+            }
+            return None;
+        }
+        if self.check_chain_tip_divergence() {
+            if let Some(event) = event {
+                warn!("Signer's node has diverged from the network. Ignoring event: {event:?}");
+            }
+            return None;
+        }
+        drop_expired_commands(&mut self.commands, current_reward_cycle);
+        let budget = PassBudget::start(self.config.pass_budget, pass_started_at);
+        let mut budget_exhausted = false;
+        for (reward_index, signer) in self.stacks_signers.iter_mut() {
+            let backlog = self
+                .deferred_events
+                .remove(reward_index)
+                .unwrap_or_default();
+            let backlog = process_within_budget(&budget, backlog, event.as_ref(), |event| {
+                let source = event.map_or_else(|| "no event".to_string(), event_source);
+                let watch = self.event_watchdog.watch(format!("{signer}: {source}"));
+                signer.watch_event(Some(watch.clone()));
+                signer.process_event(
+                    &self.stacks_client,
+                    event,
+                    res.clone(),
+                    current_reward_cycle,
+                );
+                signer.watch_event(None);
+                watch.finish();
+            });
+            if !backlog.is_empty() {
+                debug!(
+                    "{signer}: Pass ran out of time. Deferring {} events to the next pass.",
+                    backlog.len()
+                );
+                self.deferred_events.insert(*reward_index, backlog);
+            }
+            if budget.is_exhausted(Instant::now()) {
+                // Leave the commands queued for the next pass
+                budget_exhausted = true;
+                continue;
+            }
             // After processing event, run the next command for each signer
             signer.process_command(
                 &self.stacks_client,
                 current_reward_cycle,
                 self.commands.pop_front(),
             );
+            for tip in signer.take_observed_tips() {
+                self.chain_tip_monitor.observe(tip, Instant::now());
+            }
+            let round_latencies = signer.take_round_latencies();
+            if let Some(adaptive_timeouts) = self.adaptive_timeouts.as_mut() {
+                for (phase, latency) in round_latencies {
+                    adaptive_timeouts.observe(phase, latency);
+                }
+            }
+        }
+        if budget_exhausted {
+            crate::monitoring::increment_pass_budget_exhaustions();
+        }
+        // DKG completes while processing events, so catch up on it here. A degraded runloop
+        // stays degraded until the next successful refresh.
+        if self.state != State::Degraded {
+            self.transition(self.registration_state());
         }
         None
     }
@@ -437,12 +813,26 @@ impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug>
 
 #[cfg(test)]
 mod tests {
+    use std::collections::VecDeque;
+    use std::thread::spawn;
+    use std::time::Duration;
+
     use blockstack_lib::chainstate::stacks::boot::NakamotoSignerEntry;
+    use libsigner::v1::messages::SignerMessage;
     use libsigner::SignerEntries;
     use rand::{thread_rng, Rng, RngCore};
     use stacks_common::types::chainstate::{StacksPrivateKey, StacksPublicKey};
+    use stacks_common::util::get_epoch_time_secs;
+    use wsts::curve::point::Point;
 
-    use super::RewardCycleInfo;
+    use super::{
+        drop_expired_commands, CommandExpiry, RewardCycleInfo, RunLoop, RunLoopCommand,
+        SignerCommand, State, STATE_HISTORY_LENGTH,
+    };
+    use crate::client::tests::{generate_signer_config, mock_server_random, write_response};
+    use crate::config::GlobalConfig;
+    use crate::v1::signer::Signer;
+    use crate::Signer as _;
 
     #[test]
     fn parse_nakamoto_signer_entries_test() {
@@ -607,4 +997,106 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn state_follows_registration_and_dkg() {
+        let (mock_server, mock_server_addr) = mock_server_random();
+        let mut config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
+        config.node_host = mock_server_addr.to_string();
+        let mut runloop: RunLoop<Signer, SignerMessage> = RunLoop::new(config.clone());
+        assert_eq!(runloop.state, State::Uninitialized);
+        assert_eq!(runloop.registration_state(), State::WaitingForRewardSet);
+
+        // The signer looks for its persisted state in StackerDB when it is created. Answer
+        // with a 404, instead of leaving it to retry until it gives up.
+        let signer_config = generate_signer_config(&config, 5, 20);
+        let reward_cycle = signer_config.reward_cycle;
+        let h = spawn(move || Signer::new(signer_config));
+        write_response(mock_server, b"HTTP/1.1 404 Not Found\n\n");
+        let mut signer = h.join().unwrap();
+        runloop.stacks_signers.insert(reward_cycle % 2, signer);
+        assert_eq!(runloop.registration_state(), State::RegisteredPendingDkg);
+
+        signer = runloop.stacks_signers.remove(&(reward_cycle % 2)).unwrap();
+        signer.approved_aggregate_public_key = Some(Point::new());
+        runloop.stacks_signers.insert(reward_cycle % 2, signer);
+        assert_eq!(runloop.registration_state(), State::Ready);
+    }
+
+    #[test]
+    fn state_transitions_are_recorded() {
+        let config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
+        let mut runloop: RunLoop<Signer, SignerMessage> = RunLoop::new(config);
+
+        runloop.transition(State::WaitingForRewardSet);
+        runloop.transition(State::RegisteredPendingDkg);
+        // transitioning to the current state is not a transition
+        runloop.transition(State::RegisteredPendingDkg);
+        runloop.transition(State::Degraded);
+        runloop.transition(State::Ready);
+        assert_eq!(runloop.state, State::Ready);
+        assert_eq!(
+            runloop
+                .state_history
+                .iter()
+                .map(|transition| (transition.from, transition.to))
+                .collect::<Vec<_>>(),
+            vec![
+                (State::Uninitialized, State::WaitingForRewardSet),
+                (State::WaitingForRewardSet, State::RegisteredPendingDkg),
+                (State::RegisteredPendingDkg, State::Degraded),
+                (State::Degraded, State::Ready),
+            ]
+        );
+
+        // only the most recent transitions are kept
+        for _ in 0..STATE_HISTORY_LENGTH {
+            runloop.transition(State::Degraded);
+            runloop.transition(State::Ready);
+        }
+        assert_eq!(runloop.state_history.len(), STATE_HISTORY_LENGTH);
+        assert_eq!(runloop.state_history.back().unwrap().to, State::Ready);
+    }
+
+    #[test]
+    fn expired_commands_are_dropped() {
+        let now = get_epoch_time_secs();
+        let command = RunLoopCommand::new(SignerCommand::Dkg, 5);
+        assert_eq!(command.expiry(4, now), None);
+        assert_eq!(command.expiry(5, now), None);
+        assert_eq!(
+            command.expiry(6, now),
+            Some(CommandExpiry::RewardCyclePassed)
+        );
+
+        let command = RunLoopCommand::new(SignerCommand::Dkg, 5).with_ttl(Duration::from_secs(60));
+        assert_eq!(command.expiry(5, now), None);
+        assert_eq!(
+            command.expiry(5, now + 60),
+            Some(CommandExpiry::DeadlinePassed)
+        );
+        // a passed reward cycle takes precedence
+        assert_eq!(
+            command.expiry(6, now + 60),
+            Some(CommandExpiry::RewardCyclePassed)
+        );
+
+        let mut commands = VecDeque::from(vec![
+            RunLoopCommand::new(SignerCommand::Dkg, 4),
+            RunLoopCommand::new(SignerCommand::Dkg, 5),
+            RunLoopCommand {
+                expires_at: Some(now.saturating_sub(1)),
+                ..RunLoopCommand::new(SignerCommand::Dkg, 5)
+            },
+            RunLoopCommand::new(SignerCommand::Dkg, 6).with_ttl(Duration::from_secs(60)),
+        ]);
+        drop_expired_commands(&mut commands, 5);
+        assert_eq!(
+            commands
+                .iter()
+                .map(|command| (command.reward_cycle, command.expires_at.is_some()))
+                .collect::<Vec<_>>(),
+            vec![(5, false), (6, true)]
+        );
+    }
 }
@@ -0,0 +1,303 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt::{self, Debug};
+use std::sync::atomic::{compiler_fence, Ordering};
+
+use rand_core::CryptoRngCore;
+use sha2::Sha256;
+use stacks_common::types::chainstate::StacksPrivateKey;
+use stacks_common::types::PrivateKey;
+use stacks_common::util::hash::{hex_bytes, to_hex};
+use wsts::curve::scalar::Scalar;
+use zeroize::Zeroizing;
+
+/// The environment variable the key passphrase is read from, if no passphrase file is given
+pub const KEY_PASSPHRASE_ENV: &str = "STACKS_SIGNER_KEY_PASSPHRASE";
+
+/// PBKDF2-HMAC-SHA256 rounds used to derive an encryption key from a passphrase
+pub const PASSPHRASE_KDF_ROUNDS: u32 = 600_000;
+
+/// Length of the random salt stored with a passphrase-encrypted key
+const SALT_LEN: usize = 16;
+
+/// Length of the header in front of the ciphertext: kind (1), KDF rounds (4), salt
+const HEADER_LEN: usize = 1 + 4 + SALT_LEN;
+
+/// A private key, held in a buffer which is zeroized when dropped, and never printed.
+/// The Stacks and WSTS forms of the key are built from it where they are used, and should not
+/// be kept.
+#[derive(Clone)]
+pub struct Secret {
+    bytes: Zeroizing<[u8; 32]>,
+    compress_public: bool,
+}
+
+impl Secret {
+    /// Copy `key` into a new secret
+    pub fn new(key: &StacksPrivateKey) -> Self {
+        let mut bytes = Zeroizing::new([0u8; 32]);
+        bytes.copy_from_slice(key.as_slice());
+        Self {
+            bytes,
+            compress_public: key.compress_public(),
+        }
+    }
+
+    /// The key, for signing Stacks transactions and StackerDB chunks
+    pub fn stacks_private_key(&self) -> StacksPrivateKey {
+        let mut key = StacksPrivateKey::from_slice(&self.bytes[..])
+            .expect("FATAL: secret does not hold a valid private key");
+        key.set_compress_public(self.compress_public);
+        key
+    }
+
+    /// The key as a WSTS scalar, for signing WSTS packets
+    pub fn scalar(&self) -> Scalar {
+        // a valid secp256k1 private key is always less than the group order
+        Scalar::try_from(&self.bytes[..]).expect("FATAL: secret does not hold a valid scalar")
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret(<redacted>)")
+    }
+}
+
+/// Zeroize `scalar`, a WSTS private key, in place.  `Scalar` does not implement `Zeroize`, so
+/// it is set to zero through libsecp256k1, in a call the compiler cannot drop.
+pub fn zeroize_scalar(scalar: &mut Scalar) {
+    scalar.set_int(0);
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// How the key protecting an encrypted private key is obtained
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum KeyEncryptionKind {
+    /// The key is derived from an operator-chosen passphrase
+    Passphrase = 1,
+    /// The key is 32 raw bytes (hex-encoded), e.g. a data key issued by a KMS
+    RawKey = 2,
+}
+
+impl TryFrom<u8> for KeyEncryptionKind {
+    type Error = KeyEncryptionError;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Passphrase),
+            2 => Ok(Self::RawKey),
+            _ => Err(KeyEncryptionError::UnsupportedKind(value)),
+        }
+    }
+}
+
+/// Error stemming from encrypting or decrypting a private key
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum KeyEncryptionError {
+    /// The encrypted key is not well-formed
+    #[error("Malformed encrypted key: {0}")]
+    Malformed(String),
+    /// The encrypted key was produced with an unknown kind of key-encryption key
+    #[error("Unsupported key encryption kind {0}")]
+    UnsupportedKind(u8),
+    /// The key-encryption secret is not usable for this kind of encrypted key
+    #[error("Invalid key encryption secret: {0}")]
+    InvalidSecret(String),
+    /// Encryption failed
+    #[error("Failed to encrypt private key")]
+    Encrypt,
+    /// Decryption failed, most likely because the secret is wrong
+    #[error("Failed to decrypt private key (wrong passphrase or key?)")]
+    Decrypt,
+}
+
+fn derive_encryption_key(
+    kind: KeyEncryptionKind,
+    secret: &str,
+    salt: &[u8],
+    rounds: u32,
+) -> Result<Zeroizing<[u8; 32]>, KeyEncryptionError> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    match kind {
+        KeyEncryptionKind::Passphrase => {
+            if secret.is_empty() {
+                return Err(KeyEncryptionError::InvalidSecret(
+                    "empty passphrase".to_string(),
+                ));
+            }
+            pbkdf2::pbkdf2_hmac::<Sha256>(secret.as_bytes(), salt, rounds, &mut key[..]);
+        }
+        KeyEncryptionKind::RawKey => {
+            let bytes = Zeroizing::new(hex_bytes(secret.trim()).map_err(|_| {
+                KeyEncryptionError::InvalidSecret("raw key is not hex-encoded".to_string())
+            })?);
+            if bytes.len() != key.len() {
+                return Err(KeyEncryptionError::InvalidSecret(format!(
+                    "raw key must be {} bytes",
+                    key.len()
+                )));
+            }
+            key.copy_from_slice(&bytes);
+        }
+    }
+    Ok(key)
+}
+
+/// Encrypt `private_key` under `secret` (a passphrase, or a hex-encoded 32-byte key, per
+/// `kind`), returning the hex encoding to store in the config file.
+pub fn encrypt_private_key(
+    private_key: &StacksPrivateKey,
+    kind: KeyEncryptionKind,
+    secret: &str,
+    rng: &mut impl CryptoRngCore,
+) -> Result<String, KeyEncryptionError> {
+    encrypt_private_key_with_rounds(private_key, kind, secret, PASSPHRASE_KDF_ROUNDS, rng)
+}
+
+fn encrypt_private_key_with_rounds(
+    private_key: &StacksPrivateKey,
+    kind: KeyEncryptionKind,
+    secret: &str,
+    rounds: u32,
+    rng: &mut impl CryptoRngCore,
+) -> Result<String, KeyEncryptionError> {
+    let mut salt = [0u8; SALT_LEN];
+    rng.fill_bytes(&mut salt);
+    let rounds = match kind {
+        KeyEncryptionKind::Passphrase => rounds,
+        KeyEncryptionKind::RawKey => 0,
+    };
+    let key = derive_encryption_key(kind, secret, &salt, rounds)?;
+    let plaintext = Zeroizing::new(private_key.to_bytes());
+    let ciphertext =
+        wsts::util::encrypt(&key, &plaintext, rng).map_err(|_| KeyEncryptionError::Encrypt)?;
+
+    let mut encoded = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    encoded.push(kind as u8);
+    encoded.extend_from_slice(&rounds.to_be_bytes());
+    encoded.extend_from_slice(&salt);
+    encoded.extend_from_slice(&ciphertext);
+    Ok(to_hex(&encoded))
+}
+
+/// Decrypt a private key produced by `encrypt_private_key`.
+/// `secret` is the passphrase, or the hex-encoded raw key, that it was encrypted under.
+pub fn decrypt_private_key(
+    encrypted: &str,
+    secret: &str,
+) -> Result<StacksPrivateKey, KeyEncryptionError> {
+    let encoded = hex_bytes(encrypted.trim())
+        .map_err(|_| KeyEncryptionError::Malformed("not hex-encoded".to_string()))?;
+    if encoded.len() <= HEADER_LEN {
+        return Err(KeyEncryptionError::Malformed("too short".to_string()));
+    }
+    let kind = KeyEncryptionKind::try_from(encoded[0])?;
+    let mut rounds_bytes = [0u8; 4];
+    rounds_bytes.copy_from_slice(&encoded[1..5]);
+    let rounds = u32::from_be_bytes(rounds_bytes);
+    if kind == KeyEncryptionKind::Passphrase && rounds == 0 {
+        return Err(KeyEncryptionError::Malformed("zero KDF rounds".to_string()));
+    }
+    let salt = &encoded[5..HEADER_LEN];
+
+    let key = derive_encryption_key(kind, secret, salt, rounds)?;
+    let plaintext = Zeroizing::new(
+        wsts::util::decrypt(&key, &encoded[HEADER_LEN..])
+            .map_err(|_| KeyEncryptionError::Decrypt)?,
+    );
+    StacksPrivateKey::from_slice(&plaintext)
+        .map_err(|_| KeyEncryptionError::Malformed("not a private key".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    #[test]
+    fn encrypted_keys_round_trip() {
+        let private_key = StacksPrivateKey::new();
+
+        let encrypted = encrypt_private_key_with_rounds(
+            &private_key,
+            KeyEncryptionKind::Passphrase,
+            "correct horse battery staple",
+            16,
+            &mut OsRng,
+        )
+        .unwrap();
+        assert!(!encrypted.contains(&private_key.to_hex()));
+        assert_eq!(
+            decrypt_private_key(&encrypted, "correct horse battery staple").unwrap(),
+            private_key
+        );
+        assert_eq!(
+            decrypt_private_key(&encrypted, "wrong passphrase"),
+            Err(KeyEncryptionError::Decrypt)
+        );
+
+        let raw_key = to_hex(&[7u8; 32]);
+        let encrypted = encrypt_private_key(
+            &private_key,
+            KeyEncryptionKind::RawKey,
+            &raw_key,
+            &mut OsRng,
+        )
+        .unwrap();
+        assert_eq!(
+            decrypt_private_key(&encrypted, &raw_key).unwrap(),
+            private_key
+        );
+        assert_eq!(
+            decrypt_private_key(&encrypted, &to_hex(&[8u8; 32])),
+            Err(KeyEncryptionError::Decrypt)
+        );
+        assert!(matches!(
+            decrypt_private_key(&encrypted, "not a raw key"),
+            Err(KeyEncryptionError::InvalidSecret(_))
+        ));
+
+        assert!(matches!(
+            decrypt_private_key("0badc0de", &raw_key),
+            Err(KeyEncryptionError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn secrets_are_overwritten_and_redacted() {
+        let mut scalar = Scalar::random(&mut OsRng);
+        zeroize_scalar(&mut scalar);
+        assert_eq!(scalar.to_bytes(), [0u8; 32]);
+        assert_eq!(scalar, Scalar::new());
+
+        let private_key = StacksPrivateKey::new();
+        let secret = Secret::new(&private_key);
+        assert_eq!(format!("{:?}", secret), "Secret(<redacted>)");
+        assert_eq!(secret.stacks_private_key(), private_key);
+        assert_eq!(secret.clone().stacks_private_key(), private_key);
+        assert_eq!(secret.scalar().to_bytes()[..], private_key.to_bytes()[..32]);
+
+        let mut uncompressed = StacksPrivateKey::new();
+        uncompressed.set_compress_public(false);
+        assert_eq!(
+            Secret::new(&uncompressed).stacks_private_key(),
+            uncompressed
+        );
+    }
+}
@@ -0,0 +1,586 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Running a signer in-process, for end-to-end tests of the programs it interacts with.  Only
+//! built with the `testing` feature, so none of this is compiled into the signer binary.
+//!
+//! A [`TestSigner`] runs the signer runloop on its own threads, as the signer binary does, but
+//! takes its events from an [`EventInjector`] rather than from the stacks node's event
+//! dispatcher, and talks to a [`MockStacksNode`] whose responses the test programs:
+//!
+//! ```no_run
+//! use libsigner::SignerEvent;
+//! use stacks_signer::config::GlobalConfig;
+//! use stacks_signer::testing::{MockResponse, MockStacksNode, TestSigner};
+//!
+//! let node = MockStacksNode::spawn().unwrap();
+//! node.respond_with("GET", "/v2/info", MockResponse::ok_json(&serde_json::json!({})));
+//!
+//! let mut config = GlobalConfig::load_from_file("signer.toml").unwrap();
+//! node.configure(&mut config);
+//! let signer = TestSigner::spawn(config).unwrap();
+//! signer.events.inject(SignerEvent::NewBurnBlock {
+//!     burn_height: 100,
+//!     burn_header_timestamp: None,
+//! });
+//! signer.stop();
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use libsigner::v1::messages::SignerMessage;
+use libsigner::{EventError, EventReceiver, EventStopSignaler, SignerEvent, SignerEventTrait};
+use serde::Serialize;
+use slog::{slog_debug, slog_error, slog_warn};
+use stacks_common::{debug, error, warn};
+use wsts::state_machine::OperationResult;
+
+use crate::config::GlobalConfig;
+use crate::runloop::{RunLoop, RunLoopCommand};
+use crate::v1::signer::Signer;
+
+/// How often the injected event receiver checks whether it has been stopped
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How often `MockStacksNode::wait_for_request` checks for a new request
+const REQUEST_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A response served by the mock stacks node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockResponse {
+    /// The HTTP status code
+    pub status: u16,
+    /// The response body
+    pub body: String,
+}
+
+impl MockResponse {
+    /// A 200 response with `body` encoded as JSON
+    pub fn ok_json<S: Serialize>(body: &S) -> Self {
+        Self {
+            status: 200,
+            body: serde_json::to_string(body).expect("Failed to serialize mock response"),
+        }
+    }
+
+    /// An empty response with the given status code
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            body: String::new(),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            500 => "Internal Server Error",
+            503 => "Service Unavailable",
+            _ => "Unknown",
+        }
+    }
+}
+
+/// A request received by the mock stacks node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockRequest {
+    /// The HTTP method
+    pub method: String,
+    /// The request path, including the query string
+    pub path: String,
+    /// The request body
+    pub body: Vec<u8>,
+}
+
+/// A response the mock stacks node serves to the requests that match it
+#[derive(Debug, Clone)]
+struct MockRoute {
+    method: String,
+    path_prefix: String,
+    response: MockResponse,
+}
+
+impl MockRoute {
+    fn matches(&self, request: &MockRequest) -> bool {
+        self.method.eq_ignore_ascii_case(&request.method)
+            && request.path.starts_with(&self.path_prefix)
+    }
+}
+
+/// The responses and requests of a mock stacks node, shared with its server thread
+#[derive(Debug, Default)]
+struct MockNodeState {
+    /// Served to every matching request. Later routes take precedence over earlier ones
+    routes: Vec<MockRoute>,
+    /// Each served to the first matching request, in the order they were added. These take
+    /// precedence over `routes`
+    one_shot_routes: VecDeque<MockRoute>,
+    /// Every request received, oldest first
+    requests: Vec<MockRequest>,
+}
+
+impl MockNodeState {
+    /// Record `request` and choose its response. Requests that match no route get a 404
+    fn respond(&mut self, request: MockRequest) -> MockResponse {
+        let one_shot = self
+            .one_shot_routes
+            .iter()
+            .position(|route| route.matches(&request))
+            .and_then(|index| self.one_shot_routes.remove(index));
+        let response = if let Some(route) = one_shot {
+            route.response
+        } else {
+            self.routes
+                .iter()
+                .rev()
+                .find(|route| route.matches(&request))
+                .map(|route| route.response.clone())
+                .unwrap_or_else(|| {
+                    debug!(
+                        "MockStacksNode: no response for {} {}",
+                        request.method, request.path
+                    );
+                    MockResponse::status(404)
+                })
+        };
+        self.requests.push(request);
+        response
+    }
+}
+
+/// An HTTP server standing in for the stacks node's RPC interface. Each request is answered
+/// with the response registered for its method and path, and recorded so that the test can
+/// check what the signer asked the node for.
+pub struct MockStacksNode {
+    local_addr: SocketAddr,
+    state: Arc<Mutex<MockNodeState>>,
+    stop_signal: Arc<AtomicBool>,
+    server_thread: Option<JoinHandle<()>>,
+}
+
+impl MockStacksNode {
+    /// Start a mock stacks node on a random local port
+    pub fn spawn() -> io::Result<Self> {
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))?;
+        let local_addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockNodeState::default()));
+        let stop_signal = Arc::new(AtomicBool::new(false));
+
+        let server_state = state.clone();
+        let server_stop_signal = stop_signal.clone();
+        let server_thread = thread::Builder::new()
+            .name("mock_stacks_node".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if server_stop_signal.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let result = stream.and_then(|stream| serve_request(stream, &server_state));
+                    if let Err(e) = result {
+                        warn!("MockStacksNode: failed to serve request: {e:?}");
+                    }
+                }
+            })?;
+        Ok(Self {
+            local_addr,
+            state,
+            stop_signal,
+            server_thread: Some(server_thread),
+        })
+    }
+
+    /// The address the mock node listens on, in the form of the config's `node_host`
+    pub fn node_host(&self) -> String {
+        self.local_addr.to_string()
+    }
+
+    /// Point `config` at this mock node
+    pub fn configure(&self, config: &mut GlobalConfig) {
+        config.node_host = self.node_host();
+    }
+
+    /// Serve `response` to every `method` request whose path starts with `path_prefix`.
+    /// Replaces the response of an earlier call with the same prefix.
+    pub fn respond_with(&self, method: &str, path_prefix: &str, response: MockResponse) {
+        self.lock_state().routes.push(MockRoute {
+            method: method.to_string(),
+            path_prefix: path_prefix.to_string(),
+            response,
+        });
+    }
+
+    /// Serve `response` to the next `method` request whose path starts with `path_prefix`
+    /// only. Later requests get the response they would have got otherwise.
+    pub fn respond_once(&self, method: &str, path_prefix: &str, response: MockResponse) {
+        self.lock_state().one_shot_routes.push_back(MockRoute {
+            method: method.to_string(),
+            path_prefix: path_prefix.to_string(),
+            response,
+        });
+    }
+
+    /// Every request received so far, oldest first
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.lock_state().requests.clone()
+    }
+
+    /// Wait up to `timeout` for a request whose path starts with `path_prefix`, and return the
+    /// first one received
+    pub fn wait_for_request(&self, path_prefix: &str, timeout: Duration) -> Option<MockRequest> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let request = self
+                .lock_state()
+                .requests
+                .iter()
+                .find(|request| request.path.starts_with(path_prefix))
+                .cloned();
+            if request.is_some() || Instant::now() >= deadline {
+                return request;
+            }
+            thread::sleep(REQUEST_POLL_INTERVAL);
+        }
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, MockNodeState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for MockStacksNode {
+    fn drop(&mut self) {
+        self.stop_signal.store(true, Ordering::SeqCst);
+        // wake the server thread up, so it sees the stop signal
+        let _ = TcpStream::connect(self.local_addr);
+        if let Some(server_thread) = self.server_thread.take() {
+            if server_thread.join().is_err() {
+                error!("MockStacksNode: server thread panicked");
+            }
+        }
+    }
+}
+
+/// Read one HTTP request from `stream`, and write its response
+fn serve_request(stream: TcpStream, state: &Mutex<MockNodeState>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line)? == 0 {
+        // the connection was closed without a request (e.g. the stop signal's wake-up)
+        return Ok(());
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+
+    let response = state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .respond(MockRequest { method, path, body });
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.reason(),
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+/// Hands events to an `InjectedEventReceiver`, as the stacks node's event dispatcher would
+/// hand them to the signer's event receiver
+#[derive(Debug)]
+pub struct EventInjector<T: SignerEventTrait> {
+    event_send: Sender<SignerEvent<T>>,
+}
+
+impl<T: SignerEventTrait> Clone for EventInjector<T> {
+    fn clone(&self) -> Self {
+        Self {
+            event_send: self.event_send.clone(),
+        }
+    }
+}
+
+impl<T: SignerEventTrait> EventInjector<T> {
+    /// Inject `event`. Returns false if the event receiver has been dropped.
+    pub fn inject(&self, event: SignerEvent<T>) -> bool {
+        self.event_send.send(event).is_ok()
+    }
+
+    /// Inject a new burn block at `burn_block_height`, without a header timestamp
+    pub fn new_burn_block(&self, burn_block_height: u64) -> bool {
+        self.inject(SignerEvent::NewBurnBlock {
+            burn_height: burn_block_height,
+            burn_header_timestamp: None,
+        })
+    }
+
+    /// Inject a new burn block at `burn_block_height`, whose header has the given timestamp
+    pub fn new_burn_block_at(&self, burn_block_height: u64, burn_header_timestamp: u64) -> bool {
+        self.inject(SignerEvent::NewBurnBlock {
+            burn_height: burn_block_height,
+            burn_header_timestamp: Some(burn_header_timestamp),
+        })
+    }
+
+    /// Inject a status check
+    pub fn status_check(&self) -> bool {
+        self.inject(SignerEvent::StatusCheck)
+    }
+}
+
+/// Stops an `InjectedEventReceiver`
+#[derive(Debug, Clone)]
+pub struct InjectedStopSignaler {
+    stop_signal: Arc<AtomicBool>,
+}
+
+impl EventStopSignaler for InjectedStopSignaler {
+    fn send(&mut self) {
+        self.stop_signal.store(true, Ordering::SeqCst);
+    }
+}
+
+/// An event receiver which takes its events from an `EventInjector` rather than from an HTTP
+/// server, so it does not bind to any address
+pub struct InjectedEventReceiver<T: SignerEventTrait> {
+    event_recv: Receiver<SignerEvent<T>>,
+    out_channels: Vec<Sender<SignerEvent<T>>>,
+    stop_signal: Arc<AtomicBool>,
+}
+
+impl<T: SignerEventTrait> InjectedEventReceiver<T> {
+    /// Create a new receiver, and the injector which feeds it
+    pub fn new() -> (Self, EventInjector<T>) {
+        let (event_send, event_recv) = channel();
+        let receiver = Self {
+            event_recv,
+            out_channels: vec![],
+            stop_signal: Arc::new(AtomicBool::new(false)),
+        };
+        (receiver, EventInjector { event_send })
+    }
+}
+
+impl<T: SignerEventTrait> EventReceiver<T> for InjectedEventReceiver<T> {
+    type ST = InjectedStopSignaler;
+
+    /// There is nothing to bind, so this only returns `listener`
+    fn bind(&mut self, listener: SocketAddr) -> Result<SocketAddr, EventError> {
+        Ok(listener)
+    }
+
+    /// Wait for the next injected event
+    fn next_event(&mut self) -> Result<SignerEvent<T>, EventError> {
+        loop {
+            if self.is_stopped() {
+                return Err(EventError::Terminated);
+            }
+            match self.event_recv.recv_timeout(EVENT_POLL_INTERVAL) {
+                Ok(event) => return Ok(event),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return Err(EventError::Terminated),
+            }
+        }
+    }
+
+    fn add_consumer(&mut self, event_out: Sender<SignerEvent<T>>) {
+        self.out_channels.push(event_out);
+    }
+
+    fn forward_event(&mut self, ev: SignerEvent<T>) -> bool {
+        if self.out_channels.is_empty() {
+            error!("No channels connected to event receiver");
+            return false;
+        }
+        for (i, out_channel) in self.out_channels.iter().enumerate() {
+            if let Err(e) = out_channel.send(ev.clone()) {
+                error!("Failed to send to signer runloop #{}: {:?}", i, &e);
+                return false;
+            }
+        }
+        true
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stop_signal.load(Ordering::SeqCst)
+    }
+
+    fn get_stop_signaler(&mut self) -> Result<InjectedStopSignaler, EventError> {
+        Ok(InjectedStopSignaler {
+            stop_signal: self.stop_signal.clone(),
+        })
+    }
+}
+
+/// The running signer type for the test signer
+pub type TestRunningSigner = libsigner::RunningSigner<
+    InjectedEventReceiver<SignerMessage>,
+    Vec<OperationResult>,
+    SignerMessage,
+>;
+
+/// A v1 signer runloop running in-process, whose events are injected by the test
+pub struct TestSigner {
+    /// The underlying running signer thread handle
+    running_signer: TestRunningSigner,
+    /// Injects events into the signer runloop
+    pub events: EventInjector<SignerMessage>,
+    /// The command sender for interacting with the running signer
+    pub cmd_send: Sender<RunLoopCommand>,
+    /// The result receiver for interacting with the running signer
+    pub res_recv: Receiver<Vec<OperationResult>>,
+}
+
+impl TestSigner {
+    /// Start the signer runloop for `config`. The signer talks to the node at
+    /// `config.node_host` (see `MockStacksNode::configure`), but does not listen on
+    /// `config.endpoint`: its events come from `TestSigner::events` instead.
+    pub fn spawn(config: GlobalConfig) -> Result<Self, EventError> {
+        let endpoint = config.endpoint;
+        let (cmd_send, cmd_recv) = channel();
+        let (res_send, res_recv) = channel();
+        let (ev, events) = InjectedEventReceiver::new();
+        let runloop = RunLoop::new(config);
+        let mut signer: libsigner::Signer<
+            RunLoopCommand,
+            Vec<OperationResult>,
+            RunLoop<Signer, SignerMessage>,
+            InjectedEventReceiver<SignerMessage>,
+            SignerMessage,
+        > = libsigner::Signer::new(runloop, ev, cmd_recv, res_send);
+        let running_signer = signer.spawn(endpoint)?;
+        Ok(Self {
+            running_signer,
+            events,
+            cmd_send,
+            res_recv,
+        })
+    }
+
+    /// Stop the signer threads and return the final state
+    pub fn stop(self) -> Option<Vec<OperationResult>> {
+        self.running_signer.stop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::build_get_pox_data_response;
+    use crate::client::{ClientError, StacksClient};
+
+    fn test_config(node: &MockStacksNode) -> GlobalConfig {
+        let mut config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
+        node.configure(&mut config);
+        config
+    }
+
+    #[test]
+    fn mock_node_serves_registered_responses() {
+        let node = MockStacksNode::spawn().unwrap();
+        let client = StacksClient::from(&test_config(&node));
+        let (_, pox_info) = build_get_pox_data_response(Some(5), None, None, None);
+        let (_, next_pox_info) = build_get_pox_data_response(Some(6), None, None, None);
+
+        // no response registered yet
+        assert!(matches!(
+            client.get_pox_data(),
+            Err(ClientError::RequestFailure(status)) if status.as_u16() == 404
+        ));
+
+        node.respond_with("GET", "/v2/pox", MockResponse::ok_json(&pox_info));
+        node.respond_once("GET", "/v2/pox", MockResponse::ok_json(&next_pox_info));
+        assert_eq!(client.get_pox_data().unwrap(), next_pox_info);
+        assert_eq!(client.get_pox_data().unwrap(), pox_info);
+        assert_eq!(client.get_pox_data().unwrap(), pox_info);
+
+        let requests = node.requests();
+        assert_eq!(requests.len(), 4);
+        assert!(requests
+            .iter()
+            .all(|request| request.method == "GET" && request.path == "/v2/pox"));
+    }
+
+    #[test]
+    fn injected_events_reach_consumers() {
+        let (mut receiver, injector) = InjectedEventReceiver::<SignerMessage>::new();
+        let (event_send, event_recv) = channel();
+        receiver.add_consumer(event_send);
+        let mut stop_signaler = receiver.get_stop_signaler().unwrap();
+        let receiver_thread = thread::spawn(move || receiver.main_loop());
+
+        assert!(injector.new_burn_block(100));
+        assert!(injector.status_check());
+        assert!(matches!(
+            event_recv.recv_timeout(Duration::from_secs(10)),
+            Ok(SignerEvent::NewBurnBlock {
+                burn_height: 100,
+                burn_header_timestamp: None
+            })
+        ));
+        assert!(matches!(
+            event_recv.recv_timeout(Duration::from_secs(10)),
+            Ok(SignerEvent::StatusCheck)
+        ));
+
+        stop_signaler.send();
+        receiver_thread.join().unwrap();
+        assert!(!injector.status_check());
+    }
+
+    #[test]
+    fn test_signer_queries_mock_node() {
+        let node = MockStacksNode::spawn().unwrap();
+        let signer = TestSigner::spawn(test_config(&node)).unwrap();
+        assert!(signer.events.new_burn_block(100));
+
+        // the runloop asks the node about the reward cycle as it initializes
+        assert!(node
+            .wait_for_request("/v2/", Duration::from_secs(30))
+            .is_some());
+        assert!(signer.stop().is_none());
+    }
+}
@@ -0,0 +1,235 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use hashbrown::HashMap;
+use wsts::state_machine::coordinator::State as CoordinatorState;
+
+use crate::config::AdaptiveTimeoutConfig;
+
+/// Weight given to the newest latency sample in the moving average
+const EWMA_ALPHA: f64 = 0.25;
+/// Timeouts are set to this multiple of the average observed latency,
+/// so that ordinary jitter does not trip them
+const LATENCY_MULTIPLIER: f64 = 3.0;
+
+/// A coordinator gather phase that has its own timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeoutPhase {
+    /// Gathering DkgPublicShares messages
+    DkgPublic,
+    /// Gathering DkgPrivateShares messages
+    DkgPrivate,
+    /// Gathering DkgEnd messages
+    DkgEnd,
+    /// Gathering nonces
+    Nonce,
+    /// Gathering signature shares
+    Sign,
+}
+
+impl TimeoutPhase {
+    /// The timed phase the coordinator is in, if any
+    pub fn from_coordinator_state(state: &CoordinatorState) -> Option<Self> {
+        match state {
+            CoordinatorState::DkgPublicGather => Some(Self::DkgPublic),
+            CoordinatorState::DkgPrivateGather => Some(Self::DkgPrivate),
+            CoordinatorState::DkgEndGather => Some(Self::DkgEnd),
+            CoordinatorState::NonceGather(_, _) => Some(Self::Nonce),
+            CoordinatorState::SigShareGather(_, _) => Some(Self::Sign),
+            CoordinatorState::Idle
+            | CoordinatorState::DkgPublicDistribute
+            | CoordinatorState::DkgPrivateDistribute
+            | CoordinatorState::DkgEndDistribute
+            | CoordinatorState::NonceRequest(_, _)
+            | CoordinatorState::SigShareRequest(_, _) => None,
+        }
+    }
+}
+
+/// Measures how long the coordinator spends in each gather phase of a round.
+/// Phases that end because the round failed (e.g. a timeout) are discarded, since
+/// they say nothing about how long the other signers actually needed.
+#[derive(Debug, Default)]
+pub struct RoundLatencyTracker {
+    /// The phase in progress, and when it started
+    current: Option<(TimeoutPhase, Instant)>,
+    /// Completed phase latencies not yet collected
+    observed: Vec<(TimeoutPhase, Duration)>,
+}
+
+impl RoundLatencyTracker {
+    /// Update the tracker with the coordinator's current state.
+    /// `round_failed` indicates that the round ended in error.
+    pub fn update(&mut self, state: &CoordinatorState, round_failed: bool) {
+        let phase = TimeoutPhase::from_coordinator_state(state);
+        if let Some((current_phase, started_at)) = self.current {
+            if phase == Some(current_phase) {
+                return;
+            }
+            if !round_failed {
+                self.observed.push((current_phase, started_at.elapsed()));
+            }
+        }
+        self.current = phase.map(|phase| (phase, Instant::now()));
+    }
+
+    /// Drop the phase in progress without recording it
+    pub fn reset(&mut self) {
+        self.current = None;
+    }
+
+    /// Take the latencies observed since the last call
+    pub fn take_observed(&mut self) -> Vec<(TimeoutPhase, Duration)> {
+        std::mem::take(&mut self.observed)
+    }
+}
+
+/// Computes coordinator timeouts from an exponentially-weighted moving average
+/// of the latencies observed in prior rounds, clamped to the configured bounds
+#[derive(Debug, Clone)]
+pub struct AdaptiveTimeouts {
+    /// The configured bounds
+    config: AdaptiveTimeoutConfig,
+    /// Moving average of the observed latency of each phase, in milliseconds
+    average_ms: HashMap<TimeoutPhase, f64>,
+}
+
+impl AdaptiveTimeouts {
+    /// Create a new estimator with no observations
+    pub fn new(config: AdaptiveTimeoutConfig) -> Self {
+        Self {
+            config,
+            average_ms: HashMap::new(),
+        }
+    }
+
+    /// Fold an observed latency for `phase` into its moving average
+    pub fn observe(&mut self, phase: TimeoutPhase, latency: Duration) {
+        let sample_ms = latency.as_secs_f64() * 1000.0;
+        self.average_ms
+            .entry(phase)
+            .and_modify(|average| {
+                *average = EWMA_ALPHA * sample_ms + (1.0 - EWMA_ALPHA) * *average;
+            })
+            .or_insert(sample_ms);
+    }
+
+    /// The timeout to use for `phase`.
+    /// Falls back to `configured` until a latency for `phase` has been observed.
+    pub fn timeout(&self, phase: TimeoutPhase, configured: Option<Duration>) -> Option<Duration> {
+        let Some(average_ms) = self.average_ms.get(&phase) else {
+            return configured;
+        };
+        let timeout = Duration::from_millis((average_ms * LATENCY_MULTIPLIER).ceil() as u64);
+        Some(timeout.clamp(self.config.min_timeout, self.config.max_timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> AdaptiveTimeoutConfig {
+        AdaptiveTimeoutConfig {
+            min_timeout: Duration::from_millis(100),
+            max_timeout: Duration::from_millis(10_000),
+        }
+    }
+
+    #[test]
+    fn adaptive_timeouts_follow_observed_latency() {
+        let mut timeouts = AdaptiveTimeouts::new(test_config());
+        let configured = Some(Duration::from_millis(5_000));
+
+        // No observations yet
+        assert_eq!(
+            timeouts.timeout(TimeoutPhase::Nonce, configured),
+            configured
+        );
+        assert_eq!(timeouts.timeout(TimeoutPhase::Nonce, None), None);
+
+        timeouts.observe(TimeoutPhase::Nonce, Duration::from_millis(1_000));
+        assert_eq!(
+            timeouts.timeout(TimeoutPhase::Nonce, configured),
+            Some(Duration::from_millis(3_000))
+        );
+        // Other phases are unaffected
+        assert_eq!(timeouts.timeout(TimeoutPhase::Sign, configured), configured);
+
+        // 0.25 * 2000 + 0.75 * 1000 = 1250
+        timeouts.observe(TimeoutPhase::Nonce, Duration::from_millis(2_000));
+        assert_eq!(
+            timeouts.timeout(TimeoutPhase::Nonce, None),
+            Some(Duration::from_millis(3_750))
+        );
+    }
+
+    #[test]
+    fn adaptive_timeouts_are_clamped() {
+        let mut timeouts = AdaptiveTimeouts::new(test_config());
+
+        timeouts.observe(TimeoutPhase::Sign, Duration::from_millis(1));
+        assert_eq!(
+            timeouts.timeout(TimeoutPhase::Sign, None),
+            Some(Duration::from_millis(100))
+        );
+
+        timeouts.observe(TimeoutPhase::DkgEnd, Duration::from_secs(60));
+        assert_eq!(
+            timeouts.timeout(TimeoutPhase::DkgEnd, None),
+            Some(Duration::from_millis(10_000))
+        );
+    }
+
+    #[test]
+    fn round_latency_tracker_records_completed_phases() {
+        let mut tracker = RoundLatencyTracker::default();
+
+        tracker.update(&CoordinatorState::DkgPublicGather, false);
+        tracker.update(&CoordinatorState::DkgPublicGather, false);
+        tracker.update(&CoordinatorState::DkgPrivateGather, false);
+        tracker.update(&CoordinatorState::DkgEndGather, false);
+        tracker.update(&CoordinatorState::Idle, false);
+
+        let phases: Vec<_> = tracker
+            .take_observed()
+            .into_iter()
+            .map(|(phase, _)| phase)
+            .collect();
+        assert_eq!(
+            phases,
+            vec![
+                TimeoutPhase::DkgPublic,
+                TimeoutPhase::DkgPrivate,
+                TimeoutPhase::DkgEnd
+            ]
+        );
+        assert!(tracker.take_observed().is_empty());
+
+        // A phase that ends with a failed round is not recorded
+        tracker.update(&CoordinatorState::DkgPublicGather, false);
+        tracker.update(&CoordinatorState::Idle, true);
+        assert!(tracker.take_observed().is_empty());
+
+        // Nor is one that is reset
+        tracker.update(&CoordinatorState::DkgPublicGather, false);
+        tracker.reset();
+        tracker.update(&CoordinatorState::Idle, false);
+        assert!(tracker.take_observed().is_empty());
+    }
+}
@@ -17,6 +17,7 @@
 use std::time::Instant;
 
 use blockstack_lib::chainstate::burn::ConsensusHashExtensions;
+use hashbrown::HashMap;
 use slog::slog_debug;
 use stacks_common::debug;
 use stacks_common::types::chainstate::ConsensusHash;
@@ -24,6 +25,8 @@ use stacks_common::util::hash::Sha256Sum;
 use wsts::curve::ecdsa;
 use wsts::state_machine::PublicKeys;
 
+use crate::config::CoordinatorSelectionMode;
+
 /// TODO: test this value and adjust as necessary. Maybe make configurable?
 pub const COORDINATOR_OPERATION_TIMEOUT_SECS: u64 = 300;
 
@@ -45,13 +48,40 @@ pub struct CoordinatorSelector {
     tenure_start: Instant,
     /// The public keys of the coordinators
     public_keys: PublicKeys,
+    /// The signing weight of each signer id
+    signer_weights: HashMap<u32, u32>,
+    /// How the potential coordinators are ordered
+    selection_mode: CoordinatorSelectionMode,
 }
 
 impl From<PublicKeys> for CoordinatorSelector {
-    /// Create a new Coordinator selector from the given list of public keys
+    /// Create a new Coordinator selector from the given list of public keys, treating all
+    /// signers uniformly
     fn from(public_keys: PublicKeys) -> Self {
-        let coordinator_ids =
-            Self::calculate_coordinator_ids(&public_keys, &ConsensusHash::empty());
+        Self::new(
+            public_keys,
+            HashMap::new(),
+            CoordinatorSelectionMode::Uniform,
+        )
+    }
+}
+
+/// Whether or not to rotate to new coordinators in `update_coordinator`
+const ROTATE_COORDINATORS: bool = false;
+
+impl CoordinatorSelector {
+    /// Create a new Coordinator selector from the given list of public keys and signing weights
+    pub fn new(
+        public_keys: PublicKeys,
+        signer_weights: HashMap<u32, u32>,
+        selection_mode: CoordinatorSelectionMode,
+    ) -> Self {
+        let coordinator_ids = Self::calculate_ordered_ids(
+            &public_keys,
+            &signer_weights,
+            selection_mode,
+            &ConsensusHash::empty(),
+        );
         let coordinator_id = *coordinator_ids
             .first()
             .expect("FATAL: No registered signers");
@@ -65,14 +95,11 @@ impl From<PublicKeys> for CoordinatorSelector {
             last_message_time,
             tenure_start,
             public_keys,
+            signer_weights,
+            selection_mode,
         }
     }
-}
-
-/// Whether or not to rotate to new coordinators in `update_coordinator`
-const ROTATE_COORDINATORS: bool = false;
 
-impl CoordinatorSelector {
     /// Update the coordinator id
     fn update_coordinator(&mut self, new_coordinator_ids: Vec<u32>) {
         self.last_message_time = None;
@@ -107,8 +134,12 @@ impl CoordinatorSelector {
     /// Check the coordinator timeouts and update the selected coordinator accordingly
     /// Returns the resulting coordinator ID. (Note: it may be unchanged)
     pub fn refresh_coordinator(&mut self, pox_consensus_hash: &ConsensusHash) -> u32 {
-        let new_coordinator_ids =
-            Self::calculate_coordinator_ids(&self.public_keys, pox_consensus_hash);
+        let new_coordinator_ids = Self::calculate_ordered_ids(
+            &self.public_keys,
+            &self.signer_weights,
+            self.selection_mode,
+            pox_consensus_hash,
+        );
         if let Some(time) = self.last_message_time {
             if time.elapsed().as_secs() > COORDINATOR_OPERATION_TIMEOUT_SECS {
                 // We have not received a message in a while from this coordinator.
@@ -162,6 +193,72 @@ impl CoordinatorSelector {
         // Return only the ids
         selection_ids.iter().map(|(id, _)| *id).collect()
     }
+
+    /// Calculate the ordered list of coordinator ids, drawing each position at random in
+    /// proportion to the signing weights of the signers not yet drawn. Signers without any
+    /// signing weight come last.
+    pub fn calculate_weighted_coordinator_ids(
+        public_keys: &PublicKeys,
+        signer_weights: &HashMap<u32, u32>,
+        pox_consensus_hash: &ConsensusHash,
+    ) -> Vec<u32> {
+        // Draw from the uniform order, so that signers without weight are still ordered by hash
+        let mut remaining: Vec<(u32, u64)> =
+            Self::calculate_coordinator_ids(public_keys, pox_consensus_hash)
+                .into_iter()
+                .map(|id| (id, u64::from(*signer_weights.get(&id).unwrap_or(&0))))
+                .collect();
+        let mut coordinator_ids = Vec::with_capacity(remaining.len());
+        let mut round: u32 = 0;
+        loop {
+            let total_weight: u64 = remaining.iter().map(|(_, weight)| weight).sum();
+            if total_weight == 0 {
+                break;
+            }
+            // Integer-only arithmetic, so that every signer computes the same order
+            let mut buffer = Vec::with_capacity(pox_consensus_hash.as_bytes().len() + 4);
+            buffer.extend_from_slice(pox_consensus_hash.as_bytes());
+            buffer.extend_from_slice(&round.to_be_bytes());
+            let digest = Sha256Sum::from_data(&buffer);
+            let mut draw_bytes = [0u8; 8];
+            draw_bytes.copy_from_slice(&digest.as_bytes()[0..8]);
+            let mut draw = u64::from_be_bytes(draw_bytes) % total_weight;
+
+            let index = remaining
+                .iter()
+                .position(|(_, weight)| {
+                    if draw < *weight {
+                        return true;
+                    }
+                    draw -= weight;
+                    false
+                })
+                .expect("FATAL: coordinator draw exceeds the total signing weight");
+            coordinator_ids.push(remaining.remove(index).0);
+            round = round.saturating_add(1);
+        }
+        coordinator_ids.extend(remaining.into_iter().map(|(id, _)| id));
+        coordinator_ids
+    }
+
+    /// Calculate the ordered list of coordinator ids according to the selection mode
+    fn calculate_ordered_ids(
+        public_keys: &PublicKeys,
+        signer_weights: &HashMap<u32, u32>,
+        selection_mode: CoordinatorSelectionMode,
+        pox_consensus_hash: &ConsensusHash,
+    ) -> Vec<u32> {
+        match selection_mode {
+            CoordinatorSelectionMode::Uniform => {
+                Self::calculate_coordinator_ids(public_keys, pox_consensus_hash)
+            }
+            CoordinatorSelectionMode::Weighted => Self::calculate_weighted_coordinator_ids(
+                public_keys,
+                signer_weights,
+                pox_consensus_hash,
+            ),
+        }
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -227,4 +324,78 @@ mod tests {
             .all(|ids| ids == &results_with_static_hash[0]);
         assert!(all_ids_same, "All coordinator IDs should be the same");
     }
+
+    #[test]
+    fn weighted_coordinator_ids_favor_heavier_signers() {
+        let config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
+        let public_keys = generate_signer_config(&config, 4, 4)
+            .signer_entries
+            .public_keys;
+        // signer 3 holds most of the weight, and signer 2 holds none
+        let signer_weights: HashMap<u32, u32> =
+            [(0, 10), (1, 10), (2, 0), (3, 80)].into_iter().collect();
+
+        let number_of_tests = 200;
+        let mut heaviest_first = 0;
+        for _ in 0..number_of_tests {
+            let hash = generate_random_consensus_hash();
+            let ids = CoordinatorSelector::calculate_weighted_coordinator_ids(
+                &public_keys,
+                &signer_weights,
+                &hash,
+            );
+            // every signer is still a potential coordinator, and the order is deterministic
+            let mut sorted_ids = ids.clone();
+            sorted_ids.sort();
+            assert_eq!(sorted_ids, vec![0, 1, 2, 3]);
+            assert_eq!(ids.last(), Some(&2));
+            assert_eq!(
+                ids,
+                CoordinatorSelector::calculate_weighted_coordinator_ids(
+                    &public_keys,
+                    &signer_weights,
+                    &hash,
+                )
+            );
+            if ids[0] == 3 {
+                heaviest_first += 1;
+            }
+        }
+        // signer 3 should coordinate first about 80% of the time
+        assert!(
+            heaviest_first > number_of_tests / 2,
+            "Heaviest signer coordinated first only {heaviest_first} times out of {number_of_tests}"
+        );
+    }
+
+    #[test]
+    fn coordinator_selector_uses_configured_mode() {
+        let config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
+        let public_keys = generate_signer_config(&config, 4, 4)
+            .signer_entries
+            .public_keys;
+        let signer_weights: HashMap<u32, u32> =
+            [(0, 1), (1, 0), (2, 0), (3, 0)].into_iter().collect();
+
+        // only signer 0 has any weight, so it always coordinates first when weighted
+        let mut selector = CoordinatorSelector::new(
+            public_keys.clone(),
+            signer_weights.clone(),
+            CoordinatorSelectionMode::Weighted,
+        );
+        assert_eq!(selector.get_coordinator().0, 0);
+        let hash = generate_random_consensus_hash();
+        assert_eq!(selector.refresh_coordinator(&hash), 0);
+
+        let selector = CoordinatorSelector::new(
+            public_keys.clone(),
+            signer_weights,
+            CoordinatorSelectionMode::Uniform,
+        );
+        assert_eq!(
+            selector.get_coordinator().0,
+            CoordinatorSelector::calculate_coordinator_ids(&public_keys, &ConsensusHash::empty())
+                [0]
+        );
+    }
 }
@@ -20,16 +20,23 @@ pub mod coordinator;
 pub mod signer;
 /// The state module for the signer
 pub mod signerdb;
+/// Garbage collection of abandoned DKG and signing rounds
+pub mod stale_rounds;
 
+use std::path::PathBuf;
 use std::sync::mpsc::{channel, Receiver, Sender};
 
 use libsigner::v1::messages::SignerMessage;
-use libsigner::SignerEventReceiver;
-use slog::slog_info;
-use stacks_common::info;
+use libsigner::{ListenerRebinder, RejectedEventResponses, SignerEventReceiver};
+use slog::{slog_info, slog_warn};
+use stacks_common::{info, warn};
 use wsts::state_machine::OperationResult;
 
 use crate::config::GlobalConfig;
+use crate::dkg_keys::DkgKeyRegistry;
+#[cfg(unix)]
+use crate::local_rpc::{OperatorRpcServer, OperatorServices};
+use crate::message_signing::MessageSigningRegistry;
 use crate::runloop::{RunLoop, RunLoopCommand};
 use crate::v1::signer::Signer;
 
@@ -56,12 +63,29 @@ impl From<GlobalConfig> for SpawnedSigner {
         info!("Starting signer with config: {}", config);
         let (cmd_send, cmd_recv) = channel();
         let (res_send, res_recv) = channel();
-        let ev = SignerEventReceiver::new(config.network.is_mainnet());
+        let mut ev = SignerEventReceiver::new(config.network.is_mainnet())
+            .with_worker_threads(config.event_worker_threads)
+            .with_retired_listener_idle(config.retired_listener_idle);
+        if config.ack_rejected_events {
+            ev = ev.with_rejected_event_responses(RejectedEventResponses::ACKNOWLEDGE_ALL);
+        }
         #[cfg(feature = "monitoring_prom")]
         {
             crate::monitoring::start_serving_monitoring_metrics(config.clone()).ok();
         }
+        let operator_rpc_socket = config.operator_rpc_socket.clone();
+        let operator_rpc_auth_token = config.operator_rpc_auth_token.clone();
         let runloop = RunLoop::new(config);
+        if let Some(socket) = operator_rpc_socket {
+            start_operator_rpc_server(
+                socket,
+                operator_rpc_auth_token.unwrap_or_default(),
+                runloop.message_signing.clone(),
+                runloop.dkg_keys.clone(),
+                ev.listener_rebinder(),
+                cmd_send.clone(),
+            );
+        }
         let mut signer: libsigner::Signer<
             RunLoopCommand,
             Vec<OperationResult>,
@@ -78,6 +102,39 @@ impl From<GlobalConfig> for SpawnedSigner {
     }
 }
 
+/// Serve the operator RPC. Failing to do so does not stop the signer.
+#[cfg(unix)]
+fn start_operator_rpc_server(
+    socket: PathBuf,
+    auth_token: String,
+    message_signing: MessageSigningRegistry,
+    dkg_keys: DkgKeyRegistry,
+    rebinder: ListenerRebinder,
+    cmd_send: Sender<RunLoopCommand>,
+) {
+    let services = OperatorServices {
+        message_signing,
+        dkg_keys,
+        rebinder,
+        cmd_send,
+    };
+    if let Err(e) = OperatorRpcServer::spawn(socket, auth_token, services) {
+        warn!("Failed to start the operator RPC server: {e:?}");
+    }
+}
+
+#[cfg(not(unix))]
+fn start_operator_rpc_server(
+    _socket: PathBuf,
+    _auth_token: String,
+    _message_signing: MessageSigningRegistry,
+    _dkg_keys: DkgKeyRegistry,
+    _rebinder: ListenerRebinder,
+    _cmd_send: Sender<RunLoopCommand>,
+) {
+    warn!("Not starting the operator RPC server: Unix sockets are not supported on this platform");
+}
+
 impl SpawnedSigner {
     /// Stop the signer thread and return the final state
     pub fn stop(self) -> Option<Vec<OperationResult>> {
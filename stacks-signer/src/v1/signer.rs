@@ -17,7 +17,7 @@ use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::path::PathBuf;
 use std::sync::mpsc::Sender;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use blockstack_lib::chainstate::burn::ConsensusHashExtensions;
 use blockstack_lib::chainstate::nakamoto::signer_set::NakamotoSigners;
@@ -30,13 +30,14 @@ use hashbrown::HashSet;
 use libsigner::v1::messages::{
     BlockRejection, BlockResponse, MessageSlotID, RejectCode, SignerMessage,
 };
-use libsigner::{BlockProposal, SignerEvent};
+use libsigner::{BlockProposal, SignerEvent, StackerDBChunkId};
 use rand_core::OsRng;
 use serde_derive::{Deserialize, Serialize};
 use slog::{slog_debug, slog_error, slog_info, slog_warn};
 use stacks_common::codec::{read_next, StacksMessageCodec};
-use stacks_common::types::chainstate::{ConsensusHash, StacksAddress};
+use stacks_common::types::chainstate::{ConsensusHash, StacksAddress, StacksPublicKey};
 use stacks_common::types::StacksEpochId;
+use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::hash::Sha512Trunc256Sum;
 use stacks_common::{debug, error, info, warn};
 use wsts::common::Signature;
@@ -48,16 +49,26 @@ use wsts::state_machine::coordinator::fire::Coordinator as FireCoordinator;
 use wsts::state_machine::coordinator::{
     Config as CoordinatorConfig, Coordinator, State as CoordinatorState,
 };
-use wsts::state_machine::signer::Signer as SignerStateMachine;
+use wsts::state_machine::signer::{Signer as SignerStateMachine, State as SignerRoundState};
 use wsts::state_machine::{OperationResult, SignError};
 use wsts::traits::Signer as _;
 use wsts::v2;
 
 use crate::client::{ClientError, SignerSlotID, StackerDB, StacksClient};
-use crate::config::SignerConfig;
-use crate::runloop::{RunLoopCommand, SignerCommand};
+use crate::config::{DkgVoteRetryConfig, MinerKeyPolicy, SignerConfig};
+use crate::divergence::ChainTip;
+use crate::dkg_keys::{DkgKeyRegistry, DkgKeys};
+use crate::escalation::{RoundFailure, RoundFailureTracker};
+use crate::message_signing::{ArbitraryMessage, MessageSigningRegistry};
+use crate::participation::{OperationType, Participation};
+use crate::receipts::{ReceiptNotifier, SignatureReceipt};
+use crate::runloop::{drop_expired_commands, RunLoopCommand, SignerCommand};
+use crate::secrets::zeroize_scalar;
+use crate::timeouts::{RoundLatencyTracker, TimeoutPhase};
 use crate::v1::coordinator::CoordinatorSelector;
-use crate::v1::signerdb::SignerDb;
+use crate::v1::signerdb::{DkgVoteRecord, DkgVoteStatus, ProcessedEventId, SignerDb};
+use crate::v1::stale_rounds::{RoundId, StaleRoundCollector};
+use crate::watchdog::EventWatch;
 use crate::Signer as SignerTrait;
 
 /// Additional Info about a proposed block
@@ -137,7 +148,7 @@ pub struct Signer {
     /// the state of the signer
     pub state: State,
     /// Received Commands that need to be processed
-    pub commands: VecDeque<SignerCommand>,
+    pub commands: VecDeque<RunLoopCommand>,
     /// The stackerdb client
     pub stackerdb: StackerDB,
     /// Whether the signer is a mainnet signer or not
@@ -159,16 +170,49 @@ pub struct Signer {
     /// If estimating the tx fee, the max tx fee in uSTX to use when the epoch is pre Nakamoto (Epoch 3.0)
     /// If None, will not cap the fee.
     pub max_tx_fee_ustx: Option<u64>,
+    /// When and how to rebroadcast aggregate key votes that do not confirm
+    pub dkg_vote_retry: DkgVoteRetryConfig,
+    /// When this signer last rebroadcast an aggregate key vote that did not confirm
+    pub last_dkg_vote_rebroadcast: Option<Instant>,
     /// The coordinator info for the signer
     pub coordinator_selector: CoordinatorSelector,
-    /// The approved key registered to the contract
+    /// The approved key registered to the contract, or the imported key if the contract has
+    /// not approved one
     pub approved_aggregate_public_key: Option<Point>,
+    /// The known-good aggregate key the operator imported, if any. Lost on restart.
+    pub imported_aggregate_public_key: Option<Point>,
     /// The current active miner's key (if we know it!)
     pub miner_key: Option<PublicKey>,
     /// Signer DB path
     pub db_path: PathBuf,
     /// SignerDB for state management
     pub signer_db: SignerDb,
+    /// The miner public key allow/deny lists
+    pub miner_key_policy: MinerKeyPolicy,
+    /// How long the coordinator has spent in each phase of its rounds
+    pub round_latencies: RoundLatencyTracker,
+    /// Delivers signature receipts to the configured webhook, if any
+    pub receipt_notifier: Option<ReceiptNotifier>,
+    /// The signers that have sent signature shares in the current signing round
+    pub signature_share_signers: HashSet<u32>,
+    /// The arbitrary messages the operator has asked this signer to sign
+    pub message_signing: MessageSigningRegistry,
+    /// Where this signer publishes its DKG key material for the operator to export
+    pub dkg_keys: DkgKeyRegistry,
+    /// The chain tips implied by the miners' block proposals received since they were last
+    /// taken
+    pub observed_tips: Vec<ChainTip>,
+    /// Expires abandoned DKG and signing rounds
+    pub stale_rounds: StaleRoundCollector,
+    /// Alerts the operator when too many rounds in a row fail
+    pub round_failures: RoundFailureTracker,
+    /// The event watchdog's watch on the event being processed, if any
+    pub event_watch: Option<EventWatch>,
+    /// The chunks, with their messages, that an event ran out of time to handle. They are
+    /// handled before the next event, and only then recorded as processed.
+    pub unfinished_chunks: Vec<(ProcessedEventId, SignerMessage)>,
+    /// Which kinds of operation this signer takes part in
+    pub participation: Participation,
 }
 
 impl std::fmt::Display for Signer {
@@ -203,6 +247,38 @@ impl SignerTrait<SignerMessage> for Signer {
         self.reward_cycle
     }
 
+    /// Take the coordinator phase latencies observed since the last call
+    fn take_round_latencies(&mut self) -> Vec<(TimeoutPhase, Duration)> {
+        self.round_latencies.take_observed()
+    }
+
+    /// Whether DKG has produced an approved aggregate key for this reward cycle
+    fn has_aggregate_key(&self) -> bool {
+        self.approved_aggregate_public_key.is_some()
+    }
+
+    /// Take the chain tips implied by the block proposals received since the last call
+    fn take_observed_tips(&mut self) -> Vec<ChainTip> {
+        std::mem::take(&mut self.observed_tips)
+    }
+
+    fn watch_event(&mut self, watch: Option<EventWatch>) {
+        self.event_watch = watch;
+    }
+
+    /// Claim the signer's StackerDB slots and persist its freshly loaded state, so that it
+    /// can write and restore its state as soon as its reward cycle begins
+    fn publish_registration(&mut self) {
+        match self.stackerdb.sync_slot_versions() {
+            Ok(synced) => debug!("{self}: Claimed StackerDB slots"; "slots_in_use" => synced),
+            Err(e) => warn!("{self}: Failed to claim StackerDB slots: {e:?}"),
+        }
+        match self.save_signer_state() {
+            Ok(()) => info!("{self}: Published signer state ahead of its reward cycle"),
+            Err(e) => warn!("{self}: Failed to publish signer state: {e}"),
+        }
+    }
+
     /// Process the event
     fn process_event(
         &mut self,
@@ -216,7 +292,7 @@ impl SignerTrait<SignerMessage> for Signer {
             // Block proposal events do have reward cycles, but each proposal has its own cycle,
             //  and the vec could be heterogenous, so, don't differentiate.
             Some(SignerEvent::MinerMessages(..))
-            | Some(SignerEvent::NewBurnBlock(_))
+            | Some(SignerEvent::NewBurnBlock { .. })
             | Some(SignerEvent::StatusCheck)
             | None => None,
             Some(SignerEvent::SignerMessages(msg_parity, ..)) => Some(u64::from(*msg_parity) % 2),
@@ -237,29 +313,97 @@ impl SignerTrait<SignerMessage> for Signer {
             }
         }
         self.refresh_coordinator();
+        if !self.unfinished_chunks.is_empty() {
+            let (event_ids, messages): (Vec<_>, Vec<_>) =
+                std::mem::take(&mut self.unfinished_chunks)
+                    .into_iter()
+                    .unzip();
+            debug!(
+                "{self}: Resuming {} messages left over from an earlier event",
+                messages.len()
+            );
+            self.handle_chunks(
+                stacks_client,
+                res.clone(),
+                event_ids,
+                messages,
+                current_reward_cycle,
+            );
+        }
         debug!("{self}: Processing event: {event:?}");
         match event {
             Some(SignerEvent::BlockValidationResponse(block_validate_response)) => {
                 debug!("{self}: Received a block proposal result from the stacks node...");
+                let signer_signature_hash = match block_validate_response {
+                    BlockValidateResponse::Ok(ok) => ok.signer_signature_hash,
+                    BlockValidateResponse::Reject(reject) => reject.signer_signature_hash,
+                };
+                let event_id = ProcessedEventId::BlockValidation(signer_signature_hash);
+                if self.is_event_processed(&event_id) {
+                    debug!("{self}: Already processed the validation response for block {signer_signature_hash}. Ignoring...");
+                    return;
+                }
                 self.handle_block_validate_response(
                     stacks_client,
                     block_validate_response,
                     res,
                     current_reward_cycle,
-                )
+                );
+                self.mark_events_processed(&[event_id]);
             }
             Some(SignerEvent::SignerMessages(signer_set, messages)) => {
                 if *signer_set != self.stackerdb.get_signer_set() {
                     debug!("{self}: Received a signer message for a reward cycle that does not belong to this signer. Ignoring...");
                     return;
                 }
+                let (event_ids, messages) = self.filter_processed_messages(messages);
                 debug!(
-                    "{self}: Received {} messages from the other signers...",
+                    "{self}: Received {} new messages from the other signers...",
                     messages.len()
                 );
-                self.handle_signer_messages(stacks_client, res, messages, current_reward_cycle);
+                if messages.is_empty() {
+                    return;
+                }
+                self.handle_chunks(
+                    stacks_client,
+                    res,
+                    event_ids,
+                    messages,
+                    current_reward_cycle,
+                );
             }
             Some(SignerEvent::MinerMessages(messages, miner_key)) => {
+                let decision = self.miner_key_policy.evaluate(miner_key);
+                crate::monitoring::increment_miner_key_decisions(decision.reason());
+                if !decision.is_allowed() {
+                    warn!(
+                        "{self}: Refusing to process {} messages from miner",
+                        messages.len();
+                        "miner_key" => miner_key.to_hex(),
+                        "reason" => decision.reason(),
+                    );
+                    // Let the miner know that its proposals will not be signed
+                    let detail = format!(
+                        "(miner key {} is {})",
+                        miner_key.to_hex(),
+                        decision.reason()
+                    );
+                    for block_proposal in Self::proposed_blocks(messages) {
+                        if block_proposal.reward_cycle != self.reward_cycle {
+                            continue;
+                        }
+                        self.broadcast_block_rejection(BlockRejection::new_with_detail(
+                            block_proposal.block.header.signer_signature_hash(),
+                            RejectCode::MinerRefused,
+                            &detail,
+                        ));
+                    }
+                    return;
+                }
+                // The node may be too far behind to validate these blocks, so observe the tips
+                // they imply as soon as they arrive
+                self.observed_tips
+                    .extend(Self::miner_proposed_tips(messages, miner_key));
                 let miner_key = PublicKey::try_from(miner_key.to_bytes_compressed().as_slice())
                     .expect("FATAL: could not convert from StacksPublicKey to PublicKey");
                 self.miner_key = Some(miner_key);
@@ -268,18 +412,29 @@ impl SignerTrait<SignerMessage> for Signer {
                     debug!("{self}: Received a proposed block, but this signer's reward cycle is not the current one ({current_reward_cycle}). Ignoring...");
                     return;
                 }
+                let (event_ids, messages) = self.filter_processed_messages(messages);
                 debug!(
-                    "{self}: Received {} messages from the miner",
+                    "{self}: Received {} new messages from the miner",
                     messages.len();
                     "miner_key" => ?miner_key,
                 );
-                self.handle_signer_messages(stacks_client, res, messages, current_reward_cycle);
+                if messages.is_empty() {
+                    return;
+                }
+                self.handle_chunks(
+                    stacks_client,
+                    res,
+                    event_ids,
+                    messages,
+                    current_reward_cycle,
+                );
             }
             Some(SignerEvent::StatusCheck) => {
                 debug!("{self}: Received a status check event.")
             }
-            Some(SignerEvent::NewBurnBlock(height)) => {
-                debug!("{self}: Receved a new burn block event for block height {height}")
+            Some(SignerEvent::NewBurnBlock { burn_height, .. }) => {
+                debug!("{self}: Receved a new burn block event for block height {burn_height}");
+                self.collect_stale_rounds(*burn_height);
             }
             None => {
                 // No event. Do nothing.
@@ -300,14 +455,27 @@ impl SignerTrait<SignerMessage> for Signer {
                 warn!(
                     "{self}: not registered for reward cycle {reward_cycle}. Ignoring command: {command:?}"
                 );
+            } else if let Some(operation) = command
+                .command
+                .operation_type()
+                .filter(|operation| !self.participation.allows(*operation))
+            {
+                warn!(
+                    "{self}: not taking part in {operation} operations this reward cycle. Ignoring command: {command:?}"
+                );
+            } else if matches!(command.command, SignerCommand::ImportAggregateKey { .. }) {
+                // Importing a key does not involve the other signers, so it need not wait
+                // for the coordinator or for the current operation to finish
+                self.execute_command(stacks_client, &command.command);
             } else {
                 info!(
                     "{self}: Queuing an external runloop command ({:?}): {command:?}",
                     self.state_machine.public_keys.signers.get(&self.signer_id)
                 );
-                self.commands.push_back(command.command);
+                self.commands.push_back(command);
             }
         }
+        drop_expired_commands(&mut self.commands, current_reward_cycle);
         self.process_next_command(stacks_client, current_reward_cycle);
     }
 }
@@ -321,17 +489,28 @@ impl Signer {
                 warn!("{self}: Cannot process commands until state is restored. Waiting...");
             }
             State::Idle => {
-                let Some(command) = self.commands.front() else {
+                let Some(command) = self.commands.front().map(|command| &command.command) else {
                     debug!("{self}: Nothing to process. Waiting for command...");
                     return;
                 };
-                let coordinator_id = if matches!(command, SignerCommand::Dkg) {
-                    // We cannot execute a DKG command if we are not the coordinator
+                let coordinator_id = if matches!(
+                    command,
+                    SignerCommand::Dkg | SignerCommand::SignMessage { .. }
+                ) {
+                    // We cannot execute a DKG command if we are not the coordinator.
+                    // Arbitrary messages are not signed on behalf of a miner, so the signers
+                    // coordinate those rounds among themselves too.
                     Some(self.get_coordinator_dkg().0)
                 } else {
                     self.get_coordinator_sign(current_reward_cycle).0
                 };
                 if coordinator_id != Some(self.signer_id) {
+                    if matches!(command, SignerCommand::SignMessage { .. }) {
+                        // The message is authorized, so we will take part in the coordinator's round
+                        debug!("{self}: Coordinator is {coordinator_id:?}. Will take part in its signing round for the requested message.");
+                        self.commands.pop_front();
+                        return;
+                    }
                     debug!(
                                 "{self}: Coordinator is {coordinator_id:?}. Will not process any commands...",
                             );
@@ -341,7 +520,7 @@ impl Signer {
                     .commands
                     .pop_front()
                     .expect("BUG: Already asserted that the command queue was not empty");
-                self.execute_command(stacks_client, &command);
+                self.execute_command(stacks_client, &command.command);
             }
             State::OperationInProgress(op) => {
                 // We cannot execute the next command until the current one is finished...
@@ -418,6 +597,18 @@ impl Signer {
     }
 }
 
+impl Drop for Signer {
+    fn drop(&mut self) {
+        // The rest of the WSTS key material is private to the state machine
+        zeroize_scalar(&mut self.state_machine.network_private_key);
+        // The coordinator only hands out copies of its config, so its own copy of the key
+        // cannot be wiped.  Replace it with a coordinator holding no key, and wipe the copy.
+        let mut config = self.coordinator.get_config();
+        zeroize_scalar(&mut config.message_private_key);
+        self.coordinator = FireCoordinator::new(config);
+    }
+}
+
 impl From<SignerConfig> for Signer {
     fn from(signer_config: SignerConfig) -> Self {
         let mut stackerdb = StackerDB::from(&signer_config);
@@ -439,12 +630,18 @@ impl From<SignerConfig> for Signer {
             .get_dkg_threshold()
             .expect("FATAL: Too many key ids to fit in a u32");
 
+        let coordinator_selector = CoordinatorSelector::new(
+            signer_config.signer_entries.public_keys.clone(),
+            signer_config.signer_entries.signer_weights(),
+            signer_config.coordinator_selection,
+        );
+
         let coordinator_config = CoordinatorConfig {
             threshold,
             dkg_threshold,
             num_signers,
             num_keys,
-            message_private_key: signer_config.ecdsa_private_key,
+            message_private_key: signer_config.ecdsa_private_key.scalar(),
             dkg_public_timeout: signer_config.dkg_public_timeout,
             dkg_private_timeout: signer_config.dkg_private_timeout,
             dkg_end_timeout: signer_config.dkg_end_timeout,
@@ -455,8 +652,6 @@ impl From<SignerConfig> for Signer {
         };
 
         let coordinator = FireCoordinator::new(coordinator_config);
-        let coordinator_selector =
-            CoordinatorSelector::from(signer_config.signer_entries.public_keys.clone());
 
         debug!(
             "Reward cycle #{} Signer #{}: initial coordinator is signer {}",
@@ -466,6 +661,12 @@ impl From<SignerConfig> for Signer {
         );
         let signer_db =
             SignerDb::new(&signer_config.db_path).expect("Failed to connect to signer Db");
+        // Events processed by the signers of the previous reward cycle may still be redelivered
+        if let Err(e) =
+            signer_db.prune_processed_events(signer_config.reward_cycle.saturating_sub(1))
+        {
+            warn!("Failed to prune the processed event ledger: {e:?}");
+        }
 
         let mut state_machine = SignerStateMachine::new(
             threshold,
@@ -473,7 +674,7 @@ impl From<SignerConfig> for Signer {
             num_keys,
             signer_config.signer_id,
             signer_config.key_ids,
-            signer_config.ecdsa_private_key,
+            signer_config.ecdsa_private_key.scalar(),
             signer_config.signer_entries.public_keys,
         );
 
@@ -491,6 +692,21 @@ impl From<SignerConfig> for Signer {
             state_machine.signer = state;
         };
 
+        let excluded: Vec<_> = signer_config
+            .participation
+            .excluded()
+            .iter()
+            .map(OperationType::as_str)
+            .collect();
+        if !excluded.is_empty() {
+            info!(
+                "Reward cycle #{} Signer #{}: not taking part in {} operations",
+                signer_config.reward_cycle,
+                signer_config.signer_id,
+                excluded.join(", ")
+            );
+        }
+
         Self {
             coordinator,
             state_machine,
@@ -510,11 +726,32 @@ impl From<SignerConfig> for Signer {
             reward_cycle: signer_config.reward_cycle,
             tx_fee_ustx: signer_config.tx_fee_ustx,
             max_tx_fee_ustx: signer_config.max_tx_fee_ustx,
+            dkg_vote_retry: signer_config.dkg_vote_retry,
+            last_dkg_vote_rebroadcast: None,
             coordinator_selector,
             approved_aggregate_public_key: None,
+            imported_aggregate_public_key: None,
             miner_key: None,
             db_path: signer_config.db_path,
             signer_db,
+            miner_key_policy: signer_config.miner_key_policy,
+            round_latencies: RoundLatencyTracker::default(),
+            receipt_notifier: signer_config
+                .signature_receipt_webhook
+                .map(ReceiptNotifier::new),
+            signature_share_signers: HashSet::new(),
+            message_signing: signer_config.message_signing,
+            dkg_keys: signer_config.dkg_keys,
+            observed_tips: vec![],
+            stale_rounds: StaleRoundCollector::new(signer_config.stale_round_max_age),
+            round_failures: RoundFailureTracker::new(
+                &signer_config.escalation,
+                signer_config.signer_id,
+                signer_config.reward_cycle,
+            ),
+            event_watch: None,
+            unfinished_chunks: vec![],
+            participation: signer_config.participation,
         }
     }
 }
@@ -537,15 +774,88 @@ impl Signer {
             );
             self.coordinator.state = CoordinatorState::Idle;
             self.state = State::Idle;
+            self.round_latencies.reset();
+            self.signature_share_signers.clear();
         }
     }
 
     /// Finish an operation and update the coordinator selector accordingly
     fn finish_operation(&mut self) {
         self.state = State::Idle;
+        self.signature_share_signers.clear();
         self.coordinator_selector.last_message_time = None;
     }
 
+    /// Expire the rounds that have seen no packets for too many burn blocks, abandoning the
+    /// coordinator's round if it is one of them
+    fn collect_stale_rounds(&mut self, burn_height: u64) {
+        let collected = self.stale_rounds.collect(burn_height);
+        if collected.is_empty() {
+            return;
+        }
+        for round in collected.iter() {
+            crate::monitoring::increment_stale_rounds_collected(round.kind());
+        }
+        debug!("{self}: Expired stale rounds";
+            "burn_height" => burn_height,
+            "rounds" => ?collected,
+            "live_rounds" => self.stale_rounds.num_live_rounds(),
+        );
+        for round in collected.iter() {
+            self.release_signer_round(round);
+        }
+        let current_round = match self.state {
+            State::OperationInProgress(Operation::Dkg) => {
+                Some(RoundId::Dkg(self.coordinator.current_dkg_id))
+            }
+            State::OperationInProgress(Operation::Sign) => Some(RoundId::Sign {
+                dkg_id: self.coordinator.current_dkg_id,
+                sign_id: self.coordinator.current_sign_id,
+            }),
+            State::Uninitialized | State::Idle => None,
+        };
+        if let Some(round) = current_round.filter(|round| collected.contains(round)) {
+            info!("{self}: Abandoning stale round"; "round" => ?round, "burn_height" => burn_height);
+            if matches!(round, RoundId::Sign { .. }) {
+                if let Some(block) = self.signing_round_block() {
+                    self.broadcast_block_rejection(BlockRejection::new_with_detail(
+                        block.header.signer_signature_hash(),
+                        RejectCode::RoundExpired,
+                        &format!("(no progress since burn block {burn_height})"),
+                    ));
+                }
+            }
+            let coordinator_id = self.coordinator_selector.get_coordinator().0;
+            self.round_failures
+                .record_failure(round.kind(), RoundFailure::stale(coordinator_id));
+            self.coordinator.state = CoordinatorState::Idle;
+            self.round_latencies.reset();
+            self.finish_operation();
+        }
+    }
+
+    /// Free the shares and nonces the wsts signer holds for `round`, if it is the round the
+    /// signer is taking part in.  The signer's own key material is kept.
+    fn release_signer_round(&mut self, round: &RoundId) {
+        let signer = &mut self.state_machine;
+        match *round {
+            RoundId::Dkg(dkg_id) if signer.dkg_id == dkg_id => {
+                if signer.state != SignerRoundState::Idle || !signer.dkg_public_shares.is_empty() {
+                    signer.reset(dkg_id, &mut OsRng);
+                }
+            }
+            RoundId::Sign { dkg_id, sign_id }
+                if signer.dkg_id == dkg_id && signer.sign_id == sign_id =>
+            {
+                signer.public_nonces.clear();
+                if signer.state == SignerRoundState::SignGather {
+                    signer.state = SignerRoundState::Idle;
+                }
+            }
+            _ => {}
+        }
+    }
+
     /// Update operation
     fn update_operation(&mut self, operation: Operation) {
         self.state = State::OperationInProgress(operation);
@@ -579,6 +889,7 @@ impl Signer {
                     Ok(msg) => {
                         let ack = self.stackerdb.send_message_with_retry(msg.into());
                         debug!("{self}: ACK: {ack:?}",);
+                        self.round_latencies.update(&self.coordinator.state, false);
                         self.update_operation(Operation::Dkg);
                     }
                     Err(e) => {
@@ -621,6 +932,7 @@ impl Signer {
                     Ok(msg) => {
                         let ack = self.stackerdb.send_message_with_retry(msg.into());
                         debug!("{self}: ACK: {ack:?}",);
+                        self.round_latencies.update(&self.coordinator.state, false);
                         block_info.signed_over = true;
                         self.signer_db
                             .insert_block(&block_info)
@@ -636,6 +948,51 @@ impl Signer {
                 }
                 self.update_operation(Operation::Sign);
             }
+            SignerCommand::SignMessage { message } => {
+                crate::monitoring::increment_commands_processed("sign_message");
+                if self.approved_aggregate_public_key.is_none() {
+                    debug!("{self}: Cannot sign a message without an approved aggregate public key. Ignore it.");
+                    return;
+                }
+                info!("{self}: Signing requested message";
+                    "reward_cycle" => message.reward_cycle,
+                    "digest" => %message.digest,
+                );
+                match self
+                    .coordinator
+                    .start_signing_round(&message.encode(), false, None)
+                {
+                    Ok(msg) => {
+                        let ack = self.stackerdb.send_message_with_retry(msg.into());
+                        debug!("{self}: ACK: {ack:?}",);
+                        self.round_latencies.update(&self.coordinator.state, false);
+                        self.update_operation(Operation::Sign);
+                    }
+                    Err(e) => {
+                        error!("{self}: Failed to start signing message: {e:?}",);
+                    }
+                }
+            }
+            SignerCommand::ImportAggregateKey { aggregate_key } => {
+                crate::monitoring::increment_commands_processed("import_aggregate_key");
+                let computed_key = self
+                    .coordinator
+                    .party_polynomials
+                    .values()
+                    .fold(Point::default(), |sum, commitment| sum + commitment.poly[0]);
+                if !self.coordinator.party_polynomials.is_empty() && computed_key != *aggregate_key
+                {
+                    warn!("{self}: Imported aggregate key does not match this signer's DKG public shares. It will not be able to contribute to signatures under it.";
+                        "aggregate_key" => %aggregate_key,
+                        "computed_key" => %computed_key,
+                    );
+                }
+                info!("{self}: Importing aggregate key"; "aggregate_key" => %aggregate_key);
+                self.imported_aggregate_public_key = Some(*aggregate_key);
+                if let Err(e) = self.update_approved_aggregate_key(stacks_client) {
+                    warn!("{self}: Failed to check the imported aggregate key against the voting contract: {e:?}");
+                }
+            }
         }
     }
 
@@ -732,33 +1089,154 @@ impl Signer {
             .unwrap_or_else(|_| panic!("{self}: Failed to insert block in DB"));
     }
 
-    /// Handle signer messages submitted to signers stackerdb
+    /// Has this signer already processed the given event (possibly before a restart)?
+    /// If the ledger can't be read, the event is treated as new.
+    fn is_event_processed(&self, event_id: &ProcessedEventId) -> bool {
+        self.signer_db
+            .is_event_processed(self.reward_cycle, event_id)
+            .unwrap_or_else(|e| {
+                warn!("{self}: Failed to look up processed event {event_id}: {e:?}");
+                false
+            })
+    }
+
+    /// Record the given events as processed, so they are skipped if delivered again
+    fn mark_events_processed(&self, event_ids: &[ProcessedEventId]) {
+        for event_id in event_ids {
+            if let Err(e) = self
+                .signer_db
+                .insert_processed_event(self.reward_cycle, event_id)
+            {
+                warn!("{self}: Failed to record processed event {event_id}: {e:?}");
+            }
+        }
+    }
+
+    /// Drop the messages whose chunks this signer has already processed.
+    /// Returns the identifiers of the remaining chunks, along with their messages.
+    fn filter_processed_messages(
+        &self,
+        messages: &[(StackerDBChunkId, SignerMessage)],
+    ) -> (Vec<ProcessedEventId>, Vec<SignerMessage>) {
+        messages
+            .iter()
+            .filter_map(|(chunk_id, message)| {
+                let event_id = ProcessedEventId::Chunk(chunk_id.clone());
+                if self.is_event_processed(&event_id) {
+                    debug!("{self}: Already processed chunk {chunk_id}. Ignoring...");
+                    return None;
+                }
+                if self.unfinished_chunks.iter().any(|(id, _)| id == &event_id) {
+                    debug!(
+                        "{self}: Chunk {chunk_id} is already waiting to be handled. Ignoring..."
+                    );
+                    return None;
+                }
+                Some((event_id, message.clone()))
+            })
+            .unzip()
+    }
+
+    /// Handle the messages of the given chunks, and record the chunks as processed.
+    /// If the event runs out of time, the chunks whose messages were not handled are kept in
+    /// `unfinished_chunks` instead, to be handled before the next event.
+    fn handle_chunks(
+        &mut self,
+        stacks_client: &StacksClient,
+        res: Sender<Vec<OperationResult>>,
+        mut event_ids: Vec<ProcessedEventId>,
+        mut messages: Vec<SignerMessage>,
+        current_reward_cycle: u64,
+    ) {
+        let handled =
+            self.handle_signer_messages(stacks_client, res, &messages, current_reward_cycle);
+        let unfinished_ids = event_ids.split_off(handled);
+        let unfinished_messages = messages.split_off(handled);
+        self.mark_events_processed(&event_ids);
+        if !unfinished_ids.is_empty() {
+            warn!(
+                "{self}: Ran out of time verifying packets. Deferring the last {} messages to the next event.",
+                unfinished_ids.len()
+            );
+            self.unfinished_chunks
+                .extend(unfinished_ids.into_iter().zip(unfinished_messages));
+        }
+    }
+
+    /// Handle signer messages submitted to signers stackerdb.
+    /// Stops verifying packets once the event has run out of time, and returns how many of
+    /// `messages` were handled; the rest were not looked at.
     fn handle_signer_messages(
         &mut self,
         stacks_client: &StacksClient,
         res: Sender<Vec<OperationResult>>,
         messages: &[SignerMessage],
         current_reward_cycle: u64,
-    ) {
-        let packets: Vec<Packet> = messages
-            .iter()
-            .filter_map(|msg| match msg {
+    ) -> usize {
+        let mut handled = messages.len();
+        let mut excluded = 0usize;
+        let mut packets: Vec<Packet> = Vec::with_capacity(messages.len());
+        for (index, msg) in messages.iter().enumerate() {
+            let packet = match msg {
                 SignerMessage::DkgResults { .. }
                 | SignerMessage::BlockResponse(_)
                 | SignerMessage::EncryptedSignerState(_)
-                | SignerMessage::Transactions(_) => None,
+                | SignerMessage::Transactions(_) => continue,
                 // TODO: if a signer tries to trigger DKG and we already have one set in the contract, ignore the request.
-                SignerMessage::Packet(packet) => {
-                    let coordinator_pubkey = if Self::is_dkg_message(&packet.msg) {
-                        self.get_coordinator_dkg().1
-                    } else {
-                        self.get_coordinator_sign(current_reward_cycle).1
-                    };
-                    self.verify_packet(stacks_client, packet.clone(), &coordinator_pubkey)
-                }
-            })
-            .collect();
+                SignerMessage::Packet(packet) => packet,
+            };
+            if self.event_expired() {
+                handled = index;
+                break;
+            }
+            if !self.participation.allows(Self::operation_type(&packet.msg)) {
+                excluded = excluded.saturating_add(1);
+                continue;
+            }
+            let round = RoundId::of(&packet.msg);
+            if self.stale_rounds.is_expired(&round) {
+                debug!("{self}: Dropping packet for expired round"; "round" => ?round);
+                crate::monitoring::increment_stale_round_packets_dropped();
+                continue;
+            }
+            let coordinator_pubkey = if Self::is_dkg_message(&packet.msg) {
+                self.get_coordinator_dkg().1
+            } else {
+                self.get_coordinator_sign(current_reward_cycle).1
+            };
+            let Some(packet) =
+                self.verify_packet(stacks_client, packet.clone(), &coordinator_pubkey)
+            else {
+                continue;
+            };
+            self.stale_rounds.admit(&packet.msg);
+            packets.push(packet);
+        }
+        if excluded > 0 {
+            debug!(
+                "{self}: Dropped {excluded} packets for operations this signer does not take part in";
+                "participation" => ?self.participation
+            );
+        }
         self.handle_packets(stacks_client, res, &packets, current_reward_cycle);
+        handled
+    }
+
+    /// Whether the event being processed has run over the event watchdog's timeout
+    fn event_expired(&self) -> bool {
+        self.event_watch
+            .as_ref()
+            .map(EventWatch::expired)
+            .unwrap_or(false)
+    }
+
+    /// The kind of operation a round's message belongs to
+    fn operation_type(msg: &Message) -> OperationType {
+        if Self::is_dkg_message(msg) {
+            OperationType::Dkg
+        } else {
+            OperationType::Signing
+        }
     }
 
     /// Helper function for determining if the provided message is a DKG specific message
@@ -786,6 +1264,11 @@ impl Signer {
         if let Ok(packets_len) = packets.len().try_into() {
             crate::monitoring::increment_inbound_packets(packets_len);
         }
+        for packet in packets {
+            if let Message::SignatureShareResponse(response) = &packet.msg {
+                self.signature_share_signers.insert(response.signer_id);
+            }
+        }
         let signer_outbound_messages = self
             .state_machine
             .process_inbound_messages(packets)
@@ -808,6 +1291,15 @@ impl Signer {
             (vec![], vec![])
         };
 
+        let round_failed = operation_results.iter().any(|result| {
+            matches!(
+                result,
+                OperationResult::SignError(_) | OperationResult::DkgError(_)
+            )
+        });
+        self.round_latencies
+            .update(&self.coordinator.state, round_failed);
+
         if !operation_results.is_empty() {
             // We have finished a signing or DKG round, either successfully or due to error.
             // Regardless of the why, update our state to Idle as we should not expect the operation to continue.
@@ -852,6 +1344,9 @@ impl Signer {
     /// If the request is for a block it has already agreed to sign, it will overwrite the message with the agreed upon value
     /// Returns whether the request is valid or not.
     fn validate_signature_share_request(&self, request: &mut SignatureShareRequest) -> bool {
+        if let Some(message) = ArbitraryMessage::decode(&request.message) {
+            return self.validate_arbitrary_message(&message);
+        }
         let Some(block_vote): Option<NakamotoBlockVote> = read_next(&mut &request.message[..]).ok()
         else {
             // We currently reject anything that is not a block vote
@@ -899,6 +1394,26 @@ impl Signer {
         }
     }
 
+    /// Whether this signer may take part in a signing round over an arbitrary message: only
+    /// if the operator asked it to sign the message, with this reward cycle's signer set
+    fn validate_arbitrary_message(&self, message: &ArbitraryMessage) -> bool {
+        if message.reward_cycle != self.reward_cycle {
+            warn!(
+                "{self}: Received a request to sign a message for a different reward cycle. Reject it.";
+                "requested_reward_cycle" => message.reward_cycle,
+            );
+            return false;
+        }
+        if !self.message_signing.is_authorized(message) {
+            warn!(
+                "{self}: Received a request to sign a message the operator did not authorize. Reject it.";
+                "digest" => %message.digest,
+            );
+            return false;
+        }
+        true
+    }
+
     /// Validate a nonce request, updating its message appropriately.
     /// If the request is for a block, we will update the request message
     /// as either a hash indicating a vote no or the signature hash indicating a vote yes
@@ -921,6 +1436,14 @@ impl Signer {
                 "{self}: Received a nonce request for a different reward cycle. Reject it.";
                 "requested_reward_cycle" => block_proposal.reward_cycle,
             );
+            self.broadcast_block_rejection(BlockRejection::new_with_detail(
+                block_proposal.block.header.signer_signature_hash(),
+                RejectCode::RewardCycleMismatch(self.reward_cycle),
+                &format!(
+                    "(proposed for reward cycle {})",
+                    block_proposal.reward_cycle
+                ),
+            ));
             return None;
         }
         // TODO: could add a check to ignore an old burn block height if we know its oudated. Would require us to store the burn block height we last saw on the side.
@@ -1102,6 +1625,12 @@ impl Signer {
                     }
                 }
                 Message::NonceRequest(request) => {
+                    if let Some(message) = ArbitraryMessage::decode(&request.message) {
+                        if !self.validate_arbitrary_message(&message) {
+                            return None;
+                        }
+                        return Some(packet);
+                    }
                     let Some(updated_block_info) =
                         self.validate_nonce_request(stacks_client, request)
                     else {
@@ -1127,6 +1656,8 @@ impl Signer {
                 "{self}: Failed to verify wsts packet with {}: {packet:?}",
                 coordinator_public_key
             );
+            self.round_failures
+                .record_malformed_packet(RoundId::of(&packet.msg).kind());
             None
         }
     }
@@ -1144,7 +1675,9 @@ impl Signer {
                 OperationResult::Sign(signature) => {
                     crate::monitoring::increment_operation_results("sign");
                     debug!("{self}: Received signature result");
+                    self.round_failures.record_success("sign");
                     self.process_signature(signature);
+                    self.send_signature_receipt();
                 }
                 OperationResult::SignTaproot(_) => {
                     crate::monitoring::increment_operation_results("sign_taproot");
@@ -1152,16 +1685,23 @@ impl Signer {
                 }
                 OperationResult::Dkg(aggregate_key) => {
                     crate::monitoring::increment_operation_results("dkg");
+                    self.round_failures.record_success("dkg");
                     self.process_dkg(stacks_client, aggregate_key);
                 }
                 OperationResult::SignError(e) => {
                     crate::monitoring::increment_operation_results("sign_error");
                     warn!("{self}: Received a Sign error: {e:?}");
+                    let coordinator_id = self.coordinator_selector.get_coordinator().0;
+                    self.round_failures
+                        .record_failure("sign", RoundFailure::from_sign_error(coordinator_id, e));
                     self.process_sign_error(e);
                 }
                 OperationResult::DkgError(e) => {
                     crate::monitoring::increment_operation_results("dkg_error");
                     warn!("{self}: Received a DKG error: {e:?}");
+                    let coordinator_id = self.coordinator_selector.get_coordinator().0;
+                    self.round_failures
+                        .record_failure("dkg", RoundFailure::from_dkg_error(coordinator_id, e));
                     // TODO: process these errors and track malicious signers to report
                 }
             }
@@ -1175,6 +1715,7 @@ impl Signer {
             "{self}: Received DKG result. Broadcasting vote to the stacks node...";
             "dkg_public_key" => %dkg_public_key
         );
+        self.publish_dkg_keys();
         if let Err(e) = SignerMessage::serialize_dkg_result(
             &mut dkg_results_bytes,
             dkg_public_key,
@@ -1190,6 +1731,14 @@ impl Signer {
                        "error" => %e);
         }
 
+        if !self.participation.key_votes {
+            info!(
+                "{self}: Not voting for the DKG result, as this signer does not take part in key votes this reward cycle";
+                "dkg_public_key" => %dkg_public_key
+            );
+            return;
+        }
+
         // Get our current nonce from the stacks node and compare it against what we have sitting in the stackerdb instance
         let signer_address = stacks_client.get_signer_address();
         // Retreieve ALL account nonces as we may have transactions from other signers in our stackerdb slot that we care about
@@ -1209,7 +1758,7 @@ impl Signer {
         let epoch = stacks_client
             .get_node_epoch()
             .unwrap_or(StacksEpochId::Epoch24);
-        match self.build_dkg_vote(stacks_client, &epoch, next_nonce, *dkg_public_key) {
+        match self.build_dkg_vote(stacks_client, &epoch, next_nonce, *dkg_public_key, 0) {
             Ok(new_transaction) => {
                 if let Err(e) = self.broadcast_dkg_vote(
                     stacks_client,
@@ -1230,13 +1779,15 @@ impl Signer {
         }
     }
 
-    /// Build a signed DKG vote transaction
+    /// Build a signed DKG vote transaction.
+    /// Pre Epoch 3.0, its fee is at least `min_tx_fee`, unless that exceeds the max tx fee.
     fn build_dkg_vote(
         &mut self,
         stacks_client: &StacksClient,
         epoch: &StacksEpochId,
         nonce: u64,
         dkg_public_key: Point,
+        min_tx_fee: u64,
     ) -> Result<StacksTransaction, ClientError> {
         let mut unsigned_tx = stacks_client.build_unsigned_vote_for_aggregate_public_key(
             self.stackerdb.get_signer_slot_id().0,
@@ -1255,9 +1806,9 @@ impl Signer {
                         e
                     })
                     .unwrap_or(self.tx_fee_ustx);
-                std::cmp::min(estimated_fee, max_fee)
+                std::cmp::min(estimated_fee.max(min_tx_fee), max_fee)
             } else {
-                self.tx_fee_ustx
+                self.tx_fee_ustx.max(min_tx_fee)
             };
             debug!("{self}: Using a fee of {fee} uSTX for DKG vote transaction.");
             fee
@@ -1312,19 +1863,221 @@ impl Signer {
             return Ok(());
         }
         // For all Pox-4 epochs onwards, broadcast the results also to stackerDB for other signers/miners to observe
+        let vote = self.new_dkg_vote_record(&new_transaction);
         signer_transactions.push(new_transaction);
         let signer_message = SignerMessage::Transactions(signer_transactions);
         self.stackerdb.send_message_with_retry(signer_message)?;
         crate::monitoring::increment_dkg_votes_submitted();
         info!("{self}: Broadcasted DKG vote transaction ({txid}) to stacker DB");
+        if let Some(vote) = vote {
+            self.record_dkg_vote(&vote);
+        }
         Ok(())
     }
 
+    /// Describe a DKG vote transaction about to be broadcast, for the chain of custody
+    fn new_dkg_vote_record(&self, transaction: &StacksTransaction) -> Option<DkgVoteRecord> {
+        let Some(params) = NakamotoSigners::parse_vote_for_aggregate_public_key(transaction) else {
+            warn!(
+                "{self}: Broadcasting a transaction that is not a DKG vote ({}). Not recording it.",
+                transaction.txid()
+            );
+            return None;
+        };
+        Some(DkgVoteRecord {
+            txid: transaction.txid(),
+            reward_cycle: params.reward_cycle,
+            voting_round: params.voting_round,
+            aggregate_key: params.aggregate_key.to_string(),
+            nonce: transaction.get_origin_nonce(),
+            tx_fee: transaction.get_tx_fee(),
+            broadcast_time: get_epoch_time_secs(),
+            status: DkgVoteStatus::Pending,
+        })
+    }
+
+    /// Record a broadcast DKG vote in the chain of custody. Any earlier broadcast of a vote
+    /// for the same round that is still pending is replaced by it.
+    fn record_dkg_vote(&self, vote: &DkgVoteRecord) {
+        match self
+            .signer_db
+            .get_dkg_votes_with_status(vote.reward_cycle, DkgVoteStatus::Pending)
+        {
+            Ok(pending_votes) => {
+                for old_vote in pending_votes.iter().filter(|old_vote| {
+                    old_vote.voting_round == vote.voting_round && old_vote.txid != vote.txid
+                }) {
+                    self.set_dkg_vote_status(old_vote, DkgVoteStatus::Replaced);
+                }
+            }
+            Err(e) => warn!("{self}: Failed to load pending DKG votes: {e:?}"),
+        }
+        if let Err(e) = self.signer_db.insert_dkg_vote(vote) {
+            warn!("{self}: Failed to record DKG vote ({}): {e:?}", vote.txid);
+        }
+    }
+
+    /// Update where a DKG vote stands in the chain of custody
+    fn set_dkg_vote_status(&self, vote: &DkgVoteRecord, status: DkgVoteStatus) {
+        debug!("{self}: Updating DKG vote status.";
+            "txid" => %vote.txid,
+            "voting_round" => vote.voting_round,
+            "old_status" => %vote.status,
+            "status" => %status
+        );
+        if let Err(e) = self.signer_db.update_dkg_vote_status(&vote.txid, status) {
+            warn!(
+                "{self}: Failed to update DKG vote ({}) status: {e:?}",
+                vote.txid
+            );
+        }
+    }
+
+    /// Check on the DKG votes this signer broadcast that have not been counted yet.
+    /// Votes that the voting contract counted are confirmed, and votes that are no longer needed
+    /// (because a key was approved or a later round started) are abandoned. A vote still needed
+    /// that has gone unconfirmed for too long is rebroadcast with a higher fee.
+    fn update_dkg_votes(&mut self, stacks_client: &StacksClient) {
+        let pending_votes = match self
+            .signer_db
+            .get_dkg_votes_with_status(self.reward_cycle, DkgVoteStatus::Pending)
+        {
+            Ok(pending_votes) => pending_votes,
+            Err(e) => {
+                warn!("{self}: Failed to load pending DKG votes: {e:?}");
+                return;
+            }
+        };
+        let signer_address = *stacks_client.get_signer_address();
+        for vote in pending_votes {
+            match stacks_client.get_vote_for_aggregate_public_key(
+                vote.voting_round,
+                self.reward_cycle,
+                signer_address,
+            ) {
+                Ok(Some(counted_key)) => {
+                    let status = if counted_key.to_string() == vote.aggregate_key {
+                        info!("{self}: DKG vote confirmed.";
+                            "txid" => %vote.txid,
+                            "voting_round" => vote.voting_round,
+                            "aggregate_key" => &vote.aggregate_key
+                        );
+                        DkgVoteStatus::Confirmed
+                    } else {
+                        DkgVoteStatus::Abandoned
+                    };
+                    self.set_dkg_vote_status(&vote, status);
+                    continue;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    warn!(
+                        "{self}: Failed to check whether DKG vote ({}) was counted: {e:?}",
+                        vote.txid
+                    );
+                    continue;
+                }
+            }
+            if self.approved_aggregate_public_key.is_some()
+                || vote.voting_round < self.coordinator.current_dkg_id
+            {
+                self.set_dkg_vote_status(&vote, DkgVoteStatus::Abandoned);
+                continue;
+            }
+            let Some(dkg_public_key) = self.coordinator.aggregate_public_key else {
+                continue;
+            };
+            if dkg_public_key.to_string() != vote.aggregate_key {
+                self.set_dkg_vote_status(&vote, DkgVoteStatus::Abandoned);
+                continue;
+            }
+            let confirm_timeout = self.dkg_vote_retry.confirm_timeout;
+            let pending_secs = get_epoch_time_secs().saturating_sub(vote.broadcast_time);
+            let rebroadcast_recently = self
+                .last_dkg_vote_rebroadcast
+                .map_or(false, |at| at.elapsed() < confirm_timeout);
+            if pending_secs < confirm_timeout.as_secs() || rebroadcast_recently {
+                continue;
+            }
+            if !self.participation.key_votes {
+                debug!("{self}: Not rebroadcasting DKG vote, as this signer does not take part in key votes this reward cycle";
+                    "txid" => %vote.txid
+                );
+                continue;
+            }
+            self.rebroadcast_dkg_vote(stacks_client, &vote, dkg_public_key);
+        }
+    }
+
+    /// Rebroadcast a DKG vote that has not confirmed in time, with a higher fee.
+    /// The vote's nonce is reused, so that the new transaction replaces it, unless another
+    /// transaction has already spent it.
+    fn rebroadcast_dkg_vote(
+        &mut self,
+        stacks_client: &StacksClient,
+        vote: &DkgVoteRecord,
+        dkg_public_key: Point,
+    ) {
+        self.last_dkg_vote_rebroadcast = Some(Instant::now());
+        let signer_address = *stacks_client.get_signer_address();
+        let account_nonces = self.get_account_nonces(stacks_client, &self.signer_addresses);
+        let account_nonce = account_nonces.get(&signer_address).copied().unwrap_or(0);
+        let nonce = vote.nonce.max(account_nonce);
+        // Drop the transaction being replaced from our slot
+        let signer_transactions: Vec<_> = self
+            .get_signer_transactions(&account_nonces)
+            .map_err(|e| {
+                error!("{self}: Unable to get signer transactions: {e:?}.");
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|tx| {
+                tx.txid() != vote.txid
+                    && !(tx.origin_address() == signer_address && tx.get_origin_nonce() >= nonce)
+            })
+            .collect();
+        let epoch = stacks_client
+            .get_node_epoch()
+            .unwrap_or(StacksEpochId::Epoch24);
+        let min_tx_fee = self.dkg_vote_retry.bumped_fee(vote.tx_fee);
+        let new_transaction =
+            match self.build_dkg_vote(stacks_client, &epoch, nonce, dkg_public_key, min_tx_fee) {
+                Ok(new_transaction) => new_transaction,
+                Err(e) => {
+                    warn!(
+                        "{self}: Failed to build DKG vote ({}) rebroadcast: {e:?}",
+                        vote.txid
+                    );
+                    return;
+                }
+            };
+        warn!("{self}: DKG vote has not confirmed in time. Rebroadcasting it.";
+            "txid" => %vote.txid,
+            "new_txid" => %new_transaction.txid(),
+            "voting_round" => vote.voting_round,
+            "tx_fee" => vote.tx_fee,
+            "new_tx_fee" => new_transaction.get_tx_fee(),
+            "nonce" => nonce
+        );
+        match self.broadcast_dkg_vote(stacks_client, epoch, signer_transactions, new_transaction) {
+            Ok(()) => crate::monitoring::increment_dkg_votes_rebroadcast(),
+            Err(e) => warn!(
+                "{self}: Failed to rebroadcast DKG vote ({}): {e:?}",
+                vote.txid
+            ),
+        }
+    }
+
     /// Process a signature from a signing round by deserializing the signature and
     /// broadcasting an appropriate Reject or Approval message to stackerdb
     fn process_signature(&mut self, signature: &Signature) {
         // Deserialize the signature result and broadcast an appropriate Reject or Approval message to stackerdb
         let message = self.coordinator.get_message();
+        if let Some(message) = ArbitraryMessage::decode(&message) {
+            info!("{self}: Signed requested message"; "digest" => %message.digest);
+            self.message_signing.complete(&message, signature.clone());
+            return;
+        }
         let Some(block_vote): Option<NakamotoBlockVote> = read_next(&mut &message[..]).ok() else {
             debug!("{self}: Received a signature result for a non-block. Nothing to broadcast.");
             return;
@@ -1350,38 +2103,91 @@ impl Signer {
         }
     }
 
-    /// Process a sign error from a signing round, broadcasting a rejection message to stackerdb accordingly
-    fn process_sign_error(&mut self, e: &SignError) {
+    /// Send a receipt for the signature just completed to the webhook, if one is configured and
+    /// this signer contributed a signature share to it
+    fn send_signature_receipt(&self) {
+        let Some(notifier) = &self.receipt_notifier else {
+            return;
+        };
+        if !self.signature_share_signers.contains(&self.signer_id) {
+            debug!("{self}: Did not contribute to the completed signature. Not sending a receipt.");
+            return;
+        }
+        notifier.notify(SignatureReceipt::new(
+            self.signer_id,
+            self.reward_cycle,
+            &self.coordinator.get_message(),
+            self.approved_aggregate_public_key.as_ref(),
+            self.signature_share_signers.iter().copied(),
+        ));
+    }
+
+    /// Get the block that the coordinator's current signing round is for, if it is for a block
+    fn signing_round_block(&self) -> Option<NakamotoBlock> {
         let message = self.coordinator.get_message();
         // We do not sign across blocks, but across their hashes. however, the first sign request is always across the block
         // so we must handle this case first
+        if let Ok(block) = read_next::<NakamotoBlock, _>(&mut &message[..]) {
+            return Some(block);
+        }
+        // This is not a block so maybe its across its hash
+        let Some(block_vote): Option<NakamotoBlockVote> = read_next(&mut &message[..]).ok() else {
+            // This is not a block vote either
+            debug!("{self}: The signing round is for a non-block.");
+            return None;
+        };
+        let Some(block_info) = self
+            .signer_db
+            .block_lookup(self.reward_cycle, &block_vote.signer_signature_hash)
+            .unwrap_or_else(|_| panic!("{self}: Failed to connect to signer DB"))
+        else {
+            debug!("{self}: The signing round is for a block we have not seen before.");
+            return None;
+        };
+        Some(block_info.block)
+    }
 
-        let block: NakamotoBlock = read_next(&mut &message[..]).ok().unwrap_or({
-            // This is not a block so maybe its across its hash
-            let Some(block_vote): Option<NakamotoBlockVote> = read_next(&mut &message[..]).ok()
-            else {
-                // This is not a block vote either. We cannot process this error
-                debug!(
-                    "{self}: Received a signature error for a non-block. Nothing to broadcast."
-                );
-                return;
-            };
-            let Some(block_info) = self
-                .signer_db
-                .block_lookup(self.reward_cycle, &block_vote.signer_signature_hash)
-                .unwrap_or_else(|_| panic!("{self}: Failed to connect to signer DB"))
-            else {
-                debug!(
-                    "{self}: Received a signature result for a block we have not seen before. Ignoring..."
-                );
-                return;
-            };
-            block_info.block
-        });
-        let block_rejection =
-            BlockRejection::new(block.header.signer_signature_hash(), RejectCode::from(e));
+    /// Get the block proposals carried by the nonce requests in the given messages
+    fn proposed_blocks(messages: &[(StackerDBChunkId, SignerMessage)]) -> Vec<BlockProposal> {
+        messages
+            .iter()
+            .filter_map(|(_, msg)| match msg {
+                SignerMessage::Packet(Packet {
+                    msg: Message::NonceRequest(nonce_request),
+                    ..
+                }) => {
+                    BlockProposal::consensus_deserialize(&mut nonce_request.message.as_slice()).ok()
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The chain tips implied by the block proposals in the given messages that were signed, as
+    /// the block's miner, by `miner_key`, the key that wrote them to the miners' StackerDB.  The
+    /// node only accepts chunks in that StackerDB from recent sortition winners, so nobody else
+    /// can make the signer believe the network is ahead of its node.
+    fn miner_proposed_tips(
+        messages: &[(StackerDBChunkId, SignerMessage)],
+        miner_key: &StacksPublicKey,
+    ) -> Vec<ChainTip> {
+        let miner_key = miner_key.to_bytes_compressed();
+        Self::proposed_blocks(messages)
+            .iter()
+            .filter(|block_proposal| {
+                block_proposal
+                    .block
+                    .header
+                    .recover_miner_pk()
+                    .map_or(false, |pk| pk.to_bytes_compressed() == miner_key)
+            })
+            .map(ChainTip::from)
+            .collect()
+    }
+
+    /// Broadcast a block rejection to the .signers contract for miners to observe
+    fn broadcast_block_rejection(&mut self, block_rejection: BlockRejection) {
         debug!("{self}: Broadcasting block rejection: {block_rejection:?}");
-        // Submit signature result to miners to observe
         if let Err(e) = self
             .stackerdb
             .send_message_with_retry(block_rejection.into())
@@ -1390,6 +2196,19 @@ impl Signer {
         }
     }
 
+    /// Process a sign error from a signing round, broadcasting a rejection message to stackerdb accordingly
+    fn process_sign_error(&mut self, e: &SignError) {
+        let Some(block) = self.signing_round_block() else {
+            // We cannot process this error
+            debug!("{self}: Received a signature error for a non-block. Nothing to broadcast.");
+            return;
+        };
+        let block_rejection =
+            BlockRejection::new(block.header.signer_signature_hash(), RejectCode::from(e));
+        // Submit signature result to miners to observe
+        self.broadcast_block_rejection(block_rejection);
+    }
+
     /// Persist signer state in both SignerDB and StackerDB
     fn save_signer_state(&mut self) -> Result<(), PersistenceError> {
         let rng = &mut OsRng;
@@ -1489,6 +2308,8 @@ impl Signer {
         if self.approved_aggregate_public_key.is_some() {
             return Ok(());
         }
+        // Follow up on our own votes that have not been counted yet
+        self.update_dkg_votes(stacks_client);
         // Check stackerdb for any missed DKG messages to catch up our state.
         self.read_dkg_stackerdb_messages(stacks_client, res, current_reward_cycle)?;
         // Check if we should still queue DKG
@@ -1501,9 +2322,14 @@ impl Signer {
         if self.approved_aggregate_public_key.is_some() {
             return Ok(());
         }
-        if self.commands.front() != Some(&SignerCommand::Dkg) {
+        if !self.participation.dkg {
+            debug!("{self} is the current coordinator, but does not take part in DKG this reward cycle. Not queuing DKG command...");
+            return Ok(());
+        }
+        if !self.is_dkg_queued() {
             info!("{self} is the current coordinator and must trigger DKG. Queuing DKG command...");
-            self.commands.push_front(SignerCommand::Dkg);
+            self.commands
+                .push_front(RunLoopCommand::new(SignerCommand::Dkg, self.reward_cycle));
         } else {
             debug!("{self}: DKG command already queued...");
         }
@@ -1516,8 +2342,19 @@ impl Signer {
         stacks_client: &StacksClient,
     ) -> Result<(), ClientError> {
         let old_dkg = self.approved_aggregate_public_key;
-        self.approved_aggregate_public_key =
-            stacks_client.get_approved_aggregate_key(self.reward_cycle)?;
+        let contract_key = stacks_client.get_approved_aggregate_key(self.reward_cycle)?;
+        if let (Some(contract_key), Some(imported_key)) =
+            (contract_key, self.imported_aggregate_public_key)
+        {
+            if contract_key != imported_key {
+                warn!("{self}: The voting contract approved a different aggregate key than the imported one. Dropping the imported key.";
+                    "approved_key" => %contract_key,
+                    "imported_key" => %imported_key,
+                );
+                self.imported_aggregate_public_key = None;
+            }
+        }
+        self.approved_aggregate_public_key = contract_key.or(self.imported_aggregate_public_key);
         if self.approved_aggregate_public_key.is_some() {
             // TODO: this will never work as is. We need to have stored our party shares on the side etc for this particular aggregate key.
             // Need to update state to store the necessary info, check against it to see if we have participated in the winning round and
@@ -1533,6 +2370,8 @@ impl Signer {
                     "{self}: updated DKG value from {old_dkg:?} to {:?}.",
                     self.approved_aggregate_public_key
                 );
+                // Settle our votes that were still pending
+                self.update_dkg_votes(stacks_client);
             }
             match self.state {
                 State::OperationInProgress(Operation::Dkg) => {
@@ -1551,15 +2390,33 @@ impl Signer {
                 }
             }
         }
+        self.publish_dkg_keys();
         Ok(())
     }
 
+    /// Make this signer's current DKG key material available to the DKG key RPC
+    fn publish_dkg_keys(&self) {
+        self.dkg_keys.publish(DkgKeys::new(
+            self.reward_cycle,
+            self.signer_id,
+            self.coordinator.aggregate_public_key.as_ref(),
+            self.approved_aggregate_public_key.as_ref(),
+            self.imported_aggregate_public_key.as_ref(),
+            self.coordinator.party_polynomials.iter(),
+        ));
+    }
+
+    /// Is DKG the next command in the queue
+    fn is_dkg_queued(&self) -> bool {
+        self.commands.front().map(|command| &command.command) == Some(&SignerCommand::Dkg)
+    }
+
     /// Should DKG be queued to the current signer's command queue
     /// This assumes that no key has been approved by the contract yet
     pub fn should_queue_dkg(&mut self, stacks_client: &StacksClient) -> Result<bool, ClientError> {
         if self.state != State::Idle
             || self.signer_id != self.get_coordinator_dkg().0
-            || self.commands.front() == Some(&SignerCommand::Dkg)
+            || self.is_dkg_queued()
         {
             // We are not the coordinator, we are in the middle of an operation, or we have already queued DKG. Do not attempt to queue DKG
             return Ok(false);
@@ -1755,7 +2612,268 @@ pub enum EncryptionError {
 
 #[cfg(test)]
 mod tests {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::sync::mpsc::channel;
+
+    use blockstack_lib::chainstate::nakamoto::NakamotoBlockHeader;
+    use blockstack_lib::chainstate::stacks::boot::MINERS_NAME;
+    use blockstack_lib::util_lib::boot::boot_code_id;
+    use libstackerdb::{StackerDBChunkAckData, StackerDBChunkData};
+    use stacks_common::types::chainstate::StacksPrivateKey;
+
     use super::*;
+    use crate::client::tests::generate_signer_config;
+    use crate::config::GlobalConfig;
+    use crate::divergence::{ChainTipMonitor, DivergenceConfig};
+    use crate::testing::{MockResponse, MockStacksNode};
+
+    fn block_proposal(burn_height: u64, chain_length: u64, reward_cycle: u64) -> BlockProposal {
+        let mut header = NakamotoBlockHeader::empty();
+        header.chain_length = chain_length;
+        BlockProposal {
+            block: NakamotoBlock {
+                header,
+                txs: vec![],
+            },
+            burn_height,
+            reward_cycle,
+        }
+    }
+
+    fn nonce_request(block_proposal: &BlockProposal) -> NonceRequest {
+        NonceRequest {
+            dkg_id: 0,
+            sign_id: 0,
+            sign_iter_id: 0,
+            message: block_proposal.serialize_to_vec(),
+            is_taproot: false,
+            merkle_root: None,
+        }
+    }
+
+    fn miner_messages(block_proposal: &BlockProposal) -> Vec<(StackerDBChunkId, SignerMessage)> {
+        let message = SignerMessage::Packet(Packet {
+            msg: Message::NonceRequest(nonce_request(block_proposal)),
+            sig: vec![],
+        });
+        let chunk_id = StackerDBChunkId {
+            contract_id: boot_code_id(MINERS_NAME, false),
+            slot_id: 0,
+            slot_version: 1,
+            data_hash: Sha512Trunc256Sum::from_data(&message.serialize_to_vec()),
+        };
+        vec![(chunk_id, message)]
+    }
+
+    #[test]
+    fn reassigned_miner_slot_is_not_mistaken_for_a_replay() {
+        let node = MockStacksNode::spawn().unwrap();
+        let mut config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
+        node.configure(&mut config);
+        let mut signer_config = generate_signer_config(&config, 5, 20);
+        // the processed event ledger keeps reward cycles as SQL integers, which a random u64
+        // may not fit in
+        signer_config.reward_cycle = 1;
+        let signer = Signer::from(signer_config);
+
+        // the first tenure's miner proposes a block in its slot
+        let first_proposal = miner_messages(&block_proposal(105, 51, 1));
+        let (event_ids, messages) = signer.filter_processed_messages(&first_proposal);
+        assert_eq!(messages.len(), 1);
+        signer.mark_events_processed(&event_ids);
+        assert!(signer
+            .filter_processed_messages(&first_proposal)
+            .1
+            .is_empty());
+
+        // the slot is then reconfigured for the next tenure's miner, which resets its version,
+        // so the new miner's proposal is written to the same slot at the same version
+        let second_proposal = miner_messages(&block_proposal(106, 52, 1));
+        assert_eq!(first_proposal[0].0.slot_id, second_proposal[0].0.slot_id);
+        assert_eq!(
+            first_proposal[0].0.slot_version,
+            second_proposal[0].0.slot_version
+        );
+        let (_, messages) = signer.filter_processed_messages(&second_proposal);
+        assert_eq!(messages.len(), 1);
+    }
+
+    /// The block rejections that the signer has written to its StackerDB slots on `node`
+    fn broadcast_rejections(node: &MockStacksNode) -> Vec<BlockRejection> {
+        node.requests()
+            .into_iter()
+            .filter(|request| request.method == "POST" && request.path.starts_with("/v2/stackerdb"))
+            .filter_map(|request| {
+                let chunk: StackerDBChunkData = serde_json::from_slice(&request.body).unwrap();
+                match SignerMessage::consensus_deserialize(&mut chunk.data.as_slice()).unwrap() {
+                    SignerMessage::BlockResponse(BlockResponse::Rejected(rejection)) => {
+                        Some(rejection)
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn refused_miners_proposals_are_rejected_unseen() {
+        let node = MockStacksNode::spawn().unwrap();
+        node.respond_with(
+            "POST",
+            "/v2/stackerdb",
+            MockResponse::ok_json(&StackerDBChunkAckData {
+                accepted: true,
+                reason: None,
+                metadata: None,
+                code: None,
+            }),
+        );
+        let mut config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
+        node.configure(&mut config);
+        let stacks_client = StacksClient::from(&config);
+
+        let allowed_miner = StacksPrivateKey::new();
+        let denied_miner = StacksPrivateKey::new();
+        let unlisted_miner = StacksPrivateKey::new();
+        let mut signer_config = generate_signer_config(&config, 5, 20);
+        signer_config.reward_cycle = 1;
+        signer_config.miner_key_policy = MinerKeyPolicy {
+            // the deny list takes precedence over the allow list
+            allowlist: Some(vec![
+                StacksPublicKey::from_private(&allowed_miner),
+                StacksPublicKey::from_private(&denied_miner),
+            ]),
+            denylist: vec![StacksPublicKey::from_private(&denied_miner)],
+        };
+        let mut signer = Signer::from(signer_config);
+        // DKG is done, so processing the event doesn't ask the node about it
+        signer.approved_aggregate_public_key = Some(Point::new());
+        let (res_send, _res_recv) = channel();
+
+        for (miner, num_rejections) in [(&denied_miner, 1), (&unlisted_miner, 2)] {
+            let mut proposal = block_proposal(105, 51, 1);
+            proposal.block.header.sign_miner(miner).unwrap();
+            // a proposal for the next reward cycle, which this signer does not sign
+            let mut next_cycle_proposal = block_proposal(106, 52, 2);
+            next_cycle_proposal.block.header.sign_miner(miner).unwrap();
+            let mut messages = miner_messages(&proposal);
+            messages.extend(miner_messages(&next_cycle_proposal));
+
+            let event = SignerEvent::MinerMessages(messages, StacksPublicKey::from_private(miner));
+            signer.process_event(&stacks_client, Some(&event), res_send.clone(), 1);
+
+            // the miner's proposals are neither trusted nor validated
+            assert!(signer.miner_key.is_none());
+            assert!(signer.observed_tips.is_empty());
+            let signer_signature_hash = proposal.block.header.signer_signature_hash();
+            assert!(signer
+                .signer_db
+                .block_lookup(1, &signer_signature_hash)
+                .unwrap()
+                .is_none());
+
+            // and the miner is told that its current cycle's proposal won't be signed
+            let rejections = broadcast_rejections(&node);
+            assert_eq!(rejections.len(), num_rejections);
+            let rejection = rejections.last().unwrap();
+            assert_eq!(rejection.reason_code, RejectCode::MinerRefused);
+            assert_eq!(rejection.signer_signature_hash, signer_signature_hash);
+        }
+        assert!(node
+            .requests()
+            .iter()
+            .all(|request| !request.path.starts_with("/v2/block_proposal")));
+    }
+
+    #[test]
+    fn expired_rounds_release_the_signer_round_state() {
+        let node = MockStacksNode::spawn().unwrap();
+        let mut config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
+        node.configure(&mut config);
+        let mut signer_config = generate_signer_config(&config, 5, 20);
+        signer_config.stale_round_max_age = 2;
+        let mut signer = Signer::from(signer_config);
+
+        // the signer joins a DKG round before it has seen any burn block
+        let dkg_begin = wsts::net::Message::DkgBegin(wsts::net::DkgBegin { dkg_id: 3 });
+        assert!(signer.stale_rounds.admit(&dkg_begin));
+        signer.state_machine.dkg_id = 3;
+        signer.state_machine.state = SignerRoundState::DkgPublicGather;
+
+        // the first burn block does not expire it
+        signer.collect_stale_rounds(850_000);
+        assert_eq!(
+            signer.state_machine.state,
+            SignerRoundState::DkgPublicGather
+        );
+
+        // but going idle for too long does
+        signer.collect_stale_rounds(850_003);
+        assert!(signer.stale_rounds.is_expired(&RoundId::Dkg(3)));
+        assert_eq!(signer.state_machine.state, SignerRoundState::Idle);
+        assert!(signer.state_machine.dkg_public_shares.is_empty());
+    }
+
+    #[test]
+    fn lagging_node_is_detected_from_miner_proposals() {
+        let webhook = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut monitor = ChainTipMonitor::new(DivergenceConfig {
+            check_interval: Duration::from_secs(0),
+            max_burn_lag: 2,
+            max_stacks_lag: 2,
+            observation_window: Duration::from_secs(600),
+            webhook: Some(format!("http://{}", webhook.local_addr().unwrap())),
+        });
+        let node_tip = ChainTip {
+            burn_height: 100,
+            stacks_height: 50,
+        };
+        let miner_key = StacksPrivateKey::new();
+        let miner_pubkey = StacksPublicKey::from_private(&miner_key);
+
+        // a proposal whose block was not signed by the miner that wrote it is ignored
+        let mut forged = block_proposal(1_000_000, 1_000, 1);
+        forged
+            .block
+            .header
+            .sign_miner(&StacksPrivateKey::new())
+            .unwrap();
+        assert!(Signer::miner_proposed_tips(&miner_messages(&forged), &miner_pubkey).is_empty());
+
+        // the miner is building on a chain the node has not seen yet, so the node cannot
+        // validate its block, but the tip it implies is still observed
+        let mut proposal = block_proposal(105, 51, 1);
+        proposal.block.header.sign_miner(&miner_key).unwrap();
+        let tips = Signer::miner_proposed_tips(&miner_messages(&proposal), &miner_pubkey);
+        assert_eq!(
+            tips,
+            vec![ChainTip {
+                burn_height: 105,
+                stacks_height: 50,
+            }]
+        );
+        for tip in tips {
+            monitor.observe(tip, Instant::now());
+        }
+        let divergence = monitor.check(node_tip, Instant::now()).unwrap();
+        assert_eq!(divergence.burn_lag, 5);
+        assert_eq!(divergence.stacks_lag, 0);
+        assert!(monitor.is_diverged());
+
+        // and the operator is alerted
+        let (mut stream, _) = webhook.accept().unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut request = vec![];
+        let mut buf = [0u8; 1024];
+        while !String::from_utf8_lossy(&request).contains("\"diverged\":true") {
+            let n = stream.read(&mut buf).unwrap();
+            assert!(n > 0, "webhook closed before the alert was received");
+            request.extend_from_slice(&buf[..n]);
+        }
+    }
 
     #[test]
     fn encrypted_messages_should_be_possible_to_decrypt() {
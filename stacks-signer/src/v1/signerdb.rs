@@ -14,12 +14,16 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::fmt::Display;
 use std::path::Path;
 
+use blockstack_lib::burnchains::Txid;
 use blockstack_lib::util_lib::db::{
-    query_row, sqlite_open, table_exists, u64_to_sql, Error as DBError,
+    query_row, query_rows, sqlite_open, table_exists, u64_to_sql, Error as DBError, FromColumn,
+    FromRow,
 };
-use rusqlite::{params, Connection, Error as SqliteError, OpenFlags, NO_PARAMS};
+use libsigner::StackerDBChunkId;
+use rusqlite::{params, Connection, Error as SqliteError, OpenFlags, Row, NO_PARAMS};
 use slog::slog_debug;
 use stacks_common::debug;
 use stacks_common::util::hash::Sha512Trunc256Sum;
@@ -49,6 +53,123 @@ CREATE TABLE IF NOT EXISTS signer_states (
     encrypted_state BLOB NOT NULL
 )";
 
+const CREATE_PROCESSED_EVENTS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS processed_events (
+    reward_cycle INTEGER NOT NULL,
+    event_id TEXT NOT NULL,
+    PRIMARY KEY (reward_cycle, event_id)
+)";
+
+const CREATE_DKG_VOTES_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS dkg_votes (
+    txid TEXT PRIMARY KEY,
+    reward_cycle INTEGER NOT NULL,
+    voting_round INTEGER NOT NULL,
+    aggregate_key TEXT NOT NULL,
+    nonce INTEGER NOT NULL,
+    tx_fee INTEGER NOT NULL,
+    broadcast_time INTEGER NOT NULL,
+    status TEXT NOT NULL
+)";
+
+/// Where an aggregate key vote transaction broadcast by the signer stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DkgVoteStatus {
+    /// Broadcast, but not yet counted by the voting contract
+    Pending,
+    /// Counted by the voting contract
+    Confirmed,
+    /// Superseded by a rebroadcast of the same vote
+    Replaced,
+    /// No longer needed, because a key was approved or a later round started
+    Abandoned,
+}
+
+impl DkgVoteStatus {
+    /// The name under which the status is stored
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Confirmed => "confirmed",
+            Self::Replaced => "replaced",
+            Self::Abandoned => "abandoned",
+        }
+    }
+
+    /// Parse a status from the name under which it is stored
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pending" => Some(Self::Pending),
+            "confirmed" => Some(Self::Confirmed),
+            "replaced" => Some(Self::Replaced),
+            "abandoned" => Some(Self::Abandoned),
+            _ => None,
+        }
+    }
+}
+
+impl Display for DkgVoteStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// An aggregate key vote transaction broadcast by the signer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DkgVoteRecord {
+    /// The vote transaction's id
+    pub txid: Txid,
+    /// The reward cycle the vote is for
+    pub reward_cycle: u64,
+    /// The DKG round the vote is for
+    pub voting_round: u64,
+    /// The aggregate public key voted for
+    pub aggregate_key: String,
+    /// The vote transaction's nonce
+    pub nonce: u64,
+    /// The vote transaction's fee in uSTX
+    pub tx_fee: u64,
+    /// When the vote transaction was broadcast (seconds since the Unix epoch)
+    pub broadcast_time: u64,
+    /// Where the vote transaction stands
+    pub status: DkgVoteStatus,
+}
+
+impl FromRow<DkgVoteRecord> for DkgVoteRecord {
+    fn from_row<'a>(row: &'a Row) -> Result<DkgVoteRecord, DBError> {
+        let status: String = row.get("status")?;
+        Ok(DkgVoteRecord {
+            txid: Txid::from_column(row, "txid")?,
+            reward_cycle: u64::from_column(row, "reward_cycle")?,
+            voting_round: u64::from_column(row, "voting_round")?,
+            aggregate_key: row.get("aggregate_key")?,
+            nonce: u64::from_column(row, "nonce")?,
+            tx_fee: u64::from_column(row, "tx_fee")?,
+            broadcast_time: u64::from_column(row, "broadcast_time")?,
+            status: DkgVoteStatus::from_name(&status).ok_or(DBError::ParseError)?,
+        })
+    }
+}
+
+/// Identifies an event that a signer has already processed, so that it can be
+/// skipped if the node delivers it again (e.g. after the signer restarts)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProcessedEventId {
+    /// A message read from a StackerDB chunk
+    Chunk(StackerDBChunkId),
+    /// The validation response for the block with this signer signature hash
+    BlockValidation(Sha512Trunc256Sum),
+}
+
+impl Display for ProcessedEventId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Chunk(chunk_id) => write!(f, "chunk:{chunk_id}"),
+            Self::BlockValidation(hash) => write!(f, "block:{hash}"),
+        }
+    }
+}
+
 impl SignerDb {
     /// Create a new `SignerState` instance.
     /// This will create a new SQLite database at the given path
@@ -72,6 +193,14 @@ impl SignerDb {
             self.db.execute(CREATE_SIGNER_STATE_TABLE, NO_PARAMS)?;
         }
 
+        if !table_exists(&self.db, "processed_events")? {
+            self.db.execute(CREATE_PROCESSED_EVENTS_TABLE, NO_PARAMS)?;
+        }
+
+        if !table_exists(&self.db, "dkg_votes")? {
+            self.db.execute(CREATE_DKG_VOTES_TABLE, NO_PARAMS)?;
+        }
+
         Ok(())
     }
 
@@ -153,6 +282,106 @@ impl SignerDb {
 
         Ok(())
     }
+
+    /// Has the signer for the given reward cycle already processed this event?
+    pub fn is_event_processed(
+        &self,
+        reward_cycle: u64,
+        event_id: &ProcessedEventId,
+    ) -> Result<bool, DBError> {
+        let result: Option<i64> = query_row(
+            &self.db,
+            "SELECT 1 FROM processed_events WHERE reward_cycle = ? AND event_id = ?",
+            params![&u64_to_sql(reward_cycle)?, event_id.to_string()],
+        )?;
+        Ok(result.is_some())
+    }
+
+    /// Record that the signer for the given reward cycle has processed this event
+    pub fn insert_processed_event(
+        &self,
+        reward_cycle: u64,
+        event_id: &ProcessedEventId,
+    ) -> Result<(), DBError> {
+        self.db.execute(
+            "INSERT OR IGNORE INTO processed_events (reward_cycle, event_id) VALUES (?1, ?2)",
+            params![&u64_to_sql(reward_cycle)?, event_id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Forget the events processed by signers of reward cycles before `reward_cycle`
+    pub fn prune_processed_events(&self, reward_cycle: u64) -> Result<(), DBError> {
+        self.db.execute(
+            "DELETE FROM processed_events WHERE reward_cycle < ?",
+            params![&u64_to_sql(reward_cycle)?],
+        )?;
+        Ok(())
+    }
+
+    /// Record an aggregate key vote transaction broadcast by the signer
+    pub fn insert_dkg_vote(&self, vote: &DkgVoteRecord) -> Result<(), DBError> {
+        debug!("Inserting DKG vote.";
+            "txid" => %vote.txid,
+            "reward_cycle" => vote.reward_cycle,
+            "voting_round" => vote.voting_round,
+            "aggregate_key" => &vote.aggregate_key,
+            "nonce" => vote.nonce,
+            "tx_fee" => vote.tx_fee,
+            "status" => %vote.status
+        );
+        self.db.execute(
+            "INSERT OR REPLACE INTO dkg_votes (txid, reward_cycle, voting_round, aggregate_key, nonce, tx_fee, broadcast_time, status) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                vote.txid.to_string(),
+                u64_to_sql(vote.reward_cycle)?,
+                u64_to_sql(vote.voting_round)?,
+                &vote.aggregate_key,
+                u64_to_sql(vote.nonce)?,
+                u64_to_sql(vote.tx_fee)?,
+                u64_to_sql(vote.broadcast_time)?,
+                vote.status.name(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Get the aggregate key vote transactions the signer for the given reward cycle
+    /// broadcast, oldest first
+    pub fn get_dkg_votes(&self, reward_cycle: u64) -> Result<Vec<DkgVoteRecord>, DBError> {
+        query_rows(
+            &self.db,
+            "SELECT * FROM dkg_votes WHERE reward_cycle = ? ORDER BY broadcast_time, rowid",
+            params![&u64_to_sql(reward_cycle)?],
+        )
+    }
+
+    /// Get the aggregate key vote transactions the signer for the given reward cycle
+    /// broadcast that are in the given state, oldest first
+    pub fn get_dkg_votes_with_status(
+        &self,
+        reward_cycle: u64,
+        status: DkgVoteStatus,
+    ) -> Result<Vec<DkgVoteRecord>, DBError> {
+        query_rows(
+            &self.db,
+            "SELECT * FROM dkg_votes WHERE reward_cycle = ? AND status = ? ORDER BY broadcast_time, rowid",
+            params![&u64_to_sql(reward_cycle)?, status.name()],
+        )
+    }
+
+    /// Update where an aggregate key vote transaction stands
+    pub fn update_dkg_vote_status(
+        &self,
+        txid: &Txid,
+        status: DkgVoteStatus,
+    ) -> Result<(), DBError> {
+        self.db.execute(
+            "UPDATE dkg_votes SET status = ?1 WHERE txid = ?2",
+            params![status.name(), txid.to_string()],
+        )?;
+        Ok(())
+    }
 }
 
 fn try_deserialize<T>(s: Option<String>) -> Result<Option<T>, DBError>
@@ -166,6 +395,7 @@ where
 }
 
 #[cfg(test)]
+/// Create a fresh signer database at `db_path`, removing any existing file
 pub fn test_signer_db(db_path: &str) -> SignerDb {
     use std::fs;
 
@@ -183,6 +413,8 @@ mod tests {
     use blockstack_lib::chainstate::nakamoto::{
         NakamotoBlock, NakamotoBlockHeader, NakamotoBlockVote,
     };
+    use blockstack_lib::chainstate::stacks::boot::SIGNERS_NAME;
+    use blockstack_lib::util_lib::boot::boot_code_id;
     use libsigner::BlockProposal;
 
     use super::*;
@@ -342,4 +574,124 @@ mod tests {
             .expect("Failed to get signer state")
             .is_none());
     }
+
+    #[test]
+    fn test_processed_events() {
+        let db_path = tmp_db_path();
+        let db = SignerDb::new(&db_path).expect("Failed to create signer db");
+        let chunk_id = ProcessedEventId::Chunk(StackerDBChunkId {
+            contract_id: boot_code_id(SIGNERS_NAME, false),
+            slot_id: 3,
+            slot_version: 7,
+            data_hash: Sha512Trunc256Sum([0x02; 32]),
+        });
+        let block_id = ProcessedEventId::BlockValidation(Sha512Trunc256Sum([0x01; 32]));
+
+        assert!(!db.is_event_processed(10, &chunk_id).unwrap());
+        db.insert_processed_event(10, &chunk_id).unwrap();
+        // inserting the same event twice is harmless
+        db.insert_processed_event(10, &chunk_id).unwrap();
+        db.insert_processed_event(11, &block_id).unwrap();
+
+        assert!(db.is_event_processed(10, &chunk_id).unwrap());
+        assert!(db.is_event_processed(11, &block_id).unwrap());
+        // events are tracked per reward cycle
+        assert!(!db.is_event_processed(11, &chunk_id).unwrap());
+        assert!(!db.is_event_processed(10, &block_id).unwrap());
+
+        // a newer version of the same slot is a different event
+        let newer_chunk_id = ProcessedEventId::Chunk(StackerDBChunkId {
+            contract_id: boot_code_id(SIGNERS_NAME, false),
+            slot_id: 3,
+            slot_version: 8,
+            data_hash: Sha512Trunc256Sum([0x02; 32]),
+        });
+        assert!(!db.is_event_processed(10, &newer_chunk_id).unwrap());
+
+        // so is a different chunk written at the same version, once the slot has been
+        // reconfigured for a new signer
+        let reassigned_chunk_id = ProcessedEventId::Chunk(StackerDBChunkId {
+            contract_id: boot_code_id(SIGNERS_NAME, false),
+            slot_id: 3,
+            slot_version: 7,
+            data_hash: Sha512Trunc256Sum([0x03; 32]),
+        });
+        assert!(!db.is_event_processed(10, &reassigned_chunk_id).unwrap());
+
+        // the ledger survives a restart
+        drop(db);
+        let db = SignerDb::new(&db_path).expect("Failed to reopen signer db");
+        assert!(db.is_event_processed(10, &chunk_id).unwrap());
+
+        db.prune_processed_events(11).unwrap();
+        assert!(!db.is_event_processed(10, &chunk_id).unwrap());
+        assert!(db.is_event_processed(11, &block_id).unwrap());
+    }
+
+    #[test]
+    fn test_dkg_votes() {
+        let db_path = tmp_db_path();
+        let db = SignerDb::new(&db_path).expect("Failed to create signer db");
+        let vote = DkgVoteRecord {
+            txid: Txid([0x01; 32]),
+            reward_cycle: 10,
+            voting_round: 0,
+            aggregate_key: "02".repeat(33),
+            nonce: 4,
+            tx_fee: 10_000,
+            broadcast_time: 100,
+            status: DkgVoteStatus::Pending,
+        };
+        let bumped_vote = DkgVoteRecord {
+            txid: Txid([0x02; 32]),
+            tx_fee: 12_500,
+            broadcast_time: 200,
+            ..vote.clone()
+        };
+        let other_cycle_vote = DkgVoteRecord {
+            txid: Txid([0x03; 32]),
+            reward_cycle: 11,
+            ..vote.clone()
+        };
+
+        assert!(db.get_dkg_votes(10).unwrap().is_empty());
+        db.insert_dkg_vote(&bumped_vote).unwrap();
+        db.insert_dkg_vote(&vote).unwrap();
+        db.insert_dkg_vote(&other_cycle_vote).unwrap();
+
+        // votes are tracked per reward cycle, oldest first
+        assert_eq!(
+            db.get_dkg_votes(10).unwrap(),
+            vec![vote.clone(), bumped_vote.clone()]
+        );
+        assert_eq!(db.get_dkg_votes(11).unwrap(), vec![other_cycle_vote]);
+
+        db.update_dkg_vote_status(&vote.txid, DkgVoteStatus::Replaced)
+            .unwrap();
+        assert_eq!(
+            db.get_dkg_votes_with_status(10, DkgVoteStatus::Pending)
+                .unwrap(),
+            vec![bumped_vote.clone()]
+        );
+
+        // the chain of custody survives a restart
+        drop(db);
+        let db = SignerDb::new(&db_path).expect("Failed to reopen signer db");
+        db.update_dkg_vote_status(&bumped_vote.txid, DkgVoteStatus::Confirmed)
+            .unwrap();
+        let statuses: Vec<_> = db
+            .get_dkg_votes(10)
+            .unwrap()
+            .into_iter()
+            .map(|vote| vote.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![DkgVoteStatus::Replaced, DkgVoteStatus::Confirmed]
+        );
+        assert!(db
+            .get_dkg_votes_with_status(10, DkgVoteStatus::Pending)
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -0,0 +1,240 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeSet, HashMap};
+
+use wsts::net::Message;
+
+/// How many expired rounds are remembered, so that their late packets can be dropped.
+/// Beyond this, the oldest expired rounds are forgotten first.
+const MAX_EXPIRED_ROUNDS: usize = 1024;
+
+/// A DKG or signing round, as identified by the wsts packets that belong to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RoundId {
+    /// A DKG round
+    Dkg(u64),
+    /// A signing round, using the key produced by DKG round `dkg_id`
+    Sign {
+        /// The DKG round whose key is used
+        dkg_id: u64,
+        /// The signing round
+        sign_id: u64,
+    },
+}
+
+impl RoundId {
+    /// The round that `msg` belongs to
+    pub fn of(msg: &Message) -> Self {
+        match msg {
+            Message::DkgBegin(msg) => RoundId::Dkg(msg.dkg_id),
+            Message::DkgPrivateBegin(msg) => RoundId::Dkg(msg.dkg_id),
+            Message::DkgEndBegin(msg) => RoundId::Dkg(msg.dkg_id),
+            Message::DkgEnd(msg) => RoundId::Dkg(msg.dkg_id),
+            Message::DkgPublicShares(msg) => RoundId::Dkg(msg.dkg_id),
+            Message::DkgPrivateShares(msg) => RoundId::Dkg(msg.dkg_id),
+            Message::NonceRequest(msg) => RoundId::Sign {
+                dkg_id: msg.dkg_id,
+                sign_id: msg.sign_id,
+            },
+            Message::NonceResponse(msg) => RoundId::Sign {
+                dkg_id: msg.dkg_id,
+                sign_id: msg.sign_id,
+            },
+            Message::SignatureShareRequest(msg) => RoundId::Sign {
+                dkg_id: msg.dkg_id,
+                sign_id: msg.sign_id,
+            },
+            Message::SignatureShareResponse(msg) => RoundId::Sign {
+                dkg_id: msg.dkg_id,
+                sign_id: msg.sign_id,
+            },
+        }
+    }
+
+    /// The label this kind of round is counted under in metrics
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            RoundId::Dkg(_) => "dkg",
+            RoundId::Sign { .. } => "sign",
+        }
+    }
+}
+
+/// Tracks the DKG and signing rounds a signer takes part in, expiring those that have seen no
+/// packets for a number of burn blocks.  Packets for an expired round are refused, so that a
+/// late packet cannot resurrect a round that was abandoned.
+#[derive(Debug)]
+pub struct StaleRoundCollector {
+    /// How many burn blocks a round may go without packets before it expires
+    max_age: u64,
+    /// The latest burn block height seen, if any has been seen yet
+    burn_height: Option<u64>,
+    /// The burn block height at which each live round last saw a packet.  Rounds that saw
+    /// packets before the first burn block are stamped when that block arrives.
+    live: HashMap<RoundId, Option<u64>>,
+    /// Rounds that have expired
+    expired: BTreeSet<RoundId>,
+}
+
+impl StaleRoundCollector {
+    /// Create a collector that expires rounds idle for more than `max_age` burn blocks
+    pub fn new(max_age: u64) -> Self {
+        Self {
+            max_age,
+            burn_height: None,
+            live: HashMap::new(),
+            expired: BTreeSet::new(),
+        }
+    }
+
+    /// Whether `round` has expired
+    pub fn is_expired(&self, round: &RoundId) -> bool {
+        self.expired.contains(round)
+    }
+
+    /// Number of rounds being tracked
+    pub fn num_live_rounds(&self) -> usize {
+        self.live.len()
+    }
+
+    /// Record a packet for the round that `msg` belongs to.
+    /// Returns false if that round has expired, in which case the packet should be dropped.
+    pub fn admit(&mut self, msg: &Message) -> bool {
+        let round = RoundId::of(msg);
+        if self.is_expired(&round) {
+            return false;
+        }
+        self.live.insert(round, self.burn_height);
+        true
+    }
+
+    /// Advance to `burn_height`, and expire the rounds that have not seen a packet in more than
+    /// `max_age` burn blocks.  Returns the rounds that expired.
+    pub fn collect(&mut self, burn_height: u64) -> Vec<RoundId> {
+        let now = self
+            .burn_height
+            .map_or(burn_height, |height| height.max(burn_height));
+        self.burn_height = Some(now);
+        let mut collected = vec![];
+        let max_age = self.max_age;
+        self.live.retain(|round, last_seen| {
+            let last_seen = *last_seen.get_or_insert(now);
+            if now.saturating_sub(last_seen) <= max_age {
+                return true;
+            }
+            collected.push(*round);
+            false
+        });
+        collected.sort();
+        self.expired.extend(collected.iter().copied());
+        while self.expired.len() > MAX_EXPIRED_ROUNDS {
+            let Some(oldest) = self.expired.iter().next().copied() else {
+                break;
+            };
+            self.expired.remove(&oldest);
+        }
+        collected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wsts::net::{DkgBegin, NonceRequest};
+
+    use super::*;
+
+    fn dkg_begin(dkg_id: u64) -> Message {
+        Message::DkgBegin(DkgBegin { dkg_id })
+    }
+
+    fn nonce_request(dkg_id: u64, sign_id: u64) -> Message {
+        Message::NonceRequest(NonceRequest {
+            dkg_id,
+            sign_id,
+            sign_iter_id: 0,
+            message: vec![],
+            is_taproot: false,
+            merkle_root: None,
+        })
+    }
+
+    #[test]
+    fn idle_rounds_expire() {
+        let mut collector = StaleRoundCollector::new(3);
+        collector.collect(100);
+        assert!(collector.admit(&dkg_begin(1)));
+        assert!(collector.admit(&nonce_request(1, 7)));
+        assert!(collector.collect(103).is_empty());
+
+        // the signing round stays busy, the DKG round does not
+        assert!(collector.admit(&nonce_request(1, 7)));
+        assert_eq!(collector.collect(104), vec![RoundId::Dkg(1)]);
+        assert_eq!(collector.num_live_rounds(), 1);
+        assert_eq!(
+            collector.collect(107),
+            vec![RoundId::Sign {
+                dkg_id: 1,
+                sign_id: 7
+            }]
+        );
+        assert_eq!(collector.num_live_rounds(), 0);
+    }
+
+    #[test]
+    fn late_packets_do_not_resurrect_expired_rounds() {
+        let mut collector = StaleRoundCollector::new(0);
+        collector.collect(10);
+        assert!(collector.admit(&dkg_begin(2)));
+        assert!(collector.admit(&nonce_request(2, 1)));
+        assert_eq!(collector.collect(11).len(), 2);
+
+        assert!(!collector.admit(&dkg_begin(2)));
+        assert!(!collector.admit(&nonce_request(2, 1)));
+        assert_eq!(collector.num_live_rounds(), 0);
+        // new rounds are unaffected
+        assert!(collector.admit(&dkg_begin(3)));
+        assert!(collector.admit(&nonce_request(2, 2)));
+        // a stale burn height does not move the collector backwards
+        assert!(collector.collect(5).is_empty());
+    }
+
+    #[test]
+    fn rounds_admitted_before_the_first_burn_block_are_not_expired_by_it() {
+        let mut collector = StaleRoundCollector::new(3);
+        assert!(collector.admit(&dkg_begin(1)));
+        assert!(collector.admit(&nonce_request(1, 7)));
+        assert!(collector.collect(850_000).is_empty());
+        assert_eq!(collector.num_live_rounds(), 2);
+
+        // from then on, the rounds age from the first burn block seen
+        assert!(collector.collect(850_003).is_empty());
+        assert_eq!(collector.collect(850_004).len(), 2);
+    }
+
+    #[test]
+    fn expired_rounds_are_bounded() {
+        let mut collector = StaleRoundCollector::new(0);
+        let rounds = MAX_EXPIRED_ROUNDS as u64 + 10;
+        collector.collect(0);
+        for dkg_id in 0..rounds {
+            collector.admit(&dkg_begin(dkg_id));
+        }
+        assert_eq!(collector.collect(1).len(), rounds as usize);
+        assert!(!collector.is_expired(&RoundId::Dkg(0)));
+        assert!(collector.is_expired(&RoundId::Dkg(rounds - 1)));
+    }
+}
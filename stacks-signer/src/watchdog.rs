@@ -0,0 +1,320 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use blockstack_lib::net::api::postblock_proposal::BlockValidateResponse;
+use libsigner::{SignerEvent, SignerEventTrait};
+use slog::{slog_error, slog_info, slog_warn};
+use stacks_common::{error, info, warn};
+
+/// Most time the watchdog thread sleeps between checks of the event being processed
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Describe where an event came from, for logs about it
+pub fn event_source<T: SignerEventTrait>(event: &SignerEvent<T>) -> String {
+    match event {
+        SignerEvent::MinerMessages(messages, miner_key) => format!(
+            "{} messages from miner {}",
+            messages.len(),
+            miner_key.to_hex()
+        ),
+        SignerEvent::SignerMessages(signer_set, messages) => {
+            let chunks: Vec<String> = messages
+                .iter()
+                .map(|(chunk_id, _)| chunk_id.to_string())
+                .collect();
+            format!(
+                "{} messages from signer set {signer_set} (chunks {})",
+                messages.len(),
+                chunks.join(", ")
+            )
+        }
+        SignerEvent::BlockValidationResponse(BlockValidateResponse::Ok(ok)) => {
+            format!("node's approval of block {}", ok.signer_signature_hash)
+        }
+        SignerEvent::BlockValidationResponse(BlockValidateResponse::Reject(reject)) => {
+            format!("node's rejection of block {}", reject.signer_signature_hash)
+        }
+        SignerEvent::StatusCheck => "status check".to_string(),
+        SignerEvent::NewBurnBlock { burn_height, .. } => format!("burn block {burn_height}"),
+    }
+}
+
+/// An event being processed
+#[derive(Debug)]
+struct WatchedEvent {
+    /// Distinguishes this event from those watched before and after it
+    id: u64,
+    /// Where the event came from
+    source: String,
+    /// When processing began
+    started_at: Instant,
+    /// Set once the event has run over the timeout
+    expired: Arc<AtomicBool>,
+}
+
+impl WatchedEvent {
+    /// Mark the event as having run over `timeout` if it has, alerting the operator and counting
+    /// it the first time.  Returns whether it has run over.
+    fn check(&self, timeout: Duration) -> bool {
+        if self.expired.load(Ordering::SeqCst) {
+            return true;
+        }
+        let elapsed = self.started_at.elapsed();
+        if elapsed < timeout {
+            return false;
+        }
+        if !self.expired.swap(true, Ordering::SeqCst) {
+            error!("Signer has been processing an event for longer than the watchdog allows. The rest of the event will be abandoned at the next opportunity.";
+                "source" => &self.source,
+                "elapsed_ms" => elapsed.as_millis(),
+                "timeout_ms" => timeout.as_millis(),
+            );
+            crate::monitoring::increment_event_processing_timeouts();
+        }
+        true
+    }
+}
+
+/// State shared between the watchdog and its thread
+#[derive(Debug, Default)]
+struct WatchdogState {
+    /// The event being processed, if any
+    current: Option<WatchedEvent>,
+    /// Id of the next event to be watched
+    next_id: u64,
+    /// Set when the watchdog is dropped, to stop its thread
+    stopped: bool,
+}
+
+/// Bounds the time the runloop spends processing a single event.
+///
+/// Event processing is synchronous, so an event that hangs cannot be interrupted. Instead, a
+/// background thread reports an event as soon as it runs over the timeout, naming where it
+/// came from, so that the operator can tell what the signer is stuck on. The `EventWatch` for
+/// the event then reports that it has expired, so that the code processing it can defer the
+/// rest of it to the next event at its next opportunity and let the runloop move on.
+#[derive(Debug)]
+pub struct EventWatchdog {
+    /// How long an event may take to process. None if events are not watched.
+    timeout: Option<Duration>,
+    /// The event being processed
+    state: Arc<Mutex<WatchdogState>>,
+    /// The thread checking on the event being processed. Started when the first event is
+    /// watched.
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EventWatchdog {
+    /// Create a watchdog that allows each event `timeout` to process. If `timeout` is None,
+    /// events are not watched.
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            state: Arc::new(Mutex::new(WatchdogState::default())),
+            thread: None,
+        }
+    }
+
+    /// Start watching the processing of an event from `source`, until `finish()` is called on
+    /// the returned watch
+    pub fn watch(&mut self, source: String) -> EventWatch {
+        let expired = Arc::new(AtomicBool::new(false));
+        let Some(timeout) = self.timeout else {
+            return EventWatch {
+                id: 0,
+                timeout: None,
+                expired,
+                state: None,
+            };
+        };
+        if self.thread.is_none() {
+            self.thread = Self::spawn(timeout, self.state.clone());
+        }
+        let mut state = self.state.lock().expect("FATAL: watchdog state poisoned");
+        let id = state.next_id;
+        state.next_id = state.next_id.wrapping_add(1);
+        state.current = Some(WatchedEvent {
+            id,
+            source,
+            started_at: Instant::now(),
+            expired: expired.clone(),
+        });
+        EventWatch {
+            id,
+            timeout: Some(timeout),
+            expired,
+            state: Some(self.state.clone()),
+        }
+    }
+
+    /// Start the thread that checks on the event being processed.
+    /// Returns None if the thread could not be started, in which case events that run over are
+    /// only noticed by the code processing them.
+    fn spawn(timeout: Duration, state: Arc<Mutex<WatchdogState>>) -> Option<JoinHandle<()>> {
+        let check_interval = (timeout / 4).min(MAX_CHECK_INTERVAL);
+        let spawned = thread::Builder::new()
+            .name("signer-event-watchdog".into())
+            .spawn(move || loop {
+                thread::park_timeout(check_interval);
+                let state = state.lock().expect("FATAL: watchdog state poisoned");
+                if state.stopped {
+                    return;
+                }
+                if let Some(event) = state.current.as_ref() {
+                    event.check(timeout);
+                }
+            });
+        match spawned {
+            Ok(handle) => Some(handle),
+            Err(e) => {
+                warn!("Failed to start the event watchdog thread: {e}");
+                None
+            }
+        }
+    }
+}
+
+impl Drop for EventWatchdog {
+    fn drop(&mut self) {
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+        if let Ok(mut state) = self.state.lock() {
+            state.stopped = true;
+        }
+        thread.thread().unpark();
+        let _ = thread.join();
+    }
+}
+
+/// The processing of one event, watched by an `EventWatchdog`
+#[derive(Debug, Clone)]
+pub struct EventWatch {
+    /// Id of the watched event
+    id: u64,
+    /// How long the event may take to process. None if it is not watched.
+    timeout: Option<Duration>,
+    /// Set once the event has run over the timeout
+    expired: Arc<AtomicBool>,
+    /// The watchdog's state. None if the event is not watched.
+    state: Option<Arc<Mutex<WatchdogState>>>,
+}
+
+impl EventWatch {
+    /// Whether the event has run over its timeout, and the rest of it should be deferred
+    pub fn expired(&self) -> bool {
+        if self.expired.load(Ordering::SeqCst) {
+            return true;
+        }
+        let (Some(timeout), Some(state)) = (self.timeout, self.state.as_ref()) else {
+            return false;
+        };
+        let state = state.lock().expect("FATAL: watchdog state poisoned");
+        match state.current.as_ref() {
+            Some(event) if event.id == self.id => event.check(timeout),
+            _ => false,
+        }
+    }
+
+    /// Stop watching the event, since its processing has finished.
+    /// Returns whether it ran over its timeout.
+    pub fn finish(self) -> bool {
+        let expired = self.expired();
+        let Some(state) = self.state.as_ref() else {
+            return expired;
+        };
+        let mut state = state.lock().expect("FATAL: watchdog state poisoned");
+        if !matches!(state.current.as_ref(), Some(event) if event.id == self.id) {
+            return expired;
+        }
+        let Some(event) = state.current.take() else {
+            return expired;
+        };
+        if expired {
+            info!("Signer finished processing an event that ran over the watchdog's timeout";
+                "source" => &event.source,
+                "elapsed_ms" => event.started_at.elapsed().as_millis(),
+            );
+        }
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libsigner::v1::messages::SignerMessage;
+
+    use super::*;
+
+    #[test]
+    fn unwatched_events_never_expire() {
+        let mut watchdog = EventWatchdog::new(None);
+        let watch = watchdog.watch("status check".into());
+        assert!(!watch.expired());
+        assert!(!watch.finish());
+        assert!(watchdog.thread.is_none());
+    }
+
+    #[test]
+    fn quick_events_do_not_expire() {
+        let mut watchdog = EventWatchdog::new(Some(Duration::from_secs(60)));
+        let watch = watchdog.watch("status check".into());
+        assert!(!watch.expired());
+        assert!(!watch.finish());
+        assert!(watchdog.state.lock().unwrap().current.is_none());
+    }
+
+    #[test]
+    fn slow_events_expire() {
+        let mut watchdog = EventWatchdog::new(Some(Duration::from_millis(50)));
+        let watch = watchdog.watch("burn block 100".into());
+        // the watchdog thread notices without the event being checked
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !watch.expired.load(Ordering::SeqCst) {
+            assert!(
+                Instant::now() < deadline,
+                "watchdog never noticed the event"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(watch.expired());
+        assert!(watch.finish());
+
+        // the next event gets a fresh deadline
+        let watch = watchdog.watch("burn block 101".into());
+        assert!(!watch.expired());
+        drop(watchdog);
+        assert!(!watch.finish());
+    }
+
+    #[test]
+    fn event_sources_are_described() {
+        let event = SignerEvent::<SignerMessage>::NewBurnBlock {
+            burn_height: 100,
+            burn_header_timestamp: None,
+        };
+        assert_eq!(event_source(&event), "burn block 100");
+        assert_eq!(
+            event_source(&SignerEvent::<SignerMessage>::SignerMessages(1, vec![])),
+            "0 messages from signer set 1 (chunks )"
+        );
+    }
+}
@@ -0,0 +1,31 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decode a trie node's path
+
+#![no_main]
+
+use std::io::Cursor;
+
+use blockstack_lib::chainstate::stacks::index::bits::path_from_bytes;
+use blockstack_lib::chainstate::stacks::index::node::TRIEPATH_MAX_LEN;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(path) = path_from_bytes(&mut Cursor::new(data)) {
+        assert!(path.len() <= TRIEPATH_MAX_LEN);
+        assert!(path.len() < data.len());
+    }
+});
@@ -0,0 +1,36 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decode a trie node's child pointers.
+//! The first byte is the ID of the node whose children are decoded, and the rest is the node's
+//! ID byte followed by its child pointers.
+
+#![no_main]
+
+use std::io::Cursor;
+
+use blockstack_lib::chainstate::stacks::index::bits::ptrs_from_bytes;
+use blockstack_lib::chainstate::stacks::index::node::{clear_backptr, TriePtr};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((node_id, bytes)) = data.split_first() else {
+        return;
+    };
+    let mut ptrs = [TriePtr::default(); 256];
+    if let Ok(nid) = ptrs_from_bytes(*node_id, &mut Cursor::new(bytes), &mut ptrs) {
+        assert_eq!(clear_backptr(nid), clear_backptr(*node_id));
+    }
+});
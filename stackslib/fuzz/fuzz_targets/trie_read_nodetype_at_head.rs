@@ -0,0 +1,37 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decode a trie node, as read from the MARF's on-disk storage.
+//! The first byte is the node's ID (as stored in the pointer to it), and the rest is the
+//! node's hash followed by its bytes.
+
+#![no_main]
+
+use std::io::Cursor;
+
+use blockstack_lib::chainstate::stacks::index::bits::read_nodetype_at_head;
+use libfuzzer_sys::fuzz_target;
+use stacks_common::types::chainstate::TRIEHASH_ENCODED_SIZE;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((ptr_id, bytes)) = data.split_first() else {
+        return;
+    };
+    let mut cursor = Cursor::new(bytes);
+    if let Ok((node, _hash)) = read_nodetype_at_head(&mut cursor, *ptr_id) {
+        // a decoded node never claims more bytes than it was decoded from
+        assert!(TRIEHASH_ENCODED_SIZE + node.byte_len() <= bytes.len());
+    }
+});
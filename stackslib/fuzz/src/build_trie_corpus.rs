@@ -0,0 +1,121 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Build seed corpora for the trie fuzz targets out of well-formed nodes of every type, so
+//! that the fuzzer starts out from inputs that get past the first few checks.
+//!
+//! Run from this directory with `cargo run --bin build_trie_corpus`, then fuzz with e.g.
+//! `cargo fuzz run trie_read_nodetype_at_head`.  Corpora are written to `corpus/<target>/`,
+//! or under the directory given as the first argument.
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use blockstack_lib::chainstate::stacks::index::bits::write_nodetype_bytes;
+use blockstack_lib::chainstate::stacks::index::node::{
+    set_backptr, TrieNode, TrieNode16, TrieNode256, TrieNode4, TrieNode48, TrieNodeID,
+    TrieNodeType, TriePtr,
+};
+use blockstack_lib::chainstate::stacks::index::TrieLeaf;
+use stacks_common::types::chainstate::TrieHash;
+
+/// Make a node of each type with `num_children` children (or as many as fit) and the given path
+fn make_nodes(path: &[u8], num_children: u8) -> Vec<(&'static str, TrieNodeType)> {
+    let mut node4 = TrieNode4::new(path);
+    let mut node16 = TrieNode16::new(path);
+    let mut node48 = TrieNode48::new(path);
+    let mut node256 = TrieNode256::new(path);
+    for chr in 0..num_children {
+        let ptr = TriePtr::new(TrieNodeID::Leaf as u8, chr, u32::from(chr) * 100);
+        // children that live in an earlier trie are back-pointers
+        let backptr = TriePtr {
+            id: set_backptr(TrieNodeID::Node4 as u8),
+            chr,
+            ptr: 7,
+            back_block: 3,
+        };
+        let child = if chr % 2 == 0 { ptr } else { backptr };
+        node4.insert(&child);
+        node16.insert(&child);
+        node48.insert(&child);
+        node256.insert(&child);
+    }
+    vec![
+        ("node4", TrieNodeType::Node4(node4)),
+        ("node16", TrieNodeType::Node16(node16)),
+        ("node48", TrieNodeType::Node48(Box::new(node48))),
+        ("node256", TrieNodeType::Node256(Box::new(node256))),
+        (
+            "leaf",
+            TrieNodeType::Leaf(TrieLeaf::new(path, &[0xab; 40].to_vec())),
+        ),
+    ]
+}
+
+fn write_seed(dir: &Path, name: &str, bytes: &[u8]) {
+    fs::create_dir_all(dir).expect("FATAL: failed to create corpus directory");
+    fs::write(dir.join(name), bytes).expect("FATAL: failed to write corpus file");
+}
+
+fn main() {
+    let root = env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("corpus"));
+    let nodetype_dir = root.join("trie_read_nodetype_at_head");
+    let path_dir = root.join("trie_path_from_bytes");
+    let ptrs_dir = root.join("trie_ptrs_from_bytes");
+
+    let paths: [&[u8]; 3] = [&[], &[1, 2, 3, 4], &[0xff; 32]];
+    let mut num_seeds = 0;
+    for (i, path) in paths.iter().enumerate() {
+        for num_children in [0, 3, 47, 255] {
+            for (name, node) in make_nodes(path, num_children) {
+                let seed_name = format!("{}-path{}-children{}", name, i, num_children);
+
+                // ID byte, then the node as stored on disk
+                let mut node_bytes = vec![];
+                write_nodetype_bytes(
+                    &mut Cursor::new(&mut node_bytes),
+                    &node,
+                    TrieHash([0x5a; 32]),
+                )
+                .expect("FATAL: failed to serialize node");
+                let mut seed = vec![node.id()];
+                seed.extend_from_slice(&node_bytes);
+                write_seed(&nodetype_dir, &seed_name, &seed);
+                num_seeds += 1;
+
+                // the node's own bytes: ID, ptrs, then path
+                let mut node_bytes = vec![];
+                node.write_bytes(&mut node_bytes)
+                    .expect("FATAL: failed to serialize node");
+                if !node.is_leaf() {
+                    let mut seed = vec![node.id()];
+                    seed.extend_from_slice(&node_bytes);
+                    write_seed(&ptrs_dir, &seed_name, &seed);
+                    num_seeds += 1;
+                }
+            }
+        }
+
+        let mut seed = vec![path.len() as u8];
+        seed.extend_from_slice(path);
+        write_seed(&path_dir, &format!("path{}", i), &seed);
+        num_seeds += 1;
+    }
+    println!("Wrote {} seeds to {}", num_seeds, root.display());
+}
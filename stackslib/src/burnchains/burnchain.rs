@@ -14,11 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::mpsc::sync_channel;
-use std::sync::Arc;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use std::{fs, thread};
 
@@ -197,23 +197,84 @@ impl BurnchainStateTransition {
         let consumed_leader_keys =
             sort_tx.get_consumed_leader_keys(&parent_snapshot, &block_commits)?;
 
+        let (burn_dist, windowed_block_commits, windowed_missed_commits) =
+            Self::make_burn_distribution(
+                sort_tx,
+                burnchain,
+                parent_snapshot,
+                block_commits,
+                missed_commits,
+            )?;
+        BurnSamplePoint::prometheus_update_miner_commitments(&burn_dist);
+
+        // find out which block commits we're going to take
+        for i in 0..burn_dist.len() {
+            let burn_point = &burn_dist[i];
+
+            // taking this commit in this sample point
+            accepted_ops.push(BlockstackOperationType::LeaderBlockCommit(
+                burn_point.candidate.clone(),
+            ));
+            all_block_commits.remove(&burn_point.candidate.txid);
+        }
+
+        // accepted_ops contains all accepted commits now.
+        // only rejected ones remain in all_block_commits
+        for op in all_block_commits.values() {
+            warn!(
+                "REJECTED({}) block commit {} at {},{}: Committed to an already-consumed VRF key",
+                op.block_height, &op.txid, op.block_height, op.vtxindex
+            );
+        }
+
+        accepted_ops.sort_by(|ref a, ref b| a.vtxindex().partial_cmp(&b.vtxindex()).unwrap());
+
+        Ok(BurnchainStateTransition {
+            burn_dist,
+            accepted_ops,
+            consumed_leader_keys,
+            windowed_block_commits,
+            windowed_missed_commits,
+        })
+    }
+
+    /// Assemble the mining commitment window for the sortition on top of `parent_snapshot`
+    /// whose block-commits are `block_commits`, and calculate its burn distribution.
+    /// `missed_commits` are the missed block-commits discovered in that sortition's block.
+    /// Returns the distribution, along with the windowed block-commits and missed
+    /// block-commits it was calculated from, in ascending block height order.
+    pub fn make_burn_distribution<SH: SortitionHandle>(
+        sort_handle: &mut SH,
+        burnchain: &Burnchain,
+        parent_snapshot: &BlockSnapshot,
+        block_commits: Vec<LeaderBlockCommitOp>,
+        missed_commits: &[MissedBlockCommit],
+    ) -> Result<
+        (
+            Vec<BurnSamplePoint>,
+            Vec<Vec<LeaderBlockCommitOp>>,
+            Vec<Vec<MissedBlockCommit>>,
+        ),
+        burnchain_error,
+    > {
         // assemble the commit windows
         let mut windowed_block_commits = vec![block_commits];
         let mut windowed_missed_commits = vec![];
 
         // what epoch are we in?
-        let epoch_id = SortitionDB::get_stacks_epoch(sort_tx, parent_snapshot.block_height + 1)?
-            .unwrap_or_else(|| {
-                panic!(
-                    "FATAL: no epoch defined at burn height {}",
-                    parent_snapshot.block_height + 1
-                )
-            })
-            .epoch_id;
+        let epoch_id =
+            SortitionDB::get_stacks_epoch(sort_handle.sqlite(), parent_snapshot.block_height + 1)?
+                .unwrap_or_else(|| {
+                    panic!(
+                        "FATAL: no epoch defined at burn height {}",
+                        parent_snapshot.block_height + 1
+                    )
+                })
+                .epoch_id;
 
         // what was the epoch at the start of this window?
         let window_start_epoch_id = SortitionDB::get_stacks_epoch(
-            sort_tx,
+            sort_handle.sqlite(),
             parent_snapshot
                 .block_height
                 .saturating_sub(epoch_id.mining_commitment_window().into()),
@@ -254,16 +315,18 @@ impl BurnchainStateTransition {
                     break;
                 }
                 let block_height = parent_snapshot.block_height - (blocks_back as u64);
-                let sortition_id = match sort_tx.get_block_snapshot_by_height(block_height)? {
+                let sortition_id = match sort_handle.get_block_snapshot_by_height(block_height)? {
                     Some(sn) => sn.sortition_id,
                     None => break,
                 };
                 windowed_block_commits.push(SortitionDB::get_block_commits_by_block(
-                    sort_tx.tx(),
+                    sort_handle.sqlite(),
                     &sortition_id,
                 )?);
-                let mut missed_commits_at_height =
-                    SortitionDB::get_missed_commits_by_intended(sort_tx.tx(), &sortition_id)?;
+                let mut missed_commits_at_height = SortitionDB::get_missed_commits_by_intended(
+                    sort_handle.sqlite(),
+                    &sortition_id,
+                )?;
                 if let Some(missed_commit_in_block) = missed_commits_map.remove(&sortition_id) {
                     missed_commits_at_height
                         .extend(missed_commit_in_block.into_iter().map(|x| x.clone()));
@@ -324,37 +387,8 @@ impl BurnchainStateTransition {
             windowed_missed_commits.clone(),
             burn_blocks,
         );
-        BurnSamplePoint::prometheus_update_miner_commitments(&burn_dist);
-
-        // find out which block commits we're going to take
-        for i in 0..burn_dist.len() {
-            let burn_point = &burn_dist[i];
-
-            // taking this commit in this sample point
-            accepted_ops.push(BlockstackOperationType::LeaderBlockCommit(
-                burn_point.candidate.clone(),
-            ));
-            all_block_commits.remove(&burn_point.candidate.txid);
-        }
-
-        // accepted_ops contains all accepted commits now.
-        // only rejected ones remain in all_block_commits
-        for op in all_block_commits.values() {
-            warn!(
-                "REJECTED({}) block commit {} at {},{}: Committed to an already-consumed VRF key",
-                op.block_height, &op.txid, op.block_height, op.vtxindex
-            );
-        }
 
-        accepted_ops.sort_by(|ref a, ref b| a.vtxindex().partial_cmp(&b.vtxindex()).unwrap());
-
-        Ok(BurnchainStateTransition {
-            burn_dist,
-            accepted_ops,
-            consumed_leader_keys,
-            windowed_block_commits,
-            windowed_missed_commits,
-        })
+        Ok((burn_dist, windowed_block_commits, windowed_missed_commits))
     }
 }
 
@@ -1176,6 +1210,7 @@ impl Burnchain {
             target_block_height_opt,
             max_blocks_opt,
             None,
+            1,
         )?;
         Ok(chain_tip.block_height)
     }
@@ -1437,11 +1472,46 @@ impl Burnchain {
         Ok(Some(burnchain_tip))
     }
 
+    /// Receive burnchain blocks that were parsed in parallel, each numbered with its position in
+    /// download order, and pass them to `store` in that order.  Blocks that arrive ahead of
+    /// their predecessors are held back until the gap is filled; any still held back when the
+    /// parsers hang up are discarded.
+    pub(crate) fn store_parsed_blocks_in_order<F>(
+        parsed_blocks: Receiver<(u64, BurnchainBlock)>,
+        mut store: F,
+    ) -> Result<(), burnchain_error>
+    where
+        F: FnMut(BurnchainBlock) -> Result<(), burnchain_error>,
+    {
+        // parsed blocks that arrived ahead of their predecessors
+        let mut pending = BTreeMap::new();
+        let mut next_seq: u64 = 0;
+        while let Ok((seq, burnchain_block)) = parsed_blocks.recv() {
+            debug!("Try recv next parsed block");
+            pending.insert(seq, burnchain_block);
+
+            while let Some(burnchain_block) = pending.remove(&next_seq) {
+                next_seq += 1;
+                store(burnchain_block)?;
+            }
+        }
+        if !pending.is_empty() {
+            debug!(
+                "Discarding {} parsed blocks after a gap at download #{}",
+                pending.len(),
+                next_seq
+            );
+        }
+        Ok(())
+    }
+
     /// Top-level burnchain sync.
     /// Returns the burnchain block header for the new burnchain tip, which will be _at least_ as
     /// high as target_block_height_opt (if given), or whatever is currently at the tip of the
     /// burnchain DB.
     /// If this method returns Err(burnchain_error::TrySyncAgain), then call this method again.
+    /// Downloaded blocks are parsed by `parser_threads` worker threads (at least one), but are
+    /// always inserted into the burnchain DB in height order.
     pub fn sync_with_indexer<I>(
         &mut self,
         indexer: &mut I,
@@ -1449,12 +1519,13 @@ impl Burnchain {
         target_block_height_opt: Option<u64>,
         max_blocks_opt: Option<u64>,
         should_keep_running: Option<Arc<AtomicBool>>,
+        parser_threads: usize,
     ) -> Result<BurnchainBlockHeader, burnchain_error>
     where
         I: BurnchainIndexer + BurnchainHeaderReader + 'static + Send,
     {
         self.setup_chainstate(indexer)?;
-        let (sortdb, mut burnchain_db) = self.connect_db(
+        let (_, mut burnchain_db) = self.connect_db(
             true,
             indexer.get_first_block_header_hash()?,
             indexer.get_first_block_header_timestamp()?,
@@ -1570,12 +1641,13 @@ impl Burnchain {
         );
 
         // synchronize
+        let parser_threads = parser_threads.max(1);
         let (downloader_send, downloader_recv) = sync_channel(1);
-        let (parser_send, parser_recv) = sync_channel(1);
-        let (db_send, db_recv) = sync_channel(1);
+        let (parser_send, parser_recv) = sync_channel(parser_threads);
+        let (db_send, db_recv) = sync_channel(parser_threads);
 
         let mut downloader = indexer.downloader();
-        let mut parser = indexer.parser();
+        let parsers: Vec<_> = (0..parser_threads).map(|_| indexer.parser()).collect();
 
         let myself = self.clone();
         let input_headers = indexer.read_headers(start_block + 1, end_block + 1)?;
@@ -1592,6 +1664,9 @@ impl Burnchain {
             thread::Builder::new()
                 .name("burnchain-downloader".to_string())
                 .spawn(move || {
+                    // blocks are numbered in download order, so the DB thread can put them
+                    // back in order after they've been parsed in parallel
+                    let mut seq: u64 = 0;
                     while let Ok(Some(ipc_header)) = downloader_recv.recv() {
                         debug!("Try recv next header");
 
@@ -1615,61 +1690,84 @@ impl Burnchain {
                         );
 
                         parser_send
-                            .send(Some(ipc_block))
+                            .send((seq, ipc_block))
                             .map_err(|_e| burnchain_error::ThreadChannelError)?;
+                        seq += 1;
                     }
-                    parser_send
-                        .send(None)
-                        .map_err(|_e| burnchain_error::ThreadChannelError)?;
+                    // dropping `parser_send` tells the parser threads that there are no more blocks
                     Ok(())
                 })
                 .unwrap();
 
-        let parse_thread: thread::JoinHandle<Result<(), burnchain_error>> = thread::Builder::new()
-            .name("burnchain-parser".to_string())
-            .spawn(move || {
-                while let Ok(Some(ipc_block)) = parser_recv.recv() {
-                    debug!("Try recv next block");
+        // the parser threads take turns pulling downloaded blocks off of the shared channel.
+        // If any of them fails, the rest stop as well, so the downloader stops too.
+        let parser_recv = Arc::new(Mutex::new(parser_recv));
+        let parser_failed = Arc::new(AtomicBool::new(false));
+        let mut parse_threads: Vec<thread::JoinHandle<Result<(), burnchain_error>>> =
+            Vec::with_capacity(parsers.len());
+        for (i, mut parser) in parsers.into_iter().enumerate() {
+            let parser_recv = parser_recv.clone();
+            let parser_failed = parser_failed.clone();
+            let db_send = db_send.clone();
+            let epochs = epochs.clone();
+            let parse_thread = thread::Builder::new()
+                .name(format!("burnchain-parser-{}", i))
+                .spawn(move || {
+                    while !parser_failed.load(Ordering::SeqCst) {
+                        let next = parser_recv
+                            .lock()
+                            .expect("FATAL: burnchain parser channel mutex poisoned")
+                            .recv();
+                        let Ok((seq, ipc_block)) = next else {
+                            break;
+                        };
+                        debug!("Try recv next block");
 
-                    let cur_epoch =
-                        SortitionDB::get_stacks_epoch(sortdb.conn(), ipc_block.height())?
+                        let epoch_index = StacksEpoch::find_epoch(&epochs, ipc_block.height())
                             .unwrap_or_else(|| {
                                 panic!("FATAL: no stacks epoch defined for {}", ipc_block.height())
                             });
+                        let epoch_id = epochs[epoch_index].epoch_id;
 
-                    let parse_start = get_epoch_time_ms();
-                    let burnchain_block = parser.parse(&ipc_block, cur_epoch.epoch_id)?;
-                    let parse_end = get_epoch_time_ms();
+                        let parse_start = get_epoch_time_ms();
+                        let burnchain_block = match parser.parse(&ipc_block, epoch_id) {
+                            Ok(burnchain_block) => burnchain_block,
+                            Err(e) => {
+                                parser_failed.store(true, Ordering::SeqCst);
+                                return Err(e);
+                            }
+                        };
+                        let parse_end = get_epoch_time_ms();
 
-                    debug!(
-                        "Parsed block {} (in epoch {}) in {}ms",
-                        burnchain_block.block_height(),
-                        cur_epoch.epoch_id,
-                        parse_end.saturating_sub(parse_start)
-                    );
+                        debug!(
+                            "Parsed block {} (in epoch {}) in {}ms",
+                            burnchain_block.block_height(),
+                            epoch_id,
+                            parse_end.saturating_sub(parse_start)
+                        );
 
-                    db_send
-                        .send(Some(burnchain_block))
-                        .map_err(|_e| burnchain_error::ThreadChannelError)?;
-                }
-                db_send
-                    .send(None)
-                    .map_err(|_e| burnchain_error::ThreadChannelError)?;
-                Ok(())
-            })
-            .unwrap();
+                        if db_send.send((seq, burnchain_block)).is_err() {
+                            parser_failed.store(true, Ordering::SeqCst);
+                            return Err(burnchain_error::ThreadChannelError);
+                        }
+                    }
+                    Ok(())
+                })
+                .unwrap();
+            parse_threads.push(parse_thread);
+        }
+        // only the parser threads hold senders now, so the DB thread stops once they're all done
+        drop(db_send);
 
         let db_thread: thread::JoinHandle<Result<BurnchainBlockHeader, burnchain_error>> =
             thread::Builder::new()
                 .name("burnchain-db".to_string())
                 .spawn(move || {
                     let mut last_processed = burnchain_tip;
-                    while let Ok(Some(burnchain_block)) = db_recv.recv() {
-                        debug!("Try recv next parsed block");
-
+                    Burnchain::store_parsed_blocks_in_order(db_recv, |burnchain_block| {
                         let block_height = burnchain_block.block_height();
                         if block_height == 0 {
-                            continue;
+                            return Ok(());
                         }
 
                         let epoch_index = StacksEpoch::find_epoch(&epochs, block_height)
@@ -1699,7 +1797,8 @@ impl Burnchain {
                             burnchain_block.block_height(),
                             insert_end.saturating_sub(insert_start)
                         );
-                    }
+                        Ok(())
+                    })?;
                     Ok(last_processed)
                 })
                 .unwrap();
@@ -1732,7 +1831,9 @@ impl Burnchain {
 
         // join up
         let _ = download_thread.join().unwrap();
-        let _ = parse_thread.join().unwrap();
+        for parse_thread in parse_threads.into_iter() {
+            let _ = parse_thread.join().unwrap();
+        }
         let block_header = match db_thread.join().unwrap() {
             Ok(x) => x,
             Err(e) => {
@@ -56,6 +56,7 @@ pub mod bitcoin;
 pub mod burnchain;
 pub mod db;
 pub mod indexer;
+pub mod sync_control;
 
 #[cfg(test)]
 pub mod tests;
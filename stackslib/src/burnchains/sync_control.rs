@@ -0,0 +1,172 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often a paused sync loop wakes up to check whether it should shut down
+const PAUSED_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The state of the burnchain sync loop, as seen by whoever controls it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BurnchainSyncState {
+    /// The sync loop is running normally
+    Running,
+    /// A pause was requested, but the sync loop is still finishing its in-flight pass
+    Pausing,
+    /// The sync loop is parked between passes, and will not process burnchain blocks until resumed
+    Paused,
+}
+
+#[derive(Debug, Default)]
+struct SyncControlInner {
+    pause_requested: bool,
+    paused: bool,
+}
+
+/// Handle for pausing and resuming the burnchain sync loop.
+/// Clones share state, so the run loop can hold one copy and the RPC server another.
+/// The sync loop calls `wait_while_paused()` between passes; a pause therefore takes
+/// effect only once the pass in flight has finished processing its burnchain blocks.
+#[derive(Debug, Clone, Default)]
+pub struct BurnchainSyncControl {
+    inner: Arc<(Mutex<SyncControlInner>, Condvar)>,
+}
+
+impl BurnchainSyncControl {
+    pub fn new() -> BurnchainSyncControl {
+        BurnchainSyncControl::default()
+    }
+
+    pub fn state(&self) -> BurnchainSyncState {
+        let inner = self
+            .inner
+            .0
+            .lock()
+            .expect("FATAL: sync control mutex poisoned");
+        match (inner.pause_requested, inner.paused) {
+            (_, true) => BurnchainSyncState::Paused,
+            (true, false) => BurnchainSyncState::Pausing,
+            (false, false) => BurnchainSyncState::Running,
+        }
+    }
+
+    /// Ask the sync loop to pause after its current pass
+    pub fn request_pause(&self) -> BurnchainSyncState {
+        let (lock, cvar) = &*self.inner;
+        {
+            let mut inner = lock.lock().expect("FATAL: sync control mutex poisoned");
+            inner.pause_requested = true;
+        }
+        cvar.notify_all();
+        self.state()
+    }
+
+    /// Let a paused (or pausing) sync loop carry on
+    pub fn resume(&self) -> BurnchainSyncState {
+        let (lock, cvar) = &*self.inner;
+        {
+            let mut inner = lock.lock().expect("FATAL: sync control mutex poisoned");
+            inner.pause_requested = false;
+        }
+        cvar.notify_all();
+        self.state()
+    }
+
+    /// Wait up to `timeout` for the sync loop to reach the paused state.
+    /// Returns true if it is paused.
+    pub fn wait_until_paused(&self, timeout: Duration) -> bool {
+        let (lock, cvar) = &*self.inner;
+        let deadline = Instant::now() + timeout;
+        let mut inner = lock.lock().expect("FATAL: sync control mutex poisoned");
+        while inner.pause_requested && !inner.paused {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            inner = cvar
+                .wait_timeout(inner, deadline - now)
+                .expect("FATAL: sync control mutex poisoned")
+                .0;
+        }
+        inner.paused
+    }
+
+    /// Called by the sync loop between passes.  If a pause has been requested, block until
+    /// it is lifted or until `keep_running()` returns false.
+    /// Returns true if the loop was paused.
+    pub fn wait_while_paused<F: Fn() -> bool>(&self, keep_running: F) -> bool {
+        let (lock, cvar) = &*self.inner;
+        let mut inner = lock.lock().expect("FATAL: sync control mutex poisoned");
+        if !inner.pause_requested {
+            return false;
+        }
+        info!("Burnchain sync paused");
+        inner.paused = true;
+        cvar.notify_all();
+        while inner.pause_requested && keep_running() {
+            inner = cvar
+                .wait_timeout(inner, PAUSED_POLL_INTERVAL)
+                .expect("FATAL: sync control mutex poisoned")
+                .0;
+        }
+        inner.paused = false;
+        cvar.notify_all();
+        info!("Burnchain sync resumed");
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_pause_resume() {
+        let control = BurnchainSyncControl::new();
+        assert_eq!(control.state(), BurnchainSyncState::Running);
+
+        // nothing to wait for if no pause was requested
+        assert!(!control.wait_while_paused(|| true));
+        assert!(!control.wait_until_paused(Duration::from_millis(10)));
+
+        assert_eq!(control.request_pause(), BurnchainSyncState::Pausing);
+        // the sync loop hasn't reached its checkpoint yet
+        assert!(!control.wait_until_paused(Duration::from_millis(10)));
+
+        let sync_control = control.clone();
+        let sync_thread = thread::spawn(move || sync_control.wait_while_paused(|| true));
+
+        assert!(control.wait_until_paused(Duration::from_secs(30)));
+        assert_eq!(control.state(), BurnchainSyncState::Paused);
+
+        control.resume();
+        assert!(sync_thread.join().unwrap());
+        assert_eq!(control.state(), BurnchainSyncState::Running);
+    }
+
+    #[test]
+    fn test_paused_loop_stops_on_shutdown() {
+        let control = BurnchainSyncControl::new();
+        control.request_pause();
+        // a shutting-down node doesn't stay parked
+        assert!(control.wait_while_paused(|| false));
+        assert_eq!(control.state(), BurnchainSyncState::Pausing);
+    }
+}
@@ -33,7 +33,7 @@ use stacks_common::util::{get_epoch_time_secs, log};
 use crate::burnchains::bitcoin::address::*;
 use crate::burnchains::bitcoin::keys::BitcoinPublicKey;
 use crate::burnchains::bitcoin::*;
-use crate::burnchains::{Txid, *};
+use crate::burnchains::{Error as burnchain_error, Txid, *};
 use crate::chainstate::burn::db::sortdb::{SortitionDB, SortitionHandleTx};
 use crate::chainstate::burn::distribution::BurnSamplePoint;
 use crate::chainstate::burn::operations::leader_block_commit::BURN_BLOCK_MINED_AT_MODULUS;
@@ -915,3 +915,72 @@ fn test_burn_snapshot_sequence() {
         prev_snapshot = snapshot;
     }
 }
+
+#[test]
+fn test_store_parsed_blocks_in_order() {
+    let block_at = |height: u64| {
+        BurnchainBlock::Bitcoin(BitcoinBlock::new(
+            height,
+            &BurnchainHeaderHash([height as u8; 32]),
+            &BurnchainHeaderHash([height.saturating_sub(1) as u8; 32]),
+            vec![],
+            height,
+        ))
+    };
+
+    // four parser threads hand over blocks 100..120 out of order, numbered in download order
+    let (send, recv) = std::sync::mpsc::sync_channel(4);
+    let parsers: Vec<_> = (0..4u64)
+        .map(|parser| {
+            let send = send.clone();
+            std::thread::spawn(move || {
+                for seq in (0..20u64).rev().filter(|seq| seq % 4 == parser) {
+                    send.send((seq, block_at(100 + seq))).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(send);
+
+    let mut stored = vec![];
+    Burnchain::store_parsed_blocks_in_order(recv, |block| {
+        stored.push(block.block_height());
+        Ok(())
+    })
+    .unwrap();
+    for parser in parsers {
+        parser.join().unwrap();
+    }
+    assert_eq!(stored, (100..120).collect::<Vec<_>>());
+
+    // blocks after a gap are never stored
+    let (send, recv) = std::sync::mpsc::sync_channel(4);
+    for seq in [1, 0, 3, 4] {
+        send.send((seq, block_at(100 + seq))).unwrap();
+    }
+    drop(send);
+    let mut stored = vec![];
+    Burnchain::store_parsed_blocks_in_order(recv, |block| {
+        stored.push(block.block_height());
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(stored, vec![100, 101]);
+
+    // a failure to store a block stops the rest
+    let (send, recv) = std::sync::mpsc::sync_channel(4);
+    for seq in [2, 1, 0] {
+        send.send((seq, block_at(100 + seq))).unwrap();
+    }
+    drop(send);
+    let mut stored = vec![];
+    let res = Burnchain::store_parsed_blocks_in_order(recv, |block| {
+        if block.block_height() == 101 {
+            return Err(burnchain_error::CoordinatorClosed);
+        }
+        stored.push(block.block_height());
+        Ok(())
+    });
+    assert!(matches!(res, Err(burnchain_error::CoordinatorClosed)));
+    assert_eq!(stored, vec![100]);
+}
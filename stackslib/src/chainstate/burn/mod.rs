@@ -43,6 +43,7 @@ pub mod atc;
 pub mod db;
 pub mod distribution;
 pub mod operations;
+pub mod simulation;
 pub mod sortition;
 
 pub const CONSENSUS_HASH_LIFETIME: u32 = 24;
@@ -0,0 +1,255 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Simulation of sortition outcomes.
+//!
+//! A miner decides how much to spend on a block-commit before it knows what the other miners
+//! will spend.  Given the sortition history, this estimates the probability that a
+//! hypothetical block-commit wins the next sortition, by running the sortition's burn
+//! distribution over the mining commitment window with the hypothetical block-commit in it.
+//! Every other miner that sent a block-commit in the latest sortition is assumed to send the
+//! same amount again, chained to it.
+
+use stacks_common::types::chainstate::{BlockHeaderHash, BurnchainHeaderHash, VRFSeed};
+
+use crate::burnchains::{
+    Burnchain, BurnchainSigner, BurnchainStateTransition, Error as BurnchainError, Txid,
+};
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::burn::distribution::BurnSamplePoint;
+use crate::chainstate::burn::operations::leader_block_commit::BURN_BLOCK_MINED_AT_MODULUS;
+use crate::chainstate::burn::operations::LeaderBlockCommitOp;
+use crate::chainstate::burn::BlockSnapshot;
+
+/// A block-commit whose chances of winning the next sortition are to be estimated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HypotheticalCommit {
+    /// The miner sending the block-commit.  Its block-commit in the latest sortition, if any,
+    /// is replaced by this one.
+    pub apparent_sender: BurnchainSigner,
+    /// Amount to spend
+    pub burn_fee: u64,
+    /// The UTXO the block-commit spends.  If not given, it spends the chained UTXO of the
+    /// miner's block-commit in the latest sortition, if there is one.
+    #[serde(default)]
+    pub input: Option<(Txid, u32)>,
+}
+
+/// The estimated outcome of a sortition for a hypothetical block-commit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SortitionSimulation {
+    /// Height of the burnchain block whose sortition was simulated
+    pub burn_block_height: u64,
+    /// The block-commit's effective burn, i.e. min(median burn, burn fee)
+    pub burns: u128,
+    /// Median burn over the block-commit's UTXO chain in the window
+    pub median_burn: u128,
+    /// Number of block-commits in the window on the block-commit's UTXO chain
+    pub frequency: u8,
+    /// Sum of the effective burns of all block-commits in the sortition
+    pub total_burns: u128,
+    /// Number of block-commits in the sortition
+    pub num_commits: usize,
+    /// Probability that the block-commit wins the sortition
+    pub win_probability: f64,
+}
+
+/// Make a block-commit that carries just what the burn distribution looks at
+fn mock_block_commit(
+    apparent_sender: BurnchainSigner,
+    burn_fee: u64,
+    input: (Txid, u32),
+    block_height: u64,
+    vtxindex: u32,
+) -> LeaderBlockCommitOp {
+    // can't collide with a real txid, since those are hashes
+    let mut txid = [0xff; 32];
+    txid[0..4].copy_from_slice(&vtxindex.to_be_bytes());
+    LeaderBlockCommitOp {
+        sunset_burn: 0,
+        block_header_hash: BlockHeaderHash([0; 32]),
+        new_seed: VRFSeed([0; 32]),
+        parent_block_ptr: 0,
+        parent_vtxindex: 0,
+        key_block_ptr: 0,
+        key_vtxindex: 0,
+        memo: vec![],
+        commit_outs: vec![],
+        burn_fee,
+        input,
+        apparent_sender,
+        txid: Txid(txid),
+        vtxindex,
+        block_height,
+        burn_parent_modulus: ((block_height.saturating_sub(1)) % BURN_BLOCK_MINED_AT_MODULUS) as u8,
+        burn_header_hash: BurnchainHeaderHash([0; 32]),
+    }
+}
+
+impl SortitionSimulation {
+    /// Simulate the sortition of the burnchain block after `tip` with `commit` in it
+    pub fn simulate(
+        sortdb: &SortitionDB,
+        burnchain: &Burnchain,
+        tip: &BlockSnapshot,
+        commit: &HypotheticalCommit,
+    ) -> Result<SortitionSimulation, BurnchainError> {
+        let block_height = tip.block_height + 1;
+        let chained_output = LeaderBlockCommitOp::expected_chained_utxo(
+            burnchain.is_in_prepare_phase(tip.block_height),
+        );
+
+        let latest_commits =
+            SortitionDB::get_block_commits_by_block(sortdb.conn(), &tip.sortition_id)?;
+        let mut input = commit.input;
+        let mut block_commits = Vec::with_capacity(latest_commits.len() + 1);
+        for latest in latest_commits.into_iter() {
+            if latest.apparent_sender == commit.apparent_sender {
+                if input.is_none() {
+                    input = Some((latest.txid, chained_output));
+                }
+                continue;
+            }
+            let vtxindex = block_commits.len() as u32;
+            block_commits.push(mock_block_commit(
+                latest.apparent_sender,
+                latest.burn_fee,
+                (latest.txid, chained_output),
+                block_height,
+                vtxindex,
+            ));
+        }
+
+        let vtxindex = block_commits.len() as u32;
+        let candidate = mock_block_commit(
+            commit.apparent_sender.clone(),
+            commit.burn_fee,
+            // no txid will match
+            input.unwrap_or((Txid([0; 32]), 0)),
+            block_height,
+            vtxindex,
+        );
+        let candidate_txid = candidate.txid;
+        block_commits.push(candidate);
+
+        let mut handle = sortdb.index_handle(&tip.sortition_id);
+        let (burn_dist, _, _) = BurnchainStateTransition::make_burn_distribution(
+            &mut handle,
+            burnchain,
+            tip,
+            block_commits,
+            &[],
+        )?;
+        Ok(Self::from_burn_distribution(
+            block_height,
+            &burn_dist,
+            &candidate_txid,
+        ))
+    }
+
+    /// Get the outcome for the block-commit `txid` of a sortition with the distribution
+    /// `burn_dist`
+    fn from_burn_distribution(
+        burn_block_height: u64,
+        burn_dist: &[BurnSamplePoint],
+        txid: &Txid,
+    ) -> SortitionSimulation {
+        let total_burns: u128 = burn_dist.iter().map(|pt| pt.burns).sum();
+        let mut simulation = SortitionSimulation {
+            burn_block_height,
+            burns: 0,
+            median_burn: 0,
+            frequency: 0,
+            total_burns,
+            num_commits: burn_dist.len(),
+            win_probability: 0.0,
+        };
+        let point = match burn_dist.iter().find(|pt| pt.candidate.txid == *txid) {
+            Some(point) => point,
+            None => return simulation,
+        };
+
+        simulation.burns = point.burns;
+        simulation.median_burn = point.median_burn;
+        simulation.frequency = point.frequency;
+        simulation.win_probability = if burn_dist.len() == 1 {
+            // the only block-commit covers the whole range
+            1.0
+        } else if total_burns == 0 {
+            0.0
+        } else {
+            (point.burns as f64) / (total_burns as f64)
+        };
+        simulation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer(name: &str) -> BurnchainSigner {
+        BurnchainSigner(name.to_string())
+    }
+
+    #[test]
+    fn test_from_burn_distribution() {
+        let commits = vec![
+            mock_block_commit(signer("alice"), 1000, (Txid([0; 32]), 0), 100, 0),
+            mock_block_commit(signer("bob"), 3000, (Txid([0; 32]), 0), 100, 1),
+        ];
+        let alice_txid = commits[0].txid;
+        let bob_txid = commits[1].txid;
+        let burn_dist =
+            BurnSamplePoint::make_min_median_distribution(1, vec![commits], vec![], vec![false]);
+
+        let alice = SortitionSimulation::from_burn_distribution(100, &burn_dist, &alice_txid);
+        assert_eq!(alice.burn_block_height, 100);
+        assert_eq!(alice.burns, 1000);
+        assert_eq!(alice.median_burn, 1000);
+        assert_eq!(alice.frequency, 1);
+        assert_eq!(alice.total_burns, 4000);
+        assert_eq!(alice.num_commits, 2);
+        assert_eq!(alice.win_probability, 0.25);
+
+        let bob = SortitionSimulation::from_burn_distribution(100, &burn_dist, &bob_txid);
+        assert_eq!(bob.burns, 3000);
+        assert_eq!(bob.win_probability, 0.75);
+
+        // a block-commit that isn't in the distribution can't win
+        let absent = SortitionSimulation::from_burn_distribution(100, &burn_dist, &Txid([1; 32]));
+        assert_eq!(absent.burns, 0);
+        assert_eq!(absent.total_burns, 4000);
+        assert_eq!(absent.win_probability, 0.0);
+    }
+
+    #[test]
+    fn test_from_burn_distribution_single_commit() {
+        let commits = vec![mock_block_commit(
+            signer("alice"),
+            0,
+            (Txid([0; 32]), 0),
+            100,
+            0,
+        )];
+        let txid = commits[0].txid;
+        let burn_dist =
+            BurnSamplePoint::make_min_median_distribution(1, vec![commits], vec![], vec![false]);
+
+        let alice = SortitionSimulation::from_burn_distribution(100, &burn_dist, &txid);
+        assert_eq!(alice.total_burns, 0);
+        assert_eq!(alice.win_probability, 1.0);
+    }
+}
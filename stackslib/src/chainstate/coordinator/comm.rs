@@ -60,6 +60,8 @@ pub struct CoordinatorChannels {
     sortitions_processed: Arc<AtomicU64>,
     /// Does the StackerDB need to be refreshed?
     refresh_stacker_db: Arc<AtomicBool>,
+    /// ID of the burnchain sync pass that announced the pending burn blocks (0 if none)
+    burn_sync_span_id: Arc<AtomicU64>,
 }
 
 /// Notification struct for communicating to
@@ -85,6 +87,8 @@ pub struct CoordinatorReceivers {
     pub sortitions_processed: Arc<AtomicU64>,
     /// Does the StackerDB need to be refreshed?
     pub refresh_stacker_db: Arc<AtomicBool>,
+    /// ID of the burnchain sync pass that announced the pending burn blocks (0 if none)
+    burn_sync_span_id: Arc<AtomicU64>,
 }
 
 /// Static struct used to hold all the static methods
@@ -131,6 +135,12 @@ impl CoordinatorReceivers {
         }
         signal_bools.receive_signal()
     }
+
+    /// ID of the burnchain sync pass whose burn blocks the coordinator is processing, so the
+    /// coordinator's log records can be correlated with the node's.  0 if no pass set one.
+    pub fn burn_sync_span_id(&self) -> u64 {
+        self.burn_sync_span_id.load(Ordering::SeqCst)
+    }
 }
 
 impl CoordinatorChannels {
@@ -150,6 +160,11 @@ impl CoordinatorChannels {
         !bools.stop
     }
 
+    /// Attribute the burn blocks announced from now on to the burnchain sync pass `span_id`
+    pub fn set_burn_sync_span_id(&self, span_id: u64) {
+        self.burn_sync_span_id.store(span_id, Ordering::SeqCst);
+    }
+
     pub fn stop_chains_coordinator(&self) -> bool {
         let mut bools = self.signal_bools.lock().unwrap();
         bools.stop = true;
@@ -236,6 +251,7 @@ impl CoordinatorCommunication {
         let stacks_blocks_processed = Arc::new(AtomicU64::new(0));
         let sortitions_processed = Arc::new(AtomicU64::new(0));
         let refresh_stacker_db = Arc::new(AtomicBool::new(false));
+        let burn_sync_span_id = Arc::new(AtomicU64::new(0));
 
         let senders = CoordinatorChannels {
             signal_bools: signal_bools.clone(),
@@ -244,6 +260,7 @@ impl CoordinatorCommunication {
 
             sortitions_processed: sortitions_processed.clone(),
             refresh_stacker_db: refresh_stacker_db.clone(),
+            burn_sync_span_id: burn_sync_span_id.clone(),
         };
 
         let rcvrs = CoordinatorReceivers {
@@ -252,6 +269,7 @@ impl CoordinatorCommunication {
             stacks_blocks_processed,
             sortitions_processed,
             refresh_stacker_db,
+            burn_sync_span_id,
         };
 
         (rcvrs, senders)
@@ -19,7 +19,7 @@ use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::mpsc::SyncSender;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{cmp, fs};
 
 use clarity::vm::costs::ExecutionCost;
@@ -78,7 +78,7 @@ use crate::cost_estimates::{CostEstimator, FeeEstimator, PessimisticEstimator};
 use crate::monitoring::{
     increment_contract_calls_processed, increment_stx_blocks_processed_counter,
 };
-use crate::net::atlas::{AtlasConfig, AtlasDB, AttachmentInstance};
+use crate::net::atlas::{AtlasConfig, AtlasDB, AttachmentInstance, AttachmentOrigin};
 use crate::util_lib::db::{DBConn, DBTx, Error as DBError};
 
 pub mod comm;
@@ -189,6 +189,7 @@ pub trait BlockEventDispatcher {
         &self,
         burn_block: &BurnchainHeaderHash,
         burn_block_height: u64,
+        burn_block_timestamp: u64,
         rewards: Vec<(PoxAddress, u64)>,
         burns: u64,
         reward_recipients: Vec<PoxAddress>,
@@ -596,8 +597,14 @@ impl<
         }
         if (bits & (CoordinatorEvents::NEW_BURN_BLOCK as u8)) != 0 {
             signal_mining_blocked(miner_status.clone());
-            debug!("Received new burn block notice");
-            match self.handle_new_burnchain_block() {
+            let span_id = comms.burn_sync_span_id();
+            let started_at = Instant::now();
+            debug!("Received new burn block notice"; "span_id" => span_id);
+            let result = self.handle_new_burnchain_block();
+            debug!("Burnchain sync: coordinator processed burn blocks";
+                   "span_id" => span_id,
+                   "coordinator_ms" => started_at.elapsed().as_millis() as u64);
+            match result {
                 Ok(burn_block_status) => match burn_block_status {
                     NewBurnchainBlockStatus::Ready => {}
                     NewBurnchainBlockStatus::WaitForPox2x(block_hash) => {
@@ -915,6 +922,7 @@ pub fn dispatcher_announce_burn_ops<T: BlockEventDispatcher>(
     dispatcher.announce_burn_block(
         &burn_header.block_hash,
         burn_header.block_height,
+        burn_header.timestamp,
         paid_rewards.pox,
         paid_rewards.burns,
         recipients,
@@ -2915,7 +2923,10 @@ impl<
                                     Some(canonical_stacks_tip_height),
                                 );
                                 if let Some(attachment_instance) = res {
-                                    attachments_instances.insert(attachment_instance);
+                                    let origin =
+                                        AttachmentOrigin::of_receipt(block_receipt, receipt);
+                                    attachments_instances
+                                        .insert(attachment_instance.with_origin(origin));
                                 }
                             }
                         }
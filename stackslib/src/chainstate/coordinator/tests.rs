@@ -441,6 +441,7 @@ impl BlockEventDispatcher for NullEventDispatcher {
         &self,
         _burn_block: &BurnchainHeaderHash,
         _burn_block_height: u64,
+        _burn_block_timestamp: u64,
         _rewards: Vec<(PoxAddress, u64)>,
         _burns: u64,
         _slot_holders: Vec<PoxAddress>,
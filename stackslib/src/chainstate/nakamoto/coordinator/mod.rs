@@ -16,6 +16,7 @@
 
 use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use clarity::vm::clarity::ClarityConnection;
 use clarity::vm::database::BurnStateDB;
@@ -539,8 +540,14 @@ impl<
         }
         if (bits & (CoordinatorEvents::NEW_BURN_BLOCK as u8)) != 0 {
             signal_mining_blocked(miner_status.clone());
-            debug!("Received new burn block notice");
-            match self.handle_new_nakamoto_burnchain_block() {
+            let span_id = comms.burn_sync_span_id();
+            let started_at = Instant::now();
+            debug!("Received new burn block notice"; "span_id" => span_id);
+            let result = self.handle_new_nakamoto_burnchain_block();
+            debug!("Burnchain sync: coordinator processed burn blocks";
+                   "span_id" => span_id,
+                   "coordinator_ms" => started_at.elapsed().as_millis() as u64);
+            match result {
                 Ok(can_proceed) => {
                     if !can_proceed {
                         error!("Missing canonical anchor block",);
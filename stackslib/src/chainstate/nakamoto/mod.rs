@@ -1303,6 +1303,8 @@ impl NakamotoChainState {
                "burn_block_hash" => %next_ready_block_snapshot.burn_header_hash
         );
 
+        // only count the MARF I/O done to process this block
+        stacks_chain_state.with_clarity_marf(|marf| marf.take_io_stats());
         let (mut chainstate_tx, clarity_instance) = stacks_chain_state.chainstate_tx_begin()?;
 
         // find parent header
@@ -1444,6 +1446,8 @@ impl NakamotoChainState {
                 panic!()
             });
 
+        stacks_chain_state.report_block_marf_io(&block_id, next_ready_block.header.chain_length);
+
         // as a separate transaction, mark this block as processed.
         // This is done separately so that the staging blocks DB, which receives writes
         // from the network to store blocks, will be available for writes while a block is
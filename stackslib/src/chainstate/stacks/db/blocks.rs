@@ -197,6 +197,7 @@ impl BlockEventDispatcher for DummyEventDispatcher {
         &self,
         _burn_block: &BurnchainHeaderHash,
         _burn_block_height: u64,
+        _burn_block_timestamp: u64,
         _rewards: Vec<(PoxAddress, u64)>,
         _burns: u64,
         _slot_holders: Vec<PoxAddress>,
@@ -6033,6 +6034,8 @@ impl StacksChainState {
         dispatcher_opt: Option<&'a T>,
     ) -> Result<(Option<StacksEpochReceipt>, Option<TransactionPayload>), Error> {
         let blocks_path = self.blocks_path.clone();
+        // only count the MARF I/O done to process this block
+        self.with_clarity_marf(|marf| marf.take_io_stats());
         let (mut chainstate_tx, clarity_instance) = self.chainstate_tx_begin()?;
 
         // this is a transaction against both the headers and staging blocks databases!
@@ -6400,6 +6403,11 @@ impl StacksChainState {
                 panic!()
             });
 
+        self.report_block_marf_io(
+            &epoch_receipt.header.index_block_hash(),
+            epoch_receipt.header.stacks_block_height,
+        );
+
         Ok((Some(epoch_receipt), None))
     }
 
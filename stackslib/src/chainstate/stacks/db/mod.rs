@@ -1804,18 +1804,18 @@ impl StacksChainState {
         let clarity_state = ClarityInstance::new(mainnet, chain_id, vm_state);
 
         let mut chainstate = StacksChainState {
-            mainnet: mainnet,
-            chain_id: chain_id,
-            clarity_state: clarity_state,
+            mainnet,
+            chain_id,
+            clarity_state,
             nakamoto_staging_blocks_conn,
-            state_index: state_index,
+            state_index,
             blocks_path: blocks_path_root,
             clarity_state_index_path: clarity_state_index_marf,
-            clarity_state_index_root: clarity_state_index_root,
+            clarity_state_index_root,
             root_path: path_str.to_string(),
             unconfirmed_state: None,
             fault_injection: StacksChainStateFaults::new(),
-            marf_opts: marf_opts,
+            marf_opts,
         };
 
         let mut receipts = vec![];
@@ -2069,6 +2069,28 @@ impl StacksChainState {
         self.clarity_state.with_marf(f)
     }
 
+    /// Report the storage I/O the Clarity state MARF did since it was last taken as the I/O of
+    /// processing the block `block_id`: log it, and record it for the metrics and the
+    /// `/v2/marf/io` endpoint
+    pub fn report_block_marf_io(&mut self, block_id: &StacksBlockId, block_height: u64) {
+        let io = self.with_clarity_marf(|marf| marf.take_io_stats());
+        info!("Processed Stacks block MARF I/O";
+              "block_id" => %block_id,
+              "block_height" => block_height,
+              "nodes_read" => io.nodes_read,
+              "leaves_read" => io.leaves_read,
+              "backptr_hops" => io.backptr_hops,
+              "disk_reads" => io.disk_reads,
+              "bytes_read" => io.bytes_read,
+              "nodes_written" => io.nodes_written,
+              "bytes_written" => io.bytes_written);
+        monitoring::record_block_marf_io(monitoring::BlockMarfIOStats {
+            block_id: *block_id,
+            block_height,
+            io,
+        });
+    }
+
     /// Run to_do on the state of the Clarity VM at the given chain tip.
     /// Returns Some(x: R) if the given parent_tip exists.
     /// Returns None if not
@@ -48,7 +48,7 @@ pub fn path_from_bytes<R: Read>(r: &mut R) -> Result<Vec<u8>, Error> {
     let mut lenbuf = [0u8; 1];
     r.read_exact(&mut lenbuf).map_err(|e| {
         if e.kind() == ErrorKind::UnexpectedEof {
-            Error::CorruptionError("Failed to read len buf".to_string())
+            Error::corruption("Failed to read len buf".to_string())
         } else {
             eprintln!("failed: {:?}", &e);
             Error::IOError(e)
@@ -61,7 +61,7 @@ pub fn path_from_bytes<R: Read>(r: &mut R) -> Result<Vec<u8>, Error> {
             lenbuf[0],
             TRIEPATH_MAX_LEN
         );
-        return Err(Error::CorruptionError(format!(
+        return Err(Error::corruption(format!(
             "Node path is longer than {} bytes (got {})",
             TRIEPATH_MAX_LEN, lenbuf[0]
         )));
@@ -70,7 +70,7 @@ pub fn path_from_bytes<R: Read>(r: &mut R) -> Result<Vec<u8>, Error> {
     let mut retbuf = vec![0; lenbuf[0] as usize];
     r.read_exact(&mut retbuf).map_err(|e| {
         if e.kind() == ErrorKind::UnexpectedEof {
-            Error::CorruptionError(format!("Failed to read {} bytes of path", lenbuf[0]))
+            Error::corruption(format!("Failed to read {} bytes of path", lenbuf[0]))
         } else {
             eprintln!("failed: {:?}", &e);
             Error::IOError(e)
@@ -108,24 +108,31 @@ pub fn get_ptrs_byte_len(ptrs: &[TriePtr]) -> usize {
 
 /// Read a Trie node's children from a Readable object, and write them to the given ptrs_buf slice.
 /// Returns the Trie node ID detected.
+/// Returns Error::CorruptionError if `node_id` is not the ID of a node with children, or if
+/// ptrs_buf is too short to hold them.
 pub fn ptrs_from_bytes<R: Read>(
     node_id: u8,
     r: &mut R,
     ptrs_buf: &mut [TriePtr],
 ) -> Result<u8, Error> {
-    if !check_node_id(node_id) {
+    if !check_node_id(node_id) || clear_backptr(node_id) == TrieNodeID::Empty as u8 {
         trace!("Bad node ID {:x}", node_id);
-        return Err(Error::CorruptionError(format!(
-            "Bad node ID: {:x}",
-            node_id
-        )));
+        return Err(Error::corruption(format!("Bad node ID: {:x}", node_id)));
     }
 
     let num_ptrs = node_id_to_ptr_count(node_id);
+    if num_ptrs > ptrs_buf.len() {
+        return Err(Error::corruption(format!(
+            "Node ID {:x} has {} ptrs, but only {} fit",
+            node_id,
+            num_ptrs,
+            ptrs_buf.len()
+        )));
+    }
     let mut bytes = vec![0u8; 1 + num_ptrs * TRIEPTR_SIZE];
     r.read_exact(&mut bytes).map_err(|e| {
         if e.kind() == ErrorKind::UnexpectedEof {
-            Error::CorruptionError(format!(
+            Error::corruption(format!(
                 "Failed to read 1 + {} bytes of ptrs",
                 num_ptrs * TRIEPTR_SIZE
             ))
@@ -139,7 +146,7 @@ pub fn ptrs_from_bytes<R: Read>(
     let nid = bytes[0];
     if clear_backptr(nid) != clear_backptr(node_id) {
         trace!("Bad idbuf: {:x} != {:x}", nid, node_id);
-        return Err(Error::CorruptionError(
+        return Err(Error::corruption(
             "Failed to read expected node ID".to_string(),
         ));
     }
@@ -218,7 +225,7 @@ pub fn read_hash_bytes<F: Read>(f: &mut F) -> Result<[u8; TRIEHASH_ENCODED_SIZE]
     let mut hashbytes = [0u8; TRIEHASH_ENCODED_SIZE];
     f.read_exact(&mut hashbytes).map_err(|e| {
         if e.kind() == ErrorKind::UnexpectedEof {
-            Error::CorruptionError(format!(
+            Error::corruption(format!(
                 "Failed to read hash in full from {}",
                 to_hex(&hashbytes)
             ))
@@ -235,7 +242,7 @@ pub fn read_block_identifier<F: Read + Seek>(f: &mut F) -> Result<u32, Error> {
     let mut bytes = [0u8; 4];
     f.read_exact(&mut bytes).map_err(|e| {
         if e.kind() == ErrorKind::UnexpectedEof {
-            Error::CorruptionError(format!(
+            Error::corruption(format!(
                 "Failed to read hash in full from {}",
                 f.seek(SeekFrom::Current(0)).unwrap()
             ))
@@ -256,7 +263,7 @@ pub fn read_node_hash_bytes<F: Read + Seek>(
 ) -> Result<[u8; TRIEHASH_ENCODED_SIZE], Error> {
     f.seek(SeekFrom::Start(ptr.ptr() as u64))
         .map_err(Error::IOError)?;
-    read_hash_bytes(f)
+    read_hash_bytes(f).map_err(|e| e.with_corruption_ptr(ptr))
 }
 
 /// Read the root hash from a TrieFileStorage instance
@@ -284,7 +291,7 @@ pub fn read_nodetype<F: Read + Seek>(
     f.seek(SeekFrom::Start(ptr.ptr() as u64))
         .map_err(Error::IOError)?;
     trace!("read_nodetype at {:?}", ptr);
-    read_nodetype_at_head(f, ptr.id())
+    read_nodetype_at_head(f, ptr.id()).map_err(|e| e.with_corruption_ptr(ptr))
 }
 
 /// Read a node
@@ -295,7 +302,7 @@ pub fn read_nodetype_nohash<F: Read + Seek>(
     f.seek(SeekFrom::Start(ptr.ptr() as u64))
         .map_err(Error::IOError)?;
     trace!("read_nodetype_nohash at {:?}", ptr);
-    read_nodetype_at_head_nohash(f, ptr.id())
+    read_nodetype_at_head_nohash(f, ptr.id()).map_err(|e| e.with_corruption_ptr(ptr))
 }
 
 /// Read a node and hash at the stream's current position
@@ -343,7 +350,7 @@ fn inner_read_nodetype_at_head<F: Read + Seek>(
     };
 
     let node = match TrieNodeID::from_u8(ptr_id).ok_or_else(|| {
-        Error::CorruptionError(format!("read_node_type: Unknown trie node type {}", ptr_id))
+        Error::corruption(format!("read_node_type: Unknown trie node type {}", ptr_id))
     })? {
         TrieNodeID::Node4 => {
             let node = TrieNode4::from_bytes(f)?;
@@ -366,7 +373,7 @@ fn inner_read_nodetype_at_head<F: Read + Seek>(
             TrieNodeType::Leaf(node)
         }
         TrieNodeID::Empty => {
-            return Err(Error::CorruptionError(
+            return Err(Error::corruption(
                 "read_node_type: stored empty node type".to_string(),
             ))
         }
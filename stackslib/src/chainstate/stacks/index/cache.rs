@@ -42,6 +42,7 @@ use crate::chainstate::stacks::index::node::{
     TrieNode48, TrieNodeID, TrieNodeType, TriePath, TriePtr,
 };
 use crate::chainstate::stacks::index::{trie_sql, ClarityMarfTrieId, Error, MarfTrieId, TrieLeaf};
+use crate::monitoring;
 use crate::util_lib::db::{
     sql_pragma, sqlite_open, tx_begin_immediate, tx_busy_handler, Error as db_error,
     SQLITE_MMAP_SIZE,
@@ -52,6 +53,107 @@ use crate::util_lib::db::{
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct TrieNodeAddr(u32, TriePtr);
 
+/// Hit/miss counts of a `NodeHashCache`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeHashCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl NodeHashCacheStats {
+    /// Fraction of lookups that were served from the cache (0.0 if there were none)
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// Bounded cache of node hashes, keyed by the block ID and trie pointer of the node.  Unlike the
+/// strategy caches, this is used regardless of strategy, so that lookups which resolve long
+/// chains of back-pointers through ancestor tries do not re-read the same hashes from disk.
+/// The oldest entries are evicted first once `capacity` hashes are cached.  A capacity of 0
+/// disables the cache.
+pub struct NodeHashCache {
+    capacity: usize,
+    hashes: HashMap<TrieNodeAddr, TrieHash>,
+    /// insertion order, for eviction
+    order: VecDeque<TrieNodeAddr>,
+    stats: NodeHashCacheStats,
+}
+
+impl NodeHashCache {
+    pub fn new(capacity: usize) -> NodeHashCache {
+        NodeHashCache {
+            capacity,
+            hashes: HashMap::new(),
+            order: VecDeque::new(),
+            stats: NodeHashCacheStats::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    pub fn stats(&self) -> &NodeHashCacheStats {
+        &self.stats
+    }
+
+    /// Look up a node's hash, recording a hit or a miss
+    pub fn load(&mut self, block_id: u32, trieptr: &TriePtr) -> Option<TrieHash> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let hash = self.hashes.get(&TrieNodeAddr(block_id, *trieptr)).cloned();
+        if hash.is_some() {
+            self.stats.hits += 1;
+            monitoring::increment_marf_node_hash_cache_hits();
+        } else {
+            self.stats.misses += 1;
+            monitoring::increment_marf_node_hash_cache_misses();
+        }
+        hash
+    }
+
+    /// Cache a node's hash, evicting the oldest hash if the cache is full
+    pub fn store(&mut self, block_id: u32, trieptr: TriePtr, hash: TrieHash) {
+        if !self.is_enabled() {
+            return;
+        }
+        let addr = TrieNodeAddr(block_id, trieptr);
+        if self.hashes.insert(addr.clone(), hash).is_some() {
+            return;
+        }
+        self.order.push_back(addr);
+        while self.hashes.len() > self.capacity {
+            match self.order.pop_front() {
+                Some(oldest) => {
+                    self.hashes.remove(&oldest);
+                    self.stats.evictions += 1;
+                }
+                None => break,
+            }
+        }
+    }
+}
+
 /// Cache state for all node caching strategies.
 pub struct TrieCacheState<T: MarfTrieId> {
     /// Mapping between trie blob IDs (i.e. rowids) and the MarfTrieId of the trie.  Contents are
@@ -65,6 +167,9 @@ pub struct TrieCacheState<T: MarfTrieId> {
     node_cache: HashMap<TrieNodeAddr, TrieNodeType>,
     /// cached trie root hashes
     hash_cache: HashMap<TrieNodeAddr, TrieHash>,
+
+    /// bounded node hash cache, used by all strategies
+    node_hash_cache: NodeHashCache,
 }
 
 impl<T: MarfTrieId> TrieCacheState<T> {
@@ -74,6 +179,7 @@ impl<T: MarfTrieId> TrieCacheState<T> {
             block_id_cache: HashMap::new(),
             node_cache: HashMap::new(),
             hash_cache: HashMap::new(),
+            node_hash_cache: NodeHashCache::new(0),
         }
     }
 
@@ -193,6 +299,27 @@ impl<T: MarfTrieId> TrieCache<T> {
         }
     }
 
+    /// Use a bounded node hash cache of `capacity` hashes, in addition to this strategy
+    pub fn with_node_hash_cache(mut self, capacity: usize) -> TrieCache<T> {
+        self.state_mut().node_hash_cache = NodeHashCache::new(capacity);
+        self
+    }
+
+    /// Get the bounded node hash cache, which is used regardless of strategy
+    pub fn node_hash_cache(&mut self) -> &mut NodeHashCache {
+        &mut self.state_mut().node_hash_cache
+    }
+
+    /// Get the hit/miss counts of the bounded node hash cache
+    pub fn node_hash_cache_stats(&self) -> NodeHashCacheStats {
+        self.state_ref().node_hash_cache.stats().clone()
+    }
+
+    /// Get the capacity of the bounded node hash cache
+    pub fn node_hash_cache_capacity(&self) -> usize {
+        self.state_ref().node_hash_cache.capacity()
+    }
+
     /// Get the inner trie cache state, as an immutable reference
     fn state_ref(&self) -> &TrieCacheState<T> {
         match self {
@@ -854,4 +981,109 @@ pub mod test {
         );
         assert_eq!(root_hash, root_hash_batched);
     }
+
+    #[test]
+    fn test_node_hash_cache_eviction() {
+        let mut cache = NodeHashCache::new(2);
+        let ptr = |n: u32| TriePtr::new(TrieNodeID::Node4 as u8, 0, n);
+        let hash = |n: u8| TrieHash([n; 32]);
+
+        cache.store(1, ptr(10), hash(1));
+        cache.store(2, ptr(10), hash(2));
+        assert_eq!(cache.load(1, &ptr(10)), Some(hash(1)));
+        assert_eq!(cache.load(1, &ptr(11)), None);
+
+        // re-storing a cached hash does not evict anything
+        cache.store(2, ptr(10), hash(2));
+        assert_eq!(cache.len(), 2);
+
+        // the oldest hash goes first
+        cache.store(3, ptr(10), hash(3));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.load(1, &ptr(10)), None);
+        assert_eq!(cache.load(2, &ptr(10)), Some(hash(2)));
+        assert_eq!(cache.load(3, &ptr(10)), Some(hash(3)));
+
+        assert_eq!(
+            cache.stats(),
+            &NodeHashCacheStats {
+                hits: 3,
+                misses: 2,
+                evictions: 1,
+            }
+        );
+        assert_eq!(cache.stats().hit_rate(), 0.6);
+
+        // a zero-capacity cache stores nothing, and counts nothing
+        let mut disabled = NodeHashCache::new(0);
+        disabled.store(1, ptr(10), hash(1));
+        assert_eq!(disabled.load(1, &ptr(10)), None);
+        assert_eq!(disabled.stats(), &NodeHashCacheStats::default());
+    }
+
+    #[test]
+    fn test_marf_node_hash_cache_backptr_lookups() {
+        let test_data = make_test_insert_data(8, 64);
+        let tip = |i: usize| {
+            let mut block_hash_bytes = [0u8; 32];
+            block_hash_bytes[0..8].copy_from_slice(&(i as u64).to_be_bytes());
+            BlockHeaderHash(block_hash_bytes)
+        };
+
+        for node_hash_cache_size in [0, 64, 4096] {
+            let marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", false)
+                .with_node_hash_cache_size(node_hash_cache_size);
+            let f = TrieFileStorage::open(":memory:", marf_opts).unwrap();
+            let mut marf = MARF::from_storage(f);
+            let mut last_block_header = BlockHeaderHash::sentinel();
+            for (i, block_data) in test_data.iter().enumerate() {
+                marf.begin(&last_block_header, &tip(i)).unwrap();
+                for (key, value) in block_data.iter() {
+                    let path = TriePath::from_key(key);
+                    let leaf = TrieLeaf::from_value(&vec![], *value);
+                    marf.insert_raw(path, leaf).unwrap();
+                }
+                marf.commit().unwrap();
+                last_block_header = tip(i);
+            }
+
+            // old keys are reached through long chains of back-pointers
+            let tip_root_hash = marf.get_root_hash_at(&last_block_header).unwrap();
+            let mut pass_stats = vec![];
+            for _ in 0..2 {
+                for block_data in test_data.iter() {
+                    for (key, value) in block_data.iter() {
+                        let path = TriePath::from_key(key);
+                        let leaf = MARF::get_path(
+                            &mut marf.borrow_storage_backend(),
+                            &last_block_header,
+                            &path,
+                        )
+                        .unwrap()
+                        .unwrap();
+                        assert_eq!(leaf.data.to_vec(), value.to_vec());
+                    }
+                }
+                pass_stats.push(marf.borrow_storage_backend().node_hash_cache_stats());
+            }
+            assert_eq!(
+                marf.get_root_hash_at(&last_block_header).unwrap(),
+                tip_root_hash
+            );
+
+            let stats = pass_stats.pop().unwrap();
+            let first_pass_stats = pass_stats.pop().unwrap();
+            if node_hash_cache_size == 0 {
+                assert_eq!(stats, NodeHashCacheStats::default());
+                continue;
+            }
+            assert!(stats.hits > first_pass_stats.hits);
+            if node_hash_cache_size == 4096 {
+                // everything fits, so the second pass is served entirely from the cache
+                assert_eq!(stats.misses, first_pass_stats.misses);
+                assert_eq!(stats.evictions, 0);
+                assert!(stats.hit_rate() > first_pass_stats.hit_rate());
+            }
+        }
+    }
 }
@@ -0,0 +1,353 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Key/value differences between the tries of two blocks.
+//!
+//! Both tries are walked at once from their roots.  Every non-root node hash commits to the
+//! node's entire subtree, so a pair of children with equal hashes holds identical key/value
+//! pairs and is skipped without being read.  Root hashes also commit to the block's ancestry,
+//! so the roots are always descended into.
+//!
+//! Where the tries are shaped differently, the leaves of both subtrees are read and compared.
+//! The number of leaves read over the whole walk is bounded, so that diffing two unrelated
+//! blocks cannot load an unbounded part of the trie into memory.
+
+use std::collections::BTreeMap;
+
+use stacks_common::types::chainstate::TrieHash;
+
+use crate::chainstate::stacks::index::node::{
+    is_backptr, TrieNodeID, TrieNodeType, TriePath, TriePtr,
+};
+use crate::chainstate::stacks::index::storage::TrieStorageConnection;
+use crate::chainstate::stacks::index::trie::Trie;
+use crate::chainstate::stacks::index::{Error, MARFValue, MarfTrieId};
+
+/// A key whose value differs between two tries
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrieDiffEntry {
+    pub path: TriePath,
+    /// Value in the `from` trie, or None if the key is absent there
+    pub old: Option<MARFValue>,
+    /// Value in the `to` trie, or None if the key is absent there
+    pub new: Option<MARFValue>,
+}
+
+/// The differences between the tries of two blocks, ordered by path
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrieDiff {
+    pub entries: Vec<TrieDiffEntry>,
+    /// Whether the walk stopped early, either because there were more differences than the
+    /// requested limit or because it read more leaves than allowed
+    pub truncated: bool,
+}
+
+/// A node being walked, along with the block whose trie holds it
+pub(crate) struct DiffNode<T: MarfTrieId> {
+    block: T,
+    block_id: Option<u32>,
+    pub(crate) node: TrieNodeType,
+    hash: TrieHash,
+}
+
+impl TrieDiff {
+    /// Compute the key/value differences between the trie of `from` and the trie of `to`,
+    /// stopping once `limit` differences have been found or `max_leaves` leaves have been read.
+    /// Both blocks must be committed, so that their node hashes have been calculated.
+    /// The block that was open in `storage` beforehand is re-opened afterwards.
+    pub fn between<T: MarfTrieId>(
+        storage: &mut TrieStorageConnection<T>,
+        from: &T,
+        to: &T,
+        limit: usize,
+        max_leaves: usize,
+    ) -> Result<TrieDiff, Error> {
+        let (cur_block_hash, cur_block_id) = storage.get_cur_block_and_id();
+
+        let mut diff = TrieDiff {
+            entries: vec![],
+            truncated: false,
+        };
+        let result = if from == to {
+            Ok(())
+        } else {
+            diff.diff_blocks(storage, from, to, limit, max_leaves)
+        };
+
+        // restore
+        storage
+            .open_block_maybe_id(&cur_block_hash, cur_block_id)
+            .map_err(|e| {
+                warn!(
+                    "Failed to re-open {} {:?}: {:?}",
+                    &cur_block_hash, cur_block_id, &e
+                );
+                e
+            })?;
+
+        result.map(|_| diff)
+    }
+
+    fn diff_blocks<T: MarfTrieId>(
+        &mut self,
+        storage: &mut TrieStorageConnection<T>,
+        from: &T,
+        to: &T,
+        limit: usize,
+        max_leaves: usize,
+    ) -> Result<(), Error> {
+        let from_root = DiffNode::root(storage, from)?;
+        let to_root = DiffNode::root(storage, to)?;
+        let mut leaves_left = max_leaves;
+        self.diff_nodes(
+            storage,
+            Some(from_root),
+            Some(to_root),
+            &mut vec![],
+            limit,
+            &mut leaves_left,
+        )?;
+        Ok(())
+    }
+
+    /// Read the leaves below `node`, charging them to `leaves_left`.  Returns None, and marks
+    /// the diff as truncated, if there are more leaves than that.
+    fn read_leaves<T: MarfTrieId>(
+        &mut self,
+        storage: &mut TrieStorageConnection<T>,
+        node: Option<&DiffNode<T>>,
+        prefix: &[u8],
+        leaves_left: &mut usize,
+    ) -> Result<Option<BTreeMap<TriePath, MARFValue>>, Error> {
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(Some(BTreeMap::new())),
+        };
+        let leaves = node.leaves(storage, prefix, leaves_left)?;
+        if leaves.is_none() {
+            self.truncated = true;
+        }
+        Ok(leaves)
+    }
+
+    /// Record a difference.  Returns false if the limit has been reached, in which case the
+    /// walk should stop.
+    fn push(&mut self, entry: TrieDiffEntry, limit: usize) -> bool {
+        if self.entries.len() >= limit {
+            self.truncated = true;
+            return false;
+        }
+        self.entries.push(entry);
+        true
+    }
+
+    /// Diff the subtrees rooted at `old` and `new`, whose nodes are reached by `prefix`.
+    /// Returns false if the walk should stop.
+    fn diff_nodes<T: MarfTrieId>(
+        &mut self,
+        storage: &mut TrieStorageConnection<T>,
+        old: Option<DiffNode<T>>,
+        new: Option<DiffNode<T>>,
+        prefix: &mut Vec<u8>,
+        limit: usize,
+        leaves_left: &mut usize,
+    ) -> Result<bool, Error> {
+        let is_root = prefix.is_empty();
+        if let (Some(old), Some(new)) = (old.as_ref(), new.as_ref()) {
+            if !is_root && old.hash == new.hash {
+                return Ok(true);
+            }
+            if !old.node.is_leaf()
+                && !new.node.is_leaf()
+                && old.node.path_bytes() == new.node.path_bytes()
+            {
+                let prefix_len = prefix.len();
+                prefix.extend_from_slice(old.node.path_bytes());
+                for chr in 0..=255u8 {
+                    let old_child = old.read_child(storage, chr)?;
+                    let new_child = new.read_child(storage, chr)?;
+                    prefix.push(chr);
+                    let proceed =
+                        self.diff_nodes(storage, old_child, new_child, prefix, limit, leaves_left)?;
+                    prefix.pop();
+                    if !proceed {
+                        prefix.truncate(prefix_len);
+                        return Ok(false);
+                    }
+                }
+                prefix.truncate(prefix_len);
+                return Ok(true);
+            }
+        }
+
+        // one side is missing, or the tries are shaped differently here, so compare their
+        // leaves directly
+        let old_leaves = match self.read_leaves(storage, old.as_ref(), prefix, leaves_left)? {
+            Some(leaves) => leaves,
+            None => return Ok(false),
+        };
+        let new_leaves = match self.read_leaves(storage, new.as_ref(), prefix, leaves_left)? {
+            Some(leaves) => leaves,
+            None => return Ok(false),
+        };
+        Ok(self.diff_leaves(old_leaves, new_leaves, limit))
+    }
+
+    /// Record the differences between two sets of leaves.  Returns false if the walk should
+    /// stop.
+    fn diff_leaves(
+        &mut self,
+        mut old: BTreeMap<TriePath, MARFValue>,
+        new: BTreeMap<TriePath, MARFValue>,
+        limit: usize,
+    ) -> bool {
+        let mut changed = vec![];
+        for (path, new_value) in new.into_iter() {
+            match old.remove(&path) {
+                Some(old_value) if old_value == new_value => {}
+                old_value => changed.push(TrieDiffEntry {
+                    path,
+                    old: old_value,
+                    new: Some(new_value),
+                }),
+            }
+        }
+        for (path, old_value) in old.into_iter() {
+            changed.push(TrieDiffEntry {
+                path,
+                old: Some(old_value),
+                new: None,
+            });
+        }
+        changed.sort_by(|a, b| a.path.as_bytes().cmp(b.path.as_bytes()));
+
+        for entry in changed.into_iter() {
+            if !self.push(entry, limit) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl<T: MarfTrieId> DiffNode<T> {
+    /// Read the root node of `block`'s trie
+    pub(crate) fn root(
+        storage: &mut TrieStorageConnection<T>,
+        block: &T,
+    ) -> Result<DiffNode<T>, Error> {
+        storage.open_block(block)?;
+        let (block, block_id) = storage.get_cur_block_and_id();
+        let (node, hash) = Trie::read_root(storage)?;
+        Ok(DiffNode {
+            block,
+            block_id,
+            node,
+            hash,
+        })
+    }
+
+    /// Read the child of this node at `chr`, following a back-pointer if need be
+    pub(crate) fn read_child(
+        &self,
+        storage: &mut TrieStorageConnection<T>,
+        chr: u8,
+    ) -> Result<Option<DiffNode<T>>, Error> {
+        let ptr = match self.node.walk(chr) {
+            Some(ptr) => ptr,
+            None => return Ok(None),
+        };
+        self.read_ptr(storage, &ptr).map(Some)
+    }
+
+    fn read_ptr(
+        &self,
+        storage: &mut TrieStorageConnection<T>,
+        ptr: &TriePtr,
+    ) -> Result<DiffNode<T>, Error> {
+        if is_backptr(ptr.id()) {
+            let block = storage.get_block_from_local_id(ptr.back_block())?.clone();
+            storage.open_block_known_id(&block, ptr.back_block())?;
+            let (node, hash) = storage.read_nodetype(&ptr.from_backptr())?;
+            Ok(DiffNode {
+                block,
+                block_id: Some(ptr.back_block()),
+                node,
+                hash,
+            })
+        } else {
+            storage.open_block_maybe_id(&self.block, self.block_id)?;
+            let (node, hash) = storage.read_nodetype(ptr)?;
+            Ok(DiffNode {
+                block: self.block.clone(),
+                block_id: self.block_id,
+                node,
+                hash,
+            })
+        }
+    }
+
+    /// Collect every leaf in the subtree rooted at this node, which is reached by `prefix`.
+    /// Each leaf is deducted from `leaves_left`; returns None if the subtree has more leaves
+    /// than that.
+    fn leaves(
+        &self,
+        storage: &mut TrieStorageConnection<T>,
+        prefix: &[u8],
+        leaves_left: &mut usize,
+    ) -> Result<Option<BTreeMap<TriePath, MARFValue>>, Error> {
+        let mut leaves = BTreeMap::new();
+        let mut path = prefix.to_vec();
+        if !self.collect_leaves(storage, &mut path, &mut leaves, leaves_left)? {
+            return Ok(None);
+        }
+        Ok(Some(leaves))
+    }
+
+    fn collect_leaves(
+        &self,
+        storage: &mut TrieStorageConnection<T>,
+        path: &mut Vec<u8>,
+        leaves: &mut BTreeMap<TriePath, MARFValue>,
+        leaves_left: &mut usize,
+    ) -> Result<bool, Error> {
+        let path_len = path.len();
+        path.extend_from_slice(self.node.path_bytes());
+        if let TrieNodeType::Leaf(ref leaf) = self.node {
+            if *leaves_left == 0 {
+                return Ok(false);
+            }
+            *leaves_left -= 1;
+            let trie_path = TriePath::from_bytes(path)
+                .ok_or_else(|| Error::corruption(format!("Leaf path has {} bytes", path.len())))?;
+            leaves.insert(trie_path, leaf.data);
+        } else {
+            for ptr in self.node.ptrs().iter() {
+                if ptr.id() == TrieNodeID::Empty as u8 {
+                    continue;
+                }
+                let child = self.read_ptr(storage, ptr)?;
+                path.push(ptr.chr());
+                if !child.collect_leaves(storage, path, leaves, leaves_left)? {
+                    return Ok(false);
+                }
+                path.pop();
+            }
+        }
+        path.truncate(path_len);
+        Ok(true)
+    }
+}
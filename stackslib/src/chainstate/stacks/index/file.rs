@@ -22,7 +22,7 @@ use std::io::{BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 use std::{cmp, env, error, fmt, fs, io, os};
 
 use rusqlite::types::{FromSql, ToSql};
@@ -53,11 +53,46 @@ use crate::util_lib::db::{
 /// Mapping between block IDs and trie offsets
 pub type TrieIdOffsets = HashMap<u32, u64>;
 
+/// When appended trie blobs are synced to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrieSyncPolicy {
+    /// Sync each trie blob as it is appended, so a committed block's trie is always durable
+    EveryBlock,
+    /// Sync once every this many appended trie blobs (and when the file is closed).  If the host
+    /// crashes in between, the tries of up to this many recently-committed blocks can be lost or
+    /// torn even though the trie DB still refers to them, and the node will need to be restored
+    /// from a backup or resynced.
+    EveryNBlocks(u32),
+}
+
+impl Default for TrieSyncPolicy {
+    fn default() -> TrieSyncPolicy {
+        TrieSyncPolicy::EveryBlock
+    }
+}
+
+impl TrieSyncPolicy {
+    /// Number of appended trie blobs per sync
+    pub fn interval(&self) -> u32 {
+        match self {
+            TrieSyncPolicy::EveryBlock => 1,
+            TrieSyncPolicy::EveryNBlocks(n) => (*n).max(1),
+        }
+    }
+}
+
 /// Handle to a flat file containing Trie blobs
 pub struct TrieFileDisk {
     fd: fs::File,
     path: String,
     trie_offsets: TrieIdOffsets,
+    sync_policy: TrieSyncPolicy,
+    /// Number of trie blobs appended since the file was last synced
+    unsynced_blobs: u32,
+    /// Number of syncs done since they were last taken
+    syncs: u64,
+    /// Time spent syncing since it was last taken
+    sync_time: Duration,
 }
 
 /// Handle to a flat in-memory buffer containing Trie blobs (used for testing)
@@ -90,6 +125,10 @@ impl TrieFile {
             fd,
             path: path.to_string(),
             trie_offsets: TrieIdOffsets::new(),
+            sync_policy: TrieSyncPolicy::default(),
+            unsynced_blobs: 0,
+            syncs: 0,
+            sync_time: Duration::ZERO,
         }))
     }
 
@@ -121,6 +160,35 @@ impl TrieFile {
         }
     }
 
+    /// Sync appended trie blobs according to `sync_policy`.  Has no effect on a RAM-backed
+    /// TrieFile.
+    pub fn with_sync_policy(mut self, sync_policy: TrieSyncPolicy) -> TrieFile {
+        if let TrieFile::Disk(ref mut disk) = self {
+            disk.sync_policy = sync_policy;
+        }
+        self
+    }
+
+    /// Sync any trie blobs appended since the last sync to disk
+    pub fn sync(&mut self) -> Result<(), Error> {
+        match self {
+            TrieFile::Disk(ref mut disk) => disk.sync(),
+            TrieFile::RAM(_) => Ok(()),
+        }
+    }
+
+    /// Take the number of syncs done and the total time spent on them, and start counting again
+    /// from zero
+    pub fn take_sync_stats(&mut self) -> (u64, Duration) {
+        match self {
+            TrieFile::Disk(ref mut disk) => (
+                std::mem::take(&mut disk.syncs),
+                std::mem::take(&mut disk.sync_time),
+            ),
+            TrieFile::RAM(_) => (0, Duration::ZERO),
+        }
+    }
+
     /// Get a copy of the path to this TrieFile.
     /// If in RAM, then the path will be ":memory:"
     pub fn get_path(&self) -> String {
@@ -451,7 +519,8 @@ impl TrieFile {
         rows.collect()
     }
 
-    /// Append a serialized trie to the TrieFile.
+    /// Append a serialized trie to the TrieFile, and sync it to disk if the sync policy calls for
+    /// it.
     /// Returns the offset at which it was appended.
     pub fn append_trie_blob(&mut self, db: &Connection, buf: &[u8]) -> Result<u64, Error> {
         let offset = trie_sql::get_external_blobs_length(db)?;
@@ -462,7 +531,10 @@ impl TrieFile {
 
         match self {
             TrieFile::Disk(ref mut data) => {
-                data.fd.sync_data()?;
+                data.unsynced_blobs = data.unsynced_blobs.saturating_add(1);
+                if data.unsynced_blobs >= data.sync_policy.interval() {
+                    data.sync()?;
+                }
             }
             _ => {}
         }
@@ -470,6 +542,34 @@ impl TrieFile {
     }
 }
 
+impl TrieFileDisk {
+    /// Sync any trie blobs appended since the last sync to disk
+    fn sync(&mut self) -> Result<(), Error> {
+        if self.unsynced_blobs == 0 {
+            return Ok(());
+        }
+        let start = Instant::now();
+        self.fd.sync_data()?;
+        self.sync_time = self.sync_time.saturating_add(start.elapsed());
+        self.syncs = self.syncs.saturating_add(1);
+        self.unsynced_blobs = 0;
+        Ok(())
+    }
+}
+
+/// Don't leave trie blobs unsynced when the file is closed
+impl Drop for TrieFileDisk {
+    fn drop(&mut self) {
+        if let Err(e) = self.sync() {
+            error!("Failed to sync trie blobs to disk";
+                "path" => &self.path,
+                "unsynced_blobs" => self.unsynced_blobs,
+                "error" => ?e,
+            );
+        }
+    }
+}
+
 /// Boilerplate Write implementation for TrieFileDisk.  Plumbs through to the inner fd.
 impl Write for TrieFileDisk {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
@@ -26,16 +26,22 @@ use stacks_common::util::hash::Sha512Trunc256Sum;
 use stacks_common::util::log;
 
 use crate::chainstate::stacks::index::bits::{get_leaf_hash, get_node_hash, read_root_hash};
+use crate::chainstate::stacks::index::diff::TrieDiff;
+use crate::chainstate::stacks::index::file::TrieSyncPolicy;
 use crate::chainstate::stacks::index::node::{
     clear_backptr, is_backptr, set_backptr, CursorError, TrieCursor, TrieNode, TrieNode16,
     TrieNode256, TrieNode4, TrieNode48, TrieNodeID, TrieNodeType, TriePath, TriePtr, TRIEPTR_SIZE,
 };
+use crate::chainstate::stacks::index::prefix::{KeyPrefixScan, TriePrefixScan};
+use crate::chainstate::stacks::index::stats::TrieIOStats;
 use crate::chainstate::stacks::index::storage::{
     TrieFileStorage, TrieHashCalculationMode, TrieStorageConnection, TrieStorageTransaction,
 };
 use crate::chainstate::stacks::index::trie::Trie;
+use crate::chainstate::stacks::index::warm::{TrieCacheWarming, TrieCacheWarmingStats};
 use crate::chainstate::stacks::index::{
-    ClarityMarfTrieId, Error, MARFValue, MarfTrieId, TrieHashExtension, TrieLeaf, TrieMerkleProof,
+    trie_sql, ClarityMarfTrieId, Error, MARFValue, MarfTrieId, TrieHashExtension, TrieLeaf,
+    TrieMerkleProof,
 };
 use crate::util_lib::db::Error as db_error;
 
@@ -71,6 +77,15 @@ pub struct MARFOpenOpts {
     pub external_blobs: bool,
     /// unconditionally do a DB migration (used for testing)
     pub force_db_migrate: bool,
+    /// number of node hashes to keep in the bounded node hash cache, which speeds up lookups
+    /// that resolve back-pointers through many ancestor tries (0 disables it)
+    pub node_hash_cache_size: usize,
+    /// when to sync external trie blobs to disk
+    pub sync_policy: TrieSyncPolicy,
+    /// keep an index of the inserted keys, so keys can be listed by prefix.  Only takes effect
+    /// on a MARF with no tries yet; a MARF that already has tries is opened without an index,
+    /// with a warning.  Once kept, the index is kept regardless of this option.
+    pub key_index: bool,
 }
 
 impl MARFOpenOpts {
@@ -80,6 +95,9 @@ impl MARFOpenOpts {
             cache_strategy: "noop".to_string(),
             external_blobs: false,
             force_db_migrate: false,
+            node_hash_cache_size: 0,
+            sync_policy: TrieSyncPolicy::EveryBlock,
+            key_index: false,
         }
     }
 
@@ -93,9 +111,30 @@ impl MARFOpenOpts {
             cache_strategy: cache_strategy.to_string(),
             external_blobs,
             force_db_migrate: false,
+            node_hash_cache_size: 0,
+            sync_policy: TrieSyncPolicy::EveryBlock,
+            key_index: false,
         }
     }
 
+    /// Sync external trie blobs to disk according to `sync_policy`
+    pub fn with_sync_policy(mut self, sync_policy: TrieSyncPolicy) -> MARFOpenOpts {
+        self.sync_policy = sync_policy;
+        self
+    }
+
+    /// Keep an index of the inserted keys (see `MarfConnection::get_with_key_prefix`)
+    pub fn with_key_index(mut self, key_index: bool) -> MARFOpenOpts {
+        self.key_index = key_index;
+        self
+    }
+
+    /// Use a bounded node hash cache of `node_hash_cache_size` hashes
+    pub fn with_node_hash_cache_size(mut self, node_hash_cache_size: usize) -> MARFOpenOpts {
+        self.node_hash_cache_size = node_hash_cache_size;
+        self
+    }
+
     #[cfg(test)]
     pub fn all() -> Vec<MARFOpenOpts> {
         vec![
@@ -155,6 +194,57 @@ pub trait MarfConnection<T: MarfTrieId> {
         self.with_conn(|c| c.get_root_hash_at(block_hash))
     }
 
+    /// Find up to `limit` keys whose values differ between the tries of `from` and `to`,
+    /// reading at most `max_leaves` leaves to do so
+    fn diff(
+        &mut self,
+        from: &T,
+        to: &T,
+        limit: usize,
+        max_leaves: usize,
+    ) -> Result<TrieDiff, Error> {
+        self.with_conn(|c| TrieDiff::between(c, from, to, limit, max_leaves))
+    }
+
+    /// Find up to `limit` leaves at `block` whose trie paths start with `prefix`, in path order,
+    /// along with their values.  If `start_after` is given, only paths after it are returned, so
+    /// a listing can be continued from its last path.  Paths are the hashes of the keys (see
+    /// `TriePath::from_key`), so this lists a range of hashes, not the keys sharing a prefix.
+    fn get_with_path_prefix(
+        &mut self,
+        block: &T,
+        prefix: &[u8],
+        start_after: Option<&TriePath>,
+        limit: usize,
+    ) -> Result<TriePrefixScan, Error> {
+        self.with_conn(|c| TriePrefixScan::at_block(c, block, prefix, start_after, limit))
+    }
+
+    /// Find up to `limit` keys at `block` that start with `prefix`, in key order, along with
+    /// their values.  If `start_after` is given, only keys after it are returned.  The scan
+    /// stops early if it skips too many keys that are not in `block`'s fork, in which case it
+    /// can be continued after its `resume_after` key.  The MARF must keep a key index (see
+    /// `MARFOpenOpts::key_index`).
+    fn get_with_key_prefix(
+        &mut self,
+        block: &T,
+        prefix: &str,
+        start_after: Option<&str>,
+        limit: usize,
+    ) -> Result<KeyPrefixScan, Error> {
+        self.with_conn(|c| KeyPrefixScan::at_block(c, block, prefix, start_after, limit))
+    }
+
+    /// Read the hot keys and recent tries described by `warming` into the node cache, for
+    /// lookups at `tip`
+    fn warm_cache(
+        &mut self,
+        tip: &T,
+        warming: &TrieCacheWarming,
+    ) -> Result<TrieCacheWarmingStats, Error> {
+        self.with_conn(|c| warming.warm(c, tip))
+    }
+
     /// Check if a block can open successfully, i.e.,
     ///   it's a known block, the storage system isn't issueing IOErrors, _and_ it's in the same fork
     ///   as the current block
@@ -173,7 +263,7 @@ pub trait MarfConnection<T: MarfTrieId> {
                 })?;
 
             let actual_block_at_height = MARF::get_block_at_height(conn, bhh_height, &cur_block_hash)?
-                .ok_or_else(|| Error::CorruptionError(format!(
+                .ok_or_else(|| Error::corruption(format!(
                     "ERROR: Could not find block for height {}, but it was returned by MARF::get_block_height()", bhh_height)))?;
 
             if bhh != &actual_block_at_height {
@@ -365,11 +455,10 @@ impl<'a, T: MarfTrieId> MarfTransaction<'a, T> {
         self.storage.open_block(chain_tip)?;
 
         let block_height = if !is_parent_sentinel {
-            let height = MARF::get_block_height_miner_tip(&mut self.storage, chain_tip, chain_tip)?
-                .ok_or(Error::CorruptionError(format!(
-                    "Failed to find block height for `{:?}`",
-                    chain_tip
-                )))?;
+            let height =
+                MARF::get_block_height_miner_tip(&mut self.storage, chain_tip, chain_tip)?.ok_or(
+                    Error::corruption(format!("Failed to find block height for `{:?}`", chain_tip)),
+                )?;
             height
                 .checked_add(1)
                 .expect("FATAL: block height overflow!")
@@ -775,6 +864,19 @@ impl<T: MarfTrieId> MARF<T> {
         }
     }
 
+    /// Record where a walk down the MARF was when it hit a corruption error: the trie it was in,
+    /// the last pointer it followed, and the prefix of the path it had consumed.
+    fn walk_corruption(
+        storage: &TrieStorageConnection<T>,
+        cursor: &TrieCursor<T>,
+        e: Error,
+    ) -> Error {
+        let consumed = cursor.index.min(cursor.path.len());
+        e.with_corruption_path(&cursor.path.as_bytes()[..consumed])
+            .with_corruption_ptr(&cursor.ptr())
+            .with_corruption_block(storage.get_cur_block().to_bytes())
+    }
+
     /// Walk down this MARF at the given block hash, doing a copy-on-write for intermediate nodes in this block's Trie from any prior Tries.
     /// s must point to the last filled-in Trie -- i.e. block_hash points to the _new_ Trie that is
     /// being filled in.
@@ -809,8 +911,10 @@ impl<T: MarfTrieId> MARF<T> {
                                 || clear_backptr(node_ptr.id()) != TrieNodeID::Leaf as u8
                             {
                                 error!("Out-of-path but encountered a non-leaf");
-                                return Err(Error::CorruptionError(
-                                    "Non-leaf encountered at end of path".to_string(),
+                                return Err(MARF::walk_corruption(
+                                    storage,
+                                    &cursor,
+                                    Error::corruption("Non-leaf encountered at end of path"),
                                 ));
                             }
 
@@ -869,7 +973,7 @@ impl<T: MarfTrieId> MARF<T> {
                         }
                         _ => {
                             // some other error (e.g. I/O error)
-                            return Err(e);
+                            return Err(MARF::walk_corruption(storage, &cursor, e));
                         }
                     }
                 }
@@ -877,7 +981,11 @@ impl<T: MarfTrieId> MARF<T> {
         }
 
         trace!("Trie has a cycle");
-        return Err(Error::CorruptionError("Trie has a cycle".to_string()));
+        return Err(MARF::walk_corruption(
+            storage,
+            &cursor,
+            Error::corruption("Trie has a cycle"),
+        ));
     }
 
     /// Walk down this MARF at the given block hash, resolving backptrs to previous tries.
@@ -895,7 +1003,7 @@ impl<T: MarfTrieId> MARF<T> {
         // walk to insertion point
         let mut node = Trie::read_root_nohash(storage).map_err(|e| {
             test_debug!("Failed to read root of {:?}: {:?}", block_hash, &e);
-            e
+            MARF::walk_corruption(storage, &cursor, e)
         })?;
 
         for _ in 0..(cursor.path.len() + 1) {
@@ -912,8 +1020,10 @@ impl<T: MarfTrieId> MARF<T> {
                         None => {
                             // end of path.  Must be at a leaf.
                             if clear_backptr(cursor.ptr().id()) != TrieNodeID::Leaf as u8 {
-                                return Err(Error::CorruptionError(
-                                    "Non-leaf encountered at end of path".to_string(),
+                                return Err(MARF::walk_corruption(
+                                    storage,
+                                    &cursor,
+                                    Error::corruption("Non-leaf encountered at end of path"),
                                 ));
                             }
 
@@ -945,7 +1055,10 @@ impl<T: MarfTrieId> MARF<T> {
                                     // at intermediate node whose child is not present in this trie.
                                     // try to shunt to the prior node that has the child itself.
                                     let (next_node, _, next_node_ptr, _) =
-                                        MARF::walk_backptr(storage, &node, ptr.chr(), &mut cursor)?;
+                                        MARF::walk_backptr(storage, &node, ptr.chr(), &mut cursor)
+                                            .map_err(|e| {
+                                                MARF::walk_corruption(storage, &cursor, e)
+                                            })?;
                                     storage.bench_mut().marf_walk_backptr_finish();
 
                                     // finish taking the step
@@ -963,7 +1076,7 @@ impl<T: MarfTrieId> MARF<T> {
                         _ => {
                             // some other error (e.g. I/O error)
                             storage.bench_mut().marf_walk_from_finish();
-                            return Err(e);
+                            return Err(MARF::walk_corruption(storage, &cursor, e));
                         }
                     }
                 }
@@ -971,7 +1084,11 @@ impl<T: MarfTrieId> MARF<T> {
         }
 
         trace!("Trie has a cycle");
-        return Err(Error::CorruptionError("Trie has a cycle".to_string()));
+        return Err(MARF::walk_corruption(
+            storage,
+            &cursor,
+            Error::corruption("Trie has a cycle"),
+        ));
     }
 
     pub fn format(
@@ -1035,8 +1152,10 @@ impl<T: MarfTrieId> MARF<T> {
             }
             _ => {
                 // Trie invariant violation -- a full path reached a non-leaf
-                return Err(Error::CorruptionError(
-                    "Path reached a non-leaf".to_string(),
+                return Err(MARF::walk_corruption(
+                    storage,
+                    &cursor,
+                    Error::corruption("Path reached a non-leaf"),
                 ));
             }
         }
@@ -1251,6 +1370,10 @@ impl<T: MarfTrieId> MARF<T> {
             return Ok(());
         }
 
+        if conn.key_index {
+            trie_sql::index_keys(conn.sqlite_tx(), keys)?;
+        }
+
         let (cur_block_hash, cur_block_id) = conn.get_cur_block_and_id();
 
         let last = keys.len() - 1;
@@ -1357,7 +1480,11 @@ impl<T: MarfTrieId> MARF<T> {
         }
         let marf_leaf = TrieLeaf::from_value(&[], value);
         let path = TriePath::from_key(key);
-        self.insert_raw(path, marf_leaf)
+        self.insert_raw(path, marf_leaf)?;
+        if self.storage.has_key_index() {
+            trie_sql::index_keys(self.storage.sqlite_conn(), &[key.to_string()])?;
+        }
+        Ok(())
     }
 
     /// Insert the given (key, value) pair into the MARF.  Inserting the same key twice silently
@@ -1540,6 +1667,11 @@ impl<T: MarfTrieId> MARF<T> {
         self.storage.connection().get_root_hash_at(block_hash)
     }
 
+    /// Take the storage I/O this MARF has done since they were last taken
+    pub fn take_io_stats(&mut self) -> TrieIOStats {
+        self.storage.take_io_stats()
+    }
+
     /// Convert to the inner sqlite connection
     pub fn into_sqlite_conn(self) -> Connection {
         self.storage.into_sqlite_conn()
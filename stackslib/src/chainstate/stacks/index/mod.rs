@@ -26,18 +26,23 @@ use stacks_common::types::chainstate::{
 use stacks_common::util::hash::to_hex;
 use stacks_common::util::log;
 
+use crate::chainstate::stacks::index::node::TriePtr;
 use crate::util_lib::db::Error as db_error;
 
 pub mod bits;
 pub mod cache;
+pub mod diff;
 pub mod file;
 pub mod marf;
 pub mod node;
+pub mod prefix;
 pub mod profile;
 pub mod proofs;
+pub mod stats;
 pub mod storage;
 pub mod trie;
 pub mod trie_sql;
+pub mod warm;
 
 #[cfg(test)]
 pub mod test;
@@ -302,6 +307,37 @@ impl MARFValue {
     }
 }
 
+/// Where in the MARF a corruption was found.  The reader that detects the corruption only
+/// knows what it failed to decode; the pointer, trie and path are filled in as the error is
+/// propagated up through the storage layer and the MARF walk that was reading it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorruptionContext {
+    /// What was found to be corrupt
+    pub reason: String,
+    /// The block whose trie was being read
+    pub block_id: Option<[u8; 32]>,
+    /// The pointer to the node that was being read
+    pub ptr: Option<TriePtr>,
+    /// The prefix of the path that was walked before the corruption was found
+    pub path: Option<Vec<u8>>,
+}
+
+impl fmt::Display for CorruptionContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.reason)?;
+        if let Some(ref block_id) = self.block_id {
+            write!(f, "; block {}", to_hex(block_id))?;
+        }
+        if let Some(ref ptr) = self.ptr {
+            write!(f, "; ptr {:?}", ptr)?;
+        }
+        if let Some(ref path) = self.path {
+            write!(f, "; path prefix '{}'", to_hex(path))?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     NotOpenedError,
@@ -312,7 +348,7 @@ pub enum Error {
     BackptrNotFoundError,
     ExistsError,
     BadSeekValue,
-    CorruptionError(String),
+    CorruptionError(Box<CorruptionContext>),
     BlockHashMapCorruptionError(Option<Box<Error>>),
     ReadOnlyError,
     UnconfirmedError,
@@ -323,6 +359,56 @@ pub enum Error {
     CursorError(node::CursorError),
     RestoreMarfBlockError(Box<Error>),
     NonMatchingForks([u8; 32], [u8; 32]),
+    NoKeyIndex,
+}
+
+impl Error {
+    /// A corruption error, without any context yet
+    pub fn corruption<S: Into<String>>(reason: S) -> Error {
+        Error::CorruptionError(Box::new(CorruptionContext {
+            reason: reason.into(),
+            block_id: None,
+            ptr: None,
+            path: None,
+        }))
+    }
+
+    /// Where the corruption was found, if this is a corruption error
+    pub fn corruption_context(&self) -> Option<&CorruptionContext> {
+        match self {
+            Error::CorruptionError(ref context) => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Record the block whose trie was being read, if this is a corruption error and the block
+    /// isn't already known.  Other errors are returned as they are.
+    pub fn with_corruption_block(mut self, block_id: [u8; 32]) -> Error {
+        if let Error::CorruptionError(ref mut context) = self {
+            context.block_id.get_or_insert(block_id);
+        }
+        self
+    }
+
+    /// Record the pointer to the node that was being read, if this is a corruption error and the
+    /// pointer isn't already known.  Other errors are returned as they are.
+    pub fn with_corruption_ptr(mut self, ptr: &TriePtr) -> Error {
+        if let Error::CorruptionError(ref mut context) = self {
+            context.ptr.get_or_insert(*ptr);
+        }
+        self
+    }
+
+    /// Record the prefix of the path that was walked, if this is a corruption error and the path
+    /// isn't already known.  Other errors are returned as they are.
+    pub fn with_corruption_path(mut self, path: &[u8]) -> Error {
+        if let Error::CorruptionError(ref mut context) = self {
+            if context.path.is_none() {
+                context.path = Some(path.to_vec());
+            }
+        }
+        self
+    }
 }
 
 impl From<io::Error> for Error {
@@ -346,7 +432,7 @@ impl From<db_error> for Error {
         match e {
             db_error::SqliteError(se) => Error::SQLError(se),
             db_error::NotFoundError => Error::NotFoundError,
-            _ => Error::CorruptionError(format!("{}", &e)),
+            _ => Error::corruption(format!("{}", &e)),
         }
     }
 }
@@ -356,7 +442,7 @@ impl fmt::Display for Error {
         match *self {
             Error::IOError(ref e) => fmt::Display::fmt(e, f),
             Error::SQLError(ref e) => fmt::Display::fmt(e, f),
-            Error::CorruptionError(ref s) => fmt::Display::fmt(s, f),
+            Error::CorruptionError(ref context) => fmt::Display::fmt(context, f),
             Error::CursorError(ref e) => fmt::Display::fmt(e, f),
             Error::BlockHashMapCorruptionError(ref opt_e) => {
                 f.write_str("Corrupted MARF BlockHashMap")?;
@@ -388,6 +474,10 @@ impl fmt::Display for Error {
             Error::RequestedIdentifierForExtensionTrie => {
                 write!(f, "BUG: MARF requested the identifier for a RAM trie")
             }
+            Error::NoKeyIndex => write!(
+                f,
+                "MARF has no key index, and one can only be added before any trie is stored"
+            ),
         }
     }
 }
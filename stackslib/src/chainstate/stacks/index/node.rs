@@ -1002,7 +1002,7 @@ impl TrieNode for TrieNode48 {
         let l_indexes = r.read(&mut indexes).map_err(Error::IOError)?;
 
         if l_indexes != 256 {
-            return Err(Error::CorruptionError(
+            return Err(Error::corruption(
                 "Node48: Failed to read 256 indexes".to_string(),
             ));
         }
@@ -1027,7 +1027,7 @@ impl TrieNode for TrieNode48 {
                 || (indexes_slice[ptr.chr() as usize] >= 0
                     && indexes_slice[ptr.chr() as usize] < 48))
             {
-                return Err(Error::CorruptionError(
+                return Err(Error::corruption(
                     "Node48: corrupt index array: invalid index value".to_string(),
                 ));
             }
@@ -1042,7 +1042,7 @@ impl TrieNode for TrieNode48 {
                     && (indexes_slice[i] as usize) < ptrs_slice.len()
                     && ptrs_slice[indexes_slice[i] as usize].id() != TrieNodeID::Empty as u8))
             {
-                return Err(Error::CorruptionError(
+                return Err(Error::corruption(
                     "Node48: corrupt index array: index points to empty node".to_string(),
                 ));
             }
@@ -1187,16 +1187,11 @@ impl TrieNode for TrieLeaf {
         let l_idbuf = r.read(&mut idbuf).map_err(Error::IOError)?;
 
         if l_idbuf != 1 {
-            return Err(Error::CorruptionError(
-                "Leaf: failed to read ID".to_string(),
-            ));
+            return Err(Error::corruption("Leaf: failed to read ID".to_string()));
         }
 
         if clear_backptr(idbuf[0]) != TrieNodeID::Leaf as u8 {
-            return Err(Error::CorruptionError(format!(
-                "Leaf: bad ID {:x}",
-                idbuf[0]
-            )));
+            return Err(Error::corruption(format!("Leaf: bad ID {:x}", idbuf[0])));
         }
 
         let path = path_from_bytes(r)?;
@@ -1204,7 +1199,7 @@ impl TrieNode for TrieLeaf {
         let l_leaf_data = r.read(&mut leaf_data).map_err(Error::IOError)?;
 
         if l_leaf_data != (MARF_VALUE_ENCODED_SIZE as usize) {
-            return Err(Error::CorruptionError(format!(
+            return Err(Error::corruption(format!(
                 "Leaf: read only {} out of {} bytes",
                 l_leaf_data, MARF_VALUE_ENCODED_SIZE
             )));
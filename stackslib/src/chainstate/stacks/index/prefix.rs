@@ -0,0 +1,326 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The leaves of a block's trie that start with a given prefix, either of their trie paths
+//! (`TriePrefixScan`) or of their keys (`KeyPrefixScan`).
+//!
+//! The path walk follows the prefix down from the root of the block's trie, and then visits the
+//! subtree beneath it in path order, following back-pointers into ancestor tries.  Every node
+//! in a trie has at least one leaf beneath it, so the walk reads at most one root-to-leaf path
+//! per leaf it returns (plus the path to the first leaf after `start_after`), whatever the size
+//! of the subtree.
+//!
+//! Paths are the hashes of the keys (see `TriePath::from_key`), and the key strings are not
+//! stored in the trie.  So keys that share a prefix, such as the data-map entries of one
+//! contract, are spread across the whole trie, and the path walk cannot find them.  To list
+//! those, a MARF opened with `MARFOpenOpts::key_index` also records every key it inserts in a
+//! sqlite table ordered by key.  A key scan reads the indexed keys in the prefix's range, and
+//! looks each one up in the block's trie by its path, skipping keys that are only in other forks
+//! or in later blocks.  The index is shared by all forks and never pruned, so a key scan stops
+//! once it has skipped `MAX_SKIPPED_KEYS` keys, and reports where to resume: each call makes
+//! about `limit + MAX_SKIPPED_KEYS` lookups at most.
+
+use crate::chainstate::stacks::index::diff::DiffNode;
+use crate::chainstate::stacks::index::marf::MARF;
+use crate::chainstate::stacks::index::node::{TrieNodeType, TriePath};
+use crate::chainstate::stacks::index::storage::TrieStorageConnection;
+use crate::chainstate::stacks::index::{Error, MARFValue, MarfTrieId};
+
+/// Number of indexed keys read from the database at a time
+const KEY_INDEX_PAGE_SIZE: u32 = 1024;
+
+/// Number of indexed keys that are not at the block a key scan may skip before it stops
+pub const MAX_SKIPPED_KEYS: usize = 4096;
+
+/// A leaf at a block, with its value
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriePrefixEntry {
+    pub path: TriePath,
+    pub value: MARFValue,
+}
+
+/// The leaves at a block whose paths start with a prefix, in path order
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriePrefixScan {
+    pub entries: Vec<TriePrefixEntry>,
+    /// Whether there were more leaves than the requested limit.  The scan can be continued by
+    /// starting after the last entry's path.
+    pub truncated: bool,
+}
+
+impl TriePrefixScan {
+    /// Find the first `limit` leaves at `block` whose paths start with `prefix`, skipping paths
+    /// up to and including `start_after` if it is given.  A prefix longer than a path matches
+    /// nothing.  The block that was open in `storage` beforehand is re-opened afterwards.
+    pub fn at_block<T: MarfTrieId>(
+        storage: &mut TrieStorageConnection<T>,
+        block: &T,
+        prefix: &[u8],
+        start_after: Option<&TriePath>,
+        limit: usize,
+    ) -> Result<TriePrefixScan, Error> {
+        let (cur_block_hash, cur_block_id) = storage.get_cur_block_and_id();
+
+        let mut scan = TriePrefixScan {
+            entries: vec![],
+            truncated: false,
+        };
+        let result = DiffNode::root(storage, block).and_then(|root| {
+            let bounds = ScanBounds {
+                prefix,
+                start_after: start_after.map(|path| &path.as_bytes()[..]),
+            };
+            scan.walk(storage, &root, &mut vec![], &bounds, limit)
+        });
+
+        // restore
+        storage
+            .open_block_maybe_id(&cur_block_hash, cur_block_id)
+            .map_err(|e| {
+                warn!(
+                    "Failed to re-open {} {:?}: {:?}",
+                    &cur_block_hash, cur_block_id, &e
+                );
+                e
+            })?;
+
+        result.map(|_| scan)
+    }
+
+    /// Collect the leaves within `bounds` beneath `node`, which is reached by `path`.
+    /// Returns false if the limit has been reached, in which case the walk should stop.
+    fn walk<T: MarfTrieId>(
+        &mut self,
+        storage: &mut TrieStorageConnection<T>,
+        node: &DiffNode<T>,
+        path: &mut Vec<u8>,
+        bounds: &ScanBounds,
+        limit: usize,
+    ) -> Result<bool, Error> {
+        let path_len = path.len();
+        path.extend_from_slice(node.node.path_bytes());
+        let proceed = self.walk_subtree(storage, node, path, bounds, limit);
+        path.truncate(path_len);
+        proceed
+    }
+
+    fn walk_subtree<T: MarfTrieId>(
+        &mut self,
+        storage: &mut TrieStorageConnection<T>,
+        node: &DiffNode<T>,
+        path: &mut Vec<u8>,
+        bounds: &ScanBounds,
+        limit: usize,
+    ) -> Result<bool, Error> {
+        if !bounds.may_contain(path) {
+            return Ok(true);
+        }
+        if let TrieNodeType::Leaf(ref leaf) = node.node {
+            if !bounds.contains_leaf(path) {
+                return Ok(true);
+            }
+            if self.entries.len() >= limit {
+                self.truncated = true;
+                return Ok(false);
+            }
+            let trie_path = TriePath::from_bytes(path)
+                .ok_or_else(|| Error::corruption(format!("Leaf path has {} bytes", path.len())))?;
+            self.entries.push(TriePrefixEntry {
+                path: trie_path,
+                value: leaf.data,
+            });
+            return Ok(true);
+        }
+        for chr in bounds.first_child(path)..=255u8 {
+            if !bounds.may_contain_child(path, chr) {
+                continue;
+            }
+            let child = match node.read_child(storage, chr)? {
+                Some(child) => child,
+                None => continue,
+            };
+            path.push(chr);
+            let proceed = self.walk(storage, &child, path, bounds, limit);
+            path.pop();
+            if !proceed? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// A key at a block, with its value
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyPrefixEntry {
+    pub key: String,
+    pub value: MARFValue,
+}
+
+/// The keys at a block that start with a prefix, in key order
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyPrefixScan {
+    pub entries: Vec<KeyPrefixEntry>,
+    /// Set if the scan stopped before the end of the prefix's keys, either because there were
+    /// more keys than the requested limit, or because it skipped `MAX_SKIPPED_KEYS` keys that
+    /// are not at the block.  The scan can be continued by starting after this key.
+    pub resume_after: Option<String>,
+}
+
+impl KeyPrefixScan {
+    /// Find the first `limit` keys at `block` that start with `prefix`, skipping keys up to and
+    /// including `start_after` if it is given.  A `limit` of 0 finds nothing.  The MARF must keep
+    /// a key index.  The block that was open in `storage` beforehand is re-opened afterwards.
+    pub fn at_block<T: MarfTrieId>(
+        storage: &mut TrieStorageConnection<T>,
+        block: &T,
+        prefix: &str,
+        start_after: Option<&str>,
+        limit: usize,
+    ) -> Result<KeyPrefixScan, Error> {
+        if !storage.key_index {
+            return Err(Error::NoKeyIndex);
+        }
+        if limit == 0 {
+            return Ok(KeyPrefixScan {
+                entries: vec![],
+                resume_after: None,
+            });
+        }
+        let (cur_block_hash, cur_block_id) = storage.get_cur_block_and_id();
+
+        let result = storage
+            .open_block(block)
+            .and_then(|_| KeyPrefixScan::scan(storage, block, prefix, start_after, limit));
+
+        // restore
+        storage
+            .open_block_maybe_id(&cur_block_hash, cur_block_id)
+            .map_err(|e| {
+                warn!(
+                    "Failed to re-open {} {:?}: {:?}",
+                    &cur_block_hash, cur_block_id, &e
+                );
+                e
+            })?;
+
+        result
+    }
+
+    fn scan<T: MarfTrieId>(
+        storage: &mut TrieStorageConnection<T>,
+        block: &T,
+        prefix: &str,
+        start_after: Option<&str>,
+        limit: usize,
+    ) -> Result<KeyPrefixScan, Error> {
+        let mut scan = KeyPrefixScan {
+            entries: vec![],
+            resume_after: None,
+        };
+        let mut skipped = 0;
+        let (mut start, mut include_start) = match start_after {
+            Some(start_after) if start_after >= prefix => (start_after.to_string(), false),
+            _ => (prefix.to_string(), true),
+        };
+        loop {
+            let keys = storage.get_indexed_keys(&start, include_start, KEY_INDEX_PAGE_SIZE)?;
+            let last_page = keys.len() < KEY_INDEX_PAGE_SIZE as usize;
+            for key in keys.into_iter() {
+                if !key.starts_with(prefix) {
+                    // past the keys with this prefix
+                    return Ok(scan);
+                }
+                match MARF::get_by_key(storage, block, &key)? {
+                    Some(value) => {
+                        if scan.entries.len() >= limit {
+                            scan.resume_after = scan.entries.last().map(|entry| entry.key.clone());
+                            return Ok(scan);
+                        }
+                        scan.entries.push(KeyPrefixEntry {
+                            key: key.clone(),
+                            value,
+                        });
+                    }
+                    None => {
+                        skipped += 1;
+                        if skipped >= MAX_SKIPPED_KEYS {
+                            scan.resume_after = Some(key);
+                            return Ok(scan);
+                        }
+                    }
+                }
+                start = key;
+                include_start = false;
+            }
+            if last_page {
+                return Ok(scan);
+            }
+        }
+    }
+}
+
+/// The paths a scan is after: those starting with `prefix`, and coming after `start_after`
+struct ScanBounds<'a> {
+    prefix: &'a [u8],
+    start_after: Option<&'a [u8]>,
+}
+
+impl ScanBounds<'_> {
+    /// Whether paths starting with `path` may be within bounds
+    fn may_contain(&self, path: &[u8]) -> bool {
+        let len = path.len().min(self.prefix.len());
+        if path[..len] != self.prefix[..len] {
+            return false;
+        }
+        match self.start_after {
+            Some(start_after) => {
+                let len = path.len().min(start_after.len());
+                path[..len] >= start_after[..len]
+            }
+            None => true,
+        }
+    }
+
+    /// Whether paths starting with `path` followed by `chr` may be within bounds
+    fn may_contain_child(&self, path: &[u8], chr: u8) -> bool {
+        match self.prefix.get(path.len()) {
+            Some(prefix_chr) => *prefix_chr == chr,
+            None => true,
+        }
+    }
+
+    /// The lowest child of a node reached by `path` that may be within bounds
+    fn first_child(&self, path: &[u8]) -> u8 {
+        match self.start_after {
+            Some(start_after) if start_after.starts_with(path) => {
+                start_after.get(path.len()).copied().unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Whether the leaf at `path`, which `may_contain`, is within bounds
+    fn contains_leaf(&self, path: &[u8]) -> bool {
+        if path.len() < self.prefix.len() {
+            return false;
+        }
+        match self.start_after {
+            Some(start_after) => path > start_after,
+            None => true,
+        }
+    }
+}
@@ -524,7 +524,7 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         let ancestor_height =
             MARF::get_block_height_miner_tip(storage, &ancestor_block_hash, &block_header)?
                 .ok_or_else(|| {
-                    Error::CorruptionError(format!(
+                    Error::corruption(format!(
                         "Could not find block height of ancestor block {} from {}",
                         &ancestor_block_hash, &block_header
                     ))
@@ -532,7 +532,7 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         let mut current_height =
             MARF::get_block_height_miner_tip(storage, &block_header, &block_header)?.ok_or_else(
                 || {
-                    Error::CorruptionError(format!(
+                    Error::corruption(format!(
                         "Could not find block height of current block {} from {}",
                         &block_header, &block_header
                     ))
@@ -593,7 +593,7 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
 
             block_header = MARF::get_block_at_height(storage, current_height, &block_header)?
                 .ok_or_else(|| {
-                    Error::CorruptionError(format!(
+                    Error::corruption(format!(
                         "Could not find block at height of {}",
                         current_height
                     ))
@@ -621,7 +621,7 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
                     let root_hash = get_node_hash(node256.as_ref(), &child_hashes, storage);
                     root_hash
                 } else {
-                    return Err(Error::CorruptionError(format!(
+                    return Err(Error::corruption(format!(
                         "Root node at {:?} is not a TrieNode256",
                         &block_header
                     )));
@@ -1410,7 +1410,7 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
                                 CursorError::BackptrEncountered(ptr) => {
                                     // expect backptr
                                     if !is_backptr(ptr.id()) {
-                                        return Err(Error::CorruptionError(format!(
+                                        return Err(Error::corruption(format!(
                                             "Failed to walk 0x{:02x} -- got non-backptr",
                                             ptr.chr()
                                         )));
@@ -1432,7 +1432,7 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         }
 
         trace!("Trie has a cycle");
-        return Err(Error::CorruptionError("Trie has a cycle".to_string()));
+        return Err(Error::corruption("Trie has a cycle".to_string()));
     }
 
     /// Make a merkle proof of inclusion from a path.
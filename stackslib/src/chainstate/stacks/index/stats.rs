@@ -0,0 +1,88 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+/// Counters of the work trie storage does, accumulated until they are taken with
+/// `TrieFileStorage::take_io_stats()`.  Unlike `TrieBenchmark`, these are always collected, so
+/// that the storage cost of processing each block can be logged and exported as metrics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrieIOStats {
+    /// Number of non-leaf nodes read, from RAM, the cache or disk
+    pub nodes_read: u64,
+    /// Number of leaves read, from RAM, the cache or disk
+    pub leaves_read: u64,
+    /// Number of back-pointers followed into an ancestor trie
+    pub backptr_hops: u64,
+    /// Number of nodes and node hashes that had to be loaded from disk
+    pub disk_reads: u64,
+    /// Number of bytes loaded from disk
+    pub bytes_read: u64,
+    /// Number of nodes (including leaves) written to the uncommitted trie
+    pub nodes_written: u64,
+    /// Number of bytes of trie data flushed to disk
+    pub bytes_written: u64,
+    /// Number of times the trie blob file was synced to disk
+    pub syncs: u64,
+    /// Microseconds spent syncing the trie blob file to disk
+    pub sync_micros: u64,
+    /// Microseconds spent flushing tries to disk, including any syncs
+    pub flush_micros: u64,
+}
+
+impl TrieIOStats {
+    /// Record loading `num_bytes` from disk
+    pub fn record_disk_read(&mut self, num_bytes: usize) {
+        self.disk_reads = self.disk_reads.saturating_add(1);
+        self.bytes_read = self
+            .bytes_read
+            .saturating_add(u64::try_from(num_bytes).unwrap_or(u64::MAX));
+    }
+
+    /// The counters, by name
+    pub fn counters(&self) -> [(&'static str, u64); 10] {
+        [
+            ("nodes_read", self.nodes_read),
+            ("leaves_read", self.leaves_read),
+            ("backptr_hops", self.backptr_hops),
+            ("disk_reads", self.disk_reads),
+            ("bytes_read", self.bytes_read),
+            ("nodes_written", self.nodes_written),
+            ("bytes_written", self.bytes_written),
+            ("syncs", self.syncs),
+            ("sync_micros", self.sync_micros),
+            ("flush_micros", self.flush_micros),
+        ]
+    }
+
+    /// Record flushing `num_bytes` to disk
+    pub fn record_flush(&mut self, num_bytes: usize) {
+        self.bytes_written = self
+            .bytes_written
+            .saturating_add(u64::try_from(num_bytes).unwrap_or(u64::MAX));
+    }
+
+    /// Record a flush that took `flush_time`, during which the trie blob file was synced `syncs`
+    /// times, taking `sync_time`
+    pub fn record_flush_time(&mut self, flush_time: Duration, syncs: u64, sync_time: Duration) {
+        self.flush_micros = self
+            .flush_micros
+            .saturating_add(u64::try_from(flush_time.as_micros()).unwrap_or(u64::MAX));
+        self.syncs = self.syncs.saturating_add(syncs);
+        self.sync_micros = self
+            .sync_micros
+            .saturating_add(u64::try_from(sync_time.as_micros()).unwrap_or(u64::MAX));
+    }
+}
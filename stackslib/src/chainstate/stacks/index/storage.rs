@@ -21,7 +21,7 @@ use std::io::{BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 use std::{cmp, env, error, fmt, fs, io, os};
 
 use rusqlite::types::{FromSql, ToSql};
@@ -48,6 +48,7 @@ use crate::chainstate::stacks::index::node::{
     TrieNode48, TrieNodeID, TrieNodeType, TriePath, TriePtr,
 };
 use crate::chainstate::stacks::index::profile::TrieBenchmark;
+use crate::chainstate::stacks::index::stats::TrieIOStats;
 use crate::chainstate::stacks::index::trie::Trie;
 use crate::chainstate::stacks::index::{
     trie_sql, BlockMap, ClarityMarfTrieId, Error, MarfTrieId, TrieHashExtension, TrieHasher,
@@ -391,6 +392,14 @@ pub struct TrieRAM<T: MarfTrieId> {
     /// does this TrieRAM represent data temporarily moved out of another TrieRAM?
     is_moved: bool,
 
+    /// Non-leaf nodes whose stored hash was computed by `calculate_node_hashes()`, or loaded
+    /// along with a committed trie, and which have not been rewritten since.  The hashes of these
+    /// subtrees can be reused instead of being recalculated.  Any write to a node evicts it, and
+    /// since every write also rewrites the node's ancestors on the path to the root, only the
+    /// modified paths get re-hashed.  This is what keeps re-committing an unconfirmed trie (once
+    /// per microblock) from re-hashing the whole trie each time.
+    hashed_nodes: HashSet<u32>,
+
     parent: T,
 }
 
@@ -415,6 +424,8 @@ impl<T: MarfTrieId> TrieRAM<T> {
 
             is_moved: false,
 
+            hashed_nodes: HashSet::new(),
+
             parent: parent.clone(),
         }
     }
@@ -439,6 +450,8 @@ impl<T: MarfTrieId> TrieRAM<T> {
 
             is_moved: false,
 
+            hashed_nodes: HashSet::new(),
+
             parent: parent,
         }
     }
@@ -452,7 +465,8 @@ impl<T: MarfTrieId> TrieRAM<T> {
     ///
     /// Do not call directly; instead, use `with_reinstated_data()`.
     fn move_to(&mut self) -> TrieRAM<T> {
-        let moved_data = std::mem::replace(&mut self.data, vec![]);
+        let moved_data = std::mem::take(&mut self.data);
+        let moved_hashed_nodes = std::mem::take(&mut self.hashed_nodes);
         TrieRAM {
             data: moved_data,
             block_header: self.block_header.clone(),
@@ -471,6 +485,8 @@ impl<T: MarfTrieId> TrieRAM<T> {
 
             is_moved: true,
 
+            hashed_nodes: moved_hashed_nodes,
+
             parent: self.parent.clone(),
         }
     }
@@ -484,6 +500,7 @@ impl<T: MarfTrieId> TrieRAM<T> {
         assert!(other.is_moved);
         assert_eq!(self.block_header, other.block_header);
         let _ = std::mem::replace(&mut self.data, other.data);
+        let _ = std::mem::replace(&mut self.hashed_nodes, other.hashed_nodes);
     }
 
     /// Temporarily re-instate this TrieRAM's data as the `uncommitted_writes` field in a given storage
@@ -751,8 +768,13 @@ impl<T: MarfTrieId> TrieRAM<T> {
                         .bench
                         .write_children_hashes_empty_finish(start_time);
                 } else if !is_backptr(ptr.id()) {
-                    // hash is the hash of this node's children
-                    let node_hash = self.calculate_node_hashes(storage_tx, ptr.ptr() as u64)?;
+                    // hash is the hash of this node's children.  If this subtree was hashed
+                    // before and has not been modified since, then its stored hash is still valid.
+                    let node_hash = if self.hashed_nodes.contains(&ptr.ptr()) {
+                        self.read_node_hash(ptr)?
+                    } else {
+                        self.calculate_node_hashes(storage_tx, ptr.ptr() as u64)?
+                    };
 
                     // count the time taken to store the hash towards the
                     // write_children_hashes_same_benchmark
@@ -772,6 +794,7 @@ impl<T: MarfTrieId> TrieRAM<T> {
                     {
                         // need to store this hash too, since we deferred calculation
                         self.write_node_hash(ptr.ptr(), node_hash)?;
+                        self.hashed_nodes.insert(ptr.ptr());
                     }
 
                     storage_tx
@@ -918,7 +941,7 @@ impl<T: MarfTrieId> TrieRAM<T> {
                 }
             }
         } else {
-            return Err(Error::CorruptionError(
+            return Err(Error::corruption(
                 "First TrieRAM node is not a Node256".to_string(),
             ));
         }
@@ -960,7 +983,20 @@ impl<T: MarfTrieId> TrieRAM<T> {
             data.push((next_node, next_hash));
         }
 
-        Ok(TrieRAM::from_data((*bhh).clone(), data, parent_hash))
+        let mut trie_ram = TrieRAM::from_data((*bhh).clone(), data, parent_hash);
+
+        // the stored hashes of the non-root interior nodes were calculated when the trie was
+        // committed, so they remain valid until those nodes are written to again.  The root's
+        // stored hash is the MARF root hash, not the trie root hash, so it is always recalculated.
+        trie_ram.hashed_nodes = trie_ram
+            .data
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, (node, _))| !node.is_leaf())
+            .map(|(ptr, _)| ptr as u32)
+            .collect();
+        Ok(trie_ram)
     }
 
     /// Hint as to how many entries to allocate for the inner Vec when creating a TrieRAM
@@ -978,6 +1014,7 @@ impl<T: MarfTrieId> TrieRAM<T> {
         }
 
         self.data.clear();
+        self.hashed_nodes.clear();
         Ok(())
     }
 
@@ -1061,6 +1098,7 @@ impl<T: MarfTrieId> TrieRAM<T> {
         );
 
         self.write_count += 1;
+        self.hashed_nodes.remove(&node_array_ptr);
         match node {
             TrieNodeType::Leaf(_) => {
                 self.write_leaf_count += 1;
@@ -1219,6 +1257,8 @@ pub struct TrieStorageConnection<'a, T: MarfTrieId> {
     cache: &'a mut TrieCache<T>,
     bench: &'a mut TrieBenchmark,
     pub hash_calculation_mode: TrieHashCalculationMode,
+    /// whether inserted keys are recorded in the key index
+    pub key_index: bool,
 
     /// row ID of a trie that represents unconfirmed state (i.e. trie state that will never become
     /// part of the MARF, but nevertheless represents a persistent scratch space).  If this field
@@ -1259,6 +1299,9 @@ pub struct TrieStorageTransientData<T: MarfTrieId> {
     write_node_count: u64,
     write_leaf_count: u64,
 
+    /// Storage I/O done since the stats were last taken
+    io_stats: TrieIOStats,
+
     /// List of ancestral trie root hashes that must be hashed with the `uncommitted_writes` root node
     /// hash to produce the MarfTrieId for the trie when it gets written to disk.  This is
     /// maintained by the MARF whenever it needs to update the trie root hash after a leaf insert,
@@ -1285,6 +1328,7 @@ pub struct TrieFileStorage<T: MarfTrieId> {
     cache: TrieCache<T>,
     bench: TrieBenchmark,
     hash_calculation_mode: TrieHashCalculationMode,
+    key_index: bool,
 
     // used in testing in order to short-circuit block-height lookups
     //   when the trie struct is tested outside of marf.rs usage
@@ -1331,6 +1375,7 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
             cache: &mut self.cache,
             bench: &mut self.bench,
             hash_calculation_mode: self.hash_calculation_mode,
+            key_index: self.key_index,
             unconfirmed_block_id: None,
 
             #[cfg(test)]
@@ -1352,6 +1397,7 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
             cache: &mut self.cache,
             bench: &mut self.bench,
             hash_calculation_mode: self.hash_calculation_mode,
+            key_index: self.key_index,
             unconfirmed_block_id: None,
 
             #[cfg(test)]
@@ -1363,6 +1409,11 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         &self.db
     }
 
+    /// Whether inserted keys are recorded in the key index
+    pub fn has_key_index(&self) -> bool {
+        self.key_index
+    }
+
     pub fn sqlite_tx<'a>(&'a mut self) -> Result<Transaction<'a>, db_error> {
         tx_begin_immediate(&mut self.db)
     }
@@ -1419,7 +1470,9 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         }
 
         let mut blobs = if marf_opts.external_blobs {
-            Some(TrieFile::from_db_path(&db_path, readonly)?)
+            Some(
+                TrieFile::from_db_path(&db_path, readonly)?.with_sync_policy(marf_opts.sync_policy),
+            )
         } else {
             None
         };
@@ -1437,13 +1490,25 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
             panic!("PARTIAL MIGRATION DETECTED! This is an irrecoverable error. You will need to restart your node from genesis.");
         }
 
+        let mut key_index = trie_sql::has_key_index(&db)?;
+        if marf_opts.key_index && !key_index && !readonly {
+            key_index = trie_sql::create_key_index(&mut db)?;
+            if !key_index {
+                warn!(
+                    "Not keeping a key index for {}, since it already stores tries; keys cannot be listed by prefix",
+                    &db_path
+                );
+            }
+        }
+
         debug!(
             "Opened TrieFileStorage {}; external blobs: {}",
             db_path,
             blobs.is_some()
         );
 
-        let cache = TrieCache::new(&marf_opts.cache_strategy);
+        let cache = TrieCache::new(&marf_opts.cache_strategy)
+            .with_node_hash_cache(marf_opts.node_hash_cache_size);
 
         let ret = TrieFileStorage {
             db_path,
@@ -1452,6 +1517,7 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
             blobs,
             bench: TrieBenchmark::new(),
             hash_calculation_mode: marf_opts.hash_calculation_mode,
+            key_index,
 
             data: TrieStorageTransientData {
                 uncommitted_writes: None,
@@ -1467,6 +1533,8 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
                 write_node_count: 0,
                 write_leaf_count: 0,
 
+                io_stats: TrieIOStats::default(),
+
                 trie_ancestor_hash_bytes_cache: None,
 
                 readonly: readonly,
@@ -1524,7 +1592,8 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
     /// Returns Err if the underlying SQLite database connection cannot be created.
     pub fn reopen_readonly(&self) -> Result<TrieFileStorage<T>, Error> {
         let db = marf_sqlite_open(&self.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)?;
-        let cache = TrieCache::default();
+        let cache =
+            TrieCache::default().with_node_hash_cache(self.cache.node_hash_cache_capacity());
         let blobs = if self.blobs.is_some() {
             Some(TrieFile::from_db_path(&self.db_path, true)?)
         } else {
@@ -1541,6 +1610,7 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
             cache: cache,
             bench: TrieBenchmark::new(),
             hash_calculation_mode: self.hash_calculation_mode,
+            key_index: self.key_index,
 
             data: TrieStorageTransientData {
                 uncommitted_writes: self.data.uncommitted_writes.clone(),
@@ -1556,6 +1626,8 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
                 write_node_count: 0,
                 write_leaf_count: 0,
 
+                io_stats: TrieIOStats::default(),
+
                 trie_ancestor_hash_bytes_cache: None,
 
                 readonly: true,
@@ -1582,6 +1654,11 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
     pub fn reset_benchmarks(&mut self) {
         self.bench.reset();
     }
+
+    /// Take the storage I/O stats, and start counting again from zero
+    pub fn take_io_stats(&mut self) -> TrieIOStats {
+        std::mem::take(&mut self.data.io_stats)
+    }
 }
 
 impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
@@ -1600,7 +1677,8 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
             &self.db_path
         );
 
-        let cache = TrieCache::default();
+        let cache =
+            TrieCache::default().with_node_hash_cache(self.cache.node_hash_cache_capacity());
 
         // TODO: borrow self.uncommitted_writes; don't copy them
         let ret = TrieFileStorage {
@@ -1610,6 +1688,7 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
             cache: cache,
             bench: TrieBenchmark::new(),
             hash_calculation_mode: self.hash_calculation_mode,
+            key_index: self.key_index,
 
             data: TrieStorageTransientData {
                 uncommitted_writes: None,
@@ -1625,6 +1704,8 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
                 write_node_count: 0,
                 write_leaf_count: 0,
 
+                io_stats: TrieIOStats::default(),
+
                 trie_ancestor_hash_bytes_cache: None,
 
                 readonly: true,
@@ -1670,8 +1751,10 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
             // consume the cursor, get the buffer
             let buffer = buffer.into_inner();
             trace!("Buffering block flush finished.");
+            self.data.io_stats.record_flush(buffer.len());
 
             debug!("Flush: {} to {}", &bhh, flush_options);
+            let flush_start = Instant::now();
 
             let block_id = match flush_options {
                 FlushOptions::CurrentHeader => {
@@ -1727,6 +1810,15 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
 
             trie_sql::drop_lock(&self.db, &bhh)?;
 
+            let (syncs, sync_time) = self
+                .blobs
+                .as_mut()
+                .map(|blobs| blobs.take_sync_stats())
+                .unwrap_or((0, Duration::ZERO));
+            self.data
+                .io_stats
+                .record_flush_time(flush_start.elapsed(), syncs, sync_time);
+
             debug!("Flush: identifier of {} is {}", flush_options, block_id);
         }
 
@@ -1781,6 +1873,43 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
         }
     }
 
+    /// Calculate the trie root hash (not the MARF root hash) of the uncommitted TrieRAM without
+    /// sealing it.  In `TrieHashCalculationMode::Deferred` mode, the intermediate node hashes are
+    /// stored and remembered, so subsequent calls (including the one made at seal time) only
+    /// re-hash the nodes on paths that were modified in the meantime.
+    /// Only works if there's an uncommitted read/write TrieRAM extension; panics if not.
+    #[cfg(test)]
+    pub fn calculate_uncommitted_trie_hash(&mut self) -> Result<TrieHash, Error> {
+        match self.data.uncommitted_writes.take() {
+            Some((bhh, UncommittedState::RW(mut trie_ram))) => {
+                let res = trie_ram.calculate_node_hashes(self, 0);
+                self.data.uncommitted_writes = Some((bhh, UncommittedState::RW(trie_ram)));
+                res
+            }
+            Some((_, UncommittedState::Sealed(..))) => {
+                panic!("FATAL: tried to re-hash a sealed TrieRAM");
+            }
+            None => {
+                panic!("FATAL: tried to hash a trie that was not extended");
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub fn test_clear_hashed_nodes(&mut self) {
+        if let Some((_, ref mut uncommitted)) = self.data.uncommitted_writes {
+            uncommitted.trie_ram_mut().hashed_nodes.clear();
+        }
+    }
+
+    #[cfg(test)]
+    pub fn test_num_hashed_nodes(&mut self) -> usize {
+        match self.data.uncommitted_writes {
+            Some((_, ref mut uncommitted)) => uncommitted.trie_ram_mut().hashed_nodes.len(),
+            None => 0,
+        }
+    }
+
     /// Seal the inner uncommitted TrieRAM and return the MARF root hash.
     /// Only works if there's an uncommitted TrieRAM extension; panics if not.
     pub fn seal(&mut self) -> Result<TrieHash, Error> {
@@ -2271,6 +2400,20 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         self.data.cur_block.clone()
     }
 
+    /// Up to `limit` keys from the key index, in order, from `start` on.  `start` itself is
+    /// only included if `include_start` is set.
+    pub fn get_indexed_keys(
+        &self,
+        start: &str,
+        include_start: bool,
+        limit: u32,
+    ) -> Result<Vec<String>, Error> {
+        if !self.key_index {
+            return Err(Error::NoKeyIndex);
+        }
+        trie_sql::get_indexed_keys(&self.db, start, include_start, limit)
+    }
+
     /// Get the currently-open block hash and block ID (row ID)
     pub fn get_cur_block_and_id(&self) -> (T, Option<u32>) {
         (self.data.cur_block.clone(), self.data.cur_block_id.clone())
@@ -2462,6 +2605,13 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         Ok(())
     }
 
+    /// Record the currently-open trie, and the pointer into it that was being read, in a
+    /// corruption error
+    fn corruption_in_cur_block(&self, e: Error, ptr: &TriePtr) -> Error {
+        e.with_corruption_ptr(ptr)
+            .with_corruption_block(self.data.cur_block.clone().to_bytes())
+    }
+
     /// read a persisted node's hash
     fn inner_read_persisted_node_hash(
         &mut self,
@@ -2474,15 +2624,37 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
                 "Read persisted node hash from unconfirmed block id {}",
                 block_id
             );
-            return trie_sql::get_node_hash_bytes(&self.db, block_id, ptr);
+            let node_hash = trie_sql::get_node_hash_bytes(&self.db, block_id, ptr)
+                .map_err(|e| self.corruption_in_cur_block(e, ptr))?;
+            self.data.io_stats.record_disk_read(TRIEHASH_ENCODED_SIZE);
+            return Ok(node_hash);
         }
-        let node_hash = match self.blobs.as_mut() {
+        let read_result = match self.blobs.as_mut() {
             Some(blobs) => blobs.get_node_hash_bytes(&self.db, block_id, ptr),
             None => trie_sql::get_node_hash_bytes(&self.db, block_id, ptr),
-        }?;
+        };
+        let node_hash = read_result.map_err(|e| self.corruption_in_cur_block(e, ptr))?;
+        self.data.io_stats.record_disk_read(TRIEHASH_ENCODED_SIZE);
         Ok(node_hash)
     }
 
+    /// Look up a persisted node's hash in the bounded node hash cache.
+    /// Hashes in the unconfirmed trie can change, so they are never cached.
+    fn load_bounded_node_hash(&mut self, block_id: u32, ptr: &TriePtr) -> Option<TrieHash> {
+        if self.unconfirmed_block_id == Some(block_id) {
+            return None;
+        }
+        self.cache.node_hash_cache().load(block_id, ptr)
+    }
+
+    /// Store a persisted node's hash to the bounded node hash cache
+    fn store_bounded_node_hash(&mut self, block_id: u32, ptr: &TriePtr, hash: &TrieHash) {
+        if self.unconfirmed_block_id == Some(block_id) {
+            return;
+        }
+        self.cache.node_hash_cache().store(block_id, *ptr, *hash);
+    }
+
     /// Read a persisted node's hash
     pub fn read_node_hash_bytes(&mut self, ptr: &TriePtr) -> Result<TrieHash, Error> {
         if let Some((ref uncommitted_bhh, ref mut trie_ram)) = self.data.uncommitted_writes {
@@ -2500,10 +2672,14 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
                     let res = node_hash;
                     self.bench.read_node_hash_finish(true);
                     Ok(res)
+                } else if let Some(node_hash) = self.load_bounded_node_hash(block_id, ptr) {
+                    self.bench.read_node_hash_finish(true);
+                    Ok(node_hash)
                 } else {
                     let node_hash = self.inner_read_persisted_node_hash(block_id, ptr)?;
                     self.cache
                         .store_node_hash(block_id, ptr.clone(), node_hash.clone());
+                    self.store_bounded_node_hash(block_id, ptr, &node_hash);
                     self.bench.read_node_hash_finish(false);
                     Ok(node_hash)
                 }
@@ -2515,6 +2691,11 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         }
     }
 
+    /// Record following a back-pointer into an ancestor trie
+    pub fn record_backptr_hop(&mut self) {
+        self.data.io_stats.backptr_hops += 1;
+    }
+
     /// Read a persisted node and its hash.
     pub fn read_nodetype(&mut self, ptr: &TriePtr) -> Result<(TrieNodeType, TrieHash), Error> {
         self.read_nodetype_maybe_hash(ptr, true)
@@ -2542,36 +2723,43 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
             &self.unconfirmed_block_id,
             self.unconfirmed()
         );
-        if self.unconfirmed_block_id == Some(block_id) {
+        let read_result = if self.unconfirmed_block_id == Some(block_id) {
             trace!("Read persisted node from unconfirmed block id {}", block_id);
 
             // read from unconfirmed trie
             if read_hash {
-                return trie_sql::read_node_type(&self.db, block_id, &ptr);
+                trie_sql::read_node_type(&self.db, block_id, ptr)
             } else {
-                return trie_sql::read_node_type_nohash(&self.db, block_id, &ptr)
-                    .map(|node| (node, TrieHash([0u8; TRIEHASH_ENCODED_SIZE])));
+                trie_sql::read_node_type_nohash(&self.db, block_id, ptr)
+                    .map(|node| (node, TrieHash([0u8; TRIEHASH_ENCODED_SIZE])))
             }
-        }
-        let (node_inst, node_hash) = match self.blobs.as_mut() {
-            Some(blobs) => {
-                if read_hash {
-                    blobs.read_node_type(&self.db, block_id, &ptr)?
-                } else {
-                    blobs
-                        .read_node_type_nohash(&self.db, block_id, &ptr)
-                        .map(|node| (node, TrieHash([0u8; TRIEHASH_ENCODED_SIZE])))?
+        } else {
+            match self.blobs.as_mut() {
+                Some(blobs) => {
+                    if read_hash {
+                        blobs.read_node_type(&self.db, block_id, ptr)
+                    } else {
+                        blobs
+                            .read_node_type_nohash(&self.db, block_id, ptr)
+                            .map(|node| (node, TrieHash([0u8; TRIEHASH_ENCODED_SIZE])))
+                    }
                 }
-            }
-            None => {
-                if read_hash {
-                    trie_sql::read_node_type(&self.db, block_id, &ptr)?
-                } else {
-                    trie_sql::read_node_type_nohash(&self.db, block_id, &ptr)
-                        .map(|node| (node, TrieHash([0u8; TRIEHASH_ENCODED_SIZE])))?
+                None => {
+                    if read_hash {
+                        trie_sql::read_node_type(&self.db, block_id, ptr)
+                    } else {
+                        trie_sql::read_node_type_nohash(&self.db, block_id, ptr)
+                            .map(|node| (node, TrieHash([0u8; TRIEHASH_ENCODED_SIZE])))
+                    }
                 }
             }
         };
+        let (node_inst, node_hash) =
+            read_result.map_err(|e| self.corruption_in_cur_block(e, ptr))?;
+        let hash_len = if read_hash { TRIEHASH_ENCODED_SIZE } else { 0 };
+        self.data
+            .io_stats
+            .record_disk_read(get_node_byte_len(&node_inst) + hash_len);
         Ok((node_inst, node_hash))
     }
 
@@ -2591,8 +2779,10 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
             self.data.read_backptr_count += 1;
         } else if ptr.id() == TrieNodeID::Leaf as u8 {
             self.data.read_leaf_count += 1;
+            self.data.io_stats.leaves_read += 1;
         } else {
             self.data.read_node_count += 1;
+            self.data.io_stats.nodes_read += 1;
         }
 
         let clear_ptr = ptr.from_backptr();
@@ -2614,6 +2804,18 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
                         self.cache.load_node_and_hash(id, &clear_ptr)
                     {
                         (node_inst, node_hash)
+                    } else if let Some(node_hash) = self.load_bounded_node_hash(id, &clear_ptr) {
+                        // only the node itself needs to be read
+                        let node_inst = match self.cache.load_node(id, &clear_ptr) {
+                            Some(node_inst) => node_inst,
+                            None => {
+                                let (node_inst, _) =
+                                    self.inner_read_persisted_nodetype(id, &clear_ptr, false)?;
+                                self.cache.store_node(id, clear_ptr, node_inst.clone());
+                                node_inst
+                            }
+                        };
+                        (node_inst, node_hash)
                     } else {
                         let (node_inst, node_hash) =
                             self.inner_read_persisted_nodetype(id, &clear_ptr, read_hash)?;
@@ -2623,6 +2825,7 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
                             node_inst.clone(),
                             node_hash.clone(),
                         );
+                        self.store_bounded_node_hash(id, &clear_ptr, &node_hash);
                         (node_inst, node_hash)
                     }
                 } else {
@@ -2668,6 +2871,7 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         );
 
         self.data.write_count += 1;
+        self.data.io_stats.nodes_written += 1;
         match node {
             TrieNodeType::Leaf(_) => {
                 self.data.write_leaf_count += 1;
@@ -2737,6 +2941,11 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         self.bench.reset();
     }
 
+    /// Hit/miss counts of the bounded node hash cache
+    pub fn node_hash_cache_stats(&self) -> NodeHashCacheStats {
+        self.cache.node_hash_cache_stats()
+    }
+
     #[cfg(test)]
     pub fn transient_data(&self) -> &TrieStorageTransientData<T> {
         &self.data
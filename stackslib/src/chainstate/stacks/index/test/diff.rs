@@ -0,0 +1,235 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::chainstate::stacks::index::diff::{TrieDiff, TrieDiffEntry};
+use crate::chainstate::stacks::index::marf::*;
+use crate::chainstate::stacks::index::node::*;
+use crate::chainstate::stacks::index::storage::*;
+use crate::chainstate::stacks::index::{ClarityMarfTrieId, MARFValue};
+use crate::chainstate::stacks::BlockHeaderHash;
+
+/// Every key written to the MARF in `marf_diff_matches_point_lookups`, including the block
+/// height bookkeeping keys the MARF writes itself
+fn all_keys(blocks: &[BlockHeaderHash]) -> Vec<String> {
+    let mut keys: Vec<String> = (0..200).map(|i| format!("key-{}", i)).collect();
+    keys.push(OWN_BLOCK_HEIGHT_KEY.to_string());
+    for height in 0..=blocks.len() {
+        keys.push(format!("{}::{}", BLOCK_HEIGHT_TO_HASH_MAPPING_KEY, height));
+    }
+    for block in blocks.iter() {
+        keys.push(format!("{}::{}", BLOCK_HASH_TO_HEIGHT_MAPPING_KEY, block));
+    }
+    keys
+}
+
+/// Compute the diff between two blocks one key at a time
+fn diff_by_lookups(
+    marf: &mut MARF<BlockHeaderHash>,
+    keys: &[String],
+    from: &BlockHeaderHash,
+    to: &BlockHeaderHash,
+) -> Vec<TrieDiffEntry> {
+    let mut entries = vec![];
+    for key in keys.iter() {
+        let old = marf.get(from, key).unwrap();
+        let new = marf.get(to, key).unwrap();
+        if old != new {
+            entries.push(TrieDiffEntry {
+                path: TriePath::from_key(key),
+                old,
+                new,
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.path.as_bytes().cmp(b.path.as_bytes()));
+    entries
+}
+
+#[test]
+fn marf_diff_matches_point_lookups() {
+    for marf_opts in MARFOpenOpts::all().into_iter() {
+        test_debug!("With {:?}", &marf_opts);
+        let f = TrieFileStorage::new_memory(marf_opts).unwrap();
+        let mut marf = MARF::from_storage(f);
+
+        let genesis = BlockHeaderHash([0x01; 32]);
+        let child = BlockHeaderHash([0x02; 32]);
+        let grandchild = BlockHeaderHash([0x03; 32]);
+        let fork = BlockHeaderHash([0x04; 32]);
+
+        marf.begin(&BlockHeaderHash::sentinel(), &genesis).unwrap();
+        for i in 0..150 {
+            marf.insert(
+                &format!("key-{}", i),
+                MARFValue::from_value(&format!("{}", i)),
+            )
+            .unwrap();
+        }
+        marf.commit().unwrap();
+
+        // change a few values and add a few keys
+        marf.begin(&genesis, &child).unwrap();
+        for i in [3, 77, 149].iter() {
+            marf.insert(&format!("key-{}", i), MARFValue::from_value("changed"))
+                .unwrap();
+        }
+        for i in 150..160 {
+            marf.insert(
+                &format!("key-{}", i),
+                MARFValue::from_value(&format!("{}", i)),
+            )
+            .unwrap();
+        }
+        marf.commit().unwrap();
+
+        // a block that writes nothing but the height bookkeeping
+        marf.begin(&child, &grandchild).unwrap();
+        marf.commit().unwrap();
+
+        // a sibling of `child`
+        marf.begin(&genesis, &fork).unwrap();
+        for i in [3, 12].iter() {
+            marf.insert(&format!("key-{}", i), MARFValue::from_value("forked"))
+                .unwrap();
+        }
+        for i in 160..200 {
+            marf.insert(
+                &format!("key-{}", i),
+                MARFValue::from_value(&format!("{}", i)),
+            )
+            .unwrap();
+        }
+        marf.commit().unwrap();
+
+        let blocks = [genesis, child, grandchild, fork];
+        let keys = all_keys(&blocks);
+        for from in blocks.iter() {
+            for to in blocks.iter() {
+                let diff = marf.diff(from, to, usize::MAX, usize::MAX).unwrap();
+                assert!(!diff.truncated);
+                assert_eq!(diff.entries, diff_by_lookups(&mut marf, &keys, from, to));
+                if from == to {
+                    assert!(diff.entries.is_empty());
+                }
+            }
+        }
+
+        let diff = marf.diff(&genesis, &child, usize::MAX, usize::MAX).unwrap();
+        assert!(diff
+            .entries
+            .iter()
+            .any(|entry| entry.path == TriePath::from_key("key-77")
+                && entry.old == Some(MARFValue::from_value("77"))
+                && entry.new == Some(MARFValue::from_value("changed"))));
+        assert!(diff
+            .entries
+            .iter()
+            .any(|entry| entry.path == TriePath::from_key("key-155")
+                && entry.old.is_none()
+                && entry.new == Some(MARFValue::from_value("155"))));
+    }
+}
+
+#[test]
+fn marf_diff_limit() {
+    let f = TrieFileStorage::new_memory(MARFOpenOpts::default()).unwrap();
+    let mut marf = MARF::from_storage(f);
+
+    let genesis = BlockHeaderHash([0x01; 32]);
+    let child = BlockHeaderHash([0x02; 32]);
+
+    marf.begin(&BlockHeaderHash::sentinel(), &genesis).unwrap();
+    marf.commit().unwrap();
+    marf.begin(&genesis, &child).unwrap();
+    for i in 0..100 {
+        marf.insert(
+            &format!("key-{}", i),
+            MARFValue::from_value(&format!("{}", i)),
+        )
+        .unwrap();
+    }
+    marf.commit().unwrap();
+
+    let full = marf.diff(&genesis, &child, usize::MAX, usize::MAX).unwrap();
+    assert!(!full.truncated);
+    assert!(full.entries.len() > 100);
+
+    let partial = marf.diff(&genesis, &child, 10, usize::MAX).unwrap();
+    assert!(partial.truncated);
+    assert_eq!(partial.entries[..], full.entries[..10]);
+
+    let exact = marf
+        .diff(&genesis, &child, full.entries.len(), usize::MAX)
+        .unwrap();
+    assert_eq!(exact, full);
+
+    // the open block is left alone
+    marf.begin(&child, &BlockHeaderHash([0x03; 32])).unwrap();
+    marf.insert("key-100", MARFValue::from_value("100"))
+        .unwrap();
+    assert_eq!(
+        marf.diff(&genesis, &child, 0, usize::MAX).unwrap(),
+        TrieDiff {
+            entries: vec![],
+            truncated: true,
+        }
+    );
+    assert_eq!(
+        marf.get(&BlockHeaderHash([0x03; 32]), "key-100").unwrap(),
+        Some(MARFValue::from_value("100"))
+    );
+    marf.insert("key-101", MARFValue::from_value("101"))
+        .unwrap();
+    marf.commit().unwrap();
+}
+
+#[test]
+fn marf_diff_leaf_limit() {
+    let f = TrieFileStorage::new_memory(MARFOpenOpts::default()).unwrap();
+    let mut marf = MARF::from_storage(f);
+
+    let genesis = BlockHeaderHash([0x01; 32]);
+    let child = BlockHeaderHash([0x02; 32]);
+
+    marf.begin(&BlockHeaderHash::sentinel(), &genesis).unwrap();
+    marf.commit().unwrap();
+    marf.begin(&genesis, &child).unwrap();
+    for i in 0..100 {
+        marf.insert(
+            &format!("key-{}", i),
+            MARFValue::from_value(&format!("{}", i)),
+        )
+        .unwrap();
+    }
+    marf.commit().unwrap();
+
+    let full = marf.diff(&genesis, &child, usize::MAX, usize::MAX).unwrap();
+    assert!(!full.truncated);
+
+    // reading fewer leaves than differ stops the walk early, with the differences found so far
+    let partial = marf.diff(&genesis, &child, usize::MAX, 10).unwrap();
+    assert!(partial.truncated);
+    assert!(partial.entries.len() <= 10);
+    assert!(full.entries.starts_with(&partial.entries));
+
+    assert_eq!(
+        marf.diff(&genesis, &child, usize::MAX, 0).unwrap(),
+        TrieDiff {
+            entries: vec![],
+            truncated: true,
+        }
+    );
+}
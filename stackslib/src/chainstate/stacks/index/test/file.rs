@@ -78,6 +78,62 @@ fn test_load_store_trie_blob() {
     assert_eq!(buf, vec![10, 20, 30, 40, 50]);
 }
 
+#[test]
+fn test_trie_blob_sync_policy() {
+    let mut db = setup_db("test_trie_blob_sync_policy");
+    trie_sql::migrate_tables_if_needed::<BlockHeaderHash>(&mut db).unwrap();
+    let mut blobs = TrieFile::from_db_path(&db_path("test_trie_blob_sync_policy"), false)
+        .unwrap()
+        .with_sync_policy(TrieSyncPolicy::EveryNBlocks(3));
+
+    for i in 0..5u8 {
+        blobs
+            .store_trie_blob::<BlockHeaderHash>(&db, &BlockHeaderHash([i; 32]), &[i; 8])
+            .unwrap();
+    }
+    // synced once, after the third blob
+    assert_eq!(blobs.take_sync_stats().0, 1);
+    assert_eq!(blobs.take_sync_stats().0, 0);
+
+    // the remaining two are synced on demand, and only once
+    blobs.sync().unwrap();
+    blobs.sync().unwrap();
+    assert_eq!(blobs.take_sync_stats().0, 1);
+
+    // a MARF with external blobs reports its syncs in its I/O stats
+    for (i, (sync_policy, expected_syncs)) in [
+        (TrieSyncPolicy::EveryBlock, 4),
+        (TrieSyncPolicy::EveryNBlocks(2), 2),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let test_file = db_path(&format!("test_trie_blob_sync_policy_marf_{}", i));
+        for path in [test_file.clone(), format!("{}.blobs", &test_file)] {
+            if fs::metadata(&path).is_ok() {
+                fs::remove_file(&path).unwrap();
+            }
+        }
+        let marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true)
+            .with_sync_policy(sync_policy);
+        let f = TrieFileStorage::open(&test_file, marf_opts).unwrap();
+        let mut marf = MARF::from_storage(f);
+
+        let mut last_block_header = BlockHeaderHash::sentinel();
+        for j in 0..4u8 {
+            let block_header = BlockHeaderHash([j + 1; 32]);
+            marf.begin(&last_block_header, &block_header).unwrap();
+            marf.insert(&format!("{}", j), MARFValue::from(j as u32))
+                .unwrap();
+            marf.commit().unwrap();
+            last_block_header = block_header;
+        }
+        let io = marf.take_io_stats();
+        assert_eq!(io.syncs, expected_syncs);
+        assert!(io.flush_micros >= io.sync_micros);
+    }
+}
+
 #[test]
 fn test_migrate_existing_trie_blobs() {
     let test_file = "/tmp/test_migrate_existing_trie_blobs.sqlite";
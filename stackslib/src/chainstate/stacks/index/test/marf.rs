@@ -29,6 +29,7 @@ use crate::chainstate::stacks::index::bits::*;
 use crate::chainstate::stacks::index::marf::*;
 use crate::chainstate::stacks::index::node::*;
 use crate::chainstate::stacks::index::proofs::*;
+use crate::chainstate::stacks::index::stats::TrieIOStats;
 use crate::chainstate::stacks::index::storage::*;
 use crate::chainstate::stacks::index::test::*;
 use crate::chainstate::stacks::index::trie::*;
@@ -787,6 +788,43 @@ fn marf_invalid_ancestor() {
     assert_eq!(hash_1, hash_2);
 }
 
+#[test]
+fn marf_io_stats() {
+    let storage = TrieFileStorage::new_memory(MARFOpenOpts::default()).unwrap();
+    let mut marf = MARF::from_storage(storage);
+    let mock_miner_hash = BlockHeaderHash([1; 32]);
+
+    marf.begin(&BlockHeaderHash::sentinel(), &mock_miner_hash)
+        .unwrap();
+    marf.insert("a", MARFValue::from(1u32)).unwrap();
+    marf.commit_to(&BlockHeaderHash([2; 32])).unwrap();
+
+    let io = marf.take_io_stats();
+    assert!(io.nodes_written > 0);
+    assert!(io.bytes_written > 0);
+    assert_eq!(marf.take_io_stats(), TrieIOStats::default());
+
+    marf.begin(&BlockHeaderHash([2; 32]), &mock_miner_hash)
+        .unwrap();
+    marf.insert("b", MARFValue::from(2u32)).unwrap();
+    marf.commit_to(&BlockHeaderHash([3; 32])).unwrap();
+    marf.take_io_stats();
+
+    // "a" is only in the ancestor trie, so reading it from the new block follows a back-pointer
+    // into a trie that is only on disk
+    assert_eq!(
+        marf.get(&BlockHeaderHash([3; 32]), "a").unwrap(),
+        Some(MARFValue::from(1u32))
+    );
+    let io = marf.take_io_stats();
+    assert!(io.backptr_hops > 0);
+    assert!(io.leaves_read > 0);
+    assert!(io.disk_reads > 0);
+    assert!(io.bytes_read > 0);
+    assert_eq!(io.nodes_written, 0);
+    assert_eq!(io.bytes_written, 0);
+}
+
 #[test]
 fn marf_merkle_verify_backptrs() {
     let mut last_root_hashes = None;
@@ -2307,3 +2345,125 @@ fn test_marf_unconfirmed() {
         assert!(false);
     }
 }
+
+#[test]
+fn marf_deferred_hash_cache_matches_naive() {
+    let modes = [
+        TrieHashCalculationMode::Immediate,
+        TrieHashCalculationMode::Deferred,
+        TrieHashCalculationMode::Deferred,
+    ];
+    let mut marfs: Vec<_> = modes
+        .iter()
+        .map(|mode| {
+            let f = TrieFileStorage::new_memory(MARFOpenOpts::new(*mode, "noop", false)).unwrap();
+            MARF::<BlockHeaderHash>::from_storage(f)
+        })
+        .collect();
+
+    let mut parent = BlockHeaderHash::sentinel();
+    for block_num in 0..4u8 {
+        let block_header = BlockHeaderHash([block_num + 1; 32]);
+        for marf in marfs.iter_mut() {
+            marf.begin(&parent, &block_header).unwrap();
+        }
+
+        for i in 0..256u32 {
+            // overlap with prior blocks' keys so that some nodes are copied forward from
+            // ancestor tries, and with this block's keys so that some leaves are overwritten
+            let mut path_bytes = [0u8; 32];
+            path_bytes[0] = (i % 7) as u8;
+            path_bytes[1] = (i % 13) as u8;
+            path_bytes[28..32].copy_from_slice(&(i % 200).to_be_bytes());
+            let path = TriePath::from_bytes(&path_bytes).unwrap();
+            let value = TrieLeaf::new(&vec![], &[block_num ^ (i as u8); 40].to_vec());
+
+            for marf in marfs.iter_mut() {
+                marf.insert_raw(path, value.clone()).unwrap();
+            }
+
+            if i % 16 == 0 {
+                // calculate an interim hash with the cache, and check it against a full
+                // recalculation of the same trie
+                let mut tx = marfs[2].borrow_storage_transaction();
+                let cached_hash = tx.calculate_uncommitted_trie_hash().unwrap();
+                tx.test_clear_hashed_nodes();
+                let naive_hash = tx.calculate_uncommitted_trie_hash().unwrap();
+                assert_eq!(cached_hash, naive_hash);
+            }
+        }
+
+        let root_hashes: Vec<_> = marfs
+            .iter_mut()
+            .map(|marf| {
+                marf.commit().unwrap();
+                marf.get_root_hash_at(&block_header).unwrap()
+            })
+            .collect();
+
+        assert_eq!(root_hashes[0], root_hashes[1]);
+        assert_eq!(root_hashes[1], root_hashes[2]);
+
+        parent = block_header;
+    }
+}
+
+#[test]
+fn marf_reloaded_unconfirmed_trie_rehash_matches_naive() {
+    let modes = [
+        TrieHashCalculationMode::Immediate,
+        TrieHashCalculationMode::Deferred,
+    ];
+    let block_header = StacksBlockId([0x33u8; 32]);
+    let mut marfs: Vec<_> = modes
+        .iter()
+        .map(|mode| {
+            let marf_path = format!(
+                "/tmp/test_marf_reloaded_unconfirmed_trie_rehash_matches_naive-{:?}",
+                mode
+            );
+            if std::fs::metadata(&marf_path).is_ok() {
+                std::fs::remove_file(&marf_path).unwrap();
+            }
+            let marf_opts = MARFOpenOpts::new(*mode, "noop", false);
+            let cf = TrieFileStorage::<StacksBlockId>::open(&marf_path, marf_opts.clone()).unwrap();
+            let mut confirmed_marf = MARF::<StacksBlockId>::from_storage(cf);
+            confirmed_marf
+                .begin(&StacksBlockId::sentinel(), &StacksBlockId([0x11; 32]))
+                .unwrap();
+            confirmed_marf.commit_to(&block_header).unwrap();
+
+            let f =
+                TrieFileStorage::<StacksBlockId>::open_unconfirmed(&marf_path, marf_opts).unwrap();
+            (marf_path, MARF::<StacksBlockId>::from_storage(f))
+        })
+        .collect();
+
+    // each microblock reloads the unconfirmed trie, writes to some of its paths, and commits it
+    for microblock in 0..4u8 {
+        let mut root_hashes = vec![];
+        for (mode, (_, marf)) in modes.iter().zip(marfs.iter_mut()) {
+            let unconfirmed_tip = marf.begin_unconfirmed(&block_header).unwrap();
+            if microblock > 0 && *mode == TrieHashCalculationMode::Deferred {
+                // the reloaded trie's interior hashes are reused
+                assert!(marf.borrow_storage_transaction().test_num_hashed_nodes() > 0);
+            }
+            for i in 0..64u32 {
+                let mut path_bytes = [0u8; 32];
+                path_bytes[0] = (i % 5) as u8 + microblock;
+                path_bytes[1] = (i % 11) as u8;
+                path_bytes[28..32].copy_from_slice(&i.to_be_bytes());
+                let path = TriePath::from_bytes(&path_bytes).unwrap();
+                let value = TrieLeaf::new(&vec![], &[microblock ^ (i as u8); 40].to_vec());
+                marf.insert_raw(path, value).unwrap();
+            }
+            marf.commit().unwrap();
+            root_hashes.push(marf.get_root_hash_at(&unconfirmed_tip).unwrap());
+        }
+        assert_eq!(root_hashes[0], root_hashes[1]);
+    }
+
+    for (marf_path, _) in marfs.into_iter() {
+        std::fs::remove_file(&marf_path).unwrap();
+    }
+}
@@ -37,12 +37,16 @@ use crate::chainstate::stacks::index::{
 use crate::chainstate::stacks::{BlockHeaderHash, TrieHash};
 
 pub mod cache;
+pub mod diff;
 pub mod file;
 pub mod marf;
 pub mod node;
+pub mod prefix;
 pub mod proofs;
 pub mod storage;
 pub mod trie;
+pub mod warm;
+pub mod workload;
 
 /// Print out a trie to stderr
 pub fn dump_trie<T>(s: &mut TrieStorageConnection<T>)
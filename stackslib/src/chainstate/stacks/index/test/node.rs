@@ -5065,3 +5065,132 @@ fn trie_cursor_walk_32() {
 
     dump_trie(&mut trie_io);
 }
+
+/// Inputs that used to make trie node deserialization panic, as exercised by the
+/// `trie_ptrs_from_bytes` fuzz target.  Each is a node ID followed by the bytes to decode its
+/// children from.
+const PTRS_FROM_BYTES_CRASHERS: &[(u8, &[u8])] = &[
+    // empty node ID
+    (0x00, &[0x00; 11]),
+    // empty node ID with the back-pointer bit set
+    (0x80, &[0x80; 11]),
+];
+
+#[test]
+fn ptrs_from_bytes_rejects_crashers() {
+    for (node_id, bytes) in PTRS_FROM_BYTES_CRASHERS.iter() {
+        let mut ptrs = [TriePtr::default(); 256];
+        let res = ptrs_from_bytes(*node_id, &mut Cursor::new(bytes), &mut ptrs);
+        assert!(
+            matches!(res, Err(Error::CorruptionError(_))),
+            "Decoded ptrs of node ID {:x}: {:?}",
+            node_id,
+            &res
+        );
+    }
+}
+
+#[test]
+fn ptrs_from_bytes_rejects_short_buffer() {
+    let node256 = TrieNode256::new(&vec![]);
+    let bytes = node256.to_bytes();
+    let mut ptrs = [TriePtr::default(); 4];
+    let res = ptrs_from_bytes(
+        TrieNodeID::Node256 as u8,
+        &mut Cursor::new(&bytes),
+        &mut ptrs,
+    );
+    assert!(matches!(res, Err(Error::CorruptionError(_))));
+}
+
+#[test]
+fn path_from_bytes_rejects_malformed_paths() {
+    // too long
+    let mut bytes = vec![(TRIEPATH_MAX_LEN + 1) as u8];
+    bytes.extend_from_slice(&[0u8; TRIEPATH_MAX_LEN + 1]);
+    assert!(matches!(
+        path_from_bytes(&mut Cursor::new(&bytes)),
+        Err(Error::CorruptionError(_))
+    ));
+
+    // truncated
+    let bytes = vec![TRIEPATH_MAX_LEN as u8, 1, 2, 3];
+    assert!(matches!(
+        path_from_bytes(&mut Cursor::new(&bytes)),
+        Err(Error::CorruptionError(_))
+    ));
+
+    // empty
+    assert!(matches!(
+        path_from_bytes(&mut Cursor::new(&[])),
+        Err(Error::CorruptionError(_))
+    ));
+}
+
+#[test]
+fn read_nodetype_at_head_rejects_truncated_nodes() {
+    let mut node48 = TrieNode48::new(&vec![0, 1, 2, 3]);
+    for i in 0..48 {
+        assert!(node48.insert(&TriePtr::new(TrieNodeID::Leaf as u8, i, (i as u32) + 1)));
+    }
+    let nodes = [
+        TrieNodeType::Node4(TrieNode4::new(&vec![1, 2, 3])),
+        TrieNodeType::Node16(TrieNode16::new(&vec![1, 2, 3])),
+        TrieNodeType::Node48(Box::new(node48)),
+        TrieNodeType::Node256(Box::new(TrieNode256::new(&vec![1, 2, 3]))),
+        TrieNodeType::Leaf(TrieLeaf::new(&vec![1, 2, 3], &[4u8; 40].to_vec())),
+    ];
+    for node in nodes.iter() {
+        let hash = TrieHash::from_data(&[0u8; 32]);
+        let mut bytes = vec![];
+        write_nodetype_bytes(&mut Cursor::new(&mut bytes), node, hash).unwrap();
+
+        let (read_node, read_hash) =
+            read_nodetype_at_head(&mut Cursor::new(&bytes), node.id()).unwrap();
+        assert_eq!(&read_node, node);
+        assert_eq!(read_hash, hash);
+
+        for len in 0..bytes.len() {
+            assert!(
+                read_nodetype_at_head(&mut Cursor::new(&bytes[..len]), node.id()).is_err(),
+                "Decoded {:?} truncated to {} bytes",
+                node,
+                len
+            );
+        }
+    }
+}
+
+#[test]
+fn read_nodetype_reports_corruption_context() {
+    let node = TrieNodeType::Node4(TrieNode4::new(&vec![1, 2, 3]));
+    let mut bytes = vec![0u8; 8];
+    let mut cursor = Cursor::new(&mut bytes);
+    cursor.set_position(8);
+    write_nodetype_bytes(&mut cursor, &node, TrieHash::from_data(&[0u8; 32])).unwrap();
+
+    // the reader records the pointer it was reading
+    let ptr = TriePtr::new(TrieNodeID::Node4 as u8, 0x01, 8);
+    let err = read_nodetype(&mut Cursor::new(&bytes[..bytes.len() - 1]), &ptr).unwrap_err();
+    let context = err.corruption_context().unwrap();
+    assert_eq!(context.ptr, Some(ptr));
+    assert_eq!(context.block_id, None);
+    assert_eq!(context.path, None);
+
+    // context recorded closer to the corruption is kept as the error propagates
+    let err = err
+        .with_corruption_ptr(&TriePtr::new(TrieNodeID::Node256 as u8, 0x00, 0))
+        .with_corruption_block([1u8; 32])
+        .with_corruption_path(&[0x01]);
+    let context = err.corruption_context().unwrap();
+    assert_eq!(context.ptr, Some(ptr));
+    assert_eq!(context.block_id, Some([1u8; 32]));
+    assert_eq!(context.path, Some(vec![0x01]));
+    assert!(format!("{}", &err).contains(&format!("block {}", "01".repeat(32))));
+
+    // other errors are passed through
+    assert!(matches!(
+        Error::NotFoundError.with_corruption_ptr(&ptr),
+        Error::NotFoundError
+    ));
+}
@@ -0,0 +1,606 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs;
+
+use crate::chainstate::stacks::index::marf::*;
+use crate::chainstate::stacks::index::node::TriePath;
+use crate::chainstate::stacks::index::prefix::{
+    KeyPrefixEntry, KeyPrefixScan, TriePrefixEntry, TriePrefixScan, MAX_SKIPPED_KEYS,
+};
+use crate::chainstate::stacks::index::storage::*;
+use crate::chainstate::stacks::index::{ClarityMarfTrieId, Error, MARFValue};
+use crate::chainstate::stacks::BlockHeaderHash;
+
+fn contract_key(contract: &str, i: usize) -> String {
+    format!("{}::key-{}", contract, i)
+}
+
+/// Every key written to the MARF by `forked_marf`, including the block height bookkeeping keys
+/// the MARF writes itself
+fn all_keys(blocks: &[BlockHeaderHash]) -> Vec<String> {
+    let mut keys = vec![];
+    for i in 0..200 {
+        keys.push(contract_key("contract-a", i));
+        keys.push(contract_key("contract-b", i));
+    }
+    keys.push(OWN_BLOCK_HEIGHT_KEY.to_string());
+    for height in 0..=blocks.len() {
+        keys.push(format!("{}::{}", BLOCK_HEIGHT_TO_HASH_MAPPING_KEY, height));
+    }
+    for block in blocks.iter() {
+        keys.push(format!("{}::{}", BLOCK_HASH_TO_HEIGHT_MAPPING_KEY, block));
+    }
+    keys
+}
+
+/// Find the leaves within a path prefix one key at a time
+fn scan_by_lookups(
+    marf: &mut MARF<BlockHeaderHash>,
+    keys: &[String],
+    block: &BlockHeaderHash,
+    prefix: &[u8],
+) -> Vec<TriePrefixEntry> {
+    let mut entries = vec![];
+    for key in keys.iter() {
+        let path = TriePath::from_key(key);
+        if !path.as_bytes().starts_with(prefix) {
+            continue;
+        }
+        if let Some(value) = marf.get(block, key).unwrap() {
+            entries.push(TriePrefixEntry { path, value });
+        }
+    }
+    entries.sort_by(|a, b| a.path.as_bytes().cmp(b.path.as_bytes()));
+    entries
+}
+
+/// Find the keys with a prefix one key at a time
+fn scan_keys_by_lookups(
+    marf: &mut MARF<BlockHeaderHash>,
+    keys: &[String],
+    block: &BlockHeaderHash,
+    prefix: &str,
+) -> Vec<KeyPrefixEntry> {
+    let mut entries = vec![];
+    for key in keys.iter() {
+        if !key.starts_with(prefix) {
+            continue;
+        }
+        if let Some(value) = marf.get(block, key).unwrap() {
+            entries.push(KeyPrefixEntry {
+                key: key.clone(),
+                value,
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    entries
+}
+
+/// Insert `contract`'s keys `range`, each with its number as its value
+fn insert_keys(
+    marf: &mut MARF<BlockHeaderHash>,
+    contract: &str,
+    range: std::ops::Range<usize>,
+    batch: bool,
+) {
+    if batch {
+        let keys = range.clone().map(|i| contract_key(contract, i)).collect();
+        let values = range
+            .map(|i| MARFValue::from_value(&format!("{}", i)))
+            .collect();
+        marf.insert_batch(&keys, values).unwrap();
+    } else {
+        for i in range {
+            marf.insert(
+                &contract_key(contract, i),
+                MARFValue::from_value(&format!("{}", i)),
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// A MARF with a few blocks, one of them on a fork, to check scans against point lookups
+fn forked_marf(marf_opts: MARFOpenOpts) -> (MARF<BlockHeaderHash>, [BlockHeaderHash; 4]) {
+    let f = TrieFileStorage::new_memory(marf_opts).unwrap();
+    let mut marf = MARF::from_storage(f);
+
+    let genesis = BlockHeaderHash([0x01; 32]);
+    let child = BlockHeaderHash([0x02; 32]);
+    let grandchild = BlockHeaderHash([0x03; 32]);
+    let fork = BlockHeaderHash([0x04; 32]);
+
+    marf.begin(&BlockHeaderHash::sentinel(), &genesis).unwrap();
+    insert_keys(&mut marf, "contract-a", 0..150, false);
+    insert_keys(&mut marf, "contract-b", 0..100, true);
+    marf.commit().unwrap();
+
+    // change a few values and add a few keys
+    marf.begin(&genesis, &child).unwrap();
+    for i in [3, 77, 149].iter() {
+        marf.insert(
+            &contract_key("contract-a", *i),
+            MARFValue::from_value("changed"),
+        )
+        .unwrap();
+    }
+    insert_keys(&mut marf, "contract-a", 150..160, false);
+    insert_keys(&mut marf, "contract-b", 100..120, true);
+    marf.commit().unwrap();
+
+    // a block that writes nothing but the height bookkeeping, so nearly all of its trie
+    // is back-pointers
+    marf.begin(&child, &grandchild).unwrap();
+    marf.commit().unwrap();
+
+    // a sibling of `child`, whose keys must not show up at `child` or `grandchild`
+    marf.begin(&genesis, &fork).unwrap();
+    insert_keys(&mut marf, "contract-a", 160..200, false);
+    insert_keys(&mut marf, "contract-b", 120..200, true);
+    marf.commit().unwrap();
+
+    (marf, [genesis, child, grandchild, fork])
+}
+
+#[test]
+fn marf_path_prefix_scan_matches_point_lookups() {
+    for marf_opts in MARFOpenOpts::all().into_iter() {
+        test_debug!("With {:?}", &marf_opts);
+        let (mut marf, blocks) = forked_marf(marf_opts);
+        let [_, _, grandchild, fork] = blocks;
+        let keys = all_keys(&blocks);
+        let key_path = TriePath::from_key(&contract_key("contract-a", 77));
+        let prefixes: [&[u8]; 7] = [
+            &[],
+            &[0x00],
+            &[0x80],
+            &[0xff],
+            &key_path.as_bytes()[..2],
+            &key_path.as_bytes()[..20],
+            key_path.as_bytes(),
+        ];
+        for block in blocks.iter() {
+            for prefix in prefixes.iter() {
+                let scan = marf
+                    .get_with_path_prefix(block, prefix, None, usize::MAX)
+                    .unwrap();
+                assert!(!scan.truncated);
+                assert_eq!(
+                    scan.entries,
+                    scan_by_lookups(&mut marf, &keys, block, prefix)
+                );
+            }
+        }
+
+        let scan = marf
+            .get_with_path_prefix(&grandchild, &[], None, usize::MAX)
+            .unwrap();
+        assert_eq!(
+            scan.entries.len(),
+            scan_by_lookups(&mut marf, &keys, &grandchild, &[]).len()
+        );
+
+        let scan = marf
+            .get_with_path_prefix(&grandchild, key_path.as_bytes(), None, usize::MAX)
+            .unwrap();
+        assert_eq!(
+            scan.entries,
+            vec![TriePrefixEntry {
+                path: key_path,
+                value: MARFValue::from_value("changed"),
+            }]
+        );
+        let scan = marf
+            .get_with_path_prefix(&fork, key_path.as_bytes(), None, usize::MAX)
+            .unwrap();
+        assert_eq!(scan.entries[0].value, MARFValue::from_value("77"));
+    }
+}
+
+#[test]
+fn marf_path_prefix_scan_limit() {
+    let f = TrieFileStorage::new_memory(MARFOpenOpts::default()).unwrap();
+    let mut marf = MARF::from_storage(f);
+
+    let genesis = BlockHeaderHash([0x01; 32]);
+    let child = BlockHeaderHash([0x02; 32]);
+
+    marf.begin(&BlockHeaderHash::sentinel(), &genesis).unwrap();
+    insert_keys(&mut marf, "contract-a", 0..3000, true);
+    marf.commit().unwrap();
+
+    marf.take_io_stats();
+    let full = marf
+        .get_with_path_prefix(&genesis, &[], None, usize::MAX)
+        .unwrap();
+    let full_stats = marf.take_io_stats();
+    assert!(!full.truncated);
+    // the keys, plus the block height bookkeeping
+    assert!(full.entries.len() > 3000);
+    assert!(full
+        .entries
+        .windows(2)
+        .all(|w| w[0].path.as_bytes() < w[1].path.as_bytes()));
+
+    let partial = marf.get_with_path_prefix(&genesis, &[], None, 10).unwrap();
+    let partial_stats = marf.take_io_stats();
+    assert!(partial.truncated);
+    assert_eq!(partial.entries[..], full.entries[..10]);
+
+    // the walk stops once the limit is reached, however many leaves are left
+    assert_eq!(full_stats.leaves_read, full.entries.len() as u64);
+    assert!(partial_stats.leaves_read <= 11);
+    assert!(partial_stats.nodes_read <= 11 * 32);
+    assert!(partial_stats.nodes_read < full_stats.nodes_read / 10);
+
+    let exact = marf
+        .get_with_path_prefix(&genesis, &[], None, full.entries.len())
+        .unwrap();
+    assert_eq!(exact, full);
+
+    // continuing from the last path of each listing yields every leaf once, and each page
+    // costs about as much as its own leaves
+    marf.take_io_stats();
+    let mut entries = vec![];
+    let mut start_after: Option<TriePath> = None;
+    loop {
+        let scan = marf
+            .get_with_path_prefix(&genesis, &[], start_after.as_ref(), 100)
+            .unwrap();
+        let stats = marf.take_io_stats();
+        assert!(stats.leaves_read <= 102);
+        start_after = scan.entries.last().map(|entry| entry.path);
+        entries.extend(scan.entries);
+        if !scan.truncated {
+            break;
+        }
+    }
+    assert_eq!(entries, full.entries);
+
+    // a prefix narrows a continued listing too
+    let prefix = full.entries[1500].path.as_bytes()[..1].to_vec();
+    let in_prefix: Vec<_> = full
+        .entries
+        .iter()
+        .filter(|entry| entry.path.as_bytes().starts_with(&prefix))
+        .cloned()
+        .collect();
+    let scan = marf
+        .get_with_path_prefix(&genesis, &prefix, Some(&in_prefix[0].path), usize::MAX)
+        .unwrap();
+    assert_eq!(scan.entries[..], in_prefix[1..]);
+    // a path to start after that comes before the prefix is ignored
+    let scan = marf
+        .get_with_path_prefix(&genesis, &prefix, Some(&full.entries[0].path), usize::MAX)
+        .unwrap();
+    assert_eq!(scan.entries, in_prefix);
+
+    // a prefix longer than a path matches nothing
+    let scan = marf
+        .get_with_path_prefix(&genesis, &[0x00; 33], None, usize::MAX)
+        .unwrap();
+    assert_eq!(
+        scan,
+        TriePrefixScan {
+            entries: vec![],
+            truncated: false,
+        }
+    );
+
+    // an unknown block is an error
+    assert!(marf
+        .get_with_path_prefix(&child, &[], None, usize::MAX)
+        .is_err());
+
+    // the open block is left alone
+    marf.begin(&genesis, &child).unwrap();
+    marf.insert("contract-c::key-0", MARFValue::from_value("0"))
+        .unwrap();
+    assert_eq!(
+        marf.get_with_path_prefix(&genesis, &[], None, 0).unwrap(),
+        TriePrefixScan {
+            entries: vec![],
+            truncated: true,
+        }
+    );
+    assert_eq!(
+        marf.get(&child, "contract-c::key-0").unwrap(),
+        Some(MARFValue::from_value("0"))
+    );
+    marf.insert("contract-c::key-1", MARFValue::from_value("1"))
+        .unwrap();
+    marf.commit().unwrap();
+    let path = TriePath::from_key("contract-c::key-1");
+    assert_eq!(
+        marf.get_with_path_prefix(&child, path.as_bytes(), None, usize::MAX)
+            .unwrap()
+            .entries,
+        vec![TriePrefixEntry {
+            path,
+            value: MARFValue::from_value("1"),
+        }]
+    );
+}
+
+#[test]
+fn marf_key_prefix_scan_matches_point_lookups() {
+    for marf_opts in MARFOpenOpts::all().into_iter() {
+        test_debug!("With {:?}", &marf_opts);
+        let (mut marf, blocks) = forked_marf(marf_opts.with_key_index(true));
+        let [_, _, grandchild, fork] = blocks;
+        let keys = all_keys(&blocks);
+        let prefixes = [
+            "",
+            "contract-a::",
+            "contract-b::",
+            "contract-a::key-1",
+            "contract-b::key-17",
+            "contract-a::key-77",
+            "contract-c::",
+            "__MARF_",
+        ];
+        for block in blocks.iter() {
+            for prefix in prefixes.iter() {
+                let scan = marf
+                    .get_with_key_prefix(block, prefix, None, usize::MAX)
+                    .unwrap();
+                assert_eq!(scan.resume_after, None);
+                assert_eq!(
+                    scan.entries,
+                    scan_keys_by_lookups(&mut marf, &keys, block, prefix)
+                );
+            }
+        }
+
+        let scan = marf
+            .get_with_key_prefix(&grandchild, "contract-a::", None, usize::MAX)
+            .unwrap();
+        assert_eq!(scan.entries.len(), 160);
+        let scan = marf
+            .get_with_key_prefix(&fork, "contract-b::", None, usize::MAX)
+            .unwrap();
+        assert_eq!(scan.entries.len(), 180);
+
+        let scan = marf
+            .get_with_key_prefix(&grandchild, "contract-a::key-77", None, usize::MAX)
+            .unwrap();
+        assert_eq!(
+            scan.entries,
+            vec![KeyPrefixEntry {
+                key: contract_key("contract-a", 77),
+                value: MARFValue::from_value("changed"),
+            }]
+        );
+        let scan = marf
+            .get_with_key_prefix(&fork, "contract-a::key-77", None, usize::MAX)
+            .unwrap();
+        assert_eq!(scan.entries[0].value, MARFValue::from_value("77"));
+    }
+}
+
+#[test]
+fn marf_key_prefix_scan_limit() {
+    let f = TrieFileStorage::new_memory(MARFOpenOpts::default().with_key_index(true)).unwrap();
+    let mut marf = MARF::from_storage(f);
+
+    let genesis = BlockHeaderHash([0x01; 32]);
+    let child = BlockHeaderHash([0x02; 32]);
+
+    // more keys than are read from the key index at once
+    marf.begin(&BlockHeaderHash::sentinel(), &genesis).unwrap();
+    insert_keys(&mut marf, "contract-a", 0..3000, true);
+    insert_keys(&mut marf, "contract-b", 0..10, true);
+    marf.commit().unwrap();
+
+    let full = marf
+        .get_with_key_prefix(&genesis, "contract-a::", None, usize::MAX)
+        .unwrap();
+    assert_eq!(full.resume_after, None);
+    assert_eq!(full.entries.len(), 3000);
+    assert!(full.entries.windows(2).all(|w| w[0].key < w[1].key));
+
+    let partial = marf
+        .get_with_key_prefix(&genesis, "contract-a::", None, 10)
+        .unwrap();
+    assert_eq!(partial.entries[..], full.entries[..10]);
+    assert_eq!(partial.resume_after, Some(full.entries[9].key.clone()));
+
+    let exact = marf
+        .get_with_key_prefix(&genesis, "contract-a::", None, full.entries.len())
+        .unwrap();
+    assert_eq!(exact, full);
+
+    // continuing from where each listing stopped yields every key once
+    let mut entries = vec![];
+    let mut start_after: Option<String> = None;
+    loop {
+        let scan = marf
+            .get_with_key_prefix(&genesis, "contract-a::", start_after.as_deref(), 1000)
+            .unwrap();
+        entries.extend(scan.entries);
+        start_after = scan.resume_after;
+        if start_after.is_none() {
+            break;
+        }
+    }
+    assert_eq!(entries, full.entries);
+
+    // a key to start after that comes before the prefix is ignored
+    let scan = marf
+        .get_with_key_prefix(&genesis, "contract-b::", Some("contract-a::key-5"), 100)
+        .unwrap();
+    assert_eq!(scan.entries.len(), 10);
+
+    // an unknown block is an error
+    assert!(marf
+        .get_with_key_prefix(&child, "contract-a::", None, usize::MAX)
+        .is_err());
+
+    // the open block is left alone
+    marf.begin(&genesis, &child).unwrap();
+    marf.insert("contract-c::key-0", MARFValue::from_value("0"))
+        .unwrap();
+    assert_eq!(
+        marf.get_with_key_prefix(&genesis, "contract-a::", None, 0)
+            .unwrap(),
+        KeyPrefixScan {
+            entries: vec![],
+            resume_after: None,
+        }
+    );
+    assert_eq!(
+        marf.get(&child, "contract-c::key-0").unwrap(),
+        Some(MARFValue::from_value("0"))
+    );
+    marf.insert("contract-c::key-1", MARFValue::from_value("1"))
+        .unwrap();
+    marf.commit().unwrap();
+    assert_eq!(
+        marf.get_with_key_prefix(&child, "contract-c::", None, usize::MAX)
+            .unwrap()
+            .entries
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn marf_key_prefix_scan_bounds_skipped_keys() {
+    let f = TrieFileStorage::new_memory(MARFOpenOpts::default().with_key_index(true)).unwrap();
+    let mut marf = MARF::from_storage(f);
+
+    let genesis = BlockHeaderHash([0x01; 32]);
+    let fork_a = BlockHeaderHash([0x02; 32]);
+    let fork_b = BlockHeaderHash([0x03; 32]);
+
+    // keys written after `genesis`, and only in `fork_a`, are in the index but not at
+    // `genesis` or in `fork_b`
+    marf.begin(&BlockHeaderHash::sentinel(), &genesis).unwrap();
+    insert_keys(&mut marf, "contract-a", 0..10, true);
+    marf.commit().unwrap();
+    marf.begin(&genesis, &fork_a).unwrap();
+    insert_keys(
+        &mut marf,
+        "contract-a",
+        10..(10 + 2 * MAX_SKIPPED_KEYS),
+        true,
+    );
+    marf.commit().unwrap();
+    marf.begin(&genesis, &fork_b).unwrap();
+    marf.insert("contract-b::key-0", MARFValue::from_value("0"))
+        .unwrap();
+    marf.commit().unwrap();
+
+    for block in [genesis, fork_b].iter() {
+        let expected = marf
+            .get_with_key_prefix(&fork_a, "contract-a::", None, usize::MAX)
+            .unwrap()
+            .entries
+            .into_iter()
+            .filter(|entry| marf.get(block, &entry.key).unwrap().is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(expected.len(), 10);
+
+        // each listing stops after skipping too many keys that are not at the block, and
+        // continuing from where it stopped finds the rest
+        let mut entries = vec![];
+        let mut start_after: Option<String> = None;
+        let mut listings = 0;
+        loop {
+            let scan = marf
+                .get_with_key_prefix(block, "contract-a::", start_after.as_deref(), usize::MAX)
+                .unwrap();
+            listings += 1;
+            entries.extend(scan.entries);
+            start_after = scan.resume_after;
+            if start_after.is_none() {
+                break;
+            }
+        }
+        assert_eq!(entries, expected);
+        assert_eq!(listings, 3);
+    }
+}
+
+#[test]
+fn marf_key_index_is_opt_in() {
+    let genesis = BlockHeaderHash([0x01; 32]);
+
+    // without a key index, keys can't be listed
+    let f = TrieFileStorage::new_memory(MARFOpenOpts::default()).unwrap();
+    let mut marf = MARF::from_storage(f);
+    marf.begin(&BlockHeaderHash::sentinel(), &genesis).unwrap();
+    marf.insert("contract-a::key-0", MARFValue::from_value("0"))
+        .unwrap();
+    marf.commit().unwrap();
+    match marf.get_with_key_prefix(&genesis, "", None, usize::MAX) {
+        Err(Error::NoKeyIndex) => {}
+        x => panic!("Expected NoKeyIndex, got {:?}", x),
+    }
+
+    let path = "/tmp/test_marf_key_index_is_opt_in";
+    if fs::metadata(path).is_ok() {
+        fs::remove_file(path).unwrap();
+    }
+
+    // a key index can't be added once tries are stored, since their keys can't be recovered,
+    // but the MARF still opens
+    let mut marf: MARF<BlockHeaderHash> = MARF::from_path(path, MARFOpenOpts::default()).unwrap();
+    marf.begin(&BlockHeaderHash::sentinel(), &genesis).unwrap();
+    marf.insert("contract-a::key-0", MARFValue::from_value("0"))
+        .unwrap();
+    marf.commit().unwrap();
+    drop(marf);
+    let mut marf: MARF<BlockHeaderHash> =
+        MARF::from_path(path, MARFOpenOpts::default().with_key_index(true)).unwrap();
+    assert_eq!(
+        marf.get(&genesis, "contract-a::key-0").unwrap(),
+        Some(MARFValue::from_value("0"))
+    );
+    match marf.get_with_key_prefix(&genesis, "", None, usize::MAX) {
+        Err(Error::NoKeyIndex) => {}
+        x => panic!("Expected NoKeyIndex, got {:?}", x),
+    }
+    drop(marf);
+    fs::remove_file(path).unwrap();
+
+    // once kept, the key index is kept even if the option is not given again
+    let mut marf: MARF<BlockHeaderHash> =
+        MARF::from_path(path, MARFOpenOpts::default().with_key_index(true)).unwrap();
+    marf.begin(&BlockHeaderHash::sentinel(), &genesis).unwrap();
+    marf.insert("contract-a::key-0", MARFValue::from_value("0"))
+        .unwrap();
+    marf.commit().unwrap();
+    drop(marf);
+    let child = BlockHeaderHash([0x02; 32]);
+    let mut marf: MARF<BlockHeaderHash> = MARF::from_path(path, MARFOpenOpts::default()).unwrap();
+    marf.begin(&genesis, &child).unwrap();
+    marf.insert("contract-a::key-1", MARFValue::from_value("1"))
+        .unwrap();
+    marf.commit().unwrap();
+    let keys: Vec<String> = marf
+        .get_with_key_prefix(&child, "contract-a::", None, usize::MAX)
+        .unwrap()
+        .entries
+        .into_iter()
+        .map(|entry| entry.key)
+        .collect();
+    assert_eq!(keys, vec!["contract-a::key-0", "contract-a::key-1"]);
+    drop(marf);
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,91 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use crate::chainstate::stacks::index::marf::*;
+use crate::chainstate::stacks::index::storage::*;
+use crate::chainstate::stacks::index::warm::TrieCacheWarming;
+use crate::chainstate::stacks::index::{ClarityMarfTrieId, MARFValue};
+use crate::chainstate::stacks::BlockHeaderHash;
+
+#[test]
+fn marf_warm_cache() {
+    for marf_opts in MARFOpenOpts::all().into_iter() {
+        test_debug!("With {:?}", &marf_opts);
+        let f = TrieFileStorage::new_memory(marf_opts).unwrap();
+        let mut marf = MARF::from_storage(f);
+
+        let mut parent = BlockHeaderHash::sentinel();
+        let mut blocks = vec![];
+        for i in 0..4u8 {
+            let block = BlockHeaderHash([i + 1; 32]);
+            marf.begin(&parent, &block).unwrap();
+            for j in 0..50 {
+                marf.insert(
+                    &format!("key-{}-{}", i, j),
+                    MARFValue::from_value(&format!("{}", j)),
+                )
+                .unwrap();
+            }
+            marf.commit().unwrap();
+            blocks.push(block);
+            parent = block;
+        }
+        let tip = *blocks.last().unwrap();
+        let cur_block = marf.borrow_storage_backend().get_cur_block();
+
+        let warming = TrieCacheWarming {
+            recent_blocks: 2,
+            hot_keys: vec!["key-0-1".to_string(), "no-such-key".to_string()],
+            budget: Duration::from_secs(60),
+        };
+        let stats = marf.warm_cache(&tip, &warming).unwrap();
+        assert_eq!(stats.keys_read, 2);
+        assert_eq!(stats.tries_read, 2);
+        assert!(stats.nodes_read > 0);
+        assert!(!stats.out_of_time);
+        assert_eq!(marf.borrow_storage_backend().get_cur_block(), cur_block);
+
+        // asking for more tries than there are blocks reads them all
+        let warming = TrieCacheWarming {
+            recent_blocks: 100,
+            hot_keys: vec![],
+            budget: Duration::from_secs(60),
+        };
+        let all_stats = marf.warm_cache(&tip, &warming).unwrap();
+        assert_eq!(all_stats.tries_read, 4);
+        assert!(all_stats.nodes_read > stats.nodes_read);
+
+        // lookups still work afterwards
+        assert_eq!(
+            marf.get(&tip, "key-2-7").unwrap(),
+            Some(MARFValue::from_value("7"))
+        );
+
+        // nothing is read once the budget is spent
+        let warming = TrieCacheWarming {
+            recent_blocks: 4,
+            hot_keys: vec!["key-0-1".to_string()],
+            budget: Duration::ZERO,
+        };
+        let stats = marf.warm_cache(&tip, &warming).unwrap();
+        assert!(stats.out_of_time);
+        assert_eq!(stats.keys_read, 0);
+        assert_eq!(stats.tries_read, 0);
+        assert_eq!(stats.nodes_read, 0);
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Randomized MARF workloads: sequences of inserts, lookups and block extensions (including
+//! forks off of arbitrary earlier blocks), checked against a model that keeps a plain
+//! `HashMap` per block.  Lookups that reach back through several forks exercise the
+//! back-pointer logic, which is where subtle MARF bugs tend to hide.
+
+use std::collections::HashMap;
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+use proptest::test_runner::TestCaseError;
+
+use crate::chainstate::stacks::index::marf::{MARFOpenOpts, MarfConnection, MARF};
+use crate::chainstate::stacks::index::{ClarityMarfTrieId, MARFValue, MarfTrieId};
+use crate::chainstate::stacks::BlockHeaderHash;
+
+/// Number of distinct keys a workload draws from.  Kept small so that keys are overwritten
+/// often, both within a block and across forks.
+pub const WORKLOAD_KEYS: usize = 24;
+
+#[derive(Debug, Clone)]
+pub enum MarfOp {
+    /// Insert `value` under `key` in the open block
+    Insert { key: usize, value: u16 },
+    /// Look up `key` in one of the committed blocks
+    Lookup { block: Index, key: usize },
+    /// Commit the open block and open a new one on top of one of the committed blocks
+    ExtendBlock { parent: Index },
+}
+
+pub fn marf_op() -> impl Strategy<Value = MarfOp> {
+    prop_oneof![
+        5 => (0..WORKLOAD_KEYS, any::<u16>())
+            .prop_map(|(key, value)| MarfOp::Insert { key, value }),
+        3 => (any::<Index>(), 0..WORKLOAD_KEYS)
+            .prop_map(|(block, key)| MarfOp::Lookup { block, key }),
+        1 => any::<Index>().prop_map(|parent| MarfOp::ExtendBlock { parent }),
+    ]
+}
+
+pub fn marf_workload(max_ops: usize) -> impl Strategy<Value = Vec<MarfOp>> {
+    prop::collection::vec(marf_op(), 1..max_ops)
+}
+
+fn workload_key(key: usize) -> String {
+    format!("workload-key-{}", key)
+}
+
+fn workload_value(value: u16) -> String {
+    format!("workload-value-{}", value)
+}
+
+fn workload_block(n: u64) -> BlockHeaderHash {
+    let mut bytes = [0u8; 32];
+    bytes[0..8].copy_from_slice(&n.to_be_bytes());
+    BlockHeaderHash(bytes)
+}
+
+/// The model the MARF is checked against: every block's full key/value map
+pub struct MarfOracle {
+    /// Committed blocks, in the order they were committed
+    committed: Vec<BlockHeaderHash>,
+    states: HashMap<BlockHeaderHash, HashMap<String, String>>,
+    open_block: BlockHeaderHash,
+    open_state: HashMap<String, String>,
+}
+
+impl MarfOracle {
+    pub fn new(first_block: BlockHeaderHash) -> MarfOracle {
+        MarfOracle {
+            committed: vec![],
+            states: HashMap::new(),
+            open_block: first_block,
+            open_state: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, key: String, value: String) {
+        self.open_state.insert(key, value);
+    }
+
+    pub fn commit(&mut self) {
+        self.committed.push(self.open_block);
+        self.states.insert(self.open_block, self.open_state.clone());
+    }
+
+    /// Open `next_block` on top of the committed block `parent`
+    pub fn extend(&mut self, parent: &BlockHeaderHash, next_block: BlockHeaderHash) {
+        self.open_state = self
+            .states
+            .get(parent)
+            .cloned()
+            .expect("BUG: extending an uncommitted block");
+        self.open_block = next_block;
+    }
+
+    pub fn get(&self, block: &BlockHeaderHash, key: &str) -> Option<&String> {
+        self.states.get(block).and_then(|state| state.get(key))
+    }
+}
+
+/// Run `ops` against an in-memory MARF and the oracle, failing as soon as they disagree.
+/// Once all ops have run, every key is checked in every block.
+pub fn run_marf_workload(ops: &[MarfOp]) -> Result<(), TestCaseError> {
+    let mut marf: MARF<BlockHeaderHash> =
+        MARF::from_path(":memory:", MARFOpenOpts::default()).unwrap();
+    let mut next_block_num = 1u64;
+    let first_block = workload_block(next_block_num);
+    marf.begin(&BlockHeaderHash::sentinel(), &first_block)
+        .unwrap();
+    let mut oracle = MarfOracle::new(first_block);
+
+    let check = |marf: &mut MARF<BlockHeaderHash>,
+                 oracle: &MarfOracle,
+                 block: &BlockHeaderHash,
+                 key: &str|
+     -> Result<(), TestCaseError> {
+        let expected = oracle
+            .get(block, key)
+            .map(|value| MARFValue::from_value(value));
+        let actual = marf.get(block, key).unwrap();
+        prop_assert_eq!(actual, expected, "key {} in block {}", key, block);
+        Ok(())
+    };
+
+    for op in ops.iter() {
+        match op {
+            MarfOp::Insert { key, value } => {
+                let key = workload_key(*key);
+                let value = workload_value(*value);
+                marf.insert(&key, MARFValue::from_value(&value)).unwrap();
+                oracle.insert(key, value);
+            }
+            MarfOp::Lookup { block, key } => {
+                if oracle.committed.is_empty() {
+                    continue;
+                }
+                let block = oracle.committed[block.index(oracle.committed.len())];
+                check(&mut marf, &oracle, &block, &workload_key(*key))?;
+            }
+            MarfOp::ExtendBlock { parent } => {
+                marf.commit().unwrap();
+                oracle.commit();
+
+                let parent = oracle.committed[parent.index(oracle.committed.len())];
+                next_block_num += 1;
+                let next_block = workload_block(next_block_num);
+                marf.begin(&parent, &next_block).unwrap();
+                oracle.extend(&parent, next_block);
+            }
+        }
+    }
+
+    marf.commit().unwrap();
+    oracle.commit();
+
+    for block in oracle.committed.iter() {
+        for key in 0..WORKLOAD_KEYS {
+            check(&mut marf, &oracle, block, &workload_key(key))?;
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn marf_lookups_match_oracle(ops in marf_workload(128)) {
+        run_marf_workload(&ops)?;
+    }
+}
@@ -210,7 +210,7 @@ impl Trie {
             // child is in this block
             if ptr.id() == (TrieNodeID::Empty as u8) {
                 // shouldn't happen
-                return Err(Error::CorruptionError("ptr is empty".to_string()));
+                return Err(Error::corruption("ptr is empty".to_string()));
             }
             let (node, node_hash) = storage.read_nodetype(ptr)?;
             return Ok((node, node_hash, ptr.clone()));
@@ -239,6 +239,7 @@ impl Trie {
 
             let backptr = ptr.from_backptr();
             storage.bench_mut().marf_find_backptr_node_finish();
+            storage.record_backptr_hop();
 
             let (node, node_hash) = storage.read_nodetype(&backptr)?;
             cursor.repair_backptr_step_backptr(&node, &backptr, storage.get_cur_block());
@@ -277,7 +278,7 @@ impl Trie {
     ) -> Result<TriePtr, Error> {
         let (cur_leaf, _) = storage.read_nodetype(&cursor.ptr())?;
         if !cur_leaf.is_leaf() {
-            return Err(Error::CorruptionError(format!(
+            return Err(Error::corruption(format!(
                 "Not a leaf: {:?}",
                 &cursor.ptr()
             )));
@@ -761,14 +762,14 @@ impl Trie {
         let cur_block_height =
             MARF::get_block_height_miner_tip(storage, &cur_block_header, &cur_block_header)
                 .map_err(|e| match e {
-                    Error::NotFoundError => Error::CorruptionError(format!(
+                    Error::NotFoundError => Error::corruption(format!(
                         "Could not obtain block height for block {}: not found",
                         &cur_block_header
                     )),
                     x => x,
                 })?
                 .ok_or_else(|| {
-                    Error::CorruptionError(format!(
+                    Error::corruption(format!(
                         "Could not obtain block height for block {}: got None",
                         &cur_block_header
                     ))
@@ -782,7 +783,7 @@ impl Trie {
                 &cur_block_header,
             )?
             .ok_or_else(|| {
-                Error::CorruptionError(format!(
+                Error::corruption(format!(
                     "Could not obtain block hash at block height {}",
                     cur_block_height - (1u32 << log_depth)
                 ))
@@ -887,15 +888,11 @@ impl Trie {
             trace!("Fix up root node so it mixes in its ancestor hashes");
             let (node, _cur_hash) = storage.read_nodetype(&child_ptr)?;
             if !node.is_node256() {
-                return Err(Error::CorruptionError(
-                    "Only ptr was not a node256".to_string(),
-                ));
+                return Err(Error::corruption("Only ptr was not a node256".to_string()));
             }
 
             if child_ptr != storage.root_trieptr() {
-                return Err(Error::CorruptionError(
-                    "Only ptr is not the root".to_string(),
-                ));
+                return Err(Error::corruption("Only ptr is not the root".to_string()));
             }
 
             let my_hash = get_nodetype_hash(storage, &node)?;
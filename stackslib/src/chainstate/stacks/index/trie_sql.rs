@@ -28,7 +28,9 @@ use std::{error, fmt, fs, io, os};
 use regex::Regex;
 use rusqlite::blob::Blob;
 use rusqlite::types::{FromSql, ToSql};
-use rusqlite::{Connection, Error as SqliteError, OptionalExtension, Transaction, NO_PARAMS};
+use rusqlite::{
+    params, Connection, Error as SqliteError, OptionalExtension, Transaction, NO_PARAMS,
+};
 use stacks_common::types::chainstate::{
     BlockHeaderHash, TrieHash, BLOCK_HEADER_HASH_ENCODED_SIZE, TRIEHASH_ENCODED_SIZE,
 };
@@ -47,7 +49,7 @@ use crate::chainstate::stacks::index::node::{
 use crate::chainstate::stacks::index::storage::{TrieFileStorage, TrieStorageConnection};
 use crate::chainstate::stacks::index::{trie_sql, BlockMap, Error, MarfTrieId, TrieLeaf};
 use crate::util_lib::db::{
-    query_count, query_row, query_rows, sql_pragma, tx_begin_immediate, u64_to_sql,
+    query_count, query_row, query_rows, sql_pragma, table_exists, tx_begin_immediate, u64_to_sql,
 };
 
 static SQL_MARF_DATA_TABLE: &str = "
@@ -96,6 +98,12 @@ INSERT OR REPLACE INTO migrated_version (version) VALUES (1);
 
 pub static SQL_MARF_SCHEMA_VERSION: u64 = 2;
 
+/// Every key inserted into the MARF, in any block.  Only kept if the MARF was created with
+/// `MARFOpenOpts::key_index`, since the trie itself stores keys by their hashes.
+static SQL_MARF_KEY_INDEX_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS marf_key_index (key TEXT PRIMARY KEY) WITHOUT ROWID;
+";
+
 pub fn create_tables_if_needed(conn: &mut Connection) -> Result<(), Error> {
     let tx = tx_begin_immediate(conn)?;
 
@@ -106,6 +114,55 @@ pub fn create_tables_if_needed(conn: &mut Connection) -> Result<(), Error> {
     tx.commit().map_err(|e| e.into())
 }
 
+/// Does this MARF keep a key index?
+pub fn has_key_index(conn: &Connection) -> Result<bool, Error> {
+    table_exists(conn, "marf_key_index").map_err(|e| e.into())
+}
+
+/// Start keeping a key index.  This can only be done before any trie is stored, since the keys
+/// of stored tries cannot be recovered from them.  Returns whether the index was created.
+pub fn create_key_index(conn: &mut Connection) -> Result<bool, Error> {
+    let tx = tx_begin_immediate(conn)?;
+    let has_tries = tx
+        .query_row("SELECT 1 FROM marf_data LIMIT 1", NO_PARAMS, |_| Ok(()))
+        .optional()?
+        .is_some();
+    if has_tries {
+        return Ok(false);
+    }
+    tx.execute_batch(SQL_MARF_KEY_INDEX_TABLE)?;
+    tx.commit()?;
+    Ok(true)
+}
+
+/// Record `keys` in the key index
+pub fn index_keys(conn: &Connection, keys: &[String]) -> Result<(), Error> {
+    let mut stmt = conn.prepare_cached("INSERT OR IGNORE INTO marf_key_index (key) VALUES (?1)")?;
+    for key in keys.iter() {
+        stmt.execute(&[key])?;
+    }
+    Ok(())
+}
+
+/// Up to `limit` indexed keys, in order, from `start` on.  `start` itself is only included if
+/// `include_start` is set.
+pub fn get_indexed_keys(
+    conn: &Connection,
+    start: &str,
+    include_start: bool,
+    limit: u32,
+) -> Result<Vec<String>, Error> {
+    let sql = if include_start {
+        "SELECT key FROM marf_key_index WHERE key >= ?1 ORDER BY key LIMIT ?2"
+    } else {
+        "SELECT key FROM marf_key_index WHERE key > ?1 ORDER BY key LIMIT ?2"
+    };
+    let mut stmt = conn.prepare_cached(sql)?;
+    let rows = stmt.query_map(params![start, limit], |row| row.get(0))?;
+    rows.collect::<Result<Vec<String>, _>>()
+        .map_err(|e| e.into())
+}
+
 fn get_schema_version(conn: &Connection) -> u64 {
     // if the table doesn't exist, then the version is 1.
     let sql = "SELECT version FROM schema_version";
@@ -706,5 +763,8 @@ pub fn clear_tables(tx: &Transaction) -> Result<(), Error> {
     tx.execute("DELETE FROM block_extension_locks", NO_PARAMS)?;
     tx.execute("DELETE FROM marf_data", NO_PARAMS)?;
     tx.execute("DELETE FROM mined_blocks", NO_PARAMS)?;
+    if has_key_index(tx)? {
+        tx.execute("DELETE FROM marf_key_index", NO_PARAMS)?;
+    }
     Ok(())
 }
@@ -0,0 +1,145 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Warming of the trie node cache.
+//!
+//! A freshly-opened MARF has an empty node cache, so the first lookups after a restart read
+//! every node they visit from disk.  Warming reads the paths to a set of hot keys at the chain
+//! tip, and then the nodes of the tries of the most recent blocks, before the MARF is put to
+//! use.  Which of the nodes read stay cached is up to the storage's cache strategy; with any
+//! strategy, the reads also pull the underlying pages into the OS page cache.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::chainstate::stacks::index::marf::MARF;
+use crate::chainstate::stacks::index::node::{is_backptr, TrieNodeID};
+use crate::chainstate::stacks::index::storage::TrieStorageConnection;
+use crate::chainstate::stacks::index::{Error, MarfTrieId};
+
+/// What to read when warming the trie node cache
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrieCacheWarming {
+    /// Number of tries to read, from the chain tip's back
+    pub recent_blocks: u32,
+    /// Keys whose paths are read at the chain tip, before any trie
+    pub hot_keys: Vec<String>,
+    /// Warming stops once this much time has been spent
+    pub budget: Duration,
+}
+
+/// What was read when warming the trie node cache
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrieCacheWarmingStats {
+    pub keys_read: usize,
+    /// Number of tries whose nodes were all read
+    pub tries_read: u32,
+    pub nodes_read: u64,
+    /// Whether warming stopped because it ran out of time
+    pub out_of_time: bool,
+}
+
+impl TrieCacheWarming {
+    /// Warm the cache of `storage` for lookups at `tip`.
+    /// The block that was open in `storage` beforehand is re-opened afterwards.
+    pub fn warm<T: MarfTrieId>(
+        &self,
+        storage: &mut TrieStorageConnection<T>,
+        tip: &T,
+    ) -> Result<TrieCacheWarmingStats, Error> {
+        let deadline = Instant::now() + self.budget;
+        let (cur_block_hash, cur_block_id) = storage.get_cur_block_and_id();
+
+        let mut stats = TrieCacheWarmingStats::default();
+        let result = self.warm_from(storage, tip, deadline, &mut stats);
+
+        // restore
+        storage
+            .open_block_maybe_id(&cur_block_hash, cur_block_id)
+            .map_err(|e| {
+                warn!(
+                    "Failed to re-open {} {:?}: {:?}",
+                    &cur_block_hash, cur_block_id, &e
+                );
+                e
+            })?;
+
+        result.map(|_| stats)
+    }
+
+    fn warm_from<T: MarfTrieId>(
+        &self,
+        storage: &mut TrieStorageConnection<T>,
+        tip: &T,
+        deadline: Instant,
+        stats: &mut TrieCacheWarmingStats,
+    ) -> Result<(), Error> {
+        for key in self.hot_keys.iter() {
+            if Instant::now() >= deadline {
+                stats.out_of_time = true;
+                return Ok(());
+            }
+            MARF::get_by_key(storage, tip, key)?;
+            stats.keys_read += 1;
+        }
+
+        if self.recent_blocks == 0 {
+            return Ok(());
+        }
+        let tip_height = MARF::get_block_height(storage, tip, tip)?.ok_or(Error::NotFoundError)?;
+        let lowest_height = tip_height.saturating_sub(self.recent_blocks - 1);
+        for height in (lowest_height..=tip_height).rev() {
+            let block = match MARF::get_block_at_height(storage, height, tip)? {
+                Some(block) => block,
+                None => break,
+            };
+            if !Self::read_trie(storage, &block, deadline, stats)? {
+                stats.out_of_time = true;
+                return Ok(());
+            }
+            stats.tries_read += 1;
+        }
+        Ok(())
+    }
+
+    /// Read the nodes of the trie of `block` breadth-first from its root, so that the nodes
+    /// visited by the most lookups are read first.  Nodes that the trie back-points to belong
+    /// to its ancestors' tries and are not read.
+    /// Returns false if the deadline passed before every node was read.
+    fn read_trie<T: MarfTrieId>(
+        storage: &mut TrieStorageConnection<T>,
+        block: &T,
+        deadline: Instant,
+        stats: &mut TrieCacheWarmingStats,
+    ) -> Result<bool, Error> {
+        storage.open_block(block)?;
+        let mut pending = VecDeque::new();
+        pending.push_back(storage.root_trieptr());
+        while let Some(ptr) = pending.pop_front() {
+            if Instant::now() >= deadline {
+                return Ok(false);
+            }
+            let (node, _) = storage.read_nodetype(&ptr)?;
+            stats.nodes_read += 1;
+            for child in node.ptrs().iter() {
+                if child.id() != TrieNodeID::Empty as u8 && !is_backptr(child.id()) {
+                    pending.push_back(*child);
+                }
+            }
+        }
+        Ok(true)
+    }
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::VecDeque;
 use std::error::Error;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -23,10 +24,12 @@ use std::{fmt, fs};
 use clarity::vm::costs::ExecutionCost;
 use lazy_static::lazy_static;
 use rusqlite::{OpenFlags, OptionalExtension};
+use stacks_common::types::chainstate::StacksBlockId;
 use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::uint::{Uint256, Uint512};
 
 use crate::burnchains::{BurnchainSigner, Txid};
+use crate::chainstate::stacks::index::stats::TrieIOStats;
 use crate::core::MemPoolDB;
 use crate::net::httpcore::{StacksHttpRequest, StacksHttpResponse};
 use crate::net::rpc::ConversationHttp;
@@ -41,6 +44,65 @@ lazy_static! {
     static ref GLOBAL_BURNCHAIN_SIGNER: Mutex<Option<BurnchainSigner>> = Mutex::new(None);
 }
 
+lazy_static! {
+    static ref BURNCHAIN_BACKFILL_PROGRESS: Mutex<Option<BurnchainBackfillProgress>> =
+        Mutex::new(None);
+    /// Latest (burnchain headers height, sortition height), each once known
+    static ref BURNCHAIN_SYNC_HEIGHTS: Mutex<(Option<u64>, Option<u64>)> = Mutex::new((None, None));
+    /// MARF I/O of the most recently processed Stacks blocks, oldest first
+    static ref RECENT_BLOCK_MARF_IO: Mutex<VecDeque<BlockMarfIOStats>> =
+        Mutex::new(VecDeque::with_capacity(RECENT_BLOCK_MARF_IO_LEN));
+}
+
+/// Number of recently-processed blocks whose MARF I/O is retained for the `/v2/marf/io`
+/// endpoint
+pub const RECENT_BLOCK_MARF_IO_LEN: usize = 64;
+
+/// Progress of the burnchain block backfill that follows a header-only fast sync.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BurnchainBackfillProgress {
+    /// Highest processed burnchain block when the backfill started
+    pub start_height: u64,
+    /// Highest burnchain block whose operations have been parsed and processed so far
+    pub processed_height: u64,
+    /// Burnchain header height that the backfill is working towards
+    pub target_height: u64,
+}
+
+impl BurnchainBackfillProgress {
+    /// Fraction of the backfill that has been completed, in [0.0, 1.0]
+    pub fn fraction_complete(&self) -> f64 {
+        if self.target_height <= self.start_height {
+            return 1.0;
+        }
+        let done = self.processed_height.saturating_sub(self.start_height) as f64;
+        let total = (self.target_height - self.start_height) as f64;
+        (done / total).min(1.0)
+    }
+}
+
+/// How far the node has synced the burnchain.  A growing `drift` with a moving
+/// `headers_height` means the chains coordinator is behind; a stalled `headers_height` means
+/// the burnchain indexer is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BurnchainSyncHeights {
+    /// Highest burnchain header downloaded by the burnchain indexer
+    pub headers_height: u64,
+    /// Burnchain block height of the canonical sortition tip
+    pub sortition_height: u64,
+    /// Number of downloaded burnchain headers that have not yet been processed into sortitions
+    pub drift: u64,
+}
+
+/// Storage I/O done by the Clarity state MARF while processing a Stacks block, from the start of
+/// its processing until it was committed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockMarfIOStats {
+    pub block_id: StacksBlockId,
+    pub block_height: u64,
+    pub io: TrieIOStats,
+}
+
 pub fn increment_rpc_calls_counter() {
     #[cfg(feature = "monitoring_prom")]
     prometheus::RPC_CALL_COUNTER.inc();
@@ -169,6 +231,77 @@ pub fn increment_btc_ops_sent_counter() {
     prometheus::BTC_OPS_SENT_COUNTER.inc();
 }
 
+#[allow(unused_variables)]
+pub fn increment_burnchain_ops_submitted(op_type: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::BURNCHAIN_OPS_SUBMITTED
+        .with_label_values(&[op_type])
+        .inc();
+}
+
+/// Log a submitted burnchain operation that was mined `blocks` burnchain blocks and `seconds`
+/// seconds after it was submitted.
+#[allow(unused_variables)]
+pub fn log_burnchain_op_confirmed(op_type: &str, blocks: u64, seconds: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::BURNCHAIN_OPS_CONFIRMED
+            .with_label_values(&[op_type])
+            .inc();
+        prometheus::BURNCHAIN_OP_CONFIRMATION_BLOCKS
+            .with_label_values(&[op_type])
+            .observe(blocks as f64);
+        prometheus::BURNCHAIN_OP_CONFIRMATION_TIME
+            .with_label_values(&[op_type])
+            .observe(seconds as f64);
+    }
+}
+
+/// Log a submitted burnchain operation that was given up on without being mined
+#[allow(unused_variables)]
+pub fn log_burnchain_op_failed(op_type: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::BURNCHAIN_OPS_FAILED
+        .with_label_values(&[op_type])
+        .inc();
+}
+
+/// Log the time (milliseconds) an Atlas attachments batch spent in `stage`
+#[allow(unused_variables)]
+pub fn log_atlas_batch_stage_time(stage: &str, elapsed_ms: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::ATLAS_BATCH_STAGE_TIME
+        .with_label_values(&[stage])
+        .observe(elapsed_ms as f64 / 1000.0);
+}
+
+/// Log an Atlas attachments batch leaving the downloader's memory before being fully
+/// downloaded, either `spilled` back to the AtlasDB, `dropped` after exhausting its retries, or
+/// `superseded` by newer attachment instances
+#[allow(unused_variables)]
+pub fn log_atlas_batch_evicted(reason: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::ATLAS_BATCHES_EVICTED
+        .with_label_values(&[reason])
+        .inc();
+}
+
+/// Set the estimated memory (bytes) held by the Atlas downloader's batches
+#[allow(unused_variables)]
+pub fn set_atlas_downloader_memory_usage(bytes: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::ATLAS_DOWNLOADER_MEMORY_BYTES.set(i64::try_from(bytes).unwrap_or(i64::MAX));
+}
+
+/// Set the fraction of submitted burnchain operations of `op_type` that were mined
+#[allow(unused_variables)]
+pub fn set_burnchain_op_success_rate(op_type: &str, rate: f64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::BURNCHAIN_OP_SUCCESS_RATE
+        .with_label_values(&[op_type])
+        .set(rate);
+}
+
 pub fn increment_stx_blocks_processed_counter() {
     #[cfg(feature = "monitoring_prom")]
     prometheus::STX_BLOCKS_PROCESSED_COUNTER.inc();
@@ -353,6 +486,16 @@ pub fn increment_contract_calls_processed() {
     prometheus::CONTRACT_CALLS_PROCESSED_COUNT.inc();
 }
 
+pub fn increment_marf_node_hash_cache_hits() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::MARF_NODE_HASH_CACHE_HITS.inc();
+}
+
+pub fn increment_marf_node_hash_cache_misses() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::MARF_NODE_HASH_CACHE_MISSES.inc();
+}
+
 /// Given a value (type uint256), return value/uint256::max() as an f64 value.
 /// The precision of the percentage is determined by the input `precision_points`, which is capped
 /// at a max of 15.
@@ -410,6 +553,44 @@ pub fn test_convert_uint256_to_f64() {
     assert_approx_eq!(convert_uint256_to_f64_percentage(original, 1000), 12.234567);
 }
 
+#[test]
+pub fn test_burnchain_backfill_fraction_complete() {
+    let mut progress = BurnchainBackfillProgress {
+        start_height: 100,
+        processed_height: 100,
+        target_height: 200,
+    };
+    assert_approx_eq!(progress.fraction_complete(), 0.0);
+
+    progress.processed_height = 150;
+    assert_approx_eq!(progress.fraction_complete(), 0.5);
+
+    progress.processed_height = 250;
+    assert_approx_eq!(progress.fraction_complete(), 1.0);
+
+    // nothing to backfill
+    progress.target_height = 100;
+    assert_approx_eq!(progress.fraction_complete(), 1.0);
+}
+
+#[test]
+pub fn test_burnchain_sync_heights() {
+    set_burnchain_headers_height(200);
+    set_sortition_height(150);
+    assert_eq!(
+        get_burnchain_sync_heights(),
+        Some(BurnchainSyncHeights {
+            headers_height: 200,
+            sortition_height: 150,
+            drift: 50,
+        })
+    );
+
+    // the sortition height can briefly be ahead of a stale headers height
+    set_sortition_height(210);
+    assert_eq!(get_burnchain_sync_heights().unwrap().drift, 0);
+}
+
 #[allow(unused_variables)]
 pub fn update_computed_relative_miner_score(value: Uint256) {
     #[cfg(feature = "monitoring_prom")]
@@ -466,6 +647,92 @@ pub fn get_burnchain_signer() -> Option<BurnchainSigner> {
     None
 }
 
+/// Record the progress of an ongoing burnchain backfill, or clear it (`None`) once the backfill
+/// has finished.  This is reported by the `/v2/info` endpoint.
+pub fn set_burnchain_backfill_progress(progress: Option<BurnchainBackfillProgress>) {
+    *BURNCHAIN_BACKFILL_PROGRESS.lock().unwrap() = progress;
+}
+
+/// Get the progress of the ongoing burnchain backfill, if there is one.
+pub fn get_burnchain_backfill_progress() -> Option<BurnchainBackfillProgress> {
+    BURNCHAIN_BACKFILL_PROGRESS.lock().unwrap().clone()
+}
+
+/// Record the highest burnchain header downloaded by the burnchain indexer.
+pub fn set_burnchain_headers_height(height: u64) {
+    let mut heights = BURNCHAIN_SYNC_HEIGHTS.lock().unwrap();
+    heights.0 = Some(height);
+    report_burnchain_sync_heights(&heights);
+}
+
+/// Record the burnchain block height of the canonical sortition tip.
+pub fn set_sortition_height(height: u64) {
+    let mut heights = BURNCHAIN_SYNC_HEIGHTS.lock().unwrap();
+    heights.1 = Some(height);
+    report_burnchain_sync_heights(&heights);
+}
+
+#[allow(unused_variables)]
+fn report_burnchain_sync_heights(heights: &(Option<u64>, Option<u64>)) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        if let Some(headers_height) = heights.0 {
+            prometheus::BURNCHAIN_HEADERS_HEIGHT_GAUGE.set(headers_height as i64);
+        }
+        if let Some(sortition_height) = heights.1 {
+            prometheus::SORTITION_HEIGHT_GAUGE.set(sortition_height as i64);
+        }
+        if let (Some(headers_height), Some(sortition_height)) = heights {
+            prometheus::BURNCHAIN_SORTITION_DRIFT_GAUGE
+                .set(headers_height.saturating_sub(*sortition_height) as i64);
+        }
+    }
+}
+
+/// Get the burnchain sync heights, once both have been recorded.
+/// This is reported by the `/v2/info` endpoint.
+pub fn get_burnchain_sync_heights() -> Option<BurnchainSyncHeights> {
+    match *BURNCHAIN_SYNC_HEIGHTS.lock().unwrap() {
+        (Some(headers_height), Some(sortition_height)) => Some(BurnchainSyncHeights {
+            headers_height,
+            sortition_height,
+            drift: headers_height.saturating_sub(sortition_height),
+        }),
+        _ => None,
+    }
+}
+
+/// Record the MARF I/O of a processed Stacks block.  The most recent ones are reported by the
+/// `/v2/marf/io` endpoint.
+pub fn record_block_marf_io(stats: BlockMarfIOStats) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        for (counter, value) in stats.io.counters() {
+            prometheus::MARF_BLOCK_IO_TOTAL
+                .with_label_values(&[counter])
+                .inc_by(value);
+            prometheus::LAST_BLOCK_MARF_IO
+                .with_label_values(&[counter])
+                .set(i64::try_from(value).unwrap_or(i64::MAX));
+        }
+    }
+    let mut recent = RECENT_BLOCK_MARF_IO.lock().unwrap();
+    if recent.len() >= RECENT_BLOCK_MARF_IO_LEN {
+        recent.pop_front();
+    }
+    recent.push_back(stats);
+}
+
+/// Get the MARF I/O of the most recently processed Stacks blocks, oldest first
+pub fn get_recent_block_marf_io() -> Vec<BlockMarfIOStats> {
+    RECENT_BLOCK_MARF_IO
+        .lock()
+        .unwrap()
+        .iter()
+        .cloned()
+        .collect()
+}
+
 #[derive(Debug)]
 pub struct SetGlobalBurnchainSignerError;
 
@@ -16,9 +16,10 @@
 
 use lazy_static::lazy_static;
 use prometheus::{
-    histogram_opts, labels, opts, register_gauge, register_histogram, register_histogram_vec,
-    register_int_counter, register_int_counter_vec, register_int_gauge, Gauge, Histogram,
-    HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    histogram_opts, labels, opts, register_gauge, register_gauge_vec, register_histogram,
+    register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Gauge, GaugeVec, Histogram, HistogramTimer, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec,
 };
 
 lazy_static! {
@@ -170,6 +171,21 @@ lazy_static! {
         "Burnchain tip height"
     )).unwrap();
 
+    pub static ref BURNCHAIN_HEADERS_HEIGHT_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_burnchain_headers_height",
+        "Highest burnchain header downloaded by the burnchain indexer"
+    )).unwrap();
+
+    pub static ref SORTITION_HEIGHT_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_sortition_height",
+        "Burnchain block height of the canonical sortition tip"
+    )).unwrap();
+
+    pub static ref BURNCHAIN_SORTITION_DRIFT_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_burnchain_sortition_drift",
+        "Number of downloaded burnchain headers not yet processed into sortitions"
+    )).unwrap();
+
     pub static ref INBOUND_NEIGHBORS_GAUGE: IntGauge = register_int_gauge!(opts!(
         "stacks_node_neighbors_inbound",
         "Total count of current known inbound neighbors"
@@ -217,6 +233,16 @@ lazy_static! {
         "Total count of processed contract calls"
     )).unwrap();
 
+    pub static ref MARF_NODE_HASH_CACHE_HITS: IntCounter = register_int_counter!(opts!(
+        "stacks_node_marf_node_hash_cache_hits",
+        "Total count of MARF node hashes served from the node hash cache"
+    )).unwrap();
+
+    pub static ref MARF_NODE_HASH_CACHE_MISSES: IntCounter = register_int_counter!(opts!(
+        "stacks_node_marf_node_hash_cache_misses",
+        "Total count of MARF node hashes that had to be read from disk with the node hash cache enabled"
+    )).unwrap();
+
     pub static ref MEMPOOL_OUTSTANDING_TXS: IntGauge = register_int_gauge!(opts!(
         "stacks_node_mempool_outstanding_txs",
         "Number of still-unprocessed transactions received by this node since it started",
@@ -230,6 +256,71 @@ lazy_static! {
         labels! {"handler".to_string() => "all".to_string(),}
     )).unwrap();
 
+    pub static ref BURNCHAIN_OPS_SUBMITTED: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_burnchain_ops_submitted_total",
+        "Total number of burnchain operations submitted, by operation type",
+        &["op_type"]
+    ).unwrap();
+
+    pub static ref BURNCHAIN_OPS_CONFIRMED: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_burnchain_ops_confirmed_total",
+        "Total number of submitted burnchain operations that were mined, by operation type",
+        &["op_type"]
+    ).unwrap();
+
+    pub static ref BURNCHAIN_OPS_FAILED: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_burnchain_ops_failed_total",
+        "Total number of submitted burnchain operations that were never mined, by operation type",
+        &["op_type"]
+    ).unwrap();
+
+    pub static ref BURNCHAIN_OP_SUCCESS_RATE: GaugeVec = register_gauge_vec!(
+        "stacks_node_burnchain_op_success_rate",
+        "Fraction of all submitted burnchain operations of a type that were mined, including those submitted before the last restart",
+        &["op_type"]
+    ).unwrap();
+
+    pub static ref MARF_BLOCK_IO_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_marf_block_io_total",
+        "Total storage I/O done by the Clarity state MARF while processing Stacks blocks, by counter",
+        &["counter"]
+    ).unwrap();
+
+    pub static ref LAST_BLOCK_MARF_IO: IntGaugeVec = register_int_gauge_vec!(
+        "stacks_node_last_block_marf_io",
+        "Storage I/O done by the Clarity state MARF while processing the last Stacks block, by counter",
+        &["counter"]
+    ).unwrap();
+
+    pub static ref BURNCHAIN_OP_CONFIRMATION_BLOCKS: HistogramVec = register_histogram_vec!(histogram_opts!(
+        "stacks_node_burnchain_op_confirmation_blocks",
+        "Burnchain blocks between submitting a burnchain operation and it being mined",
+        vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 8.0, 10.0, 15.0, 20.0]
+    ), &["op_type"]).unwrap();
+
+    pub static ref BURNCHAIN_OP_CONFIRMATION_TIME: HistogramVec = register_histogram_vec!(histogram_opts!(
+        "stacks_node_burnchain_op_confirmation_times",
+        "Time (seconds) between submitting a burnchain operation and it being mined",
+        vec![60.0, 300.0, 600.0, 900.0, 1200.0, 1800.0, 2400.0, 3600.0, 5400.0, 7200.0, 10800.0]
+    ), &["op_type"]).unwrap();
+
+    pub static ref ATLAS_BATCH_STAGE_TIME: HistogramVec = register_histogram_vec!(histogram_opts!(
+        "stacks_node_atlas_batch_stage_times",
+        "Time (seconds) an Atlas attachments batch spent in each stage of its download",
+        vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0]
+    ), &["stage"]).unwrap();
+
+    pub static ref ATLAS_BATCHES_EVICTED: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_atlas_batches_evicted_total",
+        "Total number of Atlas attachments batches removed from the downloader's memory before completing, by reason (spilled or dropped)",
+        &["reason"]
+    ).unwrap();
+
+    pub static ref ATLAS_DOWNLOADER_MEMORY_BYTES: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_atlas_downloader_memory_bytes",
+        "Estimated memory held by the Atlas downloader's queued and ongoing attachments batches"
+    )).unwrap();
+
     pub static ref COMPUTED_RELATIVE_MINER_SCORE: Gauge = register_gauge!(opts!(
         "stacks_node_computed_relative_miner_score",
         "Percentage of the u256 range that this miner is assigned in a particular round of sortition"
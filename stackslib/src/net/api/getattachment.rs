@@ -23,7 +23,8 @@ use stacks_common::util::hash::Hash160;
 use url::form_urlencoded;
 
 use crate::net::atlas::{
-    AttachmentPage, GetAttachmentResponse, MAX_ATTACHMENT_INV_PAGES_PER_REQUEST,
+    advertise_data_url, AttachmentPage, GetAttachmentChunkResponse, GetAttachmentResponse,
+    MAX_ATTACHMENT_INV_PAGES_PER_REQUEST,
 };
 use crate::net::http::{
     parse_json, Error, HttpBadRequest, HttpNotFound, HttpRequest, HttpRequestContents,
@@ -39,12 +40,15 @@ use crate::net::{Error as NetError, StacksNodeState};
 #[derive(Clone)]
 pub struct RPCGetAttachmentRequestHandler {
     pub attachment_hash: Option<Hash160>,
+    /// `(offset, length)` of the requested byte range, if only part of the attachment is wanted
+    pub range: Option<(u64, u64)>,
 }
 
 impl RPCGetAttachmentRequestHandler {
     pub fn new() -> Self {
         Self {
             attachment_hash: None,
+            range: None,
         }
     }
 }
@@ -90,6 +94,32 @@ impl HttpRequest for RPCGetAttachmentRequestHandler {
                 .map_err(|_| Error::DecodeError("Failed to decode `attachment_hash`".into()))?,
         );
 
+        // optionally, offset= and length= select a byte range of the attachment
+        let mut offset = None;
+        let mut length = None;
+        if let Some(query_str) = query {
+            for (key, value) in form_urlencoded::parse(query_str.as_bytes()) {
+                if key == "offset" {
+                    offset = Some(value.parse::<u64>().map_err(|_| {
+                        Error::DecodeError("Invalid Http request: bad offset".to_string())
+                    })?);
+                } else if key == "length" {
+                    length = Some(value.parse::<u64>().map_err(|_| {
+                        Error::DecodeError("Invalid Http request: bad length".to_string())
+                    })?);
+                }
+            }
+        }
+        self.range = match (offset, length) {
+            (None, None) => None,
+            (Some(offset), Some(length)) if length > 0 => Some((offset, length)),
+            _ => {
+                return Err(Error::DecodeError(
+                    "Invalid Http request: expecting both offset and a non-zero length".to_string(),
+                ));
+            }
+        };
+
         Ok(HttpRequestContents::new().query_string(query))
     }
 }
@@ -98,6 +128,7 @@ impl RPCRequestHandler for RPCGetAttachmentRequestHandler {
     /// Reset internal state
     fn restart(&mut self) {
         self.attachment_hash = None;
+        self.range = None;
     }
 
     fn try_handle_request(
@@ -110,6 +141,7 @@ impl RPCRequestHandler for RPCGetAttachmentRequestHandler {
             .attachment_hash
             .take()
             .ok_or(NetError::SendError("Missing `attachment_hash`".into()))?;
+        let range = self.range.take();
 
         let attachment_res = node.with_node_state(
             |network, _sortdb, _chainstate, _mempool, _rpc_args| match network
@@ -133,10 +165,44 @@ impl RPCRequestHandler for RPCGetAttachmentRequestHandler {
                 return response.try_into_contents().map_err(NetError::from);
             }
         };
+        let data_url =
+            node.with_node_state(|network, _sortdb, _chainstate, _mempool, _rpc_args| {
+                network.get_local_peer().data_url.clone()
+            });
+
+        let (offset, length) = match range {
+            Some(range) => range,
+            None => {
+                let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+                preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+                advertise_data_url(&mut preamble, &data_url);
+                let body = HttpResponseContents::try_from_json(&attachment)?;
+                return Ok((preamble, body));
+            }
+        };
+
+        let content = attachment.attachment.content;
+        let total_length = content.len() as u64;
+        if offset >= total_length {
+            let msg = format!(
+                "Offset {} is out of range for an attachment of {} bytes",
+                offset, total_length
+            );
+            warn!("{}", msg);
+            return StacksHttpResponse::new_error(&preamble, &HttpBadRequest::new(msg))
+                .try_into_contents();
+        }
+        let end = offset.saturating_add(length).min(total_length);
+        let chunk = GetAttachmentChunkResponse {
+            offset,
+            total_length,
+            chunk: content[offset as usize..end as usize].to_vec(),
+        };
 
         let mut preamble = HttpResponsePreamble::ok_json(&preamble);
         preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
-        let body = HttpResponseContents::try_from_json(&attachment)?;
+        advertise_data_url(&mut preamble, &data_url);
+        let body = HttpResponseContents::try_from_json(&chunk)?;
         Ok((preamble, body))
     }
 }
@@ -148,8 +214,18 @@ impl HttpResponse for RPCGetAttachmentRequestHandler {
         preamble: &HttpResponsePreamble,
         body: &[u8],
     ) -> Result<HttpResponsePayload, Error> {
-        let pages: GetAttachmentResponse = parse_json(preamble, body)?;
-        Ok(HttpResponsePayload::try_from_json(pages)?)
+        // a whole attachment is a hex string, and a byte range of one is an object
+        let json: serde_json::Value = parse_json(preamble, body)?;
+        if json.is_string() {
+            let attachment: GetAttachmentResponse = serde_json::from_value(json)
+                .map_err(|e| Error::DecodeError(format!("Failed to decode attachment: {}", e)))?;
+            Ok(HttpResponsePayload::try_from_json(attachment)?)
+        } else {
+            let chunk: GetAttachmentChunkResponse = serde_json::from_value(json).map_err(|e| {
+                Error::DecodeError(format!("Failed to decode attachment chunk: {}", e))
+            })?;
+            Ok(HttpResponsePayload::try_from_json(chunk)?)
+        }
     }
 }
 
@@ -164,6 +240,24 @@ impl StacksHttpRequest {
         )
         .expect("FATAL: failed to construct request from infallible data")
     }
+
+    /// Make a new request for `length` bytes of an attachment, starting at `offset`
+    pub fn new_getattachment_range(
+        host: PeerHost,
+        attachment_id: Hash160,
+        offset: u64,
+        length: u64,
+    ) -> StacksHttpRequest {
+        StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            format!("/v2/attachments/{}", &attachment_id),
+            HttpRequestContents::new()
+                .query_arg("offset".into(), format!("{}", offset))
+                .query_arg("length".into(), format!("{}", length)),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
 }
 
 impl StacksHttpResponse {
@@ -174,4 +268,23 @@ impl StacksHttpResponse {
             .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
         Ok(resp)
     }
+
+    /// Decode the response to a range request.  Peers that do not serve byte ranges answer
+    /// with the whole attachment, which is returned as a chunk covering all of it.
+    pub fn decode_atlas_get_attachment_chunk(self) -> Result<GetAttachmentChunkResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        if contents_json.is_string() {
+            let resp: GetAttachmentResponse = serde_json::from_value(contents_json)
+                .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+            return Ok(GetAttachmentChunkResponse {
+                offset: 0,
+                total_length: resp.attachment.content.len() as u64,
+                chunk: resp.attachment.content,
+            });
+        }
+        let resp: GetAttachmentChunkResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
 }
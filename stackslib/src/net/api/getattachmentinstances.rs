@@ -0,0 +1,276 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{Read, Write};
+
+use clarity::vm::representations::{CONTRACT_NAME_REGEX_STRING, STANDARD_PRINCIPAL_REGEX_STRING};
+use clarity::vm::types::QualifiedContractIdentifier;
+use regex::{Captures, Regex};
+use stacks_common::types::net::PeerHost;
+use url::form_urlencoded;
+
+use crate::net::atlas::{GetAttachmentInstancesResponse, MAX_ATTACHMENT_INSTANCES_PER_PAGE};
+use crate::net::http::{
+    parse_json, Error, HttpBadRequest, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    request, HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+
+#[derive(Clone)]
+pub struct RPCGetAttachmentInstancesRequestHandler {
+    pub contract_identifier: Option<QualifiedContractIdentifier>,
+    pub start_height: Option<u64>,
+    pub end_height: Option<u64>,
+    pub page: Option<u32>,
+}
+
+impl RPCGetAttachmentInstancesRequestHandler {
+    pub fn new() -> Self {
+        Self {
+            contract_identifier: None,
+            start_height: None,
+            end_height: None,
+            page: None,
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetAttachmentInstancesRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(&format!(
+            "^/v2/attachments/instances/(?P<address>{})/(?P<contract>{})$",
+            *STANDARD_PRINCIPAL_REGEX_STRING, *CONTRACT_NAME_REGEX_STRING
+        ))
+        .unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v2/attachments/instances/:principal/:contract_name"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let contract_identifier = request::get_contract_address(captures, "address", "contract")?;
+
+        let query_str = if let Some(qs) = query {
+            qs
+        } else {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expecting start_height and end_height".to_string(),
+            ));
+        };
+
+        let mut start_height = None;
+        let mut end_height = None;
+        let mut page = 0;
+
+        // expect start_height= and end_height=, and optionally page=
+        for (key, value) in form_urlencoded::parse(query_str.as_bytes()) {
+            if key == "start_height" {
+                start_height = Some(value.parse::<u64>().map_err(|_| {
+                    Error::DecodeError("Invalid Http request: bad start_height".to_string())
+                })?);
+            } else if key == "end_height" {
+                end_height = Some(value.parse::<u64>().map_err(|_| {
+                    Error::DecodeError("Invalid Http request: bad end_height".to_string())
+                })?);
+            } else if key == "page" {
+                page = value.parse::<u32>().map_err(|_| {
+                    Error::DecodeError("Invalid Http request: bad page".to_string())
+                })?;
+            }
+        }
+
+        let (start_height, end_height) = match (start_height, end_height) {
+            (Some(start), Some(end)) => (start, end),
+            _ => {
+                return Err(Error::DecodeError(
+                    "Invalid Http request: expecting start_height and end_height".to_string(),
+                ));
+            }
+        };
+
+        if start_height > end_height {
+            return Err(Error::DecodeError(
+                "Invalid Http request: start_height is greater than end_height".to_string(),
+            ));
+        }
+
+        self.contract_identifier = Some(contract_identifier);
+        self.start_height = Some(start_height);
+        self.end_height = Some(end_height);
+        self.page = Some(page);
+
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCGetAttachmentInstancesRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.contract_identifier = None;
+        self.start_height = None;
+        self.end_height = None;
+        self.page = None;
+    }
+
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let contract_identifier = self.contract_identifier.take().ok_or(NetError::SendError(
+            "`contract_identifier` not set".to_string(),
+        ))?;
+        let start_height = self
+            .start_height
+            .take()
+            .ok_or(NetError::SendError("Missing `start_height`".into()))?;
+        let end_height = self
+            .end_height
+            .take()
+            .ok_or(NetError::SendError("Missing `end_height`".into()))?;
+        let page = self
+            .page
+            .take()
+            .ok_or(NetError::SendError("Missing `page`".into()))?;
+
+        let offset = match page.checked_mul(MAX_ATTACHMENT_INSTANCES_PER_PAGE) {
+            Some(offset) => offset,
+            None => {
+                let msg = format!("Page index {} is out of range", page);
+                warn!("{}", msg);
+                return StacksHttpResponse::new_error(&preamble, &HttpBadRequest::new(msg))
+                    .try_into_contents();
+            }
+        };
+
+        // fetch one extra instance so we can tell whether or not there's another page
+        let instances_res =
+            node.with_node_state(|network, _sortdb, _chainstate, _mempool, _rpc_args| {
+                network
+                    .get_atlasdb()
+                    .find_attachment_instances_by_contract(
+                        &contract_identifier,
+                        start_height,
+                        end_height,
+                        offset,
+                        MAX_ATTACHMENT_INSTANCES_PER_PAGE + 1,
+                    )
+                    .map_err(|e| {
+                        let msg = format!("Unable to read Atlas DB - {}", e);
+                        warn!("{}", msg);
+                        msg
+                    })
+            });
+
+        let mut instances = match instances_res {
+            Ok(instances) => instances,
+            Err(msg) => {
+                return StacksHttpResponse::new_error(&preamble, &HttpServerError::new(msg))
+                    .try_into_contents();
+            }
+        };
+
+        let next_page = if instances.len() > MAX_ATTACHMENT_INSTANCES_PER_PAGE as usize {
+            instances.truncate(MAX_ATTACHMENT_INSTANCES_PER_PAGE as usize);
+            Some(page + 1)
+        } else {
+            None
+        };
+
+        let content = GetAttachmentInstancesResponse {
+            instances,
+            next_page,
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&content)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetAttachmentInstancesRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let instances: GetAttachmentInstancesResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(instances)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for a page of a contract's attachment instances within a block height range
+    pub fn new_getattachmentinstances(
+        host: PeerHost,
+        contract_identifier: &QualifiedContractIdentifier,
+        start_height: u64,
+        end_height: u64,
+        page: u32,
+    ) -> StacksHttpRequest {
+        StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            format!(
+                "/v2/attachments/instances/{}/{}",
+                &contract_identifier.issuer, &contract_identifier.name
+            ),
+            HttpRequestContents::new()
+                .query_arg("start_height".into(), format!("{}", start_height))
+                .query_arg("end_height".into(), format!("{}", end_height))
+                .query_arg("page".into(), format!("{}", page)),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_attachment_instances_response(
+        self,
+    ) -> Result<GetAttachmentInstancesResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: GetAttachmentInstancesResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
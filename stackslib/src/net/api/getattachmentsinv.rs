@@ -14,16 +14,18 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::{Read, Write};
 
 use regex::{Captures, Regex};
 use stacks_common::types::chainstate::{ConsensusHash, StacksBlockId};
 use stacks_common::types::net::PeerHost;
+use stacks_common::util::hash::Hash160;
 use url::form_urlencoded;
 
 use crate::net::atlas::{
-    AttachmentPage, GetAttachmentsInvResponse, MAX_ATTACHMENT_INV_PAGES_PER_REQUEST,
+    advertise_data_url, AttachmentPage, GetAttachmentsInvResponse,
+    MAX_ATTACHMENT_INV_PAGES_PER_REQUEST,
 };
 use crate::net::http::{
     parse_json, Error, HttpBadRequest, HttpNotFound, HttpRequest, HttpRequestContents,
@@ -40,6 +42,9 @@ use crate::net::{Error as NetError, StacksNodeState};
 pub struct RPCGetAttachmentsInvRequestHandler {
     pub index_block_hash: Option<StacksBlockId>,
     pub page_indexes: Option<Vec<u32>>,
+    /// Digests of the versions of pages the client already has. Those pages are left out of
+    /// the response if they have not changed.
+    pub since: HashMap<u32, Hash160>,
 }
 
 impl RPCGetAttachmentsInvRequestHandler {
@@ -47,6 +52,7 @@ impl RPCGetAttachmentsInvRequestHandler {
         Self {
             index_block_hash: None,
             page_indexes: None,
+            since: HashMap::new(),
         }
     }
 }
@@ -90,8 +96,9 @@ impl HttpRequest for RPCGetAttachmentsInvRequestHandler {
 
         let mut index_block_hash = None;
         let mut page_indexes = HashSet::new();
+        let mut since = HashMap::new();
 
-        // expect index_block_hash= and page_indexes=
+        // expect index_block_hash= and page_indexes=, and optionally since=
         for (key, value) in form_urlencoded::parse(query_str.as_bytes()) {
             if key == "index_block_hash" {
                 index_block_hash = StacksBlockId::from_hex(&value).ok();
@@ -103,6 +110,16 @@ impl HttpRequest for RPCGetAttachmentsInvRequestHandler {
                         }
                     }
                 }
+            } else if key == "since" {
+                // page_index:digest pairs
+                for entry in value.split(',') {
+                    let mut parts = entry.splitn(2, ':');
+                    let page_index = parts.next().and_then(|p| p.parse::<u32>().ok());
+                    let digest = parts.next().and_then(|d| Hash160::from_hex(d).ok());
+                    if let (Some(page_index), Some(digest)) = (page_index, digest) {
+                        since.insert(page_index, digest);
+                    }
+                }
             }
         }
 
@@ -125,6 +142,7 @@ impl HttpRequest for RPCGetAttachmentsInvRequestHandler {
 
         self.index_block_hash = Some(index_block_hash);
         self.page_indexes = Some(page_index_list);
+        self.since = since;
 
         Ok(HttpRequestContents::new().query_string(query))
     }
@@ -135,6 +153,7 @@ impl RPCRequestHandler for RPCGetAttachmentsInvRequestHandler {
     fn restart(&mut self) {
         self.index_block_hash = None;
         self.page_indexes = None;
+        self.since.clear();
     }
 
     fn try_handle_request(
@@ -179,7 +198,9 @@ impl RPCRequestHandler for RPCGetAttachmentsInvRequestHandler {
                 .map_err(NetError::from);
         }
 
+        let since = std::mem::take(&mut self.since);
         let mut pages = vec![];
+        let mut unchanged = vec![];
 
         for page_index in page_indexes.iter() {
             let page_res =
@@ -202,7 +223,11 @@ impl RPCRequestHandler for RPCGetAttachmentsInvRequestHandler {
 
             match page_res {
                 Ok(page) => {
-                    pages.push(page);
+                    if since.get(&page.index) == Some(&page.digest()) {
+                        unchanged.push(page.index);
+                    } else {
+                        pages.push(page);
+                    }
                 }
                 Err(msg) => {
                     return StacksHttpResponse::new_error(&preamble, &HttpNotFound::new(msg))
@@ -215,10 +240,16 @@ impl RPCRequestHandler for RPCGetAttachmentsInvRequestHandler {
         let content = GetAttachmentsInvResponse {
             block_id: index_block_hash.clone(),
             pages,
+            unchanged,
         };
 
+        let data_url =
+            node.with_node_state(|network, _sortdb, _chainstate, _mempool, _rpc_args| {
+                network.get_local_peer().data_url.clone()
+            });
         let mut preamble = HttpResponsePreamble::ok_json(&preamble);
         preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        advertise_data_url(&mut preamble, &data_url);
         let body = HttpResponseContents::try_from_json(&content)?;
         Ok((preamble, body))
     }
@@ -254,6 +285,36 @@ impl StacksHttpRequest {
         )
         .expect("FATAL: failed to construct request from infallible data")
     }
+
+    /// Make a new request for attachment inventory pages, leaving out those that have not
+    /// changed since the versions identified in `since`, as (page index, digest) pairs
+    pub fn new_getattachmentsinv_since(
+        host: PeerHost,
+        index_block_hash: StacksBlockId,
+        page_indexes: HashSet<u32>,
+        since: &[(u32, Hash160)],
+    ) -> StacksHttpRequest {
+        let mut page_list: Vec<u32> = page_indexes.into_iter().collect();
+        page_list.sort();
+        let page_list: Vec<String> = page_list.into_iter().map(|i| format!("{}", i)).collect();
+        let mut contents = HttpRequestContents::new()
+            .query_arg("index_block_hash".into(), format!("{}", &index_block_hash))
+            .query_arg("pages_indexes".into(), page_list[..].join(","));
+        if !since.is_empty() {
+            contents = contents.query_arg("since".into(), since_query_value(since));
+        }
+        StacksHttpRequest::new_for_peer(host, "GET".into(), "/v2/attachments/inv".into(), contents)
+            .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+/// Encode the versions of pages a client already has, for the `since` query argument
+pub fn since_query_value(since: &[(u32, Hash160)]) -> String {
+    let entries: Vec<String> = since
+        .iter()
+        .map(|(page_index, digest)| format!("{}:{}", page_index, digest))
+        .collect();
+    entries.join(",")
 }
 
 impl StacksHttpResponse {
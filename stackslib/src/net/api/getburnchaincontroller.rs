@@ -0,0 +1,179 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use regex::{Captures, Regex};
+use stacks_common::types::net::PeerHost;
+
+use crate::net::http::{
+    parse_json, Error, HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+
+/// Fee policy the burnchain controller applies to the operations it submits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BurnchainFeePolicy {
+    /// Fee rate of newly-submitted operations, in satoshis per vbyte
+    pub satoshis_per_byte: u64,
+    /// Amount the fee rate is raised by on each replace-by-fee, in satoshis per vbyte
+    pub rbf_fee_increment: u64,
+    /// Highest fee rate replace-by-fee may reach, as a percentage of `satoshis_per_byte`
+    pub max_rbf: u64,
+    /// Most the miner burns in a single block commit, in satoshis
+    pub burn_fee_cap: u64,
+}
+
+/// Settings of the burnchain controller, as resolved from the node's configuration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BurnchainControllerSettings {
+    /// Burnchain mode, e.g. `mainnet`, `krypton` or `mocknet`
+    pub mode: String,
+    /// How often the burnchain is polled for new blocks, in seconds
+    pub poll_interval_secs: u64,
+    /// Timeout of requests to the burnchain node, in seconds
+    pub rpc_timeout_secs: u32,
+    /// How long to wait for the chains coordinator to process sortitions, if bounded
+    pub sortition_wait_timeout_ms: Option<u64>,
+    /// Burnchain blocks after which a submitted operation that was not mined is given up on
+    pub op_expiry_blocks: u64,
+    pub fees: BurnchainFeePolicy,
+}
+
+/// Operator endpoint for checking the settings the node's burnchain controller resolved from
+/// its config file.  These include the miner's fee policy, which competing miners must not
+/// see, so it shares its authorization token with `/v2/burnchain/ops`, and is disabled unless
+/// it is set.
+#[derive(Clone)]
+pub struct RPCGetBurnchainControllerRequestHandler {
+    pub auth: Option<String>,
+}
+
+impl RPCGetBurnchainControllerRequestHandler {
+    pub fn new(auth: Option<String>) -> Self {
+        Self { auth }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetBurnchainControllerRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v2/burnchain/controller$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v2/burnchain/controller"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed and authorized.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        // If no authorization is set, then the burnchain controller endpoint is not enabled
+        let password = match &self.auth {
+            Some(password) => password,
+            None => return Err(Error::Http(400, "Bad Request.".into())),
+        };
+        if preamble.headers.get("authorization") != Some(password) {
+            return Err(Error::Http(401, "Unauthorized".into()));
+        }
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCGetBurnchainControllerRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {}
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let settings_opt =
+            node.with_node_state(|_network, _sortdb, _chainstate, _mempool, rpc_args| {
+                rpc_args.burnchain_controller_settings.cloned()
+            });
+
+        let Some(settings) = settings_opt else {
+            return StacksHttpResponse::new_error(
+                &preamble,
+                &HttpNotFound::new("This node does not run a burnchain controller".to_string()),
+            )
+            .try_into_contents();
+        };
+
+        let preamble = HttpResponsePreamble::ok_json(&preamble);
+        let body = HttpResponseContents::try_from_json(&settings)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetBurnchainControllerRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let settings: BurnchainControllerSettings = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(settings)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for the burnchain controller's settings
+    pub fn new_getburnchaincontroller(host: PeerHost, auth: &str) -> StacksHttpRequest {
+        let mut request = StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            "/v2/burnchain/controller".into(),
+            HttpRequestContents::new(),
+        )
+        .expect("FATAL: failed to construct request from infallible data");
+        request.add_header("authorization".into(), auth.into());
+        request
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_burnchain_controller_response(
+        self,
+    ) -> Result<BurnchainControllerSettings, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: BurnchainControllerSettings = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use regex::{Captures, Regex};
+use stacks_common::types::net::PeerHost;
+use stacks_common::util::hash::{Hash160, Sha256Sum};
+
+use crate::burnchains::Txid;
+use crate::net::http::{
+    parse_json, Error, HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+
+/// Why an intent to submit a burnchain operation is still open
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BurnchainOpIntentStatus {
+    /// Recorded since the node last started, and not mined or expired yet
+    InFlight,
+    /// Recorded before the node last restarted, and neither mined nor expired since.  The
+    /// operation may or may not have been broadcast before the restart, so the miner will not
+    /// submit another operation of the same type, from the same key, for the same burn block.
+    Unresolved,
+}
+
+/// A burnchain operation the node's miner signed and meant to broadcast
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BurnchainOpIntent {
+    pub txid: Txid,
+    pub op_type: String,
+    /// SHA256 of the signed transaction
+    pub payload_digest: Sha256Sum,
+    /// Hash160 of the public key that signed the operation
+    pub key_id: Hash160,
+    /// The burn block the operation was signed for
+    pub target_burn_height: u64,
+    /// The burnchain height by which the operation is of no use if it has not been mined
+    pub expires_burn_height: u64,
+    /// When the intent was recorded, in seconds since the epoch
+    pub recorded_at: u64,
+    pub status: BurnchainOpIntentStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BurnchainOpIntentsResponse {
+    /// The intents neither mined nor expired yet, oldest first
+    pub intents: Vec<BurnchainOpIntent>,
+}
+
+/// The node's write-ahead log of the burnchain operations its miner submits
+pub trait BurnchainOpIntents {
+    /// Get the intents that are neither mined nor expired yet
+    fn get_open_intents(&self) -> Result<BurnchainOpIntentsResponse, String>;
+}
+
+/// Operator endpoint for reviewing the burnchain operations this node's miner meant to submit,
+/// but which have not been seen on the burnchain yet.  Shares its authorization token with
+/// `/v2/burnchain/ops`, and is disabled unless it is set.
+#[derive(Clone)]
+pub struct RPCGetBurnchainIntentsRequestHandler {
+    pub auth: Option<String>,
+}
+
+impl RPCGetBurnchainIntentsRequestHandler {
+    pub fn new(auth: Option<String>) -> Self {
+        Self { auth }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetBurnchainIntentsRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v2/burnchain/intents$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v2/burnchain/intents"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed and authorized.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        // If no authorization is set, then the burnchain intents endpoint is not enabled
+        let password = match &self.auth {
+            Some(password) => password,
+            None => return Err(Error::Http(400, "Bad Request.".into())),
+        };
+        if preamble.headers.get("authorization") != Some(password) {
+            return Err(Error::Http(401, "Unauthorized".into()));
+        }
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCGetBurnchainIntentsRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {}
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let result_opt =
+            node.with_node_state(|_network, _sortdb, _chainstate, _mempool, rpc_args| {
+                let op_intents = rpc_args.burnchain_op_intents?;
+                Some(op_intents.get_open_intents())
+            });
+
+        let response = match result_opt {
+            Some(Ok(response)) => response,
+            Some(Err(msg)) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpServerError::new(format!("Failed to load burnchain op intents: {msg}")),
+                )
+                .try_into_contents();
+            }
+            None => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("This node does not submit burnchain ops".to_string()),
+                )
+                .try_into_contents();
+            }
+        };
+
+        let preamble = HttpResponsePreamble::ok_json(&preamble);
+        let body = HttpResponseContents::try_from_json(&response)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetBurnchainIntentsRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let response: BurnchainOpIntentsResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(response)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for the burnchain operation intents that are still open
+    pub fn new_getburnchainintents(host: PeerHost, auth: &str) -> StacksHttpRequest {
+        let mut request = StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            "/v2/burnchain/intents".into(),
+            HttpRequestContents::new(),
+        )
+        .expect("FATAL: failed to construct request from infallible data");
+        request.add_header("authorization".into(), auth.into());
+        request
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_burnchain_intents_response(self) -> Result<BurnchainOpIntentsResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: BurnchainOpIntentsResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
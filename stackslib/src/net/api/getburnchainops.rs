@@ -0,0 +1,278 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use regex::{Captures, Regex};
+use stacks_common::types::chainstate::BurnchainHeaderHash;
+use stacks_common::types::net::PeerHost;
+use url::form_urlencoded;
+
+use crate::burnchains::Txid;
+use crate::net::http::{
+    parse_json, Error, HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+
+/// Number of operations returned if the request does not say
+pub const DEFAULT_BURNCHAIN_OPS_LIMIT: u32 = 50;
+/// Most operations a single request can ask for
+pub const MAX_BURNCHAIN_OPS_LIMIT: u32 = 1000;
+
+/// How a mined block commit fared in the sortition of the burn block that mined it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortitionOutcome {
+    /// The commit won the sortition
+    Won,
+    /// The commit was accepted, but another one won the sortition
+    Lost,
+    /// The commit was mined, but the sortition did not accept it (e.g. it pointed at a stale
+    /// key or parent, or paid the wrong PoX recipients)
+    Rejected,
+}
+
+impl SortitionOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortitionOutcome::Won => "won",
+            SortitionOutcome::Lost => "lost",
+            SortitionOutcome::Rejected => "rejected",
+        }
+    }
+
+    pub fn from_name(s: &str) -> Option<SortitionOutcome> {
+        match s {
+            "won" => Some(SortitionOutcome::Won),
+            "lost" => Some(SortitionOutcome::Lost),
+            "rejected" => Some(SortitionOutcome::Rejected),
+            _ => None,
+        }
+    }
+}
+
+/// A burnchain operation submitted by this node, and what became of it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmittedBurnchainOp {
+    pub txid: Txid,
+    pub op_type: String,
+    /// One of `pending`, `confirmed`, `replaced` (by a fee bump) or `failed` (not mined in time)
+    pub status: String,
+    /// Seconds since the epoch
+    pub submitted_at: u64,
+    /// The burnchain tip height when the operation was submitted
+    pub submitted_burn_height: u64,
+    /// The burn block that mined the operation, if it was mined
+    pub burn_block_height: Option<u64>,
+    pub burn_header_hash: Option<BurnchainHeaderHash>,
+    /// How a mined block commit fared.  None for other operations, and until the sortition of
+    /// the burn block that mined the commit has been processed.
+    pub sortition_outcome: Option<SortitionOutcome>,
+}
+
+/// How all the block commits this node submitted fared
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlockCommitOutcomes {
+    pub won: u64,
+    pub lost: u64,
+    pub rejected: u64,
+    /// Commits that were not mined in time for the sortition they were built for
+    pub not_mined: u64,
+}
+
+impl BlockCommitOutcomes {
+    /// Fraction of the commits with a known outcome that won their sortition
+    pub fn win_rate(&self) -> Option<f64> {
+        let total = self.won + self.lost + self.rejected + self.not_mined;
+        if total == 0 {
+            return None;
+        }
+        Some(self.won as f64 / total as f64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmittedBurnchainOpsResponse {
+    /// Outcomes of every block commit on record
+    pub block_commits: BlockCommitOutcomes,
+    /// The most recently submitted operations, newest first
+    pub ops: Vec<SubmittedBurnchainOp>,
+}
+
+/// The node's record of the burnchain operations it submitted
+pub trait SubmittedBurnchainOps {
+    /// Get the outcomes of all the block commits on record, and the `limit` most recently
+    /// submitted operations
+    fn get_submitted_ops(&self, limit: u32) -> Result<SubmittedBurnchainOpsResponse, String>;
+}
+
+/// Operator endpoint for auditing the burnchain operations this node's miner submitted: which
+/// burn blocks mined them, and whether its block commits won their sortitions.
+/// Disabled unless an authorization token is set.
+#[derive(Clone)]
+pub struct RPCGetBurnchainOpsRequestHandler {
+    pub limit: Option<u32>,
+    pub auth: Option<String>,
+}
+
+impl RPCGetBurnchainOpsRequestHandler {
+    pub fn new(auth: Option<String>) -> Self {
+        Self { limit: None, auth }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetBurnchainOpsRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v2/burnchain/ops$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v2/burnchain/ops"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed and authorized.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        // If no authorization is set, then the burnchain ops endpoint is not enabled
+        let password = match &self.auth {
+            Some(password) => password,
+            None => return Err(Error::Http(400, "Bad Request.".into())),
+        };
+        if preamble.headers.get("authorization") != Some(password) {
+            return Err(Error::Http(401, "Unauthorized".into()));
+        }
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let mut limit = DEFAULT_BURNCHAIN_OPS_LIMIT;
+        for (key, value) in form_urlencoded::parse(query.unwrap_or("").as_bytes()) {
+            if key == "limit" {
+                limit = value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|limit| *limit <= MAX_BURNCHAIN_OPS_LIMIT)
+                    .ok_or_else(|| {
+                        Error::DecodeError(format!(
+                            "Invalid Http request: limit must be at most {MAX_BURNCHAIN_OPS_LIMIT}"
+                        ))
+                    })?;
+            }
+        }
+
+        self.limit = Some(limit);
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCGetBurnchainOpsRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.limit = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let limit = self
+            .limit
+            .take()
+            .ok_or(NetError::SendError("`limit` not set".into()))?;
+
+        let result_opt =
+            node.with_node_state(|_network, _sortdb, _chainstate, _mempool, rpc_args| {
+                let submitted_ops = rpc_args.submitted_burnchain_ops?;
+                Some(submitted_ops.get_submitted_ops(limit))
+            });
+
+        let response = match result_opt {
+            Some(Ok(response)) => response,
+            Some(Err(msg)) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpServerError::new(format!("Failed to load burnchain ops: {msg}")),
+                )
+                .try_into_contents();
+            }
+            None => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("This node does not submit burnchain ops".to_string()),
+                )
+                .try_into_contents();
+            }
+        };
+
+        let preamble = HttpResponsePreamble::ok_json(&preamble);
+        let body = HttpResponseContents::try_from_json(&response)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetBurnchainOpsRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let response: SubmittedBurnchainOpsResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(response)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for the `limit` most recent burnchain operations the node submitted
+    pub fn new_getburnchainops(host: PeerHost, limit: u32, auth: &str) -> StacksHttpRequest {
+        let mut request = StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            "/v2/burnchain/ops".into(),
+            HttpRequestContents::new().query_arg("limit".into(), format!("{}", limit)),
+        )
+        .expect("FATAL: failed to construct request from infallible data");
+        request.add_header("authorization".into(), auth.into());
+        request
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_burnchain_ops_response(self) -> Result<SubmittedBurnchainOpsResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: SubmittedBurnchainOpsResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
@@ -29,6 +29,7 @@ use crate::burnchains::Txid;
 use crate::chainstate::burn::db::sortdb::SortitionDB;
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::core::mempool::MemPoolDB;
+use crate::monitoring::{self, BurnchainBackfillProgress, BurnchainSyncHeights};
 use crate::net::http::{
     parse_json, Error, HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse,
     HttpResponseContents, HttpResponsePayload, HttpResponsePreamble,
@@ -64,6 +65,17 @@ pub struct RPCLastPoxAnchorData {
     pub anchor_block_txid: Txid,
 }
 
+/// Bit in `RPCPeerInfoData::features`: the node serves the StackerDB API (`/v2/stackerdb/...`)
+pub const RPC_FEATURE_STACKERDB: u64 = 0x01;
+/// Bit in `RPCPeerInfoData::features`: the node serves reward sets (`/v2/stacker_set/...`)
+pub const RPC_FEATURE_STACKER_SET: u64 = 0x02;
+/// Bit in `RPCPeerInfoData::features`: the node validates block proposals
+/// (`/v2/block_proposal`)
+pub const RPC_FEATURE_BLOCK_PROPOSAL: u64 = 0x04;
+/// All the optional RPC features this node serves
+pub const RPC_FEATURES: u64 =
+    RPC_FEATURE_STACKERDB | RPC_FEATURE_STACKER_SET | RPC_FEATURE_BLOCK_PROPOSAL;
+
 /// The response to GET /v2/info
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RPCPeerInfoData {
@@ -97,6 +109,17 @@ pub struct RPCPeerInfoData {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stackerdbs: Option<Vec<String>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burnchain_backfill: Option<BurnchainBackfillProgress>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burnchain_sync: Option<BurnchainSyncHeights>,
+    /// Bitfield of the `RPC_FEATURE_*` endpoints this node serves.
+    /// Absent from nodes that predate feature advertisement.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<u64>,
 }
 
 impl RPCPeerInfoData {
@@ -165,6 +188,9 @@ impl RPCPeerInfoData {
                     .map(|cid| format!("{}", cid))
                     .collect(),
             ),
+            burnchain_backfill: monitoring::get_burnchain_backfill_progress(),
+            burnchain_sync: monitoring::get_burnchain_sync_heights(),
+            features: Some(RPC_FEATURES),
         }
     }
 }
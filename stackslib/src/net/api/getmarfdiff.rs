@@ -0,0 +1,288 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{Read, Write};
+
+use regex::{Captures, Regex};
+use stacks_common::types::chainstate::StacksBlockId;
+use stacks_common::types::net::PeerHost;
+
+use crate::chainstate::stacks::index::diff::{TrieDiff, TrieDiffEntry};
+use crate::chainstate::stacks::index::marf::MarfConnection;
+use crate::chainstate::stacks::index::Error as MarfError;
+use crate::net::http::{
+    parse_json, Error, HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    request, HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+
+/// Number of differences returned if the request does not set `limit=`
+pub const DEFAULT_MARF_DIFF_ENTRIES: usize = 1_000;
+/// Largest `limit=` a request may set
+pub const MAX_MARF_DIFF_ENTRIES: usize = 10_000;
+/// Most leaves a request may read from the two tries.  Diffs that need more are truncated.
+pub const MAX_MARF_DIFF_LEAVES: usize = 100_000;
+
+/// A key whose value differs between two blocks' Clarity state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMarfDiffEntry {
+    /// Hex-encoded MARF path, i.e. the hash of the key
+    pub path: String,
+    /// Hex-encoded MARF value in the `from` block, if the key is set there
+    pub old: Option<String>,
+    /// Hex-encoded MARF value in the `to` block, if the key is set there
+    pub new: Option<String>,
+}
+
+impl From<TrieDiffEntry> for RPCMarfDiffEntry {
+    fn from(entry: TrieDiffEntry) -> Self {
+        Self {
+            path: entry.path.to_hex(),
+            old: entry.old.map(|value| value.to_hex()),
+            new: entry.new.map(|value| value.to_hex()),
+        }
+    }
+}
+
+/// The differences between the Clarity state of two blocks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMarfDiffData {
+    pub from_block_id: StacksBlockId,
+    pub to_block_id: StacksBlockId,
+    /// Differences, ordered by path
+    pub entries: Vec<RPCMarfDiffEntry>,
+    /// Whether there were more differences than the request's limit, or finding them needed
+    /// more than `MAX_MARF_DIFF_LEAVES` leaves to be read
+    pub truncated: bool,
+}
+
+impl RPCMarfDiffData {
+    pub fn new(from_block_id: StacksBlockId, to_block_id: StacksBlockId, diff: TrieDiff) -> Self {
+        Self {
+            from_block_id,
+            to_block_id,
+            entries: diff
+                .entries
+                .into_iter()
+                .map(RPCMarfDiffEntry::from)
+                .collect(),
+            truncated: diff.truncated,
+        }
+    }
+}
+
+/// Debugging endpoint that reports the key/value differences between the Clarity state tries
+/// of two blocks.  Disabled unless an authorization token is set.
+#[derive(Clone)]
+pub struct RPCGetMarfDiffRequestHandler {
+    pub auth: Option<String>,
+    pub from_block_id: Option<StacksBlockId>,
+    pub to_block_id: Option<StacksBlockId>,
+    pub limit: Option<usize>,
+}
+
+impl RPCGetMarfDiffRequestHandler {
+    pub fn new(auth: Option<String>) -> Self {
+        Self {
+            auth,
+            from_block_id: None,
+            to_block_id: None,
+            limit: None,
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetMarfDiffRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(
+            r#"^/v2/marf/diff/(?P<from_block_id>[0-9a-f]{64})/(?P<to_block_id>[0-9a-f]{64})$"#,
+        )
+        .unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v2/marf/diff/:from_block_id/:to_block_id"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed and authorized.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        // If no authorization is set, then the MARF diff endpoint is not enabled
+        let password = match &self.auth {
+            Some(password) => password,
+            None => return Err(Error::Http(400, "Bad Request.".into())),
+        };
+        if preamble.headers.get("authorization") != Some(password) {
+            return Err(Error::Http(401, "Unauthorized".into()));
+        }
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let from_block_id = request::get_block_hash(captures, "from_block_id")?;
+        let to_block_id = request::get_block_hash(captures, "to_block_id")?;
+
+        let req_contents = HttpRequestContents::new().query_string(query);
+        let limit = match req_contents.get_query_arg("limit") {
+            Some(limit_str) => limit_str
+                .parse::<usize>()
+                .map_err(|_| Error::DecodeError("Invalid Http request: bad limit".to_string()))?,
+            None => DEFAULT_MARF_DIFF_ENTRIES,
+        };
+        if limit > MAX_MARF_DIFF_ENTRIES {
+            return Err(Error::DecodeError(format!(
+                "Invalid Http request: limit must be at most {}",
+                MAX_MARF_DIFF_ENTRIES
+            )));
+        }
+
+        self.from_block_id = Some(from_block_id);
+        self.to_block_id = Some(to_block_id);
+        self.limit = Some(limit);
+
+        Ok(req_contents)
+    }
+}
+
+impl RPCRequestHandler for RPCGetMarfDiffRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.from_block_id = None;
+        self.to_block_id = None;
+        self.limit = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let from_block_id = self
+            .from_block_id
+            .take()
+            .ok_or(NetError::SendError("Missing `from_block_id`".into()))?;
+        let to_block_id = self
+            .to_block_id
+            .take()
+            .ok_or(NetError::SendError("Missing `to_block_id`".into()))?;
+        let limit = self
+            .limit
+            .take()
+            .ok_or(NetError::SendError("Missing `limit`".into()))?;
+
+        let diff_res =
+            node.with_node_state(|_network, _sortdb, chainstate, _mempool, _rpc_args| {
+                chainstate.with_clarity_marf(|marf| {
+                    marf.diff(&from_block_id, &to_block_id, limit, MAX_MARF_DIFF_LEAVES)
+                })
+            });
+
+        let diff = match diff_res {
+            Ok(diff) => diff,
+            Err(MarfError::NotFoundError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new(format!(
+                        "No such block {} or {}",
+                        &from_block_id, &to_block_id
+                    )),
+                )
+                .try_into_contents();
+            }
+            Err(e) => {
+                let msg = format!(
+                    "Failed to diff {} against {}: {:?}",
+                    &from_block_id, &to_block_id, &e
+                );
+                warn!("{}", &msg);
+                return StacksHttpResponse::new_error(&preamble, &HttpServerError::new(msg))
+                    .try_into_contents();
+            }
+        };
+
+        let data = RPCMarfDiffData::new(from_block_id, to_block_id, diff);
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&data)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetMarfDiffRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let data: RPCMarfDiffData = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(data)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for the differences between two blocks' Clarity state, returning at
+    /// most `limit` of them (or the node's default if `None`)
+    pub fn new_getmarfdiff(
+        host: PeerHost,
+        from_block_id: StacksBlockId,
+        to_block_id: StacksBlockId,
+        limit: Option<usize>,
+        auth: &str,
+    ) -> StacksHttpRequest {
+        let mut contents = HttpRequestContents::new();
+        if let Some(limit) = limit {
+            contents = contents.query_arg("limit".into(), format!("{}", limit));
+        }
+        let mut request = StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            format!("/v2/marf/diff/{}/{}", &from_block_id, &to_block_id),
+            contents,
+        )
+        .expect("FATAL: failed to construct request from infallible data");
+        request.add_header("authorization".into(), auth.into());
+        request
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_marf_diff_response(self) -> Result<RPCMarfDiffData, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: RPCMarfDiffData = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
@@ -34,9 +34,15 @@ use crate::stacks_common::codec::StacksMessageCodec;
 pub mod callreadonly;
 pub mod getaccount;
 pub mod getattachment;
+pub mod getattachmentinstances;
+pub mod getattachmentresolutionlatency;
+pub mod getattachmentsdownloader;
 pub mod getattachmentsinv;
 pub mod getblock;
 pub mod getblock_v3;
+pub mod getburnchaincontroller;
+pub mod getburnchainintents;
+pub mod getburnchainops;
 pub mod getconstantval;
 pub mod getcontractabi;
 pub mod getcontractsrc;
@@ -45,6 +51,8 @@ pub mod getheaders;
 pub mod getinfo;
 pub mod getistraitimplemented;
 pub mod getmapentry;
+pub mod getmarfdiff;
+pub mod getmarfio;
 pub mod getmicroblocks_confirmed;
 pub mod getmicroblocks_indexed;
 pub mod getmicroblocks_unconfirmed;
@@ -58,11 +66,14 @@ pub mod gettenure;
 pub mod gettenureinfo;
 pub mod gettransaction_unconfirmed;
 pub mod liststackerdbreplicas;
+pub mod postattachmentsdownload;
 pub mod postblock;
 pub mod postblock_proposal;
+pub mod postburnchainsync;
 pub mod postfeerate;
 pub mod postmempoolquery;
 pub mod postmicroblock;
+pub mod postsimulatecommit;
 pub mod poststackerdbchunk;
 pub mod posttransaction;
 
@@ -79,9 +90,31 @@ impl StacksHttp {
         ));
         self.register_rpc_endpoint(getaccount::RPCGetAccountRequestHandler::new());
         self.register_rpc_endpoint(getattachment::RPCGetAttachmentRequestHandler::new());
+        self.register_rpc_endpoint(
+            getattachmentinstances::RPCGetAttachmentInstancesRequestHandler::new(),
+        );
+        self.register_rpc_endpoint(
+            getattachmentresolutionlatency::RPCGetAttachmentResolutionLatencyRequestHandler::new(),
+        );
+        self.register_rpc_endpoint(
+            getattachmentsdownloader::RPCGetAttachmentsDownloaderRequestHandler::new(),
+        );
         self.register_rpc_endpoint(getattachmentsinv::RPCGetAttachmentsInvRequestHandler::new());
         self.register_rpc_endpoint(getblock::RPCBlocksRequestHandler::new());
         self.register_rpc_endpoint(getblock_v3::RPCNakamotoBlockRequestHandler::new());
+        self.register_rpc_endpoint(
+            getburnchaincontroller::RPCGetBurnchainControllerRequestHandler::new(
+                self.burnchain_ops_token.clone(),
+            ),
+        );
+        self.register_rpc_endpoint(
+            getburnchainintents::RPCGetBurnchainIntentsRequestHandler::new(
+                self.burnchain_ops_token.clone(),
+            ),
+        );
+        self.register_rpc_endpoint(getburnchainops::RPCGetBurnchainOpsRequestHandler::new(
+            self.burnchain_ops_token.clone(),
+        ));
         self.register_rpc_endpoint(getconstantval::RPCGetConstantValRequestHandler::new());
         self.register_rpc_endpoint(getcontractabi::RPCGetContractAbiRequestHandler::new());
         self.register_rpc_endpoint(getcontractsrc::RPCGetContractSrcRequestHandler::new());
@@ -92,6 +125,10 @@ impl StacksHttp {
             getistraitimplemented::RPCGetIsTraitImplementedRequestHandler::new(),
         );
         self.register_rpc_endpoint(getmapentry::RPCGetMapEntryRequestHandler::new());
+        self.register_rpc_endpoint(getmarfdiff::RPCGetMarfDiffRequestHandler::new(
+            self.marf_diff_token.clone(),
+        ));
+        self.register_rpc_endpoint(getmarfio::RPCGetMarfIORequestHandler::new());
         self.register_rpc_endpoint(
             getmicroblocks_confirmed::RPCMicroblocksConfirmedRequestHandler::new(),
         );
@@ -117,13 +154,22 @@ impl StacksHttp {
         self.register_rpc_endpoint(
             liststackerdbreplicas::RPCListStackerDBReplicasRequestHandler::new(),
         );
+        self.register_rpc_endpoint(
+            postattachmentsdownload::RPCPostAttachmentsDownloadRequestHandler::new(
+                self.attachments_download_token.clone(),
+            ),
+        );
         self.register_rpc_endpoint(postblock::RPCPostBlockRequestHandler::new());
         self.register_rpc_endpoint(postblock_proposal::RPCBlockProposalRequestHandler::new(
             self.block_proposal_token.clone(),
         ));
+        self.register_rpc_endpoint(postburnchainsync::RPCPostBurnchainSyncRequestHandler::new(
+            self.burnchain_sync_token.clone(),
+        ));
         self.register_rpc_endpoint(postfeerate::RPCPostFeeRateRequestHandler::new());
         self.register_rpc_endpoint(postmempoolquery::RPCMempoolQueryRequestHandler::new());
         self.register_rpc_endpoint(postmicroblock::RPCPostMicroblockRequestHandler::new());
+        self.register_rpc_endpoint(postsimulatecommit::RPCPostSimulateCommitRequestHandler::new());
         self.register_rpc_endpoint(poststackerdbchunk::RPCPostStackerDBChunkRequestHandler::new());
         self.register_rpc_endpoint(posttransaction::RPCPostTransactionRequestHandler::new());
         self.register_rpc_endpoint(getstackers::GetStackersRequestHandler::default());
@@ -0,0 +1,306 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+use clarity::vm::types::QualifiedContractIdentifier;
+use regex::{Captures, Regex};
+use stacks_common::codec::MAX_PAYLOAD_LEN;
+use stacks_common::types::net::PeerHost;
+use stacks_common::util::hash::Hash160;
+
+use crate::net::atlas::AttachmentInstance;
+use crate::net::http::{
+    parse_json, Error, HttpContentType, HttpNotFound, HttpRequest, HttpRequestContents,
+    HttpRequestPreamble, HttpResponse, HttpResponseContents, HttpResponsePayload,
+    HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+use crate::util_lib::db::Error as DBError;
+
+/// The most content hashes that can be requested at once
+pub const MAX_ATTACHMENTS_DOWNLOAD_HASHES: usize = 256;
+
+/// What to download.  At least one of the fields must be set.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct AttachmentsDownloadRequestBody {
+    /// Download every attachment of this contract that we don't have yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract_id: Option<String>,
+    /// Download these attachments, if we don't have them yet
+    #[serde(default)]
+    pub content_hashes: Vec<Hash160>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttachmentsDownloadResponse {
+    /// Number of attachment instances queued for download
+    pub queued_instances: usize,
+    /// Number of on-demand batches those instances were grouped into
+    pub queued_batches: usize,
+    /// Requested content hashes whose attachment we already have
+    pub already_available: Vec<Hash160>,
+    /// Requested content hashes that no known attachment instance refers to
+    pub unknown: Vec<Hash160>,
+}
+
+/// Operator endpoint to download specific missing attachments right away, ahead of the
+/// attachments downloader's normal schedule (e.g. when a wallet reports a missing zonefile).
+/// Disabled unless an authorization token is set.
+#[derive(Clone)]
+pub struct RPCPostAttachmentsDownloadRequestHandler {
+    pub contract_id: Option<QualifiedContractIdentifier>,
+    pub content_hashes: Option<Vec<Hash160>>,
+    pub auth: Option<String>,
+}
+
+impl RPCPostAttachmentsDownloadRequestHandler {
+    pub fn new(auth: Option<String>) -> Self {
+        Self {
+            contract_id: None,
+            content_hashes: None,
+            auth,
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCPostAttachmentsDownloadRequestHandler {
+    fn verb(&self) -> &'static str {
+        "POST"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v2/attachments/download$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v2/attachments/download"
+    }
+
+    /// Try to decode this request.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        // If no authorization is set, then the attachments download endpoint is not enabled
+        let Some(password) = &self.auth else {
+            return Err(Error::Http(400, "Bad Request.".into()));
+        };
+        let Some(auth_header) = preamble.headers.get("authorization") else {
+            return Err(Error::Http(401, "Unauthorized".into()));
+        };
+        if auth_header != password {
+            return Err(Error::Http(401, "Unauthorized".into()));
+        }
+
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(Error::DecodeError(format!(
+                "Invalid Http request: invalid body length for attachments download ({})",
+                content_len
+            )));
+        }
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(Error::DecodeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let request: AttachmentsDownloadRequestBody = serde_json::from_slice(body)
+            .map_err(|e| Error::DecodeError(format!("Failed to parse JSON body: {}", e)))?;
+        if request.contract_id.is_none() && request.content_hashes.is_empty() {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected `contract_id` or `content_hashes`".to_string(),
+            ));
+        }
+        if request.content_hashes.len() > MAX_ATTACHMENTS_DOWNLOAD_HASHES {
+            return Err(Error::DecodeError(format!(
+                "Invalid Http request: at most {} content hashes may be requested",
+                MAX_ATTACHMENTS_DOWNLOAD_HASHES
+            )));
+        }
+
+        let contract_id = request
+            .contract_id
+            .as_deref()
+            .map(QualifiedContractIdentifier::parse)
+            .transpose()
+            .map_err(|_e| {
+                Error::DecodeError("Invalid Http request: malformed `contract_id`".to_string())
+            })?;
+
+        self.contract_id = contract_id;
+        self.content_hashes = Some(request.content_hashes);
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCPostAttachmentsDownloadRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.contract_id = None;
+        self.content_hashes = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let content_hashes = self
+            .content_hashes
+            .take()
+            .ok_or(NetError::SendError("`content_hashes` not set".into()))?;
+        let contract_id = self.contract_id.take();
+
+        let response_res: Result<Option<AttachmentsDownloadResponse>, DBError> = node
+            .with_node_state(|network, _sortdb, _chainstate, _mempool, _rpc_args| {
+                if network.attachments_downloader.is_none() {
+                    return Ok(None);
+                }
+
+                let mut instances: Vec<AttachmentInstance> = vec![];
+                let mut already_available = vec![];
+                let mut unknown = vec![];
+                if let Some(contract_id) = contract_id.as_ref() {
+                    instances.extend(
+                        network
+                            .atlasdb
+                            .find_unresolved_attachment_instances_by_contract(contract_id)?,
+                    );
+                }
+                for content_hash in content_hashes.iter() {
+                    if network.atlasdb.find_attachment(content_hash)?.is_some() {
+                        already_available.push(*content_hash);
+                        continue;
+                    }
+                    let found = network
+                        .atlasdb
+                        .find_all_attachment_instances(content_hash)?;
+                    if found.is_empty() {
+                        unknown.push(*content_hash);
+                    }
+                    instances.extend(found);
+                }
+
+                // a content hash may also belong to the requested contract
+                let mut seen = HashSet::new();
+                instances.retain(|instance| {
+                    seen.insert((
+                        instance.index_block_hash,
+                        instance.contract_id.clone(),
+                        instance.attachment_index,
+                    ))
+                });
+
+                let queued_instances = instances.len();
+                let queued_batches = network
+                    .attachments_downloader
+                    .as_mut()
+                    .map(|downloader| downloader.enqueue_on_demand_batches(instances))
+                    .unwrap_or(0);
+                info!("Atlas: on-demand attachments download requested over RPC";
+                      "contract_id" => ?contract_id,
+                      "content_hashes" => content_hashes.len(),
+                      "queued_instances" => queued_instances,
+                      "queued_batches" => queued_batches);
+
+                Ok(Some(AttachmentsDownloadResponse {
+                    queued_instances,
+                    queued_batches,
+                    already_available,
+                    unknown,
+                }))
+            });
+
+        let response = match response_res {
+            Ok(Some(response)) => response,
+            Ok(None) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("Attachments downloader is not running".to_string()),
+                )
+                .try_into_contents();
+            }
+            Err(e) => {
+                let msg = format!("Failed to look up attachment instances: {:?}", &e);
+                warn!("{}", &msg);
+                return StacksHttpResponse::new_error(&preamble, &HttpServerError::new(msg))
+                    .try_into_contents();
+            }
+        };
+
+        let preamble = HttpResponsePreamble::ok_json(&preamble);
+        let body = HttpResponseContents::try_from_json(&response)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCPostAttachmentsDownloadRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let response: AttachmentsDownloadResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(response)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request to download attachments on demand
+    pub fn new_postattachmentsdownload(
+        host: PeerHost,
+        request: AttachmentsDownloadRequestBody,
+        auth: &str,
+    ) -> StacksHttpRequest {
+        let mut request = StacksHttpRequest::new_for_peer(
+            host,
+            "POST".into(),
+            "/v2/attachments/download".into(),
+            HttpRequestContents::new().payload_json(
+                serde_json::to_value(request)
+                    .expect("FATAL: failed to encode attachments download request to JSON"),
+            ),
+        )
+        .expect("FATAL: failed to construct request from infallible data");
+        request.add_header("authorization".into(), auth.into());
+        request
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_attachments_download_response(
+        self,
+    ) -> Result<AttachmentsDownloadResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: AttachmentsDownloadResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{Read, Write};
+
+use regex::{Captures, Regex};
+use stacks_common::types::net::PeerHost;
+
+use crate::burnchains::sync_control::BurnchainSyncState;
+use crate::net::http::{
+    parse_json, Error, HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+
+/// What to do with the burnchain sync loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BurnchainSyncAction {
+    /// Pause after the in-flight pass.  Idempotent, so it can be re-sent to poll for `paused`.
+    Pause,
+    /// Resume a paused sync loop
+    Resume,
+}
+
+impl BurnchainSyncAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BurnchainSyncAction::Pause => "pause",
+            BurnchainSyncAction::Resume => "resume",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BurnchainSyncControlResponse {
+    pub state: BurnchainSyncState,
+}
+
+/// Operator endpoint to pause and resume the burnchain sync loop.  Only burnchain processing
+/// stops; Stacks blocks are still processed while paused.  Disabled unless an authorization
+/// token is set.
+#[derive(Clone)]
+pub struct RPCPostBurnchainSyncRequestHandler {
+    pub action: Option<BurnchainSyncAction>,
+    pub auth: Option<String>,
+}
+
+impl RPCPostBurnchainSyncRequestHandler {
+    pub fn new(auth: Option<String>) -> Self {
+        Self { action: None, auth }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCPostBurnchainSyncRequestHandler {
+    fn verb(&self) -> &'static str {
+        "POST"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v2/burnchain/sync/(?P<action>pause|resume)$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v2/burnchain/sync/:action"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed and authorized.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        // If no authorization is set, then the burnchain sync endpoint is not enabled
+        let Some(password) = &self.auth else {
+            return Err(Error::Http(400, "Bad Request.".into()));
+        };
+        let Some(auth_header) = preamble.headers.get("authorization") else {
+            return Err(Error::Http(401, "Unauthorized".into()));
+        };
+        if auth_header != password {
+            return Err(Error::Http(401, "Unauthorized".into()));
+        }
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let action = match captures.name("action").map(|action| action.as_str()) {
+            Some("pause") => BurnchainSyncAction::Pause,
+            Some("resume") => BurnchainSyncAction::Resume,
+            _ => {
+                return Err(Error::DecodeError(
+                    "Invalid Http request: expected `pause` or `resume`".to_string(),
+                ));
+            }
+        };
+
+        self.action = Some(action);
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCPostBurnchainSyncRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.action = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let action = self
+            .action
+            .take()
+            .ok_or(NetError::SendError("`action` not set".into()))?;
+
+        let state_opt =
+            node.with_node_state(|_network, _sortdb, _chainstate, _mempool, rpc_args| {
+                let control = rpc_args.burnchain_sync_control?;
+                let state = match action {
+                    BurnchainSyncAction::Pause => control.request_pause(),
+                    BurnchainSyncAction::Resume => control.resume(),
+                };
+                info!("Burnchain sync {} requested over RPC", action.as_str(); "state" => ?state);
+                Some(state)
+            });
+
+        let Some(state) = state_opt else {
+            return StacksHttpResponse::new_error(
+                &preamble,
+                &HttpNotFound::new("Burnchain sync control is not available".to_string()),
+            )
+            .try_into_contents();
+        };
+
+        let preamble = HttpResponsePreamble::ok_json(&preamble);
+        let body = HttpResponseContents::try_from_json(&BurnchainSyncControlResponse { state })?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCPostBurnchainSyncRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let response: BurnchainSyncControlResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(response)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request to pause or resume the burnchain sync loop
+    pub fn new_postburnchainsync(
+        host: PeerHost,
+        action: BurnchainSyncAction,
+        auth: &str,
+    ) -> StacksHttpRequest {
+        let mut request = StacksHttpRequest::new_for_peer(
+            host,
+            "POST".into(),
+            format!("/v2/burnchain/sync/{}", action.as_str()),
+            HttpRequestContents::new(),
+        )
+        .expect("FATAL: failed to construct request from infallible data");
+        request.add_header("authorization".into(), auth.into());
+        request
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_burnchain_sync_response(self) -> Result<BurnchainSyncControlResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: BurnchainSyncControlResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{Read, Write};
+
+use regex::{Captures, Regex};
+use stacks_common::codec::MAX_PAYLOAD_LEN;
+use stacks_common::types::net::PeerHost;
+
+use crate::chainstate::burn::simulation::{HypotheticalCommit, SortitionSimulation};
+use crate::net::http::{
+    parse_json, Error, HttpContentType, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+
+/// Estimate the probability that a hypothetical block-commit wins the next sortition, so
+/// miners can tune their spend without re-implementing the sortition's burn distribution.
+#[derive(Clone)]
+pub struct RPCPostSimulateCommitRequestHandler {
+    pub commit: Option<HypotheticalCommit>,
+}
+
+impl RPCPostSimulateCommitRequestHandler {
+    pub fn new() -> Self {
+        Self { commit: None }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCPostSimulateCommitRequestHandler {
+    fn verb(&self) -> &'static str {
+        "POST"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v2/burnchain/simulate_commit$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v2/burnchain/simulate_commit"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(Error::DecodeError(format!(
+                "Invalid Http request: invalid body length for SimulateCommit ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(Error::DecodeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let commit: HypotheticalCommit = serde_json::from_slice(body)
+            .map_err(|e| Error::DecodeError(format!("Failed to parse JSON body: {}", e)))?;
+
+        self.commit = Some(commit);
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCPostSimulateCommitRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.commit = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let commit = self
+            .commit
+            .take()
+            .ok_or(NetError::SendError("`commit` not set".into()))?;
+
+        let data_resp =
+            node.with_node_state(|network, sortdb, _chainstate, _mempool, _rpc_args| {
+                let tip = self.get_canonical_burn_chain_tip(&preamble, sortdb)?;
+                SortitionSimulation::simulate(sortdb, &network.burnchain, &tip, &commit).map_err(
+                    |e| {
+                        StacksHttpResponse::new_error(
+                            &preamble,
+                            &HttpServerError::new(format!(
+                                "Failed to simulate sortition: {:?}",
+                                &e
+                            )),
+                        )
+                    },
+                )
+            });
+
+        let data_resp = match data_resp {
+            Ok(data) => data,
+            Err(response) => {
+                return response.try_into_contents();
+            }
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&data_resp)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCPostSimulateCommitRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let simulation: SortitionSimulation = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(simulation)?)
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_sortition_simulation(self) -> Result<SortitionSimulation, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let response_json: serde_json::Value = contents.try_into()?;
+        let simulation: SortitionSimulation = serde_json::from_value(response_json)
+            .map_err(|_e| Error::DecodeError("Failed to decode JSON".to_string()))?;
+        Ok(simulation)
+    }
+}
+
+impl StacksHttpRequest {
+    pub fn new_post_simulate_commit(
+        host: PeerHost,
+        commit: &HypotheticalCommit,
+    ) -> StacksHttpRequest {
+        StacksHttpRequest::new_for_peer(
+            host,
+            "POST".into(),
+            "/v2/burnchain/simulate_commit".into(),
+            HttpRequestContents::new().payload_json(
+                serde_json::to_value(commit)
+                    .expect("FATAL: failed to encode hypothetical block-commit to JSON"),
+            ),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
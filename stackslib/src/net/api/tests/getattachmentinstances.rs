@@ -0,0 +1,149 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use clarity::vm::types::QualifiedContractIdentifier;
+
+use super::TestRPC;
+use crate::burnchains::Txid;
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::httpcore::{
+    HttpPreambleExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
+};
+use crate::net::{Attachment, ProtocolFamily};
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr, &ConnectionOptions::default());
+
+    let contract_id =
+        QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.bns")
+            .unwrap();
+    let request =
+        StacksHttpRequest::new_getattachmentinstances(addr.into(), &contract_id, 10, 20, 3);
+    let bytes = request.try_serialize().unwrap();
+
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = getattachmentinstances::RPCGetAttachmentInstancesRequestHandler::new();
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    assert_eq!(handler.contract_identifier, Some(contract_id.clone()));
+    assert_eq!(handler.start_height, Some(10));
+    assert_eq!(handler.end_height, Some(20));
+    assert_eq!(handler.page, Some(3));
+
+    // parsed request consumes headers that would not be in a constructed reqeuest
+    parsed_request.clear_headers();
+    let (preamble, contents) = parsed_request.destruct();
+
+    assert_eq!(&preamble, request.preamble());
+
+    handler.restart();
+    assert!(handler.contract_identifier.is_none());
+    assert!(handler.start_height.is_none());
+    assert!(handler.end_height.is_none());
+    assert!(handler.page.is_none());
+
+    // an inverted height range is rejected
+    let request =
+        StacksHttpRequest::new_getattachmentinstances(addr.into(), &contract_id, 20, 10, 0);
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = getattachmentinstances::RPCGetAttachmentInstancesRequestHandler::new();
+    assert!(http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .is_err());
+}
+
+#[test]
+fn test_try_make_response() {
+    let attachment = Attachment {
+        content: vec![0, 1, 2, 3, 4],
+    };
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let rpc_test = TestRPC::setup(function_name!());
+    let stacks_chain_tip = rpc_test.canonical_tip;
+    let bns_contract_id =
+        QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.bns").unwrap();
+
+    let mut requests = vec![];
+
+    // query the range that holds the instance
+    let request =
+        StacksHttpRequest::new_getattachmentinstances(addr.into(), &bns_contract_id, 0, 10, 0);
+    requests.push(request);
+
+    // query a range past the instance
+    let request =
+        StacksHttpRequest::new_getattachmentinstances(addr.into(), &bns_contract_id, 2, 10, 0);
+    requests.push(request);
+
+    // query a different contract
+    let other_contract_id =
+        QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.pox").unwrap();
+    let request =
+        StacksHttpRequest::new_getattachmentinstances(addr.into(), &other_contract_id, 0, 10, 0);
+    requests.push(request);
+
+    let mut responses = rpc_test.run(requests);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    assert_eq!(
+        response.preamble().get_canonical_stacks_tip_height(),
+        Some(1)
+    );
+
+    let resp = response.decode_attachment_instances_response().unwrap();
+    assert_eq!(resp.next_page, None);
+    assert_eq!(resp.instances.len(), 1);
+    assert_eq!(resp.instances[0].content_hash, attachment.hash());
+    assert_eq!(resp.instances[0].attachment_index, 123);
+    assert_eq!(resp.instances[0].stacks_block_height, 1);
+    assert_eq!(resp.instances[0].index_block_hash, stacks_chain_tip);
+    assert_eq!(resp.instances[0].contract_id, bns_contract_id);
+    assert_eq!(resp.instances[0].tx_id, Txid([0x22; 32]));
+
+    let response = responses.remove(0);
+    let resp = response.decode_attachment_instances_response().unwrap();
+    assert_eq!(resp.next_page, None);
+    assert!(resp.instances.is_empty());
+
+    let response = responses.remove(0);
+    let resp = response.decode_attachment_instances_response().unwrap();
+    assert_eq!(resp.next_page, None);
+    assert!(resp.instances.is_empty());
+}
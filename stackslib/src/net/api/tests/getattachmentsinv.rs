@@ -24,15 +24,17 @@ use stacks_common::codec::StacksMessageCodec;
 use stacks_common::types::chainstate::{StacksAddress, StacksBlockId};
 use stacks_common::types::net::PeerHost;
 use stacks_common::types::Address;
+use stacks_common::util::hash::Hash160;
 
 use super::{test_rpc, TestRPC};
 use crate::net::api::*;
+use crate::net::atlas::{AtlasRateLimiter, AttachmentPage, ATLAS_DATA_URL_HEADER};
 use crate::net::connection::ConnectionOptions;
 use crate::net::httpcore::{
     HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp,
     StacksHttpRequest,
 };
-use crate::net::{Attachment, ProtocolFamily, TipRequest};
+use crate::net::{Attachment, AttachmentInstance, ProtocolFamily, TipRequest};
 
 #[test]
 fn test_try_parse_request() {
@@ -77,6 +79,50 @@ fn test_try_parse_request() {
     assert!(handler.page_indexes.is_none());
 }
 
+#[test]
+fn test_try_parse_request_since() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr, &ConnectionOptions::default());
+
+    let mut pages = HashSet::new();
+    pages.insert(1);
+    pages.insert(2);
+    let since = vec![(1, Hash160([0x22; 20])), (3, Hash160([0x33; 20]))];
+
+    let request = StacksHttpRequest::new_getattachmentsinv_since(
+        addr.into(),
+        StacksBlockId([0x11; 32]),
+        pages,
+        &since,
+    );
+    let bytes = request.try_serialize().unwrap();
+
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = getattachmentsinv::RPCGetAttachmentsInvRequestHandler::new();
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    assert_eq!(handler.index_block_hash, Some(StacksBlockId([0x11; 32])));
+    assert_eq!(handler.page_indexes, Some(vec![1, 2]));
+    assert_eq!(handler.since.len(), 2);
+    assert_eq!(handler.since.get(&1), Some(&Hash160([0x22; 20])));
+    assert_eq!(handler.since.get(&3), Some(&Hash160([0x33; 20])));
+
+    parsed_request.clear_headers();
+    let (preamble, _contents) = parsed_request.destruct();
+    assert_eq!(&preamble, request.preamble());
+
+    handler.restart();
+    assert!(handler.since.is_empty());
+}
+
 #[test]
 fn test_try_make_response() {
     let attachment = Attachment {
@@ -109,6 +155,23 @@ fn test_try_make_response() {
     );
     requests.push(request);
 
+    // query non-existant block again, already having an up-to-date copy of page 1 and an
+    // outdated copy of page 2
+    let empty_page = AttachmentPage {
+        index: 1,
+        inventory: vec![0; AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE as usize],
+    };
+    let mut since_pages = pages.clone();
+    since_pages.insert(2);
+    let request = StacksHttpRequest::new_getattachmentsinv_since(
+        addr.into(),
+        StacksBlockId([0x11; 32]),
+        since_pages,
+        &[(1, empty_page.digest()), (2, Hash160([0x22; 20]))],
+    );
+    requests.push(request);
+
+    let data_url = rpc_test.peer_2.config.data_url.clone();
     let mut responses = rpc_test.run(requests);
 
     let response = responses.remove(0);
@@ -121,6 +184,11 @@ fn test_try_make_response() {
         response.preamble().get_canonical_stacks_tip_height(),
         Some(1)
     );
+    // the serving node advertises its data URL
+    assert_eq!(
+        response.preamble().get_header(ATLAS_DATA_URL_HEADER.into()),
+        Some(data_url.to_string())
+    );
 
     let resp = response.decode_atlas_attachments_inv_response().unwrap();
 
@@ -147,4 +215,58 @@ fn test_try_make_response() {
     assert_eq!(resp.pages.len(), 1);
     assert_eq!(resp.pages[0].index, 1);
     assert!(resp.pages[0].inventory.iter().find(|&&x| x == 1).is_none());
+    assert!(resp.unchanged.is_empty());
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_atlas_attachments_inv_response().unwrap();
+
+    // only the page that changed is sent back
+    assert_eq!(resp.block_id, StacksBlockId([0x11; 32]));
+    assert_eq!(resp.pages.len(), 1);
+    assert_eq!(resp.pages[0].index, 2);
+    assert_eq!(resp.unchanged, vec![1]);
+}
+
+#[test]
+fn test_try_make_response_rate_limited() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let mut rpc_test = TestRPC::setup(function_name!());
+    let stacks_chain_tip = rpc_test.canonical_tip;
+
+    // the serving peer only allows one Atlas request per minute
+    rpc_test.peer_2.network.atlas_rate_limiter = AtlasRateLimiter::new(1, 0);
+
+    let mut pages = HashSet::new();
+    pages.insert(1);
+
+    let requests = vec![
+        StacksHttpRequest::new_getattachmentsinv(addr.into(), stacks_chain_tip, pages.clone()),
+        StacksHttpRequest::new_getattachmentsinv(addr.into(), stacks_chain_tip, pages),
+    ];
+
+    let mut responses = rpc_test.run(requests);
+
+    let response = responses.remove(0);
+    assert_eq!(response.preamble().status_code, 200);
+    response.decode_atlas_attachments_inv_response().unwrap();
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+    assert_eq!(response.preamble().status_code, 429);
+    let retry_after: u64 = response
+        .preamble()
+        .get_header("retry-after".into())
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after >= 1);
 }
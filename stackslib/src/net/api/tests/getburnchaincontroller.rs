@@ -0,0 +1,95 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use crate::net::api::getburnchaincontroller::{BurnchainControllerSettings, BurnchainFeePolicy};
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::http::Error as HttpError;
+use crate::net::httpcore::{RPCRequestHandler, StacksHttp, StacksHttpRequest};
+use crate::net::{Error as NetError, ProtocolFamily};
+
+fn parse_request(
+    http: &mut StacksHttp,
+    request: &StacksHttpRequest,
+    handler: &mut getburnchaincontroller::RPCGetBurnchainControllerRequestHandler,
+) -> Result<StacksHttpRequest, NetError> {
+    let bytes = request.try_serialize().unwrap();
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    http.handle_try_parse_request(handler, &parsed_preamble.expect_request(), &bytes[offset..])
+}
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut conn_opts = ConnectionOptions::default();
+    conn_opts.burnchain_ops_token = Some("password".to_string());
+    let mut http = StacksHttp::new(addr, &conn_opts);
+
+    let request = StacksHttpRequest::new_getburnchaincontroller(addr.into(), "password");
+    let mut handler = getburnchaincontroller::RPCGetBurnchainControllerRequestHandler::new(Some(
+        "password".to_string(),
+    ));
+    let mut parsed_request = parse_request(&mut http, &request, &mut handler).unwrap();
+
+    // parsed request consumes headers that would not be in a constructed request
+    parsed_request.clear_headers();
+    let (preamble, _contents) = parsed_request.destruct();
+    let mut expected_preamble = request.preamble().clone();
+    expected_preamble.headers.clear();
+    assert_eq!(preamble, expected_preamble);
+
+    handler.restart();
+
+    // a bad token is rejected
+    let request = StacksHttpRequest::new_getburnchaincontroller(addr.into(), "wrong");
+    match parse_request(&mut http, &request, &mut handler) {
+        Err(NetError::Http(HttpError::Http(401, _))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted a request with a bad token"),
+    }
+
+    // the endpoint is disabled without a token
+    let request = StacksHttpRequest::new_getburnchaincontroller(addr.into(), "password");
+    let mut handler = getburnchaincontroller::RPCGetBurnchainControllerRequestHandler::new(None);
+    match parse_request(&mut http, &request, &mut handler) {
+        Err(NetError::Http(HttpError::Http(400, _))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted a request while disabled"),
+    }
+}
+
+#[test]
+fn test_burnchain_controller_response_json() {
+    let settings = BurnchainControllerSettings {
+        mode: "mainnet".into(),
+        poll_interval_secs: 10,
+        rpc_timeout_secs: 60,
+        sortition_wait_timeout_ms: None,
+        op_expiry_blocks: 6,
+        fees: BurnchainFeePolicy {
+            satoshis_per_byte: 50,
+            rbf_fee_increment: 5,
+            max_rbf: 150,
+            burn_fee_cap: 20000,
+        },
+    };
+    let json = serde_json::to_value(&settings).unwrap();
+    assert_eq!(json["fees"]["burn_fee_cap"], 20000);
+    let decoded: BurnchainControllerSettings = serde_json::from_value(json).unwrap();
+    assert_eq!(decoded, settings);
+}
@@ -0,0 +1,113 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use crate::net::api::getburnchainops::{
+    BlockCommitOutcomes, DEFAULT_BURNCHAIN_OPS_LIMIT, MAX_BURNCHAIN_OPS_LIMIT,
+};
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::http::{Error as HttpError, HttpRequestContents};
+use crate::net::httpcore::{RPCRequestHandler, StacksHttp, StacksHttpRequest};
+use crate::net::{Error as NetError, ProtocolFamily};
+
+fn parse_request(
+    http: &mut StacksHttp,
+    request: &StacksHttpRequest,
+    handler: &mut getburnchainops::RPCGetBurnchainOpsRequestHandler,
+) -> Result<StacksHttpRequest, NetError> {
+    let bytes = request.try_serialize().unwrap();
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    http.handle_try_parse_request(handler, &parsed_preamble.expect_request(), &bytes[offset..])
+}
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut conn_opts = ConnectionOptions::default();
+    conn_opts.burnchain_ops_token = Some("password".to_string());
+    let mut http = StacksHttp::new(addr, &conn_opts);
+
+    let request = StacksHttpRequest::new_getburnchainops(addr.into(), 20, "password");
+    let mut handler =
+        getburnchainops::RPCGetBurnchainOpsRequestHandler::new(Some("password".to_string()));
+    let mut parsed_request = parse_request(&mut http, &request, &mut handler).unwrap();
+    assert_eq!(handler.limit, Some(20));
+
+    // parsed request consumes headers that would not be in a constructed request
+    parsed_request.clear_headers();
+    let (preamble, _contents) = parsed_request.destruct();
+    let mut expected_preamble = request.preamble().clone();
+    expected_preamble.headers.clear();
+    assert_eq!(preamble, expected_preamble);
+
+    handler.restart();
+    assert!(handler.limit.is_none());
+
+    // the limit is optional...
+    let mut request = StacksHttpRequest::new_for_peer(
+        addr.into(),
+        "GET".into(),
+        "/v2/burnchain/ops".into(),
+        HttpRequestContents::new(),
+    )
+    .unwrap();
+    request.add_header("authorization".into(), "password".into());
+    parse_request(&mut http, &request, &mut handler).unwrap();
+    assert_eq!(handler.limit, Some(DEFAULT_BURNCHAIN_OPS_LIMIT));
+
+    // ...but bounded
+    let request = StacksHttpRequest::new_getburnchainops(
+        addr.into(),
+        MAX_BURNCHAIN_OPS_LIMIT + 1,
+        "password",
+    );
+    match parse_request(&mut http, &request, &mut handler) {
+        Err(NetError::Http(HttpError::DecodeError(_))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted a request with too high a limit"),
+    }
+
+    // a bad token is rejected
+    let request = StacksHttpRequest::new_getburnchainops(addr.into(), 20, "wrong");
+    match parse_request(&mut http, &request, &mut handler) {
+        Err(NetError::Http(HttpError::Http(401, _))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted a request with a bad token"),
+    }
+
+    // the endpoint is disabled without a token
+    let request = StacksHttpRequest::new_getburnchainops(addr.into(), 20, "password");
+    let mut handler = getburnchainops::RPCGetBurnchainOpsRequestHandler::new(None);
+    match parse_request(&mut http, &request, &mut handler) {
+        Err(NetError::Http(HttpError::Http(400, _))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted a request while disabled"),
+    }
+}
+
+#[test]
+fn test_block_commit_win_rate() {
+    assert_eq!(BlockCommitOutcomes::default().win_rate(), None);
+    let outcomes = BlockCommitOutcomes {
+        won: 1,
+        lost: 4,
+        rejected: 2,
+        not_mined: 1,
+    };
+    assert_eq!(outcomes.win_rate(), Some(0.125));
+}
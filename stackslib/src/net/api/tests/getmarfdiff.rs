@@ -0,0 +1,236 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use stacks_common::types::chainstate::StacksBlockId;
+
+use super::TestRPC;
+use crate::chainstate::stacks::index::marf::MarfConnection;
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::http::Error as HttpError;
+use crate::net::httpcore::{
+    HttpPreambleExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
+};
+use crate::net::{Error as NetError, ProtocolFamily};
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut conn_opts = ConnectionOptions::default();
+    conn_opts.marf_diff_token = Some("password".to_string());
+    let mut http = StacksHttp::new(addr, &conn_opts);
+
+    let request = StacksHttpRequest::new_getmarfdiff(
+        addr.into(),
+        StacksBlockId([0x11; 32]),
+        StacksBlockId([0x22; 32]),
+        Some(123),
+        "password",
+    );
+    let bytes = request.try_serialize().unwrap();
+
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = getmarfdiff::RPCGetMarfDiffRequestHandler::new(Some("password".to_string()));
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    assert_eq!(handler.from_block_id, Some(StacksBlockId([0x11; 32])));
+    assert_eq!(handler.to_block_id, Some(StacksBlockId([0x22; 32])));
+    assert_eq!(handler.limit, Some(123));
+
+    // parsed request consumes headers that would not be in a constructed reqeuest
+    parsed_request.clear_headers();
+    let (preamble, _contents) = parsed_request.destruct();
+    let mut expected_preamble = request.preamble().clone();
+    expected_preamble.headers.clear();
+    assert_eq!(preamble, expected_preamble);
+
+    handler.restart();
+    assert!(handler.from_block_id.is_none());
+    assert!(handler.to_block_id.is_none());
+    assert!(handler.limit.is_none());
+
+    // the limit is optional, but bounded
+    let request = StacksHttpRequest::new_getmarfdiff(
+        addr.into(),
+        StacksBlockId([0x11; 32]),
+        StacksBlockId([0x22; 32]),
+        None,
+        "password",
+    );
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    )
+    .unwrap();
+    assert_eq!(handler.limit, Some(getmarfdiff::DEFAULT_MARF_DIFF_ENTRIES));
+
+    let request = StacksHttpRequest::new_getmarfdiff(
+        addr.into(),
+        StacksBlockId([0x11; 32]),
+        StacksBlockId([0x22; 32]),
+        Some(getmarfdiff::MAX_MARF_DIFF_ENTRIES + 1),
+        "password",
+    );
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    assert!(http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .is_err());
+
+    // a bad token is rejected
+    let request = StacksHttpRequest::new_getmarfdiff(
+        addr.into(),
+        StacksBlockId([0x11; 32]),
+        StacksBlockId([0x22; 32]),
+        None,
+        "wrong",
+    );
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    match http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    ) {
+        Err(NetError::Http(HttpError::Http(401, _))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted a request with a bad token"),
+    }
+
+    // the endpoint is disabled without a token
+    let request = StacksHttpRequest::new_getmarfdiff(
+        addr.into(),
+        StacksBlockId([0x11; 32]),
+        StacksBlockId([0x22; 32]),
+        None,
+        "password",
+    );
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = getmarfdiff::RPCGetMarfDiffRequestHandler::new(None);
+    match http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    ) {
+        Err(NetError::Http(HttpError::Http(400, _))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted a request while disabled"),
+    }
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let mut rpc_test = TestRPC::setup(function_name!());
+    let stacks_chain_tip = rpc_test.canonical_tip;
+    let parent_tip = rpc_test.peer_1.chainstate().with_clarity_marf(|marf| {
+        let height = marf
+            .get_block_height(&stacks_chain_tip, &stacks_chain_tip)
+            .unwrap()
+            .unwrap();
+        marf.get_block_at_height(height - 1, &stacks_chain_tip)
+            .unwrap()
+            .unwrap()
+    });
+
+    let mut requests = vec![];
+
+    // the tip's changes
+    let request = StacksHttpRequest::new_getmarfdiff(
+        addr.into(),
+        parent_tip,
+        stacks_chain_tip,
+        None,
+        "password",
+    );
+    requests.push(request);
+
+    // same, but truncated
+    let request = StacksHttpRequest::new_getmarfdiff(
+        addr.into(),
+        parent_tip,
+        stacks_chain_tip,
+        Some(1),
+        "password",
+    );
+    requests.push(request);
+
+    // no changes
+    let request = StacksHttpRequest::new_getmarfdiff(
+        addr.into(),
+        stacks_chain_tip,
+        stacks_chain_tip,
+        None,
+        "password",
+    );
+    requests.push(request);
+
+    // non-existant block
+    let request = StacksHttpRequest::new_getmarfdiff(
+        addr.into(),
+        StacksBlockId([0x11; 32]),
+        stacks_chain_tip,
+        None,
+        "password",
+    );
+    requests.push(request);
+
+    let mut responses = rpc_test.run(requests);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+    let full = response.decode_marf_diff_response().unwrap();
+    assert_eq!(full.from_block_id, parent_tip);
+    assert_eq!(full.to_block_id, stacks_chain_tip);
+    assert!(full.entries.len() > 1);
+    assert!(!full.truncated);
+
+    let response = responses.remove(0);
+    let partial = response.decode_marf_diff_response().unwrap();
+    assert_eq!(partial.entries[..], full.entries[..1]);
+    assert!(partial.truncated);
+
+    let response = responses.remove(0);
+    let empty = response.decode_marf_diff_response().unwrap();
+    assert!(empty.entries.is_empty());
+    assert!(!empty.truncated);
+
+    let response = responses.remove(0);
+    let (preamble, _body) = response.destruct();
+    assert_eq!(preamble.status_code, 404);
+}
@@ -40,6 +40,7 @@ use crate::chainstate::stacks::{
     TransactionAuth, TransactionPayload, TransactionPostConditionMode, TransactionVersion,
 };
 use crate::core::MemPoolDB;
+use crate::net::atlas::AttachmentOrigin;
 use crate::net::db::PeerDB;
 use crate::net::httpcore::{StacksHttpRequest, StacksHttpResponse};
 use crate::net::relay::Relayer;
@@ -53,9 +54,15 @@ use crate::net::{
 mod callreadonly;
 mod getaccount;
 mod getattachment;
+mod getattachmentinstances;
+mod getattachmentresolutionlatency;
+mod getattachmentsdownloader;
 mod getattachmentsinv;
 mod getblock;
 mod getblock_v3;
+mod getburnchaincontroller;
+mod getburnchainintents;
+mod getburnchainops;
 mod getconstantval;
 mod getcontractabi;
 mod getcontractsrc;
@@ -64,6 +71,8 @@ mod getheaders;
 mod getinfo;
 mod getistraitimplemented;
 mod getmapentry;
+mod getmarfdiff;
+mod getmarfio;
 mod getmicroblocks_confirmed;
 mod getmicroblocks_indexed;
 mod getmicroblocks_unconfirmed;
@@ -76,10 +85,13 @@ mod gettenure;
 mod gettenureinfo;
 mod gettransaction_unconfirmed;
 mod liststackerdbreplicas;
+mod postattachmentsdownload;
 mod postblock;
+mod postburnchainsync;
 mod postfeerate;
 mod postmempoolquery;
 mod postmicroblock;
+mod postsimulatecommit;
 mod poststackerdbchunk;
 mod posttransaction;
 
@@ -268,6 +280,10 @@ impl<'a> TestRPC<'a> {
         };
         peer_2_config.connection_opts.maximum_call_argument_size = 4096;
 
+        // enable the debugging endpoints that need an authorization token
+        peer_1_config.connection_opts.marf_diff_token = Some("password".to_string());
+        peer_2_config.connection_opts.marf_diff_token = Some("password".to_string());
+
         // stacker DBs get initialized thru reconfiguration when the above block gets processed
         peer_1_config.add_stacker_db(
             QualifiedContractIdentifier::new(addr1.clone().into(), "hello-world".into()),
@@ -654,6 +670,7 @@ impl<'a> TestRPC<'a> {
                 .unwrap(),
             tx_id: Txid([0x22; 32]),
             canonical_stacks_tip_height: Some(1),
+            origin: AttachmentOrigin::AnchoredBlock,
         };
 
         peer_1
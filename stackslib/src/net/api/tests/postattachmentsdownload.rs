@@ -0,0 +1,171 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use clarity::vm::types::QualifiedContractIdentifier;
+use stacks_common::util::hash::Hash160;
+
+use crate::net::api::postattachmentsdownload::AttachmentsDownloadRequestBody;
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::http::Error as HttpError;
+use crate::net::httpcore::{RPCRequestHandler, StacksHttp, StacksHttpRequest};
+use crate::net::{Error as NetError, ProtocolFamily};
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut conn_opts = ConnectionOptions::default();
+    conn_opts.attachments_download_token = Some("password".to_string());
+    let mut http = StacksHttp::new(addr, &conn_opts);
+
+    let try_parse =
+        |http: &mut StacksHttp,
+         handler: &mut postattachmentsdownload::RPCPostAttachmentsDownloadRequestHandler,
+         body: AttachmentsDownloadRequestBody,
+         auth: &str| {
+            let request = StacksHttpRequest::new_postattachmentsdownload(addr.into(), body, auth);
+            let bytes = request.try_serialize().unwrap();
+
+            debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+            let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+            http.handle_try_parse_request(
+                handler,
+                &parsed_preamble.expect_request(),
+                &bytes[offset..],
+            )
+            .map(|parsed_request| (request, parsed_request))
+        };
+
+    let contract_id =
+        QualifiedContractIdentifier::parse("SP000000000000000000002Q6VF78.bns").unwrap();
+    let content_hash = Hash160([0x11; 20]);
+    let body = AttachmentsDownloadRequestBody {
+        contract_id: Some(contract_id.to_string()),
+        content_hashes: vec![content_hash],
+    };
+
+    let mut handler = postattachmentsdownload::RPCPostAttachmentsDownloadRequestHandler::new(Some(
+        "password".to_string(),
+    ));
+    let (request, mut parsed_request) =
+        try_parse(&mut http, &mut handler, body.clone(), "password").unwrap();
+
+    assert_eq!(handler.contract_id, Some(contract_id.clone()));
+    assert_eq!(handler.content_hashes, Some(vec![content_hash]));
+
+    // parsed request consumes headers that would not be in a constructed request
+    parsed_request.clear_headers();
+    let (preamble, _contents) = parsed_request.destruct();
+    let mut expected_preamble = request.preamble().clone();
+    expected_preamble.headers.clear();
+    assert_eq!(preamble, expected_preamble);
+
+    handler.restart();
+    assert!(handler.contract_id.is_none());
+    assert!(handler.content_hashes.is_none());
+
+    // either field alone is enough
+    try_parse(
+        &mut http,
+        &mut handler,
+        AttachmentsDownloadRequestBody {
+            contract_id: None,
+            content_hashes: vec![content_hash],
+        },
+        "password",
+    )
+    .unwrap();
+    assert!(handler.contract_id.is_none());
+    assert_eq!(handler.content_hashes, Some(vec![content_hash]));
+    handler.restart();
+
+    try_parse(
+        &mut http,
+        &mut handler,
+        AttachmentsDownloadRequestBody {
+            contract_id: Some(contract_id.to_string()),
+            content_hashes: vec![],
+        },
+        "password",
+    )
+    .unwrap();
+    assert_eq!(handler.contract_id, Some(contract_id.clone()));
+    assert_eq!(handler.content_hashes, Some(vec![]));
+    handler.restart();
+
+    // the request must name something to download
+    match try_parse(
+        &mut http,
+        &mut handler,
+        AttachmentsDownloadRequestBody::default(),
+        "password",
+    ) {
+        Err(NetError::Http(HttpError::DecodeError(_))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted an empty request"),
+    }
+
+    // a malformed contract ID is rejected
+    match try_parse(
+        &mut http,
+        &mut handler,
+        AttachmentsDownloadRequestBody {
+            contract_id: Some("not-a-contract".to_string()),
+            content_hashes: vec![],
+        },
+        "password",
+    ) {
+        Err(NetError::Http(HttpError::DecodeError(_))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted a malformed contract ID"),
+    }
+
+    // too many content hashes are rejected
+    match try_parse(
+        &mut http,
+        &mut handler,
+        AttachmentsDownloadRequestBody {
+            contract_id: None,
+            content_hashes: vec![
+                content_hash;
+                postattachmentsdownload::MAX_ATTACHMENTS_DOWNLOAD_HASHES + 1
+            ],
+        },
+        "password",
+    ) {
+        Err(NetError::Http(HttpError::DecodeError(_))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted too many content hashes"),
+    }
+
+    // a bad token is rejected
+    match try_parse(&mut http, &mut handler, body.clone(), "wrong") {
+        Err(NetError::Http(HttpError::Http(401, _))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted a request with a bad token"),
+    }
+
+    // the endpoint is disabled without a token
+    let mut handler = postattachmentsdownload::RPCPostAttachmentsDownloadRequestHandler::new(None);
+    match try_parse(&mut http, &mut handler, body, "password") {
+        Err(NetError::Http(HttpError::Http(400, _))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted a request while disabled"),
+    }
+}
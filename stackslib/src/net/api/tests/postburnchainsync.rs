@@ -0,0 +1,99 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use crate::net::api::postburnchainsync::BurnchainSyncAction;
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::http::Error as HttpError;
+use crate::net::httpcore::{RPCRequestHandler, StacksHttp, StacksHttpRequest};
+use crate::net::{Error as NetError, ProtocolFamily};
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut conn_opts = ConnectionOptions::default();
+    conn_opts.burnchain_sync_token = Some("password".to_string());
+    let mut http = StacksHttp::new(addr, &conn_opts);
+
+    for action in [BurnchainSyncAction::Pause, BurnchainSyncAction::Resume] {
+        let request = StacksHttpRequest::new_postburnchainsync(addr.into(), action, "password");
+        let bytes = request.try_serialize().unwrap();
+
+        debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+        let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let mut handler = postburnchainsync::RPCPostBurnchainSyncRequestHandler::new(Some(
+            "password".to_string(),
+        ));
+        let mut parsed_request = http
+            .handle_try_parse_request(
+                &mut handler,
+                &parsed_preamble.expect_request(),
+                &bytes[offset..],
+            )
+            .unwrap();
+
+        assert_eq!(handler.action, Some(action));
+
+        // parsed request consumes headers that would not be in a constructed request
+        parsed_request.clear_headers();
+        let (preamble, _contents) = parsed_request.destruct();
+        let mut expected_preamble = request.preamble().clone();
+        expected_preamble.headers.clear();
+        assert_eq!(preamble, expected_preamble);
+
+        handler.restart();
+        assert!(handler.action.is_none());
+    }
+
+    // a bad token is rejected
+    let request =
+        StacksHttpRequest::new_postburnchainsync(addr.into(), BurnchainSyncAction::Pause, "wrong");
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler =
+        postburnchainsync::RPCPostBurnchainSyncRequestHandler::new(Some("password".to_string()));
+    match http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    ) {
+        Err(NetError::Http(HttpError::Http(401, _))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted a request with a bad token"),
+    }
+
+    // the endpoint is disabled without a token
+    let request = StacksHttpRequest::new_postburnchainsync(
+        addr.into(),
+        BurnchainSyncAction::Pause,
+        "password",
+    );
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = postburnchainsync::RPCPostBurnchainSyncRequestHandler::new(None);
+    match http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    ) {
+        Err(NetError::Http(HttpError::Http(400, _))) => {}
+        Err(e) => panic!("Unexpected error: {:?}", &e),
+        Ok(_) => panic!("Accepted a request while disabled"),
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use super::test_rpc;
+use crate::burnchains::{BurnchainSigner, Txid};
+use crate::chainstate::burn::simulation::HypotheticalCommit;
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::httpcore::{RPCRequestHandler, StacksHttp, StacksHttpRequest};
+use crate::net::ProtocolFamily;
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr, &ConnectionOptions::default());
+
+    let commit = HypotheticalCommit {
+        apparent_sender: BurnchainSigner("miner".to_string()),
+        burn_fee: 12345,
+        input: Some((Txid([0x11; 32]), 3)),
+    };
+    let request = StacksHttpRequest::new_post_simulate_commit(addr.into(), &commit);
+    let bytes = request.try_serialize().unwrap();
+
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = postsimulatecommit::RPCPostSimulateCommitRequestHandler::new();
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    assert_eq!(handler.commit, Some(commit));
+
+    // parsed request consumes headers that would not be in a constructed request
+    parsed_request.clear_headers();
+    let (preamble, _contents) = parsed_request.destruct();
+
+    assert_eq!(&preamble, request.preamble());
+
+    handler.restart();
+    assert!(handler.commit.is_none());
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let commit = HypotheticalCommit {
+        apparent_sender: BurnchainSigner("new-miner".to_string()),
+        burn_fee: 1000,
+        input: None,
+    };
+
+    let mut requests = vec![];
+    requests.push(StacksHttpRequest::new_post_simulate_commit(
+        addr.into(),
+        &commit,
+    ));
+
+    let mut responses = test_rpc(function_name!(), requests);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let simulation = response.decode_sortition_simulation().unwrap();
+    assert!(simulation.num_commits >= 1);
+    assert!(simulation.burns <= 1000);
+    assert!(simulation.total_burns >= simulation.burns);
+    assert!(simulation.win_probability > 0.0);
+    assert!(simulation.win_probability <= 1.0);
+}
@@ -0,0 +1,295 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Integrity audit of the `AtlasDB`.
+//!
+//! The audit walks every stored attachment and every checked attachment instance, and reports:
+//! * attachments whose content no longer hashes to the hash they are stored under, or whose
+//!   blob is missing from the blob store,
+//! * instances marked available whose attachment is not stored (or is corrupt),
+//! * instances not marked available even though their attachment is stored, and
+//! * gaps in each contract's sequence of attachment indexes, i.e. instances that should
+//!   have been recorded but were not (or were evicted before being resolved).
+//!
+//! The audit can run incrementally (a bounded number of rows per step), so that it can be
+//! driven from the p2p thread without stalling it.  Once it is done, `apply_repairs` fixes up
+//! the AtlasDB and returns the instances whose attachments must be downloaded again.
+
+use clarity::vm::types::QualifiedContractIdentifier;
+use rusqlite::NO_PARAMS;
+use stacks_common::util::hash::{Hash160, MerkleHashFunc};
+
+use super::db::AttachmentInstanceStatus;
+use super::storage::AttachmentStorage;
+use super::{AtlasDB, Attachment, AttachmentInstance};
+use crate::util_lib::db::{query_rows, Error as db_error, FromRow};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AuditStage {
+    Attachments,
+    Instances,
+    IndexGaps,
+    Done,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AtlasAuditReport {
+    pub attachments_checked: u64,
+    pub instances_checked: u64,
+    /// Number of instances whose attachment has not been downloaded yet, as expected
+    pub unresolved_instances: u64,
+    /// Stored attachments whose content does not hash to the hash they are stored under
+    pub corrupt_attachments: Vec<Hash160>,
+    /// Instances marked available whose attachment is not stored, or is corrupt
+    pub missing_attachments: Vec<AttachmentInstance>,
+    /// Instances not marked available even though their attachment is stored
+    pub stale_unavailable: Vec<AttachmentInstance>,
+    /// Ranges `[first, last]` of attachment indexes for which a contract has no instance
+    pub missing_instance_indexes: Vec<(QualifiedContractIdentifier, u32, u32)>,
+}
+
+impl AtlasAuditReport {
+    /// Did the audit find nothing to repair?
+    pub fn is_clean(&self) -> bool {
+        self.corrupt_attachments.is_empty()
+            && self.missing_attachments.is_empty()
+            && self.stale_unavailable.is_empty()
+            && self.missing_instance_indexes.is_empty()
+    }
+
+    /// The instances whose attachments must be downloaded again
+    pub fn repair_work_list(&self) -> Vec<AttachmentInstance> {
+        self.missing_attachments.clone()
+    }
+}
+
+/// Rows of the `attachments` table, for auditing
+struct StoredAttachment {
+    rowid: i64,
+    hash: String,
+    attachment: Attachment,
+    storage: AttachmentStorage,
+}
+
+impl FromRow<StoredAttachment> for StoredAttachment {
+    fn from_row<'a>(row: &'a rusqlite::Row) -> Result<StoredAttachment, db_error> {
+        Ok(StoredAttachment {
+            rowid: row.get_unwrap("rowid"),
+            hash: row.get_unwrap("hash"),
+            attachment: Attachment::from_row(row)?,
+            storage: row.get_unwrap("storage"),
+        })
+    }
+}
+
+/// Rows of the `attachment_instances` table, for auditing
+struct StoredInstance {
+    rowid: i64,
+    is_available: bool,
+    instance: AttachmentInstance,
+}
+
+impl FromRow<StoredInstance> for StoredInstance {
+    fn from_row<'a>(row: &'a rusqlite::Row) -> Result<StoredInstance, db_error> {
+        Ok(StoredInstance {
+            rowid: row.get_unwrap("rowid"),
+            is_available: row.get_unwrap("is_available"),
+            instance: AttachmentInstance::from_row(row)?,
+        })
+    }
+}
+
+/// An audit of the AtlasDB, which may be run a few rows at a time
+#[derive(Debug)]
+pub struct AtlasAudit {
+    stage: AuditStage,
+    /// The last rowid audited in the current stage's table
+    cursor: i64,
+    report: AtlasAuditReport,
+}
+
+impl AtlasAudit {
+    pub fn new() -> AtlasAudit {
+        AtlasAudit {
+            stage: AuditStage::Attachments,
+            cursor: 0,
+            report: AtlasAuditReport::default(),
+        }
+    }
+
+    /// Run a whole audit at once
+    pub fn run(atlasdb: &AtlasDB) -> Result<AtlasAuditReport, db_error> {
+        let mut audit = AtlasAudit::new();
+        while !audit.step(atlasdb, u32::MAX)? {}
+        Ok(audit.into_report())
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.stage == AuditStage::Done
+    }
+
+    pub fn report(&self) -> &AtlasAuditReport {
+        &self.report
+    }
+
+    pub fn into_report(self) -> AtlasAuditReport {
+        self.report
+    }
+
+    /// Audit up to `max_rows` more rows of the current stage.
+    /// Returns `true` once the audit is done.
+    pub fn step(&mut self, atlasdb: &AtlasDB, max_rows: u32) -> Result<bool, db_error> {
+        match self.stage {
+            AuditStage::Attachments => {
+                let rows: Vec<StoredAttachment> = query_rows(
+                    atlasdb.conn(),
+                    "SELECT rowid, hash, content, storage FROM attachments
+                     WHERE was_instantiated = 1 AND content_missing = 0 AND rowid > ?1
+                     ORDER BY rowid ASC LIMIT ?2",
+                    rusqlite::params![&self.cursor, &max_rows],
+                )?;
+                let stage_done = (rows.len() as u64) < u64::from(max_rows);
+                for row in rows.into_iter() {
+                    self.cursor = row.rowid;
+                    self.report.attachments_checked += 1;
+                    let stored_hash = Hash160::from_hex(&row.hash).ok();
+                    let content = match (row.storage, stored_hash.as_ref()) {
+                        (AttachmentStorage::Sqlite, _) => Some(row.attachment.content),
+                        (AttachmentStorage::Filesystem, Some(hash)) => {
+                            atlasdb.read_attachment_blob(hash)?
+                        }
+                        (AttachmentStorage::Filesystem, None) => None,
+                    };
+                    let content = match content {
+                        Some(content) => content,
+                        None => {
+                            warn!("Atlas audit: stored attachment's blob is missing";
+                                  "hash" => &row.hash);
+                            if let Some(hash) = stored_hash {
+                                self.report.corrupt_attachments.push(hash);
+                            }
+                            continue;
+                        }
+                    };
+                    let actual_hash = Attachment::new(content).hash();
+                    if stored_hash != Some(actual_hash) {
+                        warn!("Atlas audit: stored attachment does not match its hash";
+                              "hash" => &row.hash,
+                              "actual_hash" => %actual_hash);
+                        // the stored hash is authoritative: it is what instances refer to
+                        if let Some(hash) = stored_hash {
+                            self.report.corrupt_attachments.push(hash);
+                        }
+                    }
+                }
+                if stage_done {
+                    self.next_stage(AuditStage::Instances);
+                }
+            }
+            AuditStage::Instances => {
+                let rows: Vec<StoredInstance> = query_rows(
+                    atlasdb.conn(),
+                    "SELECT rowid, * FROM attachment_instances
+                     WHERE status = ?1 AND rowid > ?2 ORDER BY rowid ASC LIMIT ?3",
+                    rusqlite::params![&AttachmentInstanceStatus::Checked, &self.cursor, &max_rows],
+                )?;
+                let stage_done = (rows.len() as u64) < u64::from(max_rows);
+                for row in rows.into_iter() {
+                    self.cursor = row.rowid;
+                    self.audit_instance(atlasdb, row.instance, row.is_available)?;
+                }
+                if stage_done {
+                    self.next_stage(AuditStage::IndexGaps);
+                }
+            }
+            AuditStage::IndexGaps => {
+                let contract_ids: Vec<String> = query_rows(
+                    atlasdb.conn(),
+                    "SELECT DISTINCT contract_id FROM attachment_instances ORDER BY contract_id",
+                    NO_PARAMS,
+                )?;
+                for contract_id in contract_ids.iter() {
+                    let contract_id = QualifiedContractIdentifier::parse(contract_id)
+                        .map_err(|_| db_error::ParseError)?;
+                    let indexes = atlasdb.get_attachment_indexes(&contract_id)?;
+                    for pair in indexes.windows(2) {
+                        if pair[1] > pair[0] + 1 {
+                            self.report.missing_instance_indexes.push((
+                                contract_id.clone(),
+                                pair[0] + 1,
+                                pair[1] - 1,
+                            ));
+                        }
+                    }
+                }
+                self.next_stage(AuditStage::Done);
+            }
+            AuditStage::Done => {}
+        }
+        Ok(self.is_done())
+    }
+
+    fn next_stage(&mut self, stage: AuditStage) {
+        self.stage = stage;
+        self.cursor = 0;
+    }
+
+    fn audit_instance(
+        &mut self,
+        atlasdb: &AtlasDB,
+        instance: AttachmentInstance,
+        is_available: bool,
+    ) -> Result<(), db_error> {
+        self.report.instances_checked += 1;
+        // the empty hash is used to undo an on-chain binding; there's nothing to store
+        let is_stored = instance.content_hash == Hash160::empty()
+            || (atlasdb.has_instantiated_attachment(&instance.content_hash)?
+                && !self
+                    .report
+                    .corrupt_attachments
+                    .contains(&instance.content_hash));
+        match (is_available, is_stored) {
+            (true, false) => self.report.missing_attachments.push(instance),
+            (false, true) => self.report.stale_unavailable.push(instance),
+            (false, false) => self.report.unresolved_instances += 1,
+            (true, true) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Fix up the AtlasDB according to a finished audit: drop corrupt attachments, and correct
+/// the availability of instances.  Returns the instances whose attachments must be downloaded
+/// again, which can be handed to `AttachmentsDownloader::enqueue_on_demand_batches`.
+pub fn apply_repairs(
+    atlasdb: &mut AtlasDB,
+    report: &AtlasAuditReport,
+) -> Result<Vec<AttachmentInstance>, db_error> {
+    for content_hash in report.corrupt_attachments.iter() {
+        atlasdb.delete_attachment(content_hash)?;
+    }
+    for instance in report.missing_attachments.iter() {
+        atlasdb.mark_attachment_instance_checked(instance, false)?;
+    }
+    for instance in report.stale_unavailable.iter() {
+        atlasdb.mark_attachment_instance_checked(instance, true)?;
+    }
+    if !report.missing_instance_indexes.is_empty() {
+        // these cannot be recovered from the AtlasDB alone
+        warn!("Atlas audit: some contracts are missing attachment instances";
+              "ranges" => report.missing_instance_indexes.len());
+    }
+    Ok(report.repair_work_list())
+}
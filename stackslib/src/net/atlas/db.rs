@@ -32,8 +32,11 @@
 //! already stored on the node) or it adds the attachment instance
 //! to its download queue.
 //!
+//! `Attachment` content is stored either in the `AtlasDB` itself, or
+//! in the `AttachmentBlobStore` next to it (see `storage`).
+//!
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 
 use clarity::vm::types::QualifiedContractIdentifier;
@@ -47,14 +50,20 @@ use stacks_common::util::log;
 use stacks_common::util::macros::is_big_endian;
 use stacks_common::util::secp256k1::{Secp256k1PrivateKey, Secp256k1PublicKey};
 
-use super::{AtlasConfig, Attachment, AttachmentInstance};
+use super::download::ReliabilityReport;
+use super::storage::{AttachmentBlobStore, AttachmentStorage};
+use super::{
+    AtlasConfig, Attachment, AttachmentInstance, AttachmentOrigin, AttachmentResolutionPercentiles,
+    GetAttachmentResolutionLatencyResponse,
+};
 use crate::burnchains::Txid;
 use crate::util_lib::db::{
-    query_count, query_int, query_row, query_rows, sqlite_open, tx_begin_immediate, u64_to_sql,
-    DBConn, Error as db_error, FromColumn, FromRow,
+    opt_u64_to_sql, query_count, query_int, query_row, query_rows, sqlite_open, tx_begin_immediate,
+    u64_to_sql, DBConn, Error as db_error, FromColumn, FromRow,
 };
+use crate::util_lib::strings::UrlString;
 
-pub const ATLASDB_VERSION: &'static str = "2";
+pub const ATLASDB_VERSION: &'static str = "7";
 
 /// The maximum number of atlas attachment instances that should be
 /// checked at once (this is used to limit the return size of
@@ -65,6 +74,10 @@ pub const ATLASDB_VERSION: &'static str = "2";
 /// Attachment as well (which is larger).
 pub const MAX_PROCESS_PER_ROUND: u32 = 1_000;
 
+/// The number of attachments moved per transaction when migrating attachments from one
+/// `AttachmentStorage` to another
+const STORAGE_MIGRATION_BATCH_SIZE: u32 = 100;
+
 const ATLASDB_INITIAL_SCHEMA: &'static [&'static str] = &[
     r#"
     CREATE TABLE attachments(
@@ -102,11 +115,81 @@ const ATLASDB_SCHEMA_2: &'static [&'static str] = &[
     r#"
     UPDATE attachment_instances SET status = 2;
     "#,
+    "INSERT INTO db_config (version) VALUES ('2');",
+];
+
+const ATLASDB_SCHEMA_3: &'static [&'static str] = &[
+    // Indexes for the lookups by content hash, by availability, and by page, which otherwise
+    //  scan the whole attachment_instances table.
+    "CREATE INDEX IF NOT EXISTS index_instance_content_hash ON attachment_instances(content_hash, status);",
+    "CREATE INDEX IF NOT EXISTS index_instance_unresolved ON attachment_instances(is_available, status, created_at);",
+    "CREATE INDEX IF NOT EXISTS index_instance_attachment_index ON attachment_instances(attachment_index);",
+    // Eviction of uninstantiated attachments goes oldest-first.
+    "CREATE INDEX IF NOT EXISTS index_uninstantiated_created_at ON attachments(was_instantiated, created_at);",
+    "INSERT INTO db_config (version) VALUES ('3');",
+];
+
+const ATLASDB_SCHEMA_4: &'static [&'static str] = &[
+    // Where each attachment's content is stored (see `AttachmentStorage`).  Attachments
+    //  stored in the blob store have an empty `content`.
+    "ALTER TABLE attachments ADD COLUMN storage INTEGER NOT NULL DEFAULT 0;",
+    // Migrations between storages look for the attachments stored elsewhere on every open.
+    "CREATE INDEX IF NOT EXISTS index_attachments_storage ON attachments(storage);",
+    // Attachments whose content was found missing or corrupt while moving them between
+    //  storages.  They are kept, but read as absent, until the downloader fetches them again.
+    "ALTER TABLE attachments ADD COLUMN content_missing INTEGER NOT NULL DEFAULT 0;",
+    // The storage that every attachment was last moved to.  Attachments are only moved again
+    //  once `AtlasConfig::attachment_storage` no longer matches it.
+    "CREATE TABLE attachment_storage_state(storage INTEGER NOT NULL);",
+    "INSERT INTO db_config (version) VALUES ('4');",
+];
+
+const ATLASDB_SCHEMA_5: &'static [&'static str] = &[
+    // The attachments downloader's reliability report for each peer it has sent requests to,
+    //  so that it does not have to learn again which peers are reliable and fast on restart.
+    r#"
+    CREATE TABLE peer_reliability_reports(
+        data_url TEXT PRIMARY KEY,
+        total_requests_sent INTEGER NOT NULL,
+        total_requests_success INTEGER NOT NULL,
+        avg_latency_ms INTEGER,
+        avg_throughput_bps INTEGER,
+        updated_at INTEGER NOT NULL
+    );"#,
+    "INSERT INTO db_config (version) VALUES ('5');",
+];
+
+const ATLASDB_SCHEMA_6: &'static [&'static str] = &[
+    // Where the transaction that emitted each attachment instance was mined (see
+    //  `AttachmentOrigin`).  Instances written before are from anchored blocks.
+    "ALTER TABLE attachment_instances ADD COLUMN origin TEXT NOT NULL DEFAULT 'anchored';",
+    "INSERT INTO db_config (version) VALUES ('6');",
+];
+
+const ATLASDB_SCHEMA_7: &'static [&'static str] = &[
+    // When each attachment instance was first seen, and when its attachment became available,
+    //  kept for `AtlasConfig::resolution_history_retention` seconds.  Unlike
+    //  attachment_instances, rows are not replaced when an instance is queued again, and are
+    //  kept after the instance is evicted.
+    r#"
+    CREATE TABLE attachment_resolutions(
+        index_block_hash TEXT NOT NULL,
+        contract_id TEXT NOT NULL,
+        attachment_index INTEGER NOT NULL,
+        content_hash TEXT NOT NULL,
+        first_seen_at INTEGER NOT NULL,
+        resolved_at INTEGER,
+        PRIMARY KEY(index_block_hash, contract_id, attachment_index)
+    );"#,
+    "CREATE INDEX IF NOT EXISTS index_resolutions_contract ON attachment_resolutions(contract_id, first_seen_at);",
+    "CREATE INDEX IF NOT EXISTS index_resolutions_unresolved ON attachment_resolutions(content_hash, resolved_at);",
+    "INSERT INTO db_config (version) VALUES ('7');",
 ];
 
 const ATLASDB_INDEXES: &'static [&'static str] = &[
     "CREATE INDEX IF NOT EXISTS index_was_instantiated ON attachments(was_instantiated);",
     "CREATE INDEX IF NOT EXISTS index_instance_status ON attachment_instances(status);",
+    "CREATE INDEX IF NOT EXISTS index_instance_contract_height ON attachment_instances(contract_id, block_height);",
 ];
 
 /// Attachment instances pass through different states once written to the AtlasDB.
@@ -132,6 +215,27 @@ impl FromRow<Attachment> for Attachment {
     }
 }
 
+/// A row of the `attachments` table, whose content may be in the blob store
+struct StoredAttachmentRow {
+    hash: Hash160,
+    content: Vec<u8>,
+    storage: AttachmentStorage,
+}
+
+impl FromRow<StoredAttachmentRow> for StoredAttachmentRow {
+    fn from_row<'a>(row: &'a Row) -> Result<StoredAttachmentRow, db_error> {
+        let hex_hash: String = row.get_unwrap("hash");
+        let hash = Hash160::from_hex(&hex_hash).map_err(|_| db_error::TypeError)?;
+        let content: Vec<u8> = row.get_unwrap("content");
+        let storage: AttachmentStorage = row.get_unwrap("storage");
+        Ok(StoredAttachmentRow {
+            hash,
+            content,
+            storage,
+        })
+    }
+}
+
 impl FromRow<AttachmentInstance> for AttachmentInstance {
     fn from_row<'a>(row: &'a Row) -> Result<AttachmentInstance, db_error> {
         let hex_content_hash: String = row.get_unwrap("content_hash");
@@ -144,6 +248,8 @@ impl FromRow<AttachmentInstance> for AttachmentInstance {
         let contract_id = QualifiedContractIdentifier::from_column(row, "contract_id")?;
         let hex_tx_id: String = row.get_unwrap("tx_id");
         let tx_id = Txid::from_hex(&hex_tx_id).map_err(|_| db_error::TypeError)?;
+        let origin: String = row.get_unwrap("origin");
+        let origin = AttachmentOrigin::from_name(&origin).ok_or(db_error::TypeError)?;
 
         Ok(AttachmentInstance {
             content_hash,
@@ -154,6 +260,7 @@ impl FromRow<AttachmentInstance> for AttachmentInstance {
             contract_id,
             tx_id,
             canonical_stacks_tip_height: None,
+            origin,
         })
     }
 }
@@ -166,6 +273,22 @@ impl FromRow<(u32, u32)> for (u32, u32) {
     }
 }
 
+impl FromRow<(UrlString, ReliabilityReport)> for (UrlString, ReliabilityReport) {
+    fn from_row<'a>(row: &'a Row) -> Result<(UrlString, ReliabilityReport), db_error> {
+        let data_url: String = row.get_unwrap("data_url");
+        let data_url = UrlString::try_from(data_url).map_err(|_| db_error::ParseError)?;
+        let avg_latency_ms: Option<u64> = u64::from_column(row, "avg_latency_ms")?;
+        let avg_throughput_bps: Option<u64> = u64::from_column(row, "avg_throughput_bps")?;
+        let report = ReliabilityReport {
+            total_requests_sent: row.get_unwrap("total_requests_sent"),
+            total_requests_success: row.get_unwrap("total_requests_success"),
+            avg_latency_ms,
+            avg_throughput_bps,
+        };
+        Ok((data_url, report))
+    }
+}
+
 impl ToSql for AttachmentInstanceStatus {
     fn to_sql(&self) -> Result<ToSqlOutput<'_>, rusqlite::Error> {
         let integer_rep: i64 = match self {
@@ -192,6 +315,8 @@ pub struct AtlasDB {
     pub atlas_config: AtlasConfig,
     pub conn: Connection,
     pub readwrite: bool,
+    /// Where attachment content is stored outside of the DB, if anywhere
+    pub blob_store: Option<AttachmentBlobStore>,
 }
 
 impl AtlasDB {
@@ -216,33 +341,20 @@ impl AtlasDB {
 
     fn instantiate(&mut self) -> Result<(), db_error> {
         let genesis_attachments = self.atlas_config.genesis_attachments.take();
+        let blob_store = self.target_blob_store();
 
         let tx = self.tx_begin()?;
 
         for row_text in ATLASDB_INITIAL_SCHEMA {
             tx.execute_batch(row_text)?;
         }
-        for row_text in ATLASDB_SCHEMA_2 {
-            tx.execute_batch(row_text)?;
-        }
-
-        tx.execute(
-            "INSERT INTO db_config (version) VALUES (?1)",
-            &[&ATLASDB_VERSION],
-        )?;
+        tx.execute("INSERT INTO db_config (version) VALUES (?1)", &["1"])?;
+        AtlasDB::apply_schema_migrations(&tx)?;
 
         if let Some(attachments) = genesis_attachments {
             let now = util::get_epoch_time_secs() as i64;
             for attachment in attachments {
-                tx.execute(
-                    "INSERT INTO attachments (hash, content, was_instantiated, created_at) VALUES (?, ?, 1, ?)",
-                    &[
-                        &attachment.hash() as &dyn ToSql,
-                        &attachment.content as &dyn ToSql,
-                        &now as &dyn ToSql,
-                    ],
-                )
-                .map_err(db_error::SqliteError)?;
+                AtlasDB::insert_attachment_row(&tx, blob_store.as_ref(), &attachment, true, now)?;
             }
         }
 
@@ -252,6 +364,199 @@ impl AtlasDB {
         Ok(())
     }
 
+    /// The blob store to put new attachment content in, if it does not go in the DB
+    fn target_blob_store(&self) -> Option<AttachmentBlobStore> {
+        match self.atlas_config.attachment_storage {
+            AttachmentStorage::Sqlite => None,
+            AttachmentStorage::Filesystem => self.blob_store.clone(),
+        }
+    }
+
+    /// Insert or replace the `attachments` row of `attachment`.  Its content goes in
+    /// `blob_store` if given, and in the row otherwise.
+    fn insert_attachment_row(
+        tx: &Transaction,
+        blob_store: Option<&AttachmentBlobStore>,
+        attachment: &Attachment,
+        was_instantiated: bool,
+        now: i64,
+    ) -> Result<(), db_error> {
+        let (content, storage) = match blob_store {
+            Some(blob_store) => {
+                blob_store.put(attachment)?;
+                (&[][..], AttachmentStorage::Filesystem)
+            }
+            None => (&attachment.content[..], AttachmentStorage::Sqlite),
+        };
+        tx.execute(
+            "INSERT OR REPLACE INTO attachments (hash, content, was_instantiated, created_at, storage) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![&attachment.hash(), content, &was_instantiated, &now, &storage],
+        )?;
+        Ok(())
+    }
+
+    /// Load the content of an `attachments` row.
+    /// Content in the blob store is checked against its hash: if it is missing or does not
+    /// match, this returns Ok(None), just as if the attachment were not stored.
+    fn load_attachment(&self, row: StoredAttachmentRow) -> Result<Option<Attachment>, db_error> {
+        if row.storage == AttachmentStorage::Sqlite {
+            return Ok(Some(Attachment::new(row.content)));
+        }
+        let blob_store = match self.blob_store.as_ref() {
+            Some(blob_store) => blob_store,
+            None => {
+                warn!("Atlas: attachment is in a blob store, but this AtlasDB has none";
+                      "hash" => %row.hash);
+                return Ok(None);
+            }
+        };
+        match blob_store.get(&row.hash) {
+            Ok(Some(attachment)) => Ok(Some(attachment)),
+            Ok(None) => {
+                warn!("Atlas: attachment blob is missing"; "hash" => %row.hash);
+                Ok(None)
+            }
+            Err(db_error::Corruption) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read the content stored in the blob store under `content_hash`, without checking it.
+    /// Returns Ok(None) if there is no such blob, or no blob store.
+    pub fn read_attachment_blob(
+        &self,
+        content_hash: &Hash160,
+    ) -> Result<Option<Vec<u8>>, db_error> {
+        match self.blob_store.as_ref() {
+            Some(blob_store) => blob_store.read(content_hash),
+            None => Ok(None),
+        }
+    }
+
+    /// Delete the blobs of the given hex-encoded hashes from the blob store
+    fn delete_attachment_blobs(&self, hex_hashes: &[String]) -> Result<(), db_error> {
+        let blob_store = match self.blob_store.as_ref() {
+            Some(blob_store) => blob_store,
+            None => return Ok(()),
+        };
+        for hex_hash in hex_hashes.iter() {
+            if let Ok(hash) = Hash160::from_hex(hex_hash) {
+                blob_store.delete(&hash)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The storage that every attachment was last moved to, if a migration has completed
+    fn get_migrated_storage(&self) -> Result<Option<AttachmentStorage>, db_error> {
+        self.conn
+            .query_row(
+                "SELECT storage FROM attachment_storage_state",
+                NO_PARAMS,
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_error::from)
+    }
+
+    /// Move every attachment that is not stored where `AtlasConfig::attachment_storage` says
+    /// to store it.  This only does any work if the configured storage has changed since the
+    /// last completed migration.  It runs a batch of attachments per transaction, so an
+    /// interrupted migration picks up where it left off the next time the DB is opened.
+    /// An attachment whose content cannot be read back intact is kept, but marked as missing,
+    /// and its instances are marked unavailable so that the downloader fetches it again.
+    /// Returns the number of attachments moved.
+    pub fn migrate_attachment_storage(&mut self) -> Result<u64, db_error> {
+        let target_storage = match (self.atlas_config.attachment_storage, &self.blob_store) {
+            (AttachmentStorage::Filesystem, None) => {
+                warn!("Atlas: no blob store for this AtlasDB; attachments stay in the DB");
+                AttachmentStorage::Sqlite
+            }
+            (storage, _) => storage,
+        };
+        if self.get_migrated_storage()? == Some(target_storage) {
+            return Ok(0);
+        }
+        let blob_store = self.blob_store.clone();
+        let mut moved = 0;
+        loop {
+            let rows: Vec<StoredAttachmentRow> = query_rows(
+                &self.conn,
+                "SELECT hash, content, storage FROM attachments WHERE storage != ?1 AND content_missing = 0 LIMIT ?2",
+                rusqlite::params![&target_storage, &STORAGE_MIGRATION_BATCH_SIZE],
+            )?;
+            if rows.is_empty() {
+                break;
+            }
+
+            let mut moved_blobs = vec![];
+            let tx = self.tx_begin()?;
+            for row in rows.into_iter() {
+                let attachment = match (row.storage, blob_store.as_ref()) {
+                    (AttachmentStorage::Sqlite, _) => Some(Attachment::new(row.content)),
+                    (AttachmentStorage::Filesystem, Some(blob_store)) => {
+                        match blob_store.get(&row.hash) {
+                            Err(db_error::Corruption) => None,
+                            res => res?,
+                        }
+                    }
+                    (AttachmentStorage::Filesystem, None) => None,
+                };
+                let attachment = match attachment {
+                    Some(attachment) if attachment.hash() == row.hash => attachment,
+                    _ => {
+                        warn!("Atlas: attachment content is missing or corrupt; it will be downloaded again";
+                              "hash" => %row.hash);
+                        tx.execute(
+                            "UPDATE attachments SET content_missing = 1 WHERE hash = ?1",
+                            &[&row.hash as &dyn ToSql],
+                        )?;
+                        tx.execute(
+                            "UPDATE attachment_instances SET is_available = 0 WHERE content_hash = ?1",
+                            &[&row.hash as &dyn ToSql],
+                        )?;
+                        continue;
+                    }
+                };
+                let content = match (target_storage, blob_store.as_ref()) {
+                    (AttachmentStorage::Filesystem, Some(blob_store)) => {
+                        blob_store.put(&attachment)?;
+                        vec![]
+                    }
+                    _ => {
+                        moved_blobs.push(to_hex(&row.hash.0[..]));
+                        attachment.content
+                    }
+                };
+                tx.execute(
+                    "UPDATE attachments SET content = ?1, storage = ?2 WHERE hash = ?3",
+                    rusqlite::params![&content, &target_storage, &row.hash],
+                )?;
+                moved += 1;
+            }
+            tx.commit()?;
+            // only drop blobs once the DB no longer refers to them
+            self.delete_attachment_blobs(&moved_blobs)?;
+        }
+
+        let tx = self.tx_begin()?;
+        tx.execute("DELETE FROM attachment_storage_state", NO_PARAMS)?;
+        tx.execute(
+            "INSERT INTO attachment_storage_state (storage) VALUES (?1)",
+            &[&target_storage as &dyn ToSql],
+        )?;
+        tx.commit()?;
+
+        if moved > 0 {
+            info!(
+                "Atlas: moved {} attachments to {} storage",
+                moved,
+                target_storage.as_str()
+            );
+        }
+        Ok(moved)
+    }
+
     pub fn should_keep_attachment(
         &self,
         contract_id: &QualifiedContractIdentifier,
@@ -296,13 +601,15 @@ impl AtlasDB {
             }
         };
         let conn = sqlite_open(path, open_flags, false)?;
-        Self::check_instantiate_db(atlas_config, conn, readwrite, create_flag)
+        let blob_store = AttachmentBlobStore::for_db_path(path);
+        Self::check_instantiate_db(atlas_config, conn, Some(blob_store), readwrite, create_flag)
     }
 
     /// Inner method for instantiating the db if necessary, updating the schema, or adding indexes
     fn check_instantiate_db(
         atlas_config: AtlasConfig,
         conn: Connection,
+        blob_store: Option<AttachmentBlobStore>,
         readwrite: bool,
         create_flag: bool,
     ) -> Result<AtlasDB, db_error> {
@@ -310,6 +617,7 @@ impl AtlasDB {
             atlas_config,
             conn,
             readwrite,
+            blob_store,
         };
         if create_flag {
             db.instantiate()?;
@@ -317,6 +625,7 @@ impl AtlasDB {
         if readwrite {
             db.check_schema_version_and_update()?;
             db.add_indexes()?;
+            db.migrate_attachment_storage()?;
         } else {
             db.check_schema_version_or_error()?;
         }
@@ -341,39 +650,103 @@ impl AtlasDB {
         }
     }
 
-    fn apply_schema_2(db_conn: &Connection) -> Result<(), db_error> {
+    fn apply_schema_2(tx: &Transaction) -> Result<(), db_error> {
+        test_debug!("Apply schema 2 to Atlas DB");
         for row_text in ATLASDB_SCHEMA_2 {
-            db_conn.execute_batch(row_text)?;
+            tx.execute_batch(row_text)?;
         }
+        Ok(())
+    }
 
-        db_conn.execute(
-            "INSERT OR REPLACE INTO db_config (version) VALUES (?1)",
-            &["2"],
-        )?;
+    fn apply_schema_3(tx: &Transaction) -> Result<(), db_error> {
+        test_debug!("Apply schema 3 to Atlas DB");
+        for row_text in ATLASDB_SCHEMA_3 {
+            tx.execute_batch(row_text)?;
+        }
+        Ok(())
+    }
 
+    fn apply_schema_4(tx: &Transaction) -> Result<(), db_error> {
+        test_debug!("Apply schema 4 to Atlas DB");
+        for row_text in ATLASDB_SCHEMA_4 {
+            tx.execute_batch(row_text)?;
+        }
         Ok(())
     }
 
-    fn check_schema_version_and_update(&mut self) -> Result<(), db_error> {
-        let tx = self.tx_begin()?;
-        match AtlasDB::get_schema_version(&tx) {
-            Ok(version) => {
-                let expected_version = ATLASDB_VERSION.to_string();
-                if version == expected_version {
-                    return Ok(());
-                }
-                if version == "1" {
-                    Self::apply_schema_2(&tx)?;
-                    tx.commit()?;
-                    Ok(())
-                } else {
-                    panic!("The schema version of the Atlas DB is invalid.")
+    fn apply_schema_5(tx: &Transaction) -> Result<(), db_error> {
+        test_debug!("Apply schema 5 to Atlas DB");
+        for row_text in ATLASDB_SCHEMA_5 {
+            tx.execute_batch(row_text)?;
+        }
+        Ok(())
+    }
+
+    fn apply_schema_6(tx: &Transaction) -> Result<(), db_error> {
+        test_debug!("Apply schema 6 to Atlas DB");
+        for row_text in ATLASDB_SCHEMA_6 {
+            tx.execute_batch(row_text)?;
+        }
+        Ok(())
+    }
+
+    fn apply_schema_7(tx: &Transaction) -> Result<(), db_error> {
+        test_debug!("Apply schema 7 to Atlas DB");
+        for row_text in ATLASDB_SCHEMA_7 {
+            tx.execute_batch(row_text)?;
+        }
+        Ok(())
+    }
+
+    /// Apply each schema migration in turn until the DB is at `ATLASDB_VERSION`.
+    /// Every migration records the version it brings the DB to in `db_config`.
+    /// Returns the version the DB was at before migrating.
+    fn apply_schema_migrations(tx: &Transaction) -> Result<String, db_error> {
+        test_debug!("Apply any schema migrations to Atlas DB");
+        let expected_version = ATLASDB_VERSION.to_string();
+        let mut ret = None;
+        loop {
+            match AtlasDB::get_schema_version(tx) {
+                Ok(version) => {
+                    if ret.is_none() {
+                        ret = Some(version.clone());
+                    }
+                    if version == "1" {
+                        AtlasDB::apply_schema_2(tx)?;
+                    } else if version == "2" {
+                        AtlasDB::apply_schema_3(tx)?;
+                    } else if version == "3" {
+                        AtlasDB::apply_schema_4(tx)?;
+                    } else if version == "4" {
+                        AtlasDB::apply_schema_5(tx)?;
+                    } else if version == "5" {
+                        AtlasDB::apply_schema_6(tx)?;
+                    } else if version == "6" {
+                        AtlasDB::apply_schema_7(tx)?;
+                    } else if version == expected_version {
+                        return Ok(ret.expect("unreachable"));
+                    } else {
+                        panic!("The schema version of the Atlas DB is invalid.")
+                    }
                 }
+                Err(e) => panic!("Error obtaining the version of the Atlas DB: {:?}", e),
             }
-            Err(e) => panic!("Error obtaining the version of the Atlas DB: {:?}", e),
         }
     }
 
+    fn check_schema_version_and_update(&mut self) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+        let version = AtlasDB::apply_schema_migrations(&tx)?;
+        tx.commit()?;
+        if version != ATLASDB_VERSION {
+            info!(
+                "Migrated Atlas DB from schema version {} to {}",
+                &version, ATLASDB_VERSION
+            );
+        }
+        Ok(())
+    }
+
     // Open an atlas database in memory (used for testing)
     #[cfg(test)]
     pub fn connect_memory(atlas_config: AtlasConfig) -> Result<AtlasDB, db_error> {
@@ -382,12 +755,24 @@ impl AtlasDB {
             atlas_config,
             conn,
             readwrite: true,
+            blob_store: None,
         };
 
         db.instantiate()?;
         Ok(db)
     }
 
+    // Open an atlas database in memory, whose blob store is in `blob_store_root` (used for testing)
+    #[cfg(test)]
+    pub fn connect_memory_with_blob_store(
+        atlas_config: AtlasConfig,
+        blob_store_root: std::path::PathBuf,
+    ) -> Result<AtlasDB, db_error> {
+        let conn = Connection::open_in_memory()?;
+        let blob_store = AttachmentBlobStore::new(blob_store_root);
+        Self::check_instantiate_db(atlas_config, conn, Some(blob_store), true, true)
+    }
+
     #[cfg(test)]
     /// Only ever to be used in testing, open and instantiate a V1 atlasdb
     pub fn connect_memory_db_v1(atlas_config: AtlasConfig) -> Result<AtlasDB, db_error> {
@@ -396,6 +781,7 @@ impl AtlasDB {
             atlas_config,
             conn,
             readwrite: true,
+            blob_store: None,
         };
 
         let genesis_attachments = db.atlas_config.genesis_attachments.take();
@@ -439,7 +825,19 @@ impl AtlasDB {
         atlas_config: AtlasConfig,
         conn: Connection,
     ) -> Result<AtlasDB, db_error> {
-        Self::check_instantiate_db(atlas_config, conn, true, false)
+        Self::check_instantiate_db(atlas_config, conn, None, true, false)
+    }
+
+    #[cfg(test)]
+    /// Only ever to be used in testing, connect to db using an existing sqlconn, whose blob
+    /// store is in `blob_store_root`
+    pub fn connect_with_sqlconn_and_blob_store(
+        atlas_config: AtlasConfig,
+        conn: Connection,
+        blob_store_root: std::path::PathBuf,
+    ) -> Result<AtlasDB, db_error> {
+        let blob_store = AttachmentBlobStore::new(blob_store_root);
+        Self::check_instantiate_db(atlas_config, conn, Some(blob_store), true, false)
     }
 
     pub fn conn(&self) -> &Connection {
@@ -525,43 +923,46 @@ impl AtlasDB {
             self.evict_k_oldest_uninstantiated_attachments(to_delete)?;
         }
 
+        let blob_store = self.target_blob_store();
         let tx = self.tx_begin()?;
         let now = util::get_epoch_time_secs() as i64;
-        let res = tx.execute(
-            "INSERT OR REPLACE INTO attachments (hash, content, was_instantiated, created_at) VALUES (?, ?, 0, ?)",
-            &[
-                &attachment.hash() as &dyn ToSql,
-                &attachment.content as &dyn ToSql,
-                &now as &dyn ToSql,
-            ],
-        );
-        res.map_err(db_error::SqliteError)?;
+        AtlasDB::insert_attachment_row(&tx, blob_store.as_ref(), attachment, false, now)?;
         tx.commit().map_err(db_error::SqliteError)?;
         Ok(())
     }
 
     pub fn evict_k_oldest_uninstantiated_attachments(&mut self, k: u32) -> Result<(), db_error> {
         let tx = self.tx_begin()?;
+        let blob_hashes: Vec<String> = query_rows(
+            &tx,
+            "SELECT hash FROM attachments WHERE storage = ?1 AND hash IN (SELECT hash FROM attachments WHERE was_instantiated = 0 ORDER BY created_at ASC LIMIT ?2)",
+            rusqlite::params![&AttachmentStorage::Filesystem, &k],
+        )?;
         let res = tx.execute(
             "DELETE FROM attachments WHERE hash IN (SELECT hash FROM attachments WHERE was_instantiated = 0 ORDER BY created_at ASC LIMIT ?)",
             &[&k as &dyn ToSql],
         );
         res.map_err(db_error::SqliteError)?;
         tx.commit().map_err(db_error::SqliteError)?;
-        Ok(())
+        self.delete_attachment_blobs(&blob_hashes)
     }
 
     pub fn evict_expired_uninstantiated_attachments(&mut self) -> Result<(), db_error> {
         let now = util::get_epoch_time_secs() as i64;
         let cut_off = now - self.atlas_config.uninstantiated_attachments_expire_after as i64;
         let tx = self.tx_begin()?;
+        let blob_hashes: Vec<String> = query_rows(
+            &tx,
+            "SELECT hash FROM attachments WHERE was_instantiated = 0 AND created_at < ?1 AND storage = ?2",
+            rusqlite::params![&cut_off, &AttachmentStorage::Filesystem],
+        )?;
         let res = tx.execute(
             "DELETE FROM attachments WHERE was_instantiated = 0 AND created_at < ?",
             &[&cut_off as &dyn ToSql],
         );
         res.map_err(db_error::SqliteError)?;
         tx.commit().map_err(db_error::SqliteError)?;
-        Ok(())
+        self.delete_attachment_blobs(&blob_hashes)
     }
 
     pub fn count_uninstantiated_attachments(&self) -> Result<u32, db_error> {
@@ -583,15 +984,17 @@ impl AtlasDB {
         attachment: &Attachment,
     ) -> Result<(), db_error> {
         let now = util::get_epoch_time_secs() as i64;
+        let blob_store = self.target_blob_store();
         let tx = self.tx_begin()?;
-        tx.execute(
-            "INSERT OR REPLACE INTO attachments (hash, content, was_instantiated, created_at) VALUES (?, ?, 1, ?)",
-            rusqlite::params![&attachment.hash(), &attachment.content, &now],
-        )?;
+        AtlasDB::insert_attachment_row(&tx, blob_store.as_ref(), attachment, true, now)?;
         tx.execute(
             "UPDATE attachment_instances SET is_available = 1 WHERE content_hash = ?1 AND status = ?2",
             rusqlite::params![&attachment.hash(), &AttachmentInstanceStatus::Checked],
         )?;
+        tx.execute(
+            "UPDATE attachment_resolutions SET resolved_at = ?1 WHERE content_hash = ?2 AND resolved_at IS NULL",
+            rusqlite::params![&now, &attachment.hash()],
+        )?;
         tx.commit()?;
         Ok(())
     }
@@ -601,11 +1004,13 @@ impl AtlasDB {
         content_hash: &Hash160,
     ) -> Result<Option<Attachment>, db_error> {
         let hex_content_hash = to_hex(&content_hash.0[..]);
-        let qry = "SELECT content, hash FROM attachments WHERE hash = ?1 AND was_instantiated = 0"
+        let qry = "SELECT content, hash, storage FROM attachments WHERE hash = ?1 AND was_instantiated = 0 AND content_missing = 0"
             .to_string();
         let args = [&hex_content_hash as &dyn ToSql];
-        let row = query_row::<Attachment, _>(&self.conn, &qry, &args)?;
-        Ok(row)
+        match query_row::<StoredAttachmentRow, _>(&self.conn, &qry, &args)? {
+            Some(row) => self.load_attachment(row),
+            None => Ok(None),
+        }
     }
 
     pub fn evict_expired_unresolved_attachment_instances(&mut self) -> Result<(), db_error> {
@@ -632,6 +1037,19 @@ impl AtlasDB {
         Ok(rows)
     }
 
+    /// List the checked attachment instances of `contract_id` whose attachment we do not have yet
+    pub fn find_unresolved_attachment_instances_by_contract(
+        &self,
+        contract_id: &QualifiedContractIdentifier,
+    ) -> Result<Vec<AttachmentInstance>, db_error> {
+        let qry = "SELECT * FROM attachment_instances
+                   WHERE contract_id = ?1 AND is_available = 0 AND status = ?2
+                   ORDER BY block_height ASC, attachment_index ASC";
+        let args = rusqlite::params![&contract_id.to_string(), &AttachmentInstanceStatus::Checked];
+        let rows = query_rows(&self.conn, qry, args)?;
+        Ok(rows)
+    }
+
     pub fn find_all_attachment_instances(
         &self,
         content_hash: &Hash160,
@@ -643,13 +1061,83 @@ impl AtlasDB {
         Ok(rows)
     }
 
+    /// List the checked attachment instances of `contract_id` whose Stacks block height is within
+    /// `[start_height, end_height]`, ordered by block height and then attachment index.
+    /// Returns at most `limit` instances, skipping the first `offset` of them.
+    pub fn find_attachment_instances_by_contract(
+        &self,
+        contract_id: &QualifiedContractIdentifier,
+        start_height: u64,
+        end_height: u64,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<AttachmentInstance>, db_error> {
+        let qry = "SELECT * FROM attachment_instances
+                   WHERE contract_id = ?1 AND block_height >= ?2 AND block_height <= ?3 AND status = ?4
+                   ORDER BY block_height ASC, attachment_index ASC, index_block_hash ASC
+                   LIMIT ?5 OFFSET ?6";
+        let args = rusqlite::params![
+            &contract_id.to_string(),
+            &u64_to_sql(start_height)?,
+            &u64_to_sql(end_height)?,
+            &AttachmentInstanceStatus::Checked,
+            &limit,
+            &offset,
+        ];
+        let rows = query_rows(&self.conn, qry, args)?;
+        Ok(rows)
+    }
+
     pub fn find_attachment(&self, content_hash: &Hash160) -> Result<Option<Attachment>, db_error> {
         let hex_content_hash = to_hex(&content_hash.0[..]);
-        let qry = "SELECT content, hash FROM attachments WHERE hash = ?1 AND was_instantiated = 1"
+        let qry = "SELECT content, hash, storage FROM attachments WHERE hash = ?1 AND was_instantiated = 1 AND content_missing = 0"
             .to_string();
         let args = [&hex_content_hash as &dyn ToSql];
-        let row = query_row::<Attachment, _>(&self.conn, &qry, &args)?;
-        Ok(row)
+        match query_row::<StoredAttachmentRow, _>(&self.conn, &qry, &args)? {
+            Some(row) => self.load_attachment(row),
+            None => Ok(None),
+        }
+    }
+
+    /// Do we have a validated attachment stored under `content_hash`?
+    pub fn has_instantiated_attachment(&self, content_hash: &Hash160) -> Result<bool, db_error> {
+        let qry = "SELECT COUNT(rowid) FROM attachments WHERE hash = ?1 AND was_instantiated = 1 AND content_missing = 0";
+        let count = query_count(&self.conn, qry, &[content_hash as &dyn ToSql])?;
+        Ok(count > 0)
+    }
+
+    /// Delete the attachment stored under `content_hash`, if any
+    pub fn delete_attachment(&mut self, content_hash: &Hash160) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+        tx.execute(
+            "DELETE FROM attachments WHERE hash = ?1",
+            &[content_hash as &dyn ToSql],
+        )?;
+        tx.commit()?;
+        if let Some(blob_store) = self.blob_store.as_ref() {
+            blob_store.delete(content_hash)?;
+        }
+        Ok(())
+    }
+
+    /// List the distinct attachment indexes of the checked instances of `contract_id`, in
+    /// ascending order
+    pub fn get_attachment_indexes(
+        &self,
+        contract_id: &QualifiedContractIdentifier,
+    ) -> Result<Vec<u32>, db_error> {
+        let qry = "SELECT DISTINCT attachment_index FROM attachment_instances
+                   WHERE contract_id = ?1 AND status = ?2
+                   ORDER BY attachment_index ASC";
+        let args = rusqlite::params![&contract_id.to_string(), &AttachmentInstanceStatus::Checked];
+        let mut stmt = self.conn.prepare(qry)?;
+        let mut rows = stmt.query(args)?;
+        let mut indexes = vec![];
+        while let Some(row) = rows.next()? {
+            let index: u32 = row.get(0)?;
+            indexes.push(index);
+        }
+        Ok(indexes)
     }
 
     /// Queue a new attachment instance, status will be set to "queued",
@@ -686,12 +1174,14 @@ impl AtlasDB {
     }
 
     /// Update a queued attachment to "checked", setting the `is_available` field.
+    /// An instance found to be available is resolved as of now.
     pub fn mark_attachment_instance_checked(
         &mut self,
         attachment: &AttachmentInstance,
         is_available: bool,
     ) -> Result<(), db_error> {
-        self.conn.execute(
+        let tx = self.tx_begin()?;
+        tx.execute(
             "UPDATE attachment_instances SET status = ?1, is_available = ?2
               WHERE index_block_hash = ?3 AND contract_id = ?4 AND attachment_index = ?5",
             rusqlite::params![
@@ -702,24 +1192,147 @@ impl AtlasDB {
                 &attachment.attachment_index,
             ],
         )?;
+        if is_available {
+            let now = util::get_epoch_time_secs() as i64;
+            tx.execute(
+                "UPDATE attachment_resolutions SET resolved_at = ?1
+                  WHERE index_block_hash = ?2 AND contract_id = ?3 AND attachment_index = ?4 AND resolved_at IS NULL",
+                rusqlite::params![
+                    &now,
+                    &attachment.index_block_hash,
+                    &attachment.contract_id.to_string(),
+                    &attachment.attachment_index,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Set the given attachment instances of the Stacks block `index_block_hash` back to "queued",
+    /// so that the AtlasDownloader checks them again on a later pass.
+    /// This is how the AtlasDownloader hands back batches it has no room for.
+    pub fn requeue_attachment_instances(
+        &mut self,
+        index_block_hash: &StacksBlockId,
+        instances: &[(QualifiedContractIdentifier, u32)],
+    ) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+        for (contract_id, attachment_index) in instances.iter() {
+            tx.execute(
+                "UPDATE attachment_instances SET status = ?1
+                  WHERE index_block_hash = ?2 AND contract_id = ?3 AND attachment_index = ?4",
+                rusqlite::params![
+                    &AttachmentInstanceStatus::Queued,
+                    index_block_hash,
+                    &contract_id.to_string(),
+                    attachment_index,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Load the attachments downloader's reliability report for each peer
+    pub fn get_reliability_reports(
+        &self,
+    ) -> Result<HashMap<UrlString, ReliabilityReport>, db_error> {
+        let rows: Vec<(UrlString, ReliabilityReport)> = query_rows(
+            &self.conn,
+            "SELECT data_url, total_requests_sent, total_requests_success, avg_latency_ms, avg_throughput_bps
+             FROM peer_reliability_reports",
+            NO_PARAMS,
+        )?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Store the attachments downloader's reliability reports for the given peers, replacing
+    /// the ones stored before
+    pub fn store_reliability_reports<'a, I>(&mut self, reports: I) -> Result<(), db_error>
+    where
+        I: IntoIterator<Item = (&'a UrlString, &'a ReliabilityReport)>,
+    {
+        let now = util::get_epoch_time_secs() as i64;
+        let tx = self.tx_begin()?;
+        for (data_url, report) in reports {
+            tx.execute(
+                "INSERT OR REPLACE INTO peer_reliability_reports
+                    (data_url, total_requests_sent, total_requests_success, avg_latency_ms, avg_throughput_bps, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![
+                    data_url.as_str(),
+                    &report.total_requests_sent,
+                    &report.total_requests_success,
+                    &opt_u64_to_sql(report.avg_latency_ms)?,
+                    &opt_u64_to_sql(report.avg_throughput_bps)?,
+                    &now,
+                ],
+            )?;
+        }
+        tx.commit()?;
         Ok(())
     }
 
-    /// Insert an attachment instance.
+    /// Delete the record of the attachment instances first seen longer than
+    /// `resolution_history_retention` seconds ago
+    pub fn evict_expired_attachment_resolutions(&mut self) -> Result<(), db_error> {
+        let now = util::get_epoch_time_secs() as i64;
+        let cut_off = now - self.atlas_config.resolution_history_retention as i64;
+        let tx = self.tx_begin()?;
+        tx.execute(
+            "DELETE FROM attachment_resolutions WHERE first_seen_at < ?1",
+            &[&cut_off as &dyn ToSql],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Summarize how long the recorded attachment instances of `contract_id` took to resolve,
+    /// from when each was first seen to when its attachment was available
+    pub fn get_attachment_resolution_latency(
+        &self,
+        contract_id: &QualifiedContractIdentifier,
+    ) -> Result<GetAttachmentResolutionLatencyResponse, db_error> {
+        let latencies: Vec<i64> = query_rows(
+            &self.conn,
+            "SELECT MAX(resolved_at - first_seen_at, 0) FROM attachment_resolutions
+              WHERE contract_id = ?1 AND resolved_at IS NOT NULL
+              ORDER BY 1 ASC",
+            &[&contract_id.to_string()],
+        )?;
+        let latencies: Vec<u64> = latencies.into_iter().map(|l| l as u64).collect();
+        let unresolved = query_count(
+            &self.conn,
+            "SELECT COUNT(*) FROM attachment_resolutions WHERE contract_id = ?1 AND resolved_at IS NULL",
+            &[&contract_id.to_string()],
+        )?;
+        Ok(GetAttachmentResolutionLatencyResponse {
+            history_secs: self.atlas_config.resolution_history_retention,
+            resolved: latencies.len() as u64,
+            unresolved: unresolved as u64,
+            latency_secs: AttachmentResolutionPercentiles::from_sorted_latencies(&latencies),
+        })
+    }
+
+    /// Insert an attachment instance.  Newly queued instances are recorded as first seen now,
+    /// unless the record is disabled.
     fn insert_attachment_instance(
         &mut self,
         attachment: &AttachmentInstance,
         status: AttachmentInstanceStatus,
         is_available: bool,
     ) -> Result<(), db_error> {
+        let record_resolution = matches!(status, AttachmentInstanceStatus::Queued)
+            && self.atlas_config.resolution_history_retention > 0;
         let sql_tx = self.tx_begin()?;
         let now = util::get_epoch_time_secs() as i64;
         sql_tx.execute(
             "INSERT OR REPLACE INTO attachment_instances (
                content_hash, created_at, index_block_hash,
                attachment_index, block_height, is_available,
-                metadata, contract_id, tx_id, status)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                metadata, contract_id, tx_id, status, origin)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
             rusqlite::params![
                 &attachment.content_hash,
                 &now,
@@ -730,9 +1343,24 @@ impl AtlasDB {
                 &attachment.metadata,
                 &attachment.contract_id.to_string(),
                 &attachment.tx_id,
-                &status
+                &status,
+                &attachment.origin.to_string()
             ],
         )?;
+        if record_resolution {
+            sql_tx.execute(
+                "INSERT OR IGNORE INTO attachment_resolutions
+                   (index_block_hash, contract_id, attachment_index, content_hash, first_seen_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![
+                    &attachment.index_block_hash,
+                    &attachment.contract_id.to_string(),
+                    &attachment.attachment_index,
+                    &attachment.content_hash,
+                    &now,
+                ],
+            )?;
+        }
         sql_tx.commit()?;
         Ok(())
     }
@@ -16,10 +16,11 @@
 
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
-use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
 use std::hash::{Hash, Hasher};
 use std::net::{IpAddr, SocketAddr};
-use std::{cmp, fmt};
+use std::time::{Duration, Instant};
+use std::{cmp, fmt, mem};
 
 use clarity::vm::types::QualifiedContractIdentifier;
 use rand::{thread_rng, Rng};
@@ -27,16 +28,28 @@ use stacks_common::types::chainstate::{BlockHeaderHash, StacksBlockId};
 use stacks_common::util::hash::{Hash160, MerkleHashFunc};
 use stacks_common::util::{get_epoch_time_ms, get_epoch_time_secs};
 
-use super::{AtlasDB, Attachment, AttachmentInstance, MAX_ATTACHMENT_INV_PAGES_PER_REQUEST};
+use super::{
+    AtlasDB, Attachment, AttachmentBinding, AttachmentInstance, AttachmentOrigin, AttachmentPage,
+    ATLAS_DATA_URL_HEADER, ATTACHMENTS_MAX_SIZE_MIN, MAX_ATTACHMENT_INV_PAGES_PER_REQUEST,
+};
 use crate::chainstate::burn::ConsensusHash;
-use crate::net::atlas::{GetAttachmentResponse, GetAttachmentsInvResponse, MAX_RETRY_DELAY};
+use crate::monitoring;
+use crate::net::atlas::rate_limit::ATLAS_RATE_LIMIT_WINDOW_SECS;
+use crate::net::atlas::{
+    GetAttachmentChunkResponse, GetAttachmentResponse, GetAttachmentsInvResponse, MAX_RETRY_DELAY,
+};
+use crate::net::chat::ConversationP2P;
 use crate::net::connection::ConnectionOptions;
 use crate::net::dns::*;
 use crate::net::http::HttpRequestContents;
 use crate::net::httpcore::{StacksHttpRequest, StacksHttpResponse};
+use crate::net::neighbors::{NeighborComms, PeerNetworkComms};
 use crate::net::p2p::PeerNetwork;
 use crate::net::server::HttpPeer;
-use crate::net::{Error as net_error, NeighborKey, PeerHost, Requestable};
+use crate::net::{
+    AttachmentsInvData, Error as net_error, GetAttachmentsInvData, NeighborAddress, NeighborKey,
+    PeerHost, Requestable, StacksMessageType,
+};
 use crate::util_lib::db::Error as DBError;
 use crate::util_lib::strings;
 use crate::util_lib::strings::UrlString;
@@ -44,23 +57,284 @@ use crate::util_lib::strings::UrlString;
 #[derive(Debug)]
 pub struct AttachmentsDownloader {
     priority_queue: BinaryHeap<AttachmentsBatch>,
+    /// Batches requested by an operator, which run ahead of everything in `priority_queue`
+    on_demand_queue: VecDeque<AttachmentsBatch>,
     initial_batch: Vec<AttachmentInstance>,
     ongoing_batch: Option<AttachmentsBatchStateMachine>,
     processed_batches: Vec<AttachmentsBatch>,
     reliability_reports: HashMap<UrlString, ReliabilityReport>,
+    /// Whether the reliability reports stored in the AtlasDB have been loaded yet
+    reliability_reports_loaded: bool,
+    not_found_cache: AttachmentsNotFoundCache,
+    /// Data URLs that peers have moved away from, and where each one moved to
+    moved_data_urls: HashMap<UrlString, UrlString>,
+    /// Number of batches handed back to the AtlasDB because `priority_queue` was full
+    spilled_batches: u64,
+    /// Number of batches given up on after exhausting their retries
+    dropped_batches: u64,
+    /// Number of queued attachment instances dropped because a newer instance superseded them
+    superseded_instances: u64,
+    /// Attachment inventory requests sent to peers over the p2p network for the ongoing batch
+    p2p_inventory_comms: PeerNetworkComms,
+    /// Data URL of each peer asked for its attachment inventory over the p2p network
+    p2p_inventory_peers: HashMap<NeighborAddress, UrlString>,
+    /// How often connecting to each peer over each address family has worked.  These are
+    /// kept in memory only, since a node's connectivity may change across restarts.
+    family_reports: HashMap<(UrlString, AddressFamily), ReliabilityReport>,
 }
 
 impl AttachmentsDownloader {
     pub fn new(initial_batch: Vec<AttachmentInstance>) -> AttachmentsDownloader {
         AttachmentsDownloader {
             priority_queue: BinaryHeap::new(),
+            on_demand_queue: VecDeque::new(),
             ongoing_batch: None,
             processed_batches: vec![],
             reliability_reports: HashMap::new(),
+            reliability_reports_loaded: false,
+            not_found_cache: AttachmentsNotFoundCache::new(0),
+            moved_data_urls: HashMap::new(),
+            spilled_batches: 0,
+            dropped_batches: 0,
+            superseded_instances: 0,
+            p2p_inventory_comms: PeerNetworkComms::new(),
+            p2p_inventory_peers: HashMap::new(),
+            family_reports: HashMap::new(),
             initial_batch,
         }
     }
 
+    /// Remember that the peer at `old_url` has moved to `new_url`, along with its reliability
+    /// report
+    pub fn record_moved_data_url(&mut self, old_url: UrlString, new_url: UrlString) {
+        if old_url == new_url {
+            return;
+        }
+        debug!("Atlas: peer {} moved to {}", &old_url, &new_url);
+        if let Some(report) = self.reliability_reports.remove(&old_url) {
+            self.reliability_reports
+                .entry(new_url.clone())
+                .or_insert(report);
+        }
+        self.moved_data_urls.insert(old_url, new_url);
+    }
+
+    /// The data URL at which a peer that advertised `data_url` can currently be reached
+    pub fn current_data_url(&self, data_url: &UrlString) -> UrlString {
+        let mut current = data_url;
+        let mut seen = HashSet::new();
+        while let Some(next) = self.moved_data_urls.get(current) {
+            if !seen.insert(next) {
+                // peers redirecting to each other
+                break;
+            }
+            current = next;
+        }
+        current.clone()
+    }
+
+    /// Pick up the reliability reports stored in `atlas_db` by a previous run, unless they have
+    /// been loaded already.  Reports learnt since the downloader started are kept.
+    pub fn load_reliability_reports(&mut self, atlas_db: &AtlasDB) -> Result<(), DBError> {
+        if self.reliability_reports_loaded {
+            return Ok(());
+        }
+        for (data_url, report) in atlas_db.get_reliability_reports()?.into_iter() {
+            self.reliability_reports.entry(data_url).or_insert(report);
+        }
+        self.reliability_reports_loaded = true;
+        Ok(())
+    }
+
+    /// Get the reliability report of the peer at `data_url`, if it has been sent requests
+    pub fn get_reliability_report(&self, data_url: &UrlString) -> Option<&ReliabilityReport> {
+        self.reliability_reports.get(data_url)
+    }
+
+    /// Get how often connecting to the peer at `data_url` over `family` has worked, if it has
+    /// been tried
+    pub fn get_family_reliability_report(
+        &self,
+        data_url: &UrlString,
+        family: AddressFamily,
+    ) -> Option<&ReliabilityReport> {
+        self.family_reports.get(&(data_url.clone(), family))
+    }
+
+    /// Describe the downloader's current state, for debugging
+    pub fn describe(&self) -> AttachmentsDownloaderSnapshot {
+        AttachmentsDownloaderSnapshot {
+            initial_batch_size: self.initial_batch.len(),
+            queued_batches: self.priority_queue.len(),
+            on_demand_batches: self.on_demand_queue.len(),
+            processed_batches: self.processed_batches.len(),
+            not_found_cache_size: self.not_found_cache.len(),
+            ongoing_batch: self.ongoing_batch.as_ref().map(|fsm| fsm.describe()),
+            spilled_batches: self.spilled_batches,
+            dropped_batches: self.dropped_batches,
+            superseded_instances: self.superseded_instances,
+            estimated_memory_bytes: self.estimated_memory_usage() as u64,
+        }
+    }
+
+    /// Rough estimate of the memory, in bytes, held by the downloader's batches: the initial
+    /// batch, the queued, on-demand and processed batches, and the ongoing batch along with the
+    /// attachments it has downloaded so far. Connection state and caches are not counted.
+    pub fn estimated_memory_usage(&self) -> usize {
+        let initial_batch_size = self
+            .initial_batch
+            .iter()
+            .map(|instance| mem::size_of::<AttachmentInstance>() + instance.metadata.len())
+            .sum::<usize>();
+        let batches_size = self
+            .priority_queue
+            .iter()
+            .chain(self.on_demand_queue.iter())
+            .chain(self.processed_batches.iter())
+            .map(|batch| batch.estimated_memory_usage())
+            .sum::<usize>();
+        let ongoing_batch_size = self.ongoing_batch.as_ref().map_or(0, |fsm| {
+            let context = fsm.context();
+            context.attachments_batch.estimated_memory_usage()
+                + context
+                    .attachments
+                    .iter()
+                    .map(|attachment| attachment.content.len())
+                    .sum::<usize>()
+        });
+        initial_batch_size + batches_size + ongoing_batch_size
+    }
+
+    /// Set the attachment instances that `batch` has yet to resolve back to "queued" in the
+    /// AtlasDB
+    fn requeue_batch(atlas_db: &mut AtlasDB, batch: &AttachmentsBatch) -> Result<(), DBError> {
+        let mut instances = vec![];
+        for (contract_id, missing_attachments) in batch.attachments_instances.iter() {
+            for attachment_index in missing_attachments.keys() {
+                instances.push((contract_id.clone(), *attachment_index));
+            }
+        }
+        atlas_db.requeue_attachment_instances(&batch.index_block_hash, &instances)
+    }
+
+    /// Stop downloading, and hand every batch that is queued or in flight back to the AtlasDB.
+    /// Their attachment instances are set back to "queued", so that they are checked again once
+    /// the node restarts instead of being left "checked" but never downloaded.  Attachments that
+    /// the ongoing batch has downloaded but not yet stored are discarded.
+    /// Returns the number of batches handed back.
+    pub fn drain(&mut self, atlas_db: &mut AtlasDB) -> Result<usize, DBError> {
+        let mut batches: Vec<AttachmentsBatch> = self.on_demand_queue.drain(..).collect();
+        batches.extend(mem::take(&mut self.priority_queue).into_vec());
+        if let Some(fsm) = self.ongoing_batch.take() {
+            batches.push(fsm.context().attachments_batch.clone());
+        }
+        self.p2p_inventory_comms.cancel_inflight();
+        self.p2p_inventory_peers.clear();
+        for batch in batches.iter() {
+            Self::requeue_batch(atlas_db, batch)?;
+        }
+        if !batches.is_empty() {
+            info!(
+                "Atlas: handed {} unfinished batches back to the AtlasDB",
+                batches.len()
+            );
+        }
+        monitoring::set_atlas_downloader_memory_usage(self.estimated_memory_usage() as u64);
+        Ok(batches.len())
+    }
+
+    /// Hand the lowest-priority batches back to the AtlasDB until no more than
+    /// `max_queued_attachment_batches` remain in `priority_queue`. Their attachment instances
+    /// are set back to "queued", so that `check_queued_attachment_instances()` reloads them once
+    /// there is room. Their retry counts are lost.
+    /// Returns the number of batches spilled.
+    fn spill_excess_batches(&mut self, atlas_db: &mut AtlasDB) -> Result<usize, DBError> {
+        let max_batches = atlas_db.atlas_config.max_queued_attachment_batches as usize;
+        if self.priority_queue.len() <= max_batches {
+            return Ok(0);
+        }
+        // ascending order, so the batches that would be popped last come first
+        let mut batches = mem::take(&mut self.priority_queue).into_sorted_vec();
+        let num_spilled = batches.len() - max_batches;
+        for batch in batches.drain(..num_spilled) {
+            Self::requeue_batch(atlas_db, &batch)?;
+            monitoring::log_atlas_batch_evicted("spilled");
+        }
+        self.priority_queue = BinaryHeap::from(batches);
+        self.spilled_batches += num_spilled as u64;
+        debug!(
+            "Atlas: spilled {} batches to the AtlasDB, {} remain queued",
+            num_spilled,
+            self.priority_queue.len()
+        );
+        Ok(num_spilled)
+    }
+
+    /// Stop trying to download the queued attachment instances that are superseded, either by
+    /// `newer` or by other queued instances: the ones emitted by the same contract, with the same
+    /// binding and a lower attachment index.  Batches left with nothing to download are dropped.
+    /// The ongoing batch and on-demand batches are left alone.
+    ///
+    /// The superseded instances are left unresolved in the AtlasDB, so they can still be
+    /// downloaded on demand.
+    /// Returns the number of instances dropped.
+    pub fn prune_superseded_instances(&mut self, newer: &[AttachmentInstance]) -> usize {
+        let mut latest: HashMap<(QualifiedContractIdentifier, AttachmentBinding), u32> =
+            HashMap::new();
+        let newer_bindings = newer.iter().filter_map(|instance| {
+            let binding = instance.binding()?;
+            Some((
+                instance.contract_id.clone(),
+                binding,
+                instance.attachment_index,
+            ))
+        });
+        let queued_bindings = self.priority_queue.iter().flat_map(|batch| {
+            batch
+                .bindings
+                .iter()
+                .map(|((contract_id, attachment_index), binding)| {
+                    (contract_id.clone(), binding.clone(), *attachment_index)
+                })
+        });
+        for (contract_id, binding, attachment_index) in newer_bindings.chain(queued_bindings) {
+            let latest_index = latest
+                .entry((contract_id, binding))
+                .or_insert(attachment_index);
+            *latest_index = cmp::max(*latest_index, attachment_index);
+        }
+        if latest.is_empty() || self.priority_queue.is_empty() {
+            return 0;
+        }
+
+        let mut num_pruned = 0;
+        let mut batches = mem::take(&mut self.priority_queue).into_vec();
+        for batch in batches.iter_mut() {
+            num_pruned += batch.prune_superseded(&latest);
+        }
+        batches.retain(|batch| {
+            if batch.has_fully_succeed() {
+                debug!(
+                    "Atlas: dropping batch for {}, all of its attachment instances are superseded",
+                    &batch.index_block_hash
+                );
+                monitoring::log_atlas_batch_evicted("superseded");
+                false
+            } else {
+                true
+            }
+        });
+        self.priority_queue = BinaryHeap::from(batches);
+        if num_pruned > 0 {
+            info!(
+                "Atlas: dropped {} superseded attachment instances from queued batches",
+                num_pruned
+            );
+        }
+        self.superseded_instances += num_pruned as u64;
+        num_pruned
+    }
+
     /// Identify whether or not any AttachmentBatches in the priority queue are ready for
     /// (re-)consideration by the downloader, based on whether or not its re-try deadline
     /// has passed.
@@ -91,6 +365,130 @@ impl AttachmentsDownloader {
         }
     }
 
+    /// Queue the given attachment instances for download ahead of any scheduled batch, and
+    /// without waiting for a retry deadline.  Instances are grouped into one batch per Stacks
+    /// block.  A batch that is already in flight is not preempted, so the new batches start
+    /// once it finishes.  If an on-demand batch does not fully succeed, it is retried on the
+    /// normal schedule.
+    /// Returns the number of batches queued.
+    pub fn enqueue_on_demand_batches(&mut self, instances: Vec<AttachmentInstance>) -> usize {
+        let mut attachments_batches: HashMap<StacksBlockId, AttachmentsBatch> = HashMap::new();
+        for attachment_instance in instances.iter() {
+            attachments_batches
+                .entry(attachment_instance.index_block_hash)
+                .or_insert_with(AttachmentsBatch::new)
+                .track_attachment(attachment_instance);
+        }
+        let mut batches: Vec<_> = attachments_batches.into_values().collect();
+        batches.sort_by_key(|batch| batch.stacks_block_height);
+        let num_batches = batches.len();
+        for batch in batches.into_iter() {
+            info!("Atlas: queueing on-demand batch {:?}", batch);
+            self.on_demand_queue.push_back(batch);
+        }
+        num_batches
+    }
+
+    /// Ask every authenticated peer that advertises a data URL and the ATTACHMENTS_INV service
+    /// for the pages of its attachment inventory that `batch` needs, over the p2p network.  Peers
+    /// without the service can't decode the request.  Requests still in flight for an earlier
+    /// batch are abandoned.
+    /// Returns the number of requests sent.
+    fn send_p2p_inventory_requests(
+        &mut self,
+        network: &mut PeerNetwork,
+        batch: &AttachmentsBatch,
+    ) -> usize {
+        self.p2p_inventory_comms.cancel_inflight();
+        self.p2p_inventory_peers.clear();
+        let request = match batch.get_p2p_inventory_request() {
+            Some(request) => request,
+            None => return 0,
+        };
+
+        let event_ids: Vec<usize> = network.iter_peer_event_ids().copied().collect();
+        for event_id in event_ids.into_iter() {
+            let (naddr, data_url) = match network.get_p2p_convo(event_id) {
+                Some(convo)
+                    if convo.is_authenticated()
+                        && !convo.data_url.is_empty()
+                        && ConversationP2P::supports_attachments_inv(convo.peer_services) =>
+                {
+                    (convo.to_neighbor_address(), convo.data_url.clone())
+                }
+                _ => continue,
+            };
+            if let Err(e) = self.p2p_inventory_comms.neighbor_send(
+                network,
+                &naddr,
+                StacksMessageType::GetAttachmentsInv(request.clone()),
+            ) {
+                debug!("Atlas: failed to send GetAttachmentsInv";
+                       "peer" => ?naddr,
+                       "err" => ?e
+                );
+                continue;
+            }
+            let peer_url = self.current_data_url(&data_url);
+            self.p2p_inventory_peers.insert(naddr, peer_url);
+        }
+        self.p2p_inventory_peers.len()
+    }
+
+    /// Merge the attachment inventories that peers sent back over the p2p network into the
+    /// batch processed by `fsm`, as long as it has not started downloading attachments yet.
+    /// Returns the number of inventories merged.
+    fn collect_p2p_inventories(
+        &mut self,
+        network: &mut PeerNetwork,
+        fsm: &mut AttachmentsBatchStateMachine,
+    ) -> usize {
+        let mut num_merged = 0;
+        for (naddr, message) in self.p2p_inventory_comms.collect_replies(network) {
+            let peer_url = match self.p2p_inventory_peers.remove(&naddr) {
+                Some(peer_url) => peer_url,
+                None => continue,
+            };
+            let inventory = match message.payload {
+                StacksMessageType::AttachmentsInv(inventory) => inventory,
+                StacksMessageType::Nack(nack_data) => {
+                    debug!("Atlas: peer {} did not send its attachment inventory", &peer_url;
+                           "error_code" => nack_data.error_code
+                    );
+                    continue;
+                }
+                payload => {
+                    debug!(
+                        "Atlas: unexpected reply to GetAttachmentsInv from {}: {}",
+                        &peer_url,
+                        payload.get_message_name()
+                    );
+                    continue;
+                }
+            };
+            let context = match fsm.context_for_inventories_mut() {
+                Some(context) => context,
+                None => {
+                    debug!(
+                        "Atlas: attachment inventory from {} arrived too late for the batch",
+                        &peer_url
+                    );
+                    continue;
+                }
+            };
+            let report = self
+                .reliability_reports
+                .get(&peer_url)
+                .cloned()
+                .unwrap_or_else(ReliabilityReport::empty);
+            num_merged += context.extend_with_p2p_inventory(peer_url, &report, inventory);
+        }
+        // the p2p state machines deal with peers that went away or misbehaved
+        let _ = self.p2p_inventory_comms.take_dead_neighbors();
+        let _ = self.p2p_inventory_comms.take_broken_neighbors();
+        num_merged
+    }
+
     /// This function executes `AttachmentsBatchStateMachine` for one step.
     /// It handles initializing and setting the batch to be processed by the machine.
     pub fn run(
@@ -107,31 +505,49 @@ impl AttachmentsDownloader {
             resolved_attachments.append(&mut resolved);
         }
 
-        let ongoing_fsm = match self.ongoing_batch.take() {
+        let mut ongoing_fsm = match self.ongoing_batch.take() {
             Some(batch) => batch,
             None => {
-                if self.priority_queue.is_empty() || !self.has_ready_batches() {
+                if self.on_demand_queue.is_empty()
+                    && (self.priority_queue.is_empty() || !self.has_ready_batches())
+                {
                     // Nothing to do!
                     return Ok((vec![], vec![]));
                 }
 
+                if let Err(e) = self.load_reliability_reports(&network.atlasdb) {
+                    warn!("Atlas: failed to load stored reliability reports"; "err" => ?e);
+                }
+
+                // Peers that told us they moved are reached at their new data URL.  Moves are
+                // forgotten once the peer is no longer one of our sync peers.
                 let mut peers = HashMap::new();
+                let mut moved_data_urls = HashMap::new();
                 for peer in network.get_outbound_sync_peers() {
                     if let Some(peer_url) = network.get_data_url(&peer) {
-                        let report = match self.reliability_reports.get(&peer_url) {
+                        let current_url = self.current_data_url(&peer_url);
+                        if current_url != peer_url {
+                            moved_data_urls.insert(peer_url, current_url.clone());
+                        }
+                        let report = match self.reliability_reports.get(&current_url) {
                             Some(report) => report.clone(),
                             None => ReliabilityReport::empty(),
                         };
-                        peers.insert(peer_url, report);
+                        peers.insert(current_url, report);
                     }
                 }
+                self.moved_data_urls = moved_data_urls;
                 if peers.is_empty() {
                     warn!("Atlas: could not get a peer to sync with");
                     // Nothing can be done!
                     return Ok((vec![], vec![]));
                 }
 
-                let attachments_batch = match self.pop_next_ready_batch() {
+                let next_batch = match self.on_demand_queue.pop_front() {
+                    Some(on_demand_batch) => Some(on_demand_batch),
+                    None => self.pop_next_ready_batch(),
+                };
+                let attachments_batch = match next_batch {
                     Some(ready_batch) => ready_batch,
                     None => {
                         // unreachable
@@ -140,20 +556,40 @@ impl AttachmentsDownloader {
                     }
                 };
 
+                // Lend our negative cache to the batch; it is handed back once the batch is done
+                let mut not_found_cache =
+                    std::mem::replace(&mut self.not_found_cache, AttachmentsNotFoundCache::new(0));
+                not_found_cache.set_ttl(network.connection_opts.attachment_not_found_ttl);
+                not_found_cache.evict_expired();
+
                 let ctx = AttachmentsBatchStateContext::new(
                     attachments_batch,
                     peers,
                     &network.connection_opts,
-                );
-                AttachmentsBatchStateMachine::new(ctx)
+                )
+                .with_not_found_cache(not_found_cache)
+                .with_max_attachment_size(network.atlasdb.atlas_config.attachments_max_size)
+                .with_family_reports(self.family_reports.clone());
+                let fsm = AttachmentsBatchStateMachine::new(ctx);
+                self.send_p2p_inventory_requests(network, &fsm.context().attachments_batch);
+                fsm
             }
         };
 
+        let num_p2p_inventories = self.collect_p2p_inventories(network, &mut ongoing_fsm);
+        if num_p2p_inventories > 0 {
+            debug!(
+                "Atlas: merged {} attachment inventories received over p2p",
+                num_p2p_inventories
+            );
+        }
+
         let mut progress =
             AttachmentsBatchStateMachine::try_proceed(ongoing_fsm, dns_client, network);
 
         match progress {
             AttachmentsBatchStateMachine::Done(ref mut context) => {
+                context.log_stage_timings();
                 for attachment in context.attachments.drain() {
                     let attachments_instances = network
                         .atlasdb
@@ -182,14 +618,38 @@ impl AttachmentsDownloader {
                     .atlasdb
                     .evict_expired_unresolved_attachment_instances()?;
 
-                // Update reliability reports
+                // And drop the resolution history that is past its retention
+                network.atlasdb.evict_expired_attachment_resolutions()?;
+
+                // Update reliability reports, and keep them for the next run
+                if let Err(e) = network
+                    .atlasdb
+                    .store_reliability_reports(context.peers.iter())
+                {
+                    warn!("Atlas: failed to store reliability reports"; "err" => ?e);
+                }
                 for (peer_url, report) in context.peers.drain() {
                     self.reliability_reports.insert(peer_url, report);
                 }
+                for (old_url, new_url) in context.moved_data_urls.drain() {
+                    self.record_moved_data_url(old_url, new_url);
+                }
+                self.family_reports = std::mem::take(&mut context.family_reports);
+
+                // Take back the negative cache, including the 404s seen during this batch
+                self.not_found_cache = std::mem::replace(
+                    &mut context.not_found_cache,
+                    AttachmentsNotFoundCache::new(0),
+                );
 
                 // Re-insert AttachmentsBatch back to the queue if not fully processed
                 if !context.attachments_batch.has_fully_succeed() {
                     context.attachments_batch.bump_retry_count();
+                    // Don't come back before throttling peers are willing to serve us again
+                    context.attachments_batch.retry_deadline = cmp::max(
+                        context.attachments_batch.retry_deadline,
+                        context.throttled_until,
+                    );
                     // If max_attachment_retry_count not reached, we'll re-enqueue the batch
                     if context.attachments_batch.retry_count
                         < context.connection_options.max_attachment_retry_count
@@ -199,11 +659,14 @@ impl AttachmentsDownloader {
                             context.attachments_batch
                         );
                         self.priority_queue.push(context.attachments_batch.clone());
+                        self.spill_excess_batches(&mut network.atlasdb)?;
                     } else {
                         info!(
                             "Atlas: dropping batch {:?} retries count exceeded",
                             context.attachments_batch
                         );
+                        self.dropped_batches += 1;
+                        monitoring::log_atlas_batch_evicted("dropped");
                     }
                 }
             }
@@ -212,6 +675,8 @@ impl AttachmentsDownloader {
             }
         };
 
+        monitoring::set_atlas_downloader_memory_usage(self.estimated_memory_usage() as u64);
+
         Ok((resolved_attachments, events_to_deregister))
     }
 
@@ -223,7 +688,8 @@ impl AttachmentsDownloader {
     ///  the attachment is marked as instantiated in the atlas db.
     ///
     /// In the event of (3), `do_if_not_found` is invoked, and the attachment instance is added
-    ///  to `self.priority_queue`.
+    ///  to `self.priority_queue`. If that overfills the queue, the lowest-priority batches are
+    ///  spilled back to the AtlasDB.
     ///
     /// The return value of this function is a vector of all the instances from `iterator` which
     ///  resolved to Attachment data, paired with that data.
@@ -284,6 +750,7 @@ impl AttachmentsDownloader {
         for (_, batch) in attachments_batches.into_iter() {
             self.priority_queue.push(batch);
         }
+        self.spill_excess_batches(atlas_db)?;
 
         Ok(resolved_attachments)
     }
@@ -292,6 +759,8 @@ impl AttachmentsDownloader {
     ///  returning a vector of (instance, attachment) pairs for any of the queued attachments
     ///  which already had the associated data
     /// Marks any processed attachments as checked
+    /// Does nothing while `priority_queue` is full, so that queued instances (including the ones
+    ///  of spilled batches) wait in the AtlasDB instead of in memory.
     ///
     /// This method is invoked in the thread managing the AttachmentDownloader. This is currently
     ///  the P2P thread.
@@ -299,9 +768,16 @@ impl AttachmentsDownloader {
         &mut self,
         atlas_db: &mut AtlasDB,
     ) -> Result<Vec<(AttachmentInstance, Attachment)>, DBError> {
+        if self.priority_queue.len() >= atlas_db.atlas_config.max_queued_attachment_batches as usize
+        {
+            return Ok(vec![]);
+        }
         let new_attachments = atlas_db.queued_attachments()?;
+        // Chainstate processing queues the instances of each new block, which may supersede
+        // instances still waiting to be downloaded (or each other)
+        let newer = new_attachments.clone();
 
-        self.check_attachment_instances(
+        let resolved_attachments = self.check_attachment_instances(
             atlas_db,
             new_attachments,
             |atlas_db, attachment_instance| {
@@ -310,7 +786,9 @@ impl AttachmentsDownloader {
             |atlas_db, attachment_instance| {
                 atlas_db.mark_attachment_instance_checked(&attachment_instance, false)
             },
-        )
+        )?;
+        self.prune_superseded_instances(&newer);
+        Ok(resolved_attachments)
     }
 
     /// Insert the initial attachments set. Only add the attachment instance if associated data
@@ -353,6 +831,22 @@ pub struct AttachmentsBatchStateContext {
     >,
     pub attachments: HashSet<Attachment>,
     pub events_to_deregister: Vec<usize>,
+    pub not_found_cache: AttachmentsNotFoundCache,
+    /// Earliest time at which every peer that throttled us (HTTP 429) will serve us again
+    pub throttled_until: u64,
+    /// Attachments being downloaded one byte range at a time, from several peers
+    pub partial_attachments: HashMap<Hash160, PartialAttachment>,
+    /// Largest attachment we are willing to download in byte ranges
+    pub max_attachment_size: u64,
+    /// Time spent in each stage of the batch that has completed so far
+    pub stage_timings: AttachmentsBatchStageTimings,
+    /// When the current stage began
+    stage_started_at: Instant,
+    /// Peers that moved to another data URL during this batch, either by redirecting our
+    /// requests or by advertising it
+    pub moved_data_urls: HashMap<UrlString, UrlString>,
+    /// How often connecting to each peer over each address family has worked
+    pub family_reports: HashMap<(UrlString, AddressFamily), ReliabilityReport>,
 }
 
 impl AttachmentsBatchStateContext {
@@ -369,6 +863,173 @@ impl AttachmentsBatchStateContext {
             inventories: HashMap::new(),
             attachments: HashSet::new(),
             events_to_deregister: vec![],
+            not_found_cache: AttachmentsNotFoundCache::new(
+                connection_options.attachment_not_found_ttl,
+            ),
+            throttled_until: 0,
+            partial_attachments: HashMap::new(),
+            max_attachment_size: u64::from(ATTACHMENTS_MAX_SIZE_MIN),
+            stage_timings: AttachmentsBatchStageTimings::default(),
+            stage_started_at: Instant::now(),
+            moved_data_urls: HashMap::new(),
+            family_reports: HashMap::new(),
+        }
+    }
+
+    /// Start timing the first stage of the batch
+    fn begin_stages(&mut self) {
+        self.stage_started_at = Instant::now();
+    }
+
+    /// Charge the time since the current stage began to `stage`, and start timing the next one
+    fn finish_stage(&mut self, stage: AttachmentsBatchStage) {
+        let now = Instant::now();
+        self.stage_timings
+            .record(stage, now.saturating_duration_since(self.stage_started_at));
+        self.stage_started_at = now;
+    }
+
+    /// Log how long each stage of the finished batch took, and export the timings
+    fn log_stage_timings(&self) {
+        let timings = &self.stage_timings;
+        info!("Atlas: batch finished";
+            "stacks_block_height" => self.attachments_batch.stacks_block_height,
+            "index_block_hash" => %self.attachments_batch.index_block_hash,
+            "retry_count" => self.attachments_batch.retry_count,
+            "downloaded_attachments" => self.attachments.len(),
+            "dns_lookup_ms" => timings.dns_lookup_ms,
+            "attachments_inv_ms" => timings.attachments_inv_ms,
+            "attachments_ms" => timings.attachments_ms,
+            "total_ms" => timings.total_ms(),
+        );
+        for (stage, elapsed_ms) in timings.stages() {
+            monitoring::log_atlas_batch_stage_time(stage, elapsed_ms);
+        }
+    }
+
+    /// Remember how long the peers that throttled us asked us to wait
+    fn note_throttled_peers(&mut self, throttled: &mut HashMap<UrlString, u64>) {
+        let now = get_epoch_time_secs();
+        for (peer_url, retry_after) in throttled.drain() {
+            debug!(
+                "Atlas: peer {} throttled us for {} seconds",
+                &peer_url, retry_after
+            );
+            self.throttled_until = cmp::max(self.throttled_until, now.saturating_add(retry_after));
+        }
+    }
+
+    /// Keep sending requests to the peers that redirected us, at their new data URL, and
+    /// remember that they moved
+    fn note_redirected_peers(&mut self, redirected: &mut HashMap<UrlString, UrlString>) {
+        for (old_url, new_url) in redirected.drain() {
+            if !self.peers.contains_key(&new_url) {
+                let report = self
+                    .peers
+                    .get(&old_url)
+                    .cloned()
+                    .unwrap_or_else(ReliabilityReport::empty);
+                self.peers.insert(new_url.clone(), report);
+            }
+            self.moved_data_urls.insert(old_url, new_url);
+        }
+    }
+
+    /// Use `not_found_cache` to skip (peer, content hash) pairs known to be missing
+    pub fn with_not_found_cache(
+        mut self,
+        not_found_cache: AttachmentsNotFoundCache,
+    ) -> AttachmentsBatchStateContext {
+        self.not_found_cache = not_found_cache;
+        self
+    }
+
+    /// Order the addresses of dual-stack peers by how often connecting over each family has
+    /// worked, as recorded in `family_reports`
+    pub fn with_family_reports(
+        mut self,
+        family_reports: HashMap<(UrlString, AddressFamily), ReliabilityReport>,
+    ) -> AttachmentsBatchStateContext {
+        self.family_reports = family_reports;
+        self
+    }
+
+    /// Add the address family outcomes of a round of requests to `family_reports`
+    fn note_family_reports(
+        &mut self,
+        family_reports: &mut HashMap<(UrlString, AddressFamily), ReliabilityReport>,
+    ) {
+        for (key, outcomes) in family_reports.drain() {
+            let report = self
+                .family_reports
+                .entry(key)
+                .or_insert_with(ReliabilityReport::empty);
+            report.total_requests_sent = report
+                .total_requests_sent
+                .saturating_add(outcomes.total_requests_sent);
+            report.total_requests_success = report
+                .total_requests_success
+                .saturating_add(outcomes.total_requests_success);
+        }
+    }
+
+    /// Put the addresses of the family that connecting to a dual-stack peer has worked best
+    /// over first.  The order the address family preference gave them in is kept unless the
+    /// family listed first has worked for a smaller fraction of requests than the other.
+    fn order_by_family_reliability(&mut self) {
+        for (peer_url, addrs) in self.dns_lookups.iter_mut() {
+            let addrs = match addrs {
+                Some(addrs) if !addrs.is_empty() => addrs,
+                _ => continue,
+            };
+            let first = AddressFamily::of(&addrs[0]);
+            let other = first.other();
+            if !addrs.iter().any(|addr| AddressFamily::of(addr) == other) {
+                continue;
+            }
+            let tier = |family: AddressFamily| {
+                self.family_reports
+                    .get(&(peer_url.clone(), family))
+                    .map(|report| report.success_tier())
+            };
+            if let (Some(first_tier), Some(other_tier)) = (tier(first), tier(other)) {
+                if other_tier > first_tier {
+                    debug!(
+                        "Atlas: connecting to {} has worked better over {} than over {}",
+                        peer_url, other, first
+                    );
+                    demote_address_family(addrs, first);
+                }
+            }
+        }
+    }
+
+    /// Refuse to download attachments larger than `max_size` bytes in byte ranges
+    pub fn with_max_attachment_size(mut self, max_size: u32) -> AttachmentsBatchStateContext {
+        self.max_attachment_size = u64::from(max_size);
+        self
+    }
+
+    /// Describe the batch being downloaded and what has been gathered for it so far
+    pub fn describe(&self) -> AttachmentsBatchContextSnapshot {
+        let mut peers = self.get_peers_urls();
+        peers.sort();
+        AttachmentsBatchContextSnapshot {
+            stacks_block_height: self.attachments_batch.stacks_block_height,
+            index_block_hash: self.attachments_batch.index_block_hash,
+            retry_count: self.attachments_batch.retry_count,
+            missing_attachments: self.attachments_batch.attachments_instances_count(),
+            missing_attachments_by_origin: self.attachments_batch.origin_counts(),
+            peers,
+            resolved_dns_lookups: self
+                .dns_lookups
+                .values()
+                .filter(|addrs| addrs.is_some())
+                .count(),
+            inventories: self.inventories.values().map(|invs| invs.len()).sum(),
+            downloaded_attachments: self.attachments.len(),
+            events_to_deregister: self.events_to_deregister.clone(),
+            stage_timings: self.stage_timings.clone(),
         }
     }
 
@@ -385,7 +1046,19 @@ impl AttachmentsBatchStateContext {
                 .attachments_batch
                 .get_paginated_missing_pages_for_contract_id(contract_id);
             for (peer_url, reliability_report) in self.peers.iter() {
+                let known_pages = self.attachments_batch.known_inventory_pages.get(peer_url);
                 for pages in pages_batches.iter() {
+                    let since = match known_pages {
+                        Some(known_pages) => pages
+                            .iter()
+                            .filter_map(|page_index| {
+                                known_pages
+                                    .get(page_index)
+                                    .map(|page| (*page_index, page.digest()))
+                            })
+                            .collect(),
+                        None => vec![],
+                    };
                     let request = AttachmentsInventoryRequest {
                         url: peer_url.clone(),
                         reliability_report: reliability_report.clone(),
@@ -396,6 +1069,8 @@ impl AttachmentsBatchStateContext {
                         canonical_stacks_tip_height: self
                             .attachments_batch
                             .canonical_stacks_tip_height,
+                        redirects: 0,
+                        since,
                     };
                     queue.push(request);
                 }
@@ -461,6 +1136,14 @@ impl AttachmentsBatchStateContext {
                         continue;
                     }
 
+                    if self.not_found_cache.contains(peer_url, content_hash) {
+                        debug!(
+                            "Atlas: skipping peer {} for attachment {}, recently answered 404",
+                            peer_url, content_hash
+                        );
+                        continue;
+                    }
+
                     let report = self
                         .peers
                         .get(peer_url)
@@ -473,12 +1156,28 @@ impl AttachmentsBatchStateContext {
                     continue;
                 }
 
+                // If several peers can serve the attachment, start by asking for its first
+                // byte range: if the attachment turns out to be larger, the remaining ranges are
+                // then requested from all of them in parallel.
+                let chunk_size = self.connection_options.attachment_chunk_size;
+                let range = if chunk_size > 0 && sources.len() > 1 {
+                    Some(AttachmentRange {
+                        offset: 0,
+                        length: chunk_size,
+                    })
+                } else {
+                    None
+                };
+
                 // Success, we found at least one inventory including the attachment we're looking for.
                 let request = AttachmentRequest {
                     sources,
                     content_hash: content_hash.clone(),
                     stacks_block_height: self.attachments_batch.stacks_block_height,
                     canonical_stacks_tip_height: self.attachments_batch.canonical_stacks_tip_height,
+                    range,
+                    source: None,
+                    redirects: 0,
                 };
                 enqueued.insert(content_hash);
                 queue.push(request);
@@ -487,6 +1186,39 @@ impl AttachmentsBatchStateContext {
         queue
     }
 
+    /// Requests for the byte ranges still missing from the attachments being downloaded in
+    /// ranges.  The ranges of each attachment are spread over all the peers that can serve it,
+    /// most reliable first.
+    pub fn get_attachment_chunk_requests(&self) -> BinaryHeap<AttachmentRequest> {
+        let mut queue = BinaryHeap::new();
+        let chunk_size = self.connection_options.attachment_chunk_size;
+        if chunk_size == 0 {
+            return queue;
+        }
+        for (content_hash, partial) in self.partial_attachments.iter() {
+            let mut sources: Vec<_> = partial
+                .request
+                .sources
+                .iter()
+                .filter(|(peer_url, _)| !self.not_found_cache.contains(peer_url, content_hash))
+                .map(|(peer_url, report)| (peer_url, self.peers.get(peer_url).unwrap_or(report)))
+                .collect();
+            if sources.is_empty() {
+                continue;
+            }
+            sources.sort_by(|(_, report), (_, other_report)| other_report.cmp(report));
+
+            for (i, range) in partial.missing_ranges(chunk_size).into_iter().enumerate() {
+                let (peer_url, _) = sources[i % sources.len()];
+                let mut request = partial.request.clone();
+                request.range = Some(range);
+                request.source = Some(peer_url.clone());
+                queue.push(request);
+            }
+        }
+        queue
+    }
+
     pub fn extend_with_dns_lookups(
         mut self,
         results: &mut BatchedDNSLookupsResults,
@@ -494,6 +1226,7 @@ impl AttachmentsBatchStateContext {
         for (k, v) in results.dns_lookups.drain() {
             self.dns_lookups.insert(k, v);
         }
+        self.order_by_family_reliability();
         self
     }
 
@@ -501,7 +1234,15 @@ impl AttachmentsBatchStateContext {
         mut self,
         results: &mut BatchedRequestsResult<AttachmentsInventoryRequest>,
     ) -> AttachmentsBatchStateContext {
+        self.note_redirected_peers(&mut results.redirected);
         for (request, response) in results.succeeded.drain() {
+            if let Some(data_url) = response
+                .as_ref()
+                .and_then(|response| advertised_data_url(request.get_url(), response))
+            {
+                self.moved_data_urls
+                    .insert(request.get_url().clone(), data_url);
+            }
             let report = self
                 .peers
                 .get_mut(request.get_url())
@@ -514,8 +1255,21 @@ impl AttachmentsBatchStateContext {
                 continue;
             };
 
-            if let Ok(response) = response.decode_atlas_attachments_inv_response() {
+            if let Ok(mut response) = response.decode_atlas_attachments_inv_response() {
                 let peer_url = request.get_url().clone();
+                let known_pages = self
+                    .attachments_batch
+                    .known_inventory_pages
+                    .entry(peer_url.clone())
+                    .or_default();
+                if !response.restore_unchanged_pages(known_pages) {
+                    // the peer claims we already have pages we never asked it about
+                    report.bump_failed_requests();
+                    continue;
+                }
+                for page in response.pages.iter() {
+                    known_pages.insert(page.index, page.clone());
+                }
                 match self.inventories.entry(request.key()) {
                     Entry::Occupied(responses) => {
                         responses.into_mut().insert(peer_url, response);
@@ -526,7 +1280,7 @@ impl AttachmentsBatchStateContext {
                         v.insert(responses);
                     }
                 };
-                report.bump_successful_requests();
+                record_success(report, results.response_samples.remove(&request));
             } else {
                 report.bump_failed_requests();
             }
@@ -537,15 +1291,93 @@ impl AttachmentsBatchStateContext {
             .map(|(k, _)| *k)
             .collect::<Vec<usize>>();
         self.events_to_deregister.append(&mut events_ids);
+        self.note_throttled_peers(&mut results.throttled);
+        self.note_family_reports(&mut results.family_reports);
 
         self
     }
 
+    /// Merge the attachment inventory that the peer at `peer_url` sent over the p2p network.
+    /// It is filed under each batch of pages that an HTTP inventory request would have asked
+    /// for, provided it covers all of them, so that the attachment requests made from the
+    /// inventories don't tell one source from the other.  An inventory already received over
+    /// HTTP is kept.  If the peer is not among the batch's peers yet, it is added with
+    /// `report`.
+    ///
+    /// The attachments themselves are still downloaded over HTTP, from `peer_url`, so an
+    /// inventory only helps if that URL can be reached: a peer behind NAT can tell us which
+    /// attachments it has, but can't serve them.  The inventory is therefore dropped if
+    /// `report` shows that every request sent to `peer_url` has failed.  A peer we have not
+    /// sent any requests to yet is given the benefit of the doubt until its requests fail.
+    /// Returns the number of batches of pages the inventory was filed under.
+    pub fn extend_with_p2p_inventory(
+        &mut self,
+        peer_url: UrlString,
+        report: &ReliabilityReport,
+        inventory: AttachmentsInvData,
+    ) -> usize {
+        if inventory.index_block_hash != self.attachments_batch.index_block_hash {
+            return 0;
+        }
+        if report.total_requests_sent > 0 && report.total_requests_success == 0 {
+            debug!(
+                "Atlas: ignoring the attachment inventory of {}, whose data URL has never answered",
+                &peer_url
+            );
+            return 0;
+        }
+        let mut num_merged = 0;
+        for contract_id in self.attachments_batch.attachments_instances.keys() {
+            let pages_batches = self
+                .attachments_batch
+                .get_paginated_missing_pages_for_contract_id(contract_id);
+            for pages in pages_batches.into_iter() {
+                let inventory_pages: Vec<AttachmentPage> = pages
+                    .iter()
+                    .filter_map(|page_index| {
+                        inventory
+                            .pages
+                            .iter()
+                            .find(|page| page.index == *page_index)
+                            .cloned()
+                    })
+                    .collect();
+                if inventory_pages.len() != pages.len() {
+                    continue;
+                }
+                let response = GetAttachmentsInvResponse {
+                    block_id: inventory.index_block_hash,
+                    pages: inventory_pages,
+                    unchanged: vec![],
+                };
+                self.inventories
+                    .entry((contract_id.clone(), pages, inventory.index_block_hash))
+                    .or_default()
+                    .entry(peer_url.clone())
+                    .or_insert(response);
+                num_merged += 1;
+            }
+        }
+        if num_merged > 0 {
+            self.peers.entry(peer_url).or_insert_with(|| report.clone());
+        }
+        num_merged
+    }
+
     pub fn extend_with_attachments(
         mut self,
         results: &mut BatchedRequestsResult<AttachmentRequest>,
     ) -> AttachmentsBatchStateContext {
+        self.note_redirected_peers(&mut results.redirected);
+        let mut chunks = vec![];
         for (request, response) in results.succeeded.drain() {
+            if let Some(data_url) = response
+                .as_ref()
+                .and_then(|response| advertised_data_url(request.get_url(), response))
+            {
+                self.moved_data_urls
+                    .insert(request.get_url().clone(), data_url);
+            }
             let report = self
                 .peers
                 .get_mut(request.get_url())
@@ -558,26 +1390,204 @@ impl AttachmentsBatchStateContext {
                 continue;
             };
 
+            if let Some(range) = request.range {
+                match response.decode_atlas_get_attachment_chunk() {
+                    Ok(chunk)
+                        if chunk.is_complete()
+                            || (chunk.offset == range.offset
+                                && !chunk.chunk.is_empty()
+                                && chunk.chunk.len() as u64 <= range.length) =>
+                    {
+                        record_success(report, results.response_samples.remove(&request));
+                        chunks.push((request, chunk));
+                    }
+                    _ => report.bump_failed_requests(),
+                }
+                continue;
+            }
+
             if let Ok(response) = response.decode_atlas_get_attachment() {
                 self.attachments.insert(response.attachment);
-                report.bump_successful_requests();
+                record_success(report, results.response_samples.remove(&request));
             } else {
                 report.bump_failed_requests();
             }
         }
+
+        // Attachments being downloaded in ranges are given up on (until the batch is retried)
+        // once a round of requests brings none of their missing ranges.
+        let mut progressed = HashSet::new();
+        for (request, chunk) in chunks.into_iter() {
+            let content_hash = request.content_hash;
+            if self.add_attachment_chunk(request, chunk) {
+                progressed.insert(content_hash);
+            }
+        }
+        self.partial_attachments
+            .retain(|content_hash, _| progressed.contains(content_hash));
+
+        for request in results.not_found.drain() {
+            debug!(
+                "Atlas: peer {} does not have attachment {}",
+                request.get_url(),
+                request.content_hash
+            );
+            self.not_found_cache
+                .insert(request.get_url().clone(), request.content_hash);
+        }
         let mut events_ids = results
             .faulty_peers
             .iter()
             .map(|(k, _)| *k)
             .collect::<Vec<usize>>();
         self.events_to_deregister.append(&mut events_ids);
+        self.note_throttled_peers(&mut results.throttled);
+        self.note_family_reports(&mut results.family_reports);
 
         self
     }
+
+    /// Store a byte range of an attachment downloaded in ranges.  Once all of its ranges are
+    /// in, the attachment is reassembled and kept if it matches its hash.
+    /// Returns `true` if the attachment is still missing some ranges.
+    fn add_attachment_chunk(
+        &mut self,
+        request: AttachmentRequest,
+        chunk: GetAttachmentChunkResponse,
+    ) -> bool {
+        let content_hash = request.content_hash;
+        if chunk.is_complete() {
+            // the attachment fit in a single range, or the peer sent all of it
+            self.partial_attachments.remove(&content_hash);
+            self.add_reassembled_attachment(&content_hash, Attachment::new(chunk.chunk));
+            return false;
+        }
+        if chunk.total_length > self.max_attachment_size {
+            warn!(
+                "Atlas: peer {} claims attachment {} is {} bytes long, more than the allowed {}",
+                request.get_url(),
+                &content_hash,
+                chunk.total_length,
+                self.max_attachment_size
+            );
+            return false;
+        }
+
+        let partial = self
+            .partial_attachments
+            .entry(content_hash)
+            .or_insert_with(|| PartialAttachment::new(chunk.total_length, request.clone()));
+        if partial.total_length != chunk.total_length
+            || !partial.insert_chunk(chunk.offset, chunk.chunk)
+        {
+            warn!(
+                "Atlas: peer {} sent a byte range that does not fit attachment {}",
+                request.get_url(),
+                &content_hash
+            );
+            return false;
+        }
+        let attachment = match partial.try_reassemble() {
+            Some(attachment) => attachment,
+            None => return true,
+        };
+        self.partial_attachments.remove(&content_hash);
+        self.add_reassembled_attachment(&content_hash, attachment);
+        false
+    }
+
+    fn add_reassembled_attachment(&mut self, content_hash: &Hash160, attachment: Attachment) {
+        if &attachment.hash() != content_hash {
+            warn!(
+                "Atlas: reassembled attachment does not match its hash {}, discarding it",
+                content_hash
+            );
+            return;
+        }
+        debug!(
+            "Atlas: reassembled attachment {} ({} bytes)",
+            content_hash,
+            attachment.content.len()
+        );
+        self.attachments.insert(attachment);
+    }
+}
+
+/// An attachment being downloaded in byte ranges
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialAttachment {
+    pub total_length: u64,
+    /// The byte ranges downloaded so far, by offset
+    pub chunks: BTreeMap<u64, Vec<u8>>,
+    /// The request the first byte range was downloaded with.  Requests for the other ranges
+    /// are derived from it.
+    pub request: AttachmentRequest,
+}
+
+impl PartialAttachment {
+    pub fn new(total_length: u64, request: AttachmentRequest) -> PartialAttachment {
+        PartialAttachment {
+            total_length,
+            chunks: BTreeMap::new(),
+            request,
+        }
+    }
+
+    /// Store a downloaded byte range.
+    /// Returns `false` if it is empty, or runs past the end of the attachment.
+    pub fn insert_chunk(&mut self, offset: u64, chunk: Vec<u8>) -> bool {
+        let end = match offset.checked_add(chunk.len() as u64) {
+            Some(end) => end,
+            None => return false,
+        };
+        if chunk.is_empty() || end > self.total_length {
+            return false;
+        }
+        self.chunks.insert(offset, chunk);
+        true
+    }
+
+    /// The byte ranges not downloaded yet, split into ranges of at most `chunk_size` bytes
+    pub fn missing_ranges(&self, chunk_size: u64) -> Vec<AttachmentRange> {
+        let mut ranges = vec![];
+        let mut add_gap = |mut start: u64, end: u64| {
+            while start < end {
+                let length = cmp::min(chunk_size, end - start);
+                ranges.push(AttachmentRange {
+                    offset: start,
+                    length,
+                });
+                start += length;
+            }
+        };
+        let mut covered = 0;
+        for (offset, chunk) in self.chunks.iter() {
+            add_gap(covered, *offset);
+            covered = cmp::max(covered, offset + chunk.len() as u64);
+        }
+        add_gap(covered, self.total_length);
+        ranges
+    }
+
+    /// The attachment's content, once all of its byte ranges are downloaded
+    pub fn try_reassemble(&self) -> Option<Attachment> {
+        if !self.missing_ranges(self.total_length).is_empty() {
+            return None;
+        }
+        let mut content = Vec::with_capacity(self.total_length as usize);
+        for (offset, chunk) in self.chunks.iter() {
+            // ranges may overlap if peers clipped them differently
+            let skip = (content.len() as u64).saturating_sub(*offset) as usize;
+            if skip < chunk.len() {
+                content.extend_from_slice(&chunk[skip..]);
+            }
+        }
+        Some(Attachment::new(content))
+    }
 }
 
 #[derive(Debug)]
-enum AttachmentsBatchStateMachine {
+pub(crate) enum AttachmentsBatchStateMachine {
     Initialized(AttachmentsBatchStateContext),
     DNSLookup((BatchedDNSLookupsState, AttachmentsBatchStateContext)),
     DownloadingAttachmentsInv(
@@ -600,16 +1610,83 @@ impl AttachmentsBatchStateMachine {
         AttachmentsBatchStateMachine::Initialized(ctx)
     }
 
+    /// The context of the batch being processed
+    pub fn context(&self) -> &AttachmentsBatchStateContext {
+        self.describe_stage().1
+    }
+
+    /// The context of the batch being processed, as long as attachment inventories can still be
+    /// added to it: once the machine has started downloading attachments, it no longer looks at
+    /// them.
+    pub fn context_for_inventories_mut(&mut self) -> Option<&mut AttachmentsBatchStateContext> {
+        match self {
+            AttachmentsBatchStateMachine::Initialized(context)
+            | AttachmentsBatchStateMachine::DNSLookup((_, context))
+            | AttachmentsBatchStateMachine::DownloadingAttachmentsInv((_, context)) => {
+                Some(context)
+            }
+            AttachmentsBatchStateMachine::DownloadingAttachment(_)
+            | AttachmentsBatchStateMachine::Done(_) => None,
+        }
+    }
+
+    fn describe_stage(&self) -> (AttachmentsBatchStage, &AttachmentsBatchStateContext) {
+        match self {
+            AttachmentsBatchStateMachine::Initialized(context) => {
+                (AttachmentsBatchStage::Initialized, context)
+            }
+            AttachmentsBatchStateMachine::DNSLookup((_, context)) => {
+                (AttachmentsBatchStage::DNSLookup, context)
+            }
+            AttachmentsBatchStateMachine::DownloadingAttachmentsInv((_, context)) => {
+                (AttachmentsBatchStage::DownloadingAttachmentsInv, context)
+            }
+            AttachmentsBatchStateMachine::DownloadingAttachment((_, context)) => {
+                (AttachmentsBatchStage::DownloadingAttachment, context)
+            }
+            AttachmentsBatchStateMachine::Done(context) => (AttachmentsBatchStage::Done, context),
+        }
+    }
+
+    /// Describe the machine's current stage, along with the progress of the stage's sub state machine
+    pub fn describe(&self) -> AttachmentsBatchSnapshot {
+        let (stage, context) = self.describe_stage();
+        let mut snapshot = AttachmentsBatchSnapshot {
+            stage,
+            pending_dns_lookups: vec![],
+            queued_requests: 0,
+            inflight_requests: vec![],
+            succeeded_requests: 0,
+            failed_requests: 0,
+            context: context.describe(),
+        };
+        match self {
+            AttachmentsBatchStateMachine::DNSLookup((dns_lookup_state, _)) => {
+                snapshot.pending_dns_lookups = dns_lookup_state.pending_lookups();
+            }
+            AttachmentsBatchStateMachine::DownloadingAttachmentsInv((requests_state, _)) => {
+                requests_state.describe(&mut snapshot);
+            }
+            AttachmentsBatchStateMachine::DownloadingAttachment((requests_state, _)) => {
+                requests_state.describe(&mut snapshot);
+            }
+            AttachmentsBatchStateMachine::Initialized(_)
+            | AttachmentsBatchStateMachine::Done(_) => {}
+        }
+        snapshot
+    }
+
     /// Runs the state machine one step. The machine transitions through the states sequentially:
     /// `Initialized`, `DNSLookup` (which invokes a sub state machine, `BatchedDNSLookupsState`),
     /// `DownloadingAttachmentsInv`, `DownloadingAttachment`, and `Done`.
-    fn try_proceed(
+    pub(crate) fn try_proceed<N: AttachmentsTransport>(
         fsm: AttachmentsBatchStateMachine,
         dns_client: &mut DNSClient,
-        network: &mut PeerNetwork,
+        transport: &mut N,
     ) -> AttachmentsBatchStateMachine {
         match fsm {
-            AttachmentsBatchStateMachine::Initialized(context) => {
+            AttachmentsBatchStateMachine::Initialized(mut context) => {
+                context.begin_stages();
                 let sub_state = BatchedDNSLookupsState::new(context.get_peers_urls());
                 AttachmentsBatchStateMachine::DNSLookup((sub_state, context))
             }
@@ -620,7 +1697,8 @@ impl AttachmentsBatchStateMachine {
                     &context.connection_options,
                 ) {
                     BatchedDNSLookupsState::Done(ref mut results) => {
-                        let context = context.extend_with_dns_lookups(results);
+                        let mut context = context.extend_with_dns_lookups(results);
+                        context.finish_stage(AttachmentsBatchStage::DNSLookup);
                         let sub_state = {
                             let requests_queue =
                                 context.get_prioritized_attachments_inventory_requests();
@@ -635,16 +1713,17 @@ impl AttachmentsBatchStateMachine {
             }
             AttachmentsBatchStateMachine::DownloadingAttachmentsInv((
                 attachments_invs_requests,
-                context,
+                mut context,
             )) => {
                 match BatchedRequestsState::try_proceed(
                     attachments_invs_requests,
-                    &context.dns_lookups,
-                    network,
+                    &mut context.dns_lookups,
+                    transport,
                     &context.connection_options,
                 ) {
                     BatchedRequestsState::Done(ref mut results) => {
-                        let context = context.extend_with_inventories(results);
+                        let mut context = context.extend_with_inventories(results);
+                        context.finish_stage(AttachmentsBatchStage::DownloadingAttachmentsInv);
                         let sub_state = {
                             let requests_queue = context.get_prioritized_attachments_requests();
                             BatchedRequestsState::BeginRequests(Some(requests_queue), None)
@@ -658,17 +1737,29 @@ impl AttachmentsBatchStateMachine {
             }
             AttachmentsBatchStateMachine::DownloadingAttachment((
                 attachments_requests,
-                context,
+                mut context,
             )) => {
                 match BatchedRequestsState::try_proceed(
                     attachments_requests,
-                    &context.dns_lookups,
-                    network,
+                    &mut context.dns_lookups,
+                    transport,
                     &context.connection_options,
                 ) {
                     BatchedRequestsState::Done(ref mut results) => {
                         let context = context.extend_with_attachments(results);
-                        AttachmentsBatchStateMachine::Done(context)
+                        // Fetch the remaining byte ranges of attachments downloaded in ranges
+                        let requests_queue = context.get_attachment_chunk_requests();
+                        if requests_queue.is_empty() {
+                            let mut context = context;
+                            context.finish_stage(AttachmentsBatchStage::DownloadingAttachment);
+                            AttachmentsBatchStateMachine::Done(context)
+                        } else {
+                            let sub_state =
+                                BatchedRequestsState::BeginRequests(Some(requests_queue), None);
+                            AttachmentsBatchStateMachine::DownloadingAttachment((
+                                sub_state, context,
+                            ))
+                        }
                     }
                     state => AttachmentsBatchStateMachine::DownloadingAttachment((state, context)),
                 }
@@ -681,7 +1772,7 @@ impl AttachmentsBatchStateMachine {
 /// State machine for doing DNS lookups for a list of URLs. The machine progresses linearly through
 /// the states, and advances through calls to `try_proceed`.
 #[derive(Debug)]
-enum BatchedDNSLookupsState {
+pub(crate) enum BatchedDNSLookupsState {
     Initialized(Vec<UrlString>),
     Resolving(Option<BatchedDNSLookupsResults>),
     Done(BatchedDNSLookupsResults),
@@ -692,6 +1783,19 @@ impl BatchedDNSLookupsState {
         BatchedDNSLookupsState::Initialized(urls)
     }
 
+    /// URLs that have not been resolved yet
+    fn pending_lookups(&self) -> Vec<UrlString> {
+        let mut urls = match self {
+            BatchedDNSLookupsState::Initialized(urls) => urls.clone(),
+            BatchedDNSLookupsState::Resolving(Some(results)) => {
+                results.parsed_urls.keys().cloned().collect()
+            }
+            BatchedDNSLookupsState::Resolving(None) | BatchedDNSLookupsState::Done(_) => vec![],
+        };
+        urls.sort();
+        urls
+    }
+
     fn try_proceed(
         fsm: BatchedDNSLookupsState,
         dns_client: &mut DNSClient,
@@ -742,15 +1846,17 @@ impl BatchedDNSLookupsState {
                             }
                         }
                         Some(url::Host::Ipv4(addr)) => {
-                            state.dns_lookups.insert(
+                            state.insert_selected_addrs(
                                 url_str,
-                                Some(vec![SocketAddr::new(IpAddr::V4(addr), port)]),
+                                vec![SocketAddr::new(IpAddr::V4(addr), port)],
+                                connection_options.attachment_address_family,
                             );
                         }
                         Some(url::Host::Ipv6(addr)) => {
-                            state.dns_lookups.insert(
+                            state.insert_selected_addrs(
                                 url_str,
-                                Some(vec![SocketAddr::new(IpAddr::V6(addr), port)]),
+                                vec![SocketAddr::new(IpAddr::V6(addr), port)],
+                                connection_options.attachment_address_family,
                             );
                         }
                         None => {
@@ -775,12 +1881,11 @@ impl BatchedDNSLookupsState {
                 for (url_str, request) in state.parsed_urls.iter() {
                     match dns_client.poll_lookup(&request.host, request.port) {
                         Ok(Some(query_result)) => {
-                            if let Some(dns_result) = state.dns_lookups.get_mut(url_str) {
+                            if state.dns_lookups.contains_key(url_str) {
                                 // solicited
                                 match query_result.result {
                                     Ok(addrs) => {
-                                        *dns_result = Some(addrs);
-                                        completed_lookups.push(url_str.clone());
+                                        completed_lookups.push((url_str.clone(), addrs));
                                     }
                                     Err(msg) => {
                                         warn!(
@@ -804,11 +1909,16 @@ impl BatchedDNSLookupsState {
                 // Remove urls that have successfully been looked up by the DNS client.
                 // If not removed, `poll_lookup` will return an error in successive calls of this
                 // function, when trying to process remaining inflight requests.
-                for url_str in completed_lookups.iter() {
+                for (url_str, addrs) in completed_lookups.into_iter() {
                     state
                         .parsed_urls
-                        .remove(url_str)
+                        .remove(&url_str)
                         .expect("BUG: had key but then didn't");
+                    state.insert_selected_addrs(
+                        url_str,
+                        addrs,
+                        connection_options.attachment_address_family,
+                    );
                 }
 
                 if inflight > 0 {
@@ -828,17 +1938,180 @@ impl BatchedDNSLookupsState {
 }
 
 #[derive(Debug)]
-enum BatchedRequestsState<T: Ord + Requestable + fmt::Display + std::hash::Hash> {
+pub(crate) enum BatchedRequestsState<T: Ord + Requestable + fmt::Display + std::hash::Hash> {
     BeginRequests(Option<BinaryHeap<T>>, Option<BatchedRequestsResult<T>>),
     PollRequests(Option<BinaryHeap<T>>, Option<BatchedRequestsResult<T>>),
     Done(BatchedRequestsResult<T>),
 }
 
-impl<T: Ord + Requestable + fmt::Display + std::hash::Hash> BatchedRequestsState<T> {
-    fn try_proceed(
-        fsm: BatchedRequestsState<T>,
+impl<T: Ord + Clone + Requestable + fmt::Display + std::hash::Hash> BatchedRequestsState<T> {
+    /// Fill in the request progress of `snapshot`
+    fn describe(&self, snapshot: &mut AttachmentsBatchSnapshot) {
+        let (queue, results) = match self {
+            BatchedRequestsState::BeginRequests(queue, results)
+            | BatchedRequestsState::PollRequests(queue, results) => {
+                (queue.as_ref(), results.as_ref())
+            }
+            BatchedRequestsState::Done(results) => (None, Some(results)),
+        };
+        snapshot.queued_requests = queue.map(|queue| queue.len()).unwrap_or(0);
+        if let Some(results) = results {
+            let mut inflight_requests: Vec<_> = results
+                .remaining
+                .iter()
+                .map(|(event_id, request)| InflightRequestSnapshot {
+                    event_id: *event_id,
+                    request: request.to_string(),
+                })
+                .collect();
+            inflight_requests.sort_by_key(|request| request.event_id);
+            snapshot.inflight_requests = inflight_requests;
+            snapshot.succeeded_requests = results.succeeded.len();
+            snapshot.failed_requests = results.errors.len() + results.faulty_peers.len();
+        }
+    }
+
+    /// Handle a request that the peer at `peer_url` answered with an HTTP redirect.  Unless the
+    /// request has already been redirected `max_attachment_redirects` times, it is sent on to the
+    /// peer's new data URL, provided that URL does not need a DNS lookup first and its address
+    /// is of an allowed family.  Either way, the move is recorded so that the next batch uses
+    /// the new data URL.
+    fn follow_redirect(
+        request: T,
+        peer_url: UrlString,
+        response: &StacksHttpResponse,
+        state: &mut BatchedRequestsResult<T>,
+        dns_lookups: &mut HashMap<UrlString, Option<Vec<SocketAddr>>>,
+        connection_options: &ConnectionOptions,
+        retries: &mut Vec<T>,
+    ) {
+        let new_url = match response
+            .preamble()
+            .get_header("location".into())
+            .and_then(|location| redirected_data_url(&peer_url, &location))
+        {
+            Some(new_url) => new_url,
+            None => {
+                debug!(
+                    "Atlas: Request {} was redirected, but not to another data URL",
+                    request
+                );
+                retries.extend(request.failover(&peer_url));
+                return;
+            }
+        };
+        let redirected = match request.redirect(
+            &peer_url,
+            &new_url,
+            connection_options.max_attachment_redirects,
+        ) {
+            Some(redirected) => redirected,
+            None => {
+                debug!(
+                    "Atlas: Request {} was redirected to {} once too often",
+                    request, &new_url
+                );
+                retries.extend(request.failover(&peer_url));
+                return;
+            }
+        };
+
+        if !dns_lookups.contains_key(&new_url) {
+            if let Some(addr) = ip_literal_lookup(&new_url) {
+                if connection_options
+                    .attachment_address_family
+                    .allows(AddressFamily::of(&addr))
+                {
+                    dns_lookups.insert(new_url.clone(), Some(vec![addr]));
+                }
+            }
+        }
+        if let Some(Some(_)) = dns_lookups.get(&new_url) {
+            debug!("Atlas: Request {} was redirected to {}", request, &new_url);
+            retries.push(redirected);
+        } else {
+            debug!(
+                "Atlas: Request {} was redirected to {}, which has not been looked up",
+                request, &new_url
+            );
+            retries.extend(request.failover(&peer_url));
+        }
+        state.redirected.insert(peer_url, new_url);
+    }
+
+    /// Send `requestable` through `transport` over one address family of its peer at a time, in
+    /// the order in which the peer's addresses are listed.  Returns the request, the event ID to
+    /// poll it with, and the family it was sent over, if the peer's addresses are known.
+    fn begin_request_by_family<N: AttachmentsTransport>(
+        transport: &mut N,
         dns_lookups: &HashMap<UrlString, Option<Vec<SocketAddr>>>,
-        network: &mut PeerNetwork,
+        requestable: T,
+    ) -> Option<(T, usize, Option<AddressFamily>)> {
+        let addrs = match dns_lookups.get(requestable.get_url()) {
+            Some(Some(addrs)) => addrs,
+            _ => {
+                return transport
+                    .begin_request(dns_lookups, requestable)
+                    .map(|(request, event_id)| (request, event_id, None));
+            }
+        };
+        let mut families = vec![];
+        for addr in addrs.iter() {
+            let family = AddressFamily::of(addr);
+            if !families.contains(&family) {
+                families.push(family);
+            }
+        }
+        for family in families.into_iter() {
+            let family_addrs = addrs
+                .iter()
+                .filter(|addr| AddressFamily::of(addr) == family)
+                .cloned()
+                .collect();
+            let mut family_lookups = HashMap::new();
+            family_lookups.insert(requestable.get_url().clone(), Some(family_addrs));
+            if let Some((request, event_id)) =
+                transport.begin_request(&family_lookups, requestable.clone())
+            {
+                return Some((request, event_id, Some(family)));
+            }
+        }
+        None
+    }
+
+    /// After a request to `peer_url` failed to connect over `family`, put the peer's addresses
+    /// of the other family first, so that the request can be retried over it.  Returns `false`
+    /// if the peer has no address of the other family, or if that family failed too.
+    fn fall_back_to_other_family(
+        state: &mut BatchedRequestsResult<T>,
+        dns_lookups: &mut HashMap<UrlString, Option<Vec<SocketAddr>>>,
+        peer_url: &UrlString,
+        family: AddressFamily,
+    ) -> bool {
+        state.failed_families.insert((peer_url.clone(), family));
+        if state
+            .failed_families
+            .contains(&(peer_url.clone(), family.other()))
+        {
+            return false;
+        }
+        match dns_lookups.get_mut(peer_url) {
+            Some(Some(addrs))
+                if addrs
+                    .iter()
+                    .any(|addr| AddressFamily::of(addr) == family.other()) =>
+            {
+                demote_address_family(addrs, family);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn try_proceed<N: AttachmentsTransport>(
+        fsm: BatchedRequestsState<T>,
+        dns_lookups: &mut HashMap<UrlString, Option<Vec<SocketAddr>>>,
+        transport: &mut N,
         connection_options: &ConnectionOptions,
     ) -> BatchedRequestsState<T> {
         let mut fsm = fsm;
@@ -858,11 +2131,13 @@ impl<T: Ord + Requestable + fmt::Display + std::hash::Hash> BatchedRequestsState
                 // so we will be batching our requests.
                 for _ in 0..connection_options.max_inflight_attachments {
                     if let Some(requestable) = queue.pop() {
-                        let mut requestables = VecDeque::new();
-                        requestables.push_back(requestable);
-                        let res =
-                            PeerNetwork::begin_request(network, dns_lookups, &mut requestables);
-                        if let Some((request, event_id)) = res {
+                        if let Some((request, event_id, family)) =
+                            Self::begin_request_by_family(transport, dns_lookups, requestable)
+                        {
+                            results.sent_at_ms.insert(event_id, transport.now_ms());
+                            if let Some(family) = family {
+                                results.address_families.insert(event_id, family);
+                            }
                             results.remaining.insert(event_id, request);
                         }
                     }
@@ -882,55 +2157,122 @@ impl<T: Ord + Requestable + fmt::Display + std::hash::Hash> BatchedRequestsState
                     state.remaining.len()
                 );
 
-                PeerNetwork::with_http(network, |_, ref mut http| {
-                    for (event_id, request) in state.remaining.drain() {
-                        match http.get_conversation(event_id) {
-                            None => {
-                                if http.is_connecting(event_id) {
-                                    debug!(
-                                        "Atlas: Request {} (event_id: {}) is still connecting",
-                                        request, event_id
-                                    );
-                                    pending_requests.insert(event_id, request);
-                                } else {
+                // requests that failed, to be sent to another peer that can serve them
+                let mut retries = vec![];
+                // poll in a fixed order, so that a replayed batch always proceeds the same way
+                let mut remaining: Vec<_> = state.remaining.drain().collect();
+                remaining.sort_by_key(|(event_id, _)| *event_id);
+                for (event_id, request) in remaining.into_iter() {
+                    let response = match transport.poll_request(event_id) {
+                        RequestPoll::Connecting => {
+                            debug!(
+                                "Atlas: Request {} (event_id: {}) is still connecting",
+                                request, event_id
+                            );
+                            pending_requests.insert(event_id, request);
+                            continue;
+                        }
+                        RequestPoll::Failed => {
+                            state.sent_at_ms.remove(&event_id);
+                            let peer_url = request.get_url().clone();
+                            if let Some(family) = state.address_families.remove(&event_id) {
+                                state.record_family_outcome(peer_url.clone(), family, false);
+                                if Self::fall_back_to_other_family(
+                                    state,
+                                    dns_lookups,
+                                    &peer_url,
+                                    family,
+                                ) {
                                     debug!(
-                                        "Atlas: Request {} (event_id: {}) failed to connect. Temporarily blocking URL",
+                                        "Atlas: Request {} (event_id: {}) failed to connect over {}. Retrying over {}",
                                         request,
-                                        event_id
+                                        event_id,
+                                        family,
+                                        family.other()
                                     );
-                                    let peer_url = request.get_url().clone();
+                                    retries.push(request);
                                     state.faulty_peers.insert(event_id, peer_url);
+                                    continue;
                                 }
                             }
-                            Some(ref mut convo) => {
-                                match convo.try_get_response() {
-                                    None => {
-                                        // still waiting
-                                        debug!(
-                                            "Atlas: Request {} (event_id: {}) is still waiting for a response",
-                                            request,
-                                            event_id
-                                        );
-                                        pending_requests.insert(event_id, request);
-                                        continue;
-                                    }
-                                    Some(response) => {
-                                        let peer_url = request.get_url().clone();
-                                        if response.preamble().status_code == 404 {
-                                            state.faulty_peers.insert(event_id, peer_url);
-                                            continue;
-                                        }
-                                        debug!(
-                                            "Atlas: Request {} (event_id: {}) received HTTP 200",
-                                            request, event_id
-                                        );
-                                        state.succeeded.insert(request, Some(response));
-                                    }
-                                }
-                            }
+                            debug!(
+                                "Atlas: Request {} (event_id: {}) failed to connect. Temporarily blocking URL",
+                                request,
+                                event_id
+                            );
+                            retries.extend(request.failover(&peer_url));
+                            state.faulty_peers.insert(event_id, peer_url);
+                            continue;
+                        }
+                        RequestPoll::Waiting => {
+                            // still waiting
+                            debug!(
+                                "Atlas: Request {} (event_id: {}) is still waiting for a response",
+                                request, event_id
+                            );
+                            pending_requests.insert(event_id, request);
+                            continue;
                         }
+                        RequestPoll::Response(response) => response,
+                    };
+                    let sent_at_ms = state.sent_at_ms.remove(&event_id);
+                    let peer_url = request.get_url().clone();
+                    if let Some(family) = state.address_families.remove(&event_id) {
+                        state.record_family_outcome(peer_url.clone(), family, true);
                     }
-                });
+                    if response.preamble().status_code == 429 {
+                        // Not the peer's fault -- we asked too much of it -- so the peer
+                        // is not marked as faulty.  Back off for as long as it told us to.
+                        let retry_after = response
+                            .preamble()
+                            .get_header("retry-after".into())
+                            .and_then(|value| value.parse::<u64>().ok())
+                            .unwrap_or(ATLAS_RATE_LIMIT_WINDOW_SECS);
+                        debug!(
+                            "Atlas: Request {} (event_id: {}) was throttled; retry in {}s",
+                            request, event_id, retry_after
+                        );
+                        retries.extend(request.failover(&peer_url));
+                        state.throttled.insert(peer_url, retry_after);
+                        continue;
+                    }
+                    if is_redirect(response.preamble().status_code) {
+                        state.faulty_peers.insert(event_id, peer_url.clone());
+                        Self::follow_redirect(
+                            request,
+                            peer_url,
+                            &response,
+                            state,
+                            dns_lookups,
+                            connection_options,
+                            &mut retries,
+                        );
+                        continue;
+                    }
+                    if response.preamble().status_code == 404 {
+                        retries.extend(request.failover(&peer_url));
+                        state.faulty_peers.insert(event_id, peer_url);
+                        state.not_found.insert(request);
+                        continue;
+                    }
+                    debug!(
+                        "Atlas: Request {} (event_id: {}) received HTTP 200",
+                        request, event_id
+                    );
+                    if let Some(sent_at_ms) = sent_at_ms {
+                        let sample =
+                            ResponseSample::measure(&response, sent_at_ms, transport.now_ms());
+                        state.response_samples.insert(request.clone(), sample);
+                    }
+                    state.succeeded.insert(request, Some(response));
+                }
+
+                if let Some(queue) = queue.as_mut() {
+                    for retry in retries.into_iter() {
+                        debug!("Atlas: will retry {}", retry);
+                        queue.push(retry);
+                    }
+                }
 
                 if pending_requests.len() > 0 {
                     // We need to keep polling
@@ -961,6 +2303,227 @@ impl<T: Ord + Requestable + fmt::Display + std::hash::Hash> BatchedRequestsState
     }
 }
 
+/// The state of an HTTP request sent through an `AttachmentsTransport`
+#[derive(Debug)]
+pub enum RequestPoll {
+    /// The connection to the peer is still being established
+    Connecting,
+    /// The request could not be sent, or the connection was lost
+    Failed,
+    /// The request was sent, and the response has not arrived yet
+    Waiting,
+    /// The peer's response
+    Response(StacksHttpResponse),
+}
+
+/// How `AttachmentsBatchStateMachine` sends its HTTP requests and collects the responses.
+/// The `PeerNetwork` sends them to peers; `atlas::simulate::FixtureTransport` replays recorded
+/// responses instead.
+pub trait AttachmentsTransport {
+    /// Start sending `request` to its peer, which must have been looked up in `dns_lookups`.
+    /// Returns the request along with the event ID to poll it with, or `None` if it could not
+    /// be sent.
+    fn begin_request<T: Requestable>(
+        &mut self,
+        dns_lookups: &HashMap<UrlString, Option<Vec<SocketAddr>>>,
+        request: T,
+    ) -> Option<(T, usize)>;
+
+    /// Check on the request sent with the given event ID
+    fn poll_request(&mut self, event_id: usize) -> RequestPoll;
+
+    /// The current time in milliseconds, by which the latency of responses is measured
+    fn now_ms(&self) -> u64 {
+        u64::try_from(get_epoch_time_ms()).unwrap_or(u64::MAX)
+    }
+}
+
+impl AttachmentsTransport for PeerNetwork {
+    fn begin_request<T: Requestable>(
+        &mut self,
+        dns_lookups: &HashMap<UrlString, Option<Vec<SocketAddr>>>,
+        request: T,
+    ) -> Option<(T, usize)> {
+        let mut requestables = VecDeque::new();
+        requestables.push_back(request);
+        PeerNetwork::begin_request(self, dns_lookups, &mut requestables)
+    }
+
+    fn poll_request(&mut self, event_id: usize) -> RequestPoll {
+        PeerNetwork::with_http(self, |_, http| {
+            if let Some(convo) = http.get_conversation(event_id) {
+                return match convo.try_get_response() {
+                    None => RequestPoll::Waiting,
+                    Some(response) => RequestPoll::Response(response),
+                };
+            }
+            if http.is_connecting(event_id) {
+                RequestPoll::Connecting
+            } else {
+                RequestPoll::Failed
+            }
+        })
+    }
+}
+
+/// Is `status_code` an HTTP redirect to another location?
+fn is_redirect(status_code: u16) -> bool {
+    matches!(status_code, 301 | 302 | 303 | 307 | 308)
+}
+
+/// The data URL that the peer at `peer_url` moved to, given the `Location` it redirected a
+/// request to.  Atlas request paths are fixed, so only the scheme, host and port of the location
+/// are kept, and a redirect to another path on the same host is not followed.
+pub fn redirected_data_url(peer_url: &UrlString, location: &str) -> Option<UrlString> {
+    let current = peer_url.parse_to_block_url().ok()?;
+    let target = current.join(location).ok()?;
+    if target.origin() == current.origin() {
+        return None;
+    }
+    let data_url = UrlString::try_from(target.origin().ascii_serialization()).ok()?;
+    data_url.parse_to_block_url().ok()?;
+    Some(data_url)
+}
+
+/// The data URL that the peer at `peer_url` advertised in `response`, if it is not `peer_url`
+pub fn advertised_data_url(
+    peer_url: &UrlString,
+    response: &StacksHttpResponse,
+) -> Option<UrlString> {
+    let advertised = response
+        .preamble()
+        .get_header(ATLAS_DATA_URL_HEADER.into())?;
+    let data_url = UrlString::try_from(advertised).ok()?;
+    if data_url.parse_to_block_url().ok()? == peer_url.parse_to_block_url().ok()? {
+        return None;
+    }
+    Some(data_url)
+}
+
+/// The socket address of a data URL whose host is an IP address, which needs no DNS lookup
+fn ip_literal_lookup(url_str: &UrlString) -> Option<SocketAddr> {
+    let url = url_str.parse_to_block_url().ok()?;
+    let port = url.port_or_known_default()?;
+    match url.host()? {
+        url::Host::Ipv4(addr) => Some(SocketAddr::new(IpAddr::V4(addr), port)),
+        url::Host::Ipv6(addr) => Some(SocketAddr::new(IpAddr::V6(addr), port)),
+        url::Host::Domain(_) => None,
+    }
+}
+
+/// The stage an `AttachmentsBatchStateMachine` is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttachmentsBatchStage {
+    Initialized,
+    DNSLookup,
+    DownloadingAttachmentsInv,
+    DownloadingAttachment,
+    Done,
+}
+
+/// A request that has been sent, and is awaiting a response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InflightRequestSnapshot {
+    pub event_id: usize,
+    pub request: String,
+}
+
+/// Snapshot of an `AttachmentsBatchStateContext`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttachmentsBatchContextSnapshot {
+    pub stacks_block_height: u64,
+    pub index_block_hash: StacksBlockId,
+    pub retry_count: u64,
+    /// Number of attachment instances in the batch that are not resolved yet
+    pub missing_attachments: usize,
+    /// Number of those instances by the kind of block they were mined in (see `AttachmentOrigin`)
+    #[serde(default)]
+    pub missing_attachments_by_origin: BTreeMap<String, usize>,
+    pub peers: Vec<UrlString>,
+    pub resolved_dns_lookups: usize,
+    /// Number of attachment inventories received from peers
+    pub inventories: usize,
+    pub downloaded_attachments: usize,
+    pub events_to_deregister: Vec<usize>,
+    /// Time spent in each stage of the batch that has completed so far
+    pub stage_timings: AttachmentsBatchStageTimings,
+}
+
+/// Wall-clock time, in milliseconds, that an `AttachmentsBatchStateMachine` spent in each of
+/// the stages that do work. Byte-range downloads count towards `attachments_ms`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentsBatchStageTimings {
+    pub dns_lookup_ms: u64,
+    pub attachments_inv_ms: u64,
+    pub attachments_ms: u64,
+}
+
+impl AttachmentsBatchStageTimings {
+    /// Charge `elapsed` to `stage`. Time spent in `Initialized` or `Done` is not recorded.
+    pub fn record(&mut self, stage: AttachmentsBatchStage, elapsed: Duration) {
+        let elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+        let timing = match stage {
+            AttachmentsBatchStage::DNSLookup => &mut self.dns_lookup_ms,
+            AttachmentsBatchStage::DownloadingAttachmentsInv => &mut self.attachments_inv_ms,
+            AttachmentsBatchStage::DownloadingAttachment => &mut self.attachments_ms,
+            AttachmentsBatchStage::Initialized | AttachmentsBatchStage::Done => return,
+        };
+        *timing = timing.saturating_add(elapsed_ms);
+    }
+
+    /// The timings of each stage, labelled with the stage's name
+    pub fn stages(&self) -> [(&'static str, u64); 3] {
+        [
+            ("dns_lookup", self.dns_lookup_ms),
+            ("attachments_inv", self.attachments_inv_ms),
+            ("attachments", self.attachments_ms),
+        ]
+    }
+
+    pub fn total_ms(&self) -> u64 {
+        self.stages()
+            .iter()
+            .fold(0u64, |total, (_, ms)| total.saturating_add(*ms))
+    }
+}
+
+/// Snapshot of an `AttachmentsBatchStateMachine`.
+/// The request counters describe the requests of the current stage only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttachmentsBatchSnapshot {
+    pub stage: AttachmentsBatchStage,
+    pub pending_dns_lookups: Vec<UrlString>,
+    pub queued_requests: usize,
+    pub inflight_requests: Vec<InflightRequestSnapshot>,
+    pub succeeded_requests: usize,
+    pub failed_requests: usize,
+    pub context: AttachmentsBatchContextSnapshot,
+}
+
+/// Snapshot of an `AttachmentsDownloader`, served by `GET /v2/attachments/downloader`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttachmentsDownloaderSnapshot {
+    pub initial_batch_size: usize,
+    pub queued_batches: usize,
+    /// Number of operator-requested batches waiting to run ahead of `queued_batches`
+    pub on_demand_batches: usize,
+    pub processed_batches: usize,
+    pub not_found_cache_size: usize,
+    pub ongoing_batch: Option<AttachmentsBatchSnapshot>,
+    /// Number of batches handed back to the AtlasDB because too many were queued
+    #[serde(default)]
+    pub spilled_batches: u64,
+    /// Number of batches given up on after exhausting their retries
+    #[serde(default)]
+    pub dropped_batches: u64,
+    /// Number of queued attachment instances dropped because a newer instance superseded them
+    #[serde(default)]
+    pub superseded_instances: u64,
+    /// Rough estimate of the memory held by the downloader's batches
+    #[serde(default)]
+    pub estimated_memory_bytes: u64,
+}
+
 #[derive(Debug, Default)]
 pub struct BatchedDNSLookupsResults {
     pub parsed_urls: HashMap<UrlString, DNSRequest>,
@@ -968,6 +2531,42 @@ pub struct BatchedDNSLookupsResults {
     pub errors: HashMap<UrlString, net_error>,
 }
 
+impl BatchedDNSLookupsResults {
+    /// Record the addresses `url_str` resolved to, keeping those of the address families that
+    /// `preference` allows, in the order it prefers them.  A peer left without an address is
+    /// recorded as a failed lookup that says why, rather than being passed over silently.
+    fn insert_selected_addrs(
+        &mut self,
+        url_str: UrlString,
+        addrs: Vec<SocketAddr>,
+        preference: AddressFamilyPreference,
+    ) {
+        let resolved = addrs.len();
+        let addrs = preference.select_addrs(addrs);
+        if addrs.is_empty() {
+            let reason = if resolved == 0 {
+                format!("{} did not resolve to any address", &url_str)
+            } else {
+                format!(
+                    "none of the {} addresses of {} can be used with address family preference {:?}",
+                    resolved, &url_str, preference
+                )
+            };
+            warn!("Atlas: no usable address for peer"; "url" => %url_str, "reason" => %reason);
+            self.dns_lookups.insert(url_str.clone(), None);
+            self.errors.insert(url_str, net_error::LookupError(reason));
+            return;
+        }
+        self.dns_lookups.insert(url_str, Some(addrs));
+    }
+}
+
+/// Move the addresses of `family` behind the other family's, keeping the order of the addresses
+/// within each family
+pub fn demote_address_family(addrs: &mut [SocketAddr], family: AddressFamily) {
+    addrs.sort_by_key(|addr| AddressFamily::of(addr) == family);
+}
+
 #[derive(Debug, Clone)]
 struct BatchedRequestsInitializedState<T: Ord + Requestable> {
     pub queue: BinaryHeap<T>,
@@ -979,15 +2578,86 @@ pub struct BatchedRequestsResult<T: Requestable> {
     pub succeeded: HashMap<T, Option<StacksHttpResponse>>,
     pub errors: HashMap<T, net_error>,
     pub faulty_peers: HashMap<usize, UrlString>,
+    pub not_found: HashSet<T>,
+    /// Peers that answered with HTTP 429, and how many seconds they asked us to wait
+    pub throttled: HashMap<UrlString, u64>,
+    /// Peers that redirected us to another data URL, and that URL
+    pub redirected: HashMap<UrlString, UrlString>,
+    /// When each request still in `remaining` was sent, by the transport's clock
+    pub sent_at_ms: HashMap<usize, u64>,
+    /// How long each request in `succeeded` took to be answered, and how large its response was
+    pub response_samples: HashMap<T, ResponseSample>,
+    /// The address family each request still in `remaining` was sent over
+    pub address_families: HashMap<usize, AddressFamily>,
+    /// How often connecting to each peer over each address family worked
+    pub family_reports: HashMap<(UrlString, AddressFamily), ReliabilityReport>,
+    /// The peers' address families that failed to connect, and are not fallen back to
+    pub failed_families: HashSet<(UrlString, AddressFamily)>,
+}
+
+/// How long a peer took to answer a request, and how large its response was
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseSample {
+    pub latency_ms: u64,
+    pub num_bytes: u64,
+}
+
+impl ResponseSample {
+    /// Measure the response to a request sent at `sent_at_ms`, received at `now_ms`
+    fn measure(response: &StacksHttpResponse, sent_at_ms: u64, now_ms: u64) -> ResponseSample {
+        let num_bytes = response
+            .preamble()
+            .content_length
+            .or_else(|| response.body().try_content_length())
+            .unwrap_or(0);
+        ResponseSample {
+            latency_ms: now_ms.saturating_sub(sent_at_ms),
+            num_bytes: u64::from(num_bytes),
+        }
+    }
+}
+
+/// Credit `report` with a successful request, timed by `sample` if it was measured
+fn record_success(report: &mut ReliabilityReport, sample: Option<ResponseSample>) {
+    match sample {
+        Some(sample) => report.record_response(sample.latency_ms, sample.num_bytes),
+        None => report.bump_successful_requests(),
+    }
 }
 
 impl<T: Requestable> BatchedRequestsResult<T> {
+    /// Record whether connecting to `peer_url` over `family` worked
+    fn record_family_outcome(
+        &mut self,
+        peer_url: UrlString,
+        family: AddressFamily,
+        connected: bool,
+    ) {
+        let report = self
+            .family_reports
+            .entry((peer_url, family))
+            .or_insert_with(ReliabilityReport::empty);
+        if connected {
+            report.bump_successful_requests();
+        } else {
+            report.bump_failed_requests();
+        }
+    }
+
     pub fn new(remaining: HashMap<usize, T>) -> BatchedRequestsResult<T> {
         BatchedRequestsResult {
             remaining,
             succeeded: HashMap::new(),
             errors: HashMap::new(),
             faulty_peers: HashMap::new(),
+            not_found: HashSet::new(),
+            throttled: HashMap::new(),
+            redirected: HashMap::new(),
+            sent_at_ms: HashMap::new(),
+            response_samples: HashMap::new(),
+            address_families: HashMap::new(),
+            family_reports: HashMap::new(),
+            failed_families: HashSet::new(),
         }
     }
 
@@ -997,6 +2667,14 @@ impl<T: Requestable> BatchedRequestsResult<T> {
             succeeded: HashMap::new(),
             errors: HashMap::new(),
             faulty_peers: HashMap::new(),
+            not_found: HashSet::new(),
+            throttled: HashMap::new(),
+            redirected: HashMap::new(),
+            sent_at_ms: HashMap::new(),
+            response_samples: HashMap::new(),
+            address_families: HashMap::new(),
+            family_reports: HashMap::new(),
+            failed_families: HashSet::new(),
         }
     }
 }
@@ -1010,6 +2688,11 @@ pub struct AttachmentsInventoryRequest {
     pub index_block_hash: StacksBlockId,
     pub reliability_report: ReliabilityReport,
     pub canonical_stacks_tip_height: Option<u64>,
+    /// Number of HTTP redirects followed to get to `url`
+    pub redirects: u64,
+    /// Digests of the versions of the pages received from this peer on previous attempts, so
+    /// that the peer only sends back the pages that have changed since
+    pub since: Vec<(u32, Hash160)>,
 }
 
 impl Hash for AttachmentsInventoryRequest {
@@ -1049,27 +2732,28 @@ impl Requestable for AttachmentsInventoryRequest {
     }
 
     fn make_request_type(&self, peer_host: PeerHost) -> StacksHttpRequest {
-        let mut page_indexes = HashSet::new();
-        for page in self.pages.iter() {
-            page_indexes.insert(*page);
-        }
-        let mut page_list: Vec<String> = page_indexes
-            .into_iter()
-            .map(|i| format!("{}", &i))
-            .collect();
-        page_list.sort();
-        StacksHttpRequest::new_for_peer(
+        let page_indexes: HashSet<u32> = self.pages.iter().cloned().collect();
+        StacksHttpRequest::new_getattachmentsinv_since(
             peer_host,
-            "GET".into(),
-            "/v2/attachments/inv".into(),
-            HttpRequestContents::new()
-                .query_arg(
-                    "index_block_hash".into(),
-                    format!("{}", &self.index_block_hash),
-                )
-                .query_arg("pages_indexes".into(), page_list[..].join(",")),
+            self.index_block_hash,
+            page_indexes,
+            &self.since,
         )
-        .expect("FATAL: failed to create an HTTP request for infallible data")
+    }
+
+    fn redirect(
+        &self,
+        from_url: &UrlString,
+        to_url: &UrlString,
+        max_redirects: u64,
+    ) -> Option<AttachmentsInventoryRequest> {
+        if self.redirects >= max_redirects || &self.url != from_url {
+            return None;
+        }
+        let mut request = self.clone();
+        request.url = to_url.clone();
+        request.redirects += 1;
+        Some(request)
     }
 }
 
@@ -1080,26 +2764,40 @@ impl std::fmt::Display for AttachmentsInventoryRequest {
     }
 }
 
+/// A byte range of an attachment
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct AttachmentRange {
+    pub offset: u64,
+    pub length: u64,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct AttachmentRequest {
     pub content_hash: Hash160,
     pub sources: HashMap<UrlString, ReliabilityReport>,
     pub stacks_block_height: u64,
     pub canonical_stacks_tip_height: Option<u64>,
+    /// If set, only this byte range of the attachment is requested
+    pub range: Option<AttachmentRange>,
+    /// The peer to send the request to, if not the most reliable of `sources`
+    pub source: Option<UrlString>,
+    /// Number of HTTP redirects followed to get to the peer the request is sent to
+    pub redirects: u64,
 }
 
 impl AttachmentRequest {
     pub fn get_most_reliable_source(&self) -> (&UrlString, &ReliabilityReport) {
         self.sources
             .iter()
-            .max_by_key(|(_, v)| v.score())
+            .max_by(|(_, report), (_, other_report)| report.cmp(other_report))
             .expect("Atlas: trying to select an Url out of an empty set")
     }
 }
 
 impl Hash for AttachmentRequest {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.content_hash.hash(state)
+        self.content_hash.hash(state);
+        self.range.hash(state);
     }
 }
 
@@ -1121,25 +2819,75 @@ impl PartialOrd for AttachmentRequest {
 
 impl Requestable for AttachmentRequest {
     fn get_url(&self) -> &UrlString {
+        if let Some(url) = self.source.as_ref() {
+            return url;
+        }
         let (url, _) = self.get_most_reliable_source();
         url
     }
 
     fn make_request_type(&self, peer_host: PeerHost) -> StacksHttpRequest {
+        let mut contents = HttpRequestContents::new();
+        if let Some(range) = self.range {
+            contents = contents
+                .query_arg("offset".into(), format!("{}", range.offset))
+                .query_arg("length".into(), format!("{}", range.length));
+        }
         StacksHttpRequest::new_for_peer(
             peer_host,
             "GET".to_string(),
             format!("/v2/attachments/{}", &self.content_hash),
-            HttpRequestContents::new(),
+            contents,
         )
         .expect("FATAL: failed to create an HTTP request for infallible data")
     }
+
+    /// Byte ranges are retried from the next most reliable source.  Whole attachments are
+    /// retried with the rest of their batch.
+    fn failover(&self, failed_url: &UrlString) -> Option<AttachmentRequest> {
+        self.range.as_ref()?;
+        let mut request = self.clone();
+        request.sources.remove(failed_url);
+        if request.sources.is_empty() {
+            return None;
+        }
+        request.source = None;
+        Some(request)
+    }
+
+    /// The peer that redirected the request keeps its reliability report at its new URL
+    fn redirect(
+        &self,
+        from_url: &UrlString,
+        to_url: &UrlString,
+        max_redirects: u64,
+    ) -> Option<AttachmentRequest> {
+        if self.redirects >= max_redirects {
+            return None;
+        }
+        let mut request = self.clone();
+        let report = request
+            .sources
+            .remove(from_url)
+            .unwrap_or_else(ReliabilityReport::empty);
+        request.sources.entry(to_url.clone()).or_insert(report);
+        request.source = Some(to_url.clone());
+        request.redirects += 1;
+        Some(request)
+    }
 }
 
 impl std::fmt::Display for AttachmentRequest {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let url = &**self.get_url();
-        write!(f, "<Request<Attachment>: url={}>", url)
+        match self.range {
+            Some(range) => write!(
+                f,
+                "<Request<Attachment>: url={}, offset={}, length={}>",
+                url, range.offset, range.length
+            ),
+            None => write!(f, "<Request<Attachment>: url={}>", url),
+        }
     }
 }
 
@@ -1151,6 +2899,18 @@ pub struct AttachmentsBatch {
     pub attachments_instances: HashMap<QualifiedContractIdentifier, HashMap<u32, Hash160>>,
     pub retry_count: u64,
     pub retry_deadline: u64,
+    /// The bindings of the tracked instances that have one, by (contract, attachment index)
+    #[serde(skip)]
+    pub bindings: HashMap<(QualifiedContractIdentifier, u32), AttachmentBinding>,
+    /// Where the transactions that emitted the tracked instances were mined, by (contract,
+    /// attachment index).  A batch holds the instances of one block, which may have been
+    /// emitted by its own transactions or by those of the microblocks it confirms.
+    #[serde(skip)]
+    pub origins: HashMap<(QualifiedContractIdentifier, u32), AttachmentOrigin>,
+    /// The inventory pages received from each peer on previous attempts, so that retries only
+    /// ask for the pages that have changed since
+    #[serde(skip)]
+    pub known_inventory_pages: HashMap<UrlString, HashMap<u32, AttachmentPage>>,
 }
 
 impl AttachmentsBatch {
@@ -1162,6 +2922,9 @@ impl AttachmentsBatch {
             attachments_instances: HashMap::new(),
             retry_count: 0,
             retry_deadline: 0,
+            bindings: HashMap::new(),
+            origins: HashMap::new(),
+            known_inventory_pages: HashMap::new(),
         }
     }
 
@@ -1180,6 +2943,14 @@ impl AttachmentsBatch {
         }
 
         let inner_key = attachment.attachment_index;
+        if let Some(binding) = attachment.binding() {
+            self.bindings
+                .insert((attachment.contract_id.clone(), inner_key), binding);
+        }
+        self.origins.insert(
+            (attachment.contract_id.clone(), inner_key),
+            attachment.origin.clone(),
+        );
         match self
             .attachments_instances
             .entry(attachment.contract_id.clone())
@@ -1228,6 +2999,26 @@ impl AttachmentsBatch {
         pages_indexes.into_iter().collect()
     }
 
+    /// The p2p request for the pages of a peer's attachment inventory that this batch needs,
+    /// across all of its contracts.  Only the first `MAX_ATTACHMENT_INV_PAGES_PER_REQUEST` pages
+    /// are asked for.  Returns None if no attachment is missing.
+    pub fn get_p2p_inventory_request(&self) -> Option<GetAttachmentsInvData> {
+        let mut pages = vec![];
+        for contract_id in self.attachments_instances.keys() {
+            pages.extend(self.get_missing_pages_for_contract_id(contract_id));
+        }
+        if pages.is_empty() {
+            return None;
+        }
+        pages.sort();
+        pages.dedup();
+        pages.truncate(MAX_ATTACHMENT_INV_PAGES_PER_REQUEST);
+        Some(GetAttachmentsInvData {
+            index_block_hash: self.index_block_hash,
+            pages,
+        })
+    }
+
     pub fn get_paginated_missing_pages_for_contract_id(
         &self,
         contract_id: &QualifiedContractIdentifier,
@@ -1242,7 +3033,7 @@ impl AttachmentsBatch {
     }
 
     pub fn resolve_attachment(&mut self, content_hash: &Hash160) {
-        for missing_attachments in self.attachments_instances.values_mut() {
+        for (contract_id, missing_attachments) in self.attachments_instances.iter_mut() {
             let mut keys = vec![];
             for (k, hash) in missing_attachments.iter() {
                 if hash == content_hash {
@@ -1251,8 +3042,35 @@ impl AttachmentsBatch {
             }
             for key in keys {
                 missing_attachments.remove(&key);
+                self.bindings.remove(&(contract_id.clone(), key));
+                self.origins.remove(&(contract_id.clone(), key));
+            }
+        }
+    }
+
+    /// Stop tracking the instances superseded by a newer instance, given as the highest
+    /// attachment index seen for each (contract, binding).
+    /// Returns the number of instances no longer tracked.
+    pub fn prune_superseded(
+        &mut self,
+        latest: &HashMap<(QualifiedContractIdentifier, AttachmentBinding), u32>,
+    ) -> usize {
+        let mut superseded = vec![];
+        for ((contract_id, attachment_index), binding) in self.bindings.iter() {
+            let key = (contract_id.clone(), binding.clone());
+            if matches!(latest.get(&key), Some(latest_index) if latest_index > attachment_index) {
+                superseded.push((contract_id.clone(), *attachment_index));
+            }
+        }
+        for key in superseded.iter() {
+            self.bindings.remove(key);
+            self.origins.remove(key);
+            let (contract_id, attachment_index) = key;
+            if let Some(missing_attachments) = self.attachments_instances.get_mut(contract_id) {
+                missing_attachments.remove(attachment_index);
             }
         }
+        superseded.len()
     }
 
     pub fn attachments_instances_count(&self) -> usize {
@@ -1260,6 +3078,44 @@ impl AttachmentsBatch {
             .values()
             .fold(0, |count, a| count + a.len())
     }
+
+    /// Number of tracked instances by the kind of block they were mined in
+    pub fn origin_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for origin in self.origins.values() {
+            *counts.entry(origin.kind().to_string()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Rough estimate of the memory, in bytes, held by this batch
+    pub fn estimated_memory_usage(&self) -> usize {
+        let instance_size = mem::size_of::<u32>()
+            + mem::size_of::<Hash160>()
+            + mem::size_of::<(QualifiedContractIdentifier, u32)>()
+            + mem::size_of::<AttachmentOrigin>();
+        let instances_size = self.attachments_instances.iter().fold(
+            mem::size_of::<AttachmentsBatch>(),
+            |size, (contract_id, missing_attachments)| {
+                size + mem::size_of::<QualifiedContractIdentifier>()
+                    + usize::from(contract_id.name.len())
+                    + mem::size_of::<HashMap<u32, Hash160>>()
+                    + missing_attachments.capacity() * instance_size
+            },
+        );
+        let known_pages_size = self
+            .known_inventory_pages
+            .iter()
+            .map(|(peer_url, pages)| {
+                usize::from(peer_url.len())
+                    + pages
+                        .values()
+                        .map(|page| mem::size_of::<AttachmentPage>() + page.inventory.len())
+                        .sum::<usize>()
+            })
+            .sum::<usize>();
+        instances_size + known_pages_size
+    }
 }
 
 impl Ord for AttachmentsBatch {
@@ -1281,10 +3137,88 @@ impl PartialOrd for AttachmentsBatch {
     }
 }
 
+/// Negative cache of (peer URL, content hash) pairs for which the peer answered 404.
+/// Entries expire after `ttl` seconds, after which the peer may be asked again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentsNotFoundCache {
+    entries: HashMap<(UrlString, Hash160), u64>,
+    ttl: u64,
+}
+
+impl AttachmentsNotFoundCache {
+    pub fn new(ttl: u64) -> AttachmentsNotFoundCache {
+        AttachmentsNotFoundCache {
+            entries: HashMap::new(),
+            ttl,
+        }
+    }
+
+    pub fn set_ttl(&mut self, ttl: u64) {
+        self.ttl = ttl;
+    }
+
+    /// Remember that `peer_url` does not have `content_hash`
+    pub fn insert(&mut self, peer_url: UrlString, content_hash: Hash160) {
+        let expires_at = get_epoch_time_secs().saturating_add(self.ttl);
+        self.entries.insert((peer_url, content_hash), expires_at);
+    }
+
+    /// Is there an unexpired record of `peer_url` not having `content_hash`?
+    pub fn contains(&self, peer_url: &UrlString, content_hash: &Hash160) -> bool {
+        match self.entries.get(&(peer_url.clone(), *content_hash)) {
+            Some(expires_at) => *expires_at > get_epoch_time_secs(),
+            None => false,
+        }
+    }
+
+    pub fn evict_expired(&mut self) {
+        let now = get_epoch_time_secs();
+        self.entries.retain(|_, expires_at| *expires_at > now);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Peers' response latencies within this many milliseconds of each other are considered
+/// equally fast, so that peers with similar latencies are told apart by their throughput
+const RELIABILITY_LATENCY_BUCKET_MS: u64 = 100;
+/// Peers are first ranked by the fraction of their requests that succeeded, in this many tiers
+const RELIABILITY_SUCCESS_TIERS: u32 = 10;
+/// A new sample counts for 1/`RELIABILITY_AVERAGE_WEIGHT` of the moving averages of a
+/// `ReliabilityReport`
+const RELIABILITY_AVERAGE_WEIGHT: u64 = 4;
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ReliabilityReport {
     pub total_requests_sent: u32,
     pub total_requests_success: u32,
+    /// Moving average of the time the peer took to answer our requests, in milliseconds.
+    /// None until the peer has answered one.
+    #[serde(default)]
+    pub avg_latency_ms: Option<u64>,
+    /// Moving average of the rate at which the peer sent its responses, in bytes per second.
+    /// None until the peer has answered one.
+    #[serde(default)]
+    pub avg_throughput_bps: Option<u64>,
+}
+
+/// Update the moving average `avg` with `sample`
+fn update_moving_average(avg: Option<u64>, sample: u64) -> u64 {
+    match avg {
+        None => sample,
+        Some(avg) => {
+            let new_avg = (u128::from(avg) * u128::from(RELIABILITY_AVERAGE_WEIGHT - 1)
+                + u128::from(sample))
+                / u128::from(RELIABILITY_AVERAGE_WEIGHT);
+            u64::try_from(new_avg).unwrap_or(u64::MAX)
+        }
+    }
 }
 
 impl ReliabilityReport {
@@ -1296,6 +3230,20 @@ impl ReliabilityReport {
     pub fn bump_failed_requests(&mut self) {
         self.total_requests_sent += 1;
     }
+
+    /// Record a successful request, which the peer answered with `num_bytes` bytes
+    /// `latency_ms` milliseconds after it was sent
+    pub fn record_response(&mut self, latency_ms: u64, num_bytes: u64) {
+        self.bump_successful_requests();
+        let throughput_bps =
+            u64::try_from(u128::from(num_bytes) * 1000 / u128::from(cmp::max(latency_ms, 1)))
+                .unwrap_or(u64::MAX);
+        self.avg_latency_ms = Some(update_moving_average(self.avg_latency_ms, latency_ms));
+        self.avg_throughput_bps = Some(update_moving_average(
+            self.avg_throughput_bps,
+            throughput_bps,
+        ));
+    }
 }
 
 impl ReliabilityReport {
@@ -1303,14 +3251,20 @@ impl ReliabilityReport {
         ReliabilityReport {
             total_requests_sent,
             total_requests_success,
+            avg_latency_ms: None,
+            avg_throughput_bps: None,
         }
     }
 
     pub fn empty() -> ReliabilityReport {
-        ReliabilityReport {
-            total_requests_sent: 0,
-            total_requests_success: 0,
-        }
+        ReliabilityReport::new(0, 0)
+    }
+
+    /// Set the moving averages of the report's latency and throughput
+    pub fn with_speed(mut self, avg_latency_ms: u64, avg_throughput_bps: u64) -> ReliabilityReport {
+        self.avg_latency_ms = Some(avg_latency_ms);
+        self.avg_throughput_bps = Some(avg_throughput_bps);
+        self
     }
 
     pub fn score(&self) -> u32 {
@@ -1319,14 +3273,47 @@ impl ReliabilityReport {
             n => self.total_requests_success * 1000 / (n * 1000) + n,
         }
     }
+
+    /// The fraction of requests that succeeded, in tiers from 0 to `RELIABILITY_SUCCESS_TIERS`
+    fn success_tier(&self) -> u32 {
+        match self.total_requests_sent {
+            0 => 0,
+            n => cmp::min(
+                self.total_requests_success
+                    .saturating_mul(RELIABILITY_SUCCESS_TIERS)
+                    / n,
+                RELIABILITY_SUCCESS_TIERS,
+            ),
+        }
+    }
+
+    /// Compare how fast this peer and `other` answer: lower latency first, then higher
+    /// throughput.  Peers that have answered are faster than peers that have not.
+    fn cmp_speed(&self, other: &ReliabilityReport) -> Ordering {
+        let latency_bucket = |report: &ReliabilityReport| {
+            report
+                .avg_latency_ms
+                .map(|latency_ms| cmp::Reverse(latency_ms / RELIABILITY_LATENCY_BUCKET_MS))
+        };
+        latency_bucket(self)
+            .cmp(&latency_bucket(other))
+            .then_with(|| self.avg_throughput_bps.cmp(&other.avg_throughput_bps))
+    }
 }
 
+/// Peers that answer a larger fraction of their requests are preferred, and among those, the
+/// faster ones, so that the downloader does not favor peers that only eventually respond.
+/// Peers that are alike on both counts are ranked by their score.
 impl Ord for ReliabilityReport {
     fn cmp(&self, other: &ReliabilityReport) -> Ordering {
-        self.score().cmp(&other.score()).then_with(|| {
-            self.total_requests_success
-                .cmp(&other.total_requests_success)
-        })
+        self.success_tier()
+            .cmp(&other.success_tier())
+            .then_with(|| self.cmp_speed(other))
+            .then_with(|| self.score().cmp(&other.score()))
+            .then_with(|| {
+                self.total_requests_success
+                    .cmp(&other.total_requests_success)
+            })
     }
 }
 
@@ -15,6 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::hash::{Hash, Hasher};
 
 use clarity::vm::types::{QualifiedContractIdentifier, SequenceData, TupleData, Value};
@@ -28,24 +29,49 @@ use stacks_common::util::hash::{hex_bytes, to_hex, Hash160, MerkleHashFunc};
 
 pub use self::db::AtlasDB;
 pub use self::download::AttachmentsDownloader;
+pub use self::rate_limit::AtlasRateLimiter;
+pub use self::storage::AttachmentStorage;
 use crate::burnchains::Txid;
 use crate::chainstate::burn::db::sortdb::SortitionDB;
 use crate::chainstate::burn::ConsensusHash;
+use crate::chainstate::stacks::db::StacksEpochReceipt;
+use crate::chainstate::stacks::events::StacksTransactionReceipt;
+use crate::net::http::HttpResponsePreamble;
 use crate::util_lib::boot::boot_code_id;
+use crate::util_lib::strings::UrlString;
 
+/// Implements `AtlasAudit`, which checks the AtlasDB for corrupt or missing attachments and
+/// attachment instances.
+pub mod audit;
 /// Implements AtlasDB and associated API. Stores information about attachments and attachment
 /// instances.
 pub mod db;
 /// Implements `AttachmentsDownloader`, which attempts to download the requested batch of
 /// attachment instances from peers.
 pub mod download;
+/// Implements `AtlasRateLimiter`, which budgets how many Atlas HTTP requests and response bytes
+/// each peer may consume.
+pub mod rate_limit;
+/// Implements a simulate mode for the attachments downloader, which replays HTTP responses
+/// recorded in fixture files instead of talking to peers.
+#[cfg(any(test, feature = "testing"))]
+pub mod simulate;
+/// Implements `AttachmentBlobStore`, which stores attachment content as files outside of the
+/// AtlasDB.
+pub mod storage;
 
 pub const MAX_ATTACHMENT_INV_PAGES_PER_REQUEST: usize = 8;
+/// Maximum number of attachment instances returned per page by
+/// `GET /v2/attachments/instances/:principal/:contract_name`
+pub const MAX_ATTACHMENT_INSTANCES_PER_PAGE: u32 = 100;
 pub const MAX_RETRY_DELAY: u64 = 600; // seconds
 /// This is the maximum number of pending attachments batches allowed
 ///  in the synchronized channel before the coordinator will stall
 ///  waiting for attachments to be processed.
 pub const ATTACHMENTS_CHANNEL_SIZE: usize = 5;
+/// Response header in which a node serving Atlas requests advertises its current data URL, so
+/// that peers still using an older one can update their records
+pub const ATLAS_DATA_URL_HEADER: &str = "X-Data-Url";
 
 lazy_static! {
     pub static ref BNS_CHARS_REGEX: Regex = Regex::new("^([a-z0-9]|[-_])*$").unwrap();
@@ -55,6 +81,15 @@ const ATTACHMENTS_MAX_SIZE_MIN: u32 = 1_048_576;
 const MAX_UNINSTANTIATED_ATTACHMENTS_MIN: u32 = 50_000;
 const UNINSTANTIATED_ATTACHMENTS_EXPIRE_AFTER_MIN: u32 = 86_400;
 const UNRESOLVED_ATTACHMENT_INSTANCES_EXPIRE_AFTER_MIN: u32 = 172_800;
+const MAX_QUEUED_ATTACHMENT_BATCHES_DEFAULT: u32 = 10_000;
+const RESOLUTION_HISTORY_RETENTION_DEFAULT: u32 = 604_800;
+
+/// Advertise this node's data URL in the response to an Atlas request
+pub fn advertise_data_url(preamble: &mut HttpResponsePreamble, data_url: &UrlString) {
+    if data_url.len() > 0 {
+        preamble.add_header(ATLAS_DATA_URL_HEADER.into(), data_url.to_string());
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct GetAttachmentResponse {
@@ -78,18 +113,124 @@ impl<'de> Deserialize<'de> for GetAttachmentResponse {
     }
 }
 
+/// A byte range of an attachment, served by `GET /v2/attachments/:hash?offset=..&length=..`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetAttachmentChunkResponse {
+    /// Offset of `chunk` within the attachment
+    pub offset: u64,
+    /// Length of the whole attachment
+    pub total_length: u64,
+    #[serde(serialize_with = "hex_serialize", deserialize_with = "hex_deserialize")]
+    pub chunk: Vec<u8>,
+}
+
+impl GetAttachmentChunkResponse {
+    /// Does this chunk hold the whole attachment?
+    pub fn is_complete(&self) -> bool {
+        self.offset == 0 && self.chunk.len() as u64 == self.total_length
+    }
+}
+
+fn hex_serialize<S: serde::Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&to_hex(bytes))
+}
+
+fn hex_deserialize<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+    let hex_encoded = String::deserialize(d)?;
+    hex_bytes(&hex_encoded).map_err(de_Error::custom)
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GetAttachmentsInvResponse {
     pub block_id: StacksBlockId,
     pub pages: Vec<AttachmentPage>,
+    /// Indexes of the requested pages left out of `pages`, because they have not changed since
+    /// the version the client said it already has
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unchanged: Vec<u32>,
+}
+
+impl GetAttachmentsInvResponse {
+    /// Put the pages the server left out as unchanged back into `pages`, taking them from
+    /// `known_pages`, the pages previously received from the same server.
+    /// Returns false if one of them is not in `known_pages`, in which case the response is
+    /// left as is.
+    pub fn restore_unchanged_pages(&mut self, known_pages: &HashMap<u32, AttachmentPage>) -> bool {
+        let mut restored = Vec::with_capacity(self.unchanged.len());
+        for page_index in self.unchanged.iter() {
+            match known_pages.get(page_index) {
+                Some(page) => restored.push(page.clone()),
+                None => return false,
+            }
+        }
+        self.pages.append(&mut restored);
+        self.pages.sort_by_key(|page| page.index);
+        self.unchanged.clear();
+        true
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetAttachmentInstancesResponse {
+    pub instances: Vec<AttachmentInstance>,
+    /// Index of the next page of results, if there are more
+    pub next_page: Option<u32>,
+}
+
+/// Percentiles of the time it took to resolve attachment instances, from when each instance was
+/// first seen to when its attachment was available, in seconds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentResolutionPercentiles {
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+impl AttachmentResolutionPercentiles {
+    /// Compute the percentiles of `latencies`, which must be sorted in ascending order.
+    /// Percentiles are nearest-rank: each is one of the latencies.  None if there are none.
+    pub fn from_sorted_latencies(latencies: &[u64]) -> Option<AttachmentResolutionPercentiles> {
+        let max = *latencies.last()?;
+        let percentile = |pct: usize| {
+            let rank = (pct * latencies.len() + 99) / 100;
+            latencies[rank.saturating_sub(1)]
+        };
+        Some(AttachmentResolutionPercentiles {
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetAttachmentResolutionLatencyResponse {
+    /// How far back the record of attachment instances goes, in seconds
+    pub history_secs: u32,
+    /// Number of recorded instances whose attachment is available
+    pub resolved: u64,
+    /// Number of recorded instances whose attachment is still missing
+    pub unresolved: u64,
+    /// Resolution latencies of the resolved instances, if there are any
+    pub latency_secs: Option<AttachmentResolutionPercentiles>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AttachmentPage {
     pub index: u32,
     pub inventory: Vec<u8>,
 }
 
+impl AttachmentPage {
+    /// Identifies this version of the page, so that a client can ask for the page only if it
+    /// has changed since
+    pub fn digest(&self) -> Hash160 {
+        Hash160::from_data(&self.inventory)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AtlasConfig {
     pub contracts: HashSet<QualifiedContractIdentifier>,
@@ -98,6 +239,16 @@ pub struct AtlasConfig {
     pub uninstantiated_attachments_expire_after: u32,
     pub unresolved_attachment_instances_expire_after: u32,
     pub genesis_attachments: Option<Vec<Attachment>>,
+    /// Maximum number of attachments batches the downloader keeps in memory, waiting for their
+    /// retry deadline. Excess batches are handed back to the AtlasDB, and reloaded once there is
+    /// room for them again.
+    pub max_queued_attachment_batches: u32,
+    /// Where to store attachment content.  Attachments already stored elsewhere are moved
+    /// when the AtlasDB is opened.
+    pub attachment_storage: AttachmentStorage,
+    /// How long to keep the record of when each attachment instance was first seen and
+    /// resolved, in seconds.  0 disables the record.
+    pub resolution_history_retention: u32,
 }
 
 impl AtlasConfig {
@@ -112,6 +263,9 @@ impl AtlasConfig {
             unresolved_attachment_instances_expire_after:
                 UNRESOLVED_ATTACHMENT_INSTANCES_EXPIRE_AFTER_MIN,
             genesis_attachments: None,
+            max_queued_attachment_batches: MAX_QUEUED_ATTACHMENT_BATCHES_DEFAULT,
+            attachment_storage: AttachmentStorage::Sqlite,
+            resolution_history_retention: RESOLUTION_HISTORY_RETENTION_DEFAULT,
         }
     }
 
@@ -140,6 +294,11 @@ impl AtlasConfig {
                 "Invalid value for `unresolved_attachment_instances_expire_after`: {}. Expected {} or greater",
                 self.unresolved_attachment_instances_expire_after, UNRESOLVED_ATTACHMENT_INSTANCES_EXPIRE_AFTER_MIN
             ))
+        } else if self.max_queued_attachment_batches == 0 {
+            Err(format!(
+                "Invalid value for `max_queued_attachment_batches`: {}. Expected 1 or greater",
+                self.max_queued_attachment_batches
+            ))
         } else {
             Ok(())
         }
@@ -166,6 +325,90 @@ impl Attachment {
     }
 }
 
+/// What an attachment instance binds its content to: the `name` and `namespace` of its metadata,
+/// following BNS.  Within a contract, an instance supersedes the earlier instances with the same
+/// binding, e.g. when a name is bound to a new zonefile.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AttachmentBinding {
+    pub name: Vec<u8>,
+    pub namespace: Vec<u8>,
+}
+
+/// Where the transaction that emitted an attachment instance was mined.  Whatever its origin, an
+/// instance is indexed under the block whose processing emitted it: instances from a microblock
+/// stream are indexed under the anchored block that confirms it.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentOrigin {
+    /// An epoch 2.x anchored block
+    AnchoredBlock,
+    /// The epoch 2.x microblock with this hash
+    Microblock(BlockHeaderHash),
+    /// A Nakamoto block of a tenure
+    NakamotoBlock,
+}
+
+impl Default for AttachmentOrigin {
+    fn default() -> AttachmentOrigin {
+        AttachmentOrigin::AnchoredBlock
+    }
+}
+
+impl AttachmentOrigin {
+    /// The origin of the instances emitted by the transaction of `tx_receipt`, processed as part
+    /// of the block of `block_receipt`
+    pub fn of_receipt(
+        block_receipt: &StacksEpochReceipt,
+        tx_receipt: &StacksTransactionReceipt,
+    ) -> AttachmentOrigin {
+        if block_receipt
+            .header
+            .anchored_header
+            .as_stacks_nakamoto()
+            .is_some()
+        {
+            AttachmentOrigin::NakamotoBlock
+        } else if let Some(microblock_header) = tx_receipt.microblock_header.as_ref() {
+            AttachmentOrigin::Microblock(microblock_header.block_hash())
+        } else {
+            AttachmentOrigin::AnchoredBlock
+        }
+    }
+
+    /// The kind of block the instance was mined in, for reporting
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AttachmentOrigin::AnchoredBlock => "anchored",
+            AttachmentOrigin::Microblock(_) => "microblock",
+            AttachmentOrigin::NakamotoBlock => "nakamoto",
+        }
+    }
+
+    /// Parse an origin from its `Display` form
+    pub fn from_name(name: &str) -> Option<AttachmentOrigin> {
+        match name {
+            "anchored" => Some(AttachmentOrigin::AnchoredBlock),
+            "nakamoto" => Some(AttachmentOrigin::NakamotoBlock),
+            _ => {
+                let hex_hash = name.strip_prefix("microblock:")?;
+                let microblock_hash = BlockHeaderHash::from_hex(hex_hash).ok()?;
+                Some(AttachmentOrigin::Microblock(microblock_hash))
+            }
+        }
+    }
+}
+
+impl fmt::Display for AttachmentOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttachmentOrigin::Microblock(microblock_hash) => {
+                write!(f, "microblock:{}", microblock_hash)
+            }
+            _ => write!(f, "{}", self.kind()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
 /// An attachment instance is a reference to atlas data: a commitment
 /// to track the content that is the inverse of `content_hash`.
@@ -180,10 +423,14 @@ pub struct AttachmentInstance {
     pub contract_id: QualifiedContractIdentifier,
     pub tx_id: Txid,
     pub canonical_stacks_tip_height: Option<u64>,
+    /// Where the transaction that emitted this instance was mined
+    #[serde(default)]
+    pub origin: AttachmentOrigin,
 }
 
 impl AttachmentInstance {
-    const ATTACHMENTS_INV_PAGE_SIZE: u32 = 64;
+    /// Number of attachment instances covered by each page of an attachment inventory
+    pub const ATTACHMENTS_INV_PAGE_SIZE: u32 = 64;
 
     pub fn try_new_from_value(
         value: &Value,
@@ -230,6 +477,7 @@ impl AttachmentInstance {
                             contract_id: contract_id.clone(),
                             tx_id,
                             canonical_stacks_tip_height: canonical_stacks_tip_height,
+                            origin: AttachmentOrigin::AnchoredBlock,
                         };
                         return Some(instance);
                     }
@@ -239,6 +487,31 @@ impl AttachmentInstance {
         }
         None
     }
+
+    /// Set where the transaction that emitted this instance was mined
+    pub fn with_origin(mut self, origin: AttachmentOrigin) -> AttachmentInstance {
+        self.origin = origin;
+        self
+    }
+
+    /// The binding of this instance, if its metadata has a `name` and a `namespace`
+    pub fn binding(&self) -> Option<AttachmentBinding> {
+        let bytes = hex_bytes(&self.metadata).ok()?;
+        let metadata = match Value::consensus_deserialize(&mut &bytes[..]) {
+            Ok(Value::Tuple(metadata)) => metadata,
+            _ => return None,
+        };
+        match (metadata.get("name"), metadata.get("namespace")) {
+            (
+                Ok(Value::Sequence(SequenceData::Buffer(name))),
+                Ok(Value::Sequence(SequenceData::Buffer(namespace))),
+            ) => Some(AttachmentBinding {
+                name: name.data.clone(),
+                namespace: namespace.data.clone(),
+            }),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,148 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// Length of the window over which per-peer Atlas request and byte budgets are counted
+pub const ATLAS_RATE_LIMIT_WINDOW_SECS: u64 = 60;
+
+/// The Atlas HTTP endpoints (by metrics identifier) that are subject to per-peer rate limiting
+pub const ATLAS_RATE_LIMITED_ENDPOINTS: &[&str] = &["/v2/attachments/inv", "/v2/attachments/:hash"];
+
+/// What a single peer has cost us on the Atlas endpoints
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AtlasPeerUsage {
+    /// When the current window started
+    pub window_start: u64,
+    /// Requests admitted in the current window
+    pub window_requests: u64,
+    /// Response bytes served in the current window
+    pub window_bytes: u64,
+    /// Requests admitted since we started tracking this peer
+    pub total_requests: u64,
+    /// Response bytes served since we started tracking this peer
+    pub total_bytes: u64,
+    /// Requests turned away with a 429 since we started tracking this peer
+    pub total_throttled: u64,
+}
+
+impl AtlasPeerUsage {
+    fn roll_window(&mut self, now: u64) {
+        if now
+            >= self
+                .window_start
+                .saturating_add(ATLAS_RATE_LIMIT_WINDOW_SECS)
+        {
+            self.window_start = now;
+            self.window_requests = 0;
+            self.window_bytes = 0;
+        }
+    }
+}
+
+/// Per-peer request and bandwidth budgets for the Atlas HTTP endpoints and the p2p
+/// `GetAttachmentsInv` message, so that a single peer can't monopolize the node by hammering
+/// `GET /v2/attachments/inv`, `GET /v2/attachments/:hash` or their p2p counterpart.
+/// Peers are keyed by IP address, so reconnecting does not reset a peer's budget.
+/// A limit of 0 means "unlimited"; usage is accounted for either way.
+#[derive(Debug, Clone, Default)]
+pub struct AtlasRateLimiter {
+    max_requests_per_minute: u64,
+    max_bytes_per_minute: u64,
+    peers: HashMap<IpAddr, AtlasPeerUsage>,
+    last_pruned: u64,
+}
+
+impl AtlasRateLimiter {
+    pub fn new(max_requests_per_minute: u64, max_bytes_per_minute: u64) -> AtlasRateLimiter {
+        AtlasRateLimiter {
+            max_requests_per_minute,
+            max_bytes_per_minute,
+            peers: HashMap::new(),
+            last_pruned: 0,
+        }
+    }
+
+    /// Decide whether or not to serve an Atlas request from `peer`.
+    /// Returns Ok(()) if the request is admitted (and counts it against the peer's budget), or
+    /// Err(retry_after) with the number of seconds until the peer's budget is replenished.
+    pub fn try_admit(&mut self, peer: IpAddr, now: u64) -> Result<(), u64> {
+        self.prune(now);
+        let max_requests = self.max_requests_per_minute;
+        let max_bytes = self.max_bytes_per_minute;
+        let usage = self.peers.entry(peer).or_insert_with(|| AtlasPeerUsage {
+            window_start: now,
+            ..AtlasPeerUsage::default()
+        });
+        usage.roll_window(now);
+
+        let over_requests = max_requests > 0 && usage.window_requests >= max_requests;
+        let over_bytes = max_bytes > 0 && usage.window_bytes >= max_bytes;
+        if over_requests || over_bytes {
+            usage.total_throttled = usage.total_throttled.saturating_add(1);
+            let retry_after = usage
+                .window_start
+                .saturating_add(ATLAS_RATE_LIMIT_WINDOW_SECS)
+                .saturating_sub(now)
+                .max(1);
+            debug!("Atlas: throttling peer";
+                   "peer" => %peer,
+                   "window_requests" => usage.window_requests,
+                   "window_bytes" => usage.window_bytes,
+                   "retry_after" => retry_after);
+            return Err(retry_after);
+        }
+
+        usage.window_requests = usage.window_requests.saturating_add(1);
+        usage.total_requests = usage.total_requests.saturating_add(1);
+        Ok(())
+    }
+
+    /// Charge `bytes` of response data to `peer`
+    pub fn record_bytes_served(&mut self, peer: IpAddr, bytes: u64, now: u64) {
+        let usage = self.peers.entry(peer).or_insert_with(|| AtlasPeerUsage {
+            window_start: now,
+            ..AtlasPeerUsage::default()
+        });
+        usage.roll_window(now);
+        usage.window_bytes = usage.window_bytes.saturating_add(bytes);
+        usage.total_bytes = usage.total_bytes.saturating_add(bytes);
+    }
+
+    /// What has this peer cost us?
+    pub fn get_usage(&self, peer: &IpAddr) -> Option<&AtlasPeerUsage> {
+        self.peers.get(peer)
+    }
+
+    /// Forget peers that have been idle for a full window, so the table stays bounded by the
+    /// number of recently-active peers.  Runs at most once per window.
+    fn prune(&mut self, now: u64) {
+        if now
+            < self
+                .last_pruned
+                .saturating_add(ATLAS_RATE_LIMIT_WINDOW_SECS)
+        {
+            return;
+        }
+        self.last_pruned = now;
+        self.peers.retain(|_, usage| {
+            now < usage
+                .window_start
+                .saturating_add(2 * ATLAS_RATE_LIMIT_WINDOW_SECS)
+        });
+    }
+}
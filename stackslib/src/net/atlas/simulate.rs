@@ -0,0 +1,255 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Simulate mode for the attachments downloader.
+//!
+//! A `FixtureTransport` answers the requests of `AttachmentsBatchStateMachine` with HTTP
+//! responses recorded in a fixture file, instead of sending them to peers.  Replaying a batch
+//! through the real state machine this way makes the downloader's retry and priority logic
+//! testable without standing up any peers, and the outcome is the same on every run.
+//!
+//! A fixture file is a JSON `AttachmentsFixtures` object.  Each recorded response names the
+//! peer's data URL and the request path (with its query string) that it answers.  Responses
+//! recorded for the same request are replayed in order, one per request sent; a request for
+//! which no response is left fails to connect.  Peers must be given by IP address, since the
+//! simulated batch does not look up DNS names.
+//!
+//! Time passes only as requests are polled: each poll takes `FIXTURE_POLL_INTERVAL_MS`, so the
+//! peers whose responses are recorded with more `polls` are measured as slower.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+
+use url::form_urlencoded;
+
+use crate::net::atlas::download::{
+    AttachmentsBatchStateContext, AttachmentsBatchStateMachine, AttachmentsTransport, RequestPoll,
+};
+use crate::net::dns::DNSResolver;
+use crate::net::http::{
+    http_reason, HttpContentType, HttpResponsePayload, HttpResponsePreamble, HttpVersion,
+};
+use crate::net::httpcore::StacksHttpResponse;
+use crate::net::{Error as net_error, PeerHost, PeerHostExtensions, Requestable};
+use crate::util_lib::strings::UrlString;
+
+/// An HTTP response recorded from a peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// The data URL of the peer that answered
+    pub peer: String,
+    /// The path of the request answered, including its query string
+    pub path: String,
+    /// The HTTP status code
+    pub status: u16,
+    /// Response headers, such as `retry-after` or `location`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// The JSON body of the response, if it had one
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+    /// How many times the request is polled before the response arrives
+    #[serde(default)]
+    pub polls: u32,
+}
+
+impl RecordedResponse {
+    /// The response as the downloader would have received it
+    fn to_http_response(&self) -> StacksHttpResponse {
+        let (content_type, payload) = match self.body.as_ref() {
+            Some(body) => (
+                HttpContentType::JSON,
+                HttpResponsePayload::JSON(body.clone()),
+            ),
+            None => (HttpContentType::Bytes, HttpResponsePayload::Empty),
+        };
+        let mut preamble = HttpResponsePreamble::new(
+            HttpVersion::Http11,
+            self.status,
+            http_reason(self.status).to_string(),
+            payload.try_content_length(),
+            content_type,
+            false,
+        );
+        for (key, value) in self.headers.iter() {
+            preamble.add_header(key.clone(), value.clone());
+        }
+        StacksHttpResponse::new(preamble, payload)
+    }
+}
+
+/// The recorded responses a `FixtureTransport` replays
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AttachmentsFixtures {
+    pub responses: Vec<RecordedResponse>,
+}
+
+impl AttachmentsFixtures {
+    /// Load recorded responses from a JSON fixture file
+    pub fn load(path: &Path) -> Result<AttachmentsFixtures, net_error> {
+        let contents = fs::read_to_string(path).map_err(net_error::ReadError)?;
+        serde_json::from_str(&contents).map_err(|e| {
+            net_error::DeserializeError(format!(
+                "Failed to parse Atlas fixtures {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Write the recorded responses to a JSON fixture file
+    pub fn save(&self, path: &Path) -> Result<(), net_error> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| net_error::SerializeError(e.to_string()))?;
+        fs::write(path, contents).map_err(net_error::WriteError)
+    }
+}
+
+/// How much simulated time each poll of a request takes, in milliseconds
+pub const FIXTURE_POLL_INTERVAL_MS: u64 = 10;
+
+/// A request is identified by its peer, its path, and its query arguments in any order
+pub type RequestKey = (String, String, BTreeMap<String, String>);
+
+pub fn request_key(peer: &str, path_and_query: &str) -> RequestKey {
+    let (path, query) = path_and_query
+        .split_once('?')
+        .unwrap_or((path_and_query, ""));
+    let query_args = form_urlencoded::parse(query.as_bytes())
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    (peer.to_string(), path.to_string(), query_args)
+}
+
+/// An `AttachmentsTransport` that answers each request with the next response recorded for it
+#[derive(Debug, Default)]
+pub struct FixtureTransport {
+    /// The recorded responses not replayed yet, for each request
+    recorded: HashMap<RequestKey, VecDeque<RecordedResponse>>,
+    /// The requests sent and not yet answered, and the response each will get, if any
+    inflight: HashMap<usize, Option<RecordedResponse>>,
+    next_event_id: usize,
+    /// Every request sent, as (peer data URL, request path), in the order they were sent
+    requests_sent: Vec<(UrlString, String)>,
+    /// The simulated time, in milliseconds
+    clock_ms: u64,
+}
+
+impl FixtureTransport {
+    pub fn new(fixtures: AttachmentsFixtures) -> FixtureTransport {
+        let mut recorded: HashMap<_, VecDeque<_>> = HashMap::new();
+        for response in fixtures.responses.into_iter() {
+            recorded
+                .entry(request_key(&response.peer, &response.path))
+                .or_default()
+                .push_back(response);
+        }
+        FixtureTransport {
+            recorded,
+            ..FixtureTransport::default()
+        }
+    }
+
+    /// Load a transport from a JSON fixture file
+    pub fn from_file(path: &Path) -> Result<FixtureTransport, net_error> {
+        Ok(FixtureTransport::new(AttachmentsFixtures::load(path)?))
+    }
+
+    /// Every request sent so far, as (peer data URL, request path), in the order they were sent
+    pub fn requests_sent(&self) -> &[(UrlString, String)] {
+        &self.requests_sent
+    }
+
+    /// The number of recorded responses that have not been replayed
+    pub fn unused_responses(&self) -> usize {
+        self.recorded
+            .values()
+            .map(|responses| responses.len())
+            .sum()
+    }
+}
+
+impl AttachmentsTransport for FixtureTransport {
+    fn begin_request<T: Requestable>(
+        &mut self,
+        dns_lookups: &HashMap<UrlString, Option<Vec<SocketAddr>>>,
+        request: T,
+    ) -> Option<(T, usize)> {
+        // Like the network, only send requests to peers whose address is known
+        if !matches!(dns_lookups.get(request.get_url()), Some(Some(_))) {
+            return None;
+        }
+        let peer_host = PeerHost::try_from_url(request.get_url())?;
+        let http_request = request.make_request_type(peer_host);
+        let peer = request.get_url().to_string();
+        let path = http_request.request_path().to_string();
+
+        let response = self
+            .recorded
+            .get_mut(&request_key(&peer, &path))
+            .and_then(|responses| responses.pop_front());
+        let event_id = self.next_event_id;
+        self.next_event_id += 1;
+        self.inflight.insert(event_id, response);
+        self.requests_sent
+            .push((request.get_url().clone(), path.clone()));
+        Some((request, event_id))
+    }
+
+    fn poll_request(&mut self, event_id: usize) -> RequestPoll {
+        self.clock_ms += FIXTURE_POLL_INTERVAL_MS;
+        match self.inflight.get_mut(&event_id) {
+            Some(Some(response)) if response.polls > 0 => {
+                response.polls -= 1;
+                RequestPoll::Waiting
+            }
+            Some(Some(response)) => {
+                let response = response.to_http_response();
+                self.inflight.remove(&event_id);
+                RequestPoll::Response(response)
+            }
+            Some(None) | None => {
+                self.inflight.remove(&event_id);
+                RequestPoll::Failed
+            }
+        }
+    }
+
+    fn now_ms(&self) -> u64 {
+        self.clock_ms
+    }
+}
+
+/// Run a batch through `AttachmentsBatchStateMachine`, with every request answered by
+/// `transport`.  Returns the context of the finished batch, or `None` if the batch is not done
+/// after `max_steps` steps.
+pub fn replay_batch(
+    context: AttachmentsBatchStateContext,
+    transport: &mut FixtureTransport,
+    max_steps: usize,
+) -> Option<AttachmentsBatchStateContext> {
+    // The resolver is never run: peers given by IP address need no lookups
+    let (_dns_resolver, mut dns_client) = DNSResolver::new(10);
+    let mut fsm = AttachmentsBatchStateMachine::new(context);
+    for _ in 0..max_steps {
+        fsm = AttachmentsBatchStateMachine::try_proceed(fsm, &mut dns_client, transport);
+        if let AttachmentsBatchStateMachine::Done(context) = fsm {
+            return Some(context);
+        }
+    }
+    None
+}
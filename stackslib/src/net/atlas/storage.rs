@@ -0,0 +1,171 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//!
+//! Attachment content is stored either inline, in the `content` column of the AtlasDB's
+//! `attachments` table, or in an `AttachmentBlobStore`: a directory of content-addressed
+//! files next to the AtlasDB.  In the latter case, the `attachments` table only keeps the
+//! attachment's metadata, which keeps the sqlite DB small (and so quick to vacuum), and lets
+//! the attachments be backed up incrementally.
+//!
+//! Every row of the `attachments` table records where its content is, so a DB can hold
+//! attachments stored both ways while it is being migrated from one to the other.
+//!
+
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use rusqlite::types::{FromSql, FromSqlError, ToSql, ToSqlOutput, ValueRef};
+use stacks_common::util::hash::{to_hex, Hash160};
+
+use super::Attachment;
+use crate::util_lib::db::Error as db_error;
+
+/// Where attachment content is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentStorage {
+    /// In the AtlasDB itself
+    Sqlite,
+    /// In the AtlasDB's `AttachmentBlobStore`
+    Filesystem,
+}
+
+impl AttachmentStorage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttachmentStorage::Sqlite => "sqlite",
+            AttachmentStorage::Filesystem => "filesystem",
+        }
+    }
+}
+
+impl FromStr for AttachmentStorage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<AttachmentStorage, String> {
+        match s {
+            "sqlite" => Ok(AttachmentStorage::Sqlite),
+            "filesystem" => Ok(AttachmentStorage::Filesystem),
+            _ => Err(format!(
+                "Invalid attachment storage `{}`: expected `sqlite` or `filesystem`",
+                s
+            )),
+        }
+    }
+}
+
+impl ToSql for AttachmentStorage {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>, rusqlite::Error> {
+        let integer_rep: i64 = match self {
+            AttachmentStorage::Sqlite => 0,
+            AttachmentStorage::Filesystem => 1,
+        };
+        Ok(integer_rep.into())
+    }
+}
+
+impl FromSql for AttachmentStorage {
+    fn column_result(value: ValueRef<'_>) -> Result<Self, FromSqlError> {
+        let integer_rep: i64 = value.as_i64()?;
+        match integer_rep {
+            0 => Ok(AttachmentStorage::Sqlite),
+            1 => Ok(AttachmentStorage::Filesystem),
+            x => Err(FromSqlError::OutOfRange(x)),
+        }
+    }
+}
+
+/// A directory of attachment contents, each in a file named after its hash.
+/// Files are spread over 256 subdirectories by the first byte of their hash, so that no
+/// directory grows too large.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentBlobStore {
+    root: PathBuf,
+}
+
+impl AttachmentBlobStore {
+    pub fn new(root: PathBuf) -> AttachmentBlobStore {
+        AttachmentBlobStore { root }
+    }
+
+    /// The blob store that goes with the AtlasDB at `db_path`
+    pub fn for_db_path(db_path: &str) -> AttachmentBlobStore {
+        AttachmentBlobStore::new(Path::new(db_path).with_extension("blobs"))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn blob_path(&self, content_hash: &Hash160) -> PathBuf {
+        let hex_hash = to_hex(&content_hash.0[..]);
+        self.root.join(&hex_hash[0..2]).join(hex_hash)
+    }
+
+    /// Store `attachment`.  The content is written to a temporary file which is then renamed,
+    /// so that a crash never leaves a partially-written blob behind.
+    pub fn put(&self, attachment: &Attachment) -> Result<(), db_error> {
+        let path = self.blob_path(&attachment.hash());
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(db_error::IOError)?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path).map_err(db_error::IOError)?;
+        file.write_all(&attachment.content)
+            .map_err(db_error::IOError)?;
+        file.sync_all().map_err(db_error::IOError)?;
+        fs::rename(&tmp_path, &path).map_err(db_error::IOError)?;
+        Ok(())
+    }
+
+    /// Read the stored content of `content_hash`, without checking it.
+    /// Returns Ok(None) if there is no such blob.
+    pub fn read(&self, content_hash: &Hash160) -> Result<Option<Vec<u8>>, db_error> {
+        match fs::read(self.blob_path(content_hash)) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(db_error::IOError(e)),
+        }
+    }
+
+    /// Load the attachment stored under `content_hash`.
+    /// Returns Ok(None) if there is no such blob, and Err(Corruption) if the stored content
+    /// does not hash to `content_hash`.
+    pub fn get(&self, content_hash: &Hash160) -> Result<Option<Attachment>, db_error> {
+        let content = match self.read(content_hash)? {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+        let attachment = Attachment::new(content);
+        if attachment.hash() != *content_hash {
+            warn!("Atlas: stored attachment blob does not match its hash";
+                  "hash" => %content_hash,
+                  "actual_hash" => %attachment.hash());
+            return Err(db_error::Corruption);
+        }
+        Ok(Some(attachment))
+    }
+
+    /// Delete the blob stored under `content_hash`, if there is one
+    pub fn delete(&self, content_hash: &Hash160) -> Result<(), db_error> {
+        match fs::remove_file(self.blob_path(content_hash)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(db_error::IOError(e)),
+        }
+    }
+}
@@ -14,28 +14,50 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
 use std::{thread, time};
 
-use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::types::{QualifiedContractIdentifier, TupleData, Value};
 use stacks_common::types::chainstate::{BlockHeaderHash, StacksBlockId};
 use stacks_common::types::net::{PeerAddress, PeerHost};
+use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::hash::Hash160;
 
+use super::audit::{apply_repairs, AtlasAudit};
+use super::db::ATLASDB_VERSION;
 use super::download::{
-    AttachmentRequest, AttachmentsBatch, AttachmentsBatchStateContext, AttachmentsInventoryRequest,
-    BatchedRequestsResult, ReliabilityReport,
+    advertised_data_url, demote_address_family, redirected_data_url, AttachmentRange,
+    AttachmentRequest, AttachmentsBatch, AttachmentsBatchContextSnapshot, AttachmentsBatchStage,
+    AttachmentsBatchStageTimings, AttachmentsBatchStateContext, AttachmentsDownloader,
+    AttachmentsInventoryRequest, AttachmentsNotFoundCache, BatchedDNSLookupsResults,
+    BatchedRequestsResult, PartialAttachment, ReliabilityReport,
 };
+use super::rate_limit::{AtlasRateLimiter, ATLAS_RATE_LIMIT_WINDOW_SECS};
+use super::simulate::{
+    replay_batch, request_key, AttachmentsFixtures, FixtureTransport, RequestKey,
+    FIXTURE_POLL_INTERVAL_MS,
+};
+use super::storage::AttachmentBlobStore;
 use super::{
-    AtlasConfig, AtlasDB, Attachment, AttachmentInstance, AttachmentPage, GetAttachmentsInvResponse,
+    advertise_data_url, AtlasConfig, AtlasDB, Attachment, AttachmentBinding, AttachmentInstance,
+    AttachmentOrigin, AttachmentPage, AttachmentResolutionPercentiles, AttachmentStorage,
+    GetAttachmentChunkResponse, GetAttachmentsInvResponse,
 };
 use crate::burnchains::Txid;
 use crate::chainstate::burn::ConsensusHash;
 use crate::chainstate::stacks::db::StacksChainState;
+use crate::net::chat::ConversationP2P;
 use crate::net::connection::ConnectionOptions;
+use crate::net::dns::{AddressFamily, AddressFamilyPreference};
 use crate::net::http::{HttpResponsePayload, HttpResponsePreamble, HttpVersion};
 use crate::net::httpcore::StacksHttpResponse;
-use crate::net::Requestable;
+use crate::net::test::{TestPeer, TestPeerConfig};
+use crate::net::{
+    AttachmentsInvData, GetAttachmentsInvData, NackErrorCodes, Requestable, StacksMessageType,
+};
 use crate::util_lib::boot::boot_code_id;
 use crate::util_lib::db::u64_to_sql;
 use crate::util_lib::strings::UrlString;
@@ -61,6 +83,54 @@ fn new_attachment_instance_from(
         contract_id: QualifiedContractIdentifier::transient(),
         tx_id: Txid([0; 32]),
         canonical_stacks_tip_height: Some(block_height),
+        origin: AttachmentOrigin::AnchoredBlock,
+    }
+}
+
+/// Like `new_attachment_instance_from`, but for instances that are read back from the AtlasDB.
+/// Its id columns are declared as `STRING`, which SQLite gives numeric affinity, so ids whose
+/// hex is all digits would be read back as numbers.
+fn new_stored_attachment_instance_from(
+    attachment: &Attachment,
+    attachment_index: u32,
+    block_height: u64,
+) -> AttachmentInstance {
+    let mut index_block_hash = [block_height as u8; 32];
+    index_block_hash[0] = 0xff;
+    AttachmentInstance {
+        index_block_hash: StacksBlockId(index_block_hash),
+        tx_id: Txid([0xff; 32]),
+        ..new_attachment_instance_from(attachment, attachment_index, block_height)
+    }
+}
+
+/// An attachment instance binding `attachment` to `name` in the `id` namespace, as BNS emits
+fn new_name_attachment_instance_from(
+    attachment: &Attachment,
+    attachment_index: u32,
+    block_height: u64,
+    name: &str,
+) -> AttachmentInstance {
+    let metadata = Value::Tuple(
+        TupleData::from_data(vec![
+            (
+                "name".into(),
+                Value::buff_from(name.as_bytes().to_vec()).unwrap(),
+            ),
+            (
+                "namespace".into(),
+                Value::buff_from(b"id".to_vec()).unwrap(),
+            ),
+            (
+                "op".into(),
+                Value::string_ascii_from_bytes(b"name-update".to_vec()).unwrap(),
+            ),
+        ])
+        .unwrap(),
+    );
+    AttachmentInstance {
+        metadata: metadata.serialize_to_hex().unwrap(),
+        ..new_stored_attachment_instance_from(attachment, attachment_index, block_height)
     }
 }
 
@@ -106,6 +176,9 @@ fn new_attachment_request(
         content_hash: content_hash.clone(),
         stacks_block_height: block_height,
         canonical_stacks_tip_height: Some(block_height),
+        range: None,
+        source: None,
+        redirects: 0,
     }
 }
 
@@ -127,10 +200,19 @@ fn new_attachments_inventory_request(
         index_block_hash: StacksBlockId([0x00; 32]),
         reliability_report: ReliabilityReport::new(req_sent, req_success),
         canonical_stacks_tip_height: Some(block_height),
+        redirects: 0,
+        since: vec![],
     }
 }
 
 fn new_attachments_inventory_response(pages: Vec<(u32, Vec<u8>)>) -> StacksHttpResponse {
+    new_differential_attachments_inventory_response(pages, vec![])
+}
+
+fn new_differential_attachments_inventory_response(
+    pages: Vec<(u32, Vec<u8>)>,
+    unchanged: Vec<u32>,
+) -> StacksHttpResponse {
     let pages = pages
         .into_iter()
         .map(|(index, inventory)| AttachmentPage { index, inventory })
@@ -138,6 +220,27 @@ fn new_attachments_inventory_response(pages: Vec<(u32, Vec<u8>)>) -> StacksHttpR
     let response = GetAttachmentsInvResponse {
         block_id: StacksBlockId([0u8; 32]),
         pages,
+        unchanged,
+    };
+
+    let response_json = serde_json::to_value(&response).unwrap();
+    let body = HttpResponsePayload::try_from_json(response_json).unwrap();
+
+    StacksHttpResponse::new(
+        HttpResponsePreamble::raw_ok_json(HttpVersion::Http11, false),
+        body,
+    )
+}
+
+fn new_attachment_chunk_response(
+    attachment: &Attachment,
+    range: AttachmentRange,
+) -> StacksHttpResponse {
+    let end = std::cmp::min(range.offset + range.length, attachment.content.len() as u64);
+    let response = GetAttachmentChunkResponse {
+        offset: range.offset,
+        total_length: attachment.content.len() as u64,
+        chunk: attachment.content[range.offset as usize..end as usize].to_vec(),
     };
 
     let response_json = serde_json::to_value(&response).unwrap();
@@ -645,6 +748,484 @@ fn test_downloader_context_attachment_inventories_requests() {
     );
 }
 
+#[test]
+fn test_downloader_context_describe() {
+    let attachments_batch = new_attachments_batch_from(
+        vec![
+            new_attachment_instance_from(&new_attachment_from("facade01"), 1, 1),
+            new_attachment_instance_from(&new_attachment_from("facade02"), 2, 1),
+        ],
+        1,
+    );
+    let peers = new_peers(vec![
+        ("http://localhost:30443", 3, 3),
+        ("http://localhost:20443", 2, 2),
+    ]);
+    let mut context =
+        AttachmentsBatchStateContext::new(attachments_batch, peers, &ConnectionOptions::default());
+    context.events_to_deregister.push(7);
+    context.attachments.insert(new_attachment_from("facade01"));
+
+    let snapshot = context.describe();
+    assert_eq!(snapshot.stacks_block_height, 1);
+    assert_eq!(snapshot.index_block_hash, StacksBlockId([1; 32]));
+    assert_eq!(snapshot.retry_count, 1);
+    assert_eq!(snapshot.missing_attachments, 2);
+    assert_eq!(
+        snapshot.peers,
+        vec![
+            UrlString::try_from("http://localhost:20443").unwrap(),
+            UrlString::try_from("http://localhost:30443").unwrap(),
+        ]
+    );
+    assert_eq!(snapshot.resolved_dns_lookups, 0);
+    assert_eq!(snapshot.inventories, 0);
+    assert_eq!(snapshot.downloaded_attachments, 1);
+    assert_eq!(snapshot.events_to_deregister, vec![7]);
+    assert_eq!(
+        snapshot.stage_timings,
+        AttachmentsBatchStageTimings::default()
+    );
+
+    // the snapshot is meant to be served as JSON
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let decoded: AttachmentsBatchContextSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, snapshot);
+}
+
+#[test]
+fn test_batch_stage_timings() {
+    let mut timings = AttachmentsBatchStageTimings::default();
+    timings.record(AttachmentsBatchStage::DNSLookup, Duration::from_millis(15));
+    timings.record(
+        AttachmentsBatchStage::DownloadingAttachmentsInv,
+        Duration::from_millis(250),
+    );
+    // byte-range downloads run the attachment stage more than once
+    timings.record(
+        AttachmentsBatchStage::DownloadingAttachment,
+        Duration::from_millis(1000),
+    );
+    timings.record(
+        AttachmentsBatchStage::DownloadingAttachment,
+        Duration::from_micros(500_900),
+    );
+    // only the stages that do work are timed
+    timings.record(AttachmentsBatchStage::Initialized, Duration::from_secs(1));
+    timings.record(AttachmentsBatchStage::Done, Duration::from_secs(1));
+
+    assert_eq!(
+        timings,
+        AttachmentsBatchStageTimings {
+            dns_lookup_ms: 15,
+            attachments_inv_ms: 250,
+            attachments_ms: 1500,
+        }
+    );
+    assert_eq!(
+        timings.stages(),
+        [
+            ("dns_lookup", 15),
+            ("attachments_inv", 250),
+            ("attachments", 1500)
+        ]
+    );
+    assert_eq!(timings.total_ms(), 1765);
+
+    timings.record(AttachmentsBatchStage::DNSLookup, Duration::MAX);
+    assert_eq!(timings.dns_lookup_ms, u64::MAX);
+    assert_eq!(timings.total_ms(), u64::MAX);
+}
+
+#[test]
+fn test_downloader_describe() {
+    let attachment_instance = new_attachment_instance_from(&new_attachment_from("facade01"), 1, 1);
+    let downloader = AttachmentsDownloader::new(vec![attachment_instance]);
+
+    let snapshot = downloader.describe();
+    assert_eq!(snapshot.initial_batch_size, 1);
+    assert_eq!(snapshot.queued_batches, 0);
+    assert_eq!(snapshot.on_demand_batches, 0);
+    assert_eq!(snapshot.processed_batches, 0);
+    assert_eq!(snapshot.not_found_cache_size, 0);
+    assert!(snapshot.ongoing_batch.is_none());
+}
+
+#[test]
+fn test_downloader_enqueue_on_demand_batches() {
+    let mut downloader = AttachmentsDownloader::new(vec![]);
+    assert_eq!(downloader.enqueue_on_demand_batches(vec![]), 0);
+
+    // instances are grouped into one batch per block
+    let instances = vec![
+        new_attachment_instance_from(&new_attachment_from("facade01"), 1, 2),
+        new_attachment_instance_from(&new_attachment_from("facade02"), 2, 1),
+        new_attachment_instance_from(&new_attachment_from("facade03"), 3, 2),
+    ];
+    assert_eq!(downloader.enqueue_on_demand_batches(instances), 2);
+
+    let snapshot = downloader.describe();
+    assert_eq!(snapshot.on_demand_batches, 2);
+    assert_eq!(snapshot.queued_batches, 0);
+    assert!(snapshot.ongoing_batch.is_none());
+}
+
+#[test]
+fn test_attachment_instance_binding() {
+    let attachment = new_attachment_from("facade01");
+    assert_eq!(
+        new_attachment_instance_from(&attachment, 1, 1).binding(),
+        None
+    );
+    assert_eq!(
+        new_name_attachment_instance_from(&attachment, 1, 1, "alice").binding(),
+        Some(AttachmentBinding {
+            name: b"alice".to_vec(),
+            namespace: b"id".to_vec(),
+        })
+    );
+}
+
+#[test]
+fn test_attachment_origin_names() {
+    for origin in [
+        AttachmentOrigin::AnchoredBlock,
+        AttachmentOrigin::Microblock(BlockHeaderHash([0x11; 32])),
+        AttachmentOrigin::NakamotoBlock,
+    ] {
+        assert_eq!(
+            AttachmentOrigin::from_name(&origin.to_string()),
+            Some(origin)
+        );
+    }
+    assert_eq!(AttachmentOrigin::from_name("microblock:00"), None);
+    assert_eq!(AttachmentOrigin::from_name("unconfirmed"), None);
+}
+
+#[test]
+fn test_downloader_mixed_origin_batches() {
+    let mut atlas_db = AtlasDB::connect_memory(AtlasConfig::new(false)).unwrap();
+    let mut downloader = AttachmentsDownloader::new(vec![]);
+    let microblock_origin = AttachmentOrigin::Microblock(BlockHeaderHash([0x11; 32]));
+
+    // block 1 confirms a microblock: both emitted attachment instances
+    let anchored_attachment = new_attachment_from("facade01");
+    let microblock_attachment = new_attachment_from("facade02");
+    let anchored_instance = new_stored_attachment_instance_from(&anchored_attachment, 1, 1);
+    let microblock_instance = new_stored_attachment_instance_from(&microblock_attachment, 2, 1)
+        .with_origin(microblock_origin.clone());
+    // block 2 is a Nakamoto block
+    let nakamoto_attachment = new_attachment_from("facade03");
+    let nakamoto_instance = new_stored_attachment_instance_from(&nakamoto_attachment, 3, 2)
+        .with_origin(AttachmentOrigin::NakamotoBlock);
+    for instance in [&anchored_instance, &microblock_instance, &nakamoto_instance] {
+        atlas_db.queue_attachment_instance(instance).unwrap();
+    }
+
+    // the origins survive the AtlasDB
+    let mut queued = atlas_db.queued_attachments().unwrap();
+    queued.sort_by_key(|instance| instance.attachment_index);
+    assert_eq!(
+        queued
+            .iter()
+            .map(|instance| instance.origin.clone())
+            .collect::<Vec<_>>(),
+        vec![
+            AttachmentOrigin::AnchoredBlock,
+            microblock_origin.clone(),
+            AttachmentOrigin::NakamotoBlock
+        ]
+    );
+
+    downloader
+        .check_queued_attachment_instances(&mut atlas_db)
+        .unwrap();
+    assert_eq!(downloader.describe().queued_batches, 2);
+
+    let mut batches = vec![];
+    while let Some(batch) = downloader.pop_next_ready_batch() {
+        batches.push(batch);
+    }
+    batches.sort_by_key(|batch| batch.stacks_block_height);
+
+    // the instances of block 1 are downloaded together, whatever their origin
+    let mut mixed_batch = batches.remove(0);
+    assert_eq!(
+        mixed_batch.index_block_hash,
+        anchored_instance.index_block_hash
+    );
+    assert_eq!(mixed_batch.attachments_instances_count(), 2);
+    assert_eq!(
+        mixed_batch.origin_counts(),
+        BTreeMap::from([("anchored".to_string(), 1), ("microblock".to_string(), 1)])
+    );
+    let request = mixed_batch.get_p2p_inventory_request().unwrap();
+    assert_eq!(request.index_block_hash, anchored_instance.index_block_hash);
+    assert_eq!(request.pages, vec![0]);
+
+    mixed_batch.resolve_attachment(&microblock_attachment.hash());
+    assert_eq!(mixed_batch.attachments_instances_count(), 1);
+    assert_eq!(
+        mixed_batch.origin_counts(),
+        BTreeMap::from([("anchored".to_string(), 1)])
+    );
+    mixed_batch.resolve_attachment(&anchored_attachment.hash());
+    assert!(mixed_batch.has_fully_succeed());
+    assert!(mixed_batch.origin_counts().is_empty());
+
+    let nakamoto_batch = batches.remove(0);
+    assert_eq!(
+        nakamoto_batch.index_block_hash,
+        nakamoto_instance.index_block_hash
+    );
+    assert_eq!(
+        nakamoto_batch.origin_counts(),
+        BTreeMap::from([("nakamoto".to_string(), 1)])
+    );
+}
+
+#[test]
+fn test_downloader_prunes_superseded_instances() {
+    let mut atlas_db = AtlasDB::connect_memory(AtlasConfig::new(false)).unwrap();
+    let mut downloader = AttachmentsDownloader::new(vec![]);
+
+    // alice's and bob's zonefiles, neither of which is known
+    for (attachment_index, name) in [(1, "alice"), (2, "bob")] {
+        let attachment = new_attachment_from(&format!("facade0{}", attachment_index));
+        atlas_db
+            .queue_attachment_instance(&new_name_attachment_instance_from(
+                &attachment,
+                attachment_index,
+                1,
+                name,
+            ))
+            .unwrap();
+    }
+    downloader
+        .check_queued_attachment_instances(&mut atlas_db)
+        .unwrap();
+    assert_eq!(downloader.describe().queued_batches, 1);
+
+    // a later block binds alice to a new zonefile, and binds carol twice
+    for (attachment_index, name) in [(3, "alice"), (4, "carol"), (5, "carol")] {
+        let attachment = new_attachment_from(&format!("facade0{}", attachment_index));
+        atlas_db
+            .queue_attachment_instance(&new_name_attachment_instance_from(
+                &attachment,
+                attachment_index,
+                2,
+                name,
+            ))
+            .unwrap();
+    }
+    downloader
+        .check_queued_attachment_instances(&mut atlas_db)
+        .unwrap();
+    let snapshot = downloader.describe();
+    assert_eq!(snapshot.superseded_instances, 2);
+    assert_eq!(snapshot.queued_batches, 2);
+
+    // only bob's zonefile remains to be downloaded from the first block, and only alice's and
+    // carol's latest ones from the second
+    let mut remaining = vec![];
+    while let Some(batch) = downloader.pop_next_ready_batch() {
+        let mut indexes: Vec<_> = batch
+            .attachments_instances
+            .values()
+            .flat_map(|missing_attachments| missing_attachments.keys().copied())
+            .collect();
+        indexes.sort();
+        remaining.push((batch.stacks_block_height, indexes));
+    }
+    remaining.sort();
+    assert_eq!(remaining, vec![(1, vec![2]), (2, vec![3, 5])]);
+
+    // superseding every instance of a batch drops it
+    let mut downloader = AttachmentsDownloader::new(vec![]);
+    let attachment = new_attachment_from("facade01");
+    atlas_db
+        .queue_attachment_instance(&new_name_attachment_instance_from(
+            &attachment,
+            6,
+            3,
+            "dave",
+        ))
+        .unwrap();
+    downloader
+        .check_queued_attachment_instances(&mut atlas_db)
+        .unwrap();
+    assert_eq!(downloader.describe().queued_batches, 1);
+    assert_eq!(
+        downloader.prune_superseded_instances(&[new_name_attachment_instance_from(
+            &attachment,
+            7,
+            4,
+            "dave"
+        )]),
+        1
+    );
+    assert_eq!(downloader.describe().queued_batches, 0);
+}
+
+#[test]
+fn test_downloader_spills_excess_batches() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 100,
+        uninstantiated_attachments_expire_after: 10,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        max_queued_attachment_batches: 2,
+        attachment_storage: AttachmentStorage::Sqlite,
+        resolution_history_retention: 3600,
+    };
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    // one unknown attachment per block, at heights 1 through 4
+    for block_height in 1..=4 {
+        let attachment = new_attachment_from(&format!("facade0{}", block_height));
+        let attachment_instance =
+            new_stored_attachment_instance_from(&attachment, block_height as u32, block_height);
+        atlas_db
+            .queue_attachment_instance(&attachment_instance)
+            .unwrap();
+    }
+
+    let mut downloader = AttachmentsDownloader::new(vec![]);
+    let resolved = downloader
+        .check_queued_attachment_instances(&mut atlas_db)
+        .unwrap();
+    assert!(resolved.is_empty());
+
+    let snapshot = downloader.describe();
+    assert_eq!(snapshot.queued_batches, 2);
+    assert_eq!(snapshot.spilled_batches, 2);
+    assert_eq!(snapshot.dropped_batches, 0);
+    assert!(snapshot.estimated_memory_bytes > 0);
+    assert_eq!(
+        snapshot.estimated_memory_bytes,
+        downloader.estimated_memory_usage() as u64
+    );
+
+    // the batches that would have been downloaded last wait in the AtlasDB
+    let mut requeued: Vec<_> = atlas_db
+        .queued_attachments()
+        .unwrap()
+        .into_iter()
+        .map(|instance| instance.stacks_block_height)
+        .collect();
+    requeued.sort();
+    assert_eq!(requeued, vec![3, 4]);
+
+    // and are not reloaded while the queue is full
+    let resolved = downloader
+        .check_queued_attachment_instances(&mut atlas_db)
+        .unwrap();
+    assert!(resolved.is_empty());
+    assert_eq!(atlas_db.queued_attachments().unwrap().len(), 2);
+    assert_eq!(downloader.describe().queued_batches, 2);
+
+    // once there is room, they are picked up again
+    assert!(downloader.pop_next_ready_batch().is_some());
+    downloader
+        .check_queued_attachment_instances(&mut atlas_db)
+        .unwrap();
+    let snapshot = downloader.describe();
+    assert_eq!(snapshot.queued_batches, 2);
+    assert_eq!(snapshot.spilled_batches, 3);
+    assert_eq!(atlas_db.queued_attachments().unwrap().len(), 1);
+}
+
+#[test]
+fn test_downloader_drain() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 100,
+        uninstantiated_attachments_expire_after: 10,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        max_queued_attachment_batches: 10,
+        attachment_storage: AttachmentStorage::Sqlite,
+        resolution_history_retention: 3600,
+    };
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    // one unknown attachment per block, at heights 1 through 3
+    let mut attachment_instances = vec![];
+    for block_height in 1..=3 {
+        let attachment = new_attachment_from(&format!("facade0{}", block_height));
+        let attachment_instance =
+            new_stored_attachment_instance_from(&attachment, block_height as u32, block_height);
+        atlas_db
+            .queue_attachment_instance(&attachment_instance)
+            .unwrap();
+        attachment_instances.push(attachment_instance);
+    }
+
+    // the last one is requested by an operator instead
+    let on_demand_instance = attachment_instances.pop().unwrap();
+    atlas_db
+        .mark_attachment_instance_checked(&on_demand_instance, false)
+        .unwrap();
+
+    let mut downloader = AttachmentsDownloader::new(vec![]);
+    downloader
+        .check_queued_attachment_instances(&mut atlas_db)
+        .unwrap();
+    assert_eq!(
+        downloader.enqueue_on_demand_batches(vec![on_demand_instance]),
+        1
+    );
+    assert!(atlas_db.queued_attachments().unwrap().is_empty());
+
+    let snapshot = downloader.describe();
+    assert_eq!(snapshot.queued_batches, 2);
+    assert_eq!(snapshot.on_demand_batches, 1);
+
+    assert_eq!(downloader.drain(&mut atlas_db).unwrap(), 3);
+    let snapshot = downloader.describe();
+    assert_eq!(snapshot.queued_batches, 0);
+    assert_eq!(snapshot.on_demand_batches, 0);
+    assert!(snapshot.ongoing_batch.is_none());
+
+    // every batch waits in the AtlasDB to be checked again
+    let mut requeued: Vec<_> = atlas_db
+        .queued_attachments()
+        .unwrap()
+        .into_iter()
+        .map(|instance| instance.stacks_block_height)
+        .collect();
+    requeued.sort();
+    assert_eq!(requeued, vec![1, 2, 3]);
+
+    // draining again has nothing left to hand back
+    assert_eq!(downloader.drain(&mut atlas_db).unwrap(), 0);
+}
+
+#[test]
+fn test_attachments_batch_memory_usage() {
+    let empty_batch = AttachmentsBatch::new();
+    let batch = new_attachments_batch_from(
+        vec![
+            new_attachment_instance_from(&new_attachment_from("facade01"), 1, 1),
+            new_attachment_instance_from(&new_attachment_from("facade02"), 2, 1),
+        ],
+        0,
+    );
+    assert!(batch.estimated_memory_usage() > empty_batch.estimated_memory_usage());
+
+    let mut downloader = AttachmentsDownloader::new(vec![]);
+    let empty_usage = downloader.estimated_memory_usage();
+    downloader.enqueue_on_demand_batches(vec![new_attachment_instance_from(
+        &new_attachment_from("facade01"),
+        1,
+        1,
+    )]);
+    assert!(downloader.estimated_memory_usage() > empty_usage);
+}
+
 #[test]
 fn test_downloader_context_attachment_requests() {
     let attachment_1 = new_attachment_from("facade01");
@@ -746,467 +1327,1393 @@ fn test_downloader_context_attachment_requests() {
 }
 
 #[test]
-fn test_keep_uninstantiated_attachments() {
-    let bns_contract_id = boot_code_id("bns", false);
-    let pox_contract_id = boot_code_id("pox", false);
-
-    let mut contracts = HashSet::new();
-    contracts.insert(bns_contract_id.clone());
+fn test_downloader_context_attachment_requests_skip_not_found() {
+    let attachment_1 = new_attachment_from("facade01");
+    let attachment_2 = new_attachment_from("facade02");
 
-    let atlas_config = AtlasConfig {
-        contracts,
-        attachments_max_size: 16,
-        max_uninstantiated_attachments: 10,
-        uninstantiated_attachments_expire_after: 10,
-        unresolved_attachment_instances_expire_after: 10,
-        genesis_attachments: None,
-    };
+    let page_size = AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE;
+    let attachments_batch = new_attachments_batch_from(
+        vec![
+            new_attachment_instance_from(&attachment_1, page_size * 0, 1),
+            new_attachment_instance_from(&attachment_2, page_size * 0 + 1, 1),
+        ],
+        0,
+    );
+    let peers = new_peers(vec![
+        ("http://localhost:20443", 4, 4),
+        ("http://localhost:30443", 3, 3),
+    ]);
+    let peer_url_1 = UrlString::try_from("http://localhost:20443").unwrap();
+    let peer_url_2 = UrlString::try_from("http://localhost:30443").unwrap();
 
-    let atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+    // Peer 1 previously answered 404 for both attachments, peer 2 for attachment 2 only
+    let mut not_found_cache = AttachmentsNotFoundCache::new(3600);
+    not_found_cache.insert(peer_url_1.clone(), attachment_1.hash());
+    not_found_cache.insert(peer_url_1.clone(), attachment_2.hash());
+    not_found_cache.insert(peer_url_2.clone(), attachment_2.hash());
+    assert!(not_found_cache.contains(&peer_url_1, &attachment_1.hash()));
+    assert!(!not_found_cache.contains(&peer_url_2, &attachment_1.hash()));
 
-    assert_eq!(
-        atlas_db.should_keep_attachment(&pox_contract_id, &new_attachment_from("facade02")),
-        false
-    );
+    let context =
+        AttachmentsBatchStateContext::new(attachments_batch, peers, &ConnectionOptions::default())
+            .with_not_found_cache(not_found_cache);
 
-    assert_eq!(
-        atlas_db.should_keep_attachment(&bns_contract_id, &new_attachment_from("facade02")),
-        true
-    );
+    let mut inventories_requests = context.get_prioritized_attachments_inventory_requests();
+    let mut inventories_results = BatchedRequestsResult::empty();
+    while let Some(request) = inventories_requests.pop() {
+        let response = new_attachments_inventory_response(vec![(0, vec![1, 1])]);
+        inventories_results
+            .succeeded
+            .insert(request, Some(response));
+    }
+    let context = context.extend_with_inventories(&mut inventories_results);
 
-    assert_eq!(
-        atlas_db.should_keep_attachment(
-            &bns_contract_id,
-            &new_attachment_from("facadefacadefacade02")
-        ),
-        false
-    );
+    // Only attachment 1 can be requested, and only from peer 2
+    let mut attachments_requests = context.get_prioritized_attachments_requests();
+    assert_eq!(attachments_requests.len(), 1);
+    let request = attachments_requests.pop().unwrap();
+    assert_eq!(request.content_hash, attachment_1.hash());
+    assert_eq!(request.get_url(), &peer_url_2);
 }
 
 #[test]
-fn schema_2_migration() {
-    let atlas_config = AtlasConfig {
-        contracts: HashSet::new(),
-        attachments_max_size: 1024,
-        max_uninstantiated_attachments: 10,
-        uninstantiated_attachments_expire_after: 0,
-        unresolved_attachment_instances_expire_after: 10,
-        genesis_attachments: None,
+fn test_downloader_context_p2p_inventories() {
+    let attachment_1 = new_attachment_from("facade01");
+    let attachment_2 = new_attachment_from("facade02");
+
+    let page_size = AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE;
+    let attachments_batch = new_attachments_batch_from(
+        vec![
+            new_attachment_instance_from(&attachment_1, page_size * 0, 1),
+            new_attachment_instance_from(&attachment_2, page_size * 1, 1),
+        ],
+        0,
+    );
+    let index_block_hash = StacksBlockId([1u8; 32]);
+    assert_eq!(
+        attachments_batch.get_p2p_inventory_request(),
+        Some(GetAttachmentsInvData {
+            index_block_hash,
+            pages: vec![0, 1],
+        })
+    );
+    assert_eq!(AttachmentsBatch::new().get_p2p_inventory_request(), None);
+
+    // Only reachable over p2p, so not one of the batch's peers yet
+    let peer_url = UrlString::try_from("http://localhost:30443").unwrap();
+    let peers = new_peers(vec![("http://localhost:20443", 4, 4)]);
+    let mut context =
+        AttachmentsBatchStateContext::new(attachments_batch, peers, &ConnectionOptions::default());
+    let report = ReliabilityReport::new(2, 2);
+
+    // An inventory taken at another block is ignored
+    let inventory = AttachmentsInvData {
+        index_block_hash: StacksBlockId([2u8; 32]),
+        pages: vec![
+            AttachmentPage {
+                index: 0,
+                inventory: vec![1],
+            },
+            AttachmentPage {
+                index: 1,
+                inventory: vec![1],
+            },
+        ],
     };
+    assert_eq!(
+        context.extend_with_p2p_inventory(peer_url.clone(), &report, inventory.clone()),
+        0
+    );
 
-    let atlas_db = AtlasDB::connect_memory_db_v1(atlas_config.clone()).unwrap();
-    let conn = atlas_db.conn;
+    // So is one that doesn't cover all the pages an HTTP request would ask for
+    let partial_inventory = AttachmentsInvData {
+        index_block_hash,
+        pages: vec![inventory.pages[0].clone()],
+    };
+    assert_eq!(
+        context.extend_with_p2p_inventory(peer_url.clone(), &report, partial_inventory),
+        0
+    );
+    assert!(context.inventories.is_empty());
+    assert!(!context.peers.contains_key(&peer_url));
 
-    let attachments = [
-        AttachmentInstance {
-            // content_hash, index_block_hash, and txid must contain hex letters!
-            //  because their fields are declared `STRING`, if you supply all numerals,
-            //  sqlite assigns the field a REAL affinity (instead of TEXT)
-            content_hash: Hash160([0xa0; 20]),
-            attachment_index: 1,
-            stacks_block_height: 1,
-            index_block_hash: StacksBlockId([0xb1; 32]),
-            metadata: "".into(),
-            contract_id: QualifiedContractIdentifier::transient(),
-            tx_id: Txid([0x2f; 32]),
-            canonical_stacks_tip_height: None,
-        },
-        AttachmentInstance {
-            content_hash: Hash160([0x00; 20]),
-            attachment_index: 1,
-            stacks_block_height: 1,
-            index_block_hash: StacksBlockId([0x0a; 32]),
-            metadata: "".into(),
-            contract_id: QualifiedContractIdentifier::transient(),
-            tx_id: Txid([0x0b; 32]),
-            canonical_stacks_tip_height: None,
-        },
-    ];
+    let inventory = AttachmentsInvData {
+        index_block_hash,
+        ..inventory
+    };
 
-    for attachment in attachments.iter() {
-        // need to manually insert data, because the insertion routine in the codebase
-        //  sets `status` which doesn't exist in v1
-        conn.execute(
-            "INSERT OR REPLACE INTO attachment_instances (
-               content_hash, created_at, index_block_hash,
-               attachment_index, block_height, is_available,
-                metadata, contract_id, tx_id)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-            rusqlite::params![
-                &attachment.content_hash,
-                &0,
-                &attachment.index_block_hash,
-                &attachment.attachment_index,
-                &u64_to_sql(attachment.stacks_block_height).unwrap(),
-                &true,
-                &attachment.metadata,
-                &attachment.contract_id.to_string(),
-                &attachment.tx_id,
-            ],
-        )
+    // So is one from a peer whose data URL has never answered, since the attachments could
+    // not be downloaded from it
+    assert_eq!(
+        context.extend_with_p2p_inventory(
+            peer_url.clone(),
+            &ReliabilityReport::new(3, 0),
+            inventory.clone()
+        ),
+        0
+    );
+    assert!(context.inventories.is_empty());
+
+    assert_eq!(
+        context.extend_with_p2p_inventory(peer_url.clone(), &report, inventory),
+        1
+    );
+    assert_eq!(context.peers.get(&peer_url), Some(&report));
+    let responses = context
+        .inventories
+        .get(&(
+            QualifiedContractIdentifier::transient(),
+            vec![0, 1],
+            index_block_hash,
+        ))
         .unwrap();
+    assert_eq!(responses.len(), 1);
+
+    // Both attachments are then requested, over HTTP, from the peer that sent its inventory
+    // over p2p
+    let mut attachments_requests = context.get_prioritized_attachments_requests();
+    assert_eq!(attachments_requests.len(), 2);
+    while let Some(request) = attachments_requests.pop() {
+        assert_eq!(request.get_url(), &peer_url);
     }
+}
 
-    // perform the migration and unwrap() to assert that it runs okay
-    let atlas_db = AtlasDB::connect_with_sqlconn(atlas_config, conn).unwrap();
+#[test]
+fn test_downloader_context_differential_inventories() {
+    let attachment_1 = new_attachment_from("facade01");
+    let attachment_2 = new_attachment_from("facade02");
 
-    let mut attachments_fetched_a0 = atlas_db
-        .find_all_attachment_instances(&Hash160([0xa0; 20]))
+    let page_size = AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE;
+    let attachments_batch = new_attachments_batch_from(
+        vec![
+            new_attachment_instance_from(&attachment_1, page_size * 0, 1),
+            new_attachment_instance_from(&attachment_2, page_size * 1, 1),
+        ],
+        0,
+    );
+    let index_block_hash = attachments_batch.index_block_hash;
+    let inventory_key = (
+        QualifiedContractIdentifier::transient(),
+        vec![0, 1],
+        index_block_hash,
+    );
+    let peer_url = UrlString::try_from("http://localhost:20443").unwrap();
+    let peers = new_peers(vec![("http://localhost:20443", 4, 4)]);
+
+    // Nothing is known about the peer's pages on the first attempt
+    let context = AttachmentsBatchStateContext::new(
+        attachments_batch,
+        peers.clone(),
+        &ConnectionOptions::default(),
+    );
+    let mut inventories_requests = context.get_prioritized_attachments_inventory_requests();
+    let request = inventories_requests.pop().unwrap();
+    assert!(inventories_requests.is_empty());
+    assert!(request.since.is_empty());
+
+    let mut inventories_results = BatchedRequestsResult::empty();
+    let response = new_attachments_inventory_response(vec![(0, vec![0]), (1, vec![0])]);
+    inventories_results
+        .succeeded
+        .insert(request, Some(response));
+    let context = context.extend_with_inventories(&mut inventories_results);
+    let known_pages = context
+        .attachments_batch
+        .known_inventory_pages
+        .get(&peer_url)
         .unwrap();
+    assert_eq!(known_pages.len(), 2);
+
+    // On retry, the versions of the pages already received are sent along...
+    let mut attachments_batch = context.attachments_batch.clone();
+    attachments_batch.bump_retry_count();
+    let context = AttachmentsBatchStateContext::new(
+        attachments_batch,
+        peers.clone(),
+        &ConnectionOptions::default(),
+    );
+    let mut inventories_requests = context.get_prioritized_attachments_inventory_requests();
+    let request = inventories_requests.pop().unwrap();
+    let unchanged_page = AttachmentPage {
+        index: 0,
+        inventory: vec![0],
+    };
     assert_eq!(
-        attachments_fetched_a0.len(),
-        1,
-        "Should have one attachment instance marked 'checked' with hash `0xa0a0a0..`"
+        request.since,
+        vec![(0, unchanged_page.digest()), (1, unchanged_page.digest())]
     );
 
-    let attachment_a0 = attachments_fetched_a0.pop().unwrap();
-    assert_eq!(&attachment_a0, &attachments[0]);
-
-    let mut attachments_fetched_00 = atlas_db
-        .find_all_attachment_instances(&Hash160([0x00; 20]))
+    // ...and pages the peer left out as unchanged are restored from them
+    let mut inventories_results = BatchedRequestsResult::empty();
+    let response = new_differential_attachments_inventory_response(vec![(1, vec![1])], vec![0]);
+    inventories_results
+        .succeeded
+        .insert(request.clone(), Some(response));
+    let context = context.extend_with_inventories(&mut inventories_results);
+    let response = context
+        .inventories
+        .get(&inventory_key)
+        .unwrap()
+        .get(&peer_url)
         .unwrap();
     assert_eq!(
-        attachments_fetched_00.len(),
-        1,
-        "Should have one attachment instance marked 'checked' with hash `0x000000..`"
+        response.pages,
+        vec![
+            unchanged_page,
+            AttachmentPage {
+                index: 1,
+                inventory: vec![1],
+            }
+        ]
     );
-
-    let attachment_00 = attachments_fetched_00.pop().unwrap();
-    assert_eq!(&attachment_00, &attachments[1]);
-
+    assert!(response.unchanged.is_empty());
     assert_eq!(
-        atlas_db.queued_attachments().unwrap().len(),
-        0,
-        "Should have no attachment instance marked 'queued'"
+        context.get_prioritized_attachments_requests().len(),
+        1,
+        "attachment 2 is now available"
     );
+
+    // A peer claiming pages are unchanged without having sent them before is not believed
+    let mut attachments_batch = context.attachments_batch.clone();
+    attachments_batch.known_inventory_pages.clear();
+    let context =
+        AttachmentsBatchStateContext::new(attachments_batch, peers, &ConnectionOptions::default());
+    let mut inventories_results = BatchedRequestsResult::empty();
+    let response = new_differential_attachments_inventory_response(vec![], vec![0, 1]);
+    inventories_results
+        .succeeded
+        .insert(request, Some(response));
+    let context = context.extend_with_inventories(&mut inventories_results);
+    assert!(context.inventories.is_empty());
+    let report = context.peers.get(&peer_url).unwrap();
+    assert_eq!(report.total_requests_sent, 5);
+    assert_eq!(report.total_requests_success, 4);
 }
 
 #[test]
-fn test_evict_k_oldest_uninstantiated_attachments() {
-    let atlas_config = AtlasConfig {
-        contracts: HashSet::new(),
-        attachments_max_size: 1024,
-        max_uninstantiated_attachments: 10,
-        uninstantiated_attachments_expire_after: 0,
-        unresolved_attachment_instances_expire_after: 10,
-        genesis_attachments: None,
-    };
+fn test_redirected_data_url() {
+    let peer_url = UrlString::try_from("http://old.example.com:20443").unwrap();
 
-    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+    // only the scheme, host and port of the location are kept
+    assert_eq!(
+        redirected_data_url(
+            &peer_url,
+            "http://new.example.com:20443/v2/attachments/inv?index_block_hash=00&pages_indexes=1"
+        ),
+        Some(UrlString::try_from("http://new.example.com:20443").unwrap())
+    );
+    assert_eq!(
+        redirected_data_url(&peer_url, "https://new.example.com/v2/attachments/inv"),
+        Some(UrlString::try_from("https://new.example.com").unwrap())
+    );
+    assert_eq!(
+        redirected_data_url(&peer_url, "//10.0.0.1:20443/v2/attachments/inv"),
+        Some(UrlString::try_from("http://10.0.0.1:20443").unwrap())
+    );
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade00"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 1);
+    // Atlas paths are fixed, so there is nothing to follow on the same host
+    assert_eq!(
+        redirected_data_url(&peer_url, "/api/v2/attachments/inv"),
+        None
+    );
+    assert_eq!(
+        redirected_data_url(
+            &peer_url,
+            "http://old.example.com:20443/api/v2/attachments/inv"
+        ),
+        None
+    );
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade01"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 2);
+    // not somewhere we can download attachments from
+    assert_eq!(
+        redirected_data_url(&peer_url, "ftp://new.example.com/"),
+        None
+    );
+}
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade02"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 3);
+#[test]
+fn test_advertised_data_url() {
+    let peer_url = UrlString::try_from("http://old.example.com:20443").unwrap();
+    let new_url = UrlString::try_from("http://new.example.com:20443").unwrap();
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade02"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 3);
+    let mut response = new_attachments_inventory_response(vec![(0, vec![1])]);
+    assert_eq!(advertised_data_url(&peer_url, &response), None);
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade03"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 4);
+    advertise_data_url(response.preamble_mut(), &peer_url);
+    assert_eq!(advertised_data_url(&peer_url, &response), None);
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade04"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 5);
+    advertise_data_url(response.preamble_mut(), &new_url);
+    assert_eq!(
+        advertised_data_url(&peer_url, &response),
+        Some(new_url.clone())
+    );
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade05"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 6);
+    // nodes without a data URL do not advertise one
+    let mut response = new_attachments_inventory_response(vec![(0, vec![1])]);
+    advertise_data_url(response.preamble_mut(), &UrlString::try_from("").unwrap());
+    assert_eq!(advertised_data_url(&peer_url, &response), None);
+}
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade06"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 7);
+#[test]
+fn test_attachment_requests_redirect() {
+    let old_url = UrlString::try_from("http://localhost:20443").unwrap();
+    let new_url = UrlString::try_from("http://localhost:30443").unwrap();
+    let newer_url = UrlString::try_from("http://localhost:40443").unwrap();
+
+    let request = new_attachments_inventory_request("http://localhost:20443", vec![0], 1, 4, 4);
+    assert!(request.redirect(&old_url, &new_url, 0).is_none());
+    assert!(request.redirect(&new_url, &newer_url, 2).is_none());
+
+    let redirected = request.redirect(&old_url, &new_url, 2).unwrap();
+    assert_eq!(redirected.get_url(), &new_url);
+    assert_eq!(redirected.redirects, 1);
+    assert_eq!(redirected.key(), request.key());
+    let redirected = redirected.redirect(&new_url, &newer_url, 2).unwrap();
+    assert_eq!(redirected.get_url(), &newer_url);
+    assert_eq!(redirected.redirects, 2);
+    assert!(redirected.redirect(&newer_url, &old_url, 2).is_none());
+
+    // the peer keeps its reliability report at its new URL
+    let attachment = new_attachment_from("facade01");
+    let request = new_attachment_request(
+        vec![
+            ("http://localhost:20443", 4, 4),
+            ("http://localhost:50443", 2, 1),
+        ],
+        &attachment.hash(),
+        1,
+    );
+    assert_eq!(request.get_url(), &old_url);
+    let redirected = request.redirect(&old_url, &new_url, 1).unwrap();
+    assert_eq!(redirected.get_url(), &new_url);
+    assert_eq!(redirected.redirects, 1);
+    assert!(!redirected.sources.contains_key(&old_url));
+    assert_eq!(
+        redirected.sources.get(&new_url),
+        Some(&ReliabilityReport::new(4, 4))
+    );
+    assert!(redirected.redirect(&new_url, &newer_url, 1).is_none());
+}
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade07"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 8);
+#[test]
+fn test_downloader_context_moved_peers() {
+    let attachment = new_attachment_from("facade01");
+    let attachments_batch =
+        new_attachments_batch_from(vec![new_attachment_instance_from(&attachment, 0, 1)], 0);
+    let peers = new_peers(vec![
+        ("http://localhost:20443", 4, 4),
+        ("http://localhost:30443", 3, 3),
+    ]);
+    let peer_url_1 = UrlString::try_from("http://localhost:20443").unwrap();
+    let peer_url_2 = UrlString::try_from("http://localhost:30443").unwrap();
+    let new_url_1 = UrlString::try_from("http://127.0.0.1:20443").unwrap();
+    let new_url_2 = UrlString::try_from("http://127.0.0.1:30443").unwrap();
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade08"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 9);
+    let context =
+        AttachmentsBatchStateContext::new(attachments_batch, peers, &ConnectionOptions::default());
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade09"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
+    // peer 1 redirected its inventory request, which then succeeded at its new URL, and peer 2
+    // advertised a new URL
+    let mut inventories_results = BatchedRequestsResult::empty();
+    inventories_results
+        .redirected
+        .insert(peer_url_1.clone(), new_url_1.clone());
+    let mut inventories_requests = context.get_prioritized_attachments_inventory_requests();
+    while let Some(request) = inventories_requests.pop() {
+        let mut response = new_attachments_inventory_response(vec![(0, vec![1])]);
+        let request = if request.get_url() == &peer_url_1 {
+            request.redirect(&peer_url_1, &new_url_1, 1).unwrap()
+        } else {
+            advertise_data_url(response.preamble_mut(), &new_url_2);
+            request
+        };
+        inventories_results
+            .succeeded
+            .insert(request, Some(response));
+    }
+    let context = context.extend_with_inventories(&mut inventories_results);
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade10"))
-        .unwrap();
-    // We reached `max_uninstantiated_attachments`. Eviction should start kicking in
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
-    // The latest attachment inserted should be available
+    assert_eq!(context.moved_data_urls.len(), 2);
+    assert_eq!(context.moved_data_urls.get(&peer_url_1), Some(&new_url_1));
+    assert_eq!(context.moved_data_urls.get(&peer_url_2), Some(&new_url_2));
+
+    // peer 1's report followed it, and was credited for the request
     assert_eq!(
-        atlas_db
-            .find_uninstantiated_attachment(&new_attachment_from("facade10").hash())
-            .unwrap()
-            .is_some(),
-        true
+        context.peers.get(&new_url_1),
+        Some(&ReliabilityReport::new(5, 5))
     );
-    // The first attachment inserted should be gone
     assert_eq!(
-        atlas_db
-            .find_uninstantiated_attachment(&new_attachment_from("facade00").hash())
-            .unwrap()
-            .is_none(),
-        true
+        context.peers.get(&peer_url_1),
+        Some(&ReliabilityReport::new(4, 4))
     );
-    // The second attachment inserted should be available
     assert_eq!(
-        atlas_db
-            .find_uninstantiated_attachment(&new_attachment_from("facade01").hash())
-            .unwrap()
-            .is_some(),
-        true
+        context.peers.get(&peer_url_2),
+        Some(&ReliabilityReport::new(4, 4))
     );
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade11"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
-
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade12"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
-
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade13"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
+    // for now, peer 2 is still asked at the URL the request was sent to
+    let mut attachments_requests = context.get_prioritized_attachments_requests();
+    let request = attachments_requests.pop().unwrap();
+    assert_eq!(request.sources.len(), 2);
+    assert!(request.sources.contains_key(&new_url_1));
+    assert!(request.sources.contains_key(&peer_url_2));
+}
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade14"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
+#[test]
+fn test_downloader_moved_data_urls() {
+    let mut downloader = AttachmentsDownloader::new(vec![]);
+    let url_1 = UrlString::try_from("http://localhost:20443").unwrap();
+    let url_2 = UrlString::try_from("http://localhost:30443").unwrap();
+    let url_3 = UrlString::try_from("http://localhost:40443").unwrap();
+
+    assert_eq!(downloader.current_data_url(&url_1), url_1);
+
+    downloader.record_moved_data_url(url_1.clone(), url_2.clone());
+    assert_eq!(downloader.current_data_url(&url_1), url_2);
+    assert_eq!(downloader.current_data_url(&url_2), url_2);
+
+    // moves are followed
+    downloader.record_moved_data_url(url_2.clone(), url_3.clone());
+    assert_eq!(downloader.current_data_url(&url_1), url_3);
+    assert_eq!(downloader.current_data_url(&url_2), url_3);
+
+    // peers redirecting to each other do not send us around in circles
+    downloader.record_moved_data_url(url_3.clone(), url_1.clone());
+    assert_eq!(downloader.current_data_url(&url_1), url_1);
+    assert_eq!(downloader.current_data_url(&url_3), url_3);
+    assert!(downloader.get_reliability_report(&url_1).is_none());
+}
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade15"))
-        .unwrap();
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
-    // The 5th attachment inserted should be gone
-    assert_eq!(
-        atlas_db
-            .find_uninstantiated_attachment(&new_attachment_from("facade05").hash())
-            .unwrap()
-            .is_none(),
-        true
+#[test]
+fn test_partial_attachment_reassembly() {
+    let attachment = new_attachment_from("facade01facade02facade03");
+    let request = new_attachment_request(
+        vec![("http://localhost:20443", 1, 1)],
+        &attachment.hash(),
+        1,
     );
-    // The 6th attachment inserted should be available
+    let mut partial = PartialAttachment::new(attachment.content.len() as u64, request);
     assert_eq!(
-        atlas_db
-            .find_uninstantiated_attachment(&new_attachment_from("facade06").hash())
-            .unwrap()
-            .is_some(),
-        true
+        partial.missing_ranges(10),
+        vec![
+            AttachmentRange {
+                offset: 0,
+                length: 10
+            },
+            AttachmentRange {
+                offset: 10,
+                length: 10
+            },
+            AttachmentRange {
+                offset: 20,
+                length: 4
+            },
+        ]
     );
-    // The latest attachment inserted should be available
+
+    // chunks that are empty or run past the end are refused
+    assert!(!partial.insert_chunk(20, vec![]));
+    assert!(!partial.insert_chunk(20, attachment.content[19..].to_vec()));
+
+    assert!(partial.insert_chunk(10, attachment.content[10..20].to_vec()));
     assert_eq!(
-        atlas_db
-            .find_uninstantiated_attachment(&new_attachment_from("facade15").hash())
-            .unwrap()
-            .is_some(),
-        true
+        partial.missing_ranges(16),
+        vec![
+            AttachmentRange {
+                offset: 0,
+                length: 10
+            },
+            AttachmentRange {
+                offset: 20,
+                length: 4
+            },
+        ]
     );
+    assert!(partial.try_reassemble().is_none());
+
+    // overlapping chunks are fine
+    assert!(partial.insert_chunk(0, attachment.content[0..12].to_vec()));
+    assert!(partial.insert_chunk(20, attachment.content[20..].to_vec()));
+    assert!(partial.missing_ranges(16).is_empty());
+    assert_eq!(partial.try_reassemble(), Some(attachment));
 }
 
 #[test]
-fn test_evict_expired_uninstantiated_attachments() {
-    let atlas_config = AtlasConfig {
-        contracts: HashSet::new(),
-        attachments_max_size: 1024,
-        max_uninstantiated_attachments: 100,
-        uninstantiated_attachments_expire_after: 10,
-        unresolved_attachment_instances_expire_after: 10,
-        genesis_attachments: None,
-    };
+fn test_downloader_context_attachment_chunk_requests() {
+    let attachment = new_attachment_from("facade01facade02facade03facade04");
+    let attachment_hash = attachment.hash();
 
-    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+    let attachments_batch =
+        new_attachments_batch_from(vec![new_attachment_instance_from(&attachment, 0, 1)], 0);
+    let peers = new_peers(vec![
+        ("http://localhost:20443", 4, 4),
+        ("http://localhost:30443", 3, 3),
+        ("http://localhost:40443", 2, 2),
+    ]);
+    let peer_url_1 = UrlString::try_from("http://localhost:20443").unwrap();
+    let peer_url_2 = UrlString::try_from("http://localhost:30443").unwrap();
+    let peer_url_3 = UrlString::try_from("http://localhost:40443").unwrap();
 
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade00"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade01"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade02"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade03"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade04"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade05"))
-        .unwrap();
-    thread::sleep(time::Duration::from_secs(11));
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade06"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade07"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade08"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade09"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade10"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade11"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade12"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade13"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade14"))
-        .unwrap();
-    atlas_db
-        .insert_uninstantiated_attachment(&new_attachment_from("facade15"))
-        .unwrap();
-    // Count before eviction should be 16
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 16);
-    atlas_db.evict_expired_uninstantiated_attachments().unwrap();
-    // Count after eviction should be 10
-    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
-}
+    let mut connection_options = ConnectionOptions::default();
+    connection_options.attachment_chunk_size = 10;
+    let context = AttachmentsBatchStateContext::new(attachments_batch, peers, &connection_options);
 
-#[test]
-fn test_evict_expired_unresolved_attachment_instances() {
-    let atlas_config = AtlasConfig {
-        contracts: HashSet::new(),
-        attachments_max_size: 1024,
-        max_uninstantiated_attachments: 100,
-        uninstantiated_attachments_expire_after: 200,
-        unresolved_attachment_instances_expire_after: 10,
-        genesis_attachments: None,
+    let mut inventories_requests = context.get_prioritized_attachments_inventory_requests();
+    let mut inventories_results = BatchedRequestsResult::empty();
+    while let Some(request) = inventories_requests.pop() {
+        let response = new_attachments_inventory_response(vec![(0, vec![1])]);
+        inventories_results
+            .succeeded
+            .insert(request, Some(response));
+    }
+    let context = context.extend_with_inventories(&mut inventories_results);
+
+    // Several peers have the attachment: its first byte range is requested from the best one
+    let mut attachments_requests = context.get_prioritized_attachments_requests();
+    assert_eq!(attachments_requests.len(), 1);
+    let request = attachments_requests.pop().unwrap();
+    let first_range = AttachmentRange {
+        offset: 0,
+        length: 10,
     };
-    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+    assert_eq!(request.range, Some(first_range));
+    assert_eq!(request.get_url(), &peer_url_1);
+    let localhost = PeerHost::from_host_port("127.0.0.1".to_string(), 1024);
+    let request_type = request.make_request_type(localhost);
+    let request_path = request_type.request_path();
+    assert!(request_path.starts_with(&format!("/v2/attachments/{}?", &attachment_hash)));
+    assert!(request_path.contains("offset=0"));
+    assert!(request_path.contains("length=10"));
+
+    // A failed byte range is retried from the next best peer
+    let retry = request.failover(&peer_url_1).unwrap();
+    assert_eq!(retry.get_url(), &peer_url_2);
+    assert_eq!(retry.range, Some(first_range));
+
+    let mut attachments_results = BatchedRequestsResult::empty();
+    let response = new_attachment_chunk_response(&attachment, first_range);
+    attachments_results
+        .succeeded
+        .insert(request, Some(response));
+    let context = context.extend_with_attachments(&mut attachments_results);
+    assert!(context.attachments.is_empty());
+    assert_eq!(context.partial_attachments.len(), 1);
+
+    // The remaining ranges are spread over all the peers, best first
+    let mut chunk_requests = context.get_attachment_chunk_requests().into_sorted_vec();
+    chunk_requests.sort_by_key(|request| request.range.unwrap().offset);
+    let ranges: Vec<_> = chunk_requests
+        .iter()
+        .map(|request| (request.range.unwrap(), request.get_url().clone()))
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            (
+                AttachmentRange {
+                    offset: 10,
+                    length: 10
+                },
+                peer_url_1.clone()
+            ),
+            (
+                AttachmentRange {
+                    offset: 20,
+                    length: 10
+                },
+                peer_url_2.clone()
+            ),
+            (
+                AttachmentRange {
+                    offset: 30,
+                    length: 2
+                },
+                peer_url_3.clone()
+            ),
+        ]
+    );
 
-    // Insert some uninstantiated attachments
-    let uninstantiated_attachment_instances = [
-        new_attachment_instance_from(&new_attachment_from("facade11"), 0, 1),
-        new_attachment_instance_from(&new_attachment_from("facade12"), 1, 1),
-        new_attachment_instance_from(&new_attachment_from("facade13"), 2, 1),
-        new_attachment_instance_from(&new_attachment_from("facade14"), 3, 1),
-        new_attachment_instance_from(&new_attachment_from("facade15"), 4, 1),
-        new_attachment_instance_from(&new_attachment_from("facade16"), 5, 1),
-        new_attachment_instance_from(&new_attachment_from("facade17"), 6, 1),
-        new_attachment_instance_from(&new_attachment_from("facade18"), 7, 1),
-    ];
-    for attachment_instance in uninstantiated_attachment_instances.iter() {
-        atlas_db
-            .queue_attachment_instance(attachment_instance)
-            .unwrap();
-        atlas_db
-            .mark_attachment_instance_checked(attachment_instance, false)
-            .unwrap();
+    // Once every range is in, the attachment is reassembled
+    let mut attachments_results = BatchedRequestsResult::empty();
+    for request in chunk_requests.into_iter() {
+        let response = new_attachment_chunk_response(&attachment, request.range.unwrap());
+        attachments_results
+            .succeeded
+            .insert(request, Some(response));
     }
+    let context = context.extend_with_attachments(&mut attachments_results);
+    assert!(context.partial_attachments.is_empty());
+    assert!(context.attachments.contains(&attachment));
+    assert!(context.get_attachment_chunk_requests().is_empty());
+}
 
-    // Insert some instanciated attachments
-    let instantiated_attachment_instances = [
-        new_attachment_instance_from(&new_attachment_from("facade21"), 8, 1),
-        new_attachment_instance_from(&new_attachment_from("facade22"), 9, 1),
-        new_attachment_instance_from(&new_attachment_from("facade23"), 10, 1),
-        new_attachment_instance_from(&new_attachment_from("facade24"), 11, 1),
-    ];
-    for attachment_instance in instantiated_attachment_instances.iter() {
-        atlas_db
-            .queue_attachment_instance(attachment_instance)
-            .unwrap();
-        atlas_db
-            .mark_attachment_instance_checked(attachment_instance, true)
-            .unwrap();
-    }
+#[test]
+fn test_downloader_context_attachment_chunks_hash_mismatch() {
+    let attachment = new_attachment_from("facade01facade02facade03facade04");
+    let forged = new_attachment_from("facade01facade02facade03facade05");
 
-    thread::sleep(time::Duration::from_secs(11));
+    let attachments_batch =
+        new_attachments_batch_from(vec![new_attachment_instance_from(&attachment, 0, 1)], 0);
+    let peers = new_peers(vec![
+        ("http://localhost:20443", 4, 4),
+        ("http://localhost:30443", 3, 3),
+    ]);
+    let mut connection_options = ConnectionOptions::default();
+    connection_options.attachment_chunk_size = 16;
+    let context = AttachmentsBatchStateContext::new(attachments_batch, peers, &connection_options);
 
-    // Insert more uninstanciated attachments
-    let uninstantiated_attachment_instances = [
-        new_attachment_instance_from(&new_attachment_from("facade31"), 12, 1),
-        new_attachment_instance_from(&new_attachment_from("facade32"), 13, 1),
-        new_attachment_instance_from(&new_attachment_from("facade33"), 14, 1),
-    ];
-    for attachment_instance in uninstantiated_attachment_instances.iter() {
-        atlas_db
-            .queue_attachment_instance(attachment_instance)
-            .unwrap();
-        atlas_db
-            .mark_attachment_instance_checked(attachment_instance, false)
-            .unwrap();
+    let mut inventories_requests = context.get_prioritized_attachments_inventory_requests();
+    let mut inventories_results = BatchedRequestsResult::empty();
+    while let Some(request) = inventories_requests.pop() {
+        let response = new_attachments_inventory_response(vec![(0, vec![1])]);
+        inventories_results
+            .succeeded
+            .insert(request, Some(response));
     }
+    let context = context.extend_with_inventories(&mut inventories_results);
 
-    // Count before eviction should be 11
-    assert_eq!(
-        atlas_db.count_unresolved_attachment_instances().unwrap(),
-        11
-    );
-    atlas_db
-        .evict_expired_unresolved_attachment_instances()
+    let request = context
+        .get_prioritized_attachments_requests()
+        .pop()
         .unwrap();
-    // Count after eviction should be 3
-    assert_eq!(atlas_db.count_unresolved_attachment_instances().unwrap(), 3);
+    let mut attachments_results = BatchedRequestsResult::empty();
+    let response = new_attachment_chunk_response(&attachment, request.range.unwrap());
+    attachments_results
+        .succeeded
+        .insert(request, Some(response));
+    let context = context.extend_with_attachments(&mut attachments_results);
+
+    // The second half comes from a peer serving different content
+    let mut attachments_results = BatchedRequestsResult::empty();
+    for request in context.get_attachment_chunk_requests().into_iter() {
+        let response = new_attachment_chunk_response(&forged, request.range.unwrap());
+        attachments_results
+            .succeeded
+            .insert(request, Some(response));
+    }
+    let context = context.extend_with_attachments(&mut attachments_results);
+
+    // The reassembled attachment is discarded, and left for the batch to retry
+    assert!(context.attachments.is_empty());
+    assert!(context.partial_attachments.is_empty());
+    assert!(context.get_attachment_chunk_requests().is_empty());
 }
 
 #[test]
-fn test_get_minmax_heights_atlasdb() {
-    let atlas_config = AtlasConfig {
-        contracts: HashSet::new(),
-        attachments_max_size: 1024,
-        max_uninstantiated_attachments: 100,
-        uninstantiated_attachments_expire_after: 10,
-        unresolved_attachment_instances_expire_after: 10,
-        genesis_attachments: None,
-    };
-
-    let atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
-
-    // Calling get_minmax_heights_window_for_page_index on a blank db should return an error,
-    // not be crashing.
-    let res = atlas_db
-        .get_minmax_heights_window_for_page_index(0)
-        .unwrap_err();
+fn test_attachments_not_found_cache_expiry() {
+    let attachment = new_attachment_from("facade01");
+    let peer_url = UrlString::try_from("http://localhost:20443").unwrap();
+
+    let mut not_found_cache = AttachmentsNotFoundCache::new(0);
+    not_found_cache.insert(peer_url.clone(), attachment.hash());
+    assert!(!not_found_cache.contains(&peer_url, &attachment.hash()));
+    not_found_cache.evict_expired();
+    assert!(not_found_cache.is_empty());
+
+    not_found_cache.set_ttl(3600);
+    not_found_cache.insert(peer_url.clone(), attachment.hash());
+    assert!(not_found_cache.contains(&peer_url, &attachment.hash()));
+    not_found_cache.evict_expired();
+    assert_eq!(not_found_cache.len(), 1);
 }
 
 #[test]
-fn test_bit_vectors() {
+fn test_keep_uninstantiated_attachments() {
+    let bns_contract_id = boot_code_id("bns", false);
+    let pox_contract_id = boot_code_id("pox", false);
+
+    let mut contracts = HashSet::new();
+    contracts.insert(bns_contract_id.clone());
+
     let atlas_config = AtlasConfig {
-        contracts: HashSet::new(),
-        attachments_max_size: 1024,
-        max_uninstantiated_attachments: 100,
+        contracts,
+        attachments_max_size: 16,
+        max_uninstantiated_attachments: 10,
         uninstantiated_attachments_expire_after: 10,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        max_queued_attachment_batches: 100,
+        attachment_storage: AttachmentStorage::Sqlite,
+        resolution_history_retention: 3600,
     };
 
-    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+    let atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    assert_eq!(
+        atlas_db.should_keep_attachment(&pox_contract_id, &new_attachment_from("facade02")),
+        false
+    );
+
+    assert_eq!(
+        atlas_db.should_keep_attachment(&bns_contract_id, &new_attachment_from("facade02")),
+        true
+    );
+
+    assert_eq!(
+        atlas_db.should_keep_attachment(
+            &bns_contract_id,
+            &new_attachment_from("facadefacadefacade02")
+        ),
+        false
+    );
+}
+
+#[test]
+fn schema_2_migration() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 10,
+        uninstantiated_attachments_expire_after: 0,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        max_queued_attachment_batches: 100,
+        attachment_storage: AttachmentStorage::Sqlite,
+        resolution_history_retention: 3600,
+    };
+
+    let atlas_db = AtlasDB::connect_memory_db_v1(atlas_config.clone()).unwrap();
+    let conn = atlas_db.conn;
+
+    let attachments = [
+        AttachmentInstance {
+            // content_hash, index_block_hash, and txid must contain hex letters!
+            //  because their fields are declared `STRING`, if you supply all numerals,
+            //  sqlite assigns the field a REAL affinity (instead of TEXT)
+            content_hash: Hash160([0xa0; 20]),
+            attachment_index: 1,
+            stacks_block_height: 1,
+            index_block_hash: StacksBlockId([0xb1; 32]),
+            metadata: "".into(),
+            contract_id: QualifiedContractIdentifier::transient(),
+            tx_id: Txid([0x2f; 32]),
+            canonical_stacks_tip_height: None,
+            origin: AttachmentOrigin::AnchoredBlock,
+        },
+        AttachmentInstance {
+            content_hash: Hash160([0x00; 20]),
+            attachment_index: 1,
+            stacks_block_height: 1,
+            index_block_hash: StacksBlockId([0x0a; 32]),
+            metadata: "".into(),
+            contract_id: QualifiedContractIdentifier::transient(),
+            tx_id: Txid([0x0b; 32]),
+            canonical_stacks_tip_height: None,
+            origin: AttachmentOrigin::AnchoredBlock,
+        },
+    ];
+
+    for attachment in attachments.iter() {
+        // need to manually insert data, because the insertion routine in the codebase
+        //  sets `status` which doesn't exist in v1
+        conn.execute(
+            "INSERT OR REPLACE INTO attachment_instances (
+               content_hash, created_at, index_block_hash,
+               attachment_index, block_height, is_available,
+                metadata, contract_id, tx_id)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                &attachment.content_hash,
+                &0,
+                &attachment.index_block_hash,
+                &attachment.attachment_index,
+                &u64_to_sql(attachment.stacks_block_height).unwrap(),
+                &true,
+                &attachment.metadata,
+                &attachment.contract_id.to_string(),
+                &attachment.tx_id,
+            ],
+        )
+        .unwrap();
+    }
+
+    // perform the migration and unwrap() to assert that it runs okay
+    let atlas_db = AtlasDB::connect_with_sqlconn(atlas_config, conn).unwrap();
+
+    let mut attachments_fetched_a0 = atlas_db
+        .find_all_attachment_instances(&Hash160([0xa0; 20]))
+        .unwrap();
+    assert_eq!(
+        attachments_fetched_a0.len(),
+        1,
+        "Should have one attachment instance marked 'checked' with hash `0xa0a0a0..`"
+    );
+
+    let attachment_a0 = attachments_fetched_a0.pop().unwrap();
+    assert_eq!(&attachment_a0, &attachments[0]);
+
+    let mut attachments_fetched_00 = atlas_db
+        .find_all_attachment_instances(&Hash160([0x00; 20]))
+        .unwrap();
+    assert_eq!(
+        attachments_fetched_00.len(),
+        1,
+        "Should have one attachment instance marked 'checked' with hash `0x000000..`"
+    );
+
+    let attachment_00 = attachments_fetched_00.pop().unwrap();
+    assert_eq!(&attachment_00, &attachments[1]);
+
+    assert_eq!(
+        atlas_db.queued_attachments().unwrap().len(),
+        0,
+        "Should have no attachment instance marked 'queued'"
+    );
+}
+
+fn atlas_db_schema_version(conn: &rusqlite::Connection) -> String {
+    conn.query_row(
+        "SELECT MAX(version) FROM db_config",
+        rusqlite::NO_PARAMS,
+        |row| row.get(0),
+    )
+    .unwrap()
+}
+
+fn atlas_db_has_index(conn: &rusqlite::Connection, name: &str) -> bool {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = ?1",
+        &[name],
+        |row| row.get::<_, i64>(0),
+    )
+    .unwrap()
+        == 1
+}
+
+#[test]
+fn schema_3_migration() {
+    let atlas_config = AtlasConfig::new(false);
+
+    // a new DB is created at the latest version
+    let atlas_db = AtlasDB::connect_memory(atlas_config.clone()).unwrap();
+    assert_eq!(atlas_db_schema_version(atlas_db.conn()), ATLASDB_VERSION);
+    assert!(atlas_db_has_index(
+        atlas_db.conn(),
+        "index_instance_content_hash"
+    ));
+
+    // a v1 DB is migrated through every schema version on connect
+    let atlas_db = AtlasDB::connect_memory_db_v1(atlas_config.clone()).unwrap();
+    assert_eq!(atlas_db_schema_version(atlas_db.conn()), "1");
+    assert!(!atlas_db_has_index(
+        atlas_db.conn(),
+        "index_instance_content_hash"
+    ));
+
+    let atlas_db = AtlasDB::connect_with_sqlconn(atlas_config.clone(), atlas_db.conn).unwrap();
+    assert_eq!(atlas_db_schema_version(atlas_db.conn()), ATLASDB_VERSION);
+    for index in [
+        "index_instance_content_hash",
+        "index_instance_unresolved",
+        "index_instance_attachment_index",
+        "index_uninstantiated_created_at",
+    ] {
+        assert!(
+            atlas_db_has_index(atlas_db.conn(), index),
+            "Missing index {}",
+            index
+        );
+    }
+
+    // connecting to an up-to-date DB is a no-op
+    let atlas_db = AtlasDB::connect_with_sqlconn(atlas_config, atlas_db.conn).unwrap();
+    assert_eq!(atlas_db_schema_version(atlas_db.conn()), ATLASDB_VERSION);
+    assert_eq!(
+        atlas_db
+            .conn()
+            .query_row(
+                "SELECT COUNT(*) FROM db_config WHERE version = ?1",
+                &[ATLASDB_VERSION],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap(),
+        1
+    );
+}
+
+#[test]
+fn test_evict_k_oldest_uninstantiated_attachments() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 10,
+        uninstantiated_attachments_expire_after: 0,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        max_queued_attachment_batches: 100,
+        attachment_storage: AttachmentStorage::Sqlite,
+        resolution_history_retention: 3600,
+    };
+
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade00"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 1);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade01"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 2);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade02"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 3);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade02"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 3);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade03"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 4);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade04"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 5);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade05"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 6);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade06"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 7);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade07"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 8);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade08"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 9);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade09"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade10"))
+        .unwrap();
+    // We reached `max_uninstantiated_attachments`. Eviction should start kicking in
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
+    // The latest attachment inserted should be available
+    assert_eq!(
+        atlas_db
+            .find_uninstantiated_attachment(&new_attachment_from("facade10").hash())
+            .unwrap()
+            .is_some(),
+        true
+    );
+    // The first attachment inserted should be gone
+    assert_eq!(
+        atlas_db
+            .find_uninstantiated_attachment(&new_attachment_from("facade00").hash())
+            .unwrap()
+            .is_none(),
+        true
+    );
+    // The second attachment inserted should be available
+    assert_eq!(
+        atlas_db
+            .find_uninstantiated_attachment(&new_attachment_from("facade01").hash())
+            .unwrap()
+            .is_some(),
+        true
+    );
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade11"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade12"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade13"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade14"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade15"))
+        .unwrap();
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
+    // The 5th attachment inserted should be gone
+    assert_eq!(
+        atlas_db
+            .find_uninstantiated_attachment(&new_attachment_from("facade05").hash())
+            .unwrap()
+            .is_none(),
+        true
+    );
+    // The 6th attachment inserted should be available
+    assert_eq!(
+        atlas_db
+            .find_uninstantiated_attachment(&new_attachment_from("facade06").hash())
+            .unwrap()
+            .is_some(),
+        true
+    );
+    // The latest attachment inserted should be available
+    assert_eq!(
+        atlas_db
+            .find_uninstantiated_attachment(&new_attachment_from("facade15").hash())
+            .unwrap()
+            .is_some(),
+        true
+    );
+}
+
+#[test]
+fn test_evict_expired_uninstantiated_attachments() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 100,
+        uninstantiated_attachments_expire_after: 10,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        max_queued_attachment_batches: 100,
+        attachment_storage: AttachmentStorage::Sqlite,
+        resolution_history_retention: 3600,
+    };
+
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade00"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade01"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade02"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade03"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade04"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade05"))
+        .unwrap();
+    thread::sleep(time::Duration::from_secs(11));
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade06"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade07"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade08"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade09"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade10"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade11"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade12"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade13"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade14"))
+        .unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&new_attachment_from("facade15"))
+        .unwrap();
+    // Count before eviction should be 16
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 16);
+    atlas_db.evict_expired_uninstantiated_attachments().unwrap();
+    // Count after eviction should be 10
+    assert_eq!(atlas_db.count_uninstantiated_attachments().unwrap(), 10);
+}
+
+#[test]
+fn test_evict_expired_unresolved_attachment_instances() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 100,
+        uninstantiated_attachments_expire_after: 200,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        max_queued_attachment_batches: 100,
+        attachment_storage: AttachmentStorage::Sqlite,
+        resolution_history_retention: 3600,
+    };
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    // Insert some uninstantiated attachments
+    let uninstantiated_attachment_instances = [
+        new_attachment_instance_from(&new_attachment_from("facade11"), 0, 1),
+        new_attachment_instance_from(&new_attachment_from("facade12"), 1, 1),
+        new_attachment_instance_from(&new_attachment_from("facade13"), 2, 1),
+        new_attachment_instance_from(&new_attachment_from("facade14"), 3, 1),
+        new_attachment_instance_from(&new_attachment_from("facade15"), 4, 1),
+        new_attachment_instance_from(&new_attachment_from("facade16"), 5, 1),
+        new_attachment_instance_from(&new_attachment_from("facade17"), 6, 1),
+        new_attachment_instance_from(&new_attachment_from("facade18"), 7, 1),
+    ];
+    for attachment_instance in uninstantiated_attachment_instances.iter() {
+        atlas_db
+            .queue_attachment_instance(attachment_instance)
+            .unwrap();
+        atlas_db
+            .mark_attachment_instance_checked(attachment_instance, false)
+            .unwrap();
+    }
+
+    // Insert some instanciated attachments
+    let instantiated_attachment_instances = [
+        new_attachment_instance_from(&new_attachment_from("facade21"), 8, 1),
+        new_attachment_instance_from(&new_attachment_from("facade22"), 9, 1),
+        new_attachment_instance_from(&new_attachment_from("facade23"), 10, 1),
+        new_attachment_instance_from(&new_attachment_from("facade24"), 11, 1),
+    ];
+    for attachment_instance in instantiated_attachment_instances.iter() {
+        atlas_db
+            .queue_attachment_instance(attachment_instance)
+            .unwrap();
+        atlas_db
+            .mark_attachment_instance_checked(attachment_instance, true)
+            .unwrap();
+    }
+
+    thread::sleep(time::Duration::from_secs(11));
+
+    // Insert more uninstanciated attachments
+    let uninstantiated_attachment_instances = [
+        new_attachment_instance_from(&new_attachment_from("facade31"), 12, 1),
+        new_attachment_instance_from(&new_attachment_from("facade32"), 13, 1),
+        new_attachment_instance_from(&new_attachment_from("facade33"), 14, 1),
+    ];
+    for attachment_instance in uninstantiated_attachment_instances.iter() {
+        atlas_db
+            .queue_attachment_instance(attachment_instance)
+            .unwrap();
+        atlas_db
+            .mark_attachment_instance_checked(attachment_instance, false)
+            .unwrap();
+    }
+
+    // Count before eviction should be 11
+    assert_eq!(
+        atlas_db.count_unresolved_attachment_instances().unwrap(),
+        11
+    );
+    atlas_db
+        .evict_expired_unresolved_attachment_instances()
+        .unwrap();
+    // Count after eviction should be 3
+    assert_eq!(atlas_db.count_unresolved_attachment_instances().unwrap(), 3);
+}
+
+#[test]
+fn test_attachment_resolution_percentiles() {
+    assert_eq!(
+        AttachmentResolutionPercentiles::from_sorted_latencies(&[]),
+        None
+    );
+    assert_eq!(
+        AttachmentResolutionPercentiles::from_sorted_latencies(&[7]),
+        Some(AttachmentResolutionPercentiles {
+            p50: 7,
+            p90: 7,
+            p99: 7,
+            max: 7,
+        })
+    );
+    let latencies: Vec<u64> = (1..=200).collect();
+    assert_eq!(
+        AttachmentResolutionPercentiles::from_sorted_latencies(&latencies),
+        Some(AttachmentResolutionPercentiles {
+            p50: 100,
+            p90: 180,
+            p99: 198,
+            max: 200,
+        })
+    );
+}
+
+#[test]
+fn test_attachment_resolution_history() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 100,
+        uninstantiated_attachments_expire_after: 200,
+        unresolved_attachment_instances_expire_after: 200,
+        genesis_attachments: None,
+        max_queued_attachment_batches: 100,
+        attachment_storage: AttachmentStorage::Sqlite,
+        resolution_history_retention: 3600,
+    };
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+    let contract_id = QualifiedContractIdentifier::transient();
+
+    // pretend each instance was first seen some time ago
+    let backdate = |atlas_db: &AtlasDB, instance: &AttachmentInstance, secs: u64| {
+        atlas_db
+            .conn()
+            .execute(
+                "UPDATE attachment_resolutions SET first_seen_at = first_seen_at - ?1 WHERE attachment_index = ?2",
+                rusqlite::params![&u64_to_sql(secs).unwrap(), &instance.attachment_index],
+            )
+            .unwrap();
+    };
+
+    // already stored when checked
+    let attachment_1 = new_attachment_from("facade01");
+    let instance_1 = new_attachment_instance_from(&attachment_1, 0, 1);
+    atlas_db.queue_attachment_instance(&instance_1).unwrap();
+    backdate(&atlas_db, &instance_1, 2);
+    atlas_db
+        .mark_attachment_instance_checked(&instance_1, true)
+        .unwrap();
+
+    // downloaded after being checked
+    let attachment_2 = new_attachment_from("facade02");
+    let instance_2 = new_attachment_instance_from(&attachment_2, 1, 1);
+    atlas_db.queue_attachment_instance(&instance_2).unwrap();
+    backdate(&atlas_db, &instance_2, 30);
+    atlas_db
+        .mark_attachment_instance_checked(&instance_2, false)
+        .unwrap();
+    // queueing the instance again does not reset when it was first seen
+    atlas_db.queue_attachment_instance(&instance_2).unwrap();
+    atlas_db
+        .mark_attachment_instance_checked(&instance_2, false)
+        .unwrap();
+    atlas_db
+        .insert_instantiated_attachment(&attachment_2)
+        .unwrap();
+
+    // never found
+    let attachment_3 = new_attachment_from("facade03");
+    let instance_3 = new_attachment_instance_from(&attachment_3, 2, 1);
+    atlas_db.queue_attachment_instance(&instance_3).unwrap();
+    atlas_db
+        .mark_attachment_instance_checked(&instance_3, false)
+        .unwrap();
+
+    // instances from the initial batch were never waited on
+    let attachment_4 = new_attachment_from("facade04");
+    let instance_4 = new_attachment_instance_from(&attachment_4, 3, 1);
+    atlas_db
+        .insert_initial_attachment_instance(&instance_4)
+        .unwrap();
+
+    let latency = atlas_db
+        .get_attachment_resolution_latency(&contract_id)
+        .unwrap();
+    assert_eq!(latency.history_secs, 3600);
+    assert_eq!(latency.resolved, 2);
+    assert_eq!(latency.unresolved, 1);
+    let percentiles = latency.latency_secs.unwrap();
+    // allow for the clock ticking over during the test
+    assert!(percentiles.p50 >= 2 && percentiles.p50 <= 3);
+    assert!(percentiles.max >= 30 && percentiles.max <= 31);
+
+    // other contracts have no history
+    let other_contract_id = boot_code_id("bns", false);
+    let latency = atlas_db
+        .get_attachment_resolution_latency(&other_contract_id)
+        .unwrap();
+    assert_eq!(latency.resolved, 0);
+    assert_eq!(latency.unresolved, 0);
+    assert_eq!(latency.latency_secs, None);
+
+    // history past its retention is dropped
+    backdate(&atlas_db, &instance_2, 3600);
+    atlas_db.evict_expired_attachment_resolutions().unwrap();
+    let latency = atlas_db
+        .get_attachment_resolution_latency(&contract_id)
+        .unwrap();
+    assert_eq!(latency.resolved, 1);
+    assert_eq!(latency.unresolved, 1);
+
+    // no history is kept if the retention is 0
+    let atlas_config = AtlasConfig {
+        resolution_history_retention: 0,
+        ..atlas_db.atlas_config.clone()
+    };
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+    atlas_db.queue_attachment_instance(&instance_1).unwrap();
+    atlas_db
+        .mark_attachment_instance_checked(&instance_1, true)
+        .unwrap();
+    let latency = atlas_db
+        .get_attachment_resolution_latency(&contract_id)
+        .unwrap();
+    assert_eq!(latency.history_secs, 0);
+    assert_eq!(latency.resolved, 0);
+    assert_eq!(latency.unresolved, 0);
+}
+
+#[test]
+fn test_get_minmax_heights_atlasdb() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 100,
+        uninstantiated_attachments_expire_after: 10,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        max_queued_attachment_batches: 100,
+        attachment_storage: AttachmentStorage::Sqlite,
+        resolution_history_retention: 3600,
+    };
+
+    let atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    // Calling get_minmax_heights_window_for_page_index on a blank db should return an error,
+    // not be crashing.
+    let res = atlas_db
+        .get_minmax_heights_window_for_page_index(0)
+        .unwrap_err();
+}
+
+#[test]
+fn test_bit_vectors() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 100,
+        uninstantiated_attachments_expire_after: 10,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        max_queued_attachment_batches: 100,
+        attachment_storage: AttachmentStorage::Sqlite,
+        resolution_history_retention: 3600,
+    };
+
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
 
     // Insert some uninstantiated attachments
     let uninstantiated_attachment_instances = [
@@ -1227,106 +2734,1088 @@ fn test_bit_vectors() {
     let bit_vector = atlas_db
         .get_attachments_available_at_page_index(0, &block_id_1)
         .unwrap();
-    assert_eq!(bit_vector, [0x00; 64]);
+    assert_eq!(bit_vector, [0x00; 64]);
+
+    let uninstantiated_attachment_instances = [
+        new_attachment_instance_from(&new_attachment_from("facade15"), 4, 1),
+        new_attachment_instance_from(&new_attachment_from("facade16"), 5, 1),
+        new_attachment_instance_from(&new_attachment_from("facade17"), 6, 1),
+        new_attachment_instance_from(&new_attachment_from("facade18"), 7, 1),
+    ];
+    for attachment_instance in uninstantiated_attachment_instances.iter() {
+        atlas_db
+            .queue_attachment_instance(attachment_instance)
+            .unwrap();
+        atlas_db
+            .mark_attachment_instance_checked(attachment_instance, false)
+            .unwrap();
+    }
+    let bit_vector = atlas_db
+        .get_attachments_available_at_page_index(0, &block_id_1)
+        .unwrap();
+    assert_eq!(bit_vector, [0x00; 64]);
+
+    let instantiated_attachment_instances = [
+        new_attachment_instance_from(&new_attachment_from("facade21"), 8, 1),
+        new_attachment_instance_from(&new_attachment_from("facade22"), 9, 1),
+        new_attachment_instance_from(&new_attachment_from("facade23"), 10, 1),
+        new_attachment_instance_from(&new_attachment_from("facade24"), 11, 1),
+    ];
+    for attachment_instance in instantiated_attachment_instances.iter() {
+        atlas_db
+            .queue_attachment_instance(attachment_instance)
+            .unwrap();
+        atlas_db
+            .mark_attachment_instance_checked(attachment_instance, true)
+            .unwrap();
+    }
+
+    let bit_vector = atlas_db
+        .get_attachments_available_at_page_index(0, &block_id_1)
+        .unwrap();
+    let mut expected = [0x00; 64];
+    expected[8] = 1;
+    expected[9] = 1;
+    expected[10] = 1;
+    expected[11] = 1;
+    assert_eq!(bit_vector, expected);
+
+    println!("1: {:?}", bit_vector);
+
+    // Insert some instanciated attachments at block 2
+    let instantiated_attachment_instances = [
+        new_attachment_instance_from(&new_attachment_from("facade31"), 12, 2),
+        new_attachment_instance_from(&new_attachment_from("facade32"), 13, 2),
+        new_attachment_instance_from(&new_attachment_from("facade33"), 14, 2),
+        new_attachment_instance_from(&new_attachment_from("facade34"), 15, 2),
+    ];
+    let block_id_2 = instantiated_attachment_instances[0].index_block_hash;
+    for attachment_instance in instantiated_attachment_instances.iter() {
+        atlas_db
+            .queue_attachment_instance(attachment_instance)
+            .unwrap();
+        atlas_db
+            .mark_attachment_instance_checked(attachment_instance, true)
+            .unwrap();
+    }
+
+    let bit_vector = atlas_db
+        .get_attachments_available_at_page_index(0, &block_id_1)
+        .unwrap();
+    assert_eq!(bit_vector, expected);
+
+    let bit_vector = atlas_db
+        .get_attachments_available_at_page_index(0, &block_id_2)
+        .unwrap();
+    let mut expected = [0x00; 64];
+    expected[12] = 1;
+    expected[13] = 1;
+    expected[14] = 1;
+    expected[15] = 1;
+    assert_eq!(bit_vector, expected);
+}
+
+#[test]
+fn test_attachments_inventory_requests_hashing() {
+    let mut requests = HashMap::new();
+
+    let attachments_inventory_1_request =
+        new_attachments_inventory_request("http://localhost:20443", vec![0, 1], 1, 0, 0);
+    requests.insert(attachments_inventory_1_request.key(), 1);
+
+    let attachments_inventory_2_request =
+        new_attachments_inventory_request("http://localhost:30443", vec![0, 1], 1, 2, 1);
+    requests.insert(attachments_inventory_2_request.key(), 2);
+
+    let attachments_inventory_3_request =
+        new_attachments_inventory_request("http://localhost:40443", vec![0, 1], 1, 2, 2);
+    requests.insert(attachments_inventory_3_request.key(), 3);
+
+    let attachments_inventory_4_request =
+        new_attachments_inventory_request("http://localhost:50443", vec![0, 1], 1, 4, 4);
+    requests.insert(attachments_inventory_4_request.key(), 4);
+
+    println!("{:?}", requests);
+}
+
+#[test]
+fn test_find_attachment_instances_by_contract() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 100,
+        uninstantiated_attachments_expire_after: 200,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        max_queued_attachment_batches: 100,
+        attachment_storage: AttachmentStorage::Sqlite,
+        resolution_history_retention: 3600,
+    };
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    // two instances per block, at heights 1 through 5, inserted out of order
+    let mut attachment_instances = vec![];
+    for block_height in [3, 1, 5, 2, 4] {
+        for i in 0..2 {
+            let attachment = new_attachment_from(&format!("facade{}{}", block_height, i));
+            attachment_instances.push(new_stored_attachment_instance_from(
+                &attachment,
+                (block_height * 2 + i) as u32,
+                block_height,
+            ));
+        }
+    }
+
+    // an instance from another contract
+    let mut other_instance =
+        new_stored_attachment_instance_from(&new_attachment_from("facade99"), 99, 3);
+    other_instance.contract_id =
+        QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.bns").unwrap();
+    attachment_instances.push(other_instance);
+
+    for attachment_instance in attachment_instances.iter() {
+        atlas_db
+            .queue_attachment_instance(attachment_instance)
+            .unwrap();
+        atlas_db
+            .mark_attachment_instance_checked(attachment_instance, true)
+            .unwrap();
+    }
+
+    // a queued (unchecked) instance is not listed
+    atlas_db
+        .queue_attachment_instance(&new_stored_attachment_instance_from(
+            &new_attachment_from("facade98"),
+            98,
+            3,
+        ))
+        .unwrap();
+
+    let contract_id = QualifiedContractIdentifier::transient();
+    let heights_and_indexes = |instances: Vec<AttachmentInstance>| -> Vec<(u64, u32)> {
+        instances
+            .into_iter()
+            .map(|inst| {
+                assert_eq!(inst.contract_id, contract_id);
+                (inst.stacks_block_height, inst.attachment_index)
+            })
+            .collect()
+    };
+
+    let all = atlas_db
+        .find_attachment_instances_by_contract(&contract_id, 0, 10, 0, 100)
+        .unwrap();
+    assert_eq!(
+        heights_and_indexes(all),
+        vec![
+            (1, 2),
+            (1, 3),
+            (2, 4),
+            (2, 5),
+            (3, 6),
+            (3, 7),
+            (4, 8),
+            (4, 9),
+            (5, 10),
+            (5, 11)
+        ]
+    );
+
+    // height range bounds are inclusive
+    let ranged = atlas_db
+        .find_attachment_instances_by_contract(&contract_id, 2, 3, 0, 100)
+        .unwrap();
+    assert_eq!(
+        heights_and_indexes(ranged),
+        vec![(2, 4), (2, 5), (3, 6), (3, 7)]
+    );
+
+    // paginate through the range
+    let page_0 = atlas_db
+        .find_attachment_instances_by_contract(&contract_id, 1, 5, 0, 3)
+        .unwrap();
+    assert_eq!(heights_and_indexes(page_0), vec![(1, 2), (1, 3), (2, 4)]);
+    let page_1 = atlas_db
+        .find_attachment_instances_by_contract(&contract_id, 1, 5, 3, 3)
+        .unwrap();
+    assert_eq!(heights_and_indexes(page_1), vec![(2, 5), (3, 6), (3, 7)]);
+    let page_3 = atlas_db
+        .find_attachment_instances_by_contract(&contract_id, 1, 5, 9, 3)
+        .unwrap();
+    assert_eq!(heights_and_indexes(page_3), vec![(5, 11)]);
+
+    let empty = atlas_db
+        .find_attachment_instances_by_contract(&contract_id, 6, 10, 0, 100)
+        .unwrap();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_find_unresolved_attachment_instances_by_contract() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 100,
+        uninstantiated_attachments_expire_after: 200,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        max_queued_attachment_batches: 100,
+        attachment_storage: AttachmentStorage::Sqlite,
+        resolution_history_retention: 3600,
+    };
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+    let contract_id = QualifiedContractIdentifier::transient();
+
+    // instances at heights 1 through 4; the ones at even heights are already available
+    for block_height in [3, 1, 4, 2] {
+        let attachment = new_attachment_from(&format!("facade{}", block_height));
+        let attachment_instance =
+            new_stored_attachment_instance_from(&attachment, block_height as u32, block_height);
+        atlas_db
+            .queue_attachment_instance(&attachment_instance)
+            .unwrap();
+        atlas_db
+            .mark_attachment_instance_checked(&attachment_instance, block_height % 2 == 0)
+            .unwrap();
+    }
+
+    // an unavailable instance from another contract
+    let mut other_instance =
+        new_stored_attachment_instance_from(&new_attachment_from("facade99"), 99, 3);
+    other_instance.contract_id =
+        QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.bns").unwrap();
+    atlas_db.queue_attachment_instance(&other_instance).unwrap();
+    atlas_db
+        .mark_attachment_instance_checked(&other_instance, false)
+        .unwrap();
+
+    // a queued (unchecked) instance is not listed
+    atlas_db
+        .queue_attachment_instance(&new_stored_attachment_instance_from(
+            &new_attachment_from("facade98"),
+            98,
+            5,
+        ))
+        .unwrap();
+
+    let unresolved: Vec<_> = atlas_db
+        .find_unresolved_attachment_instances_by_contract(&contract_id)
+        .unwrap()
+        .into_iter()
+        .map(|inst| {
+            assert_eq!(inst.contract_id, contract_id);
+            (inst.stacks_block_height, inst.attachment_index)
+        })
+        .collect();
+    assert_eq!(unresolved, vec![(1, 1), (3, 3)]);
+}
+
+#[test]
+fn test_atlas_rate_limiter_requests() {
+    let peer_1 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let peer_2 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+    let mut limiter = AtlasRateLimiter::new(3, 0);
+
+    for _ in 0..3 {
+        assert_eq!(limiter.try_admit(peer_1, 1000), Ok(()));
+    }
+    // peer 1 is out of budget until its window rolls over
+    assert_eq!(limiter.try_admit(peer_1, 1010), Err(50));
+    assert_eq!(
+        limiter.try_admit(peer_1, 1000 + ATLAS_RATE_LIMIT_WINDOW_SECS - 1),
+        Err(1)
+    );
+    // peer 2 has its own budget
+    assert_eq!(limiter.try_admit(peer_2, 1010), Ok(()));
+
+    assert_eq!(
+        limiter.try_admit(peer_1, 1000 + ATLAS_RATE_LIMIT_WINDOW_SECS),
+        Ok(())
+    );
+
+    let usage = limiter.get_usage(&peer_1).unwrap();
+    assert_eq!(usage.total_requests, 4);
+    assert_eq!(usage.total_throttled, 2);
+    assert_eq!(usage.window_requests, 1);
+}
+
+#[test]
+fn test_atlas_rate_limiter_bytes() {
+    let peer = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let mut limiter = AtlasRateLimiter::new(0, 1000);
+
+    assert_eq!(limiter.try_admit(peer, 1000), Ok(()));
+    limiter.record_bytes_served(peer, 600, 1000);
+    assert_eq!(limiter.try_admit(peer, 1001), Ok(()));
+    limiter.record_bytes_served(peer, 600, 1001);
+
+    // over the byte budget; the overshoot of the last response is tolerated
+    assert_eq!(limiter.try_admit(peer, 1002), Err(58));
+
+    let usage = limiter.get_usage(&peer).unwrap();
+    assert_eq!(usage.total_bytes, 1200);
+    assert_eq!(usage.total_requests, 2);
+
+    // idle peers are eventually forgotten
+    let other = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+    assert_eq!(
+        limiter.try_admit(other, 1000 + 3 * ATLAS_RATE_LIMIT_WINDOW_SECS),
+        Ok(())
+    );
+    assert!(limiter.get_usage(&peer).is_none());
+    assert!(limiter.get_usage(&other).is_some());
+}
+
+#[test]
+fn test_atlas_rate_limiter_unlimited() {
+    let peer = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let mut limiter = AtlasRateLimiter::new(0, 0);
+    for i in 0..1000 {
+        assert_eq!(limiter.try_admit(peer, 1000), Ok(()));
+        limiter.record_bytes_served(peer, i, 1000);
+    }
+    assert_eq!(limiter.get_usage(&peer).unwrap().total_requests, 1000);
+}
+
+#[test]
+fn test_getattachmentsinv_p2p_rate_limited() {
+    let peer_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let mut peer = TestPeer::new(TestPeerConfig::new(function_name!(), 0, 0));
+    let get_attachments_inv = GetAttachmentsInvData {
+        index_block_hash: StacksBlockId([0x11; 32]),
+        pages: vec![1],
+    };
+
+    // p2p inventory requests share the budget of the Atlas HTTP endpoints
+    let replies = peer
+        .with_network_state(|_sortdb, _chainstate, network, _relayer, _mempool| {
+            network.atlas_rate_limiter = AtlasRateLimiter::new(1, 0);
+            let replies: Result<Vec<_>, _> = (0..2)
+                .map(|_| {
+                    ConversationP2P::make_getattachmentsinv_response(
+                        network,
+                        peer_ip,
+                        &get_attachments_inv,
+                    )
+                })
+                .collect();
+            let usage = network.atlas_rate_limiter.get_usage(&peer_ip).unwrap();
+            assert_eq!(usage.total_requests, 1);
+            assert_eq!(usage.total_throttled, 1);
+            assert!(usage.total_bytes > 0);
+            replies
+        })
+        .unwrap();
+
+    match &replies[0] {
+        StacksMessageType::AttachmentsInv(inventory) => {
+            assert_eq!(inventory.pages.len(), 1);
+            assert_eq!(inventory.pages[0].index, 1);
+        }
+        x => panic!("Expected AttachmentsInv, got {:?}", x),
+    }
+    match &replies[1] {
+        StacksMessageType::Nack(nack) => {
+            assert_eq!(nack.error_code, NackErrorCodes::Throttled);
+        }
+        x => panic!("Expected a Throttled Nack, got {:?}", x),
+    }
+}
+
+#[test]
+fn test_atlas_audit_finds_and_repairs_problems() {
+    let mut atlas_db = AtlasDB::connect_memory(AtlasConfig::new(false)).unwrap();
+
+    let instance_for = |content: &str, attachment_index: u32| {
+        // hashes must contain hex letters, see `schema_2_migration`
+        let mut instance = new_attachment_instance_from(
+            &new_attachment_from(content),
+            attachment_index,
+            0xa0 + attachment_index as u64,
+        );
+        instance.tx_id = Txid([0xa0 + attachment_index as u8; 32]);
+        // not stored in the AtlasDB
+        instance.canonical_stacks_tip_height = None;
+        instance
+    };
+
+    // a healthy attachment
+    atlas_db
+        .insert_instantiated_attachment(&new_attachment_from("facade00"))
+        .unwrap();
+    atlas_db
+        .insert_initial_attachment_instance(&instance_for("facade00", 0))
+        .unwrap();
+
+    // an attachment whose stored content no longer matches its hash
+    let corrupt_hash = new_attachment_from("facade01").hash();
+    atlas_db
+        .conn
+        .execute(
+            "INSERT OR REPLACE INTO attachments (hash, content, was_instantiated, created_at) VALUES (?1, ?2, 1, 0)",
+            rusqlite::params![&corrupt_hash, &"baadf00d".as_bytes().to_vec()],
+        )
+        .unwrap();
+    atlas_db
+        .insert_initial_attachment_instance(&instance_for("facade01", 1))
+        .unwrap();
 
-    let uninstantiated_attachment_instances = [
-        new_attachment_instance_from(&new_attachment_from("facade15"), 4, 1),
-        new_attachment_instance_from(&new_attachment_from("facade16"), 5, 1),
-        new_attachment_instance_from(&new_attachment_from("facade17"), 6, 1),
-        new_attachment_instance_from(&new_attachment_from("facade18"), 7, 1),
-    ];
-    for attachment_instance in uninstantiated_attachment_instances.iter() {
-        atlas_db
-            .queue_attachment_instance(attachment_instance)
-            .unwrap();
-        atlas_db
-            .mark_attachment_instance_checked(attachment_instance, false)
-            .unwrap();
-    }
-    let bit_vector = atlas_db
-        .get_attachments_available_at_page_index(0, &block_id_1)
+    // an instance marked available, but whose attachment was never stored
+    atlas_db
+        .insert_initial_attachment_instance(&instance_for("facade02", 2))
         .unwrap();
-    assert_eq!(bit_vector, [0x00; 64]);
 
-    let instantiated_attachment_instances = [
-        new_attachment_instance_from(&new_attachment_from("facade21"), 8, 1),
-        new_attachment_instance_from(&new_attachment_from("facade22"), 9, 1),
-        new_attachment_instance_from(&new_attachment_from("facade23"), 10, 1),
-        new_attachment_instance_from(&new_attachment_from("facade24"), 11, 1),
-    ];
-    for attachment_instance in instantiated_attachment_instances.iter() {
-        atlas_db
-            .queue_attachment_instance(attachment_instance)
+    // an instance not marked available, even though its attachment is stored
+    atlas_db
+        .insert_instantiated_attachment(&new_attachment_from("facade03"))
+        .unwrap();
+    atlas_db
+        .queue_attachment_instance(&instance_for("facade03", 3))
+        .unwrap();
+    atlas_db
+        .mark_attachment_instance_checked(&instance_for("facade03", 3), false)
+        .unwrap();
+
+    // an instance still waiting for its attachment to be downloaded
+    atlas_db
+        .queue_attachment_instance(&instance_for("facade04", 4))
+        .unwrap();
+    atlas_db
+        .mark_attachment_instance_checked(&instance_for("facade04", 4), false)
+        .unwrap();
+
+    // no instances recorded for indexes 5 and 6
+    atlas_db
+        .insert_instantiated_attachment(&new_attachment_from("facade07"))
+        .unwrap();
+    atlas_db
+        .insert_initial_attachment_instance(&instance_for("facade07", 7))
+        .unwrap();
+
+    // auditing a row at a time gives the same report as auditing everything at once
+    let mut audit = AtlasAudit::new();
+    let mut steps = 0;
+    while !audit.step(&atlas_db, 1).unwrap() {
+        steps += 1;
+    }
+    assert!(steps > 2);
+    let report = audit.into_report();
+    assert_eq!(report, AtlasAudit::run(&atlas_db).unwrap());
+
+    assert!(!report.is_clean());
+    assert_eq!(report.attachments_checked, 4);
+    assert_eq!(report.instances_checked, 6);
+    assert_eq!(report.unresolved_instances, 1);
+    assert_eq!(report.corrupt_attachments, vec![corrupt_hash]);
+    assert_eq!(
+        report.missing_attachments,
+        vec![instance_for("facade01", 1), instance_for("facade02", 2)]
+    );
+    assert_eq!(report.stale_unavailable, vec![instance_for("facade03", 3)]);
+    assert_eq!(
+        report.missing_instance_indexes,
+        vec![(QualifiedContractIdentifier::transient(), 5, 6)]
+    );
+
+    let work_list = apply_repairs(&mut atlas_db, &report).unwrap();
+    assert_eq!(work_list, report.missing_attachments);
+    assert!(!atlas_db.has_instantiated_attachment(&corrupt_hash).unwrap());
+
+    // only the index gap is left, and the attachments to re-download are now unresolved
+    let report = AtlasAudit::run(&atlas_db).unwrap();
+    assert!(report.corrupt_attachments.is_empty());
+    assert!(report.missing_attachments.is_empty());
+    assert!(report.stale_unavailable.is_empty());
+    assert_eq!(report.unresolved_instances, 3);
+    assert_eq!(report.missing_instance_indexes.len(), 1);
+}
+
+#[test]
+fn test_downloader_replays_recorded_responses() {
+    let attachment_1 = new_attachment_from("facade01");
+    let attachment_2 = new_attachment_from("facade02");
+    let attachments_batch = new_attachments_batch_from(
+        vec![
+            new_attachment_instance_from(&attachment_1, 0, 1),
+            new_attachment_instance_from(&attachment_2, 1, 1),
+        ],
+        0,
+    );
+    let peers = new_peers(vec![
+        ("http://127.0.0.1:20443", 4, 4),
+        ("http://127.0.0.1:30443", 4, 1),
+    ]);
+    let peer_url_1 = UrlString::try_from("http://127.0.0.1:20443").unwrap();
+    let context =
+        AttachmentsBatchStateContext::new(attachments_batch, peers, &ConnectionOptions::default());
+
+    // Peer 1 is slow to answer its inventory request and has no copy of attachment 1, while
+    // peer 2 throttles us
+    let mut transport =
+        FixtureTransport::from_file(Path::new("./src/net/atlas/fixtures/flaky_peers.json"))
             .unwrap();
-        atlas_db
-            .mark_attachment_instance_checked(attachment_instance, true)
+    let started_at = get_epoch_time_secs();
+    let context = replay_batch(context, &mut transport, 100).expect("batch should finish");
+
+    assert_eq!(transport.unused_responses(), 0);
+    let requests_sent = transport.requests_sent();
+    assert_eq!(requests_sent.len(), 4);
+    assert!(requests_sent[..2]
+        .iter()
+        .all(|(_, path)| path.starts_with("/v2/attachments/inv?")));
+    // the attachments are only requested from the peer that sent its inventory
+    assert!(requests_sent[2..]
+        .iter()
+        .all(|(peer_url, path)| peer_url == &peer_url_1 && !path.contains("/inv?")));
+
+    assert_eq!(context.attachments.len(), 1);
+    assert!(context.attachments.contains(&attachment_2));
+    assert!(context
+        .not_found_cache
+        .contains(&peer_url_1, &attachment_1.hash()));
+    assert!(context.throttled_until >= started_at + 30);
+    // only the peer that did not have attachment 1 counts as faulty; being throttled does not
+    assert_eq!(context.events_to_deregister.len(), 1);
+
+    // replaying the same fixtures gives the same requests
+    let mut replayed =
+        FixtureTransport::from_file(Path::new("./src/net/atlas/fixtures/flaky_peers.json"))
             .unwrap();
-    }
+    let attachments_batch = new_attachments_batch_from(
+        vec![
+            new_attachment_instance_from(&attachment_1, 0, 1),
+            new_attachment_instance_from(&attachment_2, 1, 1),
+        ],
+        0,
+    );
+    let peers = new_peers(vec![
+        ("http://127.0.0.1:20443", 4, 4),
+        ("http://127.0.0.1:30443", 4, 1),
+    ]);
+    let context =
+        AttachmentsBatchStateContext::new(attachments_batch, peers, &ConnectionOptions::default());
+    replay_batch(context, &mut replayed, 100).expect("batch should finish");
+    // query arguments, and the attachments requested from the same peer, may be sent in any
+    // order
+    let request_keys = |transport: &FixtureTransport| -> Vec<RequestKey> {
+        let mut keys: Vec<_> = transport
+            .requests_sent()
+            .iter()
+            .map(|(peer_url, path)| request_key(peer_url, path))
+            .collect();
+        keys.sort();
+        keys
+    };
+    assert_eq!(request_keys(&replayed), request_keys(&transport));
+}
 
-    let bit_vector = atlas_db
-        .get_attachments_available_at_page_index(0, &block_id_1)
+#[test]
+fn test_attachments_fixtures_round_trip() {
+    let fixtures =
+        AttachmentsFixtures::load(Path::new("./src/net/atlas/fixtures/flaky_peers.json")).unwrap();
+    assert_eq!(fixtures.responses.len(), 4);
+    assert_eq!(fixtures.responses[0].polls, 2);
+    assert_eq!(
+        fixtures.responses[1].headers.get("retry-after"),
+        Some(&"30".to_string())
+    );
+    assert_eq!(fixtures.responses[2].body, None);
+
+    let path = std::env::temp_dir().join(format!(
+        "atlas-fixtures-round-trip-{}.json",
+        std::process::id()
+    ));
+    fixtures.save(&path).unwrap();
+    assert_eq!(AttachmentsFixtures::load(&path).unwrap(), fixtures);
+    std::fs::remove_file(&path).unwrap();
+}
+
+fn new_blob_store_root(name: &str) -> std::path::PathBuf {
+    let root = std::env::temp_dir().join(format!("atlas-blobs-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    root
+}
+
+#[test]
+fn test_filesystem_attachment_storage() {
+    let root = new_blob_store_root("filesystem-storage");
+    let mut atlas_config = AtlasConfig::new(false);
+    atlas_config.attachment_storage = AttachmentStorage::Filesystem;
+    let mut atlas_db = AtlasDB::connect_memory_with_blob_store(atlas_config, root.clone()).unwrap();
+    let blob_store = AttachmentBlobStore::new(root.clone());
+
+    let attachment = new_attachment_from("facade01");
+    atlas_db
+        .insert_instantiated_attachment(&attachment)
+        .unwrap();
+    let uninstantiated = new_attachment_from("facade02");
+    atlas_db
+        .insert_uninstantiated_attachment(&uninstantiated)
         .unwrap();
-    let mut expected = [0x00; 64];
-    expected[8] = 1;
-    expected[9] = 1;
-    expected[10] = 1;
-    expected[11] = 1;
-    assert_eq!(bit_vector, expected);
 
-    println!("1: {:?}", bit_vector);
+    // the content is in the blob store, not in the DB
+    let stored_content: Vec<u8> = atlas_db
+        .conn()
+        .query_row(
+            "SELECT content FROM attachments WHERE hash = ?1",
+            &[&attachment.hash()],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(stored_content.is_empty());
+    assert_eq!(
+        blob_store.read(&attachment.hash()).unwrap(),
+        Some(attachment.content.clone())
+    );
 
-    // Insert some instanciated attachments at block 2
-    let instantiated_attachment_instances = [
-        new_attachment_instance_from(&new_attachment_from("facade31"), 12, 2),
-        new_attachment_instance_from(&new_attachment_from("facade32"), 13, 2),
-        new_attachment_instance_from(&new_attachment_from("facade33"), 14, 2),
-        new_attachment_instance_from(&new_attachment_from("facade34"), 15, 2),
-    ];
-    let block_id_2 = instantiated_attachment_instances[0].index_block_hash;
-    for attachment_instance in instantiated_attachment_instances.iter() {
-        atlas_db
-            .queue_attachment_instance(attachment_instance)
-            .unwrap();
+    assert_eq!(
+        atlas_db.find_attachment(&attachment.hash()).unwrap(),
+        Some(attachment.clone())
+    );
+    assert_eq!(
         atlas_db
-            .mark_attachment_instance_checked(attachment_instance, true)
-            .unwrap();
+            .find_uninstantiated_attachment(&uninstantiated.hash())
+            .unwrap(),
+        Some(uninstantiated.clone())
+    );
+    assert!(AtlasAudit::run(&atlas_db)
+        .unwrap()
+        .corrupt_attachments
+        .is_empty());
+
+    // a corrupted blob reads as a missing attachment, and the audit flags it
+    let hex_hash = attachment.hash().to_hex();
+    std::fs::write(
+        root.join(&hex_hash[0..2]).join(&hex_hash),
+        "baadf00d".as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(atlas_db.find_attachment(&attachment.hash()).unwrap(), None);
+    let report = AtlasAudit::run(&atlas_db).unwrap();
+    assert_eq!(report.corrupt_attachments, vec![attachment.hash()]);
+
+    // repairing drops the attachment along with its blob
+    apply_repairs(&mut atlas_db, &report).unwrap();
+    assert!(!atlas_db
+        .has_instantiated_attachment(&attachment.hash())
+        .unwrap());
+    assert_eq!(blob_store.read(&attachment.hash()).unwrap(), None);
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_migrate_attachment_storage() {
+    let root = new_blob_store_root("migrate-storage");
+    let blob_store = AttachmentBlobStore::new(root.clone());
+    let mut atlas_config = AtlasConfig::new(false);
+    atlas_config.attachment_storage = AttachmentStorage::Sqlite;
+
+    let attachments: Vec<Attachment> = (0..5)
+        .map(|i| new_attachment_from(&format!("facade{:02}", i)))
+        .collect();
+    let mut atlas_db =
+        AtlasDB::connect_memory_with_blob_store(atlas_config.clone(), root.clone()).unwrap();
+    for attachment in attachments.iter() {
+        atlas_db.insert_instantiated_attachment(attachment).unwrap();
+        assert_eq!(blob_store.read(&attachment.hash()).unwrap(), None);
     }
 
-    let bit_vector = atlas_db
-        .get_attachments_available_at_page_index(0, &block_id_1)
+    // switching to filesystem storage moves every attachment into the blob store on connect
+    atlas_config.attachment_storage = AttachmentStorage::Filesystem;
+    let mut atlas_db = AtlasDB::connect_with_sqlconn_and_blob_store(
+        atlas_config.clone(),
+        atlas_db.conn,
+        root.clone(),
+    )
+    .unwrap();
+    for attachment in attachments.iter() {
+        assert_eq!(
+            blob_store.read(&attachment.hash()).unwrap(),
+            Some(attachment.content.clone())
+        );
+        assert_eq!(
+            atlas_db.find_attachment(&attachment.hash()).unwrap(),
+            Some(attachment.clone())
+        );
+    }
+    // nothing left to move
+    assert_eq!(atlas_db.migrate_attachment_storage().unwrap(), 0);
+
+    // once every attachment has been moved, re-opening the DB with the same storage does not
+    // look for attachments to move again
+    let stray = new_attachment_from("facade10");
+    atlas_db
+        .conn()
+        .execute(
+            "INSERT INTO attachments (hash, content, was_instantiated, created_at, storage) VALUES (?1, ?2, 1, 0, ?3)",
+            rusqlite::params![&stray.hash(), &stray.content, &AttachmentStorage::Sqlite],
+        )
         .unwrap();
-    assert_eq!(bit_vector, expected);
+    let mut atlas_db = AtlasDB::connect_with_sqlconn_and_blob_store(
+        atlas_config.clone(),
+        atlas_db.conn,
+        root.clone(),
+    )
+    .unwrap();
+    assert_eq!(blob_store.read(&stray.hash()).unwrap(), None);
+    atlas_db.delete_attachment(&stray.hash()).unwrap();
+
+    // a blob lost in between is kept, but marked as missing, when moving back
+    let lost = attachments[0].hash();
+    blob_store.delete(&lost).unwrap();
+
+    atlas_config.attachment_storage = AttachmentStorage::Sqlite;
+    let mut atlas_db = AtlasDB::connect_with_sqlconn_and_blob_store(
+        atlas_config.clone(),
+        atlas_db.conn,
+        root.clone(),
+    )
+    .unwrap();
+    assert!(!atlas_db.has_instantiated_attachment(&lost).unwrap());
+    assert_eq!(atlas_db.find_attachment(&lost).unwrap(), None);
+    let content_missing: bool = atlas_db
+        .conn()
+        .query_row(
+            "SELECT content_missing FROM attachments WHERE hash = ?1",
+            &[&lost],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(content_missing);
+    for attachment in attachments[1..].iter() {
+        assert_eq!(blob_store.read(&attachment.hash()).unwrap(), None);
+        assert_eq!(
+            atlas_db.find_attachment(&attachment.hash()).unwrap(),
+            Some(attachment.clone())
+        );
+    }
 
-    let bit_vector = atlas_db
-        .get_attachments_available_at_page_index(0, &block_id_2)
+    // downloading it again restores it
+    atlas_db
+        .insert_instantiated_attachment(&attachments[0])
         .unwrap();
-    let mut expected = [0x00; 64];
-    expected[12] = 1;
-    expected[13] = 1;
-    expected[14] = 1;
-    expected[15] = 1;
-    assert_eq!(bit_vector, expected);
+    assert_eq!(
+        atlas_db.find_attachment(&lost).unwrap(),
+        Some(attachments[0].clone())
+    );
+
+    let _ = std::fs::remove_dir_all(&root);
 }
 
 #[test]
-fn test_attachments_inventory_requests_hashing() {
-    let mut requests = HashMap::new();
+fn test_missing_uninstantiated_attachment_is_not_found() {
+    let root = new_blob_store_root("missing-uninstantiated");
+    let blob_store = AttachmentBlobStore::new(root.clone());
+    let mut atlas_config = AtlasConfig::new(false);
+    atlas_config.attachment_storage = AttachmentStorage::Filesystem;
+
+    let attachment = new_attachment_from("facade20");
+    let mut atlas_db =
+        AtlasDB::connect_memory_with_blob_store(atlas_config.clone(), root.clone()).unwrap();
+    atlas_db
+        .insert_uninstantiated_attachment(&attachment)
+        .unwrap();
 
-    let attachments_inventory_1_request =
-        new_attachments_inventory_request("http://localhost:20443", vec![0, 1], 1, 0, 0);
-    requests.insert(attachments_inventory_1_request.key(), 1);
+    // the blob is lost before moving back to the DB, so the attachment is marked as missing
+    blob_store.delete(&attachment.hash()).unwrap();
+    atlas_config.attachment_storage = AttachmentStorage::Sqlite;
+    let mut atlas_db = AtlasDB::connect_with_sqlconn_and_blob_store(
+        atlas_config.clone(),
+        atlas_db.conn,
+        root.clone(),
+    )
+    .unwrap();
 
-    let attachments_inventory_2_request =
-        new_attachments_inventory_request("http://localhost:30443", vec![0, 1], 1, 2, 1);
-    requests.insert(attachments_inventory_2_request.key(), 2);
+    // it stays missing, like an instantiated one would, even if its blob reappears
+    blob_store.put(&attachment).unwrap();
+    assert_eq!(
+        atlas_db
+            .find_uninstantiated_attachment(&attachment.hash())
+            .unwrap(),
+        None
+    );
 
-    let attachments_inventory_3_request =
-        new_attachments_inventory_request("http://localhost:40443", vec![0, 1], 1, 2, 2);
-    requests.insert(attachments_inventory_3_request.key(), 3);
+    std::fs::remove_dir_all(&root).unwrap();
+}
 
-    let attachments_inventory_4_request =
-        new_attachments_inventory_request("http://localhost:50443", vec![0, 1], 1, 4, 4);
-    requests.insert(attachments_inventory_4_request.key(), 4);
+#[test]
+fn test_reliability_report_speed() {
+    let mut report = ReliabilityReport::empty();
+    report.record_response(200, 1000);
+    assert_eq!(report.total_requests_sent, 1);
+    assert_eq!(report.total_requests_success, 1);
+    assert_eq!(report.avg_latency_ms, Some(200));
+    assert_eq!(report.avg_throughput_bps, Some(5000));
+
+    // later samples move the averages a quarter of the way
+    report.record_response(600, 1000);
+    assert_eq!(report.avg_latency_ms, Some(300));
+    assert_eq!(report.avg_throughput_bps, Some(4166));
+
+    // failures do not count towards the averages
+    report.bump_failed_requests();
+    assert_eq!(report.total_requests_sent, 3);
+    assert_eq!(report.avg_latency_ms, Some(300));
+}
 
-    println!("{:?}", requests);
+#[test]
+fn test_reliability_report_prefers_fast_peers() {
+    let slow = ReliabilityReport::new(10, 10).with_speed(2000, 1000);
+    let fast = ReliabilityReport::new(4, 4).with_speed(100, 1000);
+    let fast_but_flaky = ReliabilityReport::new(4, 2).with_speed(50, 1000);
+    let unmeasured = ReliabilityReport::new(10, 10);
+    let high_throughput = ReliabilityReport::new(4, 4).with_speed(120, 5000);
+
+    // among peers that answer as often, the faster ones come first
+    assert!(fast > slow);
+    assert!(slow > unmeasured);
+    assert!(high_throughput > fast);
+    // but answering is what matters most
+    assert!(slow > fast_but_flaky);
+
+    // the fastest source of an attachment is the one it is requested from
+    let attachment = new_attachment_from("facade01");
+    let mut request = new_attachment_request(
+        vec![
+            ("http://localhost:20443", 10, 10),
+            ("http://localhost:30443", 4, 4),
+        ],
+        &attachment.hash(),
+        1,
+    );
+    let slow_url = UrlString::try_from("http://localhost:20443").unwrap();
+    let fast_url = UrlString::try_from("http://localhost:30443").unwrap();
+    assert_eq!(request.get_url(), &slow_url);
+    request.sources.insert(slow_url.clone(), slow.clone());
+    request.sources.insert(fast_url.clone(), fast.clone());
+    assert_eq!(request.get_url(), &fast_url);
+
+    // and, all else being equal, the attachments whose best source is fastest come first
+    let other_attachment = new_attachment_from("facade02");
+    let mut slow_request = new_attachment_request(
+        vec![("http://localhost:20443", 10, 10)],
+        &other_attachment.hash(),
+        1,
+    );
+    slow_request.sources.insert(slow_url.clone(), slow);
+    let mut fast_request = new_attachment_request(
+        vec![("http://localhost:30443", 4, 4)],
+        &attachment.hash(),
+        1,
+    );
+    fast_request.sources.insert(fast_url, fast);
+    let mut priority_queue = BinaryHeap::new();
+    priority_queue.push(slow_request.clone());
+    priority_queue.push(fast_request.clone());
+    assert_eq!(priority_queue.pop().unwrap(), fast_request);
+    assert_eq!(priority_queue.pop().unwrap(), slow_request);
+}
+
+#[test]
+fn test_replayed_batch_measures_peer_speed() {
+    let attachment_1 = new_attachment_from("facade01");
+    let attachment_2 = new_attachment_from("facade02");
+    let attachments_batch = new_attachments_batch_from(
+        vec![
+            new_attachment_instance_from(&attachment_1, 0, 1),
+            new_attachment_instance_from(&attachment_2, 1, 1),
+        ],
+        0,
+    );
+    let peers = new_peers(vec![
+        ("http://127.0.0.1:20443", 4, 4),
+        ("http://127.0.0.1:30443", 4, 1),
+    ]);
+    let peer_url_1 = UrlString::try_from("http://127.0.0.1:20443").unwrap();
+    let peer_url_2 = UrlString::try_from("http://127.0.0.1:30443").unwrap();
+    let context =
+        AttachmentsBatchStateContext::new(attachments_batch, peers, &ConnectionOptions::default());
+
+    let mut transport =
+        FixtureTransport::from_file(Path::new("./src/net/atlas/fixtures/flaky_peers.json"))
+            .unwrap();
+    let context = replay_batch(context, &mut transport, 100).expect("batch should finish");
+
+    // peer 1 answered, after being polled a few times
+    let report_1 = context.peers.get(&peer_url_1).unwrap();
+    assert!(report_1.avg_latency_ms.unwrap() >= FIXTURE_POLL_INTERVAL_MS);
+    assert!(report_1.avg_throughput_bps.unwrap() > 0);
+    // peer 2 only throttled us
+    let report_2 = context.peers.get(&peer_url_2).unwrap();
+    assert_eq!(report_2.avg_latency_ms, None);
+    assert_eq!(report_2.avg_throughput_bps, None);
+}
+
+#[test]
+fn test_reliability_reports_persisted() {
+    let mut atlas_db = AtlasDB::connect_memory(AtlasConfig::new(false)).unwrap();
+    assert!(atlas_db.get_reliability_reports().unwrap().is_empty());
+
+    let peer_url_1 = UrlString::try_from("http://localhost:20443").unwrap();
+    let peer_url_2 = UrlString::try_from("http://localhost:30443").unwrap();
+    let mut reports = HashMap::new();
+    reports.insert(
+        peer_url_1.clone(),
+        ReliabilityReport::new(4, 3).with_speed(150, 2000),
+    );
+    reports.insert(peer_url_2.clone(), ReliabilityReport::new(2, 0));
+    atlas_db.store_reliability_reports(reports.iter()).unwrap();
+    assert_eq!(atlas_db.get_reliability_reports().unwrap(), reports);
+
+    // newer reports replace the stored ones
+    let newer_report = ReliabilityReport::new(5, 4).with_speed(140, 2100);
+    atlas_db
+        .store_reliability_reports(vec![(&peer_url_1, &newer_report)])
+        .unwrap();
+    let stored = atlas_db.get_reliability_reports().unwrap();
+    assert_eq!(stored.len(), 2);
+    assert_eq!(stored.get(&peer_url_1), Some(&newer_report));
+
+    // a restarted downloader starts from the stored reports
+    let mut downloader = AttachmentsDownloader::new(vec![]);
+    assert_eq!(downloader.get_reliability_report(&peer_url_1), None);
+    downloader.load_reliability_reports(&atlas_db).unwrap();
+    assert_eq!(
+        downloader.get_reliability_report(&peer_url_1),
+        Some(&newer_report)
+    );
+    assert_eq!(
+        downloader.get_reliability_report(&peer_url_2),
+        Some(&ReliabilityReport::new(2, 0))
+    );
+}
+
+#[test]
+fn test_replayed_batch_tracks_address_families() {
+    let attachment_1 = new_attachment_from("facade01");
+    let attachment_2 = new_attachment_from("facade02");
+    let new_context = |connection_options: &ConnectionOptions| {
+        let attachments_batch = new_attachments_batch_from(
+            vec![
+                new_attachment_instance_from(&attachment_1, 0, 1),
+                new_attachment_instance_from(&attachment_2, 1, 1),
+            ],
+            0,
+        );
+        let peers = new_peers(vec![
+            ("http://127.0.0.1:20443", 4, 4),
+            ("http://127.0.0.1:30443", 4, 1),
+        ]);
+        AttachmentsBatchStateContext::new(attachments_batch, peers, connection_options)
+    };
+    let peer_url_1 = UrlString::try_from("http://127.0.0.1:20443").unwrap();
+    let peer_url_2 = UrlString::try_from("http://127.0.0.1:30443").unwrap();
+
+    let mut transport =
+        FixtureTransport::from_file(Path::new("./src/net/atlas/fixtures/flaky_peers.json"))
+            .unwrap();
+    let context = replay_batch(
+        new_context(&ConnectionOptions::default()),
+        &mut transport,
+        100,
+    )
+    .expect("batch should finish");
+    // both peers could be reached over IPv4, even though peer 2 throttled us
+    assert_eq!(
+        context
+            .family_reports
+            .get(&(peer_url_1.clone(), AddressFamily::Ipv4)),
+        Some(&ReliabilityReport::new(3, 3))
+    );
+    assert_eq!(
+        context
+            .family_reports
+            .get(&(peer_url_2.clone(), AddressFamily::Ipv4)),
+        Some(&ReliabilityReport::new(1, 1))
+    );
+    assert!(context
+        .family_reports
+        .keys()
+        .all(|(_, family)| *family == AddressFamily::Ipv4));
+
+    // an IPv6-only node can't reach IPv4 peers, and doesn't try to
+    let mut connection_options = ConnectionOptions::default();
+    connection_options.attachment_address_family = AddressFamilyPreference::Ipv6Only;
+    let mut transport =
+        FixtureTransport::from_file(Path::new("./src/net/atlas/fixtures/flaky_peers.json"))
+            .unwrap();
+    let context = replay_batch(new_context(&connection_options), &mut transport, 100)
+        .expect("batch should finish");
+    assert!(transport.requests_sent().is_empty());
+    assert_eq!(context.dns_lookups.get(&peer_url_1), Some(&None));
+    assert_eq!(context.dns_lookups.get(&peer_url_2), Some(&None));
+    assert!(context.attachments.is_empty());
+}
+
+#[test]
+fn test_downloader_context_orders_address_families() {
+    let v4: SocketAddr = "127.0.0.1:20443".parse().unwrap();
+    let v6: SocketAddr = "[::1]:20443".parse().unwrap();
+    let dual_stack_url = UrlString::try_from("http://localhost:20443").unwrap();
+    let other_url = UrlString::try_from("http://localhost:30443").unwrap();
+
+    let mut addrs = vec![v6, v4];
+    demote_address_family(&mut addrs, AddressFamily::Ipv6);
+    assert_eq!(addrs, vec![v4, v6]);
+    demote_address_family(&mut addrs, AddressFamily::Ipv6);
+    assert_eq!(addrs, vec![v4, v6]);
+
+    let new_context = |family_reports| {
+        let peers = new_peers(vec![
+            ("http://localhost:20443", 0, 0),
+            ("http://localhost:30443", 0, 0),
+        ]);
+        let mut results = BatchedDNSLookupsResults::default();
+        results
+            .dns_lookups
+            .insert(dual_stack_url.clone(), Some(vec![v6, v4]));
+        results
+            .dns_lookups
+            .insert(other_url.clone(), Some(vec![v6, v4]));
+        AttachmentsBatchStateContext::new(
+            new_attachments_batch_from(vec![], 0),
+            peers,
+            &ConnectionOptions::default(),
+        )
+        .with_family_reports(family_reports)
+        .extend_with_dns_lookups(&mut results)
+    };
+
+    // IPv6 keeps failing to connect to the dual-stack peer, but IPv4 works
+    let mut family_reports = HashMap::new();
+    family_reports.insert(
+        (dual_stack_url.clone(), AddressFamily::Ipv6),
+        ReliabilityReport::new(4, 0),
+    );
+    family_reports.insert(
+        (dual_stack_url.clone(), AddressFamily::Ipv4),
+        ReliabilityReport::new(3, 3),
+    );
+    let context = new_context(family_reports);
+    assert_eq!(
+        context.dns_lookups.get(&dual_stack_url),
+        Some(&Some(vec![v4, v6]))
+    );
+    // peers that have not been tried over both families keep the order they were given in
+    assert_eq!(
+        context.dns_lookups.get(&other_url),
+        Some(&Some(vec![v6, v4]))
+    );
+
+    // a family that has worked as well as the other one is not passed over
+    let mut family_reports = HashMap::new();
+    family_reports.insert(
+        (dual_stack_url.clone(), AddressFamily::Ipv6),
+        ReliabilityReport::new(2, 2),
+    );
+    family_reports.insert(
+        (dual_stack_url.clone(), AddressFamily::Ipv4),
+        ReliabilityReport::new(3, 3),
+    );
+    let context = new_context(family_reports);
+    assert_eq!(
+        context.dns_lookups.get(&dual_stack_url),
+        Some(&Some(vec![v6, v4]))
+    );
 }
@@ -16,12 +16,11 @@
 
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::{Read, Write};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::{cmp, mem};
 
 use clarity::vm::types::QualifiedContractIdentifier;
-use rand;
-use rand::{thread_rng, Rng};
+use rand::{self, thread_rng, Rng};
 use stacks_common::types::chainstate::PoxId;
 use stacks_common::types::net::PeerAddress;
 use stacks_common::types::StacksPublicKeyBuffer;
@@ -38,6 +37,7 @@ use crate::chainstate::stacks::StacksPublicKey;
 use crate::core::{StacksEpoch, PEER_VERSION_EPOCH_2_2, PEER_VERSION_EPOCH_2_3};
 use crate::monitoring;
 use crate::net::asn::ASEntry4;
+use crate::net::atlas::{AttachmentInstance, AttachmentPage};
 use crate::net::codec::*;
 use crate::net::connection::{ConnectionOptions, ConnectionP2P, ReplyHandleP2P};
 use crate::net::db::{PeerDB, *};
@@ -134,7 +134,7 @@ pub struct NeighborStats {
 impl NeighborStats {
     pub fn new(outbound: bool) -> NeighborStats {
         NeighborStats {
-            outbound: outbound,
+            outbound,
             first_contact_time: 0,
             last_contact_time: 0,
             last_send_time: 0,
@@ -161,7 +161,7 @@ impl NeighborStats {
     /// message, or failed to do so (indicated by `success`).
     pub fn add_healthpoint(&mut self, success: bool) -> () {
         let hp = NeighborHealthPoint {
-            success: success,
+            success,
             time: get_epoch_time_secs(),
         };
         self.healthpoints.push_back(hp);
@@ -222,7 +222,7 @@ impl NeighborStats {
         } else {
             let info = RelayStats {
                 num_messages: 1,
-                num_bytes: num_bytes,
+                num_bytes,
                 last_seen: get_epoch_time_secs(),
             };
             self.relayed_messages.insert(addr.clone(), info);
@@ -405,8 +405,8 @@ impl NeighborKey {
         handshake_data: &HandshakeData,
     ) -> NeighborKey {
         NeighborKey {
-            peer_version: peer_version,
-            network_id: network_id,
+            peer_version,
+            network_id,
             addrbytes: handshake_data.addrbytes.clone(),
             port: handshake_data.port,
         }
@@ -414,8 +414,8 @@ impl NeighborKey {
 
     pub fn from_socketaddr(peer_version: u32, network_id: u32, addr: &SocketAddr) -> NeighborKey {
         NeighborKey {
-            peer_version: peer_version,
-            network_id: network_id,
+            peer_version,
+            network_id,
             addrbytes: PeerAddress::from_socketaddr(addr),
             port: addr.port(),
         }
@@ -551,10 +551,10 @@ impl ConversationP2P {
     ) -> ConversationP2P {
         ConversationP2P {
             instantiated: get_epoch_time_secs(),
-            network_id: network_id,
-            version: version,
+            network_id,
+            version,
             connection: ConnectionP2P::new(StacksP2P::new(), conn_opts, None),
-            conn_id: conn_id,
+            conn_id,
             heartbeat: conn_opts.heartbeat,
             burnchain: burnchain.clone(),
 
@@ -583,7 +583,7 @@ impl ConversationP2P {
 
             db_smart_contracts: vec![],
 
-            epochs: epochs,
+            epochs,
         }
     }
 
@@ -687,6 +687,12 @@ impl ConversationP2P {
         (peer_services & (ServiceFlags::STACKERDB as u16)) != 0
     }
 
+    /// Does the given services bitfield support exchanging attachment inventories over p2p
+    /// (GetAttachmentsInv)?  It will if it has the ATTACHMENTS_INV bit set
+    pub fn supports_attachments_inv(peer_services: u16) -> bool {
+        (peer_services & (ServiceFlags::ATTACHMENTS_INV as u16)) != 0
+    }
+
     /// Does this remote neighbor support a particular StackerDB?
     pub fn replicates_stackerdb(&self, db: &QualifiedContractIdentifier) -> bool {
         for cid in self.db_smart_contracts.iter() {
@@ -1183,7 +1189,7 @@ impl ConversationP2P {
         let natpunch_data = NatPunchData {
             addrbytes: self.peer_addrbytes.clone(),
             port: self.peer_port,
-            nonce: nonce,
+            nonce,
         };
         let msg = StacksMessage::from_chain_view(
             self.version,
@@ -1784,6 +1790,118 @@ impl ConversationP2P {
         )
     }
 
+    /// Create a response to an inbound GetAttachmentsInv request from `peer_ip`, but unsigned.
+    /// Like `GET /v2/attachments/inv`, the request counts against the peer's Atlas budget (see
+    /// `AtlasRateLimiter`), and so does the response's size.
+    /// Returns the requested pages of our attachment inventory, or a Nack if the peer is over its
+    /// budget, a page index is out of range, or the Atlas DB can't be read.
+    pub fn make_getattachmentsinv_response(
+        network: &mut PeerNetwork,
+        peer_ip: IpAddr,
+        get_attachments_inv: &GetAttachmentsInvData,
+    ) -> Result<StacksMessageType, net_error> {
+        let now = get_epoch_time_secs();
+        if let Err(retry_after) = network.atlas_rate_limiter.try_admit(peer_ip, now) {
+            debug!(
+                "{:?}: Throttling GetAttachmentsInv from {} for {} seconds",
+                network.get_local_peer(),
+                peer_ip,
+                retry_after
+            );
+            return Ok(StacksMessageType::Nack(NackData::new(
+                NackErrorCodes::Throttled,
+            )));
+        }
+        let response = ConversationP2P::make_attachments_inv(network, get_attachments_inv)?;
+        network.atlas_rate_limiter.record_bytes_served(
+            peer_ip,
+            response.serialize_to_vec().len() as u64,
+            now,
+        );
+        Ok(response)
+    }
+
+    /// Read the pages of our attachment inventory requested by `get_attachments_inv`.
+    /// Returns them as an AttachmentsInv, or a Nack if a page index is out of range or the Atlas
+    /// DB can't be read.
+    fn make_attachments_inv(
+        network: &PeerNetwork,
+        get_attachments_inv: &GetAttachmentsInvData,
+    ) -> Result<StacksMessageType, net_error> {
+        let mut pages = vec![];
+        for page_index in get_attachments_inv.pages.iter() {
+            if page_index
+                .checked_add(1)
+                .and_then(|end| end.checked_mul(AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE))
+                .is_none()
+            {
+                debug!(
+                    "{:?}: Attachment inventory page {} is out of range",
+                    network.get_local_peer(),
+                    page_index
+                );
+                return Ok(StacksMessageType::Nack(NackData::new(
+                    NackErrorCodes::InvalidMessage,
+                )));
+            }
+            match network
+                .get_atlasdb()
+                .get_attachments_available_at_page_index(
+                    *page_index,
+                    &get_attachments_inv.index_block_hash,
+                ) {
+                Ok(inventory) => pages.push(AttachmentPage {
+                    index: *page_index,
+                    inventory,
+                }),
+                Err(e) => {
+                    warn!(
+                        "{:?}: Unable to read Atlas DB: {:?}",
+                        network.get_local_peer(),
+                        &e
+                    );
+                    return Ok(StacksMessageType::Nack(NackData::new(
+                        NackErrorCodes::NoSuchDB,
+                    )));
+                }
+            }
+        }
+        Ok(StacksMessageType::AttachmentsInv(AttachmentsInvData {
+            index_block_hash: get_attachments_inv.index_block_hash,
+            pages,
+        }))
+    }
+
+    /// Handle an inbound GetAttachmentsInv request.
+    /// Returns a reply handle to the generated message (possibly a nack)
+    fn handle_getattachmentsinv(
+        &mut self,
+        network: &mut PeerNetwork,
+        preamble: &Preamble,
+        get_attachments_inv: &GetAttachmentsInvData,
+    ) -> Result<ReplyHandleP2P, net_error> {
+        monitoring::increment_msg_counter("p2p_get_attachments_inv".to_string());
+
+        let peer_ip = self.peer_addrbytes.to_socketaddr(self.peer_port).ip();
+        let response = ConversationP2P::make_getattachmentsinv_response(
+            network,
+            peer_ip,
+            get_attachments_inv,
+        )?;
+        debug!(
+            "{:?}: Handled GetAttachmentsInv. Reply {:?} to request {:?}",
+            network.get_local_peer(),
+            &response,
+            get_attachments_inv
+        );
+        self.sign_and_reply(
+            network.get_local_peer(),
+            network.get_chain_view(),
+            preamble,
+            response,
+        )
+    }
+
     /// Create a response an inbound GetPoxInv request, but unsigned.
     /// Returns a reply handle to the generated message (possibly a nack)
     pub fn make_getpoxinv_response(
@@ -2241,6 +2359,9 @@ impl ConversationP2P {
                 &msg.preamble,
                 get_nakamoto_inv,
             ),
+            StacksMessageType::GetAttachmentsInv(ref get_attachments_inv) => {
+                self.handle_getattachmentsinv(network, &msg.preamble, get_attachments_inv)
+            }
             StacksMessageType::Blocks(_) => {
                 monitoring::increment_stx_blocks_received_counter();
 
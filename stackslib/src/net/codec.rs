@@ -45,6 +45,7 @@ use crate::chainstate::stacks::{
     StacksBlock, StacksMicroblock, StacksPublicKey, StacksTransaction, MAX_BLOCK_LEN,
 };
 use crate::core::PEER_VERSION_TESTNET;
+use crate::net::atlas::{AttachmentInstance, AttachmentPage, MAX_ATTACHMENT_INV_PAGES_PER_REQUEST};
 use crate::net::db::LocalPeer;
 use crate::net::{Error as net_error, *};
 
@@ -335,6 +336,68 @@ impl StacksMessageCodec for NakamotoInvData {
     }
 }
 
+impl StacksMessageCodec for GetAttachmentsInvData {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.index_block_hash)?;
+        write_next(fd, &self.pages)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<Self, codec_error> {
+        let index_block_hash: StacksBlockId = read_next(fd)?;
+        let pages: Vec<u32> = read_next_at_most::<_, u32>(
+            fd,
+            u32::try_from(MAX_ATTACHMENT_INV_PAGES_PER_REQUEST)
+                .expect("infallible: MAX_ATTACHMENT_INV_PAGES_PER_REQUEST fits in a u32"),
+        )?;
+        if pages.is_empty() {
+            return Err(codec_error::DeserializeError(
+                "Failed to parse GetAttachmentsInv: no pages requested".to_string(),
+            ));
+        }
+        Ok(Self {
+            index_block_hash,
+            pages,
+        })
+    }
+}
+
+impl StacksMessageCodec for AttachmentPage {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.index)?;
+        write_next(fd, &self.inventory)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<Self, codec_error> {
+        let index: u32 = read_next(fd)?;
+        let inventory: Vec<u8> =
+            read_next_at_most::<_, u8>(fd, AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE)?;
+        Ok(Self { index, inventory })
+    }
+}
+
+impl StacksMessageCodec for AttachmentsInvData {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.index_block_hash)?;
+        write_next(fd, &self.pages)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<Self, codec_error> {
+        let index_block_hash: StacksBlockId = read_next(fd)?;
+        let pages: Vec<AttachmentPage> = read_next_at_most::<_, AttachmentPage>(
+            fd,
+            u32::try_from(MAX_ATTACHMENT_INV_PAGES_PER_REQUEST)
+                .expect("infallible: MAX_ATTACHMENT_INV_PAGES_PER_REQUEST fits in a u32"),
+        )?;
+        Ok(Self {
+            index_block_hash,
+            pages,
+        })
+    }
+}
+
 impl NakamotoInvData {
     pub fn try_from(bits: &[bool]) -> Result<Self, codec_error> {
         Ok(Self {
@@ -930,6 +993,8 @@ impl StacksMessageType {
             StacksMessageType::StackerDBPushChunk(ref _m) => StacksMessageID::StackerDBPushChunk,
             StacksMessageType::GetNakamotoInv(ref _m) => StacksMessageID::GetNakamotoInv,
             StacksMessageType::NakamotoInv(ref _m) => StacksMessageID::NakamotoInv,
+            StacksMessageType::GetAttachmentsInv(ref _m) => StacksMessageID::GetAttachmentsInv,
+            StacksMessageType::AttachmentsInv(ref _m) => StacksMessageID::AttachmentsInv,
         }
     }
 
@@ -964,6 +1029,8 @@ impl StacksMessageType {
             StacksMessageType::StackerDBPushChunk(ref _m) => "StackerDBPushChunk",
             StacksMessageType::GetNakamotoInv(ref _m) => "GetNakamotoInv",
             StacksMessageType::NakamotoInv(ref _m) => "NakamotoInv",
+            StacksMessageType::GetAttachmentsInv(ref _m) => "GetAttachmentsInv",
+            StacksMessageType::AttachmentsInv(ref _m) => "AttachmentsInv",
         }
     }
 
@@ -1071,6 +1138,12 @@ impl StacksMessageType {
             StacksMessageType::NakamotoInv(ref m) => {
                 format!("NakamotoInv({:?})", &m.tenures)
             }
+            StacksMessageType::GetAttachmentsInv(ref m) => {
+                format!("GetAttachmentsInv({},{:?})", &m.index_block_hash, &m.pages)
+            }
+            StacksMessageType::AttachmentsInv(ref m) => {
+                format!("AttachmentsInv({},{:?})", &m.index_block_hash, &m.pages)
+            }
         }
     }
 }
@@ -1122,6 +1195,10 @@ impl StacksMessageCodec for StacksMessageID {
             }
             x if x == StacksMessageID::GetNakamotoInv as u8 => StacksMessageID::GetNakamotoInv,
             x if x == StacksMessageID::NakamotoInv as u8 => StacksMessageID::NakamotoInv,
+            x if x == StacksMessageID::GetAttachmentsInv as u8 => {
+                StacksMessageID::GetAttachmentsInv
+            }
+            x if x == StacksMessageID::AttachmentsInv as u8 => StacksMessageID::AttachmentsInv,
             _ => {
                 return Err(codec_error::DeserializeError(
                     "Unknown message ID".to_string(),
@@ -1166,6 +1243,8 @@ impl StacksMessageCodec for StacksMessageType {
             StacksMessageType::StackerDBPushChunk(ref m) => write_next(fd, m)?,
             StacksMessageType::GetNakamotoInv(ref m) => write_next(fd, m)?,
             StacksMessageType::NakamotoInv(ref m) => write_next(fd, m)?,
+            StacksMessageType::GetAttachmentsInv(ref m) => write_next(fd, m)?,
+            StacksMessageType::AttachmentsInv(ref m) => write_next(fd, m)?,
         }
         Ok(())
     }
@@ -1276,6 +1355,14 @@ impl StacksMessageCodec for StacksMessageType {
                 let m: NakamotoInvData = read_next(fd)?;
                 StacksMessageType::NakamotoInv(m)
             }
+            StacksMessageID::GetAttachmentsInv => {
+                let m: GetAttachmentsInvData = read_next(fd)?;
+                StacksMessageType::GetAttachmentsInv(m)
+            }
+            StacksMessageID::AttachmentsInv => {
+                let m: AttachmentsInvData = read_next(fd)?;
+                StacksMessageType::AttachmentsInv(m)
+            }
             StacksMessageID::Reserved => {
                 return Err(codec_error::DeserializeError(
                     "Unsupported message ID 'reserved'".to_string(),
@@ -2364,6 +2451,79 @@ pub mod test {
         check_codec_and_corruption::<StackerDBPushChunkData>(&push_data, &bytes);
     }
 
+    #[test]
+    fn codec_GetAttachmentsInv() {
+        let get_attachments_inv = GetAttachmentsInvData {
+            index_block_hash: StacksBlockId([0x11; 32]),
+            pages: vec![1, 2],
+        };
+
+        let get_attachments_inv_bytes: Vec<u8> = vec![
+            // index block hash
+            0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+            0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+            0x11, 0x11, 0x11, 0x11, // pages length
+            0x00, 0x00, 0x00, 0x02, // pages
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+        ];
+
+        check_codec_and_corruption::<GetAttachmentsInvData>(
+            &get_attachments_inv,
+            &get_attachments_inv_bytes,
+        );
+
+        // too many pages
+        let too_many_pages = GetAttachmentsInvData {
+            index_block_hash: StacksBlockId([0x11; 32]),
+            pages: (0..(MAX_ATTACHMENT_INV_PAGES_PER_REQUEST as u32 + 1)).collect(),
+        };
+        let bytes = too_many_pages.serialize_to_vec();
+        GetAttachmentsInvData::consensus_deserialize(&mut &bytes[..]).unwrap_err();
+
+        // no pages
+        let no_pages = GetAttachmentsInvData {
+            index_block_hash: StacksBlockId([0x11; 32]),
+            pages: vec![],
+        };
+        let bytes = no_pages.serialize_to_vec();
+        GetAttachmentsInvData::consensus_deserialize(&mut &bytes[..]).unwrap_err();
+    }
+
+    #[test]
+    fn codec_AttachmentsInv() {
+        let attachments_inv = AttachmentsInvData {
+            index_block_hash: StacksBlockId([0x22; 32]),
+            pages: vec![AttachmentPage {
+                index: 3,
+                inventory: vec![1, 0, 1],
+            }],
+        };
+
+        let attachments_inv_bytes: Vec<u8> = vec![
+            // index block hash
+            0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22,
+            0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22,
+            0x22, 0x22, 0x22, 0x22, // pages length
+            0x00, 0x00, 0x00, 0x01, // page index
+            0x00, 0x00, 0x00, 0x03, // inventory length
+            0x00, 0x00, 0x00, 0x03, // inventory
+            0x01, 0x00, 0x01,
+        ];
+
+        check_codec_and_corruption::<AttachmentsInvData>(&attachments_inv, &attachments_inv_bytes);
+
+        // oversized page
+        let oversized_page = AttachmentsInvData {
+            index_block_hash: StacksBlockId([0x22; 32]),
+            pages: vec![AttachmentPage {
+                index: 3,
+                inventory: vec![1; AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE as usize + 1],
+            }],
+        };
+        let bytes = oversized_page.serialize_to_vec();
+        AttachmentsInvData::consensus_deserialize(&mut &bytes[..]).unwrap_err();
+    }
+
     #[test]
     fn codec_GetNakamotoInv() {
         let get_nakamoto_inv = GetNakamotoInvData {
@@ -2628,6 +2788,17 @@ pub mod test {
                     true, true, true, true, true, true, true, true].as_slice()
                 ).unwrap()
             }),
+            StacksMessageType::GetAttachmentsInv(GetAttachmentsInvData {
+                index_block_hash: StacksBlockId([0x01; 32]),
+                pages: vec![1, 2],
+            }),
+            StacksMessageType::AttachmentsInv(AttachmentsInvData {
+                index_block_hash: StacksBlockId([0x01; 32]),
+                pages: vec![AttachmentPage {
+                    index: 1,
+                    inventory: vec![1, 0, 1],
+                }],
+            }),
         ];
 
         let mut maximal_relayers: Vec<RelayData> = vec![];
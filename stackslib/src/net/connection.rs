@@ -38,6 +38,7 @@ use crate::chainstate::burn::ConsensusHash;
 use crate::core::mempool::MAX_BLOOM_COUNTER_TXS;
 use crate::monitoring::{update_inbound_bandwidth, update_outbound_bandwidth};
 use crate::net::codec::*;
+use crate::net::dns::AddressFamilyPreference;
 use crate::net::download::BLOCK_DOWNLOAD_INTERVAL;
 use crate::net::inv::{INV_REWARD_CYCLES, INV_SYNC_INTERVAL};
 use crate::net::neighbors::{
@@ -362,6 +363,23 @@ pub struct ConnectionOptions {
     pub max_inflight_blocks: u64,
     pub max_inflight_attachments: u64,
     pub max_attachment_retry_count: u64,
+    /// how long, in seconds, to remember that a peer returned a 404 for an attachment
+    pub attachment_not_found_ttl: u64,
+    /// size, in bytes, of the byte ranges in which an attachment offered by several peers is
+    /// downloaded from all of them in parallel (0 = always download attachments whole)
+    pub attachment_chunk_size: u64,
+    /// maximum number of HTTP redirects followed by a single Atlas request
+    pub max_attachment_redirects: u64,
+    /// which of a peer's IPv4 and IPv6 addresses Atlas requests are sent to, and in which order
+    pub attachment_address_family: AddressFamilyPreference,
+    /// maximum number of Atlas requests (HTTP or p2p) a single peer may make per minute (0 = unlimited)
+    pub max_atlas_requests_per_minute: u64,
+    /// maximum number of Atlas response bytes (HTTP or p2p) served to a single peer per minute (0 = unlimited)
+    pub max_atlas_bytes_per_minute: u64,
+    /// how often, in seconds, to audit the AtlasDB for corrupt or missing attachments (0 = never)
+    pub atlas_audit_interval: u64,
+    /// how many AtlasDB rows the Atlas audit may check per p2p state-machine pass
+    pub atlas_audit_rows_per_pass: u32,
     pub read_only_call_limit: ExecutionCost,
     pub maximum_call_argument_size: u32,
     pub max_block_push_bandwidth: u64,
@@ -417,6 +435,15 @@ pub struct ConnectionOptions {
     pub force_nakamoto_epoch_transition: bool,
     /// The authorization token to enable the block proposal RPC endpoint
     pub block_proposal_token: Option<String>,
+    /// The authorization token to enable the burnchain sync pause/resume RPC endpoint
+    pub burnchain_sync_token: Option<String>,
+    /// The authorization token to enable the on-demand attachments download RPC endpoint
+    pub attachments_download_token: Option<String>,
+    /// The authorization token to enable the submitted burnchain operations and operation
+    /// intents RPC endpoints
+    pub burnchain_ops_token: Option<String>,
+    /// The authorization token to enable the MARF diff debugging RPC endpoint
+    pub marf_diff_token: Option<String>,
 }
 
 impl std::default::Default for ConnectionOptions {
@@ -456,10 +483,17 @@ impl std::default::Default for ConnectionOptions {
             inv_reward_cycles: INV_REWARD_CYCLES, // how many reward cycles of blocks to sync in a non-full inventory sync
             download_interval: BLOCK_DOWNLOAD_INTERVAL, // how often to scan for blocks to download
             pingback_timeout: 60,
-            dns_timeout: 15_000,            // DNS timeout, in millis
-            max_inflight_blocks: 6,         // number of parallel block downloads
-            max_inflight_attachments: 6,    // number of parallel attachments downloads
+            dns_timeout: 15_000,              // DNS timeout, in millis
+            max_inflight_blocks: 6,           // number of parallel block downloads
+            max_inflight_attachments: 6,      // number of parallel attachments downloads
             max_attachment_retry_count: 32, // how many attempt to get an attachment before giving up
+            attachment_not_found_ttl: 600, // how long to avoid asking a peer for an attachment it didn't have
+            attachment_chunk_size: 0,      // download attachments whole
+            max_attachment_redirects: 3,   // how many times a peer may redirect an Atlas request
+            max_atlas_requests_per_minute: 0, // unlimited Atlas requests per peer
+            max_atlas_bytes_per_minute: 0, // unlimited Atlas bandwidth per peer
+            atlas_audit_interval: 0,       // no periodic Atlas audits
+            atlas_audit_rows_per_pass: 1_000, // AtlasDB rows audited per p2p pass
             read_only_call_limit: ExecutionCost {
                 write_length: 0,
                 write_count: 0,
@@ -491,6 +525,7 @@ impl std::default::Default for ConnectionOptions {
             socket_recv_buffer_size: 131072, // Linux default
             socket_send_buffer_size: 16384, // Linux default
             private_neighbors: true,
+            attachment_address_family: AddressFamilyPreference::Any, // connect in resolver order
 
             // no faults on by default
             disable_neighbor_walk: false,
@@ -511,6 +546,10 @@ impl std::default::Default for ConnectionOptions {
             force_disconnect_interval: None,
             force_nakamoto_epoch_transition: false,
             block_proposal_token: None,
+            burnchain_sync_token: None,
+            attachments_download_token: None,
+            burnchain_ops_token: None,
+            marf_diff_token: None,
         }
     }
 }
@@ -151,7 +151,8 @@ impl LocalPeer {
         let port = port;
         let services = (ServiceFlags::RELAY as u16)
             | (ServiceFlags::RPC as u16)
-            | (ServiceFlags::STACKERDB as u16);
+            | (ServiceFlags::STACKERDB as u16)
+            | (ServiceFlags::ATTACHMENTS_INV as u16);
 
         info!(
             "Will be authenticating p2p messages with the following";
@@ -1845,6 +1846,7 @@ mod test {
             (ServiceFlags::RELAY as u16)
                 | (ServiceFlags::RPC as u16)
                 | (ServiceFlags::STACKERDB as u16)
+                | (ServiceFlags::ATTACHMENTS_INV as u16)
         );
         assert_eq!(local_peer.stacker_dbs, vec![]);
 
@@ -15,6 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::sync::mpsc::{
@@ -90,6 +91,109 @@ impl DNSResponse {
     }
 }
 
+/// The IP version of an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AddressFamily {
+    Ipv4,
+    Ipv6,
+}
+
+impl AddressFamily {
+    pub fn of(addr: &SocketAddr) -> AddressFamily {
+        match addr {
+            SocketAddr::V4(_) => AddressFamily::Ipv4,
+            SocketAddr::V6(_) => AddressFamily::Ipv6,
+        }
+    }
+
+    pub fn other(&self) -> AddressFamily {
+        match self {
+            AddressFamily::Ipv4 => AddressFamily::Ipv6,
+            AddressFamily::Ipv6 => AddressFamily::Ipv4,
+        }
+    }
+}
+
+impl fmt::Display for AddressFamily {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressFamily::Ipv4 => write!(f, "IPv4"),
+            AddressFamily::Ipv6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+/// Which of the addresses a name resolves to should be connected to, and in which order.  A
+/// dual-stack host resolves to both IPv4 and IPv6 addresses, but a node may only be able to
+/// reach one of the two families.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressFamilyPreference {
+    /// Use the addresses in the order the resolver gave them
+    Any,
+    /// Try IPv4 addresses first, then IPv6 addresses
+    PreferIpv4,
+    /// Try IPv6 addresses first, then IPv4 addresses
+    PreferIpv6,
+    /// Only use IPv4 addresses
+    Ipv4Only,
+    /// Only use IPv6 addresses
+    Ipv6Only,
+}
+
+impl Default for AddressFamilyPreference {
+    fn default() -> AddressFamilyPreference {
+        AddressFamilyPreference::Any
+    }
+}
+
+impl AddressFamilyPreference {
+    pub fn from_name(name: &str) -> Option<AddressFamilyPreference> {
+        match name {
+            "any" => Some(AddressFamilyPreference::Any),
+            "prefer_ipv4" => Some(AddressFamilyPreference::PreferIpv4),
+            "prefer_ipv6" => Some(AddressFamilyPreference::PreferIpv6),
+            "ipv4_only" => Some(AddressFamilyPreference::Ipv4Only),
+            "ipv6_only" => Some(AddressFamilyPreference::Ipv6Only),
+            _ => None,
+        }
+    }
+
+    /// The family to try first, if there is one
+    pub fn preferred_family(&self) -> Option<AddressFamily> {
+        match self {
+            AddressFamilyPreference::Any => None,
+            AddressFamilyPreference::PreferIpv4 | AddressFamilyPreference::Ipv4Only => {
+                Some(AddressFamily::Ipv4)
+            }
+            AddressFamilyPreference::PreferIpv6 | AddressFamilyPreference::Ipv6Only => {
+                Some(AddressFamily::Ipv6)
+            }
+        }
+    }
+
+    /// Can addresses of `family` be used at all?
+    pub fn allows(&self, family: AddressFamily) -> bool {
+        match self {
+            AddressFamilyPreference::Ipv4Only => family == AddressFamily::Ipv4,
+            AddressFamilyPreference::Ipv6Only => family == AddressFamily::Ipv6,
+            _ => true,
+        }
+    }
+
+    /// Drop the addresses of the families that can't be used, and put the preferred family's
+    /// addresses first.  Addresses of the same family keep the resolver's order.
+    pub fn select_addrs(&self, addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        let mut addrs: Vec<_> = addrs
+            .into_iter()
+            .filter(|addr| self.allows(AddressFamily::of(addr)))
+            .collect();
+        if let Some(preferred) = self.preferred_family() {
+            addrs.sort_by_key(|addr| AddressFamily::of(addr) != preferred);
+        }
+        addrs
+    }
+}
+
 /// The DNSResolver runs as a background thread in the node. In a loop, it collects inbound requests,
 /// then tries to resolve the valid requests.
 #[derive(Debug)]
@@ -359,11 +463,51 @@ impl DNSClient {
 mod test {
     use std::collections::HashMap;
     use std::error::Error;
+    use std::net::SocketAddr;
 
     use stacks_common::util::*;
 
+    use super::AddressFamilyPreference;
     use crate::net::test::*;
 
+    #[test]
+    fn address_family_preference_select_addrs() {
+        let v4: SocketAddr = "127.0.0.1:20443".parse().unwrap();
+        let v6: SocketAddr = "[::1]:20443".parse().unwrap();
+        let v4_2: SocketAddr = "10.0.0.1:20443".parse().unwrap();
+        let addrs = vec![v6, v4, v4_2];
+
+        assert_eq!(
+            AddressFamilyPreference::Any.select_addrs(addrs.clone()),
+            addrs
+        );
+        assert_eq!(
+            AddressFamilyPreference::PreferIpv4.select_addrs(addrs.clone()),
+            vec![v4, v4_2, v6]
+        );
+        assert_eq!(
+            AddressFamilyPreference::PreferIpv6.select_addrs(addrs.clone()),
+            vec![v6, v4, v4_2]
+        );
+        assert_eq!(
+            AddressFamilyPreference::Ipv4Only.select_addrs(addrs.clone()),
+            vec![v4, v4_2]
+        );
+        assert_eq!(
+            AddressFamilyPreference::Ipv6Only.select_addrs(addrs.clone()),
+            vec![v6]
+        );
+        assert!(AddressFamilyPreference::Ipv6Only
+            .select_addrs(vec![v4, v4_2])
+            .is_empty());
+
+        assert_eq!(
+            AddressFamilyPreference::from_name("prefer_ipv6"),
+            Some(AddressFamilyPreference::PreferIpv6)
+        );
+        assert_eq!(AddressFamilyPreference::from_name("ipv7_only"), None);
+    }
+
     #[test]
     fn dns_start_stop() {
         let (client, thread_handle) = dns_thread_start(100);
@@ -2324,6 +2324,16 @@ impl PeerNetwork {
         self.attachments_downloader = Some(AttachmentsDownloader::new(initial_batch));
     }
 
+    /// Hand the attachment downloader's unfinished batches back to the AtlasDB, so they are
+    /// picked up again after a restart.  Called when the peer network shuts down.
+    /// Returns the number of batches handed back.
+    pub fn drain_attachments_downloader(&mut self) -> Result<usize, net_error> {
+        match self.attachments_downloader {
+            Some(ref mut downloader) => Ok(downloader.drain(&mut self.atlasdb)?),
+            None => Ok(0),
+        }
+    }
+
     /// Process block downloader lifetime.  Returns the new blocks and microblocks if we get
     /// anything.
     /// Returns:
@@ -32,8 +32,8 @@ use stacks_common::types::chainstate::{
 use stacks_common::types::net::PeerHost;
 use stacks_common::types::Address;
 use stacks_common::util::chunked_encoding::*;
-use stacks_common::util::get_epoch_time_ms;
 use stacks_common::util::retry::{BoundReader, RetryReader};
+use stacks_common::util::{get_epoch_time_ms, get_epoch_time_secs};
 use url::Url;
 
 use super::rpc::ConversationHttp;
@@ -43,12 +43,14 @@ use crate::chainstate::burn::BlockSnapshot;
 use crate::chainstate::nakamoto::NakamotoChainState;
 use crate::chainstate::stacks::db::{StacksChainState, StacksHeaderInfo};
 use crate::core::{MemPoolDB, StacksEpoch};
+use crate::net::atlas::rate_limit::ATLAS_RATE_LIMITED_ENDPOINTS;
 use crate::net::connection::ConnectionOptions;
 use crate::net::http::common::HTTP_PREAMBLE_MAX_ENCODED_SIZE;
 use crate::net::http::{
     http_reason, Error as HttpError, HttpBadRequest, HttpContentType, HttpErrorResponse,
     HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse,
-    HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
+    HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError,
+    HttpTooManyRequests, HttpVersion,
 };
 use crate::net::p2p::PeerNetwork;
 use crate::net::server::HttpPeer;
@@ -582,6 +584,10 @@ impl StacksHttpResponse {
         &self.preamble
     }
 
+    pub fn preamble_mut(&mut self) -> &mut HttpResponsePreamble {
+        &mut self.preamble
+    }
+
     pub fn body(&self) -> &HttpResponsePayload {
         &self.body
     }
@@ -883,6 +889,15 @@ pub struct StacksHttp {
     pub read_only_call_limit: ExecutionCost,
     /// The authorization token to enable the block proposal RPC endpoint
     pub block_proposal_token: Option<String>,
+    /// The authorization token to enable the burnchain sync pause/resume RPC endpoint
+    pub burnchain_sync_token: Option<String>,
+    /// The authorization token to enable the on-demand attachments download RPC endpoint
+    pub attachments_download_token: Option<String>,
+    /// The authorization token to enable the submitted burnchain operations and operation
+    /// intents RPC endpoints
+    pub burnchain_ops_token: Option<String>,
+    /// The authorization token to enable the MARF diff debugging RPC endpoint
+    pub marf_diff_token: Option<String>,
 }
 
 impl StacksHttp {
@@ -899,6 +914,10 @@ impl StacksHttp {
             maximum_call_argument_size: conn_opts.maximum_call_argument_size,
             read_only_call_limit: conn_opts.read_only_call_limit.clone(),
             block_proposal_token: conn_opts.block_proposal_token.clone(),
+            burnchain_sync_token: conn_opts.burnchain_sync_token.clone(),
+            attachments_download_token: conn_opts.attachments_download_token.clone(),
+            burnchain_ops_token: conn_opts.burnchain_ops_token.clone(),
+            marf_diff_token: conn_opts.marf_diff_token.clone(),
         };
         http.register_rpc_methods();
         http
@@ -1061,6 +1080,29 @@ impl StacksHttp {
         Ok(StacksHttpResponse::new(preamble.clone(), payload))
     }
 
+    /// Parse out an HTTP redirect.  The body is passed through as-is; the caller is expected to
+    /// look at the `Location` header.
+    pub fn try_parse_redirect_response(
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<StacksHttpResponse, NetError> {
+        if preamble.status_code < 300 || preamble.status_code > 399 {
+            return Err(NetError::DeserializeError(
+                "Invalid response: not a redirect".to_string(),
+            ));
+        }
+
+        let payload = if body.is_empty() {
+            HttpResponsePayload::Empty
+        } else if preamble.content_type == HttpContentType::Text {
+            HttpResponsePayload::Text(String::from_utf8_lossy(body).to_string())
+        } else {
+            HttpResponsePayload::Bytes(body.to_vec())
+        };
+
+        Ok(StacksHttpResponse::new(preamble.clone(), payload))
+    }
+
     /// Try to parse an inbound HTTP response, given its decoded HTTP preamble, and the HTTP
     /// version and request path that had originally sent.  The body will be read from `fd`.
     pub fn try_parse_response(
@@ -1072,6 +1114,9 @@ impl StacksHttp {
         if preamble.status_code >= 400 {
             return Self::try_parse_error_response(preamble, body);
         }
+        if preamble.status_code >= 300 {
+            return Self::try_parse_redirect_response(preamble, body);
+        }
 
         let (_, _, parser) = self
             .request_handlers
@@ -1108,10 +1153,36 @@ impl StacksHttp {
             .try_into_contents();
         };
 
+        let peer_ip = self.peer_addr.ip();
         let (_, _, request_handler) = self
             .request_handlers
             .get_mut(response_handler_index)
             .expect("FATAL: request points to a nonexistent handler");
+
+        // Atlas endpoints are budgeted per peer
+        let rate_limited =
+            ATLAS_RATE_LIMITED_ENDPOINTS.contains(&request_handler.metrics_identifier());
+        if rate_limited {
+            let admitted = node.with_node_state(|network, _, _, _, _| {
+                network
+                    .atlas_rate_limiter
+                    .try_admit(peer_ip, get_epoch_time_secs())
+            });
+            if let Err(retry_after) = admitted {
+                request_handler.restart();
+                let (mut response_preamble, response_contents) = StacksHttpResponse::new_error(
+                    &request.preamble,
+                    &HttpTooManyRequests::new(format!(
+                        "Too many Atlas requests; retry in {} seconds",
+                        retry_after
+                    )),
+                )
+                .try_into_contents()?;
+                response_preamble.add_header("Retry-After".into(), retry_after.to_string());
+                return Ok((response_preamble, response_contents));
+            }
+        }
+
         let request_preamble = request.preamble.clone();
         let request_result =
             request_handler.try_handle_request(request.preamble, request.contents, node);
@@ -1128,6 +1199,16 @@ impl StacksHttp {
                 return Err(e);
             }
         };
+        if rate_limited {
+            let bytes_served = u64::from(response_contents.content_length().unwrap_or(0));
+            node.with_node_state(|network, _, _, _, _| {
+                network.atlas_rate_limiter.record_bytes_served(
+                    peer_ip,
+                    bytes_served,
+                    get_epoch_time_secs(),
+                )
+            });
+        }
         Ok((response_preamble, response_contents))
     }
 
@@ -62,6 +62,7 @@ use {rusqlite, serde_json, url};
 
 use self::dns::*;
 use crate::burnchains::affirmation::AffirmationMap;
+use crate::burnchains::sync_control::BurnchainSyncControl;
 use crate::burnchains::{Error as burnchain_error, Txid};
 use crate::chainstate::burn::db::sortdb::SortitionDB;
 use crate::chainstate::burn::{ConsensusHash, Opcodes};
@@ -82,7 +83,10 @@ use crate::core::mempool::*;
 use crate::core::{StacksEpoch, POX_REWARD_CYCLE_LENGTH};
 use crate::cost_estimates::metrics::CostMetric;
 use crate::cost_estimates::{CostEstimator, FeeEstimator, FeeRateEstimate};
-use crate::net::atlas::{Attachment, AttachmentInstance};
+use crate::net::api::getburnchaincontroller::BurnchainControllerSettings;
+use crate::net::api::getburnchainintents::BurnchainOpIntents;
+use crate::net::api::getburnchainops::SubmittedBurnchainOps;
+use crate::net::atlas::{Attachment, AttachmentInstance, AttachmentPage};
 use crate::net::dns::*;
 use crate::net::http::error::{HttpNotFound, HttpServerError};
 use crate::net::http::{
@@ -626,6 +630,14 @@ pub struct RPCHandlerArgs<'a> {
     pub fee_estimator: Option<&'a dyn FeeEstimator>,
     /// tx runtime cost metric
     pub cost_metric: Option<&'a dyn CostMetric>,
+    /// handle for pausing and resuming the node's burnchain sync loop
+    pub burnchain_sync_control: Option<&'a BurnchainSyncControl>,
+    /// record of the burnchain operations this node's miner submitted
+    pub submitted_burnchain_ops: Option<&'a dyn SubmittedBurnchainOps>,
+    /// write-ahead log of the burnchain operations this node's miner meant to submit
+    pub burnchain_op_intents: Option<&'a dyn BurnchainOpIntents>,
+    /// settings of the node's burnchain controller
+    pub burnchain_controller_settings: Option<&'a BurnchainControllerSettings>,
 }
 
 impl<'a> RPCHandlerArgs<'a> {
@@ -891,6 +903,24 @@ pub struct NakamotoInvData {
     pub tenures: BitVec<2100>,
 }
 
+/// Request for pages of a peer's attachment inventory as of a Stacks block.  This is the p2p
+/// counterpart of `GET /v2/attachments/inv`, for peers whose data URL can't be reached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetAttachmentsInvData {
+    /// The Stacks block the inventory is taken at
+    pub index_block_hash: StacksBlockId,
+    /// Indexes of the requested pages.  At most `MAX_ATTACHMENT_INV_PAGES_PER_REQUEST`.
+    pub pages: Vec<u32>,
+}
+
+/// Pages of a peer's attachment inventory, sent in reply to GetAttachmentsInv
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentsInvData {
+    /// The Stacks block the inventory was taken at
+    pub index_block_hash: StacksBlockId,
+    pub pages: Vec<AttachmentPage>,
+}
+
 /// Request for a PoX bitvector range.
 /// Requests bits for [start_reward_cycle, start_reward_cycle + num_anchor_blocks)
 #[derive(Debug, Clone, PartialEq)]
@@ -1005,6 +1035,7 @@ pub enum ServiceFlags {
     RELAY = 0x01,
     RPC = 0x02,
     STACKERDB = 0x04,
+    ATTACHMENTS_INV = 0x08,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -1138,6 +1169,9 @@ pub enum StacksMessageType {
     // Nakamoto-specific
     GetNakamotoInv(GetNakamotoInvData),
     NakamotoInv(NakamotoInvData),
+    // Atlas
+    GetAttachmentsInv(GetAttachmentsInvData),
+    AttachmentsInv(AttachmentsInvData),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -1172,6 +1206,9 @@ pub enum StacksMessageID {
     // nakamoto
     GetNakamotoInv = 26,
     NakamotoInv = 27,
+    // atlas
+    GetAttachmentsInv = 28,
+    AttachmentsInv = 29,
     // reserved
     Reserved = 255,
 }
@@ -1643,6 +1680,30 @@ pub trait Requestable: std::fmt::Display {
     fn get_url(&self) -> &UrlString;
 
     fn make_request_type(&self, peer_host: PeerHost) -> StacksHttpRequest;
+
+    /// The same request, to be sent to another peer because `failed_url` could not serve it.
+    /// `None` if there is no other peer to ask, or the request should not be retried.
+    fn failover(&self, _failed_url: &UrlString) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// The same request, to be sent to `to_url` because `from_url` redirected it there.
+    /// `None` if the request does not follow redirects, or has already followed
+    /// `max_redirects` of them.
+    fn redirect(
+        &self,
+        _from_url: &UrlString,
+        _to_url: &UrlString,
+        _max_redirects: u64,
+    ) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
 }
 
 #[cfg(test)]
@@ -1977,6 +2038,7 @@ pub mod test {
             &self,
             _burn_block: &BurnchainHeaderHash,
             _burn_block_height: u64,
+            _burn_block_timestamp: u64,
             _rewards: Vec<(PoxAddress, u64)>,
             _burns: u64,
             _reward_recipients: Vec<PoxAddress>,
@@ -2072,7 +2134,8 @@ pub mod test {
                 stacker_dbs: vec![],
                 services: (ServiceFlags::RELAY as u16)
                     | (ServiceFlags::RPC as u16)
-                    | (ServiceFlags::STACKERDB as u16),
+                    | (ServiceFlags::STACKERDB as u16)
+                    | (ServiceFlags::ATTACHMENTS_INV as u16),
                 aggregate_public_key: None,
                 test_stackers: None,
                 test_signers: None,
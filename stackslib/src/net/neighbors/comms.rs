@@ -425,6 +425,7 @@ pub trait NeighborComms {
 
 /// Transport-level API for peer network state machines.
 /// Prod implementation of NeighborComms.
+#[derive(Debug)]
 pub struct PeerNetworkComms {
     /// Set of PeerNetwork event IDs that this walk is tracking (so they won't get pruned)
     events: HashSet<usize>,
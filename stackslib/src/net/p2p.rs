@@ -53,7 +53,8 @@ use crate::chainstate::stacks::{StacksBlockHeader, MAX_BLOCK_LEN, MAX_TRANSACTIO
 use crate::core::StacksEpoch;
 use crate::monitoring::{update_inbound_neighbors, update_outbound_neighbors};
 use crate::net::asn::ASEntry4;
-use crate::net::atlas::{AtlasDB, AttachmentInstance, AttachmentsDownloader};
+use crate::net::atlas::audit::{apply_repairs, AtlasAudit};
+use crate::net::atlas::{AtlasDB, AtlasRateLimiter, AttachmentInstance, AttachmentsDownloader};
 use crate::net::chat::{ConversationP2P, NeighborStats};
 use crate::net::connection::{ConnectionOptions, NetworkReplyHandle, ReplyHandleP2P};
 use crate::net::db::{LocalPeer, PeerDB};
@@ -341,6 +342,11 @@ pub struct PeerNetwork {
 
     // peer attachment downloader
     pub attachments_downloader: Option<AttachmentsDownloader>,
+    // in-progress AtlasDB integrity audit, and when the last one finished
+    pub atlas_audit: Option<AtlasAudit>,
+    pub last_atlas_audit: u64,
+    // per-peer budgets for the Atlas HTTP endpoints
+    pub atlas_rate_limiter: AtlasRateLimiter,
 
     // peer stacker DB state machines
     pub stacker_db_syncs:
@@ -453,6 +459,11 @@ impl PeerNetwork {
             stacker_db_sync_map.insert(contract_id.clone(), stacker_db_sync);
         }
 
+        let atlas_rate_limiter = AtlasRateLimiter::new(
+            connection_opts.max_atlas_requests_per_minute,
+            connection_opts.max_atlas_bytes_per_minute,
+        );
+
         let mut network = PeerNetwork {
             peer_version: peer_version,
             epochs: epochs,
@@ -521,6 +532,9 @@ impl PeerNetwork {
             block_downloader: None,
             block_downloader_nakamoto: None,
             attachments_downloader: None,
+            atlas_audit: None,
+            last_atlas_audit: get_epoch_time_secs(),
+            atlas_rate_limiter,
 
             stacker_db_syncs: Some(stacker_db_sync_map),
             stacker_db_configs: stacker_db_configs,
@@ -4267,6 +4281,9 @@ impl PeerNetwork {
             self.atlasdb
                 .evict_expired_unresolved_attachment_instances()
                 .expect("FATAL: atlasdb error: evict_expired_unresolved_attachment_instances");
+            self.atlasdb
+                .evict_expired_attachment_resolutions()
+                .expect("FATAL: atlasdb error: evict_expired_attachment_resolutions");
             let initial_batch = self
                 .atlasdb
                 .find_unresolved_attachment_instances()
@@ -4274,6 +4291,7 @@ impl PeerNetwork {
 
             self.init_attachments_downloader(initial_batch);
         }
+        self.do_atlas_audit();
 
         match dns_client_opt {
             Some(ref mut dns_client) => {
@@ -4323,6 +4341,78 @@ impl PeerNetwork {
         }
     }
 
+    /// Advance the periodic AtlasDB audit, if enabled.  Once an audit finishes, the AtlasDB is
+    /// repaired, and the attachments that must be downloaded again are queued for download.
+    fn do_atlas_audit(&mut self) {
+        if self.connection_opts.atlas_audit_interval == 0 {
+            return;
+        }
+        let mut audit = match self.atlas_audit.take() {
+            Some(audit) => audit,
+            None => {
+                if self.last_atlas_audit + self.connection_opts.atlas_audit_interval
+                    > get_epoch_time_secs()
+                {
+                    return;
+                }
+                debug!("{:?}: Atlas: begin AtlasDB audit", &self.local_peer);
+                AtlasAudit::new()
+            }
+        };
+        match audit.step(
+            &self.atlasdb,
+            self.connection_opts.atlas_audit_rows_per_pass,
+        ) {
+            Ok(false) => {
+                self.atlas_audit = Some(audit);
+                return;
+            }
+            Ok(true) => {}
+            Err(e) => {
+                warn!(
+                    "{:?}: Atlas: AtlasDB audit failed: {:?}",
+                    &self.local_peer, &e
+                );
+                self.last_atlas_audit = get_epoch_time_secs();
+                return;
+            }
+        }
+        self.last_atlas_audit = get_epoch_time_secs();
+
+        let report = audit.into_report();
+        if report.is_clean() {
+            debug!("{:?}: Atlas: AtlasDB audit found no problems", &self.local_peer;
+                   "attachments_checked" => report.attachments_checked,
+                   "instances_checked" => report.instances_checked);
+            return;
+        }
+        let work_list = match apply_repairs(&mut self.atlasdb, &report) {
+            Ok(work_list) => work_list,
+            Err(e) => {
+                warn!(
+                    "{:?}: Atlas: failed to repair AtlasDB: {:?}",
+                    &self.local_peer, &e
+                );
+                return;
+            }
+        };
+        let num_instances = work_list.len();
+        let num_batches = self
+            .attachments_downloader
+            .as_mut()
+            .map(|downloader| downloader.enqueue_on_demand_batches(work_list))
+            .unwrap_or(0);
+        info!("{:?}: Atlas: AtlasDB audit repaired problems", &self.local_peer;
+              "attachments_checked" => report.attachments_checked,
+              "instances_checked" => report.instances_checked,
+              "corrupt_attachments" => report.corrupt_attachments.len(),
+              "missing_attachments" => report.missing_attachments.len(),
+              "stale_unavailable" => report.stale_unavailable.len(),
+              "missing_instance_ranges" => report.missing_instance_indexes.len(),
+              "redownload_instances" => num_instances,
+              "redownload_batches" => num_batches);
+    }
+
     /// Given an event ID, find the other event ID corresponding
     /// to the same remote peer.  There will be at most two such events
     /// -- one registered as the inbound connection, and one registered as the
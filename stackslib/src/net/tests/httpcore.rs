@@ -38,8 +38,8 @@ use crate::net::api::getneighbors::{RPCNeighbor, RPCNeighborsInfo};
 use crate::net::connection::ConnectionOptions;
 use crate::net::http::{
     http_error_from_code_and_text, http_reason, HttpContentType, HttpErrorResponse,
-    HttpRequestContents, HttpRequestPreamble, HttpReservedHeader, HttpResponsePreamble,
-    HttpVersion, HTTP_PREAMBLE_MAX_NUM_HEADERS,
+    HttpRequestContents, HttpRequestPreamble, HttpReservedHeader, HttpResponsePayload,
+    HttpResponsePreamble, HttpVersion, HTTP_PREAMBLE_MAX_NUM_HEADERS,
 };
 use crate::net::httpcore::{
     HttpPreambleExtensions, HttpRequestContentsExtensions, StacksHttp, StacksHttpMessage,
@@ -850,6 +850,38 @@ fn test_http_response_type_codec_err() {
     }
 }
 
+#[test]
+fn test_http_redirect_response() {
+    let mut http = StacksHttp::new(
+        "127.0.0.1:20443".parse().unwrap(),
+        &ConnectionOptions::default(),
+    );
+    http.set_response_handler("GET", "/v2/attachments/inv");
+
+    // redirects are passed through instead of being parsed as the endpoint's response
+    let redirect = "HTTP/1.1 301 Moved Permanently\r\nServer: stacks/v2.0\r\nLocation: http://10.0.0.1:20443/v2/attachments/inv\r\nContent-Type: text/plain\r\nContent-length: 5\r\n\r\nmoved";
+    let (preamble, offset) = http.read_preamble(redirect.as_bytes()).unwrap();
+    let (message, _) = http
+        .read_payload(&preamble, &redirect.as_bytes()[offset..])
+        .unwrap();
+    match message {
+        StacksHttpMessage::Response(response) => {
+            assert_eq!(response.preamble().status_code, 301);
+            assert_eq!(
+                response.preamble().get_header("location".into()),
+                Some("http://10.0.0.1:20443/v2/attachments/inv".to_string())
+            );
+            assert_eq!(
+                response.body(),
+                &HttpResponsePayload::Text("moved".to_string())
+            );
+        }
+        _ => {
+            panic!("Not an HTTP response");
+        }
+    }
+}
+
 #[test]
 fn test_http_duplicate_concurrent_streamed_response_fails() {
     // do not permit multiple in-flight chunk-encoded HTTP responses with the same request ID.
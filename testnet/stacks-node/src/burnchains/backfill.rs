@@ -0,0 +1,210 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Bookkeeping for the header-only fast sync (`burnchain.fast_header_sync`).
+//!
+//! On restart, a controller in this mode syncs the burnchain headers up to the remote tip, but
+//! only downloads and processes burnchain blocks up to the height `start()` was asked for.
+//! The remaining blocks are downloaded and processed by a `BackfillTask`, a background thread
+//! that works through them one chunk at a time while the node serves chainstate queries.
+//! `BackfillTracker` follows the task until it reaches the headers' height, and publishes
+//! the progress for `/v2/info`.
+
+use std::io;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use stacks::monitoring::{set_burnchain_backfill_progress, BurnchainBackfillProgress};
+
+/// Longest a `sync()` pass waits for the backfill task to make progress
+pub const BACKFILL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Follows the `sync()` passes that process the burnchain blocks left behind by a header-only
+/// fast sync
+#[derive(Debug, Default)]
+pub struct BackfillTracker {
+    progress: Option<BurnchainBackfillProgress>,
+}
+
+impl BackfillTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Begin following the backfill from `processed_height`, the highest processed burnchain
+    /// block once `start()` has returned, up to `headers_height`
+    pub fn begin(&mut self, processed_height: u64, headers_height: u64) {
+        info!(
+            "Synced burnchain headers; the remaining burnchain blocks will be processed in the background";
+            "processed_height" => processed_height,
+            "headers_height" => headers_height
+        );
+        self.progress = Some(BurnchainBackfillProgress {
+            start_height: processed_height,
+            processed_height,
+            target_height: headers_height,
+        });
+        self.record(processed_height);
+    }
+
+    /// Record that burnchain blocks have been processed up to `processed_height`, and finish
+    /// the backfill if it has reached its target
+    pub fn record(&mut self, processed_height: u64) {
+        let Some(progress) = self.progress.as_mut() else {
+            return;
+        };
+        progress.processed_height = processed_height;
+        if progress.processed_height >= progress.target_height {
+            info!(
+                "Burnchain backfill complete";
+                "start_height" => progress.start_height,
+                "target_height" => progress.target_height
+            );
+            self.progress = None;
+            set_burnchain_backfill_progress(None);
+        } else {
+            debug!(
+                "Burnchain backfill in progress";
+                "processed_height" => progress.processed_height,
+                "target_height" => progress.target_height,
+                "percent" => 100.0 * progress.fraction_complete()
+            );
+            set_burnchain_backfill_progress(Some(progress.clone()));
+        }
+    }
+
+    /// The progress of the backfill, if one is under way
+    pub fn progress(&self) -> Option<&BurnchainBackfillProgress> {
+        self.progress.as_ref()
+    }
+}
+
+/// A background thread that processes the burnchain blocks left behind by a header-only fast
+/// sync.  It reports the height it has processed up to after each chunk; the controller
+/// receives those reports with `wait()` and records them in its `BackfillTracker`.
+pub struct BackfillTask {
+    target_height: u64,
+    progress: Receiver<u64>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl BackfillTask {
+    /// Spawn the task to process the burnchain blocks above `processed_height` up to
+    /// `target_height`.  `step` is called with the height processed so far, processes the
+    /// next chunk of blocks, and returns the new processed height, or `None` if the task must
+    /// stop (e.g. because the node is shutting down).
+    pub fn spawn<F>(processed_height: u64, target_height: u64, mut step: F) -> io::Result<Self>
+    where
+        F: FnMut(u64) -> Option<u64> + Send + 'static,
+    {
+        let (progress_tx, progress) = channel();
+        let handle = thread::Builder::new()
+            .name("burnchain-backfill".into())
+            .spawn(move || {
+                let mut processed_height = processed_height;
+                while processed_height < target_height {
+                    let Some(next_height) = step(processed_height) else {
+                        debug!("Burnchain backfill stopped"; "processed_height" => processed_height);
+                        return;
+                    };
+                    processed_height = next_height;
+                    if progress_tx.send(processed_height).is_err() {
+                        return;
+                    }
+                }
+            })?;
+        Ok(Self {
+            target_height,
+            progress,
+            handle: Some(handle),
+        })
+    }
+
+    /// The burnchain height the task processes blocks up to
+    pub fn target_height(&self) -> u64 {
+        self.target_height
+    }
+
+    /// Wait up to `timeout` for the task to process more blocks, and record all the progress
+    /// it has reported in `tracker`.  Returns `false` once the task has finished.
+    pub fn wait(&mut self, timeout: Duration, tracker: &mut BackfillTracker) -> bool {
+        let mut next = self.progress.recv_timeout(timeout);
+        loop {
+            match next {
+                Ok(processed_height) => tracker.record(processed_height),
+                Err(RecvTimeoutError::Timeout) => return true,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            next = self.progress.recv_timeout(Duration::ZERO);
+        }
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                error!("Burnchain backfill thread panicked");
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backfill_tracker() {
+        let mut tracker = BackfillTracker::new();
+        // nothing happens until a backfill begins
+        tracker.record(10);
+        assert!(tracker.progress().is_none());
+
+        tracker.begin(10, 20);
+        assert_eq!(
+            tracker.progress(),
+            Some(&BurnchainBackfillProgress {
+                start_height: 10,
+                processed_height: 10,
+                target_height: 20,
+            })
+        );
+        tracker.record(15);
+        assert_eq!(tracker.progress().unwrap().processed_height, 15);
+        tracker.record(20);
+        assert!(tracker.progress().is_none());
+
+        // headers no further ahead than the processed blocks leave nothing to backfill
+        tracker.begin(20, 20);
+        assert!(tracker.progress().is_none());
+    }
+
+    #[test]
+    fn test_backfill_task() {
+        let mut tracker = BackfillTracker::new();
+        tracker.begin(10, 20);
+        // process four blocks at a time
+        let mut task = BackfillTask::spawn(10, 20, |height| Some((height + 4).min(20))).unwrap();
+        assert_eq!(task.target_height(), 20);
+        while task.wait(BACKFILL_POLL_INTERVAL, &mut tracker) {}
+        assert!(tracker.progress().is_none());
+
+        // a task that stops early leaves the rest of the backfill to the tracker's caller
+        let mut tracker = BackfillTracker::new();
+        tracker.begin(10, 20);
+        let mut task =
+            BackfillTask::spawn(10, 20, |height| (height < 14).then_some(height + 4)).unwrap();
+        while task.wait(BACKFILL_POLL_INTERVAL, &mut tracker) {}
+        assert_eq!(tracker.progress().unwrap().processed_height, 14);
+    }
+}
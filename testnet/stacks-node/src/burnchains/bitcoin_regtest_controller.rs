@@ -1,13 +1,13 @@
 use std::cmp;
 use std::io::Cursor;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::Duration;
 
 use async_h1::client;
 use async_std::io::ReadExt;
 use async_std::net::TcpStream;
-use base64::encode;
 use http_types::{Method, Request, Url};
 use serde::Serialize;
 use serde_json::json;
@@ -31,13 +31,16 @@ use stacks::chainstate::burn::operations::{
     BlockstackOperationType, DelegateStxOp, LeaderBlockCommitOp, LeaderKeyRegisterOp, PreStxOp,
     StackStxOp, TransferStxOp, VoteForAggregateKeyOp,
 };
-#[cfg(test)]
-use stacks::chainstate::burn::Opcodes;
+use stacks::chainstate::burn::{BlockSnapshot, Opcodes};
 use stacks::chainstate::coordinator::comm::CoordinatorChannels;
 #[cfg(test)]
 use stacks::chainstate::stacks::address::PoxAddress;
 use stacks::core::{StacksEpoch, StacksEpochId};
-use stacks::monitoring::{increment_btc_blocks_received_counter, increment_btc_ops_sent_counter};
+use stacks::monitoring::{
+    increment_btc_blocks_received_counter, increment_btc_ops_sent_counter,
+    set_burnchain_headers_height, set_sortition_height,
+};
+use stacks::net::api::getburnchaincontroller::BurnchainFeePolicy;
 use stacks_common::codec::StacksMessageCodec;
 use stacks_common::deps_common::bitcoin::blockdata::opcodes;
 use stacks_common::deps_common::bitcoin::blockdata::script::{Builder, Script};
@@ -50,12 +53,23 @@ use stacks_common::deps_common::bitcoin::network::serialize::deserialize as btc_
 use stacks_common::deps_common::bitcoin::network::serialize::RawEncoder;
 use stacks_common::deps_common::bitcoin::util::hash::Sha256dHash;
 use stacks_common::types::chainstate::BurnchainHeaderHash;
-use stacks_common::util::hash::{hex_bytes, Hash160};
+use stacks_common::util::hash::{hex_bytes, Hash160, Sha256Sum};
 use stacks_common::util::secp256k1::Secp256k1PublicKey;
-use stacks_common::util::sleep_ms;
+use stacks_common::util::{get_epoch_time_secs, sleep_ms};
 
-use super::super::operations::BurnchainOpSigner;
+use super::super::operations::{BurnchainOpSigner, OpAuditLog, OpAuditLogError};
 use super::super::Config;
+use super::backfill::{BackfillTask, BackfillTracker, BACKFILL_POLL_INTERVAL};
+use super::block_stream::{BurnBlockEvent, BurnBlockStream};
+use super::clock::{Clock, SystemClock};
+use super::op_confirmations::{
+    current_run_id, find_sortition_outcome, op_confirmations_path, OpConfirmationTracker, OpIntent,
+};
+use super::readiness::{BurnchainReadiness, ReadinessLevel};
+use super::rpc_auth::RpcAuth;
+#[cfg(test)]
+use super::snapshot::{restore_snapshot, Error as SnapshotError, SnapshotManifest};
+use super::sync_span::{SyncSpan, SyncStage};
 use super::{BurnchainController, BurnchainTip, Error as BurnchainControllerError};
 use crate::config::BurnchainConfig;
 
@@ -64,6 +78,9 @@ use crate::config::BurnchainConfig;
 ///  the cache is force-reset.
 const UTXO_CACHE_STALENESS_LIMIT: u64 = 6;
 const DUST_UTXO_LIMIT: u64 = 5500;
+/// A block commit is built for the sortition of the burnchain block after the tip it was built
+/// on.  Mined any later, it cannot win, so it expires this many blocks after that tip.
+const BLOCK_COMMIT_EXPIRY_BLOCKS: u64 = 1;
 
 #[cfg(test)]
 // Used to inject invalid block commits during testing.
@@ -80,6 +97,24 @@ pub struct BitcoinRegtestController {
     ongoing_block_commit: Option<OngoingBlockCommit>,
     should_keep_running: Option<Arc<AtomicBool>>,
     allow_rbf: bool,
+    sync_span: Option<SyncSpan>,
+    /// Follows the processing of the burnchain blocks left behind by a header-only fast sync
+    backfill: BackfillTracker,
+    /// Processes those blocks in the background.  While it runs, `sync()` leaves the
+    /// burnchain to it, and only follows the sortitions it has processed.
+    backfill_task: Option<BackfillTask>,
+    /// Opened on first use if `burnchain.op_audit_log_path` is set.  If opening it fails, the
+    /// error is kept, so that the log is not re-read and re-verified for every operation.
+    op_audit_log: Option<Result<OpAuditLog, OpAuditLogError>>,
+    /// Set if `burnchain.block_stream_bind` is set (and this controller follows a coordinator)
+    block_stream: Option<BurnBlockStream>,
+    /// Opened when the first operation is submitted, or on sync if operations were submitted
+    /// before a restart
+    op_confirmations: Option<OpConfirmationTracker>,
+    /// Time source for `BurnchainTip::received_at` and retry delays
+    clock: Arc<dyn Clock>,
+    /// Set if `burnchain.readiness_bind` is set (and this controller follows a coordinator)
+    readiness: Option<BurnchainReadiness>,
 }
 
 #[derive(Clone)]
@@ -88,12 +123,19 @@ pub struct OngoingBlockCommit {
     utxos: UTXOSet,
     fees: LeaderBlockCommitFees,
     txids: Vec<Txid>,
+    /// The burnchain height by which the latest transaction must be mined to be of any use
+    expires_at_burn_height: u64,
 }
 
 impl OngoingBlockCommit {
     fn sum_utxos(&self) -> u64 {
         self.utxos.total_available()
     }
+
+    /// Has the burnchain reached `burn_height` without this commit being mined?
+    fn is_expired(&self, burn_height: u64) -> bool {
+        burn_height >= self.expires_at_burn_height
+    }
 }
 
 #[derive(Clone)]
@@ -168,16 +210,21 @@ pub fn make_bitcoin_indexer(
     burnchain_indexer
 }
 
+/// The up-to-date fee policy, re-read from the config file if there is one
+pub fn get_fee_policy(config: &Config) -> BurnchainFeePolicy {
+    config.get_burnchain_config().fee_policy()
+}
+
 pub fn get_satoshis_per_byte(config: &Config) -> u64 {
-    config.get_burnchain_config().satoshis_per_byte
+    get_fee_policy(config).satoshis_per_byte
 }
 
 pub fn get_rbf_fee_increment(config: &Config) -> u64 {
-    config.get_burnchain_config().rbf_fee_increment
+    get_fee_policy(config).rbf_fee_increment
 }
 
 pub fn get_max_rbf(config: &Config) -> u64 {
-    config.get_burnchain_config().max_rbf
+    get_fee_policy(config).max_rbf
 }
 
 impl LeaderBlockCommitFees {
@@ -272,6 +319,19 @@ impl BitcoinRegtestController {
         BitcoinRegtestController::with_burnchain(config, coordinator_channel, None, None)
     }
 
+    /// Create a controller whose burnchain and sortition DBs start out as the snapshot in
+    /// `snapshot_dir`.  `config`'s working directory must not have a sortition DB yet, and the
+    /// bitcoin node must be serving the chain the snapshot was taken from.
+    #[cfg(test)]
+    pub fn from_snapshot(
+        config: Config,
+        coordinator_channel: Option<CoordinatorChannels>,
+        snapshot_dir: &Path,
+    ) -> Result<(Self, SnapshotManifest), SnapshotError> {
+        let manifest = restore_snapshot(&config, snapshot_dir)?;
+        Ok((Self::new(config, coordinator_channel), manifest))
+    }
+
     pub fn with_burnchain(
         config: Config,
         coordinator_channel: Option<CoordinatorChannels>,
@@ -327,6 +387,40 @@ impl BitcoinRegtestController {
             should_keep_running: should_keep_running.clone(),
         };
 
+        let block_stream = match (
+            config.burnchain.block_stream_bind.as_ref(),
+            coordinator_channel.as_ref(),
+        ) {
+            (Some(bind), Some(_)) => {
+                match BurnBlockStream::get_or_start(bind, config.burnchain.block_stream_history) {
+                    Ok(stream) => Some(stream),
+                    Err(e) => {
+                        error!("Failed to start burn block stream";
+                               "bind" => bind,
+                               "error" => %e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let readiness = match (
+            config.burnchain.readiness_bind.as_ref(),
+            coordinator_channel.as_ref(),
+        ) {
+            (Some(bind), Some(_)) => match BurnchainReadiness::get_or_start(bind) {
+                Ok(readiness) => Some(readiness),
+                Err(e) => {
+                    error!("Failed to start burnchain readiness probes";
+                           "bind" => bind,
+                           "error" => %e);
+                    None
+                }
+            },
+            _ => None,
+        };
+
         Self {
             use_coordinator: coordinator_channel,
             config,
@@ -338,6 +432,14 @@ impl BitcoinRegtestController {
             ongoing_block_commit: None,
             should_keep_running,
             allow_rbf: true,
+            sync_span: None,
+            backfill: BackfillTracker::new(),
+            backfill_task: None,
+            op_audit_log: None,
+            block_stream,
+            op_confirmations: None,
+            clock: SystemClock::shared(),
+            readiness,
         }
     }
 
@@ -382,6 +484,14 @@ impl BitcoinRegtestController {
             ongoing_block_commit: None,
             should_keep_running: None,
             allow_rbf: true,
+            sync_span: None,
+            backfill: BackfillTracker::new(),
+            backfill_task: None,
+            op_audit_log: None,
+            block_stream: None,
+            op_confirmations: None,
+            clock: SystemClock::shared(),
+            readiness: None,
         }
     }
 
@@ -392,6 +502,12 @@ impl BitcoinRegtestController {
         ret
     }
 
+    /// Use `clock` instead of the system clock, e.g. so tests can control retry delays
+    #[cfg(test)]
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
     /// Get an owned copy of the ongoing block commit state
     pub fn get_ongoing_commit(&self) -> Option<OngoingBlockCommit> {
         self.ongoing_block_commit.clone()
@@ -444,12 +560,12 @@ impl BitcoinRegtestController {
                         burnchain_error::BurnchainPeerBroken => {
                             // remote burnchain peer broke, and produced a shorter blockchain fork.
                             // just keep trying
-                            sleep_ms(5000);
+                            self.clock.sleep_ms(5000);
                             continue;
                         }
                         _ => {
                             // delay and try again
-                            sleep_ms(5000);
+                            self.clock.sleep_ms(5000);
                             continue;
                         }
                     }
@@ -463,7 +579,7 @@ impl BitcoinRegtestController {
                 let burnchain_tip = BurnchainTip {
                     block_snapshot: block_snapshot,
                     state_transition: BurnchainStateTransitionOps::from(state_transition),
-                    received_at: Instant::now(),
+                    received_at: self.clock.now(),
                 };
                 self.chain_tip = Some(burnchain_tip.clone());
                 burnchain_tip
@@ -473,7 +589,7 @@ impl BitcoinRegtestController {
                 let burnchain_tip = BurnchainTip {
                     block_snapshot: block_snapshot,
                     state_transition: BurnchainStateTransitionOps::noop(),
-                    received_at: Instant::now(),
+                    received_at: self.clock.now(),
                 };
                 self.chain_tip = Some(burnchain_tip.clone());
                 burnchain_tip
@@ -499,6 +615,19 @@ impl BitcoinRegtestController {
             }
         };
 
+        if let Some(task) = self.backfill_task.as_mut() {
+            if task.wait(BACKFILL_POLL_INTERVAL, &mut self.backfill) {
+                let headers_height = task.target_height();
+                let burnchain_tip = self.canonical_sortition_tip();
+                self.follow_chain_tip(&burnchain_tip);
+                return Ok((burnchain_tip, headers_height));
+            }
+            self.backfill_task = None;
+        }
+
+        let mut sync_span = SyncSpan::begin(target_block_height_opt);
+        // let the chains coordinator log its work under this pass's span ID
+        coordinator_comms.set_burn_sync_span_id(sync_span.id());
         let mut burnchain = self.get_burnchain();
         let (block_snapshot, burnchain_height, state_transition) = loop {
             if !self.should_keep_running() {
@@ -511,9 +640,17 @@ impl BitcoinRegtestController {
                 target_block_height_opt,
                 Some(burnchain.pox_constants.reward_cycle_length as u64),
                 self.should_keep_running.clone(),
+                self.config.burnchain.parser_threads,
             ) {
                 Ok(x) => {
                     increment_btc_blocks_received_counter();
+                    sync_span.enter_stage(SyncStage::BurnBlockReceived, x.block_height);
+
+                    let burnchain_height = self
+                        .indexer
+                        .get_highest_header_height()
+                        .map_err(BurnchainControllerError::IndexerError)?;
+                    set_burnchain_headers_height(burnchain_height);
 
                     // initialize the dbs...
                     self.sortdb_mut();
@@ -521,10 +658,14 @@ impl BitcoinRegtestController {
                     // wait for the chains coordinator to catch up with us.
                     // don't wait for heights beyond the burnchain tip.
                     if block_for_sortitions {
-                        self.wait_for_sortitions(
+                        let sortition_tip = self.wait_for_sortitions(
                             coordinator_comms,
                             target_block_height_opt.unwrap_or(x.block_height),
                         )?;
+                        sync_span.enter_stage(
+                            SyncStage::SortitionProcessed,
+                            sortition_tip.block_snapshot.block_height,
+                        );
                     }
 
                     // NOTE: This is the latest _sortition_ on the canonical sortition history, not the latest burnchain block!
@@ -538,10 +679,7 @@ impl BitcoinRegtestController {
                         .expect("Sortition DB error.")
                         .expect("BUG: no data for the canonical chain tip");
 
-                    let burnchain_height = self
-                        .indexer
-                        .get_highest_header_height()
-                        .map_err(BurnchainControllerError::IndexerError)?;
+                    self.record_sync_readiness(burnchain_height, snapshot.block_height);
                     break (snapshot, burnchain_height, state_transition);
                 }
                 Err(e) => {
@@ -558,12 +696,12 @@ impl BitcoinRegtestController {
                         burnchain_error::BurnchainPeerBroken => {
                             // remote burnchain peer broke, and produced a shorter blockchain fork.
                             // just keep trying
-                            sleep_ms(5000);
+                            self.clock.sleep_ms(5000);
                             continue;
                         }
                         _ => {
                             // delay and try again
-                            sleep_ms(5000);
+                            self.clock.sleep_ms(5000);
                             continue;
                         }
                     }
@@ -574,15 +712,174 @@ impl BitcoinRegtestController {
         let burnchain_tip = BurnchainTip {
             block_snapshot: block_snapshot,
             state_transition: state_transition,
-            received_at: Instant::now(),
+            received_at: self.clock.now(),
         };
 
         self.chain_tip = Some(burnchain_tip.clone());
+        self.sync_span = Some(sync_span);
+        self.backfill
+            .record(burnchain_tip.block_snapshot.block_height);
+        self.follow_chain_tip(&burnchain_tip);
         debug!("Done receiving blocks");
 
         Ok((burnchain_tip, burnchain_height))
     }
 
+    /// Header-only fast sync.  Sync and validate the burnchain headers up to the remote tip,
+    /// but only download and process burnchain blocks up to `target_block_height_opt`, as
+    /// `start()` was asked to.  The remaining blocks are downloaded and processed by a
+    /// background `BackfillTask`, whose progress `self.backfill` follows.  This is only
+    /// possible on restart, when the burnchain DB already has blocks.
+    /// Returns `Ok(None)` if a regular sync is required instead.
+    fn start_headers_only(
+        &mut self,
+        target_block_height_opt: Option<u64>,
+    ) -> Result<Option<(BurnchainTip, u64)>, BurnchainControllerError> {
+        if self.use_coordinator.is_none() {
+            // pre-PoX helium node
+            return Ok(None);
+        }
+        if self
+            .get_burnchain()
+            .get_highest_burnchain_block()?
+            .is_none()
+        {
+            debug!("Burnchain DB has no blocks; cannot do a header-only sync");
+            return Ok(None);
+        }
+
+        let headers_height = match self
+            .indexer
+            .get_highest_header_height()
+            .and_then(|height| self.indexer.sync_headers(height, None))
+        {
+            Ok(height) => height,
+            Err(e) => {
+                warn!(
+                    "Failed to sync burnchain headers; falling back to a full sync: {:?}",
+                    &e
+                );
+                return Ok(None);
+            }
+        };
+
+        let burnchain_tip = match target_block_height_opt {
+            Some(target_block_height) => self.receive_blocks(false, Some(target_block_height))?.0,
+            None => self.canonical_sortition_tip(),
+        };
+
+        let processed_height = burnchain_tip.block_snapshot.block_height;
+        set_burnchain_headers_height(headers_height);
+        set_sortition_height(processed_height);
+        self.record_sync_readiness(headers_height, processed_height);
+        self.backfill.begin(processed_height, headers_height);
+        if processed_height < headers_height {
+            self.spawn_backfill_task(processed_height, headers_height);
+        }
+
+        Ok(Some((burnchain_tip, headers_height)))
+    }
+
+    /// Spawn the task that processes the burnchain blocks above `processed_height` up to
+    /// `headers_height`, one reward cycle at a time, with its own indexer.  If the thread
+    /// cannot be spawned, the regular `sync()` passes process the blocks instead.
+    fn spawn_backfill_task(&mut self, processed_height: u64, headers_height: u64) {
+        let coordinator_comms = self
+            .use_coordinator
+            .clone()
+            .expect("BUG: backfill requires a coordinator");
+        let mut burnchain = self.get_burnchain();
+        let mut indexer = make_bitcoin_indexer(&self.config, self.should_keep_running.clone());
+        let should_keep_running = self.should_keep_running.clone();
+        let parser_threads = self.config.burnchain.parser_threads;
+        let clock = self.clock.clone();
+        let chunk_len = u64::from(burnchain.pox_constants.reward_cycle_length);
+
+        let task = BackfillTask::spawn(processed_height, headers_height, move |processed_height| {
+            loop {
+                if let Some(should_keep_running) = should_keep_running.as_ref() {
+                    if !should_keep_running.load(Ordering::SeqCst) {
+                        return None;
+                    }
+                }
+                let target_height = processed_height
+                    .saturating_add(chunk_len)
+                    .min(headers_height);
+                match burnchain.sync_with_indexer(
+                    &mut indexer,
+                    coordinator_comms.clone(),
+                    Some(target_height),
+                    Some(chunk_len),
+                    should_keep_running.clone(),
+                    parser_threads,
+                ) {
+                    Ok(header) => {
+                        increment_btc_blocks_received_counter();
+                        return Some(header.block_height);
+                    }
+                    Err(burnchain_error::CoordinatorClosed) => return None,
+                    Err(burnchain_error::TrySyncAgain) => continue,
+                    Err(e) => {
+                        error!("Burnchain backfill: unable to sync with burnchain: {}", e);
+                        clock.sleep_ms(5000);
+                    }
+                }
+            }
+        });
+        match task {
+            Ok(task) => self.backfill_task = Some(task),
+            Err(e) => warn!(
+                "Failed to spawn the burnchain backfill thread; the blocks will be processed by subsequent syncs: {:?}",
+                &e
+            ),
+        }
+    }
+
+    /// The tip of the canonical sortition history, which becomes the chain tip
+    fn canonical_sortition_tip(&mut self) -> BurnchainTip {
+        let sort_tip = SortitionDB::get_canonical_burn_chain_tip(self.sortdb_mut().conn())
+            .expect("Sortition DB error.");
+        let (block_snapshot, state_transition) = self
+            .sortdb_ref()
+            .get_sortition_result(&sort_tip.sortition_id)
+            .expect("Sortition DB error.")
+            .expect("BUG: no data for the canonical chain tip");
+        let burnchain_tip = BurnchainTip {
+            block_snapshot,
+            state_transition,
+            received_at: self.clock.now(),
+        };
+        self.chain_tip = Some(burnchain_tip.clone());
+        burnchain_tip
+    }
+
+    /// Bring what follows the chain tip up to date with `burnchain_tip`: the sortition height
+    /// metric, the block stream, and the confirmations of submitted operations, which also
+    /// resolve the intents recorded for them.  This is done
+    /// on every sync pass, whether it processed burnchain blocks itself or left them to the
+    /// backfill task.
+    fn follow_chain_tip(&mut self, burnchain_tip: &BurnchainTip) {
+        set_sortition_height(burnchain_tip.block_snapshot.block_height);
+        self.publish_burn_blocks(&burnchain_tip.block_snapshot);
+        self.update_op_confirmations(burnchain_tip.block_snapshot.block_height);
+    }
+
+    /// Record that startup has reached `level`, for the readiness probes
+    pub fn mark_ready(&self, level: ReadinessLevel) {
+        if let Some(readiness) = self.readiness.as_ref() {
+            readiness.advance(level);
+        }
+    }
+
+    /// Record the readiness levels reached by a sync that brought the burnchain headers up to
+    /// `headers_height`, and the sortitions up to `sortition_height`
+    fn record_sync_readiness(&self, headers_height: u64, sortition_height: u64) {
+        self.mark_ready(ReadinessLevel::HeadersSynced);
+        if sortition_height >= headers_height {
+            self.mark_ready(ReadinessLevel::SortitionsCaughtUp);
+        }
+    }
+
     fn should_keep_running(&self) -> bool {
         match self.should_keep_running {
             Some(ref should_keep_running) => should_keep_running.load(Ordering::SeqCst),
@@ -607,7 +904,7 @@ impl BitcoinRegtestController {
         test_debug!("Import public key '{}'", &pubk.to_hex());
         let _result = BitcoinRPCRequest::import_public_key(&self.config, &pubk);
 
-        sleep_ms(1000);
+        self.clock.sleep_ms(1000);
 
         let min_conf = 0i64;
         let max_conf = 9999999i64;
@@ -743,7 +1040,7 @@ impl BitcoinRegtestController {
                 }
                 Err(e) => {
                     error!("Bitcoin RPC failure: error listing utxos {:?}", e);
-                    sleep_ms(5000);
+                    self.clock.sleep_ms(5000);
                     continue;
                 }
             };
@@ -758,7 +1055,7 @@ impl BitcoinRegtestController {
                     // reasonable to me.
                     // $ bitcoin-cli importaddress mxVFsFW5N4mu1HPkxPttorvocvzeZ7KZyk
                     let _result = BitcoinRPCRequest::import_public_key(&self.config, &pubk);
-                    sleep_ms(1000);
+                    self.clock.sleep_ms(1000);
                 }
 
                 let result = BitcoinRPCRequest::list_unspent(
@@ -774,7 +1071,7 @@ impl BitcoinRegtestController {
                     Ok(utxos) => utxos,
                     Err(e) => {
                         error!("Bitcoin RPC failure: error listing utxos {:?}", e);
-                        sleep_ms(5000);
+                        self.clock.sleep_ms(5000);
                         continue;
                     }
                 };
@@ -1424,6 +1721,7 @@ impl BitcoinRegtestController {
             utxos,
             fees: estimated_fees,
             txids,
+            expires_at_burn_height: burn_chain_tip.block_height + BLOCK_COMMIT_EXPIRY_BLOCKS,
         };
 
         info!(
@@ -1500,6 +1798,7 @@ impl BitcoinRegtestController {
         // Did a re-org occur since we fetched our UTXOs, or are the UTXOs so stale that they should be abandoned?
         let mut traversal_depth = 0;
         let mut burn_chain_tip = burnchain_db.get_canonical_chain_tip().ok()?;
+        let burn_height = burn_chain_tip.block_height;
         let mut found_last_mined_at = false;
         while traversal_depth < UTXO_CACHE_STALENESS_LIMIT {
             if &burn_chain_tip.block_hash == &ongoing_op.utxos.bhh {
@@ -1534,13 +1833,25 @@ impl BitcoinRegtestController {
             return res;
         }
 
+        // If the ongoing operation missed the sortition it was built for, it can no longer win
+        // one. It is not tracked any longer: it is neither re-submitted, nor does it hold up the
+        // next commit, whose inputs are ours to choose again.
+        let expired = ongoing_op.is_expired(burn_height);
+        if expired {
+            info!("Ongoing leader block commit expired without being mined";
+                  "txids" => ?ongoing_op.txids,
+                  "expires_at_burn_height" => ongoing_op.expires_at_burn_height,
+                  "burn_height" => burn_height);
+        }
+
         // Stop as soon as the fee_rate is ${self.config.burnchain.max_rbf} percent higher, stop RBF
-        if ongoing_op.fees.fee_rate
-            > (get_satoshis_per_byte(&self.config) * get_max_rbf(&self.config) / 100)
-        {
+        let fee_policy = get_fee_policy(&self.config);
+        let max_rbf_reached =
+            ongoing_op.fees.fee_rate > (fee_policy.satoshis_per_byte * fee_policy.max_rbf / 100);
+        if max_rbf_reached && !expired {
             warn!(
                 "RBF'd block commits reached {}% satoshi per byte fee rate, not resubmitting",
-                get_max_rbf(&self.config)
+                fee_policy.max_rbf
             );
             self.ongoing_block_commit = Some(ongoing_op);
             return None;
@@ -1554,15 +1865,26 @@ impl BitcoinRegtestController {
         //    b) If we have some other UTXOs, drop the ongoing operation, and track the new one.
         //  ii) If UTXOs initially used are sufficient for paying for a fee bump, then RBF
 
+        // An expired operation is dropped instead: if it was replaced by an identical one, the
+        // replacement would be just as late.  If it is replaced at all, it is by fee (2.ii), so
+        // that the stale transaction can no longer be mined and waste its fees.
+        //
         // Let's start by early returning 1)
         if payload == ongoing_op.payload {
+            if expired {
+                info!("Abort attempt to re-submit expired LeaderBlockCommit");
+                return None;
+            }
             info!("Abort attempt to re-submit identical LeaderBlockCommit");
             self.ongoing_block_commit = Some(ongoing_op);
             return None;
         }
 
-        // Let's proceed and early return 2) i)
-        let res = if ongoing_op.fees.estimated_amount_required() > ongoing_op.sum_utxos() {
+        // Let's proceed and early return 2) i), which is also where an expired operation goes
+        // when it cannot be replaced by fee any more
+        let res = if ongoing_op.fees.estimated_amount_required() > ongoing_op.sum_utxos()
+            || max_rbf_reached
+        {
             // Try to build and submit op, excluding UTXOs currently used
             info!("Attempt to submit another leader_block_commit, despite an ongoing (outdated) commit");
             self.send_block_commit_operation(
@@ -1588,7 +1910,7 @@ impl BitcoinRegtestController {
             )
         };
 
-        if res.is_none() {
+        if res.is_none() && !expired {
             self.ongoing_block_commit = Some(ongoing_op);
         }
 
@@ -1863,16 +2185,20 @@ impl BitcoinRegtestController {
     }
 
     /// wait until the ChainsCoordinator has processed sortitions up to
-    /// height_to_wait
+    /// height_to_wait, or until `burnchain.sortition_wait_timeout_ms` elapses (if set)
     pub fn wait_for_sortitions(
         &self,
         coord_comms: CoordinatorChannels,
         height_to_wait: u64,
     ) -> Result<BurnchainTip, BurnchainControllerError> {
+        let timeout_ms = self.config.burnchain.sortition_wait_timeout_ms;
+        let started_at = self.clock.now();
         let mut debug_ctr = 0;
         loop {
             let canonical_sortition_tip =
                 SortitionDB::get_canonical_burn_chain_tip(self.sortdb_ref().conn()).unwrap();
+            // keep the sortition height current while the chains coordinator catches up
+            set_sortition_height(canonical_sortition_tip.block_height);
 
             if debug_ctr % 10 == 0 {
                 debug!(
@@ -1891,7 +2217,7 @@ impl BitcoinRegtestController {
 
                 return Ok(BurnchainTip {
                     block_snapshot: canonical_sortition_tip,
-                    received_at: Instant::now(),
+                    received_at: self.clock.now(),
                     state_transition,
                 });
             }
@@ -1900,12 +2226,24 @@ impl BitcoinRegtestController {
                 return Err(BurnchainControllerError::CoordinatorClosed);
             }
 
+            if let Some(timeout_ms) = timeout_ms {
+                if self.clock.elapsed_since(started_at) >= Duration::from_millis(timeout_ms) {
+                    warn!(
+                        "Timed out waiting for sortitions";
+                        "height_to_wait" => height_to_wait,
+                        "sortition_height" => canonical_sortition_tip.block_height,
+                        "timeout_ms" => timeout_ms
+                    );
+                    return Err(BurnchainControllerError::SortitionTimeout(height_to_wait));
+                }
+            }
+
             // help the chains coordinator along
             coord_comms.announce_new_burn_block();
             coord_comms.announce_new_stacks_block();
 
             // yield some time
-            sleep_ms(1000);
+            self.clock.sleep_ms(1000);
         }
     }
 
@@ -1991,6 +2329,7 @@ impl BitcoinRegtestController {
         op_signer: &mut BurnchainOpSigner,
         attempt: u64,
     ) -> Option<SerializedTx> {
+        let opcode = operation.opcode();
         let transaction = match operation {
             BlockstackOperationType::LeaderBlockCommit(payload) => {
                 self.build_leader_block_commit_tx(epoch_id, payload, op_signer, attempt)
@@ -2015,7 +2354,301 @@ impl BitcoinRegtestController {
             }
         };
 
-        transaction.map(|tx| SerializedTx::new(tx))
+        let serialized_tx = SerializedTx::new(transaction?);
+        self.audit_signed_operation(&opcode, &serialized_tx);
+        Some(serialized_tx)
+    }
+
+    /// Publish the burnchain blocks up to `tip` that the block stream has not seen yet (or
+    /// that reorged since), if the block stream is running.  On the first call, the stream's
+    /// history is backfilled so that subscribers can resume from before this node started.
+    fn publish_burn_blocks(&self, tip: &BlockSnapshot) {
+        let Some(stream) = self.block_stream.as_ref() else {
+            return;
+        };
+        let Some(sortdb) = self.db.as_ref() else {
+            return;
+        };
+        let first_block_height = self.get_burnchain().first_block_height;
+        let ic = sortdb.index_conn();
+        let ancestor = |height: u64| {
+            SortitionDB::get_ancestor_snapshot(&ic, height, &tip.sortition_id)
+                .ok()
+                .flatten()
+        };
+
+        let history_start = tip
+            .block_height
+            .saturating_sub(stream.history_len() as u64 - 1)
+            .max(first_block_height);
+        let mut start_height = history_start;
+        if let Some((last_height, _)) = stream.last_published() {
+            // find the highest block the stream and the canonical fork agree on
+            let mut height = cmp::min(last_height, tip.block_height);
+            while let Some(published_hash) = stream.published_hash_at(height) {
+                if ancestor(height).map(|sn| sn.burn_header_hash) == Some(published_hash) {
+                    start_height = cmp::max(height + 1, history_start);
+                    break;
+                }
+                if height == 0 {
+                    break;
+                }
+                height -= 1;
+            }
+        }
+
+        for height in start_height..=tip.block_height {
+            let Some(snapshot) = ancestor(height) else {
+                warn!("Burn block stream: no snapshot for canonical block"; "height" => height);
+                return;
+            };
+            let accepted_ops = match sortdb.get_sortition_result(&snapshot.sortition_id) {
+                Ok(Some((_, state_transition))) => state_transition.accepted_ops,
+                Ok(None) => vec![],
+                Err(e) => {
+                    warn!("Burn block stream: failed to load burnchain operations";
+                          "height" => height,
+                          "error" => ?e);
+                    return;
+                }
+            };
+            stream.publish(BurnBlockEvent::new(&snapshot, &accepted_ops));
+        }
+    }
+
+    /// Append a signed operation to the audit log, if one is configured.
+    /// Failing to write the log is logged but does not stop the operation from being sent.
+    fn audit_signed_operation(&mut self, opcode: &Opcodes, tx: &SerializedTx) {
+        let Some(path) = self.config.burnchain.op_audit_log_path.as_ref() else {
+            return;
+        };
+        if self.op_audit_log.is_none() {
+            let key = OpAuditLog::key_from_seed(&self.config.node.seed);
+            let opened = OpAuditLog::open(path, key);
+            if let Err(e) = opened.as_ref() {
+                error!("Failed to open burnchain op audit log; not recording signed operations";
+                       "path" => path,
+                       "error" => %e);
+            }
+            self.op_audit_log = Some(opened);
+        }
+        let audit_log = match self.op_audit_log.as_mut() {
+            Some(Ok(audit_log)) => audit_log,
+            Some(Err(e)) => {
+                warn!("Burnchain op audit log is unavailable; not recording signed operation";
+                      "txid" => %tx.txid,
+                      "error" => %e);
+                return;
+            }
+            None => return,
+        };
+        // operations are signed for inclusion in the block after the current tip (0 if the
+        // tip is not known yet)
+        let target_burn_height = self
+            .chain_tip
+            .as_ref()
+            .map(|tip| tip.block_snapshot.block_height + 1)
+            .unwrap_or(0);
+        if let Err(e) = audit_log.record(
+            &format!("{:?}", opcode),
+            &tx.txid,
+            &tx.bytes,
+            target_burn_height,
+        ) {
+            error!("Failed to record signed operation in audit log";
+                   "txid" => %tx.txid,
+                   "error" => %e);
+        }
+    }
+
+    /// Get the op confirmation tracker, opening it if needed.  If `create` is false, it is
+    /// only opened if operations have been submitted before.
+    fn op_confirmations_mut(&mut self, create: bool) -> Option<&mut OpConfirmationTracker> {
+        if self.op_confirmations.is_none() {
+            let path = op_confirmations_path(&self.config);
+            if !create && !Path::new(&path).exists() {
+                return None;
+            }
+            match OpConfirmationTracker::open(&path) {
+                Ok(tracker) => self.op_confirmations = Some(tracker),
+                Err(e) => {
+                    warn!("Failed to open burnchain op confirmation tracker";
+                          "path" => &path,
+                          "error" => ?e);
+                    return None;
+                }
+            }
+        }
+        self.op_confirmations.as_mut()
+    }
+
+    /// Record a submitted operation, so its confirmation latency can be measured.
+    /// Failing to do so is logged, but does not affect the operation.
+    fn track_submitted_operation(&mut self, opcode: Opcodes, txid: &Txid) {
+        let burn_height = self
+            .chain_tip
+            .as_ref()
+            .map(|tip| tip.block_snapshot.block_height)
+            .unwrap_or(0);
+        // block commits replaced by fee are no longer expected to be mined
+        let (replaces, expires_burn_height) = match (&opcode, self.ongoing_block_commit.as_ref()) {
+            (Opcodes::LeaderBlockCommit, Some(ongoing)) => {
+                (ongoing.txids.clone(), Some(ongoing.expires_at_burn_height))
+            }
+            _ => (
+                vec![],
+                Some(burn_height + self.config.burnchain.op_expiry_blocks),
+            ),
+        };
+        let op_type = format!("{:?}", opcode);
+        let Some(tracker) = self.op_confirmations_mut(true) else {
+            return;
+        };
+        if let Err(e) = tracker.record_submitted(
+            txid,
+            &op_type,
+            burn_height,
+            get_epoch_time_secs(),
+            &replaces,
+            expires_burn_height,
+        ) {
+            warn!("Failed to record submitted burnchain operation";
+                  "txid" => %txid,
+                  "error" => ?e);
+        }
+    }
+
+    /// Persist the intent to broadcast the signed operation `tx`, signed by the key `key_id`.
+    /// Returns false if the operation must not be broadcast: either the intent could not be
+    /// persisted, or an operation signed before the node restarted may still be mined in its
+    /// place.  Operations are only guarded once the burnchain tip is known, which it always is
+    /// for block commits.
+    fn record_op_intent(&mut self, opcode: &Opcodes, key_id: &Hash160, tx: &SerializedTx) -> bool {
+        let tip_height = match (self.chain_tip.as_ref(), self.burnchain_db.as_ref()) {
+            (Some(tip), _) => tip.block_snapshot.block_height,
+            (None, Some(burnchain_db)) => match burnchain_db.get_canonical_chain_tip() {
+                Ok(tip) => tip.block_height,
+                Err(e) => {
+                    warn!("Failed to load the burnchain tip, not recording op intent";
+                          "txid" => %tx.txid,
+                          "error" => ?e);
+                    return true;
+                }
+            },
+            (None, None) => {
+                debug!("Burnchain tip not known, not recording op intent"; "txid" => %tx.txid);
+                return true;
+            }
+        };
+        // operations are signed for inclusion in the block after the current tip
+        let target_burn_height = tip_height + 1;
+        let (replaces, expires_burn_height) = match (opcode, self.ongoing_block_commit.as_ref()) {
+            (Opcodes::LeaderBlockCommit, Some(ongoing)) => {
+                (ongoing.txids.clone(), ongoing.expires_at_burn_height)
+            }
+            _ => (vec![], tip_height + self.config.burnchain.op_expiry_blocks),
+        };
+        let intent = OpIntent {
+            txid: tx.txid,
+            op_type: format!("{:?}", opcode),
+            payload_digest: Sha256Sum::from_data(&tx.bytes),
+            key_id: *key_id,
+            target_burn_height,
+            expires_burn_height,
+            recorded_at: get_epoch_time_secs(),
+            run_id: current_run_id().to_string(),
+        };
+        let Some(tracker) = self.op_confirmations_mut(true) else {
+            error!("Refusing to submit burnchain operation: op confirmation tracker is not available";
+                   "txid" => %tx.txid);
+            return false;
+        };
+        match tracker.find_conflict(&intent.op_type, key_id, target_burn_height) {
+            Ok(None) => {}
+            Ok(Some(conflict)) => {
+                warn!("Refusing to submit burnchain operation: an operation signed before the node restarted may still be mined in its place";
+                      "txid" => %tx.txid,
+                      "op_type" => &intent.op_type,
+                      "target_burn_height" => target_burn_height,
+                      "unresolved_txid" => %conflict.txid);
+                return false;
+            }
+            Err(e) => {
+                error!("Refusing to submit burnchain operation: failed to check op intent log";
+                       "txid" => %tx.txid,
+                       "error" => ?e);
+                return false;
+            }
+        }
+        if let Err(e) = tracker.record_intent(&intent, tip_height, &replaces) {
+            error!("Refusing to submit burnchain operation: failed to record op intent";
+                   "txid" => %tx.txid,
+                   "error" => ?e);
+            return false;
+        }
+        true
+    }
+
+    /// Close the intent for an operation that could not be broadcast
+    fn abandon_op_intent(&mut self, txid: &Txid) {
+        let Some(tracker) = self.op_confirmations.as_mut() else {
+            return;
+        };
+        if let Err(e) = tracker.abandon(txid) {
+            warn!("Failed to close burnchain op intent"; "txid" => %txid, "error" => ?e);
+        }
+    }
+
+    /// Check whether any pending submitted operations have been mined as of `tip_height`, and
+    /// how the mined block commits fared in their sortitions.  The first time this is done, the
+    /// op intents left open from before the node restarted are logged for the operator to
+    /// review.
+    fn update_op_confirmations(&mut self, tip_height: u64) {
+        let first_pass = self.op_confirmations.is_none();
+        if self.op_confirmations_mut(false).is_none() {
+            return;
+        }
+        let (Some(tracker), Some(burnchain_db), Some(sortdb)) = (
+            self.op_confirmations.as_mut(),
+            self.burnchain_db.as_ref(),
+            self.db.as_ref(),
+        ) else {
+            return;
+        };
+        let indexer = &self.indexer;
+        if let Err(e) = tracker.update(tip_height, get_epoch_time_secs(), |txid| {
+            burnchain_db
+                .find_burnchain_op(indexer, txid)
+                .map(|op| (op.block_height(), op.burn_header_hash()))
+        }) {
+            warn!("Failed to update burnchain op confirmations"; "error" => ?e);
+        } else if first_pass {
+            match tracker.get_unresolved() {
+                Ok(unresolved) => {
+                    for intent in unresolved.iter() {
+                        warn!("Burnchain operation signed before the node restarted is not mined yet; no other operation of its type will be submitted from its key for its burn block until it is";
+                              "txid" => %intent.txid,
+                              "op_type" => &intent.op_type,
+                              "target_burn_height" => intent.target_burn_height,
+                              "expires_burn_height" => intent.expires_burn_height);
+                    }
+                }
+                Err(e) => warn!("Failed to load unresolved burnchain op intents"; "error" => ?e),
+            }
+        }
+
+        let sort_tip = match SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()) {
+            Ok(sort_tip) => sort_tip,
+            Err(e) => {
+                warn!("Failed to load the canonical sortition tip"; "error" => ?e);
+                return;
+            }
+        };
+        if let Err(e) = tracker.update_sortition_outcomes(|txid, burn_header_hash| {
+            find_sortition_outcome(sortdb, &sort_tip.sortition_id, txid, burn_header_hash)
+        }) {
+            warn!("Failed to update block commit sortition outcomes"; "error" => ?e);
+        }
     }
 
     #[cfg(test)]
@@ -2086,10 +2719,24 @@ impl BurnchainController for BitcoinRegtestController {
         self.indexer.get_stacks_epochs()
     }
 
+    fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    fn sync_span_mut(&mut self) -> Option<&mut SyncSpan> {
+        self.sync_span.as_mut()
+    }
+
     fn start(
         &mut self,
         target_block_height_opt: Option<u64>,
     ) -> Result<(BurnchainTip, u64), BurnchainControllerError> {
+        if self.config.burnchain.fast_header_sync {
+            if let Some(result) = self.start_headers_only(target_block_height_opt)? {
+                return Ok(result);
+            }
+        }
+
         // if no target block height is given, just fetch the first burnchain block.
         self.receive_blocks(
             false,
@@ -2133,8 +2780,19 @@ impl BurnchainController for BitcoinRegtestController {
         op_signer: &mut BurnchainOpSigner,
         attempt: u64,
     ) -> Option<Txid> {
+        let opcode = operation.opcode();
+        let key_id = Hash160::from_node_public_key(&op_signer.get_public_key());
         let transaction = self.make_operation_tx(epoch_id, operation, op_signer, attempt)?;
-        self.send_transaction(transaction)
+        let intent_txid = transaction.txid();
+        if !self.record_op_intent(&opcode, &key_id, &transaction) {
+            return None;
+        }
+        let Some(txid) = self.send_transaction(transaction) else {
+            self.abandon_op_intent(&intent_txid);
+            return None;
+        };
+        self.track_submitted_operation(opcode, &txid);
+        Some(txid)
     }
 
     #[cfg(test)]
@@ -2332,7 +2990,7 @@ pub enum RPCError {
 type RPCResult<T> = Result<T, RPCError>;
 
 impl BitcoinRPCRequest {
-    fn build_rpc_request(config: &Config, payload: &BitcoinRPCRequest) -> Request {
+    fn build_rpc_request(config: &Config, payload: &BitcoinRPCRequest) -> RPCResult<Request> {
         let url = {
             // some methods require a wallet ID
             let wallet_id = match payload.method.as_str() {
@@ -2342,21 +3000,24 @@ impl BitcoinRPCRequest {
             let url = config.burnchain.get_rpc_url(wallet_id);
             Url::parse(&url).unwrap_or_else(|_| panic!("Unable to parse {} as a URL", url))
         };
+        let auth = RpcAuth::from_config(&config.burnchain).map_err(RPCError::Network)?;
         debug!(
-            "BitcoinRPC builder '{}': {:?}:{:?}@{}",
-            &payload.method, &config.burnchain.username, &config.burnchain.password, &url
+            "BitcoinRPC builder '{}': auth={}@{}",
+            &payload.method,
+            auth.scheme(),
+            &url
         );
 
         let mut req = Request::new(Method::Post, url);
 
-        match (&config.burnchain.username, &config.burnchain.password) {
-            (Some(username), Some(password)) => {
-                let auth_token = format!("Basic {}", encode(format!("{}:{}", username, password)));
-                req.append_header("Authorization", auth_token);
-            }
-            (_, _) => {}
-        };
-        req
+        // read the credentials for every request, so that rotated secrets are picked up
+        let auth_header = auth.authorization_header().map_err(|e| {
+            RPCError::Network(format!("Bitcoin RPC: failed to load credentials - {}", e))
+        })?;
+        if let Some(auth_header) = auth_header {
+            req.append_header("Authorization", auth_header);
+        }
+        Ok(req)
     }
 
     #[cfg(test)]
@@ -2666,7 +3327,7 @@ impl BitcoinRPCRequest {
     }
 
     fn send(config: &Config, payload: BitcoinRPCRequest) -> RPCResult<serde_json::Value> {
-        let mut request = BitcoinRPCRequest::build_rpc_request(&config, &payload);
+        let mut request = BitcoinRPCRequest::build_rpc_request(&config, &payload)?;
 
         let body = match serde_json::to_vec(&json!(payload)) {
             Ok(body) => body,
@@ -2740,7 +3401,10 @@ mod tests {
     use std::fs::File;
     use std::io::Write;
 
+    use stacks::chainstate::coordinator::comm::CoordinatorCommunication;
+
     use super::*;
+    use crate::burnchains::clock::ManualClock;
     use crate::config::DEFAULT_SATS_PER_VB;
 
     #[test]
@@ -2760,4 +3424,85 @@ mod tests {
 
         assert_eq!(get_satoshis_per_byte(&config), 51);
     }
+
+    #[test]
+    fn test_wait_for_sortitions_timeout() {
+        let mut config = crate::tests::new_test_conf();
+        config.burnchain.sortition_wait_timeout_ms = Some(10_000);
+
+        let clock = Arc::new(ManualClock::new());
+        let mut controller = BitcoinRegtestController::new_dummy(config);
+        controller.set_clock(clock.clone());
+        controller
+            .get_burnchain()
+            .connect_db(
+                true,
+                BurnchainHeaderHash::zero(),
+                0,
+                controller.get_stacks_epochs(),
+            )
+            .unwrap();
+        controller.sortdb_mut();
+
+        let (_coord_receivers, coord_channels) = CoordinatorCommunication::instantiate();
+        match controller.wait_for_sortitions(coord_channels.clone(), 1_000) {
+            Err(BurnchainControllerError::SortitionTimeout(1_000)) => {}
+            res => panic!("Expected a sortition timeout, got {:?}", res),
+        }
+        // the wait was paced by the injected clock, not the wall clock
+        assert_eq!(clock.elapsed(), Duration::from_secs(10));
+
+        // heights that are already processed need no waiting
+        let tip = controller.wait_for_sortitions(coord_channels, 0).unwrap();
+        assert_eq!(tip.received_at, clock.now());
+        assert_eq!(clock.elapsed(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_sync_follows_tip_during_backfill() {
+        let config = crate::tests::new_test_conf();
+        let mut controller = BitcoinRegtestController::new_dummy(config);
+        controller
+            .get_burnchain()
+            .connect_db(
+                true,
+                BurnchainHeaderHash::zero(),
+                0,
+                controller.get_stacks_epochs(),
+            )
+            .unwrap();
+        controller.sortdb_mut();
+        let (_coord_receivers, coord_channels) = CoordinatorCommunication::instantiate();
+        controller.use_coordinator = Some(coord_channels);
+        controller.block_stream = Some(BurnBlockStream::new(16));
+
+        // a backfill whose chunks are released by the test
+        let (chunk_send, chunk_recv) = std::sync::mpsc::channel::<u64>();
+        controller.backfill.begin(0, 10);
+        controller.backfill_task =
+            Some(BackfillTask::spawn(0, 10, move |_| chunk_recv.recv().ok()).unwrap());
+
+        chunk_send.send(4).unwrap();
+        let (tip, headers_height) = controller.sync(None).unwrap();
+        assert_eq!(headers_height, 10);
+        assert_eq!(controller.backfill.progress().unwrap().processed_height, 4);
+        // the task processes the blocks, but the tip is still followed
+        assert_eq!(
+            controller.get_chain_tip().block_snapshot,
+            tip.block_snapshot
+        );
+        assert_eq!(
+            controller.block_stream.as_ref().unwrap().last_published(),
+            Some((
+                tip.block_snapshot.block_height,
+                tip.block_snapshot.burn_header_hash
+            ))
+        );
+
+        // the task finishes once it reaches the headers
+        chunk_send.send(10).unwrap();
+        let task = controller.backfill_task.as_mut().unwrap();
+        while task.wait(BACKFILL_POLL_INTERVAL, &mut controller.backfill) {}
+        assert!(controller.backfill.progress().is_none());
+    }
 }
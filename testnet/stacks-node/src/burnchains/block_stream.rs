@@ -0,0 +1,436 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Streams the burnchain blocks processed by the burnchain controller to external subscribers
+//! over WebSocket, so that monitoring and bridge services can follow the burnchain as this node
+//! sees it without indexing it themselves.
+//!
+//! Subscribers connect to `ws://<burnchain.block_stream_bind>/`, optionally with
+//! `?from_height=<height>` to first replay the retained blocks at or above that height.  Each
+//! message is a JSON-encoded `BurnBlockEvent`.  On a burnchain reorg, the blocks of the new fork
+//! are re-sent from the fork point, so a subscriber sees an event whose height is not above
+//! the previous one; its `parent_burn_block_hash` tells where it attaches.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{io, thread};
+
+use lazy_static::lazy_static;
+use stacks::burnchains::Txid;
+use stacks::chainstate::burn::operations::BlockstackOperationType;
+use stacks::chainstate::burn::BlockSnapshot;
+use stacks::types::chainstate::{BurnchainHeaderHash, ConsensusHash};
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::Message;
+
+/// How many events a subscriber may fall behind by before it is disconnected.  A disconnected
+/// subscriber can reconnect and resume from the last height it processed.
+const SUBSCRIBER_BACKLOG: usize = 256;
+
+/// How often an idle subscriber connection is pinged, to notice that it went away
+const SUBSCRIBER_PING_INTERVAL: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    /// Streams by bind address.  The burnchain controller is re-created when the run loop
+    /// changes (e.g. at the Nakamoto transition), but the server keeps running.
+    static ref BLOCK_STREAMS: Mutex<HashMap<String, BurnBlockStream>> = Mutex::new(HashMap::new());
+}
+
+/// A burnchain block that was processed into a sortition by this node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BurnBlockEvent {
+    pub burn_block_height: u64,
+    pub burn_block_hash: BurnchainHeaderHash,
+    pub parent_burn_block_hash: BurnchainHeaderHash,
+    pub burn_block_timestamp: u64,
+    pub consensus_hash: ConsensusHash,
+    /// Whether a block-commit won sortition in this block
+    pub sortition: bool,
+    pub winning_block_txid: Txid,
+    pub total_burn: u64,
+    /// The burnchain operations accepted in this block
+    pub accepted_ops: Vec<serde_json::Value>,
+}
+
+impl BurnBlockEvent {
+    pub fn new(snapshot: &BlockSnapshot, accepted_ops: &[BlockstackOperationType]) -> Self {
+        Self {
+            burn_block_height: snapshot.block_height,
+            burn_block_hash: snapshot.burn_header_hash,
+            parent_burn_block_hash: snapshot.parent_burn_header_hash,
+            burn_block_timestamp: snapshot.burn_header_timestamp,
+            consensus_hash: snapshot.consensus_hash,
+            sortition: snapshot.sortition,
+            winning_block_txid: snapshot.winning_block_txid,
+            total_burn: snapshot.total_burn,
+            accepted_ops: accepted_ops
+                .iter()
+                .map(|op| op.blockstack_op_to_json())
+                .collect(),
+        }
+    }
+}
+
+struct StreamState {
+    /// The most recently published events, in ascending height order and without gaps
+    history: VecDeque<BurnBlockEvent>,
+    history_len: usize,
+    subscribers: Vec<SyncSender<BurnBlockEvent>>,
+}
+
+/// Fans burn block events out to subscribers, and retains the most recent ones so that
+/// subscribers can resume from a height.
+#[derive(Clone)]
+pub struct BurnBlockStream {
+    state: Arc<Mutex<StreamState>>,
+}
+
+impl BurnBlockStream {
+    /// Create a stream that retains the last `history_len` events
+    pub fn new(history_len: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(StreamState {
+                history: VecDeque::with_capacity(history_len),
+                history_len: history_len.max(1),
+                subscribers: vec![],
+            })),
+        }
+    }
+
+    /// Get the stream served at `bind`, starting its WebSocket server if it is not yet running
+    pub fn get_or_start(bind: &str, history_len: usize) -> io::Result<Self> {
+        let mut streams = BLOCK_STREAMS
+            .lock()
+            .expect("FATAL: block stream registry poisoned");
+        if let Some(stream) = streams.get(bind) {
+            return Ok(stream.clone());
+        }
+        let stream = Self::new(history_len);
+        let local_addr = stream.start_server(bind)?;
+        info!("Burn block stream: serving WebSocket subscribers"; "bind" => %local_addr);
+        streams.insert(bind.to_string(), stream.clone());
+        Ok(stream)
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, StreamState> {
+        self.state
+            .lock()
+            .expect("FATAL: block stream state poisoned")
+    }
+
+    pub fn history_len(&self) -> usize {
+        self.state().history_len
+    }
+
+    /// Height and hash of the most recently published block
+    pub fn last_published(&self) -> Option<(u64, BurnchainHeaderHash)> {
+        self.state()
+            .history
+            .back()
+            .map(|event| (event.burn_block_height, event.burn_block_hash))
+    }
+
+    /// Hash of the retained block published at `height`, if any
+    pub fn published_hash_at(&self, height: u64) -> Option<BurnchainHeaderHash> {
+        let state = self.state();
+        let oldest = state.history.front()?.burn_block_height;
+        let offset = usize::try_from(height.checked_sub(oldest)?).ok()?;
+        state.history.get(offset).map(|event| event.burn_block_hash)
+    }
+
+    /// Publish a block to all subscribers.  If a block at this height or above was published
+    /// before, those are dropped from the history (the burnchain reorged).
+    pub fn publish(&self, event: BurnBlockEvent) {
+        let mut state = self.state();
+        while state
+            .history
+            .back()
+            .map(|last| last.burn_block_height >= event.burn_block_height)
+            .unwrap_or(false)
+        {
+            state.history.pop_back();
+        }
+        // keep the history gapless
+        if state
+            .history
+            .back()
+            .map(|last| last.burn_block_height + 1 != event.burn_block_height)
+            .unwrap_or(false)
+        {
+            state.history.clear();
+        }
+        if state.history.len() >= state.history_len {
+            state.history.pop_front();
+        }
+        state.history.push_back(event.clone());
+
+        state
+            .subscribers
+            .retain(|subscriber| match subscriber.try_send(event.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    warn!("Burn block stream: dropping subscriber that fell behind";
+                          "burn_block_height" => event.burn_block_height);
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+
+    /// Subscribe to new events, first replaying the retained events at or above `from_height`.
+    /// Returns the oldest retained height instead if `from_height` is older than that.
+    pub fn subscribe(
+        &self,
+        from_height: Option<u64>,
+    ) -> Result<(Vec<BurnBlockEvent>, Receiver<BurnBlockEvent>), u64> {
+        let mut state = self.state();
+        let replay = match (from_height, state.history.front()) {
+            (Some(from_height), Some(oldest)) => {
+                if from_height < oldest.burn_block_height {
+                    return Err(oldest.burn_block_height);
+                }
+                state
+                    .history
+                    .iter()
+                    .filter(|event| event.burn_block_height >= from_height)
+                    .cloned()
+                    .collect()
+            }
+            _ => vec![],
+        };
+        let (sender, receiver) = sync_channel(SUBSCRIBER_BACKLOG);
+        state.subscribers.push(sender);
+        Ok((replay, receiver))
+    }
+
+    pub fn num_subscribers(&self) -> usize {
+        self.state().subscribers.len()
+    }
+
+    /// Accept WebSocket subscribers on `bind`.  Returns the address actually bound.
+    pub fn start_server(&self, bind: &str) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(bind)?;
+        let local_addr = listener.local_addr()?;
+        let stream = self.clone();
+        thread::Builder::new()
+            .name("burn-block-stream".into())
+            .spawn(move || {
+                for socket in listener.incoming() {
+                    let socket = match socket {
+                        Ok(socket) => socket,
+                        Err(e) => {
+                            warn!("Burn block stream: failed to accept connection: {:?}", &e);
+                            continue;
+                        }
+                    };
+                    let stream = stream.clone();
+                    if let Err(e) = thread::Builder::new()
+                        .name("burn-block-subscriber".into())
+                        .spawn(move || stream.serve_subscriber(socket))
+                    {
+                        warn!(
+                            "Burn block stream: failed to spawn subscriber thread: {:?}",
+                            &e
+                        );
+                    }
+                }
+            })?;
+        Ok(local_addr)
+    }
+
+    fn serve_subscriber(&self, socket: TcpStream) {
+        let peer = socket.peer_addr().ok();
+        let mut subscription = None;
+        let handshake = tungstenite::accept_hdr(socket, |request: &Request, response: Response| {
+            let from_height = match parse_from_height(request.uri().query()) {
+                Ok(from_height) => from_height,
+                Err(msg) => return Err(error_response(StatusCode::BAD_REQUEST, msg)),
+            };
+            match self.subscribe(from_height) {
+                Ok(sub) => {
+                    subscription = Some(sub);
+                    Ok(response)
+                }
+                Err(oldest) => Err(error_response(
+                    StatusCode::GONE,
+                    format!("Blocks below height {} are no longer retained", oldest),
+                )),
+            }
+        });
+        let mut websocket = match handshake {
+            Ok(websocket) => websocket,
+            Err(e) => {
+                debug!("Burn block stream: handshake failed"; "peer" => ?peer, "error" => %e);
+                return;
+            }
+        };
+        let Some((replay, receiver)) = subscription else {
+            return;
+        };
+        debug!("Burn block stream: subscriber connected";
+               "peer" => ?peer,
+               "replayed" => replay.len());
+
+        let send_event = |websocket: &mut tungstenite::WebSocket<TcpStream>,
+                          event: &BurnBlockEvent| {
+            let json =
+                serde_json::to_string(event).expect("FATAL: failed to serialize burn block event");
+            websocket.send(Message::Text(json))
+        };
+        for event in replay.iter() {
+            if let Err(e) = send_event(&mut websocket, event) {
+                debug!("Burn block stream: subscriber went away"; "peer" => ?peer, "error" => %e);
+                return;
+            }
+        }
+        loop {
+            let result = match receiver.recv_timeout(SUBSCRIBER_PING_INTERVAL) {
+                Ok(event) => send_event(&mut websocket, &event),
+                Err(RecvTimeoutError::Timeout) => websocket.send(Message::Ping(vec![])),
+                Err(RecvTimeoutError::Disconnected) => {
+                    // fell behind
+                    let _ = websocket.close(None);
+                    let _ = websocket.flush();
+                    return;
+                }
+            };
+            if let Err(e) = result {
+                debug!("Burn block stream: subscriber went away"; "peer" => ?peer, "error" => %e);
+                return;
+            }
+        }
+    }
+}
+
+fn parse_from_height(query: Option<&str>) -> Result<Option<u64>, String> {
+    let Some(query) = query else {
+        return Ok(None);
+    };
+    for pair in query.split('&') {
+        let mut parts = pair.splitn(2, '=');
+        if parts.next() != Some("from_height") {
+            continue;
+        }
+        let value = parts.next().unwrap_or("");
+        return value
+            .parse::<u64>()
+            .map(Some)
+            .map_err(|_| format!("Invalid from_height '{}'", value));
+    }
+    Ok(None)
+}
+
+fn error_response(status: StatusCode, msg: String) -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some(msg));
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_event(height: u64, fork: u8) -> BurnBlockEvent {
+        BurnBlockEvent {
+            burn_block_height: height,
+            burn_block_hash: BurnchainHeaderHash([fork; 32]),
+            parent_burn_block_hash: BurnchainHeaderHash([0; 32]),
+            burn_block_timestamp: height,
+            consensus_hash: ConsensusHash([fork; 20]),
+            sortition: true,
+            winning_block_txid: Txid([fork; 32]),
+            total_burn: height,
+            accepted_ops: vec![],
+        }
+    }
+
+    #[test]
+    fn test_block_stream_history_and_resume() {
+        let stream = BurnBlockStream::new(3);
+        for height in 1..=5 {
+            stream.publish(make_event(height, 1));
+        }
+        assert_eq!(
+            stream.last_published(),
+            Some((5, BurnchainHeaderHash([1; 32])))
+        );
+        assert_eq!(stream.published_hash_at(2), None);
+        assert_eq!(
+            stream.published_hash_at(3),
+            Some(BurnchainHeaderHash([1; 32]))
+        );
+
+        // only heights 3..=5 are retained
+        assert_eq!(stream.subscribe(Some(2)).unwrap_err(), 3);
+        let (replay, receiver) = stream.subscribe(Some(4)).unwrap();
+        assert_eq!(
+            replay
+                .iter()
+                .map(|event| event.burn_block_height)
+                .collect::<Vec<_>>(),
+            vec![4, 5]
+        );
+
+        // reorg at height 4
+        stream.publish(make_event(4, 2));
+        assert_eq!(receiver.try_recv().unwrap(), make_event(4, 2));
+        assert_eq!(stream.published_hash_at(5), None);
+        assert_eq!(
+            stream.last_published(),
+            Some((4, BurnchainHeaderHash([2; 32])))
+        );
+
+        // dropped subscribers are forgotten
+        drop(receiver);
+        stream.publish(make_event(5, 2));
+        assert_eq!(stream.num_subscribers(), 0);
+    }
+
+    #[test]
+    fn test_block_stream_websocket() {
+        let stream = BurnBlockStream::new(10);
+        for height in 1..=3 {
+            stream.publish(make_event(height, 1));
+        }
+        let addr = stream.start_server("127.0.0.1:0").unwrap();
+
+        let (mut websocket, _) =
+            tungstenite::connect(format!("ws://{}/?from_height=2", addr)).unwrap();
+        stream.publish(make_event(4, 1));
+        for height in 2..=4 {
+            let message = websocket.read().unwrap();
+            let event: BurnBlockEvent = serde_json::from_str(message.to_text().unwrap()).unwrap();
+            assert_eq!(event, make_event(height, 1));
+        }
+
+        assert!(tungstenite::connect(format!("ws://{}/?from_height=0", addr)).is_err());
+        assert!(tungstenite::connect(format!("ws://{}/?from_height=abc", addr)).is_err());
+    }
+
+    #[test]
+    fn test_parse_from_height() {
+        assert_eq!(parse_from_height(None), Ok(None));
+        assert_eq!(parse_from_height(Some("foo=bar")), Ok(None));
+        assert_eq!(
+            parse_from_height(Some("foo=bar&from_height=12")),
+            Ok(Some(12))
+        );
+        assert!(parse_from_height(Some("from_height=")).is_err());
+    }
+}
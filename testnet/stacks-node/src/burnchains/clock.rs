@@ -0,0 +1,146 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Time source for the burnchain controllers.
+//!
+//! The controllers stamp each `BurnchainTip` with the time it was received, and sleep between
+//! retries and while waiting for sortitions. Both go through a `Clock`, so that tests can swap
+//! in a `ManualClock` and advance time explicitly instead of depending on wall-clock timing.
+
+use std::sync::Arc;
+#[cfg(test)]
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::{fmt, thread};
+
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// The current time
+    fn now(&self) -> Instant;
+    /// Block for `duration`, as measured by this clock
+    fn sleep(&self, duration: Duration);
+
+    /// Block for `millis` milliseconds, as measured by this clock
+    fn sleep_ms(&self, millis: u64) {
+        self.sleep(Duration::from_millis(millis))
+    }
+
+    /// Time elapsed since `earlier`, as measured by this clock
+    fn elapsed_since(&self, earlier: Instant) -> Duration {
+        self.now().saturating_duration_since(earlier)
+    }
+}
+
+/// The real monotonic clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    pub fn shared() -> Arc<dyn Clock> {
+        Arc::new(SystemClock)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+/// A clock that only moves when told to.
+/// `sleep()` returns immediately, advancing the clock by the slept duration, so that a retry
+/// loop runs to completion without waiting while still observing the time it would have taken.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct ManualClock {
+    start: Instant,
+    offset: Mutex<Duration>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            offset: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Move the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        let mut offset = self.offset.lock().expect("FATAL: clock lock poisoned");
+        *offset += duration;
+    }
+
+    /// Total time this clock has been advanced by, including by `sleep()`
+    pub fn elapsed(&self) -> Duration {
+        *self.offset.lock().expect("FATAL: clock lock poisoned")
+    }
+}
+
+#[cfg(test)]
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock_only_moves_when_told() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.elapsed_since(start), Duration::from_secs(5));
+
+        clock.sleep_ms(1500);
+        assert_eq!(clock.now(), start + Duration::from_millis(6500));
+        assert_eq!(clock.elapsed(), Duration::from_millis(6500));
+
+        // instants from the future saturate
+        assert_eq!(
+            clock.elapsed_since(start + Duration::from_secs(60)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn shared_manual_clock() {
+        let manual = Arc::new(ManualClock::new());
+        let clock: Arc<dyn Clock> = manual.clone();
+        let start = clock.now();
+        manual.advance(Duration::from_secs(1));
+        assert_eq!(clock.elapsed_since(start), Duration::from_secs(1));
+    }
+}
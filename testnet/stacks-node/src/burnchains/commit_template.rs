@@ -0,0 +1,349 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Assembles block-commit operations from what a miner wants to do (which tenure to build on,
+//! how much to spend, who the PoX recipients are), checking the intent against the current
+//! sortition state first.  The result can be handed straight to
+//! `BurnchainController::submit_operation()`, so that alternative miner implementations don't
+//! need to re-derive the commit's parent pointers, sunset burn, PoX outputs and modulus.
+
+use std::fmt;
+
+use stacks::burnchains::{Burnchain, BurnchainSigner, Txid};
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::burn::operations::leader_block_commit::{
+    RewardSetInfo, BURN_BLOCK_MINED_AT_MODULUS,
+};
+use stacks::chainstate::burn::operations::{BlockstackOperationType, LeaderBlockCommitOp};
+use stacks::chainstate::burn::{BlockSnapshot, ConsensusHash};
+use stacks::chainstate::stacks::address::PoxAddress;
+use stacks::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, STACKS_EPOCH_3_0_MARKER};
+use stacks::util_lib::db::Error as DBError;
+use stacks_common::types::chainstate::{BlockHeaderHash, BurnchainHeaderHash, VRFSeed};
+use stacks_common::types::StacksEpochId;
+
+use crate::run_loop::RegisteredKey;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The intent does not spend anything
+    ZeroBurnFee,
+    /// No epoch is defined for the burn block the commit would land in
+    NoEpoch(u64),
+    /// The parent sortition is not known
+    UnknownParent(ConsensusHash),
+    /// The parent sortition is not on the canonical burnchain fork
+    ParentNotCanonical(ConsensusHash),
+    /// The parent sortition did not select a winning block-commit
+    ParentHasNoWinner(ConsensusHash),
+    /// The VRF key is not registered on the canonical burnchain fork
+    UnknownKey { block_height: u64, vtxindex: u32 },
+    /// The sunset burn would consume the whole burn fee
+    BurnFeeBelowSunset { burn_fee_cap: u64, sunset_burn: u64 },
+    /// A burn height or vtxindex does not fit in a block-commit field
+    OutOfRange(&'static str),
+    /// The sortition DB could not be queried
+    SortitionDB(DBError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ZeroBurnFee => write!(f, "Burn fee is zero"),
+            Error::NoEpoch(height) => write!(f, "No epoch defined for burn height {}", height),
+            Error::UnknownParent(ch) => write!(f, "No sortition with consensus hash {}", ch),
+            Error::ParentNotCanonical(ch) => {
+                write!(f, "Sortition {} is not on the canonical burnchain fork", ch)
+            }
+            Error::ParentHasNoWinner(ch) => {
+                write!(f, "Sortition {} has no winning block-commit", ch)
+            }
+            Error::UnknownKey {
+                block_height,
+                vtxindex,
+            } => write!(
+                f,
+                "No VRF key registered at burn height {}, vtxindex {}",
+                block_height, vtxindex
+            ),
+            Error::BurnFeeBelowSunset {
+                burn_fee_cap,
+                sunset_burn,
+            } => write!(
+                f,
+                "Burn fee {} does not cover the sunset burn {}",
+                burn_fee_cap, sunset_burn
+            ),
+            Error::OutOfRange(field) => write!(f, "Value out of range for {}", field),
+            Error::SortitionDB(ref e) => write!(f, "Sortition DB error: {:?}", e),
+        }
+    }
+}
+
+impl From<DBError> for Error {
+    fn from(e: DBError) -> Self {
+        Error::SortitionDB(e)
+    }
+}
+
+/// What a miner wants its next block-commit to say
+#[derive(Debug)]
+pub struct BlockCommitIntent {
+    /// Consensus hash of the sortition whose winning block-commit this commit builds on.
+    /// Use `FIRST_BURNCHAIN_CONSENSUS_HASH` to build on the genesis block.
+    pub parent_consensus_hash: ConsensusHash,
+    /// The block header hash to commit to (the parent block ID, once in Nakamoto)
+    pub block_header_hash: BlockHeaderHash,
+    /// Total amount of BTC to spend, including any sunset burn
+    pub burn_fee_cap: u64,
+    /// The PoX reward recipients for the next burn block, if PoX is active
+    pub recipients: Option<RewardSetInfo>,
+    /// The miner's registered VRF key
+    pub key: RegisteredKey,
+    /// The VRF seed to commit to
+    pub new_seed: VRFSeed,
+    /// Who is sending the commit
+    pub apparent_sender: BurnchainSigner,
+}
+
+/// A block-commit assembled from a `BlockCommitIntent`, along with the sortition state it was
+/// validated against
+#[derive(Debug, Clone)]
+pub struct BlockCommitTemplate {
+    /// The canonical sortition tip the commit was built against
+    pub sort_tip: BlockSnapshot,
+    /// The epoch of the burn block the commit is meant to land in
+    pub epoch_id: StacksEpochId,
+    pub op: LeaderBlockCommitOp,
+}
+
+impl BlockCommitTemplate {
+    /// The operation to hand to `BurnchainController::submit_operation()`
+    pub fn into_operation(self) -> BlockstackOperationType {
+        BlockstackOperationType::LeaderBlockCommit(self.op)
+    }
+}
+
+/// Builds block-commits against a sortition DB
+pub struct BlockCommitTemplateBuilder<'a> {
+    sortdb: &'a SortitionDB,
+    burnchain: &'a Burnchain,
+    mainnet: bool,
+}
+
+impl<'a> BlockCommitTemplateBuilder<'a> {
+    pub fn new(
+        sortdb: &'a SortitionDB,
+        burnchain: &'a Burnchain,
+        mainnet: bool,
+    ) -> BlockCommitTemplateBuilder<'a> {
+        BlockCommitTemplateBuilder {
+            sortdb,
+            burnchain,
+            mainnet,
+        }
+    }
+
+    /// Assemble a block-commit for the next burn block from `intent`.
+    /// Fails if the intent is not valid for the current canonical sortition tip: the parent
+    /// sortition must be on the canonical fork and have a winner, the VRF key must be
+    /// registered, and the burn fee must cover any sunset burn.
+    pub fn build(&self, intent: BlockCommitIntent) -> Result<BlockCommitTemplate, Error> {
+        if intent.burn_fee_cap == 0 {
+            return Err(Error::ZeroBurnFee);
+        }
+
+        let sort_tip = SortitionDB::get_canonical_burn_chain_tip(self.sortdb.conn())?;
+        let target_height = sort_tip.block_height + 1;
+        let epoch_id = SortitionDB::get_stacks_epoch(self.sortdb.conn(), target_height)?
+            .ok_or(Error::NoEpoch(target_height))?
+            .epoch_id;
+
+        let (parent_block_ptr, parent_vtxindex) = self.find_parent(&sort_tip, &intent)?;
+
+        let key = &intent.key;
+        let tip_handle = self.sortdb.index_handle(&sort_tip.sortition_id);
+        let registered = tip_handle
+            .get_leader_key_at(key.block_height, key.op_vtxindex)?
+            .map(|key_op| key_op.public_key == key.vrf_public_key)
+            .unwrap_or(false);
+        if !registered {
+            return Err(Error::UnknownKey {
+                block_height: key.block_height,
+                vtxindex: key.op_vtxindex,
+            });
+        }
+
+        let sunset_burn =
+            self.burnchain
+                .expected_sunset_burn(target_height, intent.burn_fee_cap, epoch_id);
+        if sunset_burn >= intent.burn_fee_cap {
+            return Err(Error::BurnFeeBelowSunset {
+                burn_fee_cap: intent.burn_fee_cap,
+                sunset_burn,
+            });
+        }
+
+        let commit_outs = commit_outs(
+            self.burnchain,
+            sort_tip.block_height,
+            epoch_id,
+            intent.recipients,
+            self.mainnet,
+        );
+
+        // target the current burnchain tip with our modulus
+        let burn_parent_modulus = u8::try_from(sort_tip.block_height % BURN_BLOCK_MINED_AT_MODULUS)
+            .map_err(|_| Error::OutOfRange("burn_parent_modulus"))?;
+
+        let op = LeaderBlockCommitOp {
+            sunset_burn,
+            block_header_hash: intent.block_header_hash,
+            burn_fee: intent.burn_fee_cap - sunset_burn,
+            input: (Txid([0; 32]), 0),
+            apparent_sender: intent.apparent_sender,
+            key_block_ptr: u32::try_from(key.block_height)
+                .map_err(|_| Error::OutOfRange("key_block_ptr"))?,
+            key_vtxindex: u16::try_from(key.op_vtxindex)
+                .map_err(|_| Error::OutOfRange("key_vtxindex"))?,
+            memo: vec![STACKS_EPOCH_3_0_MARKER],
+            new_seed: intent.new_seed,
+            parent_block_ptr,
+            parent_vtxindex,
+            vtxindex: 0,
+            txid: Txid([0u8; 32]),
+            block_height: 0,
+            burn_header_hash: BurnchainHeaderHash::zero(),
+            burn_parent_modulus,
+            commit_outs,
+        };
+
+        debug!("Assembled block-commit from template";
+               "parent_consensus_hash" => %intent.parent_consensus_hash,
+               "block_header_hash" => %op.block_header_hash,
+               "burn_fee" => op.burn_fee,
+               "sunset_burn" => op.sunset_burn,
+               "commit_outs" => ?op.commit_outs,
+               "target_height" => target_height);
+
+        Ok(BlockCommitTemplate {
+            sort_tip,
+            epoch_id,
+            op,
+        })
+    }
+
+    /// Find the block-commit pointers (burn height, vtxindex) of the parent sortition's winner
+    fn find_parent(
+        &self,
+        sort_tip: &BlockSnapshot,
+        intent: &BlockCommitIntent,
+    ) -> Result<(u32, u16), Error> {
+        let parent_ch = &intent.parent_consensus_hash;
+        if parent_ch == &FIRST_BURNCHAIN_CONSENSUS_HASH {
+            return Ok((0, 0));
+        }
+
+        let parent_sortition =
+            SortitionDB::get_block_snapshot_consensus(self.sortdb.conn(), parent_ch)?
+                .ok_or_else(|| Error::UnknownParent(*parent_ch))?;
+
+        let canonical = if parent_sortition.sortition_id == sort_tip.sortition_id {
+            true
+        } else {
+            self.sortdb
+                .index_handle(&sort_tip.sortition_id)
+                .get_block_snapshot_by_height(parent_sortition.block_height)?
+                .map(|sn| sn.sortition_id == parent_sortition.sortition_id)
+                .unwrap_or(false)
+        };
+        if !canonical {
+            return Err(Error::ParentNotCanonical(*parent_ch));
+        }
+
+        if !parent_sortition.sortition {
+            return Err(Error::ParentHasNoWinner(*parent_ch));
+        }
+        let parent_winning_tx = SortitionDB::get_block_commit(
+            self.sortdb.conn(),
+            &parent_sortition.winning_block_txid,
+            &parent_sortition.sortition_id,
+        )?
+        .ok_or_else(|| Error::ParentHasNoWinner(*parent_ch))?;
+
+        let parent_block_ptr = u32::try_from(parent_sortition.block_height)
+            .map_err(|_| Error::OutOfRange("parent_block_ptr"))?;
+        let parent_vtxindex = u16::try_from(parent_winning_tx.vtxindex)
+            .map_err(|_| Error::OutOfRange("parent_vtxindex"))?;
+        Ok((parent_block_ptr, parent_vtxindex))
+    }
+}
+
+/// The PoX outputs of a block-commit sent while the canonical sortition tip is at `tip_height`.
+/// Once PoX has sunset, or during a prepare phase, commits only burn.
+pub fn commit_outs(
+    burnchain: &Burnchain,
+    tip_height: u64,
+    epoch_id: StacksEpochId,
+    recipients: Option<RewardSetInfo>,
+    mainnet: bool,
+) -> Vec<PoxAddress> {
+    if !burnchain
+        .pox_constants
+        .is_after_pox_sunset_end(tip_height, epoch_id)
+        && !burnchain.is_in_prepare_phase(tip_height + 1)
+    {
+        RewardSetInfo::into_commit_outs(recipients, mainnet)
+    } else {
+        vec![PoxAddress::standard_burn_address(mainnet)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stacks::chainstate::burn::operations::leader_block_commit::OUTPUTS_PER_COMMIT;
+
+    use super::*;
+
+    #[test]
+    fn commit_outs_burn_in_prepare_phase() {
+        let burnchain = Burnchain::regtest("/tmp/commit_template_prepare_phase");
+        let reward_cycle_length = burnchain.pox_constants.reward_cycle_length as u64;
+        let prepare_length = burnchain.pox_constants.prepare_length as u64;
+        let cycle_start = burnchain.first_block_height + 2 * reward_cycle_length;
+
+        // in the reward phase, commits pay (or burn to) every PoX output
+        let outs = commit_outs(
+            &burnchain,
+            cycle_start + 1,
+            StacksEpochId::Epoch30,
+            None,
+            false,
+        );
+        assert_eq!(outs.len(), OUTPUTS_PER_COMMIT);
+        assert!(outs.iter().all(|out| out.is_burn()));
+
+        // in the prepare phase, commits only burn
+        let prepare_start = cycle_start + reward_cycle_length - prepare_length;
+        let outs = commit_outs(
+            &burnchain,
+            prepare_start,
+            StacksEpochId::Epoch30,
+            None,
+            false,
+        );
+        assert_eq!(outs, vec![PoxAddress::standard_burn_address(false)]);
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Burn block timestamps, as the miner should schedule by them.
+//!
+//! A burn block header's timestamp is whatever its miner put there: it can be earlier than its
+//! parent's, or hours in the future.  Bitcoin only requires it to be later than the median of
+//! the previous 11 headers' timestamps (the median time past, or MTP), and the MTP never goes
+//! down from one block to the next.  `BurnHeaderTime` pairs a burn block's timestamp with its
+//! MTP, and checks the timestamp against the MTP before it, so that callers scheduling work by
+//! the burnchain's clock all see the same validated view of it.
+
+use stacks::burnchains::Error as burnchain_error;
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::burn::BlockSnapshot;
+use stacks_common::types::chainstate::BurnchainHeaderHash;
+
+/// Number of headers whose timestamps the median time past is taken over
+pub const MEDIAN_TIME_PAST_SPAN: usize = 11;
+
+/// The median of the first `MEDIAN_TIME_PAST_SPAN` timestamps of `timestamps`, which are
+/// ordered newest first.  None if there are none.
+pub fn median_time_past(timestamps: &[u64]) -> Option<u64> {
+    let mut window: Vec<u64> = timestamps
+        .iter()
+        .take(MEDIAN_TIME_PAST_SPAN)
+        .copied()
+        .collect();
+    if window.is_empty() {
+        return None;
+    }
+    window.sort_unstable();
+    Some(window[window.len() / 2])
+}
+
+/// The timestamps of a burn block's header
+#[derive(Debug, Clone, PartialEq)]
+pub struct BurnHeaderTime {
+    pub block_height: u64,
+    pub burn_header_hash: BurnchainHeaderHash,
+    /// The timestamp in the block's header
+    pub header_timestamp: u64,
+    /// The median of the timestamps of the block and its 10 closest ancestors
+    pub median_time_past: u64,
+    /// Whether the header's timestamp is later than the median time past of its parent, as
+    /// Bitcoin requires.  Blocks without ancestors are taken as valid.
+    pub header_timestamp_valid: bool,
+}
+
+impl BurnHeaderTime {
+    /// Work out a block's header time from its header's timestamp followed by its ancestors'
+    /// timestamps, newest first.  Up to `MEDIAN_TIME_PAST_SPAN` ancestors are looked at.
+    pub fn from_timestamps(
+        block_height: u64,
+        burn_header_hash: BurnchainHeaderHash,
+        header_timestamp: u64,
+        ancestor_timestamps: &[u64],
+    ) -> BurnHeaderTime {
+        let mut timestamps = Vec::with_capacity(ancestor_timestamps.len() + 1);
+        timestamps.push(header_timestamp);
+        timestamps.extend_from_slice(ancestor_timestamps);
+
+        let header_timestamp_valid = match median_time_past(ancestor_timestamps) {
+            Some(parent_median_time_past) => header_timestamp > parent_median_time_past,
+            None => true,
+        };
+        BurnHeaderTime {
+            block_height,
+            burn_header_hash,
+            header_timestamp,
+            median_time_past: median_time_past(&timestamps).unwrap_or(header_timestamp),
+            header_timestamp_valid,
+        }
+    }
+
+    /// When the block was mined, as best as can be told: its header's timestamp, but never
+    /// earlier than its median time past
+    pub fn timestamp(&self) -> u64 {
+        self.header_timestamp.max(self.median_time_past)
+    }
+
+    /// How many seconds before `now_secs` the block was mined.  Blocks stamped in the future
+    /// are taken as just mined.
+    pub fn age_secs(&self, now_secs: u64) -> u64 {
+        now_secs.saturating_sub(self.timestamp())
+    }
+}
+
+/// Work out the header time of the burn block of `snapshot`, from its timestamp and those of
+/// its ancestors in `sortdb`
+pub fn header_time_at(
+    sortdb: &SortitionDB,
+    snapshot: &BlockSnapshot,
+) -> Result<BurnHeaderTime, burnchain_error> {
+    let mut ancestor_timestamps = Vec::with_capacity(MEDIAN_TIME_PAST_SPAN);
+    let mut cursor = snapshot.clone();
+    while ancestor_timestamps.len() < MEDIAN_TIME_PAST_SPAN && cursor.block_height > 0 {
+        let parent =
+            match SortitionDB::get_block_snapshot(sortdb.conn(), &cursor.parent_sortition_id)
+                .map_err(burnchain_error::DBError)?
+            {
+                Some(parent) => parent,
+                None => break,
+            };
+        ancestor_timestamps.push(parent.burn_header_timestamp);
+        cursor = parent;
+    }
+
+    let header_time = BurnHeaderTime::from_timestamps(
+        snapshot.block_height,
+        snapshot.burn_header_hash,
+        snapshot.burn_header_timestamp,
+        &ancestor_timestamps,
+    );
+    if !header_time.header_timestamp_valid {
+        warn!("Burn block header timestamp is not after its parent's median time past";
+              "block_height" => header_time.block_height,
+              "burn_header_hash" => %header_time.burn_header_hash,
+              "header_timestamp" => header_time.header_timestamp,
+              "median_time_past" => header_time.median_time_past);
+    }
+    Ok(header_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_median_time_past() {
+        assert_eq!(median_time_past(&[]), None);
+        assert_eq!(median_time_past(&[5]), Some(5));
+        assert_eq!(median_time_past(&[3, 1, 2]), Some(2));
+        // an even number of timestamps takes the later of the two in the middle
+        assert_eq!(median_time_past(&[4, 1, 3, 2]), Some(3));
+        // only the newest 11 timestamps count
+        let timestamps: Vec<u64> = (0..20).rev().map(|i| i * 10).collect();
+        assert_eq!(median_time_past(&timestamps), Some(140));
+    }
+
+    #[test]
+    fn test_burn_header_time() {
+        let hash = BurnchainHeaderHash([0x01; 32]);
+        let ancestors: Vec<u64> = (0..11).map(|i| 1_000 - i * 60).collect();
+
+        let header_time = BurnHeaderTime::from_timestamps(100, hash, 1_060, &ancestors);
+        assert!(header_time.header_timestamp_valid);
+        assert_eq!(header_time.median_time_past, 1_000 - 5 * 60 + 60);
+        assert_eq!(header_time.timestamp(), 1_060);
+        assert_eq!(header_time.age_secs(1_100), 40);
+        assert_eq!(header_time.age_secs(1_000), 0);
+
+        // a timestamp from before the median time past is clamped to it
+        let header_time = BurnHeaderTime::from_timestamps(100, hash, 500, &ancestors);
+        assert!(!header_time.header_timestamp_valid);
+        assert_eq!(header_time.timestamp(), header_time.median_time_past);
+
+        // the first block has nothing to be checked against
+        let header_time = BurnHeaderTime::from_timestamps(0, hash, 500, &[]);
+        assert!(header_time.header_timestamp_valid);
+        assert_eq!(header_time.median_time_past, 500);
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A minimal HTTP server for the burnchain's small JSON APIs (the mocknet admin API and the
+//! readiness probes).  It serves one request per connection, one connection at a time, and
+//! bounds everything it reads from the client: the length of the request line and of each
+//! header, the number of headers, the size of the body, and the time taken to send them.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+/// Longest request line or header line accepted, including its line ending
+pub const MAX_LINE_LEN: u64 = 8192;
+
+/// Most headers accepted in a request
+pub const MAX_HEADERS: usize = 64;
+
+/// How long a client may take to send its request
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A request the server read in full
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonRequest {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+/// Why a request could not be read
+#[derive(Debug)]
+enum ReadError {
+    /// The connection failed or timed out
+    Io(io::Error),
+    /// The request was malformed or too large.  Holds the status code to reply with.
+    Rejected(u16, &'static str),
+}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> Self {
+        ReadError::Io(e)
+    }
+}
+
+/// Serve requests on `bind` from a thread named `name`, passing each to `handler`, which
+/// returns the HTTP status code and the JSON response body.  Bodies longer than
+/// `max_body_len` are rejected.  Returns the address actually bound.
+pub fn start_json_server<F>(
+    name: &str,
+    bind: &str,
+    max_body_len: u64,
+    handler: F,
+) -> io::Result<SocketAddr>
+where
+    F: Fn(&JsonRequest) -> (u16, Value) + Send + 'static,
+{
+    let listener = TcpListener::bind(bind)?;
+    let local_addr = listener.local_addr()?;
+    let thread_name = name.to_string();
+    thread::Builder::new().name(name.into()).spawn(move || {
+        for socket in listener.incoming() {
+            let socket = match socket {
+                Ok(socket) => socket,
+                Err(e) => {
+                    warn!("{}: failed to accept connection: {:?}", &thread_name, &e);
+                    continue;
+                }
+            };
+            if let Err(e) = serve_request(socket, max_body_len, &handler) {
+                debug!("{}: failed to serve request", &thread_name; "error" => %e);
+            }
+        }
+    })?;
+    Ok(local_addr)
+}
+
+fn serve_request<F>(mut socket: TcpStream, max_body_len: u64, handler: &F) -> io::Result<()>
+where
+    F: Fn(&JsonRequest) -> (u16, Value),
+{
+    socket.set_read_timeout(Some(REQUEST_READ_TIMEOUT))?;
+    let mut reader = BufReader::new(socket.try_clone()?);
+    let (status, response) = match read_request(&mut reader, max_body_len) {
+        Ok(request) => handler(&request),
+        Err(ReadError::Rejected(status, msg)) => (status, json!({ "error": msg })),
+        Err(ReadError::Io(e)) => return Err(e),
+    };
+    write_response(&mut socket, status, &response)
+}
+
+/// Read a line of at most `MAX_LINE_LEN` bytes
+fn read_line<R: BufRead>(reader: &mut R, too_long: ReadError) -> Result<String, ReadError> {
+    let mut line = vec![];
+    reader.take(MAX_LINE_LEN).read_until(b'\n', &mut line)?;
+    if line.last() != Some(&b'\n') && line.len() as u64 >= MAX_LINE_LEN {
+        return Err(too_long);
+    }
+    String::from_utf8(line).map_err(|_| ReadError::Rejected(400, "Bad request"))
+}
+
+fn read_request<R: BufRead>(reader: &mut R, max_body_len: u64) -> Result<JsonRequest, ReadError> {
+    let request_line = read_line(reader, ReadError::Rejected(414, "Request line too long"))?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Err(ReadError::Rejected(400, "Bad request")),
+    };
+
+    let mut content_length = 0;
+    let mut num_headers = 0;
+    loop {
+        let header = read_line(reader, ReadError::Rejected(431, "Header too long"))?;
+        if header.trim().is_empty() {
+            break;
+        }
+        num_headers += 1;
+        if num_headers > MAX_HEADERS {
+            return Err(ReadError::Rejected(431, "Too many headers"));
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| ReadError::Rejected(400, "Invalid Content-Length"))?;
+            }
+        }
+    }
+    if content_length > max_body_len {
+        return Err(ReadError::Rejected(413, "Request too large"));
+    }
+    let mut body = vec![];
+    reader.take(content_length).read_to_end(&mut body)?;
+    Ok(JsonRequest { method, path, body })
+}
+
+fn write_response(socket: &mut TcpStream, status: u16, body: &Value) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        414 => "URI Too Long",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Error",
+    };
+    let body = body.to_string();
+    write!(
+        socket,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    socket.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(request: &[u8]) -> Result<JsonRequest, u16> {
+        read_request(&mut BufReader::new(request), 16).map_err(|e| match e {
+            ReadError::Rejected(status, _) => status,
+            ReadError::Io(e) => panic!("Unexpected I/O error: {:?}", &e),
+        })
+    }
+
+    #[test]
+    fn test_read_request() {
+        assert_eq!(
+            read(b"POST /v1/mine HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\n{}"),
+            Ok(JsonRequest {
+                method: "POST".into(),
+                path: "/v1/mine".into(),
+                body: b"{}".to_vec(),
+            })
+        );
+        assert_eq!(read(b"GARBAGE\r\n\r\n"), Err(400));
+        assert_eq!(
+            read(b"POST / HTTP/1.1\r\nContent-Length: lots\r\n\r\n"),
+            Err(400)
+        );
+        assert_eq!(
+            read(b"POST / HTTP/1.1\r\nContent-Length: 17\r\n\r\n"),
+            Err(413)
+        );
+    }
+
+    #[test]
+    fn test_read_request_is_bounded() {
+        let long_path = "a".repeat(MAX_LINE_LEN as usize);
+        assert_eq!(
+            read(format!("GET /{} HTTP/1.1\r\n\r\n", long_path).as_bytes()),
+            Err(414)
+        );
+
+        let long_header = format!("GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n", long_path);
+        assert_eq!(read(long_header.as_bytes()), Err(431));
+
+        let many_headers = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X-Header: 1\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert_eq!(read(many_headers.as_bytes()), Err(431));
+
+        let enough_headers = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X-Header: 1\r\n".repeat(MAX_HEADERS)
+        );
+        assert!(read(enough_headers.as_bytes()).is_ok());
+    }
+}
@@ -0,0 +1,312 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A small HTTP admin API for the mocknet burnchain, so that devnet users and integration tests
+//! written in other languages can drive the simulated chain without recompiling the node.
+//!
+//! The API is served on `burnchain.mocknet_admin_bind`.  Requests and responses are JSON:
+//!
+//! * `GET /v1/status`: the mocknet's tip, and the requests not yet acted on.
+//! * `POST /v1/mine` with `{"blocks": N}`: mine N more blocks the next time the run loop asks
+//!   the burnchain for a block.  The injected operations go in the last of them.
+//! * `POST /v1/block_time` with `{"timestamp": T}`: stamp the next block with T, and each one
+//!   after it one second later than the previous.  `{"timestamp": null}` reverts to the clock.
+//! * `POST /v1/operations` with a JSON-encoded `BlockstackOperationType`: include the operation
+//!   in the next block.  Its block height and header hash are filled in when it is mined.
+//! * `POST /v1/fork` with `{"height": H}`: mine the next blocks on top of the block at height H
+//!   instead of the tip.  The new fork only becomes canonical once it outgrows the old one.
+//!
+//! The run loop asks for a block once per round, so requests take effect on the next round.
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+use stacks::chainstate::burn::operations::BlockstackOperationType;
+use stacks_common::types::chainstate::BurnchainHeaderHash;
+
+use super::json_server::start_json_server;
+
+/// Largest request body the admin API accepts
+const MAX_REQUEST_BODY_LEN: u64 = 1024 * 1024;
+
+/// The requests made through the admin API that the mocknet controller has yet to act on
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PendingAdminRequests {
+    /// Number of blocks to mine on the next round, besides the one it mines anyway
+    pub blocks_to_mine: u64,
+    /// Operations to include in the next block
+    pub operations: Vec<BlockstackOperationType>,
+    /// Height of the block to mine the next blocks on top of
+    pub fork_height: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct AdminState {
+    pending: PendingAdminRequests,
+    /// Timestamp of the next block, if not taken from the clock
+    next_block_time: Option<u64>,
+    /// Height and hash of the controller's chain tip, once it has started
+    tip: Option<(u64, BurnchainHeaderHash)>,
+}
+
+/// State shared between the mocknet controller and its admin API server
+#[derive(Debug, Clone, Default)]
+pub struct MocknetAdmin {
+    state: Arc<Mutex<AdminState>>,
+}
+
+impl MocknetAdmin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, AdminState> {
+        self.state
+            .lock()
+            .expect("FATAL: mocknet admin state poisoned")
+    }
+
+    /// Mine `num_blocks` more blocks on the next round
+    pub fn request_blocks(&self, num_blocks: u64) {
+        let mut state = self.state();
+        state.pending.blocks_to_mine = state.pending.blocks_to_mine.saturating_add(num_blocks);
+    }
+
+    /// Stamp the next block with `timestamp` (and the ones after it one second apart), or
+    /// with the clock's time if None
+    pub fn set_block_time(&self, timestamp: Option<u64>) {
+        self.state().next_block_time = timestamp;
+    }
+
+    /// Include `operation` in the next block
+    pub fn inject_operation(&self, operation: BlockstackOperationType) {
+        self.state().pending.operations.push(operation);
+    }
+
+    /// Mine the next blocks on top of the block at `height`, which must be below the tip
+    pub fn request_fork(&self, height: u64) -> Result<(), String> {
+        let mut state = self.state();
+        let tip_height = match state.tip {
+            Some((tip_height, _)) => tip_height,
+            None => return Err("The mocknet has not started yet".into()),
+        };
+        if height >= tip_height {
+            return Err(format!(
+                "Fork height {} is not below the tip height {}",
+                height, tip_height
+            ));
+        }
+        state.pending.fork_height = Some(height);
+        Ok(())
+    }
+
+    /// Take the requests the controller should act on this round
+    pub fn take_pending(&self) -> PendingAdminRequests {
+        std::mem::take(&mut self.state().pending)
+    }
+
+    /// The timestamp for the next block, if one was set.  Each call advances it by a second.
+    pub fn next_block_time(&self) -> Option<u64> {
+        let mut state = self.state();
+        let timestamp = state.next_block_time?;
+        state.next_block_time = Some(timestamp.saturating_add(1));
+        Some(timestamp)
+    }
+
+    /// Record the controller's new chain tip
+    pub fn set_tip(&self, height: u64, hash: BurnchainHeaderHash) {
+        self.state().tip = Some((height, hash));
+    }
+
+    fn status(&self) -> Value {
+        let state = self.state();
+        json!({
+            "tip_height": state.tip.as_ref().map(|(height, _)| *height),
+            "tip_hash": state.tip.as_ref().map(|(_, hash)| hash.to_hex()),
+            "next_block_time": state.next_block_time,
+            "pending_blocks": state.pending.blocks_to_mine,
+            "pending_operations": state.pending.operations.len(),
+            "pending_fork_height": state.pending.fork_height,
+        })
+    }
+
+    /// Handle an admin API request.  Returns the HTTP status code and the JSON response body.
+    fn handle(&self, method: &str, path: &str, body: &[u8]) -> (u16, Value) {
+        let parse_body = || -> Result<Value, (u16, Value)> {
+            serde_json::from_slice(body)
+                .map_err(|e| bad_request(format!("Invalid JSON body: {}", e)))
+        };
+        let result = match (method, path) {
+            ("GET", "/v1/status") => Ok(self.status()),
+            ("POST", "/v1/mine") => parse_body().and_then(|body| {
+                let num_blocks = body
+                    .get("blocks")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| bad_request("Expected {\"blocks\": <number>}".into()))?;
+                self.request_blocks(num_blocks);
+                Ok(self.status())
+            }),
+            ("POST", "/v1/block_time") => parse_body().and_then(|body| {
+                let timestamp = match body.get("timestamp") {
+                    Some(Value::Null) => None,
+                    Some(timestamp) => Some(timestamp.as_u64().ok_or_else(|| {
+                        bad_request("Expected {\"timestamp\": <number or null>}".into())
+                    })?),
+                    None => {
+                        return Err(bad_request(
+                            "Expected {\"timestamp\": <number or null>}".into(),
+                        ))
+                    }
+                };
+                self.set_block_time(timestamp);
+                Ok(self.status())
+            }),
+            ("POST", "/v1/operations") => serde_json::from_slice(body)
+                .map_err(|e| bad_request(format!("Invalid burnchain operation: {}", e)))
+                .map(|operation: BlockstackOperationType| {
+                    let txid = operation.txid();
+                    self.inject_operation(operation);
+                    json!({ "txid": txid })
+                }),
+            ("POST", "/v1/fork") => parse_body().and_then(|body| {
+                let height = body
+                    .get("height")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| bad_request("Expected {\"height\": <number>}".into()))?;
+                self.request_fork(height).map_err(bad_request)?;
+                Ok(self.status())
+            }),
+            (_, "/v1/status")
+            | (_, "/v1/mine")
+            | (_, "/v1/block_time")
+            | (_, "/v1/operations")
+            | (_, "/v1/fork") => Err((405, json!({ "error": "Method not allowed" }))),
+            _ => Err((404, json!({ "error": "Not found" }))),
+        };
+        match result {
+            Ok(response) => (200, response),
+            Err(error) => error,
+        }
+    }
+
+    /// Serve the admin API on `bind`.  Returns the address actually bound.
+    pub fn start_server(&self, bind: &str) -> io::Result<SocketAddr> {
+        let admin = self.clone();
+        start_json_server(
+            "mocknet-admin",
+            bind,
+            MAX_REQUEST_BODY_LEN,
+            move |request| {
+                let (status, response) =
+                    admin.handle(&request.method, &request.path, &request.body);
+                debug!("Mocknet admin: served request";
+                   "method" => %request.method,
+                   "path" => %request.path,
+                   "status" => status);
+                (status, response)
+            },
+        )
+    }
+}
+
+fn bad_request(msg: String) -> (u16, Value) {
+    (400, json!({ "error": msg }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use super::*;
+
+    #[test]
+    fn test_admin_requests() {
+        let admin = MocknetAdmin::new();
+        admin.set_tip(10, BurnchainHeaderHash([0x01; 32]));
+
+        let (status, _) = admin.handle("POST", "/v1/mine", br#"{"blocks": 3}"#);
+        assert_eq!(status, 200);
+        let (status, _) = admin.handle("POST", "/v1/block_time", br#"{"timestamp": 1000}"#);
+        assert_eq!(status, 200);
+        let (status, _) = admin.handle("POST", "/v1/fork", br#"{"height": 7}"#);
+        assert_eq!(status, 200);
+
+        let (status, response) = admin.handle("GET", "/v1/status", b"");
+        assert_eq!(status, 200);
+        assert_eq!(response["tip_height"], 10);
+        assert_eq!(response["pending_blocks"], 3);
+        assert_eq!(response["pending_fork_height"], 7);
+
+        assert_eq!(
+            admin.take_pending(),
+            PendingAdminRequests {
+                blocks_to_mine: 3,
+                operations: vec![],
+                fork_height: Some(7),
+            }
+        );
+        assert_eq!(admin.take_pending(), PendingAdminRequests::default());
+
+        assert_eq!(admin.next_block_time(), Some(1000));
+        assert_eq!(admin.next_block_time(), Some(1001));
+        admin.handle("POST", "/v1/block_time", br#"{"timestamp": null}"#);
+        assert_eq!(admin.next_block_time(), None);
+    }
+
+    #[test]
+    fn test_admin_rejects_bad_requests() {
+        let admin = MocknetAdmin::new();
+        admin.set_tip(10, BurnchainHeaderHash([0x01; 32]));
+
+        assert_eq!(admin.handle("POST", "/v1/mine", b"not json").0, 400);
+        assert_eq!(
+            admin.handle("POST", "/v1/mine", br#"{"blocks": -1}"#).0,
+            400
+        );
+        assert_eq!(
+            admin.handle("POST", "/v1/fork", br#"{"height": 10}"#).0,
+            400
+        );
+        assert_eq!(admin.handle("POST", "/v1/operations", br#"{}"#).0, 400);
+        assert_eq!(admin.handle("GET", "/v1/mine", b"").0, 405);
+        assert_eq!(admin.handle("GET", "/v2/info", b"").0, 404);
+        assert_eq!(admin.take_pending(), PendingAdminRequests::default());
+    }
+
+    #[test]
+    fn test_admin_server() {
+        let admin = MocknetAdmin::new();
+        let addr = admin.start_server("127.0.0.1:0").unwrap();
+
+        let mut socket = TcpStream::connect(addr).unwrap();
+        let body = r#"{"blocks": 2}"#;
+        write!(
+            socket,
+            "POST /v1/mine HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        socket.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\"pending_blocks\":2"));
+
+        assert_eq!(admin.take_pending().blocks_to_mine, 2);
+    }
+}
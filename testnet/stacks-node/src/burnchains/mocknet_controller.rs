@@ -1,5 +1,7 @@
 use std::collections::VecDeque;
-use std::time::Instant;
+#[cfg(test)]
+use std::path::Path;
+use std::sync::Arc;
 
 use clarity::vm::costs::ExecutionCost;
 use stacks::burnchains::bitcoin::BitcoinBlock;
@@ -17,12 +19,17 @@ use stacks::core::{
     StacksEpoch, StacksEpochId, PEER_VERSION_EPOCH_2_0, PEER_VERSION_EPOCH_2_05,
     PEER_VERSION_EPOCH_2_1, STACKS_EPOCH_MAX,
 };
+use stacks::monitoring::{set_burnchain_headers_height, set_sortition_height};
 use stacks_common::types::chainstate::{BurnchainHeaderHash, PoxId};
 use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::hash::Sha256Sum;
 
 use super::super::operations::BurnchainOpSigner;
 use super::super::Config;
+use super::clock::{Clock, SystemClock};
+use super::mocknet_admin::MocknetAdmin;
+#[cfg(test)]
+use super::snapshot::{restore_snapshot, Error as SnapshotError, SnapshotManifest};
 use super::{BurnchainController, BurnchainTip, Error as BurnchainControllerError};
 
 /// MocknetController is simulating a simplistic burnchain.
@@ -32,6 +39,12 @@ pub struct MocknetController {
     db: Option<SortitionDB>,
     chain_tip: Option<BurnchainTip>,
     queued_operations: VecDeque<BlockstackOperationType>,
+    clock: Arc<dyn Clock>,
+    /// Requests made through the admin API, if it is served
+    admin: MocknetAdmin,
+    /// Number of forks requested so far.  Mixed into the block hashes of the forks, so that they
+    /// differ from the blocks they replace.
+    num_forks: u64,
 }
 
 impl MocknetController {
@@ -43,28 +56,123 @@ impl MocknetController {
         debug!("Opening Burnchain at {}", &config.get_burn_db_path());
         let burnchain = config.get_burnchain();
 
+        let admin = MocknetAdmin::new();
+        if let Some(bind) = config.burnchain.mocknet_admin_bind.as_ref() {
+            match admin.start_server(bind) {
+                Ok(local_addr) => {
+                    info!("Mocknet: serving admin API"; "bind" => %local_addr);
+                }
+                Err(e) => {
+                    warn!("Mocknet: failed to start admin API server"; "bind" => bind, "error" => %e);
+                }
+            }
+        }
+
         Self {
-            config: config,
-            burnchain: burnchain,
+            config,
+            burnchain,
             db: None,
             queued_operations: VecDeque::new(),
             chain_tip: None,
+            clock: SystemClock::shared(),
+            admin,
+            num_forks: 0,
         }
     }
 
-    fn build_next_block_header(current_block: &BlockSnapshot) -> BurnchainBlockHeader {
-        let curr_hash = &current_block.burn_header_hash.to_bytes()[..];
-        let next_hash = Sha256Sum::from_data(&curr_hash);
+    /// Create a controller whose burnchain starts out as the snapshot in `snapshot_dir`.
+    /// `config`'s working directory must not have a sortition DB yet.
+    #[cfg(test)]
+    pub fn from_snapshot(
+        config: Config,
+        snapshot_dir: &Path,
+    ) -> Result<(Self, SnapshotManifest), SnapshotError> {
+        let manifest = restore_snapshot(&config, snapshot_dir)?;
+        Ok((Self::new(config), manifest))
+    }
+
+    /// Use `clock` instead of the system clock
+    #[cfg(test)]
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// The requests made through the admin API
+    #[cfg(test)]
+    pub fn admin(&self) -> &MocknetAdmin {
+        &self.admin
+    }
+
+    fn build_next_block_header(
+        current_block: &BlockSnapshot,
+        timestamp: u64,
+        num_forks: u64,
+    ) -> BurnchainBlockHeader {
+        let mut preimage = current_block.burn_header_hash.to_bytes().to_vec();
+        if num_forks > 0 {
+            preimage.extend_from_slice(&num_forks.to_be_bytes());
+        }
+        let next_hash = Sha256Sum::from_data(&preimage);
 
         let block = BurnchainBlock::Bitcoin(BitcoinBlock::new(
             current_block.block_height + 1,
             &BurnchainHeaderHash::from_bytes(next_hash.as_bytes()).unwrap(),
             &current_block.burn_header_hash,
             vec![],
-            get_epoch_time_secs(),
+            timestamp,
         ));
         block.header()
     }
+
+    /// Mine a block on top of `parent` with the given operations
+    fn mine_block(
+        &mut self,
+        parent: &BlockSnapshot,
+        ops: Vec<BlockstackOperationType>,
+    ) -> (BlockSnapshot, BurnchainStateTransitionOps) {
+        let timestamp = self
+            .admin
+            .next_block_time()
+            .unwrap_or_else(get_epoch_time_secs);
+        let next_block_header = Self::build_next_block_header(parent, timestamp, self.num_forks);
+        let ops = ops
+            .into_iter()
+            .map(|op| bind_operation_to_block(op, &next_block_header))
+            .collect();
+
+        // Include txs in a new block
+        let (block_snapshot, state_transition) = {
+            match self.db {
+                None => {
+                    unreachable!();
+                }
+                Some(ref mut burn_db) => {
+                    let mut burn_tx =
+                        SortitionHandleTx::begin(burn_db, &parent.sortition_id).unwrap();
+                    let new_chain_tip = burn_tx
+                        .process_block_ops(
+                            &self.burnchain,
+                            parent,
+                            &next_block_header,
+                            ops,
+                            None,
+                            PoxId::stubbed(),
+                            None,
+                            0,
+                        )
+                        .unwrap();
+                    burn_tx.commit().unwrap();
+                    new_chain_tip
+                }
+            }
+        };
+
+        let state_transition = BurnchainStateTransitionOps {
+            accepted_ops: state_transition.accepted_ops,
+            consumed_leader_keys: state_transition.consumed_leader_keys,
+        };
+        (block_snapshot, state_transition)
+    }
 }
 
 impl BurnchainController for MocknetController {
@@ -155,10 +263,14 @@ impl BurnchainController for MocknetController {
         let genesis_state = BurnchainTip {
             block_snapshot,
             state_transition: BurnchainStateTransitionOps::noop(),
-            received_at: Instant::now(),
+            received_at: self.clock.now(),
         };
         self.chain_tip = Some(genesis_state.clone());
         let block_height = genesis_state.block_snapshot.block_height;
+        self.admin
+            .set_tip(block_height, genesis_state.block_snapshot.burn_header_hash);
+        set_burnchain_headers_height(block_height);
+        set_sortition_height(block_height);
         Ok((genesis_state, block_height))
     }
 
@@ -179,130 +291,54 @@ impl BurnchainController for MocknetController {
         _ignored_target_height_opt: Option<u64>,
     ) -> Result<(BurnchainTip, u64), BurnchainControllerError> {
         let chain_tip = self.get_chain_tip();
+        let admin_requests = self.admin.take_pending();
 
-        // Simulating mining
-        let next_block_header = Self::build_next_block_header(&chain_tip.block_snapshot);
-        let mut ops = vec![];
-
-        while let Some(payload) = self.queued_operations.pop_front() {
-            let op = match payload {
-                BlockstackOperationType::LeaderKeyRegister(payload) => {
-                    BlockstackOperationType::LeaderKeyRegister(LeaderKeyRegisterOp {
-                        consensus_hash: payload.consensus_hash,
-                        public_key: payload.public_key,
-                        memo: payload.memo,
-                        txid: payload.txid,
-                        vtxindex: payload.vtxindex,
-                        block_height: next_block_header.block_height,
-                        burn_header_hash: next_block_header.block_hash,
-                    })
+        let mut parent = chain_tip.block_snapshot;
+        if let Some(fork_height) = admin_requests.fork_height {
+            let ancestor = SortitionDB::get_ancestor_snapshot(
+                &self.sortdb_ref().index_conn(),
+                fork_height,
+                &parent.sortition_id,
+            )
+            .expect("FATAL: failed to query sortition DB");
+            match ancestor {
+                Some(ancestor) => {
+                    info!("Mocknet: forking the burnchain";
+                          "fork_height" => fork_height,
+                          "tip_height" => parent.block_height);
+                    self.num_forks += 1;
+                    parent = ancestor;
                 }
-                BlockstackOperationType::LeaderBlockCommit(payload) => {
-                    BlockstackOperationType::LeaderBlockCommit(LeaderBlockCommitOp {
-                        sunset_burn: 0,
-                        block_header_hash: payload.block_header_hash,
-                        new_seed: payload.new_seed,
-                        parent_block_ptr: payload.parent_block_ptr,
-                        parent_vtxindex: payload.parent_vtxindex,
-                        key_block_ptr: payload.key_block_ptr,
-                        key_vtxindex: payload.key_vtxindex,
-                        memo: payload.memo,
-                        burn_fee: payload.burn_fee,
-                        apparent_sender: payload.apparent_sender,
-                        input: payload.input,
-                        commit_outs: payload.commit_outs,
-                        txid: payload.txid,
-                        vtxindex: payload.vtxindex,
-                        block_height: next_block_header.block_height,
-                        burn_parent_modulus: if next_block_header.block_height > 0 {
-                            (next_block_header.block_height - 1) % BURN_BLOCK_MINED_AT_MODULUS
-                        } else {
-                            BURN_BLOCK_MINED_AT_MODULUS - 1
-                        } as u8,
-                        burn_header_hash: next_block_header.block_hash,
-                    })
-                }
-                BlockstackOperationType::PreStx(payload) => {
-                    BlockstackOperationType::PreStx(PreStxOp {
-                        block_height: next_block_header.block_height,
-                        burn_header_hash: next_block_header.block_hash,
-                        ..payload
-                    })
-                }
-                BlockstackOperationType::TransferStx(payload) => {
-                    BlockstackOperationType::TransferStx(TransferStxOp {
-                        block_height: next_block_header.block_height,
-                        burn_header_hash: next_block_header.block_hash,
-                        ..payload
-                    })
-                }
-                BlockstackOperationType::StackStx(payload) => {
-                    BlockstackOperationType::StackStx(StackStxOp {
-                        block_height: next_block_header.block_height,
-                        burn_header_hash: next_block_header.block_hash,
-                        ..payload
-                    })
-                }
-                BlockstackOperationType::DelegateStx(payload) => {
-                    BlockstackOperationType::DelegateStx(DelegateStxOp {
-                        block_height: next_block_header.block_height,
-                        burn_header_hash: next_block_header.block_hash,
-                        ..payload
-                    })
-                }
-                BlockstackOperationType::VoteForAggregateKey(payload) => {
-                    BlockstackOperationType::VoteForAggregateKey(VoteForAggregateKeyOp {
-                        block_height: next_block_header.block_height,
-                        burn_header_hash: next_block_header.block_hash,
-                        ..payload
-                    })
-                }
-            };
-            ops.push(op);
-        }
-
-        // Include txs in a new block
-        let (block_snapshot, state_transition) = {
-            match self.db {
                 None => {
-                    unreachable!();
-                }
-                Some(ref mut burn_db) => {
-                    let mut burn_tx =
-                        SortitionHandleTx::begin(burn_db, &chain_tip.block_snapshot.sortition_id)
-                            .unwrap();
-                    let new_chain_tip = burn_tx
-                        .process_block_ops(
-                            &self.burnchain,
-                            &chain_tip.block_snapshot,
-                            &next_block_header,
-                            ops,
-                            None,
-                            PoxId::stubbed(),
-                            None,
-                            0,
-                        )
-                        .unwrap();
-                    burn_tx.commit().unwrap();
-                    new_chain_tip
+                    warn!("Mocknet: no block to fork from"; "fork_height" => fork_height);
                 }
             }
-        };
+        }
 
-        let state_transition = BurnchainStateTransitionOps {
-            accepted_ops: state_transition.accepted_ops,
-            consumed_leader_keys: state_transition.consumed_leader_keys,
-        };
+        // Simulating mining.  Blocks requested through the admin API are mined empty, before
+        // the block with the queued operations.
+        for _ in 0..admin_requests.blocks_to_mine {
+            let (block_snapshot, _) = self.mine_block(&parent, vec![]);
+            parent = block_snapshot;
+        }
+        let mut ops: Vec<_> = self.queued_operations.drain(..).collect();
+        ops.extend(admin_requests.operations);
+        let (block_snapshot, state_transition) = self.mine_block(&parent, ops);
 
         // Transmit the new state
         let new_state = BurnchainTip {
             block_snapshot,
             state_transition,
-            received_at: Instant::now(),
+            received_at: self.clock.now(),
         };
         self.chain_tip = Some(new_state.clone());
 
+        // every mocknet block is processed as soon as it is mined
         let block_height = new_state.block_snapshot.block_height;
+        self.admin
+            .set_tip(block_height, new_state.block_snapshot.burn_header_hash);
+        set_burnchain_headers_height(block_height);
+        set_sortition_height(block_height);
         Ok((new_state, block_height))
     }
 
@@ -312,4 +348,177 @@ impl BurnchainController for MocknetController {
     fn connect_dbs(&mut self) -> Result<(), BurnchainControllerError> {
         Ok(())
     }
+
+    fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+}
+
+/// Rewrite a submitted operation so that it is included in the block with the given header
+pub(crate) fn bind_operation_to_block(
+    operation: BlockstackOperationType,
+    next_block_header: &BurnchainBlockHeader,
+) -> BlockstackOperationType {
+    match operation {
+        BlockstackOperationType::LeaderKeyRegister(payload) => {
+            BlockstackOperationType::LeaderKeyRegister(LeaderKeyRegisterOp {
+                consensus_hash: payload.consensus_hash,
+                public_key: payload.public_key,
+                memo: payload.memo,
+                txid: payload.txid,
+                vtxindex: payload.vtxindex,
+                block_height: next_block_header.block_height,
+                burn_header_hash: next_block_header.block_hash,
+            })
+        }
+        BlockstackOperationType::LeaderBlockCommit(payload) => {
+            BlockstackOperationType::LeaderBlockCommit(LeaderBlockCommitOp {
+                sunset_burn: 0,
+                block_header_hash: payload.block_header_hash,
+                new_seed: payload.new_seed,
+                parent_block_ptr: payload.parent_block_ptr,
+                parent_vtxindex: payload.parent_vtxindex,
+                key_block_ptr: payload.key_block_ptr,
+                key_vtxindex: payload.key_vtxindex,
+                memo: payload.memo,
+                burn_fee: payload.burn_fee,
+                apparent_sender: payload.apparent_sender,
+                input: payload.input,
+                commit_outs: payload.commit_outs,
+                txid: payload.txid,
+                vtxindex: payload.vtxindex,
+                block_height: next_block_header.block_height,
+                burn_parent_modulus: if next_block_header.block_height > 0 {
+                    (next_block_header.block_height - 1) % BURN_BLOCK_MINED_AT_MODULUS
+                } else {
+                    BURN_BLOCK_MINED_AT_MODULUS - 1
+                } as u8,
+                burn_header_hash: next_block_header.block_hash,
+            })
+        }
+        BlockstackOperationType::PreStx(payload) => BlockstackOperationType::PreStx(PreStxOp {
+            block_height: next_block_header.block_height,
+            burn_header_hash: next_block_header.block_hash,
+            ..payload
+        }),
+        BlockstackOperationType::TransferStx(payload) => {
+            BlockstackOperationType::TransferStx(TransferStxOp {
+                block_height: next_block_header.block_height,
+                burn_header_hash: next_block_header.block_hash,
+                ..payload
+            })
+        }
+        BlockstackOperationType::StackStx(payload) => {
+            BlockstackOperationType::StackStx(StackStxOp {
+                block_height: next_block_header.block_height,
+                burn_header_hash: next_block_header.block_hash,
+                ..payload
+            })
+        }
+        BlockstackOperationType::DelegateStx(payload) => {
+            BlockstackOperationType::DelegateStx(DelegateStxOp {
+                block_height: next_block_header.block_height,
+                burn_header_hash: next_block_header.block_hash,
+                ..payload
+            })
+        }
+        BlockstackOperationType::VoteForAggregateKey(payload) => {
+            BlockstackOperationType::VoteForAggregateKey(VoteForAggregateKeyOp {
+                block_height: next_block_header.block_height,
+                burn_header_hash: next_block_header.block_hash,
+                ..payload
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::burnchains::clock::ManualClock;
+    use crate::tests::new_test_conf;
+
+    #[test]
+    fn test_mocknet_tips_use_injected_clock() {
+        let clock = Arc::new(ManualClock::new());
+        let mut controller = MocknetController::new(new_test_conf());
+        controller.set_clock(clock.clone());
+
+        let (genesis, _) = controller.start(None).unwrap();
+        assert_eq!(genesis.received_at, clock.now());
+
+        clock.advance(Duration::from_secs(30));
+        let (tip, _) = controller.sync(None).unwrap();
+        assert_eq!(
+            tip.received_at.duration_since(genesis.received_at),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn test_mocknet_header_time() {
+        let mut controller = MocknetController::new(new_test_conf());
+        controller.start(None).unwrap();
+        controller.sync(None).unwrap();
+
+        controller.admin().request_blocks(3);
+        controller.admin().set_block_time(Some(1_000_000));
+        let (tip, _) = controller.sync(None).unwrap();
+
+        let header_time = controller.get_header_time().unwrap();
+        assert_eq!(header_time.block_height, tip.block_snapshot.block_height);
+        assert_eq!(
+            header_time.burn_header_hash,
+            tip.block_snapshot.burn_header_hash
+        );
+        assert_eq!(header_time.header_timestamp, 1_000_003);
+        assert!(header_time.timestamp() >= header_time.header_timestamp);
+        assert!(header_time.age_secs(1_000_003) <= header_time.age_secs(2_000_000));
+    }
+
+    #[test]
+    fn test_mocknet_admin_requests() {
+        let mut controller = MocknetController::new(new_test_conf());
+        controller.start(None).unwrap();
+        let (tip, _) = controller.sync(None).unwrap();
+        assert_eq!(tip.block_snapshot.block_height, 1);
+
+        // mine more blocks, at a set time
+        controller.admin().request_blocks(3);
+        controller.admin().set_block_time(Some(1_000_000));
+        let (tip, _) = controller.sync(None).unwrap();
+        assert_eq!(tip.block_snapshot.block_height, 5);
+        assert_eq!(tip.block_snapshot.burn_header_timestamp, 1_000_003);
+        let replaced_hash = SortitionDB::get_ancestor_snapshot(
+            &controller.sortdb_ref().index_conn(),
+            3,
+            &tip.block_snapshot.sortition_id,
+        )
+        .unwrap()
+        .unwrap()
+        .burn_header_hash;
+
+        // fork off the block at height 2
+        controller.admin().request_fork(2).unwrap();
+        assert!(controller.admin().request_fork(5).is_err());
+        controller.admin().request_blocks(3);
+        let (fork_tip, _) = controller.sync(None).unwrap();
+        assert_eq!(fork_tip.block_snapshot.block_height, 6);
+        let ic = controller.sortdb_ref().index_conn();
+        let fork_block = |height| {
+            SortitionDB::get_ancestor_snapshot(&ic, height, &fork_tip.block_snapshot.sortition_id)
+                .unwrap()
+                .unwrap()
+                .burn_header_hash
+        };
+        let common_ancestor =
+            SortitionDB::get_ancestor_snapshot(&ic, 2, &tip.block_snapshot.sortition_id)
+                .unwrap()
+                .unwrap()
+                .burn_header_hash;
+        assert_eq!(fork_block(2), common_ancestor);
+        assert_ne!(fork_block(3), replaced_hash);
+    }
 }
@@ -1,7 +1,25 @@
+pub mod backfill;
 pub mod bitcoin_regtest_controller;
+pub mod block_stream;
+pub mod clock;
+pub mod commit_template;
+pub mod header_time;
+pub mod json_server;
+pub mod mocknet_admin;
 pub mod mocknet_controller;
+pub mod op_confirmations;
+#[cfg(test)]
+pub mod op_sequences;
+pub mod readiness;
+pub mod rpc_auth;
+#[cfg(test)]
+pub mod snapshot;
+pub mod sync_span;
+#[cfg(test)]
+pub mod test_harness;
 
 use std::fmt;
+use std::sync::Arc;
 use std::time::Instant;
 
 use stacks::burnchains;
@@ -12,13 +30,18 @@ use stacks::chainstate::burn::BlockSnapshot;
 use stacks::core::{StacksEpoch, StacksEpochId};
 
 pub use self::bitcoin_regtest_controller::{make_bitcoin_indexer, BitcoinRegtestController};
+use self::clock::{Clock, SystemClock};
+use self::header_time::BurnHeaderTime;
 pub use self::mocknet_controller::MocknetController;
+use self::sync_span::SyncSpan;
 use super::operations::BurnchainOpSigner;
 
 #[derive(Debug)]
 pub enum Error {
     CoordinatorClosed,
     IndexerError(burnchains::Error),
+    /// Gave up waiting for the chains coordinator to process sortitions up to this height
+    SortitionTimeout(u64),
 }
 
 impl fmt::Display for Error {
@@ -26,6 +49,9 @@ impl fmt::Display for Error {
         match self {
             Error::CoordinatorClosed => write!(f, "ChainsCoordinator closed"),
             Error::IndexerError(ref e) => write!(f, "Indexer error: {:?}", e),
+            Error::SortitionTimeout(height) => {
+                write!(f, "Timed out waiting for sortition height {}", height)
+            }
         }
     }
 }
@@ -55,6 +81,23 @@ pub trait BurnchainController {
     ///  or instantiation before other callers may use open()
     fn connect_dbs(&mut self) -> Result<(), Error>;
     fn get_stacks_epochs(&self) -> Vec<StacksEpoch>;
+    /// Span of the most recent `sync()` pass, so callers can record later pipeline stages
+    /// under the same span ID.
+    fn sync_span_mut(&mut self) -> Option<&mut SyncSpan> {
+        None
+    }
+    /// Time source used to stamp `BurnchainTip::received_at` and to pace retries
+    fn clock(&self) -> Arc<dyn Clock> {
+        SystemClock::shared()
+    }
+    /// Header time of the canonical burnchain tip, checked against its ancestors.  Use this to
+    /// schedule work by the burnchain's clock, rather than reading the tip's timestamp directly.
+    fn get_header_time(&self) -> Result<BurnHeaderTime, Error> {
+        let sortdb = self.sortdb_ref();
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())
+            .map_err(burnchains::Error::DBError)?;
+        Ok(header_time::header_time_at(sortdb, &tip)?)
+    }
 
     #[cfg(test)]
     fn bootstrap_chain(&mut self, blocks_count: u64);
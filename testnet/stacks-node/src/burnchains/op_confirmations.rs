@@ -0,0 +1,1260 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Persistent record of every burnchain operation submitted through `submit_operation`, and of
+//! when (if ever) it was mined.  Confirmation latencies (in burnchain blocks and in seconds)
+//! and per-operation-type success rates are exported to the metrics endpoint, so that miners
+//! can tune their fees and timing.  Because the record is persisted, success rates account for
+//! operations submitted before the node was restarted.
+//!
+//! An operation that is only useful if mined by some burnchain height, such as a block commit
+//! built for the next sortition, is recorded with that height as its expiry, and counted as
+//! failed as soon as the burnchain reaches that height without it.
+//!
+//! The record also keeps the burn block that mined each operation, and for block commits,
+//! whether they won the sortition of that block.  Operators can query it through the
+//! `/v2/burnchain/ops` RPC endpoint to audit their miner's win rate.
+//!
+//! The record doubles as a write-ahead log of the operations the miner signs.  Before a signed
+//! operation is broadcast, it is recorded as an intent: along with its txid, a digest of the
+//! signed transaction, the key that signed it, and the burn block it was signed for.  If the
+//! node crashes between recording the intent and seeing the operation mined, there is no
+//! telling whether the operation reached the Bitcoin network, so after a restart the miner will
+//! not submit another operation of the same type, from the same key, for the same burn block,
+//! until the intent is resolved like any other pending operation: either the operation is
+//! mined, or its expiry passes.  This keeps a recovering miner from double-committing.  Open
+//! intents can be reviewed through the `/v2/burnchain/intents` RPC endpoint.
+
+use std::path::Path;
+
+use lazy_static::lazy_static;
+use rusqlite::{Connection, OpenFlags, Row, ToSql, NO_PARAMS};
+use stacks::burnchains::Txid;
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::monitoring::{
+    increment_burnchain_ops_submitted, log_burnchain_op_confirmed, log_burnchain_op_failed,
+    set_burnchain_op_success_rate,
+};
+use stacks::net::api::getburnchainintents::{
+    BurnchainOpIntent, BurnchainOpIntentStatus, BurnchainOpIntents, BurnchainOpIntentsResponse,
+};
+use stacks::net::api::getburnchainops::{
+    BlockCommitOutcomes, SortitionOutcome, SubmittedBurnchainOp, SubmittedBurnchainOps,
+    SubmittedBurnchainOpsResponse,
+};
+use stacks::util_lib::db::{
+    opt_u64_to_sql, query_rows, sqlite_open, tx_begin_immediate, u64_to_sql, DBConn,
+    Error as DBError, FromRow,
+};
+use stacks_common::types::chainstate::{BurnchainHeaderHash, SortitionId};
+use stacks_common::util::hash::{Hash160, Sha256Sum};
+
+use crate::Config;
+
+lazy_static! {
+    /// Identifies the intents recorded since this process started
+    static ref OP_INTENT_RUN_ID: String = format!("{:016x}", rand::random::<u64>());
+}
+
+/// The run ID of the intents recorded since this process started
+pub fn current_run_id() -> &'static str {
+    OP_INTENT_RUN_ID.as_str()
+}
+
+/// Submitted operations without an expiry of their own that are still unmined this many
+/// burnchain blocks later are counted as failed
+pub const OP_CONFIRMATION_EXPIRY_BLOCKS: u64 = 12;
+
+/// `op_type` of block commits
+pub const BLOCK_COMMIT_OP_TYPE: &str = "LeaderBlockCommit";
+
+const OP_CONFIRMATIONS_SCHEMA: &[&str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS burnchain_ops(
+        txid TEXT PRIMARY KEY NOT NULL,
+        op_type TEXT NOT NULL,
+        -- seconds since the epoch
+        submitted_at INTEGER NOT NULL,
+        -- the burnchain tip height when the operation was submitted
+        submitted_burn_height INTEGER NOT NULL,
+        -- one of 'pending', 'confirmed', 'replaced', 'failed' or 'abandoned' (never broadcast)
+        status TEXT NOT NULL,
+        confirmed_at INTEGER,
+        confirmed_burn_height INTEGER,
+        -- the last burnchain height at which the operation is of use, if it has one
+        expires_burn_height INTEGER,
+        confirmed_burn_header_hash TEXT,
+        -- for confirmed block commits, one of 'won', 'lost' or 'rejected', once the sortition
+        -- of the block that mined them has been processed
+        sortition_outcome TEXT,
+        -- for operations recorded as intents before being broadcast: the SHA256 of the signed
+        -- transaction, the Hash160 of the public key that signed it, the burn block it was
+        -- signed for, and the run of the node that recorded it
+        payload_digest TEXT,
+        key_id TEXT,
+        target_burn_height INTEGER,
+        run_id TEXT
+    );"#,
+    "CREATE INDEX IF NOT EXISTS burnchain_ops_by_status ON burnchain_ops(status);",
+];
+
+/// Columns added to `burnchain_ops` after it was first released, with their types
+const OP_CONFIRMATIONS_ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("expires_burn_height", "INTEGER"),
+    ("confirmed_burn_header_hash", "TEXT"),
+    ("sortition_outcome", "TEXT"),
+    ("payload_digest", "TEXT"),
+    ("key_id", "TEXT"),
+    ("target_burn_height", "INTEGER"),
+    ("run_id", "TEXT"),
+];
+
+/// A submitted operation that has not been mined yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingOp {
+    pub txid: Txid,
+    pub op_type: String,
+    pub submitted_at: u64,
+    pub submitted_burn_height: u64,
+    pub expires_burn_height: Option<u64>,
+    /// The run of the node that recorded the operation as an intent, if it did
+    pub run_id: Option<String>,
+}
+
+impl PendingOp {
+    /// The burnchain height by which this operation counts as failed if it has not been mined
+    pub fn expiry(&self) -> u64 {
+        self.expires_burn_height
+            .unwrap_or(self.submitted_burn_height + OP_CONFIRMATION_EXPIRY_BLOCKS)
+    }
+}
+
+impl FromRow<PendingOp> for PendingOp {
+    fn from_row<'a>(row: &'a Row) -> Result<PendingOp, DBError> {
+        let submitted_at: i64 = row.get_unwrap("submitted_at");
+        let submitted_burn_height: i64 = row.get_unwrap("submitted_burn_height");
+        let expires_burn_height: Option<i64> = row.get_unwrap("expires_burn_height");
+        Ok(PendingOp {
+            txid: row.get_unwrap("txid"),
+            op_type: row.get_unwrap("op_type"),
+            submitted_at: submitted_at as u64,
+            submitted_burn_height: submitted_burn_height as u64,
+            expires_burn_height: expires_burn_height.map(|height| height as u64),
+            run_id: row.get_unwrap("run_id"),
+        })
+    }
+}
+
+/// An intent to submit a signed burnchain operation
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpIntent {
+    pub txid: Txid,
+    pub op_type: String,
+    pub payload_digest: Sha256Sum,
+    pub key_id: Hash160,
+    pub target_burn_height: u64,
+    pub expires_burn_height: u64,
+    pub recorded_at: u64,
+    pub run_id: String,
+}
+
+impl OpIntent {
+    /// Whether this intent was recorded before the node last restarted, as of run `run_id`
+    pub fn is_carried_over(&self, run_id: &str) -> bool {
+        self.run_id != run_id
+    }
+
+    /// How the intent is reported over RPC, as of run `run_id`
+    pub fn to_rpc(&self, run_id: &str) -> BurnchainOpIntent {
+        BurnchainOpIntent {
+            txid: self.txid,
+            op_type: self.op_type.clone(),
+            payload_digest: self.payload_digest,
+            key_id: self.key_id,
+            target_burn_height: self.target_burn_height,
+            expires_burn_height: self.expires_burn_height,
+            recorded_at: self.recorded_at,
+            status: if self.is_carried_over(run_id) {
+                BurnchainOpIntentStatus::Unresolved
+            } else {
+                BurnchainOpIntentStatus::InFlight
+            },
+        }
+    }
+}
+
+impl FromRow<OpIntent> for OpIntent {
+    fn from_row<'a>(row: &'a Row) -> Result<OpIntent, DBError> {
+        let payload_digest: String = row.get_unwrap("payload_digest");
+        let key_id: String = row.get_unwrap("key_id");
+        let target_burn_height: i64 = row.get_unwrap("target_burn_height");
+        let expires_burn_height: i64 = row.get_unwrap("expires_burn_height");
+        let submitted_at: i64 = row.get_unwrap("submitted_at");
+        Ok(OpIntent {
+            txid: row.get_unwrap("txid"),
+            op_type: row.get_unwrap("op_type"),
+            payload_digest: Sha256Sum::from_hex(&payload_digest)
+                .map_err(|_| DBError::ParseError)?,
+            key_id: Hash160::from_hex(&key_id).map_err(|_| DBError::ParseError)?,
+            target_burn_height: target_burn_height as u64,
+            expires_burn_height: expires_burn_height as u64,
+            recorded_at: submitted_at as u64,
+            run_id: row.get_unwrap("run_id"),
+        })
+    }
+}
+
+/// Intents whose operations are neither mined nor expired yet, oldest first
+fn query_open_intents(conn: &Connection) -> Result<Vec<OpIntent>, DBError> {
+    query_rows(
+        conn,
+        "SELECT * FROM burnchain_ops WHERE status = 'pending' AND key_id IS NOT NULL
+         ORDER BY submitted_at ASC, rowid ASC",
+        NO_PARAMS,
+    )
+}
+
+/// How a pending operation was resolved by `OpConfirmationTracker::update`
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpResolution {
+    /// Mined in burn block `burn_header_hash`, `blocks` burnchain blocks and `seconds` seconds
+    /// after it was submitted
+    Confirmed {
+        blocks: u64,
+        seconds: u64,
+        burn_header_hash: BurnchainHeaderHash,
+    },
+    /// Not mined by its expiry
+    Failed,
+}
+
+/// Number of confirmed and failed operations of one type
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpTypeOutcomes {
+    pub op_type: String,
+    pub confirmed: u64,
+    pub failed: u64,
+}
+
+impl OpTypeOutcomes {
+    /// Fraction of resolved operations that were mined, if any were resolved
+    pub fn success_rate(&self) -> Option<f64> {
+        let resolved = self.confirmed + self.failed;
+        if resolved == 0 {
+            return None;
+        }
+        Some(self.confirmed as f64 / resolved as f64)
+    }
+}
+
+impl FromRow<OpTypeOutcomes> for OpTypeOutcomes {
+    fn from_row<'a>(row: &'a Row) -> Result<OpTypeOutcomes, DBError> {
+        let confirmed: i64 = row.get_unwrap("confirmed");
+        let failed: i64 = row.get_unwrap("failed");
+        Ok(OpTypeOutcomes {
+            op_type: row.get_unwrap("op_type"),
+            confirmed: confirmed as u64,
+            failed: failed as u64,
+        })
+    }
+}
+
+/// Row of block commit outcome counts
+struct BlockCommitOutcomesRow(BlockCommitOutcomes);
+
+impl FromRow<BlockCommitOutcomesRow> for BlockCommitOutcomesRow {
+    fn from_row<'a>(row: &'a Row) -> Result<BlockCommitOutcomesRow, DBError> {
+        let won: i64 = row.get_unwrap("won");
+        let lost: i64 = row.get_unwrap("lost");
+        let rejected: i64 = row.get_unwrap("rejected");
+        let not_mined: i64 = row.get_unwrap("not_mined");
+        Ok(BlockCommitOutcomesRow(BlockCommitOutcomes {
+            won: won as u64,
+            lost: lost as u64,
+            rejected: rejected as u64,
+            not_mined: not_mined as u64,
+        }))
+    }
+}
+
+/// Row of `burnchain_ops`, as reported over RPC
+struct SubmittedOpRow(SubmittedBurnchainOp);
+
+impl FromRow<SubmittedOpRow> for SubmittedOpRow {
+    fn from_row<'a>(row: &'a Row) -> Result<SubmittedOpRow, DBError> {
+        let submitted_at: i64 = row.get_unwrap("submitted_at");
+        let submitted_burn_height: i64 = row.get_unwrap("submitted_burn_height");
+        let confirmed_burn_height: Option<i64> = row.get_unwrap("confirmed_burn_height");
+        let sortition_outcome: Option<String> = row.get_unwrap("sortition_outcome");
+        Ok(SubmittedOpRow(SubmittedBurnchainOp {
+            txid: row.get_unwrap("txid"),
+            op_type: row.get_unwrap("op_type"),
+            status: row.get_unwrap("status"),
+            submitted_at: submitted_at as u64,
+            submitted_burn_height: submitted_burn_height as u64,
+            burn_block_height: confirmed_burn_height.map(|height| height as u64),
+            burn_header_hash: row.get_unwrap("confirmed_burn_header_hash"),
+            sortition_outcome: sortition_outcome
+                .as_deref()
+                .and_then(SortitionOutcome::from_name),
+        }))
+    }
+}
+
+/// How the block commit `txid`, mined in burn block `burn_header_hash`, fared in that block's
+/// sortition on the fork of `sort_tip`.  None if the sortition is not processed yet, or the
+/// burn block is not on that fork.
+pub fn find_sortition_outcome(
+    sortdb: &SortitionDB,
+    sort_tip: &SortitionId,
+    txid: &Txid,
+    burn_header_hash: &BurnchainHeaderHash,
+) -> Option<SortitionOutcome> {
+    let sortition_id = sortdb
+        .get_sortition_id(burn_header_hash, sort_tip)
+        .ok()
+        .flatten()?;
+    let snapshot = SortitionDB::get_block_snapshot(sortdb.conn(), &sortition_id)
+        .ok()
+        .flatten()?;
+    if snapshot.sortition && snapshot.winning_block_txid == *txid {
+        return Some(SortitionOutcome::Won);
+    }
+    match SortitionDB::get_block_commit(sortdb.conn(), txid, &sortition_id) {
+        Ok(Some(_)) => Some(SortitionOutcome::Lost),
+        Ok(None) => Some(SortitionOutcome::Rejected),
+        Err(e) => {
+            warn!("Failed to load block commit"; "txid" => %txid, "error" => ?e);
+            None
+        }
+    }
+}
+
+/// The path of the op confirmation tracker's database
+pub fn op_confirmations_path(config: &Config) -> String {
+    format!(
+        "{}/op_confirmations.sqlite",
+        config.get_burnchain_path_str()
+    )
+}
+
+/// Outcomes of all the block commits on record, and the `limit` most recently submitted
+/// operations, newest first
+fn query_submitted_ops(
+    conn: &Connection,
+    limit: u32,
+) -> Result<SubmittedBurnchainOpsResponse, DBError> {
+    let block_commits = query_rows::<BlockCommitOutcomesRow, _>(
+        conn,
+        "SELECT COALESCE(SUM(CASE WHEN sortition_outcome = 'won' THEN 1 ELSE 0 END), 0) AS won,
+                COALESCE(SUM(CASE WHEN sortition_outcome = 'lost' THEN 1 ELSE 0 END), 0) AS lost,
+                COALESCE(SUM(CASE WHEN sortition_outcome = 'rejected' THEN 1 ELSE 0 END), 0) AS rejected,
+                COALESCE(SUM(CASE WHEN status = 'failed' THEN 1 ELSE 0 END), 0) AS not_mined
+         FROM burnchain_ops WHERE op_type = ?1",
+        &[BLOCK_COMMIT_OP_TYPE],
+    )?
+    .pop()
+    .map(|BlockCommitOutcomesRow(outcomes)| outcomes)
+    .unwrap_or_default();
+    let ops: Vec<SubmittedOpRow> = query_rows(
+        conn,
+        "SELECT * FROM burnchain_ops ORDER BY submitted_at DESC, rowid DESC LIMIT ?1",
+        &[limit],
+    )?;
+    Ok(SubmittedBurnchainOpsResponse {
+        block_commits,
+        ops: ops.into_iter().map(|row| row.0).collect(),
+    })
+}
+
+/// Read-only view of the op confirmation tracker's database, for the RPC server.  The database
+/// is opened for each query, so the view can be created before any operation is submitted.
+pub struct OpConfirmationsReader {
+    path: String,
+}
+
+impl OpConfirmationsReader {
+    pub fn new(config: &Config) -> OpConfirmationsReader {
+        OpConfirmationsReader {
+            path: op_confirmations_path(config),
+        }
+    }
+}
+
+impl SubmittedBurnchainOps for OpConfirmationsReader {
+    fn get_submitted_ops(&self, limit: u32) -> Result<SubmittedBurnchainOpsResponse, String> {
+        if !Path::new(&self.path).exists() {
+            return Ok(SubmittedBurnchainOpsResponse {
+                block_commits: BlockCommitOutcomes::default(),
+                ops: vec![],
+            });
+        }
+        let conn = sqlite_open(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)
+            .map_err(|e| format!("{:?}", e))?;
+        query_submitted_ops(&conn, limit).map_err(|e| format!("{:?}", e))
+    }
+}
+
+impl BurnchainOpIntents for OpConfirmationsReader {
+    fn get_open_intents(&self) -> Result<BurnchainOpIntentsResponse, String> {
+        if !Path::new(&self.path).exists() {
+            return Ok(BurnchainOpIntentsResponse { intents: vec![] });
+        }
+        let conn = sqlite_open(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)
+            .map_err(|e| format!("{:?}", e))?;
+        let intents = query_open_intents(&conn).map_err(|e| format!("{:?}", e))?;
+        let run_id = current_run_id();
+        Ok(BurnchainOpIntentsResponse {
+            intents: intents.iter().map(|intent| intent.to_rpc(run_id)).collect(),
+        })
+    }
+}
+
+pub struct OpConfirmationTracker {
+    conn: DBConn,
+    /// Intents recorded under any other run ID were recorded before the node last restarted
+    run_id: String,
+}
+
+impl OpConfirmationTracker {
+    /// Open (or create) the tracker's database at `path`, and publish the success rates
+    /// persisted in it
+    pub fn open(path: &str) -> Result<OpConfirmationTracker, DBError> {
+        OpConfirmationTracker::open_for_run(path, current_run_id())
+    }
+
+    /// Open (or create) the tracker's database at `path`, to record intents under `run_id`
+    pub fn open_for_run(path: &str, run_id: &str) -> Result<OpConfirmationTracker, DBError> {
+        let conn = sqlite_open(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+            false,
+        )?;
+        for statement in OP_CONFIRMATIONS_SCHEMA.iter() {
+            conn.execute(statement, NO_PARAMS)?;
+        }
+        for (column, column_type) in OP_CONFIRMATIONS_ADDED_COLUMNS.iter() {
+            let has_column = conn
+                .prepare(&format!("SELECT {column} FROM burnchain_ops LIMIT 0"))
+                .is_ok();
+            if !has_column {
+                conn.execute(
+                    &format!("ALTER TABLE burnchain_ops ADD COLUMN {column} {column_type}"),
+                    NO_PARAMS,
+                )?;
+            }
+        }
+        let tracker = OpConfirmationTracker {
+            conn,
+            run_id: run_id.to_string(),
+        };
+        tracker.publish_success_rates()?;
+        Ok(tracker)
+    }
+
+    /// Record an operation sent at `now` while the burnchain tip was at `burn_height`.
+    /// `replaces` lists earlier transactions carrying the same operation (e.g. block commits
+    /// replaced by fee); those are no longer expected to be mined, and are not counted as
+    /// failed.
+    /// If `expires_burn_height` is given, the operation counts as failed if it is not mined by
+    /// then, instead of after `OP_CONFIRMATION_EXPIRY_BLOCKS`.
+    pub fn record_submitted(
+        &mut self,
+        txid: &Txid,
+        op_type: &str,
+        burn_height: u64,
+        now: u64,
+        replaces: &[Txid],
+        expires_burn_height: Option<u64>,
+    ) -> Result<(), DBError> {
+        let tx = tx_begin_immediate(&mut self.conn)?;
+        for replaced in replaces.iter().filter(|replaced| *replaced != txid) {
+            tx.execute(
+                "UPDATE burnchain_ops SET status = 'replaced' WHERE txid = ?1 AND status = 'pending'",
+                &[replaced],
+            )?;
+        }
+        let args: &[&dyn ToSql] = &[
+            txid,
+            &op_type,
+            &u64_to_sql(now)?,
+            &u64_to_sql(burn_height)?,
+            &opt_u64_to_sql(expires_burn_height)?,
+        ];
+        // keep the intent recorded before the operation was broadcast, if there is one
+        tx.execute(
+            "INSERT INTO burnchain_ops
+             (txid, op_type, submitted_at, submitted_burn_height, status, expires_burn_height)
+             VALUES (?1, ?2, ?3, ?4, 'pending', ?5)
+             ON CONFLICT(txid) DO UPDATE SET op_type = excluded.op_type,
+                submitted_at = excluded.submitted_at,
+                submitted_burn_height = excluded.submitted_burn_height,
+                status = 'pending',
+                expires_burn_height = excluded.expires_burn_height",
+            args,
+        )?;
+        tx.commit()?;
+        increment_burnchain_ops_submitted(op_type);
+        Ok(())
+    }
+
+    /// Record an intent under this tracker's run ID, before its operation is broadcast while
+    /// the burnchain tip is at `burn_height`.  The operation is pending from then on, and is
+    /// resolved by `update` like any other.  `replaces` is as for `record_submitted`.
+    pub fn record_intent(
+        &mut self,
+        intent: &OpIntent,
+        burn_height: u64,
+        replaces: &[Txid],
+    ) -> Result<(), DBError> {
+        let tx = tx_begin_immediate(&mut self.conn)?;
+        for replaced in replaces.iter().filter(|replaced| **replaced != intent.txid) {
+            tx.execute(
+                "UPDATE burnchain_ops SET status = 'replaced' WHERE txid = ?1 AND status = 'pending'",
+                &[replaced],
+            )?;
+        }
+        let args: &[&dyn ToSql] = &[
+            &intent.txid,
+            &intent.op_type,
+            &u64_to_sql(intent.recorded_at)?,
+            &u64_to_sql(burn_height)?,
+            &u64_to_sql(intent.expires_burn_height)?,
+            &intent.payload_digest.to_hex(),
+            &intent.key_id.to_hex(),
+            &u64_to_sql(intent.target_burn_height)?,
+            &self.run_id,
+        ];
+        tx.execute(
+            "INSERT OR REPLACE INTO burnchain_ops
+             (txid, op_type, submitted_at, submitted_burn_height, status, expires_burn_height,
+              payload_digest, key_id, target_burn_height, run_id)
+             VALUES (?1, ?2, ?3, ?4, 'pending', ?5, ?6, ?7, ?8, ?9)",
+            args,
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Close the intent for `txid`, whose operation could not be broadcast
+    pub fn abandon(&mut self, txid: &Txid) -> Result<(), DBError> {
+        self.conn.execute(
+            "UPDATE burnchain_ops SET status = 'abandoned' WHERE txid = ?1 AND status = 'pending'",
+            &[txid],
+        )?;
+        Ok(())
+    }
+
+    /// The open intent recorded before the node last restarted that conflicts with an
+    /// operation of type `op_type` signed by `key_id` for burn block `target_burn_height`, if
+    /// there is one: both could end up mined.  Intents recorded since the restart do not
+    /// conflict: those are known to be broadcast or abandoned, and are replaced by fee as usual.
+    pub fn find_conflict(
+        &self,
+        op_type: &str,
+        key_id: &Hash160,
+        target_burn_height: u64,
+    ) -> Result<Option<OpIntent>, DBError> {
+        let args: &[&dyn ToSql] = &[
+            &op_type,
+            &key_id.to_hex(),
+            &u64_to_sql(target_burn_height)?,
+            &self.run_id,
+        ];
+        Ok(query_rows(
+            &self.conn,
+            "SELECT * FROM burnchain_ops WHERE status = 'pending' AND op_type = ?1 AND key_id = ?2
+             AND target_burn_height = ?3 AND run_id != ?4
+             ORDER BY submitted_at ASC, rowid ASC LIMIT 1",
+            args,
+        )?
+        .pop())
+    }
+
+    /// Open intents recorded before the node last restarted, oldest first
+    pub fn get_unresolved(&self) -> Result<Vec<OpIntent>, DBError> {
+        Ok(query_open_intents(&self.conn)?
+            .into_iter()
+            .filter(|intent| intent.is_carried_over(&self.run_id))
+            .collect())
+    }
+
+    pub fn get_pending(&self) -> Result<Vec<PendingOp>, DBError> {
+        query_rows(
+            &self.conn,
+            "SELECT * FROM burnchain_ops WHERE status = 'pending' ORDER BY submitted_at ASC",
+            NO_PARAMS,
+        )
+    }
+
+    /// Resolve pending operations at burnchain height `tip_height`.  `find_mined_block` returns
+    /// the height and hash of the canonical burnchain block that mined an operation, if any.
+    /// Returns the operations resolved by this call.
+    pub fn update<F>(
+        &mut self,
+        tip_height: u64,
+        now: u64,
+        mut find_mined_block: F,
+    ) -> Result<Vec<(PendingOp, OpResolution)>, DBError>
+    where
+        F: FnMut(&Txid) -> Option<(u64, BurnchainHeaderHash)>,
+    {
+        let mut resolved = vec![];
+        for op in self.get_pending()?.into_iter() {
+            let resolution = match find_mined_block(&op.txid) {
+                Some((mined_height, burn_header_hash)) => OpResolution::Confirmed {
+                    blocks: mined_height.saturating_sub(op.submitted_burn_height),
+                    seconds: now.saturating_sub(op.submitted_at),
+                    burn_header_hash,
+                },
+                None if tip_height >= op.expiry() => OpResolution::Failed,
+                None => continue,
+            };
+            resolved.push((op, resolution));
+        }
+        if resolved.is_empty() {
+            return Ok(resolved);
+        }
+
+        let tx = tx_begin_immediate(&mut self.conn)?;
+        for (op, resolution) in resolved.iter() {
+            match resolution {
+                OpResolution::Confirmed {
+                    blocks,
+                    burn_header_hash,
+                    ..
+                } => {
+                    let args: &[&dyn ToSql] = &[
+                        &u64_to_sql(now)?,
+                        &u64_to_sql(op.submitted_burn_height + blocks)?,
+                        burn_header_hash,
+                        &op.txid,
+                    ];
+                    tx.execute(
+                        "UPDATE burnchain_ops SET status = 'confirmed', confirmed_at = ?1, confirmed_burn_height = ?2, confirmed_burn_header_hash = ?3 WHERE txid = ?4",
+                        args,
+                    )?;
+                }
+                OpResolution::Failed => {
+                    tx.execute(
+                        "UPDATE burnchain_ops SET status = 'failed' WHERE txid = ?1",
+                        &[&op.txid],
+                    )?;
+                }
+            }
+        }
+        tx.commit()?;
+
+        for (op, resolution) in resolved.iter() {
+            match resolution {
+                OpResolution::Confirmed {
+                    blocks, seconds, ..
+                } => {
+                    debug!("Submitted burnchain operation was mined";
+                           "txid" => %op.txid,
+                           "op_type" => &op.op_type,
+                           "blocks" => blocks,
+                           "seconds" => seconds);
+                    log_burnchain_op_confirmed(&op.op_type, *blocks, *seconds);
+                }
+                OpResolution::Failed => {
+                    info!("Submitted burnchain operation was not mined";
+                          "txid" => %op.txid,
+                          "op_type" => &op.op_type,
+                          "submitted_burn_height" => op.submitted_burn_height,
+                          "expiry" => op.expiry());
+                    log_burnchain_op_failed(&op.op_type);
+                }
+            }
+            if op
+                .run_id
+                .as_ref()
+                .is_some_and(|run_id| *run_id != self.run_id)
+            {
+                info!("Resolved burnchain operation intent from before the node restarted";
+                      "txid" => %op.txid,
+                      "op_type" => &op.op_type,
+                      "mined" => matches!(resolution, OpResolution::Confirmed { .. }));
+            }
+        }
+        self.publish_success_rates()?;
+        Ok(resolved)
+    }
+
+    /// Record how the confirmed block commits whose sortitions were not processed yet fared.
+    /// `find_outcome` returns the outcome of a commit mined in a burn block, or None if that
+    /// block's sortition is not processed, or no longer canonical.
+    /// Returns the commits whose outcome was recorded by this call.
+    pub fn update_sortition_outcomes<F>(
+        &mut self,
+        mut find_outcome: F,
+    ) -> Result<Vec<(Txid, SortitionOutcome)>, DBError>
+    where
+        F: FnMut(&Txid, &BurnchainHeaderHash) -> Option<SortitionOutcome>,
+    {
+        let undecided: Vec<SubmittedOpRow> = query_rows(
+            &self.conn,
+            "SELECT * FROM burnchain_ops WHERE op_type = ?1 AND status = 'confirmed'
+             AND confirmed_burn_header_hash IS NOT NULL AND sortition_outcome IS NULL",
+            &[BLOCK_COMMIT_OP_TYPE],
+        )?;
+        let mut decided = vec![];
+        for SubmittedOpRow(op) in undecided.into_iter() {
+            let Some(burn_header_hash) = op.burn_header_hash else {
+                continue;
+            };
+            if let Some(outcome) = find_outcome(&op.txid, &burn_header_hash) {
+                decided.push((op.txid, outcome));
+            }
+        }
+        if decided.is_empty() {
+            return Ok(decided);
+        }
+
+        let tx = tx_begin_immediate(&mut self.conn)?;
+        for (txid, outcome) in decided.iter() {
+            let args: &[&dyn ToSql] = &[&outcome.as_str(), txid];
+            tx.execute(
+                "UPDATE burnchain_ops SET sortition_outcome = ?1 WHERE txid = ?2",
+                args,
+            )?;
+        }
+        tx.commit()?;
+
+        for (txid, outcome) in decided.iter() {
+            info!("Sortition outcome of submitted block commit";
+                  "txid" => %txid,
+                  "outcome" => outcome.as_str());
+        }
+        Ok(decided)
+    }
+
+    /// Outcomes of all the block commits on record, and the `limit` most recently submitted
+    /// operations, newest first
+    pub fn get_submitted_ops(&self, limit: u32) -> Result<SubmittedBurnchainOpsResponse, DBError> {
+        query_submitted_ops(&self.conn, limit)
+    }
+
+    /// Number of confirmed and failed operations of each type, over the whole record
+    pub fn get_outcomes(&self) -> Result<Vec<OpTypeOutcomes>, DBError> {
+        query_rows(
+            &self.conn,
+            "SELECT op_type,
+                    SUM(CASE WHEN status = 'confirmed' THEN 1 ELSE 0 END) AS confirmed,
+                    SUM(CASE WHEN status = 'failed' THEN 1 ELSE 0 END) AS failed
+             FROM burnchain_ops GROUP BY op_type ORDER BY op_type",
+            NO_PARAMS,
+        )
+    }
+
+    fn publish_success_rates(&self) -> Result<(), DBError> {
+        for outcomes in self.get_outcomes()?.iter() {
+            if let Some(rate) = outcomes.success_rate() {
+                set_burnchain_op_success_rate(&outcomes.op_type, rate);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use stacks::burnchains::Txid;
+
+    use super::*;
+
+    #[test]
+    fn test_op_confirmation_tracking() {
+        let path = std::env::temp_dir().join(format!(
+            "test_op_confirmation_tracking-{}.sqlite",
+            rand::random::<u64>()
+        ));
+        let path = path.to_str().unwrap();
+        let mut tracker = OpConfirmationTracker::open(path).unwrap();
+
+        let commit_1 = Txid([0x01; 32]);
+        let commit_2 = Txid([0x02; 32]);
+        let commit_rbf = Txid([0x03; 32]);
+        let key_register = Txid([0x04; 32]);
+        tracker
+            .record_submitted(&commit_1, "LeaderBlockCommit", 100, 1000, &[], None)
+            .unwrap();
+        tracker
+            .record_submitted(&key_register, "LeaderKeyRegister", 100, 1000, &[], None)
+            .unwrap();
+        tracker
+            .record_submitted(&commit_2, "LeaderBlockCommit", 101, 1600, &[], None)
+            .unwrap();
+        // commit_2 is replaced by fee
+        tracker
+            .record_submitted(
+                &commit_rbf,
+                "LeaderBlockCommit",
+                101,
+                1700,
+                &[commit_2, commit_rbf],
+                None,
+            )
+            .unwrap();
+        assert_eq!(tracker.get_pending().unwrap().len(), 3);
+
+        // nothing is mined in the next block
+        let mut mined: HashMap<Txid, (u64, BurnchainHeaderHash)> = HashMap::new();
+        assert!(tracker
+            .update(101, 1650, |txid| mined.get(txid).cloned())
+            .unwrap()
+            .is_empty());
+
+        mined.insert(commit_1, (102, BurnchainHeaderHash([0x66; 32])));
+        mined.insert(commit_rbf, (103, BurnchainHeaderHash([0x67; 32])));
+        let resolved = tracker
+            .update(103, 2200, |txid| mined.get(txid).cloned())
+            .unwrap();
+        let resolved: HashMap<_, _> = resolved
+            .into_iter()
+            .map(|(op, resolution)| (op.txid, resolution))
+            .collect();
+        assert_eq!(
+            resolved.get(&commit_1),
+            Some(&OpResolution::Confirmed {
+                blocks: 2,
+                seconds: 1200,
+                burn_header_hash: BurnchainHeaderHash([0x66; 32]),
+            })
+        );
+        assert_eq!(
+            resolved.get(&commit_rbf),
+            Some(&OpResolution::Confirmed {
+                blocks: 2,
+                seconds: 500,
+                burn_header_hash: BurnchainHeaderHash([0x67; 32]),
+            })
+        );
+        assert_eq!(resolved.len(), 2);
+
+        // the key registration is never mined
+        let resolved = tracker
+            .update(100 + OP_CONFIRMATION_EXPIRY_BLOCKS, 9000, |_| None)
+            .unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].0.txid, key_register);
+        assert_eq!(resolved[0].1, OpResolution::Failed);
+        assert!(tracker.get_pending().unwrap().is_empty());
+
+        // outcomes survive a restart, and replaced commits are not failures
+        drop(tracker);
+        let tracker = OpConfirmationTracker::open(path).unwrap();
+        let outcomes = tracker.get_outcomes().unwrap();
+        assert_eq!(
+            outcomes,
+            vec![
+                OpTypeOutcomes {
+                    op_type: "LeaderBlockCommit".into(),
+                    confirmed: 2,
+                    failed: 0,
+                },
+                OpTypeOutcomes {
+                    op_type: "LeaderKeyRegister".into(),
+                    confirmed: 0,
+                    failed: 1,
+                },
+            ]
+        );
+        assert_eq!(outcomes[0].success_rate(), Some(1.0));
+        assert_eq!(outcomes[1].success_rate(), Some(0.0));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    fn make_intent(txid: Txid, op_type: &str, target_burn_height: u64) -> OpIntent {
+        OpIntent {
+            txid,
+            op_type: op_type.to_string(),
+            payload_digest: Sha256Sum::from_data(&txid.0),
+            key_id: Hash160([0x11; 20]),
+            target_burn_height,
+            expires_burn_height: target_burn_height,
+            recorded_at: 1000 + target_burn_height,
+            run_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_op_intents_survive_restart() {
+        let path = std::env::temp_dir().join(format!(
+            "test_op_intents_survive_restart-{}.sqlite",
+            rand::random::<u64>()
+        ));
+        let path = path.to_str().unwrap();
+
+        let commit = make_intent(Txid([0x01; 32]), "LeaderBlockCommit", 101);
+        let commit_rbf = make_intent(Txid([0x02; 32]), "LeaderBlockCommit", 101);
+        let unsent = make_intent(Txid([0x03; 32]), "LeaderKeyRegister", 101);
+        let key_id = commit.key_id;
+        {
+            let mut tracker = OpConfirmationTracker::open_for_run(path, "run-1").unwrap();
+            tracker.record_intent(&commit, 100, &[]).unwrap();
+            tracker
+                .record_submitted(&commit.txid, "LeaderBlockCommit", 100, 1101, &[], Some(101))
+                .unwrap();
+            tracker
+                .record_intent(&commit_rbf, 100, &[commit.txid])
+                .unwrap();
+            tracker.record_intent(&unsent, 100, &[]).unwrap();
+            tracker.abandon(&unsent.txid).unwrap();
+
+            // intents recorded in the same run never conflict
+            assert_eq!(
+                tracker
+                    .find_conflict("LeaderBlockCommit", &key_id, 101)
+                    .unwrap(),
+                None
+            );
+            let pending = tracker.get_pending().unwrap();
+            assert_eq!(pending.len(), 1);
+            assert_eq!(pending[0].txid, commit_rbf.txid);
+            assert_eq!(pending[0].run_id.as_deref(), Some("run-1"));
+            assert!(tracker.get_unresolved().unwrap().is_empty());
+
+            // recording the broadcast keeps the intent
+            tracker
+                .record_submitted(
+                    &commit_rbf.txid,
+                    "LeaderBlockCommit",
+                    100,
+                    1102,
+                    &[commit.txid],
+                    Some(101),
+                )
+                .unwrap();
+            let statuses: Vec<_> = tracker
+                .get_submitted_ops(10)
+                .unwrap()
+                .ops
+                .into_iter()
+                .map(|op| (op.txid, op.status))
+                .collect();
+            assert_eq!(
+                statuses,
+                vec![
+                    (commit_rbf.txid, "pending".to_string()),
+                    (unsent.txid, "abandoned".to_string()),
+                    (commit.txid, "replaced".to_string()),
+                ]
+            );
+        }
+
+        // the node crashes before seeing commit_rbf mined
+        let tracker = OpConfirmationTracker::open_for_run(path, "run-2").unwrap();
+        let unresolved = tracker.get_unresolved().unwrap();
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(unresolved[0].txid, commit_rbf.txid);
+        assert_eq!(unresolved[0].payload_digest, commit_rbf.payload_digest);
+        assert_eq!(
+            unresolved[0].to_rpc("run-2").status,
+            BurnchainOpIntentStatus::Unresolved
+        );
+        assert_eq!(
+            unresolved[0].to_rpc("run-1").status,
+            BurnchainOpIntentStatus::InFlight
+        );
+
+        // so another commit for the same burn block from the same key conflicts with it...
+        let conflict = tracker
+            .find_conflict("LeaderBlockCommit", &key_id, 101)
+            .unwrap();
+        assert_eq!(conflict.map(|intent| intent.txid), Some(commit_rbf.txid));
+        // ...but not one for another block, of another type, or from another key
+        assert!(tracker
+            .find_conflict("LeaderBlockCommit", &key_id, 102)
+            .unwrap()
+            .is_none());
+        assert!(tracker
+            .find_conflict("LeaderKeyRegister", &key_id, 101)
+            .unwrap()
+            .is_none());
+        assert!(tracker
+            .find_conflict("LeaderBlockCommit", &Hash160([0x22; 20]), 101)
+            .unwrap()
+            .is_none());
+
+        // the RPC server reads the same record
+        let reader = OpConfirmationsReader {
+            path: path.to_string(),
+        };
+        let intents = reader.get_open_intents().unwrap().intents;
+        assert_eq!(intents.len(), 1);
+        assert_eq!(intents[0].txid, commit_rbf.txid);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_op_intents_are_resolved_by_update() {
+        let path = std::env::temp_dir().join(format!(
+            "test_op_intents_are_resolved_by_update-{}.sqlite",
+            rand::random::<u64>()
+        ));
+        let path = path.to_str().unwrap();
+
+        let mined = make_intent(Txid([0x01; 32]), "LeaderBlockCommit", 101);
+        let lost = make_intent(Txid([0x02; 32]), "LeaderKeyRegister", 101);
+        let later = make_intent(Txid([0x03; 32]), "LeaderBlockCommit", 103);
+        {
+            let mut tracker = OpConfirmationTracker::open_for_run(path, "run-1").unwrap();
+            for intent in [&mined, &lost, &later] {
+                tracker
+                    .record_intent(intent, intent.target_burn_height - 1, &[])
+                    .unwrap();
+            }
+        }
+
+        let mut tracker = OpConfirmationTracker::open_for_run(path, "run-2").unwrap();
+        assert_eq!(tracker.get_unresolved().unwrap().len(), 3);
+
+        // nothing is resolved before the target block
+        assert!(tracker.update(100, 2000, |_| None).unwrap().is_empty());
+
+        let resolved: HashMap<Txid, OpResolution> = tracker
+            .update(101, 2100, |txid| {
+                (*txid == mined.txid).then(|| (101, BurnchainHeaderHash([0x66; 32])))
+            })
+            .unwrap()
+            .into_iter()
+            .map(|(op, resolution)| (op.txid, resolution))
+            .collect();
+        assert_eq!(resolved.len(), 2);
+        assert!(matches!(
+            resolved.get(&mined.txid),
+            Some(OpResolution::Confirmed { blocks: 1, .. })
+        ));
+        assert_eq!(resolved.get(&lost.txid), Some(&OpResolution::Failed));
+
+        // the later commit stays unresolved until its target block has passed
+        let unresolved = tracker.get_unresolved().unwrap();
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(unresolved[0].txid, later.txid);
+        assert!(tracker
+            .find_conflict("LeaderBlockCommit", &later.key_id, 103)
+            .unwrap()
+            .is_some());
+
+        tracker.update(103, 2300, |_| None).unwrap();
+        assert!(tracker.get_pending().unwrap().is_empty());
+        assert!(tracker
+            .find_conflict("LeaderBlockCommit", &later.key_id, 103)
+            .unwrap()
+            .is_none());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_op_expiry() {
+        let path =
+            std::env::temp_dir().join(format!("test_op_expiry-{}.sqlite", rand::random::<u64>()));
+        let path = path.to_str().unwrap();
+
+        // a record from before operations had expiries
+        {
+            let conn = sqlite_open(
+                path,
+                OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+                false,
+            )
+            .unwrap();
+            conn.execute(
+                "CREATE TABLE burnchain_ops(
+                    txid TEXT PRIMARY KEY NOT NULL,
+                    op_type TEXT NOT NULL,
+                    submitted_at INTEGER NOT NULL,
+                    submitted_burn_height INTEGER NOT NULL,
+                    status TEXT NOT NULL,
+                    confirmed_at INTEGER,
+                    confirmed_burn_height INTEGER
+                );",
+                NO_PARAMS,
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO burnchain_ops (txid, op_type, submitted_at, submitted_burn_height, status)
+                 VALUES (?1, 'LeaderKeyRegister', 1000, 100, 'pending')",
+                &[&Txid([0x01; 32])],
+            )
+            .unwrap();
+        }
+
+        let mut tracker = OpConfirmationTracker::open(path).unwrap();
+        let old_op = Txid([0x01; 32]);
+        let commit = Txid([0x02; 32]);
+        tracker
+            .record_submitted(&commit, "LeaderBlockCommit", 100, 1000, &[], Some(101))
+            .unwrap();
+        let pending = tracker.get_pending().unwrap();
+        assert_eq!(pending.len(), 2);
+        let expiries: HashMap<_, _> = pending
+            .iter()
+            .map(|op| (op.txid, (op.expires_burn_height, op.expiry())))
+            .collect();
+        assert_eq!(
+            expiries.get(&old_op),
+            Some(&(None, 100 + OP_CONFIRMATION_EXPIRY_BLOCKS))
+        );
+        assert_eq!(expiries.get(&commit), Some(&(Some(101), 101)));
+
+        // the commit misses the block it was built for
+        let resolved = tracker.update(101, 1600, |_| None).unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].0.txid, commit);
+        assert_eq!(resolved[0].1, OpResolution::Failed);
+
+        // operations without an expiry of their own keep the default one
+        assert!(tracker
+            .update(100 + OP_CONFIRMATION_EXPIRY_BLOCKS - 1, 2000, |_| None)
+            .unwrap()
+            .is_empty());
+        let resolved = tracker
+            .update(100 + OP_CONFIRMATION_EXPIRY_BLOCKS, 2000, |_| None)
+            .unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].0.txid, old_op);
+
+        // reopening an upgraded record works
+        drop(tracker);
+        let tracker = OpConfirmationTracker::open(path).unwrap();
+        assert!(tracker.get_pending().unwrap().is_empty());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_sortition_outcomes() {
+        let path = std::env::temp_dir().join(format!(
+            "test_sortition_outcomes-{}.sqlite",
+            rand::random::<u64>()
+        ));
+        let path = path.to_str().unwrap();
+        let mut tracker = OpConfirmationTracker::open(path).unwrap();
+
+        let won = Txid([0x01; 32]);
+        let lost = Txid([0x02; 32]);
+        let rejected = Txid([0x03; 32]);
+        let not_mined = Txid([0x04; 32]);
+        let undecided = Txid([0x05; 32]);
+        let key_register = Txid([0x06; 32]);
+        for (i, txid) in [won, lost, rejected, not_mined, undecided]
+            .iter()
+            .enumerate()
+        {
+            let height = 100 + i as u64;
+            tracker
+                .record_submitted(
+                    txid,
+                    BLOCK_COMMIT_OP_TYPE,
+                    height,
+                    1000 + i as u64,
+                    &[],
+                    Some(height + 1),
+                )
+                .unwrap();
+        }
+        tracker
+            .record_submitted(&key_register, "LeaderKeyRegister", 100, 999, &[], None)
+            .unwrap();
+
+        // everything but `not_mined` is mined in the block after it was submitted
+        let mined_block = |txid: &Txid| {
+            if *txid == not_mined {
+                return None;
+            }
+            let height = if *txid == key_register {
+                101
+            } else {
+                101 + u64::from(txid.0[0]) - 1
+            };
+            Some((height, BurnchainHeaderHash([txid.0[0]; 32])))
+        };
+        tracker.update(105, 2000, mined_block).unwrap();
+
+        // the sortition that mined `undecided` is not processed yet
+        let decided = tracker
+            .update_sortition_outcomes(|txid, burn_header_hash| {
+                assert_eq!(burn_header_hash.0[0], txid.0[0]);
+                assert_ne!(*txid, key_register);
+                match txid.0[0] {
+                    0x01 => Some(SortitionOutcome::Won),
+                    0x02 => Some(SortitionOutcome::Lost),
+                    0x03 => Some(SortitionOutcome::Rejected),
+                    _ => None,
+                }
+            })
+            .unwrap();
+        assert_eq!(decided.len(), 3);
+
+        let response = tracker.get_submitted_ops(3).unwrap();
+        assert_eq!(
+            response.block_commits,
+            BlockCommitOutcomes {
+                won: 1,
+                lost: 1,
+                rejected: 1,
+                not_mined: 1,
+            }
+        );
+        let txids: Vec<_> = response.ops.iter().map(|op| op.txid).collect();
+        assert_eq!(txids, vec![undecided, not_mined, rejected]);
+        assert_eq!(response.ops[0].status, "confirmed");
+        assert_eq!(response.ops[0].burn_block_height, Some(105));
+        assert_eq!(response.ops[0].sortition_outcome, None);
+        assert_eq!(response.ops[1].status, "failed");
+        assert_eq!(response.ops[1].burn_header_hash, None);
+        assert_eq!(
+            response.ops[2].sortition_outcome,
+            Some(SortitionOutcome::Rejected)
+        );
+
+        // decided commits are not looked up again
+        let decided = tracker
+            .update_sortition_outcomes(|txid, _| {
+                assert_eq!(*txid, undecided);
+                Some(SortitionOutcome::Lost)
+            })
+            .unwrap();
+        assert_eq!(decided, vec![(undecided, SortitionOutcome::Lost)]);
+
+        // the RPC server reads the same record
+        let mut config = crate::tests::new_test_conf();
+        config.node.working_dir = std::env::temp_dir()
+            .join(format!("test_sortition_outcomes-{}", rand::random::<u64>()))
+            .to_str()
+            .unwrap()
+            .to_string();
+        let reader = OpConfirmationsReader::new(&config);
+        assert_eq!(
+            reader.get_submitted_ops(10).unwrap().ops,
+            Vec::<SubmittedBurnchainOp>::new()
+        );
+        let reader = OpConfirmationsReader {
+            path: path.to_string(),
+        };
+        let response = reader.get_submitted_ops(10).unwrap();
+        assert_eq!(response.ops.len(), 6);
+        assert_eq!(response.block_commits.lost, 2);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
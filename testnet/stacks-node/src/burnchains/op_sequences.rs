@@ -0,0 +1,298 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Proptest strategies for sequences of burnchain operations.
+//!
+//! `op_sequence()` generates a run of burn blocks, each holding leader key registrations,
+//! block commits and STX transfers.  The strategy first draws a plan of abstract operations and
+//! then turns it into concrete `BlockstackOperationType`s, so that every block commit points at
+//! a key registered in an earlier block of the same sequence.  Every generated operation is
+//! therefore valid, which lets property tests check sortition invariants rather than chase
+//! rejected operations.  The sequences are fed through `TestBurnchainController`.
+
+use std::collections::HashSet;
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+use proptest::test_runner::TestCaseError;
+use stacks::burnchains::{BurnchainSigner, Txid};
+use stacks::chainstate::burn::operations::{
+    BlockstackOperationType, LeaderBlockCommitOp, LeaderKeyRegisterOp, TransferStxOp,
+};
+use stacks::chainstate::stacks::address::PoxAddress;
+use stacks::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, STACKS_EPOCH_3_0_MARKER};
+use stacks_common::address::C32_ADDRESS_VERSION_TESTNET_SINGLESIG;
+use stacks_common::types::chainstate::{
+    BlockHeaderHash, BurnchainHeaderHash, StacksAddress, VRFSeed,
+};
+use stacks_common::util::hash::Hash160;
+use stacks_common::util::vrf::{VRFPrivateKey, VRFPublicKey};
+
+use super::test_harness::{ScriptedBlock, TestBurnchainController};
+use super::BurnchainController;
+use crate::tests::new_test_conf;
+
+/// Number of distinct STX accounts that transfers move tokens between
+pub const OP_SEQUENCE_ACCOUNTS: usize = 8;
+/// Largest burn a generated block commit makes.  Kept small so that total burns never overflow.
+pub const OP_SEQUENCE_MAX_BURN_FEE: u64 = 10_000;
+
+/// An operation to generate, before it is bound to a block
+#[derive(Debug, Clone)]
+pub enum PlannedOp {
+    /// Register a fresh VRF key
+    RegisterKey,
+    /// Commit to a block using one of the keys registered in an earlier block.
+    /// Dropped if no key has been registered yet.
+    Commit { key: Index, burn_fee: u64 },
+    /// Transfer `ustx` between two distinct accounts
+    TransferStx {
+        sender: usize,
+        recipient_offset: usize,
+        ustx: u128,
+    },
+}
+
+pub fn planned_op() -> impl Strategy<Value = PlannedOp> {
+    prop_oneof![
+        2 => Just(PlannedOp::RegisterKey),
+        3 => (any::<Index>(), 1..=OP_SEQUENCE_MAX_BURN_FEE)
+            .prop_map(|(key, burn_fee)| PlannedOp::Commit { key, burn_fee }),
+        2 => (0..OP_SEQUENCE_ACCOUNTS, 1..OP_SEQUENCE_ACCOUNTS, 1..1_000_000u128).prop_map(
+            |(sender, recipient_offset, ustx)| PlannedOp::TransferStx {
+                sender,
+                recipient_offset,
+                ustx,
+            }
+        ),
+    ]
+}
+
+/// Plans for up to `max_blocks` burn blocks of up to `max_ops_per_block` operations each.
+/// Blocks may be empty.
+pub fn planned_blocks(
+    max_blocks: usize,
+    max_ops_per_block: usize,
+) -> impl Strategy<Value = Vec<Vec<PlannedOp>>> {
+    prop::collection::vec(
+        prop::collection::vec(planned_op(), 0..=max_ops_per_block),
+        1..=max_blocks,
+    )
+}
+
+/// Valid operations for up to `max_blocks` consecutive burn blocks, the first of which will be
+/// mined at `first_block_height`.  Element `i` holds the operations for block
+/// `first_block_height + i`.
+pub fn op_sequence(
+    first_block_height: u64,
+    max_blocks: usize,
+    max_ops_per_block: usize,
+) -> impl Strategy<Value = Vec<Vec<BlockstackOperationType>>> {
+    planned_blocks(max_blocks, max_ops_per_block).prop_map(move |plans| {
+        let mut builder = OpSequenceBuilder::default();
+        plans
+            .iter()
+            .enumerate()
+            .map(|(i, plan)| builder.build_block(first_block_height + i as u64, plan))
+            .collect()
+    })
+}
+
+fn op_sequence_bytes(n: u64) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes[0..8].copy_from_slice(&n.to_be_bytes());
+    bytes
+}
+
+fn op_sequence_account(n: usize) -> StacksAddress {
+    StacksAddress {
+        version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        bytes: Hash160([n as u8 + 1; 20]),
+    }
+}
+
+/// Turns plans into concrete operations, remembering where each leader key was registered so
+/// that later block commits can point at it
+#[derive(Debug, Default)]
+pub struct OpSequenceBuilder {
+    /// Source of unique txids, VRF keys and block hashes
+    last_id: u64,
+    /// (block height, vtxindex) of every key registered so far
+    registered_keys: Vec<(u64, u32)>,
+}
+
+impl OpSequenceBuilder {
+    fn next_id(&mut self) -> [u8; 32] {
+        self.last_id += 1;
+        op_sequence_bytes(self.last_id)
+    }
+
+    /// Build the operations for the block mined at `block_height`.  Keys registered in this
+    /// block only become available to commits in later blocks.
+    pub fn build_block(
+        &mut self,
+        block_height: u64,
+        plan: &[PlannedOp],
+    ) -> Vec<BlockstackOperationType> {
+        let mut ops = vec![];
+        let mut new_keys = vec![];
+        for planned in plan.iter() {
+            let vtxindex = ops.len() as u32;
+            let op = match planned {
+                PlannedOp::RegisterKey => {
+                    let id = self.next_id();
+                    let private_key = VRFPrivateKey::from_bytes(&id)
+                        .expect("FATAL: failed to make VRF private key");
+                    new_keys.push((block_height, vtxindex));
+                    BlockstackOperationType::LeaderKeyRegister(LeaderKeyRegisterOp {
+                        consensus_hash: FIRST_BURNCHAIN_CONSENSUS_HASH,
+                        public_key: VRFPublicKey::from_private(&private_key),
+                        memo: vec![],
+                        txid: Txid(id),
+                        vtxindex,
+                        block_height: 0,
+                        burn_header_hash: BurnchainHeaderHash::zero(),
+                    })
+                }
+                PlannedOp::Commit { key, burn_fee } => {
+                    if self.registered_keys.is_empty() {
+                        continue;
+                    }
+                    let (key_block_height, key_vtxindex) =
+                        self.registered_keys[key.index(self.registered_keys.len())];
+                    let id = self.next_id();
+                    BlockstackOperationType::LeaderBlockCommit(LeaderBlockCommitOp {
+                        sunset_burn: 0,
+                        block_header_hash: BlockHeaderHash(id),
+                        new_seed: VRFSeed(id),
+                        parent_block_ptr: 0,
+                        parent_vtxindex: 0,
+                        key_block_ptr: key_block_height as u32,
+                        key_vtxindex: key_vtxindex as u16,
+                        memo: vec![STACKS_EPOCH_3_0_MARKER],
+                        burn_fee: *burn_fee,
+                        input: (Txid(id), 0),
+                        apparent_sender: BurnchainSigner(format!(
+                            "op-sequence-miner-{}-{}",
+                            key_block_height, key_vtxindex
+                        )),
+                        commit_outs: vec![PoxAddress::standard_burn_address(false)],
+                        txid: Txid(id),
+                        vtxindex,
+                        block_height: 0,
+                        burn_parent_modulus: 0,
+                        burn_header_hash: BurnchainHeaderHash::zero(),
+                    })
+                }
+                PlannedOp::TransferStx {
+                    sender,
+                    recipient_offset,
+                    ustx,
+                } => {
+                    let id = self.next_id();
+                    BlockstackOperationType::TransferStx(TransferStxOp {
+                        sender: op_sequence_account(*sender),
+                        recipient: op_sequence_account(
+                            (sender + recipient_offset) % OP_SEQUENCE_ACCOUNTS,
+                        ),
+                        transfered_ustx: *ustx,
+                        memo: vec![],
+                        txid: Txid(id),
+                        vtxindex,
+                        block_height: 0,
+                        burn_header_hash: BurnchainHeaderHash::zero(),
+                    })
+                }
+            };
+            ops.push(op);
+        }
+        self.registered_keys.extend(new_keys);
+        ops
+    }
+}
+
+/// Mine `blocks` with the test burnchain controller, checking after each block that:
+/// * every generated operation was accepted into it,
+/// * a sortition happened if and only if the block held a block commit,
+/// * the sortition winner is one of the block's commits, and
+/// * the sortition count and total burn only move forward.
+pub fn run_op_sequence(
+    first_block_height: u64,
+    blocks: &[Vec<BlockstackOperationType>],
+) -> Result<(), TestCaseError> {
+    let mut controller = TestBurnchainController::new(new_test_conf());
+    controller.start(None).unwrap();
+    prop_assert_eq!(controller.get_headers_height() + 1, first_block_height);
+
+    for block_ops in blocks.iter() {
+        controller.script_block(ScriptedBlock::with_ops(block_ops.clone()));
+    }
+
+    let mut parent = controller.get_chain_tip().block_snapshot;
+    for (i, block_ops) in blocks.iter().enumerate() {
+        let block_height = first_block_height + i as u64;
+        let tip = controller.mine_block();
+        let snapshot = &tip.block_snapshot;
+        prop_assert_eq!(snapshot.block_height, block_height);
+
+        let accepted: HashSet<_> = tip
+            .state_transition
+            .accepted_ops
+            .iter()
+            .map(|op| op.txid())
+            .collect();
+        for op in block_ops.iter() {
+            prop_assert!(
+                accepted.contains(op.txid_ref()),
+                "operation {:?} was not accepted at height {}",
+                op,
+                block_height
+            );
+        }
+
+        let commits: Vec<_> = block_ops
+            .iter()
+            .filter_map(|op| match op {
+                BlockstackOperationType::LeaderBlockCommit(commit) => Some(&commit.txid),
+                _ => None,
+            })
+            .collect();
+        prop_assert_eq!(snapshot.sortition, !commits.is_empty());
+        if snapshot.sortition {
+            prop_assert!(
+                commits.contains(&&snapshot.winning_block_txid),
+                "winner {} at height {} is not one of the block's commits",
+                &snapshot.winning_block_txid,
+                block_height
+            );
+            prop_assert_eq!(snapshot.num_sortitions, parent.num_sortitions + 1);
+            prop_assert!(snapshot.total_burn > parent.total_burn);
+        } else {
+            prop_assert_eq!(snapshot.num_sortitions, parent.num_sortitions);
+            prop_assert_eq!(snapshot.total_burn, parent.total_burn);
+        }
+        parent = snapshot.clone();
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn sortitions_follow_generated_op_sequences(blocks in op_sequence(1, 12, 6)) {
+        run_op_sequence(1, &blocks)?;
+    }
+}
@@ -0,0 +1,306 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tracks how far the burnchain controller has come in starting up, and serves it over HTTP,
+//! so that orchestration systems can hold back services that depend on the node (a miner, a
+//! signer) until the burnchain is ready enough for them, rather than until the process is up.
+//!
+//! Startup goes through these levels, in order:
+//!
+//! * `starting`: the controller was created.
+//! * `dbs_migrated`: the existing chainstate databases were migrated to the current schema.
+//! * `headers_synced`: the burnchain headers were synced up to the bitcoin node's tip.
+//! * `sortitions_caught_up`: sortitions were processed up to the synced headers.
+//!
+//! A level, once reached, is kept for the life of the process, even if the burnchain later
+//! moves ahead of the node again.  The levels are served on `burnchain.readiness_bind`:
+//!
+//! * `GET /v1/readiness`: the level reached, and when each level was reached, as a Unix
+//!   timestamp.
+//! * `GET /v1/readiness/<level>`: the same, with status 200 if `<level>` was reached, and 503
+//!   if not, for use as a readiness probe.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::{fmt, io};
+
+use lazy_static::lazy_static;
+use serde_json::{json, Map, Value};
+use stacks_common::util::get_epoch_time_secs;
+
+use super::json_server::start_json_server;
+
+lazy_static! {
+    /// Readiness by bind address.  The burnchain controller is re-created when the run loop
+    /// changes (e.g. at the Nakamoto transition), but the levels reached are kept.
+    static ref READINESS: Mutex<HashMap<String, BurnchainReadiness>> = Mutex::new(HashMap::new());
+}
+
+/// How far the burnchain controller has come in starting up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ReadinessLevel {
+    Starting,
+    DbsMigrated,
+    HeadersSynced,
+    SortitionsCaughtUp,
+}
+
+impl ReadinessLevel {
+    pub const ALL: [ReadinessLevel; 4] = [
+        ReadinessLevel::Starting,
+        ReadinessLevel::DbsMigrated,
+        ReadinessLevel::HeadersSynced,
+        ReadinessLevel::SortitionsCaughtUp,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReadinessLevel::Starting => "starting",
+            ReadinessLevel::DbsMigrated => "dbs_migrated",
+            ReadinessLevel::HeadersSynced => "headers_synced",
+            ReadinessLevel::SortitionsCaughtUp => "sortitions_caught_up",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ReadinessLevel> {
+        Self::ALL.iter().find(|level| level.name() == name).copied()
+    }
+}
+
+impl fmt::Display for ReadinessLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug)]
+struct ReadinessState {
+    /// When each level was reached, as a Unix timestamp
+    reached_at: HashMap<ReadinessLevel, u64>,
+}
+
+impl ReadinessState {
+    fn level(&self) -> ReadinessLevel {
+        self.reached_at
+            .keys()
+            .max()
+            .copied()
+            .unwrap_or(ReadinessLevel::Starting)
+    }
+}
+
+/// The burnchain controller's startup progress, shared with the server reporting it
+#[derive(Debug, Clone)]
+pub struct BurnchainReadiness {
+    state: Arc<Mutex<ReadinessState>>,
+}
+
+impl Default for BurnchainReadiness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BurnchainReadiness {
+    pub fn new() -> Self {
+        let mut reached_at = HashMap::new();
+        reached_at.insert(ReadinessLevel::Starting, get_epoch_time_secs());
+        Self {
+            state: Arc::new(Mutex::new(ReadinessState { reached_at })),
+        }
+    }
+
+    /// Get the readiness served at `bind`, starting its HTTP server if it is not yet running
+    pub fn get_or_start(bind: &str) -> io::Result<Self> {
+        let mut registry = READINESS
+            .lock()
+            .expect("FATAL: burnchain readiness registry poisoned");
+        if let Some(readiness) = registry.get(bind) {
+            return Ok(readiness.clone());
+        }
+        let readiness = Self::new();
+        let local_addr = readiness.start_server(bind)?;
+        info!("Burnchain readiness: serving readiness probes"; "bind" => %local_addr);
+        registry.insert(bind.to_string(), readiness.clone());
+        Ok(readiness)
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, ReadinessState> {
+        self.state
+            .lock()
+            .expect("FATAL: burnchain readiness state poisoned")
+    }
+
+    /// The highest level reached
+    pub fn level(&self) -> ReadinessLevel {
+        self.state().level()
+    }
+
+    /// Whether `level` was reached
+    pub fn is_ready(&self, level: ReadinessLevel) -> bool {
+        self.level() >= level
+    }
+
+    /// Record that `level`, and so every level below it, was reached.  Does nothing if it
+    /// already was.
+    pub fn advance(&self, level: ReadinessLevel) {
+        let mut state = self.state();
+        if state.level() >= level {
+            return;
+        }
+        let now = get_epoch_time_secs();
+        for lower_level in ReadinessLevel::ALL.iter().filter(|l| **l <= level) {
+            state.reached_at.entry(*lower_level).or_insert(now);
+        }
+        let started_at = state
+            .reached_at
+            .get(&ReadinessLevel::Starting)
+            .copied()
+            .unwrap_or(now);
+        info!("Burnchain readiness: reached a new level";
+              "level" => %level,
+              "secs_since_start" => now.saturating_sub(started_at));
+    }
+
+    fn status(&self) -> Value {
+        let state = self.state();
+        let mut reached_at = Map::new();
+        for level in ReadinessLevel::ALL.iter() {
+            reached_at.insert(
+                level.name().to_string(),
+                json!(state.reached_at.get(level).copied()),
+            );
+        }
+        json!({
+            "level": state.level().name(),
+            "reached_at": reached_at,
+        })
+    }
+
+    /// Handle a readiness request.  Returns the HTTP status code and the JSON response body.
+    fn handle(&self, method: &str, path: &str) -> (u16, Value) {
+        if path != "/v1/readiness" && !path.starts_with("/v1/readiness/") {
+            return (404, json!({ "error": "Not found" }));
+        }
+        if method != "GET" {
+            return (405, json!({ "error": "Method not allowed" }));
+        }
+        let level_name = match path.strip_prefix("/v1/readiness/") {
+            Some(level_name) => level_name,
+            None => return (200, self.status()),
+        };
+        match ReadinessLevel::from_name(level_name) {
+            Some(level) if self.is_ready(level) => (200, self.status()),
+            Some(_) => (503, self.status()),
+            None => (
+                404,
+                json!({ "error": format!("Unknown readiness level {}", level_name) }),
+            ),
+        }
+    }
+
+    /// Serve readiness probes on `bind`.  Returns the address actually bound.
+    pub fn start_server(&self, bind: &str) -> io::Result<SocketAddr> {
+        let readiness = self.clone();
+        // readiness requests have no body
+        start_json_server("burnchain-readiness", bind, 0, move |request| {
+            readiness.handle(&request.method, &request.path)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use super::*;
+
+    #[test]
+    fn test_readiness_levels() {
+        let readiness = BurnchainReadiness::new();
+        assert_eq!(readiness.level(), ReadinessLevel::Starting);
+        assert!(readiness.is_ready(ReadinessLevel::Starting));
+        assert!(!readiness.is_ready(ReadinessLevel::DbsMigrated));
+
+        // skipping a level reaches the ones below it too
+        readiness.advance(ReadinessLevel::HeadersSynced);
+        assert_eq!(readiness.level(), ReadinessLevel::HeadersSynced);
+        assert!(readiness.is_ready(ReadinessLevel::DbsMigrated));
+        assert!(!readiness.is_ready(ReadinessLevel::SortitionsCaughtUp));
+
+        // levels are never lost
+        readiness.advance(ReadinessLevel::DbsMigrated);
+        assert_eq!(readiness.level(), ReadinessLevel::HeadersSynced);
+
+        let status = readiness.status();
+        assert_eq!(status["level"], "headers_synced");
+        assert!(status["reached_at"]["dbs_migrated"].is_u64());
+        assert!(status["reached_at"]["sortitions_caught_up"].is_null());
+
+        for level in ReadinessLevel::ALL.iter() {
+            assert_eq!(ReadinessLevel::from_name(level.name()), Some(*level));
+        }
+        assert_eq!(ReadinessLevel::from_name("ready"), None);
+    }
+
+    #[test]
+    fn test_readiness_requests() {
+        let readiness = BurnchainReadiness::new();
+        readiness.advance(ReadinessLevel::DbsMigrated);
+
+        assert_eq!(readiness.handle("GET", "/v1/readiness").0, 200);
+        assert_eq!(readiness.handle("GET", "/v1/readiness/dbs_migrated").0, 200);
+        assert_eq!(
+            readiness.handle("GET", "/v1/readiness/headers_synced").0,
+            503
+        );
+        assert_eq!(readiness.handle("GET", "/v1/readiness/ready").0, 404);
+        assert_eq!(readiness.handle("POST", "/v1/readiness").0, 405);
+        assert_eq!(readiness.handle("GET", "/v2/info").0, 404);
+
+        readiness.advance(ReadinessLevel::SortitionsCaughtUp);
+        assert_eq!(
+            readiness
+                .handle("GET", "/v1/readiness/sortitions_caught_up")
+                .0,
+            200
+        );
+    }
+
+    #[test]
+    fn test_readiness_server() {
+        let readiness = BurnchainReadiness::new();
+        let addr = readiness.start_server("127.0.0.1:0").unwrap();
+
+        let get = |path: &str| {
+            let mut socket = TcpStream::connect(addr).unwrap();
+            write!(socket, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            socket.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = get("/v1/readiness/headers_synced");
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(response.contains("\"level\":\"starting\""));
+
+        readiness.advance(ReadinessLevel::HeadersSynced);
+        let response = get("/v1/readiness/headers_synced");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\"level\":\"headers_synced\""));
+    }
+}
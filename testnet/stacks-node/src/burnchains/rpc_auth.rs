@@ -0,0 +1,349 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Credentials the burnchain controller presents to the bitcoin node's RPC interface.
+//!
+//! The node can authenticate either with HTTP basic auth (`burnchain.username` and a password)
+//! or with a bearer token, for bitcoin nodes that sit behind an authenticating proxy.  The
+//! password or token can be given inline, in a file, or in an environment variable:
+//!
+//! * `burnchain.password`, `burnchain.password_file` or `burnchain.password_env`
+//! * `burnchain.rpc_auth_token`, `burnchain.rpc_auth_token_file` or `burnchain.rpc_auth_token_env`
+//!
+//! A secret file is checked on every request and re-read whenever it changes, so a secret can
+//! be rotated by rewriting the file, without restarting the node.  If the file is missing or
+//! empty (e.g. while it is being rewritten), the last secret read from it is used instead.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use base64::encode;
+use lazy_static::lazy_static;
+
+use crate::config::BurnchainConfig;
+
+lazy_static! {
+    /// The last secret read from each secret file, and the file's modification time and
+    /// length when it was read.
+    static ref SECRET_FILES: Mutex<HashMap<PathBuf, CachedSecret>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone)]
+struct CachedSecret {
+    modified: Option<SystemTime>,
+    len: u64,
+    value: String,
+}
+
+/// Where a password or token is read from
+#[derive(Debug, Clone, PartialEq)]
+pub enum SecretSource {
+    Inline(String),
+    /// Re-read whenever the file changes
+    File(PathBuf),
+    /// Read from the node's environment.  The environment can't change from outside the
+    /// process, so use a file to rotate a secret without restarting.
+    Env(String),
+}
+
+impl SecretSource {
+    /// Pick the source of a secret from its inline, file and environment variable settings,
+    /// of which at most one may be set.
+    fn from_settings(
+        key: &str,
+        inline: &Option<String>,
+        file: &Option<String>,
+        env: &Option<String>,
+    ) -> Result<Option<SecretSource>, String> {
+        match (inline, file, env) {
+            (None, None, None) => Ok(None),
+            (Some(value), None, None) => Ok(Some(SecretSource::Inline(value.clone()))),
+            (None, Some(path), None) => Ok(Some(SecretSource::File(PathBuf::from(path)))),
+            (None, None, Some(var)) => Ok(Some(SecretSource::Env(var.clone()))),
+            _ => Err(format!(
+                "At most one of burnchain.{0}, burnchain.{0}_file and burnchain.{0}_env may be set",
+                key
+            )),
+        }
+    }
+
+    /// Read the current secret
+    pub fn read(&self) -> Result<String, String> {
+        match self {
+            SecretSource::Inline(value) => Ok(value.clone()),
+            SecretSource::File(path) => read_secret_file(path),
+            SecretSource::Env(var) => match std::env::var(var) {
+                Ok(value) if !value.trim().is_empty() => Ok(value.trim().to_string()),
+                Ok(_) => Err(format!("Environment variable {} is empty", var)),
+                Err(e) => Err(format!(
+                    "Failed to read environment variable {}: {}",
+                    var, e
+                )),
+            },
+        }
+    }
+}
+
+/// Read a secret file, unless it is unchanged since it was last read.  Falls back to the last
+/// secret read from it if it can't be read now.
+fn read_secret_file(path: &Path) -> Result<String, String> {
+    let mut cache = SECRET_FILES
+        .lock()
+        .expect("FATAL: secret file cache poisoned");
+    let cached = cache.get(path);
+
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            return match cached {
+                Some(cached) => {
+                    warn!("Failed to stat secret file; using the last secret read from it";
+                          "path" => %path.display(), "error" => %e);
+                    Ok(cached.value.clone())
+                }
+                None => Err(format!(
+                    "Failed to read secret file {}: {}",
+                    path.display(),
+                    e
+                )),
+            };
+        }
+    };
+    let modified = metadata.modified().ok();
+    if let Some(cached) = cached {
+        if cached.modified.is_some() && cached.modified == modified && cached.len == metadata.len()
+        {
+            return Ok(cached.value.clone());
+        }
+    }
+
+    let value = match fs::read_to_string(path) {
+        Ok(contents) if !contents.trim().is_empty() => contents.trim().to_string(),
+        result => {
+            let error = match result {
+                Ok(_) => "file is empty".to_string(),
+                Err(e) => e.to_string(),
+            };
+            return match cached {
+                Some(cached) => {
+                    warn!("Failed to read secret file; using the last secret read from it";
+                          "path" => %path.display(), "error" => %error);
+                    Ok(cached.value.clone())
+                }
+                None => Err(format!(
+                    "Failed to read secret file {}: {}",
+                    path.display(),
+                    error
+                )),
+            };
+        }
+    };
+
+    if cached.is_some() {
+        info!("Reloaded rotated secret file"; "path" => %path.display());
+    }
+    cache.insert(
+        path.to_path_buf(),
+        CachedSecret {
+            modified,
+            len: metadata.len(),
+            value: value.clone(),
+        },
+    );
+    Ok(value)
+}
+
+/// How the burnchain controller authenticates to the bitcoin node's RPC interface
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpcAuth {
+    None,
+    Basic {
+        username: String,
+        password: SecretSource,
+    },
+    Bearer(SecretSource),
+}
+
+impl RpcAuth {
+    /// Work out the RPC credentials from the burnchain config.  Fails if the settings
+    /// conflict.
+    pub fn from_config(config: &BurnchainConfig) -> Result<RpcAuth, String> {
+        let password = SecretSource::from_settings(
+            "password",
+            &config.password,
+            &config.password_file,
+            &config.password_env,
+        )?;
+        let token = SecretSource::from_settings(
+            "rpc_auth_token",
+            &config.rpc_auth_token,
+            &config.rpc_auth_token_file,
+            &config.rpc_auth_token_env,
+        )?;
+
+        match (token, &config.username, password) {
+            (Some(_), _, Some(_)) => {
+                Err("burnchain.rpc_auth_token cannot be combined with a burnchain password".into())
+            }
+            (Some(token), _, None) => Ok(RpcAuth::Bearer(token)),
+            (None, Some(username), Some(password)) => Ok(RpcAuth::Basic {
+                username: username.clone(),
+                password,
+            }),
+            (None, None, Some(SecretSource::Inline(_))) => Ok(RpcAuth::None),
+            (None, None, Some(_)) => {
+                Err("burnchain.username must be set to use a burnchain password".into())
+            }
+            (None, _, None) => Ok(RpcAuth::None),
+        }
+    }
+
+    /// The name of the auth scheme, for logging
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            RpcAuth::None => "none",
+            RpcAuth::Basic { .. } => "basic",
+            RpcAuth::Bearer(_) => "bearer",
+        }
+    }
+
+    /// The value of the `Authorization` header to send with the next request, if any.  Reads
+    /// the secret anew, so that rotated secrets are picked up.
+    pub fn authorization_header(&self) -> Result<Option<String>, String> {
+        match self {
+            RpcAuth::None => Ok(None),
+            RpcAuth::Basic { username, password } => Ok(Some(format!(
+                "Basic {}",
+                encode(format!("{}:{}", username, password.read()?))
+            ))),
+            RpcAuth::Bearer(token) => Ok(Some(format!("Bearer {}", token.read()?))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn secret_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}", name, rand::random::<u64>()))
+    }
+
+    /// Rewrite a secret file so that its modification time changes
+    fn rewrite(path: &Path, contents: &str) {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        loop {
+            fs::write(path, contents).unwrap();
+            if fs::metadata(path).unwrap().modified().ok() != modified {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_rpc_auth_from_config() {
+        let mut config = Config::default().burnchain;
+        assert_eq!(RpcAuth::from_config(&config), Ok(RpcAuth::None));
+
+        config.username = Some("user".into());
+        config.password = Some("pass".into());
+        assert_eq!(
+            RpcAuth::from_config(&config),
+            Ok(RpcAuth::Basic {
+                username: "user".into(),
+                password: SecretSource::Inline("pass".into()),
+            })
+        );
+
+        config.password_file = Some("/tmp/password".into());
+        assert!(RpcAuth::from_config(&config).is_err());
+
+        config.password = None;
+        assert_eq!(
+            RpcAuth::from_config(&config),
+            Ok(RpcAuth::Basic {
+                username: "user".into(),
+                password: SecretSource::File("/tmp/password".into()),
+            })
+        );
+
+        config.rpc_auth_token_env = Some("BITCOIN_RPC_TOKEN".into());
+        assert!(RpcAuth::from_config(&config).is_err());
+
+        config.password_file = None;
+        assert_eq!(
+            RpcAuth::from_config(&config),
+            Ok(RpcAuth::Bearer(SecretSource::Env(
+                "BITCOIN_RPC_TOKEN".into()
+            )))
+        );
+
+        let mut config = Config::default().burnchain;
+        config.password_env = Some("BITCOIN_RPC_PASSWORD".into());
+        assert!(RpcAuth::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_rpc_auth_headers() {
+        let auth = RpcAuth::Basic {
+            username: "user".into(),
+            password: SecretSource::Inline("pass".into()),
+        };
+        assert_eq!(
+            auth.authorization_header().unwrap().as_deref(),
+            Some("Basic dXNlcjpwYXNz")
+        );
+
+        let var = format!("TEST_RPC_AUTH_TOKEN_{}", rand::random::<u64>());
+        let auth = RpcAuth::Bearer(SecretSource::Env(var.clone()));
+        assert!(auth.authorization_header().is_err());
+        std::env::set_var(&var, "token\n");
+        assert_eq!(
+            auth.authorization_header().unwrap().as_deref(),
+            Some("Bearer token")
+        );
+        std::env::remove_var(&var);
+
+        assert_eq!(RpcAuth::None.authorization_header(), Ok(None));
+    }
+
+    #[test]
+    fn test_secret_file_rotation() {
+        let path = secret_file("test_secret_file_rotation");
+        let source = SecretSource::File(path.clone());
+        assert!(source.read().is_err());
+
+        fs::write(&path, "first\n").unwrap();
+        assert_eq!(source.read().unwrap(), "first");
+
+        rewrite(&path, "second");
+        assert_eq!(source.read().unwrap(), "second");
+
+        // a file caught mid-rewrite, or removed, keeps the last secret
+        rewrite(&path, "");
+        assert_eq!(source.read().unwrap(), "second");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(source.read().unwrap(), "second");
+
+        fs::write(&path, "third").unwrap();
+        assert_eq!(source.read().unwrap(), "third");
+        fs::remove_file(&path).unwrap();
+    }
+}
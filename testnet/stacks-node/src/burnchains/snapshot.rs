@@ -0,0 +1,404 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Canned burnchain snapshots, so that integration tests can start a burnchain controller from
+//! a known height instead of bootstrapping the chain every time.
+//!
+//! A snapshot is a directory holding a copy of the burnchain state of a node's working
+//! directory (the burnchain and sortition DBs, plus the SPV headers DB if there is one), and a
+//! `snapshot.json` manifest recording the burnchain mode it was taken in, the canonical tip at
+//! the time, and the size and SHA-256 checksum of every file.  Restoring a snapshot checks every
+//! file against the manifest before anything is copied into the working directory.
+
+use std::path::{Component, Path, PathBuf};
+use std::{fmt, fs, io};
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::util_lib::db::Error as DBError;
+use stacks_common::util::hash::Sha256Sum;
+
+use crate::Config;
+
+/// Name of the manifest file in a snapshot directory
+pub const MANIFEST_FILE_NAME: &str = "snapshot.json";
+/// Version of the snapshot format written by `save_snapshot()`
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+/// Name of the directory holding the burnchain and sortition DBs, relative to the burnchain
+/// mode's directory in the working directory
+const BURNCHAIN_DIR_NAME: &str = "burnchain";
+/// Name of the SPV headers DB, relative to the burnchain mode's directory
+const HEADERS_FILE_NAME: &str = "headers.sqlite";
+
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read, written or copied
+    Io(io::Error),
+    /// The manifest could not be parsed, is for an unsupported format version, or lists a path
+    /// outside of the snapshot
+    BadManifest(String),
+    /// A file's contents do not match the manifest
+    ChecksumMismatch {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+    /// The snapshot was taken in a different burnchain mode
+    ModeMismatch { expected: String, actual: String },
+    /// The working directory already has a sortition DB
+    AlreadyInitialized(PathBuf),
+    /// The working directory has no sortition DB to take a snapshot of
+    NotInitialized(PathBuf),
+    /// The sortition DB could not be queried
+    SortitionDB(DBError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(ref e) => write!(f, "I/O error: {}", e),
+            Error::BadManifest(msg) => write!(f, "Bad snapshot manifest: {}", msg),
+            Error::ChecksumMismatch {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Checksum mismatch for {}: expected {}, got {}",
+                path, expected, actual
+            ),
+            Error::ModeMismatch { expected, actual } => write!(
+                f,
+                "Snapshot is for burnchain mode {}, not {}",
+                actual, expected
+            ),
+            Error::AlreadyInitialized(path) => {
+                write!(f, "Sortition DB already exists at {}", path.display())
+            }
+            Error::NotInitialized(path) => {
+                write!(f, "No sortition DB at {}", path.display())
+            }
+            Error::SortitionDB(ref e) => write!(f, "Sortition DB error: {:?}", e),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<DBError> for Error {
+    fn from(e: DBError) -> Self {
+        Error::SortitionDB(e)
+    }
+}
+
+/// A file in a snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    /// Path of the file, relative to the snapshot directory and to the burnchain mode's
+    /// directory in the working directory.  Components are separated by `/`.
+    pub path: String,
+    pub size: u64,
+    /// Hex-encoded SHA-256 of the file's contents
+    pub sha256: String,
+}
+
+/// The manifest of a snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub version: u32,
+    /// The burnchain mode (e.g. `mocknet`, `krypton`) of the node the snapshot was taken from
+    pub mode: String,
+    /// Height of the canonical burnchain tip in the snapshot
+    pub block_height: u64,
+    /// Hex-encoded header hash of the canonical burnchain tip in the snapshot
+    pub burn_header_hash: String,
+    pub files: Vec<SnapshotFile>,
+}
+
+impl SnapshotManifest {
+    /// Read the manifest of the snapshot in `snapshot_dir`
+    pub fn load(snapshot_dir: &Path) -> Result<SnapshotManifest, Error> {
+        let bytes = fs::read(snapshot_dir.join(MANIFEST_FILE_NAME))?;
+        let manifest: SnapshotManifest = serde_json::from_slice(&bytes)
+            .map_err(|e| Error::BadManifest(format!("failed to parse: {}", e)))?;
+        if manifest.version != SNAPSHOT_FORMAT_VERSION {
+            return Err(Error::BadManifest(format!(
+                "unsupported format version {}",
+                manifest.version
+            )));
+        }
+        for file in manifest.files.iter() {
+            relative_path(&file.path)?;
+        }
+        Ok(manifest)
+    }
+
+    /// Check every file in the snapshot in `snapshot_dir` against this manifest
+    pub fn verify(&self, snapshot_dir: &Path) -> Result<(), Error> {
+        for file in self.files.iter() {
+            let bytes = fs::read(snapshot_dir.join(relative_path(&file.path)?))?;
+            let actual = Sha256Sum::from_data(&bytes).to_hex();
+            if actual != file.sha256 || bytes.len() as u64 != file.size {
+                return Err(Error::ChecksumMismatch {
+                    path: file.path.clone(),
+                    expected: file.sha256.clone(),
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Convert a manifest path to a relative path, refusing any path that could escape the
+/// directory it is resolved against
+fn relative_path(path: &str) -> Result<PathBuf, Error> {
+    let relative: PathBuf = path.split('/').collect();
+    let is_safe = !path.is_empty()
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if !is_safe {
+        return Err(Error::BadManifest(format!("bad file path {}", path)));
+    }
+    Ok(relative)
+}
+
+/// The burnchain mode's directory in the node's working directory, which holds the burnchain
+/// state a snapshot is made of
+fn mode_dir(config: &Config) -> PathBuf {
+    let mut path = config.get_burnchain_path();
+    path.pop();
+    path
+}
+
+/// List the files under `dir`, as `/`-separated paths prefixed with `prefix`
+fn list_files(dir: &Path, prefix: &str, files: &mut Vec<String>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = format!("{}/{}", prefix, name);
+        if entry.file_type()?.is_dir() {
+            list_files(&entry.path(), &path, files)?;
+        } else if !name.ends_with("-shm") {
+            // sqlite recreates the shared-memory index of a write-ahead log when it is opened
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Save the burnchain state of `config`'s working directory as a snapshot in `snapshot_dir`.
+/// The burnchain controller using the working directory must not be processing blocks.
+pub fn save_snapshot(config: &Config, snapshot_dir: &Path) -> Result<SnapshotManifest, Error> {
+    let sortdb_path = config.get_burn_db_file_path();
+    if !Path::new(&sortdb_path).exists() {
+        return Err(Error::NotInitialized(PathBuf::from(sortdb_path)));
+    }
+    let tip = {
+        let sortdb = SortitionDB::open(&sortdb_path, false, config.get_burnchain().pox_constants)?;
+        SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?
+    };
+
+    let source_dir = mode_dir(config);
+    let mut paths = vec![];
+    list_files(
+        &source_dir.join(BURNCHAIN_DIR_NAME),
+        BURNCHAIN_DIR_NAME,
+        &mut paths,
+    )?;
+    for name in [
+        HEADERS_FILE_NAME.to_string(),
+        format!("{}-wal", HEADERS_FILE_NAME),
+    ] {
+        if source_dir.join(&name).exists() {
+            paths.push(name);
+        }
+    }
+    paths.sort();
+
+    let mut files = vec![];
+    for path in paths {
+        let relative = relative_path(&path)?;
+        let bytes = fs::read(source_dir.join(&relative))?;
+        let dest = snapshot_dir.join(&relative);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&dest, &bytes)?;
+        files.push(SnapshotFile {
+            path,
+            size: bytes.len() as u64,
+            sha256: Sha256Sum::from_data(&bytes).to_hex(),
+        });
+    }
+
+    let manifest = SnapshotManifest {
+        version: SNAPSHOT_FORMAT_VERSION,
+        mode: config.burnchain.mode.clone(),
+        block_height: tip.block_height,
+        burn_header_hash: tip.burn_header_hash.to_hex(),
+        files,
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| Error::BadManifest(format!("failed to serialize: {}", e)))?;
+    fs::write(snapshot_dir.join(MANIFEST_FILE_NAME), manifest_json)?;
+    info!("Saved burnchain snapshot";
+          "snapshot_dir" => %snapshot_dir.display(),
+          "block_height" => manifest.block_height,
+          "burn_header_hash" => &manifest.burn_header_hash);
+    Ok(manifest)
+}
+
+/// Initialize the burnchain state of `config`'s working directory from the snapshot in
+/// `snapshot_dir`, after checking every file in it against its manifest.  Must be called before
+/// a burnchain controller is created for `config`.
+pub fn restore_snapshot(config: &Config, snapshot_dir: &Path) -> Result<SnapshotManifest, Error> {
+    let manifest = SnapshotManifest::load(snapshot_dir)?;
+    if manifest.mode != config.burnchain.mode {
+        return Err(Error::ModeMismatch {
+            expected: config.burnchain.mode.clone(),
+            actual: manifest.mode,
+        });
+    }
+    let sortdb_path = PathBuf::from(config.get_burn_db_file_path());
+    if sortdb_path.exists() {
+        return Err(Error::AlreadyInitialized(sortdb_path));
+    }
+    manifest.verify(snapshot_dir)?;
+
+    let dest_dir = mode_dir(config);
+    for file in manifest.files.iter() {
+        let relative = relative_path(&file.path)?;
+        let dest = dest_dir.join(&relative);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(snapshot_dir.join(&relative), &dest)?;
+    }
+    info!("Restored burnchain snapshot";
+          "snapshot_dir" => %snapshot_dir.display(),
+          "block_height" => manifest.block_height,
+          "burn_header_hash" => &manifest.burn_header_hash);
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::burnchains::{BitcoinRegtestController, BurnchainController, MocknetController};
+    use crate::tests::new_test_conf;
+
+    fn snapshot_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "stacks-node-burnchain-snapshot-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// Mine `num_blocks` mocknet blocks and save a snapshot of the result in `dir`
+    fn make_mocknet_snapshot(dir: &Path, num_blocks: u64) -> SnapshotManifest {
+        let config = new_test_conf();
+        let mut controller = MocknetController::generic(config.clone());
+        controller.start(None).unwrap();
+        for _ in 0..num_blocks {
+            controller.sync(None).unwrap();
+        }
+        save_snapshot(&config, dir).unwrap()
+    }
+
+    #[test]
+    fn mocknet_snapshot_round_trip() {
+        let dir = snapshot_dir("round-trip");
+        let manifest = make_mocknet_snapshot(&dir, 5);
+        assert_eq!(manifest.block_height, 5);
+        assert_eq!(SnapshotManifest::load(&dir).unwrap(), manifest);
+
+        let config = new_test_conf();
+        let (mut controller, restored) =
+            MocknetController::from_snapshot(config.clone(), &dir).unwrap();
+        assert_eq!(restored, manifest);
+        let (tip, height) = controller.start(None).unwrap();
+        assert_eq!(height, 5);
+        assert_eq!(
+            tip.block_snapshot.burn_header_hash.to_hex(),
+            manifest.burn_header_hash
+        );
+
+        // the restored chain can be extended
+        let (tip, _) = controller.sync(None).unwrap();
+        assert_eq!(tip.block_snapshot.block_height, 6);
+
+        // a working directory can only be initialized once
+        assert!(matches!(
+            restore_snapshot(&config, &dir),
+            Err(Error::AlreadyInitialized(_))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn snapshot_checksums_are_verified() {
+        let dir = snapshot_dir("checksums");
+        let manifest = make_mocknet_snapshot(&dir, 2);
+        let tampered = manifest.files.iter().max_by_key(|file| file.size).unwrap();
+        let tampered_path = dir.join(relative_path(&tampered.path).unwrap());
+        let mut bytes = fs::read(&tampered_path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&tampered_path, bytes).unwrap();
+
+        let config = new_test_conf();
+        assert!(matches!(
+            restore_snapshot(&config, &dir),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        // nothing was copied
+        assert!(!Path::new(&config.get_burn_db_file_path()).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn snapshot_manifest_is_validated() {
+        let dir = snapshot_dir("manifest");
+        let mut manifest = make_mocknet_snapshot(&dir, 1);
+
+        // a mocknet snapshot can't be used to start an L1 controller
+        let mut config = new_test_conf();
+        config.burnchain.mode = "krypton".into();
+        assert!(matches!(
+            BitcoinRegtestController::from_snapshot(config, None, &dir),
+            Err(Error::ModeMismatch { .. })
+        ));
+
+        manifest.files[0].path = "../escape".into();
+        fs::write(
+            dir.join(MANIFEST_FILE_NAME),
+            serde_json::to_vec(&manifest).unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(
+            SnapshotManifest::load(&dir),
+            Err(Error::BadManifest(_))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
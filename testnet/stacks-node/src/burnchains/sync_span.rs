@@ -0,0 +1,229 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tracks one pass of the burnchain sync pipeline (burn block received, sortition processed,
+//! tip updated) under a single span ID, so that log records emitted by the controller, the
+//! chains coordinator and the run loop can be correlated when diagnosing burnchain lag.  The
+//! span ID reaches the coordinator through its `CoordinatorChannels`.
+//!
+//! With the `tracing_spans` feature, each pass also opens a `tracing` span.  If
+//! `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` or `OTEL_EXPORTER_OTLP_ENDPOINT` is set,
+//! `install_trace_exporter()` exports these spans to that OTLP/HTTP collector.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+static NEXT_SYNC_SPAN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Export the sync passes' spans to the OTLP/HTTP collector named by
+/// `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` or `OTEL_EXPORTER_OTLP_ENDPOINT`, by installing a global
+/// `tracing` subscriber with an OpenTelemetry layer.  Does nothing if neither is set.  Each span
+/// is exported as soon as it ends, on the thread that ends it.
+#[cfg(feature = "tracing_spans")]
+pub fn install_trace_exporter() {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::{OTEL_EXPORTER_OTLP_ENDPOINT, OTEL_EXPORTER_OTLP_TRACES_ENDPOINT};
+    use opentelemetry_sdk::trace::TracerProvider;
+    use opentelemetry_sdk::Resource;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    if std::env::var_os(OTEL_EXPORTER_OTLP_TRACES_ENDPOINT).is_none()
+        && std::env::var_os(OTEL_EXPORTER_OTLP_ENDPOINT).is_none()
+    {
+        return;
+    }
+    let exporter = match opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            warn!("Failed to create the OTLP span exporter; sync spans will not be exported: {e}");
+            return;
+        }
+    };
+    let provider = TracerProvider::builder()
+        .with_simple_exporter(exporter)
+        .with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            "stacks-node",
+        )]))
+        .build();
+    let tracer = provider.tracer("stacks-node");
+    opentelemetry::global::set_tracer_provider(provider);
+
+    let subscriber =
+        tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+    if let Err(e) = tracing::subscriber::set_global_default(subscriber) {
+        warn!("Failed to install the sync span exporter: {e}");
+        return;
+    }
+    info!("Exporting burnchain sync spans over OTLP");
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStage {
+    /// The indexer downloaded burnchain blocks and announced them to the coordinator
+    BurnBlockReceived,
+    /// The chains coordinator processed sortitions up to the requested height
+    SortitionProcessed,
+    /// The run loop adopted the new burnchain tip
+    TipUpdated,
+}
+
+impl fmt::Display for SyncStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyncStage::BurnBlockReceived => write!(f, "burn_block_received"),
+            SyncStage::SortitionProcessed => write!(f, "sortition_processed"),
+            SyncStage::TipUpdated => write!(f, "tip_updated"),
+        }
+    }
+}
+
+/// Time a sync pass took to reach one of its stages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageTiming {
+    pub stage: SyncStage,
+    pub height: u64,
+    /// Time since the previous stage (or since the pass began)
+    pub stage_time: Duration,
+    /// Time since the pass began
+    pub total_time: Duration,
+}
+
+#[derive(Debug)]
+pub struct SyncSpan {
+    id: u64,
+    target_height: Option<u64>,
+    started_at: Instant,
+    last_stage_at: Instant,
+    last_stage: Option<SyncStage>,
+    #[cfg(feature = "tracing_spans")]
+    span: tracing::Span,
+}
+
+impl SyncSpan {
+    /// Begin a new sync pass towards `target_height` (if known)
+    pub fn begin(target_height: Option<u64>) -> SyncSpan {
+        let id = NEXT_SYNC_SPAN_ID.fetch_add(1, Ordering::SeqCst);
+        let now = Instant::now();
+        debug!("Burnchain sync: begin pass";
+               "span_id" => id,
+               "target_height" => ?target_height);
+        SyncSpan {
+            id,
+            target_height,
+            started_at: now,
+            last_stage_at: now,
+            last_stage: None,
+            #[cfg(feature = "tracing_spans")]
+            span: tracing::info_span!("burnchain_sync", span_id = id, target_height = ?target_height),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn last_stage(&self) -> Option<SyncStage> {
+        self.last_stage
+    }
+
+    /// Record that this pass reached `stage` at burnchain height `height`.
+    /// Logs the time spent since the previous stage and since the pass began, and returns it.
+    pub fn enter_stage(&mut self, stage: SyncStage, height: u64) -> StageTiming {
+        let now = Instant::now();
+        let timing = StageTiming {
+            stage,
+            height,
+            stage_time: now.duration_since(self.last_stage_at),
+            total_time: now.duration_since(self.started_at),
+        };
+        let stage_ms = timing.stage_time.as_millis() as u64;
+        let total_ms = timing.total_time.as_millis() as u64;
+        debug!("Burnchain sync: stage reached";
+               "span_id" => self.id,
+               "stage" => %stage,
+               "height" => height,
+               "target_height" => ?self.target_height,
+               "stage_ms" => stage_ms,
+               "total_ms" => total_ms);
+
+        #[cfg(feature = "tracing_spans")]
+        self.span.in_scope(|| {
+            tracing::info!(
+                stage = %stage,
+                height = height,
+                stage_ms = stage_ms,
+                total_ms = total_ms,
+                "burnchain sync stage reached"
+            );
+        });
+
+        self.last_stage_at = now;
+        self.last_stage = Some(stage);
+        timing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_sync_span_stages() {
+        let mut span_1 = SyncSpan::begin(Some(100));
+        let span_2 = SyncSpan::begin(None);
+        assert!(span_2.id() > span_1.id());
+
+        assert_eq!(span_1.last_stage(), None);
+        span_1.enter_stage(SyncStage::BurnBlockReceived, 100);
+        assert_eq!(span_1.last_stage(), Some(SyncStage::BurnBlockReceived));
+        span_1.enter_stage(SyncStage::SortitionProcessed, 100);
+        span_1.enter_stage(SyncStage::TipUpdated, 100);
+        assert_eq!(span_1.last_stage(), Some(SyncStage::TipUpdated));
+    }
+
+    #[test]
+    fn test_sync_span_stage_timings() {
+        let mut span = SyncSpan::begin(Some(100));
+        let stages = [
+            (SyncStage::BurnBlockReceived, 99),
+            (SyncStage::SortitionProcessed, 100),
+            (SyncStage::TipUpdated, 100),
+        ];
+
+        let mut timings = vec![];
+        for (stage, height) in stages {
+            thread::sleep(Duration::from_millis(10));
+            timings.push(span.enter_stage(stage, height));
+        }
+
+        let mut elapsed = Duration::ZERO;
+        for (timing, (stage, height)) in timings.iter().zip(stages) {
+            assert_eq!(timing.stage, stage);
+            assert_eq!(timing.height, height);
+            assert!(timing.stage_time >= Duration::from_millis(10));
+            // each stage's time picks up exactly where the previous stage left off
+            elapsed += timing.stage_time;
+            assert_eq!(timing.total_time, elapsed);
+        }
+    }
+}
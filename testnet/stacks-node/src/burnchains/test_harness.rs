@@ -0,0 +1,610 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A scripted, fully deterministic `BurnchainController` for node-level tests.
+//!
+//! `TestBurnchainController` mines one burnchain block per step, with block hashes and
+//! timestamps derived only from the parent block, and feeds each block's operations through
+//! the real sortition DB. Tests drive it either through the `BurnchainController` trait (as
+//! the miner, relayer and run loop do) or directly via `mine_block()`, and then check the
+//! outcome with the `assert_*` helpers. No bitcoind or mock-events server is needed.
+//! Tips are stamped by a `ManualClock`, so `received_at` only moves when the test advances it.
+//! With `burnchain.fast_header_sync` set, a restarted controller treats the scripted blocks as
+//! headers already synced from the remote tip.  It processes them in its `sync()` passes rather
+//! than in a background task, and follows them with the same `BackfillTracker` as
+//! `BitcoinRegtestController`.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use stacks::burnchains::bitcoin::BitcoinBlock;
+use stacks::burnchains::{
+    Burnchain, BurnchainBlock, BurnchainBlockHeader, BurnchainStateTransitionOps, Txid,
+};
+use stacks::chainstate::burn::db::sortdb::{SortitionDB, SortitionHandleTx};
+use stacks::chainstate::burn::operations::BlockstackOperationType;
+use stacks::chainstate::burn::BlockSnapshot;
+use stacks::core::{StacksEpoch, StacksEpochExtension, StacksEpochId};
+use stacks::monitoring::BurnchainBackfillProgress;
+use stacks_common::types::chainstate::{BurnchainHeaderHash, PoxId};
+use stacks_common::util::hash::Sha256Sum;
+
+use super::backfill::BackfillTracker;
+use super::clock::{Clock, ManualClock};
+use super::mocknet_controller::bind_operation_to_block;
+use super::{BurnchainController, BurnchainTip, Error as BurnchainControllerError};
+use crate::operations::BurnchainOpSigner;
+use crate::Config;
+
+/// Timestamp of the first burnchain block
+pub const TEST_BURNCHAIN_GENESIS_TIMESTAMP: u64 = 1_600_000_000;
+/// Seconds between consecutive burnchain blocks
+pub const TEST_BURNCHAIN_BLOCK_INTERVAL_SECS: u64 = 600;
+
+/// The contents of one scripted burnchain block
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedBlock {
+    /// Operations to include in the block, in addition to any submitted ones
+    pub ops: Vec<BlockstackOperationType>,
+    /// If set, operations submitted via `submit_operation()` are held back for a later block
+    pub withhold_submitted: bool,
+}
+
+impl ScriptedBlock {
+    /// A block that only includes the submitted operations
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// A block that includes `ops` along with the submitted operations
+    pub fn with_ops(ops: Vec<BlockstackOperationType>) -> Self {
+        Self {
+            ops,
+            withhold_submitted: false,
+        }
+    }
+}
+
+/// An operation handed to `submit_operation()`
+#[derive(Debug, Clone)]
+pub struct SubmittedOperation {
+    pub epoch_id: StacksEpochId,
+    pub operation: BlockstackOperationType,
+    pub attempt: u64,
+}
+
+pub struct TestBurnchainController {
+    config: Config,
+    burnchain: Burnchain,
+    db: Option<SortitionDB>,
+    chain_tip: Option<BurnchainTip>,
+    /// Submitted operations waiting to be mined
+    pending_operations: VecDeque<BlockstackOperationType>,
+    /// Blocks to mine next. Once exhausted, blocks only include submitted operations.
+    script: VecDeque<ScriptedBlock>,
+    /// Every operation ever submitted, in order
+    submissions: Vec<SubmittedOperation>,
+    /// Every tip produced, in order, starting with the first block after genesis
+    history: Vec<BurnchainTip>,
+    /// If set, `submit_operation()` refuses all operations
+    reject_submissions: bool,
+    /// Stamps `BurnchainTip::received_at`. Only moves when advanced by the test.
+    clock: Arc<dyn Clock>,
+    /// Follows the processing of the scripted blocks after a header-only fast sync
+    backfill: BackfillTracker,
+}
+
+impl TestBurnchainController {
+    pub fn new(config: Config) -> Self {
+        let burnchain = config.get_burnchain();
+        Self {
+            config,
+            burnchain,
+            db: None,
+            chain_tip: None,
+            pending_operations: VecDeque::new(),
+            script: VecDeque::new(),
+            submissions: vec![],
+            history: vec![],
+            reject_submissions: false,
+            clock: Arc::new(ManualClock::new()),
+            backfill: BackfillTracker::new(),
+        }
+    }
+
+    /// Queue up a block to be mined after all previously scripted blocks
+    pub fn script_block(&mut self, block: ScriptedBlock) {
+        self.script.push_back(block);
+    }
+
+    /// Make `submit_operation()` fail (returning `None`) until unset
+    pub fn set_reject_submissions(&mut self, reject: bool) {
+        self.reject_submissions = reject;
+    }
+
+    /// Use `clock` to stamp mined tips, e.g. a `ManualClock` shared with the code under test
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// All operations handed to `submit_operation()` so far, including rejected ones
+    pub fn submissions(&self) -> &[SubmittedOperation] {
+        &self.submissions
+    }
+
+    /// Submitted operations that have not been mined yet
+    pub fn pending_operations(&self) -> impl Iterator<Item = &BlockstackOperationType> {
+        self.pending_operations.iter()
+    }
+
+    /// All tips mined so far, oldest first
+    pub fn history(&self) -> &[BurnchainTip] {
+        &self.history
+    }
+
+    /// Find the mined tip at the given height
+    pub fn tip_at_height(&self, block_height: u64) -> Option<&BurnchainTip> {
+        self.history
+            .iter()
+            .find(|tip| tip.block_snapshot.block_height == block_height)
+    }
+
+    /// The progress of the backfill that follows a header-only fast sync, if one is under way
+    pub fn backfill_progress(&self) -> Option<&BurnchainBackfillProgress> {
+        self.backfill.progress()
+    }
+
+    /// Find the mined tip whose block accepted the operation with the given txid
+    pub fn find_accepted_op(&self, txid: &Txid) -> Option<&BurnchainTip> {
+        self.history.iter().find(|tip| {
+            tip.state_transition
+                .accepted_ops
+                .iter()
+                .any(|op| op.txid_ref() == txid)
+        })
+    }
+
+    fn build_next_block_header(parent: &BlockSnapshot) -> BurnchainBlockHeader {
+        let next_hash = Sha256Sum::from_data(parent.burn_header_hash.as_bytes());
+        let block_height = parent.block_height + 1;
+        let block = BurnchainBlock::Bitcoin(BitcoinBlock::new(
+            block_height,
+            &BurnchainHeaderHash::from_bytes(next_hash.as_bytes()).unwrap(),
+            &parent.burn_header_hash,
+            vec![],
+            TEST_BURNCHAIN_GENESIS_TIMESTAMP + block_height * TEST_BURNCHAIN_BLOCK_INTERVAL_SECS,
+        ));
+        block.header()
+    }
+
+    /// Mine the next burnchain block and process its sortition
+    pub fn mine_block(&mut self) -> BurnchainTip {
+        let chain_tip = self.get_chain_tip();
+        let next_block_header = Self::build_next_block_header(&chain_tip.block_snapshot);
+
+        let scripted = self.script.pop_front().unwrap_or_default();
+        let mut ops = scripted.ops;
+        if !scripted.withhold_submitted {
+            ops.extend(self.pending_operations.drain(..));
+        }
+        let ops = ops
+            .into_iter()
+            .map(|op| bind_operation_to_block(op, &next_block_header))
+            .collect();
+
+        let burnchain = &self.burnchain;
+        let sortdb = self.db.as_mut().expect("BUG: did not instantiate burn DB");
+        let mut burn_tx =
+            SortitionHandleTx::begin(sortdb, &chain_tip.block_snapshot.sortition_id).unwrap();
+        let (block_snapshot, state_transition) = burn_tx
+            .process_block_ops(
+                burnchain,
+                &chain_tip.block_snapshot,
+                &next_block_header,
+                ops,
+                None,
+                PoxId::stubbed(),
+                None,
+                0,
+            )
+            .unwrap();
+        burn_tx.commit().unwrap();
+
+        let new_tip = BurnchainTip {
+            block_snapshot,
+            state_transition: BurnchainStateTransitionOps {
+                accepted_ops: state_transition.accepted_ops,
+                consumed_leader_keys: state_transition.consumed_leader_keys,
+            },
+            received_at: self.clock.now(),
+        };
+        self.chain_tip = Some(new_tip.clone());
+        self.history.push(new_tip.clone());
+        new_tip
+    }
+
+    /// Mine blocks until the tip reaches `target_block_height`
+    fn mine_to_height(&mut self, target_block_height: u64) {
+        while self.get_chain_tip().block_snapshot.block_height < target_block_height {
+            self.mine_block();
+        }
+    }
+
+    /// Panic unless the canonical burnchain tip is at `expected_height`
+    pub fn assert_tip_height(&self, expected_height: u64) {
+        let tip = SortitionDB::get_canonical_burn_chain_tip(self.sortdb_ref().conn())
+            .expect("FATAL: failed to get canonical chain tip");
+        assert_eq!(
+            tip.block_height, expected_height,
+            "Expected burnchain tip at height {expected_height}, but it is at {}",
+            tip.block_height
+        );
+        assert_eq!(self.get_headers_height(), expected_height);
+    }
+
+    /// Panic unless the operation with the given txid was accepted into some mined block.
+    /// Returns the height of that block.
+    pub fn assert_op_accepted(&self, txid: &Txid) -> u64 {
+        self.find_accepted_op(txid)
+            .unwrap_or_else(|| panic!("Operation {txid:?} was not accepted into any block"))
+            .block_snapshot
+            .block_height
+    }
+
+    /// Panic if the operation with the given txid was accepted into any mined block
+    pub fn assert_op_not_accepted(&self, txid: &Txid) {
+        if let Some(tip) = self.find_accepted_op(txid) {
+            panic!(
+                "Operation {txid:?} was accepted at height {}",
+                tip.block_snapshot.block_height
+            );
+        }
+    }
+
+    /// Panic unless the block at `block_height` had (or did not have) a sortition
+    pub fn assert_sortition_at(&self, block_height: u64, expected_sortition: bool) {
+        let tip = self
+            .tip_at_height(block_height)
+            .unwrap_or_else(|| panic!("No block mined at height {block_height}"));
+        assert_eq!(
+            tip.block_snapshot.sortition, expected_sortition,
+            "Unexpected sortition outcome at height {block_height}"
+        );
+    }
+
+    /// Panic unless the block commit with the given txid won the sortition at `block_height`
+    pub fn assert_winner_at(&self, block_height: u64, expected_winner: &Txid) {
+        self.assert_sortition_at(block_height, true);
+        let tip = self.tip_at_height(block_height).unwrap();
+        assert_eq!(
+            &tip.block_snapshot.winning_block_txid, expected_winner,
+            "Unexpected sortition winner at height {block_height}"
+        );
+    }
+}
+
+impl BurnchainController for TestBurnchainController {
+    fn start(
+        &mut self,
+        target_block_height_opt: Option<u64>,
+    ) -> Result<(BurnchainTip, u64), BurnchainControllerError> {
+        let epochs = self.get_stacks_epochs();
+        let db = SortitionDB::connect(
+            &self.config.get_burn_db_file_path(),
+            0,
+            &BurnchainHeaderHash::zero(),
+            TEST_BURNCHAIN_GENESIS_TIMESTAMP,
+            &epochs,
+            self.burnchain.pox_constants.clone(),
+            None,
+            true,
+        )
+        .expect("Error while connecting to burnchain db");
+        let block_snapshot = SortitionDB::get_canonical_burn_chain_tip(db.conn())
+            .expect("FATAL: failed to get canonical chain tip");
+        // as with `BitcoinRegtestController`, a header-only sync needs processed blocks
+        let fast_header_sync =
+            self.config.burnchain.fast_header_sync && block_snapshot.block_height > 0;
+        let headers_height = block_snapshot.block_height + self.script.len() as u64;
+        self.db = Some(db);
+        self.chain_tip = Some(BurnchainTip {
+            block_snapshot,
+            state_transition: BurnchainStateTransitionOps::noop(),
+            received_at: self.clock.now(),
+        });
+
+        if let Some(target_block_height) = target_block_height_opt {
+            self.mine_to_height(target_block_height);
+        }
+        let tip = self.get_chain_tip();
+        let block_height = tip.block_snapshot.block_height;
+        if fast_header_sync {
+            self.backfill.begin(block_height, headers_height);
+            return Ok((tip, headers_height.max(block_height)));
+        }
+        Ok((tip, block_height))
+    }
+
+    fn submit_operation(
+        &mut self,
+        epoch_id: StacksEpochId,
+        operation: BlockstackOperationType,
+        _op_signer: &mut BurnchainOpSigner,
+        attempt: u64,
+    ) -> Option<Txid> {
+        self.submissions.push(SubmittedOperation {
+            epoch_id,
+            operation: operation.clone(),
+            attempt,
+        });
+        if self.reject_submissions {
+            return None;
+        }
+        let txid = operation.txid();
+        self.pending_operations.push_back(operation);
+        Some(txid)
+    }
+
+    /// Mine up to `target_block_height_opt`, or a single block if no target is given
+    fn sync(
+        &mut self,
+        target_block_height_opt: Option<u64>,
+    ) -> Result<(BurnchainTip, u64), BurnchainControllerError> {
+        match target_block_height_opt {
+            Some(target_block_height) => self.mine_to_height(target_block_height),
+            None => {
+                self.mine_block();
+            }
+        }
+        let tip = self.get_chain_tip();
+        let block_height = tip.block_snapshot.block_height;
+        self.backfill.record(block_height);
+        Ok((tip, block_height))
+    }
+
+    fn sortdb_ref(&self) -> &SortitionDB {
+        self.db.as_ref().expect("BUG: did not instantiate burn DB")
+    }
+
+    fn sortdb_mut(&mut self) -> &mut SortitionDB {
+        self.db.as_mut().expect("BUG: did not instantiate burn DB")
+    }
+
+    fn get_chain_tip(&self) -> BurnchainTip {
+        self.chain_tip
+            .clone()
+            .expect("BUG: burnchain controller not started")
+    }
+
+    fn get_headers_height(&self) -> u64 {
+        self.chain_tip
+            .as_ref()
+            .expect("BUG: burnchain controller not started")
+            .block_snapshot
+            .block_height
+    }
+
+    fn connect_dbs(&mut self) -> Result<(), BurnchainControllerError> {
+        Ok(())
+    }
+
+    fn get_stacks_epochs(&self) -> Vec<StacksEpoch> {
+        self.config
+            .burnchain
+            .epochs
+            .clone()
+            .unwrap_or_else(|| StacksEpoch::all(0, 0, 0))
+    }
+
+    fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    fn bootstrap_chain(&mut self, blocks_count: u64) {
+        for _ in 0..blocks_count {
+            self.mine_block();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use stacks::chainstate::burn::operations::TransferStxOp;
+    use stacks_common::types::chainstate::StacksAddress;
+    use stacks_common::util::secp256k1::Secp256k1PrivateKey;
+
+    use super::*;
+    use crate::tests::new_test_conf;
+
+    fn transfer_stx_op(txid_byte: u8) -> BlockstackOperationType {
+        BlockstackOperationType::TransferStx(TransferStxOp {
+            sender: StacksAddress::burn_address(false),
+            recipient: StacksAddress::burn_address(true),
+            transfered_ustx: 1,
+            memo: vec![],
+            txid: Txid([txid_byte; 32]),
+            vtxindex: txid_byte as u32,
+            block_height: 0,
+            burn_header_hash: BurnchainHeaderHash::zero(),
+        })
+    }
+
+    #[test]
+    fn test_harness_is_deterministic() {
+        let mut controllers: Vec<_> = (0..2)
+            .map(|_| TestBurnchainController::new(new_test_conf()))
+            .collect();
+        for controller in controllers.iter_mut() {
+            controller.start(None).unwrap();
+            controller.script_block(ScriptedBlock::with_ops(vec![transfer_stx_op(1)]));
+            controller.script_block(ScriptedBlock::empty());
+            controller.sync(Some(3)).unwrap();
+            controller.assert_tip_height(3);
+        }
+
+        let snapshots: Vec<Vec<_>> = controllers
+            .iter()
+            .map(|controller| {
+                controller
+                    .history()
+                    .iter()
+                    .map(|tip| {
+                        (
+                            tip.block_snapshot.burn_header_hash,
+                            tip.block_snapshot.consensus_hash,
+                            tip.block_snapshot.burn_header_timestamp,
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(snapshots[0].len(), 3);
+        assert_eq!(snapshots[0], snapshots[1]);
+        assert_eq!(controllers[0].assert_op_accepted(&Txid([1; 32])), 1);
+    }
+
+    #[test]
+    fn test_harness_submitted_operations() {
+        let mut controller = TestBurnchainController::new(new_test_conf());
+        controller.start(Some(1)).unwrap();
+        controller.assert_tip_height(1);
+
+        let mut op_signer = BurnchainOpSigner::new(Secp256k1PrivateKey::new(), false);
+        let invalid_transfer = BlockstackOperationType::TransferStx(TransferStxOp {
+            sender: StacksAddress::burn_address(false),
+            recipient: StacksAddress::burn_address(false),
+            transfered_ustx: 0,
+            memo: vec![],
+            txid: Txid([3; 32]),
+            vtxindex: 3,
+            block_height: 0,
+            burn_header_hash: BurnchainHeaderHash::zero(),
+        });
+
+        controller
+            .submit_operation(
+                StacksEpochId::Epoch21,
+                transfer_stx_op(2),
+                &mut op_signer,
+                0,
+            )
+            .unwrap();
+        controller
+            .submit_operation(StacksEpochId::Epoch21, invalid_transfer, &mut op_signer, 0)
+            .unwrap();
+
+        // the next block holds back the submitted operations
+        controller.script_block(ScriptedBlock {
+            ops: vec![],
+            withhold_submitted: true,
+        });
+        controller.sync(None).unwrap();
+        assert_eq!(controller.pending_operations().count(), 2);
+        controller.assert_op_not_accepted(&Txid([2; 32]));
+
+        controller.sync(None).unwrap();
+        assert_eq!(controller.pending_operations().count(), 0);
+        assert_eq!(controller.assert_op_accepted(&Txid([2; 32])), 3);
+        controller.assert_op_not_accepted(&Txid([3; 32]));
+        // no block commits, so no sortitions
+        controller.assert_sortition_at(3, false);
+
+        // rejected submissions are recorded, but never mined
+        controller.set_reject_submissions(true);
+        assert!(controller
+            .submit_operation(
+                StacksEpochId::Epoch21,
+                transfer_stx_op(4),
+                &mut op_signer,
+                1
+            )
+            .is_none());
+        controller.sync(None).unwrap();
+        controller.assert_op_not_accepted(&Txid([4; 32]));
+        assert_eq!(controller.submissions().len(), 3);
+        controller.assert_tip_height(4);
+    }
+
+    #[test]
+    fn test_harness_received_at_follows_clock() {
+        let clock = Arc::new(ManualClock::new());
+        let mut controller = TestBurnchainController::new(new_test_conf());
+        controller.set_clock(clock.clone());
+        controller.start(None).unwrap();
+
+        controller.mine_block();
+        clock.advance(Duration::from_secs(600));
+        controller.mine_block();
+        controller.mine_block();
+
+        let received_at: Vec<_> = controller
+            .history()
+            .iter()
+            .map(|tip| tip.received_at)
+            .collect();
+        assert_eq!(
+            received_at[1].duration_since(received_at[0]),
+            Duration::from_secs(600)
+        );
+        assert_eq!(received_at[2], received_at[1]);
+        assert_eq!(
+            controller.clock().elapsed_since(received_at[0]),
+            Duration::from_secs(600)
+        );
+    }
+
+    #[test]
+    fn test_harness_fast_header_sync() {
+        let mut conf = new_test_conf();
+        conf.burnchain.fast_header_sync = true;
+        {
+            // nothing has been processed yet, so the first start is a full sync
+            let mut controller = TestBurnchainController::new(conf.clone());
+            let (tip, headers_height) = controller.start(Some(5)).unwrap();
+            assert_eq!(tip.block_snapshot.block_height, 5);
+            assert_eq!(headers_height, 5);
+            assert!(controller.backfill_progress().is_none());
+        }
+
+        // restart, with the headers of ten more blocks to sync
+        let mut controller = TestBurnchainController::new(conf);
+        for _ in 0..10 {
+            controller.script_block(ScriptedBlock::empty());
+        }
+        let (tip, headers_height) = controller.start(Some(6)).unwrap();
+        // blocks are processed up to the height asked for, and no further
+        assert_eq!(tip.block_snapshot.block_height, 6);
+        assert_eq!(headers_height, 15);
+        assert_eq!(
+            controller.backfill_progress(),
+            Some(&BurnchainBackfillProgress {
+                start_height: 6,
+                processed_height: 6,
+                target_height: 15,
+            })
+        );
+
+        // the sync passes process the rest
+        controller.sync(None).unwrap();
+        assert_eq!(controller.backfill_progress().unwrap().processed_height, 7);
+        controller.sync(Some(14)).unwrap();
+        assert_eq!(controller.backfill_progress().unwrap().processed_height, 14);
+        controller.sync(None).unwrap();
+        assert!(controller.backfill_progress().is_none());
+        controller.assert_tip_height(15);
+    }
+}
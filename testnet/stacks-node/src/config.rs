@@ -4,9 +4,10 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use std::{cmp, fs, thread};
+use std::{cmp, fmt, fs, thread};
 
 use clarity::vm::costs::ExecutionCost;
+use clarity::vm::database::ClarityDatabase;
 use clarity::vm::types::{AssetIdentifier, PrincipalData, QualifiedContractIdentifier};
 use lazy_static::lazy_static;
 use rand::RngCore;
@@ -16,8 +17,10 @@ use stacks::burnchains::bitcoin::BitcoinNetworkType;
 use stacks::burnchains::{Burnchain, MagicBytes, PoxConstants, BLOCKSTACK_MAGIC_MAINNET};
 use stacks::chainstate::nakamoto::signer_set::NakamotoSigners;
 use stacks::chainstate::stacks::boot::MINERS_NAME;
+use stacks::chainstate::stacks::index::file::TrieSyncPolicy;
 use stacks::chainstate::stacks::index::marf::MARFOpenOpts;
 use stacks::chainstate::stacks::index::storage::TrieHashCalculationMode;
+use stacks::chainstate::stacks::index::warm::TrieCacheWarming;
 use stacks::chainstate::stacks::miner::{BlockBuilderSettings, MinerStatus};
 use stacks::chainstate::stacks::MAX_BLOCK_LEN;
 use stacks::core::mempool::{MemPoolWalkSettings, MemPoolWalkTxTypes};
@@ -32,8 +35,10 @@ use stacks::cost_estimates::fee_rate_fuzzer::FeeRateFuzzer;
 use stacks::cost_estimates::fee_scalar::ScalarFeeRateEstimator;
 use stacks::cost_estimates::metrics::{CostMetric, ProportionalDotProduct, UnitMetric};
 use stacks::cost_estimates::{CostEstimator, FeeEstimator, PessimisticEstimator, UnitEstimator};
+use stacks::net::api::getburnchaincontroller::{BurnchainControllerSettings, BurnchainFeePolicy};
 use stacks::net::atlas::AtlasConfig;
 use stacks::net::connection::ConnectionOptions;
+use stacks::net::dns::AddressFamilyPreference;
 use stacks::net::{Neighbor, NeighborKey};
 use stacks::types::chainstate::BurnchainHeaderHash;
 use stacks::util_lib::boot::boot_code_id;
@@ -46,6 +51,8 @@ use stacks_common::util::get_epoch_time_ms;
 use stacks_common::util::hash::hex_bytes;
 use stacks_common::util::secp256k1::{Secp256k1PrivateKey, Secp256k1PublicKey};
 
+use crate::burnchains::op_confirmations::OP_CONFIRMATION_EXPIRY_BLOCKS;
+use crate::burnchains::rpc_auth::RpcAuth;
 use crate::chain_data::MinerStats;
 
 pub const DEFAULT_SATS_PER_VB: u64 = 50;
@@ -143,6 +150,392 @@ mod tests {
         assert!(Config::from_config_file(ConfigFile::from_str("").unwrap(), false).is_ok());
     }
 
+    #[test]
+    fn test_events_observer_drop_rejected_events() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [[events_observer]]
+                endpoint = "localhost:30000"
+                events_keys = ["stackerdb", "block_proposal", "burn_blocks"]
+                drop_rejected_events = true
+
+                [[events_observer]]
+                endpoint = "localhost:30001"
+                events_keys = ["*", "block_proposal"]
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap();
+        let drop_rejected_events: HashMap<_, _> = config
+            .events_observers
+            .iter()
+            .map(|observer| (observer.endpoint.as_str(), observer.drop_rejected_events))
+            .collect();
+        assert_eq!(drop_rejected_events.len(), 2);
+        assert_eq!(drop_rejected_events.get("localhost:30000"), Some(&true));
+        assert_eq!(drop_rejected_events.get("localhost:30001"), Some(&false));
+    }
+
+    #[test]
+    fn test_burnchain_parser_threads() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap(), false).unwrap();
+        assert_eq!(config.burnchain.parser_threads, 1);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                parser_threads = 4
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(config.burnchain.parser_threads, 4);
+
+        assert_eq!(
+            format!("burnchain.parser_threads must be at least 1"),
+            Config::from_config_file(
+                ConfigFile::from_str(
+                    r#"
+                    [burnchain]
+                    parser_threads = 0
+                    "#,
+                )
+                .unwrap(),
+                false
+            )
+            .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_burnchain_controller_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap(), false).unwrap();
+        let settings = config.burnchain.controller_settings();
+        assert_eq!(settings.poll_interval_secs, 10);
+        assert_eq!(settings.op_expiry_blocks, OP_CONFIRMATION_EXPIRY_BLOCKS);
+        assert_eq!(settings.fees.satoshis_per_byte, DEFAULT_SATS_PER_VB);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                satoshis_per_byte = 20
+                max_rbf = 200
+
+                [burnchain.controller]
+                poll_interval_secs = 2
+                op_expiry_blocks = 6
+
+                [burnchain.controller.fees]
+                satoshis_per_byte = 20
+                rbf_fee_increment = 3
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(config.burnchain.poll_time_secs, 2);
+        assert_eq!(config.burnchain.op_expiry_blocks, 6);
+        assert_eq!(
+            config.burnchain.fee_policy(),
+            BurnchainFeePolicy {
+                satoshis_per_byte: 20,
+                rbf_fee_increment: 3,
+                max_rbf: 200,
+                burn_fee_cap: 20000,
+            }
+        );
+
+        assert_eq!(
+            format!("burnchain.poll_time_secs (5) conflicts with burnchain.controller.poll_interval_secs (2); remove burnchain.poll_time_secs"),
+            Config::from_config_file(
+                ConfigFile::from_str(
+                    r#"
+                    [burnchain]
+                    poll_time_secs = 5
+
+                    [burnchain.controller]
+                    poll_interval_secs = 2
+                    "#,
+                )
+                .unwrap(),
+                false
+            )
+            .unwrap_err()
+        );
+
+        assert_eq!(
+            format!("burnchain.controller.fees.max_rbf is a percentage of satoshis_per_byte and must be at least 100, but is 50"),
+            Config::from_config_file(
+                ConfigFile::from_str(
+                    r#"
+                    [burnchain.controller.fees]
+                    max_rbf = 50
+                    "#,
+                )
+                .unwrap(),
+                false
+            )
+            .unwrap_err()
+        );
+
+        assert_eq!(
+            format!("burnchain.controller.poll_interval_secs must be at least 1"),
+            Config::from_config_file(
+                ConfigFile::from_str(
+                    r#"
+                    [burnchain]
+                    poll_time_secs = 0
+                    "#,
+                )
+                .unwrap(),
+                false
+            )
+            .unwrap_err()
+        );
+
+        assert!(ConfigFile::from_str(
+            r#"
+            [burnchain.controller]
+            poll_interval = 2
+            "#,
+        )
+        .unwrap_err()
+        .contains("unknown field `poll_interval`"));
+    }
+
+    #[test]
+    fn test_marf_cache_warming() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap(), false).unwrap();
+        assert_eq!(config.node.get_marf_cache_warming(), None);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                marf_cache_warm_blocks = 16
+                marf_cache_warm_accounts = ["ST2CY5V39NHDPWSXMW9QDT3HC3GD6Q6XX4CFRK9AG"]
+                marf_cache_warm_budget_ms = 5000
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap();
+        let warming = config.node.get_marf_cache_warming().unwrap();
+        assert_eq!(warming.recent_blocks, 16);
+        assert_eq!(warming.budget, Duration::from_millis(5000));
+        let principal = PrincipalData::parse("ST2CY5V39NHDPWSXMW9QDT3HC3GD6Q6XX4CFRK9AG").unwrap();
+        assert_eq!(
+            warming.hot_keys,
+            vec![
+                ClarityDatabase::make_key_for_account_balance(&principal),
+                ClarityDatabase::make_key_for_account_nonce(&principal),
+            ]
+        );
+
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                marf_cache_warm_accounts = ["not-a-principal"]
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap_err()
+        .starts_with("node.marf_cache_warm_accounts: invalid principal 'not-a-principal'"));
+    }
+
+    #[test]
+    fn test_marf_sync_policy() {
+        let node_config = |toml: &str| {
+            Config::from_config_file(ConfigFile::from_str(toml).unwrap(), false)
+                .map(|config| config.node)
+        };
+
+        let node = node_config("").unwrap();
+        assert_eq!(node.marf_sync_policy, TrieSyncPolicy::EveryBlock);
+        assert_eq!(node.get_marf_opts().sync_policy, TrieSyncPolicy::EveryBlock);
+
+        let node = node_config(
+            r#"
+            [node]
+            marf_sync_interval_blocks = 1
+            "#,
+        )
+        .unwrap();
+        assert_eq!(node.marf_sync_policy, TrieSyncPolicy::EveryBlock);
+
+        // syncing less often must be acknowledged as risky
+        let err = node_config(
+            r#"
+            [node]
+            marf_sync_interval_blocks = 10
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.contains("node.marf_sync_risk_acknowledged"), "{err}");
+
+        let node = node_config(
+            r#"
+            [node]
+            marf_sync_interval_blocks = 10
+            marf_sync_risk_acknowledged = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(node.marf_sync_policy, TrieSyncPolicy::EveryNBlocks(10));
+        assert_eq!(
+            node.get_marf_opts().sync_policy,
+            TrieSyncPolicy::EveryNBlocks(10)
+        );
+
+        assert!(node_config(
+            r#"
+            [node]
+            marf_sync_interval_blocks = 0
+            "#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_marf_key_index() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap(), false).unwrap();
+        assert!(!config.node.marf_key_index);
+        assert!(!config.node.get_marf_opts().key_index);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                marf_key_index = true
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap();
+        assert!(config.node.marf_key_index);
+        assert!(config.node.get_marf_opts().key_index);
+    }
+
+    #[test]
+    fn test_burnchain_block_stream() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap(), false).unwrap();
+        assert_eq!(config.burnchain.block_stream_bind, None);
+        assert_eq!(config.burnchain.block_stream_history, 1024);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                block_stream_bind = "127.0.0.1:20446"
+                block_stream_history = 16
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            config.burnchain.block_stream_bind.as_deref(),
+            Some("127.0.0.1:20446")
+        );
+        assert_eq!(config.burnchain.block_stream_history, 16);
+    }
+
+    #[test]
+    fn test_burnchain_readiness_bind() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap(), false).unwrap();
+        assert_eq!(config.burnchain.readiness_bind, None);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                readiness_bind = "127.0.0.1:20448"
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            config.burnchain.readiness_bind.as_deref(),
+            Some("127.0.0.1:20448")
+        );
+    }
+
+    #[test]
+    fn test_burnchain_rpc_auth() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                username = "user"
+                password_file = "/run/secrets/bitcoind-password"
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            config.burnchain.password_file.as_deref(),
+            Some("/run/secrets/bitcoind-password")
+        );
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                rpc_auth_token_env = "BITCOIND_TOKEN"
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            config.burnchain.rpc_auth_token_env.as_deref(),
+            Some("BITCOIND_TOKEN")
+        );
+
+        for conflicting in [
+            r#"
+            [burnchain]
+            username = "user"
+            password = "pass"
+            password_env = "BITCOIND_PASSWORD"
+            "#,
+            r#"
+            [burnchain]
+            username = "user"
+            password = "pass"
+            rpc_auth_token = "token"
+            "#,
+            r#"
+            [burnchain]
+            password_file = "/run/secrets/bitcoind-password"
+            "#,
+        ] {
+            assert!(
+                Config::from_config_file(ConfigFile::from_str(conflicting).unwrap(), false)
+                    .is_err()
+            );
+        }
+    }
+
     #[test]
     fn should_load_legacy_mstx_balances_toml() {
         let config = ConfigFile::from_str(
@@ -208,6 +601,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_load_attachment_address_family() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap(), false).unwrap();
+        assert_eq!(
+            config.connection_options.attachment_address_family,
+            AddressFamilyPreference::Any
+        );
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [connection_options]
+                attachment_address_family = "prefer_ipv6"
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .expect("Expected to be able to parse the attachment address family");
+        assert_eq!(
+            config.connection_options.attachment_address_family,
+            AddressFamilyPreference::PreferIpv6
+        );
+
+        let err = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [connection_options]
+                attachment_address_family = "ipv5"
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap_err();
+        assert!(err.contains("attachment_address_family"));
+    }
+
     #[test]
     fn should_load_affirmation_map() {
         let affirmation_string = "nnnnnnnnnnnnnnnnnnnnnnnnnnnnnnnnppnnnnnnnnnnnnnnnnnnnnnnnnpppppnnnnnnnnnnnnnnnnnnnnnnnpppppppppppppppnnnnnnnnnnnnnnnnnnnnnnnppppppppppnnnnnnnnnnnnnnnnnnnppppnnnnnnnnnnnnnnnnnnnnnnnppppppppnnnnnnnnnnnnnnnnnnnnnnnppnppnnnnnnnnnnnnnnnnnnnnnnnppppnnnnnnnnnnnnnnnnnnnnnnnnnppppppnnnnnnnnnnnnnnnnnnnnnnnnnppnnnnnnnnnnnnnnnnnnnnnnnnnpppppppnnnnnnnnnnnnnnnnnnnnnnnnnnpnnnnnnnnnnnnnnnnnnnnnnnnnpppnppppppppppppppnnppppnpa";
@@ -1096,6 +1527,7 @@ impl Config {
                     observers.insert(EventObserverConfig {
                         endpoint,
                         events_keys,
+                        drop_rejected_events: observer.drop_rejected_events,
                     });
                 }
                 observers
@@ -1109,6 +1541,7 @@ impl Config {
                 events_observers.insert(EventObserverConfig {
                     endpoint: val,
                     events_keys: vec![EventKeyType::AnyEvent],
+                    ..Default::default()
                 });
                 ()
             }
@@ -1126,7 +1559,9 @@ impl Config {
         };
 
         let atlas = match config_file.atlas {
-            Some(f) => f.into_config(is_mainnet),
+            Some(f) => f
+                .into_config(is_mainnet)
+                .map_err(|e| format!("Atlas config error: {e}"))?,
             None => AtlasConfig::new(is_mainnet),
         };
 
@@ -1147,7 +1582,7 @@ impl Config {
         })
     }
 
-    fn get_burnchain_path(&self) -> PathBuf {
+    pub fn get_burnchain_path(&self) -> PathBuf {
         let mut path = PathBuf::from(&self.node.working_dir);
         path.push(&self.burnchain.mode);
         path.push("burnchain");
@@ -1375,6 +1810,17 @@ pub struct BurnchainConfig {
     pub rpc_ssl: bool,
     pub username: Option<String>,
     pub password: Option<String>,
+    /// Read the RPC password from this file instead, re-reading it whenever it changes
+    pub password_file: Option<String>,
+    /// Read the RPC password from this environment variable instead
+    pub password_env: Option<String>,
+    /// Authenticate to the bitcoin node's RPC interface with this bearer token, instead of a
+    /// username and password
+    pub rpc_auth_token: Option<String>,
+    /// Read the bearer token from this file instead, re-reading it whenever it changes
+    pub rpc_auth_token_file: Option<String>,
+    /// Read the bearer token from this environment variable instead
+    pub rpc_auth_token_env: Option<String>,
     pub timeout: u32,
     pub magic_bytes: MagicBytes,
     pub local_mining_public_key: Option<String>,
@@ -1399,6 +1845,36 @@ pub struct BurnchainConfig {
     pub wallet_name: String,
     pub ast_precheck_size_height: Option<u64>,
     pub affirmation_overrides: HashMap<u64, AffirmationMap>,
+    /// On restart, sync and validate the burnchain headers up to the tip, but only process
+    /// burnchain blocks up to the height the run loop starts from before handing control to
+    /// it.  The remaining blocks are processed by a background task, one reward cycle at a
+    /// time, and its progress is reported in `/v2/info`.  This lets the node begin serving
+    /// chainstate queries sooner.
+    pub fast_header_sync: bool,
+    /// If set, every burnchain operation this node signs is appended to an HMAC-chained
+    /// audit log at this path.  The HMAC key is derived from the node seed.
+    pub op_audit_log_path: Option<String>,
+    /// Number of threads used to parse downloaded burnchain blocks into operations during
+    /// burnchain sync.  Blocks are still applied to the burnchain DB in order.
+    pub parser_threads: usize,
+    /// If set, serve the burnchain blocks processed by this node to WebSocket subscribers on
+    /// this address (e.g. `127.0.0.1:20446`).
+    pub block_stream_bind: Option<String>,
+    /// Number of recent burnchain blocks the block stream retains for subscribers that
+    /// resume from a height.
+    pub block_stream_history: usize,
+    /// If set, and the burnchain mode is `mocknet`, serve an HTTP admin API for driving the
+    /// simulated burnchain on this address (e.g. `127.0.0.1:20447`).
+    pub mocknet_admin_bind: Option<String>,
+    /// If set, serve readiness probes reporting how far the burnchain controller has come in
+    /// starting up on this address (e.g. `127.0.0.1:20448`).
+    pub readiness_bind: Option<String>,
+    /// If set, give up waiting for the chains coordinator to process sortitions after this
+    /// many milliseconds, instead of waiting indefinitely.
+    pub sortition_wait_timeout_ms: Option<u64>,
+    /// Number of burnchain blocks after which a submitted operation that was not mined is
+    /// considered failed.
+    pub op_expiry_blocks: u64,
 }
 
 impl BurnchainConfig {
@@ -1416,6 +1892,11 @@ impl BurnchainConfig {
             rpc_ssl: false,
             username: None,
             password: None,
+            password_file: None,
+            password_env: None,
+            rpc_auth_token: None,
+            rpc_auth_token_file: None,
+            rpc_auth_token_env: None,
             timeout: 300,
             magic_bytes: BLOCKSTACK_MAGIC_MAINNET.clone(),
             local_mining_public_key: None,
@@ -1438,8 +1919,70 @@ impl BurnchainConfig {
             wallet_name: "".to_string(),
             ast_precheck_size_height: None,
             affirmation_overrides: HashMap::new(),
+            fast_header_sync: false,
+            op_audit_log_path: None,
+            parser_threads: 1,
+            block_stream_bind: None,
+            block_stream_history: 1024,
+            mocknet_admin_bind: None,
+            readiness_bind: None,
+            sortition_wait_timeout_ms: None,
+            op_expiry_blocks: OP_CONFIRMATION_EXPIRY_BLOCKS,
+        }
+    }
+
+    /// The fee policy the burnchain controller applies to the operations it submits
+    pub fn fee_policy(&self) -> BurnchainFeePolicy {
+        BurnchainFeePolicy {
+            satoshis_per_byte: self.satoshis_per_byte,
+            rbf_fee_increment: self.rbf_fee_increment,
+            max_rbf: self.max_rbf,
+            burn_fee_cap: self.burn_fee_cap,
         }
     }
+
+    /// The settings of the burnchain controller, as reported by `/v2/burnchain/controller`
+    pub fn controller_settings(&self) -> BurnchainControllerSettings {
+        BurnchainControllerSettings {
+            mode: self.mode.clone(),
+            poll_interval_secs: self.poll_time_secs,
+            rpc_timeout_secs: self.timeout,
+            sortition_wait_timeout_ms: self.sortition_wait_timeout_ms,
+            op_expiry_blocks: self.op_expiry_blocks,
+            fees: self.fee_policy(),
+        }
+    }
+
+    /// Check the burnchain controller settings, whichever keys they were given by
+    fn validate_controller_settings(&self) -> Result<(), String> {
+        if self.poll_time_secs == 0 {
+            return Err("burnchain.controller.poll_interval_secs must be at least 1".into());
+        }
+        if self.timeout == 0 {
+            return Err("burnchain.controller.rpc_timeout_secs must be at least 1".into());
+        }
+        if self.sortition_wait_timeout_ms == Some(0) {
+            return Err(
+                "burnchain.controller.sortition_wait_timeout_ms must be at least 1, if set".into(),
+            );
+        }
+        if self.op_expiry_blocks == 0 {
+            return Err("burnchain.controller.op_expiry_blocks must be at least 1".into());
+        }
+        if self.satoshis_per_byte == 0 {
+            return Err("burnchain.controller.fees.satoshis_per_byte must be at least 1".into());
+        }
+        if self.max_rbf < 100 {
+            return Err(format!(
+                "burnchain.controller.fees.max_rbf is a percentage of satoshis_per_byte and must be at least 100, but is {}",
+                self.max_rbf
+            ));
+        }
+        if self.max_rbf > 100 && self.rbf_fee_increment == 0 {
+            return Err("burnchain.controller.fees.rbf_fee_increment must be at least 1 when max_rbf allows replace-by-fee".into());
+        }
+        Ok(())
+    }
     pub fn get_rpc_url(&self, wallet: Option<String>) -> String {
         let scheme = match self.rpc_ssl {
             true => "https://",
@@ -1510,6 +2053,11 @@ pub struct BurnchainConfigFile {
     pub rpc_ssl: Option<bool>,
     pub username: Option<String>,
     pub password: Option<String>,
+    pub password_file: Option<String>,
+    pub password_env: Option<String>,
+    pub rpc_auth_token: Option<String>,
+    pub rpc_auth_token_file: Option<String>,
+    pub rpc_auth_token_env: Option<String>,
     pub timeout: Option<u32>,
     pub magic_bytes: Option<String>,
     pub local_mining_public_key: Option<String>,
@@ -1532,6 +2080,61 @@ pub struct BurnchainConfigFile {
     pub wallet_name: Option<String>,
     pub ast_precheck_size_height: Option<u64>,
     pub affirmation_overrides: Option<Vec<AffirmationOverride>>,
+    pub fast_header_sync: Option<bool>,
+    pub op_audit_log_path: Option<String>,
+    pub parser_threads: Option<usize>,
+    pub block_stream_bind: Option<String>,
+    pub block_stream_history: Option<usize>,
+    pub mocknet_admin_bind: Option<String>,
+    pub readiness_bind: Option<String>,
+    pub sortition_wait_timeout_ms: Option<u64>,
+    pub controller: Option<BurnchainControllerConfigFile>,
+}
+
+/// The `[burnchain.controller]` section.  Each of its settings can instead be given by the
+/// older `[burnchain]` key noted next to it, but not by both.
+#[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BurnchainControllerConfigFile {
+    /// Or `burnchain.poll_time_secs`
+    pub poll_interval_secs: Option<u64>,
+    /// Or `burnchain.timeout`
+    pub rpc_timeout_secs: Option<u32>,
+    /// Or `burnchain.sortition_wait_timeout_ms`
+    pub sortition_wait_timeout_ms: Option<u64>,
+    pub op_expiry_blocks: Option<u64>,
+    pub fees: Option<BurnchainFeePolicyConfigFile>,
+}
+
+/// The `[burnchain.controller.fees]` section
+#[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BurnchainFeePolicyConfigFile {
+    /// Or `burnchain.satoshis_per_byte`
+    pub satoshis_per_byte: Option<u64>,
+    /// Or `burnchain.rbf_fee_increment`
+    pub rbf_fee_increment: Option<u64>,
+    /// Or `burnchain.max_rbf`
+    pub max_rbf: Option<u64>,
+    /// Or `burnchain.burn_fee_cap`
+    pub burn_fee_cap: Option<u64>,
+}
+
+/// Resolve a burnchain controller setting that can be given by either its older `[burnchain]`
+/// key or its `[burnchain.controller]` key.  Giving it by both is only allowed if they agree.
+fn resolve_controller_setting<T: PartialEq + fmt::Display>(
+    legacy: Option<T>,
+    legacy_key: &str,
+    controller: Option<T>,
+    controller_key: &str,
+) -> Result<Option<T>, String> {
+    match (legacy, controller) {
+        (Some(legacy), Some(controller)) if legacy != controller => Err(format!(
+            "{} ({}) conflicts with {} ({}); remove {}",
+            legacy_key, legacy, controller_key, controller, legacy_key
+        )),
+        (legacy, controller) => Ok(controller.or(legacy)),
+    }
 }
 
 impl BurnchainConfigFile {
@@ -1625,6 +2228,51 @@ impl BurnchainConfigFile {
             }
         }
 
+        let controller = self.controller.take().unwrap_or_default();
+        let fees = controller.fees.unwrap_or_default();
+        let poll_time_secs = resolve_controller_setting(
+            self.poll_time_secs,
+            "burnchain.poll_time_secs",
+            controller.poll_interval_secs,
+            "burnchain.controller.poll_interval_secs",
+        )?;
+        let timeout = resolve_controller_setting(
+            self.timeout,
+            "burnchain.timeout",
+            controller.rpc_timeout_secs,
+            "burnchain.controller.rpc_timeout_secs",
+        )?;
+        let sortition_wait_timeout_ms = resolve_controller_setting(
+            self.sortition_wait_timeout_ms,
+            "burnchain.sortition_wait_timeout_ms",
+            controller.sortition_wait_timeout_ms,
+            "burnchain.controller.sortition_wait_timeout_ms",
+        )?;
+        let satoshis_per_byte = resolve_controller_setting(
+            self.satoshis_per_byte,
+            "burnchain.satoshis_per_byte",
+            fees.satoshis_per_byte,
+            "burnchain.controller.fees.satoshis_per_byte",
+        )?;
+        let rbf_fee_increment = resolve_controller_setting(
+            self.rbf_fee_increment,
+            "burnchain.rbf_fee_increment",
+            fees.rbf_fee_increment,
+            "burnchain.controller.fees.rbf_fee_increment",
+        )?;
+        let max_rbf = resolve_controller_setting(
+            self.max_rbf,
+            "burnchain.max_rbf",
+            fees.max_rbf,
+            "burnchain.controller.fees.max_rbf",
+        )?;
+        let burn_fee_cap = resolve_controller_setting(
+            self.burn_fee_cap,
+            "burnchain.burn_fee_cap",
+            fees.burn_fee_cap,
+            "burnchain.controller.fees.burn_fee_cap",
+        )?;
+
         let mut affirmation_overrides = HashMap::new();
         if let Some(aos) = self.affirmation_overrides {
             for ao in aos {
@@ -1657,9 +2305,7 @@ impl BurnchainConfigFile {
                 PEER_VERSION_TESTNET
             },
             mode,
-            burn_fee_cap: self
-                .burn_fee_cap
-                .unwrap_or(default_burnchain_config.burn_fee_cap),
+            burn_fee_cap: burn_fee_cap.unwrap_or(default_burnchain_config.burn_fee_cap),
             commit_anchor_block_within: self
                 .commit_anchor_block_within
                 .unwrap_or(default_burnchain_config.commit_anchor_block_within),
@@ -1689,7 +2335,12 @@ impl BurnchainConfigFile {
             rpc_ssl: self.rpc_ssl.unwrap_or(default_burnchain_config.rpc_ssl),
             username: self.username,
             password: self.password,
-            timeout: self.timeout.unwrap_or(default_burnchain_config.timeout),
+            password_file: self.password_file,
+            password_env: self.password_env,
+            rpc_auth_token: self.rpc_auth_token,
+            rpc_auth_token_file: self.rpc_auth_token_file,
+            rpc_auth_token_env: self.rpc_auth_token_env,
+            timeout: timeout.unwrap_or(default_burnchain_config.timeout),
             magic_bytes: self
                 .magic_bytes
                 .map(|magic_ascii| {
@@ -1700,21 +2351,17 @@ impl BurnchainConfigFile {
                 .unwrap_or(default_burnchain_config.magic_bytes),
             local_mining_public_key: self.local_mining_public_key,
             process_exit_at_block_height: self.process_exit_at_block_height,
-            poll_time_secs: self
-                .poll_time_secs
-                .unwrap_or(default_burnchain_config.poll_time_secs),
-            satoshis_per_byte: self
-                .satoshis_per_byte
+            poll_time_secs: poll_time_secs.unwrap_or(default_burnchain_config.poll_time_secs),
+            satoshis_per_byte: satoshis_per_byte
                 .unwrap_or(default_burnchain_config.satoshis_per_byte),
-            max_rbf: self.max_rbf.unwrap_or(default_burnchain_config.max_rbf),
+            max_rbf: max_rbf.unwrap_or(default_burnchain_config.max_rbf),
             leader_key_tx_estimated_size: self
                 .leader_key_tx_estimated_size
                 .unwrap_or(default_burnchain_config.leader_key_tx_estimated_size),
             block_commit_tx_estimated_size: self
                 .block_commit_tx_estimated_size
                 .unwrap_or(default_burnchain_config.block_commit_tx_estimated_size),
-            rbf_fee_increment: self
-                .rbf_fee_increment
+            rbf_fee_increment: rbf_fee_increment
                 .unwrap_or(default_burnchain_config.rbf_fee_increment),
             first_burn_block_height: self
                 .first_burn_block_height
@@ -1744,6 +2391,23 @@ impl BurnchainConfigFile {
                 .pox_prepare_length
                 .or(default_burnchain_config.pox_prepare_length),
             affirmation_overrides,
+            fast_header_sync: self
+                .fast_header_sync
+                .unwrap_or(default_burnchain_config.fast_header_sync),
+            op_audit_log_path: self.op_audit_log_path,
+            parser_threads: self
+                .parser_threads
+                .unwrap_or(default_burnchain_config.parser_threads),
+            block_stream_bind: self.block_stream_bind,
+            block_stream_history: self
+                .block_stream_history
+                .unwrap_or(default_burnchain_config.block_stream_history),
+            mocknet_admin_bind: self.mocknet_admin_bind,
+            readiness_bind: self.readiness_bind,
+            sortition_wait_timeout_ms,
+            op_expiry_blocks: controller
+                .op_expiry_blocks
+                .unwrap_or(default_burnchain_config.op_expiry_blocks),
         };
 
         if let BitcoinNetworkType::Mainnet = config.get_bitcoin_network().1 {
@@ -1763,6 +2427,13 @@ impl BurnchainConfigFile {
             }
         }
 
+        if config.parser_threads == 0 {
+            return Err("burnchain.parser_threads must be at least 1".into());
+        }
+
+        config.validate_controller_settings()?;
+        RpcAuth::from_config(&config)?;
+
         if let Some(ref conf_epochs) = self.epochs {
             config.epochs = Some(Config::make_epochs(
                 conf_epochs,
@@ -1805,6 +2476,26 @@ pub struct NodeConfig {
     pub prometheus_bind: Option<String>,
     pub marf_cache_strategy: Option<String>,
     pub marf_defer_hashing: bool,
+    /// Number of MARF node hashes to cache across blocks, which speeds up reads of old keys
+    /// (0 disables the cache)
+    pub marf_node_hash_cache_size: usize,
+    /// Number of most recent tries of the Clarity MARF to read into its node cache on startup,
+    /// before the node serves RPC requests (0 disables warming the recent tries)
+    pub marf_cache_warm_blocks: u32,
+    /// Accounts whose balance and nonce are read at the chain tip on startup, to warm the MARF
+    /// paths to them
+    pub marf_cache_warm_accounts: Vec<PrincipalData>,
+    /// Time budget, in milliseconds, for warming the MARF node cache on startup
+    pub marf_cache_warm_budget_ms: u64,
+    /// When the Clarity MARF's trie blobs are synced to disk.  Syncing less often than every
+    /// block speeds up block processing on disks with slow syncs, at the risk of having to
+    /// resync the chainstate after a host crash.
+    pub marf_sync_policy: TrieSyncPolicy,
+    /// Whether the MARFs keep an index of their keys, so that keys can be listed by prefix (e.g.
+    /// all the data-map entries of a contract).  Only takes effect on a new chainstate: an
+    /// existing chainstate is opened without the index, with a warning.  The index holds every
+    /// key ever written in any fork and is never pruned, and takes extra disk space to match.
+    pub marf_key_index: bool,
     pub pox_sync_sample_secs: u64,
     pub use_test_genesis_chainstate: Option<bool>,
     pub always_use_affirmation_maps: bool,
@@ -1815,6 +2506,9 @@ pub struct NodeConfig {
     /// At most, how often should the chain-liveness thread
     ///  wake up the chains-coordinator. Defaults to 300s (5 min).
     pub chain_liveness_poll_time_secs: u64,
+    /// How long to wait at shutdown for the event deliveries that are under way, after which
+    /// events that can't be delivered are dropped. Defaults to 30s.
+    pub event_observers_shutdown_timeout_secs: u64,
     /// stacker DBs we replicate
     pub stacker_dbs: Vec<QualifiedContractIdentifier>,
 }
@@ -2091,12 +2785,19 @@ impl Default for NodeConfig {
             prometheus_bind: None,
             marf_cache_strategy: None,
             marf_defer_hashing: true,
+            marf_node_hash_cache_size: 0,
+            marf_cache_warm_blocks: 0,
+            marf_cache_warm_accounts: vec![],
+            marf_cache_warm_budget_ms: 30_000,
+            marf_sync_policy: TrieSyncPolicy::EveryBlock,
+            marf_key_index: false,
             pox_sync_sample_secs: 30,
             use_test_genesis_chainstate: None,
             always_use_affirmation_maps: false,
             require_affirmed_anchor_blocks: true,
             fault_injection_hide_blocks: false,
             chain_liveness_poll_time_secs: 300,
+            event_observers_shutdown_timeout_secs: 30,
             stacker_dbs: vec![],
         }
     }
@@ -2260,6 +2961,32 @@ impl NodeConfig {
                 .unwrap_or(&"noop".to_string()),
             false,
         )
+        .with_node_hash_cache_size(self.marf_node_hash_cache_size)
+        .with_sync_policy(self.marf_sync_policy)
+        .with_key_index(self.marf_key_index)
+    }
+
+    /// What to read into the Clarity MARF's node cache on startup, or None if warming is
+    /// disabled
+    pub fn get_marf_cache_warming(&self) -> Option<TrieCacheWarming> {
+        if self.marf_cache_warm_blocks == 0 && self.marf_cache_warm_accounts.is_empty() {
+            return None;
+        }
+        let hot_keys = self
+            .marf_cache_warm_accounts
+            .iter()
+            .flat_map(|principal| {
+                [
+                    ClarityDatabase::make_key_for_account_balance(principal),
+                    ClarityDatabase::make_key_for_account_nonce(principal),
+                ]
+            })
+            .collect();
+        Some(TrieCacheWarming {
+            recent_blocks: self.marf_cache_warm_blocks,
+            hot_keys,
+            budget: Duration::from_millis(self.marf_cache_warm_budget_ms),
+        })
     }
 }
 
@@ -2312,6 +3039,9 @@ pub struct MinerConfig {
     pub max_reorg_depth: u64,
     /// Amount of time while mining in nakamoto to wait for signers to respond to a proposed block
     pub wait_on_signers: Duration,
+    /// Don't start a tenure on a burnchain tip whose header time is more than this many seconds
+    /// in the past.  None to mine on burnchain tips of any age.
+    pub max_burn_tip_age_secs: Option<u64>,
 }
 
 impl Default for MinerConfig {
@@ -2342,6 +3072,7 @@ impl Default for MinerConfig {
             max_reorg_depth: 3,
             // TODO: update to a sane value based on stackerdb benchmarking
             wait_on_signers: Duration::from_secs(200),
+            max_burn_tip_age_secs: None,
         }
     }
 }
@@ -2371,6 +3102,16 @@ pub struct ConnectionOptionsFile {
     pub dns_timeout: Option<u64>,
     pub max_inflight_blocks: Option<u64>,
     pub max_inflight_attachments: Option<u64>,
+    pub attachment_not_found_ttl: Option<u64>,
+    pub attachment_chunk_size: Option<u64>,
+    pub max_attachment_redirects: Option<u64>,
+    /// Which of a peer's addresses to download attachments from: `any` (the default),
+    /// `prefer_ipv4`, `prefer_ipv6`, `ipv4_only` or `ipv6_only`
+    pub attachment_address_family: Option<String>,
+    pub max_atlas_requests_per_minute: Option<u64>,
+    pub max_atlas_bytes_per_minute: Option<u64>,
+    pub atlas_audit_interval: Option<u64>,
+    pub atlas_audit_rows_per_pass: Option<u32>,
     pub read_only_call_limit_write_length: Option<u64>,
     pub read_only_call_limit_read_length: Option<u64>,
     pub read_only_call_limit_write_count: Option<u64>,
@@ -2389,6 +3130,10 @@ pub struct ConnectionOptionsFile {
     pub antientropy_public: Option<bool>,
     pub private_neighbors: Option<bool>,
     pub block_proposal_token: Option<String>,
+    pub burnchain_sync_token: Option<String>,
+    pub attachments_download_token: Option<String>,
+    pub burnchain_ops_token: Option<String>,
+    pub marf_diff_token: Option<String>,
     pub antientropy_retry: Option<u64>,
 }
 
@@ -2421,6 +3166,19 @@ impl ConnectionOptionsFile {
         self.read_only_call_limit_runtime.map(|x| {
             read_only_call_limit.runtime = x;
         });
+        let attachment_address_family = self
+            .attachment_address_family
+            .as_deref()
+            .map(|name| {
+                AddressFamilyPreference::from_name(name).ok_or_else(|| {
+                    format!(
+                        "Invalid connection_options.attachment_address_family: {}. Expected one of any, prefer_ipv4, prefer_ipv6, ipv4_only or ipv6_only",
+                        name
+                    )
+                })
+            })
+            .transpose()?
+            .unwrap_or(HELIUM_DEFAULT_CONNECTION_OPTIONS.attachment_address_family);
         let default = ConnectionOptions::default();
         Ok(ConnectionOptions {
             read_only_call_limit,
@@ -2482,6 +3240,28 @@ impl ConnectionOptionsFile {
             max_inflight_attachments: self
                 .max_inflight_attachments
                 .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.max_inflight_attachments),
+            attachment_not_found_ttl: self
+                .attachment_not_found_ttl
+                .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.attachment_not_found_ttl),
+            attachment_chunk_size: self
+                .attachment_chunk_size
+                .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.attachment_chunk_size),
+            max_attachment_redirects: self
+                .max_attachment_redirects
+                .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.max_attachment_redirects),
+            attachment_address_family,
+            max_atlas_requests_per_minute: self
+                .max_atlas_requests_per_minute
+                .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.max_atlas_requests_per_minute),
+            max_atlas_bytes_per_minute: self
+                .max_atlas_bytes_per_minute
+                .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.max_atlas_bytes_per_minute),
+            atlas_audit_interval: self
+                .atlas_audit_interval
+                .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.atlas_audit_interval),
+            atlas_audit_rows_per_pass: self
+                .atlas_audit_rows_per_pass
+                .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.atlas_audit_rows_per_pass),
             maximum_call_argument_size: self
                 .maximum_call_argument_size
                 .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.maximum_call_argument_size),
@@ -2515,6 +3295,10 @@ impl ConnectionOptionsFile {
             antientropy_public: self.antientropy_public.unwrap_or(true),
             private_neighbors: self.private_neighbors.unwrap_or(true),
             block_proposal_token: self.block_proposal_token,
+            burnchain_sync_token: self.burnchain_sync_token,
+            attachments_download_token: self.attachments_download_token,
+            burnchain_ops_token: self.burnchain_ops_token,
+            marf_diff_token: self.marf_diff_token,
             antientropy_retry: self.antientropy_retry.unwrap_or(default.antientropy_retry),
             ..default
         })
@@ -2545,6 +3329,17 @@ pub struct NodeConfigFile {
     pub prometheus_bind: Option<String>,
     pub marf_cache_strategy: Option<String>,
     pub marf_defer_hashing: Option<bool>,
+    pub marf_node_hash_cache_size: Option<usize>,
+    pub marf_cache_warm_blocks: Option<u32>,
+    pub marf_cache_warm_accounts: Option<Vec<String>>,
+    pub marf_cache_warm_budget_ms: Option<u64>,
+    /// Sync the Clarity MARF's trie blobs to disk once every this many blocks, rather than after
+    /// every block.  Values above 1 require `marf_sync_risk_acknowledged`.
+    pub marf_sync_interval_blocks: Option<u32>,
+    /// Acknowledge that with `marf_sync_interval_blocks` above 1, a host crash can corrupt the
+    /// chainstate
+    pub marf_sync_risk_acknowledged: Option<bool>,
+    pub marf_key_index: Option<bool>,
     pub pox_sync_sample_secs: Option<u64>,
     pub use_test_genesis_chainstate: Option<bool>,
     pub always_use_affirmation_maps: Option<bool>,
@@ -2552,6 +3347,9 @@ pub struct NodeConfigFile {
     /// At most, how often should the chain-liveness thread
     ///  wake up the chains-coordinator. Defaults to 300s (5 min).
     pub chain_liveness_poll_time_secs: Option<u64>,
+    /// How long to wait at shutdown for the event deliveries that are under way, after which
+    /// events that can't be delivered are dropped. Defaults to 30s.
+    pub event_observers_shutdown_timeout_secs: Option<u64>,
     /// Stacker DBs we replicate
     pub stacker_dbs: Option<Vec<String>>,
 }
@@ -2561,6 +3359,21 @@ impl NodeConfigFile {
         let rpc_bind = self.rpc_bind.unwrap_or(default_node_config.rpc_bind);
         let miner = self.miner.unwrap_or(default_node_config.miner);
         let stacker = self.stacker.unwrap_or(default_node_config.stacker);
+        let marf_sync_policy = match self.marf_sync_interval_blocks {
+            None => default_node_config.marf_sync_policy,
+            Some(0) => return Err("node.marf_sync_interval_blocks must be at least 1".into()),
+            Some(1) => TrieSyncPolicy::EveryBlock,
+            Some(interval) => {
+                if !self.marf_sync_risk_acknowledged.unwrap_or(false) {
+                    return Err(format!(
+                        "node.marf_sync_interval_blocks = {interval} means a host crash can lose \
+                         the MARF tries of up to {interval} blocks and leave the chainstate \
+                         corrupt; set node.marf_sync_risk_acknowledged = true to accept this"
+                    ));
+                }
+                TrieSyncPolicy::EveryNBlocks(interval)
+            }
+        };
         let node_config = NodeConfig {
             name: self.name.unwrap_or(default_node_config.name),
             seed: match self.seed {
@@ -2610,6 +3423,33 @@ impl NodeConfigFile {
             marf_defer_hashing: self
                 .marf_defer_hashing
                 .unwrap_or(default_node_config.marf_defer_hashing),
+            marf_node_hash_cache_size: self
+                .marf_node_hash_cache_size
+                .unwrap_or(default_node_config.marf_node_hash_cache_size),
+            marf_cache_warm_blocks: self
+                .marf_cache_warm_blocks
+                .unwrap_or(default_node_config.marf_cache_warm_blocks),
+            marf_cache_warm_accounts: match self.marf_cache_warm_accounts {
+                Some(accounts) => accounts
+                    .iter()
+                    .map(|account| {
+                        PrincipalData::parse(account).map_err(|e| {
+                            format!(
+                                "node.marf_cache_warm_accounts: invalid principal '{}': {}",
+                                account, e
+                            )
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                None => default_node_config.marf_cache_warm_accounts,
+            },
+            marf_cache_warm_budget_ms: self
+                .marf_cache_warm_budget_ms
+                .unwrap_or(default_node_config.marf_cache_warm_budget_ms),
+            marf_sync_policy,
+            marf_key_index: self
+                .marf_key_index
+                .unwrap_or(default_node_config.marf_key_index),
             pox_sync_sample_secs: self
                 .pox_sync_sample_secs
                 .unwrap_or(default_node_config.pox_sync_sample_secs),
@@ -2626,6 +3466,9 @@ impl NodeConfigFile {
             chain_liveness_poll_time_secs: self
                 .chain_liveness_poll_time_secs
                 .unwrap_or(default_node_config.chain_liveness_poll_time_secs),
+            event_observers_shutdown_timeout_secs: self
+                .event_observers_shutdown_timeout_secs
+                .unwrap_or(default_node_config.event_observers_shutdown_timeout_secs),
             stacker_dbs: self
                 .stacker_dbs
                 .unwrap_or(vec![])
@@ -2673,6 +3516,7 @@ pub struct MinerConfigFile {
     pub filter_origins: Option<String>,
     pub max_reorg_depth: Option<u64>,
     pub wait_on_signers_ms: Option<u64>,
+    pub max_burn_tip_age_secs: Option<u64>,
 }
 
 impl MinerConfigFile {
@@ -2775,6 +3619,9 @@ impl MinerConfigFile {
                 .wait_on_signers_ms
                 .map(Duration::from_millis)
                 .unwrap_or(miner_default_config.wait_on_signers),
+            max_burn_tip_age_secs: self
+                .max_burn_tip_age_secs
+                .or(miner_default_config.max_burn_tip_age_secs),
         })
     }
 }
@@ -2784,11 +3631,18 @@ pub struct AtlasConfigFile {
     pub max_uninstantiated_attachments: Option<u32>,
     pub uninstantiated_attachments_expire_after: Option<u32>,
     pub unresolved_attachment_instances_expire_after: Option<u32>,
+    pub max_queued_attachment_batches: Option<u32>,
+    /// Where to store attachment content: `sqlite` (in the Atlas DB) or `filesystem` (in a
+    /// directory of content-addressed files next to it)
+    pub attachment_storage: Option<String>,
+    /// How long to keep the record of when attachment instances were first seen and resolved,
+    /// in seconds.  0 disables the record.
+    pub resolution_history_retention: Option<u32>,
 }
 
 impl AtlasConfigFile {
     // Can't inplement `Into` trait because this takes a parameter
-    fn into_config(&self, mainnet: bool) -> AtlasConfig {
+    fn into_config(&self, mainnet: bool) -> Result<AtlasConfig, String> {
         let mut conf = AtlasConfig::new(mainnet);
         if let Some(val) = self.attachments_max_size {
             conf.attachments_max_size = val
@@ -2802,7 +3656,16 @@ impl AtlasConfigFile {
         if let Some(val) = self.unresolved_attachment_instances_expire_after {
             conf.unresolved_attachment_instances_expire_after = val
         }
-        conf
+        if let Some(val) = self.max_queued_attachment_batches {
+            conf.max_queued_attachment_batches = val
+        }
+        if let Some(val) = self.attachment_storage.as_ref() {
+            conf.attachment_storage = val.parse()?;
+        }
+        if let Some(val) = self.resolution_history_retention {
+            conf.resolution_history_retention = val
+        }
+        Ok(conf)
     }
 }
 
@@ -2810,12 +3673,18 @@ impl AtlasConfigFile {
 pub struct EventObserverConfigFile {
     pub endpoint: String,
     pub events_keys: Vec<String>,
+    /// Drop, rather than retry, events that the observer answers with 400 or 422.  Set this
+    /// for a signer's event receiver, which uses these to reject events it will never accept.
+    #[serde(default)]
+    pub drop_rejected_events: bool,
 }
 
 #[derive(Clone, Default, Debug, Hash, PartialEq, Eq, PartialOrd)]
 pub struct EventObserverConfig {
     pub endpoint: String,
     pub events_keys: Vec<EventKeyType>,
+    /// Drop, rather than retry, events that the observer answers with 400 or 422
+    pub drop_rejected_events: bool,
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd)]
@@ -2831,6 +3700,8 @@ pub enum EventKeyType {
     MinedMicroblocks,
     StackerDBChunks,
     BlockProposal,
+    /// `print-event` events emitted under the given topic, from any contract
+    StructuredEvent(String),
 }
 
 impl EventKeyType {
@@ -2863,6 +3734,13 @@ impl EventKeyType {
             return Some(EventKeyType::BlockProposal);
         }
 
+        if let Some(topic) = raw_key.strip_prefix("print_event:") {
+            if topic.is_empty() || topic.len() > 128 || !topic.is_ascii() {
+                return None;
+            }
+            return Some(EventKeyType::StructuredEvent(topic.to_string()));
+        }
+
         let comps: Vec<_> = raw_key.split("::").collect();
         if comps.len() == 1 {
             let split: Vec<_> = comps[0].split('.').collect();
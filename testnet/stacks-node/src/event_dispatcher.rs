@@ -1,7 +1,8 @@
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use std::thread::sleep;
 use std::time::Duration;
 
@@ -52,6 +53,90 @@ use super::config::{EventKeyType, EventObserverConfig};
 #[derive(Debug, Clone)]
 struct EventObserver {
     endpoint: String,
+    delivery: Arc<DeliveryState>,
+    /// Whether an event that this observer rejects is dropped rather than retried.  This is
+    /// configured per observer, and is meant for the signer's event receiver, which tells
+    /// rejected events apart from failed deliveries.
+    drop_rejected_events: bool,
+}
+
+/// The state of event delivery, shared by an `EventDispatcher`, its clones and their observers
+#[derive(Debug, Default)]
+struct DeliveryState {
+    /// Set once the node is shutting down and has stopped waiting for deliveries: an event that
+    /// can't be delivered is dropped rather than retried
+    stopping: AtomicBool,
+    /// Identifies the next delivery in `in_flight`
+    next_delivery_id: AtomicU64,
+    /// The events being delivered
+    in_flight: Mutex<HashMap<u64, PendingDelivery>>,
+}
+
+/// An event that is being delivered to an observer
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingDelivery {
+    pub endpoint: String,
+    pub path: String,
+    /// The block, transaction or contract that the event is about, if its payload names one
+    pub event_id: Option<String>,
+}
+
+/// The payload fields that identify what an event is about, in order of preference
+const PAYLOAD_ID_FIELDS: [&str; 7] = [
+    "index_block_hash",
+    "block_hash",
+    "signer_signature_hash",
+    "burn_block_hash",
+    "parent_index_block_hash",
+    "txid",
+    "contract_id",
+];
+
+/// The block, transaction or contract that the event with `payload` is about, if it names one
+fn payload_event_id(payload: &serde_json::Value) -> Option<String> {
+    PAYLOAD_ID_FIELDS.iter().find_map(|field| {
+        payload
+            .get(field)
+            .and_then(|value| value.as_str())
+            .map(|value| format!("{}={}", field, value))
+    })
+}
+
+/// Counts an event as being delivered for as long as it is alive
+struct InFlightDelivery<'a> {
+    delivery: &'a DeliveryState,
+    id: u64,
+}
+
+impl<'a> InFlightDelivery<'a> {
+    fn new(delivery: &'a DeliveryState, pending: PendingDelivery) -> Self {
+        let id = delivery.next_delivery_id.fetch_add(1, Ordering::SeqCst);
+        delivery
+            .in_flight
+            .lock()
+            .expect("FATAL: poisoned event delivery lock")
+            .insert(id, pending);
+        Self { delivery, id }
+    }
+}
+
+impl Drop for InFlightDelivery<'_> {
+    fn drop(&mut self) {
+        // don't panic again while unwinding
+        if let Ok(mut in_flight) = self.delivery.in_flight.lock() {
+            in_flight.remove(&self.id);
+        }
+    }
+}
+
+/// Whether to retry a POST to an event observer that was answered with the non-success
+/// `status`.  The signer's event receiver answers an event that it read and rejected, because
+/// it is malformed or concerns a contract that signers do not follow, with 400 or 422 (see
+/// libsigner's `RejectedEventResponses`), so sending it again will not help, if the observer
+/// is configured with `drop_rejected_events`.  Any other failure may be transient, and
+/// observers without that setting are always retried.
+fn should_retry_failed_post(status: u16, drop_rejected_events: bool) -> bool {
+    !(drop_rejected_events && matches!(status, 400 | 422))
 }
 
 struct ReceiptPayloadInfo<'a> {
@@ -315,6 +400,14 @@ impl EventObserver {
         };
 
         let backoff = Duration::from_millis((1.0 * 1_000.0) as u64);
+        let _in_flight = InFlightDelivery::new(
+            &self.delivery,
+            PendingDelivery {
+                endpoint: self.endpoint.clone(),
+                path: path.to_string(),
+                event_id: payload_event_id(payload),
+            },
+        );
 
         loop {
             let body = body.clone();
@@ -346,12 +439,26 @@ impl EventObserver {
                         "Event dispatcher: Successful POST"; "url" => %url
                     );
                     break;
+                } else if !should_retry_failed_post(
+                    response.status() as u16,
+                    self.drop_rejected_events,
+                ) {
+                    error!(
+                        "Event dispatcher: POST rejected by observer, will not retry"; "url" => %url, "err" => ?response
+                    );
+                    break;
                 } else {
                     error!(
                         "Event dispatcher: Failed POST"; "url" => %url, "err" => ?response
                     );
                 }
             }
+            if self.delivery.stopping.load(Ordering::SeqCst) {
+                warn!(
+                    "Event dispatcher: shutting down, dropping event that could not be delivered"; "url" => %url
+                );
+                break;
+            }
             sleep(backoff);
         }
     }
@@ -370,6 +477,7 @@ impl EventObserver {
     fn make_new_burn_block_payload(
         burn_block: &BurnchainHeaderHash,
         burn_block_height: u64,
+        burn_block_timestamp: u64,
         rewards: Vec<(PoxAddress, u64)>,
         burns: u64,
         slot_holders: Vec<PoxAddress>,
@@ -392,6 +500,7 @@ impl EventObserver {
         json!({
             "burn_block_hash": format!("0x{}", burn_block),
             "burn_block_height": burn_block_height,
+            "burn_block_timestamp": burn_block_timestamp,
             "reward_recipients": serde_json::Value::Array(reward_recipients),
             "reward_slot_holders": serde_json::Value::Array(reward_slot_holders),
             "burn_amount": burns
@@ -680,6 +789,8 @@ pub struct EventDispatcher {
     mined_microblocks_observers_lookup: HashSet<u16>,
     stackerdb_observers_lookup: HashSet<u16>,
     block_proposal_observers_lookup: HashSet<u16>,
+    structured_events_observers_lookup: HashMap<String, HashSet<u16>>,
+    delivery: Arc<DeliveryState>,
 }
 
 /// This struct is used specifically for receiving proposal responses.
@@ -845,6 +956,7 @@ impl BlockEventDispatcher for EventDispatcher {
         &self,
         burn_block: &BurnchainHeaderHash,
         burn_block_height: u64,
+        burn_block_timestamp: u64,
         rewards: Vec<(PoxAddress, u64)>,
         burns: u64,
         recipient_info: Vec<PoxAddress>,
@@ -852,6 +964,7 @@ impl BlockEventDispatcher for EventDispatcher {
         self.process_burn_block(
             burn_block,
             burn_block_height,
+            burn_block_timestamp,
             rewards,
             burns,
             recipient_info,
@@ -874,13 +987,40 @@ impl EventDispatcher {
             mined_microblocks_observers_lookup: HashSet::new(),
             stackerdb_observers_lookup: HashSet::new(),
             block_proposal_observers_lookup: HashSet::new(),
+            structured_events_observers_lookup: HashMap::new(),
+            delivery: Arc::new(DeliveryState::default()),
         }
     }
 
+    /// Stop retrying deliveries that fail, in this dispatcher and all of its clones.  Each event
+    /// that can't be delivered from now on is dropped after one more attempt.  Called on
+    /// shutdown once the node has stopped waiting for deliveries, so that an observer that is
+    /// down can't keep the node from exiting.
+    pub fn stop_retrying(&self) {
+        self.delivery.stopping.store(true, Ordering::SeqCst);
+    }
+
+    /// Number of events being delivered by this dispatcher and all of its clones
+    pub fn deliveries_in_flight(&self) -> usize {
+        self.pending_deliveries().len()
+    }
+
+    /// The events being delivered by this dispatcher and all of its clones
+    pub fn pending_deliveries(&self) -> Vec<PendingDelivery> {
+        self.delivery
+            .in_flight
+            .lock()
+            .expect("FATAL: poisoned event delivery lock")
+            .values()
+            .cloned()
+            .collect()
+    }
+
     pub fn process_burn_block(
         &self,
         burn_block: &BurnchainHeaderHash,
         burn_block_height: u64,
+        burn_block_timestamp: u64,
         rewards: Vec<(PoxAddress, u64)>,
         burns: u64,
         recipient_info: Vec<PoxAddress>,
@@ -894,6 +1034,7 @@ impl EventDispatcher {
         let payload = EventObserver::make_new_burn_block_payload(
             burn_block,
             burn_block_height,
+            burn_block_timestamp,
             rewards,
             burns,
             recipient_info,
@@ -939,6 +1080,16 @@ impl EventDispatcher {
                             }
                         }
                     }
+                    StacksTransactionEvent::StructuredEvent(event_data) => {
+                        if let Some(observer_indexes) = self
+                            .structured_events_observers_lookup
+                            .get(&event_data.topic)
+                        {
+                            for o_i in observer_indexes {
+                                dispatch_matrix[*o_i as usize].insert(i);
+                            }
+                        }
+                    }
                     StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(_))
                     | StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(_))
                     | StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(_))
@@ -1363,6 +1514,8 @@ impl EventDispatcher {
         info!("Registering event observer at: {}", conf.endpoint);
         let event_observer = EventObserver {
             endpoint: conf.endpoint.clone(),
+            delivery: self.delivery.clone(),
+            drop_rejected_events: conf.drop_rejected_events,
         };
 
         let observer_index = self.registered_observers.len() as u16;
@@ -1424,6 +1577,12 @@ impl EventDispatcher {
                 EventKeyType::BlockProposal => {
                     self.block_proposal_observers_lookup.insert(observer_index);
                 }
+                EventKeyType::StructuredEvent(topic) => {
+                    self.structured_events_observers_lookup
+                        .entry(topic.clone())
+                        .or_insert_with(HashSet::new)
+                        .insert(observer_index);
+                }
             }
         }
 
@@ -1433,6 +1592,12 @@ impl EventDispatcher {
 
 #[cfg(test)]
 mod test {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
     use clarity::vm::costs::ExecutionCost;
     use stacks::burnchains::{PoxConstants, Txid};
     use stacks::chainstate::stacks::db::StacksHeaderInfo;
@@ -1440,12 +1605,19 @@ mod test {
     use stacks_common::bitvec::BitVec;
     use stacks_common::types::chainstate::{BurnchainHeaderHash, StacksBlockId};
 
-    use crate::event_dispatcher::EventObserver;
+    use crate::config::{EventKeyType, EventObserverConfig};
+    use crate::event_dispatcher::{
+        should_retry_failed_post, EventDispatcher, EventObserver, PendingDelivery,
+    };
+    use crate::shutdown::{ShutdownManager, StageStatus};
+    use crate::Config;
 
     #[test]
     fn build_block_processed_event() {
         let observer = EventObserver {
             endpoint: "nowhere".to_string(),
+            delivery: Arc::default(),
+            drop_rejected_events: false,
         };
 
         let filtered_events = vec![];
@@ -1499,4 +1671,174 @@ mod test {
             expected_bitvec_str
         );
     }
+
+    #[test]
+    fn rejected_posts_are_not_retried() {
+        // the signer's event receiver rejected a malformed event, or one it does not follow
+        assert!(!should_retry_failed_post(400, true));
+        assert!(!should_retry_failed_post(422, true));
+        // other failures
+        assert!(should_retry_failed_post(404, true));
+        assert!(should_retry_failed_post(408, true));
+        assert!(should_retry_failed_post(429, true));
+        assert!(should_retry_failed_post(500, true));
+        assert!(should_retry_failed_post(503, true));
+        // observers other than the signer are always retried
+        assert!(should_retry_failed_post(400, false));
+        assert!(should_retry_failed_post(422, false));
+    }
+
+    /// Serve `statuses` to one POST each, and return the number of POSTs served
+    fn serve_statuses(statuses: Vec<u16>) -> (String, thread::JoinHandle<usize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let mut served = 0;
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = vec![];
+                let mut buf = [0u8; 1024];
+                // read the headers and the (empty JSON object) body
+                while !request.ends_with(b"{}") {
+                    let n = stream.read(&mut buf).unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+                served += 1;
+            }
+            served
+        });
+        (endpoint, handle)
+    }
+
+    #[test]
+    fn rejected_posts_are_retried_for_other_observers() {
+        let (endpoint, server) = serve_statuses(vec![400, 200]);
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.register_observer(&EventObserverConfig {
+            endpoint,
+            events_keys: vec![EventKeyType::BurnchainBlocks],
+            drop_rejected_events: false,
+        });
+
+        let observer = &dispatcher.registered_observers[0];
+        observer.send_payload(&serde_json::json!({}), "/new_burn_block");
+        assert_eq!(server.join().unwrap(), 2);
+
+        // subscribing to block proposals, as an indexer might, does not make an observer the
+        // signer
+        let (endpoint, server) = serve_statuses(vec![422, 200]);
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.register_observer(&EventObserverConfig {
+            endpoint,
+            events_keys: vec![EventKeyType::AnyEvent, EventKeyType::BlockProposal],
+            drop_rejected_events: false,
+        });
+
+        let observer = &dispatcher.registered_observers[0];
+        observer.send_payload(&serde_json::json!({}), "/proposal_response");
+        assert_eq!(server.join().unwrap(), 2);
+    }
+
+    #[test]
+    fn rejected_posts_are_dropped_for_the_signer() {
+        let (endpoint, server) = serve_statuses(vec![422]);
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.register_observer(&EventObserverConfig {
+            endpoint,
+            events_keys: vec![EventKeyType::StackerDBChunks, EventKeyType::BlockProposal],
+            drop_rejected_events: true,
+        });
+
+        // returns after the first POST instead of retrying
+        let observer = &dispatcher.registered_observers[0];
+        observer.send_payload(&serde_json::json!({}), "/stackerdb_chunks");
+        assert_eq!(server.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn failed_posts_are_retried_until_delivered() {
+        let (endpoint, server) = serve_statuses(vec![503, 503, 200]);
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.register_observer(&EventObserverConfig {
+            endpoint,
+            events_keys: vec![],
+            ..Default::default()
+        });
+
+        let observer = &dispatcher.registered_observers[0];
+        observer.send_payload(&serde_json::json!({}), "/new_block");
+        assert_eq!(server.join().unwrap(), 3);
+        assert_eq!(dispatcher.deliveries_in_flight(), 0);
+    }
+
+    #[test]
+    fn failed_posts_are_dropped_once_stopping() {
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.register_observer(&EventObserverConfig {
+            // nothing listens on port 1
+            endpoint: "127.0.0.1:1".to_string(),
+            events_keys: vec![],
+            ..Default::default()
+        });
+        dispatcher.stop_retrying();
+
+        // returns instead of retrying forever
+        let observer = &dispatcher.registered_observers[0];
+        observer.send_payload(&serde_json::json!({}), "/new_block");
+        assert_eq!(dispatcher.deliveries_in_flight(), 0);
+    }
+
+    #[test]
+    fn shutdown_gives_up_on_dead_observers() {
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.register_observer(&EventObserverConfig {
+            // nothing listens on port 1
+            endpoint: "127.0.0.1:1".to_string(),
+            events_keys: vec![],
+            ..Default::default()
+        });
+        let observer = dispatcher.registered_observers[0].clone();
+        let delivery = thread::spawn(move || {
+            observer.send_payload(
+                &serde_json::json!({ "index_block_hash": "0x01", "block_height": 1 }),
+                "/new_block",
+            )
+        });
+        while dispatcher.deliveries_in_flight() == 0 {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            dispatcher.pending_deliveries(),
+            vec![PendingDelivery {
+                endpoint: "127.0.0.1:1".to_string(),
+                path: "/new_block".to_string(),
+                event_id: Some("index_block_hash=0x01".to_string()),
+            }]
+        );
+
+        let mut config = Config::default();
+        config.node.event_observers_shutdown_timeout_secs = 1;
+        let mut shutdown = ShutdownManager::new();
+        shutdown.run_final_stages(&dispatcher, &config);
+
+        // the databases are not checkpointed after the observers' stage times out
+        let stages: Vec<_> = shutdown
+            .outcomes()
+            .iter()
+            .map(|outcome| (outcome.name, outcome.status))
+            .collect();
+        assert_eq!(stages, vec![("event-observers", StageStatus::TimedOut)]);
+
+        // and the delivery is no longer retried
+        delivery.join().unwrap();
+        assert_eq!(dispatcher.deliveries_in_flight(), 0);
+    }
 }
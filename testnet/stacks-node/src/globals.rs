@@ -3,6 +3,7 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::SyncSender;
 use std::sync::{Arc, Mutex};
 
+use stacks::burnchains::sync_control::BurnchainSyncControl;
 use stacks::burnchains::Txid;
 use stacks::chainstate::burn::operations::LeaderKeyRegisterOp;
 use stacks::chainstate::burn::BlockSnapshot;
@@ -69,6 +70,8 @@ pub struct Globals<T> {
     /// previously-selected best tips
     /// maps stacks height to tip candidate
     previous_best_tips: Arc<Mutex<BTreeMap<u64, TipCandidate>>>,
+    /// Pause/resume handle for the main thread's burnchain sync loop (shared with the p2p thread)
+    pub burnchain_sync_control: BurnchainSyncControl,
 }
 
 // Need to manually implement Clone, because [derive(Clone)] requires
@@ -90,6 +93,7 @@ impl<T> Clone for Globals<T> {
             start_mining_height: self.start_mining_height.clone(),
             estimated_winning_probs: self.estimated_winning_probs.clone(),
             previous_best_tips: self.previous_best_tips.clone(),
+            burnchain_sync_control: self.burnchain_sync_control.clone(),
         }
     }
 }
@@ -120,6 +124,7 @@ impl<T> Globals<T> {
             start_mining_height: Arc::new(Mutex::new(start_mining_height)),
             estimated_winning_probs: Arc::new(Mutex::new(HashMap::new())),
             previous_best_tips: Arc::new(Mutex::new(BTreeMap::new())),
+            burnchain_sync_control: BurnchainSyncControl::new(),
         }
     }
 
@@ -27,6 +27,7 @@ pub mod neon_node;
 pub mod node;
 pub mod operations;
 pub mod run_loop;
+pub mod shutdown;
 pub mod syncctl;
 pub mod tenure;
 
@@ -55,6 +56,7 @@ pub use self::run_loop::{helium, neon};
 pub use self::tenure::Tenure;
 use crate::chain_data::MinerStats;
 use crate::neon_node::{BlockMinerThread, TipCandidate};
+use crate::operations::OpAuditLog;
 use crate::run_loop::boot_nakamoto;
 
 #[cfg(not(any(target_os = "macos", target_os = "windows", target_arch = "arm")))]
@@ -292,6 +294,9 @@ fn main() {
 
     info!("{}", version());
 
+    #[cfg(feature = "tracing_spans")]
+    burnchains::sync_span::install_trace_exporter();
+
     let mine_start: Option<u64> = args
         .opt_value_from_str("--mine-at-height")
         .expect("Failed to parse --mine-at-height argument");
@@ -392,6 +397,29 @@ fn main() {
             );
             return;
         }
+        "export-op-audit-log" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            args.finish();
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path).unwrap(), true)
+                .unwrap();
+            let Some(log_path) = conf.burnchain.op_audit_log_path.as_ref() else {
+                eprintln!("No `burnchain.op_audit_log_path` is configured");
+                process::exit(1);
+            };
+            let key = OpAuditLog::key_from_seed(&conf.node.seed);
+            match OpAuditLog::export(log_path, &key) {
+                Ok(records) => {
+                    for record in records.iter() {
+                        println!("{}", serde_json::to_string(record).unwrap());
+                    }
+                    process::exit(0);
+                }
+                Err(e) => {
+                    eprintln!("Failed to export op audit log at {}: {}", log_path, e);
+                    process::exit(1);
+                }
+            }
+        }
         "pick-best-tip" => {
             let config_path: String = args.value_from_str("--config").unwrap();
             let at_stacks_height: Option<u64> =
@@ -502,6 +530,10 @@ key-for-seed\tOutput the associated secret key for a burnchain signer created wi
 \t\tCan be passed a config file for the seed via the `--config <file>` option *or* by supplying the hex seed on
 \t\tthe command line directly.
 
+export-op-audit-log\tVerify the audit log of signed burnchain operations and print its records as JSON lines.
+\t\tArguments:
+\t\t  --config: path of the config that sets `burnchain.op_audit_log_path` and the node seed.
+
 help\t\tDisplay this help.
 
 OPTIONAL ARGUMENTS:
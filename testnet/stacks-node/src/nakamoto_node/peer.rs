@@ -33,8 +33,9 @@ use stacks::net::RPCHandlerArgs;
 use stacks_common::util::hash::Sha256Sum;
 
 use crate::burnchains::make_bitcoin_indexer;
+use crate::burnchains::op_confirmations::OpConfirmationsReader;
 use crate::nakamoto_node::relayer::RelayerDirective;
-use crate::neon_node::open_chainstate_with_faults;
+use crate::neon_node::{open_chainstate_with_faults, warm_chainstate_cache};
 use crate::run_loop::nakamoto::{Globals, RunLoop};
 use crate::{Config, EventDispatcher};
 
@@ -124,6 +125,11 @@ impl PeerThread {
             }
         }
 
+        // hand the Atlas downloader's unfinished batches back to the AtlasDB
+        if let Err(e) = self.net.drain_attachments_downloader() {
+            warn!("P2P: failed to drain the Atlas downloader"; "err" => ?e);
+        }
+
         // kill miner
         signal_mining_blocked(self.globals.get_miner_status());
 
@@ -168,8 +174,9 @@ impl PeerThread {
         let sortdb = SortitionDB::open(&burn_db_path, false, pox_constants)
             .expect("FATAL: could not open sortition DB");
 
-        let chainstate =
+        let mut chainstate =
             open_chainstate_with_faults(&config).expect("FATAL: could not open chainstate DB");
+        warm_chainstate_cache(&config, &sortdb, &mut chainstate);
 
         let p2p_sock: SocketAddr = config
             .node
@@ -258,6 +265,9 @@ impl PeerThread {
         let p2p_res = {
             // NOTE: handler_args must be created such that it outlives the inner net.run() call and
             // doesn't ref anything within p2p_thread.
+            let burnchain_sync_control = self.globals.burnchain_sync_control.clone();
+            let submitted_burnchain_ops = OpConfirmationsReader::new(&self.config);
+            let burnchain_controller_settings = self.config.burnchain.controller_settings();
             let handler_args = RPCHandlerArgs {
                 exit_at_block_height: self.config.burnchain.process_exit_at_block_height.clone(),
                 genesis_chainstate_hash: Sha256Sum::from_hex(stx_genesis::GENESIS_CHAINSTATE_HASH)
@@ -266,6 +276,10 @@ impl PeerThread {
                 cost_estimator: Some(cost_estimator.as_ref()),
                 cost_metric: Some(cost_metric.as_ref()),
                 fee_estimator: fee_estimator.map(|boxed_estimator| boxed_estimator.as_ref()),
+                burnchain_sync_control: Some(&burnchain_sync_control),
+                submitted_burnchain_ops: Some(&submitted_burnchain_ops),
+                burnchain_op_intents: Some(&submitted_burnchain_ops),
+                burnchain_controller_settings: Some(&burnchain_controller_settings),
                 ..RPCHandlerArgs::default()
             };
             self.net.run(
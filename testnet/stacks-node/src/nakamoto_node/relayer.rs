@@ -21,24 +21,18 @@ use std::time::{Duration, Instant};
 
 use stacks::burnchains::{Burnchain, Txid};
 use stacks::chainstate::burn::db::sortdb::SortitionDB;
-use stacks::chainstate::burn::operations::leader_block_commit::{
-    RewardSetInfo, BURN_BLOCK_MINED_AT_MODULUS,
-};
 use stacks::chainstate::burn::operations::{
     BlockstackOperationType, LeaderBlockCommitOp, LeaderKeyRegisterOp,
 };
 use stacks::chainstate::burn::{BlockSnapshot, ConsensusHash};
 use stacks::chainstate::nakamoto::coordinator::get_nakamoto_next_recipients;
 use stacks::chainstate::nakamoto::NakamotoChainState;
-use stacks::chainstate::stacks::address::PoxAddress;
 use stacks::chainstate::stacks::db::StacksChainState;
 use stacks::chainstate::stacks::miner::{
     get_mining_spend_amount, signal_mining_blocked, signal_mining_ready,
 };
 use stacks::core::mempool::MemPoolDB;
-use stacks::core::{
-    FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH, STACKS_EPOCH_3_0_MARKER,
-};
+use stacks::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
 use stacks::monitoring::increment_stx_blocks_mined_counter;
 use stacks::net::db::LocalPeer;
 use stacks::net::relay::Relayer;
@@ -55,6 +49,9 @@ use super::{
     BlockCommits, Config, Error as NakamotoNodeError, EventDispatcher, Keychain,
     BLOCK_PROCESSOR_STACK_SIZE,
 };
+use crate::burnchains::commit_template::{
+    BlockCommitIntent, BlockCommitTemplateBuilder, Error as CommitTemplateError,
+};
 use crate::burnchains::BurnchainController;
 use crate::nakamoto_node::miner::{BlockMinerThread, MinerDirective};
 use crate::neon_node::{
@@ -441,89 +438,49 @@ impl RelayerThread {
             return Err(NakamotoNodeError::UnexpectedChainState);
         }
 
-        let Ok(Some(parent_sortition)) =
-            SortitionDB::get_block_snapshot_consensus(self.sortdb.conn(), target_ch)
-        else {
-            error!("Relayer: Failed to lookup the block snapshot of parent tenure ID"; "tenure_consensus_hash" => %target_ch);
-            return Err(NakamotoNodeError::ParentNotFound);
-        };
-
-        let Ok(Some(target_epoch)) =
-            SortitionDB::get_stacks_epoch(self.sortdb.conn(), sort_tip.block_height + 1)
-        else {
-            error!("Relayer: Failed to lookup its epoch"; "target_height" => sort_tip.block_height + 1);
-            return Err(NakamotoNodeError::SnapshotNotFoundForChainTip);
-        };
-
-        let parent_block_burn_height = parent_sortition.block_height;
-        let Ok(Some(parent_winning_tx)) = SortitionDB::get_block_commit(
-            self.sortdb.conn(),
-            &parent_sortition.winning_block_txid,
-            &parent_sortition.sortition_id,
-        ) else {
-            error!("Relayer: Failed to lookup the block commit of parent tenure ID"; "tenure_consensus_hash" => %target_ch);
-            return Err(NakamotoNodeError::SnapshotNotFoundForChainTip);
-        };
-
-        let parent_winning_vtxindex = parent_winning_tx.vtxindex;
-
-        let burn_fee_cap = get_mining_spend_amount(self.globals.get_miner_status());
-        let sunset_burn = self.burnchain.expected_sunset_burn(
-            sort_tip.block_height + 1,
-            burn_fee_cap,
-            target_epoch.epoch_id,
-        );
-        let rest_commit = burn_fee_cap - sunset_burn;
-
-        let commit_outs = if !self
-            .burnchain
-            .pox_constants
-            .is_after_pox_sunset_end(sort_tip.block_height, target_epoch.epoch_id)
-            && !self
-                .burnchain
-                .is_in_prepare_phase(sort_tip.block_height + 1)
-        {
-            RewardSetInfo::into_commit_outs(recipients, self.config.is_mainnet())
-        } else {
-            vec![PoxAddress::standard_burn_address(self.config.is_mainnet())]
-        };
-
-        // let's commit, but target the current burnchain tip with our modulus
-        let burn_parent_modulus = u8::try_from(sort_tip.block_height % BURN_BLOCK_MINED_AT_MODULUS)
-            .map_err(|_| {
-                error!("Relayer: Block mining modulus is not u8");
-                NakamotoNodeError::UnexpectedChainState
-            })?;
-        let sender = self.keychain.get_burnchain_signer();
         let key = self
             .globals
             .get_leader_key_registration_state()
             .get_active()
             .ok_or_else(|| NakamotoNodeError::NoVRFKeyActive)?;
-        let op = LeaderBlockCommitOp {
-            sunset_burn,
+        let intent = BlockCommitIntent {
+            parent_consensus_hash: *target_ch,
             block_header_hash: BlockHeaderHash(parent_block_id.0),
-            burn_fee: rest_commit,
-            input: (Txid([0; 32]), 0),
-            apparent_sender: sender,
-            key_block_ptr: u32::try_from(key.block_height)
-                .expect("FATAL: burn block height exceeded u32"),
-            key_vtxindex: u16::try_from(key.op_vtxindex).expect("FATAL: vtxindex exceeded u16"),
-            memo: vec![STACKS_EPOCH_3_0_MARKER],
+            burn_fee_cap: get_mining_spend_amount(self.globals.get_miner_status()),
+            recipients,
+            key,
             new_seed: VRFSeed::from_proof(&parent_vrf_proof),
-            parent_block_ptr: u32::try_from(parent_block_burn_height)
-                .expect("FATAL: burn block height exceeded u32"),
-            parent_vtxindex: u16::try_from(parent_winning_vtxindex)
-                .expect("FATAL: vtxindex exceeded u16"),
-            vtxindex: 0,
-            txid: Txid([0u8; 32]),
-            block_height: 0,
-            burn_header_hash: BurnchainHeaderHash::zero(),
-            burn_parent_modulus,
-            commit_outs,
+            apparent_sender: self.keychain.get_burnchain_signer(),
         };
+        let builder = BlockCommitTemplateBuilder::new(
+            &self.sortdb,
+            &self.burnchain,
+            self.config.is_mainnet(),
+        );
+        let template = match builder.build(intent) {
+            Ok(template) => template,
+            Err(e) => {
+                error!("Relayer: Failed to assemble block-commit: {e}";
+                       "tenure_consensus_hash" => %target_ch);
+                return Err(match e {
+                    CommitTemplateError::UnknownParent(_)
+                    | CommitTemplateError::ParentNotCanonical(_)
+                    | CommitTemplateError::ParentHasNoWinner(_) => {
+                        NakamotoNodeError::ParentNotFound
+                    }
+                    CommitTemplateError::UnknownKey { .. } => NakamotoNodeError::NoVRFKeyActive,
+                    CommitTemplateError::OutOfRange(_) => NakamotoNodeError::UnexpectedChainState,
+                    _ => NakamotoNodeError::SnapshotNotFoundForChainTip,
+                });
+            }
+        };
+
+        // the recipient set was computed against our view of the tip
+        if template.sort_tip.sortition_id != sort_tip.sortition_id {
+            return Err(NakamotoNodeError::BurnchainTipChanged);
+        }
 
-        Ok((sort_tip, target_epoch.epoch_id, op))
+        Ok((template.sort_tip, template.epoch_id, template.op))
     }
 
     /// Create the block miner thread state.
@@ -482,7 +482,7 @@ impl SignCoordinator {
             })?;
             let packets: Vec<_> = messages
                 .into_iter()
-                .filter_map(|msg| match msg {
+                .filter_map(|(_chunk_id, msg)| match msg {
                     SignerMessage::DkgResults { .. }
                     | SignerMessage::BlockResponse(_)
                     | SignerMessage::EncryptedSignerState(_)
@@ -144,7 +144,7 @@ use std::io::{Read, Write};
 use std::net::SocketAddr;
 use std::sync::mpsc::{Receiver, TrySendError};
 use std::thread::JoinHandle;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{fs, mem, thread};
 
 use clarity::vm::ast::ASTRules;
@@ -160,12 +160,14 @@ use stacks::chainstate::burn::operations::leader_block_commit::{
 use stacks::chainstate::burn::operations::{
     BlockstackOperationType, LeaderBlockCommitOp, LeaderKeyRegisterOp,
 };
+use stacks::chainstate::burn::simulation::{HypotheticalCommit, SortitionSimulation};
 use stacks::chainstate::burn::{BlockSnapshot, ConsensusHash};
 use stacks::chainstate::coordinator::{get_next_recipients, OnChainRewardSetProvider};
 use stacks::chainstate::nakamoto::NakamotoChainState;
 use stacks::chainstate::stacks::address::PoxAddress;
 use stacks::chainstate::stacks::db::blocks::StagingBlock;
 use stacks::chainstate::stacks::db::{StacksChainState, StacksHeaderInfo, MINER_REWARD_MATURITY};
+use stacks::chainstate::stacks::index::marf::MarfConnection;
 use stacks::chainstate::stacks::miner::{
     signal_mining_blocked, signal_mining_ready, BlockBuilderSettings, StacksMicroblockBuilder,
 };
@@ -207,6 +209,7 @@ use crate::burnchains::bitcoin_regtest_controller::{
     addr2str, burnchain_params_from_config, BitcoinRegtestController, OngoingBlockCommit,
 };
 use crate::burnchains::make_bitcoin_indexer;
+use crate::burnchains::op_confirmations::OpConfirmationsReader;
 use crate::chain_data::MinerStats;
 use crate::globals::{NeonGlobals as Globals, RelayerDirective};
 use crate::run_loop::neon::RunLoop;
@@ -371,6 +374,43 @@ pub(crate) fn open_chainstate_with_faults(
     Ok(chainstate)
 }
 
+/// Read the recent tries and hot accounts configured in `node.marf_cache_warm_*` into the
+/// Clarity MARF's node cache, so that the first RPC requests after startup do not all go to
+/// disk.  Failing to warm the cache is not fatal.
+pub(crate) fn warm_chainstate_cache(
+    config: &Config,
+    sortdb: &SortitionDB,
+    chainstate: &mut StacksChainState,
+) {
+    let Some(warming) = config.node.get_marf_cache_warming() else {
+        return;
+    };
+    let tip = match NakamotoChainState::get_canonical_block_header(chainstate.db(), sortdb) {
+        Ok(Some(header)) => header.index_block_hash(),
+        Ok(None) => {
+            debug!("No Stacks chain tip yet; not warming the MARF node cache");
+            return;
+        }
+        Err(e) => {
+            warn!("Failed to load the Stacks chain tip to warm the MARF node cache: {e:?}");
+            return;
+        }
+    };
+    let started_at = Instant::now();
+    match chainstate.with_clarity_marf(|marf| marf.warm_cache(&tip, &warming)) {
+        Ok(stats) => info!(
+            "Warmed the MARF node cache";
+            "tip" => %tip,
+            "keys_read" => stats.keys_read,
+            "tries_read" => stats.tries_read,
+            "nodes_read" => stats.nodes_read,
+            "out_of_time" => stats.out_of_time,
+            "elapsed_ms" => started_at.elapsed().as_millis(),
+        ),
+        Err(e) => warn!("Failed to warm the MARF node cache: {e:?}"; "tip" => %tip),
+    }
+}
+
 /// Types of errors that can arise during mining
 enum Error {
     /// Can't find the header record for the chain tip
@@ -2168,6 +2208,31 @@ impl BlockMinerThread {
         );
         let rest_commit = burn_fee_cap - sunset_burn;
 
+        // let the operator know how this spend is likely to fare
+        match SortitionSimulation::simulate(
+            burn_db,
+            &self.burnchain,
+            &self.burn_block,
+            &HypotheticalCommit {
+                apparent_sender: self.keychain.get_burnchain_signer(),
+                burn_fee: rest_commit,
+                input: None,
+            },
+        ) {
+            Ok(simulation) => {
+                info!("Relayer: estimated block-commit win probability";
+                      "burn_fee" => rest_commit,
+                      "burn_block_height" => simulation.burn_block_height,
+                      "effective_burn" => simulation.burns,
+                      "total_burns" => simulation.total_burns,
+                      "num_commits" => simulation.num_commits,
+                      "win_probability" => simulation.win_probability);
+            }
+            Err(e) => {
+                warn!("Relayer: failed to simulate sortition: {:?}", &e);
+            }
+        }
+
         // let's commit, but target the current burnchain tip with our modulus
         let op = self.inner_generate_block_commit_op(
             block_hash,
@@ -4179,8 +4244,9 @@ impl PeerThread {
         let sortdb = SortitionDB::open(&burn_db_path, false, pox_constants)
             .expect("FATAL: could not open sortition DB");
 
-        let chainstate =
+        let mut chainstate =
             open_chainstate_with_faults(&config).expect("FATAL: could not open chainstate DB");
+        warm_chainstate_cache(&config, &sortdb, &mut chainstate);
 
         let p2p_sock: SocketAddr = config
             .node
@@ -4294,6 +4360,9 @@ impl PeerThread {
         let p2p_res = self.with_chainstate(|p2p_thread, sortdb, chainstate, mempool| {
             // NOTE: handler_args must be created such that it outlives the inner net.run() call and
             // doesn't ref anything within p2p_thread.
+            let burnchain_sync_control = p2p_thread.globals.burnchain_sync_control.clone();
+            let submitted_burnchain_ops = OpConfirmationsReader::new(&p2p_thread.config);
+            let burnchain_controller_settings = p2p_thread.config.burnchain.controller_settings();
             let handler_args = RPCHandlerArgs {
                 exit_at_block_height: p2p_thread
                     .config
@@ -4306,6 +4375,10 @@ impl PeerThread {
                 cost_estimator: Some(cost_estimator.as_ref()),
                 cost_metric: Some(cost_metric.as_ref()),
                 fee_estimator: fee_estimator.map(|boxed_estimator| boxed_estimator.as_ref()),
+                burnchain_sync_control: Some(&burnchain_sync_control),
+                submitted_burnchain_ops: Some(&submitted_burnchain_ops),
+                burnchain_op_intents: Some(&submitted_burnchain_ops),
+                burnchain_controller_settings: Some(&burnchain_controller_settings),
                 ..RPCHandlerArgs::default()
             };
             p2p_thread.with_network(|_, net| {
@@ -4563,14 +4636,16 @@ impl StacksNode {
             tx.commit().unwrap();
         }
 
-        // update services to indicate we can support mempool sync and stackerdb
+        // update services to indicate we can support mempool sync, stackerdb, and attachment
+        // inventories over p2p
         {
             let mut tx = peerdb.tx_begin().unwrap();
             PeerDB::set_local_services(
                 &mut tx,
                 (ServiceFlags::RPC as u16)
                     | (ServiceFlags::RELAY as u16)
-                    | (ServiceFlags::STACKERDB as u16),
+                    | (ServiceFlags::STACKERDB as u16)
+                    | (ServiceFlags::ATTACHMENTS_INV as u16),
             )
             .unwrap();
             tx.commit().unwrap();
@@ -4730,6 +4805,13 @@ impl StacksNode {
             }
         }
 
+        // hand the Atlas downloader's unfinished batches back to the AtlasDB
+        if let Some(net) = p2p_thread.net.as_mut() {
+            if let Err(e) = net.drain_attachments_downloader() {
+                warn!("P2P: failed to drain the Atlas downloader"; "err" => ?e);
+            }
+        }
+
         // kill miner
         signal_mining_blocked(p2p_thread.globals.get_miner_status());
 
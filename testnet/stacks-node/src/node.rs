@@ -32,7 +32,7 @@ use stacks::core::mempool::MemPoolDB;
 use stacks::core::STACKS_EPOCH_2_1_MARKER;
 use stacks::cost_estimates::metrics::UnitMetric;
 use stacks::cost_estimates::UnitEstimator;
-use stacks::net::atlas::{AtlasConfig, AtlasDB, AttachmentInstance};
+use stacks::net::atlas::{AtlasConfig, AtlasDB, AttachmentInstance, AttachmentOrigin};
 use stacks::net::db::PeerDB;
 use stacks::net::p2p::PeerNetwork;
 use stacks::net::stackerdb::StackerDBs;
@@ -965,7 +965,10 @@ impl Node {
                                         .map(|t| t.metadata.stacks_block_height),
                                 );
                                 if let Some(attachment_instance) = res {
-                                    attachments_instances.insert(attachment_instance);
+                                    let origin =
+                                        AttachmentOrigin::of_receipt(epoch_receipt, receipt);
+                                    attachments_instances
+                                        .insert(attachment_instance.with_origin(origin));
                                 }
                             }
                         }
@@ -1,5 +1,13 @@
-use stacks::burnchains::PrivateKey;
-use stacks_common::util::hash::hex_bytes;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::{fmt, io};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use stacks::burnchains::{PrivateKey, Txid};
+use stacks_common::util::get_epoch_time_secs;
+use stacks_common::util::hash::{hex_bytes, Sha256Sum};
 use stacks_common::util::secp256k1::{MessageSignature, Secp256k1PrivateKey, Secp256k1PublicKey};
 
 pub struct BurnchainOpSigner {
@@ -62,11 +70,350 @@ impl BurnchainOpSigner {
     }
 }
 
+/// Domain separator for deriving the audit log's HMAC key from the node seed
+const OP_AUDIT_LOG_KEY_DOMAIN: &[u8] = b"stacks-node-op-audit-log";
+/// Suffix of the file, next to the log, that records the log's last record
+const OP_AUDIT_LOG_HEAD_SUFFIX: &str = ".head";
+
+fn hmac_sha256(key: &[u8; 32], data: &[u8]) -> Sha256Sum {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key).expect("FATAL: HMAC accepts keys of any length");
+    mac.update(data);
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&mac.finalize().into_bytes());
+    Sha256Sum(digest)
+}
+
+#[derive(Debug)]
+pub enum OpAuditLogError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The record on the given (1-indexed) line does not extend the HMAC chain
+    BrokenChain(u64),
+    /// The log holds fewer records than its head file says were written: records were removed
+    /// from its end
+    Truncated {
+        records: u64,
+        expected: u64,
+    },
+    /// The log has records, but its head file is missing or was not written with this key
+    BadHead,
+}
+
+impl fmt::Display for OpAuditLogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpAuditLogError::Io(e) => write!(f, "I/O error: {}", e),
+            OpAuditLogError::Json(e) => write!(f, "Malformed record: {}", e),
+            OpAuditLogError::BrokenChain(line) => {
+                write!(f, "Audit log HMAC chain is broken at line {}", line)
+            }
+            OpAuditLogError::Truncated { records, expected } => write!(
+                f,
+                "Audit log holds {} records, but {} were written",
+                records, expected
+            ),
+            OpAuditLogError::BadHead => write!(f, "Audit log head file is missing or invalid"),
+        }
+    }
+}
+
+impl From<io::Error> for OpAuditLogError {
+    fn from(e: io::Error) -> Self {
+        OpAuditLogError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for OpAuditLogError {
+    fn from(e: serde_json::Error) -> Self {
+        OpAuditLogError::Json(e)
+    }
+}
+
+/// One burnchain operation signed by a `BurnchainOpSigner`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpAuditRecord {
+    /// Position of this record in the log, starting at 0
+    pub seq: u64,
+    /// When the operation was signed, in seconds since the epoch
+    pub timestamp: u64,
+    /// The kind of operation (e.g. `LeaderBlockCommit`)
+    pub op_type: String,
+    /// The burnchain transaction that carries the operation
+    pub txid: Txid,
+    /// SHA256 of the signed, serialized burnchain transaction
+    pub payload_digest: Sha256Sum,
+    /// The burnchain height the operation was signed for
+    pub target_burn_height: u64,
+    /// The `hmac` of the previous record, or all zeros for the first record
+    pub prev_hmac: Sha256Sum,
+    /// HMAC over this record's fields, including `prev_hmac`
+    pub hmac: Sha256Sum,
+}
+
+impl OpAuditRecord {
+    fn compute_hmac(&self, key: &[u8; 32]) -> Sha256Sum {
+        let data = format!(
+            "{}|{}|{}|{}|{}|{}|{}",
+            self.seq,
+            self.timestamp,
+            self.op_type,
+            self.txid,
+            self.payload_digest,
+            self.target_burn_height,
+            self.prev_hmac
+        );
+        hmac_sha256(key, data.as_bytes())
+    }
+}
+
+/// The number of records written to an `OpAuditLog` and the HMAC of the last one, kept in a
+/// file next to the log.  The HMAC chain alone can't tell a log whose last records were cut
+/// off from a shorter log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct OpAuditLogHead {
+    records: u64,
+    /// `hmac` of the last record, or all zeros if there are none
+    last_hmac: Sha256Sum,
+    /// HMAC over `records` and `last_hmac`
+    hmac: Sha256Sum,
+}
+
+impl OpAuditLogHead {
+    fn new(records: u64, last_hmac: Sha256Sum, key: &[u8; 32]) -> Self {
+        let mut head = OpAuditLogHead {
+            records,
+            last_hmac,
+            hmac: Sha256Sum::zero(),
+        };
+        head.hmac = head.compute_hmac(key);
+        head
+    }
+
+    fn compute_hmac(&self, key: &[u8; 32]) -> Sha256Sum {
+        let data = format!("head|{}|{}", self.records, self.last_hmac);
+        hmac_sha256(key, data.as_bytes())
+    }
+
+    fn path(log_path: &Path) -> PathBuf {
+        let mut path = log_path.as_os_str().to_owned();
+        path.push(OP_AUDIT_LOG_HEAD_SUFFIX);
+        PathBuf::from(path)
+    }
+
+    /// Read the head of the log at `log_path`, if there is one
+    fn read(log_path: &Path, key: &[u8; 32]) -> Result<Option<Self>, OpAuditLogError> {
+        let path = Self::path(log_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let head: OpAuditLogHead =
+            serde_json::from_slice(&fs::read(&path)?).map_err(|_| OpAuditLogError::BadHead)?;
+        if head.compute_hmac(key) != head.hmac {
+            return Err(OpAuditLogError::BadHead);
+        }
+        Ok(Some(head))
+    }
+
+    /// Replace the head of the log at `log_path`
+    fn write(&self, log_path: &Path) -> Result<(), OpAuditLogError> {
+        let path = Self::path(log_path);
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&serde_json::to_vec(self)?)?;
+        file.sync_data()?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    /// Check that `records`, whose HMAC chain is intact, hold every record this head says
+    /// was written.  The log may hold one more: the node can stop between appending a record
+    /// and updating the head.
+    fn check(&self, records: &[OpAuditRecord]) -> Result<(), OpAuditLogError> {
+        let truncated = OpAuditLogError::Truncated {
+            records: records.len() as u64,
+            expected: self.records,
+        };
+        if (records.len() as u64) < self.records {
+            return Err(truncated);
+        }
+        let last_hmac = match self.records.checked_sub(1) {
+            Some(last) => records[last as usize].hmac,
+            None => Sha256Sum::zero(),
+        };
+        if last_hmac != self.last_hmac || records.len() as u64 > self.records + 1 {
+            return Err(OpAuditLogError::BadHead);
+        }
+        Ok(())
+    }
+}
+
+/// Append-only JSONL log of every burnchain operation this node signs.
+/// Each record's HMAC covers the previous record's HMAC, so any edit, deletion or
+/// reordering of records is detected when the log is read back with `export`.  The number of
+/// records written is kept in a head file next to the log (`<path>.head`), so that records
+/// cut off from the end of the log are detected too.
+pub struct OpAuditLog {
+    path: PathBuf,
+    key: [u8; 32],
+    next_seq: u64,
+    last_hmac: Sha256Sum,
+}
+
+impl OpAuditLog {
+    /// Derive the log's HMAC key from the node seed
+    pub fn key_from_seed(seed: &[u8]) -> [u8; 32] {
+        Sha256Sum::from_data(&[OP_AUDIT_LOG_KEY_DOMAIN, seed].concat()).0
+    }
+
+    /// Open the log at `path`, creating it if it does not exist.
+    /// An existing log is verified first, and new records extend its chain.  A partial record
+    /// left at its end by an interrupted append is cut off.
+    pub fn open<P: AsRef<Path>>(path: P, key: [u8; 32]) -> Result<OpAuditLog, OpAuditLogError> {
+        let path = path.as_ref().to_path_buf();
+        let records = if path.exists() {
+            Self::truncate_partial_record(&path, &key)?;
+            Self::export(&path, &key)?
+        } else if let Some(head) = OpAuditLogHead::read(&path, &key)? {
+            // the whole log was removed
+            return Err(OpAuditLogError::Truncated {
+                records: 0,
+                expected: head.records,
+            });
+        } else {
+            vec![]
+        };
+        let (next_seq, last_hmac) = match records.last() {
+            Some(record) => (record.seq + 1, record.hmac),
+            None => (0, Sha256Sum::zero()),
+        };
+        Ok(OpAuditLog {
+            path,
+            key,
+            next_seq,
+            last_hmac,
+        })
+    }
+
+    /// Cut off the last line of the log at `path` if it is a partial record, which an append
+    /// interrupted by a crash leaves behind.  This is only done if the head file accounts for
+    /// every complete record before it, since the partial record is then the one whose append
+    /// never finished.  Otherwise the log is left as it is, for `export` to report.
+    fn truncate_partial_record(path: &Path, key: &[u8; 32]) -> Result<(), OpAuditLogError> {
+        let contents = fs::read(path)?;
+        if contents.last().map_or(true, |byte| *byte == b'\n') {
+            return Ok(());
+        }
+        let complete_len = contents
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |i| i + 1);
+        let complete_records = contents[..complete_len]
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+            .count() as u64;
+        let expected = OpAuditLogHead::read(path, key)?.map_or(0, |head| head.records);
+        if complete_records != expected {
+            return Ok(());
+        }
+        warn!(
+            "Op audit log {}: cutting off a partial record of {} bytes left by an interrupted append",
+            path.display(),
+            contents.len() - complete_len
+        );
+        let file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(complete_len as u64)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Append a record for a signed operation
+    pub fn record(
+        &mut self,
+        op_type: &str,
+        txid: &Txid,
+        payload: &[u8],
+        target_burn_height: u64,
+    ) -> Result<OpAuditRecord, OpAuditLogError> {
+        let mut record = OpAuditRecord {
+            seq: self.next_seq,
+            timestamp: get_epoch_time_secs(),
+            op_type: op_type.to_string(),
+            txid: *txid,
+            payload_digest: Sha256Sum::from_data(payload),
+            target_burn_height,
+            prev_hmac: self.last_hmac,
+            hmac: Sha256Sum::zero(),
+        };
+        record.hmac = record.compute_hmac(&self.key);
+
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let len = file.metadata()?.len();
+        if let Err(e) = file.write_all(&line).and_then(|_| file.sync_data()) {
+            // don't leave a partial record for the next one to be appended after
+            if let Err(truncate_err) = file.set_len(len) {
+                warn!(
+                    "Op audit log {}: failed to cut off a partial record: {}",
+                    self.path.display(),
+                    truncate_err
+                );
+            }
+            return Err(e.into());
+        }
+
+        // the record is in the log, so the next one extends it even if the head isn't updated
+        self.next_seq += 1;
+        self.last_hmac = record.hmac;
+        OpAuditLogHead::new(record.seq + 1, record.hmac, &self.key).write(&self.path)?;
+        Ok(record)
+    }
+
+    /// Read back every record in the log at `path`, verifying the HMAC chain and that no
+    /// records are missing from its end
+    pub fn export<P: AsRef<Path>>(
+        path: P,
+        key: &[u8; 32],
+    ) -> Result<Vec<OpAuditRecord>, OpAuditLogError> {
+        let path = path.as_ref();
+        let reader = BufReader::new(File::open(path)?);
+        let mut records = vec![];
+        let mut prev_hmac = Sha256Sum::zero();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: OpAuditRecord = serde_json::from_str(&line)?;
+            if record.seq != records.len() as u64
+                || record.prev_hmac != prev_hmac
+                || record.compute_hmac(key) != record.hmac
+            {
+                return Err(OpAuditLogError::BrokenChain(i as u64 + 1));
+            }
+            prev_hmac = record.hmac;
+            records.push(record);
+        }
+        match OpAuditLogHead::read(path, key)? {
+            Some(head) => head.check(&records)?,
+            None if records.is_empty() => {}
+            None => return Err(OpAuditLogError::BadHead),
+        }
+        Ok(records)
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use stacks::burnchains::Txid;
     use stacks_common::util::secp256k1::Secp256k1PrivateKey;
 
-    use super::BurnchainOpSigner;
+    use super::{BurnchainOpSigner, OpAuditLog, OpAuditLogError, OpAuditLogHead};
 
     #[test]
     fn test_wif() {
@@ -80,4 +427,202 @@ mod test {
             assert_eq!(expected_wif, &op_signer.get_sk_as_wif());
         }
     }
+
+    #[test]
+    fn test_op_audit_log() {
+        let path =
+            std::env::temp_dir().join(format!("test_op_audit_log-{}.jsonl", rand::random::<u64>()));
+        let key = OpAuditLog::key_from_seed(&[0x01; 32]);
+
+        let mut log = OpAuditLog::open(&path, key).unwrap();
+        let first = log
+            .record("LeaderKeyRegister", &Txid([0x11; 32]), &[1, 2, 3], 100)
+            .unwrap();
+        let second = log
+            .record("LeaderBlockCommit", &Txid([0x22; 32]), &[4, 5, 6], 101)
+            .unwrap();
+        assert_eq!(second.prev_hmac, first.hmac);
+
+        // reopening the log continues the chain
+        let mut log = OpAuditLog::open(&path, key).unwrap();
+        let third = log
+            .record("LeaderBlockCommit", &Txid([0x33; 32]), &[7, 8, 9], 102)
+            .unwrap();
+        assert_eq!(third.seq, 2);
+        assert_eq!(third.prev_hmac, second.hmac);
+
+        let records = OpAuditLog::export(&path, &key).unwrap();
+        assert_eq!(records, vec![first, second, third]);
+
+        // the wrong key can't verify the log
+        let other_key = OpAuditLog::key_from_seed(&[0x02; 32]);
+        assert!(matches!(
+            OpAuditLog::export(&path, &other_key),
+            Err(OpAuditLogError::BrokenChain(1))
+        ));
+
+        // dropping a record breaks the chain
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = contents.lines().collect();
+        std::fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        assert!(matches!(
+            OpAuditLog::export(&path, &key),
+            Err(OpAuditLogError::BrokenChain(2))
+        ));
+
+        // tampering with a record breaks the chain
+        let tampered = lines[1].replace("\"target_burn_height\":101", "\"target_burn_height\":200");
+        assert_ne!(tampered, lines[1]);
+        std::fs::write(&path, format!("{}\n{}\n", lines[0], tampered)).unwrap();
+        assert!(matches!(
+            OpAuditLog::export(&path, &key),
+            Err(OpAuditLogError::BrokenChain(2))
+        ));
+        assert!(OpAuditLog::open(&path, key).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(OpAuditLogHead::path(&path)).unwrap();
+    }
+
+    #[test]
+    fn test_op_audit_log_truncation() {
+        let path = std::env::temp_dir().join(format!(
+            "test_op_audit_log_truncation-{}.jsonl",
+            rand::random::<u64>()
+        ));
+        let head_path = OpAuditLogHead::path(&path);
+        let key = OpAuditLog::key_from_seed(&[0x01; 32]);
+
+        let mut log = OpAuditLog::open(&path, key).unwrap();
+        for i in 0..3 {
+            log.record("LeaderBlockCommit", &Txid([i; 32]), &[i], 100 + i as u64)
+                .unwrap();
+        }
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = contents.lines().collect();
+        let head = std::fs::read(&head_path).unwrap();
+
+        // cutting off the last record is detected, although the chain is intact
+        std::fs::write(&path, format!("{}\n{}\n", lines[0], lines[1])).unwrap();
+        assert!(matches!(
+            OpAuditLog::export(&path, &key),
+            Err(OpAuditLogError::Truncated {
+                records: 2,
+                expected: 3
+            })
+        ));
+        assert!(OpAuditLog::open(&path, key).is_err());
+
+        // so is removing the whole log
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            OpAuditLog::open(&path, key),
+            Err(OpAuditLogError::Truncated {
+                records: 0,
+                expected: 3
+            })
+        ));
+
+        // a head file left one record behind by a crash is accepted
+        std::fs::write(&path, &contents).unwrap();
+        let records = OpAuditLog::export(&path, &key).unwrap();
+        OpAuditLogHead::new(2, records[1].hmac, &key)
+            .write(&path)
+            .unwrap();
+        assert_eq!(OpAuditLog::export(&path, &key).unwrap(), records);
+
+        // but a head file that is missing, or was written with another key, is not
+        std::fs::remove_file(&head_path).unwrap();
+        assert!(matches!(
+            OpAuditLog::export(&path, &key),
+            Err(OpAuditLogError::BadHead)
+        ));
+        let other_key = OpAuditLog::key_from_seed(&[0x02; 32]);
+        OpAuditLogHead::new(3, records[2].hmac, &other_key)
+            .write(&path)
+            .unwrap();
+        assert!(matches!(
+            OpAuditLog::export(&path, &key),
+            Err(OpAuditLogError::BadHead)
+        ));
+
+        std::fs::write(&head_path, head).unwrap();
+        assert_eq!(OpAuditLog::export(&path, &key).unwrap(), records);
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&head_path).unwrap();
+    }
+
+    #[test]
+    fn test_op_audit_log_partial_record() {
+        let path = std::env::temp_dir().join(format!(
+            "test_op_audit_log_partial_record-{}.jsonl",
+            rand::random::<u64>()
+        ));
+        let head_path = OpAuditLogHead::path(&path);
+        let key = OpAuditLog::key_from_seed(&[0x01; 32]);
+
+        // an append interrupted before the first record was complete
+        std::fs::write(&path, "{\"seq\":0,\"timest").unwrap();
+        let mut log = OpAuditLog::open(&path, key).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"");
+
+        let mut records = vec![];
+        for i in 0..2 {
+            records.push(
+                log.record("LeaderBlockCommit", &Txid([i; 32]), &[i], 100 + i as u64)
+                    .unwrap(),
+            );
+        }
+        let contents = std::fs::read_to_string(&path).unwrap();
+
+        // an append interrupted halfway through a line, after two complete records
+        let third = OpAuditLog::open(&path, key)
+            .unwrap()
+            .record("LeaderBlockCommit", &Txid([2; 32]), &[2], 102)
+            .unwrap();
+        let third_line = std::fs::read_to_string(&path).unwrap()[contents.len()..].to_string();
+        std::fs::write(
+            &path,
+            format!("{}{}", contents, &third_line[..third_line.len() / 2]),
+        )
+        .unwrap();
+        OpAuditLogHead::new(2, records[1].hmac, &key)
+            .write(&path)
+            .unwrap();
+        assert!(matches!(
+            OpAuditLog::export(&path, &key),
+            Err(OpAuditLogError::Json(_))
+        ));
+
+        // reopening the log cuts off the partial record, and the chain carries on
+        let mut log = OpAuditLog::open(&path, key).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+        assert_eq!(OpAuditLog::export(&path, &key).unwrap(), records);
+        let next = log
+            .record("LeaderBlockCommit", &Txid([3; 32]), &[3], 103)
+            .unwrap();
+        assert_eq!(next.seq, 2);
+        assert_eq!(next.prev_hmac, records[1].hmac);
+        assert_ne!(next, third);
+        records.push(next);
+        assert_eq!(OpAuditLog::export(&path, &key).unwrap(), records);
+
+        // a partial record after records the head file doesn't account for is left alone
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, format!("{}{{\"seq\":3", contents)).unwrap();
+        OpAuditLogHead::new(1, records[0].hmac, &key)
+            .write(&path)
+            .unwrap();
+        assert!(matches!(
+            OpAuditLog::open(&path, key),
+            Err(OpAuditLogError::Json(_))
+        ));
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .ends_with("{\"seq\":3"));
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&head_path).unwrap();
+    }
 }
@@ -37,6 +37,7 @@ use stacks_common::util::hash::Hash160;
 use stx_genesis::GenesisData;
 
 use crate::burnchains::make_bitcoin_indexer;
+use crate::burnchains::sync_span::SyncStage;
 use crate::globals::Globals as GenericGlobals;
 use crate::monitoring::{start_serving_monitoring_metrics, MonitoringError};
 use crate::nakamoto_node::{self, StacksNode, BLOCK_PROCESSOR_STACK_SIZE, RELAYER_MAX_BUFFER};
@@ -46,6 +47,7 @@ use crate::node::{
 };
 use crate::run_loop::neon;
 use crate::run_loop::neon::Counters;
+use crate::shutdown::{ShutdownManager, COORDINATOR_STAGE_TIMEOUT, PEER_NETWORK_STAGE_TIMEOUT};
 use crate::syncctl::{PoxSyncWatchdog, PoxSyncWatchdogComms};
 use crate::{
     run_loop, BitcoinRegtestController, BurnchainController, Config, EventDispatcher, Keychain,
@@ -528,11 +530,17 @@ impl RunLoop {
                 info!("Terminating relayer");
                 info!("Terminating chains-coordinator");
 
+                let mut shutdown = ShutdownManager::new();
                 globals.coord().stop_chains_coordinator();
-                coordinator_thread_handle.join().unwrap();
-                node.join();
-
-                info!("Exiting stacks-node");
+                shutdown.run_stage("chains-coordinator", COORDINATOR_STAGE_TIMEOUT, move || {
+                    coordinator_thread_handle.join().unwrap()
+                });
+                shutdown.run_stage("peer-network", PEER_NETWORK_STAGE_TIMEOUT, move || {
+                    node.join()
+                });
+                shutdown.run_final_stages(&self.event_dispatcher, &self.config);
+
+                info!("Exiting stacks-node"; "clean_shutdown" => shutdown.is_clean());
                 break;
             }
 
@@ -578,6 +586,15 @@ impl RunLoop {
                     break;
                 }
 
+                // if an operator asked us to pause burnchain processing, park here, between
+                // passes, until they resume us
+                if globals
+                    .burnchain_sync_control
+                    .wait_while_paused(|| globals.keep_running())
+                {
+                    continue;
+                }
+
                 let (next_burnchain_tip, tip_burnchain_height) =
                     match burnchain.sync(Some(target_burnchain_block_height)) {
                         Ok(x) => x,
@@ -590,6 +607,12 @@ impl RunLoop {
                 // *now* we know the burnchain height
                 burnchain_tip = next_burnchain_tip;
                 burnchain_height = tip_burnchain_height;
+                if let Some(sync_span) = burnchain.sync_span_mut() {
+                    sync_span.enter_stage(
+                        SyncStage::TipUpdated,
+                        burnchain_tip.block_snapshot.block_height,
+                    );
+                }
 
                 let sortition_tip = &burnchain_tip.block_snapshot.sortition_id;
                 let next_sortition_height = burnchain_tip.block_snapshot.block_height;
@@ -31,6 +31,8 @@ use stacks_common::util::{get_epoch_time_secs, sleep_ms};
 use stx_genesis::GenesisData;
 
 use super::RunLoopCallbacks;
+use crate::burnchains::readiness::ReadinessLevel;
+use crate::burnchains::sync_span::SyncStage;
 use crate::burnchains::{make_bitcoin_indexer, Error};
 use crate::globals::NeonGlobals as Globals;
 use crate::monitoring::{start_serving_monitoring_metrics, MonitoringError};
@@ -39,6 +41,10 @@ use crate::node::{
     get_account_balances, get_account_lockups, get_names, get_namespaces,
     use_test_genesis_chainstate,
 };
+use crate::shutdown::{
+    ShutdownManager, CHAIN_LIVENESS_STAGE_TIMEOUT, COORDINATOR_STAGE_TIMEOUT,
+    PEER_NETWORK_STAGE_TIMEOUT,
+};
 use crate::syncctl::{PoxSyncWatchdog, PoxSyncWatchdogComms};
 use crate::{
     run_loop, BitcoinRegtestController, BurnchainController, Config, EventDispatcher, Keychain,
@@ -430,7 +436,10 @@ impl RunLoop {
                 panic!("FATAL: unable to query filesystem or databases: {:?}", &e);
             }
         }
+        burnchain_controller.mark_ready(ReadinessLevel::DbsMigrated);
 
+        // Syncing the headers (and, if the sortitions catch up with them, processing the
+        // sortitions) reports the next readiness levels from within the burnchain controller.
         info!("Start syncing Bitcoin headers, feel free to grab a cup of coffee, this can take a while");
 
         let burnchain_config = burnchain_controller.get_burnchain();
@@ -462,7 +471,7 @@ impl RunLoop {
                             return burnchain_error::ShutdownInitiated;
                         }
                     }
-                    Error::IndexerError(_) => {}
+                    Error::IndexerError(_) | Error::SortitionTimeout(_) => {}
                 }
                 error!("Burnchain controller stopped: {}", e);
                 panic!();
@@ -968,6 +977,32 @@ impl RunLoop {
     }
 
     /// Spawn a thread to drive chain liveness
+    /// Is the burnchain tip too old to start a tenure on?  Only if `miner.max_burn_tip_age_secs`
+    /// is set, and the tip's header time is further in the past than that.
+    fn burn_tip_too_old_to_mine(&self, burnchain: &BitcoinRegtestController) -> bool {
+        let max_age_secs = match self.config.miner.max_burn_tip_age_secs {
+            Some(max_age_secs) => max_age_secs,
+            None => return false,
+        };
+        let header_time = match burnchain.get_header_time() {
+            Ok(header_time) => header_time,
+            Err(e) => {
+                warn!("Runloop: failed to get the burnchain tip's header time"; "err" => %e);
+                return false;
+            }
+        };
+        let age_secs = header_time.age_secs(get_epoch_time_secs());
+        if age_secs <= max_age_secs {
+            return false;
+        }
+        debug!("Runloop: burnchain tip is too old to mine on";
+               "burn_block_height" => header_time.block_height,
+               "burn_header_hash" => %header_time.burn_header_hash,
+               "age_secs" => age_secs,
+               "max_burn_tip_age_secs" => max_age_secs);
+        true
+    }
+
     fn spawn_chain_liveness_thread(&self, globals: Globals) -> JoinHandle<()> {
         let config = self.config.clone();
         let burnchain = self.get_burnchain();
@@ -1145,12 +1180,22 @@ impl RunLoop {
                 info!("Terminating relayer");
                 info!("Terminating chains-coordinator");
 
+                let mut shutdown = ShutdownManager::new();
                 globals.coord().stop_chains_coordinator();
-                coordinator_thread_handle.join().unwrap();
-                let peer_network = node.join();
-                liveness_thread.join().unwrap();
-
-                info!("Exiting stacks-node");
+                shutdown.run_stage("chains-coordinator", COORDINATOR_STAGE_TIMEOUT, move || {
+                    coordinator_thread_handle.join().unwrap()
+                });
+                let peer_network = shutdown
+                    .run_stage("peer-network", PEER_NETWORK_STAGE_TIMEOUT, move || {
+                        node.join()
+                    })
+                    .flatten();
+                shutdown.run_stage("chain-liveness", CHAIN_LIVENESS_STAGE_TIMEOUT, move || {
+                    liveness_thread.join().unwrap()
+                });
+                shutdown.run_final_stages(&self.event_dispatcher, &self.config);
+
+                info!("Exiting stacks-node"; "clean_shutdown" => shutdown.is_clean());
                 break peer_network;
             }
 
@@ -1204,6 +1249,15 @@ impl RunLoop {
                     break;
                 }
 
+                // if an operator asked us to pause burnchain processing, park here, between
+                // passes, until they resume us
+                if globals
+                    .burnchain_sync_control
+                    .wait_while_paused(|| globals.keep_running())
+                {
+                    continue;
+                }
+
                 let (next_burnchain_tip, tip_burnchain_height) =
                     match burnchain.sync(Some(target_burnchain_block_height)) {
                         Ok(x) => x,
@@ -1216,6 +1270,12 @@ impl RunLoop {
                 // *now* we know the burnchain height
                 burnchain_tip = next_burnchain_tip;
                 burnchain_height = tip_burnchain_height;
+                if let Some(sync_span) = burnchain.sync_span_mut() {
+                    sync_span.enter_stage(
+                        SyncStage::TipUpdated,
+                        burnchain_tip.block_snapshot.block_height,
+                    );
+                }
 
                 let sortition_tip = &burnchain_tip.block_snapshot.sortition_id;
                 let next_sortition_height = burnchain_tip.block_snapshot.block_height;
@@ -1345,7 +1405,9 @@ impl RunLoop {
                         last_tenure_sortition_height = sortition_db_height;
                     }
 
-                    if !node.relayer_issue_tenure(ibd) {
+                    if self.burn_tip_too_old_to_mine(&burnchain) {
+                        // wait for a fresh burnchain tip
+                    } else if !node.relayer_issue_tenure(ibd) {
                         // relayer hung up, exit.
                         error!("Runloop: Block relayer and miner hung up, exiting.");
                         break None;
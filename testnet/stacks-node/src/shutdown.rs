@@ -0,0 +1,328 @@
+//! Orderly shutdown of the node's subsystems.
+//!
+//! When the node is told to stop, the run loop tears its subsystems down in dependency order,
+//! one stage at a time:
+//!
+//! 1. the chains coordinator, which finishes processing the block it is on;
+//! 2. the p2p and relayer threads.  The p2p thread hands the Atlas downloader's unfinished
+//!    batches back to the AtlasDB as it exits;
+//! 3. any other thread holding DB connections (e.g. the chain liveness thread);
+//! 4. the event observers, whose deliveries that are under way are retried as they are while
+//!    the node runs, for up to `node.event_observers_shutdown_timeout_secs`.  If that expires,
+//!    failed deliveries are no longer retried, and each event left undelivered is logged;
+//! 5. the databases, whose write-ahead logs are checkpointed once nothing writes to them.
+//!
+//! Each stage runs with a timeout, so that a subsystem that hangs can't keep the node from
+//! exiting.  A stage that times out is left running in the background, and the databases are
+//! then not checkpointed, since it may still write to them.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::core::mempool::MemPoolDB;
+use stacks_common::util::sleep_ms;
+
+use crate::event_dispatcher::EventDispatcher;
+use crate::Config;
+
+/// How long to wait for the chains coordinator to finish the block it is processing
+pub const COORDINATOR_STAGE_TIMEOUT: Duration = Duration::from_secs(300);
+/// How long to wait for the p2p and relayer threads to exit
+pub const PEER_NETWORK_STAGE_TIMEOUT: Duration = Duration::from_secs(120);
+/// How long to wait for the chain liveness thread to exit
+pub const CHAIN_LIVENESS_STAGE_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long to wait for the databases to be checkpointed
+pub const DATABASES_STAGE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How a shutdown stage ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageStatus {
+    /// The stage finished within its timeout
+    Completed,
+    /// The stage was still running when its timeout expired
+    TimedOut,
+    /// The stage panicked
+    Failed,
+}
+
+/// The outcome of one shutdown stage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageOutcome {
+    pub name: &'static str,
+    pub status: StageStatus,
+    /// How long the run loop waited for the stage
+    pub elapsed: Duration,
+}
+
+/// Runs the stages of the node's shutdown one after the other, each with its own timeout
+#[derive(Debug, Default)]
+pub struct ShutdownManager {
+    outcomes: Vec<StageOutcome>,
+}
+
+impl ShutdownManager {
+    pub fn new() -> ShutdownManager {
+        ShutdownManager { outcomes: vec![] }
+    }
+
+    /// Run the shutdown stage `name`, waiting up to `timeout` for `stage` to finish.
+    /// The stage runs on its own thread, which is left running if it times out.
+    /// Returns what `stage` returned, or None if it timed out or panicked.
+    pub fn run_stage<T, F>(&mut self, name: &'static str, timeout: Duration, stage: F) -> Option<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        info!("Shutdown: begin stage"; "stage" => name);
+        let start = Instant::now();
+        let (result_send, result_recv) = channel();
+        let spawned = thread::Builder::new()
+            .name(format!("shutdown-{}", name))
+            .spawn(move || {
+                // the run loop may have stopped waiting
+                let _ = result_send.send(stage());
+            });
+        let (status, result) = match spawned {
+            Ok(_) => match result_recv.recv_timeout(timeout) {
+                Ok(result) => (StageStatus::Completed, Some(result)),
+                Err(RecvTimeoutError::Timeout) => (StageStatus::TimedOut, None),
+                Err(RecvTimeoutError::Disconnected) => (StageStatus::Failed, None),
+            },
+            Err(e) => {
+                error!("Shutdown: failed to spawn stage thread"; "stage" => name, "err" => ?e);
+                (StageStatus::Failed, None)
+            }
+        };
+
+        let elapsed = start.elapsed();
+        match status {
+            StageStatus::Completed => {
+                info!("Shutdown: finished stage"; "stage" => name, "elapsed_ms" => elapsed.as_millis());
+            }
+            StageStatus::TimedOut => {
+                warn!("Shutdown: stage timed out, moving on"; "stage" => name, "elapsed_ms" => elapsed.as_millis());
+            }
+            StageStatus::Failed => {
+                error!("Shutdown: stage failed"; "stage" => name, "elapsed_ms" => elapsed.as_millis());
+            }
+        }
+        self.outcomes.push(StageOutcome {
+            name,
+            status,
+            elapsed,
+        });
+        result
+    }
+
+    /// The outcomes of the stages run so far, in the order they ran
+    pub fn outcomes(&self) -> &[StageOutcome] {
+        &self.outcomes
+    }
+
+    /// Run the stages that follow the threads' exit: wait for the event deliveries under way,
+    /// then checkpoint the databases.  The databases are left alone if an earlier stage did not
+    /// complete, since its thread may still be writing to them.
+    pub fn run_final_stages(&mut self, event_dispatcher: &EventDispatcher, config: &Config) {
+        let timeout = Duration::from_secs(config.node.event_observers_shutdown_timeout_secs);
+        let waiting_dispatcher = event_dispatcher.clone();
+        let delivered = self.run_stage("event-observers", timeout, move || {
+            wait_for_event_observers(&waiting_dispatcher)
+        });
+        if delivered.is_none() {
+            give_up_on_event_observers(event_dispatcher);
+        }
+        if !self.is_clean() {
+            warn!("Shutdown: not checkpointing the databases, since a stage did not complete");
+            return;
+        }
+        let config = config.clone();
+        if let Some(num_checkpointed) =
+            self.run_stage("databases", DATABASES_STAGE_TIMEOUT, move || {
+                checkpoint_databases(&config)
+            })
+        {
+            debug!("Shutdown: checkpointed {} databases", num_checkpointed);
+        }
+    }
+
+    /// Did every stage run so far complete?
+    pub fn is_clean(&self) -> bool {
+        self.outcomes
+            .iter()
+            .all(|outcome| outcome.status == StageStatus::Completed)
+    }
+}
+
+/// Wait for the deliveries that `event_dispatcher` has under way to finish
+pub fn wait_for_event_observers(event_dispatcher: &EventDispatcher) {
+    while event_dispatcher.deliveries_in_flight() > 0 {
+        sleep_ms(100);
+    }
+}
+
+/// Stop retrying the deliveries that `event_dispatcher` has under way, and log each event that
+/// is left undelivered
+pub fn give_up_on_event_observers(event_dispatcher: &EventDispatcher) {
+    event_dispatcher.stop_retrying();
+    for pending in event_dispatcher.pending_deliveries() {
+        warn!(
+            "Shutdown: giving up on delivering event";
+            "endpoint" => &pending.endpoint,
+            "path" => &pending.path,
+            "event_id" => pending.event_id.as_deref().unwrap_or("unknown"),
+        );
+    }
+}
+
+/// The paths of the node's sqlite databases
+fn sqlite_db_paths(config: &Config) -> Vec<PathBuf> {
+    let chainstate_path = config.get_chainstate_path();
+    let mut paths = vec![
+        StacksChainState::header_index_root_path(chainstate_path.clone()),
+        StacksChainState::vm_state_index_marf_path(chainstate_path.clone()),
+        StacksChainState::blocks_path(chainstate_path).join("nakamoto.sqlite"),
+        PathBuf::from(config.get_burn_db_file_path()).join("marf.sqlite"),
+        PathBuf::from(config.get_burn_db_path()).join("burnchain.sqlite"),
+        PathBuf::from(config.get_atlas_db_file_path()),
+        PathBuf::from(config.get_peer_db_file_path()),
+        PathBuf::from(config.get_stacker_db_file_path()),
+    ];
+    if let Ok(mempool_path) = MemPoolDB::db_path(&config.get_chainstate_path_str()) {
+        paths.push(PathBuf::from(mempool_path));
+    }
+    paths
+}
+
+/// Checkpoint the write-ahead log of the sqlite DB at `path` into the DB, and truncate it.
+/// Returns Ok(false) if a connection that is still open kept the checkpoint from completing.
+fn checkpoint_sqlite_db(path: &Path) -> Result<bool, rusqlite::Error> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+    conn.busy_timeout(Duration::from_secs(5))?;
+    let busy: i64 = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", NO_PARAMS, |row| {
+        row.get(0)
+    })?;
+    Ok(busy == 0)
+}
+
+/// Checkpoint the write-ahead logs of all of the node's sqlite databases, so that the next
+/// start does not have to replay them.  Must only be called once the threads that write to them
+/// have exited.
+/// Returns the number of databases checkpointed.
+pub fn checkpoint_databases(config: &Config) -> usize {
+    let mut num_checkpointed = 0;
+    for path in sqlite_db_paths(config) {
+        if !path.exists() {
+            continue;
+        }
+        match checkpoint_sqlite_db(&path) {
+            Ok(true) => {
+                debug!("Shutdown: checkpointed {}", path.display());
+                num_checkpointed += 1;
+            }
+            Ok(false) => {
+                warn!(
+                    "Shutdown: could not fully checkpoint {}, it is still in use",
+                    path.display()
+                );
+            }
+            Err(e) => {
+                warn!("Shutdown: failed to checkpoint {}", path.display(); "err" => ?e);
+            }
+        }
+    }
+    num_checkpointed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::sync_channel;
+
+    use super::*;
+
+    #[test]
+    fn stages_run_in_order() {
+        let mut manager = ShutdownManager::new();
+        let (order_send, order_recv) = channel();
+        for name in ["first", "second", "third"] {
+            let order_send = order_send.clone();
+            let result = manager.run_stage(name, Duration::from_secs(10), move || {
+                order_send.send(name).unwrap();
+                name.len()
+            });
+            assert_eq!(result, Some(name.len()));
+        }
+        drop(order_send);
+
+        let order: Vec<_> = order_recv.iter().collect();
+        assert_eq!(order, vec!["first", "second", "third"]);
+        let names: Vec<_> = manager
+            .outcomes()
+            .iter()
+            .map(|outcome| outcome.name)
+            .collect();
+        assert_eq!(names, order);
+        assert!(manager.is_clean());
+    }
+
+    #[test]
+    fn hung_stage_times_out() {
+        let mut manager = ShutdownManager::new();
+        let (release_send, release_recv) = sync_channel::<()>(0);
+
+        let result = manager.run_stage("hung", Duration::from_millis(100), move || {
+            release_recv.recv().ok();
+        });
+        assert_eq!(result, None);
+        assert_eq!(manager.outcomes()[0].status, StageStatus::TimedOut);
+        assert!(manager.outcomes()[0].elapsed >= Duration::from_millis(100));
+
+        // later stages still run
+        assert_eq!(
+            manager.run_stage("next", Duration::from_secs(10), || 1),
+            Some(1)
+        );
+        assert_eq!(manager.outcomes()[1].status, StageStatus::Completed);
+        assert!(!manager.is_clean());
+
+        drop(release_send);
+    }
+
+    #[test]
+    fn panicking_stage_fails() {
+        let mut manager = ShutdownManager::new();
+        let result: Option<()> = manager.run_stage("panics", Duration::from_secs(10), || {
+            panic!("stage panicked")
+        });
+        assert_eq!(result, None);
+        assert_eq!(manager.outcomes()[0].status, StageStatus::Failed);
+        assert!(!manager.is_clean());
+    }
+
+    #[test]
+    fn checkpoint_sqlite_db_truncates_wal() {
+        let path = std::env::temp_dir().join(format!(
+            "stacks-node-shutdown-checkpoint-{}.sqlite",
+            std::process::id()
+        ));
+        let wal_path = PathBuf::from(format!("{}-wal", path.display()));
+        let _ = std::fs::remove_file(&path);
+
+        let conn = Connection::open(&path).unwrap();
+        conn.query_row("PRAGMA journal_mode = WAL", NO_PARAMS, |_row| Ok(()))
+            .unwrap();
+        conn.execute("CREATE TABLE t (x INTEGER)", NO_PARAMS)
+            .unwrap();
+        conn.execute("INSERT INTO t VALUES (1)", NO_PARAMS).unwrap();
+        assert!(std::fs::metadata(&wal_path).unwrap().len() > 0);
+
+        assert!(checkpoint_sqlite_db(&path).unwrap());
+        assert_eq!(std::fs::metadata(&wal_path).unwrap().len(), 0);
+
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,6 +1,3 @@
-use std::thread;
-use std::time::{Duration, Instant};
-
 #[cfg(test)]
 use stacks::burnchains::PoxConstants;
 #[cfg(test)]
@@ -17,6 +14,7 @@ use stacks_common::types::chainstate::VRFSeed;
 use stacks_common::util::hash::Hash160;
 use stacks_common::util::vrf::VRFProof;
 
+use super::burnchains::clock::Clock;
 /// Only used by the Helium (Mocknet) node
 use super::node::ChainTip;
 use super::{BurnchainTip, Config};
@@ -72,14 +70,20 @@ impl<'a> Tenure {
         }
     }
 
-    pub fn run(&mut self, burn_dbconn: &SortitionDBConn) -> Option<TenureArtifacts> {
+    /// Build this tenure's anchored block, once `commit_anchor_block_within` has elapsed on
+    /// `clock` since the burnchain tip was received
+    pub fn run(
+        &mut self,
+        burn_dbconn: &SortitionDBConn,
+        clock: &dyn Clock,
+    ) -> Option<TenureArtifacts> {
         info!("Node starting new tenure with VRF {:?}", self.vrf_seed);
 
         let duration_left: u128 = self.config.burnchain.commit_anchor_block_within as u128;
-        let mut elapsed = Instant::now().duration_since(self.burnchain_tip.received_at);
+        let mut elapsed = clock.elapsed_since(self.burnchain_tip.received_at);
         while duration_left.saturating_sub(elapsed.as_millis()) > 0 {
-            thread::sleep(Duration::from_millis(1000));
-            elapsed = Instant::now().duration_since(self.burnchain_tip.received_at);
+            clock.sleep_ms(1000);
+            elapsed = clock.elapsed_since(self.burnchain_tip.received_at);
         }
 
         let (mut chain_state, _) = StacksChainState::open(
@@ -112,6 +112,7 @@ fn test_exact_block_costs() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent, EventKeyType::MinedBlocks],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -338,6 +339,7 @@ fn test_dynamic_db_method_costs() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -774,6 +776,7 @@ fn test_cost_limit_switch_version205() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -1031,6 +1034,7 @@ fn bigger_microblock_streams_in_2_05() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
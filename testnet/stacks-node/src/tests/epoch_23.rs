@@ -104,6 +104,7 @@ fn trait_invocation_behavior() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
     conf.initial_balances.append(&mut initial_balances);
 
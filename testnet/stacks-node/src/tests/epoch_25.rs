@@ -86,6 +86,7 @@ fn microblocks_disabled() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
     conf.initial_balances.append(&mut initial_balances);
 
@@ -1005,6 +1005,7 @@ fn simple_neon_integration() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -1246,6 +1247,7 @@ fn mine_multiple_per_tenure_integration() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -1441,6 +1443,7 @@ fn correct_burn_outs() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -1783,6 +1786,7 @@ fn block_proposal_api_endpoint() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::BlockProposal],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -2152,6 +2156,7 @@ fn miner_writes_proposed_block_to_stackerdb() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent, EventKeyType::MinedBlocks],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -2277,6 +2282,7 @@ fn vote_for_aggregate_key_burn_op() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -2537,6 +2543,7 @@ fn follower_bootup() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -2781,6 +2788,7 @@ fn stack_stx_burn_op_integration_test() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -3225,6 +3233,7 @@ fn forked_tenure_is_ignored() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent, EventKeyType::MinedBlocks],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
@@ -3506,6 +3515,7 @@ fn check_block_heights() {
     naka_conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{observer_port}"),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(naka_conf.clone());
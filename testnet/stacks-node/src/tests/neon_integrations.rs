@@ -943,6 +943,7 @@ fn bitcoind_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -1056,6 +1057,7 @@ fn confirm_unparsed_ongoing_ops() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -1378,6 +1380,7 @@ fn deep_contract() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let spender_bal = 10_000_000_000 * (core::MICROSTACKS_PER_STACKS as u64);
@@ -1482,6 +1485,7 @@ fn bad_microblock_pubkey() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -1567,6 +1571,7 @@ fn liquid_ustx_integration() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let spender_bal = 10_000_000_000 * (core::MICROSTACKS_PER_STACKS as u64);
@@ -1695,6 +1700,7 @@ fn lockup_integration() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -1811,6 +1817,7 @@ fn stx_transfer_btc_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     conf.initial_balances.push(InitialBalance {
@@ -2080,6 +2087,7 @@ fn stx_delegate_btc_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -2368,6 +2376,7 @@ fn stack_stx_burn_op_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -2773,6 +2782,7 @@ fn vote_for_aggregate_key_burn_op_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -3375,6 +3385,7 @@ fn microblock_fork_poison_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -3606,6 +3617,7 @@ fn microblock_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -4581,6 +4593,7 @@ fn size_overflow_unconfirmed_microblocks_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -4777,6 +4790,7 @@ fn size_overflow_unconfirmed_stream_microblocks_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -4971,6 +4985,7 @@ fn size_overflow_unconfirmed_invalid_stream_microblocks_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -5237,6 +5252,7 @@ fn runtime_overflow_unconfirmed_microblocks_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -5409,6 +5425,7 @@ fn block_replay_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -5541,6 +5558,7 @@ fn cost_voting_integration() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let spender_bal = 10_000_000_000 * (core::MICROSTACKS_PER_STACKS as u64);
@@ -5863,6 +5881,7 @@ fn mining_events_integration_test() {
             EventKeyType::MinedBlocks,
             EventKeyType::MinedMicroblocks,
         ],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -6130,6 +6149,7 @@ fn block_limit_hit_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -6385,6 +6405,7 @@ fn microblock_limit_hit_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -6535,6 +6556,7 @@ fn block_large_tx_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     conf.initial_balances.push(InitialBalance {
@@ -6673,6 +6695,7 @@ fn microblock_large_tx_integration_test_FLAKY() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     conf.initial_balances.push(InitialBalance {
@@ -6811,6 +6834,7 @@ fn pox_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let first_bal = 6_000_000_000 * (core::MICROSTACKS_PER_STACKS as u64);
@@ -7356,6 +7380,7 @@ fn atlas_integration_test() {
         .insert(EventObserverConfig {
             endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
             events_keys: vec![EventKeyType::AnyEvent],
+            ..Default::default()
         });
 
     conf_follower_node.node.always_use_affirmation_maps = false;
@@ -7896,6 +7921,7 @@ fn antientropy_integration_test() {
         .insert(EventObserverConfig {
             endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
             events_keys: vec![EventKeyType::AnyEvent],
+            ..Default::default()
         });
 
     conf_follower_node.node.mine_microblocks = true;
@@ -8898,6 +8924,7 @@ fn fuzzed_median_fee_rate_estimation_test(window_size: u64, expected_final_value
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -9078,6 +9105,7 @@ fn use_latest_tip_integration_test() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -9472,6 +9500,7 @@ fn test_problematic_txs_are_not_stored() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -9624,6 +9653,7 @@ fn spawn_follower_node(
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     conf.initial_balances = initial_conf.initial_balances.clone();
@@ -9723,6 +9753,7 @@ fn test_problematic_blocks_are_not_mined() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -10081,6 +10112,7 @@ fn test_problematic_blocks_are_not_relayed_or_stored() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -10480,6 +10512,7 @@ fn test_problematic_microblocks_are_not_mined() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -10866,6 +10899,7 @@ fn test_problematic_microblocks_are_not_relayed_or_stored() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -11217,6 +11251,7 @@ fn push_boot_receipts() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     let burnchain_config = Burnchain::regtest(&conf.get_burn_db_path());
@@ -11265,6 +11300,7 @@ fn run_with_custom_wallet() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     // custom wallet
@@ -11865,6 +11901,7 @@ fn min_txs() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     conf.miner.min_tx_count = 4;
@@ -11971,6 +12008,7 @@ fn filter_txs_by_type() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     conf.miner.min_tx_count = 4;
@@ -12087,6 +12125,7 @@ fn filter_txs_by_origin() {
     conf.events_observers.insert(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..Default::default()
     });
 
     conf.miner.min_tx_count = 4;
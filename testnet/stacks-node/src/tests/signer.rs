@@ -789,6 +789,7 @@ fn setup_stx_btc_node(
                 EventKeyType::BlockProposal,
                 EventKeyType::BurnchainBlocks,
             ],
+            drop_rejected_events: true,
         });
     }
 
@@ -802,6 +803,7 @@ fn setup_stx_btc_node(
             EventKeyType::BlockProposal,
             EventKeyType::MinedBlocks,
         ],
+        ..Default::default()
     });
 
     // The signers need some initial balances in order to pay for epoch 2.5 transaction votes
@@ -942,10 +944,7 @@ fn stackerdb_dkg() {
     for signer in signer_test.spawned_signers.iter() {
         signer
             .cmd_send
-            .send(RunLoopCommand {
-                reward_cycle,
-                command: SignerCommand::Dkg,
-            })
+            .send(RunLoopCommand::new(SignerCommand::Dkg, reward_cycle))
             .expect("failed to send DKG command");
     }
     let new_key = signer_test.wait_for_dkg(timeout);
@@ -1044,22 +1043,22 @@ fn stackerdb_sign_request_rejected() {
     // Determine the coordinator of the current node height
     info!("signer_runloop: spawn send commands to do sign");
     let sign_now = Instant::now();
-    let sign_command = RunLoopCommand {
-        reward_cycle,
-        command: SignerCommand::Sign {
+    let sign_command = RunLoopCommand::new(
+        SignerCommand::Sign {
             block_proposal: block_proposal_1,
             is_taproot: false,
             merkle_root: None,
         },
-    };
-    let sign_taproot_command = RunLoopCommand {
         reward_cycle,
-        command: SignerCommand::Sign {
+    );
+    let sign_taproot_command = RunLoopCommand::new(
+        SignerCommand::Sign {
             block_proposal: block_proposal_2,
             is_taproot: true,
             merkle_root: None,
         },
-    };
+        reward_cycle,
+    );
     for signer in signer_test.spawned_signers.iter() {
         signer
             .cmd_send